@@ -1,341 +1,3467 @@
-use std::sync::Mutex;
-use tauri::State;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager, State};
 
+use osnova_lib::address;
+use osnova_lib::audit::{AuditFilter, AuditLog, PageRequest};
+use osnova_lib::cache::{CacheManager, EvictionClass};
+use osnova_lib::components::ComponentDownloader;
+use osnova_lib::context::startup::{LogLevel, RunMode, StartupConfig, DEFAULT_CACHE_SIZE_BYTES};
+use osnova_lib::context::{OsnovaContext, TaskHandle, DEFAULT_SHUTDOWN_TIMEOUT};
+use osnova_lib::context::{GuestSession, GuestSessionInfo, NotYetUnlocked, UnlockState};
+use osnova_lib::diagnostics::{DiagnosticQueries, QUERIES};
+use osnova_lib::events::{BackendReadinessChanged, OsnovaEvent, PermissionResolved};
+use osnova_lib::logs::{self, LogFiles, LogFilter, Logger};
+use osnova_lib::models::key_cocoon::KeyType;
+use osnova_lib::models::launcher_layout::LauncherLayout;
+use osnova_lib::models::mutation::{MutationReceipt, MutationRejected, Since};
+use osnova_lib::platform::auth::{self, AuthProof};
+use osnova_lib::policies::{PolicyStore, SignedPolicy, SignedRemoval};
+use osnova_lib::http::FetchPolicy;
+use osnova_lib::network::bandwidth::{BandwidthMeter, BandwidthPolicy};
+use osnova_lib::network::discovery::LanDiscovery;
+use osnova_lib::network::doctor::NetworkDoctor;
+use osnova_lib::network::{kill_switch, AutonomiClient, CancellationToken, NetworkOptions};
+use osnova_lib::rpc::{self, RpcServer};
 use osnova_lib::services::{
-    AppsService, BottomMenuTab, ConfigService, IdentityService, KeyService, LauncherService,
-    NavigationService, StatusService, Theme, UIService,
+    AppsService, CatalogService, ConfigService, IdentityService, KeyService, LauncherService,
+    NavigationService, NetworkProxy, PresetDocument, PresetImportPolicy, ProcessService,
+    ProvenanceService, SessionService, SharingService, UIService,
 };
+use osnova_lib::services::features::FeatureFlagService;
+use osnova_lib::services::handshake::COMPONENT_READY_METHOD;
+use osnova_lib::services::identity::{IdentityStatus, RecoveryReport};
+use osnova_lib::services::key_usage::{KeyUsageReport, KeyUsageStats, UsageWindow};
+use osnova_lib::services::provenance::DEFAULT_PROVENANCE_RETENTION_DAYS;
+use osnova_lib::models::notification::Notification;
+use osnova_lib::models::retention::{Policy, RetentionStore};
+use osnova_lib::models::permission::Capability;
+use osnova_lib::models::settings_schema::SettingFieldError;
+use osnova_lib::services::config::SettingsPayload;
+use osnova_lib::services::launcher::LauncherMutation;
+use osnova_lib::models::config_cache::AppConfiguration;
+use osnova_lib::models::config_snapshot::{ConfigSnapshot, RestoreMode};
+use osnova_lib::OsnovaError;
+use osnova_lib::util::format::{DisplayContext, Locale, Localize};
+use osnova_lib::services::apps::DEFAULT_GC_INTERVAL;
+use osnova_lib::services::health::{HealthMonitor, DEFAULT_HEALTH_TICK};
+use osnova_lib::services::prediction::LaunchPredictor;
+use osnova_lib::services::warming::{CacheWarmer, WarmingPolicy, DEFAULT_WARMING_INTERVAL};
+use osnova_lib::services::processes::DEFAULT_WATCHDOG_INTERVAL;
+use osnova_lib::services::{AppEvent, EventBus, LaunchHandshake, SearchScope, SearchService};
+use osnova_lib::services::AnnotationService;
+use osnova_lib::models::annotation::Subject;
+use osnova_lib::services::{NotificationFilter, NotificationService, PermissionService};
+use osnova_lib::services::{AuditContext, EncryptionAudit};
+use osnova_lib::services::{Caller, LogsService};
+use osnova_lib::services::RuntimeSettings;
+use osnova_lib::services::{BatchOptions, InstallRequest, MaterializeOutcome, MaterializePolicy};
+use osnova_lib::services::{ResetChallenge, StorageRoots, StorageService};
+use osnova_lib::services::{UpdatePolicy, UpdateService};
+use osnova_lib::services::metadata::{MetadataService, DEFAULT_REFRESH_CONCURRENCY};
+use osnova_lib::services::MigrationService;
+use osnova_lib::services::{ReauthService, SensitiveOperation};
+use osnova_lib::services::RetentionService;
+use osnova_lib::services::AppBadgeService;
+use osnova_lib::services::SessionOverrides;
+use osnova_lib::services::{ExportFormat, HistoryFilter, WalletService};
+use osnova_lib::storage::SqlStorage;
+use osnova_lib::tauri_api::dto::{
+    AppsLaunchRequest, IdentityImportRequest, LauncherSetLayoutRequest,
+    NavigationSetBottomMenuRequest, UiSetThemeRequest,
+};
+use osnova_lib::tauri_api::{TauriApi, INIT_FAILED_PREFIX};
+use osnova_lib::time::{self, HybridClock};
 
 /// Application state holding all services
 pub struct AppState {
-    // Services are wrapped in Mutex for interior mutability
-    identity_service: Mutex<Option<IdentityService>>,
-    key_service: Mutex<Option<KeyService>>,
+    // Services behind the core commands, whose responses are pinned
+    api: TauriApi,
+    // Other services are wrapped in Mutex for interior mutability
     config_service: Mutex<Option<ConfigService>>,
-    apps_service: Mutex<Option<AppsService>>,
-    launcher_service: Mutex<Option<LauncherService>>,
-    ui_service: Mutex<Option<UIService>>,
-    navigation_service: Mutex<Option<NavigationService>>,
-    status_service: Mutex<StatusService>,
+    catalog_service: Mutex<Option<Arc<CatalogService>>>,
+    feature_flag_service: Mutex<Option<Arc<FeatureFlagService>>>,
+    ui_state_writer: Mutex<Option<TaskHandle>>,
+    bandwidth_meter: Mutex<Option<Arc<BandwidthMeter>>>,
+    process_service: Mutex<Option<Arc<ProcessService>>>,
+    launch_handshake: Mutex<Option<Arc<LaunchHandshake>>>,
+    logs_service: Mutex<Option<Arc<LogsService>>>,
+    search_service: Mutex<Option<Arc<SearchService>>>,
+    annotation_service: Mutex<Option<Arc<AnnotationService>>>,
+    policy_store: Mutex<Option<Arc<PolicyStore>>>,
+    provenance_service: Mutex<Option<Arc<ProvenanceService>>>,
+    session_service: Mutex<Option<SessionService>>,
+    notification_service: Mutex<Option<Arc<NotificationService>>>,
+    search_indexer: Mutex<Option<TaskHandle>>,
+    badge_service: Mutex<Option<Arc<AppBadgeService>>>,
+    badge_updater: Mutex<Option<TaskHandle>>,
+    update_service: Mutex<Option<Arc<UpdateService>>>,
+    permission_service: Mutex<Option<Arc<PermissionService>>>,
+    sharing_service: Mutex<Option<Arc<SharingService>>>,
+    lan_discovery: Mutex<Option<Arc<LanDiscovery>>>,
+    update_scheduler: Mutex<Option<TaskHandle>>,
+    metadata_service: Mutex<Option<Arc<MetadataService>>>,
+    metadata_scheduler: Mutex<Option<TaskHandle>>,
+    cache_gc_scheduler: Mutex<Option<TaskHandle>>,
+    cache_warmer: Mutex<Option<TaskHandle>>,
+    health_checker: Mutex<Option<TaskHandle>>,
+    network_proxy: Mutex<Option<TaskHandle>>,
+    diagnostic_queries: Mutex<Option<Arc<DiagnosticQueries>>>,
+    storage_service: Mutex<Option<Arc<StorageService>>>,
+    retention_service: Mutex<Option<Arc<RetentionService>>>,
+    tasks: Arc<osnova_lib::services::TaskRegistry>,
+    reauth_service: Arc<ReauthService>,
+    network_requests: Mutex<HashMap<String, CancellationToken>>,
+    events: EventBus,
+    context: OsnovaContext,
+    guest_session: Mutex<Option<ActiveGuest>>,
+    user_id: Mutex<Option<String>>,
     storage_path: String,
+    rpc_listen: std::net::SocketAddr,
+    cache_size_bytes: u64,
+    session_overrides: SessionOverrides,
+}
+
+/// A running guest session, standing in for the host user's services
+struct ActiveGuest {
+    session: GuestSession,
+    /// User whose services are reopened when the session ends
+    host_user: Option<String>,
+    expiry: TaskHandle,
 }
 
 impl AppState {
     pub fn new(storage_path: String) -> Self {
+        let events = EventBus::new();
+        // Task history survives restarts when its database can be opened
+        let tasks = osnova_lib::services::TaskRegistry::new()
+            .with_persistence(&storage_path)
+            .unwrap_or_else(|e| {
+                eprintln!("Task history is not kept across restarts: {}", e);
+                osnova_lib::services::TaskRegistry::new()
+            });
+        let context = OsnovaContext::new(&storage_path).with_events(events.clone());
+        let reauth_service = Arc::new(
+            ReauthService::new(&storage_path, auth::default_authenticator())
+                .with_events(events.clone()),
+        );
         Self {
-            identity_service: Mutex::new(None),
-            key_service: Mutex::new(None),
+            api: TauriApi::from_context(&context).with_reauth(reauth_service.clone()),
             config_service: Mutex::new(None),
-            apps_service: Mutex::new(None),
-            launcher_service: Mutex::new(None),
-            ui_service: Mutex::new(None),
-            navigation_service: Mutex::new(None),
-            status_service: Mutex::new(StatusService::new()),
+            catalog_service: Mutex::new(None),
+            feature_flag_service: Mutex::new(None),
+            ui_state_writer: Mutex::new(None),
+            bandwidth_meter: Mutex::new(None),
+            process_service: Mutex::new(None),
+            launch_handshake: Mutex::new(None),
+            logs_service: Mutex::new(None),
+            search_service: Mutex::new(None),
+            annotation_service: Mutex::new(None),
+            policy_store: Mutex::new(None),
+            provenance_service: Mutex::new(None),
+            session_service: Mutex::new(None),
+            notification_service: Mutex::new(None),
+            search_indexer: Mutex::new(None),
+            badge_service: Mutex::new(None),
+            badge_updater: Mutex::new(None),
+            update_service: Mutex::new(None),
+            permission_service: Mutex::new(None),
+            sharing_service: Mutex::new(None),
+            lan_discovery: Mutex::new(None),
+            update_scheduler: Mutex::new(None),
+            metadata_service: Mutex::new(None),
+            metadata_scheduler: Mutex::new(None),
+            cache_gc_scheduler: Mutex::new(None),
+            cache_warmer: Mutex::new(None),
+            health_checker: Mutex::new(None),
+            network_proxy: Mutex::new(None),
+            diagnostic_queries: Mutex::new(None),
+            storage_service: Mutex::new(None),
+            retention_service: Mutex::new(None),
+            tasks: Arc::new(tasks),
+            reauth_service,
+            network_requests: Mutex::new(HashMap::new()),
+            context,
+            guest_session: Mutex::new(None),
+            events,
+            user_id: Mutex::new(None),
             storage_path,
+            rpc_listen: std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+            cache_size_bytes: DEFAULT_CACHE_SIZE_BYTES,
+            session_overrides: SessionOverrides::default(),
         }
     }
 
-    /// Derive cocoon key for key service
-    fn derive_cocoon_key(user_id: &str, master_key: &[u8; 32]) -> [u8; 32] {
-        use blake3::Hasher;
-        let mut hasher = Hasher::new();
-        hasher.update(b"osnova-key-service-cocoon:");
-        hasher.update(user_id.as_bytes());
-        hasher.update(master_key);
-        let hash = hasher.finalize();
-        let mut key = [0u8; 32];
-        key.copy_from_slice(hash.as_bytes());
-        key
+    /// Take the listen address and cache size from the startup
+    /// configuration, and shadow stored system settings for this run
+    pub fn with_startup(mut self, startup: &StartupConfig, overrides: SessionOverrides) -> Self {
+        self.rpc_listen = startup.rpc_listen();
+        self.cache_size_bytes = startup.cache_size_bytes();
+        self.session_overrides = overrides;
+        self
     }
 
     /// Initialize services for a specific user
+    ///
+    /// Phase one opens the services that need no key material, so status,
+    /// theme and the app list are available at once. Phase two follows
+    /// when the identity can be read; if the platform keystore refuses,
+    /// the shell stays locked until `context_unlock` succeeds.
     pub fn init_for_user(&self, user_id: &str) -> Result<(), String> {
-        // Initialize identity service
-        let identity_service =
-            IdentityService::new(&self.storage_path).map_err(|e| e.to_string())?;
-        *self.identity_service.lock().unwrap() = Some(identity_service);
-
-        // Get identity to derive key service cocoon key
-        let identity = self
-            .identity_service
-            .lock()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .get_identity()
-            .map_err(|e| e.to_string())?;
+        // A server whose data moved to another server stays down until reset
+        MigrationService::ensure_not_migrated(&self.storage_path).map_err(|e| e.to_string())?;
 
-        // Derive cocoon key from identity
-        let cocoon_key = Self::derive_cocoon_key(user_id, identity.master_key());
+        // Services of a previous user's identity must not carry over
+        self.context.lock();
 
-        // Initialize key service
-        let key_service =
-            KeyService::new(&self.storage_path, &cocoon_key).map_err(|e| e.to_string())?;
-        *self.key_service.lock().unwrap() = Some(key_service);
+        // Initialize identity service; reading the identity waits for phase two
+        let identity_service = IdentityService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_reauth(self.reauth_service.clone())
+            .with_guest_gate(self.context.guest_gate());
+        self.api.identity().set(identity_service);
 
-        // Initialize config service
-        let config_service = ConfigService::new(&self.storage_path).map_err(|e| e.to_string())?;
+        // Initialize config service; per-app configuration waits for phase two
+        let config_service = ConfigService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone())
+            .with_session_overrides(self.session_overrides.clone())
+            .with_unlock(self.context.unlock_gate());
+
+        // Storage opened from here on compresses with the configured settings
+        let runtime = config_service.get_runtime_settings().map_err(|e| e.to_string())?;
+        osnova_lib::storage::compression::set_shared_settings(runtime.compression);
+
+        // Initialize bandwidth meter from the configured policy
+        let policy = config_service.get_bandwidth_policy().map_err(|e| e.to_string())?;
+        let metered_mode = config_service.get_metered_mode().map_err(|e| e.to_string())?;
+        let db_path = std::path::Path::new(&self.storage_path).join("osnova.db");
+        let sql_storage = SqlStorage::new(db_path).map_err(|e| e.to_string())?;
+        let bandwidth_meter = BandwidthMeter::new(sql_storage, policy);
+        bandwidth_meter.set_metered_mode(metered_mode);
+        *self.bandwidth_meter.lock().unwrap() = Some(Arc::new(bandwidth_meter));
+
+        // Fetch remote content under the configured policy
+        let fetch_policy = config_service.get_fetch_policy().map_err(|e| e.to_string())?;
+        osnova_lib::http::set_shared_policy(fetch_policy);
+
+        // Stay offline if the user left Osnova offline
+        let offline = config_service.get_offline_mode().map_err(|e| e.to_string())?;
+        kill_switch::shared().set(offline);
+
+        // Keep the configured amount of disk space free during large writes
+        let disk_guard = config_service.disk_guard().map_err(|e| e.to_string())?;
+        self.api
+            .status()
+            .set_disk_check(&self.storage_path, disk_guard.clone());
+
+        let provenance_policy = config_service
+            .get_retention_policy(RetentionStore::Provenance)
+            .map_err(|e| e.to_string())?;
         *self.config_service.lock().unwrap() = Some(config_service);
 
-        // Initialize apps service
-        let apps_service = AppsService::new(&self.storage_path).map_err(|e| e.to_string())?;
-        *self.apps_service.lock().unwrap() = Some(apps_service);
+        // Record where components come from, dropping history past retention
+        let provenance_service = ProvenanceService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_retention_policy(provenance_policy);
+        let provenance_service = Arc::new(provenance_service);
+        provenance_service
+            .prune(DEFAULT_PROVENANCE_RETENTION_DAYS)
+            .map_err(|e| e.to_string())?;
+
+        // Initialize apps service with the component cache for verify/repair
+        let cache_dir = osnova_lib::platform::paths::get_component_cache_dir()
+            .map_err(|e| e.to_string())?;
+        let cache_size = usize::try_from(self.cache_size_bytes).unwrap_or(usize::MAX);
+        let cache = CacheManager::new(cache_dir, cache_size)
+            .map_err(|e| e.to_string())?
+            .with_disk_guard(disk_guard.clone());
+        let downloader = ComponentDownloader::new(cache.clone(), None)
+            .with_provenance(provenance_service.clone())
+            .with_disk_guard(disk_guard.clone())
+            .with_tasks(self.tasks.clone());
+
+        // Update downloads run in the background, so they honour the
+        // bandwidth policy
+        let mut update_downloader = ComponentDownloader::new(cache.clone(), None)
+            .with_provenance(provenance_service.clone())
+            .with_disk_guard(disk_guard.clone())
+            .with_tasks(self.tasks.clone());
+        if let Some(meter) = self.bandwidth_meter.lock().unwrap().clone() {
+            update_downloader = update_downloader.with_bandwidth_meter(meter);
+        }
+        *self.provenance_service.lock().unwrap() = Some(provenance_service);
+
+        // Sessions of client devices paired with this server
+        let session_service = SessionService::new(&self.storage_path).map_err(|e| e.to_string())?;
+        *self.session_service.lock().unwrap() = Some(session_service);
+
+        // Listings are refreshed a few apps at a time, within the bandwidth
+        // policy
+        let mut metadata_service = MetadataService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone());
+        if let Some(meter) = self.bandwidth_meter.lock().unwrap().clone() {
+            metadata_service = metadata_service.with_bandwidth_meter(meter);
+        }
+        let metadata_service = Arc::new(metadata_service);
+        let metadata_scheduler = self.spawn_task("metadata-scheduler", |token| {
+            metadata_service.clone().run_scheduler(token)
+        });
+        if let Some(previous) = self.metadata_scheduler.lock().unwrap().replace(metadata_scheduler)
+        {
+            previous.cancel();
+        }
+        *self.metadata_service.lock().unwrap() = Some(metadata_service.clone());
+
+        // Initialize notification center
+        let notification_service = NotificationService::new(&self.storage_path, user_id)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone());
+        let notification_service = Arc::new(notification_service);
+        *self.notification_service.lock().unwrap() = Some(notification_service.clone());
+
+        // Audited services need the current user
+        *self.user_id.lock().unwrap() = Some(user_id.to_string());
+
+        // Backends reach the network through a proxy enforcing what they
+        // declare, counted against the bandwidth policy
+        let proxy_listener = tauri::async_runtime::block_on(rpc::server::bind_local())
+            .map_err(|e| e.to_string())?;
+        let proxy_address = proxy_listener.local_addr().map_err(|e| e.to_string())?;
+        let mut network_proxy = NetworkProxy::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_address(proxy_address);
+        if let Some(meter) = self.bandwidth_meter.lock().unwrap().clone() {
+            network_proxy = network_proxy.with_bandwidth_meter(meter);
+        }
+        if let Ok(audit) = self.audit_log() {
+            network_proxy = network_proxy.with_audit(Arc::new(audit));
+        }
+        let network_proxy = Arc::new(network_proxy);
+        let server = network_proxy.clone();
+        let proxy_task = self.spawn_task("network-proxy", |token| async move {
+            let _ = token.run_until_cancelled(server.serve(proxy_listener)).await;
+        });
+        if let Some(previous) = self.network_proxy.lock().unwrap().replace(proxy_task) {
+            previous.cancel();
+        }
+
+        // Launches teach the local model of which apps are opened when
+        let launch_predictor =
+            Arc::new(LaunchPredictor::new(&self.storage_path).map_err(|e| e.to_string())?);
+
+        // Launch consent is kept per user and audited once an identity exists
+        let health_monitor = Arc::new(HealthMonitor::new());
+        let mut apps_service = AppsService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_downloader(downloader)
+            .with_events(self.events.clone())
+            .with_metadata(metadata_service.clone())
+            .with_notifications(notification_service.clone())
+            .with_tasks(self.tasks.clone())
+            .with_health(health_monitor.clone())
+            .with_network_proxy(network_proxy)
+            .with_predictor(launch_predictor.clone())
+            .with_user(user_id);
+        if let Ok(audit) = self.audit_log() {
+            apps_service = apps_service.with_audit(Arc::new(audit));
+        }
+        if let Some(process_service) = self.process_service.lock().unwrap().clone() {
+            apps_service = apps_service.with_processes(process_service);
+        }
+        if let Some(handshake) = self.launch_handshake.lock().unwrap().clone() {
+            apps_service = apps_service.with_handshake(handshake);
+        }
+
+        // Finish or undo installs a crash interrupted before listing apps
+        tauri::async_runtime::block_on(apps_service.recover_installs())
+            .map_err(|e| e.to_string())?;
+
+        // Purge trashed apps whose retention window has elapsed, then drop
+        // shared components no remaining app references
+        apps_service.purge_expired_trash().map_err(|e| e.to_string())?;
+        tauri::async_runtime::block_on(apps_service.remove_unused_shared_components())
+            .map_err(|e| e.to_string())?;
+        self.api.apps().set(apps_service);
+
+        // Growing stores are pruned on write; weekly maintenance applies
+        // their configured policies in full, including expiry
+        let config = ConfigService::new(&self.storage_path).map_err(|e| e.to_string())?;
+        let key_usage = KeyUsageStats::from_context(&self.context).map_err(|e| e.to_string())?;
+        let mut retention_service = RetentionService::new(config)
+            .with_store(notification_service.clone())
+            .with_store(Arc::new(key_usage));
+        if let Some(provenance) = self.provenance_service.lock().unwrap().clone() {
+            retention_service = retention_service.with_store(provenance);
+        }
+        if let Some(processes) = self.process_service.lock().unwrap().clone() {
+            retention_service = retention_service.with_store(processes);
+        }
+        if let Ok(audit) = self.audit_log() {
+            retention_service = retention_service.with_store(Arc::new(audit));
+        }
+        let retention_service = Arc::new(retention_service);
+        *self.retention_service.lock().unwrap() = Some(retention_service.clone());
+
+        // Weekly, remove cached files no installed app owns, apply retention
+        // policies, and report what a storage compaction would remove
+        let mut gc_service = AppsService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_downloader(
+                ComponentDownloader::new(cache.clone(), None).with_tasks(self.tasks.clone()),
+            )
+            .with_user(user_id)
+            .with_retention(retention_service);
+        if let Some(storage_service) = self.storage_service.lock().unwrap().clone() {
+            gc_service = gc_service.with_storage(storage_service);
+        }
+        let gc_service = Arc::new(gc_service);
+        let gc_scheduler = self.spawn_task("cache-gc-scheduler", |token| {
+            gc_service.clone().run_gc_scheduler(DEFAULT_GC_INTERVAL, token)
+        });
+        if let Some(previous) = self.cache_gc_scheduler.lock().unwrap().replace(gc_scheduler) {
+            previous.cancel();
+        }
+
+        // While no other task runs, get the apps likely to be opened soon
+        // ready, within the bandwidth policy and the disk reserve. Warm-ups
+        // are the first to be evicted until an app uses them.
+        let cache_dir = cache.cache_dir().to_path_buf();
+        let mut warm_downloader = ComponentDownloader::new(cache, None)
+            .with_eviction_class(EvictionClass::Opportunistic)
+            .with_disk_guard(disk_guard.clone())
+            .with_tasks(self.tasks.clone());
+        if let Some(provenance) = self.provenance_service.lock().unwrap().clone() {
+            warm_downloader = warm_downloader.with_provenance(provenance);
+        }
+        let mut cache_warmer = CacheWarmer::new(
+            AppsService::new(&self.storage_path)
+                .map_err(|e| e.to_string())?
+                .with_downloader(warm_downloader)
+                .with_user(user_id),
+            launch_predictor,
+        )
+        .with_disk_guard(disk_guard, cache_dir)
+        .with_tasks(self.tasks.clone())
+        .with_metadata(metadata_service);
+        if let Some(meter) = self.bandwidth_meter.lock().unwrap().clone() {
+            cache_warmer = cache_warmer.with_bandwidth_meter(meter);
+        }
+        let cache_warmer = Arc::new(cache_warmer);
+        let warmer = self.spawn_task("cache-warmer", |token| {
+            cache_warmer.clone().run(DEFAULT_WARMING_INTERVAL, token)
+        });
+        if let Some(previous) = self.cache_warmer.lock().unwrap().replace(warmer) {
+            previous.cancel();
+        }
+
+        // Check running backends against their declared health checks,
+        // sharing health state with the apps service
+        let mut health_service = AppsService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone())
+            .with_health(health_monitor);
+        if let Some(process_service) = self.process_service.lock().unwrap().clone() {
+            health_service = health_service.with_processes(process_service);
+        }
+        if let Some(handshake) = self.launch_handshake.lock().unwrap().clone() {
+            health_service = health_service.with_handshake(handshake);
+        }
+        let health_service = Arc::new(health_service);
+        let health_checker = self.spawn_task("health-checker", |token| {
+            health_service.clone().run_health_checks(DEFAULT_HEALTH_TICK, token)
+        });
+        if let Some(previous) = self.health_checker.lock().unwrap().replace(health_checker) {
+            previous.cancel();
+        }
 
         // Initialize launcher service
         let launcher_service =
             LauncherService::new(&self.storage_path, user_id).map_err(|e| e.to_string())?;
-        *self.launcher_service.lock().unwrap() = Some(launcher_service);
+        self.api.launcher().set(launcher_service);
 
-        // Initialize UI service
+        // Initialize UI service, writing apps' UI state once they stop saving
         let ui_service = UIService::new(&self.storage_path, user_id).map_err(|e| e.to_string())?;
-        *self.ui_service.lock().unwrap() = Some(ui_service);
+        let ui_service = Arc::new(ui_service);
+        let ui_state_writer = self.spawn_task("ui-state-writer", |token| {
+            ui_service.clone().run(self.events.subscribe(), token)
+        });
+        if let Some(previous) = self.ui_state_writer.lock().unwrap().replace(ui_state_writer) {
+            previous.cancel();
+        }
+        self.api.ui().set(ui_service);
 
         // Initialize navigation service
         let navigation_service =
             NavigationService::new(&self.storage_path, user_id).map_err(|e| e.to_string())?;
-        *self.navigation_service.lock().unwrap() = Some(navigation_service);
+        self.api.navigation().set(navigation_service);
+
+        // Keep launcher icon badges updated from service events
+        let mut badge_service = AppBadgeService::new(&self.storage_path, user_id)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone());
+        if let Some(process_service) = self.process_service.lock().unwrap().clone() {
+            badge_service = badge_service.with_processes(process_service);
+        }
+        let badge_service = Arc::new(badge_service);
+        let badge_updater = self.spawn_task("badge-updater", |token| {
+            badge_service.clone().run(self.events.subscribe(), token)
+        });
+        if let Some(previous) = self.badge_updater.lock().unwrap().replace(badge_updater) {
+            previous.cancel();
+        }
+        *self.badge_service.lock().unwrap() = Some(badge_service);
+
+        // Check for app updates on the configured interval
+        let mut update_service = UpdateService::new(&self.storage_path)
+            .map_err(|e| e.to_string())?
+            .with_downloader(Arc::new(update_downloader))
+            .with_notifications(notification_service)
+            .with_events(self.events.clone());
+        if let Some(process_service) = self.process_service.lock().unwrap().clone() {
+            update_service = update_service.with_processes(process_service);
+        }
+        let update_service = Arc::new(update_service);
+        let scheduler = self.spawn_task("update-scheduler", |token| {
+            update_service.clone().run_scheduler(token)
+        });
+        if let Some(previous) = self.update_scheduler.lock().unwrap().replace(scheduler) {
+            previous.cancel();
+        }
+        *self.update_service.lock().unwrap() = Some(update_service);
+
+        // Runtime permission prompts, answered from the shell's modal
+        let permission_service = PermissionService::new(&self.storage_path, user_id)
+            .map_err(|e| e.to_string())?
+            .with_events(self.events.clone())
+            .with_reauth(self.reauth_service.clone());
+        let permission_service = Arc::new(permission_service);
+        *self.permission_service.lock().unwrap() = Some(permission_service.clone());
+
+        // Data sharing between apps, audited once an identity exists
+        let mut sharing_service =
+            SharingService::new(&self.storage_path, user_id, permission_service)
+                .map_err(|e| e.to_string())?;
+        if let Ok(audit) = self.audit_log() {
+            sharing_service = sharing_service.with_audit(Arc::new(audit));
+        }
+        *self.sharing_service.lock().unwrap() = Some(Arc::new(sharing_service));
+
+        if let Err(e) = self.unlock_for_user(user_id) {
+            eprintln!("Services stay locked until the identity is unlocked: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Phase two: read the identity and start the services that need it
+    ///
+    /// Phase-one services keep running; the context is upgraded in place
+    /// and `context-unlocked` tells the frontend to retry what it deferred.
+    fn unlock_for_user(&self, user_id: &str) -> Result<(), String> {
+        // Reading the identity may prompt for the platform keystore
+        let identity = self.api.identity().lock()
+            .as_ref()
+            .ok_or("Identity service not initialized")?
+            .unlock()
+            .map_err(|e| e.to_string())?;
+
+        // Initialize feature flags, refreshed whenever the catalog is fetched
+        let feature_flag_service =
+            FeatureFlagService::new(&self.storage_path, user_id, &identity)
+                .map_err(|e| e.to_string())?
+                .with_events(self.events.clone());
+        let feature_flag_service = Arc::new(feature_flag_service);
+        *self.feature_flag_service.lock().unwrap() = Some(feature_flag_service.clone());
+
+        // Initialize launcher catalog, falling back to the embedded catalog
+        let catalog_service =
+            CatalogService::new(&self.storage_path, user_id, &identity)
+                .map_err(|e| e.to_string())?
+                .with_events(self.events.clone())
+                .with_feature_flags(feature_flag_service);
+        *self.catalog_service.lock().unwrap() = Some(Arc::new(catalog_service));
+
+        // Initialize search, rebuilding only if there is no persisted index.
+        // Opted-in setting values are indexed under a key from the identity.
+        let search_service = SearchService::new(&self.storage_path, user_id)
+            .and_then(|service| service.with_value_index(&identity))
+            .map_err(|e| e.to_string())?;
+        let search_service = Arc::new(search_service);
+        if search_service.document_count() == 0 {
+            search_service.index_all().map_err(|e| e.to_string())?;
+        }
+
+        // Keep the index updated from service events
+        let indexer = self.spawn_task("search-indexer", |token| {
+            search_service.clone().run_indexer(self.events.subscribe(), token)
+        });
+        if let Some(previous) = self.search_indexer.lock().unwrap().replace(indexer) {
+            previous.cancel();
+        }
+        *self.search_service.lock().unwrap() = Some(search_service);
+
+        // Annotations are encrypted under a key from the identity; app
+        // details show them from now on
+        let annotation_service = AnnotationService::new(&self.storage_path, user_id, &identity)
+            .map_err(|e| e.to_string())?;
+        let annotation_service = Arc::new(annotation_service);
+        {
+            let mut apps_guard = self.api.apps().lock();
+            if let Some(apps_service) = apps_guard.take() {
+                *apps_guard = Some(apps_service.with_annotations(annotation_service.clone()));
+            }
+        }
+        *self.annotation_service.lock().unwrap() = Some(annotation_service);
+
+        // A managed device's policy is verified on every read; installs and
+        // launches are checked against it from now on
+        let mut policy_store = PolicyStore::new(&self.storage_path, user_id, &identity)
+            .map_err(|e| e.to_string())?
+            .with_guest_gate(self.context.guest_gate());
+        if let Ok(audit) = self.audit_log() {
+            policy_store = policy_store.with_audit(Arc::new(audit));
+        }
+        let policy_store = Arc::new(policy_store);
+        {
+            let mut apps_guard = self.api.apps().lock();
+            if let Some(apps_service) = apps_guard.take() {
+                *apps_guard = Some(apps_service.with_policies(policy_store.clone()));
+            }
+        }
+        *self.policy_store.lock().unwrap() = Some(policy_store);
+
+        // Exported presets are signed and secret settings encrypted under
+        // keys from the identity; launches hand the secrets to backends
+        {
+            let mut config_guard = self.config_service.lock().unwrap();
+            if let Some(config_service) = config_guard.take() {
+                *config_guard =
+                    Some(config_service.with_identity(&identity).map_err(|e| e.to_string())?);
+            }
+        }
+        {
+            let mut apps_guard = self.api.apps().lock();
+            if let Some(apps_service) = apps_guard.take() {
+                *apps_guard =
+                    Some(apps_service.with_identity(&identity).map_err(|e| e.to_string())?);
+            }
+        }
+
+        // Key service, re-encrypting cocoons written under the old key;
+        // unlocking publishes the event last, once everything is in place
+        self.context.unlock(&identity, user_id).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Spawn a background task that is drained when the shell exits
+    fn spawn_task<F, Fut>(&self, name: &str, task: F) -> TaskHandle
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        // Enter the async runtime so the context can spawn onto it
+        tauri::async_runtime::block_on(async { self.context.spawn(name, task) })
+    }
+
+    /// Network options for one command invocation
+    ///
+    /// A running invocation with the same key is superseded and cancelled.
+    fn network_options(&self, key: String) -> NetworkOptions {
+        let token = CancellationToken::new();
+        let mut requests = self.network_requests.lock().unwrap();
+        if let Some(previous) = requests.insert(key, token.clone()) {
+            previous.cancel();
+        }
+        NetworkOptions::default().with_cancellation(token)
+    }
+
+    /// Cancel every in-flight network request, e.g. when the webview navigates
+    fn cancel_network_requests(&self) {
+        for (_, token) in self.network_requests.lock().unwrap().drain() {
+            token.cancel();
+        }
+    }
+
+    /// Drop all per-user services, closing their database connections
+    fn clear_services(&self) {
+        if let Some(indexer) = self.search_indexer.lock().unwrap().take() {
+            indexer.cancel();
+        }
+        if let Some(updater) = self.badge_updater.lock().unwrap().take() {
+            updater.cancel();
+        }
+        if let Some(writer) = self.ui_state_writer.lock().unwrap().take() {
+            writer.cancel();
+        }
+        if let Some(scheduler) = self.update_scheduler.lock().unwrap().take() {
+            scheduler.cancel();
+        }
+        if let Some(scheduler) = self.metadata_scheduler.lock().unwrap().take() {
+            scheduler.cancel();
+        }
+        if let Some(scheduler) = self.cache_gc_scheduler.lock().unwrap().take() {
+            scheduler.cancel();
+        }
+        if let Some(warmer) = self.cache_warmer.lock().unwrap().take() {
+            warmer.cancel();
+        }
+        if let Some(checker) = self.health_checker.lock().unwrap().take() {
+            checker.cancel();
+        }
+        if let Some(proxy) = self.network_proxy.lock().unwrap().take() {
+            proxy.cancel();
+        }
+        self.api.identity().clear();
+        self.context.lock();
+        *self.config_service.lock().unwrap() = None;
+        self.api.apps().clear();
+        self.api.launcher().clear();
+        *self.catalog_service.lock().unwrap() = None;
+        *self.feature_flag_service.lock().unwrap() = None;
+        // Snapshots still waiting out the debounce are written, not lost
+        if let Some(ui_service) = self.api.ui().lock().take() {
+            let _ = ui_service.flush_app_states();
+        }
+        self.api.navigation().clear();
+        *self.bandwidth_meter.lock().unwrap() = None;
+        *self.search_service.lock().unwrap() = None;
+        *self.annotation_service.lock().unwrap() = None;
+        *self.policy_store.lock().unwrap() = None;
+        *self.provenance_service.lock().unwrap() = None;
+        *self.diagnostic_queries.lock().unwrap() = None;
+        *self.session_service.lock().unwrap() = None;
+        *self.notification_service.lock().unwrap() = None;
+        *self.retention_service.lock().unwrap() = None;
+        *self.badge_service.lock().unwrap() = None;
+        *self.update_service.lock().unwrap() = None;
+        *self.metadata_service.lock().unwrap() = None;
+        *self.permission_service.lock().unwrap() = None;
+        *self.sharing_service.lock().unwrap() = None;
+        *self.user_id.lock().unwrap() = None;
+    }
 
+    /// Get the user the services were initialized for
+    fn current_user(&self) -> Result<String, String> {
+        self.user_id
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| "Services not initialized for a user".to_string())
+    }
+
+    /// Locale and time to fill in display fields for: the user's locale,
+    /// or the system's before the UI service is up
+    fn display_context(&self) -> Result<DisplayContext, String> {
+        match self.api.ui().lock().as_ref() {
+            Some(service) => service.display_context().map_err(|e| e.to_string()),
+            None => Ok(DisplayContext::new(
+                Locale::system(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_err(|e| e.to_string())?
+                    .as_secs(),
+            )),
+        }
+    }
+
+    /// Start a guest session in place of the current user
+    ///
+    /// The host's per-user services are closed and the guest's config,
+    /// navigation and keys are opened from the session's ephemeral context;
+    /// services without an in-memory backend stay closed until the session
+    /// ends, which it does by itself at its time limit.
+    fn start_guest_session(&self, handle: tauri::AppHandle) -> Result<GuestSessionInfo, String> {
+        let mut guard = self.guest_session.lock().unwrap();
+        let session = self
+            .context
+            .start_guest_session()
+            .map_err(|e| e.to_string())?;
+        let host_user = self.user_id.lock().unwrap().clone();
+        self.clear_services();
+        if let Err(e) = self.open_guest_services(&session) {
+            self.clear_services();
+            drop(session);
+            self.reopen_host_services(host_user);
+            return Err(e);
+        }
+
+        let expiry = self.spawn_task("guest-session-expiry", |token| {
+            let expiry = session.expiry();
+            async move {
+                if token.run_until_cancelled(expiry).await.is_some() {
+                    // Reopening the host's services blocks on the runtime
+                    let _ = tauri::async_runtime::spawn_blocking(move || {
+                        handle.state::<AppState>().end_guest_session();
+                    })
+                    .await;
+                }
+            }
+        });
+        let info = session.info().clone();
+        *guard = Some(ActiveGuest {
+            session,
+            host_user,
+            expiry,
+        });
+        Ok(info)
+    }
+
+    /// Open the per-user services a guest gets from its ephemeral context
+    fn open_guest_services(&self, session: &GuestSession) -> Result<(), String> {
+        let config_service = ConfigService::from_context(session.context())
+            .and_then(|service| service.with_identity(session.identity()))
+            .map_err(|e| e.to_string())?;
+        *self.config_service.lock().unwrap() = Some(config_service);
+        let navigation_service =
+            NavigationService::from_context(session.context(), session.user_id())
+                .map_err(|e| e.to_string())?;
+        self.api.navigation().set(navigation_service);
+        *self.user_id.lock().unwrap() = Some(session.user_id().to_string());
         Ok(())
     }
+
+    /// End the guest session, if one runs, and reopen the host's services
+    fn end_guest_session(&self) {
+        let Some(guest) = self.guest_session.lock().unwrap().take() else {
+            return;
+        };
+        guest.expiry.cancel();
+        self.clear_services();
+        guest.session.end();
+        self.reopen_host_services(guest.host_user);
+    }
+
+    /// Reopen the services of the user a guest session replaced
+    fn reopen_host_services(&self, host_user: Option<String>) {
+        if let Some(user_id) = host_user {
+            if let Err(e) = self.init_for_user(&user_id) {
+                eprintln!("Failed to reopen services after the guest session: {}", e);
+            }
+        }
+    }
+
+    /// Key service of whoever uses the device: the guest's while a guest
+    /// session runs, the host's otherwise
+    fn key_service(&self) -> Result<Arc<KeyService>, String> {
+        match self.guest_session.lock().unwrap().as_ref() {
+            Some(guest) => guest.session.context().key_service(),
+            None => self.context.key_service(),
+        }
+        .map_err(unlock_error)
+    }
+
+    /// Error for a missing phase-two service: refused until the identity is
+    /// unlocked while the user's other services run, missing otherwise
+    fn phase_two_error(&self, operation: &str, service: &str) -> String {
+        if self.context.guest_gate().is_active() {
+            return format!("{} service is not available in a guest session", service);
+        }
+        let initialized = self.user_id.lock().unwrap().is_some();
+        if initialized && self.context.unlock_state() == UnlockState::Locked {
+            let refused = NotYetUnlocked {
+                operation: operation.to_string(),
+            };
+            unlock_error(refused.into())
+        } else {
+            format!("{} service not initialized", service)
+        }
+    }
+
+    /// Local network discovery, joining the mDNS group on first use
+    ///
+    /// Not per-user: clients browse before anyone has signed in.
+    fn lan_discovery(&self) -> Result<Arc<LanDiscovery>, String> {
+        let mut guard = self.lan_discovery.lock().unwrap();
+        if let Some(discovery) = guard.as_ref() {
+            return Ok(discovery.clone());
+        }
+
+        let config = ConfigService::new(&self.storage_path).map_err(|e| e.to_string())?;
+        let enabled = config.get_lan_announcements_enabled().map_err(|e| e.to_string())?;
+        let discovery = LanDiscovery::mdns()
+            .map_err(|e| e.to_string())?
+            .with_announcements(enabled);
+        Ok(guard.insert(Arc::new(discovery)).clone())
+    }
+
+    /// Diagnostic queries audited under the current identity, kept so a
+    /// confirmation issued by one command is found by the next
+    fn diagnostic_queries(&self) -> Result<Arc<DiagnosticQueries>, String> {
+        let mut guard = self.diagnostic_queries.lock().unwrap();
+        if let Some(queries) = guard.as_ref() {
+            return Ok(queries.clone());
+        }
+
+        let audit = Arc::new(self.audit_log()?);
+        let queries = DiagnosticQueries::new(&self.storage_path, audit);
+        Ok(guard.insert(Arc::new(queries)).clone())
+    }
+
+    /// Open the audit log for the current identity
+    fn audit_log(&self) -> Result<AuditLog, String> {
+        let guard = self.api.identity().lock();
+        let service = guard.as_ref().ok_or("Identity service not initialized")?;
+        let identity = service.get_identity().map_err(|e| e.to_string())?;
+        let user_id = self.current_user()?;
+        AuditLog::new(&self.storage_path, &identity, &user_id).map_err(|e| e.to_string())
+    }
+
+    /// Config service for secret settings, keyed by the current identity
+    /// and auditing every change
+    fn secrets_config(&self) -> Result<ConfigService, String> {
+        let identity = {
+            let guard = self.api.identity().lock();
+            let service = guard.as_ref().ok_or("Identity service not initialized")?;
+            service.get_identity().map_err(|e| e.to_string())?
+        };
+        let service = ConfigService::new(&self.storage_path)
+            .and_then(|service| service.with_identity(&identity))
+            .map_err(|e| e.to_string())?;
+        Ok(service.with_audit(Arc::new(self.audit_log()?)))
+    }
 }
 
 // ============================================================================
 // Identity Service Commands
 // ============================================================================
 
-/// Check if identity exists and initialize identity service
-#[tauri::command]
-fn identity_check(state: State<AppState>) -> Result<bool, String> {
-    // Initialize identity service if not already initialized
-    if state.identity_service.lock().unwrap().is_none() {
-        let identity_service =
-            IdentityService::new(&state.storage_path).map_err(|e| e.to_string())?;
-        *state.identity_service.lock().unwrap() = Some(identity_service);
-    }
+/// Emit a shell event to the frontend under its registered name
+fn emit(handle: &tauri::AppHandle, event: impl Into<OsnovaEvent>) {
+    let event = event.into();
+    // Nothing is lost if no window is listening
+    let _ = handle.emit(event.name(), &event);
+}
 
-    // Check if identity exists
-    let guard = state.identity_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Identity service not initialized")?;
-    match service.status() {
-        Ok(status) => Ok(status.initialized),
-        Err(_) => Ok(false),
+/// Error message for a command, marking operations refused until the
+/// identity is unlocked so the frontend can prompt instead of failing
+fn unlock_error(e: anyhow::Error) -> String {
+    match e.downcast_ref::<NotYetUnlocked>() {
+        Some(refused) => serde_json::json!({ "notYetUnlocked": refused.operation }).to_string(),
+        None => e.to_string(),
     }
 }
 
+/// Whether the services that need the identity are running
 #[tauri::command]
-fn identity_create(state: State<AppState>) -> Result<String, String> {
-    // Ensure identity service is initialized
-    if state.identity_service.lock().unwrap().is_none() {
-        let identity_service =
-            IdentityService::new(&state.storage_path).map_err(|e| e.to_string())?;
-        *state.identity_service.lock().unwrap() = Some(identity_service);
+fn context_unlock_state(state: State<AppState>) -> Result<UnlockState, String> {
+    Ok(state.context.unlock_state())
+}
+
+/// Retry phase two after the platform keystore refused or was dismissed
+#[tauri::command]
+fn context_unlock(state: State<AppState>) -> Result<UnlockState, String> {
+    if state.context.guest_gate().is_active() {
+        return Err("Unlocking is not available in a guest session".to_string());
     }
+    if state.context.unlock_state() == UnlockState::Locked {
+        let user_id = state.current_user()?;
+        state.unlock_for_user(&user_id)?;
+    }
+    Ok(state.context.unlock_state())
+}
 
-    let guard = state.identity_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Identity service not initialized")?;
-    let (seed_phrase, address) = service.create().map_err(|e| e.to_string())?;
+/// Check if identity exists and initialize identity service
+#[tauri::command]
+fn identity_check(state: State<AppState>) -> Result<bool, String> {
+    state.api.identity_check()
+}
 
-    // After creating identity, initialize other services
-    drop(guard); // Release lock before calling init_for_user
-    state.init_for_user(&address).map_err(|e| format!("Failed to initialize services: {}", e))?;
+/// Identity status, including whether the stored identity needs recovery
+#[tauri::command]
+fn identity_status(state: State<AppState>) -> Result<IdentityStatus, String> {
+    state.api.open_identity()?;
+    state
+        .api
+        .identity()
+        .with(|service| service.status().map_err(|e| e.to_string()))
+}
 
-    Ok(seed_phrase)
+#[tauri::command]
+fn identity_create(state: State<AppState>) -> Result<String, String> {
+    state.api.identity_create(|address| state.init_for_user(address))
 }
 
 #[tauri::command]
 fn identity_import(state: State<AppState>, seed_phrase: String) -> Result<String, String> {
-    // Ensure identity service is initialized
-    if state.identity_service.lock().unwrap().is_none() {
-        let identity_service =
-            IdentityService::new(&state.storage_path).map_err(|e| e.to_string())?;
-        *state.identity_service.lock().unwrap() = Some(identity_service);
-    }
+    let request = IdentityImportRequest { seed_phrase };
+    state.api.identity_import(request, |address| state.init_for_user(address))
+}
 
-    let guard = state.identity_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Identity service not initialized")?;
-    let address = service.import_with_phrase(&seed_phrase).map_err(|e| e.to_string())?;
+/// Recover an identity whose keystore key was lost, using its seed phrase
+#[tauri::command]
+fn identity_recover(state: State<AppState>, seed_phrase: String) -> Result<RecoveryReport, String> {
+    state.api.open_identity()?;
+    let report = state
+        .api
+        .identity()
+        .with(|service| service.recover_with_phrase(&seed_phrase).map_err(|e| e.to_string()))?;
 
-    // After importing identity, initialize other services
-    drop(guard); // Release lock before calling init_for_user
-    state.init_for_user(&address).map_err(|e| format!("Failed to initialize services: {}", e))?;
+    state
+        .init_for_user(&report.address)
+        .map_err(|e| format!("{}{}", INIT_FAILED_PREFIX, e))?;
 
-    Ok(address)
+    Ok(report)
 }
 
 #[tauri::command]
 fn identity_get(state: State<AppState>) -> Result<String, String> {
-    // Ensure identity service is initialized
-    if state.identity_service.lock().unwrap().is_none() {
-        let identity_service =
-            IdentityService::new(&state.storage_path).map_err(|e| e.to_string())?;
-        *state.identity_service.lock().unwrap() = Some(identity_service);
-    }
+    state.api.identity_get()
+}
 
-    let guard = state.identity_service.lock().unwrap();
+#[tauri::command]
+fn identity_reveal_seed(
+    state: State<AppState>,
+    proof: Option<AuthProof>,
+) -> Result<String, String> {
+    let guard = state.api.identity().lock();
     let service = guard.as_ref().ok_or("Identity service not initialized")?;
-    let identity = service.get_identity().map_err(|e| e.to_string())?;
-    // Return fingerprint as hex string
-    let fingerprint = identity.fingerprint();
-    Ok(hex::encode(fingerprint))
+    service.reveal_seed_phrase(proof.as_ref()).map_err(|e| e.to_string())
+}
+
+/// Check an address for typos and, when an identity exists, whether it
+/// is this identity's address
+#[tauri::command]
+fn address_validate(state: State<AppState>, address: String) -> Result<String, String> {
+    let guard = state.api.identity().lock();
+    let identity = guard.as_ref().and_then(|service| service.get_identity().ok());
+    let matches_identity =
+        identity.map(|identity| address::address_matches_identity(&address, &identity));
+
+    serde_json::to_string(&serde_json::json!({
+        "valid": address::validate_address(&address),
+        "matchesIdentity": matches_identity,
+    }))
+    .map_err(|e| e.to_string())
 }
 
 // ============================================================================
-// Apps Service Commands
+// Guest Session Commands
 // ============================================================================
 
+/// Start a guest session, which lasts an hour unless ended earlier
+///
+/// Until it ends, commands reach the guest's services instead of the
+/// host's, and identity export, wallet approvals and policy changes are
+/// refused.
 #[tauri::command]
-fn apps_list(state: State<AppState>) -> Result<String, String> {
-    let guard = state.apps_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Apps service not initialized")?;
-    let apps = service.list().map_err(|e| e.to_string())?;
-    serde_json::to_string(&apps).map_err(|e| e.to_string())
+fn guest_start(
+    state: State<AppState>,
+    handle: tauri::AppHandle,
+) -> Result<GuestSessionInfo, String> {
+    state.start_guest_session(handle)
 }
 
+/// End the guest session, discarding everything it wrote, and reopen the
+/// host user's services
 #[tauri::command]
-fn apps_launch(state: State<AppState>, app_id: String) -> Result<(), String> {
-    let guard = state.apps_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Apps service not initialized")?;
-    service.launch(&app_id).map_err(|e| e.to_string())
+fn guest_end(state: State<AppState>) -> Result<(), String> {
+    state.end_guest_session();
+    Ok(())
 }
 
 // ============================================================================
-// Launcher Service Commands
+// Key Service Commands
 // ============================================================================
 
 #[tauri::command]
-fn launcher_get_layout(state: State<AppState>) -> Result<String, String> {
-    let guard = state.launcher_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
-    let layout = service.get_layout().map_err(|e| e.to_string())?;
-    serde_json::to_string(&layout.app_ids).map_err(|e| e.to_string())
+fn keys_derive_batch(
+    state: State<AppState>,
+    component_id: String,
+    start_index: u64,
+    count: u64,
+    key_type: KeyType,
+) -> Result<String, String> {
+    let service = state.key_service()?;
+    let keys = service
+        .derive_batch(&component_id, start_index, count, key_type)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&keys).map_err(|e| e.to_string())
 }
 
+/// Operations made with a component's keys, for the security screen
 #[tauri::command]
-fn launcher_set_layout(state: State<AppState>, app_ids: Vec<String>) -> Result<(), String> {
-    let guard = state.launcher_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
-    service.set_layout(app_ids).map_err(|e| e.to_string())
+fn keys_usage_report(
+    state: State<AppState>,
+    component_id: String,
+    window: UsageWindow,
+) -> Result<KeyUsageReport, String> {
+    let service = state.key_service()?;
+    service
+        .usage_report(&component_id, window)
+        .map_err(|e| e.to_string())
 }
 
 // ============================================================================
-// UI Service Commands
+// Log Commands
 // ============================================================================
 
+/// Most recent persisted log records, for the debug console
 #[tauri::command]
-fn ui_get_theme(state: State<AppState>) -> Result<String, String> {
-    let guard = state.ui_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("UI service not initialized")?;
-    let theme = service.get_theme().map_err(|e| e.to_string())?;
-    Ok(match theme {
-        Theme::Light => "light".to_string(),
-        Theme::Dark => "dark".to_string(),
-        Theme::System => "system".to_string(),
-    })
+fn logs_query(
+    state: State<AppState>,
+    filter: Option<LogFilter>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let guard = state.logs_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Logs service not initialized")?;
+    let records = service
+        .query(&Caller::Local, &filter.unwrap_or_default(), limit)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&records).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Audit Log Commands
+// ============================================================================
+
+#[tauri::command]
+fn audit_list(
+    state: State<AppState>,
+    filter: Option<AuditFilter>,
+    page: Option<PageRequest>,
+) -> Result<String, String> {
+    let log = state.audit_log()?;
+    let page = log
+        .list(&filter.unwrap_or_default(), page.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&page).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn ui_set_theme(state: State<AppState>, theme: String) -> Result<(), String> {
-    let guard = state.ui_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("UI service not initialized")?;
-    let theme_enum = match theme.as_str() {
-        "light" => Theme::Light,
-        "dark" => Theme::Dark,
-        "system" => Theme::System,
-        _ => return Err("Invalid theme value".to_string()),
-    };
-    service.set_theme(theme_enum).map_err(|e| e.to_string())
+fn audit_verify(state: State<AppState>) -> Result<String, String> {
+    let status = state.audit_log()?.verify_chain().map_err(|e| e.to_string())?;
+    serde_json::to_string(&status).map_err(|e| e.to_string())
 }
 
 // ============================================================================
-// Navigation Service Commands
+// Wallet Commands
 // ============================================================================
 
+/// Upload payments matching a filter, newest first, with monthly totals
 #[tauri::command]
-fn navigation_get_bottom_menu(state: State<AppState>) -> Result<String, String> {
-    let guard = state.navigation_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Navigation service not initialized")?;
-    let tab = service.get_bottom_menu().map_err(|e| e.to_string())?;
-    Ok(match tab {
-        BottomMenuTab::Launcher => "launcher".to_string(),
-        BottomMenuTab::Wallet => "wallet".to_string(),
-        BottomMenuTab::Config => "config".to_string(),
-    })
+fn wallet_history(
+    state: State<AppState>,
+    filter: Option<HistoryFilter>,
+    page: Option<PageRequest>,
+) -> Result<String, String> {
+    let wallet = WalletService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    let history = wallet
+        .history(&filter.unwrap_or_default(), page.unwrap_or_default())
+        .map_err(|e| e.to_string())?
+        .localized(&state.display_context()?);
+    serde_json::to_string(&history).map_err(|e| e.to_string())
 }
 
+/// Check a payment an app asks for against the device policy's wallet caps
 #[tauri::command]
-fn navigation_set_bottom_menu(state: State<AppState>, tab: String) -> Result<(), String> {
-    let guard = state.navigation_service.lock().unwrap();
-    let service = guard.as_ref().ok_or("Navigation service not initialized")?;
-    let tab_enum = match tab.as_str() {
-        "launcher" => BottomMenuTab::Launcher,
-        "wallet" => BottomMenuTab::Wallet,
-        "config" => BottomMenuTab::Config,
-        _ => return Err("Invalid tab value".to_string()),
-    };
-    service.set_bottom_menu(tab_enum).map_err(|e| e.to_string())
+fn wallet_approve_payment(
+    state: State<AppState>,
+    app_id: String,
+    amount: u64,
+) -> Result<(), String> {
+    let policies = state
+        .policy_store
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| state.phase_two_error("wallet.approvePayment", "Policy"))?;
+    let wallet = WalletService::new(&state.storage_path)
+        .map_err(|e| e.to_string())?
+        .with_policies(policies)
+        .with_guest_gate(state.context.guest_gate());
+    wallet.approve_payment(&app_id, amount).map_err(|e| e.to_string())
+}
+
+/// Export every upload payment to a CSV or JSON file; returns the count
+#[tauri::command]
+fn wallet_export_history(
+    state: State<AppState>,
+    path: String,
+    format: ExportFormat,
+) -> Result<usize, String> {
+    let wallet = WalletService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    wallet.export_history(path, format).map_err(|e| e.to_string())
 }
 
 // ============================================================================
-// Status Service Commands
+// Apps Service Commands
 // ============================================================================
 
+/// Installed apps with the launcher generation they are current for
 #[tauri::command]
-fn status_get_server(state: State<AppState>) -> Result<String, String> {
-    let guard = state.status_service.lock().unwrap();
-    let status = guard.get_server().map_err(|e| e.to_string())?;
-    serde_json::to_string(&status).map_err(|e| e.to_string())
+fn apps_list(state: State<AppState>) -> Result<String, String> {
+    state.api.apps_list()
 }
 
-// ============================================================================
-// Tauri Entry Point
-// ============================================================================
+#[tauri::command]
+fn apps_list_uri_handlers(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let handlers = service.uri_handlers().map_err(|e| e.to_string())?;
+    serde_json::to_string(&handlers).map_err(|e| e.to_string())
+}
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Determine storage path
-    let storage_path = std::env::var("OSNOVA_STORAGE_PATH").unwrap_or_else(|_| {
-        use osnova_lib::platform::paths::get_data_dir;
-        get_data_dir()
-            .expect("Failed to get data directory")
-            .to_str()
-            .expect("Failed to convert path to string")
-            .to_string()
-    });
+#[tauri::command]
+fn apps_set_uri_handler(
+    state: State<AppState>,
+    scheme: String,
+    app_id: Option<String>,
+) -> Result<(), String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    service
+        .set_uri_handler(&scheme, app_id.as_deref())
+        .map_err(|e| e.to_string())
+}
 
-    let app_state = AppState::new(storage_path);
+/// Launch the app that handles a deep link; the UI opens its initial route
+#[tauri::command]
+fn apps_open_uri(state: State<AppState>, uri: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let handler = service.open_uri(&uri).map_err(|e| e.to_string())?;
+    serde_json::to_string(&handler).map_err(|e| e.to_string())
+}
 
-    let mut builder = tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init());
+#[tauri::command]
+fn apps_info(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let info = service.info(&app_id).map_err(|e| e.to_string())?;
+    let info = info.localized(&state.display_context()?);
+    serde_json::to_string(&info).map_err(|e| e.to_string())
+}
 
-    // Enable MCP plugin for AI-powered testing (debug builds only)
-    #[cfg(debug_assertions)]
-    {
-        use std::path::PathBuf;
-        builder = builder.plugin(tauri_plugin_mcp::init_with_config(
-            tauri_plugin_mcp::PluginConfig::new("app".to_string())
-                .start_socket_server(true)
-                // Use IPC socket (Unix domain socket on Linux/macOS)
-                .socket_path(PathBuf::from("/tmp/osnova-tauri-mcp.sock")),
-        ));
-    }
+#[tauri::command]
+fn provenance_for_component(
+    state: State<AppState>,
+    component_hash: String,
+) -> Result<String, String> {
+    let guard = state.provenance_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Provenance service not initialized")?;
+    let records = service.for_component(&component_hash).map_err(|e| e.to_string())?;
+    serde_json::to_string(&records).map_err(|e| e.to_string())
+}
 
-    builder
-        .manage(app_state)
+#[tauri::command]
+fn sessions_list(state: State<AppState>) -> Result<String, String> {
+    let guard = state.session_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Session service not initialized")?;
+    let sessions = service.list_sessions().map_err(|e| e.to_string())?;
+    serde_json::to_string(&sessions).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn sessions_revoke(state: State<AppState>, device_id: String) -> Result<usize, String> {
+    let guard = state.session_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Session service not initialized")?;
+    service.revoke(&device_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_launch(
+    handle: tauri::AppHandle,
+    state: State<AppState>,
+    app_id: String,
+    verify: Option<bool>,
+) -> Result<(), String> {
+    // Cancel a superseded launch before waiting on the service lock
+    let options = state.network_options(format!("apps_launch:{}", app_id));
+    let request = AppsLaunchRequest { app_id, verify };
+    // A restored app reports its download progress; a consent error
+    // carries the review so the frontend can show the consent sheet
+    tauri::async_runtime::block_on(state.api.apps_launch(request, &options, |p| {
+        emit(&handle, p.clone())
+    }))
+}
+
+/// Close an app's window; returns whether its backends were kept warm
+#[tauri::command]
+fn apps_close(state: State<AppState>, app_id: String) -> Result<bool, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    service.close(&app_id).map_err(|e| e.to_string())
+}
+
+/// What the user reviews before an app's first launch
+#[tauri::command]
+fn apps_consent_review(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let review = service.consent_review(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&review).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_record_consent(
+    state: State<AppState>,
+    app_id: String,
+    accepted: bool,
+    acknowledged_warnings: Option<Vec<String>>,
+) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let consent = service
+        .record_consent(&app_id, accepted, acknowledged_warnings.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&consent).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_pin(state: State<AppState>, app_id: String, version: String) -> Result<(), String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    service.pin_version(&app_id, &version).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_unpin(state: State<AppState>, app_id: String) -> Result<(), String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    service.unpin(&app_id).map_err(|e| e.to_string())
+}
+
+/// Update an app's name, icon and description from its published manifest
+#[tauri::command]
+fn apps_refresh_metadata(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let refresh = tauri::async_runtime::block_on(service.refresh_metadata(&app_id))
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&refresh).map_err(|e| e.to_string())
+}
+
+/// Refresh every installed app's listing, emitting progress as each finishes
+#[tauri::command]
+async fn apps_refresh_all(
+    handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let options = state.network_options("apps_refresh_all".to_string());
+    let service = state.metadata_service.lock().unwrap().clone();
+    let service = service.ok_or("Metadata service not initialized")?;
+    let progress = service
+        .refresh_all(DEFAULT_REFRESH_CONCURRENCY, &options, |progress| {
+            emit(&handle, progress.clone())
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&progress).map_err(|e| e.to_string())
+}
+
+/// Download the missing components of apps restored without their cache
+#[tauri::command]
+fn apps_materialize_all(state: State<AppState>) -> Result<Vec<MaterializeOutcome>, String> {
+    let options = state.network_options("apps_materialize_all".to_string());
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let policy = MaterializePolicy::default();
+    // Progress reaches the shell through the task registry
+    tauri::async_runtime::block_on(service.materialize_all(&policy, &options, |_| {}))
+        .map_err(|e| e.to_string())
+}
+
+/// Resolve a list of apps to install, adding up downloads and permissions
+#[tauri::command]
+fn apps_preview_install_many(state: State<AppState>, requests: String) -> Result<String, String> {
+    let requests: Vec<InstallRequest> =
+        serde_json::from_str(&requests).map_err(|e| e.to_string())?;
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let preview = tauri::async_runtime::block_on(service.preview_install_many(&requests))
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&preview).map_err(|e| e.to_string())
+}
+
+/// Install a list of apps, emitting progress as each finishes
+#[tauri::command]
+fn apps_install_many(
+    handle: tauri::AppHandle,
+    state: State<AppState>,
+    requests: String,
+) -> Result<String, String> {
+    let requests: Vec<InstallRequest> =
+        serde_json::from_str(&requests).map_err(|e| e.to_string())?;
+    let options = state.network_options("apps_install_many".to_string());
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let batch = BatchOptions::default();
+    let report = tauri::async_runtime::block_on(service.install_many(
+        &requests,
+        &batch,
+        &options,
+        |progress| emit(&handle, progress.clone()),
+    ))
+    .map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_launch_descriptor(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    serde_json::to_string(&service.launch_descriptor(&app_id)).map_err(|e| e.to_string())
+}
+
+/// Entry page and bundle root of an app's frontend
+#[tauri::command]
+fn apps_frontend_entry(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let entry = service.frontend_entry(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&entry).map_err(|e| e.to_string())
+}
+
+/// Crash reports of an app's backends, newest first
+#[tauri::command]
+fn apps_crash_reports(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let reports = service.crash_reports(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&reports).map_err(|e| e.to_string())
+}
+
+/// Health transitions of an app's backends, newest first
+#[tauri::command]
+fn apps_health_history(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let history = service.health_history(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&history).map_err(|e| e.to_string())
+}
+
+/// Compare a local build of a component with the hash its manifest declares
+#[tauri::command]
+fn apps_verify_reproducible(
+    state: State<AppState>,
+    app_id: String,
+    component_id: String,
+    artifact_path: String,
+) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let verdict = service
+        .verify_reproducible(&app_id, &component_id, std::path::Path::new(&artifact_path))
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&verdict).map_err(|e| e.to_string())
+}
+
+/// Declared and observed network access of an app's backends
+#[tauri::command]
+fn apps_privacy_report(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let report = service.privacy_report(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+/// Native backtrace of a crash report, resolved against debug symbols
+#[tauri::command]
+fn apps_symbolicate(state: State<AppState>, report_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let report = tauri::async_runtime::block_on(service.symbolicate(&report_id))
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+/// Remove cached component files no installed app owns ("Clean up now")
+#[tauri::command]
+fn cache_collect_garbage(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let report = tauri::async_runtime::block_on(service.collect_garbage())
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_verify(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let report =
+        tauri::async_runtime::block_on(service.verify(&app_id)).map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_repair(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let options = state.network_options(format!("apps_repair:{}", app_id));
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let report = tauri::async_runtime::block_on(service.repair_with(&app_id, &options))
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_restore(state: State<AppState>, app_id: String) -> Result<(), String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    service.restore(&app_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apps_list_trash(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let apps = service.list_trash().map_err(|e| e.to_string())?;
+    serde_json::to_string(&apps).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Notification Commands
+// ============================================================================
+
+#[tauri::command]
+fn notifications_post(
+    state: State<AppState>,
+    app_id: String,
+    notification: Notification,
+) -> Result<String, String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    let outcome = service.post(&app_id, notification).map_err(|e| e.to_string())?;
+    serde_json::to_string(&outcome).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn notifications_list(
+    state: State<AppState>,
+    filter: Option<NotificationFilter>,
+) -> Result<String, String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    let notifications = service
+        .list(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&notifications).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn notifications_unread_counts(state: State<AppState>) -> Result<String, String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    let counts = service.unread_counts().map_err(|e| e.to_string())?;
+    serde_json::to_string(&counts).map_err(|e| e.to_string())
+}
+
+/// Icon badge of every installed app, for the launcher's first render
+#[tauri::command]
+fn apps_badges(state: State<AppState>) -> Result<String, String> {
+    let service = state
+        .badge_service
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Badge service not initialized")?;
+    let badges = service.badges_all().map_err(|e| e.to_string())?;
+    serde_json::to_string(&badges).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn notifications_mark_read(state: State<AppState>, ids: Vec<i64>) -> Result<usize, String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    service.mark_read(&ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn notifications_clear(state: State<AppState>, app_id: String) -> Result<usize, String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    service.clear(&app_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn notifications_set_enabled(
+    state: State<AppState>,
+    app_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let guard = state.notification_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Notification service not initialized")?;
+    service.set_enabled(&app_id, enabled).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Permission Commands
+// ============================================================================
+
+/// Ask the user for a capability; resolves once the prompt is answered
+#[tauri::command]
+async fn permissions_request(
+    state: State<'_, AppState>,
+    app_id: String,
+    component_id: String,
+    capability: Capability,
+    rationale: String,
+) -> Result<(), String> {
+    let service = state.permission_service.lock().unwrap().clone();
+    let service = service.ok_or("Permission service not initialized")?;
+    service
+        .request(&app_id, &component_id, capability, &rationale)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn permissions_respond(
+    state: State<AppState>,
+    prompt_id: String,
+    granted: bool,
+    remember: bool,
+    proof: Option<AuthProof>,
+) -> Result<(), String> {
+    let guard = state.permission_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Permission service not initialized")?;
+    service
+        .respond(&prompt_id, granted, remember, proof.as_ref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn permissions_pending(state: State<AppState>) -> Result<String, String> {
+    let guard = state.permission_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Permission service not initialized")?;
+    serde_json::to_string(&service.pending()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn permissions_list(state: State<AppState>, app_id: Option<String>) -> Result<String, String> {
+    let guard = state.permission_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Permission service not initialized")?;
+    let grants = service.grants(app_id.as_deref()).map_err(|e| e.to_string())?;
+    serde_json::to_string(&grants).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn permissions_revoke(
+    state: State<AppState>,
+    app_id: String,
+    component_id: String,
+    capability: Capability,
+) -> Result<bool, String> {
+    let guard = state.permission_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Permission service not initialized")?;
+    service
+        .revoke(&app_id, &component_id, capability)
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Data Sharing Commands
+// ============================================================================
+
+/// Ask the user to let an app read another app's data offer
+#[tauri::command]
+async fn shared_request_access(
+    state: State<'_, AppState>,
+    consumer_app_id: String,
+    component_id: String,
+    provider_app_id: String,
+    offer_id: String,
+) -> Result<String, String> {
+    let service = state.sharing_service.lock().unwrap().clone();
+    let service = service.ok_or("Sharing service not initialized")?;
+    let grant = service
+        .request_access(&consumer_app_id, &component_id, &provider_app_id, &offer_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&grant).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn shared_list(
+    state: State<AppState>,
+    consumer_app_id: String,
+    provider_app_id: String,
+    offer_id: String,
+) -> Result<Vec<String>, String> {
+    let guard = state.sharing_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Sharing service not initialized")?;
+    service
+        .list(&consumer_app_id, &provider_app_id, &offer_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn shared_read(
+    state: State<AppState>,
+    consumer_app_id: String,
+    provider_app_id: String,
+    offer_id: String,
+    key: String,
+) -> Result<Vec<u8>, String> {
+    let guard = state.sharing_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Sharing service not initialized")?;
+    service
+        .read(&consumer_app_id, &provider_app_id, &offer_id, &key)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn shared_revoke(
+    state: State<AppState>,
+    provider_app_id: String,
+    offer_id: String,
+    consumer_app_id: String,
+) -> Result<bool, String> {
+    let guard = state.sharing_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Sharing service not initialized")?;
+    service
+        .revoke(&provider_app_id, &offer_id, &consumer_app_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Grants for the privacy settings screen
+#[tauri::command]
+fn shared_grants(state: State<AppState>, app_id: Option<String>) -> Result<String, String> {
+    let guard = state.sharing_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Sharing service not initialized")?;
+    let grants = service.grants(app_id.as_deref()).map_err(|e| e.to_string())?;
+    serde_json::to_string(&grants).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Launcher Service Commands
+// ============================================================================
+
+/// Layout with the launcher generation it is current for
+#[tauri::command]
+fn launcher_get_layout(state: State<AppState>) -> Result<String, String> {
+    state.api.launcher_get_layout()
+}
+
+#[tauri::command]
+fn launcher_generation(state: State<AppState>) -> Result<u64, String> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    service.generation().map_err(|e| e.to_string())
+}
+
+/// What changed in the launcher grid since the generation last rendered
+#[tauri::command]
+fn launcher_changes_since(state: State<AppState>, generation: u64) -> Result<String, String> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    let changes = service.changes_since(generation).map_err(|e| e.to_string())?;
+    serde_json::to_string(&changes).map_err(|e| e.to_string())
+}
+
+/// Launcher catalog labelled with its source (embedded, network, or cache)
+#[tauri::command]
+fn launcher_get_catalog(state: State<AppState>) -> Result<String, String> {
+    let options = state.network_options("launcher_get_catalog".to_string());
+    let service = state
+        .catalog_service
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| state.phase_two_error("launcher.getCatalog", "Catalog"))?;
+    let manifest_uri = {
+        let guard = state.config_service.lock().unwrap();
+        let config = guard.as_ref().ok_or("Config service not initialized")?;
+        config.get_launcher_manifest().map_err(|e| e.to_string())?
+    };
+
+    let catalog = tauri::async_runtime::block_on(async {
+        // Without a client an ant:// catalog fails over to the cache
+        let client = match manifest_uri.as_deref() {
+            Some(uri) if uri.starts_with("ant://") => {
+                AutonomiClient::connect_with(&options).await.ok()
+            }
+            _ => None,
+        };
+        service.load(manifest_uri.as_deref(), client.as_ref(), &options).await
+    });
+    serde_json::to_string(&catalog).map_err(|e| e.to_string())
+}
+
+/// Effective values of an app's feature flags, with their evaluation
+#[tauri::command]
+fn features_all(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.feature_flag_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Feature flag service not initialized")?;
+    let flags = service.all(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&flags).map_err(|e| e.to_string())
+}
+
+/// Override a feature flag locally; `null` clears the override
+#[tauri::command]
+fn features_set_override(
+    state: State<AppState>,
+    app_id: String,
+    flag: String,
+    value: Option<bool>,
+) -> Result<String, String> {
+    let guard = state.feature_flag_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Feature flag service not initialized")?;
+    let flag = service
+        .set_override(&app_id, &flag, value)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&flag).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn launcher_set_layout(state: State<AppState>, app_ids: Vec<String>) -> Result<(), String> {
+    state.api.launcher_set_layout(LauncherSetLayoutRequest { app_ids })
+}
+
+#[tauri::command]
+fn launcher_set_folder(
+    state: State<AppState>,
+    app_id: String,
+    folder: Option<String>,
+) -> Result<(), String> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    service
+        .set_folder(&app_id, folder.as_deref())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn launcher_set_pinned(state: State<AppState>, app_id: String, pinned: bool) -> Result<(), String> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    service.set_pinned(&app_id, pinned).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Rollback information for a failed optimistic mutation
+fn mutation_rejected(error: anyhow::Error, mutation_id: &str) -> MutationRejected {
+    match error.downcast_ref::<MutationRejected>() {
+        Some(rejection) => rejection.clone(),
+        None => MutationRejected {
+            mutation_id: mutation_id.to_string(),
+            last_good_generation: None,
+            reason: error.to_string(),
+        },
+    }
+}
+
+/// Apply a layout edit the frontend already shows; retries reuse `mutation_id`
+#[tauri::command]
+fn launcher_mutate(
+    state: State<AppState>,
+    mutation_id: String,
+    mutation: LauncherMutation,
+) -> Result<MutationReceipt, MutationRejected> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or_else(|| {
+        mutation_rejected(anyhow::anyhow!("Launcher service not initialized"), &mutation_id)
+    })?;
+    service
+        .mutate(&mutation_id, mutation)
+        .map_err(|e| mutation_rejected(e, &mutation_id))
+}
+
+/// The layout, only if it moved on since the frontend's generation
+#[tauri::command]
+fn launcher_get_since(
+    state: State<AppState>,
+    generation: u64,
+) -> Result<Since<LauncherLayout>, String> {
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    service.get_since(generation).map_err(|e| e.to_string())
+}
+
+/// Merge the layout synced from another device; returns the merge report
+#[tauri::command]
+fn launcher_merge_layout(state: State<AppState>, layout: String) -> Result<String, String> {
+    let remote: LauncherLayout = serde_json::from_str(&layout).map_err(|e| e.to_string())?;
+    let guard = state.api.launcher().lock();
+    let service = guard.as_ref().ok_or("Launcher service not initialized")?;
+    let report = service.merge_layout(&remote).map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// UI Service Commands
+// ============================================================================
+
+#[tauri::command]
+fn ui_get_theme(state: State<AppState>) -> Result<String, String> {
+    state.api.ui_get_theme()
+}
+
+/// Locale the display fields of responses are formatted for
+#[tauri::command]
+fn ui_get_locale(state: State<AppState>) -> Result<Locale, String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    service.get_locale().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ui_set_locale(state: State<AppState>, locale: Locale) -> Result<(), String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    service.set_locale(&locale).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ui_set_theme(state: State<AppState>, theme: String) -> Result<(), String> {
+    state.api.ui_set_theme(UiSetThemeRequest { theme })
+}
+
+#[tauri::command]
+fn ui_save_app_state(
+    state: State<AppState>,
+    app_id: String,
+    snapshot: Vec<u8>,
+) -> Result<(), String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    service
+        .save_app_state(&app_id, snapshot)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ui_load_app_state(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    let snapshot = service.load_app_state(&app_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&snapshot).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ui_clear_app_state(state: State<AppState>, app_id: String) -> Result<bool, String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    service.clear_app_state(&app_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ui_app_state_usage(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.ui().lock();
+    let service = guard.as_ref().ok_or("UI service not initialized")?;
+    let usage = service.app_state_usage().map_err(|e| e.to_string())?;
+    serde_json::to_string(&usage).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Navigation Service Commands
+// ============================================================================
+
+#[tauri::command]
+fn navigation_get_bottom_menu(state: State<AppState>) -> Result<String, String> {
+    state.api.navigation_get_bottom_menu()
+}
+
+#[tauri::command]
+fn navigation_set_bottom_menu(state: State<AppState>, tab: String) -> Result<(), String> {
+    state.api.navigation_set_bottom_menu(NavigationSetBottomMenuRequest { tab })
+}
+
+// ============================================================================
+// Status Service Commands
+// ============================================================================
+
+#[tauri::command]
+fn status_get_server(state: State<AppState>) -> Result<String, String> {
+    state.api.status_get_server()
+}
+
+/// The guest session running, if any, for the status indicator
+#[tauri::command]
+fn status_get_guest_session(state: State<AppState>) -> Result<Option<GuestSessionInfo>, String> {
+    state.api.status().get_guest_session().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn status_get_disk_health(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.status();
+    let health = guard.get_disk_health().map_err(|e| e.to_string())?;
+    serde_json::to_string(&health).map_err(|e| e.to_string())
+}
+
+/// Diagnose why the network cannot be reached, for onboarding and the
+/// status screen's "Having trouble?" button
+#[tauri::command]
+async fn network_run_diagnostics(state: State<'_, AppState>) -> Result<String, String> {
+    let mut doctor = NetworkDoctor::system();
+    let handshake = state.launch_handshake.lock().unwrap().clone();
+    if let Some(address) = handshake.and_then(|handshake| handshake.rpc_address()) {
+        doctor = doctor.with_listen_address(address);
+    }
+    let diagnosis = doctor.run().await;
+    serde_json::to_string(&diagnosis).map_err(|e| e.to_string())
+}
+
+/// Whether the user switched Osnova offline, for the status indicator
+#[tauri::command]
+fn status_get_network(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.status();
+    let network = guard.get_network().map_err(|e| e.to_string())?;
+    serde_json::to_string(&network).map_err(|e| e.to_string())
+}
+
+/// Retry budget and circuit of each network destination
+#[tauri::command]
+fn status_get_retry_budgets(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.status();
+    let budgets = guard.get_retry_budgets().map_err(|e| e.to_string())?;
+    serde_json::to_string(&budgets).map_err(|e| e.to_string())
+}
+
+/// Switch Osnova offline or back online, returning the new network status
+///
+/// Going offline cancels the requests in flight and withdraws this
+/// device's LAN announcement; deferred work resumes once back online.
+#[tauri::command]
+fn network_set_offline_mode(state: State<AppState>, offline: bool) -> Result<String, String> {
+    {
+        let guard = state.config_service.lock().unwrap();
+        let service = guard.as_ref().ok_or("Config service not initialized")?;
+        service.set_offline_mode(offline).map_err(|e| e.to_string())?;
+    }
+
+    kill_switch::shared().set(offline);
+    if offline {
+        state.cancel_network_requests();
+        if let Some(discovery) = state.lan_discovery.lock().unwrap().as_ref() {
+            discovery.withdraw().map_err(|e| e.to_string())?;
+        }
+    }
+    status_get_network(state)
+}
+
+// ============================================================================
+// Config Preset Commands
+// ============================================================================
+
+#[tauri::command]
+fn config_export_preset(
+    state: State<AppState>,
+    app_id: String,
+    include_keys: Vec<String>,
+    path: String,
+) -> Result<(), String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let preset = service
+        .export_app_preset(&app_id, &user_id, &include_keys)
+        .map_err(unlock_error)?;
+    let json = serde_json::to_vec_pretty(&preset).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write preset: {}", e))
+}
+
+#[tauri::command]
+fn config_import_preset(
+    state: State<AppState>,
+    app_id: String,
+    path: String,
+    policy: String,
+    author_key: Option<String>,
+) -> Result<String, String> {
+    let policy = match policy.as_str() {
+        "merge" => PresetImportPolicy::Merge,
+        "overwrite" => PresetImportPolicy::Overwrite,
+        _ => return Err("Invalid import policy".to_string()),
+    };
+    let json = std::fs::read(&path).map_err(|e| format!("Failed to read preset: {}", e))?;
+    let preset: PresetDocument =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid preset: {}", e))?;
+
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    // Without a named author only the user's own presets are accepted
+    let author_key = match author_key {
+        Some(key) => key,
+        None => service.preset_author_key().map_err(unlock_error)?,
+    };
+    let result = service
+        .import_app_preset(&app_id, &user_id, &preset, &author_key, policy)
+        .map_err(unlock_error)?;
+    serde_json::to_string(&result).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// App Settings Commands
+// ============================================================================
+
+/// An app's settings schema with the current values, for the Config screen
+#[tauri::command]
+fn config_get_settings(state: State<AppState>, app_id: String) -> Result<SettingsPayload, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service.settings_payload(&app_id, &user_id).map_err(unlock_error)
+}
+
+/// Save values from the Config screen
+///
+/// Returns the fields the app's settings schema rejects; nothing is saved
+/// unless the list is empty.
+#[tauri::command]
+fn config_set_settings(
+    state: State<AppState>,
+    app_id: String,
+    values: HashMap<String, serde_json::Value>,
+) -> Result<Vec<SettingFieldError>, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    match service.set_app_config(&app_id, &user_id, values) {
+        Ok(_) => Ok(Vec::new()),
+        Err(e) => match e.downcast_ref::<OsnovaError>() {
+            Some(OsnovaError::InvalidSettings { errors, .. }) => Ok(errors.clone()),
+            _ => Err(unlock_error(e)),
+        },
+    }
+}
+
+/// Set a secret setting from the Config screen; it is never sent back
+///
+/// Returns the rejection if the value does not fit the field.
+#[tauri::command]
+fn config_set_secret(
+    state: State<AppState>,
+    app_id: String,
+    key: String,
+    value: serde_json::Value,
+) -> Result<Vec<SettingFieldError>, String> {
+    let user_id = state.current_user()?;
+    let service = state.secrets_config()?;
+    match service.set_app_secret(&app_id, &user_id, &key, value) {
+        Ok(()) => Ok(Vec::new()),
+        Err(e) => match e.downcast_ref::<OsnovaError>() {
+            Some(OsnovaError::InvalidSettings { errors, .. }) => Ok(errors.clone()),
+            _ => Err(unlock_error(e)),
+        },
+    }
+}
+
+/// Clear a secret setting from the Config screen
+#[tauri::command]
+fn config_clear_secret(
+    state: State<AppState>,
+    app_id: String,
+    key: String,
+) -> Result<bool, String> {
+    let user_id = state.current_user()?;
+    let service = state.secrets_config()?;
+    service
+        .clear_app_secret(&app_id, &user_id, &key)
+        .map_err(unlock_error)
+}
+
+/// Snapshots of an app's settings for the Config screen, newest first
+#[tauri::command]
+fn config_list_snapshots(
+    state: State<AppState>,
+    app_id: String,
+) -> Result<Vec<ConfigSnapshot>, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service.list_snapshots(&app_id, &user_id).map_err(unlock_error)
+}
+
+/// Restore an app's settings from a snapshot
+///
+/// Returns the snapshot of the settings as they were, to undo the restore.
+#[tauri::command]
+fn config_restore_snapshot(
+    state: State<AppState>,
+    app_id: String,
+    snapshot_id: i64,
+    mode: RestoreMode,
+) -> Result<ConfigSnapshot, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service
+        .restore_snapshot(&app_id, &user_id, snapshot_id, mode)
+        .map_err(unlock_error)
+}
+
+/// Bytes of settings snapshots stored per app, as JSON
+#[tauri::command]
+fn config_snapshot_usage(state: State<AppState>) -> Result<String, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let usage = service.snapshot_usage(&user_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&usage).map_err(|e| e.to_string())
+}
+
+/// Save a setting the Config screen already shows; retries reuse `mutation_id`
+///
+/// Values the app's settings schema rejects fail like any other error, with
+/// the generation to roll back to.
+#[tauri::command]
+fn config_mutate_settings(
+    state: State<AppState>,
+    mutation_id: String,
+    app_id: String,
+    values: HashMap<String, serde_json::Value>,
+) -> Result<MutationReceipt, MutationRejected> {
+    let rejected = |e| mutation_rejected(e, &mutation_id);
+    let user_id = state.current_user().map_err(|e| rejected(anyhow::anyhow!(e)))?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| rejected(anyhow::anyhow!("Config service not initialized")))?;
+    service
+        .mutate_app_config(&mutation_id, &app_id, &user_id, values)
+        .map_err(rejected)
+}
+
+/// An app's configuration, only if it moved on since the frontend's generation
+#[tauri::command]
+fn config_get_since(
+    state: State<AppState>,
+    app_id: String,
+    generation: u64,
+) -> Result<Since<AppConfiguration>, String> {
+    let user_id = state.current_user()?;
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service.get_since(&app_id, &user_id, generation).map_err(unlock_error)
+}
+
+/// Hit and miss counters of the per-app configuration cache
+#[tauri::command]
+fn config_cache_metrics(state: State<AppState>) -> Result<String, String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    serde_json::to_string(&service.cache_metrics()).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Discovery Commands
+// ============================================================================
+
+/// Servers on the local network, for the pairing picker
+///
+/// The fingerprints are unverified until pairing completes.
+#[tauri::command]
+async fn discovery_find(
+    state: State<'_, AppState>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    let discovery = state.lan_discovery()?;
+    let timeout = std::time::Duration::from_millis(timeout_ms.unwrap_or(3000));
+    let servers = tauri::async_runtime::spawn_blocking(move || discovery.discover(timeout))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&servers).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn discovery_get_announcements(state: State<AppState>) -> Result<bool, String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config.get_lan_announcements_enabled().map_err(|e| e.to_string())
+}
+
+/// Allow or forbid announcing this device; turning it off withdraws a
+/// running announcement
+#[tauri::command]
+fn discovery_set_announcements(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config
+        .set_lan_announcements_enabled(enabled)
+        .map_err(|e| e.to_string())?;
+
+    if let Some(discovery) = state.lan_discovery.lock().unwrap().as_ref() {
+        discovery
+            .set_announcements_enabled(enabled)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Diagnostics Commands
+// ============================================================================
+
+/// Whether chaos mode can be switched on in this build, and the profile in
+/// effect if it is
+#[tauri::command]
+fn diagnostics_chaos_status(state: State<AppState>) -> Result<String, String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    let profile = config.get_chaos_profile().map_err(|e| e.to_string())?;
+    serde_json::to_string(&serde_json::json!({
+        "available": osnova_lib::storage::chaos::DebugGate::chaos_allowed(),
+        "active": profile.is_some(),
+        "profile": profile,
+    }))
+    .map_err(|e| e.to_string())
+}
+
+/// Turn chaos mode on with a JSON profile, or off with `null`; refused in
+/// builds without the `chaos` feature
+#[tauri::command]
+fn diagnostics_set_chaos_profile(
+    state: State<AppState>,
+    profile_json: Option<String>,
+) -> Result<(), String> {
+    let profile = profile_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config.set_chaos_profile(profile).map_err(|e| e.to_string())
+}
+
+/// Whether diagnostic queries can run in this build, and the queries
+#[tauri::command]
+fn diagnostics_queries() -> Result<String, String> {
+    serde_json::to_string(&serde_json::json!({
+        "available": osnova_lib::storage::chaos::DebugGate::diagnostic_queries_allowed(),
+        "queries": QUERIES,
+    }))
+    .map_err(|e| e.to_string())
+}
+
+/// Parse the JSON object of a diagnostic query's parameters
+fn query_params(
+    params_json: Option<String>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match params_json {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(serde_json::Map::new()),
+    }
+}
+
+/// Issue the confirmation the user has to give before a diagnostic query
+/// runs; refused in builds without the `diagnostics` feature
+#[tauri::command]
+fn diagnostics_begin_query(
+    state: State<AppState>,
+    name: String,
+    params_json: Option<String>,
+) -> Result<String, String> {
+    let params = query_params(params_json)?;
+    let confirmation = state
+        .diagnostic_queries()?
+        .begin(&name, &params)
+        .map_err(|e| format!("{:#}", e))?;
+    serde_json::to_string(&confirmation).map_err(|e| e.to_string())
+}
+
+/// Run a diagnostic query the user confirmed, returning its redacted rows
+#[tauri::command]
+fn diagnostics_run_query(
+    state: State<AppState>,
+    name: String,
+    params_json: Option<String>,
+    token: String,
+) -> Result<String, String> {
+    let params = query_params(params_json)?;
+    let result = state
+        .diagnostic_queries()?
+        .run_diagnostic_query(&name, &params, &token)
+        .map_err(|e| format!("{:#}", e))?;
+    serde_json::to_string(&result).map_err(|e| e.to_string())
+}
+
+/// Whether apps need the user's consent before their first launch
+#[tauri::command]
+fn diagnostics_get_launch_consent(state: State<AppState>) -> Result<bool, String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config.get_first_launch_consent_required().map_err(|e| e.to_string())
+}
+
+/// Turn the first-launch consent requirement off while developing apps, or
+/// back on
+#[tauri::command]
+fn diagnostics_set_launch_consent(state: State<AppState>, required: bool) -> Result<(), String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config
+        .set_first_launch_consent_required(required)
+        .map_err(|e| e.to_string())
+}
+
+/// Whether every installed manifest must declare a signature policy
+#[tauri::command]
+fn diagnostics_get_signature_policy_required(state: State<AppState>) -> Result<bool, String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config.get_signature_policy_required().map_err(|e| e.to_string())
+}
+
+/// Refuse installs and updates whose manifest declares no signature policy
+/// (strict mode), or accept them again
+#[tauri::command]
+fn diagnostics_set_signature_policy_required(
+    state: State<AppState>,
+    required: bool,
+) -> Result<(), String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config
+        .set_signature_policy_required(required)
+        .map_err(|e| e.to_string())
+}
+
+/// Crash reports included in diagnostics
+const DIAGNOSTIC_CRASH_REPORTS: usize = 20;
+
+/// Most recent backend crash reports of every app, for diagnostics
+#[tauri::command]
+fn diagnostics_crash_reports(state: State<AppState>) -> Result<String, String> {
+    let guard = state.api.apps().lock();
+    let service = guard.as_ref().ok_or("Apps service not initialized")?;
+    let reports = service
+        .recent_crash_reports(DIAGNOSTIC_CRASH_REPORTS)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&reports).map_err(|e| e.to_string())
+}
+
+/// Upload payments of the last 30 days, for diagnostics; amounts only
+/// unless addresses were allowed
+#[tauri::command]
+fn diagnostics_payment_summary(state: State<AppState>) -> Result<String, String> {
+    let wallet = WalletService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    let summary = wallet.diagnostics_summary().map_err(|e| e.to_string())?;
+    serde_json::to_string(&summary).map_err(|e| e.to_string())
+}
+
+/// Apps predicted by the launch predictor included in diagnostics
+const DIAGNOSTIC_PREDICTIONS: usize = 20;
+
+/// Apps the launch predictor expects within the next `window_secs` (the
+/// cache warmer's window unless given), likeliest first, with their scores
+#[tauri::command]
+fn diagnostics_launch_predictions(
+    state: State<AppState>,
+    window_secs: Option<u64>,
+) -> Result<String, String> {
+    let predictor = LaunchPredictor::new(&state.storage_path).map_err(|e| e.to_string())?;
+    let window = window_secs.unwrap_or(WarmingPolicy::default().window_secs);
+    let predicted = predictor
+        .predict(std::time::Duration::from_secs(window), DIAGNOSTIC_PREDICTIONS)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&predicted).map_err(|e| e.to_string())
+}
+
+/// Forget every recorded app launch, resetting the launch predictor
+#[tauri::command]
+fn diagnostics_clear_app_usage(state: State<AppState>) -> Result<(), String> {
+    let predictor = LaunchPredictor::new(&state.storage_path).map_err(|e| e.to_string())?;
+    predictor.clear_usage().map_err(|e| e.to_string())
+}
+
+/// Include the addresses paid for in the diagnostics payment summary, or stop
+#[tauri::command]
+fn diagnostics_set_payment_addresses(state: State<AppState>, include: bool) -> Result<(), String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config
+        .set_diagnostics_payment_addresses(include)
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Bandwidth Commands
+// ============================================================================
+
+#[tauri::command]
+fn bandwidth_usage(state: State<AppState>) -> Result<String, String> {
+    let guard = state.bandwidth_meter.lock().unwrap();
+    let meter = guard.as_ref().ok_or("Bandwidth meter not initialized")?;
+    let usage = meter.usage().map_err(|e| e.to_string())?;
+    let usage = usage.localized(&state.display_context()?);
+    serde_json::to_string(&usage).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn bandwidth_get_policy(state: State<AppState>) -> Result<String, String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let policy = service.get_bandwidth_policy().map_err(|e| e.to_string())?;
+    let metered_mode = service.get_metered_mode().map_err(|e| e.to_string())?;
+    serde_json::to_string(&serde_json::json!({
+        "policy": policy,
+        "meteredMode": metered_mode,
+    }))
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn bandwidth_set_policy(
+    state: State<AppState>,
+    policy: String,
+    metered_mode: bool,
+) -> Result<(), String> {
+    let policy: BandwidthPolicy =
+        serde_json::from_str(&policy).map_err(|e| format!("Invalid policy: {}", e))?;
+
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service.set_bandwidth_policy(policy).map_err(|e| e.to_string())?;
+    service.set_metered_mode(metered_mode).map_err(|e| e.to_string())?;
+
+    // Apply to the running meter so deferred work can resume
+    if let Some(meter) = state.bandwidth_meter.lock().unwrap().as_ref() {
+        meter.set_policy(policy);
+        meter.set_metered_mode(metered_mode);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn fetch_get_policy(state: State<AppState>) -> Result<String, String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let policy = service.get_fetch_policy().map_err(|e| e.to_string())?;
+    serde_json::to_string(&policy).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn fetch_set_policy(state: State<AppState>, policy: String) -> Result<(), String> {
+    let policy: FetchPolicy =
+        serde_json::from_str(&policy).map_err(|e| format!("Invalid policy: {}", e))?;
+
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    service.set_fetch_policy(policy.clone()).map_err(|e| e.to_string())?;
+
+    // Later fetches use the new policy
+    osnova_lib::http::set_shared_policy(policy);
+    Ok(())
+}
+
+#[tauri::command]
+fn runtime_get_settings(state: State<AppState>) -> Result<String, String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let settings = service.get_runtime_settings().map_err(|e| e.to_string())?;
+    serde_json::to_string(&settings).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn runtime_set_settings(state: State<AppState>, settings: String) -> Result<(), String> {
+    let settings: RuntimeSettings =
+        serde_json::from_str(&settings).map_err(|e| format!("Invalid settings: {}", e))?;
+
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    let compression = settings.compression;
+    service.set_runtime_settings(settings).map_err(|e| e.to_string())?;
+
+    // Storage opened later compresses with the new settings
+    osnova_lib::storage::compression::set_shared_settings(compression);
+    Ok(())
+}
+
+// ============================================================================
+// Task Commands
+// ============================================================================
+
+/// Long-running operations in flight, oldest first
+#[tauri::command]
+fn tasks_list(state: State<AppState>) -> Result<String, String> {
+    serde_json::to_string(&state.tasks.list_active()).map_err(|e| e.to_string())
+}
+
+/// Recently finished operations, newest first
+#[tauri::command]
+fn tasks_history(state: State<AppState>, limit: Option<usize>) -> Result<String, String> {
+    let limit = limit.unwrap_or(osnova_lib::models::task::TASK_HISTORY);
+    serde_json::to_string(&state.tasks.history(limit)).map_err(|e| e.to_string())
+}
+
+/// Cancel a running operation
+#[tauri::command]
+fn tasks_cancel(state: State<AppState>, task_id: String) -> Result<(), String> {
+    state.tasks.cancel(&task_id).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Update Commands
+// ============================================================================
+
+#[tauri::command]
+fn updates_check(state: State<AppState>) -> Result<String, String> {
+    let service = state.update_service.lock().unwrap().clone();
+    let service = service.ok_or("Update service not initialized")?;
+    let updates =
+        tauri::async_runtime::block_on(service.check_updates()).map_err(|e| e.to_string())?;
+    serde_json::to_string(&updates).map_err(|e| e.to_string())
+}
+
+/// Install a downloaded update; returns the installed version
+#[tauri::command]
+fn updates_apply(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let guard = state.update_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Update service not initialized")?;
+    service.apply_update(&app_id).map_err(|e| e.to_string())
+}
+
+/// Restore the version the last update replaced; returns that version
+#[tauri::command]
+fn updates_rollback(state: State<AppState>, app_id: String) -> Result<String, String> {
+    let service = state.update_service.lock().unwrap().clone();
+    let service = service.ok_or("Update service not initialized")?;
+    tauri::async_runtime::block_on(service.rollback(&app_id)).map_err(|e| e.to_string())
+}
+
+/// Effective policy for an app, or the default policy without `app_id`
+#[tauri::command]
+fn updates_get_policy(
+    state: State<AppState>,
+    app_id: Option<String>,
+) -> Result<UpdatePolicy, String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    match app_id {
+        Some(app_id) => service.get_update_policy(&app_id),
+        None => service.get_default_update_policy(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Set an app's policy (`None` follows the default), or the default policy
+/// without `app_id`
+#[tauri::command]
+fn updates_set_policy(
+    state: State<AppState>,
+    app_id: Option<String>,
+    policy: Option<UpdatePolicy>,
+) -> Result<(), String> {
+    let guard = state.config_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Config service not initialized")?;
+    match app_id {
+        Some(app_id) => service.set_app_update_policy(&app_id, policy),
+        None => service.set_default_update_policy(policy.unwrap_or_default()),
+    }
+    .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Search Commands
+// ============================================================================
+
+#[tauri::command]
+fn search_query(
+    state: State<AppState>,
+    query: String,
+    scope: Option<SearchScope>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let guard = state.search_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("search.query", "Search"))?;
+    let results = service.query(&query, scope.unwrap_or_default(), limit.unwrap_or(20));
+    serde_json::to_string(&results).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_reindex(state: State<AppState>) -> Result<usize, String> {
+    let guard = state.search_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("search.reindex", "Search"))?;
+    service.index_all().map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Annotation Commands
+// ============================================================================
+
+/// Attach a note to an app or device, replacing any earlier one
+#[tauri::command]
+fn annotations_set(
+    state: State<AppState>,
+    subject: Subject,
+    text: String,
+) -> Result<String, String> {
+    let guard = state.annotation_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("annotations.set", "Annotation"))?;
+    let annotation = service.set(&subject, &text).map_err(|e| e.to_string())?;
+    serde_json::to_string(&annotation).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn annotations_get(state: State<AppState>, subject: Subject) -> Result<String, String> {
+    let guard = state.annotation_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("annotations.get", "Annotation"))?;
+    let annotation = service.get(&subject).map_err(|e| e.to_string())?;
+    serde_json::to_string(&annotation).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn annotations_delete(state: State<AppState>, subject: Subject) -> Result<bool, String> {
+    let guard = state.annotation_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("annotations.delete", "Annotation"))?;
+    service.delete(&subject).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn annotations_search(state: State<AppState>, query: String) -> Result<String, String> {
+    let guard = state.annotation_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("annotations.search", "Annotation"))?;
+    let annotations = service.search(&query).map_err(|e| e.to_string())?;
+    serde_json::to_string(&annotations).map_err(|e| e.to_string())
+}
+
+/// Every note, for the devices screen to show next to each device
+#[tauri::command]
+fn annotations_list(state: State<AppState>) -> Result<String, String> {
+    let guard = state.annotation_service.lock().unwrap();
+    let service = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("annotations.list", "Annotation"))?;
+    let annotations = service.list().map_err(|e| e.to_string())?;
+    serde_json::to_string(&annotations).map_err(|e| e.to_string())
+}
+
+/// Include annotations in network backups, or keep them on this device
+#[tauri::command]
+fn annotations_set_network_backup(state: State<AppState>, include: bool) -> Result<(), String> {
+    let config = ConfigService::new(&state.storage_path).map_err(|e| e.to_string())?;
+    config
+        .set_annotations_network_backup(include)
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Group Policy Commands
+// ============================================================================
+
+/// Apply a group policy signed by the admin; returns its summary
+#[tauri::command]
+fn policies_apply(state: State<AppState>, policy: SignedPolicy) -> Result<String, String> {
+    let guard = state.policy_store.lock().unwrap();
+    let store = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("policies.apply", "Policy"))?;
+    let summary = store.apply(&policy).map_err(|e| e.to_string())?;
+    serde_json::to_string(&summary).map_err(|e| e.to_string())
+}
+
+/// Lift the group policy with a removal signed by its admin
+#[tauri::command]
+fn policies_remove(state: State<AppState>, removal: SignedRemoval) -> Result<(), String> {
+    let guard = state.policy_store.lock().unwrap();
+    let store = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("policies.remove", "Policy"))?;
+    store.remove(&removal).map_err(|e| e.to_string())
+}
+
+/// The group policy in force, for the settings screen
+#[tauri::command]
+fn policies_summary(state: State<AppState>) -> Result<String, String> {
+    let guard = state.policy_store.lock().unwrap();
+    let store = guard
+        .as_ref()
+        .ok_or_else(|| state.phase_two_error("policies.summary", "Policy"))?;
+    serde_json::to_string(&store.summary()).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Security Commands
+// ============================================================================
+
+/// At-rest encryption audit for the security settings screen
+#[tauri::command]
+fn security_audit(state: State<AppState>) -> Result<String, String> {
+    let user_id = state.current_user()?;
+    let guard = state.api.identity().lock();
+    let identity = guard.as_ref().and_then(|service| service.get_identity().ok());
+
+    let mut ctx = AuditContext::new(&state.storage_path, &user_id);
+    if let Some(identity) = &identity {
+        ctx = ctx.with_identity(identity);
+    }
+    if let Ok(cache_dir) = osnova_lib::platform::paths::get_component_cache_dir() {
+        ctx = ctx.with_cache_dir(cache_dir);
+    }
+    ctx = ctx.with_authenticator(state.reauth_service.backend());
+
+    let report = EncryptionAudit::run(&ctx).map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// OS Re-authentication Commands
+// ============================================================================
+
+/// Authenticator backend and the operations that require it
+#[tauri::command]
+fn auth_status(state: State<AppState>) -> Result<String, String> {
+    let status = state.reauth_service.status().map_err(|e| e.to_string())?;
+    serde_json::to_string(&status).map_err(|e| e.to_string())
+}
+
+/// Show the OS prompt for an operation; the proof goes with the operation
+#[tauri::command]
+async fn auth_authenticate(
+    state: State<'_, AppState>,
+    operation: SensitiveOperation,
+) -> Result<AuthProof, String> {
+    let reauth = state.reauth_service.clone();
+    tauri::async_runtime::spawn_blocking(move || reauth.authenticate(operation))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn auth_set_required(
+    state: State<AppState>,
+    operation: SensitiveOperation,
+    required: bool,
+    proof: Option<AuthProof>,
+) -> Result<(), String> {
+    state
+        .reauth_service
+        .set_required(operation, required, proof.as_ref())
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Storage Commands (settings/debug screen only)
+// ============================================================================
+
+#[tauri::command]
+fn storage_overview(state: State<AppState>) -> Result<String, String> {
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+    let context = state.display_context()?;
+    let overview: Vec<_> = service
+        .overview()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|volume| volume.localized(&context))
+        .collect();
+    serde_json::to_string(&overview).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn storage_factory_reset_begin(state: State<AppState>) -> Result<String, String> {
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+    serde_json::to_string(&service.begin_factory_reset()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn storage_factory_reset(
+    state: State<AppState>,
+    challenge: ResetChallenge,
+) -> Result<String, String> {
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+
+    // Close every database handle before the files go away; the frontend
+    // restarts the app afterwards
+    state.clear_services();
+    let report = service.factory_reset(challenge).map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn storage_compact(state: State<AppState>, report_only: bool) -> Result<String, String> {
+    let user_id = state.current_user()?;
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+    let report = service
+        .compact(&[user_id], report_only)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn storage_last_compaction(state: State<AppState>) -> Result<String, String> {
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+    serde_json::to_string(&service.last_compaction()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn storage_adopt(state: State<AppState>, install_id: String) -> Result<String, String> {
+    let guard = state.storage_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Storage service not initialized")?;
+    let adopted = service.adopt(&install_id).map_err(|e| e.to_string())?;
+    serde_json::to_string(&adopted).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn retention_footprints(state: State<AppState>) -> Result<String, String> {
+    let guard = state.retention_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Retention service not initialized")?;
+    let footprints = service.footprints().map_err(|e| e.to_string())?;
+    serde_json::to_string(&footprints).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn retention_set_policy(
+    state: State<AppState>,
+    store: RetentionStore,
+    policy: Option<Policy>,
+) -> Result<(), String> {
+    let guard = state.retention_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Retention service not initialized")?;
+    service.set_policy(store, policy).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn retention_run(state: State<AppState>) -> Result<String, String> {
+    let guard = state.retention_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Retention service not initialized")?;
+    let run = service.run().map_err(|e| e.to_string())?;
+    serde_json::to_string(&run).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn retention_last_run(state: State<AppState>) -> Result<String, String> {
+    let guard = state.retention_service.lock().unwrap();
+    let service = guard.as_ref().ok_or("Retention service not initialized")?;
+    serde_json::to_string(&service.last_run()).map_err(|e| e.to_string())
+}
+// ============================================================================
+// Tauri Entry Point
+// ============================================================================
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // Without a command line, only the defaults and environment apply
+    let startup = StartupConfig::load(None, std::env::vars())
+        .expect("Invalid startup configuration");
+    run_with(startup, false)
+}
+
+/// Start the shell with a resolved startup configuration
+///
+/// `persist_env` stores environment overrides of system settings instead
+/// of applying them for this run only.
+pub fn run_with(startup: StartupConfig, persist_env: bool) {
+    if startup.log_level() >= LogLevel::Info {
+        eprint!("Effective configuration:\n{}", startup);
+    }
+    if startup.mode() == RunMode::Server {
+        eprintln!("Warning: run osnova-daemon for headless server mode; starting the shell");
+    }
+
+    let storage_path = startup
+        .storage_path()
+        .to_str()
+        .expect("Failed to convert path to string")
+        .to_string();
+
+    // Launcher manifest and server address are also system settings
+    let overrides = ConfigService::new(&storage_path)
+        .and_then(|config| startup.apply(&config, persist_env))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to apply startup configuration: {:#}", e);
+            SessionOverrides::default()
+        });
+
+    // Keep timestamps ordered across wall-clock corrections and restarts
+    let clock_path = std::path::Path::new(&storage_path).join("clock.hwm");
+    match HybridClock::new().with_persistence(&clock_path) {
+        Ok(clock) => time::set_default_clock(Arc::new(clock)),
+        Err(e) => eprintln!("Failed to load clock high-water mark: {}", e),
+    }
+
+    // The shell's own log, kept in rotating files and streamed to paired
+    // devices granted logs.read
+    let log_dir = std::path::Path::new(&storage_path).join(logs::LOG_DIR);
+    logs::install(Arc::new(
+        Logger::new()
+            .with_level(startup.log_level())
+            .with_files(LogFiles::new(log_dir))
+            .with_stderr(),
+    ));
+
+    let app_state = AppState::new(storage_path).with_startup(&startup, overrides);
+
+    let mut builder = tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init());
+
+    // Enable MCP plugin for AI-powered testing (debug builds only)
+    #[cfg(debug_assertions)]
+    {
+        use std::path::PathBuf;
+        builder = builder.plugin(tauri_plugin_mcp::init_with_config(
+            tauri_plugin_mcp::PluginConfig::new("app".to_string())
+                .start_socket_server(true)
+                // Use IPC socket (Unix domain socket on Linux/macOS)
+                .socket_path(PathBuf::from("/tmp/osnova-tauri-mcp.sock")),
+        ));
+    }
+
+    builder
+        .manage(app_state)
+        .setup(|app| {
+            let state = app.state::<AppState>();
+            let process_service = Arc::new(ProcessService::new(&state.storage_path)?);
+
+            // Terminate backends orphaned by a previous crash before spawning new ones
+            let report = process_service.cleanup_orphans()?;
+            for entry in &report.entries {
+                eprintln!(
+                    "Orphaned backend for {} (pid {}): {:?}",
+                    entry.app_id, entry.pid, entry.outcome
+                );
+            }
+
+            // Watch for backends that die unexpectedly and notify the frontend
+            let mut crashes = process_service.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("crash-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(crashes.recv()).await {
+                    let notifications = handle
+                        .state::<AppState>()
+                        .notification_service
+                        .lock()
+                        .unwrap()
+                        .clone();
+                    if let Some(notifications) = notifications {
+                        let _ = notifications.notify_crash(&event);
+                    }
+                    emit(&handle, event);
+                }
+            });
+
+            // Forward delivered notifications, asking for attention when the
+            // window is in the background (OS toasts need the notification
+            // plugin, which is not bundled)
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("notification-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    let AppEvent::NotificationPosted { notification, .. } = event else {
+                        continue;
+                    };
+                    emit(&handle, notification);
+                    if let Some(window) = handle.get_webview_window("main") {
+                        if !window.is_focused().unwrap_or(true) {
+                            let attention = tauri::UserAttentionType::Informational;
+                            let _ = window.request_user_attention(Some(attention));
+                        }
+                    }
+                }
+            });
+            // Forward permission prompts to the shell's modal, and their
+            // resolution so a timed-out modal closes
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("permission-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    match event {
+                        AppEvent::PermissionRequested { prompt, .. } => emit(&handle, prompt),
+                        AppEvent::PermissionResolved {
+                            prompt_id, granted, ..
+                        } => emit(&handle, PermissionResolved { prompt_id, granted }),
+                        _ => {}
+                    }
+                }
+            });
+            state.spawn_task("process-watchdog", |token| {
+                process_service.clone().run_watchdog(DEFAULT_WATCHDOG_INTERVAL, token)
+            });
+
+            // Backends report ready over the RPC socket, on loopback unless configured
+            let listener = tauri::async_runtime::block_on(rpc::server::bind(state.rpc_listen))?;
+            let handshake = Arc::new(
+                LaunchHandshake::new()
+                    .with_events(state.events.clone())
+                    .with_rpc_address(listener.local_addr()?),
+            );
+            let ready = handshake.clone();
+            let logger = logs::global().cloned().unwrap_or_default();
+            let logs_service = Arc::new(LogsService::new(&state.storage_path, logger)?);
+            let (tail, query) = (logs_service.clone(), logs_service.clone());
+            // Backends keep UI state through the signed-in user's UI service
+            let ui_service = |handle: &tauri::AppHandle| {
+                let state = handle.state::<AppState>();
+                let service = state.api.ui().lock().clone();
+                service.ok_or_else(|| anyhow::anyhow!("UI service not initialized"))
+            };
+            let handle = app.handle().clone();
+            let (save, load, clear) = (handle.clone(), handle.clone(), handle);
+            let server = RpcServer::new(rpc::core_registry())
+                .with_handler(COMPONENT_READY_METHOD, move |params| ready.handle_ready(params))?
+                .with_subscription("logs.tail", move |params| tail.handle_tail(params))?
+                .with_handler("logs.query", move |params| query.handle_query(params))?
+                .with_handler("ui.state.save", move |params| {
+                    ui_service(&save)?.handle_state_save(params)
+                })?
+                .with_handler("ui.state.load", move |params| {
+                    ui_service(&load)?.handle_state_load(params)
+                })?
+                .with_handler("ui.state.clear", move |params| {
+                    ui_service(&clear)?.handle_state_clear(params)
+                })?;
+            *state.logs_service.lock().unwrap() = Some(logs_service);
+            state.spawn_task("rpc-server", |token| async move {
+                let _ = token.run_until_cancelled(Arc::new(server).serve(listener)).await;
+            });
+            *state.launch_handshake.lock().unwrap() = Some(handshake);
+
+            // Forward backend readiness so frontends can wait on their backend
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("readiness-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    let AppEvent::BackendReadinessChanged {
+                        app_id,
+                        component_id,
+                        state,
+                    } = event
+                    else {
+                        continue;
+                    };
+                    let readiness = BackendReadinessChanged {
+                        app_id,
+                        component_id,
+                        state,
+                    };
+                    emit(&handle, readiness);
+                }
+            });
+
+            // Forward backend health transitions so frontends can show them
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("health-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::BackendHealthChanged { transition } = event {
+                        emit(&handle, transition);
+                    }
+                }
+            });
+
+            // Forward refreshed listings so the launcher re-renders
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("metadata-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::AppMetadataChanged { refresh } = event {
+                        emit(&handle, refresh);
+                    }
+                }
+            });
+
+            // Forward the unlock so deferred screens retry
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("unlock-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::ContextUnlocked { unlocked } = event {
+                        emit(&handle, unlocked);
+                    }
+                }
+            });
+
+            // Warn the user when a component starts using its keys unusually often
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("key-usage-notifier", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    let AppEvent::KeyUsageAnomaly { anomaly } = event else {
+                        continue;
+                    };
+                    let notifications = handle
+                        .state::<AppState>()
+                        .notification_service
+                        .lock()
+                        .unwrap()
+                        .clone();
+                    if let Some(notifications) = notifications {
+                        let _ = notifications.notify_key_usage_anomaly(&anomaly);
+                    }
+                }
+            });
+
+            // Forward task progress, and the legacy events it shims, to the
+            // activity center
+            let mut task_events = state.tasks.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("task-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(task_events.recv()).await {
+                    emit(&handle, event);
+                }
+            });
+
+            // Forward icon badge changes so the launcher updates in place
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("badge-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::AppBadgeChanged { change } = event {
+                        emit(&handle, change);
+                    }
+                }
+            });
+
+            // Forward feature flag changes so open apps can re-read them
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("feature-flag-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::FeatureFlagsChanged { change, .. } = event {
+                        emit(&handle, change);
+                    }
+                }
+            });
+
+            // Forward OS authentication prompts so the UI shows why the
+            // user is asked and what the backend wants them to do
+            let mut events = state.events.subscribe();
+            let handle = app.handle().clone();
+            state.spawn_task("auth-prompt-forwarder", |token| async move {
+                while let Some(Ok(event)) = token.run_until_cancelled(events.recv()).await {
+                    if let AppEvent::AuthPrompt { prompt } = event {
+                        emit(&handle, prompt);
+                    }
+                }
+            });
+
+            // Factory reset covers the platform directories plus the
+            // (possibly overridden) storage path
+            let roots = StorageRoots::from_platform()?.with_data_dir(&state.storage_path);
+            let storage_service =
+                StorageService::new(roots).with_processes(process_service.clone());
+
+            // Tag data written before data classes existed, and register
+            // secret paths for log redaction
+            if let Err(e) = storage_service.classify_known_paths() {
+                eprintln!("Warning: Failed to classify stored data: {}", e);
+            }
+            *state.storage_service.lock().unwrap() = Some(Arc::new(storage_service));
+
+            // Exit steps, run after background tasks have drained
+            let backends = process_service.clone();
+            state.context.on_flush("backends", &[], move || {
+                backends.stop_all()?;
+                Ok(())
+            });
+            let handle = app.handle().clone();
+            state.context.on_flush("lan-discovery", &[], move || {
+                let state = handle.state::<AppState>();
+                if let Some(discovery) = state.lan_discovery.lock().unwrap().as_ref() {
+                    discovery.withdraw()?;
+                }
+                Ok(())
+            });
+            // Whatever a guest wrote is discarded, never flushed
+            let handle = app.handle().clone();
+            state.context.on_flush("guest-session", &["backends"], move || {
+                let state = handle.state::<AppState>();
+                if let Some(guest) = state.guest_session.lock().unwrap().take() {
+                    guest.expiry.cancel();
+                }
+                Ok(())
+            });
+            let handle = app.handle().clone();
+            state.context.on_flush("services", &["backends", "lan-discovery"], move || {
+                handle.state::<AppState>().clear_services();
+                Ok(())
+            });
+
+            *state.process_service.lock().unwrap() = Some(process_service);
+            Ok(())
+        })
+        .on_page_load(|webview, payload| {
+            // Nothing is left to show the result of requests from the old page
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                webview.state::<AppState>().cancel_network_requests();
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             identity_check,
+            context_unlock_state,
+            context_unlock,
             identity_create,
             identity_import,
+            identity_status,
+            identity_recover,
             identity_get,
+            identity_reveal_seed,
+            address_validate,
+            keys_derive_batch,
+            keys_usage_report,
+            audit_list,
+            audit_verify,
+            wallet_history,
+            wallet_export_history,
+            wallet_approve_payment,
             apps_list,
+            apps_info,
+            apps_list_uri_handlers,
+            apps_set_uri_handler,
+            apps_open_uri,
+            provenance_for_component,
+            notifications_post,
+            notifications_list,
+            notifications_unread_counts,
+            apps_badges,
+            notifications_mark_read,
+            notifications_clear,
+            notifications_set_enabled,
+            permissions_request,
+            permissions_respond,
+            permissions_pending,
+            permissions_list,
+            permissions_revoke,
+            shared_request_access,
+            shared_list,
+            shared_read,
+            shared_revoke,
+            shared_grants,
+            sessions_list,
+            sessions_revoke,
             apps_launch,
+            apps_close,
+            apps_consent_review,
+            apps_record_consent,
+            apps_launch_descriptor,
+            apps_frontend_entry,
+            apps_crash_reports,
+            apps_health_history,
+            apps_verify_reproducible,
+            apps_privacy_report,
+            apps_symbolicate,
+            cache_collect_garbage,
+            logs_query,
+            apps_pin,
+            apps_unpin,
+            apps_refresh_metadata,
+            apps_refresh_all,
+            apps_materialize_all,
+            apps_preview_install_many,
+            apps_install_many,
+            apps_restore,
+            apps_list_trash,
+            apps_verify,
+            apps_repair,
             launcher_get_layout,
+            launcher_generation,
+            launcher_changes_since,
             launcher_set_layout,
+            launcher_set_folder,
+            launcher_set_pinned,
+            launcher_mutate,
+            launcher_get_since,
+            launcher_merge_layout,
+            launcher_get_catalog,
+            features_all,
+            features_set_override,
             ui_get_theme,
             ui_set_theme,
+            ui_get_locale,
+            ui_set_locale,
+            ui_save_app_state,
+            ui_load_app_state,
+            ui_clear_app_state,
+            ui_app_state_usage,
             navigation_get_bottom_menu,
             navigation_set_bottom_menu,
             status_get_server,
+            status_get_guest_session,
+            guest_start,
+            guest_end,
+            status_get_network,
+            status_get_retry_budgets,
+            network_set_offline_mode,
+            network_run_diagnostics,
+            status_get_disk_health,
+            config_export_preset,
+            config_import_preset,
+            config_get_settings,
+            config_set_settings,
+            config_set_secret,
+            config_clear_secret,
+            config_list_snapshots,
+            config_restore_snapshot,
+            config_snapshot_usage,
+            config_mutate_settings,
+            config_get_since,
+            config_cache_metrics,
+            discovery_find,
+            discovery_get_announcements,
+            discovery_set_announcements,
+            diagnostics_chaos_status,
+            diagnostics_set_chaos_profile,
+            diagnostics_queries,
+            diagnostics_begin_query,
+            diagnostics_run_query,
+            diagnostics_get_launch_consent,
+            diagnostics_set_launch_consent,
+            diagnostics_get_signature_policy_required,
+            diagnostics_set_signature_policy_required,
+            diagnostics_crash_reports,
+            diagnostics_payment_summary,
+            diagnostics_launch_predictions,
+            diagnostics_clear_app_usage,
+            diagnostics_set_payment_addresses,
+            bandwidth_usage,
+            bandwidth_get_policy,
+            bandwidth_set_policy,
+            fetch_get_policy,
+            fetch_set_policy,
+            runtime_get_settings,
+            runtime_set_settings,
+            tasks_list,
+            tasks_history,
+            tasks_cancel,
+            updates_check,
+            updates_apply,
+            updates_rollback,
+            updates_get_policy,
+            updates_set_policy,
+            search_query,
+            search_reindex,
+            annotations_set,
+            annotations_get,
+            annotations_delete,
+            annotations_search,
+            annotations_list,
+            annotations_set_network_backup,
+            policies_apply,
+            policies_remove,
+            policies_summary,
+            security_audit,
+            auth_status,
+            auth_authenticate,
+            auth_set_required,
+            storage_overview,
+            storage_factory_reset_begin,
+            storage_factory_reset,
+            storage_adopt,
+            storage_compact,
+            storage_last_compaction,
+            retention_footprints,
+            retention_set_policy,
+            retention_run,
+            retention_last_run,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Drain background work, then stop backends so they are not
+            // treated as orphans, then close the services
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<AppState>();
+                let shutdown = state.context.shutdown(DEFAULT_SHUTDOWN_TIMEOUT);
+                let report = tauri::async_runtime::block_on(shutdown);
+                for task in &report.abandoned {
+                    eprintln!("Background task {} did not stop in time", task);
+                }
+                for failure in &report.flush_failures {
+                    eprintln!("Shutdown step {} failed: {}", failure.hook, failure.error);
+                }
+            }
+        });
 }
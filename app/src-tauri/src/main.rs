@@ -1,6 +1,65 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use osnova_lib::context::startup::StartupConfig;
+
 fn main() {
-    app_lib::run()
+    // `--write-openrpc <path>` writes the OpenRPC document for tooling and exits
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(flag) = args.iter().position(|arg| arg == "--write-openrpc") {
+        let Some(path) = args.get(flag + 1) else {
+            eprintln!("Usage: --write-openrpc <path>");
+            std::process::exit(2);
+        };
+        if let Err(e) = osnova_lib::rpc::write_openrpc_document(path) {
+            eprintln!("Failed to write OpenRPC document: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `--write-event-types <path>` writes the TypeScript event payloads and exits
+    if let Some(flag) = args.iter().position(|arg| arg == "--write-event-types") {
+        let Some(path) = args.get(flag + 1) else {
+            eprintln!("Usage: --write-event-types <path>");
+            std::process::exit(2);
+        };
+        if let Err(e) = osnova_lib::events::write_typescript_definitions(path) {
+            eprintln!("Failed to write event types: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `--config <path>` layers a TOML file between the defaults and the
+    // OSNOVA_* environment variables
+    let config_path = match args.iter().position(|arg| arg == "--config") {
+        Some(flag) => match args.get(flag + 1) {
+            Some(path) => Some(std::path::PathBuf::from(path)),
+            None => {
+                eprintln!("Usage: --config <path>");
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let startup = match StartupConfig::load(config_path.as_deref(), std::env::vars()) {
+        Ok(startup) => startup,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:#}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // `--print-config` prints the effective configuration, secrets redacted,
+    // and exits
+    if args.iter().any(|arg| arg == "--print-config") {
+        print!("{}", startup);
+        return;
+    }
+
+    // `--persist-env` stores environment overrides of system settings
+    // instead of applying them for this run only
+    let persist_env = args.iter().any(|arg| arg == "--persist-env");
+    app_lib::run_with(startup, persist_env)
 }
@@ -11,6 +11,8 @@ export interface AppListItem {
 
 interface AppsState {
   apps: AppListItem[];
+  /** Launcher generation the list is current for */
+  generation: number;
   loading: boolean;
   error: string | null;
 }
@@ -18,6 +20,7 @@ interface AppsState {
 function createAppsStore() {
   const { subscribe, set, update } = writable<AppsState>({
     apps: [],
+    generation: 0,
     loading: false,
     error: null
   });
@@ -33,12 +36,16 @@ function createAppsStore() {
 
       try {
         const appsJson = (await invoke('apps_list')) as string;
-        const apps = JSON.parse(appsJson) as AppListItem[];
-        set({ apps, loading: false, error: null });
+        const { apps, generation } = JSON.parse(appsJson) as {
+          apps: AppListItem[];
+          generation: number;
+        };
+        set({ apps, generation, loading: false, error: null });
       } catch (error) {
         console.error('Failed to load apps:', error);
         set({
           apps: [],
+          generation: 0,
           loading: false,
           error: error instanceof Error ? error.message : 'Failed to load apps'
         });
@@ -57,6 +64,16 @@ function createAppsStore() {
       }
     },
 
+    /**
+     * Re-resolve every installed app's name, icon and description
+     *
+     * Progress arrives as `apps-refresh-progress` events.
+     */
+    async refreshMetadata() {
+      await invoke('apps_refresh_all');
+      await this.loadApps();
+    },
+
     /**
      * Clear error state
      */
@@ -0,0 +1,34 @@
+import { writable } from 'svelte/store';
+import { invoke, listen } from '$lib/utils/tauri';
+import type { BadgeState } from '$lib/types/events';
+
+function createBadgesStore() {
+  const { subscribe, set, update } = writable<Record<string, BadgeState>>({});
+
+  return {
+    subscribe,
+
+    /**
+     * Load the badge of every installed app, for the first render
+     */
+    async loadBadges() {
+      try {
+        const badgesJson = (await invoke('apps_badges')) as string;
+        set(JSON.parse(badgesJson) as Record<string, BadgeState>);
+      } catch (error) {
+        console.error('Failed to load badges:', error);
+      }
+    },
+
+    /**
+     * Apply `badge-changed` events; resolves to a function that stops
+     */
+    follow() {
+      return listen('badge-changed', ({ appId, badge }) =>
+        update((badges) => ({ ...badges, [appId]: badge }))
+      );
+    }
+  };
+}
+
+export const badgesStore = createBadgesStore();
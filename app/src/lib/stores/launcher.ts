@@ -1,8 +1,22 @@
 import { writable } from 'svelte/store';
 import { invoke } from '$lib/utils/tauri';
 
+/** What changed in the launcher grid since a generation */
+export interface ChangeSummary {
+  since: number;
+  generation: number;
+  added: string[];
+  removed: string[];
+  updated: string[];
+  layoutChanged: boolean;
+  /** The history no longer reaches `since`; reload everything */
+  fullReload: boolean;
+}
+
 interface LauncherState {
   layout: string[];
+  /** Launcher generation the layout is current for */
+  generation: number;
   loading: boolean;
   error: string | null;
 }
@@ -10,6 +24,7 @@ interface LauncherState {
 function createLauncherStore() {
   const { subscribe, set, update } = writable<LauncherState>({
     layout: [],
+    generation: 0,
     loading: false,
     error: null
   });
@@ -25,18 +40,30 @@ function createLauncherStore() {
 
       try {
         const layoutJson = (await invoke('launcher_get_layout')) as string;
-        const layout = JSON.parse(layoutJson) as string[];
-        set({ layout, loading: false, error: null });
+        const { appIds: layout, generation } = JSON.parse(layoutJson) as {
+          appIds: string[];
+          generation: number;
+        };
+        set({ layout, generation, loading: false, error: null });
       } catch (error) {
         console.error('Failed to load layout:', error);
         set({
           layout: [],
+          generation: 0,
           loading: false,
           error: error instanceof Error ? error.message : 'Failed to load layout'
         });
       }
     },
 
+    /**
+     * What changed in the grid since a generation
+     */
+    async changesSince(generation: number): Promise<ChangeSummary> {
+      const changesJson = (await invoke('launcher_changes_since', { generation })) as string;
+      return JSON.parse(changesJson) as ChangeSummary;
+    },
+
     /**
      * Save launcher layout to backend
      */
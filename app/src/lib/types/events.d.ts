@@ -0,0 +1,426 @@
+// Generated from osnova_lib::events; do not edit by hand.
+// Regenerate from app/src-tauri with:
+//   cargo run -- --write-event-types ../src/lib/types/events.d.ts
+
+/** Emitted when a registered backend process dies unexpectedly */
+export interface AppCrashed {
+  /** Application whose backend died */
+  appId: string;
+  /** Exit code, if the process was our child and exited normally */
+  exitCode?: number | null;
+  /** Process id of the dead backend */
+  pid: number;
+  /** Crash report recorded for the exit */
+  reportId: string;
+  /** Signal that killed the process, if it was our child */
+  signal?: number | null;
+}
+
+/** Why an app icon asks for attention */
+export type AttentionReason =
+  /** The app's backend crashed on its last run */
+  | "crashed"
+  /** The app is syncing its data */
+  | "syncing";
+
+/** Mechanism an authenticator uses */
+export type AuthBackend =
+  /** Fingerprint reader through fprintd (Linux) */
+  | "fprintd"
+  /** Polkit authentication agent (Linux) */
+  | "polkit"
+  /** No OS authentication on this device */
+  | "unavailable";
+
+/**
+ * What the OS authentication in progress asks of the user
+ * 
+ * Published once with no message when the prompt opens, then once for every instruction the backend gives.
+ */
+export interface AuthPrompt {
+  /** Backend asking */
+  backend: AuthBackend;
+  /** Instruction from the backend, such as which finger to scan */
+  message?: string | null;
+  /** Operation being authenticated */
+  operation: SensitiveOperation;
+  /** Why the user is asked */
+  reason: string;
+}
+
+/** A launched backend's readiness changed */
+export interface BackendReadinessChanged {
+  /** Application identifier */
+  appId: string;
+  /** Backend component identifier */
+  componentId: string;
+  /** New readiness */
+  state: ReadinessState;
+}
+
+/** An app's badge changed */
+export interface BadgeChanged {
+  /** Application identifier */
+  appId: string;
+  /** New badge; clear once the app is uninstalled */
+  badge: BadgeState;
+}
+
+/** State shown on an app icon */
+export interface BadgeState {
+  /** Why the app asks for attention; a crash outranks a sync */
+  attention?: AttentionReason | null;
+  /** Unread notifications */
+  unread: number;
+  /** Whether a newer version can be installed */
+  updateAvailable: boolean;
+}
+
+/** What happened to a request of a batch install */
+export type BatchInstallOutcome =
+  /** The app was installed */
+  | { status: "installed"; warnings: string[] }
+  /** The app was installed already and left as it was */
+  | { status: "skippedAlreadyInstalled" }
+  /** The app could not be installed */
+  | { reason: string; status: "failed" }
+  /** The batch was cancelled before the install started */
+  | { status: "cancelled" };
+
+/** A request of a batch install finished */
+export interface BatchInstallProgress {
+  /** Requests finished so far */
+  completed: number;
+  /** Manifest URI requested */
+  manifestUri: string;
+  /** What happened */
+  outcome: BatchInstallOutcome;
+  /** Requests in the batch */
+  total: number;
+}
+
+/** A capability that must be granted by the user at runtime */
+export type Capability =
+  /** Read the system clipboard */
+  | "clipboard.read"
+  /** Initiate a payment above the wallet's confirmation threshold */
+  | "payments.send"
+  /** Read data another app shares */
+  | "apps.readSharedData";
+
+/** The context was unlocked */
+export interface ContextUnlocked {
+  /** Unix timestamp of the unlock */
+  unlockedAt: number;
+}
+
+/** An app's effective flag values changed */
+export interface FeatureFlagsChanged {
+  /** Application identifier */
+  appId: string;
+  /** New values of the flags that changed */
+  flags: FlagValue[];
+}
+
+/** One layer of a flag's evaluation */
+export interface FlagLayer {
+  /** Why the layer does or does not set a value */
+  detail: string;
+  /** Layer */
+  source: FlagSource;
+  /** Value the layer sets, if it sets one */
+  value?: boolean | null;
+}
+
+/** Layer a flag value comes from */
+export type FlagSource =
+  /** Declared in the app's manifest */
+  | "default"
+  /** Set by the publisher's flag document */
+  | "remote"
+  /** Overridden by the user */
+  | "local";
+
+/** Effective value of a flag */
+export interface FlagValue {
+  /** Layers in evaluation order: default, remote, local */
+  evaluation: FlagLayer[];
+  /** Flag name */
+  name: string;
+  /** Layer the value comes from */
+  source: FlagSource;
+  /** Effective value */
+  value: boolean;
+}
+
+/** Health of a backend */
+export type HealthStatus =
+  /** Not checked since it was started */
+  | "unknown"
+  /** Passed its last check */
+  | "healthy"
+  /** Failed its threshold of checks in a row */
+  | "unhealthy"
+  /** Stopped for good after too many restarts */
+  | "blocked";
+
+/** A backend became unhealthy, recovered, was restarted or blocked */
+export interface HealthTransition {
+  /** Application identifier */
+  appId: string;
+  /** Unix timestamp of the transition */
+  at: number;
+  /** Component identifier */
+  componentId: string;
+  /** Health before */
+  from: HealthStatus;
+  /** What caused the transition */
+  reason: string;
+  /** Restarts since the app was launched */
+  restarts: number;
+  /** Health after */
+  to: HealthStatus;
+}
+
+/** A missing component finished downloading, or failed to */
+export interface MaterializeProgress {
+  /** Application the component belongs to */
+  appId: string;
+  /** Components finished so far */
+  completed: number;
+  /** Component identifier */
+  componentId: string;
+  /** Why the download failed */
+  error?: string | null;
+  /** Missing components to download */
+  total: number;
+}
+
+/** A listing field that can change between manifest resolutions */
+export type MetadataField =
+  /** Application name */
+  | "name"
+  /** Icon URI */
+  | "iconUri"
+  /** Description */
+  | "description";
+
+/** Outcome of refreshing one app's listing */
+export interface MetadataRefresh {
+  /** Application identifier */
+  appId: string;
+  /** Fields that changed; empty when the listing was already current */
+  changed: MetadataField[];
+  /** Launcher generation of the change; absent when nothing changed */
+  generation?: number | null;
+  /** When the listing was refreshed (Unix seconds) */
+  refreshedAt: number;
+}
+
+/** Side of a migration */
+export type MigrationDirection =
+  /** The old server, sending its data */
+  | "export"
+  /** The new server, receiving it */
+  | "import";
+
+/** Progress of a migration, on either server */
+export interface MigrationProgress {
+  /** Plaintext bytes transferred so far */
+  bytesDone: number;
+  /** Chunks transferred so far */
+  chunksDone: number;
+  /** Chunks in the export */
+  chunksTotal: number;
+  /** Which server this is */
+  direction: MigrationDirection;
+  /** Export identifier */
+  exportId: string;
+  /** Current step */
+  stage: MigrationStage;
+}
+
+/** Step a migration is at */
+export type MigrationStage =
+  /** Chunks are being transferred */
+  | "streaming"
+  /** The staged data is being checked */
+  | "validating"
+  /** The staged data is replacing the storage root */
+  | "swapping"
+  /** The new server confirmed the import */
+  | "completed";
+
+/** How prominently a notification is shown */
+export type NotificationLevel =
+  /** Informational (default) */
+  | "info"
+  /** Something completed successfully */
+  | "success"
+  /** Needs the user's attention */
+  | "warning"
+  /** Something failed */
+  | "error";
+
+/** A permission request waiting for the user's answer */
+export interface PermissionPrompt {
+  /** App whose component asked */
+  appId: string;
+  /** Capability requested */
+  capability: Capability;
+  /** Component that asked */
+  componentId: string;
+  /** Unix timestamp after which the prompt counts as denied */
+  expiresAt: number;
+  /** Prompt identifier, passed back with the answer */
+  id: string;
+  /** Why the component needs it, shown to the user */
+  rationale: string;
+  /** Unix timestamp when the prompt was raised */
+  requestedAt: number;
+  /** What the capability is for when it is granted per target (e.g. one data offer); such decisions are kept by the service that asked */
+  scope?: string | null;
+}
+
+/** A permission prompt was answered or timed out, so its modal can close */
+export interface PermissionResolved {
+  /** Whether the capability was granted */
+  granted: boolean;
+  /** Prompt that was resolved */
+  promptId: string;
+}
+
+/** Readiness of a launched backend */
+export type ReadinessState =
+  /** Launched, not reported yet */
+  | { state: "starting" }
+  /** Reported ready with the expected version */
+  | { state: "ready" }
+  /** Did not start or did not report in time */
+  | { reason: string; state: "failed" }
+  /** Reported ready with a different version than the manifest pins */
+  | { actual: string; expected: string; state: "versionMismatch" };
+
+/** Progress of a manual refresh of every installed app */
+export interface RefreshProgress {
+  /** Application that just finished */
+  appId: string;
+  /** Fields of this app that changed */
+  changed: MetadataField[];
+  /** Apps finished so far, including this one */
+  completed: number;
+  /** Why refreshing this app failed */
+  error?: string | null;
+  /** Apps being refreshed */
+  total: number;
+}
+
+/** An operation that can require OS re-authentication */
+export type SensitiveOperation =
+  /** Showing the seed phrase */
+  | "seedReveal"
+  /** Allowing a payment prompt */
+  | "paymentApproval"
+  /** Allowing any other permission prompt */
+  | "permissionGrant"
+  /** Deleting the identity */
+  | "identityDeletion";
+
+/** A notification in a user's notification center */
+export interface StoredNotification {
+  /** Route inside the app to open when the notification is clicked */
+  actionRoute?: string | null;
+  /** App that posted the notification */
+  appId: string;
+  /** Body text */
+  body?: string;
+  /** Notification identifier */
+  id: number;
+  /** Severity */
+  level?: NotificationLevel;
+  /** Unix timestamp when the notification was posted */
+  postedAt: number;
+  /** Whether the user has read the notification */
+  read: boolean;
+  /** Deduplication key; a second post with the same tag is dropped */
+  tag?: string | null;
+  /** Short title */
+  title: string;
+}
+
+/** What kind of operation a task is */
+export type TaskCategory =
+  /** A component download */
+  | "download"
+  /** An upload of a backup or archive to the network */
+  | "backup"
+  /** A server-to-server migration */
+  | "migration"
+  /** Downloading the missing components of installed apps */
+  | "materialization"
+  /** A refresh of app metadata or the catalog */
+  | "catalogRefresh"
+  /** Collection of cached files no app needs */
+  | "cacheGc"
+  /** Warming the cache for apps likely to be launched soon */
+  | "cacheWarming";
+
+/** A long-running operation, as shown in the activity center */
+export interface TaskInfo {
+  /** Whether the task can be cancelled */
+  cancellable: boolean;
+  /** Kind of operation */
+  category: TaskCategory;
+  /** Units of work done (bytes, files, batches, ...) */
+  completed: number;
+  /** Why the task failed */
+  error?: string | null;
+  /** Unix timestamp when the task ended */
+  finishedAt?: number | null;
+  /** Task identifier */
+  id: string;
+  /** Latest status message */
+  message?: string | null;
+  /** What the task does, for display */
+  name: string;
+  /** Unix timestamp when the task started */
+  startedAt: number;
+  /** Where the task stands */
+  state: TaskState;
+  /** Units of work in total, if known */
+  total?: number | null;
+}
+
+/** Where a task stands */
+export type TaskState =
+  /** Still running */
+  | "running"
+  /** Finished successfully */
+  | "completed"
+  /** Finished with an error */
+  | "failed"
+  /** Stopped by its cancellation token */
+  | "cancelled";
+
+/** Payload of every shell event, by event name */
+export interface OsnovaEvents {
+  "app-crashed": AppCrashed;
+  "notification-posted": StoredNotification;
+  "permission-prompt": PermissionPrompt;
+  "permission-resolved": PermissionResolved;
+  "backend-readiness": BackendReadinessChanged;
+  "apps-metadata-changed": MetadataRefresh;
+  "apps-refresh-progress": RefreshProgress;
+  "migration-progress": MigrationProgress;
+  "badge-changed": BadgeChanged;
+  "context-unlocked": ContextUnlocked;
+  "apps-materialize-progress": MaterializeProgress;
+  "apps-install-progress": BatchInstallProgress;
+  "task-updated": TaskInfo;
+  "backend-health": HealthTransition;
+  "feature-flags-changed": FeatureFlagsChanged;
+  "auth-prompt": AuthPrompt;
+}
+
+/** Name of a shell event */
+export type OsnovaEventName = keyof OsnovaEvents;
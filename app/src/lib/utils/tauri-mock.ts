@@ -67,7 +67,21 @@ export async function mockInvoke(command: string, args?: Record<string, unknown>
 
     // Launcher commands
     case 'launcher_get_layout':
-      return JSON.stringify(mockStorage.layout);
+      return JSON.stringify({ appIds: mockStorage.layout, generation: 0 });
+
+    case 'launcher_generation':
+      return 0;
+
+    case 'launcher_changes_since':
+      return JSON.stringify({
+        since: args?.generation ?? 0,
+        generation: 0,
+        added: [],
+        removed: [],
+        updated: [],
+        layoutChanged: false,
+        fullReload: false
+      });
 
     case 'launcher_save_layout':
       if (args?.layout && Array.isArray(args.layout)) {
@@ -77,7 +91,10 @@ export async function mockInvoke(command: string, args?: Record<string, unknown>
 
     // Apps commands
     case 'apps_list':
-      return JSON.stringify(mockStorage.apps);
+      return JSON.stringify({ apps: mockStorage.apps, generation: 0 });
+
+    case 'apps_badges':
+      return JSON.stringify({});
 
     case 'apps_launch':
       console.log('[MOCK] Launching app:', args?.app_id);
@@ -104,6 +121,13 @@ export async function mockInvoke(command: string, args?: Record<string, unknown>
     case 'identity_get':
       return mockStorage.identityId;
 
+    case 'identity_status':
+      return {
+        initialized: mockStorage.hasIdentity,
+        address: mockStorage.identityId,
+        state: mockStorage.hasIdentity ? 'initialized' : 'uninitialized'
+      };
+
     // Status commands
     case 'status_server_info':
       return null; // Not running in server mode
@@ -7,6 +7,7 @@
  */
 
 import { mockInvoke, installTauriMock } from './tauri-mock';
+import type { OsnovaEventName, OsnovaEvents } from '$lib/types/events';
 
 // Install mock immediately if not in Tauri
 if (typeof window !== 'undefined' && !('__TAURI__' in window)) {
@@ -42,3 +43,22 @@ export async function invoke<T>(command: string, args?: Record<string, unknown>)
   const { invoke: tauriInvoke } = await import('@tauri-apps/api/core');
   return tauriInvoke<T>(command, args);
 }
+
+/**
+ * Listen for a shell event
+ *
+ * Event names and payloads are generated from osnova_lib::events, so an
+ * unknown name or a renamed payload field fails type checking. Resolves to
+ * a function that stops listening; no events arrive in the browser mock.
+ */
+export async function listen<K extends OsnovaEventName>(
+  event: K,
+  handler: (payload: OsnovaEvents[K]) => void
+): Promise<() => void> {
+  if (!isTauri()) {
+    return () => {};
+  }
+
+  const { listen: tauriListen } = await import('@tauri-apps/api/event');
+  return tauriListen<OsnovaEvents[K]>(event, (e) => handler(e.payload));
+}
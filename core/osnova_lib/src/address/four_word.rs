@@ -0,0 +1,289 @@
+//! 4-word address encoding
+//!
+//! Each word encodes 11 bits as an index into the BIP-39 English wordlist
+//! (2048 words), so four words carry the leading 44 bits of the identity
+//! fingerprint.
+//!
+//! The checksummed scheme appends a fifth, check word to those four:
+//!
+//! ```text
+//! check = w0 ^ a*w1 ^ a^2*w2 ^ a^3*w3    (arithmetic in GF(2^11), a = x)
+//! ```
+//!
+//! Because each data word is multiplied by a distinct non-zero field element,
+//! changing any single word always changes the check, and swapping two
+//! different adjacent data words does too. A random string of five valid
+//! words passes with probability 1/2048.
+//!
+//! The legacy scheme is the four fingerprint words alone. They are the
+//! checksummed address's first four words, so a legacy address maps to its
+//! checksummed form without the identity.
+
+use bip39::Language;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OsnovaError, Result};
+use crate::models::identity::RootIdentity;
+
+/// Number of words in a checksummed address
+pub const ADDRESS_WORD_COUNT: usize = DATA_WORD_COUNT + 1;
+
+/// Number of fingerprint words, the whole of a legacy address
+const DATA_WORD_COUNT: usize = 4;
+
+/// Bits encoded by one word
+const WORD_BITS: usize = 11;
+
+/// Mask for one word's bits
+const WORD_MASK: u64 = (1 << WORD_BITS) - 1;
+
+/// Reduction polynomial x^11 + x^2 + 1 for GF(2^11)
+const FIELD_POLY: u16 = 0x805;
+
+/// How an address was derived from the fingerprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AddressScheme {
+    /// Four fingerprint words with no checksum
+    Legacy,
+    /// Four fingerprint words and a check word
+    Checksummed,
+}
+
+/// Word indices of the leading 44 fingerprint bits
+fn fingerprint_words(fingerprint: &[u8; 32]) -> [u16; DATA_WORD_COUNT] {
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&fingerprint[..8]);
+    let bits = u64::from_be_bytes(prefix);
+
+    std::array::from_fn(|i| ((bits >> (64 - WORD_BITS * (i + 1))) & WORD_MASK) as u16)
+}
+
+/// Multiply a field element by `x`
+fn times_x(value: u16) -> u16 {
+    let shifted = value << 1;
+    if shifted & (1 << WORD_BITS) != 0 {
+        shifted ^ FIELD_POLY
+    } else {
+        shifted
+    }
+}
+
+/// Check word for the data words
+fn check_word(data: [u16; DATA_WORD_COUNT]) -> u16 {
+    data.iter()
+        .rev()
+        .fold(0, |check, &word| times_x(check) ^ word)
+}
+
+/// Data words followed by their check word
+fn checksummed(data: [u16; DATA_WORD_COUNT]) -> [u16; ADDRESS_WORD_COUNT] {
+    let mut words = [0; ADDRESS_WORD_COUNT];
+    words[..DATA_WORD_COUNT].copy_from_slice(&data);
+    words[DATA_WORD_COUNT] = check_word(data);
+    words
+}
+
+/// Render word indices as a space-separated address
+fn encode(words: &[u16]) -> String {
+    let list = Language::English.word_list();
+    words
+        .iter()
+        .map(|&w| list[usize::from(w)])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse an address of `N` words into word indices
+///
+/// Case-insensitive; words may be separated by whitespace or hyphens.
+fn parse<const N: usize>(address: &str) -> Option<[u16; N]> {
+    let words = address
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|w| !w.is_empty())
+        .map(|w| Language::English.find_word(&w.to_lowercase()))
+        .collect::<Option<Vec<_>>>()?;
+
+    words.try_into().ok()
+}
+
+/// Derive the checksummed address of a fingerprint
+pub fn derive_address(fingerprint: &[u8; 32]) -> String {
+    encode(&checksummed(fingerprint_words(fingerprint)))
+}
+
+/// Derive the legacy (unchecksummed) address of a fingerprint
+pub fn derive_legacy_address(fingerprint: &[u8; 32]) -> String {
+    encode(&fingerprint_words(fingerprint))
+}
+
+/// Derive the address of a fingerprint under `scheme`
+pub fn address_for_scheme(fingerprint: &[u8; 32], scheme: AddressScheme) -> String {
+    match scheme {
+        AddressScheme::Legacy => derive_legacy_address(fingerprint),
+        AddressScheme::Checksummed => derive_address(fingerprint),
+    }
+}
+
+/// Whether `address` is a well-formed checksummed address
+///
+/// Legacy addresses carry no check word and are rejected; use
+/// [`address_matches_identity`] to check one.
+pub fn validate_address(address: &str) -> bool {
+    parse::<ADDRESS_WORD_COUNT>(address)
+        .is_some_and(|[w0, w1, w2, w3, check]| check_word([w0, w1, w2, w3]) == check)
+}
+
+/// Whether `address` belongs to `identity` under either scheme
+pub fn address_matches_identity(address: &str, identity: &RootIdentity) -> bool {
+    let data = fingerprint_words(&identity.fingerprint());
+    parse::<DATA_WORD_COUNT>(address) == Some(data)
+        || parse::<ADDRESS_WORD_COUNT>(address) == Some(checksummed(data))
+}
+
+/// Map a legacy address to the checksummed address of the same identity
+///
+/// # Errors
+///
+/// Returns an error if `legacy` is not four wordlist words.
+pub fn upgrade_legacy_address(legacy: &str) -> Result<String> {
+    let data = parse(legacy)
+        .ok_or_else(|| OsnovaError::Identity(format!("Invalid address: {}", legacy)))?;
+    Ok(encode(&checksummed(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bip39::rand::{thread_rng, Rng};
+
+    const TEST_SEED: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn test_identity() -> RootIdentity {
+        RootIdentity::from_seed(TEST_SEED).unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_vectors() {
+        assert_eq!(
+            derive_address(&[0u8; 32]),
+            "abandon abandon abandon abandon abandon"
+        );
+        assert_eq!(derive_address(&[0xff; 32]), "zoo zoo zoo zoo action");
+        assert_eq!(
+            derive_address(&test_identity().fingerprint()),
+            "battle believe alter obey old"
+        );
+    }
+
+    #[test]
+    fn test_derived_addresses_validate() {
+        for _ in 0..100 {
+            let identity = RootIdentity::generate().unwrap();
+            let address = derive_address(&identity.fingerprint());
+
+            assert_eq!(address.split_whitespace().count(), ADDRESS_WORD_COUNT);
+            assert!(validate_address(&address));
+            assert!(address_matches_identity(&address, &identity));
+        }
+    }
+
+    #[test]
+    fn test_single_word_substitution_is_detected() {
+        let address = derive_address(&test_identity().fingerprint());
+        let words: Vec<&str> = address.split(' ').collect();
+
+        for position in 0..ADDRESS_WORD_COUNT {
+            for replacement in Language::English.word_list() {
+                if *replacement == words[position] {
+                    continue;
+                }
+                let mut typo = words.clone();
+                typo[position] = replacement;
+                assert!(!validate_address(&typo.join(" ")), "accepted {:?}", typo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adjacent_swap_is_detected() {
+        let words: [u16; ADDRESS_WORD_COUNT] =
+            parse(&derive_address(&test_identity().fingerprint())).unwrap();
+
+        for i in 0..DATA_WORD_COUNT - 1 {
+            if words[i] != words[i + 1] {
+                let mut swapped = words;
+                swapped.swap(i, i + 1);
+                assert!(!validate_address(&encode(&swapped)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_addresses_are_rejected() {
+        let mut rng = thread_rng();
+        let trials = 20_000;
+
+        let accepted = (0..trials)
+            .filter(|_| {
+                validate_address(&encode(
+                    &[(); ADDRESS_WORD_COUNT].map(|_| rng.gen_range(0..2048)),
+                ))
+            })
+            .count();
+
+        // Expected about trials / 2048 ≈ 10
+        assert!(
+            accepted < 40,
+            "{} of {} random addresses accepted",
+            accepted,
+            trials
+        );
+    }
+
+    #[test]
+    fn test_legacy_fixture() {
+        let identity = test_identity();
+        let legacy = derive_legacy_address(&identity.fingerprint());
+
+        // Address shown to users created before the checksummed scheme
+        assert_eq!(legacy, "battle believe alter obey");
+        let mnemonic = bip39::Mnemonic::from_entropy(&identity.fingerprint()[..16]).unwrap();
+        assert_eq!(
+            legacy,
+            mnemonic.words().take(4).collect::<Vec<_>>().join(" ")
+        );
+        assert_eq!(
+            address_for_scheme(&identity.fingerprint(), AddressScheme::Legacy),
+            legacy
+        );
+        assert!(address_matches_identity(&legacy, &identity));
+
+        let upgraded = upgrade_legacy_address(&legacy).unwrap();
+        assert_eq!(upgraded, derive_address(&identity.fingerprint()));
+        assert!(upgrade_legacy_address("not an address").is_err());
+    }
+
+    #[test]
+    fn test_parse_is_lenient_about_formatting() {
+        let identity = test_identity();
+        let address = derive_address(&identity.fingerprint());
+
+        let shouted = address.to_uppercase().replace(' ', "-");
+        assert!(validate_address(&shouted));
+        assert!(address_matches_identity(
+            &format!("  {}  ", shouted),
+            &identity
+        ));
+
+        let four_words: Vec<&str> = address.split(' ').take(4).collect();
+        assert!(!validate_address(&four_words.join(" ")));
+        assert!(!validate_address(&format!("{} abandon", address)));
+        assert!(!validate_address("abandon abandon abandon notaword"));
+        assert!(!address_matches_identity(
+            &derive_address(&RootIdentity::generate().unwrap().fingerprint()),
+            &identity
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+//! # Address Module
+//!
+//! Human-readable 4-word addresses derived from an identity fingerprint.
+//!
+//! This module provides:
+//! - The checksummed scheme: four words of fingerprint plus a check word,
+//!   so any single mistyped or substituted word is detected
+//! - Validation of an address on its own, and matching against an identity
+//! - The legacy scheme (four words of fingerprint, no checksum) used by
+//!   identities created before the checksummed scheme, and the mapping from
+//!   a legacy address to its checksummed form
+//!
+//! Words come from the BIP-39 English wordlist, the same list used for seed
+//! phrases. See [`four_word`] for the encoding.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::address::{address_matches_identity, derive_address, validate_address};
+//! use osnova_lib::models::identity::RootIdentity;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let identity = RootIdentity::generate()?;
+//! let address = derive_address(&identity.fingerprint());
+//!
+//! assert!(validate_address(&address));
+//! assert!(address_matches_identity(&address, &identity));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod four_word;
+
+pub use four_word::{
+    address_for_scheme, address_matches_identity, derive_address, derive_legacy_address,
+    upgrade_legacy_address, validate_address, AddressScheme, ADDRESS_WORD_COUNT,
+};
@@ -0,0 +1,829 @@
+//! Audit log storage and hash-chain verification
+//!
+//! Each entry stores the MAC of the entry before it and its own MAC, computed
+//! with a BLAKE3 keyed hash over the previous MAC and the entry's contents.
+//! Changing or removing any entry breaks the chain from that point on, and the
+//! stored chain head exposes entries removed from the end.
+//!
+//! Retention removes entries from the start of the chain only, and leaves a
+//! truncation marker recording the sequence and MAC of the last removed
+//! entry, so what remains still verifies.
+//!
+//! Version 2 entries MAC their canonical JSON encoding. Version 1 entries,
+//! written before the canonical encoder existed, MAC the fields in
+//! declaration order and keep verifying that way.
+
+use crate::error::{OsnovaError, Result};
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::models::retention::{Footprint, Policy, RetainedItem, RetentionStore};
+use crate::services::retention::{self, RetainedStore};
+use crate::storage::sql::RetainedRows;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+use crate::util::canonical_json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Current version of the audit entry format
+pub const AUDIT_FORMAT_VERSION: u32 = 2;
+
+/// Default maximum number of retained entries
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Actor recorded on entries written by Osnova itself
+const SYSTEM_ACTOR: &str = "osnova";
+
+/// Component identifier used to derive the audit MAC key
+const AUDIT_KEY_COMPONENT: &str = "osnova-audit-log";
+
+/// Security-relevant action recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A new identity was created
+    IdentityCreated,
+    /// An identity was imported from a seed phrase
+    IdentityImported,
+    /// An identity whose keystore key was lost was recovered with its
+    /// seed phrase
+    IdentityRecovered,
+    /// The identity was deleted
+    IdentityDeleted,
+    /// The seed phrase was revealed to the user
+    SeedRevealed,
+    /// A device pairing was confirmed
+    PairingConfirmed,
+    /// A device pairing failed its confirmation code check or timed out
+    PairingFailed,
+    /// A paired device was revoked
+    DeviceRevoked,
+    /// An app was granted a permission
+    PermissionGranted,
+    /// An app permission was revoked
+    PermissionRevoked,
+    /// The manifest signing requirement was changed
+    ManifestSigningRequirementChanged,
+    /// A user agreed to run an app after reviewing it
+    AppConsentGiven,
+    /// A user declined to run an app after reviewing it
+    AppConsentDeclined,
+    /// The network proxy refused a backend a host its app does not declare
+    NetworkAccessDenied,
+    /// A support diagnostic query was run after the user confirmed it
+    DiagnosticQueryRun,
+    /// A signed device policy was applied
+    PolicyApplied,
+    /// A device policy was lifted with its admin's signed removal
+    PolicyRemoved,
+    /// A secret setting of an app was set
+    AppSecretSet,
+    /// A secret setting of an app was cleared
+    AppSecretCleared,
+    /// Old entries were removed by retention
+    LogTruncated,
+}
+
+/// A single audit log entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Entry format version
+    pub version: u32,
+    /// Position in the chain, starting at 0
+    pub sequence: u64,
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    /// Recorded action
+    pub action: AuditAction,
+    /// Who performed the action
+    pub actor: String,
+    /// Action-specific details
+    pub details: Value,
+    /// Fingerprint of the MAC key
+    pub key_id: String,
+    /// MAC of the previous entry (hex)
+    pub prev_mac: String,
+    /// MAC of this entry (hex)
+    pub mac: String,
+}
+
+/// Fields covered by an entry's MAC
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MacPayload<'a> {
+    version: u32,
+    sequence: u64,
+    timestamp: u64,
+    action: AuditAction,
+    actor: &'a str,
+    details: &'a Value,
+    key_id: &'a str,
+    prev_mac: &'a str,
+}
+
+impl AuditEntry {
+    /// Compute the MAC of this entry under `key`
+    fn compute_mac(&self, key: &[u8; 32]) -> Result<String> {
+        let fields = MacPayload {
+            version: self.version,
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            action: self.action,
+            actor: &self.actor,
+            details: &self.details,
+            key_id: &self.key_id,
+            prev_mac: &self.prev_mac,
+        };
+        let payload = match self.version {
+            1 => serde_json::to_vec(&fields)?,
+            _ => canonical_json::to_canonical_vec(&fields)?,
+        };
+        Ok(blake3::keyed_hash(key, &payload).to_hex().to_string())
+    }
+}
+
+/// Result of verifying the audit chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ChainStatus {
+    /// The chain is intact
+    Valid {
+        /// Number of entries checked
+        entries: usize,
+        /// Entries written under a previous identity, whose links were
+        /// checked but whose MACs cannot be verified with the current key
+        unverified: usize,
+    },
+    /// An entry's contents or link to its predecessor was altered
+    Modified {
+        /// Sequence of the first altered entry
+        sequence: u64,
+    },
+    /// One or more entries are missing from the middle of the chain
+    Missing {
+        /// Sequence of the last entry before the gap
+        after_sequence: u64,
+    },
+    /// Entries were removed from the start or end of the chain without a
+    /// truncation marker
+    Truncated,
+}
+
+/// Criteria for listing audit entries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFilter {
+    /// Only entries with this action
+    pub action: Option<AuditAction>,
+    /// Only entries by this actor
+    pub actor: Option<String>,
+    /// Only entries at or after this timestamp
+    pub since: Option<u64>,
+    /// Only entries at or before this timestamp
+    pub until: Option<u64>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.action.is_none_or(|action| entry.action == action)
+            && self
+                .actor
+                .as_deref()
+                .is_none_or(|actor| entry.actor == actor)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+}
+
+/// Page of results to return
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PageRequest {
+    /// Number of matching entries to skip
+    pub offset: usize,
+    /// Maximum number of entries to return
+    pub limit: usize,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+/// A page of audit entries, newest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditPage {
+    /// Entries on this page
+    pub entries: Vec<AuditEntry>,
+    /// Total number of matching entries
+    pub total: usize,
+}
+
+/// Append-only, hash-chained audit log
+pub struct AuditLog {
+    storage: Mutex<SqlStorage>,
+    key: [u8; 32],
+    key_id: String,
+    actor: String,
+    retention: Policy,
+    clock: SharedClock,
+}
+
+impl AuditLog {
+    /// Open the audit log
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database
+    /// * `identity` - Identity the MAC key is derived from
+    /// * `actor` - Actor recorded on appended entries
+    pub fn new<P: AsRef<Path>>(
+        storage_path: P,
+        identity: &RootIdentity,
+        actor: &str,
+    ) -> Result<Self> {
+        let storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+        let key = identity.derive_component_key(AUDIT_KEY_COMPONENT, 0, KeyPurpose::AuditLog)?;
+
+        Ok(Self {
+            storage: Mutex::new(storage),
+            key,
+            key_id: blake3::hash(&key).to_hex()[..16].to_string(),
+            actor: actor.to_string(),
+            retention: RetentionStore::AuditLog.default_policy(),
+            clock: time::default_clock(),
+        })
+    }
+
+    /// Use a specific clock for the timestamps of appended entries
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the maximum number of retained entries (at least 2)
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.retention.max_count = Some(max_entries.max(2) as u32);
+        self
+    }
+
+    /// Keep entries under `policy`; a count limit is at least 2, leaving
+    /// room for the truncation marker
+    pub fn with_retention_policy(mut self, policy: Policy) -> Self {
+        self.retention = Policy {
+            max_count: policy.max_count.map(|max| max.max(2)),
+            ..policy
+        };
+        self
+    }
+
+    /// Append an entry for `action`
+    pub fn append(&self, action: AuditAction, details: Value) -> Result<AuditEntry> {
+        self.append_at(action, details, self.clock.now_unix())
+    }
+
+    /// Append an entry with an explicit timestamp
+    pub fn append_at(
+        &self,
+        action: AuditAction,
+        details: Value,
+        timestamp: u64,
+    ) -> Result<AuditEntry> {
+        let storage = self.storage.lock().unwrap();
+        let entry = self.write_entry(&storage, action, &self.actor, details, timestamp)?;
+        drop(storage);
+
+        retention::apply_on_write(self, &self.retention, timestamp)
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+        Ok(entry)
+    }
+
+    /// Verify the whole chain
+    pub fn verify_chain(&self) -> Result<ChainStatus> {
+        let storage = self.storage.lock().unwrap();
+        let rows = storage
+            .list_audit_entries()
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+        let head = storage
+            .get_audit_head()
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for (sequence, data) in rows {
+            match serde_json::from_str::<AuditEntry>(&data) {
+                Ok(entry) if entry.sequence == sequence => entries.push(entry),
+                _ => return Ok(ChainStatus::Modified { sequence }),
+            }
+        }
+
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Ok(match head {
+                None => ChainStatus::Valid {
+                    entries: 0,
+                    unverified: 0,
+                },
+                Some(_) => ChainStatus::Truncated,
+            });
+        };
+
+        // The chain must start at genesis or right after a truncation marker
+        let explained_start = if first.sequence == 0 {
+            first.prev_mac == genesis_mac()
+        } else {
+            entries.iter().any(|e| {
+                e.action == AuditAction::LogTruncated
+                    && e.details["throughSequence"] == first.sequence - 1
+                    && e.details["throughMac"] == first.prev_mac.as_str()
+            })
+        };
+
+        let mut unverified = 0;
+        let mut previous: Option<&AuditEntry> = None;
+        for entry in &entries {
+            if let Some(previous) = previous {
+                if entry.sequence != previous.sequence + 1 {
+                    return Ok(ChainStatus::Missing {
+                        after_sequence: previous.sequence,
+                    });
+                }
+                if entry.prev_mac != previous.mac {
+                    return Ok(ChainStatus::Modified {
+                        sequence: entry.sequence,
+                    });
+                }
+            }
+
+            if entry.key_id == self.key_id {
+                if entry.compute_mac(&self.key)? != entry.mac {
+                    return Ok(ChainStatus::Modified {
+                        sequence: entry.sequence,
+                    });
+                }
+            } else {
+                unverified += 1;
+            }
+            previous = Some(entry);
+        }
+
+        // Entries are only trusted once the chain links are known to be intact
+        if !explained_start || head != Some((last.sequence, last.mac.clone())) {
+            return Ok(ChainStatus::Truncated);
+        }
+
+        Ok(ChainStatus::Valid {
+            entries: entries.len(),
+            unverified,
+        })
+    }
+
+    /// List entries matching `filter`, newest first
+    pub fn list(&self, filter: &AuditFilter, page: PageRequest) -> Result<AuditPage> {
+        let rows = self
+            .storage
+            .lock()
+            .unwrap()
+            .list_audit_entries()
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+
+        let mut matching = Vec::new();
+        for (_, data) in rows.iter().rev() {
+            let entry: AuditEntry = serde_json::from_str(data)?;
+            if filter.matches(&entry) {
+                matching.push(entry);
+            }
+        }
+
+        let total = matching.len();
+        let entries = matching
+            .into_iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .collect();
+
+        Ok(AuditPage { entries, total })
+    }
+
+    /// Build, MAC, and store the next entry
+    fn write_entry(
+        &self,
+        storage: &SqlStorage,
+        action: AuditAction,
+        actor: &str,
+        details: Value,
+        timestamp: u64,
+    ) -> Result<AuditEntry> {
+        let head = storage
+            .get_audit_head()
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+        let (sequence, prev_mac) = match head {
+            Some((sequence, mac)) => (sequence + 1, mac),
+            None => (0, genesis_mac()),
+        };
+
+        let mut entry = AuditEntry {
+            version: AUDIT_FORMAT_VERSION,
+            sequence,
+            timestamp,
+            action,
+            actor: actor.to_string(),
+            details,
+            key_id: self.key_id.clone(),
+            prev_mac,
+            mac: String::new(),
+        };
+        entry.mac = entry.compute_mac(&self.key)?;
+
+        let data = serde_json::to_string(&entry)?;
+        storage
+            .append_audit_entry(sequence, &data, &entry.mac)
+            .map_err(|e| OsnovaError::Database(e.to_string()))?;
+
+        Ok(entry)
+    }
+}
+
+impl RetainedStore for AuditLog {
+    fn store(&self) -> RetentionStore {
+        RetentionStore::AuditLog
+    }
+
+    fn footprint(&self) -> anyhow::Result<Footprint> {
+        self.storage
+            .lock()
+            .unwrap()
+            .retained_footprint(RetainedRows::AuditLog)
+    }
+
+    fn items(&self) -> anyhow::Result<Vec<RetainedItem>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_retained(RetainedRows::AuditLog)
+    }
+
+    /// Drop the entries up to the last of `items`, leaving a truncation
+    /// marker
+    ///
+    /// The marker records the sequence and MAC of the last removed entry, so
+    /// verification can confirm the new first entry continues the chain.
+    fn remove(&self, items: &[RetainedItem], now: u64) -> anyhow::Result<()> {
+        let Some(through_sequence) = items.iter().map(|item| item.id as u64).max() else {
+            return Ok(());
+        };
+
+        let storage = self.storage.lock().unwrap();
+        let rows = storage.list_audit_entries()?;
+        let removed = rows
+            .iter()
+            .filter(|(sequence, _)| *sequence <= through_sequence)
+            .count();
+        // Already removed by a concurrent append
+        let Some((_, through_data)) = rows
+            .iter()
+            .find(|(sequence, _)| *sequence == through_sequence)
+        else {
+            return Ok(());
+        };
+        let through: AuditEntry = serde_json::from_str(through_data)?;
+
+        self.write_entry(
+            &storage,
+            AuditAction::LogTruncated,
+            SYSTEM_ACTOR,
+            serde_json::json!({
+                "throughSequence": through_sequence,
+                "throughMac": through.mac,
+                "removed": removed,
+            }),
+            now,
+        )?;
+        storage.delete_audit_entries_through(through_sequence)?;
+
+        Ok(())
+    }
+
+    /// The truncation marker
+    fn removal_overhead(&self) -> u64 {
+        1
+    }
+}
+
+/// MAC placeholder preceding the first entry
+fn genesis_mac() -> String {
+    hex::encode([0u8; 32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const SEED: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn open(temp_dir: &TempDir) -> AuditLog {
+        let identity = RootIdentity::from_seed(SEED).unwrap();
+        AuditLog::new(temp_dir.path(), &identity, "user-123").unwrap()
+    }
+
+    fn connection(temp_dir: &TempDir) -> rusqlite::Connection {
+        rusqlite::Connection::open(temp_dir.path().join("osnova.db")).unwrap()
+    }
+
+    fn fill(log: &AuditLog, count: u64) -> Result<()> {
+        for i in 0..count {
+            log.append_at(
+                AuditAction::PermissionGranted,
+                json!({ "index": i }),
+                1000 + i,
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chain_verification_passes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Valid {
+                entries: 0,
+                unverified: 0
+            }
+        );
+
+        fill(&log, 5)?;
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Valid {
+                entries: 5,
+                unverified: 0
+            }
+        );
+
+        // Reopening with the same identity verifies the same chain
+        assert_eq!(open(&temp_dir).verify_chain()?, log.verify_chain()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_are_stamped_by_the_clock() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let clock = std::sync::Arc::new(crate::time::MockClock::new(1_700_000_000));
+        let log = open(&temp_dir).with_clock(clock.clone());
+        assert_eq!(
+            log.append(AuditAction::SeedRevealed, json!({}))?.timestamp,
+            1_700_000_000
+        );
+        clock.set_unix(1_700_000_060);
+        assert_eq!(
+            log.append(AuditAction::SeedRevealed, json!({}))?.timestamp,
+            1_700_000_060
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mac_encoding_follows_entry_version() -> Result<()> {
+        let key = [9u8; 32];
+        let mut entry = AuditEntry {
+            version: 1,
+            sequence: 0,
+            timestamp: 1000,
+            action: AuditAction::SeedRevealed,
+            actor: "osnova".to_string(),
+            details: json!({ "b": 1.0, "a": "x" }),
+            key_id: "key".to_string(),
+            prev_mac: "00".to_string(),
+            mac: String::new(),
+        };
+        let mac = |payload: &str| {
+            blake3::keyed_hash(&key, payload.as_bytes())
+                .to_hex()
+                .to_string()
+        };
+
+        // Version 1: declaration order, serde_json number formatting
+        assert_eq!(
+            entry.compute_mac(&key)?,
+            mac(concat!(
+                r#"{"version":1,"sequence":0,"timestamp":1000,"action":"seed_revealed","#,
+                r#""actor":"osnova","details":{"a":"x","b":1.0},"keyId":"key","prevMac":"00"}"#
+            ))
+        );
+
+        // Version 2: canonical JSON
+        entry.version = 2;
+        assert_eq!(
+            entry.compute_mac(&key)?,
+            mac(concat!(
+                r#"{"action":"seed_revealed","actor":"osnova","details":{"a":"x","b":1},"#,
+                r#""keyId":"key","prevMac":"00","sequence":0,"timestamp":1000,"version":2}"#
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detects_modified_middle_entry() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 5)?;
+
+        let data: String = connection(&temp_dir)
+            .query_row("SELECT data FROM audit_log WHERE sequence = 2", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let mut entry: AuditEntry = serde_json::from_str(&data)?;
+        entry.details = json!({ "index": 99 });
+        connection(&temp_dir)
+            .execute(
+                "UPDATE audit_log SET data = ?1 WHERE sequence = 2",
+                [serde_json::to_string(&entry)?],
+            )
+            .unwrap();
+
+        assert_eq!(log.verify_chain()?, ChainStatus::Modified { sequence: 2 });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detects_deleted_entries() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 5)?;
+
+        // Middle entry
+        connection(&temp_dir)
+            .execute("DELETE FROM audit_log WHERE sequence = 2", [])
+            .unwrap();
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Missing { after_sequence: 1 }
+        );
+
+        // Tail and head entries
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 5)?;
+        connection(&temp_dir)
+            .execute("DELETE FROM audit_log WHERE sequence = 4", [])
+            .unwrap();
+        assert_eq!(log.verify_chain()?, ChainStatus::Truncated);
+
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 5)?;
+        connection(&temp_dir)
+            .execute("DELETE FROM audit_log WHERE sequence = 0", [])
+            .unwrap();
+        assert_eq!(log.verify_chain()?, ChainStatus::Truncated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention_leaves_verifiable_marker() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir).with_max_entries(4);
+        fill(&log, 10)?;
+
+        let page = log.list(&AuditFilter::default(), PageRequest::default())?;
+        assert!(page.total <= 4);
+        assert_eq!(page.entries[0].action, AuditAction::LogTruncated);
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Valid {
+                entries: page.total,
+                unverified: 0
+            }
+        );
+
+        // Tampering with the marker is still detected
+        connection(&temp_dir)
+            .execute(
+                "UPDATE audit_log SET data = replace(data, '\"removed\"', '\"gone\"')
+                 WHERE data LIKE '%log_truncated%'",
+                [],
+            )
+            .unwrap();
+        assert!(matches!(log.verify_chain()?, ChainStatus::Modified { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scheduled_expiry_leaves_verifiable_marker() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 6)?;
+
+        // Entries written at 1000..=1005; those before 1003 have expired
+        let policy = Policy {
+            max_age: Some(std::time::Duration::from_secs(7)),
+            ..Policy::default()
+        };
+        let report = retention::apply(&log, &policy, 1010).unwrap();
+        assert_eq!(report.removed, 3);
+        assert_eq!(report.remaining.items, 4);
+
+        let page = log.list(&AuditFilter::default(), PageRequest::default())?;
+        assert_eq!(page.entries[0].action, AuditAction::LogTruncated);
+        assert_eq!(page.entries[0].details["throughSequence"], 2);
+        assert_eq!(page.entries[0].details["removed"], 3);
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Valid {
+                entries: 4,
+                unverified: 0
+            }
+        );
+
+        // A later pass removes what has aged since, chaining a new marker
+        let report = retention::apply(&log, &policy, 1011).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(matches!(log.verify_chain()?, ChainStatus::Valid { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_from_previous_identity_are_unverified() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fill(&open(&temp_dir), 2)?;
+
+        let other = RootIdentity::generate()?;
+        let log = AuditLog::new(temp_dir.path(), &other, "user-456")?;
+        log.append(AuditAction::IdentityCreated, json!({}))?;
+
+        assert_eq!(
+            log.verify_chain()?,
+            ChainStatus::Valid {
+                entries: 3,
+                unverified: 2
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_filter_and_pagination() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = open(&temp_dir);
+        fill(&log, 5)?;
+        log.append_at(AuditAction::SeedRevealed, json!({}), 2000)?;
+
+        let all = log.list(&AuditFilter::default(), PageRequest::default())?;
+        assert_eq!(all.total, 6);
+        assert_eq!(all.entries[0].action, AuditAction::SeedRevealed);
+
+        let granted = AuditFilter {
+            action: Some(AuditAction::PermissionGranted),
+            ..Default::default()
+        };
+        let page = log.list(
+            &granted,
+            PageRequest {
+                offset: 1,
+                limit: 2,
+            },
+        )?;
+        assert_eq!(page.total, 5);
+        assert_eq!(
+            page.entries
+                .iter()
+                .map(|e| e.details["index"].as_u64().unwrap())
+                .collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+
+        let window = AuditFilter {
+            since: Some(1001),
+            until: Some(1002),
+            ..Default::default()
+        };
+        assert_eq!(log.list(&window, PageRequest::default())?.total, 2);
+
+        let other_actor = AuditFilter {
+            actor: Some("someone-else".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.list(&other_actor, PageRequest::default())?.total, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+//! # Audit Log Module
+//!
+//! Tamper-evident record of security-relevant actions.
+//!
+//! This module provides:
+//! - Append-only entries (timestamp, action, actor, details) in SQL storage
+//! - A MAC hash chain keyed by an identity-derived key, so modified, deleted,
+//!   or truncated entries are detected
+//! - Capped retention with verifiable truncation markers
+//! - Filtered, paginated listing for the settings-screen viewer
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::audit::{AuditAction, AuditLog, ChainStatus};
+//! use osnova_lib::models::identity::RootIdentity;
+//! use serde_json::json;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let identity = RootIdentity::generate()?;
+//! let log = AuditLog::new("/tmp/osnova", &identity, "user-123")?;
+//!
+//! log.append(AuditAction::SeedRevealed, json!({}))?;
+//! assert!(matches!(log.verify_chain()?, ChainStatus::Valid { .. }));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod log;
+
+pub use log::{
+    AuditAction, AuditEntry, AuditFilter, AuditLog, AuditPage, ChainStatus, PageRequest,
+};
@@ -0,0 +1,254 @@
+//! Headless Osnova daemon
+//!
+//! ```text
+//! osnova-daemon [--config <path>]          start and serve RPC until interrupted
+//! osnova-daemon check [--config <path>]    run the startup checks, print a JSON report
+//! osnova-daemon query [--config <path>]    list the support diagnostic queries as JSON
+//! osnova-daemon query <name> [<param>=<value>...] [--config <path>]
+//!     run a diagnostic query once the user confirms it on the terminal
+//! ```
+//!
+//! Diagnostic queries only run in builds with the `diagnostics` feature;
+//! see [`osnova_lib::diagnostics::queries`].
+//!
+//! Every exit status is one of [`ExitCode`]; see there for supervisor and
+//! health-check examples.
+
+use osnova_lib::audit::AuditLog;
+use osnova_lib::context::preflight::{self, CheckReport, ExitCode};
+use osnova_lib::context::startup::StartupConfig;
+use osnova_lib::context::{OsnovaContext, DEFAULT_SHUTDOWN_TIMEOUT};
+use osnova_lib::diagnostics::{DiagnosticQueries, DiagnosticQuery, ParamKind, QUERIES};
+use osnova_lib::rpc::{self, RpcServer};
+use osnova_lib::services::IdentityService;
+use osnova_lib::storage::chaos::DebugGate;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Actor recorded in the audit log for diagnostic queries run here
+const QUERY_ACTOR: &str = "osnova-daemon";
+
+fn main() -> std::process::ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let check = args.first().is_some_and(|arg| arg == "check");
+    let query = args.first().is_some_and(|arg| arg == "query");
+
+    // `--config <path>` layers a TOML file between the defaults and the
+    // OSNOVA_* environment variables
+    let config_path = match args.iter().position(|arg| arg == "--config") {
+        Some(flag) => match args.get(flag + 1) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => {
+                eprintln!("Usage: osnova-daemon [check | query [<name> ...]] [--config <path>]");
+                return ExitCode::InvalidConfig.into();
+            }
+        },
+        None => None,
+    };
+    let startup = StartupConfig::load(config_path.as_deref(), std::env::vars());
+
+    if check {
+        let report = match &startup {
+            Ok(startup) => preflight::run(startup),
+            Err(e) => CheckReport::invalid_config(e),
+        };
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to print check report: {}", e);
+                return ExitCode::Failure.into();
+            }
+        }
+        return report.exit_code().into();
+    }
+
+    let startup = match startup {
+        Ok(startup) => startup,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:#}", e);
+            return ExitCode::InvalidConfig.into();
+        }
+    };
+
+    if query {
+        // Everything after `query` except the `--config <path>` pair
+        let mut query_args = Vec::new();
+        let mut rest = args.iter().skip(1);
+        while let Some(arg) = rest.next() {
+            if arg == "--config" {
+                rest.next();
+            } else {
+                query_args.push(arg.as_str());
+            }
+        }
+        return run_query(&startup, &query_args).into();
+    }
+
+    let report = preflight::run(&startup);
+    for failed in report.checks.iter().filter(|c| c.exit_code.is_some()) {
+        eprintln!("Startup check {} failed: {}", failed.name, failed.detail);
+    }
+    if !report.ok {
+        return report.exit_code().into();
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start runtime: {}", e);
+            return ExitCode::Failure.into();
+        }
+    };
+    runtime.block_on(serve(startup)).into()
+}
+
+/// Open storage and serve RPC until interrupted
+async fn serve(startup: StartupConfig) -> ExitCode {
+    let context = OsnovaContext::new(startup.storage_path());
+    if let Err(e) = context.open_database() {
+        eprintln!("Failed to open storage: {:#}", e);
+        return ExitCode::from_error(&e);
+    }
+
+    let listener = match rpc::server::bind(startup.rpc_listen()).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", startup.rpc_listen(), e);
+            return ExitCode::BindFailed;
+        }
+    };
+    if let Ok(addr) = listener.local_addr() {
+        eprintln!("Serving RPC on {}", addr);
+    }
+
+    let server = Arc::new(RpcServer::new(rpc::core_registry()));
+    let code = tokio::select! {
+        result = server.serve(listener) => match result {
+            Ok(()) => ExitCode::Success,
+            Err(e) => {
+                eprintln!("RPC server stopped: {}", e);
+                ExitCode::Failure
+            }
+        },
+        _ = tokio::signal::ctrl_c() => ExitCode::Success,
+    };
+
+    if !context.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await.is_clean() {
+        eprintln!("Shutdown did not finish cleanly");
+    }
+    code
+}
+
+/// List the diagnostic queries, or run one after the user confirms it
+fn run_query(startup: &StartupConfig, args: &[&str]) -> ExitCode {
+    let Some((name, assignments)) = args.split_first() else {
+        return match serde_json::to_string_pretty(QUERIES) {
+            Ok(json) => {
+                println!("{}", json);
+                ExitCode::Success
+            }
+            Err(e) => {
+                eprintln!("Failed to print queries: {}", e);
+                ExitCode::Failure
+            }
+        };
+    };
+    let Some(query) = DiagnosticQuery::find(name) else {
+        eprintln!("Unknown diagnostic query: {}", name);
+        return ExitCode::InvalidConfig;
+    };
+    let params = match parse_params(query, assignments) {
+        Ok(params) => params,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::InvalidConfig;
+        }
+    };
+    if let Err(e) = DebugGate::ensure_diagnostic_queries_allowed() {
+        eprintln!("{}", e);
+        return ExitCode::Failure;
+    }
+
+    // Every run is audited under the identity's audit key
+    let storage_path = startup.storage_path();
+    let identity = match IdentityService::new(storage_path).and_then(|s| s.get_identity()) {
+        Ok(identity) => identity,
+        Err(e) => {
+            eprintln!("Failed to unlock the identity: {:#}", e);
+            return ExitCode::KeystoreUnavailable;
+        }
+    };
+    let audit = match AuditLog::new(storage_path, &identity, QUERY_ACTOR) {
+        Ok(audit) => audit,
+        Err(e) => {
+            eprintln!("Failed to open the audit log: {}", e);
+            return ExitCode::Failure;
+        }
+    };
+    let queries = DiagnosticQueries::new(storage_path, Arc::new(audit));
+
+    let confirmation = match queries.begin(name, &params) {
+        Ok(confirmation) => confirmation,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return ExitCode::Failure;
+        }
+    };
+    eprintln!("{}", confirmation.description);
+    eprint!(
+        "Run diagnostic query {} with {}? [y/N] ",
+        confirmation.name,
+        serde_json::Value::from_iter(confirmation.params.clone())
+    );
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err()
+        || !matches!(answer.trim(), "y" | "Y" | "yes")
+    {
+        eprintln!("Not confirmed; nothing was run");
+        return ExitCode::Failure;
+    }
+
+    let result = match queries.run_diagnostic_query(name, &params, &confirmation.token) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return ExitCode::Failure;
+        }
+    };
+    match serde_json::to_string_pretty(&result) {
+        Ok(json) => {
+            println!("{}", json);
+            ExitCode::Success
+        }
+        Err(e) => {
+            eprintln!("Failed to print query result: {}", e);
+            ExitCode::Failure
+        }
+    }
+}
+
+/// Parse `<param>=<value>` arguments by the types `query` declares
+fn parse_params(
+    query: &DiagnosticQuery,
+    assignments: &[&str],
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let mut params = serde_json::Map::new();
+    for assignment in assignments {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <param>=<value>, got {}", assignment))?;
+        let param = query
+            .param(name)
+            .ok_or_else(|| format!("Query {} has no parameter {}", query.name, name))?;
+        let value = match param.kind {
+            ParamKind::Text => serde_json::Value::from(value),
+            ParamKind::Integer { .. } => value
+                .parse::<i64>()
+                .map(serde_json::Value::from)
+                .map_err(|_| format!("Parameter {} must be an integer", name))?,
+        };
+        params.insert(name.to_string(), value);
+    }
+    Ok(params)
+}
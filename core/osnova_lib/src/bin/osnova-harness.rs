@@ -0,0 +1,130 @@
+//! Runs scenario documents against a scratch device
+//!
+//! ```text
+//! osnova-harness [--json] <scenario.json>...
+//!     run scenario files in order
+//! osnova-harness [--json] --bundled
+//!     run the scenarios shipped with Osnova
+//! osnova-harness --list
+//!     print the names of the bundled scenarios
+//! ```
+//!
+//! Each scenario runs on its own device. A line per scenario tells whether
+//! it passed; a failure is followed by its step, message and the device
+//! state. With `--json` the reports are printed as a JSON array instead.
+//!
+//! Exits with 0 when every scenario passes, 1 when one fails, and 2 when
+//! the arguments are wrong or a scenario cannot be read.
+
+use osnova_lib::harness::{self, Scenario, ScenarioReport};
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage:
+  osnova-harness [--json] <scenario.json>...
+  osnova-harness [--json] --bundled
+  osnova-harness --list";
+
+/// Exit status of a failed scenario
+const FAILED: u8 = 1;
+
+/// Exit status of bad arguments or an unreadable scenario
+const INVALID: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != "--json")
+        .collect();
+
+    let scenarios = match args.as_slice() {
+        ["--list"] if !json => return list(),
+        ["--bundled"] => harness::bundled().map_err(|e| format!("{:#}", e)),
+        files if !files.is_empty() && files.iter().all(|file| !file.starts_with("--")) => {
+            files.iter().map(|file| read(file)).collect()
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    let scenarios = match scenarios {
+        Ok(scenarios) => scenarios,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(INVALID);
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut reports = Vec::new();
+    for scenario in &scenarios {
+        match runtime.block_on(scenario.run()) {
+            Ok(report) => {
+                if !json {
+                    print_report(&report);
+                }
+                reports.push(report);
+            }
+            Err(e) => {
+                eprintln!("Failed to set up scenario {:?}: {:#}", scenario.name, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Failed to serialize the reports: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if reports.iter().all(ScenarioReport::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(FAILED)
+    }
+}
+
+/// Print the names of the bundled scenarios
+fn list() -> ExitCode {
+    match harness::bundled() {
+        Ok(scenarios) => {
+            for scenario in scenarios {
+                println!("{}: {}", scenario.name, scenario.description);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            ExitCode::from(INVALID)
+        }
+    }
+}
+
+/// Parse a scenario file
+fn read(file: &str) -> Result<Scenario, String> {
+    std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|json| Scenario::from_json(&json).map_err(|e| format!("{:#}", e)))
+        .map_err(|e| format!("Failed to read scenario {}: {}", file, e))
+}
+
+/// One line per passed scenario; the whole failure otherwise
+fn print_report(report: &ScenarioReport) {
+    match &report.failure {
+        None => println!("ok   {} ({} steps)", report.name, report.steps_run),
+        Some(failure) => {
+            println!("FAIL {}", report.name);
+            print!("{}", failure);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Manifest tooling for publishers and auditors
+//!
+//! ```text
+//! osnova-manifest normalize <artifact> <output>
+//!     normalize a build artifact for publishing; prints its hash
+//! osnova-manifest verify-repro <manifest.json> <component-id> <artifact>
+//!     compare a local build with the hash the manifest declares
+//! ```
+//!
+//! `verify-repro` prints its verdict as JSON and exits with 0 when the
+//! build matches, 1 when it does not, and 2 when it cannot be compared or
+//! the arguments are wrong.
+
+use osnova_lib::manifest::{
+    normalize_artifact, validate_manifest, verify_reproducible, ReproVerdict,
+};
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage:
+  osnova-manifest normalize <artifact> <output>
+  osnova-manifest verify-repro <manifest.json> <component-id> <artifact>";
+
+/// Exit status of a build that does not match
+const MISMATCH: u8 = 1;
+
+/// Exit status of bad arguments or a build that cannot be compared
+const NOT_COMPARABLE: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["normalize", artifact, output] => normalize(Path::new(artifact), Path::new(output)),
+        ["verify-repro", manifest, component_id, artifact] => {
+            verify_repro(Path::new(manifest), component_id, Path::new(artifact))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(NOT_COMPARABLE)
+        }
+    }
+}
+
+/// Write the normalized artifact and print its hash for the manifest
+fn normalize(artifact: &Path, output: &Path) -> ExitCode {
+    let normalized = match normalize_artifact(artifact) {
+        Ok(normalized) => normalized,
+        Err(e) => {
+            eprintln!("Failed to normalize {}: {}", artifact.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = std::fs::write(output, &normalized.bytes) {
+        eprintln!("Failed to write {}: {}", output.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    for note in &normalized.notes {
+        eprintln!("{}", note);
+    }
+    println!("{}", normalized.hash);
+    ExitCode::SUCCESS
+}
+
+/// Print the verdict of comparing a local build with the manifest
+fn verify_repro(manifest: &Path, component_id: &str, artifact: &Path) -> ExitCode {
+    let manifest = match std::fs::read_to_string(manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|json| validate_manifest(&json).map_err(|e| e.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Failed to read manifest {}: {}", manifest.display(), e);
+            return ExitCode::from(NOT_COMPARABLE);
+        }
+    };
+
+    let verdict = verify_reproducible(&manifest, component_id, artifact);
+    match serde_json::to_string_pretty(&verdict) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Failed to print verdict: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    match verdict {
+        ReproVerdict::Match { .. } => ExitCode::SUCCESS,
+        ReproVerdict::Mismatch { .. } => ExitCode::from(MISMATCH),
+        ReproVerdict::NotComparable { .. } => ExitCode::from(NOT_COMPARABLE),
+    }
+}
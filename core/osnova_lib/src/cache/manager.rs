@@ -4,7 +4,12 @@
 //!
 //! This module provides:
 //! - LRU (Least Recently Used) eviction policy
+//! - Eviction classes, so data fetched ahead of need goes before what
+//!   installed apps rely on, and pinning
 //! - Configurable cache size limits
+//! - Transparent compression of large payloads
+//! - Refusing stores that would leave too little free disk space
+//! - Garbage collection of files no installed app owns
 //! - Platform-specific cache directories
 //! - Thread-safe operations
 //!
@@ -34,27 +39,270 @@
 //! ```
 
 use crate::error::{OsnovaError, Result};
-use std::collections::HashMap;
+use crate::platform::disk::DiskGuard;
+use crate::storage::compression::{self, CompressionSettings};
+use crate::time::{self, SharedClock};
+use crate::util::format::{DisplayContext, Localize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Suffix of a payload still being written; it is renamed into place once
+/// complete, so an interrupted store never leaves a truncated entry
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// How long an unowned or partial file is left alone before garbage
+/// collection removes it, so in-flight installs are never collected
+pub const DEFAULT_GC_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// File in the cache directory recording each entry's eviction class and
+/// pin; it is never treated as an entry itself
+pub const INDEX_FILE: &str = ".osnova-cache-index.json";
+
+/// Current version of the [`INDEX_FILE`] format
+const INDEX_VERSION: u32 = 1;
+
+/// How readily an entry gives way when the cache needs room
+///
+/// Variants are ordered from first to last evicted. The writer picks the
+/// class: the component downloader stores what installed apps run as
+/// `Critical`, cache warming stores its warm-ups as `Opportunistic`, and
+/// anything stored without a class, such as icons, is `Normal`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum EvictionClass {
+    /// Fetched ahead of need; evicted first
+    Opportunistic,
+    /// Evicted once no opportunistic entry is left
+    #[default]
+    Normal,
+    /// Needed by installed apps; evicted only when forced
+    Critical,
+}
+
 /// Cache entry metadata
 #[derive(Clone, Debug)]
 struct CacheEntry {
     /// File path in cache directory
     path: PathBuf,
-    /// Size in bytes
+    /// Logical (decompressed) size in bytes
     size: usize,
+    /// Physical size on disk in bytes
+    stored_size: usize,
     /// Last access timestamp (for LRU)
     last_accessed: u64,
+    /// Eviction class
+    class: EvictionClass,
+    /// Never evicted, whatever the class
+    pinned: bool,
+}
+
+/// Class and pin of an entry as kept in [`INDEX_FILE`]
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexRecord {
+    #[serde(default)]
+    class: EvictionClass,
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// Contents of [`INDEX_FILE`], keyed by file name
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct CacheIndex {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    entries: BTreeMap<String, IndexRecord>,
+}
+
+/// Occupancy of a group of entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassUsage {
+    /// Number of entries
+    pub entries: usize,
+    /// Decompressed size in bytes
+    pub logical_size: usize,
+    /// Size on disk in bytes
+    pub physical_size: usize,
+}
+
+impl ClassUsage {
+    fn add(&mut self, entry: &CacheEntry) {
+        self.entries += 1;
+        self.logical_size += entry.size;
+        self.physical_size += entry.stored_size;
+    }
+}
+
+/// Cache occupancy by eviction class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassBreakdown {
+    /// Entries installed apps need
+    pub critical: ClassUsage,
+    /// Regular entries
+    pub normal: ClassUsage,
+    /// Entries fetched ahead of need
+    pub opportunistic: ClassUsage,
+}
+
+impl ClassBreakdown {
+    /// Occupancy of `class`
+    pub fn get(&self, class: EvictionClass) -> &ClassUsage {
+        match class {
+            EvictionClass::Critical => &self.critical,
+            EvictionClass::Normal => &self.normal,
+            EvictionClass::Opportunistic => &self.opportunistic,
+        }
+    }
+
+    fn get_mut(&mut self, class: EvictionClass) -> &mut ClassUsage {
+        match class {
+            EvictionClass::Critical => &mut self.critical,
+            EvictionClass::Normal => &mut self.normal,
+            EvictionClass::Opportunistic => &mut self.opportunistic,
+        }
+    }
+}
+
+/// Cache occupancy
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Number of cached entries
+    pub entries: usize,
+    /// Total decompressed size in bytes (counted against the quota)
+    pub logical_size: usize,
+    /// Total size on disk in bytes
+    pub physical_size: usize,
+    /// Quota in bytes
+    pub max_size: usize,
+    /// Occupancy of each eviction class
+    pub by_class: ClassBreakdown,
+    /// Occupancy of pinned entries, which also count towards their class
+    pub pinned: ClassUsage,
+    /// Sizes formatted for display, once localized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<CacheStatsDisplay>,
+}
+
+/// [`CacheStats`] sizes formatted for display
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatsDisplay {
+    /// Total decompressed size
+    pub logical_size: String,
+    /// Total size on disk
+    pub physical_size: String,
+    /// Quota
+    pub max_size: String,
+}
+
+impl Localize for CacheStats {
+    fn localize(&mut self, context: &DisplayContext) {
+        self.display = Some(CacheStatsDisplay {
+            logical_size: context.bytes(self.logical_size as u64),
+            physical_size: context.bytes(self.physical_size as u64),
+            max_size: context.bytes(self.max_size as u64),
+        });
+    }
+}
+
+/// A cached entry as listed by [`CacheManager::list_entries`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntryInfo {
+    /// Key as stored on disk, with filesystem-unsafe characters replaced
+    pub key: String,
+    /// Eviction class
+    pub class: EvictionClass,
+    /// Whether the entry is pinned
+    pub pinned: bool,
+    /// Decompressed size in bytes
+    pub logical_size: usize,
+    /// Size on disk in bytes
+    pub physical_size: usize,
+    /// Last access as Unix seconds
+    pub last_accessed: u64,
+}
+
+/// Files of one kind removed by garbage collection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcCategory {
+    /// Number of files or directories removed
+    pub count: usize,
+    /// Bytes they occupied on disk
+    pub bytes: u64,
+}
+
+impl GcCategory {
+    /// Count one removed item of `bytes`
+    pub fn add(&mut self, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Outcome of [`CacheManager::gc`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    /// Cached payloads no installed app owns
+    pub orphaned_entries: GcCategory,
+    /// Directories no installed app owns
+    pub orphaned_directories: GcCategory,
+    /// Interrupted writes older than the grace period
+    pub stale_partials: GcCategory,
+    /// Logical size the cache accounted for before collection
+    pub size_before: usize,
+    /// Logical size of what is on disk after collection
+    pub size_after: usize,
+    /// Ownership could not be determined, so only partial files were
+    /// collected
+    pub ownership_unknown: bool,
+}
+
+impl GcReport {
+    /// Bytes freed on disk across all categories
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.orphaned_entries.bytes + self.orphaned_directories.bytes + self.stale_partials.bytes
+    }
 }
 
 /// Component cache manager with LRU eviction
 ///
 /// Manages a local cache of downloaded components with automatic
 /// eviction when the cache size exceeds the configured limit.
+///
+/// Eviction goes class by class: least recently used
+/// [`Opportunistic`](EvictionClass::Opportunistic) entries first, then
+/// [`Normal`](EvictionClass::Normal) ones. [`Critical`](EvictionClass::Critical)
+/// entries are only evicted by a forced [`evict`](Self::evict), and pinned
+/// entries never are. Classes and pins are kept in [`INDEX_FILE`]; entries
+/// it does not list, such as those written by an older version, are
+/// `Normal` and unpinned.
+///
+/// Payloads are compressed according to [`CompressionSettings`]; the quota
+/// always applies to logical (decompressed) sizes, so compression ratios
+/// never change which entries fit.
 #[derive(Clone)]
 pub struct CacheManager {
     /// Base cache directory
@@ -63,8 +311,14 @@ pub struct CacheManager {
     max_size: usize,
     /// Cache entries metadata
     entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
-    /// Current cache size in bytes
+    /// Current cache size in bytes (logical)
     current_size: Arc<RwLock<usize>>,
+    /// Clock for LRU access timestamps
+    clock: SharedClock,
+    /// Compression applied to stored payloads
+    compression: CompressionSettings,
+    /// Free disk space check applied before stores
+    disk_guard: Option<DiskGuard>,
 }
 
 impl CacheManager {
@@ -100,20 +354,65 @@ impl CacheManager {
         })?;
 
         // Load existing cache entries
-        let (entries, current_size) = Self::load_cache_index(&cache_dir)?;
+        let clock = time::default_clock();
+        let (entries, current_size) = Self::load_cache_index(&cache_dir, clock.now_unix())?;
+
+        // Record the classes of entries the index did not list yet
+        if let Err(e) = Self::write_index(&cache_dir, &entries) {
+            crate::log!(Warn, "{}", e);
+        }
 
         Ok(Self {
             cache_dir,
             max_size,
             entries: Arc::new(RwLock::new(entries)),
             current_size: Arc::new(RwLock::new(current_size)),
+            clock,
+            compression: compression::shared_settings(),
+            disk_guard: None,
         })
     }
 
+    /// Use a specific clock for LRU access timestamps
+    ///
+    /// Entries already on disk are stamped with the new clock's current time.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        let now = clock.now_unix();
+        if let Ok(mut entries) = self.entries.try_write() {
+            for entry in entries.values_mut() {
+                entry.last_accessed = now;
+            }
+        }
+
+        self.clock = clock;
+        self
+    }
+
+    /// Use specific compression settings for newly stored payloads, instead
+    /// of the [process-wide ones](compression::shared_settings)
+    ///
+    /// Entries already on disk are read back regardless of these settings.
+    pub fn with_compression(mut self, compression: CompressionSettings) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Refuse stores that would leave less free space than `guard` requires
+    pub fn with_disk_guard(mut self, guard: DiskGuard) -> Self {
+        self.disk_guard = Some(guard);
+        self
+    }
+
+    /// Cache directory
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Store data in the cache
     ///
-    /// Stores data under the given key. If the cache is full, evicts
-    /// least recently used entries to make space.
+    /// Stores data under the given key, compressed when worthwhile, as a
+    /// [`Normal`](EvictionClass::Normal) entry. If the cache is full,
+    /// evicts least recently used entries to make space.
     ///
     /// # Arguments
     ///
@@ -126,30 +425,65 @@ impl CacheManager {
     /// cache.store("component-v1.0.0", data).await?;
     /// ```
     pub async fn store(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.store_with_class(key, data, EvictionClass::default()).await
+    }
+
+    /// Store data in the cache as an entry of `class`
+    ///
+    /// Like [`store`](Self::store); replacing an entry keeps its pin.
+    /// Making space never evicts critical or pinned entries, so the cache
+    /// may end up over its quota when they fill it.
+    pub async fn store_with_class(
+        &self,
+        key: &str,
+        data: &[u8],
+        class: EvictionClass,
+    ) -> Result<()> {
         let data_size = data.len();
+        let stored = self.compression.encode(data);
+
+        if let Some(guard) = &self.disk_guard {
+            guard.check(&self.cache_dir, stored.len() as u64)?;
+        }
 
         // Evict entries if necessary
         self.evict_if_needed(data_size).await?;
 
-        // Write data to file
+        // Write data to a partial file, then move it into place
         let file_path = self.cache_dir.join(Self::sanitize_key(key));
-        tokio::fs::write(&file_path, data)
+        let partial_path = Self::partial_path(&file_path);
+        tokio::fs::write(&partial_path, &stored)
+            .await
+            .map_err(|e| OsnovaError::Storage(format!("Failed to write cache file: {}", e)))?;
+        tokio::fs::rename(&partial_path, &file_path)
             .await
             .map_err(|e| OsnovaError::Storage(format!("Failed to write cache file: {}", e)))?;
 
         // Update metadata
-        let entry = CacheEntry {
+        let mut entry = CacheEntry {
             path: file_path,
             size: data_size,
-            last_accessed: Self::current_timestamp(),
+            stored_size: stored.len(),
+            last_accessed: self.clock.now_unix(),
+            class,
+            pinned: false,
         };
 
         let mut entries = self.entries.write().await;
         let mut current_size = self.current_size.write().await;
 
-        entries.insert(key.to_string(), entry);
+        let key = Self::sanitize_key(key);
+        if let Some(previous) = entries.remove(&key) {
+            entry.pinned = previous.pinned;
+            *current_size = current_size.saturating_sub(previous.size);
+        }
+        entries.insert(key, entry);
         *current_size += data_size;
 
+        if let Err(e) = Self::write_index(&self.cache_dir, &entries) {
+            crate::log!(Warn, "{}", e);
+        }
+
         Ok(())
     }
 
@@ -177,21 +511,35 @@ impl CacheManager {
     pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
         let mut entries = self.entries.write().await;
 
-        if let Some(entry) = entries.get_mut(key) {
+        if let Some(entry) = entries.get_mut(&Self::sanitize_key(key)) {
             // Update last accessed time
-            entry.last_accessed = Self::current_timestamp();
+            entry.last_accessed = self.clock.now_unix();
 
             // Read file
-            let data = tokio::fs::read(&entry.path)
+            let stored = tokio::fs::read(&entry.path)
                 .await
                 .map_err(|e| OsnovaError::Storage(format!("Failed to read cache file: {}", e)))?;
 
-            Ok(Some(data))
+            Ok(Some(compression::decode(&stored)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Check whether an entry exists in the cache index
+    ///
+    /// Does not read the cached data.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Unique identifier for the cached data
+    pub async fn contains(&self, key: &str) -> bool {
+        let entries = self.entries.read().await;
+        entries
+            .get(&Self::sanitize_key(key))
+            .is_some_and(|entry| entry.path.exists())
+    }
+
     /// Remove a specific entry from the cache
     ///
     /// # Arguments
@@ -207,15 +555,19 @@ impl CacheManager {
         let mut entries = self.entries.write().await;
         let mut current_size = self.current_size.write().await;
 
-        if let Some(entry) = entries.remove(key) {
+        if let Some(entry) = entries.remove(&Self::sanitize_key(key)) {
             // Delete file
             if let Err(e) = tokio::fs::remove_file(&entry.path).await {
                 // Log error but don't fail the operation
-                eprintln!("Warning: Failed to delete cache file: {}", e);
+                crate::log!(Warn, "Failed to delete cache file: {}", e);
             }
 
             // Update size
             *current_size = current_size.saturating_sub(entry.size);
+
+            if let Err(e) = Self::write_index(&self.cache_dir, &entries) {
+                crate::log!(Warn, "{}", e);
+            }
         }
 
         Ok(())
@@ -235,7 +587,7 @@ impl CacheManager {
         // Delete all files
         for entry in entries.values() {
             if let Err(e) = tokio::fs::remove_file(&entry.path).await {
-                eprintln!("Warning: Failed to delete cache file: {}", e);
+                crate::log!(Warn, "Failed to delete cache file: {}", e);
             }
         }
 
@@ -243,9 +595,62 @@ impl CacheManager {
         entries.clear();
         *current_size = 0;
 
+        if let Err(e) = Self::write_index(&self.cache_dir, &entries) {
+            crate::log!(Warn, "{}", e);
+        }
+
         Ok(())
     }
 
+    /// Change the eviction class of a cached entry
+    ///
+    /// Returns whether the entry exists.
+    pub async fn set_class(&self, key: &str, class: EvictionClass) -> Result<bool> {
+        self.update_entry(key, |entry| entry.class = class).await
+    }
+
+    /// Raise the eviction class of a cached entry to at least `class`
+    ///
+    /// An entry already in a higher class keeps it. Returns whether the
+    /// entry exists.
+    pub async fn promote(&self, key: &str, class: EvictionClass) -> Result<bool> {
+        self.update_entry(key, |entry| entry.class = entry.class.max(class)).await
+    }
+
+    /// Keep a cached entry whatever the cache pressure, even when eviction
+    /// is forced
+    ///
+    /// Garbage collection keeps pinned entries too. Returns whether the
+    /// entry exists.
+    pub async fn pin(&self, key: &str) -> Result<bool> {
+        self.update_entry(key, |entry| entry.pinned = true).await
+    }
+
+    /// Let a pinned entry be evicted again according to its class
+    ///
+    /// Returns whether the entry exists.
+    pub async fn unpin(&self, key: &str) -> Result<bool> {
+        self.update_entry(key, |entry| entry.pinned = false).await
+    }
+
+    /// Cached entries ordered by key, for breaking down what the cache holds
+    pub async fn list_entries(&self) -> Vec<CacheEntryInfo> {
+        let entries = self.entries.read().await;
+        let mut listed: Vec<CacheEntryInfo> = entries
+            .iter()
+            .map(|(key, entry)| CacheEntryInfo {
+                key: key.clone(),
+                class: entry.class,
+                pinned: entry.pinned,
+                logical_size: entry.size,
+                physical_size: entry.stored_size,
+                last_accessed: entry.last_accessed,
+            })
+            .collect();
+        listed.sort_by(|a, b| a.key.cmp(&b.key));
+        listed
+    }
+
     /// Get current cache size in bytes
     pub fn current_size(&self) -> usize {
         // Safe to use blocking read since this is a simple counter
@@ -260,6 +665,140 @@ impl CacheManager {
         self.max_size
     }
 
+    /// Get logical and physical cache occupancy, overall and per class
+    pub async fn stats(&self) -> CacheStats {
+        let entries = self.entries.read().await;
+        let mut stats = CacheStats {
+            entries: entries.len(),
+            logical_size: *self.current_size.read().await,
+            physical_size: 0,
+            max_size: self.max_size,
+            by_class: ClassBreakdown::default(),
+            pinned: ClassUsage::default(),
+            display: None,
+        };
+        for entry in entries.values() {
+            stats.physical_size += entry.stored_size;
+            stats.by_class.get_mut(entry.class).add(entry);
+            if entry.pinned {
+                stats.pinned.add(entry);
+            }
+        }
+        stats
+    }
+
+    /// Remove files no installed app owns and correct the size accounting
+    ///
+    /// Walks the cache directory rather than trusting the index, so files
+    /// the index lost track of (a crash during eviction, entries written by
+    /// an older version) are found too. `owners` returns the keys of
+    /// everything installed apps still need, or `None` if that cannot be
+    /// determined; then nothing but stale partial files is removed. Only
+    /// files untouched for at least `grace` are removed. Unowned entries go
+    /// whatever their eviction class, so this is where critical entries of
+    /// uninstalled apps are let go; pinned entries are kept. Afterwards the
+    /// index holds exactly the files left on disk and the current size is
+    /// their true logical total.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let report = cache.gc(|| Some(installed_keys), DEFAULT_GC_GRACE).await?;
+    /// println!("Reclaimed {} bytes", report.reclaimed_bytes());
+    /// ```
+    pub async fn gc<F>(&self, owners: F, grace: Duration) -> Result<GcReport>
+    where
+        F: FnOnce() -> Option<HashSet<String>>,
+    {
+        let owned: Option<HashSet<String>> =
+            owners().map(|keys| keys.iter().map(|key| Self::sanitize_key(key)).collect());
+
+        let mut entries = self.entries.write().await;
+        let mut current_size = self.current_size.write().await;
+        let mut report = GcReport {
+            size_before: *current_size,
+            ownership_unknown: owned.is_none(),
+            ..GcReport::default()
+        };
+
+        let read_dir = fs::read_dir(&self.cache_dir)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to read cache directory: {}", e)))?;
+        for dir_entry in read_dir.flatten() {
+            let Ok(metadata) = dir_entry.metadata() else {
+                continue;
+            };
+            let path = dir_entry.path();
+            let name = dir_entry.file_name().to_string_lossy().into_owned();
+            if name == INDEX_FILE || entries.get(&name).is_some_and(|entry| entry.pinned) {
+                continue;
+            }
+            let expired = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= grace);
+
+            let category = if name.ends_with(PARTIAL_SUFFIX) {
+                &mut report.stale_partials
+            } else if owned.as_ref().is_some_and(|keys| !keys.contains(&name)) {
+                if metadata.is_dir() {
+                    &mut report.orphaned_directories
+                } else {
+                    &mut report.orphaned_entries
+                }
+            } else {
+                continue;
+            };
+            if !expired {
+                continue;
+            }
+
+            let removed = if metadata.is_dir() {
+                let bytes = dir_size(&path);
+                fs::remove_dir_all(&path).map(|_| bytes)
+            } else {
+                fs::remove_file(&path).map(|_| metadata.len())
+            };
+            match removed {
+                Ok(bytes) => category.add(bytes),
+                Err(e) => crate::log!(
+                    Warn,
+                    "Failed to remove {} during cache GC: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+
+        // Rebuild the index from what is left, keeping access times
+        let (on_disk, size) = Self::load_cache_index(&self.cache_dir, self.clock.now_unix())?;
+        let mut by_path: HashMap<PathBuf, (String, CacheEntry)> = entries
+            .drain()
+            .map(|(key, entry)| (entry.path.clone(), (key, entry)))
+            .collect();
+        for (name, mut entry) in on_disk {
+            match by_path.remove(&entry.path) {
+                Some((key, known)) => {
+                    entry.last_accessed = known.last_accessed;
+                    entry.class = known.class;
+                    entry.pinned = known.pinned;
+                    entries.insert(key, entry);
+                }
+                None => {
+                    entries.insert(name, entry);
+                }
+            }
+        }
+        *current_size = size;
+        report.size_after = size;
+
+        if let Err(e) = Self::write_index(&self.cache_dir, &entries) {
+            crate::log!(Warn, "{}", e);
+        }
+
+        Ok(report)
+    }
+
     /// Evict entries if needed to make space for new data
     async fn evict_if_needed(&self, required_size: usize) -> Result<()> {
         let current_size = *self.current_size.read().await;
@@ -268,64 +807,109 @@ impl CacheManager {
             return Ok(()); // No eviction needed
         }
 
-        let mut entries = self.entries.write().await;
-        let mut current_size_guard = self.current_size.write().await;
+        self.evict(self.max_size.saturating_sub(required_size), false).await?;
+        Ok(())
+    }
 
-        // Sort entries by last accessed (oldest first)
-        let mut sorted_entries: Vec<_> = entries.iter().collect();
-        sorted_entries.sort_by_key(|(_, entry)| entry.last_accessed);
+    /// Evict entries until the cache holds at most `target_size` logical
+    /// bytes
+    ///
+    /// Opportunistic entries go first, then normal ones, each least
+    /// recently used first. Critical entries are only evicted when `force`
+    /// is set, after all others; pinned entries never are, so the target
+    /// may not be reached. Returns what was evicted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let evicted = cache.evict(cache.max_size() / 2, false).await?;
+    /// println!("Evicted {} entries", evicted.count);
+    /// ```
+    pub async fn evict(&self, target_size: usize, force: bool) -> Result<GcCategory> {
+        let mut entries = self.entries.write().await;
+        let mut current_size = self.current_size.write().await;
 
-        // Evict oldest entries until we have enough space
-        let target_size = self.max_size - required_size;
-        let mut evicted_size = 0;
-        let mut keys_to_remove = Vec::new();
+        // Evictable entries, in class order and oldest first within a class
+        let mut candidates: Vec<(EvictionClass, u64, String)> = entries
+            .iter()
+            .filter(|(_, entry)| !entry.pinned && (force || entry.class != EvictionClass::Critical))
+            .map(|(key, entry)| (entry.class, entry.last_accessed, key.clone()))
+            .collect();
+        candidates.sort();
 
-        for (key, entry) in sorted_entries {
-            if *current_size_guard - evicted_size <= target_size {
+        let mut evicted = GcCategory::default();
+        for (_, _, key) in candidates {
+            if *current_size <= target_size {
                 break;
             }
-
-            keys_to_remove.push(key.clone());
-            evicted_size += entry.size;
+            let Some(entry) = entries.remove(&key) else {
+                continue;
+            };
 
             // Delete file
             if let Err(e) = tokio::fs::remove_file(&entry.path).await {
-                eprintln!("Warning: Failed to delete cache file during eviction: {}", e);
+                crate::log!(Warn, "Failed to delete cache file during eviction: {}", e);
             }
+            *current_size = current_size.saturating_sub(entry.size);
+            evicted.add(entry.stored_size as u64);
         }
 
-        // Remove from metadata
-        for key in keys_to_remove {
-            entries.remove(&key);
+        if evicted.count > 0 {
+            if let Err(e) = Self::write_index(&self.cache_dir, &entries) {
+                crate::log!(Warn, "{}", e);
+            }
         }
 
-        *current_size_guard -= evicted_size;
+        Ok(evicted)
+    }
 
-        Ok(())
+    /// Apply `update` to a cached entry and persist the index
+    async fn update_entry<F>(&self, key: &str, update: F) -> Result<bool>
+    where
+        F: FnOnce(&mut CacheEntry),
+    {
+        let mut entries = self.entries.write().await;
+        let Some(entry) = entries.get_mut(&Self::sanitize_key(key)) else {
+            return Ok(false);
+        };
+        update(entry);
+        Self::write_index(&self.cache_dir, &entries)?;
+        Ok(true)
     }
 
     /// Load existing cache index from disk
-    fn load_cache_index(cache_dir: &Path) -> Result<(HashMap<String, CacheEntry>, usize)> {
+    fn load_cache_index(
+        cache_dir: &Path,
+        now: u64,
+    ) -> Result<(HashMap<String, CacheEntry>, usize)> {
         let mut entries = HashMap::new();
         let mut total_size = 0;
+        let index = Self::read_index(cache_dir);
 
         // Read all files in cache directory
         if let Ok(read_dir) = fs::read_dir(cache_dir) {
             for entry in read_dir.flatten() {
                 if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let partial = name.ends_with(PARTIAL_SUFFIX) || name == INDEX_FILE;
+                    if metadata.is_file() && !partial {
                         let path = entry.path();
-                        let size = metadata.len() as usize;
+                        let stored_size = metadata.len() as usize;
+                        let size = Self::read_logical_size(&path, stored_size);
                         let file_name = path
                             .file_name()
                             .and_then(|n| n.to_str())
                             .unwrap_or("")
                             .to_string();
 
+                        let record = index.entries.get(&file_name).copied().unwrap_or_default();
                         let cache_entry = CacheEntry {
                             path: path.clone(),
                             size,
-                            last_accessed: Self::current_timestamp(),
+                            stored_size,
+                            last_accessed: now,
+                            class: record.class,
+                            pinned: record.pinned,
                         };
 
                         entries.insert(file_name, cache_entry);
@@ -338,23 +922,176 @@ impl CacheManager {
         Ok((entries, total_size))
     }
 
+    /// Classes and pins recorded in the cache directory
+    ///
+    /// A missing or unreadable index yields none, so every entry falls
+    /// back to `Normal` and unpinned.
+    fn read_index(cache_dir: &Path) -> CacheIndex {
+        let path = cache_dir.join(INDEX_FILE);
+        let Ok(bytes) = fs::read(&path) else {
+            return CacheIndex::default();
+        };
+        serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            crate::log!(Warn, "Ignoring unreadable cache index {}: {}", path.display(), e);
+            CacheIndex::default()
+        })
+    }
+
+    /// Record the class and pin of every entry in the cache directory
+    fn write_index(cache_dir: &Path, entries: &HashMap<String, CacheEntry>) -> Result<()> {
+        let index = CacheIndex {
+            version: INDEX_VERSION,
+            entries: entries
+                .iter()
+                .map(|(key, entry)| {
+                    let record = IndexRecord {
+                        class: entry.class,
+                        pinned: entry.pinned,
+                    };
+                    (key.clone(), record)
+                })
+                .collect(),
+        };
+        let json = serde_json::to_vec(&index)?;
+        let path = cache_dir.join(INDEX_FILE);
+        let partial_path = Self::partial_path(&path);
+        fs::write(&partial_path, json)
+            .and_then(|_| fs::rename(&partial_path, &path))
+            .map_err(|e| OsnovaError::Storage(format!("Failed to write cache index: {}", e)))
+    }
+
+    /// Logical size of a cache file, from its compression header if any
+    fn read_logical_size(path: &Path, stored_size: usize) -> usize {
+        use std::io::Read;
+
+        let mut header = Vec::with_capacity(compression::HEADER_LEN);
+        match fs::File::open(path) {
+            Ok(file) => match file
+                .take(compression::HEADER_LEN as u64)
+                .read_to_end(&mut header)
+            {
+                Ok(_) => compression::logical_size(&header, stored_size),
+                Err(_) => stored_size,
+            },
+            Err(_) => stored_size,
+        }
+    }
+
+    /// Path a payload is written to before it is moved into place
+    fn partial_path(file_path: &Path) -> PathBuf {
+        let mut partial = file_path.as_os_str().to_os_string();
+        partial.push(PARTIAL_SUFFIX);
+        PathBuf::from(partial)
+    }
+
     /// Sanitize key to be filesystem-safe
     fn sanitize_key(key: &str) -> String {
         key.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
     }
+}
 
-    /// Get current timestamp in seconds since epoch
-    fn current_timestamp() -> u64 {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs()
+/// Read-only view of a cache directory another profile owns
+///
+/// Reads entries the way [`CacheManager`] stores them but never writes:
+/// no directory is created, no access time or index is updated, so a
+/// guest session can launch the host's cached components without leaving
+/// a trace in its cache.
+#[derive(Debug, Clone)]
+pub struct CacheReader {
+    cache_dir: PathBuf,
+}
+
+impl CacheReader {
+    /// Open a view of `cache_dir`, which need not exist
+    pub fn new<P: Into<PathBuf>>(cache_dir: P) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+        }
     }
+
+    /// Cache directory
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Path of a cached entry, if it exists
+    pub fn path(&self, key: &str) -> Option<PathBuf> {
+        let path = self.cache_dir.join(CacheManager::sanitize_key(key));
+        path.is_file().then_some(path)
+    }
+
+    /// Whether an entry exists
+    pub fn contains(&self, key: &str) -> bool {
+        self.path(key).is_some()
+    }
+
+    /// Read an entry's data, decompressed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Storage`] if the entry cannot be read
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(path) = self.path(key) else {
+            return Ok(None);
+        };
+        let stored = fs::read(&path)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to read cache file: {}", e)))?;
+        Ok(Some(compression::decode(&stored)?))
+    }
+}
+
+/// Total size of the files under a directory
+pub(crate) fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|read_dir| {
+            read_dir
+                .flatten()
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok()?;
+                    Some(if metadata.is_dir() {
+                        dir_size(&entry.path())
+                    } else {
+                        metadata.len()
+                    })
+                })
+                .sum()
+        })
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::platform::disk::FixedDiskSpace;
+    use crate::time::MockClock;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_store_refused_when_disk_space_low() {
+        let temp_dir = TempDir::new().unwrap();
+        let disk = Arc::new(FixedDiskSpace::new(10_000));
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024)
+            .unwrap()
+            .with_compression(CompressionSettings::disabled())
+            .with_disk_guard(DiskGuard::new(8_000).with_provider(disk.clone()));
+
+        cache.store("small", &[1u8; 2_000]).await.unwrap();
+
+        let err = cache.store("large", &[2u8; 2_001]).await.unwrap_err();
+        assert!(matches!(
+            err,
+            OsnovaError::DiskSpaceLow {
+                available: 10_000,
+                required: 10_001,
+                ..
+            }
+        ));
+        assert!(!cache.contains("large").await);
+
+        disk.set(20_000);
+        cache.store("large", &[2u8; 2_001]).await.unwrap();
+    }
 
     #[test]
     fn test_sanitize_key() {
@@ -368,4 +1105,488 @@ mod tests {
             "key_with_special_chars"
         );
     }
+
+    #[tokio::test]
+    async fn test_lru_eviction_follows_access_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = CacheManager::new(temp_dir.path(), 2500)
+            .unwrap()
+            .with_clock(clock.clone());
+        let data = vec![0u8; 1000];
+
+        cache.store("key1", &data).await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        cache.store("key2", &data).await.unwrap();
+        clock.advance(Duration::from_secs(1));
+
+        // Access key1 to make key2 the least recently used
+        cache.get("key1").await.unwrap();
+        clock.advance(Duration::from_secs(1));
+
+        cache.store("key3", &data).await.unwrap();
+
+        assert!(cache.contains("key1").await);
+        assert!(!cache.contains("key2").await);
+        assert!(cache.contains("key3").await);
+    }
+
+    #[tokio::test]
+    async fn test_lru_survives_backwards_clock_jump() {
+        let temp_dir = TempDir::new().unwrap();
+        let wall = Arc::new(MockClock::new(10_000));
+        let clock = Arc::new(crate::time::HybridClock::with_source(wall.clone()));
+        let cache = CacheManager::new(temp_dir.path(), 2500)
+            .unwrap()
+            .with_clock(clock);
+        let data = vec![0u8; 1000];
+
+        cache.store("key1", &data).await.unwrap();
+        wall.advance(Duration::from_secs(1));
+
+        // An hour-long correction must not make the fresh entry look oldest
+        wall.set_unix(10_000 - 3_600);
+        wall.advance(Duration::from_secs(1));
+        cache.store("key2", &data).await.unwrap();
+        wall.advance(Duration::from_secs(1));
+
+        cache.store("key3", &data).await.unwrap();
+
+        assert!(!cache.contains("key1").await);
+        assert!(cache.contains("key2").await);
+        assert!(cache.contains("key3").await);
+    }
+
+    fn compressible(len: usize) -> Vec<u8> {
+        b"{\"name\":\"component\"},".iter().copied().cycle().take(len).collect()
+    }
+
+    #[tokio::test]
+    async fn test_stats_report_logical_and_physical_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        let json = compressible(64 * 1024);
+        let mut png = vec![0x89, b'P', b'N', b'G'];
+        png.extend(compressible(8 * 1024));
+
+        cache.store("bundle", &json).await.unwrap();
+        cache.store("icon", &png).await.unwrap();
+        assert_eq!(cache.get("bundle").await.unwrap(), Some(json.clone()));
+        assert_eq!(cache.get("icon").await.unwrap(), Some(png.clone()));
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.logical_size, json.len() + png.len());
+        assert_eq!(cache.current_size(), stats.logical_size);
+        // The PNG is stored as-is; the JSON shrinks
+        assert!(stats.physical_size < json.len() / 4 + png.len());
+        assert!(stats.physical_size >= png.len());
+
+        // Sizes are recovered from disk after a restart
+        let reopened = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        assert_eq!(reopened.stats().await, stats);
+    }
+
+    #[tokio::test]
+    async fn test_stats_display_matches_raw_sizes() {
+        use crate::util::format::{format_bytes, Locale};
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        cache.store("key", &[7u8; 1536]).await.unwrap();
+
+        let stats = cache.stats().await;
+        assert!(stats.display.is_none());
+        assert!(serde_json::to_value(&stats).unwrap().get("display").is_none());
+
+        let german = Locale::new("de-DE");
+        let stats = stats.localized(&DisplayContext::new(german.clone(), 0));
+        let display = stats.display.clone().unwrap();
+        assert_eq!(display.logical_size, "1,5 KiB");
+        assert_eq!(display.max_size, "1 MiB");
+        assert_eq!(
+            display.physical_size,
+            format_bytes(stats.physical_size as u64, &german)
+        );
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap()["display"]["logicalSize"],
+            "1,5 KiB"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reads_entries_written_without_compression() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = compressible(16 * 1024);
+        fs::write(temp_dir.path().join("legacy-component"), &legacy).unwrap();
+
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        assert_eq!(cache.get("legacy-component").await.unwrap(), Some(legacy.clone()));
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.logical_size, legacy.len());
+        assert_eq!(stats.physical_size, legacy.len());
+    }
+
+    #[tokio::test]
+    async fn test_uri_keys_found_after_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = "ant://component-1.0.0";
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        cache.store(key, b"data").await.unwrap();
+
+        let reopened = CacheManager::new(temp_dir.path(), 1024 * 1024).unwrap();
+        assert!(reopened.contains(key).await);
+        assert_eq!(reopened.get(key).await.unwrap(), Some(b"data".to_vec()));
+        reopened.remove(key).await.unwrap();
+        assert!(!reopened.contains(key).await);
+        assert_eq!(reopened.stats().await.logical_size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_quota_counts_logical_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = CacheManager::new(temp_dir.path(), 25_000)
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_compression(CompressionSettings {
+                threshold: 1024,
+                ..CompressionSettings::default()
+            });
+        let data = compressible(10_000);
+
+        for key in ["key1", "key2", "key3"] {
+            cache.store(key, &data).await.unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+
+        // Physically all three fit, but 30,000 logical bytes exceed the quota
+        assert!(!cache.contains("key1").await);
+        assert!(cache.contains("key2").await);
+        assert!(cache.contains("key3").await);
+        assert_eq!(cache.current_size(), 20_000);
+        assert!(cache.stats().await.physical_size < 2_000);
+    }
+
+    /// Backdate a file or directory's modification time
+    fn age(path: &Path, secs: u64) {
+        let modified = std::time::SystemTime::now() - Duration::from_secs(secs);
+        fs::File::open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    fn plain_cache(dir: &Path) -> CacheManager {
+        CacheManager::new(dir, 1024 * 1024)
+            .unwrap()
+            .with_compression(CompressionSettings::disabled())
+    }
+
+    fn keys(keys: &[&str]) -> Option<HashSet<String>> {
+        Some(keys.iter().map(|key| key.to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn test_gc_removes_orphans_and_corrects_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = plain_cache(temp_dir.path());
+        cache.store("ant://installed-1.0.0", &[1u8; 1_000]).await.unwrap();
+        cache.store("uninstalled-1.0.0", &[2u8; 300]).await.unwrap();
+        cache.store("vanished-1.0.0", &[3u8; 200]).await.unwrap();
+
+        // Left behind without the index knowing
+        fs::write(temp_dir.path().join("lost-entry"), [4u8; 50]).unwrap();
+        let extracted = temp_dir.path().join("old-extract");
+        fs::create_dir_all(extracted.join("assets")).unwrap();
+        fs::write(extracted.join("index.html"), [5u8; 70]).unwrap();
+        fs::write(extracted.join("assets/app.js"), [6u8; 30]).unwrap();
+        // Deleted without the index knowing
+        fs::remove_file(temp_dir.path().join("vanished-1.0.0")).unwrap();
+        assert_eq!(cache.current_size(), 1_500);
+
+        let owners = keys(&["ant://installed-1.0.0", "vanished-1.0.0"]);
+        let report = cache.gc(|| owners, Duration::ZERO).await.unwrap();
+
+        assert_eq!(report.orphaned_entries, GcCategory { count: 2, bytes: 350 });
+        assert_eq!(report.orphaned_directories, GcCategory { count: 1, bytes: 100 });
+        assert_eq!(report.stale_partials, GcCategory::default());
+        assert_eq!(report.reclaimed_bytes(), 450);
+        assert_eq!((report.size_before, report.size_after), (1_500, 1_000));
+        assert!(!report.ownership_unknown);
+
+        assert_eq!(cache.current_size(), 1_000);
+        assert_eq!(cache.stats().await.entries, 1);
+        assert!(cache.contains("ant://installed-1.0.0").await);
+        assert!(!extracted.exists());
+        assert!(!temp_dir.path().join("lost-entry").exists());
+    }
+
+    #[tokio::test]
+    async fn test_gc_cleans_only_stale_partials() {
+        let temp_dir = TempDir::new().unwrap();
+        let stale = temp_dir.path().join("interrupted-1.0.0.partial");
+        let fresh = temp_dir.path().join("downloading-1.0.0.partial");
+        fs::write(&stale, [1u8; 400]).unwrap();
+        fs::write(&fresh, [2u8; 100]).unwrap();
+        fs::write(temp_dir.path().join("unowned"), [3u8; 10]).unwrap();
+        age(&stale, 2 * 24 * 60 * 60);
+
+        // Partial files are never loaded as entries
+        let cache = plain_cache(temp_dir.path());
+        assert_eq!(cache.stats().await.entries, 1);
+        assert_eq!(cache.current_size(), 10);
+
+        // Without ownership information only stale partials go
+        let report = cache.gc(|| None, DEFAULT_GC_GRACE).await.unwrap();
+        assert_eq!(report.stale_partials, GcCategory { count: 1, bytes: 400 });
+        assert_eq!(report.reclaimed_bytes(), 400);
+        assert!(report.ownership_unknown);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(cache.contains("unowned").await);
+
+        // Unowned files inside the grace period are kept too
+        let report = cache.gc(|| keys(&[]), DEFAULT_GC_GRACE).await.unwrap();
+        assert_eq!(report.reclaimed_bytes(), 0);
+        assert!(cache.contains("unowned").await);
+    }
+
+    #[tokio::test]
+    async fn test_gc_protects_installed_artifacts_with_corrupted_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = plain_cache(temp_dir.path());
+        cache.store("installed-1.0.0", &[1u8; 600]).await.unwrap();
+        cache.store("other-1.0.0", &[2u8; 400]).await.unwrap();
+        age(&temp_dir.path().join("installed-1.0.0"), 30 * 24 * 60 * 60);
+        age(&temp_dir.path().join("other-1.0.0"), 30 * 24 * 60 * 60);
+
+        // The index lost every entry and its size counter
+        cache.entries.write().await.clear();
+        *cache.current_size.write().await = 0;
+
+        // Unknown ownership removes nothing
+        let report = cache.gc(|| None, Duration::ZERO).await.unwrap();
+        assert_eq!(report.reclaimed_bytes(), 0);
+        assert_eq!(report.size_after, 1_000);
+
+        cache.entries.write().await.clear();
+        let report = cache
+            .gc(|| keys(&["installed-1.0.0"]), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(report.orphaned_entries, GcCategory { count: 1, bytes: 400 });
+        assert_eq!(
+            cache.get("installed-1.0.0").await.unwrap(),
+            Some(vec![1u8; 600])
+        );
+        assert_eq!(cache.current_size(), 600);
+    }
+
+    /// A 1,000-byte-per-entry cache with room for four entries
+    fn classed_cache(dir: &Path, clock: Arc<MockClock>) -> CacheManager {
+        CacheManager::new(dir, 4_000)
+            .unwrap()
+            .with_compression(CompressionSettings::disabled())
+            .with_clock(clock)
+    }
+
+    #[tokio::test]
+    async fn test_eviction_exhausts_classes_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = classed_cache(temp_dir.path(), clock.clone());
+        let data = vec![0u8; 1000];
+
+        // The oldest entries are critical and normal; the newest are warm-ups
+        for (key, class) in [
+            ("frontend", EvictionClass::Critical),
+            ("icon-old", EvictionClass::Normal),
+            ("icon-new", EvictionClass::Normal),
+            ("prefetched", EvictionClass::Opportunistic),
+        ] {
+            cache.store_with_class(key, &data, class).await.unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+
+        cache.store("icon-next", &data).await.unwrap();
+        assert!(!cache.contains("prefetched").await);
+        assert!(cache.contains("icon-old").await);
+
+        // With no opportunistic entry left, normal ones go oldest first
+        clock.advance(Duration::from_secs(1));
+        cache.store("icon-last", &data).await.unwrap();
+        assert!(!cache.contains("icon-old").await);
+        assert!(cache.contains("icon-new").await);
+        assert!(cache.contains("frontend").await);
+        assert_eq!(cache.current_size(), 4_000);
+    }
+
+    #[tokio::test]
+    async fn test_critical_entries_evicted_only_when_forced() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = classed_cache(temp_dir.path(), clock.clone());
+        let data = vec![0u8; 1000];
+
+        for key in ["app-1", "app-2", "app-3", "app-4"] {
+            cache
+                .store_with_class(key, &data, EvictionClass::Critical)
+                .await
+                .unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+
+        // Pressure alone never evicts critical entries, even over quota
+        cache.store("icon", &data).await.unwrap();
+        assert_eq!(cache.current_size(), 5_000);
+        let evicted = cache.evict(2_000, false).await.unwrap();
+        assert_eq!(evicted, GcCategory { count: 1, bytes: 1_000 });
+        assert!(!cache.contains("icon").await);
+        assert_eq!(cache.stats().await.by_class.critical.entries, 4);
+
+        let evicted = cache.evict(2_000, true).await.unwrap();
+        assert_eq!(evicted, GcCategory { count: 2, bytes: 2_000 });
+        assert!(!cache.contains("app-1").await);
+        assert!(!cache.contains("app-2").await);
+        assert!(cache.contains("app-3").await);
+    }
+
+    #[tokio::test]
+    async fn test_stats_and_listing_break_down_classes() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = classed_cache(temp_dir.path(), clock);
+
+        cache
+            .store_with_class("ant://frontend", &[1u8; 1_000], EvictionClass::Critical)
+            .await
+            .unwrap();
+        cache.store("icon", &[2u8; 200]).await.unwrap();
+        cache
+            .store_with_class("catalog", &[3u8; 300], EvictionClass::Opportunistic)
+            .await
+            .unwrap();
+        cache
+            .store_with_class("manifest", &[4u8; 50], EvictionClass::Opportunistic)
+            .await
+            .unwrap();
+        assert!(cache.pin("catalog").await.unwrap());
+        assert!(!cache.pin("missing").await.unwrap());
+
+        let stats = cache.stats().await;
+        let usage = |entries, size| ClassUsage {
+            entries,
+            logical_size: size,
+            physical_size: size,
+        };
+        assert_eq!(stats.by_class.critical, usage(1, 1_000));
+        assert_eq!(stats.by_class.normal, usage(1, 200));
+        assert_eq!(*stats.by_class.get(EvictionClass::Opportunistic), usage(2, 350));
+        assert_eq!(stats.pinned, usage(1, 300));
+        assert_eq!(stats.logical_size, 1_550);
+
+        let listed = cache.list_entries().await;
+        let keys: Vec<&str> = listed.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, vec!["ant___frontend", "catalog", "icon", "manifest"]);
+        assert_eq!(listed[1].class, EvictionClass::Opportunistic);
+        assert!(listed[1].pinned);
+        assert_eq!(listed[2].logical_size, 200);
+        assert_eq!(listed[2].last_accessed, 1_000);
+
+        // Re-storing replaces the size and keeps the pin
+        cache.store("catalog", &[3u8; 100]).await.unwrap();
+        let stats = cache.stats().await;
+        assert_eq!(stats.pinned, usage(1, 100));
+        assert_eq!(stats.logical_size, 1_350);
+        assert_eq!(stats.by_class.normal, usage(2, 300));
+    }
+
+    #[tokio::test]
+    async fn test_index_migration_defaults_to_normal() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("legacy-1.0.0"), [1u8; 100]).unwrap();
+        fs::write(temp_dir.path().join("legacy-2.0.0"), [2u8; 100]).unwrap();
+
+        // Entries from before the index are normal and unpinned
+        let cache = plain_cache(temp_dir.path());
+        assert!(temp_dir.path().join(INDEX_FILE).exists());
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.by_class.normal.entries, 2);
+        assert_eq!(stats.pinned, ClassUsage::default());
+
+        // Changes survive a restart, and the index is never an entry
+        cache
+            .set_class("legacy-1.0.0", EvictionClass::Critical)
+            .await
+            .unwrap();
+        cache.pin("legacy-2.0.0").await.unwrap();
+        let reopened = plain_cache(temp_dir.path());
+        let listed = reopened.list_entries().await;
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].class, EvictionClass::Critical);
+        assert!(listed[1].pinned);
+        assert_eq!(reopened.stats().await, cache.stats().await);
+
+        // An unreadable index falls back to the defaults
+        fs::write(temp_dir.path().join(INDEX_FILE), b"not json").unwrap();
+        let reset = plain_cache(temp_dir.path());
+        assert_eq!(reset.stats().await.by_class.normal.entries, 2);
+
+        // Collection never takes the index for an orphan
+        let report = reset.gc(|| keys(&[]), Duration::ZERO).await.unwrap();
+        assert_eq!(report.orphaned_entries.count, 2);
+        assert!(temp_dir.path().join(INDEX_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_entries_outlast_every_class() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = classed_cache(temp_dir.path(), clock.clone());
+        let data = vec![0u8; 1000];
+
+        cache
+            .store_with_class("pinned-warmup", &data, EvictionClass::Opportunistic)
+            .await
+            .unwrap();
+        cache.pin("pinned-warmup").await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        for key in ["icon", "frontend"] {
+            cache.store(key, &data).await.unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+        cache.set_class("frontend", EvictionClass::Critical).await.unwrap();
+
+        // Pinning is stronger than any class, even under forced eviction
+        let evicted = cache.evict(0, true).await.unwrap();
+        assert_eq!(evicted.count, 2);
+        assert!(cache.contains("pinned-warmup").await);
+        assert_eq!(cache.current_size(), 1_000);
+
+        // Garbage collection keeps pinned entries nobody owns
+        let report = cache.gc(|| keys(&[]), Duration::ZERO).await.unwrap();
+        assert_eq!(report.reclaimed_bytes(), 0);
+        assert!(cache.contains("pinned-warmup").await);
+
+        // Unpinned, the entry is evicted by its class again
+        cache.unpin("pinned-warmup").await.unwrap();
+        cache
+            .store_with_class("frontend", &[0u8; 3_500], EvictionClass::Critical)
+            .await
+            .unwrap();
+        assert!(!cache.contains("pinned-warmup").await);
+    }
+
+    #[tokio::test]
+    async fn test_compression_can_be_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp_dir.path(), 1024 * 1024)
+            .unwrap()
+            .with_compression(CompressionSettings::disabled());
+        let data = compressible(16 * 1024);
+
+        cache.store("key", &data).await.unwrap();
+        assert_eq!(fs::read(temp_dir.path().join("key")).unwrap(), data);
+        assert_eq!(cache.get("key").await.unwrap(), Some(data));
+    }
 }
@@ -5,6 +5,9 @@
 //! This module provides:
 //! - Cache manager with configurable size limits
 //! - LRU (Least Recently Used) eviction policy
+//! - Eviction classes and pinning, reported per class in cache stats
+//! - Transparent compression, with logical and physical size reporting
+//! - Garbage collection of files no installed app owns
 //! - Platform-specific cache directories
 //! - Thread-safe operations
 //!
@@ -33,4 +36,7 @@
 
 pub mod manager;
 
-pub use manager::CacheManager;
+pub use manager::{
+    CacheEntryInfo, CacheManager, CacheReader, CacheStats, CacheStatsDisplay, ClassBreakdown,
+    ClassUsage, EvictionClass, GcCategory, GcReport, DEFAULT_GC_GRACE, INDEX_FILE, PARTIAL_SUFFIX,
+};
@@ -0,0 +1,632 @@
+//! # Backend Binary Inspection
+//!
+//! Tell which platform a backend binary was built for from its header, so
+//! a binary that cannot run here fails with a clear error before it is
+//! marked ready or launched, rather than with an exec error from the OS.
+//!
+//! Handles:
+//! - ELF (machine and class), Mach-O (CPU type, including universal
+//!   binaries), and PE (machine) headers
+//! - `#!` scripts, accepted only when the manifest declares an interpreter
+//! - Comparing the detected format and architecture with the manifest's
+//!   target triple and the host
+//!
+//! Architectures are named as in `std::env::consts::ARCH`.
+
+use crate::error::{OsnovaError, Result};
+use crate::manifest::ComponentSchema;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes of a file read to inspect its header
+const HEADER_BYTES: u64 = 4096;
+
+/// Most architectures a universal Mach-O binary is taken to hold; Java
+/// class files share its magic and read as many more
+const MAX_FAT_ARCHES: u32 = 20;
+
+/// Executable file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutableFormat {
+    /// Linux, Android, and the BSDs
+    Elf,
+    /// macOS and iOS
+    MachO,
+    /// Windows
+    Pe,
+}
+
+impl ExecutableFormat {
+    /// Format executables use on an operating system
+    fn for_os(os: &str) -> Self {
+        match os {
+            "windows" => ExecutableFormat::Pe,
+            "macos" | "ios" => ExecutableFormat::MachO,
+            _ => ExecutableFormat::Elf,
+        }
+    }
+
+    /// Format named by a target triple, if it names a known system
+    fn for_triple(triple: &str) -> Option<Self> {
+        let parts: Vec<&str> = triple.split('-').collect();
+        if parts.contains(&"windows") {
+            Some(ExecutableFormat::Pe)
+        } else if parts
+            .iter()
+            .any(|part| matches!(*part, "apple" | "darwin" | "macos" | "ios"))
+        {
+            Some(ExecutableFormat::MachO)
+        } else if parts.iter().any(|part| {
+            matches!(
+                *part,
+                "linux" | "android" | "androideabi" | "freebsd" | "netbsd" | "openbsd"
+            )
+        }) {
+            Some(ExecutableFormat::Elf)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ExecutableFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExecutableFormat::Elf => "ELF",
+            ExecutableFormat::MachO => "Mach-O",
+            ExecutableFormat::Pe => "PE",
+        })
+    }
+}
+
+/// What a backend artifact is, from its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryKind {
+    /// Native executable; universal Mach-O binaries list several
+    /// architectures
+    Native {
+        /// Executable format
+        format: ExecutableFormat,
+        /// Architectures the executable runs on
+        arches: Vec<String>,
+    },
+    /// Script run by the program on its `#!` line
+    Script {
+        /// The `#!` line, without the `#!`
+        interpreter: String,
+    },
+    /// Not a recognized executable
+    Unknown,
+}
+
+impl fmt::Display for BinaryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryKind::Native { format, arches } if arches.len() == 1 => {
+                write!(f, "{} {}", format, arches[0])
+            }
+            BinaryKind::Native { format, arches } => {
+                write!(f, "{} universal ({})", format, arches.join(", "))
+            }
+            BinaryKind::Script { interpreter } => write!(f, "a script for {}", interpreter),
+            BinaryKind::Unknown => f.write_str("not a recognized executable"),
+        }
+    }
+}
+
+/// Platform an executable is built for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryTarget {
+    /// Executable format
+    pub format: ExecutableFormat,
+    /// Architecture
+    pub arch: String,
+}
+
+impl BinaryTarget {
+    /// The platform this process runs on
+    pub fn host() -> Self {
+        Self {
+            format: ExecutableFormat::for_os(std::env::consts::OS),
+            arch: std::env::consts::ARCH.to_string(),
+        }
+    }
+
+    /// Platform of a Rust target triple, if it names a known system
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use osnova_lib::components::binary::{BinaryTarget, ExecutableFormat};
+    ///
+    /// let target = BinaryTarget::from_triple("aarch64-apple-darwin").unwrap();
+    /// assert_eq!(target.format, ExecutableFormat::MachO);
+    /// assert_eq!(target.arch, "aarch64");
+    /// ```
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let format = ExecutableFormat::for_triple(triple)?;
+        let arch = triple.split('-').next().unwrap_or_default();
+        let arch = match arch {
+            "amd64" => "x86_64",
+            "i386" | "i486" | "i586" | "i686" => "x86",
+            "arm64" | "arm64e" => "aarch64",
+            arch if arch.starts_with("aarch64") => "aarch64",
+            arch if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+            arch if arch.starts_with("riscv64") => "riscv64",
+            arch if arch.starts_with("riscv32") => "riscv32",
+            arch if arch.starts_with("powerpc64") => "powerpc64",
+            arch if arch.starts_with("mips64") => "mips64",
+            arch if arch.starts_with("mips") => "mips",
+            arch => arch,
+        };
+        Some(Self {
+            format,
+            arch: arch.to_string(),
+        })
+    }
+
+    /// Whether an executable of `kind` runs on this platform
+    pub fn runs(&self, kind: &BinaryKind) -> bool {
+        match kind {
+            BinaryKind::Native { format, arches } => {
+                *format == self.format && arches.contains(&self.arch)
+            }
+            BinaryKind::Script { .. } | BinaryKind::Unknown => false,
+        }
+    }
+}
+
+impl fmt::Display for BinaryTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.format, self.arch)
+    }
+}
+
+/// Identify an executable from its header
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::components::binary::{inspect, BinaryKind};
+///
+/// let kind = inspect(b"#!/bin/sh\necho hello\n");
+/// assert_eq!(kind, BinaryKind::Script { interpreter: "/bin/sh".to_string() });
+/// ```
+pub fn inspect(data: &[u8]) -> BinaryKind {
+    if let Some(line) = data.strip_prefix(b"#!") {
+        let end = line.iter().position(|b| *b == b'\n').unwrap_or(line.len());
+        let interpreter = String::from_utf8_lossy(&line[..end]).trim().to_string();
+        return BinaryKind::Script { interpreter };
+    }
+
+    let native = match data.get(..4) {
+        Some([0x7f, b'E', b'L', b'F']) => inspect_elf(data),
+        Some([0xfe, 0xed, 0xfa, 0xce | 0xcf]) => inspect_macho(data, true),
+        Some([0xce | 0xcf, 0xfa, 0xed, 0xfe]) => inspect_macho(data, false),
+        Some([0xca, 0xfe, 0xba, 0xbe | 0xbf]) => inspect_fat(data),
+        Some([b'M', b'Z', ..]) => inspect_pe(data),
+        _ => None,
+    };
+    native.unwrap_or(BinaryKind::Unknown)
+}
+
+/// Identify an executable file from its header
+pub fn inspect_file(path: &Path) -> Result<BinaryKind> {
+    Ok(inspect(&read_header(path)?))
+}
+
+/// Check that a backend artifact runs on this host as declared
+///
+/// Native binaries must match the host and, when the manifest names one,
+/// the target triple. Scripts and other artifacts are only accepted when
+/// the manifest declares an interpreter to run them with.
+///
+/// # Errors
+///
+/// Returns [`OsnovaError::MismatchedBinaryTarget`] for a binary built for
+/// another platform or an unrecognized file, and an error naming the
+/// script's interpreter for an undeclared script
+pub fn verify_backend(component: &ComponentSchema, data: &[u8]) -> Result<BinaryKind> {
+    let kind = inspect(data);
+    if component.interpreter.is_some() {
+        return Ok(kind);
+    }
+    if let BinaryKind::Script { interpreter } = &kind {
+        return Err(OsnovaError::Other(format!(
+            "Backend component {} is a script for {}, but its manifest declares no interpreter",
+            component.name, interpreter
+        )));
+    }
+
+    let host = BinaryTarget::host();
+    let declared = component
+        .target
+        .as_deref()
+        .and_then(BinaryTarget::from_triple);
+    if host.runs(&kind) && declared.is_none_or(|declared| declared.runs(&kind)) {
+        return Ok(kind);
+    }
+
+    Err(OsnovaError::MismatchedBinaryTarget {
+        component: component.name.clone(),
+        declared: component
+            .target
+            .clone()
+            .unwrap_or_else(|| "no target".to_string()),
+        detected: kind.to_string(),
+        host: host.to_string(),
+    })
+}
+
+/// Check a prepared backend file before launching it
+///
+/// Applies [`verify_backend`] to the file's header and, on Unix, requires
+/// native binaries to be executable.
+pub fn verify_prepared(component: &ComponentSchema, path: &Path) -> Result<BinaryKind> {
+    let kind = verify_backend(component, &read_header(path)?)?;
+
+    #[cfg(unix)]
+    if component.interpreter.is_none() && !is_executable(&std::fs::metadata(path)?) {
+        return Err(OsnovaError::Other(format!(
+            "Backend component {} at {} is not executable",
+            component.name,
+            path.display()
+        )));
+    }
+
+    Ok(kind)
+}
+
+/// Whether any execute bit is set; always false where files have none
+#[cfg(unix)]
+pub(crate) fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+fn read_header(path: &Path) -> Result<Vec<u8>> {
+    let mut header = Vec::new();
+    std::fs::File::open(path)?
+        .take(HEADER_BYTES)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
+fn inspect_elf(data: &[u8]) -> Option<BinaryKind> {
+    let wide = *data.get(4)? == 2;
+    let machine = match data.get(5)? {
+        2 => u16::from_be_bytes(data.get(18..20)?.try_into().ok()?),
+        _ => u16::from_le_bytes(data.get(18..20)?.try_into().ok()?),
+    };
+    let arch = match (machine, wide) {
+        (0x03, _) => "x86",
+        (0x3e, _) => "x86_64",
+        (0x28, _) => "arm",
+        (0xb7, _) => "aarch64",
+        (0xf3, true) => "riscv64",
+        (0xf3, false) => "riscv32",
+        (0x08, true) => "mips64",
+        (0x08, false) => "mips",
+        (0x14, _) => "powerpc",
+        (0x15, _) => "powerpc64",
+        (0x16, _) => "s390x",
+        (0x102, _) => "loongarch64",
+        (machine, _) => return Some(native(ExecutableFormat::Elf, unknown(machine.into()))),
+    };
+    Some(native(ExecutableFormat::Elf, arch.to_string()))
+}
+
+fn inspect_macho(data: &[u8], big_endian: bool) -> Option<BinaryKind> {
+    let bytes: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+    let cpu_type = if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    };
+    Some(native(ExecutableFormat::MachO, macho_arch(cpu_type)))
+}
+
+/// Universal binary: a big-endian table of per-architecture slices
+fn inspect_fat(data: &[u8]) -> Option<BinaryKind> {
+    let entry_len = if data[3] == 0xbf { 32 } else { 20 };
+    let count = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?);
+    if count == 0 || count > MAX_FAT_ARCHES {
+        return None;
+    }
+
+    let mut arches = Vec::new();
+    for i in 0..count as usize {
+        let start = 8 + i * entry_len;
+        let cpu_type = u32::from_be_bytes(data.get(start..start + 4)?.try_into().ok()?);
+        arches.push(macho_arch(cpu_type));
+    }
+    Some(BinaryKind::Native {
+        format: ExecutableFormat::MachO,
+        arches,
+    })
+}
+
+fn macho_arch(cpu_type: u32) -> String {
+    match cpu_type {
+        0x0000_0007 => "x86".to_string(),
+        0x0100_0007 => "x86_64".to_string(),
+        0x0000_000c => "arm".to_string(),
+        0x0100_000c => "aarch64".to_string(),
+        0x0000_0012 => "powerpc".to_string(),
+        0x0100_0012 => "powerpc64".to_string(),
+        cpu_type => unknown(cpu_type),
+    }
+}
+
+fn inspect_pe(data: &[u8]) -> Option<BinaryKind> {
+    let offset = u32::from_le_bytes(data.get(0x3c..0x40)?.try_into().ok()?) as usize;
+    if data.get(offset..offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16::from_le_bytes(data.get(offset + 4..offset + 6)?.try_into().ok()?);
+    let arch = match machine {
+        0x014c => "x86".to_string(),
+        0x8664 => "x86_64".to_string(),
+        0x01c0 | 0x01c2 | 0x01c4 => "arm".to_string(),
+        0xaa64 => "aarch64".to_string(),
+        0x5064 => "riscv64".to_string(),
+        machine => unknown(machine.into()),
+    };
+    Some(native(ExecutableFormat::Pe, arch))
+}
+
+fn native(format: ExecutableFormat, arch: String) -> BinaryKind {
+    BinaryKind::Native {
+        format,
+        arches: vec![arch],
+    }
+}
+
+fn unknown(machine: u32) -> String {
+    format!("unknown machine {:#x}", machine)
+}
+
+/// Smallest header [`inspect`] reads as a native binary for this host,
+/// followed by `payload`
+#[cfg(test)]
+pub(crate) fn host_stub(payload: &[u8]) -> Vec<u8> {
+    let host = BinaryTarget::host();
+    let mut data = match host.format {
+        ExecutableFormat::Elf => {
+            let machine: u16 = match host.arch.as_str() {
+                "x86" => 0x03,
+                "arm" => 0x28,
+                "aarch64" => 0xb7,
+                "riscv64" => 0xf3,
+                _ => 0x3e,
+            };
+            let mut header = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+            header.resize(18, 0);
+            header.extend(machine.to_le_bytes());
+            header
+        }
+        ExecutableFormat::MachO => {
+            let cpu_type: u32 = match host.arch.as_str() {
+                "aarch64" => 0x0100_000c,
+                _ => 0x0100_0007,
+            };
+            let mut header = vec![0xcf, 0xfa, 0xed, 0xfe];
+            header.extend(cpu_type.to_le_bytes());
+            header
+        }
+        ExecutableFormat::Pe => {
+            let machine: u16 = match host.arch.as_str() {
+                "aarch64" => 0xaa64,
+                "x86" => 0x014c,
+                _ => 0x8664,
+            };
+            let mut header = vec![b'M', b'Z'];
+            header.resize(0x3c, 0);
+            header.extend(0x40u32.to_le_bytes());
+            header.extend(b"PE\0\0");
+            header.extend(machine.to_le_bytes());
+            header
+        }
+    };
+    data.extend_from_slice(payload);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elf(class: u8, big_endian: bool, machine: u16) -> Vec<u8> {
+        let endianness = if big_endian { 2 } else { 1 };
+        let mut data = vec![0x7f, b'E', b'L', b'F', class, endianness];
+        data.resize(18, 0);
+        if big_endian {
+            data.extend(machine.to_be_bytes());
+        } else {
+            data.extend(machine.to_le_bytes());
+        }
+        data
+    }
+
+    fn pe(machine: u16) -> Vec<u8> {
+        let mut data = vec![b'M', b'Z'];
+        data.resize(0x3c, 0);
+        data.extend(0x80u32.to_le_bytes());
+        data.resize(0x80, 0);
+        data.extend(b"PE\0\0");
+        data.extend(machine.to_le_bytes());
+        data
+    }
+
+    fn component(target: Option<&str>, interpreter: Option<&str>) -> ComponentSchema {
+        ComponentSchema {
+            id: "ant://backend".to_string(),
+            name: "sync-agent".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: target.map(str::to_string),
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: interpreter.map(str::to_string),
+            extra: Default::default(),
+        }
+    }
+
+    fn arches(kind: &BinaryKind) -> Vec<&str> {
+        match kind {
+            BinaryKind::Native { arches, .. } => arches.iter().map(String::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_detects_headers() {
+        let kind = inspect(&elf(2, false, 0x3e));
+        assert_eq!(kind.to_string(), "ELF x86_64");
+        assert_eq!(arches(&inspect(&elf(1, false, 0x28))), vec!["arm"]);
+        assert_eq!(arches(&inspect(&elf(2, false, 0xf3))), vec!["riscv64"]);
+        assert_eq!(arches(&inspect(&elf(2, true, 0x15))), vec!["powerpc64"]);
+
+        // Thin Mach-O, both byte orders
+        let mut macho = vec![0xcf, 0xfa, 0xed, 0xfe];
+        macho.extend(0x0100_000cu32.to_le_bytes());
+        assert_eq!(inspect(&macho).to_string(), "Mach-O aarch64");
+        let mut macho = vec![0xfe, 0xed, 0xfa, 0xce];
+        macho.extend(7u32.to_be_bytes());
+        assert_eq!(inspect(&macho).to_string(), "Mach-O x86");
+
+        // Universal Mach-O
+        let mut fat = vec![0xca, 0xfe, 0xba, 0xbe];
+        fat.extend(2u32.to_be_bytes());
+        for cpu_type in [0x0100_0007u32, 0x0100_000c] {
+            fat.extend(cpu_type.to_be_bytes());
+            fat.extend([0u8; 16]);
+        }
+        assert_eq!(
+            inspect(&fat).to_string(),
+            "Mach-O universal (x86_64, aarch64)"
+        );
+        // A Java class file shares the magic
+        let mut class = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+        class.extend([0u8; 32]);
+        assert_eq!(inspect(&class), BinaryKind::Unknown);
+
+        assert_eq!(inspect(&pe(0x8664)).to_string(), "PE x86_64");
+        assert_eq!(inspect(&pe(0xaa64)).to_string(), "PE aarch64");
+        // A DOS stub without a PE header
+        assert_eq!(inspect(b"MZ just text"), BinaryKind::Unknown);
+
+        assert_eq!(
+            inspect(b"#!/usr/bin/env python3\nprint()\n"),
+            BinaryKind::Script {
+                interpreter: "/usr/bin/env python3".to_string()
+            }
+        );
+        assert_eq!(inspect(b"\x7fEL"), BinaryKind::Unknown);
+        assert_eq!(inspect(b""), BinaryKind::Unknown);
+    }
+
+    #[test]
+    fn test_target_triples() {
+        let cases = [
+            ("x86_64-unknown-linux-gnu", ExecutableFormat::Elf, "x86_64"),
+            ("i686-pc-windows-msvc", ExecutableFormat::Pe, "x86"),
+            ("aarch64-apple-darwin", ExecutableFormat::MachO, "aarch64"),
+            ("armv7-linux-androideabi", ExecutableFormat::Elf, "arm"),
+            (
+                "riscv64gc-unknown-linux-gnu",
+                ExecutableFormat::Elf,
+                "riscv64",
+            ),
+        ];
+        for (triple, format, arch) in cases {
+            let target = BinaryTarget::from_triple(triple).unwrap();
+            assert_eq!(target.format, format, "{}", triple);
+            assert_eq!(target.arch, arch, "{}", triple);
+        }
+        assert!(BinaryTarget::from_triple("wasm32-unknown-unknown").is_none());
+    }
+
+    #[test]
+    fn test_mismatch_errors_name_every_platform() {
+        let arm_linux = elf(2, false, 0xb7);
+        let windows = pe(0x8664);
+        let host = BinaryTarget::host();
+
+        for (target, data, detected) in [
+            (Some("x86_64-unknown-linux-gnu"), &arm_linux, "ELF aarch64"),
+            (Some("x86_64-unknown-linux-gnu"), &windows, "PE x86_64"),
+            (None, &b"plain text".to_vec(), "not a recognized executable"),
+        ] {
+            // Declared and host mismatches both fail; skip the case this
+            // host would legitimately run
+            if target.is_none() || !host.runs(&inspect(data)) {
+                let error = verify_backend(&component(target, None), data).unwrap_err();
+                let OsnovaError::MismatchedBinaryTarget {
+                    component,
+                    declared,
+                    detected: found,
+                    host: host_name,
+                } = &error
+                else {
+                    panic!("unexpected error: {}", error);
+                };
+                assert_eq!(component, "sync-agent");
+                assert_eq!(declared, target.unwrap_or("no target"));
+                assert_eq!(found, detected);
+                assert_eq!(*host_name, host.to_string());
+                assert!(error.to_string().contains(detected));
+            }
+        }
+
+        // A host binary declared for another platform
+        let declared = if host.arch == "x86_64" {
+            "aarch64-unknown-linux-gnu"
+        } else {
+            "x86_64-unknown-linux-gnu"
+        };
+        let error = verify_backend(&component(Some(declared), None), &host_stub(b"")).unwrap_err();
+        assert!(matches!(error, OsnovaError::MismatchedBinaryTarget { .. }));
+    }
+
+    #[test]
+    fn test_scripts_need_a_declared_interpreter() {
+        let script = b"#!/bin/sh\nexec sleep 30\n";
+        let error = verify_backend(&component(None, None), script).unwrap_err();
+        assert!(error.to_string().contains("a script for /bin/sh"));
+        assert!(error.to_string().contains("no interpreter"));
+
+        let declared = component(Some("x86_64-unknown-linux-gnu"), Some("sh"));
+        assert!(matches!(
+            verify_backend(&declared, script).unwrap(),
+            BinaryKind::Script { .. }
+        ));
+        // Whatever the interpreter reads is accepted
+        assert_eq!(
+            verify_backend(&declared, b"print('hi')").unwrap(),
+            BinaryKind::Unknown
+        );
+    }
+
+    #[test]
+    fn test_host_binary_passes() {
+        let host = BinaryTarget::host();
+        let stub = host_stub(b"payload");
+        assert!(host.runs(&inspect(&stub)));
+        assert!(verify_backend(&component(None, None), &stub).is_ok());
+
+        // The test binary itself is a real host executable
+        let current = std::env::current_exe().unwrap();
+        let kind = inspect_file(&current).unwrap();
+        assert!(host.runs(&kind), "{} on {}", kind, host);
+    }
+}
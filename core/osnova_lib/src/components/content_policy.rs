@@ -0,0 +1,489 @@
+//! # Frontend Content Policy
+//!
+//! Frontend bundles are served to a webview and never run natively, so a
+//! file in one that could be executed from the cache directory is suspect.
+//! After extraction every file is checked, and flagged when it:
+//!
+//! - is an ELF, Mach-O or PE binary, or a `#!` script
+//! - has an executable permission bit set
+//! - has an extension on the denylist ([`DEFAULT_DENIED_EXTENSIONS`] unless
+//!   configured)
+//!
+//! A flagged file fails the install unless the component config lists it
+//! under `allowedExecutablePaths` together with the hash of its content
+//! (base64 BLAKE3, as in [`content_hash`]). Executable bits are removed
+//! from every file that is not allowed, flagged or not. The result is
+//! recorded with the component's provenance.
+//!
+//! Paths are relative to the bundle root and use `/` separators.
+
+use super::binary::{self, BinaryKind};
+use super::entry::validate_entry;
+use super::integrity::content_hash;
+use crate::error::{OsnovaError, Result};
+use crate::manifest::ComponentSchema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Component config key listing the files allowed to be executable
+pub const ALLOWED_EXECUTABLES_CONFIG_KEY: &str = "allowedExecutablePaths";
+
+/// Extensions flagged unless the policy is configured otherwise
+pub const DEFAULT_DENIED_EXTENSIONS: &[&str] = &["sh", "dylib", "so", "dll", "exe"];
+
+/// A bundle file allowed to be executable, pinned to its content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AllowedExecutable {
+    /// Path relative to the bundle root
+    pub path: String,
+    /// Base64 BLAKE3 hash the file's content must have
+    pub hash: String,
+}
+
+/// Why a bundle file was flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentFlag {
+    /// ELF, Mach-O or PE binary
+    NativeBinary,
+    /// Starts with a `#!` line
+    Script,
+    /// Has an executable permission bit set
+    ExecutableMode,
+    /// Extension is on the denylist
+    DeniedExtension,
+    /// Listed as allowed, but the content does not match the pinned hash
+    PinMismatch,
+}
+
+/// A flagged bundle file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentFinding {
+    /// Path relative to the bundle root
+    pub path: String,
+    /// Why the file was flagged
+    pub flags: Vec<ContentFlag>,
+    /// Whether the manifest allows the file with a matching hash
+    pub allowed: bool,
+}
+
+/// Result of checking an extracted frontend bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentCheck {
+    /// Flagged files, sorted by path
+    pub findings: Vec<ContentFinding>,
+    /// Files whose executable bits were removed, sorted
+    pub stripped: Vec<String>,
+}
+
+impl ContentCheck {
+    /// Whether every flagged file is allowed
+    pub fn passed(&self) -> bool {
+        self.findings.iter().all(|finding| finding.allowed)
+    }
+
+    /// Paths of flagged files that are not allowed
+    pub fn offending(&self) -> Vec<String> {
+        self.findings
+            .iter()
+            .filter(|finding| !finding.allowed)
+            .map(|finding| finding.path.clone())
+            .collect()
+    }
+}
+
+/// What a frontend bundle may contain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentPolicy {
+    denied_extensions: BTreeSet<String>,
+    enforce: bool,
+}
+
+impl Default for ContentPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentPolicy {
+    /// Policy flagging [`DEFAULT_DENIED_EXTENSIONS`] and failing installs
+    /// with offending files
+    pub fn new() -> Self {
+        Self {
+            denied_extensions: DEFAULT_DENIED_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            enforce: true,
+        }
+    }
+
+    /// Flag these extensions instead of the defaults
+    ///
+    /// Extensions match case-insensitively, with or without a leading `.`.
+    pub fn with_denied_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.denied_extensions = extensions
+            .into_iter()
+            .map(|extension| extension.as_ref().trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Record offending files without failing the install
+    pub fn report_only(mut self) -> Self {
+        self.enforce = false;
+        self
+    }
+
+    /// Whether installs with offending files fail
+    pub fn enforces(&self) -> bool {
+        self.enforce
+    }
+
+    /// Check an extracted bundle, removing executable bits from every file
+    /// that is not allowed
+    ///
+    /// Symlinks are neither followed nor changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tree cannot be read or permissions cannot be
+    /// changed
+    pub fn check(&self, root: &Path, allowed: &[AllowedExecutable]) -> Result<ContentCheck> {
+        let allowed: BTreeMap<&str, &str> = allowed
+            .iter()
+            .map(|entry| (entry.path.trim_start_matches("./"), entry.hash.as_str()))
+            .collect();
+        let mut check = ContentCheck::default();
+        self.walk(root, root, &allowed, &mut check)?;
+        check.findings.sort_by(|a, b| a.path.cmp(&b.path));
+        check.stripped.sort();
+        Ok(check)
+    }
+
+    fn walk(
+        &self,
+        root: &Path,
+        dir: &Path,
+        allowed: &BTreeMap<&str, &str>,
+        check: &mut ContentCheck,
+    ) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.walk(root, &path, allowed, check)?;
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(root)
+                .map_err(|e| OsnovaError::Storage(format!("Invalid path: {}", e)))?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let mut flags = Vec::new();
+            match binary::inspect_file(&path)? {
+                BinaryKind::Native { .. } => flags.push(ContentFlag::NativeBinary),
+                BinaryKind::Script { .. } => flags.push(ContentFlag::Script),
+                BinaryKind::Unknown => {}
+            }
+            let executable = binary::is_executable(&std::fs::metadata(&path)?);
+            if executable {
+                flags.push(ContentFlag::ExecutableMode);
+            }
+            let extension = path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase());
+            if extension.is_some_and(|extension| self.denied_extensions.contains(&extension)) {
+                flags.push(ContentFlag::DeniedExtension);
+            }
+
+            let pinned = match allowed.get(relative.as_str()) {
+                Some(hash) => Some(content_hash(&std::fs::read(&path)?) == *hash),
+                None => None,
+            };
+            let is_allowed = pinned == Some(true);
+            if pinned == Some(false) && !flags.is_empty() {
+                flags.push(ContentFlag::PinMismatch);
+            }
+
+            if executable && !is_allowed {
+                strip_executable(&path)?;
+                check.stripped.push(relative.clone());
+            }
+            if !flags.is_empty() {
+                check.findings.push(ContentFinding {
+                    path: relative,
+                    flags,
+                    allowed: is_allowed,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Files a component's config allows to be executable
+///
+/// # Errors
+///
+/// Returns an error message if the list is malformed, names a path outside
+/// the bundle, or is declared on a backend
+pub fn allowed_executables(
+    component: &ComponentSchema,
+) -> std::result::Result<Vec<AllowedExecutable>, String> {
+    let Some(value) = component
+        .config
+        .as_ref()
+        .and_then(|config| config.get(ALLOWED_EXECUTABLES_CONFIG_KEY))
+    else {
+        return Ok(Vec::new());
+    };
+    if component.kind != "frontend" {
+        return Err(format!(
+            "{} is only allowed on frontend components",
+            ALLOWED_EXECUTABLES_CONFIG_KEY
+        ));
+    }
+
+    let entries: Vec<AllowedExecutable> = serde_json::from_value(value.clone()).map_err(|e| {
+        format!(
+            "{} must be a list of {{path, hash}} objects: {}",
+            ALLOWED_EXECUTABLES_CONFIG_KEY, e
+        )
+    })?;
+    for entry in &entries {
+        validate_entry(&entry.path).map_err(|e| e.replacen("entry", "allowed path", 1))?;
+        if entry.hash.trim().is_empty() {
+            return Err(format!("allowed path '{}' must pin a hash", entry.path));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(unix)]
+fn strip_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() & !0o111);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn strip_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::binary::host_stub;
+    use tempfile::TempDir;
+
+    fn bundle(files: &[(&str, &[u8])]) -> TempDir {
+        let temp = TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = temp.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        temp
+    }
+
+    #[cfg(unix)]
+    fn set_mode(path: &Path, mode: u32) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    fn flags_of<'a>(check: &'a ContentCheck, path: &str) -> &'a [ContentFlag] {
+        &check
+            .findings
+            .iter()
+            .find(|finding| finding.path == path)
+            .unwrap()
+            .flags
+    }
+
+    #[test]
+    fn test_clean_bundle_passes() {
+        let temp = bundle(&[
+            ("index.html", b"<html></html>"),
+            ("assets/app.js", b"console.log(1)"),
+            ("assets/app.wasm", b"\0asm\x01\0\0\0"),
+        ]);
+        let check = ContentPolicy::new().check(temp.path(), &[]).unwrap();
+        assert!(check.passed());
+        assert_eq!(check, ContentCheck::default());
+    }
+
+    #[test]
+    fn test_flags_each_category() {
+        let temp = bundle(&[
+            ("index.html", b"<html></html>"),
+            ("bin/helper", &host_stub(b"payload")),
+            ("tools/run", b"#!/bin/sh\nrm -rf ~\n"),
+            ("lib/libnative.DYLIB", b"not really a library"),
+            ("win/setup.exe", b"plain text"),
+        ]);
+        let check = ContentPolicy::new().check(temp.path(), &[]).unwrap();
+
+        assert!(!check.passed());
+        assert_eq!(
+            check.offending(),
+            vec![
+                "bin/helper",
+                "lib/libnative.DYLIB",
+                "tools/run",
+                "win/setup.exe"
+            ]
+        );
+        assert_eq!(flags_of(&check, "bin/helper"), [ContentFlag::NativeBinary]);
+        assert_eq!(flags_of(&check, "tools/run"), [ContentFlag::Script]);
+        assert_eq!(
+            flags_of(&check, "lib/libnative.DYLIB"),
+            [ContentFlag::DeniedExtension]
+        );
+
+        // The denylist is configurable
+        let check = ContentPolicy::new()
+            .with_denied_extensions([".html"])
+            .check(temp.path(), &[])
+            .unwrap();
+        assert_eq!(
+            flags_of(&check, "index.html"),
+            [ContentFlag::DeniedExtension]
+        );
+        assert!(!check.findings.iter().any(|f| f.path == "win/setup.exe"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_strips_executable_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = bundle(&[("index.html", b"<html></html>"), ("app.js", b"1")]);
+        set_mode(&temp.path().join("app.js"), 0o755);
+        let check = ContentPolicy::new().check(temp.path(), &[]).unwrap();
+
+        assert_eq!(flags_of(&check, "app.js"), [ContentFlag::ExecutableMode]);
+        assert_eq!(check.stripped, vec!["app.js"]);
+        let mode = std::fs::metadata(temp.path().join("app.js"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o644);
+
+        // Nothing is left to flag once stripped
+        assert!(ContentPolicy::new()
+            .check(temp.path(), &[])
+            .unwrap()
+            .passed());
+    }
+
+    #[test]
+    fn test_allowlist_requires_matching_hash() {
+        let helper = host_stub(b"wasm runtime helper");
+        let temp = bundle(&[("index.html", b"<html></html>"), ("bin/helper", &helper)]);
+        #[cfg(unix)]
+        set_mode(&temp.path().join("bin/helper"), 0o755);
+
+        let pinned = AllowedExecutable {
+            path: "bin/helper".to_string(),
+            hash: content_hash(&helper),
+        };
+        let check = ContentPolicy::new()
+            .check(temp.path(), std::slice::from_ref(&pinned))
+            .unwrap();
+        assert!(check.passed());
+        assert!(check.findings[0].allowed);
+        assert!(check.stripped.is_empty());
+
+        let stale = AllowedExecutable {
+            hash: content_hash(b"an older helper"),
+            ..pinned
+        };
+        let check = ContentPolicy::new().check(temp.path(), &[stale]).unwrap();
+        assert!(!check.passed());
+        assert!(flags_of(&check, "bin/helper").contains(&ContentFlag::PinMismatch));
+    }
+
+    #[test]
+    fn test_allowed_executables_from_config() {
+        let mut component = ComponentSchema {
+            id: "ant://frontend".to_string(),
+            name: "ui".to_string(),
+            kind: "frontend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+        assert_eq!(allowed_executables(&component), Ok(vec![]));
+
+        let declare = |component: &mut ComponentSchema, value: serde_json::Value| {
+            component.config = Some(std::collections::HashMap::from([(
+                ALLOWED_EXECUTABLES_CONFIG_KEY.to_string(),
+                value,
+            )]));
+        };
+        declare(
+            &mut component,
+            serde_json::json!([{ "path": "bin/helper", "hash": "abc=" }]),
+        );
+        assert_eq!(
+            allowed_executables(&component).unwrap(),
+            vec![AllowedExecutable {
+                path: "bin/helper".to_string(),
+                hash: "abc=".to_string(),
+            }]
+        );
+
+        declare(
+            &mut component,
+            serde_json::json!([{ "path": "bin/helper" }]),
+        );
+        assert!(allowed_executables(&component).is_err());
+        declare(
+            &mut component,
+            serde_json::json!([{ "path": "../escape", "hash": "abc=" }]),
+        );
+        assert!(allowed_executables(&component)
+            .unwrap_err()
+            .contains("allowed path"));
+        declare(
+            &mut component,
+            serde_json::json!([{ "path": "bin/helper", "hash": " " }]),
+        );
+        assert!(allowed_executables(&component).is_err());
+
+        component.kind = "backend".to_string();
+        declare(
+            &mut component,
+            serde_json::json!([{ "path": "bin/helper", "hash": "abc=" }]),
+        );
+        assert!(allowed_executables(&component).is_err());
+    }
+}
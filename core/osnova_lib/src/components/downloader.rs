@@ -7,17 +7,51 @@
 //! - Downloading from network or local files
 //! - Hash verification
 //! - Extracting frontend tarballs
+//! - Refusing frontends with executable content (see [`content_policy`](super::content_policy))
 //! - Managing backend binaries
+//! - Deferring network fetches under a bandwidth policy
+//! - Verifying and repairing prepared components
+//! - Recording the provenance of every fetch from source
+//! - Refusing downloads and extraction when disk space is low
+//! - Finding or fetching backend debug symbols for crash symbolication
+//! - Collecting cached and extracted files no installed app needs
+//! - Fetching HTTP(S) sources under a [`FetchPolicy`](crate::http::FetchPolicy)
+//! - Showing fetches and collections in the activity center ([`TaskRegistry`])
 
-use crate::cache::CacheManager;
+use super::binary;
+use super::content_policy::{allowed_executables, ContentCheck, ContentPolicy};
+use super::integrity::{content_hash, ComponentIntegrity, ComponentVerification, ContentManifest};
+use super::symbols;
+use crate::cache::{
+    manager::dir_size, CacheManager, EvictionClass, GcCategory, GcReport, DEFAULT_GC_GRACE,
+};
 use crate::error::{OsnovaError, Result};
+use crate::http::{self, HttpFetcher, Trust};
 use crate::manifest::ComponentSchema;
-use crate::network::{download_data, AutonomiClient};
-use blake3::Hasher;
+use crate::models::provenance::{
+    ManifestOrigin, ProvenanceRecord, VerificationOutcome, DOWNLOADER_VERSION,
+};
+use crate::network::bandwidth::{
+    BandwidthMeter, TransferCategory, TransferDecision, TransferStatus,
+};
+use crate::models::task::TaskCategory;
+use crate::network::{download_data, AutonomiClient, CancellationToken, NetworkOptions};
+use crate::platform::disk::{DiskGuard, DEFAULT_DOWNLOAD_ESTIMATE};
+use crate::services::{ProvenanceService, TaskRegistry};
+use crate::time;
 use flate2::read::GzDecoder;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tar::Archive;
 
+/// Prefix of prepared artifacts in the temporary directory
+const PREPARED_PREFIX: &str = "osnova-";
+
+/// Suffix of the content manifest written next to an extracted frontend
+const CONTENT_MANIFEST_SUFFIX: &str = ".content.json";
+
 /// Component downloader with caching and verification
 ///
 /// Manages the full workflow of downloading, caching, and verifying components.
@@ -40,6 +74,20 @@ pub struct ComponentDownloader {
     cache: CacheManager,
     /// Optional Autonomi client
     client: Option<AutonomiClient>,
+    /// Optional bandwidth meter for metered connections
+    bandwidth: Option<Arc<BandwidthMeter>>,
+    /// Optional provenance log for fetched components
+    provenance: Option<Arc<ProvenanceService>>,
+    /// Optional free disk space check before downloads and extraction
+    disk_guard: Option<DiskGuard>,
+    /// What extracted frontends may contain
+    content_policy: ContentPolicy,
+    /// HTTP(S) fetcher; the shared one when unset
+    http: Option<Arc<HttpFetcher>>,
+    /// Optional registry showing fetches and collections as tasks
+    tasks: Option<Arc<TaskRegistry>>,
+    /// Eviction class of cached downloads
+    eviction_class: EvictionClass,
 }
 
 impl ComponentDownloader {
@@ -50,7 +98,85 @@ impl ComponentDownloader {
     /// * `cache` - Cache manager for storing components
     /// * `client` - Optional Autonomi client (required for ant:// URIs)
     pub fn new(cache: CacheManager, client: Option<AutonomiClient>) -> Self {
-        Self { cache, client }
+        Self {
+            cache,
+            client,
+            bandwidth: None,
+            provenance: None,
+            disk_guard: None,
+            content_policy: ContentPolicy::default(),
+            http: None,
+            tasks: None,
+            eviction_class: EvictionClass::Critical,
+        }
+    }
+
+    /// Cache downloads as `class` instead of
+    /// [`Critical`](EvictionClass::Critical)
+    ///
+    /// Components are fetched for installed apps by default, so they are
+    /// the last to be evicted. Fetches ahead of need, such as cache warming,
+    /// use [`Opportunistic`](EvictionClass::Opportunistic); a later cache
+    /// hit through a downloader of a higher class promotes the entry.
+    pub fn with_eviction_class(mut self, class: EvictionClass) -> Self {
+        self.eviction_class = class;
+        self
+    }
+
+    /// Meter network fetches and enforce the bandwidth policy
+    ///
+    /// With a meter set, network fetches are recorded as component downloads
+    /// and deferred when the policy does not allow them. Cache hits and local
+    /// files are never metered.
+    pub fn with_bandwidth_meter(mut self, meter: Arc<BandwidthMeter>) -> Self {
+        self.bandwidth = Some(meter);
+        self
+    }
+
+    /// Record the provenance of every component fetched from its source
+    ///
+    /// Cache hits are not recorded; they were recorded when first fetched.
+    pub fn with_provenance(mut self, provenance: Arc<ProvenanceService>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Refuse downloads and extraction that would leave too little free space
+    ///
+    /// Downloads are checked against the cache volume before fetching, using
+    /// the source size when known and [`DEFAULT_DOWNLOAD_ESTIMATE`]
+    /// otherwise. Prepared components are checked against their own volume.
+    pub fn with_disk_guard(mut self, guard: DiskGuard) -> Self {
+        self.disk_guard = Some(guard);
+        self
+    }
+
+    /// Check extracted frontends against this policy instead of the default
+    ///
+    /// The default flags native binaries, scripts, executable files and
+    /// [`DEFAULT_DENIED_EXTENSIONS`](super::content_policy::DEFAULT_DENIED_EXTENSIONS),
+    /// and fails the download when one is not allowed by the manifest.
+    pub fn with_content_policy(mut self, policy: ContentPolicy) -> Self {
+        self.content_policy = policy;
+        self
+    }
+
+    /// Fetch HTTP(S) sources with `http` instead of [`http::shared`]
+    ///
+    /// Component URIs come from manifests, so they are fetched as
+    /// [`Trust::Untrusted`].
+    pub fn with_http(mut self, http: Arc<HttpFetcher>) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Show fetches from source and cache collections in `tasks`
+    ///
+    /// A fetch is cancelled through the cancellation token of its
+    /// [`NetworkOptions`]; cache hits are not shown.
+    pub fn with_tasks(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = Some(tasks);
+        self
     }
 
     /// Download and prepare a component
@@ -73,6 +199,123 @@ impl ComponentDownloader {
     /// let path = downloader.download(&component).await?;
     /// ```
     pub async fn download(&self, component: &ComponentSchema) -> Result<PathBuf> {
+        self.download_with(component, &NetworkOptions::default()).await
+    }
+
+    /// Download and prepare a component with a timeout and cancellation
+    ///
+    /// Like [`download`](Self::download), but fails with
+    /// `OsnovaError::Timeout` or `OsnovaError::Cancelled` according to
+    /// `options`.
+    pub async fn download_with(
+        &self,
+        component: &ComponentSchema,
+        options: &NetworkOptions,
+    ) -> Result<PathBuf> {
+        self.fetch_and_prepare(component, None, options).await
+    }
+
+    /// Download a component on behalf of a manifest
+    ///
+    /// Like [`download_with`](Self::download_with); the provenance record
+    /// names the manifest, its publisher, and its signature.
+    pub async fn download_for(
+        &self,
+        component: &ComponentSchema,
+        origin: &ManifestOrigin,
+        options: &NetworkOptions,
+    ) -> Result<PathBuf> {
+        self.fetch_and_prepare(component, Some(origin), options).await
+    }
+
+    /// Download, treating a policy deferral as an error
+    async fn fetch_and_prepare(
+        &self,
+        component: &ComponentSchema,
+        origin: Option<&ManifestOrigin>,
+        options: &NetworkOptions,
+    ) -> Result<PathBuf> {
+        match self.run_pipeline(component, origin, options).await? {
+            TransferStatus::Completed(path) => Ok(path),
+            TransferStatus::Deferred(reason) => Err(OsnovaError::Network(format!(
+                "Download deferred by bandwidth policy: {:?}",
+                reason
+            ))),
+        }
+    }
+
+    /// Download and prepare a component, honouring the bandwidth policy
+    ///
+    /// Like [`download`](Self::download), but returns
+    /// `TransferStatus::Deferred` instead of an error when the bandwidth
+    /// policy does not currently allow the fetch. Callers should retry once
+    /// the policy allows it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// match downloader.try_download(&component).await? {
+    ///     TransferStatus::Completed(path) => println!("Ready at {}", path.display()),
+    ///     TransferStatus::Deferred(reason) => println!("Deferred: {:?}", reason),
+    /// }
+    /// ```
+    pub async fn try_download(
+        &self,
+        component: &ComponentSchema,
+    ) -> Result<TransferStatus<PathBuf>> {
+        self.try_download_with(component, &NetworkOptions::default()).await
+    }
+
+    /// Download a component honouring the bandwidth policy, with a timeout
+    /// and cancellation
+    ///
+    /// An interrupted download removes its cache entry and any partially
+    /// prepared files, so the next attempt starts clean.
+    pub async fn try_download_with(
+        &self,
+        component: &ComponentSchema,
+        options: &NetworkOptions,
+    ) -> Result<TransferStatus<PathBuf>> {
+        self.run_pipeline(component, None, options).await
+    }
+
+    /// Download a component on behalf of a manifest, honouring the bandwidth
+    /// policy
+    ///
+    /// Like [`try_download_with`](Self::try_download_with); the provenance
+    /// record names the manifest, its publisher, and its signature.
+    pub async fn try_download_for(
+        &self,
+        component: &ComponentSchema,
+        origin: &ManifestOrigin,
+        options: &NetworkOptions,
+    ) -> Result<TransferStatus<PathBuf>> {
+        self.run_pipeline(component, Some(origin), options).await
+    }
+
+    /// Run the download pipeline under `options`, cleaning up on interruption
+    async fn run_pipeline(
+        &self,
+        component: &ComponentSchema,
+        origin: Option<&ManifestOrigin>,
+        options: &NetworkOptions,
+    ) -> Result<TransferStatus<PathBuf>> {
+        let pipeline = self.download_pipeline(component, origin, &options.cancellation);
+        let result = options.run_local_first("component download", pipeline).await;
+
+        if matches!(&result, Err(e) if e.is_interrupted()) {
+            let _ = self.remove(component).await;
+        }
+        result
+    }
+
+    /// Cache lookup, fetch, verify, and prepare without a timeout
+    async fn download_pipeline(
+        &self,
+        component: &ComponentSchema,
+        origin: Option<&ManifestOrigin>,
+        cancellation: &CancellationToken,
+    ) -> Result<TransferStatus<PathBuf>> {
         // Check cache first
         let cache_key = Self::cache_key(component);
         if let Some(cached_data) = self.cache.get(&cache_key).await? {
@@ -80,30 +323,166 @@ impl ComponentDownloader {
             if let Some(expected_hash) = &component.hash {
                 Self::verify_hash(&cached_data, expected_hash)?;
             }
+            self.cache.promote(&cache_key, self.eviction_class).await?;
 
             // Return cached component path
-            return self.prepare_component(component, &cached_data).await;
+            let (path, check) = self.prepare_component(component, &cached_data).await?;
+            self.enforce_content_policy(component, check.as_ref()).await?;
+            return Ok(TransferStatus::Completed(path));
+        }
+
+        // Enforce bandwidth policy for network fetches
+        let meter = self.bandwidth.as_ref().filter(|_| Self::is_remote(component));
+        if let Some(meter) = meter {
+            if let TransferDecision::Deferred(reason) = meter.check()? {
+                return Ok(TransferStatus::Deferred(reason));
+            }
+        }
+
+        if let Some(guard) = &self.disk_guard {
+            guard.check(self.cache.cache_dir(), Self::estimated_size(component).await)?;
         }
 
-        // Download from source
+        // Fetches from source show in the activity center; an interrupted
+        // fetch drops the task, ending it as cancelled or failed
+        let task = self.tasks.as_ref().map(|tasks| {
+            tasks.register_cancellable(
+                format!("Download {}", component.name),
+                TaskCategory::Download,
+                cancellation.clone(),
+            )
+        });
+        let result = self.fetch_from_source(component, origin, meter).await;
+        if let Some(task) = task {
+            task.finish(&result);
+        }
+        result
+    }
+
+    /// Fetch, verify, cache, and prepare a component missing from the cache
+    async fn fetch_from_source(
+        &self,
+        component: &ComponentSchema,
+        origin: Option<&ManifestOrigin>,
+        meter: Option<&Arc<BandwidthMeter>>,
+    ) -> Result<TransferStatus<PathBuf>> {
         let data = self.fetch_component(component).await?;
 
+        if let Some(meter) = meter {
+            meter.record(TransferCategory::ComponentDownload, data.len() as u64)?;
+        }
+
         // Verify hash if provided
         if let Some(expected_hash) = &component.hash {
             Self::verify_hash(&data, expected_hash)?;
         }
 
         // Store in cache
-        self.cache.store(&cache_key, &data).await?;
+        self.cache
+            .store_with_class(&Self::cache_key(component), &data, self.eviction_class)
+            .await?;
 
         // Prepare component (extract if needed)
-        self.prepare_component(component, &data).await
+        let (path, check) = self.prepare_component(component, &data).await?;
+
+        if let Some(provenance) = &self.provenance {
+            let mut record = Self::provenance_record(component, &data, origin, time::now_unix());
+            record.content_check = check.clone();
+            provenance.record(&record).map_err(|e| {
+                OsnovaError::Storage(format!("Failed to record provenance: {}", e))
+            })?;
+        }
+
+        self.enforce_content_policy(component, check.as_ref()).await?;
+        Ok(TransferStatus::Completed(path))
+    }
+
+    /// Fail, removing the component, if its content check found files the
+    /// manifest does not allow and the policy is enforced
+    async fn enforce_content_policy(
+        &self,
+        component: &ComponentSchema,
+        check: Option<&ContentCheck>,
+    ) -> Result<()> {
+        let Some(check) = check.filter(|check| !check.passed()) else {
+            return Ok(());
+        };
+        if !self.content_policy.enforces() {
+            return Ok(());
+        }
+
+        self.remove(component).await?;
+        Err(OsnovaError::ExecutableContent {
+            component: component.name.clone(),
+            paths: check.offending(),
+        })
+    }
+
+    /// Describe a completed fetch for the provenance log
+    fn provenance_record(
+        component: &ComponentSchema,
+        data: &[u8],
+        origin: Option<&ManifestOrigin>,
+        downloaded_at: u64,
+    ) -> ProvenanceRecord {
+        // Content was verified before this is called, if a hash was declared
+        let verification = match component.hash {
+            Some(_) => VerificationOutcome::HashVerified,
+            None => VerificationOutcome::NoHashDeclared,
+        };
+
+        ProvenanceRecord {
+            component_id: component.id.clone(),
+            component_version: component.version.clone(),
+            component_hash: content_hash(data),
+            source_uri: component.id.clone(),
+            origin: origin.cloned(),
+            downloaded_at,
+            verification,
+            downloader_version: DOWNLOADER_VERSION.to_string(),
+            content_check: None,
+            network: component.declared_network(),
+        }
+    }
+
+    /// Expected download size: the file size for local sources, otherwise
+    /// a conservative default
+    pub(crate) async fn estimated_size(component: &ComponentSchema) -> u64 {
+        match component.id.strip_prefix("file://") {
+            Some(path) => tokio::fs::metadata(path)
+                .await
+                .map(|metadata| metadata.len())
+                .unwrap_or(DEFAULT_DOWNLOAD_ESTIMATE),
+            None => DEFAULT_DOWNLOAD_ESTIMATE,
+        }
+    }
+
+    /// Bytes written when preparing a component
+    ///
+    /// For tarballs this is the uncompressed size from the gzip trailer
+    /// (exact below 4 GiB).
+    fn prepared_size(component: &ComponentSchema, data: &[u8]) -> u64 {
+        if component.kind == "frontend" && data.len() >= 4 {
+            let mut isize = [0u8; 4];
+            isize.copy_from_slice(&data[data.len() - 4..]);
+            u64::from(u32::from_le_bytes(isize)).max(data.len() as u64)
+        } else {
+            data.len() as u64
+        }
+    }
+
+    /// Whether the component is fetched over the network
+    fn is_remote(component: &ComponentSchema) -> bool {
+        !component.id.starts_with("file://")
     }
 
     /// Fetch component from source
     async fn fetch_component(&self, component: &ComponentSchema) -> Result<Vec<u8>> {
-        let uri = &component.id;
+        self.fetch_uri(&component.id).await
+    }
 
+    /// Fetch data from an `ant://`, `file://`, or HTTP(S) URI
+    async fn fetch_uri(&self, uri: &str) -> Result<Vec<u8>> {
         if uri.starts_with("ant://") {
             let client = self.client.as_ref().ok_or_else(|| {
                 OsnovaError::Network("Autonomi client required for ant:// URIs".to_string())
@@ -115,24 +494,8 @@ impl ComponentDownloader {
                 .await
                 .map_err(|e| OsnovaError::Storage(format!("Failed to read component: {}", e)))
         } else if uri.starts_with("https://") || uri.starts_with("http://") {
-            let response = reqwest::get(uri)
-                .await
-                .map_err(|e| OsnovaError::Network(format!("HTTP request failed: {}", e)))?;
-
-            if !response.status().is_success() {
-                return Err(OsnovaError::Network(format!(
-                    "HTTP error {}: {}",
-                    response.status(),
-                    response.status().canonical_reason().unwrap_or("Unknown")
-                )));
-            }
-
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|e| OsnovaError::Network(format!("Failed to read response: {}", e)))?;
-
-            Ok(bytes.to_vec())
+            let http = self.http.clone().unwrap_or_else(http::shared);
+            http.fetch(uri, Trust::Untrusted).await
         } else {
             Err(OsnovaError::Other(format!(
                 "Unsupported component URI scheme: {}",
@@ -142,28 +505,54 @@ impl ComponentDownloader {
     }
 
     /// Prepare component for use (extract if needed)
+    ///
+    /// Frontends also return the content policy check of the extracted tree.
     async fn prepare_component(
         &self,
         component: &ComponentSchema,
         data: &[u8],
-    ) -> Result<PathBuf> {
+    ) -> Result<(PathBuf, Option<ContentCheck>)> {
+        if component.kind == "backend" {
+            // Refuse binaries for another platform before they are marked ready
+            binary::verify_backend(component, data)?;
+        }
+
+        if let Some(guard) = &self.disk_guard {
+            let path = Self::prepared_path(component);
+            guard.check(path.parent().unwrap_or(&path), Self::prepared_size(component, data))?;
+        }
+
         if component.kind == "frontend" {
             // Frontend components are ZLIB tarballs - extract them
-            self.extract_tarball(component, data).await
+            let (path, check) = self.extract_tarball(component, data).await?;
+            Ok((path, Some(check)))
         } else {
             // Backend components are binaries - write directly
-            self.write_binary(component, data).await
+            Ok((self.write_binary(component, data).await?, None))
         }
     }
 
     /// Extract frontend tarball
+    ///
+    /// Checks the extracted tree against the content policy, removing
+    /// executable bits the manifest does not allow, then writes a content
+    /// manifest of it for later verification.
     async fn extract_tarball(
         &self,
         component: &ComponentSchema,
         data: &[u8],
-    ) -> Result<PathBuf> {
-        let temp_dir = std::env::temp_dir();
-        let extract_dir = temp_dir.join(format!("osnova-{}-{}", component.name, component.version));
+    ) -> Result<(PathBuf, ContentCheck)> {
+        let allowed = allowed_executables(component).map_err(OsnovaError::Other)?;
+        let policy = self.content_policy.clone();
+        let extract_dir = Self::prepared_path(component);
+        let manifest_path = Self::content_manifest_path(component);
+
+        // Start from a clean directory so stale files don't linger
+        if extract_dir.exists() {
+            tokio::fs::remove_dir_all(&extract_dir)
+                .await
+                .map_err(|e| OsnovaError::Storage(format!("Failed to clear extract dir: {}", e)))?;
+        }
 
         // Create extraction directory
         tokio::fs::create_dir_all(&extract_dir)
@@ -175,23 +564,28 @@ impl ComponentDownloader {
 
         // Use blocking task for tar extraction (not async-friendly)
         let extract_dir_clone = extract_dir.clone();
-        tokio::task::spawn_blocking(move || {
+        let check = tokio::task::spawn_blocking(move || {
             let decoder = GzDecoder::new(data_owned.as_slice());
             let mut archive = Archive::new(decoder);
             archive
                 .unpack(&extract_dir_clone)
-                .map_err(|e| OsnovaError::Storage(format!("Failed to extract tarball: {}", e)))
+                .map_err(|e| OsnovaError::Storage(format!("Failed to extract tarball: {}", e)))?;
+
+            let check = policy.check(&extract_dir_clone, &allowed)?;
+
+            // Record the extracted content for integrity checks
+            ContentManifest::from_dir(&extract_dir_clone)?.save(&manifest_path)?;
+            Ok::<_, OsnovaError>(check)
         })
         .await
         .map_err(|e| OsnovaError::Other(format!("Extraction task failed: {}", e)))??;
 
-        Ok(extract_dir)
+        Ok((extract_dir, check))
     }
 
     /// Write backend binary
     async fn write_binary(&self, component: &ComponentSchema, data: &[u8]) -> Result<PathBuf> {
-        let temp_dir = std::env::temp_dir();
-        let binary_path = temp_dir.join(format!("osnova-{}-{}", component.name, component.version));
+        let binary_path = Self::prepared_path(component);
 
         tokio::fs::write(&binary_path, data)
             .await
@@ -216,12 +610,7 @@ impl ComponentDownloader {
 
     /// Verify component hash
     fn verify_hash(data: &[u8], expected_hash: &str) -> Result<()> {
-        let mut hasher = Hasher::new();
-        hasher.update(data);
-        let hash = hasher.finalize();
-
-        let actual_hash_b64 =
-            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, hash.as_bytes());
+        let actual_hash_b64 = content_hash(data);
 
         if actual_hash_b64 != expected_hash {
             return Err(OsnovaError::Other(format!(
@@ -233,9 +622,294 @@ impl ComponentDownloader {
         Ok(())
     }
 
+    /// Get a backend's debug symbols, fetching them on first use
+    ///
+    /// Looks next to the prepared binary, then next to a local (`file://`)
+    /// source artifact, then fetches the URI the component's
+    /// `config.symbols` declares. Fetched symbols are kept next to the
+    /// prepared binary.
+    ///
+    /// Returns `None` when the component ships no symbols.
+    pub async fn fetch_symbols(&self, component: &ComponentSchema) -> Result<Option<PathBuf>> {
+        if let Some(path) = Self::local_symbols(component) {
+            return Ok(Some(path));
+        }
+        let Some(uri) = component.declared_symbols() else {
+            return Ok(None);
+        };
+
+        let data = self.fetch_uri(uri).await?;
+        let path = symbols::symbol_path(&Self::prepared_path(component));
+        tokio::fs::write(&path, data)
+            .await
+            .map_err(|e| OsnovaError::Storage(format!("Failed to store symbols: {}", e)))?;
+
+        Ok(Some(path))
+    }
+
+    /// Symbols shipped with a backend, without fetching anything
+    ///
+    /// Checks next to the prepared binary, then next to a local (`file://`)
+    /// source artifact.
+    pub fn local_symbols(component: &ComponentSchema) -> Option<PathBuf> {
+        let prepared = symbols::symbol_path(&Self::prepared_path(component));
+        let source = component
+            .id
+            .strip_prefix("file://")
+            .map(|path| symbols::symbol_path(path.as_ref()));
+
+        std::iter::once(prepared)
+            .chain(source)
+            .find(|path| path.is_file())
+    }
+
+    /// Verify a prepared component against its cache entry and manifest hash
+    ///
+    /// Checks that the cache entry exists and matches the manifest hash. For
+    /// extracted frontends, every file is checked against the content
+    /// manifest written at extraction time.
+    pub async fn verify(&self, component: &ComponentSchema) -> Result<ComponentVerification> {
+        let integrity = self.check_integrity(component).await?;
+
+        Ok(ComponentVerification {
+            component_id: component.id.clone(),
+            name: component.name.clone(),
+            integrity,
+        })
+    }
+
+    /// Quick manifest-level check that the component is cached
+    ///
+    /// Does not read or hash any data; suitable for launch-time checks.
+    pub async fn quick_check(&self, component: &ComponentSchema) -> bool {
+        self.cache.contains(&Self::cache_key(component)).await
+    }
+
+    /// Evict a component and download it again through the normal pipeline
+    pub async fn repair(&self, component: &ComponentSchema) -> Result<PathBuf> {
+        self.repair_with(component, &NetworkOptions::default()).await
+    }
+
+    /// Repair a component with a timeout and cancellation on the download
+    pub async fn repair_with(
+        &self,
+        component: &ComponentSchema,
+        options: &NetworkOptions,
+    ) -> Result<PathBuf> {
+        self.remove(component).await?;
+        self.download_with(component, options).await
+    }
+
+    /// Repair a component on behalf of a manifest
+    pub async fn repair_for(
+        &self,
+        component: &ComponentSchema,
+        origin: &ManifestOrigin,
+        options: &NetworkOptions,
+    ) -> Result<PathBuf> {
+        self.remove(component).await?;
+        self.download_for(component, origin, options).await
+    }
+
+    /// Remove a component's cache entry and prepared files
+    pub async fn remove(&self, component: &ComponentSchema) -> Result<()> {
+        self.cache.remove(&Self::cache_key(component)).await?;
+
+        let prepared = Self::prepared_path(component);
+        if prepared.is_dir() {
+            tokio::fs::remove_dir_all(&prepared).await?;
+        } else if prepared.exists() {
+            tokio::fs::remove_file(&prepared).await?;
+        }
+
+        let manifest_path = Self::content_manifest_path(component);
+        if manifest_path.exists() {
+            tokio::fs::remove_file(&manifest_path).await?;
+        }
+
+        let symbols_path = symbols::symbol_path(&prepared);
+        if symbols_path.exists() {
+            tokio::fs::remove_file(&symbols_path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove cached and extracted files no installed component needs
+    ///
+    /// `installed` lists every component installed apps still reference,
+    /// or `None` if that cannot be determined, in which case only stale
+    /// partial files are removed. Extracted frontends are recognised by
+    /// their content manifest; prepared files without one are left alone.
+    pub async fn gc(&self, installed: Option<&[ComponentSchema]>) -> Result<GcReport> {
+        let task = self
+            .tasks
+            .as_ref()
+            .map(|tasks| tasks.register("Collect unused cache files", TaskCategory::CacheGc));
+        let result = self
+            .gc_in(installed, &std::env::temp_dir(), DEFAULT_GC_GRACE)
+            .await;
+        match (task, &result) {
+            (Some(task), Ok(report)) => {
+                task.complete_with(format!("Freed {} bytes", report.reclaimed_bytes()))
+            }
+            (Some(task), Err(e)) => task.fail(e),
+            (None, _) => {}
+        }
+        result
+    }
+
+    /// [`gc`](Self::gc) with extracted frontends under `prepared_root`
+    async fn gc_in(
+        &self,
+        installed: Option<&[ComponentSchema]>,
+        prepared_root: &Path,
+        grace: Duration,
+    ) -> Result<GcReport> {
+        let cache_keys =
+            installed.map(|components| components.iter().map(Self::cache_key).collect());
+        let mut report = self.cache.gc(|| cache_keys, grace).await?;
+
+        if let Some(components) = installed {
+            let owned: HashSet<String> = components.iter().map(Self::artifact_name).collect();
+            Self::sweep_extracted(
+                prepared_root,
+                &owned,
+                grace,
+                &mut report.orphaned_directories,
+            );
+        }
+        Ok(report)
+    }
+
+    /// Remove extracted frontends whose artifact is not in `owned`
+    fn sweep_extracted(
+        root: &Path,
+        owned: &HashSet<String>,
+        grace: Duration,
+        removed: &mut GcCategory,
+    ) {
+        let Ok(read_dir) = std::fs::read_dir(root) else {
+            return;
+        };
+        for entry in read_dir.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(artifact) = name
+                .strip_prefix(PREPARED_PREFIX)
+                .and_then(|rest| rest.strip_suffix(CONTENT_MANIFEST_SUFFIX))
+            else {
+                continue;
+            };
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= grace);
+            if owned.contains(artifact) || !expired {
+                continue;
+            }
+
+            let manifest_path = entry.path();
+            let extracted = root.join(format!("{}{}", PREPARED_PREFIX, artifact));
+            let mut bytes = std::fs::metadata(&manifest_path).map_or(0, |m| m.len());
+            if extracted.is_dir() {
+                bytes += dir_size(&extracted);
+                if let Err(e) = std::fs::remove_dir_all(&extracted) {
+                    crate::log!(
+                        Warn,
+                        "Failed to remove {} during cache GC: {}",
+                        extracted.display(),
+                        e
+                    );
+                    continue;
+                }
+            }
+            match std::fs::remove_file(&manifest_path) {
+                Ok(()) => removed.add(bytes),
+                Err(e) => crate::log!(
+                    Warn,
+                    "Failed to remove {} during cache GC: {}",
+                    manifest_path.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Determine the integrity state of a component
+    async fn check_integrity(&self, component: &ComponentSchema) -> Result<ComponentIntegrity> {
+        // A cache entry whose file can't be read counts as missing
+        let data = match self.cache.get(&Self::cache_key(component)).await {
+            Ok(Some(data)) => data,
+            Ok(None) | Err(_) => return Ok(ComponentIntegrity::MissingFromCache),
+        };
+
+        if let Some(expected_hash) = &component.hash {
+            if content_hash(&data) != *expected_hash {
+                return Ok(ComponentIntegrity::HashMismatch);
+            }
+        }
+
+        let prepared = Self::prepared_path(component);
+        let files = if component.kind == "frontend" {
+            let manifest_path = Self::content_manifest_path(component);
+            if !manifest_path.exists() {
+                // Extracted before content manifests were recorded
+                return Ok(ComponentIntegrity::Ok);
+            }
+            ContentManifest::load(&manifest_path)?.diff(&prepared)?
+        } else if prepared.exists() && tokio::fs::read(&prepared).await? != data {
+            vec![component.name.clone()]
+        } else {
+            Vec::new()
+        };
+
+        if files.is_empty() {
+            Ok(ComponentIntegrity::Ok)
+        } else {
+            Ok(ComponentIntegrity::FilesCorrupted { files })
+        }
+    }
+
+    /// Path where the prepared (extracted or written) component lives
+    ///
+    /// Shared components are prepared once per `sharedId` and version.
+    pub fn prepared_path(component: &ComponentSchema) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "{}{}",
+            PREPARED_PREFIX,
+            Self::artifact_name(component)
+        ))
+    }
+
+    /// Path of the content manifest for an extracted frontend
+    fn content_manifest_path(component: &ComponentSchema) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "{}{}{}",
+            PREPARED_PREFIX,
+            Self::artifact_name(component),
+            CONTENT_MANIFEST_SUFFIX
+        ))
+    }
+
+    /// Name of the prepared artifact
+    fn artifact_name(component: &ComponentSchema) -> String {
+        match component.shared_key() {
+            Some(key) => format!("shared-{}-{}", key.shared_id, key.version),
+            None => format!("{}-{}", component.name, component.version),
+        }
+    }
+
     /// Generate cache key for component
+    ///
+    /// Shared components are keyed by `sharedId` so every app referencing
+    /// them resolves to the same cache entry.
     fn cache_key(component: &ComponentSchema) -> String {
-        format!("{}-{}", component.id, component.version)
+        match component.shared_key() {
+            Some(key) => format!("shared-{}-{}", key.shared_id, key.version),
+            None => format!("{}-{}", component.id, component.version),
+        }
     }
 }
 
@@ -274,6 +948,556 @@ pub async fn download_component(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::task::TaskState;
+    use crate::network::bandwidth::{BandwidthPolicy, DeferReason};
+    use crate::storage::SqlStorage;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `body` over HTTP on a local port, returning the base URL
+    async fn spawn_mock_server(body: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    /// Accept connections on a local port but never respond
+    async fn spawn_stalled_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn backend_component(id: String, name: &str) -> ComponentSchema {
+        ComponentSchema {
+            id,
+            name: name.to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: None,
+            shared: false,
+            shared_id: None,
+            // Fixture bodies are not native binaries
+            interpreter: Some("sh".to_string()),
+            extra: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_refuses_binary_for_another_platform() {
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None);
+        let host = binary::BinaryTarget::host();
+        if host.format == binary::ExecutableFormat::Pe && host.arch == "aarch64" {
+            return;
+        }
+
+        // An arm64 Windows binary, foreign to every other host
+        let mut windows = vec![b'M', b'Z'];
+        windows.resize(0x3c, 0);
+        windows.extend(0x40u32.to_le_bytes());
+        windows.extend(b"PE\0\0");
+        windows.extend(0xaa64u16.to_le_bytes());
+        let source = temp.path().join("backend.exe");
+        std::fs::write(&source, &windows).unwrap();
+
+        let mut component = backend_component(
+            format!("file://{}", source.display()),
+            "foreign-platform-test",
+        );
+        component.interpreter = None;
+        component.target = Some("aarch64-pc-windows-msvc".to_string());
+        let prepared = ComponentDownloader::prepared_path(&component);
+        let _ = std::fs::remove_file(&prepared);
+
+        let err = downloader.download(&component).await.unwrap_err();
+        assert!(matches!(err, OsnovaError::MismatchedBinaryTarget { .. }));
+        assert!(err.to_string().contains("PE aarch64"));
+        assert!(!prepared.exists());
+
+        // The same component with a host binary is prepared
+        let source = temp.path().join("backend");
+        std::fs::write(&source, binary::host_stub(b"payload")).unwrap();
+        component.id = format!("file://{}", source.display());
+        component.target = None;
+        let path = downloader.download(&component).await.unwrap();
+        assert!(binary::inspect_file(&path).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_deferred_at_limit_and_resumed() {
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path(), 1024 * 1024).unwrap();
+        let meter = Arc::new(BandwidthMeter::new(
+            SqlStorage::new_in_memory().unwrap(),
+            BandwidthPolicy::DailyLimit { bytes_per_day: 16 },
+        ));
+        let downloader =
+            ComponentDownloader::new(cache, None).with_bandwidth_meter(meter.clone())
+                .with_http(http::local_fetcher());
+
+        let base = spawn_mock_server(b"0123456789abcdef").await;
+        let first = backend_component(format!("{}/first", base), "bandwidth-test-first");
+        let second = backend_component(format!("{}/second", base), "bandwidth-test-second");
+
+        // First download consumes the whole daily allowance
+        let status = downloader.try_download(&first).await.unwrap();
+        assert!(matches!(status, TransferStatus::Completed(_)));
+        assert_eq!(meter.usage().unwrap().today.total, 16);
+
+        // Second download is paused at the limit rather than failing
+        let status = downloader.try_download(&second).await.unwrap();
+        assert_eq!(
+            status,
+            TransferStatus::Deferred(DeferReason::DailyLimitReached {
+                used: 16,
+                limit: 16
+            })
+        );
+
+        // Cached components are still served
+        let status = downloader.try_download(&first).await.unwrap();
+        assert!(matches!(status, TransferStatus::Completed(_)));
+
+        // Lifting the limit lets the deferred download resume
+        meter.set_policy(BandwidthPolicy::Unlimited);
+        let status = downloader.try_download(&second).await.unwrap();
+        assert!(matches!(status, TransferStatus::Completed(_)));
+        assert_eq!(meter.usage().unwrap().today.total, 32);
+    }
+
+    #[tokio::test]
+    async fn test_offline_downloads_served_from_cache_only() {
+        use crate::network::NetworkKillSwitch;
+
+        let temp = TempDir::new().unwrap();
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let http = Arc::new(
+            http::HttpFetcher::new(http::FetchPolicy::default().with_private_host("127.0.0.1"))
+                .with_kill_switch(switch.clone()),
+        );
+        let meter = Arc::new(
+            BandwidthMeter::new(SqlStorage::new_in_memory().unwrap(), BandwidthPolicy::Unlimited)
+                .with_kill_switch(switch.clone()),
+        );
+        let cache = CacheManager::new(temp.path().join("metered"), 1024 * 1024).unwrap();
+        let metered = ComponentDownloader::new(cache, None)
+            .with_bandwidth_meter(meter.clone())
+            .with_http(http.clone());
+        let cache = CacheManager::new(temp.path().join("unmetered"), 1024 * 1024).unwrap();
+        let unmetered = ComponentDownloader::new(cache, None).with_http(http);
+
+        let base = spawn_mock_server(b"0123456789abcdef").await;
+        let cached = backend_component(format!("{}/cached", base), "offline-test-cached");
+        let remote = backend_component(format!("{}/remote", base), "offline-test-remote");
+        metered.try_download(&cached).await.unwrap();
+        switch.engage();
+
+        // Cached components are still served
+        let status = metered.try_download(&cached).await.unwrap();
+        assert!(matches!(status, TransferStatus::Completed(_)));
+
+        // Queued downloads wait; direct ones fail without a request
+        let status = metered.try_download(&remote).await.unwrap();
+        assert_eq!(status, TransferStatus::Deferred(DeferReason::OfflineByUser));
+        let err = unmetered.download(&remote).await.unwrap_err();
+        assert!(matches!(err, OsnovaError::OfflineMode { .. }), "{:?}", err);
+        assert_eq!(meter.usage().unwrap().today.total, 16);
+
+        switch.release();
+        let status = metered.try_download(&remote).await.unwrap();
+        assert!(matches!(status, TransferStatus::Completed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_download_refused_when_disk_space_low() {
+        use crate::platform::disk::FixedDiskSpace;
+
+        const MIB: u64 = 1024 * 1024;
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let guard = DiskGuard::new(50 * MIB).with_provider(Arc::new(FixedDiskSpace::new(100 * MIB)));
+        let downloader = ComponentDownloader::new(cache, None).with_disk_guard(guard);
+
+        // Unknown size: the conservative estimate does not fit
+        let base = spawn_mock_server(b"0123456789abcdef").await;
+        let remote = backend_component(format!("{}/remote", base), "disk-test-remote");
+        let err = downloader.download(&remote).await.unwrap_err();
+        match err {
+            OsnovaError::DiskSpaceLow {
+                available, required, ..
+            } => {
+                assert_eq!(available, 100 * MIB);
+                assert_eq!(required, 50 * MIB + DEFAULT_DOWNLOAD_ESTIMATE);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        // Known size: a small local file fits
+        let source = temp.path().join("backend.bin");
+        std::fs::write(&source, b"binary").unwrap();
+        let local = backend_component(format!("file://{}", source.display()), "disk-test-local");
+        downloader.download(&local).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_times_out_on_stalled_server() {
+        use std::time::Duration;
+
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path(), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_http(http::local_fetcher());
+
+        let base = spawn_stalled_server().await;
+        let component = backend_component(format!("{}/stalled", base), "timeout-test");
+        let options = NetworkOptions::default().with_timeout(Duration::from_millis(100));
+
+        let result = downloader.download_with(&component, &options).await;
+        match result {
+            Err(OsnovaError::Timeout { after, .. }) => {
+                assert_eq!(after, Duration::from_millis(100))
+            }
+            other => panic!("Expected timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gc_removes_extracted_frontends_of_uninstalled_apps() {
+        let temp = TempDir::new().unwrap();
+        let prepared = temp.path().join("prepared");
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None);
+        let installed = backend_component("installed".to_string(), "kept");
+
+        for artifact in ["kept-1.0.0", "gone-1.0.0"] {
+            let dir = prepared.join(format!("osnova-{}", artifact));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("index.html"), [0u8; 90]).unwrap();
+            std::fs::write(prepared.join(format!("osnova-{}.content.json", artifact)), b"{}")
+                .unwrap();
+        }
+        // Prepared binaries carry no manifest, so their origin is unknown
+        std::fs::write(prepared.join("osnova-stray-1.0.0"), b"binary").unwrap();
+
+        let report = downloader
+            .gc_in(None, &prepared, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(report.orphaned_directories, GcCategory::default());
+
+        let report = downloader
+            .gc_in(Some(&[installed]), &prepared, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(report.orphaned_directories, GcCategory { count: 1, bytes: 92 });
+        assert!(prepared.join("osnova-kept-1.0.0/index.html").exists());
+        assert!(!prepared.join("osnova-gone-1.0.0").exists());
+        assert!(!prepared.join("osnova-gone-1.0.0.content.json").exists());
+        assert!(prepared.join("osnova-stray-1.0.0").exists());
+
+        // Collections show as tasks
+        let tasks = Arc::new(TaskRegistry::new());
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_tasks(tasks.clone());
+        downloader.gc(None).await.unwrap();
+        let task = &tasks.history(1)[0];
+        assert_eq!(task.category, TaskCategory::CacheGc);
+        assert_eq!(task.state, TaskState::Completed);
+        assert_eq!(task.message.as_deref(), Some("Freed 0 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_download_cleans_up() {
+        use std::time::{Duration, Instant};
+
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path(), 1024 * 1024).unwrap();
+        let tasks = Arc::new(TaskRegistry::new());
+        let downloader = ComponentDownloader::new(cache, None)
+            .with_http(http::local_fetcher())
+            .with_tasks(tasks.clone());
+
+        let base = spawn_stalled_server().await;
+        let component = backend_component(format!("{}/stalled", base), "cancel-test");
+
+        // Leftover from an earlier interrupted attempt
+        let prepared = ComponentDownloader::prepared_path(&component);
+        std::fs::write(&prepared, b"partial").unwrap();
+
+        // Cancelled from the activity center once the fetch shows up
+        let token = CancellationToken::new();
+        let options = NetworkOptions::default().with_cancellation(token.clone());
+        let registry = tasks.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Some(task) = registry.list_active().first() {
+                    registry.cancel(&task.id).unwrap();
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let started = Instant::now();
+        let result = downloader.download_with(&component, &options).await;
+
+        assert!(matches!(result, Err(OsnovaError::Cancelled { .. })));
+        assert!(token.is_cancelled());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!prepared.exists());
+        assert!(!downloader.quick_check(&component).await);
+
+        let task = &tasks.history(1)[0];
+        assert_eq!(task.name, "Download cancel-test");
+        assert_eq!(task.state, TaskState::Cancelled);
+        assert!(tasks.list_active().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_applies_default_options() {
+        use crate::network::DEFAULT_NETWORK_TIMEOUT;
+
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path(), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_http(http::local_fetcher());
+
+        // Plain download goes through the same pipeline with the defaults
+        let base = spawn_mock_server(b"default-options").await;
+        let component = backend_component(format!("{}/default", base), "default-options-test");
+        let path = downloader.download(&component).await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"default-options");
+        assert_eq!(NetworkOptions::default().timeout, DEFAULT_NETWORK_TIMEOUT);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_warm_ups_promoted_when_installed_apps_use_them() {
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path(), 1024 * 1024).unwrap();
+        let warming = ComponentDownloader::new(cache.clone(), None)
+            .with_http(http::local_fetcher())
+            .with_eviction_class(EvictionClass::Opportunistic);
+        let downloader =
+            ComponentDownloader::new(cache.clone(), None).with_http(http::local_fetcher());
+
+        let base = spawn_mock_server(b"warmed").await;
+        let component = backend_component(format!("{}/warmed", base), "eviction-class-test");
+        let class = || async { cache.list_entries().await[0].class };
+
+        let path = warming.download(&component).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(class().await, EvictionClass::Opportunistic);
+
+        let path = downloader.download(&component).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(class().await, EvictionClass::Critical);
+
+        // A warm-up never demotes what an installed app uses
+        let path = warming.download(&component).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(class().await, EvictionClass::Critical);
+    }
+
+    #[tokio::test]
+    async fn test_https_download_records_provenance() {
+        use crate::services::ProvenanceService;
+
+        let temp = TempDir::new().unwrap();
+        let provenance = Arc::new(ProvenanceService::new(temp.path()).unwrap());
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_provenance(provenance.clone())
+            .with_http(http::local_fetcher());
+
+        let base = spawn_mock_server(b"provenance-body").await;
+        let component = backend_component(format!("{}/backend", base), "provenance-https-test");
+        let path = downloader.download(&component).await.unwrap();
+
+        let hash = content_hash(b"provenance-body");
+        let records = provenance.for_component(&hash).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].component_id, component.id);
+        assert_eq!(records[0].source_uri, component.id);
+        assert_eq!(records[0].component_version, "1.0.0");
+        assert_eq!(records[0].verification, VerificationOutcome::NoHashDeclared);
+        assert_eq!(records[0].downloader_version, DOWNLOADER_VERSION);
+
+        // Cache hits are not new downloads
+        downloader.download(&component).await.unwrap();
+        assert_eq!(provenance.for_component(&hash).unwrap().len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Write a frontend tarball of `(path, mode, contents)` entries
+    fn frontend_tarball(path: &Path, files: &[(&str, u32, &[u8])]) -> ComponentSchema {
+        let encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, mode, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        ComponentSchema {
+            kind: "frontend".to_string(),
+            platform: Some("desktop".to_string()),
+            ..backend_component(format!("file://{}", path.display()), "content-policy-test")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_refuses_frontend_with_executable_content() {
+        use crate::components::content_policy::{ContentFlag, ALLOWED_EXECUTABLES_CONFIG_KEY};
+        use crate::services::ProvenanceService;
+
+        let temp = TempDir::new().unwrap();
+        let provenance = Arc::new(ProvenanceService::new(temp.path()).unwrap());
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_provenance(provenance.clone());
+        let helper: &[u8] = b"#!/bin/sh\necho helper\n";
+        let mut component = frontend_tarball(
+            &temp.path().join("bundle.tar.gz"),
+            &[
+                ("index.html", 0o644, b"<html></html>"),
+                ("bin/helper", 0o755, helper),
+                ("lib/native.so", 0o644, b"library"),
+            ],
+        );
+
+        let err = downloader.download(&component).await.unwrap_err();
+        let OsnovaError::ExecutableContent { paths, .. } = err else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(paths, vec!["bin/helper", "lib/native.so"]);
+        assert!(!ComponentDownloader::prepared_path(&component).exists());
+        assert!(!ComponentDownloader::content_manifest_path(&component).exists());
+
+        // The failed check is kept with the provenance of the fetch
+        let hash = content_hash(&std::fs::read(temp.path().join("bundle.tar.gz")).unwrap());
+        let records = provenance.for_component(&hash).unwrap();
+        let check = records[0].content_check.clone().unwrap();
+        assert_eq!(check.offending(), paths);
+        assert_eq!(
+            check.findings[0].flags,
+            [ContentFlag::Script, ContentFlag::ExecutableMode]
+        );
+
+        // Allowing the script with a pinned hash leaves only the library
+        let allowed = serde_json::json!([{ "path": "bin/helper", "hash": content_hash(helper) }]);
+        component.config = Some(std::collections::HashMap::from([(
+            ALLOWED_EXECUTABLES_CONFIG_KEY.to_string(),
+            allowed,
+        )]));
+        let err = downloader.download(&component).await.unwrap_err();
+        assert!(matches!(
+            err,
+            OsnovaError::ExecutableContent { ref paths, .. } if paths == &["lib/native.so"]
+        ));
+
+        // A report-only policy installs, stripping executable bits
+        let component = frontend_tarball(
+            &temp.path().join("bundle.tar.gz"),
+            &[("index.html", 0o644, b"<html></html>"), ("app.js", 0o755, b"1")],
+        );
+        let downloader = ComponentDownloader::new(
+            CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap(),
+            None,
+        )
+        .with_content_policy(ContentPolicy::new().report_only());
+        let path = downloader.download(&component).await.unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(path.join("app.js")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0);
+        }
+        downloader.remove(&component).await.unwrap();
+    }
+
+    #[test]
+    fn test_provenance_record_for_ant_source() {
+        let data = b"network bytes";
+        let mut component = backend_component(
+            "ant://0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".to_string(),
+            "provenance-ant-test",
+        );
+        component.hash = Some(content_hash(data));
+        let origin = ManifestOrigin {
+            manifest_id: "ant://manifest".to_string(),
+            manifest_version: "3.0.0".to_string(),
+            publisher: Some("osnova-labs".to_string()),
+            signature: Some("sig".into()),
+        };
+
+        let record = ComponentDownloader::provenance_record(&component, data, Some(&origin), 42);
+
+        assert_eq!(record.source_uri, component.id);
+        assert_eq!(record.component_hash, content_hash(data));
+        assert_eq!(record.downloaded_at, 42);
+        assert_eq!(record.verification, VerificationOutcome::HashVerified);
+        assert_eq!(record.publisher(), Some("osnova-labs"));
+        assert_eq!(record.origin, Some(origin));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_symbols_from_declared_uri() {
+        let temp = TempDir::new().unwrap();
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader = ComponentDownloader::new(cache, None).with_http(http::local_fetcher());
+        let url = spawn_mock_server(b"MODULE Linux x86_64 0 backend\n").await;
+
+        let mut component = backend_component("ant://backend".to_string(), "symbols-fetch-test");
+        let symbols_path = symbols::symbol_path(&ComponentDownloader::prepared_path(&component));
+        let _ = std::fs::remove_file(&symbols_path);
+        assert_eq!(downloader.fetch_symbols(&component).await.unwrap(), None);
+
+        component.config = Some(std::collections::HashMap::from([(
+            symbols::SYMBOLS_CONFIG_KEY.to_string(),
+            serde_json::json!(format!("{}/backend.sym", url)),
+        )]));
+        let fetched = downloader.fetch_symbols(&component).await.unwrap();
+        assert_eq!(fetched.as_ref(), Some(&symbols_path));
+        assert!(std::fs::read(&symbols_path).unwrap().starts_with(b"MODULE "));
+
+        // Kept for next time, and removed with the component
+        assert_eq!(ComponentDownloader::local_symbols(&component), fetched);
+        downloader.remove(&component).await.unwrap();
+        assert!(!symbols_path.exists());
+    }
 
     #[test]
     fn test_cache_key() {
@@ -286,16 +1510,45 @@ mod tests {
             version: "1.0.0".to_string(),
             hash: None,
             config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
         };
 
         let key = ComponentDownloader::cache_key(&component);
         assert_eq!(key, "test-id-1.0.0");
     }
 
+    #[test]
+    fn test_shared_cache_key_ignores_app_specific_fields() {
+        let mut first = backend_component("ant://app-a/sync".to_string(), "Sync");
+        first.hash = Some("abc".to_string());
+        first.shared = true;
+        first.shared_id = Some("org.autonomi.sync-agent".to_string());
+
+        let mut second = first.clone();
+        second.id = "ant://app-b/sync".to_string();
+        second.name = "Sync Agent".to_string();
+
+        assert_eq!(
+            ComponentDownloader::cache_key(&first),
+            "shared-org.autonomi.sync-agent-1.0.0"
+        );
+        assert_eq!(
+            ComponentDownloader::cache_key(&first),
+            ComponentDownloader::cache_key(&second)
+        );
+        assert_eq!(
+            ComponentDownloader::prepared_path(&first),
+            ComponentDownloader::prepared_path(&second)
+        );
+    }
+
     #[test]
     fn test_verify_hash_success() {
         let data = b"test data";
-        let mut hasher = Hasher::new();
+        let mut hasher = blake3::Hasher::new();
         hasher.update(data);
         let hash = hasher.finalize();
         let hash_b64 =
@@ -0,0 +1,467 @@
+//! # Frontend Entry Points
+//!
+//! Find the page an extracted frontend bundle opens with. Build tools nest
+//! their output (`dist/`, `build/`), and tarballs made from a folder wrap
+//! everything in one top-level directory, so `index.html` is often not at
+//! the root of the bundle.
+//!
+//! Handles:
+//! - An explicit `entry` in the component config (e.g. `"dist/index.html"`)
+//! - Searching the root, `dist/`, `build/` and `public/` for `index.html`,
+//!   then the same locations inside a single top-level directory
+//! - Warning about absolute (`/` or `file://`) references in the entry page,
+//!   which do not resolve once the bundle is served from its own root
+//!
+//! Entry paths are relative to the bundle root and use `/` separators.
+
+use crate::components::ComponentDownloader;
+use crate::error::{OsnovaError, Result};
+use crate::manifest::ComponentSchema;
+use crate::models::application::{ComponentKind, OsnovaApplication};
+use std::path::{Component, Path, PathBuf};
+
+/// Component config key that names the entry page
+pub const ENTRY_CONFIG_KEY: &str = "entry";
+
+/// Page the resolver looks for when no entry is declared
+pub const DEFAULT_ENTRY_FILE: &str = "index.html";
+
+/// Directories searched for [`DEFAULT_ENTRY_FILE`], in order (`""` is the
+/// bundle root)
+pub const ENTRY_SEARCH_DIRS: &[&str] = &["", "dist", "build", "public"];
+
+/// Most bundle paths listed when no entry is found
+const MAX_LISTED_PATHS: usize = 20;
+
+/// HTML attributes that reference other assets
+const REFERENCE_ATTRIBUTES: &[&str] = &["src", "href", "srcset", "poster", "action", "data"];
+
+/// Entry page resolved for an extracted frontend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedEntry {
+    /// Path of the entry page, relative to the bundle root
+    pub path: String,
+    /// Problems with the entry page that do not fail the install
+    pub warnings: Vec<String>,
+}
+
+/// Check a declared entry is a relative path inside the bundle
+///
+/// # Errors
+///
+/// Returns an error message for an empty or absolute path, or one that
+/// leaves the bundle with `..`.
+pub fn validate_entry(entry: &str) -> std::result::Result<(), String> {
+    if entry.trim().is_empty() {
+        return Err("entry must not be empty".to_string());
+    }
+    if entry.starts_with('/') || entry.starts_with('\\') || entry.contains(':') {
+        return Err(format!(
+            "entry '{}' must be a path relative to the bundle root",
+            entry
+        ));
+    }
+    let inside = Path::new(entry)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(format!("entry '{}' must stay inside the bundle", entry));
+    }
+    Ok(())
+}
+
+/// Resolve the entry page of the bundle extracted at `root`
+///
+/// A declared entry must exist. Without one, the first `index.html` in
+/// [`ENTRY_SEARCH_DIRS`] wins, then the same search inside the bundle's
+/// only top-level directory, if it has nothing else.
+///
+/// # Errors
+///
+/// Returns an error naming the bundle's contents if the declared entry is
+/// missing or no `index.html` is found.
+pub fn resolve_entry(root: &Path, declared: Option<&str>) -> Result<ResolvedEntry> {
+    let path = match declared {
+        Some(entry) => {
+            validate_entry(entry).map_err(OsnovaError::Other)?;
+            let entry = normalize(entry);
+            if !root.join(&entry).is_file() {
+                return Err(OsnovaError::Other(format!(
+                    "Declared entry '{}' is not in the bundle; found: {}",
+                    entry,
+                    describe_contents(root)
+                )));
+            }
+            entry
+        }
+        None => find_entry(root)?.ok_or_else(|| {
+            OsnovaError::Other(format!(
+                "No {} found in the bundle root, dist/, build/, public/ or a single \
+                 top-level directory; found: {}. Set `{}` in the component config",
+                DEFAULT_ENTRY_FILE,
+                describe_contents(root),
+                ENTRY_CONFIG_KEY
+            ))
+        })?,
+    };
+
+    let html = std::fs::read_to_string(root.join(&path))?;
+    let warnings = absolute_references(&html)
+        .into_iter()
+        .map(|reference| {
+            format!(
+                "{} references '{}' by absolute path; it will not load from the bundle, \
+                 use a relative path",
+                path, reference
+            )
+        })
+        .collect();
+
+    Ok(ResolvedEntry { path, warnings })
+}
+
+/// Resolve and record the entry page of each extracted frontend of `app`
+///
+/// Frontends that have not been extracted keep the entry they had.
+/// Returns the warnings about each entry page.
+///
+/// # Errors
+///
+/// Returns an error naming the component if an entry cannot be resolved.
+pub fn record_entries(app: &mut OsnovaApplication) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    for component in app.components_mut() {
+        if component.kind() != ComponentKind::Frontend {
+            continue;
+        }
+        let schema = ComponentSchema::from(&*component);
+        let root = ComponentDownloader::prepared_path(&schema);
+        if !root.is_dir() {
+            continue;
+        }
+
+        let entry = resolve_entry(&root, schema.declared_entry())
+            .map_err(|e| OsnovaError::Other(format!("Frontend {}: {}", schema.name, e)))?;
+        warnings.extend(
+            entry
+                .warnings
+                .iter()
+                .map(|warning| format!("Frontend {}: {}", schema.name, warning)),
+        );
+        *component = component.clone().with_entry(entry.path);
+    }
+    Ok(warnings)
+}
+
+/// Asset references in `html` that are absolute (`/...` or `file://...`)
+///
+/// Protocol-relative (`//host/...`) and other URLs are left alone, as they
+/// never pointed into the bundle.
+pub fn absolute_references(html: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    let lower = html.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+
+    let mut start = 0;
+    while let Some(offset) = lower[start..].find('=') {
+        let equals = start + offset;
+        start = equals + 1;
+
+        let name_end = lower[..equals].trim_end().len();
+        let name_start = lower[..name_end]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .map_or(0, |i| i + 1);
+        if !REFERENCE_ATTRIBUTES.contains(&&lower[name_start..name_end]) {
+            continue;
+        }
+
+        let rest = &lower[equals + 1..];
+        let value_start = equals + 1 + (rest.len() - rest.trim_start().len());
+        let (value_start, value_end) = match bytes.get(value_start) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end = lower[value_start + 1..]
+                    .find(quote as char)
+                    .map_or(lower.len(), |i| value_start + 1 + i);
+                (value_start + 1, end)
+            }
+            Some(_) => {
+                let end = lower[value_start..]
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .map_or(lower.len(), |i| value_start + i);
+                (value_start, end)
+            }
+            None => continue,
+        };
+
+        let value = html[value_start..value_end].trim();
+        let value_lower = lower[value_start..value_end].trim();
+        let absolute = (value.starts_with('/') && !value.starts_with("//"))
+            || value_lower.starts_with("file://");
+        if absolute && !references.iter().any(|r| r == value) {
+            references.push(value.to_string());
+        }
+        start = value_end;
+    }
+
+    references
+}
+
+/// Search the usual locations, then the same inside a wrapper directory
+fn find_entry(root: &Path) -> Result<Option<String>> {
+    if let Some(entry) = search(root, None) {
+        return Ok(Some(entry));
+    }
+    // `tar -czf app.tar.gz app/` puts everything under `app/`
+    Ok(wrapper_directory(root)?.and_then(|wrapper| search(root, Some(&wrapper))))
+}
+
+/// First `index.html` in [`ENTRY_SEARCH_DIRS`] under `prefix`
+fn search(root: &Path, prefix: Option<&str>) -> Option<String> {
+    ENTRY_SEARCH_DIRS.iter().find_map(|dir| {
+        let entry = [prefix.unwrap_or(""), dir, DEFAULT_ENTRY_FILE]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("/");
+        root.join(&entry).is_file().then_some(entry)
+    })
+}
+
+/// Name of the bundle's only top-level entry, if it is a directory
+///
+/// Hidden files (`.DS_Store` and the like) are ignored.
+fn wrapper_directory(root: &Path) -> Result<Option<String>> {
+    let mut visible = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with('.') {
+            visible.push((name, entry.path()));
+        }
+    }
+    Ok(match visible.as_slice() {
+        [(name, path)] if path.is_dir() => Some(name.clone()),
+        _ => None,
+    })
+}
+
+/// Summary of a bundle for error messages
+///
+/// Lists HTML files anywhere in the bundle if there are any, since one of
+/// them is likely the intended entry, and the top-level entries otherwise.
+fn describe_contents(root: &Path) -> String {
+    let mut html = Vec::new();
+    collect_html(root, root, &mut html);
+    html.sort();
+
+    let listed = if html.is_empty() {
+        let mut top = std::fs::read_dir(root)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        if entry.path().is_dir() {
+                            format!("{}/", name)
+                        } else {
+                            name
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        top.sort();
+        top
+    } else {
+        html
+    };
+
+    if listed.is_empty() {
+        return "an empty bundle".to_string();
+    }
+    let mut summary = listed
+        .iter()
+        .take(MAX_LISTED_PATHS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if listed.len() > MAX_LISTED_PATHS {
+        summary.push_str(&format!(" and {} more", listed.len() - MAX_LISTED_PATHS));
+    }
+    summary
+}
+
+/// Collect the paths of HTML files under `dir`, relative to `root`
+fn collect_html(root: &Path, dir: &Path, found: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_html(root, &path, found);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+        {
+            if let Ok(relative) = path.strip_prefix(root) {
+                found.push(to_entry_path(relative));
+            }
+        }
+    }
+}
+
+/// Drop `.` segments and use `/` separators
+fn normalize(entry: &str) -> String {
+    to_entry_path(Path::new(entry))
+}
+
+/// Render a relative path with `/` separators
+fn to_entry_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Absolute path of the entry page of a bundle extracted at `root`
+pub fn entry_file(root: &Path, entry: &str) -> PathBuf {
+    entry
+        .split('/')
+        .fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Create a bundle with `files` (paths relative to the root)
+    fn bundle(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, contents) in files {
+            let path = entry_file(dir.path(), path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_declared_entry_is_honored() {
+        // A root index.html does not win over the declared entry
+        let dir = bundle(&[
+            ("index.html", "<html></html>"),
+            ("app/main.html", "<html></html>"),
+        ]);
+
+        let entry = resolve_entry(dir.path(), Some("./app/main.html")).unwrap();
+        assert_eq!(entry.path, "app/main.html");
+        assert!(entry.warnings.is_empty());
+
+        let error = resolve_entry(dir.path(), Some("dist/index.html")).unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.contains("Declared entry 'dist/index.html'"),
+            "{}",
+            message
+        );
+        assert!(message.contains("app/main.html"), "{}", message);
+
+        assert!(resolve_entry(dir.path(), Some("../index.html")).is_err());
+        assert!(validate_entry("/index.html").is_err());
+        assert!(validate_entry("C:/index.html").is_err());
+        assert!(validate_entry(" ").is_err());
+        assert!(validate_entry("dist/index.html").is_ok());
+    }
+
+    #[test]
+    fn test_heuristic_locations() {
+        for dir in ENTRY_SEARCH_DIRS {
+            let expected = if dir.is_empty() {
+                DEFAULT_ENTRY_FILE.to_string()
+            } else {
+                format!("{}/{}", dir, DEFAULT_ENTRY_FILE)
+            };
+            let root = bundle(&[(&expected, "<html></html>"), ("favicon.ico", "")]);
+
+            let entry = resolve_entry(root.path(), None).unwrap();
+            assert_eq!(entry.path, expected);
+        }
+
+        // Earlier locations win
+        let root = bundle(&[
+            ("public/index.html", "<html></html>"),
+            ("dist/index.html", "<html></html>"),
+        ]);
+        assert_eq!(
+            resolve_entry(root.path(), None).unwrap().path,
+            "dist/index.html"
+        );
+    }
+
+    #[test]
+    fn test_single_wrapper_directory_is_unwrapped() {
+        let root = bundle(&[
+            ("my-app/dist/index.html", "<html></html>"),
+            ("my-app/README.md", ""),
+            (".DS_Store", ""),
+        ]);
+        let entry = resolve_entry(root.path(), None).unwrap();
+        assert_eq!(entry.path, "my-app/dist/index.html");
+
+        // Two top-level directories are not a wrapper
+        let root = bundle(&[("a/index.html", ""), ("b/other.txt", "")]);
+        assert!(resolve_entry(root.path(), None).is_err());
+    }
+
+    #[test]
+    fn test_missing_entry_lists_contents() {
+        let root = bundle(&[("src/pages/home.html", ""), ("README.md", "")]);
+        let message = resolve_entry(root.path(), None).unwrap_err().to_string();
+        assert!(message.contains("No index.html found"), "{}", message);
+        assert!(
+            message.contains("found: src/pages/home.html"),
+            "{}",
+            message
+        );
+        assert!(message.contains("`entry`"), "{}", message);
+
+        // Without HTML files, the top level is listed
+        let root = bundle(&[("data.bin", ""), ("lib/code.js", "")]);
+        let message = resolve_entry(root.path(), None).unwrap_err().to_string();
+        assert!(message.contains("found: data.bin, lib/"), "{}", message);
+    }
+
+    #[test]
+    fn test_absolute_references_warn() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/assets/style.css">
+            <script type="module" src='file:///home/dev/app.js'></script>
+            <script src=./main.js></script>
+            <script src="//cdn.example.com/lib.js"></script>
+            <img src=/logo.png alt="a = /b">
+            <a href="https://example.com/">x</a>
+        </head></html>"#;
+        assert_eq!(
+            absolute_references(html),
+            vec!["/assets/style.css", "file:///home/dev/app.js", "/logo.png"]
+        );
+
+        let root = bundle(&[("index.html", html)]);
+        let entry = resolve_entry(root.path(), None).unwrap();
+        assert_eq!(entry.warnings.len(), 3);
+        assert!(entry.warnings[0].contains("'/assets/style.css'"));
+        assert!(entry.warnings[0].contains("relative path"));
+
+        let root = bundle(&[("index.html", r#"<script src="assets/app.js"></script>"#)]);
+        assert!(resolve_entry(root.path(), None)
+            .unwrap()
+            .warnings
+            .is_empty());
+    }
+}
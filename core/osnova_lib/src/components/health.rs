@@ -0,0 +1,374 @@
+//! # Backend Health Checks
+//!
+//! A backend can wedge without exiting (a deadlocked event loop, leaked
+//! connections), which the process watchdog cannot see. Backends declare
+//! how the core checks on them in their `config.health`:
+//!
+//! ```json
+//! "health": {
+//!     "probe": "rpc",
+//!     "method": "component.health",
+//!     "intervalSecs": 30,
+//!     "timeoutSecs": 5,
+//!     "failureThreshold": 3
+//! }
+//! ```
+//!
+//! Probes:
+//! - `rpc` - call a JSON-RPC method over the backend's socket; an error
+//!   response or a `false` result fails the check
+//! - `tcp` - connect to a local TCP `port`
+//! - `socket` - connect to the backend's socket
+//!
+//! What happens to a backend failing its checks is up to the
+//! [`HealthMonitor`](crate::services::health::HealthMonitor).
+
+use crate::error::{OsnovaError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+
+/// Component config key declaring a backend's health check
+pub const HEALTH_CONFIG_KEY: &str = "health";
+
+/// RPC method called when a backend declares an `rpc` probe without one
+pub const DEFAULT_HEALTH_METHOD: &str = "component.health";
+
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// How the core checks on a backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "probe", rename_all = "camelCase")]
+pub enum HealthProbe {
+    /// Call a JSON-RPC method over the backend's socket
+    Rpc {
+        /// Method to call
+        #[serde(default = "default_method")]
+        method: String,
+    },
+    /// Connect to a local TCP port
+    Tcp {
+        /// Port the backend listens on
+        port: u16,
+    },
+    /// Connect to the backend's socket
+    Socket,
+}
+
+fn default_method() -> String {
+    DEFAULT_HEALTH_METHOD.to_string()
+}
+
+/// A backend's declared health check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// How the backend is checked
+    #[serde(flatten)]
+    pub probe: HealthProbe,
+    /// Seconds between checks
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Seconds a check may take before it fails
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Consecutive failed checks that make the backend unhealthy
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+fn default_interval_secs() -> u64 {
+    DEFAULT_INTERVAL_SECS
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_failure_threshold() -> u32 {
+    DEFAULT_FAILURE_THRESHOLD
+}
+
+impl HealthCheck {
+    /// Parse a `config.health` value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is not a health check, or fails
+    /// [`validate`](Self::validate)
+    pub fn from_config(value: &Value) -> std::result::Result<Self, String> {
+        let check: Self =
+            serde_json::from_value(value.clone()).map_err(|e| format!("health: {}", e))?;
+        check.validate()?;
+        Ok(check)
+    }
+
+    /// Check the probe and its timing
+    ///
+    /// RPC methods are dotted names (`component.health`), ports are
+    /// non-zero, intervals and timeouts at least a second with the timeout
+    /// no longer than the interval, and the threshold at least one.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match &self.probe {
+            HealthProbe::Rpc { method } if !is_method_name(method) => {
+                return Err(format!(
+                    "health method '{}' must be a dotted name such as '{}'",
+                    method, DEFAULT_HEALTH_METHOD
+                ));
+            }
+            HealthProbe::Tcp { port: 0 } => {
+                return Err("health port must be between 1 and 65535".to_string());
+            }
+            _ => {}
+        }
+        if self.interval_secs == 0 || self.timeout_secs == 0 {
+            return Err("health intervalSecs and timeoutSecs must be at least 1".to_string());
+        }
+        if self.timeout_secs > self.interval_secs {
+            return Err("health timeoutSecs must not exceed intervalSecs".to_string());
+        }
+        if self.failure_threshold == 0 {
+            return Err("health failureThreshold must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Time between checks
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    /// Time a check may take
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Whether a method name is dotted segments of letters, digits and `_`,
+/// each starting with a letter
+fn is_method_name(method: &str) -> bool {
+    let segments: Vec<&str> = method.split('.').collect();
+    segments.len() >= 2
+        && segments.iter().all(|segment| {
+            let mut chars = segment.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Run one check of a backend listening on `socket_path`
+///
+/// # Errors
+///
+/// Returns an error describing why the check failed, including a
+/// [`OsnovaError::Timeout`] when it took longer than the check allows
+pub async fn probe(check: &HealthCheck, socket_path: &Path) -> Result<()> {
+    let run = async {
+        match &check.probe {
+            HealthProbe::Rpc { method } => call(socket_path, method).await,
+            HealthProbe::Tcp { port } => {
+                tokio::net::TcpStream::connect(("127.0.0.1", *port)).await?;
+                Ok(())
+            }
+            HealthProbe::Socket => connect(socket_path).await.map(|_| ()),
+        }
+    };
+    tokio::time::timeout(check.timeout(), run)
+        .await
+        .map_err(|_| OsnovaError::Timeout {
+            operation: "health check".to_string(),
+            after: check.timeout(),
+        })?
+}
+
+/// Call a method over the backend's socket and check the response
+async fn call(socket_path: &Path, method: &str) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let mut stream = BufReader::new(connect(socket_path).await?);
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method});
+    let mut line = serde_json::to_vec(&request)?;
+    line.push(b'\n');
+    stream.get_mut().write_all(&line).await?;
+
+    let mut response = String::new();
+    if stream.read_line(&mut response).await? == 0 {
+        return Err(OsnovaError::Network(
+            "backend closed the connection".to_string(),
+        ));
+    }
+    let response: Value = serde_json::from_str(&response)?;
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("error response");
+        return Err(OsnovaError::Network(format!(
+            "{} failed: {}",
+            method, message
+        )));
+    }
+    match response.get("result") {
+        Some(Value::Bool(false)) => Err(OsnovaError::Network(format!(
+            "{} reported unhealthy",
+            method
+        ))),
+        Some(_) => Ok(()),
+        None => Err(OsnovaError::Network(format!(
+            "{} returned no result",
+            method
+        ))),
+    }
+}
+
+#[cfg(unix)]
+async fn connect(socket_path: &Path) -> Result<tokio::net::UnixStream> {
+    Ok(tokio::net::UnixStream::connect(socket_path).await?)
+}
+
+#[cfg(not(unix))]
+async fn connect(socket_path: &Path) -> Result<tokio::net::TcpStream> {
+    Err(OsnovaError::Network(format!(
+        "backend sockets are not supported on this platform: {}",
+        socket_path.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(value: Value) -> std::result::Result<HealthCheck, String> {
+        HealthCheck::from_config(&value)
+    }
+
+    #[test]
+    fn test_declarations() {
+        let rpc = check(serde_json::json!({"probe": "rpc"})).unwrap();
+        assert_eq!(
+            rpc,
+            HealthCheck {
+                probe: HealthProbe::Rpc {
+                    method: DEFAULT_HEALTH_METHOD.to_string()
+                },
+                interval_secs: 30,
+                timeout_secs: 5,
+                failure_threshold: 3,
+            }
+        );
+        let tcp = check(serde_json::json!({
+            "probe": "tcp", "port": 8080, "intervalSecs": 10, "failureThreshold": 1
+        }))
+        .unwrap();
+        assert_eq!(tcp.probe, HealthProbe::Tcp { port: 8080 });
+        assert_eq!(tcp.interval(), Duration::from_secs(10));
+
+        for (value, expected) in [
+            (
+                serde_json::json!({"probe": "rpc", "method": "health"}),
+                "dotted name",
+            ),
+            (
+                serde_json::json!({"probe": "rpc", "method": "a..b"}),
+                "dotted name",
+            ),
+            (
+                serde_json::json!({"probe": "rpc", "method": "1x.health"}),
+                "dotted name",
+            ),
+            (
+                serde_json::json!({"probe": "tcp", "port": 0}),
+                "between 1 and 65535",
+            ),
+            (
+                serde_json::json!({"probe": "tcp", "port": 70000}),
+                "health:",
+            ),
+            (serde_json::json!({"probe": "tcp"}), "health:"),
+            (serde_json::json!({"probe": "ping"}), "health:"),
+            (
+                serde_json::json!({"probe": "socket", "intervalSecs": 0}),
+                "at least 1",
+            ),
+            (
+                serde_json::json!({"probe": "socket", "timeoutSecs": 60}),
+                "must not exceed",
+            ),
+            (
+                serde_json::json!({"probe": "socket", "failureThreshold": 0}),
+                "at least 1",
+            ),
+        ] {
+            let error = check(value.clone()).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", value, error);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_probes_against_stub_backend() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let socket = temp.path().join("backend.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let healthy = Arc::new(AtomicBool::new(true));
+        let answers = healthy.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut stream = BufReader::new(stream);
+                let mut request = String::new();
+                if stream.read_line(&mut request).await.unwrap_or(0) == 0 {
+                    continue;
+                }
+                let response = if answers.load(Ordering::SeqCst) {
+                    serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": {"ok": true}})
+                } else {
+                    serde_json::json!({
+                        "jsonrpc": "2.0", "id": 1,
+                        "error": {"code": -32000, "message": "event loop stalled"}
+                    })
+                };
+                let mut line = response.to_string();
+                line.push('\n');
+                let _ = stream.get_mut().write_all(line.as_bytes()).await;
+            }
+        });
+
+        let rpc = check(serde_json::json!({"probe": "rpc"})).unwrap();
+        probe(&rpc, &socket).await.unwrap();
+        healthy.store(false, Ordering::SeqCst);
+        let error = probe(&rpc, &socket).await.unwrap_err();
+        assert!(
+            error.to_string().contains("event loop stalled"),
+            "{}",
+            error
+        );
+
+        let socket_check = check(serde_json::json!({"probe": "socket"})).unwrap();
+        probe(&socket_check, &socket).await.unwrap();
+        assert!(probe(&socket_check, &temp.path().join("missing.sock"))
+            .await
+            .is_err());
+
+        // A backend that accepts but never answers times out
+        let silent = temp.path().join("silent.sock");
+        let _listener = tokio::net::UnixListener::bind(&silent).unwrap();
+        let error = probe(
+            &HealthCheck {
+                timeout_secs: 1,
+                ..rpc
+            },
+            &silent,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, OsnovaError::Timeout { .. }), "{}", error);
+    }
+}
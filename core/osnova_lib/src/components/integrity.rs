@@ -0,0 +1,206 @@
+//! # Component Integrity
+//!
+//! Detect corrupted or missing component artifacts.
+//!
+//! Handles:
+//! - Content manifests listing the BLAKE3 hash of every extracted file
+//! - Comparing an extracted tree against its content manifest
+//! - Per-component and per-app verification reports
+
+use crate::error::{OsnovaError, Result};
+use blake3::Hasher;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Compute the base64-encoded BLAKE3 hash used for component hashes
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    let hash = hasher.finalize();
+    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, hash.as_bytes())
+}
+
+/// Post-extraction content manifest for a frontend component
+///
+/// Generated when a tarball is extracted and stored next to the extracted
+/// tree, so later verification can pinpoint individual corrupted files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentManifest {
+    /// Relative file path (using `/` separators) to content hash
+    pub files: BTreeMap<String, String>,
+}
+
+impl ContentManifest {
+    /// Build a content manifest by hashing every file under `dir`
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        Self::collect(dir, dir, &mut files)?;
+        Ok(Self { files })
+    }
+
+    /// Load a content manifest from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Save the content manifest as a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Compare an extracted tree against this manifest
+    ///
+    /// Returns the sorted relative paths of files that are missing, modified,
+    /// or not listed in the manifest. An empty list means the tree matches.
+    pub fn diff(&self, dir: &Path) -> Result<Vec<String>> {
+        let actual = if dir.exists() {
+            Self::from_dir(dir)?
+        } else {
+            Self::default()
+        };
+
+        let mut failing: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(path, hash)| actual.files.get(*path) != Some(*hash))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        failing.extend(
+            actual
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .cloned(),
+        );
+        failing.sort();
+
+        Ok(failing)
+    }
+
+    /// Recursively hash files under `dir`
+    fn collect(base: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                Self::collect(base, &path, files)?;
+            } else {
+                let relative = path
+                    .strip_prefix(base)
+                    .map_err(|e| OsnovaError::Storage(format!("Invalid path: {}", e)))?;
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(key, content_hash(&std::fs::read(&path)?));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Integrity state of a single component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ComponentIntegrity {
+    /// Cache entry and extracted files match the manifest
+    Ok,
+    /// No cache entry exists for the component
+    MissingFromCache,
+    /// The cached artifact does not match the manifest hash
+    HashMismatch,
+    /// Extracted files are missing, modified, or unexpected
+    FilesCorrupted {
+        /// Relative paths of the failing files
+        files: Vec<String>,
+    },
+}
+
+/// Verification result for one component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ComponentVerification {
+    /// Component identifier
+    pub component_id: String,
+    /// Component name
+    pub name: String,
+    /// Integrity state
+    pub integrity: ComponentIntegrity,
+}
+
+/// Verification report for an installed application
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyReport {
+    /// Application identifier
+    pub app_id: String,
+    /// Per-component results
+    pub components: Vec<ComponentVerification>,
+}
+
+impl VerifyReport {
+    /// Whether every component passed verification
+    pub fn is_healthy(&self) -> bool {
+        self.components
+            .iter()
+            .all(|c| c.integrity == ComponentIntegrity::Ok)
+    }
+
+    /// Components that failed verification
+    pub fn failing(&self) -> impl Iterator<Item = &ComponentVerification> {
+        self.components
+            .iter()
+            .filter(|c| c.integrity != ComponentIntegrity::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_content_manifest_diff() -> Result<()> {
+        let temp = TempDir::new()?;
+        std::fs::create_dir_all(temp.path().join("assets"))?;
+        std::fs::write(temp.path().join("index.html"), b"<html></html>")?;
+        std::fs::write(temp.path().join("assets/app.js"), b"console.log(1)")?;
+
+        let manifest = ContentManifest::from_dir(temp.path())?;
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec!["assets/app.js", "index.html"]
+        );
+        assert!(manifest.diff(temp.path())?.is_empty());
+
+        std::fs::write(temp.path().join("assets/app.js"), b"corrupted")?;
+        std::fs::write(temp.path().join("extra.txt"), b"extra")?;
+        std::fs::remove_file(temp.path().join("index.html"))?;
+
+        assert_eq!(
+            manifest.diff(temp.path())?,
+            vec!["assets/app.js", "extra.txt", "index.html"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_manifest_round_trip() -> Result<()> {
+        let temp = TempDir::new()?;
+        std::fs::write(temp.path().join("a.txt"), b"a")?;
+
+        let manifest = ContentManifest::from_dir(temp.path())?;
+        let path = temp.path().join("manifest.json");
+        manifest.save(&path)?;
+
+        assert_eq!(ContentManifest::load(&path)?, manifest);
+
+        Ok(())
+    }
+}
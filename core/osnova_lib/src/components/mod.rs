@@ -2,6 +2,24 @@
 //!
 //! Download and manage application components (frontend and backend).
 
+pub mod binary;
+pub mod content_policy;
 pub mod downloader;
+pub mod entry;
+pub mod health;
+pub mod integrity;
+pub mod network;
+pub mod symbols;
 
+pub use binary::{
+    inspect, verify_backend, verify_prepared, BinaryKind, BinaryTarget, ExecutableFormat,
+};
+pub use content_policy::{
+    AllowedExecutable, ContentCheck, ContentFinding, ContentFlag, ContentPolicy,
+};
 pub use downloader::{download_component, ComponentDownloader};
+pub use entry::{resolve_entry, ResolvedEntry};
+pub use health::{HealthCheck, HealthProbe};
+pub use integrity::{ComponentIntegrity, ComponentVerification, VerifyReport};
+pub use network::NetworkDeclaration;
+pub use symbols::{parse_frames, NativeFrame, ResolvedFrame, SymbolFile, SymbolInfo};
@@ -0,0 +1,273 @@
+//! # Backend Network Declarations
+//!
+//! Users should know which backends talk to the network, and to where.
+//! Backends declare it in their `config.network`:
+//!
+//! ```json
+//! "network": {
+//!     "autonomi": true,
+//!     "hosts": ["api.example.com", "*.cdn.example.com"]
+//! }
+//! ```
+//!
+//! - `autonomi` - the backend stores or fetches data on the Autonomi
+//!   network through the core
+//! - `hosts` - hosts the backend reaches over HTTP(S); `*.example.com`
+//!   matches any subdomain of `example.com`, but not `example.com` itself
+//! - `localOnly` - the backend only reaches this device and the local
+//!   network; cannot be combined with `hosts`
+//!
+//! A backend declaring nothing reaches no hosts. The declaration is shown
+//! when the user reviews the app, and enforced by the
+//! [`NetworkProxy`](crate::services::network_proxy::NetworkProxy) backends
+//! make their HTTP(S) requests through.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+
+use crate::http;
+
+/// Component config key declaring a backend's network access
+pub const NETWORK_CONFIG_KEY: &str = "network";
+
+/// Longest host name, per RFC 1035
+const MAX_HOST_LEN: usize = 253;
+
+/// Network access a backend declares
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NetworkDeclaration {
+    /// Whether the backend uses the Autonomi network through the core
+    #[serde(default)]
+    pub autonomi: bool,
+    /// Hosts the backend reaches, exact or `*.` wildcards
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Whether the backend only reaches this device and the local network
+    #[serde(default)]
+    pub local_only: bool,
+}
+
+impl NetworkDeclaration {
+    /// Parse a `config.network` value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is not a network declaration, or fails
+    /// [`validate`](Self::validate)
+    pub fn from_config(value: &Value) -> std::result::Result<Self, String> {
+        let declaration: Self =
+            serde_json::from_value(value.clone()).map_err(|e| format!("network: {}", e))?;
+        declaration.validate()?;
+        Ok(declaration)
+    }
+
+    /// Check the declared hosts
+    ///
+    /// Hosts are names or IP addresses without a scheme or port, optionally
+    /// prefixed with `*.` followed by at least two labels.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.local_only && !self.hosts.is_empty() {
+            return Err("network hosts cannot be combined with localOnly".to_string());
+        }
+        for host in &self.hosts {
+            if !is_host_pattern(host) {
+                return Err(format!(
+                    "network host '{}' must be a host name such as 'api.example.com' or '*.example.com'",
+                    host
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the declaration lets the backend reach `host`
+    ///
+    /// Host names compare case-insensitively, ignoring a trailing dot.
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = normalize_host(host);
+        if self.local_only {
+            return is_local_host(&host);
+        }
+        self.hosts
+            .iter()
+            .any(|pattern| host_matches(&normalize_host(pattern), &host))
+    }
+
+    /// Permissions the declaration adds to an app's review, such as
+    /// `network:autonomi` or `networkHost:api.example.com`
+    pub fn permissions(&self) -> Vec<String> {
+        let mut permissions = Vec::new();
+        if self.autonomi {
+            permissions.push("network:autonomi".to_string());
+        }
+        if self.local_only {
+            permissions.push("network:local".to_string());
+        }
+        permissions.extend(
+            self.hosts
+                .iter()
+                .map(|host| format!("networkHost:{}", normalize_host(host))),
+        );
+        permissions
+    }
+}
+
+/// Lowercase `host` and strip a trailing dot and IPv6 brackets
+fn normalize_host(host: &str) -> String {
+    let host = host.trim_end_matches('.');
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    host.to_ascii_lowercase()
+}
+
+/// Whether normalized `host` matches normalized `pattern`
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+/// Whether `host` is a valid exact or wildcard host
+fn is_host_pattern(host: &str) -> bool {
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    let (name, min_labels) = match host.strip_prefix("*.") {
+        Some(domain) => (domain, 2),
+        None => (host, 1),
+    };
+    let labels: Vec<&str> = name.split('.').collect();
+    name.len() <= MAX_HOST_LEN
+        && labels.len() >= min_labels
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Whether normalized `host` is this device or on the local network
+///
+/// Names are local if they are `localhost` or end in `.local` (mDNS);
+/// addresses if [`http::is_private`] refuses them to remote content.
+fn is_local_host(host: &str) -> bool {
+    match host.parse::<IpAddr>() {
+        Ok(ip) => http::is_private(ip),
+        Err(_) => host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declaration(value: Value) -> std::result::Result<NetworkDeclaration, String> {
+        NetworkDeclaration::from_config(&value)
+    }
+
+    #[test]
+    fn test_declarations() {
+        let declared = declaration(serde_json::json!({
+            "autonomi": true, "hosts": ["api.example.com", "*.cdn.example.com"]
+        }))
+        .unwrap();
+        assert!(declared.autonomi);
+        assert!(!declared.local_only);
+        assert_eq!(
+            declared.permissions(),
+            vec![
+                "network:autonomi",
+                "networkHost:api.example.com",
+                "networkHost:*.cdn.example.com"
+            ]
+        );
+        assert_eq!(
+            declaration(serde_json::json!({})).unwrap(),
+            NetworkDeclaration::default()
+        );
+
+        for (value, expected) in [
+            (
+                serde_json::json!({"hosts": ["https://api.example.com"]}),
+                "must be a host name",
+            ),
+            (
+                serde_json::json!({"hosts": ["api.example.com:443"]}),
+                "must be a host name",
+            ),
+            (
+                serde_json::json!({"hosts": ["*.com"]}),
+                "must be a host name",
+            ),
+            (serde_json::json!({"hosts": ["*"]}), "must be a host name"),
+            (
+                serde_json::json!({"hosts": ["api.*.example.com"]}),
+                "must be a host name",
+            ),
+            (serde_json::json!({"hosts": [""]}), "must be a host name"),
+            (
+                serde_json::json!({"localOnly": true, "hosts": ["api.example.com"]}),
+                "localOnly",
+            ),
+            (serde_json::json!({"host": ["api.example.com"]}), "network:"),
+            (serde_json::json!({"hosts": "api.example.com"}), "network:"),
+        ] {
+            let error = declaration(value.clone()).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", value, error);
+        }
+    }
+
+    #[test]
+    fn test_exact_and_wildcard_hosts() {
+        let declared = declaration(serde_json::json!({
+            "hosts": ["api.example.com", "*.cdn.example.com", "10.0.0.8"]
+        }))
+        .unwrap();
+
+        assert!(declared.allows_host("api.example.com"));
+        assert!(declared.allows_host("API.Example.com."));
+        assert!(!declared.allows_host("example.com"));
+        assert!(!declared.allows_host("v2.api.example.com"));
+        assert!(!declared.allows_host("api.example.com.evil.net"));
+
+        assert!(declared.allows_host("eu.cdn.example.com"));
+        assert!(declared.allows_host("a.b.cdn.example.com"));
+        assert!(!declared.allows_host("cdn.example.com"));
+        assert!(!declared.allows_host("evilcdn.example.com"));
+        assert!(!declared.allows_host(".cdn.example.com"));
+
+        assert!(declared.allows_host("10.0.0.8"));
+        assert!(!declared.allows_host("10.0.0.9"));
+        assert!(!NetworkDeclaration::default().allows_host("api.example.com"));
+    }
+
+    #[test]
+    fn test_local_only() {
+        let local = declaration(serde_json::json!({"localOnly": true})).unwrap();
+        assert_eq!(local.permissions(), vec!["network:local"]);
+
+        for host in [
+            "localhost",
+            "printer.local",
+            "127.0.0.1",
+            "192.168.1.20",
+            "[::1]",
+            "fd12::1",
+        ] {
+            assert!(local.allows_host(host), "{}", host);
+        }
+        for host in ["api.example.com", "8.8.8.8", "2001:db8::1", "local"] {
+            assert!(!local.allows_host(host), "{}", host);
+        }
+    }
+}
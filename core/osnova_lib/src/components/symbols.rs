@@ -0,0 +1,479 @@
+//! # Backend Crash Symbolication
+//!
+//! Turn the native backtrace a crashed backend printed to stderr into
+//! function names and source lines, using debug symbols shipped with the
+//! component. Best-effort: frames that cannot be resolved keep their raw
+//! address.
+//!
+//! Handles:
+//! - Finding frame addresses in stderr: glibc `backtrace_symbols` lines
+//!   (`./backend(+0x11a9) [0x55d0...]`, module-relative when shown) and
+//!   Rust's `3: 0x55d0... - <unknown>` lines
+//! - Breakpad text symbol files (`FUNC`, line, and `PUBLIC` records)
+//! - Debug files and unstripped binaries carrying DWARF (ELF, Mach-O, PE),
+//!   read with `addr2line`
+//!
+//! Symbols are looked for next to the prepared binary (`<binary>.sym`), or
+//! fetched from the URI a backend declares in its `config.symbols`.
+//!
+//! Absolute addresses are resolved as file addresses, which is exact for
+//! non-relocated binaries and module-relative frames only.
+
+use crate::error::{OsnovaError, Result};
+use addr2line::gimli;
+use object::{Object, ObjectSection};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Component config key naming where a backend's symbols can be fetched
+pub const SYMBOLS_CONFIG_KEY: &str = "symbols";
+
+/// Extension of a symbol file shipped next to a backend binary
+pub const SYMBOL_FILE_EXTENSION: &str = "sym";
+
+/// Magic line starting a Breakpad text symbol file
+const BREAKPAD_MAGIC: &[u8] = b"MODULE ";
+
+/// DWARF reader over a symbol file held in memory
+type DwarfReader<'a> = gimli::EndianSlice<'a, gimli::RunTimeEndian>;
+
+/// A frame of a native backtrace found in captured stderr
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeFrame {
+    /// Position in the backtrace, innermost first
+    pub index: usize,
+    /// Address to resolve: module-relative when the line showed an offset
+    pub address: u64,
+    /// The stderr line the frame was read from
+    pub line: String,
+}
+
+/// Where a frame's address falls in the source
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    /// Demangled function name
+    pub function: Option<String>,
+    /// Source file
+    pub file: Option<String>,
+    /// Line in the source file
+    pub line: Option<u32>,
+}
+
+/// A backtrace frame after symbolication
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedFrame {
+    /// The frame as captured
+    #[serde(flatten)]
+    pub frame: NativeFrame,
+    /// What the symbols say about it; `None` when they do not cover it
+    pub symbol: Option<SymbolInfo>,
+}
+
+/// Find the frames of native backtraces in captured stderr lines
+///
+/// Lines that are not backtrace frames are skipped. Frames are numbered in
+/// the order they appear.
+pub fn parse_frames(lines: &[String]) -> Vec<NativeFrame> {
+    lines
+        .iter()
+        .filter_map(|line| frame_address(line).map(|address| (address, line)))
+        .enumerate()
+        .map(|(index, (address, line))| NativeFrame {
+            index,
+            address,
+            line: line.clone(),
+        })
+        .collect()
+}
+
+/// Address a backtrace line points at, if it is a frame
+fn frame_address(line: &str) -> Option<u64> {
+    let trimmed = line.trim_start();
+
+    // glibc: `module(+0x11a9) [0x55d0...]` or `module(symbol+0x20) [0x...]`
+    if let (Some(open), Some(bracket)) = (trimmed.find('('), trimmed.rfind(" [0x")) {
+        if let Some(offset) = trimmed[open..bracket]
+            .strip_prefix("(+0x")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return u64::from_str_radix(offset, 16).ok();
+        }
+        return hex_prefix(&trimmed[bracket + 2..]);
+    }
+
+    // Rust: `  3: 0x55d0... - <unknown>`
+    let (index, rest) = trimmed.split_once(':')?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    hex_prefix(rest.trim_start())
+}
+
+/// Parse the `0x`-prefixed hex number a string starts with
+fn hex_prefix(text: &str) -> Option<u64> {
+    let digits = text.strip_prefix("0x")?;
+    let end = digits
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(digits.len());
+    u64::from_str_radix(&digits[..end], 16).ok()
+}
+
+/// Path of the symbol file shipped next to a prepared backend binary
+pub fn symbol_path(binary: &Path) -> PathBuf {
+    let mut name = binary.as_os_str().to_owned();
+    name.push(".");
+    name.push(SYMBOL_FILE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Debug symbols of a backend binary
+pub enum SymbolFile {
+    /// Breakpad text symbols
+    Breakpad(BreakpadSymbols),
+    /// Object file carrying DWARF debug info
+    Dwarf(Vec<u8>),
+}
+
+impl SymbolFile {
+    /// Parse a symbol file, telling Breakpad text from an object file
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.starts_with(BREAKPAD_MAGIC) {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| OsnovaError::Other("Breakpad symbols are not UTF-8".to_string()))?;
+            return Ok(Self::Breakpad(BreakpadSymbols::parse(text)));
+        }
+
+        // Index once up front so a broken file fails here, not per frame
+        Self::with_dwarf(data, |_| ())?;
+        Ok(Self::Dwarf(data.to_vec()))
+    }
+
+    /// Look up what an address points at
+    pub fn lookup(&self, address: u64) -> Option<SymbolInfo> {
+        match self {
+            Self::Breakpad(symbols) => symbols.lookup(address),
+            Self::Dwarf(data) => {
+                Self::with_dwarf(data, |context| Self::lookup_dwarf(context, address))
+                    .ok()
+                    .flatten()
+            }
+        }
+    }
+
+    /// Resolve every frame, leaving the ones the symbols do not cover bare
+    pub fn symbolicate(&self, frames: &[NativeFrame]) -> Vec<ResolvedFrame> {
+        let resolve = |lookup: &dyn Fn(u64) -> Option<SymbolInfo>| {
+            frames
+                .iter()
+                .map(|frame| ResolvedFrame {
+                    frame: frame.clone(),
+                    symbol: lookup(frame.address),
+                })
+                .collect()
+        };
+
+        match self {
+            Self::Breakpad(symbols) => resolve(&|address| symbols.lookup(address)),
+            Self::Dwarf(data) => Self::with_dwarf(data, |context| {
+                resolve(&|address| Self::lookup_dwarf(context, address))
+            })
+            .unwrap_or_else(|_| resolve(&|_| None)),
+        }
+    }
+
+    /// Index the DWARF debug info of an object file and run `f` over it
+    fn with_dwarf<T>(
+        data: &[u8],
+        f: impl FnOnce(&addr2line::Context<DwarfReader<'_>>) -> T,
+    ) -> Result<T> {
+        let file = object::File::parse(data)
+            .map_err(|e| OsnovaError::Other(format!("Unrecognized symbol file: {}", e)))?;
+        if file.section_by_name(".debug_info").is_none()
+            && file.section_by_name("__debug_info").is_none()
+        {
+            return Err(OsnovaError::Other(
+                "Symbol file carries no debug info".to_string(),
+            ));
+        }
+
+        let endian = if file.is_little_endian() {
+            gimli::RunTimeEndian::Little
+        } else {
+            gimli::RunTimeEndian::Big
+        };
+        let dwarf = gimli::Dwarf::load(|id| -> std::result::Result<_, gimli::Error> {
+            let data = file
+                .section_by_name(id.name())
+                .or_else(|| {
+                    // Mach-O names `.debug_info` `__debug_info`
+                    id.name()
+                        .strip_prefix('.')
+                        .and_then(|name| file.section_by_name(&format!("__{}", name)))
+                })
+                .and_then(|section| section.data().ok())
+                .unwrap_or(&[]);
+            Ok(gimli::EndianSlice::new(data, endian))
+        })
+        .map_err(|e| OsnovaError::Other(format!("Failed to load debug info: {}", e)))?;
+        let context = addr2line::Context::from_dwarf(dwarf)
+            .map_err(|e| OsnovaError::Other(format!("Failed to index debug info: {}", e)))?;
+
+        Ok(f(&context))
+    }
+
+    /// Outermost non-inlined frame of an address in DWARF debug info
+    fn lookup_dwarf(
+        context: &addr2line::Context<DwarfReader<'_>>,
+        address: u64,
+    ) -> Option<SymbolInfo> {
+        let mut frames = context.find_frames(address).skip_all_loads().ok()?;
+        let mut symbol = None;
+        while let Ok(Some(frame)) = frames.next() {
+            let function = frame
+                .function
+                .as_ref()
+                .and_then(|name| name.demangle().ok().map(Cow::into_owned));
+            let location = frame.location.as_ref();
+            symbol = Some(SymbolInfo {
+                function,
+                file: location.and_then(|l| l.file).map(str::to_string),
+                line: location.and_then(|l| l.line),
+            });
+        }
+
+        symbol.or_else(|| {
+            let location = context.find_location(address).ok()??;
+            Some(SymbolInfo {
+                function: None,
+                file: location.file.map(str::to_string),
+                line: location.line,
+            })
+        })
+    }
+}
+
+/// Function record of a Breakpad symbol file
+#[derive(Debug, Clone)]
+struct BreakpadFunction {
+    address: u64,
+    size: u64,
+    name: String,
+    /// Line records: address, size, line, file number
+    lines: Vec<(u64, u64, u32, u64)>,
+}
+
+/// Symbols read from a Breakpad text symbol file
+///
+/// Only the records needed to resolve addresses are kept; stack unwinding
+/// records are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct BreakpadSymbols {
+    files: std::collections::HashMap<u64, String>,
+    functions: Vec<BreakpadFunction>,
+    publics: Vec<(u64, String)>,
+}
+
+impl BreakpadSymbols {
+    /// Parse Breakpad text symbols, skipping malformed records
+    pub fn parse(text: &str) -> Self {
+        let mut symbols = Self::default();
+
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("FILE") => {
+                    let mut parts = line.splitn(3, ' ').skip(1);
+                    if let (Some(Ok(number)), Some(name)) =
+                        (parts.next().map(str::parse), parts.next())
+                    {
+                        symbols.files.insert(number, name.to_string());
+                    }
+                }
+                Some("FUNC") => {
+                    // FUNC [m] address size parameter_size name
+                    let rest = line["FUNC ".len().min(line.len())..].trim_start();
+                    let rest = rest.strip_prefix("m ").unwrap_or(rest);
+                    let mut parts = rest.splitn(4, ' ');
+                    let (Some(address), Some(size), Some(_), Some(name)) =
+                        (parts.next(), parts.next(), parts.next(), parts.next())
+                    else {
+                        continue;
+                    };
+                    if let (Ok(address), Ok(size)) = (
+                        u64::from_str_radix(address, 16),
+                        u64::from_str_radix(size, 16),
+                    ) {
+                        symbols.functions.push(BreakpadFunction {
+                            address,
+                            size,
+                            name: name.to_string(),
+                            lines: Vec::new(),
+                        });
+                    }
+                }
+                Some("PUBLIC") => {
+                    // PUBLIC [m] address parameter_size name
+                    let rest = line["PUBLIC ".len().min(line.len())..].trim_start();
+                    let rest = rest.strip_prefix("m ").unwrap_or(rest);
+                    let mut parts = rest.splitn(3, ' ');
+                    if let (Some(Ok(address)), Some(_), Some(name)) = (
+                        parts.next().map(|a| u64::from_str_radix(a, 16)),
+                        parts.next(),
+                        parts.next(),
+                    ) {
+                        symbols.publics.push((address, name.to_string()));
+                    }
+                }
+                Some(first) if first.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                    // Line record of the preceding FUNC: address size line file
+                    let record: Vec<u64> = std::iter::once(first)
+                        .chain(fields)
+                        .enumerate()
+                        .filter_map(|(i, field)| match i {
+                            0 | 1 => u64::from_str_radix(field, 16).ok(),
+                            _ => field.parse().ok(),
+                        })
+                        .collect();
+                    if let (Some(function), [address, size, line, file]) =
+                        (symbols.functions.last_mut(), record.as_slice())
+                    {
+                        function.lines.push((*address, *size, *line as u32, *file));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        symbols.functions.sort_by_key(|f| f.address);
+        symbols.publics.sort_by_key(|(address, _)| *address);
+        symbols
+    }
+
+    /// Look up what an address points at
+    ///
+    /// Falls back to the nearest preceding `PUBLIC` symbol, without a
+    /// source line, when no function record covers the address.
+    pub fn lookup(&self, address: u64) -> Option<SymbolInfo> {
+        let index = self.functions.partition_point(|f| f.address <= address);
+        let function = index
+            .checked_sub(1)
+            .map(|i| &self.functions[i])
+            .filter(|f| address < f.address.saturating_add(f.size.max(1)));
+
+        if let Some(function) = function {
+            let line = function
+                .lines
+                .iter()
+                .find(|(start, size, _, _)| *start <= address && address < start + size);
+            return Some(SymbolInfo {
+                function: Some(function.name.clone()),
+                file: line.and_then(|(_, _, _, file)| self.files.get(file).cloned()),
+                line: line.map(|(_, _, line, _)| *line),
+            });
+        }
+
+        let index = self.publics.partition_point(|(start, _)| *start <= address);
+        index.checked_sub(1).map(|i| SymbolInfo {
+            function: Some(self.publics[i].1.clone()),
+            file: None,
+            line: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Breakpad symbols of a stub backend that crashes in `explode`
+    const FIXTURE: &str = include_str!("crash_stub.sym");
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_frames_from_stderr() {
+        let stderr = lines(&[
+            "worker: starting",
+            "./crash-stub(+0x1148) [0x55d0c0a01148]",
+            "./crash-stub(main+0x8) [0x55d0c0a01208]",
+            "   2: 0x7f3e2c029d90 - <unknown>",
+            "panicked at src/main.rs:4:5: 12: not a frame",
+        ]);
+
+        let frames = parse_frames(&stderr);
+        let addresses: Vec<u64> = frames.iter().map(|f| f.address).collect();
+        assert_eq!(addresses, vec![0x1148, 0x55d0c0a01208, 0x7f3e2c029d90]);
+        assert_eq!(frames[1].index, 1);
+        assert_eq!(frames[0].line, stderr[1]);
+    }
+
+    #[test]
+    fn test_breakpad_symbolication() -> Result<()> {
+        let symbols = SymbolFile::parse(FIXTURE.as_bytes())?;
+        let frames = parse_frames(&lines(&[
+            "./crash-stub(+0x1148) [0x55d0c0a01148]",
+            "./crash-stub(+0x1210) [0x55d0c0a01210]",
+            "./crash-stub(+0x1004) [0x55d0c0a01004]",
+            "./crash-stub(+0x9000) [0x55d0c0a09000]",
+        ]));
+
+        let resolved = symbols.symbolicate(&frames);
+        assert_eq!(
+            resolved[0].symbol,
+            Some(SymbolInfo {
+                function: Some("crash_stub::worker::explode".to_string()),
+                file: Some("/build/crash-stub/src/worker.rs".to_string()),
+                line: Some(18),
+            })
+        );
+        assert_eq!(resolved[1].symbol.as_ref().unwrap().line, Some(4));
+        // Outside every function, the nearest public symbol still names it
+        assert_eq!(
+            resolved[2].symbol.as_ref().unwrap().function.as_deref(),
+            Some("_start")
+        );
+        assert_eq!(resolved[3].symbol.as_ref().unwrap().line, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_files_without_symbols() {
+        assert!(SymbolFile::parse(b"not a symbol file").is_err());
+        assert_eq!(
+            symbol_path(Path::new("/cache/backend")),
+            PathBuf::from("/cache/backend.sym")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_dwarf_symbolication_of_test_binary() -> Result<()> {
+        #[inline(never)]
+        fn marker() -> usize {
+            std::hint::black_box(42)
+        }
+        std::hint::black_box(marker());
+
+        // File address of `marker` in this (relocated) executable
+        let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+        let function = marker as *const () as usize;
+        assert_ne!(
+            unsafe { libc::dladdr(function as *const libc::c_void, &mut info) },
+            0
+        );
+        let address = (function - info.dli_fbase as usize) as u64;
+
+        let symbols = SymbolFile::parse(&std::fs::read(std::env::current_exe()?)?)?;
+        let symbol = symbols.lookup(address).expect("test binary has debug info");
+        assert!(symbol.function.unwrap_or_default().ends_with("marker"));
+        assert!(symbol.file.unwrap_or_default().ends_with("symbols.rs"));
+        Ok(())
+    }
+}
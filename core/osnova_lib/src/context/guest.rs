@@ -0,0 +1,546 @@
+//! Time-boxed guest sessions that leave no trace
+//!
+//! A guest session lets someone try Osnova without touching the host
+//! user's identity. [`OsnovaContext::start_guest_session`] creates a
+//! throwaway identity and an ephemeral context for it: config, keys, files
+//! and navigation opened from [`GuestSession::context`] live only in
+//! memory. The host's component cache is shared read-only through
+//! [`GuestSession::components`], so apps launch without downloading again
+//! and without the guest touching the cache.
+//!
+//! While a session runs, the host context's [`GuestGate`] refuses the
+//! operations a guest must not reach (see [`GuestRestriction`]) with
+//! [`GuestModeRestricted`], and [`StatusService`] reports the session so
+//! the UI can show an indicator. [`GuestSession::expiry`] resolves at the
+//! session's time limit. Ending the session, or dropping it when the shell
+//! exits, discards everything it wrote.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::context::OsnovaContext;
+//! use osnova_lib::services::ConfigService;
+//!
+//! # fn example() -> anyhow::Result<()> {
+//! let context = OsnovaContext::new("/tmp/osnova");
+//! let session = context.start_guest_session()?;
+//! let config = ConfigService::from_context(session.context())?;
+//! config.set_launcher_manifest("ant://launcher")?;
+//!
+//! drop(config);
+//! session.end();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`StatusService`]: crate::services::StatusService
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use super::OsnovaContext;
+use crate::address;
+use crate::cache::CacheReader;
+use crate::models::identity::RootIdentity;
+use crate::time::SharedClock;
+
+/// How long a guest session lasts unless started with another limit
+pub const DEFAULT_GUEST_SESSION_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Operations refused while a guest session runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum GuestRestriction {
+    /// Revealing or exporting the host's seed phrase
+    IdentityExport,
+    /// Pairing the device with a server
+    Pairing,
+    /// Approving a payment from the host's wallet
+    WalletApproval,
+    /// Applying or lifting a device policy
+    PolicyChange,
+}
+
+impl GuestRestriction {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::IdentityExport => "Identity export",
+            Self::Pairing => "Pairing",
+            Self::WalletApproval => "Wallet approval",
+            Self::PolicyChange => "Policy change",
+        }
+    }
+}
+
+/// An operation was refused because a guest session is running
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestModeRestricted {
+    /// Operation that was refused
+    pub operation: GuestRestriction,
+}
+
+impl fmt::Display for GuestModeRestricted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not available in a guest session",
+            self.operation.describe()
+        )
+    }
+}
+
+impl std::error::Error for GuestModeRestricted {}
+
+/// A running guest session, as the status indicator shows it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestSessionInfo {
+    /// 4-word address of the guest identity
+    pub address: String,
+    /// Unix timestamp the session started at
+    pub started_at: u64,
+    /// Unix timestamp the session ends at
+    pub expires_at: u64,
+}
+
+/// Handle on whether a guest session runs in a context, held by services
+/// with operations a guest must not reach
+///
+/// Cloning the gate yields another handle to the same state. A default
+/// gate belongs to no context and never refuses.
+#[derive(Clone, Default)]
+pub struct GuestGate {
+    session: Arc<RwLock<Option<GuestSessionInfo>>>,
+}
+
+impl GuestGate {
+    /// The running session, if any
+    pub fn session(&self) -> Option<GuestSessionInfo> {
+        self.session.read().unwrap().clone()
+    }
+
+    /// Whether a guest session runs
+    pub fn is_active(&self) -> bool {
+        self.session.read().unwrap().is_some()
+    }
+
+    /// Refuse an operation while a guest session runs
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GuestModeRestricted`] naming the operation
+    pub fn require_host(
+        &self,
+        operation: GuestRestriction,
+    ) -> std::result::Result<(), GuestModeRestricted> {
+        match self.is_active() {
+            true => Err(GuestModeRestricted { operation }),
+            false => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for GuestGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestGate")
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+/// A guest identity with its own in-memory storage
+///
+/// Dropping the session ends it. Services opened from its
+/// [`context`](Self::context) keep their data until they are dropped too.
+pub struct GuestSession {
+    context: OsnovaContext,
+    identity: RootIdentity,
+    address: String,
+    components: CacheReader,
+    gate: GuestGate,
+    clock: SharedClock,
+    info: GuestSessionInfo,
+}
+
+impl GuestSession {
+    /// The ephemeral context of the guest's services
+    pub fn context(&self) -> &OsnovaContext {
+        &self.context
+    }
+
+    /// The throwaway identity of the guest
+    pub fn identity(&self) -> &RootIdentity {
+        &self.identity
+    }
+
+    /// User ID of the guest (its 4-word address)
+    pub fn user_id(&self) -> &str {
+        &self.address
+    }
+
+    /// The host's component cache, read-only
+    pub fn components(&self) -> &CacheReader {
+        &self.components
+    }
+
+    /// The session as the status indicator shows it
+    pub fn info(&self) -> &GuestSessionInfo {
+        &self.info
+    }
+
+    /// Time left before the session should end
+    pub fn remaining(&self) -> Duration {
+        Duration::from_secs(self.info.expires_at.saturating_sub(self.clock.now_unix()))
+    }
+
+    /// Wait until the session reaches its time limit
+    ///
+    /// The future does not borrow the session, so a task can wait on it
+    /// while the session is kept elsewhere.
+    pub fn expiry(&self) -> impl Future<Output = ()> + Send + 'static {
+        tokio::time::sleep(self.remaining())
+    }
+
+    /// Whether the session ran past its time limit
+    pub fn is_expired(&self) -> bool {
+        self.clock.now_unix() >= self.info.expires_at
+    }
+
+    /// End the session, discarding everything it wrote
+    pub fn end(self) {}
+}
+
+impl Drop for GuestSession {
+    fn drop(&mut self) {
+        self.context.lock();
+        *self.gate.session.write().unwrap() = None;
+    }
+}
+
+impl OsnovaContext {
+    /// A gate for services with operations a guest must not reach
+    pub fn guest_gate(&self) -> GuestGate {
+        self.guest.clone()
+    }
+
+    /// Share a component cache directory with guest sessions
+    ///
+    /// Without one, guests read the platform's component cache.
+    pub fn with_component_cache(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.component_cache = Some(cache_dir.into());
+        self
+    }
+
+    /// Start a guest session lasting [`DEFAULT_GUEST_SESSION_DURATION`]
+    ///
+    /// See [`start_guest_session_for`](Self::start_guest_session_for).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a guest session already runs or the guest's
+    /// storage cannot be created
+    pub fn start_guest_session(&self) -> Result<GuestSession> {
+        self.start_guest_session_for(DEFAULT_GUEST_SESSION_DURATION)
+    }
+
+    /// Start a guest session that should end after `limit`
+    ///
+    /// The guest gets a new identity, unlocked in an ephemeral context
+    /// that runs on this context's clock. Until the session ends, this
+    /// context's [`guest_gate`](Self::guest_gate) refuses the restricted
+    /// operations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a guest session already runs or the guest's
+    /// storage cannot be created
+    pub fn start_guest_session_for(&self, limit: Duration) -> Result<GuestSession> {
+        let mut session = self.guest.session.write().unwrap();
+        if session.is_some() {
+            bail!("A guest session is already running");
+        }
+
+        let cache_dir = match &self.component_cache {
+            Some(dir) => dir.clone(),
+            None => crate::platform::paths::get_component_cache_dir()?,
+        };
+        let mut context = OsnovaContext::new_ephemeral()?;
+        context.clock = self.clock.clone();
+        let identity = RootIdentity::generate()?;
+        let address = address::derive_address(&identity.fingerprint());
+        context
+            .unlock(&identity, &address)?
+            .initialize(identity.master_key())?;
+
+        let started_at = self.clock.now_unix();
+        let info = GuestSessionInfo {
+            address: address.clone(),
+            started_at,
+            expires_at: started_at.saturating_add(limit.as_secs()),
+        };
+        *session = Some(info.clone());
+
+        Ok(GuestSession {
+            context,
+            identity,
+            address,
+            components: CacheReader::new(cache_dir),
+            gate: self.guest.clone(),
+            clock: self.clock.clone(),
+            info,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use crate::models::key_cocoon::KeyType;
+    use crate::policies::{admin_signing_key, PolicyDocument, PolicyStore};
+    use crate::services::{
+        BottomMenuTab, ConfigService, IdentityService, NavigationService, PairingService,
+        StatusService, WalletService,
+    };
+    use crate::time::MockClock;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Every file under a directory with its contents
+    fn snapshot(root: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let contents = std::fs::read(&path).unwrap();
+                    files.insert(path.strip_prefix(root).unwrap().to_path_buf(), contents);
+                }
+            }
+        }
+        files
+    }
+
+    fn restricted(error: &anyhow::Error) -> Option<GuestRestriction> {
+        error
+            .downcast_ref::<GuestModeRestricted>()
+            .map(|refused| refused.operation)
+    }
+
+    /// A host with an identity, a config and a cached component
+    fn host(temp: &TempDir) -> Result<OsnovaContext> {
+        let context = OsnovaContext::new(temp.path().join("data"))
+            .with_component_cache(temp.path().join("cache"));
+        IdentityService::new(context.storage_path())?.create()?;
+        ConfigService::from_context(&context)?.set_launcher_manifest("ant://host-launcher")?;
+        NavigationService::from_context(&context, "host")?
+            .set_bottom_menu(BottomMenuTab::Wallet)?;
+
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024)?;
+        tokio::runtime::Runtime::new()?
+            .block_on(cache.store("ant://notes-frontend", b"<html>notes</html>"))?;
+        Ok(context)
+    }
+
+    #[test]
+    fn test_guest_writes_stay_out_of_the_host_profile() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = host(&temp)?;
+        let session = context.start_guest_session()?;
+        let guest = session.context();
+        assert!(guest.is_ephemeral());
+
+        // The guest starts from empty config and navigation
+        let config = ConfigService::from_context(guest)?;
+        assert_eq!(config.get_launcher_manifest()?, None);
+        config.set_launcher_manifest("ant://guest-launcher")?;
+        let navigation = NavigationService::from_context(guest, session.user_id())?;
+        assert_eq!(navigation.get_bottom_menu()?, BottomMenuTab::Launcher);
+        navigation.set_bottom_menu(BottomMenuTab::Config)?;
+        guest
+            .file_store()?
+            .write(Path::new("blobs/draft"), b"guest draft", &[3u8; 32])?;
+
+        // Its keys are its own
+        let key = guest
+            .key_service()?
+            .derive("com.example.notes", KeyType::Ed25519)?;
+        assert!(IdentityService::new(context.storage_path())?
+            .get_identity()?
+            .fingerprint()
+            .ne(&session.identity().fingerprint()));
+
+        // The host sees none of it
+        assert_eq!(
+            ConfigService::from_context(&context)?
+                .get_launcher_manifest()?
+                .as_deref(),
+            Some("ant://host-launcher")
+        );
+        assert_eq!(
+            NavigationService::from_context(&context, "host")?.get_bottom_menu()?,
+            BottomMenuTab::Wallet
+        );
+        assert!(!context.file_store()?.exists(Path::new("blobs/draft")));
+        assert!(guest
+            .key_service()?
+            .get_by_public_key(&key.public_key)
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_guest_reads_host_components_without_writing() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = host(&temp)?;
+        let before = snapshot(&temp.path().join("cache"));
+
+        let session = context.start_guest_session()?;
+        let components = session.components();
+        assert!(components.contains("ant://notes-frontend"));
+        assert_eq!(
+            components.get("ant://notes-frontend")?.as_deref(),
+            Some(&b"<html>notes</html>"[..])
+        );
+        assert_eq!(components.get("ant://missing")?, None);
+        session.end();
+
+        assert_eq!(snapshot(&temp.path().join("cache")), before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restricted_operations_are_refused_during_the_session() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = host(&temp)?;
+        let storage = context.storage_path().to_path_buf();
+        let identity = IdentityService::new(&storage)?.with_guest_gate(context.guest_gate());
+        let wallet = WalletService::new(&storage)?.with_guest_gate(context.guest_gate());
+        let host_identity = identity.get_identity()?;
+        let policies = PolicyStore::new(&storage, "host", &host_identity)?
+            .with_guest_gate(context.guest_gate());
+        let admin = admin_signing_key(&RootIdentity::generate()?)?;
+        let policy = PolicyDocument::new("guest-test", 1, 0, Default::default()).sign(&admin)?;
+
+        let session = context.start_guest_session()?;
+        let error = identity.reveal_seed_phrase(None).unwrap_err();
+        assert_eq!(restricted(&error), Some(GuestRestriction::IdentityExport));
+        assert_eq!(
+            error.to_string(),
+            "Identity export is not available in a guest session"
+        );
+        let error = PairingService::new(&storage, &[1u8; 32])?
+            .with_guest_gate(context.guest_gate())
+            .start("session-1", &[1u8; 32], &[2u8; 32])
+            .unwrap_err();
+        assert_eq!(restricted(&error), Some(GuestRestriction::Pairing));
+        let error = wallet.approve_payment("com.example.notes", 10).unwrap_err();
+        assert_eq!(restricted(&error), Some(GuestRestriction::WalletApproval));
+        let error = policies.apply(&policy).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::OsnovaError::GuestModeRestricted(GuestModeRestricted {
+                operation: GuestRestriction::PolicyChange
+            })
+        ));
+        assert_eq!(error.rpc_code(), crate::error::PERMISSION_DENIED_ERROR_CODE);
+
+        // Only one guest at a time
+        assert!(context.start_guest_session().is_err());
+        session.end();
+
+        assert_eq!(
+            identity.reveal_seed_phrase(None)?,
+            host_identity.seed_phrase()
+        );
+        wallet.approve_payment("com.example.notes", 10)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_ending_the_session_leaves_no_files() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = host(&temp)?;
+        let before = snapshot(temp.path());
+
+        let session = context.start_guest_session()?;
+        let guest = session.context();
+        ConfigService::from_context(guest)?.set_launcher_manifest("ant://guest")?;
+        NavigationService::from_context(guest, session.user_id())?
+            .set_bottom_menu(BottomMenuTab::Config)?;
+        guest
+            .key_service()?
+            .derive("com.example.notes", KeyType::X25519)?;
+        session.components().get("ant://notes-frontend")?;
+        session.end();
+
+        assert_eq!(snapshot(temp.path()), before);
+        assert!(!context.guest_gate().is_active());
+
+        // A new guest starts from scratch
+        let next = context.start_guest_session()?;
+        assert_eq!(
+            ConfigService::from_context(next.context())?.get_launcher_manifest()?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_shows_the_running_session() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_800_000_000));
+        let mut context = host(&temp)?;
+        context.clock = clock.clone();
+        let status = StatusService::new().with_guest_gate(context.guest_gate());
+        assert_eq!(status.get_guest_session()?, None);
+
+        let session = context.start_guest_session_for(Duration::from_secs(600))?;
+        let shown = status.get_guest_session()?.unwrap();
+        assert_eq!(shown, *session.info());
+        assert_eq!(shown.address, session.user_id());
+        assert_eq!(shown.started_at, 1_800_000_000);
+        assert_eq!(shown.expires_at, 1_800_000_600);
+        assert_eq!(session.context().clock().now_unix(), 1_800_000_000);
+
+        clock.advance(Duration::from_secs(599));
+        assert_eq!(session.remaining(), Duration::from_secs(1));
+        assert!(!session.is_expired());
+        clock.advance(Duration::from_secs(1));
+        assert!(session.is_expired());
+
+        drop(session);
+        assert_eq!(status.get_guest_session()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expiry_resolves_at_the_time_limit() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = host(&temp)?;
+        let runtime = tokio::runtime::Runtime::new()?;
+
+        let session = context.start_guest_session_for(Duration::from_secs(600))?;
+        let waited = runtime.block_on(async {
+            tokio::time::timeout(Duration::from_millis(50), session.expiry()).await
+        });
+        assert!(waited.is_err());
+        drop(session);
+
+        let session = context.start_guest_session_for(Duration::ZERO)?;
+        runtime.block_on(async {
+            tokio::time::timeout(Duration::from_secs(5), session.expiry()).await
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+//! Shared runtime context of the core services
+//!
+//! An [`OsnovaContext`] owns what outlives any single service: the storage
+//! root and the background tasks and flush hooks that have to be stopped
+//! in order when the shell exits. It also carries the services that need
+//! key material once the identity is unlocked (see [`unlock`]).
+//!
+//! [`OsnovaContext::new_ephemeral`] creates a context that never touches
+//! the disk: services opened through [`OsnovaContext::open_database`] and
+//! [`OsnovaContext::file_store`] share an in-memory database and file
+//! store, and time comes from a [`MockClock`]. Guest sessions (see
+//! [`guest`]) run on such a context.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::context::{OsnovaContext, DEFAULT_SHUTDOWN_TIMEOUT};
+//!
+//! # async fn example() {
+//! let context = OsnovaContext::new("/tmp/osnova");
+//! context.spawn("ticker", |token| async move {
+//!     token.cancelled().await;
+//! });
+//! context.on_flush("services", &[], || Ok(()));
+//!
+//! let report = context.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await;
+//! assert!(report.is_clean());
+//! # }
+//! ```
+
+pub mod guest;
+pub mod preflight;
+pub mod shutdown;
+pub mod startup;
+pub mod unlock;
+
+use crate::services::events::EventBus;
+use crate::storage::{FileStorage, FileStore, MemoryFileStorage, SqlStorage};
+use crate::time::{default_clock, MockClock, SharedClock};
+use anyhow::Result;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub use guest::{
+    GuestGate, GuestModeRestricted, GuestRestriction, GuestSession, GuestSessionInfo,
+    DEFAULT_GUEST_SESSION_DURATION,
+};
+pub use shutdown::{FlushFailure, Shutdown, ShutdownReport, TaskHandle, DEFAULT_SHUTDOWN_TIMEOUT};
+pub use unlock::{ContextUnlocked, NotYetUnlocked, UnlockGate, UnlockState};
+
+/// Unix time an ephemeral context's clock starts at
+pub const EPHEMERAL_START_UNIX: u64 = 1_700_000_000;
+
+/// Runtime context shared by the services of one shell
+pub struct OsnovaContext {
+    storage_path: PathBuf,
+    shutdown: Shutdown,
+    clock: SharedClock,
+    ephemeral: Option<Ephemeral>,
+    unlock: UnlockGate,
+    events: Option<EventBus>,
+    guest: GuestGate,
+    component_cache: Option<PathBuf>,
+}
+
+/// In-memory backends of an ephemeral context
+struct Ephemeral {
+    /// Name of the shared in-memory database
+    database: String,
+    /// Keeps the database alive while services come and go
+    _keeper: Mutex<SqlStorage>,
+    files: Arc<MemoryFileStorage>,
+    clock: Arc<MockClock>,
+}
+
+impl OsnovaContext {
+    /// Create a context rooted at a storage directory
+    pub fn new(storage_path: impl Into<PathBuf>) -> Self {
+        Self {
+            storage_path: storage_path.into(),
+            shutdown: Shutdown::new(),
+            clock: default_clock(),
+            ephemeral: None,
+            unlock: UnlockGate::default(),
+            events: None,
+            guest: GuestGate::default(),
+            component_cache: None,
+        }
+    }
+
+    /// Create a context backed entirely by memory
+    ///
+    /// Nothing is written to disk. The storage path is empty, so services
+    /// must be opened through [`open_database`](Self::open_database) and
+    /// [`file_store`](Self::file_store) rather than from the path. The
+    /// clock starts at [`EPHEMERAL_START_UNIX`] and only moves when
+    /// advanced through [`mock_clock`](Self::mock_clock).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the in-memory database cannot be created
+    pub fn new_ephemeral() -> Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let database = format!(
+            "osnova-ephemeral-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let keeper = SqlStorage::new_shared_memory(&database)?;
+        let clock = Arc::new(MockClock::new(EPHEMERAL_START_UNIX));
+
+        Ok(Self {
+            storage_path: PathBuf::new(),
+            shutdown: Shutdown::new(),
+            clock: clock.clone(),
+            ephemeral: Some(Ephemeral {
+                database,
+                _keeper: Mutex::new(keeper),
+                files: Arc::new(MemoryFileStorage::new()),
+                clock,
+            }),
+            unlock: UnlockGate::default(),
+            events: None,
+            guest: GuestGate::default(),
+            component_cache: None,
+        })
+    }
+
+    /// Publish context changes, such as the unlock, on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Event bus context changes are published on, if any
+    pub fn events(&self) -> Option<EventBus> {
+        self.events.clone()
+    }
+
+    /// Storage root the services are opened at (empty when ephemeral)
+    pub fn storage_path(&self) -> &Path {
+        &self.storage_path
+    }
+
+    /// Whether the context is backed by memory rather than the disk
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral.is_some()
+    }
+
+    /// Clock the services of this context read
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// The hand-driven clock of an ephemeral context
+    pub fn mock_clock(&self) -> Option<Arc<MockClock>> {
+        self.ephemeral.as_ref().map(|e| e.clock.clone())
+    }
+
+    /// Open a connection to the context's database
+    ///
+    /// Every connection of an ephemeral context sees the same in-memory
+    /// database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened
+    pub fn open_database(&self) -> Result<SqlStorage> {
+        match &self.ephemeral {
+            Some(ephemeral) => SqlStorage::new_shared_memory(&ephemeral.database),
+            None => SqlStorage::new(self.storage_path.join("osnova.db")),
+        }
+    }
+
+    /// The encrypted file store at the storage root
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage directory cannot be opened
+    pub fn file_store(&self) -> Result<Arc<dyn FileStore>> {
+        Ok(match &self.ephemeral {
+            Some(ephemeral) => ephemeral.files.clone(),
+            None => Arc::new(FileStorage::new(&self.storage_path)?),
+        })
+    }
+
+    /// Spawn a background task that is drained at shutdown
+    ///
+    /// See [`Shutdown::spawn`].
+    pub fn spawn<F, Fut>(&self, name: &str, task: F) -> TaskHandle
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown.spawn(name, task)
+    }
+
+    /// Register a hook to run at shutdown after the named hooks
+    ///
+    /// See [`Shutdown::on_flush`].
+    pub fn on_flush<F>(&self, name: &str, after: &[&str], hook: F)
+    where
+        F: FnOnce() -> anyhow::Result<()> + Send + 'static,
+    {
+        self.shutdown.on_flush(name, after, hook);
+    }
+
+    /// A token cancelled when shutdown starts
+    pub fn cancellation(&self) -> CancellationToken {
+        self.shutdown.cancellation()
+    }
+
+    /// Whether shutdown has started
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_shutting_down()
+    }
+
+    /// Drain background tasks and run flush hooks
+    ///
+    /// See [`Shutdown::shutdown`].
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.shutdown.shutdown(timeout).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use crate::components::ComponentDownloader;
+    use crate::manifest::ComponentSchema;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::key_cocoon::KeyType;
+    use crate::network::NetworkOptions;
+    use crate::services::{ConfigService, KeyService};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    /// Files and directories directly under the working directory
+    fn working_directory_entries() -> Vec<std::ffi::OsString> {
+        let mut entries: Vec<_> = std::fs::read_dir(std::env::current_dir().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn test_ephemeral_context_flows_stay_off_disk() -> Result<()> {
+        let before = working_directory_entries();
+        let context = OsnovaContext::new_ephemeral()?;
+        assert!(context.is_ephemeral());
+        assert_eq!(context.storage_path(), Path::new(""));
+
+        // Keys survive a service restart within the context
+        let cocoon_key = [7u8; 32];
+        let derived = {
+            let keys = KeyService::from_context(&context, &cocoon_key)?;
+            keys.initialize(&[1u8; 32])?;
+            keys.derive("com.test.wallet", KeyType::Ed25519)?
+        };
+        let keys = KeyService::from_context(&context, &cocoon_key)?;
+        let secret = keys.get_by_public_key(&derived.public_key)?;
+        assert_eq!(secret.component_id, "com.test.wallet");
+
+        // Services share the database and the file store
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "ant://icon",
+            "Test app",
+            vec![],
+        )?;
+        context.open_database()?.upsert_application(&app)?;
+        let writer = ConfigService::from_context(&context)?;
+        writer.set_launcher_manifest("ant://launcher")?;
+        writer.set_app_config(
+            "com.test.app",
+            "user-1",
+            HashMap::from([("theme".to_string(), serde_json::json!("dark"))]),
+        )?;
+        let reader = ConfigService::from_context(&context)?;
+        assert_eq!(
+            reader.get_launcher_manifest()?.as_deref(),
+            Some("ant://launcher")
+        );
+        let config = reader.get_app_config("com.test.app", "user-1")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+
+        // Contexts are isolated from each other
+        let other = OsnovaContext::new_ephemeral()?;
+        assert_eq!(
+            ConfigService::from_context(&other)?.get_launcher_manifest()?,
+            None
+        );
+
+        // Time only moves when the test moves it
+        let clock = context.clock();
+        assert_eq!(clock.now_unix(), EPHEMERAL_START_UNIX);
+        context
+            .mock_clock()
+            .unwrap()
+            .advance(Duration::from_secs(60));
+        assert_eq!(clock.now_unix(), EPHEMERAL_START_UNIX + 60);
+
+        assert_eq!(working_directory_entries(), before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_context_opens_storage_at_its_root() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = OsnovaContext::new(temp.path());
+        assert!(!context.is_ephemeral());
+        assert!(context.mock_clock().is_none());
+
+        let config = ConfigService::from_context(&context)?;
+        config.set_server("https://server.example.com")?;
+        assert!(temp.path().join("osnova.db").exists());
+        assert!(temp.path().join("config/system.json").exists());
+
+        let reopened = ConfigService::new(temp.path())?;
+        assert_eq!(
+            reopened.get_server()?.as_deref(),
+            Some("https://server.example.com")
+        );
+        Ok(())
+    }
+
+    /// Serve connections that never answer, so downloads stay in flight
+    async fn spawn_stalled_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_download() {
+        let temp = TempDir::new().unwrap();
+        let context = OsnovaContext::new(temp.path());
+        assert_eq!(context.storage_path(), temp.path());
+
+        let base = spawn_stalled_server().await;
+        let component = ComponentSchema {
+            id: format!("{}/stalled", base),
+            name: "shutdown-drain-test".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+        let prepared = ComponentDownloader::prepared_path(&component);
+        std::fs::write(&prepared, b"partial").unwrap();
+
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024).unwrap();
+        let downloader =
+            ComponentDownloader::new(cache, None).with_http(crate::http::local_fetcher());
+        let cancelled = std::sync::Arc::new(std::sync::Mutex::new(false));
+        let flag = cancelled.clone();
+        context.spawn("download", move |token| async move {
+            let options = NetworkOptions::default().with_cancellation(token);
+            let result = downloader.download_with(&component, &options).await;
+            *flag.lock().unwrap() = result.is_err();
+        });
+
+        // Let the request reach the stalled server
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let report = context.shutdown(Duration::from_secs(5)).await;
+
+        assert!(context.is_shutting_down());
+        assert!(context.cancellation().is_cancelled());
+        assert!(report.is_clean());
+        assert_eq!(report.drained, vec!["download".to_string()]);
+        assert!(*cancelled.lock().unwrap());
+        assert!(!prepared.exists());
+    }
+}
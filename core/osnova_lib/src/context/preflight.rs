@@ -0,0 +1,448 @@
+//! Startup validation and the daemon's exit codes
+//!
+//! Before `osnova-daemon` starts any service it runs the checks in [`run`]:
+//! the configuration loads, the storage belongs to this install, the
+//! database schema is one this release can migrate, the platform keystore
+//! unseals the stored identity, and the RPC address can be bound. Nothing
+//! is written while checking, so `osnova-daemon check` can validate a
+//! deployment without touching it, printing the [`CheckReport`] as JSON
+//! and exiting with the [`ExitCode`] of the first failure.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::context::preflight;
+//! use osnova_lib::context::startup::StartupConfig;
+//!
+//! # fn example() -> anyhow::Result<()> {
+//! let startup = StartupConfig::load(None, std::env::vars())?;
+//! let report = preflight::run(&startup);
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//! std::process::exit(report.exit_code().code().into());
+//! # }
+//! ```
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::path::Path;
+
+use super::startup::{ConfigKeyError, StartupConfig};
+use crate::error::OsnovaError;
+use crate::services::IdentityService;
+use crate::storage::{ownership, SqlStorage, SCHEMA_VERSION};
+
+/// Database file under the storage path
+const DATABASE_FILE: &str = "osnova.db";
+
+/// Exit status of `osnova-daemon`
+///
+/// The numbers are a contract with scripts and supervisors: they never
+/// change meaning, and a new kind of failure gets a new number.
+///
+/// Codes 2 to 5 mean the daemon cannot start until someone fixes its
+/// configuration or data, so a supervisor should not restart it in a loop.
+/// A bind failure (6) usually clears once the previous instance releases
+/// the port, so it is left restartable:
+///
+/// ```text
+/// # /etc/systemd/system/osnova.service
+/// [Service]
+/// ExecStartPre=/usr/bin/osnova-daemon check --config /etc/osnova/config.toml
+/// ExecStart=/usr/bin/osnova-daemon --config /etc/osnova/config.toml
+/// Restart=on-failure
+/// RestartSec=5
+/// RestartPreventExitStatus=2 3 4 5
+/// ```
+///
+/// `check` binds the RPC address itself, so it fails against a daemon that
+/// is already listening there. Run it before the daemon starts (as in
+/// `ExecStartPre` above, or as a container's init step), not as a liveness
+/// probe of a running daemon:
+///
+/// ```text
+/// # Kubernetes init container
+/// initContainers:
+///   - name: osnova-check
+///     image: osnova
+///     command: ["osnova-daemon", "check", "--config", "/etc/osnova/config.toml"]
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ExitCode {
+    /// Started and stopped cleanly, or every check passed
+    Success = 0,
+    /// Any failure without a code of its own
+    Failure = 1,
+    /// Bad command line, config file, or `OSNOVA_*` variable
+    InvalidConfig = 2,
+    /// The storage belongs to another install id
+    StorageOwnedElsewhere = 3,
+    /// The database was written by a newer release
+    SchemaTooNew = 4,
+    /// The platform keystore is missing or cannot unseal the identity
+    KeystoreUnavailable = 5,
+    /// The RPC address could not be bound
+    BindFailed = 6,
+}
+
+impl ExitCode {
+    /// Numeric process exit status
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Code for an error opening configuration or storage
+    ///
+    /// Keystore and bind failures are not recognizable from the error
+    /// alone; the step that hit them picks their code.
+    pub fn from_error(error: &Error) -> Self {
+        if error.downcast_ref::<ConfigKeyError>().is_some() {
+            return Self::InvalidConfig;
+        }
+        match error.downcast_ref::<OsnovaError>() {
+            Some(OsnovaError::StorageOwnedElsewhere { .. }) => Self::StorageOwnedElsewhere,
+            Some(OsnovaError::SchemaTooNew { .. }) => Self::SchemaTooNew,
+            _ => Self::Failure,
+        }
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        Self::from(code.code())
+    }
+}
+
+/// Outcome of one startup check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    /// The check passed
+    Passed,
+    /// The check failed; the daemon would not start
+    Failed,
+    /// Not run, because a check it depends on failed
+    Skipped,
+}
+
+/// One startup check in a [`CheckReport`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+    /// Check name: `config`, `storage`, `schema`, `keystore` or `socket`
+    pub name: String,
+    /// Outcome
+    pub status: CheckStatus,
+    /// What was found, or why the check failed or was skipped
+    pub detail: String,
+    /// Exit code the failure maps to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<u8>,
+}
+
+/// Result of every startup check, printed by `osnova-daemon check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    /// Whether every check passed
+    pub ok: bool,
+    /// Exit code of the first failed check, 0 if none failed
+    pub exit_code: u8,
+    /// Checks in the order they ran
+    pub checks: Vec<Check>,
+}
+
+impl CheckReport {
+    /// Report for a configuration that did not load; nothing else is checked
+    pub fn invalid_config(error: &Error) -> Self {
+        let mut checks = Checks::default();
+        checks.fail("config", ExitCode::InvalidConfig, format!("{:#}", error));
+        for name in ["storage", "schema", "keystore", "socket"] {
+            checks.skip(name, "configuration did not load");
+        }
+        checks.finish()
+    }
+
+    /// Exit code of the report
+    pub fn exit_code(&self) -> ExitCode {
+        match self.exit_code {
+            0 => ExitCode::Success,
+            2 => ExitCode::InvalidConfig,
+            3 => ExitCode::StorageOwnedElsewhere,
+            4 => ExitCode::SchemaTooNew,
+            5 => ExitCode::KeystoreUnavailable,
+            6 => ExitCode::BindFailed,
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+/// Checks collected so far
+#[derive(Default)]
+struct Checks(Vec<Check>);
+
+impl Checks {
+    fn pass(&mut self, name: &str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Passed, detail.into(), None);
+    }
+
+    fn fail(&mut self, name: &str, code: ExitCode, detail: String) {
+        self.push(name, CheckStatus::Failed, detail, Some(code.code()));
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.push(name, CheckStatus::Skipped, reason.to_string(), None);
+    }
+
+    fn push(&mut self, name: &str, status: CheckStatus, detail: String, exit_code: Option<u8>) {
+        self.0.push(Check {
+            name: name.to_string(),
+            status,
+            detail,
+            exit_code,
+        });
+    }
+
+    fn finish(self) -> CheckReport {
+        let exit_code = self
+            .0
+            .iter()
+            .find_map(|check| check.exit_code)
+            .unwrap_or(ExitCode::Success.code());
+        CheckReport {
+            ok: exit_code == ExitCode::Success.code(),
+            exit_code,
+            checks: self.0,
+        }
+    }
+}
+
+/// Run every startup check for a loaded configuration, as this OS user's
+/// install
+pub fn run(startup: &StartupConfig) -> CheckReport {
+    run_for_install(startup, ownership::local_install_id())
+}
+
+/// Run every startup check as the install `install_id`
+///
+/// Storage that does not exist yet passes: the daemon creates it on start.
+/// The schema and keystore checks are skipped when the storage belongs to
+/// another install, since its data is not this install's to read.
+pub fn run_for_install(startup: &StartupConfig, install_id: &str) -> CheckReport {
+    let mut checks = Checks::default();
+    let storage_path = startup.storage_path();
+    checks.pass("config", format!("storage at {}", storage_path.display()));
+
+    match check_storage(storage_path, install_id) {
+        Ok(detail) => {
+            checks.pass("storage", detail);
+            match check_schema(&storage_path.join(DATABASE_FILE)) {
+                Ok(detail) => checks.pass("schema", detail),
+                Err(e) => checks.fail("schema", ExitCode::from_error(&e), format!("{:#}", e)),
+            }
+            match IdentityService::probe_keystore(storage_path) {
+                Ok(true) => checks.pass("keystore", "stored identity unseals"),
+                Ok(false) => checks.pass("keystore", "keystore available, no identity stored"),
+                Err(e) => checks.fail(
+                    "keystore",
+                    ExitCode::KeystoreUnavailable,
+                    format!("{:#}", e),
+                ),
+            }
+        }
+        Err(e) => {
+            checks.fail("storage", ExitCode::from_error(&e), format!("{:#}", e));
+            checks.skip("schema", "storage check failed");
+            checks.skip("keystore", "storage check failed");
+        }
+    }
+
+    let addr = startup.rpc_listen();
+    match TcpListener::bind(addr) {
+        Ok(_) => checks.pass("socket", format!("{} can be bound", addr)),
+        Err(e) => checks.fail(
+            "socket",
+            ExitCode::BindFailed,
+            format!("Failed to bind {}: {}", addr, e),
+        ),
+    }
+
+    checks.finish()
+}
+
+/// Check that no other install owns the storage root or its database
+fn check_storage(root: &Path, install_id: &str) -> anyhow::Result<String> {
+    if !root.exists() {
+        return Ok(format!("{} will be created", root.display()));
+    }
+    ownership::verify(&ownership::root_lock_path(root), root, install_id)?;
+    let database = root.join(DATABASE_FILE);
+    ownership::verify(
+        &ownership::database_lock_path(&database),
+        &database,
+        install_id,
+    )?;
+    Ok(format!("{} belongs to this install", root.display()))
+}
+
+/// Dry run of the schema migration: read the stored version only
+fn check_schema(database: &Path) -> anyhow::Result<String> {
+    Ok(match SqlStorage::check_schema(database)? {
+        None => format!("new database at version {}", SCHEMA_VERSION),
+        Some(version) if version == SCHEMA_VERSION => format!("version {}", version),
+        Some(version) => format!("version {} migrates to {}", version, SCHEMA_VERSION),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const INSTALL: &str = "test-install";
+
+    fn startup(storage: &Path, rpc_listen: &str) -> StartupConfig {
+        let env = [
+            ("OSNOVA_STORAGE_PATH", storage.to_str().unwrap()),
+            ("OSNOVA_RPC_LISTEN", rpc_listen),
+        ];
+        StartupConfig::load(
+            None,
+            env.map(|(key, value)| (key.to_string(), value.to_string())),
+        )
+        .unwrap()
+    }
+
+    fn status(report: &CheckReport, name: &str) -> CheckStatus {
+        report
+            .checks
+            .iter()
+            .find(|check| check.name == name)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn test_fresh_storage_passes() {
+        let temp = TempDir::new().unwrap();
+        let storage = temp.path().join("data");
+        let report = run_for_install(&startup(&storage, "127.0.0.1:0"), INSTALL);
+
+        assert!(report.ok, "{:?}", report);
+        assert_eq!(report.exit_code(), ExitCode::Success);
+        assert!(report
+            .checks
+            .iter()
+            .all(|c| c.status == CheckStatus::Passed));
+        // Checking writes nothing
+        assert!(!storage.exists());
+    }
+
+    #[test]
+    fn test_report_structure() {
+        let temp = TempDir::new().unwrap();
+        let report = run_for_install(&startup(temp.path(), "127.0.0.1:0"), INSTALL);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["exitCode"], 0);
+        let names: Vec<_> = json["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|check| {
+                assert_eq!(check["status"], "passed");
+                assert!(check["detail"].is_string());
+                assert!(check.get("exitCode").is_none());
+                check["name"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(names, ["config", "storage", "schema", "keystore", "socket"]);
+        assert_eq!(serde_json::from_value::<CheckReport>(json).unwrap(), report);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let temp = TempDir::new().unwrap();
+        let config = temp.path().join("config.toml");
+        std::fs::write(&config, "rpc_listen = \"not an address\"\n").unwrap();
+        let error = StartupConfig::load(Some(&config), std::iter::empty()).unwrap_err();
+        assert_eq!(ExitCode::from_error(&error), ExitCode::InvalidConfig);
+
+        let report = CheckReport::invalid_config(&error);
+        assert!(!report.ok);
+        assert_eq!(report.exit_code, 2);
+        assert_eq!(report.exit_code(), ExitCode::InvalidConfig);
+        assert_eq!(status(&report, "config"), CheckStatus::Failed);
+        assert_eq!(status(&report, "socket"), CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn test_storage_owned_elsewhere() {
+        let temp = TempDir::new().unwrap();
+        drop(
+            SqlStorage::new_for_install(temp.path().join(DATABASE_FILE), "other-install").unwrap(),
+        );
+
+        let report = run_for_install(&startup(temp.path(), "127.0.0.1:0"), INSTALL);
+        assert_eq!(report.exit_code(), ExitCode::StorageOwnedElsewhere);
+        assert_eq!(report.exit_code, 3);
+        assert_eq!(status(&report, "schema"), CheckStatus::Skipped);
+        assert_eq!(status(&report, "keystore"), CheckStatus::Skipped);
+        assert_eq!(status(&report, "socket"), CheckStatus::Passed);
+    }
+
+    #[test]
+    fn test_schema_too_new() {
+        let temp = TempDir::new().unwrap();
+        let database = temp.path().join(DATABASE_FILE);
+        drop(SqlStorage::new_for_install(&database, INSTALL).unwrap());
+        rusqlite::Connection::open(&database)
+            .unwrap()
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        let report = run_for_install(&startup(temp.path(), "127.0.0.1:0"), INSTALL);
+        assert_eq!(report.exit_code(), ExitCode::SchemaTooNew);
+        assert_eq!(report.exit_code, 4);
+        assert_eq!(status(&report, "storage"), CheckStatus::Passed);
+    }
+
+    #[test]
+    fn test_keystore_unavailable() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("identity")).unwrap();
+        std::fs::write(temp.path().join("identity/root.enc"), b"sealed elsewhere").unwrap();
+
+        let report = run_for_install(&startup(temp.path(), "127.0.0.1:0"), INSTALL);
+        assert_eq!(report.exit_code(), ExitCode::KeystoreUnavailable);
+        assert_eq!(report.exit_code, 5);
+    }
+
+    #[test]
+    fn test_bind_failed() {
+        let temp = TempDir::new().unwrap();
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap().to_string();
+
+        let report = run_for_install(&startup(temp.path(), &addr), INSTALL);
+        assert_eq!(report.exit_code(), ExitCode::BindFailed);
+        assert_eq!(report.exit_code, 6);
+        assert_eq!(status(&report, "keystore"), CheckStatus::Passed);
+    }
+
+    #[test]
+    fn test_first_failure_sets_exit_code() {
+        let temp = TempDir::new().unwrap();
+        drop(
+            SqlStorage::new_for_install(temp.path().join(DATABASE_FILE), "other-install").unwrap(),
+        );
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap().to_string();
+
+        let report = run_for_install(&startup(temp.path(), &addr), INSTALL);
+        assert_eq!(report.exit_code(), ExitCode::StorageOwnedElsewhere);
+        assert_eq!(status(&report, "socket"), CheckStatus::Failed);
+    }
+}
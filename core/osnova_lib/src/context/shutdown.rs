@@ -0,0 +1,312 @@
+//! Structured shutdown of background tasks
+//!
+//! Background work (the search indexer, update scheduler, process watchdog,
+//! event forwarders) is spawned through a [`Shutdown`], which hands each
+//! task a cancellation token derived from one shared root. Shutting down
+//! cancels the root, waits for the tasks to return up to a deadline, aborts
+//! whatever is still running, and then runs flush hooks in dependency
+//! order. The returned [`ShutdownReport`] names every task that had to be
+//! abandoned, so stuck work shows up in logs instead of silently losing
+//! data.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// How long shutdown waits for tasks to drain unless told otherwise
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+type FlushFn = Box<dyn FnOnce() -> Result<()> + Send>;
+
+struct FlushHook {
+    name: String,
+    after: Vec<String>,
+    run: FlushFn,
+}
+
+/// Handle to a task spawned through [`Shutdown::spawn`]
+///
+/// Dropping the handle leaves the task running; it still stops at shutdown.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    name: String,
+    token: CancellationToken,
+}
+
+impl TaskHandle {
+    /// Name the task was spawned with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Ask the task to stop, without waiting for it
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether the task has been asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// A flush hook that returned an error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushFailure {
+    /// Hook name
+    pub hook: String,
+    /// Error the hook returned
+    pub error: String,
+}
+
+/// Outcome of a shutdown
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownReport {
+    /// Tasks that returned before the deadline
+    pub drained: Vec<String>,
+    /// Tasks still running at the deadline, which were aborted
+    pub abandoned: Vec<String>,
+    /// Flush hooks that ran, in the order they ran
+    pub flushed: Vec<String>,
+    /// Flush hooks that failed
+    pub flush_failures: Vec<FlushFailure>,
+}
+
+impl ShutdownReport {
+    /// Whether every task drained and every hook succeeded
+    pub fn is_clean(&self) -> bool {
+        self.abandoned.is_empty() && self.flush_failures.is_empty()
+    }
+}
+
+/// Tracks background tasks and flush hooks until shutdown
+#[derive(Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: Mutex<Vec<(String, JoinHandle<()>)>>,
+    hooks: Mutex<Vec<FlushHook>>,
+}
+
+impl Shutdown {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether shutdown has started
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// A token cancelled when shutdown starts
+    ///
+    /// For work that is not spawned through [`Self::spawn`] but should
+    /// still stop early, such as a long download.
+    pub fn cancellation(&self) -> CancellationToken {
+        self.token.child_token()
+    }
+
+    /// Spawn a tracked task on the current Tokio runtime
+    ///
+    /// `task` receives a token that is cancelled at shutdown or through the
+    /// returned handle; the task should return promptly once it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn spawn<F, Fut>(&self, name: &str, task: F) -> TaskHandle
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.token.child_token();
+        let handle = tokio::spawn(task(token.clone()));
+
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|(_, handle)| !handle.is_finished());
+        tasks.push((name.to_string(), handle));
+
+        TaskHandle {
+            name: name.to_string(),
+            token,
+        }
+    }
+
+    /// Register a hook to run once at shutdown, after the tasks drain
+    ///
+    /// Hooks run after every hook named in `after`; otherwise they run in
+    /// registration order. Names in `after` that were never registered are
+    /// ignored, and hooks caught in a dependency cycle run last.
+    pub fn on_flush<F>(&self, name: &str, after: &[&str], hook: F)
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        self.hooks.lock().unwrap().push(FlushHook {
+            name: name.to_string(),
+            after: after.iter().map(|dep| dep.to_string()).collect(),
+            run: Box::new(hook),
+        });
+    }
+
+    /// Stop all tracked tasks and run the flush hooks
+    ///
+    /// Tasks get `timeout` in total to return after cancellation; any still
+    /// running are aborted and reported as abandoned. Hooks then run even if
+    /// tasks were abandoned or earlier hooks failed. Calling this again only
+    /// drains tasks spawned since.
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.token.cancel();
+        let deadline = Instant::now() + timeout;
+        let mut report = ShutdownReport::default();
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for (name, mut handle) in tasks {
+            // A task that panicked has still finished
+            match tokio::time::timeout_at(deadline, &mut handle).await {
+                Ok(_) => report.drained.push(name),
+                Err(_) => {
+                    handle.abort();
+                    report.abandoned.push(name);
+                }
+            }
+        }
+
+        let hooks = std::mem::take(&mut *self.hooks.lock().unwrap());
+        for hook in flush_order(hooks) {
+            if let Err(e) = (hook.run)() {
+                report.flush_failures.push(FlushFailure {
+                    hook: hook.name.clone(),
+                    error: format!("{:#}", e),
+                });
+            }
+            report.flushed.push(hook.name);
+        }
+
+        report
+    }
+}
+
+/// Order hooks so each runs after its dependencies, stable otherwise
+fn flush_order(mut pending: Vec<FlushHook>) -> Vec<FlushHook> {
+    let mut ordered = Vec::with_capacity(pending.len());
+
+    loop {
+        // Unregistered dependencies are never pending, so never block
+        let ready = pending.iter().position(|hook| {
+            hook.after
+                .iter()
+                .all(|dep| !pending.iter().any(|other| &other.name == dep))
+        });
+        match ready {
+            Some(index) => ordered.push(pending.remove(index)),
+            None => break,
+        }
+    }
+
+    // Whatever is left depends on itself through a cycle
+    ordered.append(&mut pending);
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_cooperative_task_drains() {
+        let shutdown = Shutdown::new();
+        let finished = Arc::new(Mutex::new(false));
+
+        let flag = finished.clone();
+        let handle = shutdown.spawn("worker", |token| async move {
+            token.cancelled().await;
+            // Cleanup after cancellation still gets to run
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            *flag.lock().unwrap() = true;
+        });
+        assert_eq!(handle.name(), "worker");
+        assert!(!shutdown.is_shutting_down());
+
+        let report = shutdown.shutdown(Duration::from_secs(5)).await;
+        assert!(report.is_clean());
+        assert_eq!(report.drained, vec!["worker".to_string()]);
+        assert!(*finished.lock().unwrap());
+        assert!(shutdown.is_shutting_down());
+        assert!(handle.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_stuck_task_is_abandoned() {
+        let shutdown = Shutdown::new();
+        shutdown.spawn("quick", |token| async move { token.cancelled().await });
+        shutdown.spawn("stuck", |_token| async move {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        });
+
+        let flushed = Arc::new(Mutex::new(false));
+        let flag = flushed.clone();
+        shutdown.on_flush("state", &[], move || {
+            *flag.lock().unwrap() = true;
+            Ok(())
+        });
+
+        let report = shutdown.shutdown(Duration::from_millis(100)).await;
+        assert!(!report.is_clean());
+        assert_eq!(report.drained, vec!["quick".to_string()]);
+        assert_eq!(report.abandoned, vec!["stuck".to_string()]);
+        // Hooks still run after abandoning a task
+        assert!(*flushed.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_flush_hooks_run_in_dependency_order() {
+        let shutdown = Shutdown::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for (name, after) in [
+            ("services", &["backends", "network"][..]),
+            ("backends", &[][..]),
+            ("audit", &["services"][..]),
+            ("network", &["missing"][..]),
+        ] {
+            let order = order.clone();
+            shutdown.on_flush(name, after, move || {
+                order.lock().unwrap().push(name);
+                if name == "backends" {
+                    anyhow::bail!("one backend refused to stop");
+                }
+                Ok(())
+            });
+        }
+
+        let report = shutdown.shutdown(Duration::from_secs(1)).await;
+        let expected = vec!["backends", "network", "services", "audit"];
+        assert_eq!(*order.lock().unwrap(), expected);
+        assert_eq!(report.flushed, expected);
+        assert_eq!(report.flush_failures.len(), 1);
+        assert_eq!(report.flush_failures[0].hook, "backends");
+
+        // Hooks run once
+        let report = shutdown.shutdown(Duration::from_secs(1)).await;
+        assert!(report.flushed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_hooks_still_run() {
+        let shutdown = Shutdown::new();
+        shutdown.on_flush("a", &["b"], || Ok(()));
+        shutdown.on_flush("b", &["a"], || Ok(()));
+        shutdown.on_flush("c", &[], || Ok(()));
+
+        let report = shutdown.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(report.flushed, vec!["c", "a", "b"]);
+    }
+}
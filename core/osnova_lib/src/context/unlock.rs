@@ -0,0 +1,385 @@
+//! Progressive unlock of the services that need key material
+//!
+//! Reading the platform key can wait on the user (a Keychain prompt, a
+//! fingerprint), so the shell starts in two phases. Phase one opens what
+//! needs no secrets straight away: status, theme, navigation, the installed
+//! apps and the component cache. Phase two runs once the identity can be
+//! read: [`OsnovaContext::unlock`] derives the key service from it and
+//! upgrades the context in place, so the services opened in phase one keep
+//! running and start serving reads of encrypted data.
+//!
+//! Services that need phase two hold an [`UnlockGate`] and fail with
+//! [`NotYetUnlocked`] until then, which the UI turns into an "unlock to
+//! continue" prompt rather than an error. Unlocking publishes
+//! [`AppEvent::ContextUnlocked`], forwarded to frontends as
+//! [`CONTEXT_UNLOCKED_EVENT`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use osnova_lib::context::{NotYetUnlocked, OsnovaContext, UnlockState};
+//! use osnova_lib::models::identity::RootIdentity;
+//!
+//! # fn example() -> anyhow::Result<()> {
+//! let context = OsnovaContext::new_ephemeral()?;
+//! let error = context.key_service().err().unwrap();
+//! assert!(error.downcast_ref::<NotYetUnlocked>().is_some());
+//!
+//! let identity = RootIdentity::generate()?;
+//! context.unlock(&identity, "user-1")?;
+//! assert_eq!(context.unlock_state(), UnlockState::Unlocked);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use super::OsnovaContext;
+use crate::models::identity::RootIdentity;
+use crate::services::events::AppEvent;
+use crate::services::KeyService;
+
+/// Event name used when surfacing the unlock to frontends
+pub const CONTEXT_UNLOCKED_EVENT: &str = "context-unlocked";
+
+/// Whether the services that need key material are available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UnlockState {
+    /// Only services that need no key material are running
+    Locked,
+    /// The identity was read and every service is available
+    Unlocked,
+}
+
+/// An operation needs the identity, which has not been unlocked yet
+///
+/// Not a failure: the operation can be retried once the context is
+/// unlocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotYetUnlocked {
+    /// Operation that was refused
+    pub operation: String,
+}
+
+impl fmt::Display for NotYetUnlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} needs the identity to be unlocked", self.operation)
+    }
+}
+
+impl std::error::Error for NotYetUnlocked {}
+
+/// The context was unlocked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextUnlocked {
+    /// Unix timestamp of the unlock
+    pub unlocked_at: u64,
+}
+
+/// Services of phase two
+struct Unlocked {
+    keys: Arc<KeyService>,
+}
+
+/// Handle on a context's unlock state, held by services with operations
+/// that need phase two
+///
+/// Cloning the gate yields another handle to the same state.
+#[derive(Clone, Default)]
+pub struct UnlockGate {
+    unlocked: Arc<RwLock<Option<Unlocked>>>,
+    epoch: Arc<AtomicU64>,
+}
+
+impl UnlockGate {
+    /// Current unlock state
+    pub fn state(&self) -> UnlockState {
+        match *self.unlocked.read().unwrap() {
+            Some(_) => UnlockState::Unlocked,
+            None => UnlockState::Locked,
+        }
+    }
+
+    /// Refuse an operation until the context is unlocked
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotYetUnlocked`] naming the operation while locked
+    pub fn require(&self, operation: &str) -> std::result::Result<(), NotYetUnlocked> {
+        match self.state() {
+            UnlockState::Unlocked => Ok(()),
+            UnlockState::Locked => Err(NotYetUnlocked {
+                operation: operation.to_string(),
+            }),
+        }
+    }
+
+    /// Counter bumped by every unlock and lock
+    ///
+    /// Caches of per-user data remember the epoch they were filled in and
+    /// drop their entries when it moves, so nothing read under one identity
+    /// is served after a sign-out or under another.
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+}
+
+impl fmt::Debug for UnlockGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnlockGate")
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl OsnovaContext {
+    /// Whether phase two has run (see [`unlock`](Self::unlock))
+    pub fn unlock_state(&self) -> UnlockState {
+        self.unlock.state()
+    }
+
+    /// A gate for services with operations that need phase two
+    pub fn unlock_gate(&self) -> UnlockGate {
+        self.unlock.clone()
+    }
+
+    /// Run phase two: open the key service of a user's identity
+    ///
+    /// Key cocoons written under the legacy key are re-encrypted first, and
+    /// those from before real public key derivation migrated, which
+    /// publishes [`AppEvent::KeysMigrated`] once.
+    /// Services opened before keep running; those holding the
+    /// [`unlock_gate`](Self::unlock_gate) start serving their encrypted
+    /// operations. Publishes [`AppEvent::ContextUnlocked`] on the context's
+    /// event bus.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context is already unlocked or the key
+    /// service cannot be opened
+    pub fn unlock(&self, identity: &RootIdentity, user_id: &str) -> Result<Arc<KeyService>> {
+        let mut unlocked = self.unlock.unlocked.write().unwrap();
+        if unlocked.is_some() {
+            bail!("Context is already unlocked");
+        }
+
+        let cocoon_key = identity.derive_cocoon_key(user_id)?;
+        let keys = KeyService::from_context(self, &cocoon_key)?;
+        keys.migrate_cocoon_key(&identity.legacy_cocoon_key(user_id))?;
+        let migration = keys.migrate_public_keys()?;
+        let keys = Arc::new(keys);
+        *unlocked = Some(Unlocked { keys: keys.clone() });
+        self.unlock.epoch.fetch_add(1, Ordering::SeqCst);
+        drop(unlocked);
+
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::ContextUnlocked {
+                unlocked: ContextUnlocked {
+                    unlocked_at: self.clock.now_unix(),
+                },
+            });
+            if let Some(migration) = migration {
+                events.publish(AppEvent::KeysMigrated { migration });
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Drop the services of phase two, e.g. when the user signs out
+    pub fn lock(&self) {
+        *self.unlock.unlocked.write().unwrap() = None;
+        self.unlock.epoch.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The key service opened by [`unlock`](Self::unlock)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotYetUnlocked`] while the context is locked
+    pub fn key_service(&self) -> Result<Arc<KeyService>> {
+        match self.unlock.unlocked.read().unwrap().as_ref() {
+            Some(unlocked) => Ok(unlocked.keys.clone()),
+            None => Err(NotYetUnlocked {
+                operation: "keys".to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::key_cocoon::KeyType;
+    use crate::services::events::EventBus;
+    use crate::services::{
+        AppsService, BottomMenuTab, ConfigService, NavigationService, ServerStatus, StatusService,
+        Theme, UIService,
+    };
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    fn not_yet_unlocked(error: &anyhow::Error) -> Option<&str> {
+        error
+            .downcast_ref::<NotYetUnlocked>()
+            .map(|refused| refused.operation.as_str())
+    }
+
+    #[test]
+    fn test_phase_one_services_run_before_unlock() -> Result<()> {
+        let temp = TempDir::new()?;
+        let context = OsnovaContext::new(temp.path());
+        assert_eq!(context.unlock_state(), UnlockState::Locked);
+
+        let status = StatusService::new();
+        assert_eq!(status.get_server()?.status, ServerStatus::Disconnected);
+        let ui = UIService::new(temp.path(), "user-1")?;
+        ui.set_theme(Theme::Dark)?;
+        assert_eq!(ui.get_theme()?, Theme::Dark);
+        let navigation = NavigationService::new(temp.path(), "user-1")?;
+        navigation.set_bottom_menu(BottomMenuTab::Wallet)?;
+        assert!(AppsService::new(temp.path())?.list()?.is_empty());
+
+        let config = ConfigService::from_context(&context)?.with_unlock(context.unlock_gate());
+        config.set_launcher_manifest("ant://launcher")?;
+        assert_eq!(
+            config.get_launcher_manifest()?.as_deref(),
+            Some("ant://launcher")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_phase_two_apis_refuse_before_unlock() -> Result<()> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let config = ConfigService::from_context(&context)?.with_unlock(context.unlock_gate());
+
+        let error = context.key_service().err().unwrap();
+        assert_eq!(not_yet_unlocked(&error), Some("keys"));
+        let error = config.get_app_config("com.test.app", "user-1").unwrap_err();
+        assert_eq!(not_yet_unlocked(&error), Some("config.getAppConfig"));
+        let settings = HashMap::from([("theme".to_string(), serde_json::json!("dark"))]);
+        let error = config
+            .set_app_config("com.test.app", "user-1", settings)
+            .unwrap_err();
+        assert_eq!(not_yet_unlocked(&error), Some("config.setAppConfig"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlock_upgrades_context_in_place() -> Result<()> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let config =
+            Rc::new(ConfigService::from_context(&context)?.with_unlock(context.unlock_gate()));
+        let before = Rc::clone(&config);
+
+        let identity = RootIdentity::generate()?;
+        let keys = context.unlock(&identity, "user-1")?;
+        assert_eq!(context.unlock_state(), UnlockState::Unlocked);
+        assert!(Arc::ptr_eq(&keys, &context.key_service()?));
+
+        // The phase-one instance now serves encrypted reads
+        assert!(Rc::ptr_eq(&config, &before));
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "ant://icon",
+            "Test app",
+            vec![],
+        )?;
+        context.open_database()?.upsert_application(&app)?;
+        let settings = HashMap::from([("theme".to_string(), serde_json::json!("dark"))]);
+        config.set_app_config("com.test.app", "user-1", settings)?;
+        let stored = config.get_app_config("com.test.app", "user-1")?;
+        assert_eq!(
+            stored.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+
+        keys.initialize(identity.master_key())?;
+        keys.derive("com.test.wallet", KeyType::Ed25519)?;
+
+        assert!(context.unlock(&identity, "user-1").is_err());
+        context.lock();
+        assert_eq!(context.unlock_state(), UnlockState::Locked);
+        assert!(context.key_service().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlock_publishes_event() -> Result<()> {
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let context = OsnovaContext::new_ephemeral()?.with_events(events);
+
+        context.unlock(&RootIdentity::generate()?, "user-1")?;
+        assert_eq!(
+            received.try_recv()?,
+            AppEvent::ContextUnlocked {
+                unlocked: ContextUnlocked {
+                    unlocked_at: crate::context::EPHEMERAL_START_UNIX,
+                },
+            }
+        );
+        assert!(received.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlock_reports_migrated_keys_once() -> Result<()> {
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let context = OsnovaContext::new_ephemeral()?.with_events(events);
+        let identity = RootIdentity::generate()?;
+
+        // A version 1 cocoon left by a development build
+        let keys = KeyService::from_context(&context, &identity.derive_cocoon_key("user-1")?)?;
+        keys.initialize(identity.master_key())?;
+        keys.derive("com.test.wallet", KeyType::Ed25519)?;
+        keys.derive("com.test.chat", KeyType::X25519)?;
+        let cocoon_path = std::path::Path::new("identity/keys.cocoon");
+        let files = context.file_store()?;
+        let mut cocoon: serde_json::Value = serde_json::from_slice(
+            &files.read(cocoon_path, &identity.derive_cocoon_key("user-1")?)?,
+        )?;
+        cocoon["metadata"]["version"] = serde_json::json!(1);
+        let chat = &mut cocoon["derived_keys"]["com.test.chat:0"];
+        chat["public_key"] = chat["secret_key"].clone();
+        files.write(
+            cocoon_path,
+            &serde_json::to_vec(&cocoon)?,
+            &identity.derive_cocoon_key("user-1")?,
+        )?;
+
+        context.unlock(&identity, "user-1")?;
+        assert!(matches!(
+            received.try_recv()?,
+            AppEvent::ContextUnlocked { .. }
+        ));
+        let AppEvent::KeysMigrated { migration } = received.try_recv()? else {
+            panic!("expected the migration report");
+        };
+        // The Ed25519 key was already real
+        assert_eq!(migration.keys.len(), 1);
+        assert_eq!(migration.keys[0].component_id, "com.test.chat");
+
+        context.lock();
+        context.unlock(&identity, "user-1")?;
+        assert!(matches!(
+            received.try_recv()?,
+            AppEvent::ContextUnlocked { .. }
+        ));
+        assert!(received.try_recv().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! Blinded trigram index
+//!
+//! Supports substring search over short texts without storing them. Each
+//! text is split into trigrams and every trigram is replaced by a keyed
+//! BLAKE3 MAC under the index key, so an index reveals nothing about the
+//! texts to anyone without the key, even with its structure in hand. A
+//! query is blinded the same way and matches the entries holding all of
+//! its trigrams.
+//!
+//! Sealed indexes are additionally encrypted with [`CocoonEncryption`]
+//! under a key derived from a fresh random salt, so no two writes share a
+//! key and nonce.
+//!
+//! Trigram intersection can report an entry whose trigrams all occur but
+//! not contiguously; the index cannot rule that out without the text.
+//! Callers normalize texts (case folding, whitespace) the same way for
+//! indexing and querying.
+//!
+//! # Example
+//!
+//! ```
+//! use osnova_lib::crypto::blind_index::{BlindIndex, BlindIndexKey};
+//!
+//! let key = BlindIndexKey::new(&[7u8; 32]);
+//! let mut index = BlindIndex::default();
+//! index.insert(&key, "bookmarks", "grandma's recipes", usize::MAX);
+//!
+//! let sealed = key.seal(&index).unwrap();
+//! let opened = key.open(&sealed).unwrap();
+//! assert_eq!(opened.search(&key, "recip"), vec!["bookmarks"]);
+//! ```
+
+use bip39::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::encryption::CocoonEncryption;
+use crate::{OsnovaError, Result};
+
+/// Shortest query that can match, in characters
+pub const MIN_QUERY_CHARS: usize = 3;
+
+/// Bytes of the MAC kept per blinded trigram
+const TOKEN_BYTES: usize = 16;
+
+/// Bytes of random salt prefixed to a sealed index
+const SALT_BYTES: usize = 32;
+
+const MAC_CONTEXT: &str = "osnova blind index 2025 trigram mac";
+const SEAL_CONTEXT: &str = "osnova blind index 2025 seal";
+
+/// Keys for blinding trigrams and sealing indexes
+///
+/// Both are derived from one 32-byte index key, so blinding and sealing
+/// never use the same key.
+pub struct BlindIndexKey {
+    mac_key: [u8; 32],
+    seal_key: [u8; 32],
+}
+
+impl BlindIndexKey {
+    /// Derive the blinding and sealing keys from an index key
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            mac_key: blake3::derive_key(MAC_CONTEXT, key),
+            seal_key: blake3::derive_key(SEAL_CONTEXT, key),
+        }
+    }
+
+    /// Blinded trigrams of a text
+    fn tokens(&self, text: &str) -> BTreeSet<String> {
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .windows(3)
+            .map(|trigram| {
+                let trigram: String = trigram.iter().collect();
+                let mac = blake3::keyed_hash(&self.mac_key, trigram.as_bytes());
+                hex::encode(&mac.as_bytes()[..TOKEN_BYTES])
+            })
+            .collect()
+    }
+
+    /// Encrypt an index for storage
+    pub fn seal(&self, index: &BlindIndex) -> Result<Vec<u8>> {
+        let plaintext = serde_json::to_vec(index)?;
+        let mut salt = [0u8; SALT_BYTES];
+        thread_rng().fill_bytes(&mut salt);
+
+        let mut sealed = salt.to_vec();
+        sealed.extend(self.cipher(&salt).encrypt(&plaintext)?);
+        Ok(sealed)
+    }
+
+    /// Decrypt a sealed index
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data was sealed under another key or is
+    /// corrupted
+    pub fn open(&self, sealed: &[u8]) -> Result<BlindIndex> {
+        if sealed.len() < SALT_BYTES {
+            return Err(OsnovaError::Crypto("Sealed index too short".to_string()));
+        }
+        let (salt, ciphertext) = sealed.split_at(SALT_BYTES);
+        let plaintext = self.cipher(salt).decrypt(ciphertext)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn cipher(&self, salt: &[u8]) -> CocoonEncryption {
+        CocoonEncryption::new(blake3::keyed_hash(&self.seal_key, salt).as_bytes())
+    }
+}
+
+/// Index of blinded trigrams to labelled entries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlindIndex {
+    /// Label of each entry, by entry number
+    labels: Vec<String>,
+    /// Blinded trigram to the entries containing it
+    postings: BTreeMap<String, Vec<u32>>,
+    /// Postings across all trigrams, the measure of the index size
+    size: usize,
+}
+
+impl BlindIndex {
+    /// Index a text under a label, unless it would grow the index past `cap`
+    ///
+    /// `cap` bounds [`size`](Self::size). Returns whether the text was
+    /// indexed; texts shorter than a trigram are never indexed.
+    pub fn insert(&mut self, key: &BlindIndexKey, label: &str, text: &str, cap: usize) -> bool {
+        let tokens = key.tokens(text);
+        if tokens.is_empty() || self.size + tokens.len() > cap {
+            return false;
+        }
+
+        let entry = self.labels.len() as u32;
+        self.labels.push(label.to_string());
+        self.size += tokens.len();
+        for token in tokens {
+            self.postings.entry(token).or_default().push(entry);
+        }
+        true
+    }
+
+    /// Labels of the entries containing every trigram of `query`
+    ///
+    /// Each label is listed once, in insertion order. Queries shorter than
+    /// [`MIN_QUERY_CHARS`] match nothing.
+    pub fn search(&self, key: &BlindIndexKey, query: &str) -> Vec<&str> {
+        let mut matches: Option<BTreeSet<u32>> = None;
+        for token in key.tokens(query) {
+            let Some(entries) = self.postings.get(&token) else {
+                return Vec::new();
+            };
+            let entries: BTreeSet<u32> = entries.iter().copied().collect();
+            matches = Some(match matches {
+                Some(matches) => matches.intersection(&entries).copied().collect(),
+                None => entries,
+            });
+        }
+
+        let mut labels: Vec<&str> = Vec::new();
+        for entry in matches.unwrap_or_default() {
+            let label = self.labels[entry as usize].as_str();
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+        labels
+    }
+
+    /// Whether nothing is indexed
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Postings across all trigrams
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_intersects_trigrams() {
+        let key = BlindIndexKey::new(&[1u8; 32]);
+        let mut index = BlindIndex::default();
+        assert!(index.insert(&key, "bookmarks", "grandma's recipes", usize::MAX));
+        assert!(index.insert(&key, "contacts", "alice cooper", usize::MAX));
+        assert!(index.insert(&key, "bookmarks", "recipe box", usize::MAX));
+        assert!(!index.insert(&key, "contacts", "al", usize::MAX));
+
+        assert_eq!(index.search(&key, "recipe"), vec!["bookmarks"]);
+        assert_eq!(index.search(&key, "e c"), vec!["contacts"]);
+        assert_eq!(index.search(&key, "coop"), vec!["contacts"]);
+        assert!(index.search(&key, "al").is_empty());
+        assert!(index.search(&key, "recipez").is_empty());
+
+        // Another key blinds trigrams differently
+        let other = BlindIndexKey::new(&[2u8; 32]);
+        assert!(index.search(&other, "recipe").is_empty());
+    }
+
+    #[test]
+    fn test_insert_respects_cap() {
+        let key = BlindIndexKey::new(&[1u8; 32]);
+        let mut index = BlindIndex::default();
+        // "abcdef" has four trigrams
+        assert!(index.insert(&key, "a", "abcdef", 6));
+        assert!(!index.insert(&key, "b", "uvwxyz", 6));
+        assert!(index.insert(&key, "c", "xyz", 6));
+        assert_eq!(index.size(), 5);
+    }
+
+    #[test]
+    fn test_sealed_index_hides_text() {
+        let key = BlindIndexKey::new(&[1u8; 32]);
+        let mut index = BlindIndex::default();
+        index.insert(&key, "bookmarks", "grandma's recipes", usize::MAX);
+
+        let sealed = key.seal(&index).unwrap();
+        let haystack = String::from_utf8_lossy(&sealed);
+        assert!(!haystack.contains("recipes"));
+        assert!(!haystack.contains("bookmarks"));
+        // A fresh salt per write, so equal indexes seal differently
+        assert_ne!(sealed, key.seal(&index).unwrap());
+
+        assert_eq!(key.open(&sealed).unwrap(), index);
+        assert!(BlindIndexKey::new(&[2u8; 32]).open(&sealed).is_err());
+        assert!(key.open(&sealed[..8]).is_err());
+    }
+}
@@ -20,9 +20,102 @@
 //! let decrypted = encryption.decrypt(&ciphertext)?;
 //! assert_eq!(decrypted, plaintext);
 //! ```
+//!
+//! # Failures
+//!
+//! A ciphertext that does not decrypt is classified as a [`DecryptFailure`]
+//! from its layout, so a truncated file or a container of another format
+//! is told apart from a wrong key. The on-disk format is the keyed Cocoon
+//! container: a 20-byte header (nonce and body length) and a 16-byte tag
+//! ahead of the body. A password-based Cocoon container starts with the
+//! magic bytes `7f c0 0a` and a version byte instead.
 
 use crate::{OsnovaError, Result};
 use cocoon::{Error as CocoonError, MiniCocoon};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Bytes ahead of the body of a container: header and tag
+pub const CONTAINER_PREFIX_LEN: usize = 36;
+
+/// Offset of the body length in a container's header
+const BODY_LENGTH_OFFSET: usize = 12;
+
+/// Magic bytes that start a password-based Cocoon container
+const PASSWORD_CONTAINER_MAGIC: [u8; 3] = [0x7f, 0xc0, b'\n'];
+
+/// Version of the password-based Cocoon containers earlier tools wrote
+const PASSWORD_CONTAINER_VERSION: u8 = 1;
+
+/// Why a ciphertext does not decrypt
+///
+/// Only describes the container; it never holds key material or data.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, thiserror::Error,
+)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DecryptFailure {
+    /// The container is well formed but its tag does not verify: the key
+    /// is not the one it was written with, or the data was modified
+    #[error("authentication failed (wrong key or modified data)")]
+    AuthenticationFailed,
+    /// A password-based Cocoon container of a version this release cannot
+    /// read
+    #[error("unknown container format version {found}")]
+    UnknownFormatVersion {
+        /// Version byte found
+        found: u8,
+    },
+    /// Shorter than the container's header requires
+    #[error("truncated ciphertext: {got} bytes, expected at least {expected_min}")]
+    #[serde(rename_all = "camelCase")]
+    TruncatedCiphertext {
+        /// Smallest length the container could have
+        expected_min: usize,
+        /// Actual length
+        got: usize,
+    },
+    /// A password-based Cocoon container, which Osnova does not write;
+    /// it was written by an older tool or copied from elsewhere
+    #[error("legacy password-based container format")]
+    LegacyFormat,
+}
+
+impl DecryptFailure {
+    /// Classify why `ciphertext` does not decrypt
+    ///
+    /// Format problems are recognized from the layout alone; a well-formed
+    /// container that still failed is an authentication failure.
+    pub fn classify(ciphertext: &[u8]) -> Self {
+        if ciphertext.starts_with(&PASSWORD_CONTAINER_MAGIC) {
+            return match ciphertext.get(PASSWORD_CONTAINER_MAGIC.len()) {
+                Some(&PASSWORD_CONTAINER_VERSION) => Self::LegacyFormat,
+                Some(&found) => Self::UnknownFormatVersion { found },
+                None => Self::TruncatedCiphertext {
+                    expected_min: CONTAINER_PREFIX_LEN,
+                    got: ciphertext.len(),
+                },
+            };
+        }
+        if ciphertext.len() < CONTAINER_PREFIX_LEN {
+            return Self::TruncatedCiphertext {
+                expected_min: CONTAINER_PREFIX_LEN,
+                got: ciphertext.len(),
+            };
+        }
+
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&ciphertext[BODY_LENGTH_OFFSET..BODY_LENGTH_OFFSET + 8]);
+        let expected_min = CONTAINER_PREFIX_LEN.saturating_add(u64::from_be_bytes(length) as usize);
+        if ciphertext.len() < expected_min {
+            return Self::TruncatedCiphertext {
+                expected_min,
+                got: ciphertext.len(),
+            };
+        }
+        Self::AuthenticationFailed
+    }
+}
 
 /// Encryption wrapper using cocoon for file encryption
 ///
@@ -93,8 +186,8 @@ impl CocoonEncryption {
     ///
     /// # Errors
     ///
-    /// Returns error if:
-    /// - Ciphertext is invalid or corrupted
+    /// Returns [`OsnovaError::Decrypt`] saying why if:
+    /// - Ciphertext is invalid, truncated, or of another format
     /// - Authentication tag verification fails
     /// - Wrong key is used
     ///
@@ -114,8 +207,18 @@ impl CocoonEncryption {
     /// assert_eq!(decrypted, plaintext);
     /// ```
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.try_decrypt(ciphertext).map_err(OsnovaError::Decrypt)
+    }
+
+    /// Decrypt data, classifying a failure
+    ///
+    /// For callers that add their own context to a [`DecryptFailure`],
+    /// such as the storage backends.
+    pub fn try_decrypt(&self, ciphertext: &[u8]) -> std::result::Result<Vec<u8>, DecryptFailure> {
         let cocoon = MiniCocoon::from_key(&self.key, &[0u8; 32]);
-        cocoon.unwrap(ciphertext).map_err(Self::map_cocoon_error)
+        cocoon
+            .unwrap(ciphertext)
+            .map_err(|_| DecryptFailure::classify(ciphertext))
     }
 
     /// Map cocoon errors to OsnovaError
@@ -207,8 +310,8 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(OsnovaError::Crypto(_)) => (),
-            _ => panic!("Expected Crypto error"),
+            Err(OsnovaError::Decrypt(DecryptFailure::AuthenticationFailed)) => (),
+            _ => panic!("Expected an authentication failure"),
         }
     }
 
@@ -235,10 +338,14 @@ mod tests {
 
         assert!(result.is_err());
         match result {
-            Err(OsnovaError::Crypto(msg)) => {
-                assert!(msg.contains("too short") || msg.contains("Unrecognized"));
+            Err(OsnovaError::Decrypt(DecryptFailure::TruncatedCiphertext {
+                expected_min,
+                got,
+            })) => {
+                assert_eq!(expected_min, CONTAINER_PREFIX_LEN);
+                assert_eq!(got, 3);
             }
-            _ => panic!("Expected Crypto error for short ciphertext"),
+            _ => panic!("Expected a truncation error for short ciphertext"),
         }
     }
 
@@ -329,5 +436,54 @@ mod tests {
         // Decryption should fail due to authentication tag mismatch
         let result = encryption.decrypt(&ciphertext);
         assert!(result.is_err());
+        assert_eq!(
+            encryption.try_decrypt(&ciphertext),
+            Err(DecryptFailure::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_truncated_body_is_classified() {
+        let encryption = CocoonEncryption::new(&sample_key());
+        let ciphertext = encryption.encrypt(b"twenty bytes of data").unwrap();
+
+        let cut = &ciphertext[..ciphertext.len() - 4];
+        assert_eq!(
+            encryption.try_decrypt(cut),
+            Err(DecryptFailure::TruncatedCiphertext {
+                expected_min: CONTAINER_PREFIX_LEN + 20,
+                got: CONTAINER_PREFIX_LEN + 16,
+            })
+        );
+    }
+
+    #[test]
+    fn test_password_containers_are_classified() {
+        let encryption = CocoonEncryption::new(&sample_key());
+        let mut container = PASSWORD_CONTAINER_MAGIC.to_vec();
+        container.push(PASSWORD_CONTAINER_VERSION);
+        container.extend_from_slice(&[0u8; 80]);
+        assert_eq!(
+            encryption.try_decrypt(&container),
+            Err(DecryptFailure::LegacyFormat)
+        );
+
+        container[PASSWORD_CONTAINER_MAGIC.len()] = 2;
+        assert_eq!(
+            encryption.try_decrypt(&container),
+            Err(DecryptFailure::UnknownFormatVersion { found: 2 })
+        );
+    }
+
+    #[test]
+    fn test_failure_serializes_with_kind() {
+        let failure = DecryptFailure::TruncatedCiphertext {
+            expected_min: 36,
+            got: 3,
+        };
+        assert_eq!(
+            serde_json::to_value(failure).unwrap(),
+            serde_json::json!({ "kind": "truncatedCiphertext", "expectedMin": 36, "got": 3 })
+        );
     }
 }
@@ -1,7 +1,10 @@
 //! Key derivation functions for Osnova
 //!
 //! This module provides key derivation utilities for generating component-specific keys
-//! from a master key using HKDF-SHA256.
+//! from a master key using HKDF-SHA256, and Ed25519/X25519 keypairs from them.
+//!
+//! Development builds returned BLAKE3 placeholders as public keys from
+//! [`generate_keypair`]; see [`legacy_placeholder_public_key`].
 //!
 //! # Example
 //!
@@ -84,6 +87,9 @@ pub fn derive_symmetric_key(
 
 /// Generate a keypair from a symmetric key seed
 ///
+/// The seed is the secret key: an Ed25519 signing key seed, or an X25519
+/// static secret. The public key is the matching curve point.
+///
 /// # Arguments
 ///
 /// * `seed` - 256-bit symmetric key to use as seed
@@ -102,60 +108,48 @@ pub fn derive_symmetric_key(
 /// assert_eq!(keypair.secret_key.len(), 32);
 /// ```
 pub fn generate_keypair(seed: &[u8; 32], key_type: KeyType) -> Result<KeyPair> {
-    match key_type {
-        KeyType::Ed25519 => {
-            // For Ed25519, we use the seed directly as the secret key
-            // The public key is derived from the secret key
-            let secret_key = *seed;
-
-            // In a real implementation, we would use ed25519-dalek or similar
-            // For now, we'll use a placeholder that derives the public key deterministically
-            let public_key = derive_ed25519_public_key(&secret_key)?;
-
-            Ok(KeyPair {
-                public_key: public_key.to_vec(),
-                secret_key: secret_key.to_vec(),
-                key_type: KeyType::Ed25519,
-            })
-        }
-        KeyType::X25519 => {
-            // For X25519, we derive from the seed
-            let secret_key = *seed;
-
-            // Derive public key for X25519
-            let public_key = derive_x25519_public_key(&secret_key)?;
-
-            Ok(KeyPair {
-                public_key: public_key.to_vec(),
-                secret_key: secret_key.to_vec(),
-                key_type: KeyType::X25519,
-            })
-        }
-    }
+    let public_key = match key_type {
+        KeyType::Ed25519 => derive_ed25519_public_key(seed)?,
+        KeyType::X25519 => derive_x25519_public_key(seed)?,
+    };
+
+    Ok(KeyPair {
+        public_key: public_key.to_vec(),
+        secret_key: seed.to_vec(),
+        key_type,
+    })
 }
 
-/// Derive Ed25519 public key from secret key
+/// Public key development builds derived for a secret key
 ///
-/// This is a placeholder implementation. In production, use ed25519-dalek or similar.
-fn derive_ed25519_public_key(secret_key: &[u8; 32]) -> Result<[u8; 32]> {
-    // TODO: Replace with actual Ed25519 key derivation when adding ed25519-dalek
-    // For now, use BLAKE3 hash as a deterministic placeholder
+/// Before the cutover to real curve math, [`generate_keypair`] returned a
+/// BLAKE3 hash of the secret key under a per-type domain prefix. Key
+/// cocoons never stored these, so their migration maps the public keys
+/// they did store instead. Kept only so a caller holding one of these
+/// values can recognise it; never use it to derive new keys.
+pub fn legacy_placeholder_public_key(secret_key: &[u8; 32], key_type: KeyType) -> [u8; 32] {
+    let domain: &[u8] = match key_type {
+        KeyType::Ed25519 => b"ed25519-public-key:",
+        KeyType::X25519 => b"x25519-public-key:",
+    };
     let mut hasher = blake3::Hasher::new();
-    hasher.update(b"ed25519-public-key:");
+    hasher.update(domain);
     hasher.update(secret_key);
-    Ok(*hasher.finalize().as_bytes())
+    *hasher.finalize().as_bytes()
 }
 
-/// Derive X25519 public key from secret key
-///
-/// This is a placeholder implementation. In production, use x25519-dalek or similar.
+/// Derive the Ed25519 public key of a signing key seed
+fn derive_ed25519_public_key(secret_key: &[u8; 32]) -> Result<[u8; 32]> {
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(secret_key);
+    Ok(signing_key.verifying_key().to_bytes())
+}
+
+/// Derive the X25519 public key of a static secret
 fn derive_x25519_public_key(secret_key: &[u8; 32]) -> Result<[u8; 32]> {
-    // TODO: Replace with actual X25519 key derivation when adding x25519-dalek
-    // For now, use BLAKE3 hash as a deterministic placeholder
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(b"x25519-public-key:");
-    hasher.update(secret_key);
-    Ok(*hasher.finalize().as_bytes())
+    Ok(x25519_dalek::x25519(
+        *secret_key,
+        x25519_dalek::X25519_BASEPOINT_BYTES,
+    ))
 }
 
 #[cfg(test)]
@@ -322,6 +316,134 @@ mod tests {
         assert_ne!(keypair0.secret_key, keypair1.secret_key);
     }
 
+    /// Committed test vectors; changing any output here orphans every key
+    /// derived so far
+    const VECTORS: &str = include_str!("key_derivation_vectors.json");
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Vector {
+        master_key: String,
+        component_id: String,
+        index: u64,
+        seed: String,
+        ed25519_public_key: String,
+        x25519_public_key: String,
+    }
+
+    fn unhex(value: &str) -> [u8; 32] {
+        hex::decode(value).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_vectors_are_stable() {
+        let vectors: Vec<Vector> = serde_json::from_str(VECTORS).unwrap();
+        for vector in vectors {
+            let name = format!("{}:{}", vector.component_id, vector.index);
+            let seed = derive_symmetric_key(
+                &unhex(&vector.master_key),
+                &vector.component_id,
+                vector.index,
+            )
+            .unwrap();
+            assert_eq!(hex::encode(seed), vector.seed, "seed of {}", name);
+
+            let ed25519 = generate_keypair(&seed, KeyType::Ed25519).unwrap();
+            assert_eq!(
+                hex::encode(ed25519.public_key),
+                vector.ed25519_public_key,
+                "Ed25519 key of {}",
+                name
+            );
+            let x25519 = generate_keypair(&seed, KeyType::X25519).unwrap();
+            assert_eq!(
+                hex::encode(x25519.public_key),
+                vector.x25519_public_key,
+                "X25519 key of {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_keys_match_rfc_vectors() {
+        // RFC 8032, section 7.1, test 1
+        let ed25519 = generate_keypair(
+            &unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"),
+            KeyType::Ed25519,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(ed25519.public_key),
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        );
+
+        // RFC 7748, section 6.1, Alice
+        let x25519 = generate_keypair(
+            &unhex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"),
+            KeyType::X25519,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(x25519.public_key),
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+        );
+    }
+
+    #[test]
+    fn test_ed25519_signature_verifies_against_public_key() {
+        use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+
+        let seed = derive_symmetric_key(&sample_master_key(), "com.osnova.wallet", 0).unwrap();
+        let keypair = generate_keypair(&seed, KeyType::Ed25519).unwrap();
+
+        let signature = SigningKey::from_bytes(&seed).sign(b"osnova");
+        let public: [u8; 32] = keypair.public_key.as_slice().try_into().unwrap();
+        let verifying_key = VerifyingKey::from_bytes(&public).unwrap();
+        assert!(verifying_key.verify(b"osnova", &signature).is_ok());
+        assert!(verifying_key.verify(b"tampered", &signature).is_err());
+
+        // A different derived key must not verify the signature
+        let other_seed =
+            derive_symmetric_key(&sample_master_key(), "com.osnova.wallet", 1).unwrap();
+        let other = generate_keypair(&other_seed, KeyType::Ed25519).unwrap();
+        let other_public: [u8; 32] = other.public_key.as_slice().try_into().unwrap();
+        assert!(VerifyingKey::from_bytes(&other_public)
+            .unwrap()
+            .verify(b"osnova", &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_x25519_pairs_agree_on_shared_secret() {
+        let alice_seed = derive_symmetric_key(&sample_master_key(), "com.osnova.chat", 0).unwrap();
+        let bob_seed = derive_symmetric_key(&sample_master_key(), "com.osnova.chat", 1).unwrap();
+        let alice = generate_keypair(&alice_seed, KeyType::X25519).unwrap();
+        let bob = generate_keypair(&bob_seed, KeyType::X25519).unwrap();
+
+        let alice_public: [u8; 32] = alice.public_key.as_slice().try_into().unwrap();
+        let bob_public: [u8; 32] = bob.public_key.as_slice().try_into().unwrap();
+        let alice_shared = x25519_dalek::x25519(alice_seed, bob_public);
+        let bob_shared = x25519_dalek::x25519(bob_seed, alice_public);
+
+        assert_eq!(alice_shared, bob_shared);
+        assert_ne!(alice_shared, [0u8; 32]);
+    }
+
+    #[test]
+    fn test_legacy_placeholder_is_frozen() {
+        // Values development builds returned for the second committed vector
+        let seed = unhex("0e47b06f2fa85eed978a78c47a01237fceca1736369162846649bfa642d1e0d4");
+        assert_eq!(
+            hex::encode(legacy_placeholder_public_key(&seed, KeyType::Ed25519)),
+            "00d13e08898ffa985178a1ec46a07d2ca8fecd27e1f1ca4c85e3c64b36f1e783"
+        );
+        assert_eq!(
+            hex::encode(legacy_placeholder_public_key(&seed, KeyType::X25519)),
+            "b14d6967a802530b6df919011691f3abfb7920f42c22148db8d2d2352c53ead7"
+        );
+    }
+
     // Property-based tests
     #[cfg(test)]
     use proptest::prelude::*;
@@ -0,0 +1,16 @@
+//! # Support Diagnostics
+//!
+//! Tools a support engineer uses on a user's install to find out what is
+//! wrong with it, without shipping a new build.
+//!
+//! - [`queries`] - named, read-only SQL queries over the Osnova database,
+//!   defined in code, run only in builds whose
+//!   [`DebugGate`](crate::storage::chaos::DebugGate) allows them, after the
+//!   user confirms, and recorded in the audit log
+
+pub mod queries;
+
+pub use queries::{
+    DiagnosticQueries, DiagnosticQuery, ParamKind, QueryColumn, QueryConfirmation, QueryParam,
+    QueryResult, QUERIES,
+};
@@ -0,0 +1,811 @@
+//! Named, read-only diagnostic queries
+//!
+//! Support engineers need answers such as "how many blobs does each
+//! namespace hold" from a user's database, but raw SQL from outside is
+//! never accepted. Every query a support build can run is an entry in
+//! [`QUERIES`]: a name, a description, typed parameters, and the data
+//! class of each column it returns. Adding a query means adding an entry
+//! here.
+//!
+//! Running one takes three things:
+//!
+//! 1. A build whose [`DebugGate`] allows diagnostic queries (the
+//!    `diagnostics` feature)
+//! 2. The user's confirmation: [`DiagnosticQueries::begin`] issues a
+//!    single-use [`QueryConfirmation`] for one query with its parameters,
+//!    shown to the user, and [`DiagnosticQueries::run_diagnostic_query`]
+//!    only runs that query with those parameters
+//! 3. An audit log: every run is recorded as
+//!    [`AuditAction::DiagnosticQueryRun`]
+//!
+//! Queries run on a read-only connection, and a statement that would write
+//! is refused before it runs. Values in columns whose [`DataClass`] does
+//! not permit a diagnostics bundle are redacted: secrets entirely, personal
+//! data to a fingerprint that is stable within one result, so rows can be
+//! matched up without showing the value.
+
+use anyhow::{bail, Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::storage::chaos::DebugGate;
+use crate::storage::{DataClass, Purpose, SqlStorage};
+use crate::time;
+
+/// Most rows a query returns
+pub const MAX_ROWS: usize = 500;
+
+/// Seconds a confirmation stays usable
+pub const CONFIRMATION_TTL_SECS: u64 = 120;
+
+/// Type of a query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ParamKind {
+    /// A string
+    Text,
+    /// An integer between `min` and `max`, `default` if not given
+    Integer {
+        /// Smallest accepted value
+        min: i64,
+        /// Largest accepted value
+        max: i64,
+        /// Value used when the parameter is left out
+        default: i64,
+    },
+}
+
+/// A parameter a query takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParam {
+    /// Name, bound as `:name` in the query
+    pub name: &'static str,
+    /// What the parameter selects
+    pub description: &'static str,
+    /// Accepted values
+    pub kind: ParamKind,
+    /// Whether the parameter must be given; optional text parameters are
+    /// bound to `NULL` when left out
+    pub required: bool,
+}
+
+/// A column a query returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryColumn {
+    /// Column name, as the query selects it
+    pub name: &'static str,
+    /// What kind of data the column holds, deciding whether it is redacted
+    pub class: DataClass,
+}
+
+impl QueryColumn {
+    /// Shape a value of this column for a support engineer
+    ///
+    /// Returns the value and whether it was redacted. `key` keys the
+    /// fingerprints of personal values.
+    fn shape(&self, value: Value, key: &[u8; 32]) -> (Value, bool) {
+        if value.is_null() || self.class.permits(Purpose::DiagnosticsBundle) {
+            return (value, false);
+        }
+        if self.class == DataClass::Secret {
+            // No fingerprint: a low-entropy secret could be guessed from one
+            return (Value::from("redacted"), true);
+        }
+        let text = match &value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let fingerprint = blake3::keyed_hash(key, text.as_bytes()).to_hex();
+        (Value::from(format!("redacted:{}", &fingerprint[..8])), true)
+    }
+}
+
+/// A named, read-only diagnostic query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticQuery {
+    /// Name the query is run by
+    pub name: &'static str,
+    /// What the query answers, shown when the user confirms it
+    pub description: &'static str,
+    /// Parameters the query takes
+    pub params: &'static [QueryParam],
+    /// Columns the query returns, in order
+    pub columns: &'static [QueryColumn],
+    #[serde(skip)]
+    sql: &'static str,
+}
+
+impl DiagnosticQuery {
+    /// Look up a query by name
+    pub fn find(name: &str) -> Option<&'static DiagnosticQuery> {
+        QUERIES.iter().find(|query| query.name == name)
+    }
+
+    /// Look up one of the query's parameters by name
+    pub fn param(&self, name: &str) -> Option<&'static QueryParam> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    /// Check `params` against the declared parameters and fill in defaults
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a parameter is unknown, missing, of the wrong
+    /// type or out of range
+    pub fn bind(&self, params: &Map<String, Value>) -> Result<BTreeMap<String, Value>> {
+        if let Some(unknown) = params.keys().find(|name| self.param(name).is_none()) {
+            bail!("Query {} has no parameter {}", self.name, unknown);
+        }
+
+        let mut bound = BTreeMap::new();
+        for param in self.params {
+            let value = match (params.get(param.name), param.kind) {
+                (None | Some(Value::Null), _) if param.required => {
+                    bail!("Query {} needs parameter {}", self.name, param.name)
+                }
+                (None | Some(Value::Null), ParamKind::Text) => Value::Null,
+                (None | Some(Value::Null), ParamKind::Integer { default, .. }) => default.into(),
+                (Some(Value::String(text)), ParamKind::Text) => Value::from(text.as_str()),
+                (Some(value), ParamKind::Integer { min, max, .. }) => match value.as_i64() {
+                    Some(integer) if (min..=max).contains(&integer) => integer.into(),
+                    _ => bail!(
+                        "Parameter {} must be an integer from {} to {}",
+                        param.name,
+                        min,
+                        max
+                    ),
+                },
+                (Some(_), ParamKind::Text) => bail!("Parameter {} must be a string", param.name),
+            };
+            bound.insert(param.name.to_string(), value);
+        }
+        Ok(bound)
+    }
+
+    /// Run the query with bound parameters and redact its rows
+    fn run(&self, database: &Path, params: &BTreeMap<String, Value>) -> Result<QueryResult> {
+        let names: Vec<String> = params.keys().map(|name| format!(":{}", name)).collect();
+        let named: Vec<(&str, Value)> = names
+            .iter()
+            .zip(params.values())
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        let rows = SqlStorage::query_read_only(database, self.sql, &named, MAX_ROWS)
+            .with_context(|| format!("Diagnostic query {} failed", self.name))?;
+
+        let mut key = [0u8; 32];
+        thread_rng().fill_bytes(&mut key);
+        let mut redacted = 0;
+        let shaped = rows
+            .rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .zip(self.columns)
+                    .map(|(value, column)| {
+                        let (value, was_redacted) = column.shape(value, &key);
+                        redacted += usize::from(was_redacted);
+                        value
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(QueryResult {
+            name: self.name.to_string(),
+            columns: self.columns.iter().map(|c| c.name.to_string()).collect(),
+            rows: shaped,
+            redacted,
+            truncated: rows.truncated,
+        })
+    }
+}
+
+const fn column(name: &'static str, class: DataClass) -> QueryColumn {
+    QueryColumn { name, class }
+}
+
+const APP_ID: QueryParam = QueryParam {
+    name: "appId",
+    description: "Only this application",
+    kind: ParamKind::Text,
+    required: false,
+};
+
+/// Every query a support build can run
+pub const QUERIES: &[DiagnosticQuery] = &[
+    DiagnosticQuery {
+        name: "applications",
+        description: "Installed applications with their version, pin, component cache state \
+                      and last metadata refresh",
+        params: &[],
+        columns: &[
+            column("appId", DataClass::Preferences),
+            column("version", DataClass::Preferences),
+            column("pinnedVersion", DataClass::Preferences),
+            column("materialization", DataClass::CacheRegenerable),
+            column("lastRefreshed", DataClass::Telemetry),
+            column("createdAt", DataClass::Telemetry),
+        ],
+        sql: "SELECT id, json_extract(data, '$.version'), pinned_version,
+                     json_extract(materialization, '$.state'), last_refreshed, created_at
+              FROM applications
+              ORDER BY id",
+    },
+    DiagnosticQuery {
+        name: "blobsPerNamespace",
+        description: "Number and encrypted size of stored blobs per key namespace (the part \
+                      before the first '/') and data class",
+        params: &[],
+        columns: &[
+            column("namespace", DataClass::Preferences),
+            column("dataClass", DataClass::Telemetry),
+            column("blobs", DataClass::Telemetry),
+            column("bytes", DataClass::Telemetry),
+        ],
+        sql: "SELECT CASE WHEN instr(key, '/') > 0 THEN substr(key, 1, instr(key, '/') - 1)
+                          ELSE '' END AS namespace,
+                     data_class, COUNT(*), SUM(length(value_encrypted))
+              FROM encrypted_blobs
+              GROUP BY namespace, data_class
+              ORDER BY namespace, data_class",
+    },
+    DiagnosticQuery {
+        name: "unclassifiedBlobs",
+        description: "Blobs stored without a data class, which backups and diagnostics \
+                      bundles leave out",
+        params: &[],
+        columns: &[
+            column("key", DataClass::Personal),
+            column("bytes", DataClass::Telemetry),
+            column("updatedAt", DataClass::Telemetry),
+        ],
+        sql: "SELECT key, length(value_encrypted), updated_at
+              FROM encrypted_blobs
+              WHERE data_class IS NULL
+              ORDER BY key",
+    },
+    DiagnosticQuery {
+        name: "appConfigurations",
+        description: "Stored per-app configuration rows with their encrypted size, to spot \
+                      missing, empty or stale rows",
+        params: &[APP_ID],
+        columns: &[
+            column("appId", DataClass::Preferences),
+            column("userId", DataClass::Personal),
+            column("encryptedBytes", DataClass::Telemetry),
+            column("updatedAt", DataClass::Telemetry),
+        ],
+        sql: "SELECT app_id, user_id, length(settings_encrypted), updated_at
+              FROM app_configurations
+              WHERE :appId IS NULL OR app_id = :appId
+              ORDER BY app_id, user_id",
+    },
+    DiagnosticQuery {
+        name: "componentProvenance",
+        description: "Downloads of one component: version, hash, source and how the content \
+                      was verified, newest first",
+        params: &[QueryParam {
+            name: "componentId",
+            description: "Component to list downloads of",
+            kind: ParamKind::Text,
+            required: true,
+        }],
+        columns: &[
+            column("componentVersion", DataClass::Preferences),
+            column("hash", DataClass::Preferences),
+            column("sourceUri", DataClass::Preferences),
+            column("verification", DataClass::Telemetry),
+            column("downloadedAt", DataClass::Telemetry),
+        ],
+        sql: "SELECT json_extract(data, '$.componentVersion'), component_hash,
+                     json_extract(data, '$.sourceUri'), json_extract(data, '$.verification'),
+                     downloaded_at
+              FROM component_provenance
+              WHERE component_id = :componentId
+              ORDER BY downloaded_at DESC, id DESC",
+    },
+    DiagnosticQuery {
+        name: "recentCrashReports",
+        description: "Most recent backend crashes with their exit status; stderr is counted, \
+                      not shown",
+        params: &[
+            APP_ID,
+            QueryParam {
+                name: "limit",
+                description: "Most crashes to list",
+                kind: ParamKind::Integer {
+                    min: 1,
+                    max: 200,
+                    default: 20,
+                },
+                required: false,
+            },
+        ],
+        columns: &[
+            column("reportId", DataClass::Telemetry),
+            column("appId", DataClass::Preferences),
+            column("componentId", DataClass::Preferences),
+            column("exitCode", DataClass::Telemetry),
+            column("signal", DataClass::Telemetry),
+            column("crashedAt", DataClass::Telemetry),
+            column("stderrLines", DataClass::Telemetry),
+        ],
+        sql: "SELECT id, app_id, json_extract(data, '$.componentId'),
+                     json_extract(data, '$.exitCode'), json_extract(data, '$.signal'),
+                     crashed_at, json_array_length(data, '$.stderrTail')
+              FROM crash_reports
+              WHERE :appId IS NULL OR app_id = :appId
+              ORDER BY crashed_at DESC, id
+              LIMIT :limit",
+    },
+    DiagnosticQuery {
+        name: "bandwidthUsage",
+        description: "Bytes transferred per day and category, which the daily bandwidth limit \
+                      counts against",
+        params: &[QueryParam {
+            name: "days",
+            description: "Number of days to list, including today",
+            kind: ParamKind::Integer {
+                min: 1,
+                max: 90,
+                default: 7,
+            },
+            required: false,
+        }],
+        columns: &[
+            column("day", DataClass::Telemetry),
+            column("category", DataClass::Telemetry),
+            column("bytes", DataClass::Telemetry),
+        ],
+        sql: "SELECT day, category, bytes
+              FROM bandwidth_usage
+              WHERE day > strftime('%s', 'now') / 86400 - :days
+              ORDER BY day DESC, category",
+    },
+];
+
+/// The user's go-ahead for one query with its parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryConfirmation {
+    /// Random token that must be handed back to run the query
+    pub token: String,
+    /// Query to run
+    pub name: String,
+    /// What the query answers, to show the user
+    pub description: String,
+    /// Parameters it runs with, defaults filled in
+    pub params: BTreeMap<String, Value>,
+    /// Unix time after which the confirmation is rejected
+    pub expires_at: u64,
+}
+
+/// Redacted rows of a diagnostic query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    /// Query that ran
+    pub name: String,
+    /// Column names, in order
+    pub columns: Vec<String>,
+    /// Row values, in column order
+    pub rows: Vec<Vec<Value>>,
+    /// Number of values redacted
+    pub redacted: usize,
+    /// Whether more than [`MAX_ROWS`] rows matched
+    pub truncated: bool,
+}
+
+/// Runs diagnostic queries against the Osnova database
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::audit::AuditLog;
+/// use osnova_lib::diagnostics::DiagnosticQueries;
+/// use osnova_lib::models::identity::RootIdentity;
+/// use std::sync::Arc;
+///
+/// # fn example(identity: &RootIdentity) -> anyhow::Result<()> {
+/// let audit = Arc::new(AuditLog::new("/path/to/storage", identity, "support")?);
+/// let queries = DiagnosticQueries::new("/path/to/storage", audit);
+///
+/// // The user is shown the confirmation and agrees
+/// let params = serde_json::Map::new();
+/// let confirmation = queries.begin("blobsPerNamespace", &params)?;
+/// let result =
+///     queries.run_diagnostic_query("blobsPerNamespace", &params, &confirmation.token)?;
+/// println!("{} rows", result.rows.len());
+/// # Ok(())
+/// # }
+/// ```
+pub struct DiagnosticQueries {
+    database: PathBuf,
+    audit: Arc<AuditLog>,
+    allowed: bool,
+    pending: Mutex<Option<QueryConfirmation>>,
+}
+
+impl DiagnosticQueries {
+    /// Run queries against the database under `storage_path`, recording
+    /// them in `audit`
+    pub fn new<P: AsRef<Path>>(storage_path: P, audit: Arc<AuditLog>) -> Self {
+        Self {
+            database: storage_path.as_ref().join("osnova.db"),
+            audit,
+            allowed: DebugGate::diagnostic_queries_allowed(),
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Every query that can be run
+    pub fn list(&self) -> &'static [DiagnosticQuery] {
+        QUERIES
+    }
+
+    /// Issue the confirmation the user has to give before `name` runs
+    /// with `params`
+    ///
+    /// Replaces any confirmation issued earlier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the build does not allow diagnostic queries, the
+    /// query is unknown, or the parameters do not fit it
+    pub fn begin(&self, name: &str, params: &Map<String, Value>) -> Result<QueryConfirmation> {
+        self.ensure_allowed()?;
+        let query = Self::find(name)?;
+        let params = query.bind(params)?;
+
+        let mut token = [0u8; 16];
+        thread_rng().fill_bytes(&mut token);
+        let confirmation = QueryConfirmation {
+            token: hex::encode(token),
+            name: query.name.to_string(),
+            description: query.description.to_string(),
+            params,
+            expires_at: time::now_unix() + CONFIRMATION_TTL_SECS,
+        };
+        *self.pending.lock().unwrap() = Some(confirmation.clone());
+        Ok(confirmation)
+    }
+
+    /// Run a query the user confirmed, and record it in the audit log
+    ///
+    /// `token` is the one [`begin`](Self::begin) issued for this query with
+    /// these parameters; it is used up whether or not it matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the build does not allow diagnostic queries, the
+    /// query is unknown, the confirmation does not match or has expired,
+    /// the query fails, or the run cannot be audited. Nothing is returned
+    /// from a run that was not audited.
+    pub fn run_diagnostic_query(
+        &self,
+        name: &str,
+        params: &Map<String, Value>,
+        token: &str,
+    ) -> Result<QueryResult> {
+        self.ensure_allowed()?;
+        let query = Self::find(name)?;
+        let params = query.bind(params)?;
+
+        let pending = self.pending.lock().unwrap().take();
+        match pending {
+            Some(expected)
+                if expected.token == token
+                    && expected.name == query.name
+                    && expected.params == params =>
+            {
+                if time::now_unix() > expected.expires_at {
+                    bail!("Diagnostic query confirmation expired");
+                }
+            }
+            _ => bail!("Diagnostic query {} was not confirmed", query.name),
+        }
+
+        let result = query.run(&self.database, &params)?;
+        self.audit
+            .append(
+                AuditAction::DiagnosticQueryRun,
+                serde_json::json!({
+                    "query": query.name,
+                    "params": params,
+                    "rows": result.rows.len(),
+                    "redacted": result.redacted,
+                }),
+            )
+            .context("Failed to audit diagnostic query")?;
+        Ok(result)
+    }
+
+    fn ensure_allowed(&self) -> Result<()> {
+        if !self.allowed {
+            DebugGate::ensure_diagnostic_queries_allowed()?;
+        }
+        Ok(())
+    }
+
+    fn find(name: &str) -> Result<&'static DiagnosticQuery> {
+        DiagnosticQuery::find(name).with_context(|| format!("Unknown diagnostic query: {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::models::crash_report::CrashReport;
+    use crate::models::identity::RootIdentity;
+    use tempfile::TempDir;
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    /// Queries over a fresh database, with the gate open or closed
+    fn queries(allowed: bool) -> Result<(DiagnosticQueries, SqlStorage, TempDir)> {
+        let temp = TempDir::new()?;
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &RootIdentity::generate()?,
+            "support",
+        )?);
+        let mut queries = DiagnosticQueries::new(temp.path(), audit);
+        queries.allowed = allowed;
+        Ok((queries, storage, temp))
+    }
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn confirm_and_run(
+        queries: &DiagnosticQueries,
+        name: &str,
+        params: &Map<String, Value>,
+    ) -> Result<QueryResult> {
+        let confirmation = queries.begin(name, params)?;
+        queries.run_diagnostic_query(name, params, &confirmation.token)
+    }
+
+    fn crash(id: &str, app_id: &str, crashed_at: u64) -> CrashReport {
+        CrashReport {
+            id: id.to_string(),
+            app_id: app_id.to_string(),
+            component_id: Some(format!("{}-backend", app_id)),
+            component_version: None,
+            pid: 42,
+            exit_code: None,
+            signal: Some(11),
+            started_at: crashed_at - 10,
+            crashed_at,
+            stderr_tail: vec!["panicked at user data".to_string(); 3],
+        }
+    }
+
+    #[test]
+    fn test_every_query_runs_with_defaults() -> Result<()> {
+        let (queries, _storage, _temp) = queries(true)?;
+
+        let mut names: Vec<&str> = QUERIES.iter().map(|query| query.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), QUERIES.len());
+
+        for query in queries.list() {
+            let params = match query.name {
+                "componentProvenance" => params(serde_json::json!({"componentId": "c"})),
+                _ => Map::new(),
+            };
+            let result = confirm_and_run(&queries, query.name, &params)?;
+            assert_eq!(result.columns.len(), query.columns.len(), "{}", query.name);
+            assert!(result.rows.is_empty(), "{}", query.name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameters_select_rows() -> Result<()> {
+        let (queries, storage, _temp) = queries(true)?;
+        storage.insert_crash_report(&crash("r1", "notes", 100))?;
+        storage.insert_crash_report(&crash("r2", "notes", 300))?;
+        storage.insert_crash_report(&crash("r3", "wallet", 200))?;
+
+        let result = confirm_and_run(&queries, "recentCrashReports", &Map::new())?;
+        let ids: Vec<&Value> = result.rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(ids, vec!["r2", "r3", "r1"]);
+        assert_eq!(
+            result.rows[0],
+            serde_json::json!(["r2", "notes", "notes-backend", null, 11, 300, 3])
+                .as_array()
+                .unwrap()
+                .clone()
+        );
+
+        let only_notes = params(serde_json::json!({"appId": "notes", "limit": 1}));
+        let result = confirm_and_run(&queries, "recentCrashReports", &only_notes)?;
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0][0], "r2");
+
+        for (bad, expected) in [
+            (serde_json::json!({"limit": 0}), "from 1 to 200"),
+            (serde_json::json!({"limit": "5"}), "from 1 to 200"),
+            (serde_json::json!({"appId": 5}), "must be a string"),
+            (
+                serde_json::json!({"sql": "DROP TABLE crash_reports"}),
+                "no parameter sql",
+            ),
+        ] {
+            let error = queries
+                .begin("recentCrashReports", &params(bad))
+                .unwrap_err();
+            assert!(error.to_string().contains(expected), "{}", error);
+        }
+        let error = queries
+            .begin("componentProvenance", &Map::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("needs parameter componentId"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_personal_and_secret_columns_are_redacted() -> Result<()> {
+        let (queries, storage, _temp) = queries(true)?;
+        storage.set_encrypted_blob("notes/diary-of-alice", b"x", &KEY)?;
+        storage.set_encrypted_blob("notes/bank-pin", b"y", &KEY)?;
+        storage.set_encrypted_blob_classified("wallet/seed", b"z", &KEY, DataClass::Secret)?;
+
+        let result = confirm_and_run(&queries, "unclassifiedBlobs", &Map::new())?;
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.redacted, 2);
+        let json = serde_json::to_string(&result)?;
+        assert!(
+            !json.contains("alice") && !json.contains("bank-pin"),
+            "{}",
+            json
+        );
+        let keys: Vec<&str> = result.rows.iter().map(|r| r[0].as_str().unwrap()).collect();
+        assert!(keys.iter().all(|key| key.starts_with("redacted:")));
+        assert_ne!(keys[0], keys[1]);
+        assert!(result.rows[0][1].as_i64().unwrap() > 0);
+
+        // Classes that a diagnostics bundle may include are shown
+        let result = confirm_and_run(&queries, "blobsPerNamespace", &Map::new())?;
+        assert_eq!(result.redacted, 0);
+        let namespaces: Vec<(&Value, &Value, &Value)> = result
+            .rows
+            .iter()
+            .map(|row| (&row[0], &row[1], &row[2]))
+            .collect();
+        assert_eq!(
+            namespaces,
+            vec![
+                (&Value::from("notes"), &Value::Null, &Value::from(2)),
+                (
+                    &Value::from("wallet"),
+                    &Value::from("secret"),
+                    &Value::from(1)
+                ),
+            ]
+        );
+
+        let secret = QueryColumn {
+            name: "value",
+            class: DataClass::Secret,
+        };
+        assert_eq!(
+            secret.shape(Value::from("hunter2"), &KEY),
+            (Value::from("redacted"), true)
+        );
+        assert_eq!(secret.shape(Value::Null, &KEY), (Value::Null, false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gate_and_confirmation_are_enforced() -> Result<()> {
+        let (closed, _storage, _temp) = queries(false)?;
+        if !DebugGate::diagnostic_queries_allowed() {
+            let error = closed.begin("applications", &Map::new()).unwrap_err();
+            assert!(error.to_string().contains("not available in this build"));
+            let error = closed
+                .run_diagnostic_query("applications", &Map::new(), "token")
+                .unwrap_err();
+            assert!(error.to_string().contains("not available in this build"));
+        }
+
+        let (queries, _storage, _temp) = queries(true)?;
+        let none = Map::new();
+        // Never confirmed
+        assert!(queries
+            .run_diagnostic_query("applications", &none, "token")
+            .is_err());
+
+        // Wrong token, which also uses up the confirmation
+        let confirmation = queries.begin("applications", &none)?;
+        assert!(queries
+            .run_diagnostic_query("applications", &none, "guess")
+            .is_err());
+        assert!(queries
+            .run_diagnostic_query("applications", &none, &confirmation.token)
+            .is_err());
+
+        // A confirmation covers one query with its parameters only
+        let confirmation = queries.begin("bandwidthUsage", &none)?;
+        assert_eq!(confirmation.params["days"], 7);
+        let error = queries
+            .run_diagnostic_query(
+                "bandwidthUsage",
+                &params(serde_json::json!({"days": 90})),
+                &confirmation.token,
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("was not confirmed"));
+        let confirmation = queries.begin("bandwidthUsage", &none)?;
+        assert!(queries
+            .run_diagnostic_query("applications", &none, &confirmation.token)
+            .is_err());
+
+        // Single use
+        let confirmation = queries.begin("applications", &none)?;
+        queries.run_diagnostic_query("applications", &none, &confirmation.token)?;
+        assert!(queries
+            .run_diagnostic_query("applications", &none, &confirmation.token)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_runs_are_audited() -> Result<()> {
+        let (queries, storage, _temp) = queries(true)?;
+        storage.insert_crash_report(&crash("r1", "notes", 100))?;
+
+        let only_notes = params(serde_json::json!({"appId": "notes"}));
+        confirm_and_run(&queries, "recentCrashReports", &only_notes)?;
+        // Refused runs are not
+        assert!(queries
+            .run_diagnostic_query("applications", &Map::new(), "token")
+            .is_err());
+
+        let filter = AuditFilter {
+            action: Some(AuditAction::DiagnosticQueryRun),
+            ..AuditFilter::default()
+        };
+        let entries = queries.audit.list(&filter, PageRequest::default())?.entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "support");
+        assert_eq!(entries[0].details["query"], "recentCrashReports");
+        assert_eq!(
+            entries[0].details["params"],
+            serde_json::json!({"appId": "notes", "limit": 20})
+        );
+        assert_eq!(entries[0].details["rows"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_query_is_rejected() -> Result<()> {
+        let (queries, _storage, _temp) = queries(true)?;
+        for name in ["", "dropEverything", "SELECT * FROM encrypted_blobs"] {
+            let error = queries.begin(name, &Map::new()).unwrap_err();
+            assert!(
+                error.to_string().contains("Unknown diagnostic query"),
+                "{}",
+                error
+            );
+            assert!(queries
+                .run_diagnostic_query(name, &Map::new(), "token")
+                .is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,746 @@
+//! Events the shell emits to its frontend
+//!
+//! Every event the Tauri shell emits is a variant of [`OsnovaEvent`]. Each
+//! payload is a serde struct implementing [`ShellEvent`], which fixes the
+//! name the frontend listens on, so emitting goes through a typed value
+//! and names and payloads cannot drift apart. [`typescript_definitions`]
+//! generates the matching TypeScript interfaces for the frontend, checked
+//! in at `app/src/lib/types/events.d.ts`.
+//!
+//! In-process notifications between services are a separate channel; see
+//! [`crate::services::events`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use osnova_lib::events::{OsnovaEvent, PermissionResolved, ShellEvent};
+//!
+//! let event = OsnovaEvent::from(PermissionResolved {
+//!     prompt_id: "prompt-1".to_string(),
+//!     granted: true,
+//! });
+//! assert_eq!(event.name(), PermissionResolved::NAME);
+//! ```
+
+pub mod typescript;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::context::unlock::{ContextUnlocked, CONTEXT_UNLOCKED_EVENT};
+use crate::models::health::HealthTransition;
+use crate::models::notification::StoredNotification;
+use crate::models::permission::PermissionPrompt;
+use crate::models::task::TaskInfo;
+use crate::services::apps::{
+    BatchInstallProgress, MaterializeProgress, APPS_INSTALL_PROGRESS_EVENT,
+    APPS_MATERIALIZE_PROGRESS_EVENT,
+};
+use crate::services::badges::{BadgeChanged, BADGE_CHANGED_EVENT};
+use crate::services::features::{FeatureFlagsChanged, FEATURE_FLAGS_CHANGED_EVENT};
+use crate::services::handshake::{ReadinessState, BACKEND_READINESS_EVENT};
+use crate::services::health::BACKEND_HEALTH_EVENT;
+use crate::services::metadata::{
+    MetadataRefresh, RefreshProgress, APPS_METADATA_CHANGED_EVENT, APPS_REFRESH_PROGRESS_EVENT,
+};
+use crate::services::migration::{MigrationProgress, MIGRATION_PROGRESS_EVENT};
+use crate::services::notifications::NOTIFICATION_POSTED_EVENT;
+use crate::services::permissions::{PERMISSION_PROMPT_EVENT, PERMISSION_RESOLVED_EVENT};
+use crate::services::processes::{AppCrashed, APP_CRASHED_EVENT};
+use crate::services::reauth::{AuthPrompt, AUTH_PROMPT_EVENT};
+use crate::services::tasks::TASK_UPDATED_EVENT;
+
+pub use typescript::{typescript_definitions, write_typescript_definitions};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Payload of a shell event
+///
+/// Sealed: only the payloads of [`OsnovaEvent`] implement it, so every
+/// event name is one of the constants below.
+pub trait ShellEvent: Serialize + JsonSchema + Into<OsnovaEvent> + sealed::Sealed {
+    /// Event name the frontend listens on
+    const NAME: &'static str;
+}
+
+/// A permission prompt was answered or timed out, so its modal can close
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionResolved {
+    /// Prompt that was resolved
+    pub prompt_id: String,
+    /// Whether the capability was granted
+    pub granted: bool,
+}
+
+/// A launched backend's readiness changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendReadinessChanged {
+    /// Application identifier
+    pub app_id: String,
+    /// Backend component identifier
+    pub component_id: String,
+    /// New readiness
+    pub state: ReadinessState,
+}
+
+/// An event emitted by the shell
+///
+/// Serializes as its payload alone; the name travels separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OsnovaEvent {
+    /// A backend process died unexpectedly
+    AppCrashed(AppCrashed),
+    /// A notification was delivered to the user's notification center
+    NotificationPosted(StoredNotification),
+    /// A component asked for a capability and the user must answer
+    PermissionPrompt(PermissionPrompt),
+    /// A permission prompt was answered or timed out
+    PermissionResolved(PermissionResolved),
+    /// A launched backend's readiness changed
+    BackendReadiness(BackendReadinessChanged),
+    /// An installed app's name, icon or description changed
+    AppsMetadataChanged(MetadataRefresh),
+    /// An app finished during a manual metadata refresh
+    AppsRefreshProgress(RefreshProgress),
+    /// A server-to-server migration advanced
+    MigrationProgress(MigrationProgress),
+    /// An app's icon badge changed
+    BadgeChanged(BadgeChanged),
+    /// The identity was unlocked and every service is available
+    ContextUnlocked(ContextUnlocked),
+    /// A missing component of an installed app was downloaded
+    AppsMaterializeProgress(MaterializeProgress),
+    /// An app of a batch install finished
+    AppsInstallProgress(BatchInstallProgress),
+    /// A long-running operation started, advanced or ended
+    TaskUpdated(TaskInfo),
+    /// A running backend became unhealthy, recovered, was restarted or
+    /// blocked
+    BackendHealth(HealthTransition),
+    /// An app's effective feature flag values changed
+    FeatureFlagsChanged(FeatureFlagsChanged),
+    /// OS authentication for a sensitive operation is asking the user
+    AuthPrompt(AuthPrompt),
+}
+
+impl OsnovaEvent {
+    /// Event name the frontend listens on
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AppCrashed(_) => AppCrashed::NAME,
+            Self::NotificationPosted(_) => StoredNotification::NAME,
+            Self::PermissionPrompt(_) => PermissionPrompt::NAME,
+            Self::PermissionResolved(_) => PermissionResolved::NAME,
+            Self::BackendReadiness(_) => BackendReadinessChanged::NAME,
+            Self::AppsMetadataChanged(_) => MetadataRefresh::NAME,
+            Self::AppsRefreshProgress(_) => RefreshProgress::NAME,
+            Self::MigrationProgress(_) => MigrationProgress::NAME,
+            Self::BadgeChanged(_) => BadgeChanged::NAME,
+            Self::ContextUnlocked(_) => ContextUnlocked::NAME,
+            Self::AppsMaterializeProgress(_) => MaterializeProgress::NAME,
+            Self::AppsInstallProgress(_) => BatchInstallProgress::NAME,
+            Self::TaskUpdated(_) => TaskInfo::NAME,
+            Self::BackendHealth(_) => HealthTransition::NAME,
+            Self::FeatureFlagsChanged(_) => FeatureFlagsChanged::NAME,
+            Self::AuthPrompt(_) => AuthPrompt::NAME,
+        }
+    }
+}
+
+impl Serialize for OsnovaEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::AppCrashed(payload) => payload.serialize(serializer),
+            Self::NotificationPosted(payload) => payload.serialize(serializer),
+            Self::PermissionPrompt(payload) => payload.serialize(serializer),
+            Self::PermissionResolved(payload) => payload.serialize(serializer),
+            Self::BackendReadiness(payload) => payload.serialize(serializer),
+            Self::AppsMetadataChanged(payload) => payload.serialize(serializer),
+            Self::AppsRefreshProgress(payload) => payload.serialize(serializer),
+            Self::MigrationProgress(payload) => payload.serialize(serializer),
+            Self::BadgeChanged(payload) => payload.serialize(serializer),
+            Self::ContextUnlocked(payload) => payload.serialize(serializer),
+            Self::AppsMaterializeProgress(payload) => payload.serialize(serializer),
+            Self::AppsInstallProgress(payload) => payload.serialize(serializer),
+            Self::TaskUpdated(payload) => payload.serialize(serializer),
+            Self::BackendHealth(payload) => payload.serialize(serializer),
+            Self::FeatureFlagsChanged(payload) => payload.serialize(serializer),
+            Self::AuthPrompt(payload) => payload.serialize(serializer),
+        }
+    }
+}
+
+impl sealed::Sealed for AppCrashed {}
+
+impl ShellEvent for AppCrashed {
+    const NAME: &'static str = APP_CRASHED_EVENT;
+}
+
+impl From<AppCrashed> for OsnovaEvent {
+    fn from(payload: AppCrashed) -> Self {
+        Self::AppCrashed(payload)
+    }
+}
+
+impl sealed::Sealed for StoredNotification {}
+
+impl ShellEvent for StoredNotification {
+    const NAME: &'static str = NOTIFICATION_POSTED_EVENT;
+}
+
+impl From<StoredNotification> for OsnovaEvent {
+    fn from(payload: StoredNotification) -> Self {
+        Self::NotificationPosted(payload)
+    }
+}
+
+impl sealed::Sealed for PermissionPrompt {}
+
+impl ShellEvent for PermissionPrompt {
+    const NAME: &'static str = PERMISSION_PROMPT_EVENT;
+}
+
+impl From<PermissionPrompt> for OsnovaEvent {
+    fn from(payload: PermissionPrompt) -> Self {
+        Self::PermissionPrompt(payload)
+    }
+}
+
+impl sealed::Sealed for PermissionResolved {}
+
+impl ShellEvent for PermissionResolved {
+    const NAME: &'static str = PERMISSION_RESOLVED_EVENT;
+}
+
+impl From<PermissionResolved> for OsnovaEvent {
+    fn from(payload: PermissionResolved) -> Self {
+        Self::PermissionResolved(payload)
+    }
+}
+
+impl sealed::Sealed for BackendReadinessChanged {}
+
+impl ShellEvent for BackendReadinessChanged {
+    const NAME: &'static str = BACKEND_READINESS_EVENT;
+}
+
+impl From<BackendReadinessChanged> for OsnovaEvent {
+    fn from(payload: BackendReadinessChanged) -> Self {
+        Self::BackendReadiness(payload)
+    }
+}
+
+impl sealed::Sealed for MetadataRefresh {}
+
+impl ShellEvent for MetadataRefresh {
+    const NAME: &'static str = APPS_METADATA_CHANGED_EVENT;
+}
+
+impl From<MetadataRefresh> for OsnovaEvent {
+    fn from(payload: MetadataRefresh) -> Self {
+        Self::AppsMetadataChanged(payload)
+    }
+}
+
+impl sealed::Sealed for RefreshProgress {}
+
+impl ShellEvent for RefreshProgress {
+    const NAME: &'static str = APPS_REFRESH_PROGRESS_EVENT;
+}
+
+impl From<RefreshProgress> for OsnovaEvent {
+    fn from(payload: RefreshProgress) -> Self {
+        Self::AppsRefreshProgress(payload)
+    }
+}
+
+impl sealed::Sealed for MaterializeProgress {}
+
+impl ShellEvent for MaterializeProgress {
+    const NAME: &'static str = APPS_MATERIALIZE_PROGRESS_EVENT;
+}
+
+impl From<MaterializeProgress> for OsnovaEvent {
+    fn from(payload: MaterializeProgress) -> Self {
+        Self::AppsMaterializeProgress(payload)
+    }
+}
+
+impl sealed::Sealed for BatchInstallProgress {}
+
+impl ShellEvent for BatchInstallProgress {
+    const NAME: &'static str = APPS_INSTALL_PROGRESS_EVENT;
+}
+
+impl From<BatchInstallProgress> for OsnovaEvent {
+    fn from(payload: BatchInstallProgress) -> Self {
+        Self::AppsInstallProgress(payload)
+    }
+}
+
+impl sealed::Sealed for TaskInfo {}
+
+impl ShellEvent for TaskInfo {
+    const NAME: &'static str = TASK_UPDATED_EVENT;
+}
+
+impl From<TaskInfo> for OsnovaEvent {
+    fn from(payload: TaskInfo) -> Self {
+        Self::TaskUpdated(payload)
+    }
+}
+
+impl sealed::Sealed for HealthTransition {}
+
+impl ShellEvent for HealthTransition {
+    const NAME: &'static str = BACKEND_HEALTH_EVENT;
+}
+
+impl From<HealthTransition> for OsnovaEvent {
+    fn from(payload: HealthTransition) -> Self {
+        Self::BackendHealth(payload)
+    }
+}
+
+impl sealed::Sealed for MigrationProgress {}
+
+impl ShellEvent for MigrationProgress {
+    const NAME: &'static str = MIGRATION_PROGRESS_EVENT;
+}
+
+impl From<MigrationProgress> for OsnovaEvent {
+    fn from(payload: MigrationProgress) -> Self {
+        Self::MigrationProgress(payload)
+    }
+}
+
+impl sealed::Sealed for BadgeChanged {}
+
+impl ShellEvent for BadgeChanged {
+    const NAME: &'static str = BADGE_CHANGED_EVENT;
+}
+
+impl From<BadgeChanged> for OsnovaEvent {
+    fn from(payload: BadgeChanged) -> Self {
+        Self::BadgeChanged(payload)
+    }
+}
+
+impl sealed::Sealed for ContextUnlocked {}
+
+impl ShellEvent for ContextUnlocked {
+    const NAME: &'static str = CONTEXT_UNLOCKED_EVENT;
+}
+
+impl From<ContextUnlocked> for OsnovaEvent {
+    fn from(payload: ContextUnlocked) -> Self {
+        Self::ContextUnlocked(payload)
+    }
+}
+
+impl sealed::Sealed for FeatureFlagsChanged {}
+
+impl ShellEvent for FeatureFlagsChanged {
+    const NAME: &'static str = FEATURE_FLAGS_CHANGED_EVENT;
+}
+
+impl From<FeatureFlagsChanged> for OsnovaEvent {
+    fn from(payload: FeatureFlagsChanged) -> Self {
+        Self::FeatureFlagsChanged(payload)
+    }
+}
+
+impl sealed::Sealed for AuthPrompt {}
+
+impl ShellEvent for AuthPrompt {
+    const NAME: &'static str = AUTH_PROMPT_EVENT;
+}
+
+impl From<AuthPrompt> for OsnovaEvent {
+    fn from(payload: AuthPrompt) -> Self {
+        Self::AuthPrompt(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::health::HealthStatus;
+    use crate::models::notification::{Notification, NotificationLevel};
+    use crate::models::permission::Capability;
+    use crate::models::task::{TaskCategory, TaskState};
+    use crate::platform::auth::AuthBackend;
+    use crate::services::apps::BatchInstallOutcome;
+    use crate::services::badges::{AttentionReason, BadgeState};
+    use crate::services::features::{FlagLayer, FlagSource, FlagValue};
+    use crate::services::metadata::MetadataField;
+    use crate::services::migration::{MigrationDirection, MigrationStage};
+    use crate::services::reauth::SensitiveOperation;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    /// One event of every kind, with its expected wire payload
+    pub(super) fn samples() -> Vec<(OsnovaEvent, serde_json::Value)> {
+        vec![
+            (
+                AppCrashed {
+                    app_id: "com.osnova.wallet".to_string(),
+                    pid: 4242,
+                    exit_code: None,
+                    signal: Some(11),
+                    report_id: "3f2a".to_string(),
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "pid": 4242,
+                    "exitCode": null,
+                    "signal": 11,
+                    "reportId": "3f2a"
+                }),
+            ),
+            (
+                StoredNotification {
+                    id: 7,
+                    app_id: "com.osnova.wallet".to_string(),
+                    posted_at: 100,
+                    read: false,
+                    notification: Notification::new("Payment request", "5 ANT from alice")
+                        .with_level(NotificationLevel::Warning),
+                }
+                .into(),
+                json!({
+                    "id": 7,
+                    "appId": "com.osnova.wallet",
+                    "postedAt": 100,
+                    "read": false,
+                    "title": "Payment request",
+                    "body": "5 ANT from alice",
+                    "level": "warning",
+                }),
+            ),
+            (
+                PermissionPrompt {
+                    id: "prompt-1".to_string(),
+                    app_id: "com.osnova.wallet".to_string(),
+                    component_id: "backend".to_string(),
+                    capability: Capability::PaymentsSend,
+                    rationale: "Pay the invoice".to_string(),
+                    scope: None,
+                    requested_at: 100,
+                    expires_at: 160,
+                }
+                .into(),
+                json!({
+                    "id": "prompt-1",
+                    "appId": "com.osnova.wallet",
+                    "componentId": "backend",
+                    "capability": "payments.send",
+                    "rationale": "Pay the invoice",
+                    "requestedAt": 100,
+                    "expiresAt": 160,
+                }),
+            ),
+            (
+                PermissionResolved {
+                    prompt_id: "prompt-1".to_string(),
+                    granted: true,
+                }
+                .into(),
+                json!({ "promptId": "prompt-1", "granted": true }),
+            ),
+            (
+                BackendReadinessChanged {
+                    app_id: "com.osnova.wallet".to_string(),
+                    component_id: "backend".to_string(),
+                    state: ReadinessState::Failed {
+                        reason: "exited with code 1".to_string(),
+                    },
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "componentId": "backend",
+                    "state": { "state": "failed", "reason": "exited with code 1" },
+                }),
+            ),
+            (
+                MetadataRefresh {
+                    app_id: "com.osnova.wallet".to_string(),
+                    changed: vec![MetadataField::IconUri],
+                    refreshed_at: 100,
+                    generation: Some(7),
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "changed": ["iconUri"],
+                    "refreshedAt": 100,
+                    "generation": 7,
+                }),
+            ),
+            (
+                RefreshProgress {
+                    app_id: "com.osnova.wallet".to_string(),
+                    completed: 1,
+                    total: 3,
+                    changed: vec![],
+                    error: None,
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "completed": 1,
+                    "total": 3,
+                    "changed": [],
+                }),
+            ),
+            (
+                MigrationProgress {
+                    export_id: "0badc0ffee0ddf00".to_string(),
+                    direction: MigrationDirection::Import,
+                    stage: MigrationStage::Streaming,
+                    chunks_done: 16,
+                    chunks_total: 40,
+                    bytes_done: 4_194_304,
+                }
+                .into(),
+                json!({
+                    "exportId": "0badc0ffee0ddf00",
+                    "direction": "import",
+                    "stage": "streaming",
+                    "chunksDone": 16,
+                    "chunksTotal": 40,
+                    "bytesDone": 4_194_304,
+                }),
+            ),
+            (
+                BadgeChanged {
+                    app_id: "com.osnova.wallet".to_string(),
+                    badge: BadgeState {
+                        unread: 2,
+                        update_available: true,
+                        attention: Some(AttentionReason::Crashed),
+                    },
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "badge": {
+                        "unread": 2,
+                        "updateAvailable": true,
+                        "attention": "crashed",
+                    },
+                }),
+            ),
+            (
+                ContextUnlocked { unlocked_at: 100 }.into(),
+                json!({ "unlockedAt": 100 }),
+            ),
+            (
+                MaterializeProgress {
+                    app_id: "com.osnova.wallet".to_string(),
+                    component_id: "ant://backend".to_string(),
+                    completed: 2,
+                    total: 5,
+                    error: None,
+                }
+                .into(),
+                json!({
+                    "appId": "com.osnova.wallet",
+                    "componentId": "ant://backend",
+                    "completed": 2,
+                    "total": 5,
+                }),
+            ),
+            (
+                BatchInstallProgress {
+                    manifest_uri: "ant://wallet-manifest".to_string(),
+                    outcome: BatchInstallOutcome::Failed {
+                        reason: "HTTP error 404".to_string(),
+                    },
+                    completed: 3,
+                    total: 4,
+                }
+                .into(),
+                json!({
+                    "manifestUri": "ant://wallet-manifest",
+                    "outcome": { "status": "failed", "reason": "HTTP error 404" },
+                    "completed": 3,
+                    "total": 4,
+                }),
+            ),
+            (
+                TaskInfo {
+                    id: "9c1e".to_string(),
+                    name: "Download Wallet".to_string(),
+                    category: TaskCategory::Download,
+                    cancellable: true,
+                    state: TaskState::Running,
+                    completed: 512,
+                    total: Some(2048),
+                    message: None,
+                    error: None,
+                    started_at: 100,
+                    finished_at: None,
+                }
+                .into(),
+                json!({
+                    "id": "9c1e",
+                    "name": "Download Wallet",
+                    "category": "download",
+                    "cancellable": true,
+                    "state": "running",
+                    "completed": 512,
+                    "total": 2048,
+                    "startedAt": 100,
+                }),
+            ),
+            (
+                HealthTransition {
+                    app_id: "com.example.wallet".to_string(),
+                    component_id: "ant://wallet-backend".to_string(),
+                    from: HealthStatus::Healthy,
+                    to: HealthStatus::Unhealthy,
+                    reason: "3 health checks failed in a row: timed out".to_string(),
+                    restarts: 0,
+                    at: 1_700_000_000,
+                }
+                .into(),
+                json!({
+                    "appId": "com.example.wallet",
+                    "componentId": "ant://wallet-backend",
+                    "from": "healthy",
+                    "to": "unhealthy",
+                    "reason": "3 health checks failed in a row: timed out",
+                    "restarts": 0,
+                    "at": 1_700_000_000,
+                }),
+            ),
+            (
+                FeatureFlagsChanged {
+                    app_id: "com.example.mail".to_string(),
+                    flags: vec![FlagValue {
+                        name: "newComposer".to_string(),
+                        value: true,
+                        source: FlagSource::Remote,
+                        evaluation: vec![FlagLayer {
+                            source: FlagSource::Remote,
+                            value: Some(true),
+                            detail: "flag document 1.0.0, rollout 25% reached (bucket 3)"
+                                .to_string(),
+                        }],
+                    }],
+                }
+                .into(),
+                json!({
+                    "appId": "com.example.mail",
+                    "flags": [{
+                        "name": "newComposer",
+                        "value": true,
+                        "source": "remote",
+                        "evaluation": [{
+                            "source": "remote",
+                            "value": true,
+                            "detail": "flag document 1.0.0, rollout 25% reached (bucket 3)",
+                        }],
+                    }],
+                }),
+            ),
+            (
+                AuthPrompt {
+                    operation: SensitiveOperation::SeedReveal,
+                    backend: AuthBackend::Fprintd,
+                    reason: "Osnova wants to show your seed phrase".to_string(),
+                    message: Some("Place your right index finger on the reader".to_string()),
+                }
+                .into(),
+                json!({
+                    "operation": "seedReveal",
+                    "backend": "fprintd",
+                    "reason": "Osnova wants to show your seed phrase",
+                    "message": "Place your right index finger on the reader",
+                }),
+            ),
+        ]
+    }
+
+    /// Position of an event's kind in [`samples`]
+    ///
+    /// Exhaustive on purpose: a new event does not compile until it is
+    /// given a position here, right next to the samples it needs.
+    fn ordinal(event: &OsnovaEvent) -> usize {
+        match event {
+            OsnovaEvent::AppCrashed(_) => 0,
+            OsnovaEvent::NotificationPosted(_) => 1,
+            OsnovaEvent::PermissionPrompt(_) => 2,
+            OsnovaEvent::PermissionResolved(_) => 3,
+            OsnovaEvent::BackendReadiness(_) => 4,
+            OsnovaEvent::AppsMetadataChanged(_) => 5,
+            OsnovaEvent::AppsRefreshProgress(_) => 6,
+            OsnovaEvent::MigrationProgress(_) => 7,
+            OsnovaEvent::BadgeChanged(_) => 8,
+            OsnovaEvent::ContextUnlocked(_) => 9,
+            OsnovaEvent::AppsMaterializeProgress(_) => 10,
+            OsnovaEvent::AppsInstallProgress(_) => 11,
+            OsnovaEvent::TaskUpdated(_) => 12,
+            OsnovaEvent::BackendHealth(_) => 13,
+            OsnovaEvent::FeatureFlagsChanged(_) => 14,
+            OsnovaEvent::AuthPrompt(_) => 15,
+        }
+    }
+
+    #[test]
+    fn test_payload_snapshots() {
+        for (event, expected) in samples() {
+            assert_eq!(
+                serde_json::to_value(&event).unwrap(),
+                expected,
+                "{}",
+                event.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_event_has_a_sample() {
+        let samples = samples();
+        let ordinals: Vec<usize> = samples.iter().map(|(event, _)| ordinal(event)).collect();
+        assert_eq!(ordinals, (0..samples.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_names_come_from_payload_types() {
+        // The only way to name an event is through its payload type, so an
+        // unknown name cannot be emitted; here the names just must be unique
+        let samples = samples();
+        let names: HashSet<&str> = samples.iter().map(|(event, _)| event.name()).collect();
+        assert_eq!(names.len(), samples.len());
+
+        for (event, _) in &samples {
+            let expected = match event {
+                OsnovaEvent::AppCrashed(_) => APP_CRASHED_EVENT,
+                OsnovaEvent::NotificationPosted(_) => NOTIFICATION_POSTED_EVENT,
+                OsnovaEvent::PermissionPrompt(_) => PERMISSION_PROMPT_EVENT,
+                OsnovaEvent::PermissionResolved(_) => PERMISSION_RESOLVED_EVENT,
+                OsnovaEvent::BackendReadiness(_) => BACKEND_READINESS_EVENT,
+                OsnovaEvent::AppsMetadataChanged(_) => APPS_METADATA_CHANGED_EVENT,
+                OsnovaEvent::AppsRefreshProgress(_) => APPS_REFRESH_PROGRESS_EVENT,
+                OsnovaEvent::MigrationProgress(_) => MIGRATION_PROGRESS_EVENT,
+                OsnovaEvent::BadgeChanged(_) => BADGE_CHANGED_EVENT,
+                OsnovaEvent::ContextUnlocked(_) => CONTEXT_UNLOCKED_EVENT,
+                OsnovaEvent::AppsMaterializeProgress(_) => APPS_MATERIALIZE_PROGRESS_EVENT,
+                OsnovaEvent::AppsInstallProgress(_) => APPS_INSTALL_PROGRESS_EVENT,
+                OsnovaEvent::TaskUpdated(_) => TASK_UPDATED_EVENT,
+                OsnovaEvent::BackendHealth(_) => BACKEND_HEALTH_EVENT,
+                OsnovaEvent::FeatureFlagsChanged(_) => FEATURE_FLAGS_CHANGED_EVENT,
+                OsnovaEvent::AuthPrompt(_) => AUTH_PROMPT_EVENT,
+            };
+            assert_eq!(event.name(), expected);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! TypeScript definitions of shell event payloads
+//!
+//! Payload schemas are derived with `schemars`, like the OpenRPC document,
+//! and rendered as TypeScript declarations: structs become interfaces,
+//! enums become unions. The `OsnovaEvents` interface maps every event name
+//! to its payload type, so a typed `listen` wrapper on the frontend can
+//! reject unknown names.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{InstanceType, Schema, SchemaObject, SingleOrVec};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{BackendReadinessChanged, PermissionResolved, ShellEvent};
+use crate::context::ContextUnlocked;
+use crate::error::Result;
+use crate::models::health::HealthTransition;
+use crate::models::notification::StoredNotification;
+use crate::models::permission::PermissionPrompt;
+use crate::models::task::TaskInfo;
+use crate::services::apps::{BatchInstallProgress, MaterializeProgress};
+use crate::services::badges::BadgeChanged;
+use crate::services::features::FeatureFlagsChanged;
+use crate::services::metadata::{MetadataRefresh, RefreshProgress};
+use crate::services::migration::MigrationProgress;
+use crate::services::processes::AppCrashed;
+use crate::services::reauth::AuthPrompt;
+
+/// Where payload schemas are collected by the generator
+const DEFINITIONS_PATH: &str = "#/definitions/";
+
+const HEADER: &str = "\
+// Generated from osnova_lib::events; do not edit by hand.
+// Regenerate from app/src-tauri with:
+//   cargo run -- --write-event-types ../src/lib/types/events.d.ts
+";
+
+/// TypeScript declarations of every shell event payload
+pub fn typescript_definitions() -> String {
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| settings.definitions_path = DEFINITIONS_PATH.to_string())
+        .into_generator();
+    let events = [
+        event::<AppCrashed>(&mut generator),
+        event::<StoredNotification>(&mut generator),
+        event::<PermissionPrompt>(&mut generator),
+        event::<PermissionResolved>(&mut generator),
+        event::<BackendReadinessChanged>(&mut generator),
+        event::<MetadataRefresh>(&mut generator),
+        event::<RefreshProgress>(&mut generator),
+        event::<MigrationProgress>(&mut generator),
+        event::<BadgeChanged>(&mut generator),
+        event::<ContextUnlocked>(&mut generator),
+        event::<MaterializeProgress>(&mut generator),
+        event::<BatchInstallProgress>(&mut generator),
+        event::<TaskInfo>(&mut generator),
+        event::<HealthTransition>(&mut generator),
+        event::<FeatureFlagsChanged>(&mut generator),
+        event::<AuthPrompt>(&mut generator),
+    ];
+
+    let mut out = String::from(HEADER);
+    for (name, schema) in generator.definitions() {
+        out.push('\n');
+        declaration(&mut out, name, schema);
+    }
+
+    out.push_str("\n/** Payload of every shell event, by event name */\n");
+    out.push_str("export interface OsnovaEvents {\n");
+    for (name, payload) in events {
+        let _ = writeln!(out, "  \"{}\": {};", name, payload);
+    }
+    out.push_str("}\n\n/** Name of a shell event */\n");
+    out.push_str("export type OsnovaEventName = keyof OsnovaEvents;\n");
+    out
+}
+
+/// Write the TypeScript declarations of every shell event payload
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written
+pub fn write_typescript_definitions<P: AsRef<Path>>(path: P) -> Result<()> {
+    std::fs::write(path, typescript_definitions())?;
+    Ok(())
+}
+
+/// Register an event's payload schema, returning its name and type
+fn event<T: ShellEvent>(generator: &mut SchemaGenerator) -> (&'static str, String) {
+    (T::NAME, type_of(&generator.subschema_for::<T>()))
+}
+
+/// Declare a named type: an interface for objects, an alias otherwise
+fn declaration(out: &mut String, name: &str, schema: &Schema) {
+    let Schema::Object(object) = schema else {
+        let _ = writeln!(out, "export type {} = {};", name, type_of(schema));
+        return;
+    };
+    doc_comment(out, "", object);
+
+    let variants = object
+        .subschemas
+        .as_ref()
+        .and_then(|sub| sub.one_of.as_ref().or(sub.any_of.as_ref()));
+    if let Some(variants) = variants {
+        let _ = writeln!(out, "export type {} =", name);
+        for variant in variants {
+            if let Schema::Object(variant) = variant {
+                doc_comment(out, "  ", variant);
+            }
+            let _ = writeln!(out, "  | {}", type_of(variant));
+        }
+        // Close the alias on the last variant's line
+        out.pop();
+        out.push_str(";\n");
+    } else if is_interface(object) {
+        let _ = writeln!(out, "export interface {} {{", name);
+        for (property, schema, required) in properties(object) {
+            if let Schema::Object(schema) = schema {
+                doc_comment(out, "  ", schema);
+            }
+            let optional = if required { "" } else { "?" };
+            let _ = writeln!(out, "  {}{}: {};", key(property), optional, type_of(schema));
+        }
+        out.push_str("}\n");
+    } else {
+        let _ = writeln!(out, "export type {} = {};", name, type_of(schema));
+    }
+}
+
+/// Whether an object schema has named properties
+fn is_interface(object: &SchemaObject) -> bool {
+    object
+        .object
+        .as_ref()
+        .is_some_and(|validation| !validation.properties.is_empty())
+}
+
+/// Properties of an object schema, with whether each is required
+fn properties(object: &SchemaObject) -> Vec<(&String, &Schema, bool)> {
+    object
+        .object
+        .as_ref()
+        .map(|validation| {
+            validation
+                .properties
+                .iter()
+                .map(|(name, schema)| (name, schema, validation.required.contains(name)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// TypeScript type of a schema, on one line
+fn type_of(schema: &Schema) -> String {
+    let object = match schema {
+        Schema::Bool(true) => return "unknown".to_string(),
+        Schema::Bool(false) => return "never".to_string(),
+        Schema::Object(object) => object,
+    };
+
+    if let Some(reference) = &object.reference {
+        return reference.trim_start_matches(DEFINITIONS_PATH).to_string();
+    }
+    if let Some(value) = &object.const_value {
+        return value.to_string();
+    }
+    if let Some(values) = &object.enum_values {
+        return union(values.iter().map(|value| value.to_string()));
+    }
+    if let Some(sub) = &object.subschemas {
+        if let Some(all) = &sub.all_of {
+            return all.iter().map(type_of).collect::<Vec<_>>().join(" & ");
+        }
+        if let Some(variants) = sub.one_of.as_ref().or(sub.any_of.as_ref()) {
+            return union(variants.iter().map(type_of));
+        }
+    }
+
+    match &object.instance_type {
+        Some(SingleOrVec::Single(instance)) => instance_type(instance, object),
+        Some(SingleOrVec::Vec(instances)) => union(
+            instances
+                .iter()
+                .map(|instance| instance_type(instance, object)),
+        ),
+        None => "unknown".to_string(),
+    }
+}
+
+fn instance_type(instance: &InstanceType, object: &SchemaObject) -> String {
+    match instance {
+        InstanceType::Null => "null".to_string(),
+        InstanceType::Boolean => "boolean".to_string(),
+        InstanceType::Integer | InstanceType::Number => "number".to_string(),
+        InstanceType::String => "string".to_string(),
+        InstanceType::Array => match object.array.as_ref().and_then(|array| array.items.as_ref()) {
+            Some(SingleOrVec::Single(item)) => {
+                let item = type_of(item);
+                if item.contains(' ') {
+                    format!("({})[]", item)
+                } else {
+                    format!("{}[]", item)
+                }
+            }
+            Some(SingleOrVec::Vec(items)) => {
+                let items: Vec<String> = items.iter().map(type_of).collect();
+                format!("[{}]", items.join(", "))
+            }
+            None => "unknown[]".to_string(),
+        },
+        InstanceType::Object => {
+            if is_interface(object) {
+                let fields: Vec<String> = properties(object)
+                    .into_iter()
+                    .map(|(name, schema, required)| {
+                        let optional = if required { "" } else { "?" };
+                        format!("{}{}: {}", key(name), optional, type_of(schema))
+                    })
+                    .collect();
+                return format!("{{ {} }}", fields.join("; "));
+            }
+            let values = object
+                .object
+                .as_ref()
+                .and_then(|validation| validation.additional_properties.as_deref())
+                .map_or_else(|| "unknown".to_string(), type_of);
+            format!("Record<string, {}>", values)
+        }
+    }
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut seen: Vec<String> = Vec::new();
+    for ty in types {
+        if !seen.contains(&ty) {
+            seen.push(ty);
+        }
+    }
+    seen.join(" | ")
+}
+
+/// A property name, quoted unless it is a plain identifier
+fn key(name: &str) -> String {
+    let plain = name.chars().enumerate().all(|(i, c)| {
+        c == '_' || c == '$' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+    });
+    if plain && !name.is_empty() {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+fn doc_comment(out: &mut String, indent: &str, object: &SchemaObject) {
+    let Some(description) = object
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.description.as_deref())
+    else {
+        return;
+    };
+
+    let lines: Vec<&str> = description.lines().collect();
+    if lines.len() == 1 {
+        let _ = writeln!(out, "{}/** {} */", indent, lines[0]);
+        return;
+    }
+    let _ = writeln!(out, "{}/**", indent);
+    for line in lines {
+        let _ = writeln!(out, "{} * {}", indent, line);
+    }
+    let _ = writeln!(out, "{} */", indent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::samples;
+    use super::*;
+
+    #[test]
+    fn test_every_event_is_declared() {
+        let definitions = typescript_definitions();
+        for (event, _) in samples() {
+            let entry = format!("  \"{}\": ", event.name());
+            assert!(
+                definitions.contains(&entry),
+                "{} is not declared",
+                event.name()
+            );
+        }
+
+        let declared = definitions
+            .lines()
+            .skip_while(|line| !line.starts_with("export interface OsnovaEvents"))
+            .filter(|line| line.starts_with("  \""))
+            .count();
+        assert_eq!(declared, samples().len());
+    }
+
+    #[test]
+    fn test_payloads_are_rendered() {
+        let definitions = typescript_definitions();
+        assert!(definitions.contains(
+            "export interface AppCrashed {\n  /** Application whose backend died */\n  appId: string;"
+        ));
+        assert!(definitions.contains("  exitCode?: number | null;"));
+        assert!(definitions.contains("  \"app-crashed\": AppCrashed;"));
+        assert!(definitions.contains("  | { reason: string; state: \"failed\" }"));
+        assert!(definitions.contains("  | \"payments.send\""));
+    }
+
+    #[test]
+    fn test_checked_in_definitions_are_current() {
+        // Regenerate with `--write-event-types` when this fails
+        let checked_in = include_str!("../../../../app/src/lib/types/events.d.ts");
+        assert_eq!(checked_in, typescript_definitions());
+    }
+
+    #[test]
+    fn test_write_typescript_definitions() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("events.d.ts");
+        write_typescript_definitions(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            typescript_definitions()
+        );
+    }
+}
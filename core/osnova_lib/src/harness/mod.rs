@@ -0,0 +1,53 @@
+//! Scriptable end-to-end scenarios
+//!
+//! QA and app developers drive multi-step flows (create an identity,
+//! install an app from a package, launch it, configure it, back up, wipe,
+//! restore) through the same services the shell uses, without the GUI. A
+//! [`Scenario`] is a list of typed [`Step`]s and [`Check`]s run against a
+//! scratch [`World`] with a mock clock, a local package publisher and
+//! optional storage faults from [`crate::storage::chaos`].
+//!
+//! Scenarios can also be written as JSON and run with the
+//! `osnova-harness` binary; [`bundled`] returns the ones shipped in
+//! `scenarios/`, which the integration tests run.
+//!
+//! Only builds with the `harness` feature have this module.
+
+pub mod scenario;
+pub mod step;
+pub mod world;
+
+pub use scenario::{Scenario, ScenarioFailure, ScenarioReport};
+pub use step::{Check, ScenarioStep, Step};
+pub use world::{AppState, StateDump, World, HARNESS_USER};
+
+use anyhow::{Context, Result};
+
+/// Scenario documents shipped with the crate, by file name
+const BUNDLED: &[(&str, &str)] = &[
+    (
+        "fresh-onboarding.json",
+        include_str!("../../scenarios/fresh-onboarding.json"),
+    ),
+    (
+        "backup-restore.json",
+        include_str!("../../scenarios/backup-restore.json"),
+    ),
+    (
+        "update-rollback.json",
+        include_str!("../../scenarios/update-rollback.json"),
+    ),
+];
+
+/// The bundled scenarios: fresh onboarding, a backup and restore round
+/// trip, and an update with rollback
+///
+/// # Errors
+///
+/// Returns an error if a bundled document does not parse
+pub fn bundled() -> Result<Vec<Scenario>> {
+    BUNDLED
+        .iter()
+        .map(|(file, json)| Scenario::from_json(json).with_context(|| file.to_string()))
+        .collect()
+}
@@ -0,0 +1,541 @@
+//! Scenarios and their reports
+//!
+//! A [`Scenario`] is built either step by step in Rust or from a JSON
+//! document, and both run the same way:
+//!
+//! ```rust,no_run
+//! use osnova_lib::harness::{Check, Scenario};
+//! use osnova_lib::services::OnboardingState;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let report = Scenario::new("onboarding")
+//!     .create_identity()
+//!     .check(Check::Onboarding { state: OnboardingState::Complete })
+//!     .install_app("com.example.notes", "1.0.0")
+//!     .launch_app("com.example.notes")
+//!     .run()
+//!     .await?;
+//! report.ensure_passed()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use super::step::{Check, ScenarioStep, Step};
+use super::world::{StateDump, World};
+use crate::services::updates::UpdatePolicy;
+use crate::storage::chaos::ChaosProfile;
+
+/// A named list of steps run against a fresh device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    /// Scenario name
+    pub name: String,
+    /// What the scenario exercises
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Steps in order
+    pub steps: Vec<ScenarioStep>,
+}
+
+impl Scenario {
+    /// Start an empty scenario
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: String::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Parse a scenario document
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the offending line if the document is not a
+    /// valid scenario
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid scenario")
+    }
+
+    /// The scenario as a JSON document
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Describe what the scenario exercises
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Append a step
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step.into());
+        self
+    }
+
+    /// Expect the last step to fail with an error containing `fragment`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scenario has no steps yet
+    pub fn expect_error(mut self, fragment: impl Into<String>) -> Self {
+        self.steps
+            .last_mut()
+            .expect("expect_error follows the step it applies to")
+            .expect_error = Some(fragment.into());
+        self
+    }
+
+    /// Append a [`Step::CreateIdentity`]
+    pub fn create_identity(self) -> Self {
+        self.step(Step::CreateIdentity)
+    }
+
+    /// Append a [`Step::ImportIdentity`] of the remembered seed phrase
+    pub fn import_identity(self) -> Self {
+        self.step(Step::ImportIdentity { seed_phrase: None })
+    }
+
+    /// Append a [`Step::InstallApp`] with a bare frontend
+    pub fn install_app(self, app_id: &str, version: &str) -> Self {
+        self.step(Step::InstallApp {
+            app_id: app_id.to_string(),
+            version: version.to_string(),
+            files: Default::default(),
+        })
+    }
+
+    /// Append a [`Step::PublishUpdate`] with a bare frontend
+    pub fn publish_update(self, app_id: &str, version: &str) -> Self {
+        self.step(Step::PublishUpdate {
+            app_id: app_id.to_string(),
+            version: version.to_string(),
+            files: Default::default(),
+        })
+    }
+
+    /// Append a [`Step::LaunchApp`]
+    pub fn launch_app(self, app_id: &str) -> Self {
+        self.step(Step::LaunchApp {
+            app_id: app_id.to_string(),
+        })
+    }
+
+    /// Append a [`Step::UninstallApp`]
+    pub fn uninstall_app(self, app_id: &str) -> Self {
+        self.step(Step::UninstallApp {
+            app_id: app_id.to_string(),
+        })
+    }
+
+    /// Append a [`Step::SetConfig`]
+    pub fn set_config(self, app_id: &str, key: &str, value: Value) -> Self {
+        self.step(Step::SetConfig {
+            app_id: app_id.to_string(),
+            key: key.to_string(),
+            value,
+        })
+    }
+
+    /// Append a [`Step::SetUpdatePolicy`]
+    pub fn set_update_policy(self, app_id: &str, policy: UpdatePolicy) -> Self {
+        self.step(Step::SetUpdatePolicy {
+            app_id: app_id.to_string(),
+            policy,
+        })
+    }
+
+    /// Append a [`Step::CheckUpdates`]
+    pub fn check_updates(self) -> Self {
+        self.step(Step::CheckUpdates)
+    }
+
+    /// Append a [`Step::Rollback`]
+    pub fn rollback(self, app_id: &str) -> Self {
+        self.step(Step::Rollback {
+            app_id: app_id.to_string(),
+        })
+    }
+
+    /// Append a [`Step::WriteAppData`]
+    pub fn write_app_data(self, app_id: &str, path: &str, content: &str) -> Self {
+        self.step(Step::WriteAppData {
+            app_id: app_id.to_string(),
+            path: path.to_string(),
+            content: content.to_string(),
+        })
+    }
+
+    /// Append a [`Step::Backup`]
+    pub fn backup(self, name: &str) -> Self {
+        self.step(Step::Backup {
+            name: name.to_string(),
+        })
+    }
+
+    /// Append a [`Step::Wipe`]
+    pub fn wipe(self) -> Self {
+        self.step(Step::Wipe)
+    }
+
+    /// Append a [`Step::Restore`]
+    pub fn restore(self, name: &str) -> Self {
+        self.step(Step::Restore {
+            name: name.to_string(),
+        })
+    }
+
+    /// Append a [`Step::AdvanceClock`]
+    pub fn advance_clock(self, secs: u64) -> Self {
+        self.step(Step::AdvanceClock { secs })
+    }
+
+    /// Append a [`Step::InjectFaults`]
+    pub fn inject_faults(self, profile: ChaosProfile) -> Self {
+        self.step(Step::InjectFaults { profile })
+    }
+
+    /// Append a [`Step::ClearFaults`]
+    pub fn clear_faults(self) -> Self {
+        self.step(Step::ClearFaults)
+    }
+
+    /// Append a [`Step::Check`]
+    pub fn check(self, check: Check) -> Self {
+        self.step(Step::Check(check))
+    }
+
+    /// Run the scenario against a fresh device
+    ///
+    /// A failing step ends the run and is described in the report rather
+    /// than returned as an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the device cannot be set up
+    pub async fn run(&self) -> Result<ScenarioReport> {
+        let mut world = World::new()?;
+        Ok(self.run_in(&mut world).await)
+    }
+
+    /// Run the scenario against an existing device
+    pub async fn run_in(&self, world: &mut World) -> ScenarioReport {
+        let mut failure = None;
+        let mut steps_run = 0;
+        for (index, step) in self.steps.iter().enumerate() {
+            steps_run += 1;
+            let outcome = world.apply(&step.step).await;
+            let message = match (&step.expect_error, outcome) {
+                (None, Ok(())) => continue,
+                (None, Err(e)) => format!("{:#}", e),
+                (Some(fragment), Err(e)) => {
+                    let error = format!("{:#}", e);
+                    if error.contains(fragment.as_str()) {
+                        continue;
+                    }
+                    format!(
+                        "expected an error containing {:?}, got: {}",
+                        fragment, error
+                    )
+                }
+                (Some(fragment), Ok(())) => format!(
+                    "expected the step to fail with an error containing {:?}, but it succeeded",
+                    fragment
+                ),
+            };
+            failure = Some(ScenarioFailure {
+                scenario: self.name.clone(),
+                step_index: index + 1,
+                total_steps: self.steps.len(),
+                step: step.describe(),
+                message,
+                state: world.state(),
+            });
+            break;
+        }
+
+        ScenarioReport {
+            name: self.name.clone(),
+            steps_run,
+            total_steps: self.steps.len(),
+            state: world.state(),
+            failure,
+        }
+    }
+}
+
+/// Outcome of running a scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioReport {
+    /// Scenario name
+    pub name: String,
+    /// Steps carried out, including a failed one
+    pub steps_run: usize,
+    /// Steps in the scenario
+    pub total_steps: usize,
+    /// The step that failed, if any
+    pub failure: Option<ScenarioFailure>,
+    /// The device at the end of the run
+    pub state: StateDump,
+}
+
+impl ScenarioReport {
+    /// Whether every step went as expected
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// The failure as an error, for `?` in tests
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`ScenarioFailure`] if a step failed
+    pub fn ensure_passed(&self) -> Result<()> {
+        match &self.failure {
+            Some(failure) => Err(failure.clone().into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A step that did not go as expected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioFailure {
+    /// Scenario name
+    pub scenario: String,
+    /// Position of the step, counted from 1
+    pub step_index: usize,
+    /// Steps in the scenario
+    pub total_steps: usize,
+    /// The step as JSON
+    pub step: String,
+    /// What went wrong
+    pub message: String,
+    /// The device right after the step
+    pub state: StateDump,
+}
+
+impl fmt::Display for ScenarioFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "scenario {:?} failed at step {} of {}: {}",
+            self.scenario, self.step_index, self.total_steps, self.step
+        )?;
+        writeln!(f, "  {}", self.message)?;
+        writeln!(f, "state after the step:")?;
+        write!(f, "{}", self.state)
+    }
+}
+
+impl std::error::Error for ScenarioFailure {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::OnboardingState;
+    use crate::storage::chaos::ChaosRule;
+    use serde_json::json;
+
+    const APP: &str = "com.test.notes";
+
+    /// A scenario using every kind of step and check
+    fn every_step() -> Scenario {
+        let faults =
+            ChaosProfile::new("broken-apps").with_rule(ChaosRule::new("apps").with_error_rate(1.0));
+
+        Scenario::new("every step")
+            .check(Check::Onboarding {
+                state: OnboardingState::NeedsIdentity,
+            })
+            .create_identity()
+            .install_app(APP, "1.0.0")
+            .launch_app(APP)
+            .launch_app(APP)
+            .check(Check::AppLaunched {
+                app_id: APP.to_string(),
+                times: 2,
+            })
+            .set_config(APP, "theme", json!("dark"))
+            .check(Check::ConfigEquals {
+                app_id: APP.to_string(),
+                key: "theme".to_string(),
+                value: json!("dark"),
+            })
+            .set_update_policy(APP, UpdatePolicy::AutoApply)
+            .publish_update(APP, "1.1.0")
+            .advance_clock(3600)
+            .check_updates()
+            .check(Check::AppInstalled {
+                app_id: APP.to_string(),
+                version: Some("1.1.0".to_string()),
+            })
+            .rollback(APP)
+            .check(Check::AppInstalled {
+                app_id: APP.to_string(),
+                version: Some("1.0.0".to_string()),
+            })
+            .write_app_data(APP, "notes.txt", "hello")
+            .inject_faults(faults)
+            .write_app_data(APP, "notes.txt", "lost")
+            .expect_error("injected by chaos mode")
+            .clear_faults()
+            .check(Check::AppDataEquals {
+                app_id: APP.to_string(),
+                path: "notes.txt".to_string(),
+                content: "hello".to_string(),
+            })
+            .backup("before-wipe")
+            .wipe()
+            .check(Check::AppNotInstalled {
+                app_id: APP.to_string(),
+            })
+            .import_identity()
+            .check(Check::SameIdentity)
+            .restore("before-wipe")
+            .check(Check::SameIdentity)
+            .uninstall_app(APP)
+            .check(Check::AppNotInstalled {
+                app_id: APP.to_string(),
+            })
+    }
+
+    /// A state dump that does not depend on the generated identity
+    fn comparable(mut state: StateDump) -> StateDump {
+        state.address = state.address.map(|_| "address".to_string());
+        state
+    }
+
+    #[tokio::test]
+    async fn test_builder_runs_every_step_type() -> Result<()> {
+        let scenario = every_step();
+        let report = scenario.run().await?;
+        report.ensure_passed()?;
+        assert_eq!(report.steps_run, scenario.steps.len());
+        assert_eq!(
+            report.state.clock,
+            crate::context::EPHEMERAL_START_UNIX + 3600
+        );
+        assert_eq!(report.state.backups, vec!["before-wipe".to_string()]);
+        assert_eq!(report.state.onboarding, Some(OnboardingState::Complete));
+        assert!(report.state.apps.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_scenarios_match_the_builder() -> Result<()> {
+        let built = every_step();
+        let json = built.to_json()?;
+        let parsed = Scenario::from_json(&json)?;
+        assert_eq!(parsed, built);
+
+        // Hand-written documents use the same names
+        let document = r#"{
+            "name": "hand written",
+            "steps": [
+                { "step": "createIdentity" },
+                { "step": "installApp", "appId": "com.test.notes", "version": "1.0.0" },
+                { "step": "launchApp", "appId": "com.test.notes" },
+                { "step": "setConfig", "appId": "com.test.notes", "key": "theme", "value": "dark" },
+                { "step": "rollback", "appId": "com.test.notes",
+                  "expectError": "No previous version" },
+                { "step": "check", "check": "appLaunched", "appId": "com.test.notes", "times": 1 }
+            ]
+        }"#;
+        let from_json = Scenario::from_json(document)?;
+        let from_builder = Scenario::new("hand written")
+            .create_identity()
+            .install_app(APP, "1.0.0")
+            .launch_app(APP)
+            .set_config(APP, "theme", json!("dark"))
+            .rollback(APP)
+            .expect_error("No previous version")
+            .check(Check::AppLaunched {
+                app_id: APP.to_string(),
+                times: 1,
+            });
+        assert_eq!(from_json, from_builder);
+
+        let json_report = from_json.run().await?;
+        let builder_report = from_builder.run().await?;
+        json_report.ensure_passed()?;
+        builder_report.ensure_passed()?;
+        assert_eq!(
+            comparable(json_report.state),
+            comparable(builder_report.state)
+        );
+        assert_eq!(json_report.steps_run, builder_report.steps_run);
+
+        let error =
+            Scenario::from_json(r#"{ "name": "bad", "steps": [{ "step": "fly" }] }"#).unwrap_err();
+        assert!(format!("{:#}", error).contains("fly"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_the_step_and_state() -> Result<()> {
+        let report = Scenario::new("failing")
+            .create_identity()
+            .install_app(APP, "1.0.0")
+            .launch_app(APP)
+            .check(Check::AppLaunched {
+                app_id: APP.to_string(),
+                times: 3,
+            })
+            .wipe()
+            .run()
+            .await?;
+
+        assert!(!report.passed());
+        assert_eq!(report.steps_run, 4);
+        let error = report.ensure_passed().unwrap_err();
+        let failure = error.downcast_ref::<ScenarioFailure>().unwrap();
+        assert_eq!(failure.step_index, 4);
+        assert_eq!(failure.total_steps, 5);
+        assert_eq!(
+            failure.message,
+            "expected com.test.notes to have been launched 3 times, found 1"
+        );
+        assert_eq!(failure.state.apps.len(), 1);
+        assert_eq!(failure.state.apps[0].launches, 1);
+
+        let text = failure.to_string();
+        assert!(text.starts_with("scenario \"failing\" failed at step 4 of 5: "));
+        assert!(text.contains(r#""check":"appLaunched""#));
+        assert!(text.contains("state after the step:"));
+        assert!(text.contains("app com.test.notes 1.0.0: 1 launches"));
+        assert!(text.contains("onboarding: Complete"));
+
+        // Expected errors that do not happen, or differ, are failures too
+        let report = Scenario::new("unexpected success")
+            .create_identity()
+            .expect_error("already exists")
+            .run()
+            .await?;
+        let failure = report.failure.unwrap();
+        assert_eq!(failure.step_index, 1);
+        assert!(failure.message.contains("but it succeeded"));
+
+        let report = Scenario::new("other error")
+            .create_identity()
+            .launch_app(APP)
+            .expect_error("injected")
+            .run()
+            .await?;
+        let failure = report.failure.unwrap();
+        assert_eq!(failure.step_index, 2);
+        assert!(failure
+            .message
+            .starts_with("expected an error containing \"injected\", got: "));
+        Ok(())
+    }
+}
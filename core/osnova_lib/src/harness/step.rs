@@ -0,0 +1,218 @@
+//! Steps and checks a scenario is made of
+//!
+//! Both serialize as JSON objects tagged by `step` (and, for checks, also
+//! by `check`), so a scenario file is a plain list of them:
+//!
+//! ```json
+//! [
+//!   { "step": "createIdentity" },
+//!   { "step": "installApp", "appId": "com.example.notes", "version": "1.0.0" },
+//!   { "step": "check", "check": "appInstalled", "appId": "com.example.notes" }
+//! ]
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::services::identity::OnboardingState;
+use crate::services::updates::UpdatePolicy;
+use crate::storage::chaos::ChaosProfile;
+
+/// One operation of a scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "camelCase")]
+pub enum Step {
+    /// Create a new identity; its seed phrase is remembered as the user's
+    /// paper backup
+    CreateIdentity,
+    /// Import an identity from a seed phrase, by default the remembered one
+    #[serde(rename_all = "camelCase")]
+    ImportIdentity {
+        /// Seed phrase to import instead of the remembered one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed_phrase: Option<String>,
+    },
+    /// Package a frontend, install the app from it and accept its
+    /// first-launch review
+    #[serde(rename_all = "camelCase")]
+    InstallApp {
+        /// Application ID
+        app_id: String,
+        /// Version to install
+        version: String,
+        /// Files of the frontend by path; a bare `index.html` when empty
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        files: BTreeMap<String, String>,
+    },
+    /// Package a new version of an app and publish it for update checks
+    #[serde(rename_all = "camelCase")]
+    PublishUpdate {
+        /// Application ID
+        app_id: String,
+        /// Version to publish
+        version: String,
+        /// Files of the frontend by path; a bare `index.html` when empty
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        files: BTreeMap<String, String>,
+    },
+    /// Launch an installed app
+    #[serde(rename_all = "camelCase")]
+    LaunchApp {
+        /// Application ID
+        app_id: String,
+    },
+    /// Uninstall an app, keeping nothing in the trash
+    #[serde(rename_all = "camelCase")]
+    UninstallApp {
+        /// Application ID
+        app_id: String,
+    },
+    /// Set one setting of an app's configuration
+    #[serde(rename_all = "camelCase")]
+    SetConfig {
+        /// Application ID
+        app_id: String,
+        /// Setting name
+        key: String,
+        /// New value
+        value: Value,
+    },
+    /// Choose what happens when an update to an app is found
+    #[serde(rename_all = "camelCase")]
+    SetUpdatePolicy {
+        /// Application ID
+        app_id: String,
+        /// Update policy
+        policy: UpdatePolicy,
+    },
+    /// Check for updates and handle each according to its policy; fails if
+    /// any update fails
+    CheckUpdates,
+    /// Restore the version the last update of an app replaced
+    #[serde(rename_all = "camelCase")]
+    Rollback {
+        /// Application ID
+        app_id: String,
+    },
+    /// Write a file to an app's encrypted data, through any injected faults
+    #[serde(rename_all = "camelCase")]
+    WriteAppData {
+        /// Application ID
+        app_id: String,
+        /// Path within the app's data
+        path: String,
+        /// File contents
+        content: String,
+    },
+    /// Take a named backup of the device's storage
+    Backup {
+        /// Backup name
+        name: String,
+    },
+    /// Erase the device's storage and component cache, as on a lost device
+    Wipe,
+    /// Replace the device's storage with a named backup
+    Restore {
+        /// Backup name
+        name: String,
+    },
+    /// Move the scenario clock forward
+    AdvanceClock {
+        /// Seconds to advance by
+        secs: u64,
+    },
+    /// Inject storage faults into later app data writes
+    InjectFaults {
+        /// Faults to inject
+        profile: ChaosProfile,
+    },
+    /// Stop injecting faults
+    ClearFaults,
+    /// Assert something about the device's state
+    Check(Check),
+}
+
+/// An assertion about the device's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "camelCase")]
+pub enum Check {
+    /// Where first-run onboarding stands
+    Onboarding {
+        /// Expected state
+        state: OnboardingState,
+    },
+    /// The identity has the address of the one created or imported first
+    SameIdentity,
+    /// An app is installed, at a given version if set
+    #[serde(rename_all = "camelCase")]
+    AppInstalled {
+        /// Application ID
+        app_id: String,
+        /// Expected version
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+    /// An app is not installed
+    #[serde(rename_all = "camelCase")]
+    AppNotInstalled {
+        /// Application ID
+        app_id: String,
+    },
+    /// A setting of an app's configuration has a value
+    #[serde(rename_all = "camelCase")]
+    ConfigEquals {
+        /// Application ID
+        app_id: String,
+        /// Setting name
+        key: String,
+        /// Expected value
+        value: Value,
+    },
+    /// An app has been launched a number of times
+    #[serde(rename_all = "camelCase")]
+    AppLaunched {
+        /// Application ID
+        app_id: String,
+        /// Expected launch count
+        times: u64,
+    },
+    /// A file in an app's data has the given contents
+    #[serde(rename_all = "camelCase")]
+    AppDataEquals {
+        /// Application ID
+        app_id: String,
+        /// Path within the app's data
+        path: String,
+        /// Expected contents
+        content: String,
+    },
+}
+
+/// A step with what the scenario expects of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioStep {
+    /// The operation
+    #[serde(flatten)]
+    pub step: Step,
+    /// The step must fail with an error containing this text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_error: Option<String>,
+}
+
+impl ScenarioStep {
+    /// The step as one line of JSON, for failure reports
+    pub fn describe(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!("{:?}", self.step))
+    }
+}
+
+impl From<Step> for ScenarioStep {
+    fn from(step: Step) -> Self {
+        Self {
+            step,
+            expect_error: None,
+        }
+    }
+}
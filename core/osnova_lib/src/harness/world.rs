@@ -0,0 +1,590 @@
+//! The device a scenario runs against
+//!
+//! A [`World`] is a scratch storage root with its own component cache, a
+//! package publisher standing in for the network, and a [`MockClock`].
+//! Services are opened from the storage root for every step, as the shell
+//! opens them at start, so a wipe or restore is seen by the next step.
+//! The root is removed when the world is dropped.
+//!
+//! The in-memory [`OsnovaContext`](crate::context::OsnovaContext) only
+//! backs the config and key services, so worlds live in the temporary
+//! directory instead.
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::step::{Check, Step};
+use crate::cache::CacheManager;
+use crate::components::integrity::content_hash;
+use crate::components::ComponentDownloader;
+use crate::context::EPHEMERAL_START_UNIX;
+use crate::manifest::ManifestSchema;
+use crate::models::application::{ComponentKind, ComponentRef, OsnovaApplication};
+use crate::network::NetworkOptions;
+use crate::services::identity::OnboardingState;
+use crate::services::{AppsService, ConfigService, IdentityService, UpdateService};
+use crate::storage::chaos::ChaosFileStorage;
+use crate::storage::{FileStorage, SqlStorage};
+use crate::time::{Clock, MockClock};
+
+/// User the harness acts as
+pub const HARNESS_USER: &str = "harness-user";
+
+/// Component cache quota of a world
+const CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Key app data written by scenarios is encrypted with
+const APP_DATA_KEY: [u8; 32] = [0x5a; 32];
+
+/// Published manifests by app ID
+type Publisher = Arc<Mutex<HashMap<String, ManifestSchema>>>;
+
+/// Scratch device the steps of a scenario act on
+pub struct World {
+    root: PathBuf,
+    /// Distinguishes this world's packages from those of other worlds
+    token: String,
+    clock: Arc<MockClock>,
+    cache: CacheManager,
+    published: Publisher,
+    /// The seed phrase and address of the first identity, as the user
+    /// would have written them down
+    seed_phrase: Option<String>,
+    address: Option<String>,
+    faults: Option<ChaosFileStorage>,
+    backups: BTreeSet<String>,
+}
+
+impl World {
+    /// Create an empty device in a new scratch directory
+    ///
+    /// The clock starts at [`EPHEMERAL_START_UNIX`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scratch directory cannot be created
+    pub fn new() -> Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let token = format!(
+            "{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let root = std::env::temp_dir().join(format!("osnova-harness-{}", token));
+        fs::create_dir_all(root.join("storage"))
+            .context("Failed to create the scenario directory")?;
+
+        let clock = Arc::new(MockClock::new(EPHEMERAL_START_UNIX));
+        let cache = CacheManager::new(root.join("cache"), CACHE_BYTES)?.with_clock(clock.clone());
+        Ok(Self {
+            root,
+            token,
+            clock,
+            cache,
+            published: Arc::default(),
+            seed_phrase: None,
+            address: None,
+            faults: None,
+            backups: BTreeSet::new(),
+        })
+    }
+
+    /// Storage root of the device
+    pub fn storage_path(&self) -> PathBuf {
+        self.root.join("storage")
+    }
+
+    /// The scenario clock
+    pub fn clock(&self) -> Arc<MockClock> {
+        self.clock.clone()
+    }
+
+    /// Carry out one step
+    ///
+    /// # Errors
+    ///
+    /// Returns the service's error if the step fails, or a description of
+    /// the mismatch if a check does not hold
+    pub async fn apply(&mut self, step: &Step) -> Result<()> {
+        match step {
+            Step::CreateIdentity => {
+                let (seed_phrase, address) = self.identity()?.create()?;
+                self.seed_phrase.get_or_insert(seed_phrase);
+                self.address.get_or_insert(address);
+            }
+            Step::ImportIdentity { seed_phrase } => {
+                let seed_phrase = seed_phrase
+                    .clone()
+                    .or_else(|| self.seed_phrase.clone())
+                    .context("No seed phrase to import; create an identity first")?;
+                let address = self.identity()?.import_with_phrase(&seed_phrase)?;
+                self.seed_phrase.get_or_insert(seed_phrase);
+                self.address.get_or_insert(address);
+            }
+            Step::InstallApp {
+                app_id,
+                version,
+                files,
+            } => {
+                let manifest = self.package(app_id, version, files)?;
+                let apps = self.apps()?;
+                for warning in apps.install_manifest(&manifest).await? {
+                    crate::log!(Warn, "{}", warning);
+                }
+                apps.record_consent(app_id, true, vec![])?;
+            }
+            Step::PublishUpdate {
+                app_id,
+                version,
+                files,
+            } => {
+                let manifest = self.package(app_id, version, files)?;
+                self.published
+                    .lock()
+                    .unwrap()
+                    .insert(app_id.clone(), manifest);
+            }
+            Step::LaunchApp { app_id } => self.apps()?.launch(app_id)?,
+            Step::UninstallApp { app_id } => self.apps()?.uninstall(app_id, Some(0))?,
+            Step::SetConfig { app_id, key, value } => {
+                let config = self.config()?;
+                let mut settings = config
+                    .get_app_config(app_id, HARNESS_USER)?
+                    .settings()
+                    .clone();
+                settings.insert(key.clone(), value.clone());
+                config.set_app_config(app_id, HARNESS_USER, settings)?;
+            }
+            Step::SetUpdatePolicy { app_id, policy } => {
+                self.config()?
+                    .set_app_update_policy(app_id, Some(*policy))?;
+            }
+            Step::CheckUpdates => {
+                let outcomes = self
+                    .updates()?
+                    .check_and_apply(&NetworkOptions::default())
+                    .await?;
+                for outcome in outcomes {
+                    if let Some(error) = outcome.error {
+                        bail!(
+                            "Update of {} to {} failed: {}",
+                            outcome.app_id,
+                            outcome.to_version,
+                            error
+                        );
+                    }
+                }
+            }
+            Step::Rollback { app_id } => {
+                self.updates()?.rollback(app_id).await?;
+            }
+            Step::WriteAppData {
+                app_id,
+                path,
+                content,
+            } => {
+                let path = Self::app_data_path(app_id, path);
+                match &self.faults {
+                    Some(chaos) => chaos.write(&path, content.as_bytes(), &APP_DATA_KEY)?,
+                    None => self
+                        .files()?
+                        .write(&path, content.as_bytes(), &APP_DATA_KEY)?,
+                }
+            }
+            Step::Backup { name } => self.backup(name)?,
+            Step::Wipe => {
+                // The storage root itself is left, empty, as on a fresh install
+                let storage_path = self.storage_path();
+                if storage_path.exists() {
+                    fs::remove_dir_all(&storage_path).context("Failed to wipe the storage")?;
+                }
+                fs::create_dir_all(&storage_path).context("Failed to wipe the storage")?;
+                self.cache.clear().await?;
+            }
+            Step::Restore { name } => self.restore(name)?,
+            Step::AdvanceClock { secs } => self.clock.advance(Duration::from_secs(*secs)),
+            Step::InjectFaults { profile } => {
+                let files = FileStorage::new(self.storage_path())?;
+                self.faults = Some(ChaosFileStorage::new(files, profile.clone())?);
+            }
+            Step::ClearFaults => self.faults = None,
+            Step::Check(check) => self.check(check)?,
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the device for failure reports
+    ///
+    /// Parts that cannot be read are listed in [`StateDump::errors`]
+    /// instead.
+    pub fn state(&self) -> StateDump {
+        let mut state = StateDump {
+            clock: self.clock.now_unix(),
+            backups: self.backups.iter().cloned().collect(),
+            faults: self
+                .faults
+                .as_ref()
+                .map(|chaos| chaos.profile().name.clone()),
+            ..StateDump::default()
+        };
+        match self.identity().and_then(|identity| identity.status()) {
+            Ok(status) => {
+                state.onboarding = Some(OnboardingState::from(status.state));
+                state.address = status.address;
+            }
+            Err(e) => state.errors.push(format!("identity: {:#}", e)),
+        }
+
+        let apps = self.database().and_then(|database| {
+            let config = self.config()?;
+            let mut apps = Vec::new();
+            for app in database.list_applications()? {
+                let settings = config
+                    .get_app_config(app.id(), HARNESS_USER)?
+                    .settings()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                apps.push(AppState {
+                    app_id: app.id().to_string(),
+                    version: app.version().to_string(),
+                    launches: database.app_launch_count(app.id())?,
+                    config: settings,
+                });
+            }
+            apps.sort_by(|a, b| a.app_id.cmp(&b.app_id));
+            Ok(apps)
+        });
+        match apps {
+            Ok(apps) => state.apps = apps,
+            Err(e) => state.errors.push(format!("apps: {:#}", e)),
+        }
+        state
+    }
+
+    /// Evaluate a check against the device
+    fn check(&self, check: &Check) -> Result<()> {
+        match check {
+            Check::Onboarding { state } => {
+                let actual = self.identity()?.onboarding_state()?;
+                ensure!(
+                    actual == *state,
+                    "expected onboarding to be {:?}, found {:?}",
+                    state,
+                    actual
+                );
+            }
+            Check::SameIdentity => {
+                let expected = self
+                    .address
+                    .as_deref()
+                    .context("No identity was created or imported")?;
+                let actual = self.identity()?.status()?.address;
+                ensure!(
+                    actual.as_deref() == Some(expected),
+                    "expected the identity {}, found {}",
+                    expected,
+                    actual.as_deref().unwrap_or("none")
+                );
+            }
+            Check::AppInstalled { app_id, version } => {
+                let app = self.database()?.get_application(app_id)?.with_context(|| {
+                    format!("expected {} to be installed, found nothing", app_id)
+                })?;
+                if let Some(version) = version {
+                    ensure!(
+                        app.version() == version,
+                        "expected {} {} to be installed, found {}",
+                        app_id,
+                        version,
+                        app.version()
+                    );
+                }
+            }
+            Check::AppNotInstalled { app_id } => {
+                if let Some(app) = self.database()?.get_application(app_id)? {
+                    bail!(
+                        "expected {} not to be installed, found {}",
+                        app_id,
+                        app.version()
+                    );
+                }
+            }
+            Check::ConfigEquals { app_id, key, value } => {
+                let config = self.config()?.get_app_config(app_id, HARNESS_USER)?;
+                let actual = config.get_setting(key);
+                ensure!(
+                    actual == Some(value),
+                    "expected {} of {} to be {}, found {}",
+                    key,
+                    app_id,
+                    value,
+                    actual.map_or("nothing".to_string(), Value::to_string)
+                );
+            }
+            Check::AppLaunched { app_id, times } => {
+                let launches = self.database()?.app_launch_count(app_id)?;
+                ensure!(
+                    launches == *times,
+                    "expected {} to have been launched {} times, found {}",
+                    app_id,
+                    times,
+                    launches
+                );
+            }
+            Check::AppDataEquals {
+                app_id,
+                path,
+                content,
+            } => {
+                let data = self
+                    .files()?
+                    .read(Self::app_data_path(app_id, path), &APP_DATA_KEY)?;
+                let actual = String::from_utf8_lossy(&data);
+                ensure!(
+                    actual == content.as_str(),
+                    "expected {} of {} to hold {:?}, found {:?}",
+                    path,
+                    app_id,
+                    content,
+                    actual
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a frontend package for a version of an app
+    fn package(
+        &self,
+        app_id: &str,
+        version: &str,
+        files: &BTreeMap<String, String>,
+    ) -> Result<ManifestSchema> {
+        let packages = self.root.join("packages");
+        fs::create_dir_all(&packages).context("Failed to create the package directory")?;
+        let tarball = packages.join(format!("{}-{}.tar.gz", app_id, version));
+
+        let default_files =
+            BTreeMap::from([("index.html".to_string(), "<html></html>".to_string())]);
+        let files = if files.is_empty() {
+            &default_files
+        } else {
+            files
+        };
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&tarball).context("Failed to create the package")?,
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+
+        // Frontends are extracted next to those of other worlds
+        let name = app_id.rsplit('.').next().unwrap_or(app_id);
+        let component = ComponentRef::new(
+            format!("file://{}", tarball.display()),
+            format!("{}-{}", name, self.token),
+            ComponentKind::Frontend,
+            version,
+        )?
+        .with_hash(content_hash(&fs::read(&tarball)?));
+        let app = OsnovaApplication::new(
+            app_id,
+            name,
+            version,
+            "ant://icon",
+            format!("{} packaged by a scenario", name),
+            vec![component],
+        )?;
+        Ok(ManifestSchema::from(&app))
+    }
+
+    /// Copy the storage root aside, the database through an online backup
+    fn backup(&mut self, name: &str) -> Result<()> {
+        let storage_path = self.storage_path();
+        let destination = self.backup_path(name)?;
+        if destination.exists() {
+            fs::remove_dir_all(&destination).context("Failed to replace the backup")?;
+        }
+        fs::create_dir_all(&destination).context("Failed to create the backup")?;
+
+        self.database()?.backup_to(destination.join("osnova.db"))?;
+        copy_tree(&storage_path, &destination, &|name| {
+            !name.starts_with("osnova.db")
+        })?;
+        self.backups.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Replace the storage root with a backup
+    fn restore(&mut self, name: &str) -> Result<()> {
+        ensure!(self.backups.contains(name), "No backup named {}", name);
+        let storage_path = self.storage_path();
+        if storage_path.exists() {
+            fs::remove_dir_all(&storage_path).context("Failed to clear the storage")?;
+        }
+        copy_tree(&self.backup_path(name)?, &storage_path, &|_| true)
+    }
+
+    fn backup_path(&self, name: &str) -> Result<PathBuf> {
+        ensure!(
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+            "Backup names may only hold letters, digits and dashes: {:?}",
+            name
+        );
+        Ok(self.root.join("backups").join(name))
+    }
+
+    fn app_data_path(app_id: &str, path: &str) -> PathBuf {
+        Path::new("apps").join(app_id).join("data").join(path)
+    }
+
+    fn identity(&self) -> Result<IdentityService> {
+        IdentityService::new(self.storage_path())
+    }
+
+    fn config(&self) -> Result<ConfigService> {
+        ConfigService::new(self.storage_path())
+    }
+
+    fn database(&self) -> Result<SqlStorage> {
+        SqlStorage::new(self.storage_path().join("osnova.db"))
+    }
+
+    fn files(&self) -> Result<FileStorage> {
+        FileStorage::new(self.storage_path())
+    }
+
+    fn downloader(&self) -> ComponentDownloader {
+        ComponentDownloader::new(self.cache.clone(), None)
+    }
+
+    fn apps(&self) -> Result<AppsService> {
+        Ok(AppsService::new(self.storage_path())?
+            .with_downloader(self.downloader())
+            .with_user(HARNESS_USER))
+    }
+
+    fn updates(&self) -> Result<UpdateService> {
+        let published = self.published.clone();
+        Ok(UpdateService::new(self.storage_path())?
+            .with_downloader(Arc::new(self.downloader()))
+            .with_clock(self.clock.clone())
+            .with_manifest_fetcher(move |app_id: String| {
+                let manifest = published.lock().unwrap().get(&app_id).cloned();
+                async move { manifest.with_context(|| format!("Nothing published for {}", app_id)) }
+            }))
+    }
+}
+
+impl Drop for World {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.root) {
+            crate::log!(
+                Warn,
+                "Failed to remove scenario directory {}: {}",
+                self.root.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Copy the files under `from` whose top-level name passes `keep` to `to`
+fn copy_tree(from: &Path, to: &Path, keep: &dyn Fn(&str) -> bool) -> Result<()> {
+    fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    for entry in fs::read_dir(from).with_context(|| format!("Failed to read {}", from.display()))? {
+        let entry = entry?;
+        if !keep(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree(&entry.path(), &target, &|_| true)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// An installed app as seen by a [`StateDump`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppState {
+    /// Application ID
+    pub app_id: String,
+    /// Installed version
+    pub version: String,
+    /// Recorded launches
+    pub launches: u64,
+    /// The harness user's configuration of the app
+    pub config: BTreeMap<String, Value>,
+}
+
+/// What a device held when a scenario stopped
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDump {
+    /// Scenario clock as Unix seconds
+    pub clock: u64,
+    /// Where onboarding stands
+    pub onboarding: Option<OnboardingState>,
+    /// Address of the identity
+    pub address: Option<String>,
+    /// Installed apps, by ID
+    pub apps: Vec<AppState>,
+    /// Names of the backups taken
+    pub backups: Vec<String>,
+    /// Name of the fault profile in effect
+    pub faults: Option<String>,
+    /// Parts of the state that could not be read
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for StateDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  clock: {}", self.clock)?;
+        if let Some(onboarding) = self.onboarding {
+            let address = self.address.as_deref().unwrap_or("no identity");
+            writeln!(f, "  onboarding: {:?} ({})", onboarding, address)?;
+        }
+        if self.apps.is_empty() {
+            writeln!(f, "  apps: none")?;
+        }
+        for app in &self.apps {
+            let config = serde_json::to_string(&app.config).map_err(|_| fmt::Error)?;
+            writeln!(
+                f,
+                "  app {} {}: {} launches, config {}",
+                app.app_id, app.version, app.launches, config
+            )?;
+        }
+        if !self.backups.is_empty() {
+            writeln!(f, "  backups: {}", self.backups.join(", "))?;
+        }
+        if let Some(faults) = &self.faults {
+            writeln!(f, "  faults: {}", faults)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "  unreadable {}", error)?;
+        }
+        Ok(())
+    }
+}
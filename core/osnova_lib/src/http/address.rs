@@ -0,0 +1,176 @@
+//! Refusing private-network targets
+//!
+//! A URL taken from a manifest or catalog must not reach the machine's own
+//! services or its local network: on a server deployment that would let
+//! any published manifest probe the operator's network. Hosts are resolved
+//! here, and the fetch fails if any address they resolve to is private.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// A fetch refused by the [`FetchPolicy`](super::FetchPolicy)
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Refused {
+    /// The host is, or resolves to, a private or link-local address
+    #[error("{host} resolves to private address {address}")]
+    PrivateAddress {
+        /// Host as written in the URL
+        host: String,
+        /// Offending address
+        address: IpAddr,
+    },
+    /// A redirect left the origin the request was made to
+    #[error("redirect from {from} to {to} changes origin")]
+    CrossOriginRedirect {
+        /// Origin of the original request
+        from: String,
+        /// Origin redirected to
+        to: String,
+    },
+    /// Too many redirects in a row
+    #[error("more than {0} redirects")]
+    TooManyRedirects(usize),
+    /// The response is larger than the origin may send
+    #[error("response from {origin} exceeds {limit} bytes")]
+    TooLarge {
+        /// Origin that answered
+        origin: String,
+        /// Limit in bytes
+        limit: u64,
+    },
+    /// The origin's certificate key does not match its pins
+    #[error("certificate of {origin} does not match its pinned keys (got {found})")]
+    PinMismatch {
+        /// Pinned origin
+        origin: String,
+        /// Pin of the key presented, or why there was none
+        found: String,
+    },
+}
+
+/// Whether remote content may be sent to `ip`
+///
+/// Refuses loopback, private (RFC 1918, unique local), link-local,
+/// carrier-grade NAT, unspecified, broadcast and multicast addresses,
+/// including IPv4 addresses embedded in IPv6.
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_private_v4(mapped),
+            None => is_private_v6(ip),
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT (100.64.0.0/10)
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10)
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Refuse `host` when it is a private IP literal
+pub(super) fn check_literal(host: &str) -> Result<(), Refused> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    match literal.parse::<IpAddr>() {
+        Ok(address) if is_private(address) => Err(Refused::PrivateAddress {
+            host: host.to_string(),
+            address,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves with the operating system's resolver
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Resolver that fails for hosts resolving to a private address
+pub(super) struct GuardedResolver {
+    pub(super) inner: Arc<dyn Resolve>,
+    /// Whether to refuse private addresses at all; trusted requests only
+    /// pass through to `inner`
+    pub(super) guarded: bool,
+    /// Hosts allowed to resolve to private addresses
+    pub(super) allowed: Vec<String>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let allowed = !self.guarded || self.allowed.iter().any(|h| h.eq_ignore_ascii_case(&host));
+        let resolving = self.inner.resolve(name);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = resolving.await?.collect();
+            if !allowed {
+                // Refuse if any address is private, so DNS answers mixing
+                // public and private addresses cannot slip through
+                if let Some(addr) = addrs.iter().find(|addr| is_private(addr.ip())) {
+                    return Err(Box::new(Refused::PrivateAddress {
+                        host,
+                        address: addr.ip(),
+                    }) as _);
+                }
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_private() {
+        for private in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(is_private(private.parse().unwrap()), "{}", private);
+        }
+        for public in ["1.1.1.1", "172.32.0.1", "100.128.0.1", "2606:4700::1111"] {
+            assert!(!is_private(public.parse().unwrap()), "{}", public);
+        }
+        assert!(check_literal("[::1]").is_err());
+        assert!(check_literal("example.com").is_ok());
+    }
+}
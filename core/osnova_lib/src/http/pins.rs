@@ -0,0 +1,107 @@
+//! Certificate public key pins
+//!
+//! A pin is the base64 SHA-256 of a certificate's DER-encoded
+//! SubjectPublicKeyInfo, the `pin-sha256` value of RFC 7469. Pinning the
+//! key rather than the certificate survives renewals that keep the key.
+//! Compute one with
+//!
+//! ```text
+//! openssl x509 -in cert.pem -pubkey -noout | openssl pkey -pubin -outform der \
+//!     | openssl dgst -sha256 -binary | base64
+//! ```
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+/// Pin of the public key in a DER-encoded X.509 certificate
+///
+/// Returns `None` if the certificate cannot be parsed.
+pub fn spki_pin(certificate: &[u8]) -> Option<String> {
+    let spki = subject_public_key_info(certificate)?;
+    Some(base64::engine::general_purpose::STANDARD.encode(Sha256::digest(spki)))
+}
+
+/// The SubjectPublicKeyInfo element, tag and length included
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let certificate = read_element(certificate)?;
+    if certificate.tag != SEQUENCE {
+        return None;
+    }
+    let tbs = read_element(certificate.contents)?;
+    if tbs.tag != SEQUENCE {
+        return None;
+    }
+    // version (optional), serialNumber, signature, issuer, validity, subject
+    let mut fields = tbs.contents;
+    if fields.first() == Some(&VERSION) {
+        fields = read_element(fields)?.rest;
+    }
+    for _ in 0..5 {
+        fields = read_element(fields)?.rest;
+    }
+    let spki = read_element(fields)?;
+    (spki.tag == SEQUENCE).then_some(spki.whole)
+}
+
+/// A DER element split off the front of its input
+struct Element<'a> {
+    tag: u8,
+    /// The element, tag and length included
+    whole: &'a [u8],
+    contents: &'a [u8],
+    /// Input following the element
+    rest: &'a [u8],
+}
+
+/// Split the first DER element off `input`
+fn read_element(input: &[u8]) -> Option<Element<'_>> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (header, length) = if first < 0x80 {
+        (2, first)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = input.get(2..2 + count)?;
+        (
+            2 + count,
+            bytes.iter().fold(0usize, |n, b| n << 8 | *b as usize),
+        )
+    };
+    let end = header.checked_add(length)?;
+    let whole = input.get(..end)?;
+    Some(Element {
+        tag,
+        whole,
+        contents: &whole[header..],
+        rest: &input[end..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spki_pin_matches_openssl() {
+        let pem = super::super::tests::CERT_PEM;
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(
+                pem.lines()
+                    .filter(|line| !line.starts_with("-----"))
+                    .collect::<String>(),
+            )
+            .unwrap();
+        assert_eq!(
+            spki_pin(&der).as_deref(),
+            Some(super::super::tests::CERT_PIN)
+        );
+        assert_eq!(spki_pin(&der[..40]), None);
+        assert_eq!(spki_pin(b""), None);
+    }
+}
@@ -27,16 +27,42 @@
 
 /// Data models for Osnova entities
 pub mod models {
+    pub mod annotation;
     pub mod application;
+    pub mod archive_upload;
+    pub mod backend_process;
     pub mod config_cache;
+    pub mod config_snapshot;
+    pub mod consent;
+    pub mod crash_report;
     pub mod device_key;
+    pub mod feature_flag;
+    pub mod health;
     pub mod identity;
+    pub mod install_journal;
     pub mod key_cocoon;
+    pub mod key_usage;
+    pub mod launcher_change;
+    pub mod launcher_layout;
+    pub mod materialization;
+    pub mod mutation;
+    pub mod notification;
     pub mod pairing;
+    pub mod payment_record;
+    pub mod permission;
+    pub mod privacy;
+    pub mod provenance;
+    pub mod retention;
+    pub mod session;
+    pub mod settings_schema;
+    pub mod sharing;
+    pub mod signature;
+    pub mod task;
 }
 
 /// Cryptographic operations (key derivation, encryption)
 pub mod crypto {
+    pub mod blind_index;
     pub mod encryption;
     pub mod key_derivation;
 }
@@ -50,6 +76,9 @@ pub mod services;
 /// Network operations (Autonomi integration)
 pub mod network;
 
+/// Policy-enforcing HTTP(S) fetching of remote content
+pub mod http;
+
 /// Component cache (local storage)
 pub mod cache;
 
@@ -62,6 +91,46 @@ pub mod components;
 /// Platform-specific utilities (paths, system integration)
 pub mod platform;
 
+/// Checksummed 4-word identity addresses
+pub mod address;
+
+/// Tamper-evident audit log of security-relevant actions
+pub mod audit;
+
+/// Structured logging with a live stream and rotating files
+pub mod logs;
+
+/// Clock abstraction with monotonic, skew-tolerant timestamps
+pub mod time;
+
+/// Shared helpers (canonical JSON)
+pub mod util;
+
+/// Multi-device sync (incremental settings diffs)
+pub mod sync;
+
+/// OpenRPC method registry, document, and server
+pub mod rpc;
+
+/// Typed events the shell emits to its frontend
+pub mod events;
+
+/// Shared runtime context and structured shutdown of background tasks
+pub mod context;
+
+/// Support diagnostics (allowlisted read-only queries)
+pub mod diagnostics;
+
+/// Group policies for managed devices (signed install, launch and wallet rules)
+pub mod policies;
+
+/// Stable request/response shapes and wrappers behind the shell's commands
+pub mod tauri_api;
+
+/// Scriptable end-to-end scenarios (harness feature, for QA builds only)
+#[cfg(feature = "harness")]
+pub mod harness;
+
 /// Error types for Osnova operations
 pub mod error {
     use thiserror::Error;
@@ -77,6 +146,10 @@ pub mod error {
         #[error("Cryptographic error: {0}")]
         Crypto(String),
 
+        /// Ciphertext did not decrypt
+        #[error("Cryptographic error: {0}")]
+        Decrypt(#[from] crate::crypto::encryption::DecryptFailure),
+
         /// Storage operation failed
         #[error("Storage error: {0}")]
         Storage(String),
@@ -89,6 +162,45 @@ pub mod error {
         #[error("Network error: {0}")]
         Network(String),
 
+        /// Network operation did not finish within its timeout
+        #[error("Network error: {operation} timed out after {after:?}")]
+        Timeout {
+            /// Operation that timed out
+            operation: String,
+            /// Timeout that elapsed
+            after: std::time::Duration,
+        },
+
+        /// Network operation was cancelled by the caller
+        #[error("Network error: {operation} was cancelled")]
+        Cancelled {
+            /// Operation that was cancelled
+            operation: String,
+        },
+
+        /// Network operation refused because the user switched Osnova offline
+        #[error("Network error: {operation} is blocked in offline mode")]
+        OfflineMode {
+            /// Operation that was refused
+            operation: String,
+        },
+
+        /// Network operation refused because its destination's circuit is
+        /// open after retries used up its retry budget
+        #[error("Network error: {operation} refused, {destination} is unavailable (retry after {retry_after:?})")]
+        CircuitOpen {
+            /// Operation that was refused
+            operation: String,
+            /// Destination whose circuit is open
+            destination: String,
+            /// Time until the destination admits a probe
+            retry_after: std::time::Duration,
+        },
+
+        /// Fetch refused by the HTTP fetch policy
+        #[error("Network error: fetch refused: {0}")]
+        FetchRefused(#[from] crate::http::Refused),
+
         /// Serialization/deserialization failed
         #[error("Serialization error: {0}")]
         Serialization(#[from] serde_json::Error),
@@ -97,11 +209,178 @@ pub mod error {
         #[error("I/O error: {0}")]
         Io(#[from] std::io::Error),
 
+        /// Not enough free disk space for a write
+        #[error(
+            "Storage error: low disk space at {}: {available} bytes available, {required} required",
+            path.display()
+        )]
+        DiskSpaceLow {
+            /// Directory the write was headed for
+            path: std::path::PathBuf,
+            /// Bytes available on the volume
+            available: u64,
+            /// Bytes needed, including the free space reserve
+            required: u64,
+        },
+
+        /// Storage was created by a different Osnova install
+        #[error(
+            "Storage error: {} belongs to another Osnova install ({owner}, OS user '{owner_user}'). \
+             This usually means two OS accounts share one data directory; check \
+             OSNOVA_STORAGE_PATH and give each account its own. To move this data to this \
+             account on purpose, adopt it from install {owner}",
+            path.display()
+        )]
+        StorageOwnedElsewhere {
+            /// Database or storage root that was refused
+            path: std::path::PathBuf,
+            /// Install id that owns it
+            owner: String,
+            /// OS user the owning install belonged to
+            owner_user: String,
+        },
+
+        /// Database was written by a newer Osnova release
+        #[error(
+            "Database error: {} has schema version {found}, but this release supports up to \
+             {supported}. Upgrade Osnova, or restore a backup made by this release",
+            path.display()
+        )]
+        SchemaTooNew {
+            /// Database that was refused
+            path: std::path::PathBuf,
+            /// Schema version stored in the database
+            found: u32,
+            /// Newest schema version this release can open
+            supported: u32,
+        },
+
+        /// Backend binary was built for another platform
+        #[error(
+            "Component {component} is {detected}, which does not run on this {host} host \
+             (manifest target: {declared})"
+        )]
+        MismatchedBinaryTarget {
+            /// Component name
+            component: String,
+            /// Target triple the manifest declares
+            declared: String,
+            /// Format and architecture read from the binary
+            detected: String,
+            /// Format and architecture of this host
+            host: String,
+        },
+
+        /// Manifest needs a newer Osnova release than this one
+        #[error(
+            "{app} requires {} (this is Osnova {current}){}",
+            required
+                .as_ref()
+                .map_or("a newer Osnova".to_string(), |v| format!("Osnova {} or later", v)),
+            if features.is_empty() {
+                String::new()
+            } else {
+                format!("; unsupported: {}", features.join(", "))
+            }
+        )]
+        RequiresNewerCore {
+            /// Manifest id
+            app: String,
+            /// Oldest release the manifest declares it needs, if any
+            required: Option<String>,
+            /// This release
+            current: String,
+            /// Features this release does not support
+            features: Vec<String>,
+        },
+
+        /// Frontend bundle contains executable content the manifest does not allow
+        #[error(
+            "Component {component} contains executable content: {}",
+            paths.join(", ")
+        )]
+        ExecutableContent {
+            /// Component name
+            component: String,
+            /// Offending paths relative to the bundle root
+            paths: Vec<String>,
+        },
+
+        /// Settings written for an app do not fit its settings schema
+        #[error(
+            "Invalid settings for {app_id}: {}",
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+        )]
+        InvalidSettings {
+            /// Application the settings were written for
+            app_id: String,
+            /// Rejected fields
+            errors: Vec<crate::models::settings_schema::SettingFieldError>,
+        },
+
+        /// Refused by the group policy in force on this device
+        #[error("Blocked by device policy {policy_id} ({rule}): {reason}")]
+        PolicyBlocked {
+            /// Policy in force
+            policy_id: String,
+            /// Rule that refused
+            rule: crate::policies::PolicyRule,
+            /// What was refused, and why
+            reason: String,
+        },
+
+        /// Refused while a guest session runs
+        #[error(transparent)]
+        GuestModeRestricted(#[from] crate::context::GuestModeRestricted),
+
+        /// Remote caller presented a missing, expired, or revoked session
+        #[error("Unauthorized: {0}")]
+        Unauthorized(String),
+
+        /// The user denied, or did not answer, a permission prompt
+        #[error("Permission denied: {0}")]
+        PermissionDenied(String),
+
         /// Generic error
         #[error("{0}")]
         Other(String),
     }
 
+    /// OpenRPC error code for [`OsnovaError::Unauthorized`]
+    pub const UNAUTHORIZED_ERROR_CODE: i64 = -32001;
+
+    /// OpenRPC error code for [`OsnovaError::PermissionDenied`],
+    /// [`OsnovaError::PolicyBlocked`] and [`OsnovaError::GuestModeRestricted`]
+    pub const PERMISSION_DENIED_ERROR_CODE: i64 = -32002;
+
+    /// OpenRPC (JSON-RPC) error code for internal errors
+    pub const INTERNAL_ERROR_CODE: i64 = -32603;
+
+    impl OsnovaError {
+        /// Whether this is a timeout, cancellation, offline mode or open
+        /// circuit rather than a failure reported by the other side
+        pub fn is_interrupted(&self) -> bool {
+            matches!(
+                self,
+                Self::Timeout { .. }
+                    | Self::Cancelled { .. }
+                    | Self::OfflineMode { .. }
+                    | Self::CircuitOpen { .. }
+            )
+        }
+
+        /// OpenRPC error code reported to remote callers
+        pub fn rpc_code(&self) -> i64 {
+            match self {
+                Self::Unauthorized(_) => UNAUTHORIZED_ERROR_CODE,
+                Self::PermissionDenied(_)
+                | Self::PolicyBlocked { .. }
+                | Self::GuestModeRestricted(_) => PERMISSION_DENIED_ERROR_CODE,
+                _ => INTERNAL_ERROR_CODE,
+            }
+        }
+    }
+
     /// Result type alias for Osnova operations
     pub type Result<T> = std::result::Result<T, OsnovaError>;
 }
@@ -112,6 +391,7 @@ pub use error::{OsnovaError, Result};
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_library_loads() {
         // Basic smoke test to verify the library loads
         assert!(true);
@@ -0,0 +1,214 @@
+//! Persisted log files with size-based rotation
+//!
+//! Records are appended as JSON lines to `osnova.log`. When the file would
+//! grow past its size limit it is renamed to `osnova.log.1`, older files
+//! shift up by one, and the oldest beyond the file limit is deleted.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::record::{LogFilter, LogRecord};
+use crate::error::Result;
+
+/// Name of the file being written; rotated files get a numeric suffix
+pub const LOG_FILE_NAME: &str = "osnova.log";
+
+/// Size at which the current file is rotated unless configured
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Files kept, the current one included, unless configured
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+/// Rotating JSON-lines log files in one directory
+#[derive(Debug)]
+pub struct LogFiles {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    current: Option<(File, u64)>,
+}
+
+impl LogFiles {
+    /// Log files in `dir`, created on first write
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+            current: None,
+        }
+    }
+
+    /// Rotate once the current file would grow past `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Keep at most `max_files` files, the current one included
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    /// Directory holding the files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append a record, rotating first if it would not fit
+    pub fn append(&mut self, record: &LogRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let size = match &self.current {
+            Some((_, size)) => *size,
+            None => fs::metadata(self.dir.join(LOG_FILE_NAME)).map_or(0, |m| m.len()),
+        };
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let (file, size) = match &mut self.current {
+            Some(current) => current,
+            None => {
+                fs::create_dir_all(&self.dir)?;
+                let path = self.dir.join(LOG_FILE_NAME);
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                let size = file.metadata()?.len();
+                self.current.insert((file, size))
+            }
+        };
+        file.write_all(&line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift every file up by one suffix, dropping the oldest
+    fn rotate(&mut self) -> Result<()> {
+        self.current = None;
+        let path = |n: usize| rotated_path(&self.dir, n);
+
+        let oldest = path(self.max_files - 1);
+        if oldest.exists() && self.max_files > 1 {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (1..self.max_files - 1).rev() {
+            if path(n).exists() {
+                fs::rename(path(n), path(n + 1))?;
+            }
+        }
+        if self.max_files > 1 {
+            fs::rename(path(0), path(1))?;
+        } else {
+            fs::remove_file(path(0))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `n`th most recently rotated file, or the current file for 0
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => dir.join(LOG_FILE_NAME),
+        n => dir.join(format!("{}.{}", LOG_FILE_NAME, n)),
+    }
+}
+
+/// The most recent records in the log files of `dir` that match a filter,
+/// oldest first
+///
+/// Rotated files are read oldest first, then the current file. Lines that
+/// are not records, such as one cut short by a crash, are skipped.
+pub fn query_files(dir: &Path, filter: &LogFilter, limit: usize) -> Result<Vec<LogRecord>> {
+    let mut rotated = Vec::new();
+    while rotated_path(dir, rotated.len() + 1).exists() {
+        rotated.push(rotated_path(dir, rotated.len() + 1));
+    }
+
+    let mut records = VecDeque::with_capacity(limit.min(1024));
+    let files = rotated.into_iter().rev().chain([rotated_path(dir, 0)]);
+    for path in files {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = serde_json::from_str::<LogRecord>(&line?) else {
+                continue;
+            };
+            if !filter.matches(&record) {
+                continue;
+            }
+            if records.len() == limit {
+                records.pop_front();
+            }
+            if limit > 0 {
+                records.push_back(record);
+            }
+        }
+    }
+    Ok(records.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::startup::LogLevel;
+    use tempfile::TempDir;
+
+    fn record(timestamp: u64, level: LogLevel) -> LogRecord {
+        LogRecord {
+            timestamp,
+            level,
+            module: "osnova_lib::test".to_string(),
+            message: format!("record {}", timestamp),
+        }
+    }
+
+    #[test]
+    fn test_query_spans_rotated_files() -> Result<()> {
+        let temp = TempDir::new()?;
+        let line_len = serde_json::to_vec(&record(10, LogLevel::Info))?.len() as u64 + 1;
+        let mut files = LogFiles::new(temp.path())
+            .with_max_bytes(line_len * 3)
+            .with_max_files(3);
+
+        for timestamp in 10..20 {
+            let level = if timestamp % 2 == 0 {
+                LogLevel::Warn
+            } else {
+                LogLevel::Info
+            };
+            files.append(&record(timestamp, level))?;
+        }
+        // Three per file; the oldest file (10..13) was deleted
+        assert!(rotated_path(temp.path(), 2).exists());
+        assert!(!rotated_path(temp.path(), 3).exists());
+        fs::write(
+            rotated_path(temp.path(), 0),
+            [fs::read(rotated_path(temp.path(), 0))?, b"{\"cut".to_vec()].concat(),
+        )?;
+
+        let all = query_files(temp.path(), &LogFilter::default(), 100)?;
+        let timestamps: Vec<u64> = all.iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, (13..20).collect::<Vec<_>>());
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Warn),
+            since: Some(14),
+            ..Default::default()
+        };
+        let latest = query_files(temp.path(), &filter, 2)?;
+        let timestamps: Vec<u64> = latest.iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, vec![16, 18]);
+
+        assert!(query_files(temp.path(), &filter, 0)?.is_empty());
+        assert!(query_files(&temp.path().join("missing"), &filter, 5)?.is_empty());
+
+        Ok(())
+    }
+}
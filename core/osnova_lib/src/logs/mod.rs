@@ -0,0 +1,327 @@
+//! # Logs Module
+//!
+//! The shell's own structured log, for debugging without a terminal.
+//!
+//! This module provides:
+//! - [`Logger`], which redacts each record once and then writes it to the
+//!   rotating log files and a bounded live stream
+//! - [`LogSubscription`], a filtered view of the live stream; a subscriber
+//!   that falls behind loses the oldest records and is told how many
+//! - [`query_files`], a filtered read of the most recent persisted records
+//! - The [`log!`](crate::log) macro, which sends a record to the process-wide
+//!   logger once one is installed, and to stderr until then
+//!
+//! Remote access goes through
+//! [`LogsService`](crate::services::logs::LogsService), which requires the
+//! [`LOGS_READ_PERMISSION`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::context::startup::LogLevel;
+//! use osnova_lib::logs::{LogFilter, LogStreamItem, Logger};
+//!
+//! let logger = Logger::new();
+//! let mut subscription = logger.subscribe(LogFilter::default());
+//!
+//! logger.log(LogLevel::Warn, "osnova_lib::example", "token=abc expired");
+//! match subscription.try_next() {
+//!     Some(LogStreamItem::Record(record)) => assert_eq!(record.message, "token=*** expired"),
+//!     other => panic!("unexpected {:?}", other),
+//! }
+//! ```
+
+pub mod files;
+pub mod record;
+
+pub use files::{query_files, LogFiles, LOG_FILE_NAME};
+pub use record::{always_redact, redact, LogFilter, LogRecord, LogStreamItem, REDACTED};
+
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
+
+use crate::context::startup::LogLevel;
+use crate::error::Result;
+use crate::time::{self, SharedClock};
+
+/// Device permission needed to read the log remotely
+pub const LOGS_READ_PERMISSION: &str = "logs.read";
+
+/// Records buffered for live subscribers unless configured
+pub const DEFAULT_STREAM_CAPACITY: usize = 1024;
+
+/// Directory of the log files, relative to the storage path
+pub const LOG_DIR: &str = "logs";
+
+static GLOBAL: OnceLock<Arc<Logger>> = OnceLock::new();
+
+/// Structured logger with a live stream and optional log files
+pub struct Logger {
+    level: LogLevel,
+    clock: SharedClock,
+    stream: broadcast::Sender<LogRecord>,
+    files: Option<Mutex<LogFiles>>,
+    stderr: bool,
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Logger {
+    /// Logger at `info` level with no files, writing only to the stream
+    pub fn new() -> Self {
+        Self {
+            level: LogLevel::Info,
+            clock: time::default_clock(),
+            stream: broadcast::channel(DEFAULT_STREAM_CAPACITY).0,
+            files: None,
+            stderr: false,
+        }
+    }
+
+    /// Drop records less severe than `level`
+    pub fn with_level(mut self, level: LogLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Also persist records to rotating files
+    pub fn with_files(mut self, files: LogFiles) -> Self {
+        self.files = Some(Mutex::new(files));
+        self
+    }
+
+    /// Buffer at most `capacity` records for each live subscriber
+    pub fn with_stream_capacity(mut self, capacity: usize) -> Self {
+        self.stream = broadcast::channel(capacity.max(1)).0;
+        self
+    }
+
+    /// Also print records to stderr
+    pub fn with_stderr(mut self) -> Self {
+        self.stderr = true;
+        self
+    }
+
+    /// Use a specific clock for record timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Least severe level recorded
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// Redact and record a message
+    ///
+    /// A record that cannot be written to the log files is still streamed.
+    pub fn log(&self, level: LogLevel, module: &str, message: &str) {
+        if level > self.level {
+            return;
+        }
+        let record = LogRecord {
+            timestamp: self.clock.now_unix(),
+            level,
+            module: module.to_string(),
+            message: redact(message),
+        };
+
+        if self.stderr {
+            eprintln!(
+                "{} {}: {}",
+                record.level.as_str(),
+                record.module,
+                record.message
+            );
+        }
+        if let Some(files) = &self.files {
+            if let Err(e) = files.lock().unwrap().append(&record) {
+                eprintln!("Warning: Failed to write log file: {}", e);
+            }
+        }
+        // No subscribers is not an error
+        let _ = self.stream.send(record);
+    }
+
+    /// Follow records emitted from now on that match a filter
+    pub fn subscribe(&self, filter: LogFilter) -> LogSubscription {
+        LogSubscription {
+            receiver: self.stream.subscribe(),
+            filter,
+        }
+    }
+
+    /// The most recent `limit` persisted records matching a filter, oldest
+    /// first; empty when the logger has no files
+    pub fn query(&self, filter: &LogFilter, limit: usize) -> Result<Vec<LogRecord>> {
+        let Some(files) = &self.files else {
+            return Ok(Vec::new());
+        };
+        let dir = files.lock().unwrap().dir().to_path_buf();
+        query_files(&dir, filter, limit)
+    }
+}
+
+/// A filtered view of a logger's live stream
+pub struct LogSubscription {
+    receiver: broadcast::Receiver<LogRecord>,
+    filter: LogFilter,
+}
+
+impl LogSubscription {
+    /// Wait for the next matching record, or the count of records dropped
+    /// because this subscriber fell behind
+    ///
+    /// Returns `None` once the logger is gone.
+    pub async fn next(&mut self) -> Option<LogStreamItem> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(record) if self.filter.matches(&record) => {
+                    return Some(LogStreamItem::Record(record))
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(count)) => return Some(LogStreamItem::Dropped { count }),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Like [`Self::next`], without waiting; `None` when nothing is queued
+    pub fn try_next(&mut self) -> Option<LogStreamItem> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(record) if self.filter.matches(&record) => {
+                    return Some(LogStreamItem::Record(record))
+                }
+                Ok(_) => continue,
+                Err(TryRecvError::Lagged(count)) => return Some(LogStreamItem::Dropped { count }),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Make a logger the process-wide target of [`log!`](crate::log)
+///
+/// Returns `false` if one was already installed; it stays in place.
+pub fn install(logger: Arc<Logger>) -> bool {
+    GLOBAL.set(logger).is_ok()
+}
+
+/// The process-wide logger, if one is installed
+pub fn global() -> Option<&'static Arc<Logger>> {
+    GLOBAL.get()
+}
+
+/// Record a message with the process-wide logger, or print it to stderr if
+/// none is installed
+pub fn emit(level: LogLevel, module: &str, message: &str) {
+    match GLOBAL.get() {
+        Some(logger) => logger.log(level, module, message),
+        None => eprintln!("{}: {}", level.as_str(), message),
+    }
+}
+
+/// Log a formatted message from the calling module
+///
+/// ```rust
+/// osnova_lib::log!(Warn, "Failed to delete cache file: {}", "busy");
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::logs::emit(
+            $crate::context::startup::LogLevel::$level,
+            module_path!(),
+            &format!($($arg)+),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn message(item: Option<LogStreamItem>) -> String {
+        match item {
+            Some(LogStreamItem::Record(record)) => record.message,
+            other => panic!("expected a record, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscription_receives_matching_records() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let logger = Logger::new()
+            .with_level(LogLevel::Debug)
+            .with_clock(clock.clone());
+        let mut subscription = logger.subscribe(LogFilter {
+            level: Some(LogLevel::Info),
+            module_prefix: Some("osnova_lib::cache".to_string()),
+            since: Some(1_010),
+        });
+
+        logger.log(LogLevel::Warn, "osnova_lib::cache", "too early");
+        clock.advance(Duration::from_secs(10));
+        logger.log(LogLevel::Debug, "osnova_lib::cache", "too verbose");
+        logger.log(LogLevel::Error, "osnova_lib::apps", "other module");
+        logger.log(
+            LogLevel::Trace,
+            "osnova_lib::cache",
+            "below the logger level",
+        );
+        logger.log(LogLevel::Info, "osnova_lib::cache::manager", "matches");
+
+        assert_eq!(message(subscription.next().await), "matches");
+        assert!(subscription.try_next().is_none());
+
+        drop(logger);
+        assert!(subscription.next().await.is_none());
+    }
+
+    #[test]
+    fn test_slow_subscriber_drops_oldest_with_count() {
+        let logger = Logger::new().with_stream_capacity(4);
+        let mut slow = logger.subscribe(LogFilter::default());
+        let mut fast = logger.subscribe(LogFilter::default());
+
+        for n in 0..10 {
+            logger.log(LogLevel::Info, "test", &format!("record {}", n));
+            assert_eq!(message(fast.try_next()), format!("record {}", n));
+        }
+
+        assert_eq!(slow.try_next(), Some(LogStreamItem::Dropped { count: 6 }));
+        for n in 6..10 {
+            assert_eq!(message(slow.try_next()), format!("record {}", n));
+        }
+        assert!(slow.try_next().is_none());
+    }
+
+    #[test]
+    fn test_stream_and_files_are_redacted() -> Result<()> {
+        let temp = TempDir::new()?;
+        let logger = Logger::new().with_files(LogFiles::new(temp.path()));
+        let mut subscription = logger.subscribe(LogFilter::default());
+
+        logger.log(LogLevel::Warn, "test", "login password=hunter2 failed");
+
+        assert_eq!(
+            message(subscription.try_next()),
+            "login password=*** failed"
+        );
+        let persisted = logger.query(&LogFilter::default(), 10)?;
+        assert_eq!(persisted[0].message, "login password=*** failed");
+        let file = std::fs::read_to_string(temp.path().join(LOG_FILE_NAME))?;
+        assert!(!file.contains("hunter2"));
+
+        Ok(())
+    }
+}
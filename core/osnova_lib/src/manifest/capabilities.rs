@@ -0,0 +1,415 @@
+//! # Capability Negotiation
+//!
+//! Manifests written for a newer Osnova use features this release does not
+//! know. Rather than failing on whichever unknown value is parsed first, and
+//! reading as a broken app, validation collects them into a
+//! [`CapabilityReport`]:
+//!
+//! - Unknown fields of the manifest and of its components are kept (see
+//!   [`ManifestSchema::extra`]) and are ignorable: extra metadata this
+//!   release does without
+//! - Unknown component kinds and frontend platforms are blocking: this
+//!   release cannot install the component
+//!
+//! Blocking features, or a `minCoreVersion` above this release, fail with
+//! one [`OsnovaError::RequiresNewerCore`] naming every unsupported feature,
+//! which the install preview and launcher catalog show as "requires newer
+//! Osnova". A manifest whose `minCoreVersion` this release meets but which
+//! still uses unsupported features is invalid.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::manifest::validate_manifest;
+//!
+//! let manifest = validate_manifest(json)?;
+//! for warning in manifest.capabilities().warnings() {
+//!     println!("{}", warning);
+//! }
+//! ```
+
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::launcher::{parse_version, CORE_VERSION};
+use super::schema::ManifestSchema;
+use crate::error::{OsnovaError, Result};
+
+/// Component kinds this release installs
+const KNOWN_KINDS: &[&str] = &["frontend", "backend"];
+
+/// Frontend platforms this release installs
+const KNOWN_PLATFORMS: &[&str] = &["iOS", "Android", "desktop"];
+
+/// A manifest feature this release does not support
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedFeature {
+    /// Where the feature appears, e.g. `components[1].kind`
+    pub path: String,
+    /// What the feature is, e.g. `component kind 'worker'`
+    pub feature: String,
+    /// Whether the app cannot be installed without it
+    pub blocking: bool,
+}
+
+impl fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.feature, self.path)
+    }
+}
+
+/// Features of a manifest this release does not support
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityReport {
+    /// Manifest id
+    pub app: String,
+    /// Release the manifest was checked against
+    pub core_version: String,
+    /// Oldest release the manifest declares it needs, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_core_version: Option<String>,
+    /// Unsupported features, in manifest order
+    pub unsupported: Vec<UnsupportedFeature>,
+}
+
+impl CapabilityReport {
+    /// Check `manifest` against this release
+    pub fn negotiate(manifest: &ManifestSchema) -> Self {
+        Self::negotiate_for(manifest, CORE_VERSION)
+    }
+
+    /// Check `manifest` against release `core_version`
+    pub fn negotiate_for(manifest: &ManifestSchema, core_version: &str) -> Self {
+        let mut unsupported: Vec<UnsupportedFeature> = manifest
+            .extra
+            .keys()
+            .map(|field| ignorable(field.clone(), field))
+            .collect();
+
+        for (idx, component) in manifest.components.iter().enumerate() {
+            let path = |field: &str| format!("components[{}].{}", idx, field);
+            if is_feature_name(&component.kind) && !KNOWN_KINDS.contains(&component.kind.as_str()) {
+                unsupported.push(UnsupportedFeature {
+                    path: path("kind"),
+                    feature: format!("component kind '{}'", component.kind),
+                    blocking: true,
+                });
+            }
+            if let Some(platform) = &component.platform {
+                if component.kind == "frontend"
+                    && is_feature_name(platform)
+                    && !KNOWN_PLATFORMS.contains(&platform.as_str())
+                {
+                    unsupported.push(UnsupportedFeature {
+                        path: path("platform"),
+                        feature: format!("frontend platform '{}'", platform),
+                        blocking: true,
+                    });
+                }
+            }
+            unsupported.extend(
+                component
+                    .extra
+                    .keys()
+                    .map(|field| ignorable(path(field), field)),
+            );
+        }
+
+        Self {
+            app: manifest.id.clone(),
+            core_version: core_version.to_string(),
+            min_core_version: manifest.min_core_version.clone(),
+            unsupported,
+        }
+    }
+
+    /// Features the app cannot be installed without
+    pub fn blocking(&self) -> impl Iterator<Item = &UnsupportedFeature> {
+        self.unsupported.iter().filter(|feature| feature.blocking)
+    }
+
+    /// Ignorable features, worded for the user
+    pub fn warnings(&self) -> Vec<String> {
+        self.unsupported
+            .iter()
+            .filter(|feature| !feature.blocking)
+            .map(|feature| format!("Ignored by this version of Osnova: {}", feature))
+            .collect()
+    }
+
+    /// Whether the app needs a newer release than the one checked against
+    pub fn requires_newer_core(&self) -> bool {
+        matches!(self.check(), Err(OsnovaError::RequiresNewerCore { .. }))
+    }
+
+    /// Fail unless the release checked against can install the app
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::RequiresNewerCore`] if `minCoreVersion` is
+    /// above the release, or the manifest uses blocking features without
+    /// declaring a `minCoreVersion` the release meets. A manifest whose
+    /// `minCoreVersion` the release meets, but which uses blocking features
+    /// anyway, fails validation.
+    pub fn check(&self) -> Result<()> {
+        let blocking: Vec<String> = self.blocking().map(ToString::to_string).collect();
+        let required = self.min_core_version.as_deref().and_then(parse_version);
+        let met = required.is_some_and(|required| {
+            parse_version(&self.core_version).is_some_and(|current| current >= required)
+        });
+
+        if met {
+            if !blocking.is_empty() {
+                return Err(OsnovaError::Other(format!(
+                    "Manifest validation failed: {} declares minCoreVersion {}, which Osnova {} \
+                     meets, but uses unsupported {}",
+                    self.app,
+                    self.min_core_version.as_deref().unwrap_or_default(),
+                    self.core_version,
+                    blocking.join(", ")
+                )));
+            }
+            return Ok(());
+        }
+
+        if required.is_some() || !blocking.is_empty() {
+            return Err(OsnovaError::RequiresNewerCore {
+                app: self.app.clone(),
+                required: required.and(self.min_core_version.clone()),
+                current: self.core_version.clone(),
+                features: blocking,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ManifestSchema {
+    /// Features of this manifest the running release does not support
+    pub fn capabilities(&self) -> CapabilityReport {
+        CapabilityReport::negotiate(self)
+    }
+}
+
+/// An unknown field, kept as extra metadata
+fn ignorable(path: String, field: &str) -> UnsupportedFeature {
+    UnsupportedFeature {
+        path,
+        feature: format!("field '{}'", field),
+        blocking: false,
+    }
+}
+
+/// Whether an unknown enum value names a feature, rather than being
+/// malformed (which validation reports as such)
+fn is_feature_name(value: &str) -> bool {
+    value
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::validate_manifest;
+    use serde_json::{json, Value};
+
+    fn manifest(extra: Value) -> String {
+        let mut manifest = json!({
+            "id": "ant://app",
+            "name": "App",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "An app",
+            "components": [{
+                "id": "ant://ui",
+                "name": "UI",
+                "kind": "frontend",
+                "platform": "desktop",
+                "version": "1.0.0"
+            }]
+        });
+        json_merge(&mut manifest, extra);
+        manifest.to_string()
+    }
+
+    fn json_merge(target: &mut Value, patch: Value) {
+        match (target, patch) {
+            (Value::Object(target), Value::Object(patch)) => {
+                for (key, value) in patch {
+                    json_merge(target.entry(key).or_insert(Value::Null), value);
+                }
+            }
+            (Value::Array(target), Value::Array(patch)) => {
+                for (idx, value) in patch.into_iter().enumerate() {
+                    match target.get_mut(idx) {
+                        Some(existing) => json_merge(existing, value),
+                        None => target.push(value),
+                    }
+                }
+            }
+            (target, patch) => *target = patch,
+        }
+    }
+
+    #[test]
+    fn test_ignorable_unknowns_pass_with_warnings() {
+        let json = manifest(json!({
+            "expressions": {"engine": "cel"},
+            "components": [{"sandbox": "strict"}]
+        }));
+
+        let validated = validate_manifest(&json).unwrap();
+        let report = validated.capabilities();
+        assert!(report.check().is_ok());
+        assert!(!report.requires_newer_core());
+        assert_eq!(
+            report.unsupported,
+            vec![
+                UnsupportedFeature {
+                    path: "expressions".to_string(),
+                    feature: "field 'expressions'".to_string(),
+                    blocking: false,
+                },
+                UnsupportedFeature {
+                    path: "components[0].sandbox".to_string(),
+                    feature: "field 'sandbox'".to_string(),
+                    blocking: false,
+                },
+            ]
+        );
+        assert_eq!(
+            report.warnings(),
+            vec![
+                "Ignored by this version of Osnova: field 'expressions' (expressions)",
+                "Ignored by this version of Osnova: field 'sandbox' (components[0].sandbox)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocking_unknowns_aggregate_into_one_error() {
+        let json = manifest(json!({
+            "permissionScopes": ["camera"],
+            "components": [
+                {"kind": "worker"},
+                {
+                    "id": "ant://watch",
+                    "name": "Watch",
+                    "kind": "frontend",
+                    "platform": "watchOS",
+                    "version": "1.0.0"
+                }
+            ]
+        }));
+
+        let err = validate_manifest(&json).unwrap_err();
+        let OsnovaError::RequiresNewerCore {
+            app,
+            required,
+            current,
+            features,
+        } = &err
+        else {
+            panic!("Expected RequiresNewerCore, got {:?}", err);
+        };
+        assert_eq!(app, "ant://app");
+        assert_eq!(*required, None);
+        assert_eq!(current, CORE_VERSION);
+        assert_eq!(
+            *features,
+            vec![
+                "component kind 'worker' (components[0].kind)",
+                "frontend platform 'watchOS' (components[1].platform)",
+            ]
+        );
+        let message = err.to_string();
+        assert!(message.contains("requires a newer Osnova"), "{}", message);
+        assert!(message.contains("worker") && message.contains("watchOS"));
+        // Ignorable fields are not reasons to upgrade
+        assert!(!message.contains("permissionScopes"));
+
+        // Malformed values are validation errors, not newer features
+        let err = validate_manifest(&manifest(json!({"components": [{"kind": ""}]})))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Invalid component kind"), "{}", err);
+    }
+
+    #[test]
+    fn test_min_core_version_cross_reference() {
+        let json = manifest(json!({
+            "minCoreVersion": "2.1.0",
+            "components": [{"kind": "worker"}]
+        }));
+        let parsed: ManifestSchema = serde_json::from_str(&json).unwrap();
+
+        // An older release names the version to upgrade to
+        let report = CapabilityReport::negotiate_for(&parsed, "1.9.0");
+        assert!(report.requires_newer_core());
+        let err = report.check().unwrap_err();
+        assert!(matches!(
+            &err,
+            OsnovaError::RequiresNewerCore { required: Some(required), .. } if required == "2.1.0"
+        ));
+        assert!(err
+            .to_string()
+            .contains("requires Osnova 2.1.0 or later (this is Osnova 1.9.0)"));
+
+        // A release meeting minCoreVersion that still lacks the feature
+        // means the manifest is wrong, not the release too old
+        let report = CapabilityReport::negotiate_for(&parsed, "2.1.0");
+        assert!(!report.requires_newer_core());
+        let err = report.check().unwrap_err().to_string();
+        assert!(err.contains("declares minCoreVersion 2.1.0"), "{}", err);
+
+        // minCoreVersion alone gates the app, even without unknown features
+        let json = manifest(json!({"minCoreVersion": "999.0.0"}));
+        let parsed: ManifestSchema = serde_json::from_str(&json).unwrap();
+        assert!(parsed.capabilities().unsupported.is_empty());
+        assert!(matches!(
+            validate_manifest(&json),
+            Err(OsnovaError::RequiresNewerCore { .. })
+        ));
+        let report = CapabilityReport::negotiate_for(&parsed, "999.0.1");
+        assert!(report.check().is_ok());
+
+        assert!(validate_manifest(&manifest(json!({"minCoreVersion": "0.0.1"}))).is_ok());
+        let err = validate_manifest(&manifest(json!({"minCoreVersion": "2.x"})))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Invalid minCoreVersion"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_fields_survive_round_trip() {
+        let json = manifest(json!({
+            "minCoreVersion": "0.0.1",
+            "expressions": {"engine": "cel", "strict": true},
+            "components": [{"sandbox": ["net", "fs"]}]
+        }));
+        let parsed = validate_manifest(&json).unwrap();
+        assert_eq!(
+            parsed.extra["expressions"],
+            json!({"engine": "cel", "strict": true})
+        );
+        assert_eq!(parsed.components[0].extra["sandbox"], json!(["net", "fs"]));
+
+        // As stored and reloaded, e.g. as an install's manifest snapshot
+        let stored = serde_json::to_string(&parsed).unwrap();
+        let reloaded: ManifestSchema = serde_json::from_str(&stored).unwrap();
+        assert_eq!(reloaded, parsed);
+        assert_eq!(
+            serde_json::from_str::<Value>(&stored).unwrap(),
+            serde_json::from_str::<Value>(&json).unwrap()
+        );
+    }
+}
@@ -0,0 +1,927 @@
+//! # Manifest Conditions
+//!
+//! A small expression language for conditional configuration in manifests
+//! and launcher catalogs, so one manifest can serve every platform.
+//!
+//! Expressions have literals (`"desktop"`, `'en'`, `3`, `true`), the
+//! comparisons `==`, `!=`, `<`, `<=`, `>`, `>=`, the boolean operators `!`,
+//! `&&`, `||`, and parentheses. The only variables are:
+//! - `platform` - `"desktop"`, `"iOS"`, or `"Android"`
+//! - `arch` - CPU architecture (e.g. `"x86_64"`, `"aarch64"`)
+//! - `coreVersion` - Version of this library (x.y.z)
+//! - `locale` - User's locale as a BCP 47 tag (e.g. `"en-US"`)
+//!
+//! There are no loops or functions. Expressions are type-checked when
+//! parsed, so a manifest that validates never fails to evaluate. Ordering
+//! comparisons between two x.y.z strings compare them as versions.
+//!
+//! Used in two places, both evaluated once at install or fetch time:
+//! - Component `config` values of the form
+//!   `{"$when": "<expr>", "value": ..., "else": ...}`; the key is dropped
+//!   when the condition is false and there is no `else`
+//! - A catalog entry's `visibleWhen`
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::manifest::condition::{Condition, ConditionContext};
+//!
+//! let condition = Condition::parse(r#"platform == "desktop" && coreVersion >= "0.1.0""#)?;
+//! let context = ConditionContext::current().with_platform("iOS");
+//! assert!(!condition.evaluate(&context));
+//! # Ok::<(), osnova_lib::manifest::condition::ConditionError>(())
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use serde_json::Value;
+
+use super::launcher::{parse_version, CORE_VERSION};
+
+/// Longest expression accepted, in bytes
+pub const MAX_CONDITION_LEN: usize = 256;
+
+/// Config key holding the condition of a conditional value
+pub const WHEN_KEY: &str = "$when";
+
+/// Config key holding the value used when the condition holds
+const VALUE_KEY: &str = "value";
+
+/// Config key holding the value used when the condition does not hold
+const ELSE_KEY: &str = "else";
+
+/// Deepest nesting of parentheses and `!` accepted
+const MAX_DEPTH: usize = 32;
+
+/// Variables an expression may reference
+const VARIABLES: &[&str] = &["platform", "arch", "coreVersion", "locale"];
+
+/// An expression that failed to parse or type-check
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} at position {position}")]
+pub struct ConditionError {
+    /// Byte offset in the expression where the problem starts
+    pub position: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl ConditionError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+/// Values of the variables an expression may reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionContext {
+    /// `platform`
+    pub platform: String,
+    /// `arch`
+    pub arch: String,
+    /// `coreVersion`
+    pub core_version: String,
+    /// `locale`
+    pub locale: String,
+}
+
+impl ConditionContext {
+    /// Values for this device and build
+    ///
+    /// The locale comes from `LC_ALL`, `LC_MESSAGES`, or `LANG`
+    /// (`en_US.UTF-8` becomes `en-US`), defaulting to `en`.
+    pub fn current() -> Self {
+        let platform = if cfg!(target_os = "ios") {
+            "iOS"
+        } else if cfg!(target_os = "android") {
+            "Android"
+        } else {
+            "desktop"
+        };
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find_map(|value| normalize_locale(&value))
+            .unwrap_or_else(|| "en".to_string());
+
+        Self {
+            platform: platform.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            core_version: CORE_VERSION.to_string(),
+            locale,
+        }
+    }
+
+    /// Replace `platform`
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.platform = platform.into();
+        self
+    }
+
+    /// Replace `arch`
+    pub fn with_arch(mut self, arch: impl Into<String>) -> Self {
+        self.arch = arch.into();
+        self
+    }
+
+    /// Replace `coreVersion`
+    pub fn with_core_version(mut self, core_version: impl Into<String>) -> Self {
+        self.core_version = core_version.into();
+        self
+    }
+
+    /// Replace `locale`
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = locale.into();
+        self
+    }
+
+    fn variable(&self, variable: Variable) -> &str {
+        match variable {
+            Variable::Platform => &self.platform,
+            Variable::Arch => &self.arch,
+            Variable::CoreVersion => &self.core_version,
+            Variable::Locale => &self.locale,
+        }
+    }
+}
+
+/// Turn a POSIX locale (`en_US.UTF-8`) into a BCP 47 tag (`en-US`)
+fn normalize_locale(value: &str) -> Option<String> {
+    let tag = value.split(['.', '@']).next()?.replace('_', "-");
+    (!tag.is_empty() && tag != "C" && tag != "POSIX").then_some(tag)
+}
+
+/// A parsed, type-checked expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    root: Node,
+}
+
+impl Condition {
+    /// Parse and type-check an expression
+    ///
+    /// # Errors
+    ///
+    /// Returns the position and nature of the first problem: an expression
+    /// longer than [`MAX_CONDITION_LEN`], a syntax error, an unknown
+    /// identifier, or operands of the wrong type.
+    pub fn parse(source: &str) -> Result<Self, ConditionError> {
+        if source.len() > MAX_CONDITION_LEN {
+            return Err(ConditionError::new(
+                MAX_CONDITION_LEN,
+                format!("expression longer than {} bytes", MAX_CONDITION_LEN),
+            ));
+        }
+
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            depth: 0,
+        };
+        let (root, kind) = parser.parse_or()?;
+        let token = parser.peek();
+        if token.kind != TokenKind::End {
+            return Err(ConditionError::new(
+                token.position,
+                format!("unexpected {}", token.kind),
+            ));
+        }
+        if kind != Type::Bool {
+            return Err(ConditionError::new(0, "expression must be true or false"));
+        }
+
+        Ok(Self { root })
+    }
+
+    /// Evaluate against the given variables
+    pub fn evaluate(&self, context: &ConditionContext) -> bool {
+        matches!(self.root.evaluate(context), Scalar::Bool(true))
+    }
+}
+
+/// Check that a config value's conditions, if any, are well-formed
+///
+/// A conditional value must have a string `$when` and a `value`, may have
+/// an `else`, and nothing else; `value` and `else` may themselves be
+/// conditional.
+pub fn validate_conditional(value: &Value) -> Result<(), String> {
+    let Some(conditional) = Conditional::from_value(value)? else {
+        return Ok(());
+    };
+    Condition::parse(conditional.when).map_err(|e| format!("{}: {}", WHEN_KEY, e))?;
+    validate_conditional(conditional.value)?;
+    if let Some(otherwise) = conditional.otherwise {
+        validate_conditional(otherwise)?;
+    }
+    Ok(())
+}
+
+/// Every value a config value can resolve to
+pub fn conditional_branches(value: &Value) -> Vec<&Value> {
+    match Conditional::from_value(value) {
+        Ok(Some(conditional)) => {
+            let mut branches = conditional_branches(conditional.value);
+            if let Some(otherwise) = conditional.otherwise {
+                branches.extend(conditional_branches(otherwise));
+            }
+            branches
+        }
+        _ => vec![value],
+    }
+}
+
+/// Resolve a config value's conditions
+///
+/// Returns `None` when a condition does not hold and there is no `else`.
+///
+/// # Errors
+///
+/// Returns an error if the value fails [`validate_conditional`]
+pub fn resolve_conditional(
+    value: &Value,
+    context: &ConditionContext,
+) -> Result<Option<Value>, String> {
+    let Some(conditional) = Conditional::from_value(value)? else {
+        return Ok(Some(value.clone()));
+    };
+    let condition =
+        Condition::parse(conditional.when).map_err(|e| format!("{}: {}", WHEN_KEY, e))?;
+    match (condition.evaluate(context), conditional.otherwise) {
+        (true, _) => resolve_conditional(conditional.value, context),
+        (false, Some(otherwise)) => resolve_conditional(otherwise, context),
+        (false, None) => Ok(None),
+    }
+}
+
+/// Resolve every value of a component config, dropping keys that resolve
+/// to nothing
+///
+/// # Errors
+///
+/// Returns an error naming the first key whose value is not a valid
+/// conditional
+pub fn resolve_config(
+    config: &HashMap<String, Value>,
+    context: &ConditionContext,
+) -> Result<HashMap<String, Value>, String> {
+    let mut resolved = HashMap::new();
+    for (key, value) in config {
+        if let Some(value) =
+            resolve_conditional(value, context).map_err(|e| format!("config '{}': {}", key, e))?
+        {
+            resolved.insert(key.clone(), value);
+        }
+    }
+    Ok(resolved)
+}
+
+/// The parts of a conditional config value
+struct Conditional<'a> {
+    when: &'a str,
+    value: &'a Value,
+    otherwise: Option<&'a Value>,
+}
+
+impl<'a> Conditional<'a> {
+    /// Split a conditional value; `None` for plain values
+    fn from_value(value: &'a Value) -> Result<Option<Self>, String> {
+        let Some(object) = value.as_object().filter(|o| o.contains_key(WHEN_KEY)) else {
+            return Ok(None);
+        };
+        if let Some(key) = object
+            .keys()
+            .find(|key| ![WHEN_KEY, VALUE_KEY, ELSE_KEY].contains(&key.as_str()))
+        {
+            return Err(format!("unexpected key '{}' in conditional value", key));
+        }
+        let when = object[WHEN_KEY]
+            .as_str()
+            .ok_or_else(|| format!("{} must be a string", WHEN_KEY))?;
+        let value = object
+            .get(VALUE_KEY)
+            .ok_or_else(|| format!("conditional value requires '{}'", VALUE_KEY))?;
+
+        Ok(Some(Self {
+            when,
+            value,
+            otherwise: object.get(ELSE_KEY),
+        }))
+    }
+}
+
+// Syntax tree and evaluation
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    Platform,
+    Arch,
+    CoreVersion,
+    Locale,
+}
+
+impl Variable {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "platform" => Some(Self::Platform),
+            "arch" => Some(Self::Arch),
+            "coreVersion" => Some(Self::CoreVersion),
+            "locale" => Some(Self::Locale),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, Self::Eq | Self::Ne)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    Variable(Variable),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Compare(Comparison, Box<Node>, Box<Node>),
+}
+
+/// Static type of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Number,
+    Text,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool => write!(f, "true/false"),
+            Self::Number => write!(f, "a number"),
+            Self::Text => write!(f, "a string"),
+        }
+    }
+}
+
+/// Runtime value of a node
+enum Scalar<'a> {
+    Bool(bool),
+    Number(f64),
+    Text(&'a str),
+}
+
+impl Node {
+    fn evaluate<'a>(&'a self, context: &'a ConditionContext) -> Scalar<'a> {
+        match self {
+            Self::Bool(value) => Scalar::Bool(*value),
+            Self::Number(value) => Scalar::Number(*value),
+            Self::Text(value) => Scalar::Text(value),
+            Self::Variable(variable) => Scalar::Text(context.variable(*variable)),
+            Self::Not(operand) => Scalar::Bool(!operand.truthy(context)),
+            Self::And(left, right) => Scalar::Bool(left.truthy(context) && right.truthy(context)),
+            Self::Or(left, right) => Scalar::Bool(left.truthy(context) || right.truthy(context)),
+            Self::Compare(comparison, left, right) => {
+                let ordering = match (left.evaluate(context), right.evaluate(context)) {
+                    (Scalar::Bool(a), Scalar::Bool(b)) => a.cmp(&b),
+                    (Scalar::Number(a), Scalar::Number(b)) => {
+                        a.partial_cmp(&b).unwrap_or(Ordering::Less)
+                    }
+                    (Scalar::Text(a), Scalar::Text(b)) if comparison.is_ordering() => {
+                        match (parse_version(a), parse_version(b)) {
+                            (Some(a), Some(b)) => a.cmp(&b),
+                            _ => a.cmp(b),
+                        }
+                    }
+                    (Scalar::Text(a), Scalar::Text(b)) => a.cmp(b),
+                    // Ruled out by the type check
+                    _ => return Scalar::Bool(false),
+                };
+                Scalar::Bool(comparison.holds(ordering))
+            }
+        }
+    }
+
+    fn truthy(&self, context: &ConditionContext) -> bool {
+        matches!(self.evaluate(context), Scalar::Bool(true))
+    }
+}
+
+// Tokenizer
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Text(String),
+    Number(f64),
+    Identifier(String),
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Compare(Comparison),
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => write!(f, "string {:?}", text),
+            Self::Number(number) => write!(f, "number {}", number),
+            Self::Identifier(name) => write!(f, "'{}'", name),
+            Self::Open => write!(f, "'('"),
+            Self::Close => write!(f, "')'"),
+            Self::Not => write!(f, "'!'"),
+            Self::And => write!(f, "'&&'"),
+            Self::Or => write!(f, "'||'"),
+            Self::Compare(comparison) => {
+                let symbol = match comparison {
+                    Comparison::Eq => "==",
+                    Comparison::Ne => "!=",
+                    Comparison::Lt => "<",
+                    Comparison::Le => "<=",
+                    Comparison::Gt => ">",
+                    Comparison::Ge => ">=",
+                };
+                write!(f, "'{}'", symbol)
+            }
+            Self::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '&' if followed_by(&mut chars, '&') => TokenKind::And,
+            '|' if followed_by(&mut chars, '|') => TokenKind::Or,
+            '=' if followed_by(&mut chars, '=') => TokenKind::Compare(Comparison::Eq),
+            '!' if followed_by(&mut chars, '=') => TokenKind::Compare(Comparison::Ne),
+            '!' => TokenKind::Not,
+            '<' if followed_by(&mut chars, '=') => TokenKind::Compare(Comparison::Le),
+            '<' => TokenKind::Compare(Comparison::Lt),
+            '>' if followed_by(&mut chars, '=') => TokenKind::Compare(Comparison::Ge),
+            '>' => TokenKind::Compare(Comparison::Gt),
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, closing)) if closing == c => break,
+                        Some((escape, '\\')) => match chars.next() {
+                            Some((_, escaped @ ('\\' | '"' | '\''))) => text.push(escaped),
+                            _ => return Err(ConditionError::new(escape, "invalid escape")),
+                        },
+                        Some((_, other)) => text.push(other),
+                        None => return Err(ConditionError::new(position, "unterminated string")),
+                    }
+                }
+                TokenKind::Text(text)
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = position + 1;
+                while let Some((index, _)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.')
+                {
+                    end = index + 1;
+                }
+                let literal = &source[position..end];
+                let number = literal
+                    .parse::<f64>()
+                    .map_err(|_| ConditionError::new(position, "invalid number"))?;
+                TokenKind::Number(number)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = position + 1;
+                while let Some((index, _)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    end = index + 1;
+                }
+                TokenKind::Identifier(source[position..end].to_string())
+            }
+            other => {
+                return Err(ConditionError::new(
+                    position,
+                    format!("unexpected character '{}'", other),
+                ))
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        position: source.len(),
+    });
+    Ok(tokens)
+}
+
+/// Consume the next character if it is `expected`
+fn followed_by(chars: &mut Peekable<CharIndices<'_>>, expected: char) -> bool {
+    chars.next_if(|&(_, c)| c == expected).is_some()
+}
+
+// Parser
+//
+// or      := and ("||" and)*
+// and     := unary ("&&" unary)*
+// unary   := "!" unary | compare
+// compare := primary (op primary)?
+// primary := literal | variable | "(" or ")"
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].clone();
+        if token.kind != TokenKind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<(Node, Type), ConditionError> {
+        let mut left = self.parse_and()?;
+        while self.peek().kind == TokenKind::Or {
+            let operator = self.advance();
+            let right = self.parse_and()?;
+            left = (
+                Node::Or(
+                    Box::new(expect_bool(left, &operator)?),
+                    Box::new(expect_bool(right, &operator)?),
+                ),
+                Type::Bool,
+            );
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<(Node, Type), ConditionError> {
+        let mut left = self.parse_unary()?;
+        while self.peek().kind == TokenKind::And {
+            let operator = self.advance();
+            let right = self.parse_unary()?;
+            left = (
+                Node::And(
+                    Box::new(expect_bool(left, &operator)?),
+                    Box::new(expect_bool(right, &operator)?),
+                ),
+                Type::Bool,
+            );
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<(Node, Type), ConditionError> {
+        if self.peek().kind != TokenKind::Not {
+            return self.parse_compare();
+        }
+        let operator = self.advance();
+        self.descend(operator.position)?;
+        let operand = self.parse_unary()?;
+        self.depth -= 1;
+        Ok((
+            Node::Not(Box::new(expect_bool(operand, &operator)?)),
+            Type::Bool,
+        ))
+    }
+
+    fn parse_compare(&mut self) -> Result<(Node, Type), ConditionError> {
+        let left = self.parse_primary()?;
+        let TokenKind::Compare(comparison) = self.peek().kind else {
+            return Ok(left);
+        };
+        let operator = self.advance();
+        let right = self.parse_primary()?;
+
+        let (left, left_type) = left;
+        let (right, right_type) = right;
+        if left_type != right_type {
+            return Err(ConditionError::new(
+                operator.position,
+                format!("cannot compare {} with {}", left_type, right_type),
+            ));
+        }
+        if left_type == Type::Bool && comparison.is_ordering() {
+            return Err(ConditionError::new(
+                operator.position,
+                format!("{} cannot order true/false", operator.kind),
+            ));
+        }
+        Ok((
+            Node::Compare(comparison, Box::new(left), Box::new(right)),
+            Type::Bool,
+        ))
+    }
+
+    fn parse_primary(&mut self) -> Result<(Node, Type), ConditionError> {
+        let token = self.advance();
+        match token.kind {
+            TokenKind::Text(text) => Ok((Node::Text(text), Type::Text)),
+            TokenKind::Number(number) => Ok((Node::Number(number), Type::Number)),
+            TokenKind::Identifier(name) => match name.as_str() {
+                "true" => Ok((Node::Bool(true), Type::Bool)),
+                "false" => Ok((Node::Bool(false), Type::Bool)),
+                _ => match Variable::from_name(&name) {
+                    Some(variable) => Ok((Node::Variable(variable), Type::Text)),
+                    None => Err(ConditionError::new(
+                        token.position,
+                        format!(
+                            "unknown identifier '{}' (expected one of {})",
+                            name,
+                            VARIABLES.join(", ")
+                        ),
+                    )),
+                },
+            },
+            TokenKind::Open => {
+                self.descend(token.position)?;
+                let inner = self.parse_or()?;
+                self.depth -= 1;
+                let closing = self.advance();
+                if closing.kind != TokenKind::Close {
+                    return Err(ConditionError::new(
+                        closing.position,
+                        format!("expected ')' but found {}", closing.kind),
+                    ));
+                }
+                Ok(inner)
+            }
+            other => Err(ConditionError::new(
+                token.position,
+                format!("expected a value but found {}", other),
+            )),
+        }
+    }
+
+    fn descend(&mut self, position: usize) -> Result<(), ConditionError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ConditionError::new(
+                position,
+                "expression nested too deeply",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Require a boolean operand for a logical operator
+fn expect_bool((node, kind): (Node, Type), operator: &Token) -> Result<Node, ConditionError> {
+    if kind != Type::Bool {
+        return Err(ConditionError::new(
+            operator.position,
+            format!("{} needs true/false operands, not {}", operator.kind, kind),
+        ));
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    fn context() -> ConditionContext {
+        ConditionContext {
+            platform: "desktop".to_string(),
+            arch: "x86_64".to_string(),
+            core_version: "0.3.0".to_string(),
+            locale: "en-US".to_string(),
+        }
+    }
+
+    fn eval(source: &str) -> bool {
+        Condition::parse(source).unwrap().evaluate(&context())
+    }
+
+    fn error(source: &str) -> ConditionError {
+        Condition::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_grammar() {
+        assert!(eval("true"));
+        assert!(!eval("false"));
+        assert!(eval("!false"));
+        assert!(eval("!!true"));
+        assert!(eval("true && true"));
+        assert!(!eval("true && false"));
+        assert!(eval("false || true"));
+        // && binds tighter than ||
+        assert!(eval("true || false && false"));
+        assert!(!eval("(true || false) && false"));
+        assert!(eval("'a' == \"a\""));
+        assert!(eval("'a' != 'b'"));
+        assert!(eval(r#""say \"hi\"" == 'say "hi"'"#));
+        assert!(eval("1 < 2 && 2 <= 2 && 3 > 2 && 3 >= 3"));
+        assert!(eval("1.5 == 1.50"));
+        assert!(eval("true == (!false)"));
+        assert!(eval("  ( platform  ==  'desktop' )  "));
+    }
+
+    #[test]
+    fn test_variables() {
+        assert!(eval("platform == 'desktop'"));
+        assert!(eval("arch == 'x86_64'"));
+        assert!(eval("coreVersion == '0.3.0'"));
+        assert!(eval("locale == 'en-US'"));
+
+        let condition = Condition::parse("platform != 'desktop'").unwrap();
+        assert!(condition.evaluate(&context().with_platform("iOS")));
+        let condition = Condition::parse("arch == 'aarch64'").unwrap();
+        assert!(condition.evaluate(&context().with_arch("aarch64")));
+        let condition = Condition::parse("locale >= 'de'").unwrap();
+        assert!(!condition.evaluate(&context().with_locale("ca")));
+    }
+
+    #[test]
+    fn test_versions_compare_numerically() {
+        assert!(eval("coreVersion >= '0.2.10'"));
+        assert!(eval("coreVersion < '0.10.0'"));
+        let condition = Condition::parse("coreVersion >= '1.2.0'").unwrap();
+        assert!(condition.evaluate(&context().with_core_version("1.10.0")));
+        assert!(!condition.evaluate(&context().with_core_version("1.1.9")));
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let unknown = error("platform == 'iOS' || os == 'linux'");
+        assert_eq!(unknown.position, 21);
+        assert!(unknown.message.contains("unknown identifier 'os'"));
+
+        assert_eq!(error("platform == 'iOS").position, 12);
+        assert_eq!(error("platform = 'iOS'").position, 9);
+        assert_eq!(error("platform == ").position, 12);
+        assert_eq!(error("(true").position, 5);
+        assert_eq!(error("true false").position, 5);
+        assert_eq!(error("platform == 1").position, 9);
+        assert_eq!(error("true < false").position, 5);
+        assert_eq!(error("!platform").position, 0);
+        assert_eq!(error("1 && true").position, 2);
+        assert_eq!(error("platform").position, 0);
+        assert_eq!(error("'\\n'").position, 1);
+        assert_eq!(error("1.2.3 == 1").position, 0);
+        assert_eq!(
+            error("true == true == true").to_string(),
+            "unexpected '==' at position 13"
+        );
+    }
+
+    #[test]
+    fn test_rejects_oversized_and_deep_expressions() {
+        let long = format!("platform == '{}'", "x".repeat(MAX_CONDITION_LEN));
+        assert!(error(&long).message.contains("longer than"));
+
+        let deep = format!("{}true{}", "(".repeat(40), ")".repeat(40));
+        assert_eq!(error(&deep).position, MAX_DEPTH);
+        assert!(Condition::parse(&"!".repeat(MAX_DEPTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_conditional_config() {
+        let config = HashMap::from([
+            (
+                "theme".to_string(),
+                json!({"$when": "platform == 'iOS'", "value": "cupertino", "else": "material"}),
+            ),
+            (
+                "haptics".to_string(),
+                json!({"$when": "platform != 'desktop'", "value": true}),
+            ),
+            (
+                "layout".to_string(),
+                json!({
+                    "$when": "platform == 'desktop'",
+                    "value": {"$when": "locale == 'ar'", "value": "rtl", "else": "ltr"}
+                }),
+            ),
+            ("plain".to_string(), json!({"nested": 1})),
+        ]);
+        for value in config.values() {
+            validate_conditional(value).unwrap();
+        }
+
+        let desktop = resolve_config(&config, &context()).unwrap();
+        assert_eq!(desktop["theme"], json!("material"));
+        assert_eq!(desktop["layout"], json!("ltr"));
+        assert_eq!(desktop["plain"], json!({"nested": 1}));
+        assert!(!desktop.contains_key("haptics"));
+
+        let ios = resolve_config(&config, &context().with_platform("iOS")).unwrap();
+        assert_eq!(ios["theme"], json!("cupertino"));
+        assert_eq!(ios["haptics"], json!(true));
+        assert!(!ios.contains_key("layout"));
+
+        assert_eq!(
+            conditional_branches(&config["theme"]),
+            vec![&json!("cupertino"), &json!("material")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_conditional_values() {
+        let invalid = [
+            json!({"$when": "os == 'iOS'", "value": 1}),
+            json!({"$when": true, "value": 1}),
+            json!({"$when": "true"}),
+            json!({"$when": "true", "value": 1, "otherwise": 2}),
+            json!({"$when": "true", "value": {"$when": "(", "value": 1}}),
+        ];
+        for value in &invalid {
+            assert!(validate_conditional(value).is_err(), "{}", value);
+            assert!(resolve_conditional(value, &context()).is_err(), "{}", value);
+        }
+        assert_eq!(
+            validate_conditional(&invalid[0]).unwrap_err(),
+            "$when: unknown identifier 'os' (expected one of platform, arch, coreVersion, \
+             locale) at position 0"
+        );
+    }
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("en_US.UTF-8").as_deref(), Some("en-US"));
+        assert_eq!(normalize_locale("de").as_deref(), Some("de"));
+        assert_eq!(normalize_locale("sr_RS@latin").as_deref(), Some("sr-RS"));
+        assert_eq!(normalize_locale("C.UTF-8"), None);
+        assert_eq!(normalize_locale(""), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_never_panics(source in "\\PC{0,300}") {
+            let _ = Condition::parse(&source);
+        }
+
+        #[test]
+        fn test_parse_never_panics_on_token_soup(
+            tokens in proptest::collection::vec(
+                prop_oneof![
+                    Just("("), Just(")"), Just("!"), Just("&&"), Just("||"), Just("=="),
+                    Just("!="), Just("<"), Just(">="), Just("'a'"), Just("\""), Just("1.5"),
+                    Just("platform"), Just("locale"), Just("true"), Just("x"), Just("&"),
+                    Just("\\"), Just("é"), Just(" "),
+                ],
+                0..80,
+            )
+        ) {
+            let source = tokens.concat();
+            if let Ok(condition) = Condition::parse(&source) {
+                condition.evaluate(&context());
+            }
+        }
+    }
+}
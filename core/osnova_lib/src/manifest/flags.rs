@@ -0,0 +1,386 @@
+//! # Feature Flag Documents
+//!
+//! A publisher turns the flags apps declare (see
+//! [`crate::models::feature_flag`]) on or off for their users with a signed
+//! flag document, published next to the launcher catalog: the catalog's
+//! `featureFlagsUri` points at it, and it is fetched and cached with the
+//! catalog.
+//!
+//! A rule sets a flag's value for a stable percentage of identities,
+//! bucketed by identity fingerprint like the catalog's staged rollout;
+//! identities outside the rollout keep the app's default.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::manifest::flags::{publish_flag_document, FlagDocument, FlagRule};
+//!
+//! let mut document = FlagDocument::new("1.0.0");
+//! document.set_rule("com.example.mail", "newComposer", FlagRule::rollout(true, 25));
+//! let uri = publish_flag_document(&client, &document, &signing_key).await?;
+//! ```
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::launcher::{parse_version, rollout_bucket};
+use super::resolver::fetch_manifest;
+use crate::error::{OsnovaError, Result};
+use crate::http::{self, Trust};
+use crate::models::feature_flag::{validate_flags, FeatureFlag};
+use crate::network::{upload_data, AutonomiClient, NetworkOptions};
+use crate::util::canonical_json;
+
+/// Rollout percentage of rules that do not specify one
+const FULL_ROLLOUT: u8 = 100;
+
+fn full_rollout() -> u8 {
+    FULL_ROLLOUT
+}
+
+/// A publisher's value for one flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagRule {
+    /// Value for identities the rollout reaches
+    pub enabled: bool,
+    /// Percentage of identities (0-100) the rule applies to
+    #[serde(default = "full_rollout")]
+    pub rollout_percent: u8,
+}
+
+impl FlagRule {
+    /// A rule reaching every identity
+    pub fn new(enabled: bool) -> Self {
+        Self::rollout(enabled, FULL_ROLLOUT)
+    }
+
+    /// A rule reaching `percent` of identities
+    pub fn rollout(enabled: bool, percent: u8) -> Self {
+        Self {
+            enabled,
+            rollout_percent: percent,
+        }
+    }
+}
+
+/// Stable rollout bucket (0-99) of an identity for one flag of an app
+///
+/// Buckets of different flags are independent, so two flags rolled out to
+/// 10% do not reach the same identities.
+pub fn flag_bucket(fingerprint: &[u8; 32], app_id: &str, flag: &str) -> u8 {
+    rollout_bucket(fingerprint, &format!("{}#{}", app_id, flag))
+}
+
+/// Flag values a publisher ships for its apps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagDocument {
+    /// Document revision (x.y.z)
+    pub version: String,
+    /// Rules by app id, then flag name
+    #[serde(default)]
+    pub apps: BTreeMap<String, BTreeMap<String, FlagRule>>,
+    /// Publisher's Ed25519 public key (base64), set when signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Signature over the canonical payload (base64), set when signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl FlagDocument {
+    /// An unsigned document without rules
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            apps: BTreeMap::new(),
+            public_key: None,
+            signature: None,
+        }
+    }
+
+    /// Set the rule of one flag
+    pub fn set_rule(&mut self, app_id: &str, flag: &str, rule: FlagRule) {
+        self.apps
+            .entry(app_id.to_string())
+            .or_default()
+            .insert(flag.to_string(), rule);
+    }
+
+    /// Rule of one flag, if the document has one
+    pub fn rule(&self, app_id: &str, flag: &str) -> Option<FlagRule> {
+        self.apps.get(app_id)?.get(flag).copied()
+    }
+
+    /// Parse, validate and verify a document
+    ///
+    /// Unlike catalogs, flag documents must be signed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is malformed, the document is invalid,
+    /// or it is unsigned or its signature does not verify
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let document: Self = serde_json::from_slice(data)
+            .map_err(|e| OsnovaError::Other(format!("Failed to parse flag document: {}", e)))?;
+        document.validate()?;
+        document.verify_signature()?;
+        Ok(document)
+    }
+
+    /// Check document rules
+    ///
+    /// Checks:
+    /// - The version follows x.y.z
+    /// - App ids are non-empty and flag names are valid
+    /// - Rollout percentages are at most 100
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first rule broken
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| {
+            Err(OsnovaError::Other(format!(
+                "Flag document validation failed: {}",
+                message
+            )))
+        };
+
+        if parse_version(&self.version).is_none() {
+            return invalid(format!("invalid version {}", self.version));
+        }
+        for (app_id, rules) in &self.apps {
+            if app_id.is_empty() {
+                return invalid("rules for an empty app id".to_string());
+            }
+            let names: Vec<FeatureFlag> = rules
+                .keys()
+                .map(|name| FeatureFlag {
+                    name: name.clone(),
+                    description: String::new(),
+                    default: false,
+                })
+                .collect();
+            if let Err(e) = validate_flags(&names) {
+                return invalid(format!("{}: {}", app_id, e));
+            }
+            for (flag, rule) in rules {
+                if rule.rollout_percent > FULL_ROLLOUT {
+                    return invalid(format!(
+                        "{} {} has rollout {}%",
+                        app_id, flag, rule.rollout_percent
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Canonical bytes covered by the signature
+    fn signing_payload(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        canonical_json::to_canonical_vec(&unsigned)
+    }
+
+    /// Return a copy signed with `signing_key`
+    pub fn sign(&self, signing_key: &SigningKey) -> Result<Self> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut signed = Self {
+            public_key: Some(engine.encode(signing_key.verifying_key().to_bytes())),
+            signature: None,
+            ..self.clone()
+        };
+
+        let signature = signing_key.sign(&signed.signing_payload()?);
+        signed.signature = Some(engine.encode(signature.to_bytes()));
+        Ok(signed)
+    }
+
+    /// Check the document's signature against its public key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is unsigned or the signature is
+    /// invalid
+    pub fn verify_signature(&self) -> Result<()> {
+        let crypto_error =
+            |message: &str| OsnovaError::Crypto(format!("Flag document {}", message));
+        let (Some(public_key), Some(signature)) = (&self.public_key, &self.signature) else {
+            return Err(crypto_error("is not signed"));
+        };
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let public_key: [u8; 32] = engine
+            .decode(public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("public key is malformed"))?;
+        let signature: [u8; 64] = engine
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("signature is malformed"))?;
+
+        VerifyingKey::from_bytes(&public_key)
+            .map_err(|_| crypto_error("public key is invalid"))?
+            .verify(&self.signing_payload()?, &Signature::from_bytes(&signature))
+            .map_err(|_| crypto_error("signature verification failed"))
+    }
+
+    /// Check the document was signed by `publisher` (base64 public key)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another key signed it
+    pub fn verify_publisher(&self, publisher: &str) -> Result<()> {
+        if self.public_key.as_deref() != Some(publisher) {
+            return Err(OsnovaError::Crypto(
+                "Flag document is not signed by the catalog publisher".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fetch a flag document and verify it
+///
+/// # Arguments
+///
+/// * `uri` - Document URI (ant://, file://, or https://)
+/// * `client` - Optional Autonomi client (required for ant:// URIs)
+/// * `options` - Timeout and cancellation for the fetch
+pub async fn fetch_flag_document(
+    uri: &str,
+    client: Option<&AutonomiClient>,
+    options: &NetworkOptions,
+) -> Result<FlagDocument> {
+    // Referenced by the operator's catalog, so trusted like it
+    let http = http::shared();
+    let data = options
+        .run(
+            "flag document fetch",
+            fetch_manifest(uri, client, &http, Trust::Trusted),
+        )
+        .await?;
+
+    FlagDocument::from_bytes(&data)
+}
+
+/// Validate, sign, and upload a flag document
+///
+/// # Returns
+///
+/// The ant:// URI of the published document
+///
+/// # Errors
+///
+/// Returns an error if the document is invalid or the upload fails
+pub async fn publish_flag_document(
+    client: &AutonomiClient,
+    document: &FlagDocument,
+    signing_key: &SigningKey,
+) -> Result<String> {
+    document.validate()?;
+    let signed = document.sign(signing_key)?;
+    upload_data(client, &serde_json::to_vec_pretty(&signed)?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> FlagDocument {
+        let mut document = FlagDocument::new("1.0.0");
+        document.set_rule(
+            "com.example.mail",
+            "newComposer",
+            FlagRule::rollout(true, 25),
+        );
+        document.set_rule("com.example.mail", "threads", FlagRule::new(false));
+        document
+    }
+
+    #[test]
+    fn test_signed_document_round_trips() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let signed = document().sign(&key).unwrap();
+        let data = serde_json::to_vec(&signed).unwrap();
+
+        let parsed = FlagDocument::from_bytes(&data).unwrap();
+        assert_eq!(parsed, signed);
+        assert_eq!(
+            parsed.rule("com.example.mail", "newComposer"),
+            Some(FlagRule::rollout(true, 25))
+        );
+        assert_eq!(parsed.rule("com.example.mail", "missing"), None);
+        parsed
+            .verify_publisher(signed.public_key.as_deref().unwrap())
+            .unwrap();
+
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        let foreign = document().sign(&other).unwrap();
+        assert!(foreign
+            .verify_publisher(signed.public_key.as_deref().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_unsigned_or_tampered_documents_are_rejected() {
+        let unsigned = serde_json::to_vec(&document()).unwrap();
+        assert!(FlagDocument::from_bytes(&unsigned)
+            .unwrap_err()
+            .to_string()
+            .contains("not signed"));
+
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let mut tampered = document().sign(&key).unwrap();
+        tampered.set_rule("com.example.mail", "threads", FlagRule::new(true));
+        let data = serde_json::to_vec(&tampered).unwrap();
+        assert!(FlagDocument::from_bytes(&data)
+            .unwrap_err()
+            .to_string()
+            .contains("signature verification failed"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(document().validate().is_ok());
+
+        let mut over = document();
+        over.set_rule("com.example.mail", "beta", FlagRule::rollout(true, 101));
+        assert!(over.validate().unwrap_err().to_string().contains("101%"));
+
+        let mut bad_name = document();
+        bad_name.set_rule("com.example.mail", "new composer", FlagRule::new(true));
+        assert!(bad_name.validate().is_err());
+
+        assert!(FlagDocument::new("1.0").validate().is_err());
+    }
+
+    #[test]
+    fn test_flag_buckets_are_deterministic_and_independent() {
+        let mut same = 0;
+        for i in 0..200u8 {
+            let fingerprint = [i; 32];
+            let bucket = flag_bucket(&fingerprint, "com.example.mail", "newComposer");
+            assert!(bucket < 100);
+            assert_eq!(
+                bucket,
+                flag_bucket(&fingerprint, "com.example.mail", "newComposer")
+            );
+            if bucket == flag_bucket(&fingerprint, "com.example.mail", "threads") {
+                same += 1;
+            }
+        }
+        // Independent buckets coincide about 1% of the time
+        assert!(same < 20);
+    }
+}
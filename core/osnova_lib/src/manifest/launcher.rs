@@ -0,0 +1,875 @@
+//! # Launcher Catalog
+//!
+//! The list of apps a launcher offers for installation, and tooling for the
+//! publishers who maintain it.
+//!
+//! Supports:
+//! - Signed catalogs, published to the Autonomi Network like manifests
+//! - Field-level diffs between two catalog revisions
+//! - Staged rollout: each entry reaches a stable percentage of identities,
+//!   and may require a minimum core version
+//! - Per-entry `visibleWhen` conditions on platform, architecture, core
+//!   version, or locale
+//! - An embedded default catalog for first runs without network access
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::manifest::launcher::{diff_catalogs, fetch_launcher_catalog, publish_catalog};
+//!
+//! // Publisher: review the change, then publish
+//! let diff = diff_catalogs(&published, &draft);
+//! let uri = publish_catalog(&client, &draft, &signing_key).await?;
+//!
+//! // Client: entries visible to this identity and core version
+//! let catalog = fetch_launcher_catalog(&uri, Some(&client), &fingerprint, &options).await?;
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::condition::{Condition, ConditionContext};
+use super::resolver::fetch_manifest;
+use crate::error::{OsnovaError, Result};
+use crate::http::{self, Trust};
+use crate::network::{upload_data, AutonomiClient, NetworkOptions};
+use crate::util::canonical_json;
+
+/// Version of this library, compared against [`CatalogEntry::min_core_version`]
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Rollout percentage of entries that do not specify one
+const FULL_ROLLOUT: u8 = 100;
+
+/// Domain separator for rollout bucketing
+const ROLLOUT_CONTEXT: &[u8] = b"osnova-catalog-rollout-v1:";
+
+/// Default catalog compiled into the binary
+const EMBEDDED_CATALOG: &str = include_str!("default_catalog.json");
+
+fn full_rollout() -> u8 {
+    FULL_ROLLOUT
+}
+
+/// One app offered by a launcher catalog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogEntry {
+    /// Application id
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// URI of the app's manifest
+    pub manifest_uri: String,
+    /// Short description
+    #[serde(default)]
+    pub description: String,
+    /// Icon URI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_uri: Option<String>,
+    /// Percentage of identities (0-100) the entry is shown to
+    #[serde(default = "full_rollout")]
+    pub rollout_percent: u8,
+    /// Oldest core version (x.y.z) able to run the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_core_version: Option<String>,
+    /// Condition the client must meet to show the entry (see
+    /// [`condition`](super::condition)), e.g. `platform == "desktop"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible_when: Option<String>,
+}
+
+impl CatalogEntry {
+    /// Whether the entry is shown to `fingerprint` running `core_version`
+    pub fn is_available_to(&self, fingerprint: &[u8; 32], core_version: &str) -> bool {
+        self.is_rolled_out_to(fingerprint) && self.supports_core(core_version)
+    }
+
+    /// Whether the staged rollout has reached `fingerprint`
+    pub fn is_rolled_out_to(&self, fingerprint: &[u8; 32]) -> bool {
+        rollout_bucket(fingerprint, &self.id) < self.rollout_percent
+    }
+
+    /// Whether `core_version` meets the entry's `minCoreVersion`
+    pub fn supports_core(&self, core_version: &str) -> bool {
+        match (&self.min_core_version, parse_version(core_version)) {
+            (None, _) => true,
+            (Some(min), Some(current)) => parse_version(min).is_some_and(|min| current >= min),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Whether the entry's `visibleWhen` holds in `context`
+    ///
+    /// An entry whose condition does not parse is hidden; catalog
+    /// validation rejects those.
+    pub fn is_visible_in(&self, context: &ConditionContext) -> bool {
+        self.visible_when.as_deref().is_none_or(|when| {
+            Condition::parse(when).is_ok_and(|condition| condition.evaluate(context))
+        })
+    }
+}
+
+/// A launcher catalog as published
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherCatalog {
+    /// Catalog revision (x.y.z)
+    pub version: String,
+    /// Offered apps
+    pub entries: Vec<CatalogEntry>,
+    /// URI of the publisher's feature flag document (see
+    /// [`flags`](super::flags)), signed with the same key as the catalog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_flags_uri: Option<String>,
+    /// Publisher's Ed25519 public key (base64), set when signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Signature over the canonical payload (base64), set when signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl LauncherCatalog {
+    /// Parse and validate a catalog
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is malformed, the catalog is invalid, or
+    /// it carries a signature that does not verify.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let catalog: Self = serde_json::from_slice(data)
+            .map_err(|e| OsnovaError::Other(format!("Failed to parse launcher catalog: {}", e)))?;
+        catalog.validate()?;
+        if catalog.signature.is_some() {
+            catalog.verify_signature()?;
+        }
+        Ok(catalog)
+    }
+
+    /// Check catalog rules
+    ///
+    /// Checks:
+    /// - Versions follow x.y.z
+    /// - Entry ids are non-empty and unique
+    /// - Rollout percentages are at most 100
+    /// - `visibleWhen` conditions parse
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first rule broken
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| {
+            Err(OsnovaError::Other(format!(
+                "Launcher catalog validation failed: {}",
+                message
+            )))
+        };
+
+        if parse_version(&self.version).is_none() {
+            return invalid(format!("invalid version {}", self.version));
+        }
+
+        let mut ids = HashSet::new();
+        for entry in &self.entries {
+            if entry.id.is_empty() || entry.manifest_uri.is_empty() {
+                return invalid("entry with empty id or manifest URI".to_string());
+            }
+            if !ids.insert(entry.id.as_str()) {
+                return invalid(format!("duplicate entry {}", entry.id));
+            }
+            if entry.rollout_percent > FULL_ROLLOUT {
+                return invalid(format!(
+                    "{} has rollout {}%",
+                    entry.id, entry.rollout_percent
+                ));
+            }
+            if let Some(min) = &entry.min_core_version {
+                if parse_version(min).is_none() {
+                    return invalid(format!("{} has invalid minCoreVersion {}", entry.id, min));
+                }
+            }
+            if let Some(when) = &entry.visible_when {
+                if let Err(e) = Condition::parse(when) {
+                    return invalid(format!("{} has invalid visibleWhen: {}", entry.id, e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Canonical bytes covered by the signature
+    ///
+    /// The catalog without its signature, in canonical JSON so the payload
+    /// does not depend on field order in the published JSON.
+    fn signing_payload(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        canonical_json::to_canonical_vec(&unsigned)
+    }
+
+    /// Return a copy signed with `signing_key`
+    pub fn sign(&self, signing_key: &SigningKey) -> Result<Self> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut signed = Self {
+            public_key: Some(engine.encode(signing_key.verifying_key().to_bytes())),
+            signature: None,
+            ..self.clone()
+        };
+
+        let signature = signing_key.sign(&signed.signing_payload()?);
+        signed.signature = Some(engine.encode(signature.to_bytes()));
+        Ok(signed)
+    }
+
+    /// Check the catalog's signature against its public key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the catalog is unsigned or the signature is invalid
+    pub fn verify_signature(&self) -> Result<()> {
+        let crypto_error = |message: &str| OsnovaError::Crypto(format!("Catalog {}", message));
+        let (Some(public_key), Some(signature)) = (&self.public_key, &self.signature) else {
+            return Err(crypto_error("is not signed"));
+        };
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let public_key: [u8; 32] = engine
+            .decode(public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("public key is malformed"))?;
+        let signature: [u8; 64] = engine
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("signature is malformed"))?;
+
+        VerifyingKey::from_bytes(&public_key)
+            .map_err(|_| crypto_error("public key is invalid"))?
+            .verify(&self.signing_payload()?, &Signature::from_bytes(&signature))
+            .map_err(|_| crypto_error("signature verification failed"))
+    }
+
+    /// Entries shown to `fingerprint` on a client described by `context`
+    ///
+    /// Checks rollout, minimum core version, and `visibleWhen`.
+    pub fn entries_for(
+        &self,
+        fingerprint: &[u8; 32],
+        context: &ConditionContext,
+    ) -> Vec<CatalogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.is_available_to(fingerprint, &context.core_version)
+                    && entry.is_visible_in(context)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Entries that would be shown to `fingerprint`, but need a newer core
+    /// than the client described by `context`
+    ///
+    /// The launcher lists these as requiring a newer Osnova.
+    pub fn entries_requiring_newer_core(
+        &self,
+        fingerprint: &[u8; 32],
+        context: &ConditionContext,
+    ) -> Vec<CatalogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.is_rolled_out_to(fingerprint)
+                    && !entry.supports_core(&context.core_version)
+                    && entry.is_visible_in(context)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Where a catalog shown to the user came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CatalogSource {
+    /// The default catalog compiled into the binary
+    Embedded,
+    /// Freshly fetched from the configured launcher manifest
+    Network,
+    /// The last network catalog, saved locally
+    Cache,
+}
+
+/// A catalog labelled with its source, so the UI can show an offline banner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcedCatalog {
+    /// Where the catalog came from
+    pub source: CatalogSource,
+    /// The catalog
+    pub catalog: LauncherCatalog,
+    /// Entries left out of the catalog because they need a newer Osnova
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires_newer_osnova: Vec<CatalogEntry>,
+}
+
+/// The default catalog compiled into the binary
+///
+/// Lists the built-in screens and reference apps. Its validity is checked by
+/// a unit test.
+pub fn embedded_catalog() -> LauncherCatalog {
+    LauncherCatalog::from_bytes(EMBEDDED_CATALOG.as_bytes())
+        .expect("embedded launcher catalog is valid")
+}
+
+/// Overlay `overlay` on `base`, the overlay winning per app id
+///
+/// Entries keep `base` order, with overlay-only entries appended; the flag
+/// document is the overlay's. The result is unsigned since neither
+/// signature covers it.
+pub fn merge_catalogs(base: &LauncherCatalog, overlay: &LauncherCatalog) -> LauncherCatalog {
+    let mut entries: Vec<CatalogEntry> = base
+        .entries
+        .iter()
+        .map(|entry| {
+            overlay
+                .entries
+                .iter()
+                .find(|o| o.id == entry.id)
+                .unwrap_or(entry)
+                .clone()
+        })
+        .collect();
+    entries.extend(
+        overlay
+            .entries
+            .iter()
+            .filter(|o| !base.entries.iter().any(|entry| entry.id == o.id))
+            .cloned(),
+    );
+
+    LauncherCatalog {
+        version: overlay.version.clone(),
+        entries,
+        feature_flags_uri: overlay.feature_flags_uri.clone(),
+        public_key: None,
+        signature: None,
+    }
+}
+
+/// Change to one field of a catalog entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    /// Field name as published (camelCase)
+    pub field: String,
+    /// Previous value (null if absent)
+    pub old: Value,
+    /// New value (null if absent)
+    pub new: Value,
+}
+
+/// Changed fields of an entry present in both catalogs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryChange {
+    /// Entry id
+    pub id: String,
+    /// Changed fields, sorted by name
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences between two catalog revisions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogDiff {
+    /// Entries only in the new catalog
+    pub added: Vec<CatalogEntry>,
+    /// Entries only in the old catalog
+    pub removed: Vec<CatalogEntry>,
+    /// Entries in both catalogs whose fields differ
+    pub changed: Vec<EntryChange>,
+}
+
+impl CatalogDiff {
+    /// Whether the catalogs offer identical entries
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two catalog revisions entry by entry
+///
+/// Entries are matched by id; results follow the order of the catalog they
+/// come from.
+pub fn diff_catalogs(old: &LauncherCatalog, new: &LauncherCatalog) -> CatalogDiff {
+    let find = |catalog: &LauncherCatalog, id: &str| {
+        catalog.entries.iter().find(|entry| entry.id == id).cloned()
+    };
+
+    let mut diff = CatalogDiff::default();
+    for entry in &new.entries {
+        match find(old, &entry.id) {
+            None => diff.added.push(entry.clone()),
+            Some(previous) => {
+                let fields = diff_fields(&previous, entry);
+                if !fields.is_empty() {
+                    diff.changed.push(EntryChange {
+                        id: entry.id.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = old
+        .entries
+        .iter()
+        .filter(|entry| find(new, &entry.id).is_none())
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Field-level changes between two versions of an entry
+fn diff_fields(old: &CatalogEntry, new: &CatalogEntry) -> Vec<FieldChange> {
+    let fields = |entry: &CatalogEntry| -> BTreeMap<String, Value> {
+        match serde_json::to_value(entry) {
+            Ok(Value::Object(map)) => map.into_iter().collect(),
+            _ => BTreeMap::new(),
+        }
+    };
+    let (old, new) = (fields(old), fields(new));
+
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let before = old.get(name).cloned().unwrap_or(Value::Null);
+            let after = new.get(name).cloned().unwrap_or(Value::Null);
+            (before != after).then(|| FieldChange {
+                field: name.clone(),
+                old: before,
+                new: after,
+            })
+        })
+        .collect()
+}
+
+/// Stable rollout bucket (0-99) of an identity for an app
+///
+/// An entry with rollout `p` is shown to identities in buckets below `p`,
+/// so raising the percentage only ever adds identities.
+pub fn rollout_bucket(fingerprint: &[u8; 32], app_id: &str) -> u8 {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(ROLLOUT_CONTEXT);
+    hasher.update(fingerprint);
+    hasher.update(app_id.as_bytes());
+
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+    (u64::from_be_bytes(prefix) % 100) as u8
+}
+
+/// Parse an x.y.z version for ordering
+pub(crate) fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    match (parts.next()?, parts.next()?, parts.next()?, parts.next()) {
+        (Some(major), Some(minor), Some(patch), None) => Some((major, minor, patch)),
+        _ => None,
+    }
+}
+
+/// Fetch a launcher catalog, with all of its entries
+///
+/// A signed catalog must verify.
+///
+/// # Arguments
+///
+/// * `uri` - Catalog URI (ant://, file://, or https://)
+/// * `client` - Optional Autonomi client (required for ant:// URIs)
+/// * `options` - Timeout and cancellation for the fetch
+pub async fn fetch_catalog(
+    uri: &str,
+    client: Option<&AutonomiClient>,
+    options: &NetworkOptions,
+) -> Result<LauncherCatalog> {
+    // The catalog URI is configured by the operator, unlike the URIs in it
+    let http = http::shared();
+    let data = options
+        .run(
+            "launcher catalog fetch",
+            fetch_manifest(uri, client, &http, Trust::Trusted),
+        )
+        .await?;
+
+    LauncherCatalog::from_bytes(&data)
+}
+
+/// Fetch a launcher catalog and keep the entries this client should show
+///
+/// Entries are filtered for `fingerprint` (staged rollout), [`CORE_VERSION`],
+/// and `visibleWhen` on this client. A signed catalog must verify; the returned catalog keeps
+/// the published signature, which no longer covers the filtered entries.
+///
+/// # Arguments
+///
+/// * `uri` - Catalog URI (ant://, file://, or https://)
+/// * `client` - Optional Autonomi client (required for ant:// URIs)
+/// * `fingerprint` - Identity fingerprint used for rollout bucketing
+/// * `options` - Timeout and cancellation for the fetch
+pub async fn fetch_launcher_catalog(
+    uri: &str,
+    client: Option<&AutonomiClient>,
+    fingerprint: &[u8; 32],
+    options: &NetworkOptions,
+) -> Result<LauncherCatalog> {
+    let mut catalog = fetch_catalog(uri, client, options).await?;
+    catalog.entries = catalog.entries_for(fingerprint, &ConditionContext::current());
+    Ok(catalog)
+}
+
+/// Validate, sign, and upload a launcher catalog
+///
+/// # Returns
+///
+/// The ant:// URI of the published catalog
+///
+/// # Errors
+///
+/// Returns an error if the catalog is invalid or the upload fails
+pub async fn publish_catalog(
+    client: &AutonomiClient,
+    catalog: &LauncherCatalog,
+    signing_key: &SigningKey,
+) -> Result<String> {
+    catalog.validate()?;
+    let signed = catalog.sign(signing_key)?;
+    upload_data(client, &serde_json::to_vec_pretty(&signed)?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(id: &str) -> CatalogEntry {
+        CatalogEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            manifest_uri: format!("ant://{}", id),
+            description: String::new(),
+            icon_uri: None,
+            rollout_percent: FULL_ROLLOUT,
+            min_core_version: None,
+            visible_when: None,
+        }
+    }
+
+    fn catalog(entries: Vec<CatalogEntry>) -> LauncherCatalog {
+        LauncherCatalog {
+            version: "1.0.0".to_string(),
+            entries,
+            feature_flags_uri: None,
+            public_key: None,
+            signature: None,
+        }
+    }
+
+    fn fingerprint(n: u32) -> [u8; 32] {
+        *blake3::hash(&n.to_be_bytes()).as_bytes()
+    }
+
+    #[test]
+    fn test_diff_catalogs() {
+        let old = catalog(vec![entry("wallet"), entry("notes"), entry("chat")]);
+
+        let mut notes = entry("notes");
+        notes.name = "Notes 2".to_string();
+        notes.rollout_percent = 10;
+        let new = catalog(vec![entry("wallet"), notes, entry("music")]);
+
+        let diff = diff_catalogs(&old, &new);
+        assert_eq!(diff.added, vec![entry("music")]);
+        assert_eq!(diff.removed, vec![entry("chat")]);
+        assert_eq!(
+            diff.changed,
+            vec![EntryChange {
+                id: "notes".to_string(),
+                fields: vec![
+                    FieldChange {
+                        field: "name".to_string(),
+                        old: Value::from("notes"),
+                        new: Value::from("Notes 2"),
+                    },
+                    FieldChange {
+                        field: "rolloutPercent".to_string(),
+                        old: Value::from(100),
+                        new: Value::from(10),
+                    },
+                ],
+            }]
+        );
+
+        assert!(diff_catalogs(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_optional_fields() {
+        let mut gated = entry("wallet");
+        gated.min_core_version = Some("2.0.0".to_string());
+
+        let diff = diff_catalogs(&catalog(vec![entry("wallet")]), &catalog(vec![gated]));
+        assert_eq!(diff.changed[0].fields.len(), 1);
+        assert_eq!(diff.changed[0].fields[0].field, "minCoreVersion");
+        assert_eq!(diff.changed[0].fields[0].old, Value::Null);
+    }
+
+    #[test]
+    fn test_rollout_bucket_is_deterministic() {
+        let mut staged = entry("wallet");
+        staged.rollout_percent = 10;
+
+        for n in 0..50 {
+            let fp = fingerprint(n);
+            let bucket = rollout_bucket(&fp, "wallet");
+            assert!(bucket < 100);
+            assert_eq!(bucket, rollout_bucket(&fp, "wallet"));
+            assert_eq!(staged.is_available_to(&fp, CORE_VERSION), bucket < 10);
+        }
+
+        let mut none = entry("wallet");
+        none.rollout_percent = 0;
+        assert!(!none.is_available_to(&fingerprint(0), CORE_VERSION));
+    }
+
+    #[test]
+    fn test_rollout_distribution() {
+        let mut staged = entry("wallet");
+        staged.rollout_percent = 10;
+        let identities = 10_000;
+
+        let reached = (0..identities)
+            .filter(|&n| staged.is_available_to(&fingerprint(n), CORE_VERSION))
+            .count();
+
+        // 10% of 10,000 identities, with generous slack
+        assert!((800..1200).contains(&reached), "reached {}", reached);
+
+        // Raising the percentage keeps everyone already included
+        let mut wider = staged.clone();
+        wider.rollout_percent = 50;
+        assert!((0..1_000).all(|n| {
+            !staged.is_available_to(&fingerprint(n), CORE_VERSION)
+                || wider.is_available_to(&fingerprint(n), CORE_VERSION)
+        }));
+    }
+
+    #[test]
+    fn test_core_version_filtering() {
+        let mut gated = entry("wallet");
+        gated.min_core_version = Some("1.4.0".to_string());
+        let catalog = catalog(vec![gated, entry("notes")]);
+        let fp = fingerprint(1);
+
+        let ids = |version: &str| -> Vec<String> {
+            catalog
+                .entries_for(&fp, &ConditionContext::current().with_core_version(version))
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        assert_eq!(ids("1.3.9"), vec!["notes"]);
+        assert_eq!(ids("1.4.0"), vec!["wallet", "notes"]);
+        assert_eq!(ids("1.10.0"), vec!["wallet", "notes"]);
+        assert_eq!(ids("dev"), vec!["notes"]);
+    }
+
+    #[test]
+    fn test_entries_requiring_newer_core() {
+        let mut gated = entry("wallet");
+        gated.min_core_version = Some("1.4.0".to_string());
+        let mut gated_elsewhere = gated.clone();
+        gated_elsewhere.id = "mobile-wallet".to_string();
+        gated_elsewhere.visible_when = Some("platform == \"Android\"".to_string());
+        let mut unreached = gated.clone();
+        unreached.id = "beta-wallet".to_string();
+        unreached.rollout_percent = 0;
+        let catalog = catalog(vec![gated, gated_elsewhere, unreached, entry("notes")]);
+        let context = ConditionContext::current()
+            .with_platform("desktop")
+            .with_core_version("1.3.9");
+
+        let ids: Vec<String> = catalog
+            .entries_requiring_newer_core(&fingerprint(1), &context)
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        // Not entries hidden for other reasons, nor those already shown
+        assert_eq!(ids, vec!["wallet"]);
+        assert!(catalog
+            .entries_requiring_newer_core(&fingerprint(1), &context.with_core_version("1.4.0"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_visible_when_filtering() {
+        let mut mobile = entry("camera");
+        mobile.visible_when = Some("platform != 'desktop'".to_string());
+        let mut german = entry("steuer");
+        german.visible_when = Some("locale == 'de-DE' && coreVersion >= '0.1.0'".to_string());
+        let catalog = catalog(vec![mobile, german, entry("notes")]);
+        assert!(catalog.validate().is_ok());
+
+        let ids = |context: ConditionContext| -> Vec<String> {
+            catalog
+                .entries_for(&fingerprint(1), &context)
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        let desktop = ConditionContext::current()
+            .with_platform("desktop")
+            .with_locale("en-US");
+        assert_eq!(ids(desktop.clone()), vec!["notes"]);
+        assert_eq!(
+            ids(desktop.clone().with_platform("Android")),
+            vec!["camera", "notes"]
+        );
+        assert_eq!(ids(desktop.with_locale("de-DE")), vec!["steuer", "notes"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(catalog(vec![entry("a"), entry("b")]).validate().is_ok());
+        assert!(catalog(vec![entry("a"), entry("a")]).validate().is_err());
+
+        let mut over = entry("a");
+        over.rollout_percent = 101;
+        assert!(catalog(vec![over]).validate().is_err());
+
+        let mut bad_min = entry("a");
+        bad_min.min_core_version = Some("1.0".to_string());
+        assert!(catalog(vec![bad_min]).validate().is_err());
+
+        let mut bad_when = entry("a");
+        bad_when.visible_when = Some("platform == 'iOS' || device == 'tv'".to_string());
+        let error = catalog(vec![bad_when]).validate().unwrap_err().to_string();
+        assert!(error.contains("unknown identifier 'device'"));
+        assert!(error.contains("at position 21"));
+
+        let mut bad_version = catalog(vec![]);
+        bad_version.version = "one".to_string();
+        assert!(bad_version.validate().is_err());
+    }
+
+    #[test]
+    fn test_signature_round_trip() -> Result<()> {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let signed = catalog(vec![entry("wallet")]).sign(&key)?;
+        signed.verify_signature()?;
+
+        let bytes = serde_json::to_vec(&signed)?;
+        assert_eq!(LauncherCatalog::from_bytes(&bytes)?, signed);
+
+        let mut tampered = signed.clone();
+        tampered.entries[0].manifest_uri = "ant://evil".to_string();
+        assert!(tampered.verify_signature().is_err());
+        assert!(LauncherCatalog::from_bytes(&serde_json::to_vec(&tampered)?).is_err());
+
+        assert!(catalog(vec![]).verify_signature().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signing_payload_matches_sorted_key_encoding() -> Result<()> {
+        // Catalogs were signed over sorted-key serde_json output before the
+        // canonical encoder existed; those signatures must keep verifying
+        let signed = embedded_catalog().sign(&SigningKey::from_bytes(&[7u8; 32]))?;
+        let unsigned = LauncherCatalog {
+            signature: None,
+            ..signed.clone()
+        };
+        let legacy = serde_json::to_vec(&serde_json::to_value(&unsigned)?)?;
+
+        assert_eq!(signed.signing_payload()?, legacy);
+        Ok(())
+    }
+
+    #[test]
+    fn test_embedded_catalog_is_valid() {
+        let embedded: LauncherCatalog = serde_json::from_str(EMBEDDED_CATALOG).unwrap();
+        embedded.validate().unwrap();
+
+        assert!(embedded.signature.is_none());
+        assert!(embedded
+            .entries
+            .iter()
+            .any(|e| e.id == "com.osnova.launcher"));
+        // Every identity on every version sees the whole default catalog
+        assert_eq!(
+            embedded.entries_for(
+                &fingerprint(0),
+                &ConditionContext::current().with_core_version("0.0.1")
+            ),
+            embedded.entries
+        );
+        assert_eq!(embedded_catalog(), embedded);
+    }
+
+    #[test]
+    fn test_merge_network_wins_per_app() {
+        let mut base_wallet = entry("wallet");
+        base_wallet.name = "Embedded Wallet".to_string();
+        let base = catalog(vec![entry("launcher"), base_wallet]);
+
+        let mut network = catalog(vec![entry("music"), entry("wallet")]);
+        network.version = "2.0.0".to_string();
+        let network = network.sign(&SigningKey::from_bytes(&[7u8; 32])).unwrap();
+
+        let merged = merge_catalogs(&base, &network);
+        assert_eq!(merged.version, "2.0.0");
+        assert_eq!(
+            merged.entries,
+            vec![entry("launcher"), entry("wallet"), entry("music")]
+        );
+        assert!(merged.signature.is_none());
+        merged.validate().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_filters_for_identity() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("catalog.json");
+
+        let mut hidden = entry("hidden");
+        hidden.rollout_percent = 0;
+        let mut future = entry("future");
+        future.min_core_version = Some("999.0.0".to_string());
+        let published = catalog(vec![entry("wallet"), hidden, future])
+            .sign(&SigningKey::from_bytes(&[7u8; 32]))?;
+        std::fs::write(&path, serde_json::to_vec(&published)?)?;
+
+        let fetched = fetch_launcher_catalog(
+            &format!("file://{}", path.display()),
+            None,
+            &fingerprint(3),
+            &NetworkOptions::default(),
+        )
+        .await?;
+
+        assert_eq!(fetched.entries, vec![entry("wallet")]);
+        Ok(())
+    }
+}
@@ -6,6 +6,12 @@
 //! - Manifest schema definition
 //! - JSON parsing and validation
 //! - Support for ant:// URIs and local paths
+//! - Launcher catalogs with diffs and staged rollout
+//! - Signed feature flag documents published with the catalog
+//! - Manifest co-signing and signature policies
+//! - Conditions for platform-dependent config and catalog entries
+//! - Verification of reproducible component builds
+//! - Capability negotiation for manifests written for newer releases
 //!
 //! ## Example
 //!
@@ -36,7 +42,24 @@
 pub mod schema;
 pub mod validator;
 pub mod resolver;
+pub mod launcher;
+pub mod signing;
+pub mod condition;
+pub mod reproducibility;
+pub mod capabilities;
+pub mod flags;
 
-pub use schema::{ManifestSchema, ComponentSchema};
+pub use schema::{validate_uri_scheme, ManifestSchema, ComponentSchema, RESERVED_URI_SCHEMES};
+pub use capabilities::{CapabilityReport, UnsupportedFeature};
 pub use validator::{validate_manifest, validate_manifest_bytes};
-pub use resolver::resolve_manifest;
+pub use resolver::{resolve_manifest, resolve_manifest_with};
+pub use signing::{add_signature, check_signature_policy, publish_manifest};
+pub use reproducibility::{
+    normalize_artifact, verify_reproducible, NormalizedArtifact, ReproVerdict,
+};
+pub use launcher::{
+    diff_catalogs, embedded_catalog, fetch_catalog, fetch_launcher_catalog, merge_catalogs,
+    publish_catalog,
+    CatalogDiff, CatalogEntry, CatalogSource, LauncherCatalog, SourcedCatalog,
+};
+pub use flags::{fetch_flag_document, publish_flag_document, FlagDocument, FlagRule};
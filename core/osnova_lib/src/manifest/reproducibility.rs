@@ -0,0 +1,611 @@
+//! # Reproducible Builds
+//!
+//! Verification that a component built locally from source matches the
+//! hash its manifest declares.
+//!
+//! Archives carry incidental differences between builds of the same
+//! content: entry order, modification times, owners, and the gzip header
+//! timestamp. [`normalize_artifact`] removes them, producing the bytes that
+//! are published (`osnova-manifest normalize`) and that local builds are
+//! compared by (`osnova-manifest verify-repro`):
+//!
+//! - Directories and tar archives (plain or gzip-compressed) become a
+//!   gzip-compressed tar with entries sorted by path, every parent
+//!   directory present, modification times and owners cleared, and modes
+//!   reduced to `0644`, or `0755` for directories and executables
+//! - Any other file, such as a backend binary, is taken as is
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::manifest::reproducibility::{verify_reproducible, ReproVerdict};
+//!
+//! match verify_reproducible(&manifest, "ant://frontend", Path::new("dist")) {
+//!     ReproVerdict::Match { hash } => println!("Reproduced {}", hash),
+//!     verdict => println!("{:?}", verdict),
+//! }
+//! ```
+
+use flate2::read::GzDecoder;
+use flate2::{Compression, GzBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::components::binary::is_executable;
+use crate::components::integrity::content_hash;
+use crate::error::{OsnovaError, Result};
+use crate::manifest::ManifestSchema;
+
+/// Mode of normalized directories and executable files
+const EXECUTABLE_MODE: u32 = 0o755;
+
+/// Mode of normalized regular files
+const FILE_MODE: u32 = 0o644;
+
+/// Artifact bytes as published, with what normalization changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedArtifact {
+    /// Normalized bytes
+    pub bytes: Vec<u8>,
+    /// Canonical hash of the bytes, as declared in manifests
+    pub hash: String,
+    /// What was changed to normalize the artifact
+    pub notes: Vec<String>,
+}
+
+/// Outcome of comparing a local build against its manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "verdict", rename_all = "camelCase")]
+pub enum ReproVerdict {
+    /// The normalized local build has the declared hash
+    Match {
+        /// Declared and reproduced hash
+        hash: String,
+    },
+    /// The normalized local build has a different hash
+    #[serde(rename_all = "camelCase")]
+    Mismatch {
+        /// Hash the manifest declares
+        expected: String,
+        /// Hash of the normalized local build
+        actual: String,
+        /// What normalization changed in the local build
+        normalization_notes: Vec<String>,
+    },
+    /// The build could not be compared
+    NotComparable {
+        /// Why not
+        reason: String,
+    },
+}
+
+/// An archive entry kept by normalization
+enum Entry {
+    Directory,
+    File { data: Vec<u8>, executable: bool },
+    Symlink { target: PathBuf },
+}
+
+/// What normalizing an archive changed, counted per kind of change
+#[derive(Default)]
+struct Changes {
+    reordered: bool,
+    timestamps: usize,
+    owners: usize,
+    modes: usize,
+    duplicates: usize,
+    skipped: usize,
+    parents: usize,
+    compressed: bool,
+    gzip_timestamp: bool,
+}
+
+impl Changes {
+    fn notes(&self) -> Vec<String> {
+        let counted = [
+            (self.timestamps, "Cleared modification times"),
+            (self.owners, "Cleared owners"),
+            (self.modes, "Normalized permissions"),
+            (self.duplicates, "Kept the last of duplicated paths"),
+            (self.skipped, "Left out devices, fifos and hard links"),
+            (self.parents, "Added missing parent directories"),
+        ];
+
+        let mut notes = Vec::new();
+        if self.reordered {
+            notes.push("Sorted entries by path".to_string());
+        }
+        for (count, change) in counted {
+            if count > 0 {
+                let entries = if count == 1 { "entry" } else { "entries" };
+                notes.push(format!("{}: {} {}", change, count, entries));
+            }
+        }
+        if self.gzip_timestamp {
+            notes.push("Cleared the gzip header timestamp".to_string());
+        }
+        if self.compressed {
+            notes.push("Compressed the tar archive with gzip".to_string());
+        }
+        notes
+    }
+}
+
+/// Normalize a build artifact the way packaging does
+///
+/// # Arguments
+///
+/// * `path` - A build output directory, a tar archive (plain or
+///   gzip-compressed), or any other file
+///
+/// # Errors
+///
+/// Returns an error if the artifact cannot be read, or an archive holds
+/// paths outside its root
+pub fn normalize_artifact(path: &Path) -> Result<NormalizedArtifact> {
+    let mut changes = Changes::default();
+    let mut notes = Vec::new();
+
+    let bytes = if path.is_dir() {
+        let entries = read_directory(path)?;
+        notes.push(format!("Packed directory of {} entries", entries.len()));
+        pack(entries, &mut changes)?
+    } else {
+        let data = std::fs::read(path)?;
+        match read_archive(&data, &mut changes)? {
+            Some(entries) => pack(entries, &mut changes)?,
+            None => data,
+        }
+    };
+    notes.extend(changes.notes());
+
+    Ok(NormalizedArtifact {
+        hash: content_hash(&bytes),
+        bytes,
+        notes,
+    })
+}
+
+/// Compare a local build of a component against its manifest's hash
+///
+/// # Arguments
+///
+/// * `manifest` - Published manifest declaring the component
+/// * `component_id` - Component to compare
+/// * `local_artifact_path` - Local build of the component, as accepted by
+///   [`normalize_artifact`]
+pub fn verify_reproducible(
+    manifest: &ManifestSchema,
+    component_id: &str,
+    local_artifact_path: &Path,
+) -> ReproVerdict {
+    let not_comparable = |reason: String| ReproVerdict::NotComparable { reason };
+
+    let Some(component) = manifest.components.iter().find(|c| c.id == component_id) else {
+        return not_comparable(format!(
+            "{} {} has no component {}",
+            manifest.name, manifest.version, component_id
+        ));
+    };
+    let Some(expected) = &component.hash else {
+        return not_comparable(format!(
+            "The manifest declares no hash for {}",
+            component_id
+        ));
+    };
+    let normalized = match normalize_artifact(local_artifact_path) {
+        Ok(normalized) => normalized,
+        Err(e) => {
+            return not_comparable(format!(
+                "Failed to normalize {}: {}",
+                local_artifact_path.display(),
+                e
+            ))
+        }
+    };
+
+    if normalized.hash == *expected {
+        ReproVerdict::Match {
+            hash: normalized.hash,
+        }
+    } else {
+        ReproVerdict::Mismatch {
+            expected: expected.clone(),
+            actual: normalized.hash,
+            normalization_notes: normalized.notes,
+        }
+    }
+}
+
+/// Entries of a build output directory, by relative path
+fn read_directory(root: &Path) -> Result<BTreeMap<PathBuf, Entry>> {
+    let mut entries = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for item in std::fs::read_dir(&dir)? {
+            let item = item?;
+            let path = item.path();
+            let relative = path
+                .strip_prefix(root)
+                .map_err(|e| OsnovaError::Storage(e.to_string()))?
+                .to_path_buf();
+            let file_type = item.file_type()?;
+
+            let entry = if file_type.is_symlink() {
+                Entry::Symlink {
+                    target: std::fs::read_link(&path)?,
+                }
+            } else if file_type.is_dir() {
+                pending.push(path);
+                Entry::Directory
+            } else {
+                Entry::File {
+                    executable: is_executable(&item.metadata()?),
+                    data: std::fs::read(&path)?,
+                }
+            };
+            entries.insert(relative, entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Entries of a tar archive, plain or gzip-compressed; `None` if `data` is
+/// not an archive
+fn read_archive(data: &[u8], changes: &mut Changes) -> Result<Option<BTreeMap<PathBuf, Entry>>> {
+    let gzipped = data.starts_with(&[0x1f, 0x8b]);
+    let tar = if gzipped {
+        let mut decompressed = Vec::new();
+        if GzDecoder::new(data).read_to_end(&mut decompressed).is_err() {
+            return Ok(None);
+        }
+        decompressed
+    } else {
+        data.to_vec()
+    };
+    // ustar and GNU archives carry their magic after the first file name
+    if tar.get(257..262) != Some(b"ustar") {
+        return Ok(None);
+    }
+
+    let mut archive = tar::Archive::new(tar.as_slice());
+    let mut entries = BTreeMap::new();
+    let mut previous: Option<PathBuf> = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = relative_path(&entry.path()?)?;
+        let header = entry.header();
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let mode = header.mode()?;
+        if header.mtime().ok() != Some(0) {
+            changes.timestamps += 1;
+        }
+        let named = |name: Option<&[u8]>| name.is_some_and(|name| !name.is_empty());
+        // Unreadable numeric fields are cleared like any other value
+        if header.uid().ok() != Some(0)
+            || header.gid().ok() != Some(0)
+            || named(header.username_bytes())
+            || named(header.groupname_bytes())
+        {
+            changes.owners += 1;
+        }
+
+        let entry_type = header.entry_type();
+        let normalized = if entry_type.is_dir() {
+            if mode != EXECUTABLE_MODE {
+                changes.modes += 1;
+            }
+            Entry::Directory
+        } else if entry_type.is_symlink() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| OsnovaError::Storage("Symlink without a target".to_string()))?
+                .into_owned();
+            Entry::Symlink { target }
+        } else if entry_type.is_file() {
+            let executable = mode & 0o111 != 0;
+            let normal = if executable {
+                EXECUTABLE_MODE
+            } else {
+                FILE_MODE
+            };
+            if mode != normal {
+                changes.modes += 1;
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            Entry::File { data, executable }
+        } else {
+            changes.skipped += 1;
+            continue;
+        };
+
+        if previous.as_ref().is_some_and(|previous| *previous > path) {
+            changes.reordered = true;
+        }
+        previous = Some(path.clone());
+        if entries.insert(path, normalized).is_some() {
+            changes.duplicates += 1;
+        }
+    }
+
+    changes.compressed = !gzipped;
+    // Bytes 4..8 of a gzip header hold the compression time
+    changes.gzip_timestamp = gzipped && data.get(4..8).is_some_and(|time| time != [0; 4]);
+    Ok(Some(entries))
+}
+
+/// An archive path relative to the archive root
+fn relative_path(path: &Path) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::Normal(part) => relative.push(part),
+            std::path::Component::CurDir => {}
+            _ => {
+                return Err(OsnovaError::Storage(format!(
+                    "Archive path {} leaves the archive root",
+                    path.display()
+                )))
+            }
+        }
+    }
+    Ok(relative)
+}
+
+/// Pack entries into a gzip-compressed tar with stable metadata
+fn pack(mut entries: BTreeMap<PathBuf, Entry>, changes: &mut Changes) -> Result<Vec<u8>> {
+    let parents: BTreeSet<PathBuf> = entries
+        .keys()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    for parent in parents {
+        if let std::collections::btree_map::Entry::Vacant(vacant) = entries.entry(parent) {
+            vacant.insert(Entry::Directory);
+            changes.parents += 1;
+        }
+    }
+
+    let encoder = GzBuilder::new()
+        .mtime(0)
+        .write(Vec::new(), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for (path, entry) in &entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        match entry {
+            Entry::Directory => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(EXECUTABLE_MODE);
+                header.set_size(0);
+                builder.append_data(&mut header, path, std::io::empty())?;
+            }
+            Entry::File { data, executable } => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(if *executable {
+                    EXECUTABLE_MODE
+                } else {
+                    FILE_MODE
+                });
+                header.set_size(data.len() as u64);
+                builder.append_data(&mut header, path, data.as_slice())?;
+            }
+            Entry::Symlink { target } => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(EXECUTABLE_MODE);
+                header.set_size(0);
+                builder.append_link(&mut header, path, target)?;
+            }
+        }
+    }
+    Ok(builder.into_inner()?.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A gzip-compressed tar of `files`, in the given order, with build
+    /// metadata that differs between builds
+    fn build_archive(files: &[(&str, &[u8])], mtime: u64, uid: u64) -> Vec<u8> {
+        let encoder = GzBuilder::new()
+            .mtime(mtime as u32)
+            .write(Vec::new(), Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, data) in files {
+            let mut header = tar::Header::new_ustar();
+            header.set_mtime(mtime);
+            header.set_uid(uid);
+            header.set_gid(uid);
+            header.set_mode(0o664);
+            header.set_size(data.len() as u64);
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn manifest(hash: Option<String>) -> ManifestSchema {
+        serde_json::from_value(serde_json::json!({
+            "id": "com.test.repro",
+            "name": "Repro",
+            "version": "1.0.0",
+            "iconUri": "icon.png",
+            "description": "Reproducible app",
+            "components": [{
+                "id": "ant://frontend",
+                "name": "Frontend",
+                "kind": "frontend",
+                "platform": "desktop",
+                "version": "1.0.0",
+                "hash": hash,
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn write(dir: &TempDir, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_builds_at_different_times_normalize_identically() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let files: [(&str, &[u8]); 2] = [
+            ("index.html", b"<html></html>"),
+            ("assets/app.js", b"console.log(1)"),
+        ];
+        let mut reversed = files;
+        reversed.reverse();
+
+        let monday = write(
+            &dir,
+            "monday.tar.gz",
+            &build_archive(&files, 1_700_000_000, 0),
+        );
+        let tuesday = write(
+            &dir,
+            "tuesday.tar.gz",
+            &build_archive(&reversed, 1_700_086_400, 1000),
+        );
+        assert_ne!(std::fs::read(&monday)?, std::fs::read(&tuesday)?);
+
+        let monday = normalize_artifact(&monday)?;
+        let tuesday = normalize_artifact(&tuesday)?;
+        assert_eq!(monday.bytes, tuesday.bytes);
+        assert_eq!(monday.hash, tuesday.hash);
+        assert!(monday.notes.contains(&"Sorted entries by path".to_string()));
+        assert!(tuesday
+            .notes
+            .contains(&"Cleared modification times: 2 entries".to_string()));
+        assert!(tuesday
+            .notes
+            .contains(&"Cleared the gzip header timestamp".to_string()));
+
+        // Normalizing is idempotent, and an unpacked build directory
+        // normalizes to the same archive
+        let again = normalize_artifact(&write(&dir, "again.tar.gz", &monday.bytes))?;
+        assert_eq!(again.hash, monday.hash);
+        assert!(again.notes.is_empty());
+
+        let build = dir.path().join("dist");
+        std::fs::create_dir_all(build.join("assets"))?;
+        std::fs::write(build.join("index.html"), b"<html></html>")?;
+        std::fs::write(build.join("assets/app.js"), b"console.log(1)")?;
+        assert_eq!(normalize_artifact(&build)?.hash, monday.hash);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_archives_are_hashed_as_is() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let binary = write(&dir, "backend", b"\x7fELF backend");
+        let normalized = normalize_artifact(&binary)?;
+        assert_eq!(normalized.bytes, b"\x7fELF backend");
+        assert_eq!(normalized.hash, content_hash(b"\x7fELF backend"));
+        assert!(normalized.notes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_paths_outside_root_are_refused() {
+        let dir = TempDir::new().unwrap();
+        // tar::Builder refuses such paths, so the header is written by hand
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..12].copy_from_slice(b"../escape.js");
+        header.set_mode(FILE_MODE);
+        header.set_size(1);
+        header.set_cksum();
+        let mut tar = header.as_bytes().to_vec();
+        tar.push(b'x');
+        tar.resize(512 * 4, 0);
+
+        assert!(normalize_artifact(&write(&dir, "evil.tar", &tar)).is_err());
+    }
+
+    #[test]
+    fn test_verify_reproducible() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let files: [(&str, &[u8]); 1] = [("index.html", b"<html>v1</html>")];
+        let published = normalize_artifact(&write(
+            &dir,
+            "published.tar.gz",
+            &build_archive(&files, 1, 0),
+        ))?;
+        let manifest = manifest(Some(published.hash.clone()));
+
+        let local = write(&dir, "local.tar.gz", &build_archive(&files, 2, 501));
+        assert_eq!(
+            verify_reproducible(&manifest, "ant://frontend", &local),
+            ReproVerdict::Match {
+                hash: published.hash.clone()
+            }
+        );
+
+        // A one-byte change is a mismatch
+        let changed: [(&str, &[u8]); 1] = [("index.html", b"<html>v2</html>")];
+        let local = write(&dir, "changed.tar.gz", &build_archive(&changed, 2, 501));
+        match verify_reproducible(&manifest, "ant://frontend", &local) {
+            ReproVerdict::Mismatch {
+                expected,
+                actual,
+                normalization_notes,
+            } => {
+                assert_eq!(expected, published.hash);
+                assert_ne!(actual, published.hash);
+                assert!(!normalization_notes.is_empty());
+            }
+            verdict => panic!("Expected a mismatch, got {:?}", verdict),
+        }
+
+        let not_comparable = |verdict| matches!(verdict, ReproVerdict::NotComparable { .. });
+        assert!(not_comparable(verify_reproducible(
+            &manifest,
+            "ant://backend",
+            &local
+        )));
+        assert!(not_comparable(verify_reproducible(
+            &manifest,
+            "ant://frontend",
+            &dir.path().join("missing.tar.gz")
+        )));
+        assert!(not_comparable(verify_reproducible(
+            &self::manifest(None),
+            "ant://frontend",
+            &local
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verdict_serialization() {
+        let verdict = ReproVerdict::Mismatch {
+            expected: "a".to_string(),
+            actual: "b".to_string(),
+            normalization_notes: vec![],
+        };
+        assert_eq!(
+            serde_json::to_value(&verdict).unwrap(),
+            serde_json::json!({
+                "verdict": "mismatch",
+                "expected": "a",
+                "actual": "b",
+                "normalizationNotes": [],
+            })
+        );
+    }
+}
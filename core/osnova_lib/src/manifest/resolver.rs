@@ -5,12 +5,14 @@
 //! Supports:
 //! - ant:// URIs (Autonomi Network)
 //! - file:// paths (local development)
-//! - https:// URLs (fallback/testing)
+//! - https:// URLs (fallback/testing), fetched under the
+//!   [`FetchPolicy`](crate::http::FetchPolicy) of the shared fetcher
 
 use super::schema::ManifestSchema;
 use super::validator::validate_manifest_bytes;
 use crate::error::{OsnovaError, Result};
-use crate::network::{AutonomiClient, download_data};
+use crate::http::{self, HttpFetcher, Trust};
+use crate::network::{AutonomiClient, NetworkOptions, download_data};
 
 /// Resolve a manifest from a URI
 ///
@@ -43,22 +45,52 @@ pub async fn resolve_manifest(
     uri: &str,
     client: Option<&AutonomiClient>,
 ) -> Result<ManifestSchema> {
+    resolve_manifest_with(uri, client, &NetworkOptions::default()).await
+}
+
+/// Resolve a manifest from a URI with a timeout and cancellation
+///
+/// Like [`resolve_manifest`], but fetching fails with `OsnovaError::Timeout`
+/// or `OsnovaError::Cancelled` according to `options`.
+pub async fn resolve_manifest_with(
+    uri: &str,
+    client: Option<&AutonomiClient>,
+    options: &NetworkOptions,
+) -> Result<ManifestSchema> {
+    let http = http::shared();
+    let data = options
+        .run_local_first(
+            "manifest resolve",
+            fetch_manifest(uri, client, &http, Trust::Untrusted),
+        )
+        .await?;
+
+    // Validate manifest
+    validate_manifest_bytes(&data)
+}
+
+/// Fetch raw manifest bytes without a timeout
+///
+/// HTTP(S) URIs are fetched with `http`, treating the URI as `trust`.
+pub(super) async fn fetch_manifest(
+    uri: &str,
+    client: Option<&AutonomiClient>,
+    http: &HttpFetcher,
+    trust: Trust,
+) -> Result<Vec<u8>> {
     // Determine source based on URI scheme
-    let data = if uri.starts_with("ant://") {
-        resolve_from_autonomi(uri, client).await?
+    if uri.starts_with("ant://") {
+        resolve_from_autonomi(uri, client).await
     } else if uri.starts_with("file://") {
-        resolve_from_file(uri).await?
+        resolve_from_file(uri).await
     } else if uri.starts_with("https://") || uri.starts_with("http://") {
-        resolve_from_http(uri).await?
+        http.fetch(uri, trust).await
     } else {
-        return Err(OsnovaError::Other(format!(
+        Err(OsnovaError::Other(format!(
             "Unsupported URI scheme: {} (must be ant://, file://, or https://)",
             uri
-        )));
-    };
-
-    // Validate manifest
-    validate_manifest_bytes(&data)
+        )))
+    }
 }
 
 /// Resolve manifest from Autonomi Network
@@ -86,29 +118,6 @@ async fn resolve_from_file(uri: &str) -> Result<Vec<u8>> {
         .map_err(|e| OsnovaError::Storage(format!("Failed to read manifest file: {}", e)))
 }
 
-/// Resolve manifest from HTTP(S) URL
-async fn resolve_from_http(uri: &str) -> Result<Vec<u8>> {
-    // Use reqwest for HTTP fetching
-    let response = reqwest::get(uri)
-        .await
-        .map_err(|e| OsnovaError::Network(format!("HTTP request failed: {}", e)))?;
-
-    if !response.status().is_success() {
-        return Err(OsnovaError::Network(format!(
-            "HTTP error {}: {}",
-            response.status(),
-            response.status().canonical_reason().unwrap_or("Unknown")
-        )));
-    }
-
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| OsnovaError::Network(format!("Failed to read HTTP response: {}", e)))?;
-
-    Ok(bytes.to_vec())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,6 +143,43 @@ mod tests {
             .contains("client required"));
     }
 
+    #[tokio::test]
+    async fn test_resolve_times_out_on_stalled_server() {
+        use std::time::Duration;
+        use tokio::net::TcpListener;
+
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/manifest.json", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let http = HttpFetcher::new(http::FetchPolicy::default().with_private_host("127.0.0.1"));
+        let options = NetworkOptions::default().with_timeout(Duration::from_millis(100));
+        let result = options
+            .run(
+                "manifest resolve",
+                fetch_manifest(&uri, None, &http, Trust::Untrusted),
+            )
+            .await;
+
+        assert!(matches!(result, Err(OsnovaError::Timeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_refuses_private_http_address() {
+        let result = resolve_manifest("http://127.0.0.1:9/manifest.json", None).await;
+
+        assert!(matches!(
+            result,
+            Err(OsnovaError::FetchRefused(http::Refused::PrivateAddress { .. }))
+        ));
+    }
+
     #[tokio::test]
     async fn test_invalid_file_uri() {
         let uri = "file://";
@@ -4,8 +4,25 @@
 //!
 //! Implements the schema defined in docs/06-protocols/manifest-schema.md
 
+use super::condition::{conditional_branches, validate_conditional};
+use crate::components::content_policy::allowed_executables;
+use crate::components::entry::{validate_entry, ENTRY_CONFIG_KEY};
+use crate::components::health::{HealthCheck, HEALTH_CONFIG_KEY};
+use crate::components::network::{NetworkDeclaration, NETWORK_CONFIG_KEY};
+use crate::components::symbols::SYMBOLS_CONFIG_KEY;
+use crate::error::OsnovaError;
+use crate::models::application::{
+    ComponentKind, ComponentRef, OsnovaApplication, Platform, SharedComponentKey,
+};
+use crate::models::feature_flag::{validate_flags, FeatureFlag};
+use crate::models::settings_schema::SettingsSchema;
+use crate::models::sharing::{self, DataOffer};
+use crate::models::signature::{ManifestSignatures, SignaturePolicy};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// URI schemes apps may not claim; Osnova and the platform handle these
+pub const RESERVED_URI_SCHEMES: &[&str] = &["osnova", "ant", "file", "http", "https"];
 
 /// Application manifest schema
 ///
@@ -22,8 +39,16 @@ use std::collections::HashMap;
 ///     description: "My application".to_string(),
 ///     publisher: Some("ACME Corp".to_string()),
 ///     signature: None,
+///     signature_policy: None,
 ///     components: vec![...],
+///     uri_schemes: vec!["mailto".to_string()],
+///     data_offers: vec![],
+///     searchable_config_keys: vec![],
+///     settings_schema: None,
+///     feature_flags: vec![],
 ///     metadata: None,
+///     min_core_version: None,
+///     extra: BTreeMap::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -48,16 +73,71 @@ pub struct ManifestSchema {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub publisher: Option<String>,
 
-    /// Detached signature (optional)
+    /// Detached signatures: a list of co-signatures, or a single signature
+    /// string from before co-signing (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub signature: Option<String>,
+    pub signature: Option<ManifestSignatures>,
+
+    /// Signers required for the manifest to install (optional)
+    #[serde(
+        rename = "signaturePolicy",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub signature_policy: Option<SignaturePolicy>,
 
     /// List of components
     pub components: Vec<ComponentSchema>,
 
+    /// URI schemes the app handles (e.g. "mailto"), so deep links route to it
+    #[serde(rename = "uriSchemes", default, skip_serializing_if = "Vec::is_empty")]
+    pub uri_schemes: Vec<String>,
+
+    /// Data the app exposes to other apps through sharing contracts
+    #[serde(rename = "dataOffers", default, skip_serializing_if = "Vec::is_empty")]
+    pub data_offers: Vec<DataOffer>,
+
+    /// Configuration keys whose values users may search
+    #[serde(
+        rename = "searchableConfigKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub searchable_config_keys: Vec<String>,
+
+    /// Settings the Config screen renders and validates for the app
+    #[serde(
+        rename = "settingsSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub settings_schema: Option<SettingsSchema>,
+
+    /// Flags the app reads to ship features dark and turn them on later
+    #[serde(
+        rename = "featureFlags",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub feature_flags: Vec<FeatureFlag>,
+
     /// Additional metadata (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
+
+    /// Oldest Osnova release (x.y.z) able to run the app (optional)
+    #[serde(
+        rename = "minCoreVersion",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_core_version: Option<String>,
+
+    /// Fields this release does not know, kept so a stored manifest
+    /// re-serializes (and its signature verifies) as published; see
+    /// [`capabilities`](super::capabilities)
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Component schema
@@ -76,6 +156,10 @@ pub struct ManifestSchema {
 ///     version: "1.0.0".to_string(),
 ///     hash: Some("abc123".to_string()),
 ///     config: None,
+///     shared: false,
+///     shared_id: None,
+///     interpreter: None,
+///     extra: BTreeMap::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -105,8 +189,31 @@ pub struct ComponentSchema {
     pub hash: Option<String>,
 
     /// Component configuration (optional)
+    ///
+    /// Values may be conditional (`{"$when": ..., "value": ..., "else": ...}`,
+    /// see [`condition`](super::condition)); they are resolved at install.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<HashMap<String, serde_json::Value>>,
+
+    /// Whether the component is shared with other apps
+    ///
+    /// Shared components are cached and run once per `sharedId` and version,
+    /// however many apps reference them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared: bool,
+
+    /// Stable identifier of a shared component (required when `shared`)
+    #[serde(rename = "sharedId", skip_serializing_if = "Option::is_none")]
+    pub shared_id: Option<String>,
+
+    /// Program that runs a backend artifact that is not a native binary
+    /// (e.g., "python3"); native binaries must match the host and `target`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+
+    /// Fields this release does not know, kept as published
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl ManifestSchema {
@@ -117,6 +224,13 @@ impl ManifestSchema {
     /// - Version follows semver format
     /// - Component kinds are valid
     /// - Platform/target fields are appropriate
+    /// - URI schemes are well-formed and not reserved
+    /// - Data offers are valid and have unique ids
+    /// - The settings schema, if any, is well-formed
+    /// - `minCoreVersion`, if any, follows semver format
+    ///
+    /// Features newer than this release are checked first, by
+    /// [`capabilities`](Self::capabilities).
     ///
     /// # Returns
     ///
@@ -135,6 +249,24 @@ impl ManifestSchema {
             }
         }
 
+        for scheme in &self.uri_schemes {
+            validate_uri_scheme(scheme)?;
+        }
+
+        sharing::validate_offers(&self.data_offers).map_err(|e| e.to_string())?;
+
+        if let Some(schema) = &self.settings_schema {
+            schema.validate().map_err(|e| e.to_string())?;
+        }
+
+        validate_flags(&self.feature_flags).map_err(|e| e.to_string())?;
+
+        if let Some(min) = &self.min_core_version {
+            if !Self::is_valid_semver(min) {
+                return Err(format!("Invalid minCoreVersion format: {}", min));
+            }
+        }
+
         Ok(())
     }
 
@@ -149,9 +281,33 @@ impl ManifestSchema {
     }
 }
 
+/// Validate a URI scheme an app wants to handle
+///
+/// Schemes follow RFC 3986 (a letter, then letters, digits, `+`, `-`, or
+/// `.`) and are compared case-insensitively. Schemes in
+/// [`RESERVED_URI_SCHEMES`] are rejected.
+pub fn validate_uri_scheme(scheme: &str) -> Result<(), String> {
+    let mut chars = scheme.chars();
+    let well_formed = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !well_formed {
+        return Err(format!("Invalid URI scheme: '{}'", scheme));
+    }
+
+    if RESERVED_URI_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+        return Err(format!("URI scheme '{}' is reserved", scheme));
+    }
+
+    Ok(())
+}
+
 impl ComponentSchema {
     /// Validate component against schema rules
     ///
+    /// Shared components must carry a `sharedId` and be content-addressed
+    /// (have a hash), since apps from different publishers reuse the same
+    /// artifact.
+    ///
     /// # Returns
     ///
     /// * `Ok(())` - Component is valid
@@ -182,8 +338,259 @@ impl ComponentSchema {
             }
         }
 
+        // Validate shared component identity
+        let has_shared_id = self.shared_id.as_ref().is_some_and(|id| !id.is_empty());
+        if self.shared {
+            if !has_shared_id {
+                return Err("Shared component requires a sharedId".to_string());
+            }
+            if self.hash.as_ref().is_none_or(|hash| hash.is_empty()) {
+                return Err(
+                    "Shared component must be content-addressed (hash required)".to_string()
+                );
+            }
+        } else if self.shared_id.is_some() {
+            return Err("sharedId is only allowed on shared components".to_string());
+        }
+
+        if let Some(interpreter) = &self.interpreter {
+            if self.kind != "backend" {
+                return Err("interpreter is only allowed on backend components".to_string());
+            }
+            if interpreter.trim().is_empty() {
+                return Err("interpreter must not be empty".to_string());
+            }
+        }
+
+        if let Some(config) = &self.config {
+            let mut keys: Vec<&String> = config.keys().collect();
+            keys.sort();
+            for key in keys {
+                validate_conditional(&config[key])
+                    .map_err(|e| format!("config '{}': {}", key, e))?;
+            }
+        }
+
+        if let Some(entry) = self.config.as_ref().and_then(|c| c.get(ENTRY_CONFIG_KEY)) {
+            if self.kind != "frontend" {
+                return Err("entry is only allowed on frontend components".to_string());
+            }
+            for entry in conditional_branches(entry) {
+                let entry = entry.as_str().ok_or("entry must be a string")?;
+                validate_entry(entry)?;
+            }
+        }
+
+        if let Some(symbols) = self.config.as_ref().and_then(|c| c.get(SYMBOLS_CONFIG_KEY)) {
+            if self.kind != "backend" {
+                return Err("symbols is only allowed on backend components".to_string());
+            }
+            for symbols in conditional_branches(symbols) {
+                if symbols.as_str().is_none_or(|uri| uri.trim().is_empty()) {
+                    return Err("symbols must be a non-empty URI".to_string());
+                }
+            }
+        }
+
+        if let Some(health) = self.config.as_ref().and_then(|c| c.get(HEALTH_CONFIG_KEY)) {
+            if self.kind != "backend" {
+                return Err("health is only allowed on backend components".to_string());
+            }
+            for health in conditional_branches(health) {
+                HealthCheck::from_config(health)?;
+            }
+        }
+
+        if let Some(network) = self.config.as_ref().and_then(|c| c.get(NETWORK_CONFIG_KEY)) {
+            if self.kind != "backend" {
+                return Err("network is only allowed on backend components".to_string());
+            }
+            for network in conditional_branches(network) {
+                NetworkDeclaration::from_config(network)?;
+            }
+        }
+
+        allowed_executables(self)?;
+
         Ok(())
     }
+
+    /// Entry page the component config declares for a frontend, if any
+    pub fn declared_entry(&self) -> Option<&str> {
+        self.config
+            .as_ref()?
+            .get(ENTRY_CONFIG_KEY)
+            .and_then(serde_json::Value::as_str)
+    }
+
+    /// URI of the debug symbols the component config declares for a backend, if any
+    pub fn declared_symbols(&self) -> Option<&str> {
+        self.config
+            .as_ref()?
+            .get(SYMBOLS_CONFIG_KEY)
+            .and_then(serde_json::Value::as_str)
+    }
+
+    /// Health check the component config declares for a backend, if any
+    pub fn declared_health(&self) -> Option<HealthCheck> {
+        HealthCheck::from_config(self.config.as_ref()?.get(HEALTH_CONFIG_KEY)?).ok()
+    }
+
+    /// Network access the component config declares for a backend, if any
+    pub fn declared_network(&self) -> Option<NetworkDeclaration> {
+        NetworkDeclaration::from_config(self.config.as_ref()?.get(NETWORK_CONFIG_KEY)?).ok()
+    }
+
+    /// Get the shared artifact key, if the component is shared
+    pub fn shared_key(&self) -> Option<SharedComponentKey> {
+        if !self.shared {
+            return None;
+        }
+
+        self.shared_id
+            .as_ref()
+            .map(|shared_id| SharedComponentKey::new(shared_id, &self.version))
+    }
+}
+
+impl From<&ComponentRef> for ComponentSchema {
+    fn from(component: &ComponentRef) -> Self {
+        Self {
+            id: component.id().to_string(),
+            name: component.name().to_string(),
+            kind: match component.kind() {
+                ComponentKind::Frontend => "frontend".to_string(),
+                ComponentKind::Backend => "backend".to_string(),
+            },
+            platform: component.platform().map(|platform| {
+                match platform {
+                    Platform::IOS => "iOS",
+                    Platform::Android => "Android",
+                    Platform::Desktop => "desktop",
+                }
+                .to_string()
+            }),
+            target: component.target().map(String::from),
+            version: component.version().to_string(),
+            hash: component.hash().map(String::from),
+            config: component.config().cloned(),
+            shared: component.shared_id().is_some(),
+            shared_id: component.shared_id().map(String::from),
+            interpreter: component.interpreter().map(String::from),
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+impl TryFrom<&ComponentSchema> for ComponentRef {
+    type Error = OsnovaError;
+
+    fn try_from(component: &ComponentSchema) -> Result<Self, Self::Error> {
+        component.validate().map_err(OsnovaError::Other)?;
+
+        let kind = match component.kind.as_str() {
+            "backend" => ComponentKind::Backend,
+            _ => ComponentKind::Frontend,
+        };
+        let mut component_ref =
+            ComponentRef::new(&component.id, &component.name, kind, &component.version)?;
+
+        if let Some(platform) = &component.platform {
+            component_ref = component_ref.with_platform(match platform.as_str() {
+                "iOS" => Platform::IOS,
+                "Android" => Platform::Android,
+                _ => Platform::Desktop,
+            });
+        }
+        if let Some(target) = &component.target {
+            component_ref = component_ref.with_target(target);
+        }
+        if let Some(hash) = &component.hash {
+            component_ref = component_ref.with_hash(hash);
+        }
+        if let Some(config) = &component.config {
+            component_ref = component_ref.with_config(config.clone());
+        }
+        if let Some(shared_id) = component.shared_key().map(|key| key.shared_id) {
+            component_ref = component_ref.with_shared_id(shared_id);
+        }
+        if let Some(interpreter) = &component.interpreter {
+            component_ref = component_ref.with_interpreter(interpreter);
+        }
+
+        Ok(component_ref)
+    }
+}
+
+impl TryFrom<&ManifestSchema> for OsnovaApplication {
+    type Error = OsnovaError;
+
+    /// Build the application record installed from a validated manifest
+    fn try_from(manifest: &ManifestSchema) -> Result<Self, Self::Error> {
+        manifest.validate().map_err(OsnovaError::Other)?;
+
+        let components = manifest
+            .components
+            .iter()
+            .map(ComponentRef::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut app = OsnovaApplication::new(
+            &manifest.id,
+            &manifest.name,
+            &manifest.version,
+            &manifest.icon_uri,
+            &manifest.description,
+            components,
+        )?
+        .with_uri_schemes(&manifest.uri_schemes)
+        .with_data_offers(manifest.data_offers.clone())
+        .with_searchable_config_keys(&manifest.searchable_config_keys)
+        .with_feature_flags(manifest.feature_flags.clone());
+
+        if let Some(publisher) = &manifest.publisher {
+            app = app.with_publisher(publisher);
+        }
+        if let Some(signature) = &manifest.signature {
+            app = app.with_signature(signature.clone());
+        }
+        if let Some(policy) = &manifest.signature_policy {
+            app = app.with_signature_policy(policy.clone());
+        }
+        if let Some(schema) = &manifest.settings_schema {
+            app = app.with_settings_schema(schema.clone());
+        }
+        if let Some(metadata) = &manifest.metadata {
+            app = app.with_metadata(metadata.clone());
+        }
+
+        Ok(app)
+    }
+}
+
+impl From<&OsnovaApplication> for ManifestSchema {
+    /// Rebuild the manifest an installed application record came from
+    fn from(app: &OsnovaApplication) -> Self {
+        Self {
+            id: app.id().to_string(),
+            name: app.name().to_string(),
+            version: app.version().to_string(),
+            icon_uri: app.icon_uri().to_string(),
+            description: app.description().to_string(),
+            publisher: app.publisher().map(str::to_string),
+            signature: app.signature().cloned(),
+            signature_policy: app.signature_policy().cloned(),
+            components: app.components().iter().map(ComponentSchema::from).collect(),
+            uri_schemes: app.uri_schemes().to_vec(),
+            data_offers: app.data_offers().to_vec(),
+            searchable_config_keys: app.searchable_config_keys().to_vec(),
+            settings_schema: app.settings_schema().cloned(),
+            feature_flags: app.feature_flags().to_vec(),
+            metadata: app.metadata().cloned(),
+            min_core_version: None,
+            extra: BTreeMap::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -212,6 +619,10 @@ mod tests {
             version: "1.0.0".to_string(),
             hash: None,
             config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
         };
         assert!(valid_frontend.validate().is_ok());
 
@@ -224,6 +635,10 @@ mod tests {
             version: "1.0.0".to_string(),
             hash: None,
             config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
         };
         assert!(valid_backend.validate().is_ok());
 
@@ -236,7 +651,447 @@ mod tests {
             version: "1.0.0".to_string(),
             hash: None,
             config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
         };
         assert!(invalid_kind.validate().is_err());
     }
+
+    #[test]
+    fn test_component_entry_validation() {
+        let frontend = |entry: serde_json::Value| ComponentSchema {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            kind: "frontend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: Some(HashMap::from([("entry".to_string(), entry)])),
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+
+        let valid = frontend(serde_json::json!("dist/index.html"));
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.declared_entry(), Some("dist/index.html"));
+
+        assert!(frontend(serde_json::json!("/dist/index.html"))
+            .validate()
+            .is_err());
+        assert!(frontend(serde_json::json!("../index.html"))
+            .validate()
+            .is_err());
+        assert!(frontend(serde_json::json!(42)).validate().is_err());
+
+        // Every branch of a conditional entry must be valid
+        assert!(frontend(serde_json::json!({
+            "$when": "platform == 'iOS'",
+            "value": "mobile/index.html",
+            "else": "dist/index.html"
+        }))
+        .validate()
+        .is_ok());
+        assert!(frontend(serde_json::json!({
+            "$when": "platform == 'iOS'",
+            "value": "mobile/index.html",
+            "else": "../index.html"
+        }))
+        .validate()
+        .is_err());
+
+        let backend = ComponentSchema {
+            kind: "backend".to_string(),
+            ..valid
+        };
+        assert!(backend.validate().is_err());
+    }
+
+    #[test]
+    fn test_component_symbols_validation() {
+        let backend = |symbols: serde_json::Value| ComponentSchema {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: Some(HashMap::from([("symbols".to_string(), symbols)])),
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+
+        let valid = backend(serde_json::json!("ant://backend-symbols"));
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.declared_symbols(), Some("ant://backend-symbols"));
+
+        assert!(backend(serde_json::json!("")).validate().is_err());
+        assert!(backend(serde_json::json!(["ant://a"])).validate().is_err());
+
+        let frontend = ComponentSchema {
+            kind: "frontend".to_string(),
+            ..valid
+        };
+        assert!(frontend.validate().is_err());
+    }
+
+    #[test]
+    fn test_component_network_validation() {
+        let backend = |network: serde_json::Value| ComponentSchema {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: Some(HashMap::from([("network".to_string(), network)])),
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+
+        let valid = backend(serde_json::json!({"hosts": ["*.example.com"]}));
+        assert!(valid.validate().is_ok());
+        assert!(valid
+            .declared_network()
+            .unwrap()
+            .allows_host("api.example.com"));
+
+        let error = backend(serde_json::json!({"hosts": ["https://example.com"]}))
+            .validate()
+            .unwrap_err();
+        assert!(error.contains("must be a host name"), "{}", error);
+
+        let frontend = ComponentSchema {
+            kind: "frontend".to_string(),
+            ..valid
+        };
+        assert!(frontend.validate().is_err());
+    }
+
+    #[test]
+    fn test_component_health_validation() {
+        let backend = |health: serde_json::Value| ComponentSchema {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: None,
+            version: "1.0.0".to_string(),
+            hash: None,
+            config: Some(HashMap::from([("health".to_string(), health)])),
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
+        };
+
+        let valid = backend(serde_json::json!({"probe": "tcp", "port": 7000}));
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.declared_health().unwrap().failure_threshold, 3);
+
+        let error = backend(serde_json::json!({"probe": "rpc", "method": "health"}))
+            .validate()
+            .unwrap_err();
+        assert!(error.contains("dotted name"), "{}", error);
+        assert!(backend(serde_json::json!({"probe": "tcp", "port": 0}))
+            .validate()
+            .is_err());
+        // Every branch of a conditional declaration is checked
+        assert!(backend(serde_json::json!({
+            "$when": "platform == 'linux'",
+            "value": {"probe": "socket"},
+            "else": {"probe": "tcp"}
+        }))
+        .validate()
+        .is_err());
+
+        let frontend = ComponentSchema {
+            kind: "frontend".to_string(),
+            ..valid
+        };
+        assert!(frontend.validate().is_err());
+    }
+
+    #[test]
+    fn test_component_schema_from_ref() {
+        let component = ComponentRef::new("ant://ui", "UI", ComponentKind::Frontend, "1.2.3")
+            .unwrap()
+            .with_platform(Platform::Desktop)
+            .with_hash("abc");
+
+        let schema = ComponentSchema::from(&component);
+        assert_eq!(schema.kind, "frontend");
+        assert_eq!(schema.platform.as_deref(), Some("desktop"));
+        assert_eq!(schema.hash.as_deref(), Some("abc"));
+        assert!(schema.validate().is_ok());
+    }
+
+    fn shared_backend(hash: Option<&str>) -> ComponentSchema {
+        ComponentSchema {
+            id: "ant://sync".to_string(),
+            name: "Sync Agent".to_string(),
+            kind: "backend".to_string(),
+            platform: None,
+            target: Some("x86_64-unknown-linux-gnu".to_string()),
+            version: "2.1.0".to_string(),
+            hash: hash.map(String::from),
+            config: None,
+            shared: true,
+            shared_id: Some("org.autonomi.sync-agent".to_string()),
+            interpreter: None,
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_shared_component_requires_hash() {
+        assert!(shared_backend(Some("abc")).validate().is_ok());
+
+        let err = shared_backend(None).validate().unwrap_err();
+        assert!(err.contains("hash required"));
+        assert!(shared_backend(Some("")).validate().is_err());
+    }
+
+    #[test]
+    fn test_shared_component_requires_shared_id() {
+        let mut component = shared_backend(Some("abc"));
+        component.shared_id = None;
+        assert!(component.validate().is_err());
+
+        // sharedId without the shared flag is a manifest mistake
+        let mut component = shared_backend(Some("abc"));
+        component.shared = false;
+        assert!(component.validate().is_err());
+    }
+
+    #[test]
+    fn test_shared_component_serde() {
+        let component = shared_backend(Some("abc"));
+        let json = serde_json::to_value(&component).unwrap();
+        assert_eq!(json["shared"], true);
+        assert_eq!(json["sharedId"], "org.autonomi.sync-agent");
+
+        let parsed: ComponentSchema = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            parsed.shared_key(),
+            Some(SharedComponentKey::new("org.autonomi.sync-agent", "2.1.0"))
+        );
+
+        // Private components omit both fields
+        let private = ComponentSchema::from(
+            &ComponentRef::new("ant://ui", "UI", ComponentKind::Frontend, "1.0.0").unwrap(),
+        );
+        let json = serde_json::to_value(&private).unwrap();
+        assert!(json.get("shared").is_none());
+        assert!(json.get("sharedId").is_none());
+    }
+
+    #[test]
+    fn test_manifest_validation_rejects_unhashed_shared_component() {
+        let manifest = ManifestSchema {
+            id: "ant://app".to_string(),
+            name: "App".to_string(),
+            version: "1.0.0".to_string(),
+            icon_uri: "ant://icon".to_string(),
+            description: "App".to_string(),
+            publisher: None,
+            signature: None,
+            signature_policy: None,
+            components: vec![shared_backend(None)],
+            uri_schemes: Vec::new(),
+            data_offers: Vec::new(),
+            searchable_config_keys: Vec::new(),
+            settings_schema: None,
+            feature_flags: Vec::new(),
+            metadata: None,
+            min_core_version: None,
+            extra: Default::default(),
+        };
+
+        let err = manifest.validate().unwrap_err();
+        assert!(err.starts_with("Component 0:"));
+    }
+
+    #[test]
+    fn test_application_from_manifest() {
+        let manifest = ManifestSchema {
+            id: "ant://app".to_string(),
+            name: "App".to_string(),
+            version: "1.1.0".to_string(),
+            icon_uri: "ant://icon".to_string(),
+            description: "App".to_string(),
+            publisher: Some("ACME".to_string()),
+            signature: None,
+            signature_policy: None,
+            components: vec![shared_backend(Some("abc"))],
+            uri_schemes: vec!["MailTo".to_string()],
+            data_offers: Vec::new(),
+            searchable_config_keys: Vec::new(),
+            settings_schema: None,
+            feature_flags: Vec::new(),
+            metadata: None,
+            min_core_version: None,
+            extra: Default::default(),
+        };
+
+        let app = OsnovaApplication::try_from(&manifest).unwrap();
+        assert_eq!(app.version(), "1.1.0");
+        assert_eq!(app.publisher(), Some("ACME"));
+        assert_eq!(app.uri_schemes(), ["mailto"]);
+        assert_eq!(
+            ComponentSchema::from(&app.components()[0]),
+            shared_backend(Some("abc"))
+        );
+
+        let mut invalid = manifest;
+        invalid.components = vec![shared_backend(None)];
+        assert!(OsnovaApplication::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_data_offer_declaration() {
+        let manifest: ManifestSchema = serde_json::from_value(serde_json::json!({
+            "id": "ant://photos",
+            "name": "Photos",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "Photos",
+            "components": [],
+            "dataOffers": [{
+                "id": "album",
+                "description": "Photos in the shared album",
+                "kind": "image",
+                "pathPrefix": "albums/shared"
+            }]
+        }))
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+
+        let app = OsnovaApplication::try_from(&manifest).unwrap();
+        let offer = app.find_data_offer("album").unwrap();
+        assert_eq!(offer.path_prefix, "albums/shared");
+
+        let mut escaping = manifest.clone();
+        escaping.data_offers[0].path_prefix = "../keys".to_string();
+        assert!(escaping
+            .validate()
+            .unwrap_err()
+            .contains("invalid path prefix"));
+
+        let mut duplicate = manifest;
+        duplicate.data_offers.push(duplicate.data_offers[0].clone());
+        assert!(duplicate.validate().unwrap_err().contains("Duplicate"));
+    }
+
+    #[test]
+    fn test_settings_schema_declaration() {
+        let mut json = serde_json::json!({
+            "id": "ant://reader",
+            "name": "Reader",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "Reader",
+            "components": [],
+            "settingsSchema": {"sections": [{
+                "id": "display",
+                "label": "Display",
+                "fields": [{"key": "fontSize", "label": "Font size", "type": "number",
+                            "min": 8, "max": 32, "default": 14}]
+            }]}
+        });
+        let manifest: ManifestSchema = serde_json::from_value(json.clone()).unwrap();
+        assert!(manifest.validate().is_ok());
+
+        // Stored with the app and rebuilt from it
+        let app = OsnovaApplication::try_from(&manifest).unwrap();
+        assert!(app.settings_schema().unwrap().field("fontSize").is_some());
+        assert_eq!(ManifestSchema::from(&app), manifest);
+
+        json["settingsSchema"]["sections"][0]["fields"][0]["default"] = serde_json::json!(40);
+        let invalid: ManifestSchema = serde_json::from_value(json).unwrap();
+        assert!(invalid
+            .validate()
+            .unwrap_err()
+            .contains("field 'fontSize' default must be between 8 and 32"));
+        assert!(OsnovaApplication::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_feature_flag_declaration() {
+        let mut json = serde_json::json!({
+            "id": "ant://mail",
+            "name": "Mail",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "Mail",
+            "components": [],
+            "featureFlags": [
+                {"name": "newComposer", "description": "Rich text composer", "default": false},
+                {"name": "threads", "default": true}
+            ]
+        });
+        let manifest: ManifestSchema = serde_json::from_value(json.clone()).unwrap();
+        assert!(manifest.validate().is_ok());
+
+        let app = OsnovaApplication::try_from(&manifest).unwrap();
+        assert_eq!(app.feature_flags().len(), 2);
+        assert!(app.feature_flags()[1].default);
+        assert_eq!(ManifestSchema::from(&app), manifest);
+
+        json["featureFlags"][1]["name"] = serde_json::json!("newComposer");
+        let duplicate: ManifestSchema = serde_json::from_value(json.clone()).unwrap();
+        assert!(duplicate
+            .validate()
+            .unwrap_err()
+            .contains("duplicate flag 'newComposer'"));
+
+        json["featureFlags"][1]["name"] = serde_json::json!("new composer");
+        let invalid: ManifestSchema = serde_json::from_value(json).unwrap();
+        assert!(invalid
+            .validate()
+            .unwrap_err()
+            .contains("invalid flag name"));
+        assert!(OsnovaApplication::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_uri_scheme_validation() {
+        assert!(validate_uri_scheme("mailto").is_ok());
+        assert!(validate_uri_scheme("web+ethereum").is_ok());
+
+        for reserved in ["osnova", "ant", "file", "http", "HTTPS"] {
+            assert!(validate_uri_scheme(reserved)
+                .unwrap_err()
+                .contains("reserved"));
+        }
+        assert!(validate_uri_scheme("").is_err());
+        assert!(validate_uri_scheme("1mail").is_err());
+        assert!(validate_uri_scheme("mail to").is_err());
+
+        let manifest: ManifestSchema = serde_json::from_value(serde_json::json!({
+            "id": "ant://mail",
+            "name": "Mail",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "Mail",
+            "components": [],
+            "uriSchemes": ["mailto", "ant"]
+        }))
+        .unwrap();
+        assert_eq!(manifest.uri_schemes, vec!["mailto", "ant"]);
+        assert!(manifest
+            .validate()
+            .unwrap_err()
+            .contains("'ant' is reserved"));
+    }
 }
@@ -0,0 +1,278 @@
+//! # Manifest Signing
+//!
+//! Co-signing and signature policy checks for application manifests.
+//!
+//! Signatures cover the manifest without its whole signature set, in
+//! canonical JSON, so co-signers can sign one after another, in any order,
+//! without invalidating each other. The signature policy is part of the
+//! payload, so it cannot be changed without every signer signing again.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::manifest::{add_signature, publish_manifest};
+//!
+//! let manifest = add_signature(&manifest, &developer_key, "developer")?;
+//! // Handed to the release manager, who signs the same manifest JSON
+//! let manifest = add_signature(&manifest, &release_key, "release-manager")?;
+//! let uri = publish_manifest(&client, &manifest).await?;
+//! ```
+
+use ed25519_dalek::SigningKey;
+
+use crate::error::{OsnovaError, Result};
+use crate::manifest::ManifestSchema;
+use crate::models::signature::{ManifestSignature, ManifestSignatures, SignaturePolicy};
+use crate::network::{upload_data_for, AutonomiClient, NetworkOptions, UploadContext};
+use crate::util::canonical_json;
+
+impl ManifestSchema {
+    /// Canonical bytes covered by the signatures
+    ///
+    /// The manifest without its signature set, in canonical JSON so the
+    /// payload does not depend on field order in the published JSON.
+    pub fn signing_payload(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        canonical_json::to_canonical_vec(&unsigned)
+    }
+
+    /// Verify every co-signature the manifest carries
+    ///
+    /// Returns the co-signatures, all of which verified. A single signature
+    /// from before co-signing names no key and is not checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if any co-signature does not verify
+    pub fn verify_signatures(&self) -> Result<&[ManifestSignature]> {
+        let Some(signatures) = &self.signature else {
+            return Ok(&[]);
+        };
+        let payload = self.signing_payload()?;
+        for signature in signatures.co_signatures() {
+            signature.verify(&payload)?;
+        }
+        Ok(signatures.co_signatures())
+    }
+}
+
+/// Return a copy of a manifest with one more co-signature
+///
+/// Earlier co-signatures are kept; signing again with the same key
+/// replaces that key's signature.
+///
+/// # Errors
+///
+/// Returns an error if the manifest carries a signature from before
+/// co-signing, which cannot be kept alongside co-signatures, or an earlier
+/// co-signature does not verify
+pub fn add_signature(
+    manifest: &ManifestSchema,
+    signing_key: &SigningKey,
+    role: &str,
+) -> Result<ManifestSchema> {
+    if let Some(ManifestSignatures::Detached(_)) = &manifest.signature {
+        return Err(OsnovaError::Other(
+            "Manifest carries a single-signature field; remove it before co-signing".to_string(),
+        ));
+    }
+    let earlier = manifest.verify_signatures()?;
+
+    let signature = ManifestSignature::sign(&manifest.signing_payload()?, signing_key, role);
+    let mut signatures: Vec<_> = earlier
+        .iter()
+        .filter(|existing| existing.public_key != signature.public_key)
+        .cloned()
+        .collect();
+    signatures.push(signature);
+
+    Ok(ManifestSchema {
+        signature: Some(ManifestSignatures::CoSigned(signatures)),
+        ..manifest.clone()
+    })
+}
+
+/// Check a manifest's signatures against the policies it must meet
+///
+/// Every co-signature must verify. The manifest must then satisfy its own
+/// policy and, for an update, the policy of the installed version, so a new
+/// version cannot drop or weaken the policy it was installed under. With
+/// `require_policy` (strict mode), a manifest that no policy applies to is
+/// refused.
+///
+/// # Errors
+///
+/// Returns an error if a signature does not verify, a policy is not met,
+/// or no policy applies in strict mode
+pub fn check_signature_policy(
+    manifest: &ManifestSchema,
+    installed_policy: Option<&SignaturePolicy>,
+    require_policy: bool,
+) -> Result<()> {
+    let verified = manifest.verify_signatures()?;
+
+    let policies: Vec<_> = manifest
+        .signature_policy
+        .iter()
+        .chain(
+            installed_policy
+                .filter(|installed| Some(*installed) != manifest.signature_policy.as_ref()),
+        )
+        .collect();
+    if policies.is_empty() && require_policy {
+        return Err(OsnovaError::PermissionDenied(format!(
+            "{} declares no signature policy, which strict mode requires",
+            manifest.id
+        )));
+    }
+    for policy in policies {
+        policy.check(verified)?;
+    }
+    Ok(())
+}
+
+/// Check and upload a signed manifest
+///
+/// Only the manifest is uploaded; the components it lists are already
+/// published, so co-signers never re-upload them.
+///
+/// # Returns
+///
+/// The ant:// URI of the published manifest
+///
+/// # Errors
+///
+/// Returns an error if the manifest is invalid, does not meet its own
+/// signature policy, or the upload fails
+pub async fn publish_manifest(
+    client: &AutonomiClient,
+    manifest: &ManifestSchema,
+) -> Result<String> {
+    manifest.validate().map_err(OsnovaError::Other)?;
+    check_signature_policy(manifest, None, false)?;
+    let context = UploadContext::for_component(&manifest.id);
+    let receipt = upload_data_for(
+        client,
+        &serde_json::to_vec_pretty(manifest)?,
+        &context,
+        &NetworkOptions::default(),
+    )
+    .await?;
+    Ok(receipt.address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public_key(seed: u8) -> String {
+        base64::engine::general_purpose::STANDARD.encode(key(seed).verifying_key().to_bytes())
+    }
+
+    fn manifest(policy: Option<SignaturePolicy>) -> ManifestSchema {
+        serde_json::from_value(serde_json::json!({
+            "id": "ant://manifest",
+            "name": "Wallet",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "A wallet",
+            "components": [],
+            "signaturePolicy": policy,
+        }))
+        .unwrap()
+    }
+
+    fn two_of_three() -> SignaturePolicy {
+        SignaturePolicy {
+            required: 2,
+            allowed_signers: vec![public_key(1), public_key(2), public_key(3)],
+        }
+    }
+
+    #[test]
+    fn test_two_of_three_policy() -> Result<()> {
+        let unsigned = manifest(Some(two_of_three()));
+        let once = add_signature(&unsigned, &key(1), "developer")?;
+        assert!(check_signature_policy(&once, None, false).is_err());
+
+        let twice = add_signature(&once, &key(3), "release-manager")?;
+        check_signature_policy(&twice, None, false)?;
+
+        // Signing again with the same key does not count twice
+        let repeated = add_signature(&once, &key(1), "developer")?;
+        assert_eq!(repeated.verify_signatures()?.len(), 1);
+        assert!(check_signature_policy(&repeated, None, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signer_outside_allowlist_is_rejected() -> Result<()> {
+        let signed = add_signature(&manifest(Some(two_of_three())), &key(1), "developer")?;
+        let signed = add_signature(&signed, &key(9), "intruder")?;
+
+        let error = check_signature_policy(&signed, None, false).unwrap_err();
+        assert!(error.to_string().contains("intruder"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequential_cosigning_keeps_earlier_signatures() -> Result<()> {
+        let signed = add_signature(&manifest(Some(two_of_three())), &key(2), "developer")?;
+        // The second signer works from the published JSON, not the struct
+        let json = serde_json::to_string(&signed)?;
+        let received: ManifestSchema = serde_json::from_str(&json)?;
+        let signed = add_signature(&received, &key(1), "release-manager")?;
+
+        let roles: Vec<_> = signed
+            .verify_signatures()?
+            .iter()
+            .map(|signature| signature.role.as_str())
+            .collect();
+        assert_eq!(roles, vec!["developer", "release-manager"]);
+
+        // Changing the manifest, including its policy, breaks every signature
+        let mut tampered = signed.clone();
+        tampered.signature_policy = None;
+        assert!(tampered.verify_signatures().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_signature_format_still_parses() -> Result<()> {
+        let mut legacy = manifest(None);
+        legacy.signature = Some("bGVnYWN5".into());
+        let json = serde_json::to_value(&legacy)?;
+        assert_eq!(json["signature"], "bGVnYWN5");
+        let parsed: ManifestSchema = serde_json::from_value(json)?;
+        assert_eq!(parsed, legacy);
+
+        // Accepted without a policy, as before, but it names no signer
+        check_signature_policy(&parsed, None, false)?;
+        assert!(check_signature_policy(&parsed, None, true).is_err());
+        assert!(check_signature_policy(&parsed, Some(&two_of_three()), false).is_err());
+        assert!(add_signature(&parsed, &key(1), "developer").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_is_held_to_installed_policy() -> Result<()> {
+        // A new version that drops the policy still needs the old signers
+        let unsigned = manifest(None);
+        let signed = add_signature(&unsigned, &key(9), "developer")?;
+        check_signature_policy(&signed, None, false)?;
+        assert!(check_signature_policy(&signed, Some(&two_of_three()), false).is_err());
+
+        let cosigned = add_signature(&signed, &key(2), "developer")?;
+        let cosigned = add_signature(&cosigned, &key(3), "release-manager")?;
+        check_signature_policy(&cosigned, Some(&two_of_three()), true)?;
+        Ok(())
+    }
+}
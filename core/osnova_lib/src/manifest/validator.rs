@@ -7,7 +7,10 @@ use crate::error::{OsnovaError, Result};
 
 /// Validate a manifest from JSON string
 ///
-/// Parses JSON and validates against the manifest schema.
+/// Parses JSON and validates against the manifest schema. Fields this
+/// release does not know are kept; features it cannot install fail first,
+/// with one error naming all of them (see
+/// [`capabilities`](super::capabilities)).
 ///
 /// # Arguments
 ///
@@ -16,7 +19,8 @@ use crate::error::{OsnovaError, Result};
 /// # Returns
 ///
 /// * `Ok(ManifestSchema)` - Valid manifest
-/// * `Err(OsnovaError)` - Validation or parsing error
+/// * `Err(OsnovaError)` - Validation or parsing error, or
+///   [`OsnovaError::RequiresNewerCore`]
 ///
 /// # Example
 ///
@@ -40,6 +44,9 @@ pub fn validate_manifest(json: &str) -> Result<ManifestSchema> {
     let manifest: ManifestSchema = serde_json::from_str(json)
         .map_err(|e| OsnovaError::Other(format!("Failed to parse manifest JSON: {}", e)))?;
 
+    // Newer features first, so they read as such rather than as invalid
+    manifest.capabilities().check()?;
+
     // Validate against schema rules
     manifest
         .validate()
@@ -126,6 +133,45 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_manifest_checks_conditions() {
+        let manifest = |theme: serde_json::Value| {
+            serde_json::json!({
+                "id": "ant://test",
+                "name": "Test App",
+                "version": "1.0.0",
+                "iconUri": "ant://icon",
+                "description": "Test",
+                "components": [{
+                    "id": "ant://frontend",
+                    "name": "Frontend",
+                    "kind": "frontend",
+                    "version": "1.0.0",
+                    "config": {"theme": theme}
+                }]
+            })
+            .to_string()
+        };
+
+        let valid = json_theme("platform == 'iOS'");
+        assert!(validate_manifest(&manifest(valid)).is_ok());
+
+        let error = validate_manifest(&manifest(json_theme("platform == 'iOS' && os == 'x'")))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Component 0: config 'theme': $when: unknown identifier 'os'"));
+        assert!(error.contains("at position 21"));
+
+        let error = validate_manifest(&manifest(json_theme("platform == ")))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("expected a value but found end of expression at position 12"));
+    }
+
+    fn json_theme(when: &str) -> serde_json::Value {
+        serde_json::json!({"$when": when, "value": "cupertino", "else": "material"})
+    }
+
     #[test]
     fn test_validate_manifest_bytes_invalid_utf8() {
         let invalid_utf8 = vec![0xFF, 0xFE, 0xFD];
@@ -0,0 +1,96 @@
+//! Annotation models for Osnova
+//!
+//! Private notes an admin attaches to installed apps and paired devices
+//! ("approved for kids", "pinned because v2 breaks plugin X"), kept by the
+//! [`AnnotationService`](crate::services::AnnotationService).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What an annotation is attached to
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(tag = "kind", content = "id", rename_all = "camelCase")]
+pub enum Subject {
+    /// An installed application, by app ID
+    App(String),
+    /// A paired device, by device ID
+    Device(String),
+}
+
+impl Subject {
+    /// Stored name of the subject's kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Subject::App(_) => "app",
+            Subject::Device(_) => "device",
+        }
+    }
+
+    /// App or device ID
+    pub fn id(&self) -> &str {
+        match self {
+            Subject::App(id) | Subject::Device(id) => id,
+        }
+    }
+
+    /// Subject from its stored kind and ID; `None` for an unknown kind
+    pub fn from_parts(kind: &str, id: &str) -> Option<Self> {
+        match kind {
+            "app" => Some(Subject::App(id.to_string())),
+            "device" => Some(Subject::Device(id.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.id())
+    }
+}
+
+/// A note attached to an app or device
+///
+/// The text is plain text, safe to render as markdown; it never holds
+/// HTML.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    /// What the note is about
+    pub subject: Subject,
+    /// Note text
+    pub text: String,
+    /// Unix timestamp of the last change
+    pub updated_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_parts_round_trip() {
+        for subject in [
+            Subject::App("com.example.app".to_string()),
+            Subject::Device("device-1".to_string()),
+        ] {
+            assert_eq!(
+                Subject::from_parts(subject.kind(), subject.id()),
+                Some(subject.clone())
+            );
+        }
+        assert_eq!(Subject::from_parts("user", "x"), None);
+    }
+
+    #[test]
+    fn test_subject_serialization() {
+        let json = serde_json::to_value(Subject::Device("device-1".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "kind": "device", "id": "device-1" })
+        );
+    }
+}
@@ -24,9 +24,14 @@
 //! ).unwrap();
 //! ```
 
+use crate::models::feature_flag::FeatureFlag;
+use crate::models::launcher_layout::OrderKey;
+use crate::models::settings_schema::SettingsSchema;
+use crate::models::sharing::DataOffer;
+use crate::models::signature::{ManifestSignatures, SignaturePolicy};
 use crate::{OsnovaError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Component kind (frontend or backend)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -51,6 +56,34 @@ pub enum Platform {
     Desktop,
 }
 
+/// Identity of a shared component artifact
+///
+/// Apps referencing the same `shared_id` and version share one cached
+/// artifact and, for backends, one running process.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SharedComponentKey {
+    /// Stable identifier shared across app manifests
+    pub shared_id: String,
+    /// Component version (semver)
+    pub version: String,
+}
+
+impl SharedComponentKey {
+    /// Create a shared component key
+    pub fn new(shared_id: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            shared_id: shared_id.into(),
+            version: version.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for SharedComponentKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.shared_id, self.version)
+    }
+}
+
 /// Component reference within an application
 ///
 /// Each component is identified by its content address and has a specific kind
@@ -84,6 +117,18 @@ pub struct ComponentRef {
     /// Component configuration
     #[serde(skip_serializing_if = "Option::is_none")]
     config: Option<HashMap<String, serde_json::Value>>,
+
+    /// Shared component identifier (None for app-private components)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shared_id: Option<String>,
+
+    /// Program that runs a backend artifact that is not a native binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interpreter: Option<String>,
+
+    /// Entry page of an installed frontend, relative to the bundle root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
 }
 
 impl ComponentRef {
@@ -126,6 +171,9 @@ impl ComponentRef {
             platform: None,
             hash: None,
             config: None,
+            shared_id: None,
+            interpreter: None,
+            entry: None,
         })
     }
 
@@ -153,6 +201,24 @@ impl ComponentRef {
         self
     }
 
+    /// Mark the component as shared across apps under `shared_id`
+    pub fn with_shared_id(mut self, shared_id: impl Into<String>) -> Self {
+        self.shared_id = Some(shared_id.into());
+        self
+    }
+
+    /// Run the backend artifact with an interpreter instead of executing it
+    pub fn with_interpreter(mut self, interpreter: impl Into<String>) -> Self {
+        self.interpreter = Some(interpreter.into());
+        self
+    }
+
+    /// Record the entry page resolved when the frontend was installed
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Self {
+        self.entry = Some(entry.into());
+        self
+    }
+
     /// Get the component ID
     pub fn id(&self) -> &str {
         &self.id
@@ -193,6 +259,28 @@ impl ComponentRef {
         self.config.as_ref()
     }
 
+    /// Get the shared component identifier
+    pub fn shared_id(&self) -> Option<&str> {
+        self.shared_id.as_deref()
+    }
+
+    /// Get the interpreter that runs the backend artifact
+    pub fn interpreter(&self) -> Option<&str> {
+        self.interpreter.as_deref()
+    }
+
+    /// Get the entry page resolved at install (for frontend components)
+    pub fn entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    /// Get the shared artifact key, if the component is shared
+    pub fn shared_key(&self) -> Option<SharedComponentKey> {
+        self.shared_id
+            .as_ref()
+            .map(|shared_id| SharedComponentKey::new(shared_id, &self.version))
+    }
+
     /// Validate semver version string
     fn validate_version(version: &str) -> Result<()> {
         let parts: Vec<&str> = version.split('.').collect();
@@ -240,13 +328,37 @@ pub struct OsnovaApplication {
     #[serde(skip_serializing_if = "Option::is_none")]
     publisher: Option<String>,
 
-    /// Detached signature over canonical manifest
+    /// Detached signatures over canonical manifest
     #[serde(skip_serializing_if = "Option::is_none")]
-    signature: Option<String>,
+    signature: Option<ManifestSignatures>,
+
+    /// Signers the manifest requires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature_policy: Option<SignaturePolicy>,
 
     /// Application components
     components: Vec<ComponentRef>,
 
+    /// URI schemes the application handles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    uri_schemes: Vec<String>,
+
+    /// Data the application offers to other apps
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    data_offers: Vec<DataOffer>,
+
+    /// Configuration keys whose values users may search
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    searchable_config_keys: Vec<String>,
+
+    /// Settings the Config screen renders and validates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings_schema: Option<SettingsSchema>,
+
+    /// Feature flags the application declares
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    feature_flags: Vec<FeatureFlag>,
+
     /// Additional metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     metadata: Option<HashMap<String, serde_json::Value>>,
@@ -299,7 +411,13 @@ impl OsnovaApplication {
             description: description.into(),
             publisher: None,
             signature: None,
+            signature_policy: None,
             components,
+            uri_schemes: Vec::new(),
+            data_offers: Vec::new(),
+            searchable_config_keys: Vec::new(),
+            settings_schema: None,
+            feature_flags: Vec::new(),
             metadata: None,
         })
     }
@@ -310,18 +428,57 @@ impl OsnovaApplication {
         self
     }
 
-    /// Set the signature
-    pub fn with_signature(mut self, signature: impl Into<String>) -> Self {
+    /// Set the signatures
+    pub fn with_signature(mut self, signature: impl Into<ManifestSignatures>) -> Self {
         self.signature = Some(signature.into());
         self
     }
 
+    /// Set the signers the manifest requires
+    pub fn with_signature_policy(mut self, policy: SignaturePolicy) -> Self {
+        self.signature_policy = Some(policy);
+        self
+    }
+
+    /// Set the URI schemes the application handles
+    ///
+    /// Schemes are stored lowercase.
+    pub fn with_uri_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.uri_schemes = schemes
+            .into_iter()
+            .map(|scheme| scheme.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
     /// Set the metadata
     pub fn with_metadata(mut self, metadata: HashMap<String, serde_json::Value>) -> Self {
         self.metadata = Some(metadata);
         self
     }
 
+    /// Set the application name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the icon URI
+    pub fn with_icon_uri(mut self, icon_uri: impl Into<String>) -> Self {
+        self.icon_uri = icon_uri.into();
+        self
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
     /// Get the application ID
     pub fn id(&self) -> &str {
         &self.id
@@ -352,9 +509,14 @@ impl OsnovaApplication {
         self.publisher.as_deref()
     }
 
-    /// Get the signature
-    pub fn signature(&self) -> Option<&str> {
-        self.signature.as_deref()
+    /// Get the signatures
+    pub fn signature(&self) -> Option<&ManifestSignatures> {
+        self.signature.as_ref()
+    }
+
+    /// Get the signers the manifest requires
+    pub fn signature_policy(&self) -> Option<&SignaturePolicy> {
+        self.signature_policy.as_ref()
     }
 
     /// Get the components
@@ -362,6 +524,69 @@ impl OsnovaApplication {
         &self.components
     }
 
+    /// Get the components for updating install-time state
+    pub fn components_mut(&mut self) -> &mut [ComponentRef] {
+        &mut self.components
+    }
+
+    /// Get the URI schemes the application handles
+    pub fn uri_schemes(&self) -> &[String] {
+        &self.uri_schemes
+    }
+
+    /// Set the data the application offers to other apps
+    pub fn with_data_offers(mut self, offers: Vec<DataOffer>) -> Self {
+        self.data_offers = offers;
+        self
+    }
+
+    /// Get the data the application offers to other apps
+    pub fn data_offers(&self) -> &[DataOffer] {
+        &self.data_offers
+    }
+
+    /// Set the configuration keys whose values users may search
+    pub fn with_searchable_config_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.searchable_config_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Get the configuration keys whose values users may search
+    pub fn searchable_config_keys(&self) -> &[String] {
+        &self.searchable_config_keys
+    }
+
+    /// Set the settings the Config screen renders and validates
+    pub fn with_settings_schema(mut self, schema: SettingsSchema) -> Self {
+        self.settings_schema = Some(schema);
+        self
+    }
+
+    /// Get the settings the Config screen renders and validates
+    pub fn settings_schema(&self) -> Option<&SettingsSchema> {
+        self.settings_schema.as_ref()
+    }
+
+    /// Set the feature flags the application declares
+    pub fn with_feature_flags(mut self, flags: Vec<FeatureFlag>) -> Self {
+        self.feature_flags = flags;
+        self
+    }
+
+    /// Get the feature flags the application declares
+    pub fn feature_flags(&self) -> &[FeatureFlag] {
+        &self.feature_flags
+    }
+
+    /// Find a data offer by ID
+    pub fn find_data_offer(&self, offer_id: &str) -> Option<&DataOffer> {
+        self.data_offers.iter().find(|offer| offer.id == offer_id)
+    }
+
     /// Get the metadata
     pub fn metadata(&self) -> Option<&HashMap<String, serde_json::Value>> {
         self.metadata.as_ref()
@@ -384,12 +609,113 @@ impl OsnovaApplication {
     pub fn find_component(&self, id: &str) -> Option<&ComponentRef> {
         self.components.iter().find(|c| c.id() == id)
     }
+
+    /// Get the configuration keys the manifest declares as sensitive
+    ///
+    /// Read from the `sensitiveConfigKeys` metadata entry. A trailing `*`
+    /// matches any key with that prefix. Sensitive keys never leave the
+    /// device in shared presets.
+    pub fn sensitive_config_keys(&self) -> Vec<String> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("sensitiveConfigKeys"))
+            .and_then(|v| v.as_array())
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|k| k.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// An uninstalled application held in the recently-deleted (trash) state
+///
+/// Trashed applications keep their configuration and keys until `purge_after`
+/// has passed, at which point they are permanently removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashedApplication {
+    /// The application as it was installed
+    application: OsnovaApplication,
+
+    /// Unix timestamp when the application was moved to the trash
+    deleted_at: u64,
+
+    /// Unix timestamp after which the application may be purged
+    purge_after: u64,
+
+    /// Where the app was on each user's launcher grid, by user ID
+    #[serde(default)]
+    launcher_positions: BTreeMap<String, OrderKey>,
+}
+
+impl TrashedApplication {
+    /// Create a new trashed application record
+    pub fn new(application: OsnovaApplication, deleted_at: u64, purge_after: u64) -> Self {
+        Self {
+            application,
+            deleted_at,
+            purge_after,
+            launcher_positions: BTreeMap::new(),
+        }
+    }
+
+    /// Set where the app was on each user's launcher grid
+    pub fn with_launcher_positions(mut self, positions: BTreeMap<String, OrderKey>) -> Self {
+        self.launcher_positions = positions;
+        self
+    }
+
+    /// Get the trashed application
+    pub fn application(&self) -> &OsnovaApplication {
+        &self.application
+    }
+
+    /// Get the deletion timestamp
+    pub fn deleted_at(&self) -> u64 {
+        self.deleted_at
+    }
+
+    /// Get the timestamp after which the application may be purged
+    pub fn purge_after(&self) -> u64 {
+        self.purge_after
+    }
+
+    /// Get where the app was on each user's launcher grid, by user ID
+    pub fn launcher_positions(&self) -> &BTreeMap<String, OrderKey> {
+        &self.launcher_positions
+    }
+
+    /// Check whether the retention window has elapsed at `now`
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.purge_after
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_shared_component_ref() {
+        let component = ComponentRef::new("ant://sync", "Sync", ComponentKind::Backend, "2.1.0")
+            .unwrap()
+            .with_shared_id("org.autonomi.sync-agent");
+
+        let key = component.shared_key().unwrap();
+        assert_eq!(
+            key,
+            SharedComponentKey::new("org.autonomi.sync-agent", "2.1.0")
+        );
+        assert_eq!(key.to_string(), "org.autonomi.sync-agent@2.1.0");
+
+        // Components stored before sharing existed deserialize as private
+        let mut json = serde_json::to_value(&component).unwrap();
+        json.as_object_mut().unwrap().remove("shared_id");
+        let legacy: ComponentRef = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.shared_key(), None);
+    }
+
     #[test]
     fn test_component_kind_serialization() {
         let frontend = ComponentKind::Frontend;
@@ -529,7 +855,7 @@ mod tests {
         .unwrap()
         .with_signature("signature-data");
 
-        assert_eq!(app.signature(), Some("signature-data"));
+        assert_eq!(app.signature(), Some(&"signature-data".into()));
     }
 
     #[test]
@@ -617,6 +943,20 @@ mod tests {
         assert!(app.find_component("non-existent").is_none());
     }
 
+    #[test]
+    fn test_osnova_application_sensitive_config_keys() {
+        let app = OsnovaApplication::new("app", "App", "1.0.0", "icon", "desc", vec![]).unwrap();
+        assert!(app.sensitive_config_keys().is_empty());
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "sensitiveConfigKeys".to_string(),
+            serde_json::json!(["apiToken", "wallet.*"]),
+        );
+        let app = app.with_metadata(metadata);
+        assert_eq!(app.sensitive_config_keys(), vec!["apiToken", "wallet.*"]);
+    }
+
     #[test]
     fn test_osnova_application_serialization() {
         let component = ComponentRef::new("comp-id", "Component", ComponentKind::Frontend, "1.0.0")
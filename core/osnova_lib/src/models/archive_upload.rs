@@ -0,0 +1,42 @@
+//! Archive upload models for Osnova
+//!
+//! A large archive is uploaded in batches, each paid for separately. What
+//! was paid for and what was stored is recorded as it happens, so an
+//! interrupted upload resumes without paying for a batch twice or storing
+//! a file again.
+//!
+//! Costs are in AttoTokens (10^-18 ANT).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A batch of an archive upload, once its payment was made
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBatchRecord {
+    /// Position of the batch in the upload; the index follows the last
+    /// batch of files
+    pub batch: usize,
+    /// Wallet payment request that paid for the batch; `None` when every
+    /// file in it was already stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_request_id: Option<String>,
+    /// Cost quoted and paid for, in AttoTokens
+    pub quoted_cost: u64,
+    /// Cost of the files stored so far, in AttoTokens
+    pub actual_cost: u64,
+    /// Whether every file of the batch is stored
+    pub completed: bool,
+}
+
+/// A file stored as part of an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+    /// Name of the file within the archive
+    pub name: String,
+    /// ant:// address the contents are stored at
+    pub address: String,
+    /// Size of the contents in bytes
+    pub size: u64,
+}
@@ -0,0 +1,249 @@
+//! Backend process registry models for Osnova
+//!
+//! This module provides the BackendProcess type which records:
+//! - Which application a spawned backend component process belongs to
+//! - The pid together with the process start time and command line, so a
+//!   recycled pid is never mistaken for one of our processes
+//! - The socket path the backend listens on, for cleanup after a crash
+//! - The component and version the process runs, for crash reports
+//! - Whether the process acts for a single app or a shared component
+//! - Whether the process serves an open app or waits idle in the warm pool
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::models::backend_process::BackendProcess;
+//!
+//! let record = BackendProcess::new("com.osnova.wallet", pid, start_time, command_line, now)
+//!     .with_socket_path("/tmp/osnova-wallet.sock");
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Registry owner prefix marking shared component processes
+const SHARED_OWNER_PREFIX: &str = "shared:";
+
+/// Principal a backend process acts on behalf of
+///
+/// Calls from a shared component are attributed to its `sharedId`, never to
+/// one of the apps that happen to use it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "lowercase")]
+pub enum ComponentOwner {
+    /// Backend private to one application
+    App(String),
+    /// Shared component, identified by its `sharedId`
+    Shared(String),
+}
+
+impl ComponentOwner {
+    /// Owner string stored in the process registry
+    pub fn registry_id(&self) -> String {
+        match self {
+            Self::App(app_id) => app_id.clone(),
+            Self::Shared(shared_id) => format!("{}{}", SHARED_OWNER_PREFIX, shared_id),
+        }
+    }
+
+    /// Parse an owner string stored in the process registry
+    pub fn from_registry_id(registry_id: &str) -> Self {
+        match registry_id.strip_prefix(SHARED_OWNER_PREFIX) {
+            Some(shared_id) => Self::Shared(shared_id.to_string()),
+            None => Self::App(registry_id.to_string()),
+        }
+    }
+}
+
+/// Whether a backend process serves an open app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessState {
+    /// The app's UI is open, or the process was never idle
+    Active,
+    /// The app's UI closed and the process is kept warm for the next launch
+    Idle,
+}
+
+/// Registry entry for a spawned backend component process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendProcess {
+    /// Application the backend belongs to
+    app_id: String,
+
+    /// Operating system process id
+    pid: u32,
+
+    /// Platform-specific process start time captured at spawn
+    start_time: String,
+
+    /// Process command line captured at spawn
+    command_line: String,
+
+    /// Socket the backend listens on, if any
+    socket_path: Option<PathBuf>,
+
+    /// Component the process runs, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    component_id: Option<String>,
+
+    /// Version of the component the process runs, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    component_version: Option<String>,
+
+    /// Unix timestamp when the process was registered
+    registered_at: u64,
+
+    /// Unix timestamp when the app's UI closed, while the process is idle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_since: Option<u64>,
+
+    /// Launches that reused the process instead of spawning a new one
+    #[serde(default)]
+    warm_starts: u32,
+}
+
+impl BackendProcess {
+    /// Create a new registry entry
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application the backend belongs to
+    /// * `pid` - Process id
+    /// * `start_time` - Process start time as reported by the platform
+    /// * `command_line` - Process command line as reported by the platform
+    /// * `registered_at` - Unix time the process was spawned
+    pub fn new(
+        app_id: impl Into<String>,
+        pid: u32,
+        start_time: impl Into<String>,
+        command_line: impl Into<String>,
+        registered_at: u64,
+    ) -> Self {
+        Self {
+            app_id: app_id.into(),
+            pid,
+            start_time: start_time.into(),
+            command_line: command_line.into(),
+            socket_path: None,
+            component_id: None,
+            component_version: None,
+            registered_at,
+            idle_since: None,
+            warm_starts: 0,
+        }
+    }
+
+    /// Set the socket path the backend listens on
+    pub fn with_socket_path(mut self, socket_path: impl Into<PathBuf>) -> Self {
+        self.socket_path = Some(socket_path.into());
+        self
+    }
+
+    /// Set the component and version the process runs
+    pub fn with_component(
+        mut self,
+        component_id: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        self.component_id = Some(component_id.into());
+        self.component_version = Some(version.into());
+        self
+    }
+
+    /// Get the application ID
+    ///
+    /// For shared components this is the registry form of the owner; use
+    /// [`owner`](Self::owner) for attribution.
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Get the principal the process acts for
+    pub fn owner(&self) -> ComponentOwner {
+        ComponentOwner::from_registry_id(&self.app_id)
+    }
+
+    /// Get the process id
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Get the recorded process start time
+    pub fn start_time(&self) -> &str {
+        &self.start_time
+    }
+
+    /// Get the recorded command line
+    pub fn command_line(&self) -> &str {
+        &self.command_line
+    }
+
+    /// Get the socket path, if any
+    pub fn socket_path(&self) -> Option<&Path> {
+        self.socket_path.as_deref()
+    }
+
+    /// Get the component the process runs, if known
+    pub fn component_id(&self) -> Option<&str> {
+        self.component_id.as_deref()
+    }
+
+    /// Get the version of the component the process runs, if known
+    pub fn component_version(&self) -> Option<&str> {
+        self.component_version.as_deref()
+    }
+
+    /// Get the registration timestamp
+    pub fn registered_at(&self) -> u64 {
+        self.registered_at
+    }
+
+    /// Get whether the process serves an open app
+    pub fn state(&self) -> ProcessState {
+        match self.idle_since {
+            Some(_) => ProcessState::Idle,
+            None => ProcessState::Active,
+        }
+    }
+
+    /// Check whether the process waits idle in the warm pool
+    pub fn is_idle(&self) -> bool {
+        self.idle_since.is_some()
+    }
+
+    /// Get when the app's UI closed, while the process is idle
+    pub fn idle_since(&self) -> Option<u64> {
+        self.idle_since
+    }
+
+    /// Get how many launches reused the process
+    pub fn warm_starts(&self) -> u32 {
+        self.warm_starts
+    }
+
+    /// Mark the process idle from Unix time `at`
+    ///
+    /// An already idle process keeps its original timestamp.
+    pub fn mark_idle(&mut self, at: u64) {
+        self.idle_since.get_or_insert(at);
+    }
+
+    /// Mark an idle process active again, counting a warm start
+    ///
+    /// Has no effect on an active process.
+    pub fn mark_active(&mut self) {
+        if self.idle_since.take().is_some() {
+            self.warm_starts += 1;
+        }
+    }
+
+    /// Check whether a live process is the one this entry was recorded for
+    ///
+    /// Both the start time and the command line must match; a mismatch means
+    /// the pid has been recycled by an unrelated process.
+    pub fn matches(&self, start_time: &str, command_line: &str) -> bool {
+        self.start_time == start_time && self.command_line == command_line
+    }
+}
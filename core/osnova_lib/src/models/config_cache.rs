@@ -4,6 +4,7 @@
 //! - Per-user application configuration settings
 //! - Per-user application cache data
 //! - Encryption at rest for user data
+//! - Per-key version counters used by [`crate::sync::diff`]
 //!
 //! # Example
 //!
@@ -19,16 +20,18 @@
 //! let cache = AppCache::new("app-id", "user-id", vec![1, 2, 3]);
 //! ```
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::time;
 
 /// Application configuration scoped to a specific user
 ///
 /// Each user can have their own configuration settings for each application.
 /// Configuration is encrypted at rest using cocoon.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AppConfiguration {
     /// Application ID (FK -> OsnovaApplication.id)
     app_id: String,
@@ -41,6 +44,18 @@ pub struct AppConfiguration {
 
     /// Unix timestamp when configuration was last updated
     updated_at: u64,
+
+    /// Version counter of each key's last write
+    ///
+    /// Counters are Lamport clocks shared by all keys: every write takes
+    /// one more than the highest counter seen. A key that has a counter
+    /// but no setting was removed (a tombstone).
+    #[serde(default)]
+    key_versions: HashMap<String, u64>,
+
+    /// Highest version applied from each sync origin (device ID)
+    #[serde(default)]
+    synced_versions: HashMap<String, u64>,
 }
 
 impl AppConfiguration {
@@ -66,6 +81,8 @@ impl AppConfiguration {
             user_id: user_id.into(),
             settings: HashMap::new(),
             updated_at: Self::current_timestamp(),
+            key_versions: HashMap::new(),
+            synced_versions: HashMap::new(),
         }
     }
 
@@ -80,20 +97,27 @@ impl AppConfiguration {
             user_id: user_id.into(),
             settings: HashMap::new(),
             updated_at,
+            key_versions: HashMap::new(),
+            synced_versions: HashMap::new(),
         }
     }
 
     /// Create configuration with initial settings
+    ///
+    /// Every initial setting starts at version 1.
     pub fn with_settings(
         app_id: impl Into<String>,
         user_id: impl Into<String>,
         settings: HashMap<String, Value>,
     ) -> Self {
+        let key_versions = settings.keys().map(|key| (key.clone(), 1)).collect();
         Self {
             app_id: app_id.into(),
             user_id: user_id.into(),
             settings,
             updated_at: Self::current_timestamp(),
+            key_versions,
+            synced_versions: HashMap::new(),
         }
     }
 
@@ -132,7 +156,7 @@ impl AppConfiguration {
 
     /// Set a configuration setting
     ///
-    /// Updates the `updated_at` timestamp.
+    /// Updates the `updated_at` timestamp and bumps the key's version.
     ///
     /// # Example
     ///
@@ -147,16 +171,32 @@ impl AppConfiguration {
     /// assert_eq!(config.settings().len(), 2);
     /// ```
     pub fn set_setting(&mut self, key: impl Into<String>, value: Value) {
-        self.settings.insert(key.into(), value);
+        let key = key.into();
+        let version = self.version() + 1;
+        self.key_versions.insert(key.clone(), version);
+        self.settings.insert(key, value);
         self.updated_at = Self::current_timestamp();
     }
 
+    /// Show a default for a key without a value
+    ///
+    /// Not recorded as a write: defaults get no version, so they are not
+    /// synced to other devices and a later change of default applies.
+    pub(crate) fn fill_default(&mut self, key: &str, value: Value) {
+        if !self.settings.contains_key(key) {
+            self.settings.insert(key.to_string(), value);
+        }
+    }
+
     /// Remove a configuration setting
     ///
-    /// Updates the `updated_at` timestamp if the key existed.
+    /// Updates the `updated_at` timestamp and leaves a tombstone version if
+    /// the key existed.
     pub fn remove_setting(&mut self, key: &str) -> Option<Value> {
         let result = self.settings.remove(key);
         if result.is_some() {
+            let version = self.version() + 1;
+            self.key_versions.insert(key.to_string(), version);
             self.updated_at = Self::current_timestamp();
         }
         result
@@ -164,12 +204,59 @@ impl AppConfiguration {
 
     /// Clear all settings
     ///
-    /// Updates the `updated_at` timestamp.
+    /// Updates the `updated_at` timestamp and leaves a tombstone version for
+    /// every removed key.
     pub fn clear_settings(&mut self) {
+        let version = self.version() + 1;
+        for key in self.settings.keys() {
+            self.key_versions.insert(key.clone(), version);
+        }
         self.settings.clear();
         self.updated_at = Self::current_timestamp();
     }
 
+    /// Highest key version, or 0 if nothing was ever written
+    pub fn version(&self) -> u64 {
+        self.key_versions.values().copied().max().unwrap_or(0)
+    }
+
+    /// Version of a key's last write or removal, or 0 if never written
+    pub fn key_version(&self, key: &str) -> u64 {
+        self.key_versions.get(key).copied().unwrap_or(0)
+    }
+
+    /// Versions of every written key, including tombstones
+    pub fn key_versions(&self) -> &HashMap<String, u64> {
+        &self.key_versions
+    }
+
+    /// Highest version applied from a sync origin, or 0 if none
+    pub fn synced_version(&self, origin: &str) -> u64 {
+        self.synced_versions.get(origin).copied().unwrap_or(0)
+    }
+
+    /// Write a key at a given version, as received from another device
+    ///
+    /// `None` removes the key, leaving a tombstone at `version`.
+    pub(crate) fn put_versioned(&mut self, key: &str, value: Option<Value>, version: u64) {
+        match value {
+            Some(value) => {
+                self.settings.insert(key.to_string(), value);
+            }
+            None => {
+                self.settings.remove(key);
+            }
+        }
+        self.key_versions.insert(key.to_string(), version);
+        self.updated_at = Self::current_timestamp();
+    }
+
+    /// Record the highest version applied from a sync origin
+    pub(crate) fn mark_synced(&mut self, origin: &str, version: u64) {
+        let synced = self.synced_versions.entry(origin.to_string()).or_insert(0);
+        *synced = (*synced).max(version);
+    }
+
     /// Get the last updated timestamp
     pub fn updated_at(&self) -> u64 {
         self.updated_at
@@ -177,10 +264,7 @@ impl AppConfiguration {
 
     /// Get current Unix timestamp
     fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before Unix epoch")
-            .as_secs()
+        time::now_unix()
     }
 }
 
@@ -188,7 +272,7 @@ impl AppConfiguration {
 ///
 /// Each user can have their own cache data for each application.
 /// Cache data is regenerable and encrypted at rest using cocoon.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AppCache {
     /// Application ID (FK -> OsnovaApplication.id)
     app_id: String,
@@ -305,10 +389,7 @@ impl AppCache {
 
     /// Get current Unix timestamp
     fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before Unix epoch")
-            .as_secs()
+        time::now_unix()
     }
 }
 
@@ -390,6 +471,34 @@ mod tests {
         assert_eq!(config.settings().len(), 0);
     }
 
+    #[test]
+    fn test_app_configuration_key_versions() {
+        let mut config = AppConfiguration::new("app-123", "user-456");
+        assert_eq!(config.version(), 0);
+
+        config.set_setting("theme", json!("dark"));
+        config.set_setting("fontSize", json!(14));
+        config.set_setting("theme", json!("light"));
+        assert_eq!(config.key_version("fontSize"), 2);
+        assert_eq!(config.key_version("theme"), 3);
+        assert_eq!(config.version(), 3);
+
+        // Removal leaves a tombstone version behind
+        config.remove_setting("fontSize");
+        assert_eq!(config.key_version("fontSize"), 4);
+        assert!(config.get_setting("fontSize").is_none());
+
+        // Configurations stored before key versions existed still load
+        let legacy: AppConfiguration = serde_json::from_value(json!({
+            "app_id": "app-123",
+            "user_id": "user-456",
+            "settings": {"theme": "dark"},
+            "updated_at": 1000
+        }))
+        .expect("Failed to deserialize");
+        assert_eq!(legacy.version(), 0);
+    }
+
     #[test]
     fn test_app_configuration_serialization() {
         let mut config = AppConfiguration::with_timestamp("app-123", "user-456", 1000);
@@ -0,0 +1,241 @@
+//! Point-in-time snapshots of per-app configurations
+//!
+//! Users who break an app by changing its settings want them back as they
+//! were yesterday. [`ConfigService`](crate::services::ConfigService) keeps
+//! snapshots of each app's configuration per user, taken before a write
+//! every few mutations and at least once a day while the settings change.
+//!
+//! Snapshots of one app and user form a chain. The oldest holds every
+//! setting ([`SnapshotContent::Full`]); each later one holds only what
+//! differs from the one before ([`SnapshotContent::Delta`]), so settings
+//! that rarely change are not stored again with every snapshot. Pruning the
+//! oldest snapshot turns the next one into a full one.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::error::{OsnovaError, Result};
+
+/// Settings of a configuration, by key
+pub type Settings = BTreeMap<String, Value>;
+
+/// Why a snapshot was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapshotReason {
+    /// Taken on the regular cadence, before a write
+    Scheduled,
+    /// The state a restore replaced, so the restore can be undone
+    BeforeRestore,
+}
+
+impl SnapshotReason {
+    /// Stored form of the reason
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::BeforeRestore => "beforeRestore",
+        }
+    }
+
+    /// Parse the stored form of a reason
+    pub fn parse(reason: &str) -> Option<Self> {
+        match reason {
+            "scheduled" => Some(Self::Scheduled),
+            "beforeRestore" => Some(Self::BeforeRestore),
+            _ => None,
+        }
+    }
+}
+
+/// A stored snapshot, as listed for the Config screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSnapshot {
+    /// Snapshot ID
+    pub id: i64,
+    /// Application ID
+    pub app_id: String,
+    /// User ID
+    pub user_id: String,
+    /// Unix timestamp the snapshot was taken
+    pub created_at: u64,
+    /// Version of the configuration it holds
+    pub version: u64,
+    /// Why it was taken
+    pub reason: SnapshotReason,
+    /// Bytes stored for it, encrypted
+    pub bytes: u64,
+}
+
+/// How a snapshot is applied to the current configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreMode {
+    /// Make the settings exactly what the snapshot holds
+    Replace,
+    /// Only bring back keys that currently have no value
+    MergeMissing,
+}
+
+/// Stored settings of a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SnapshotContent {
+    /// Every setting
+    Full {
+        /// Settings by key
+        settings: Settings,
+    },
+    /// What differs from the previous snapshot of the chain
+    Delta {
+        /// Keys added or changed, with their values
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        set: Settings,
+        /// Keys removed
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        removed: Vec<String>,
+    },
+}
+
+impl SnapshotContent {
+    /// Encode `settings` against the settings of the previous snapshot, or
+    /// in full when there is none
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use osnova_lib::models::config_snapshot::{Settings, SnapshotContent};
+    /// use serde_json::json;
+    ///
+    /// let before = Settings::from([
+    ///     ("theme".to_string(), json!("dark")),
+    ///     ("fontSize".to_string(), json!(14)),
+    /// ]);
+    /// let after = Settings::from([("theme".to_string(), json!("light"))]);
+    ///
+    /// let delta = SnapshotContent::encode(Some(&before), &after);
+    /// assert!(!delta.is_full());
+    /// assert_eq!(delta.decode(Some(&before))?, after);
+    /// # Ok::<(), osnova_lib::error::OsnovaError>(())
+    /// ```
+    pub fn encode(previous: Option<&Settings>, settings: &Settings) -> Self {
+        let Some(previous) = previous else {
+            return Self::Full {
+                settings: settings.clone(),
+            };
+        };
+
+        let set = settings
+            .iter()
+            .filter(|(key, value)| previous.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let removed = previous
+            .keys()
+            .filter(|key| !settings.contains_key(*key))
+            .cloned()
+            .collect();
+        Self::Delta { set, removed }
+    }
+
+    /// Settings the snapshot holds, given those of the previous snapshot
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for a delta without a previous snapshot
+    pub fn decode(&self, previous: Option<&Settings>) -> Result<Settings> {
+        match (self, previous) {
+            (Self::Full { settings }, _) => Ok(settings.clone()),
+            (Self::Delta { set, removed }, Some(previous)) => {
+                let mut settings = previous.clone();
+                for key in removed {
+                    settings.remove(key);
+                }
+                settings.extend(set.iter().map(|(key, value)| (key.clone(), value.clone())));
+                Ok(settings)
+            }
+            (Self::Delta { .. }, None) => Err(OsnovaError::Other(
+                "Snapshot delta has no snapshot to apply to".to_string(),
+            )),
+        }
+    }
+
+    /// Whether the snapshot holds every setting
+    pub fn is_full(&self) -> bool {
+        matches!(self, Self::Full { .. })
+    }
+}
+
+/// Settings of every snapshot of a chain, oldest first
+///
+/// # Errors
+///
+/// Returns an error if the chain does not start with a full snapshot
+pub fn decode_chain<'a, I>(contents: I) -> Result<Vec<Settings>>
+where
+    I: IntoIterator<Item = &'a SnapshotContent>,
+{
+    let mut decoded: Vec<Settings> = Vec::new();
+    for content in contents {
+        let settings = content.decode(decoded.last())?;
+        decoded.push(settings);
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings(pairs: &[(&str, Value)]) -> Settings {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_delta_round_trip() -> Result<()> {
+        let first = settings(&[("theme", json!("dark")), ("fontSize", json!(14))]);
+        let second = settings(&[
+            ("theme", json!("dark")),
+            ("fontSize", json!(16)),
+            ("lang", json!("en")),
+        ]);
+        let third = settings(&[("lang", json!("en"))]);
+
+        let contents = vec![
+            SnapshotContent::encode(None, &first),
+            SnapshotContent::encode(Some(&first), &second),
+            SnapshotContent::encode(Some(&second), &third),
+        ];
+        assert!(contents[0].is_full());
+        // Unchanged keys are not stored again
+        assert_eq!(
+            contents[1],
+            SnapshotContent::Delta {
+                set: settings(&[("fontSize", json!(16)), ("lang", json!("en"))]),
+                removed: vec![],
+            }
+        );
+        assert_eq!(
+            contents[2],
+            SnapshotContent::Delta {
+                set: Settings::new(),
+                removed: vec!["fontSize".to_string(), "theme".to_string()],
+            }
+        );
+
+        let json = serde_json::to_string(&contents)?;
+        let parsed: Vec<SnapshotContent> = serde_json::from_str(&json)?;
+        assert_eq!(decode_chain(&parsed)?, vec![first, second, third]);
+
+        // A chain must start in full
+        assert!(decode_chain(&parsed[1..]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! App consent models for Osnova
+//!
+//! This module provides the AppConsent type, the user's decision to run, or
+//! not run, an installed app after reviewing what it is and what it asks
+//! for. A decision covers the version it was made for and later versions
+//! with the same major version, as long as they declare no permission the
+//! reviewed version did not.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A user's recorded review decision for an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConsent {
+    /// Application identifier
+    pub app_id: String,
+    /// Version reviewed
+    pub version: String,
+    /// Whether the user agreed to run the app
+    pub accepted: bool,
+    /// Review warnings the user acknowledged
+    pub acknowledged_warnings: Vec<String>,
+    /// Permissions the reviewed version declared, sorted
+    pub permissions: Vec<String>,
+    /// Unix timestamp of the decision
+    pub decided_at: u64,
+}
+
+impl AppConsent {
+    /// Whether the decision still applies to `version` declaring
+    /// `permissions`
+    pub fn applies_to(&self, version: &str, permissions: &[String]) -> bool {
+        major(&self.version) == major(version) && self.new_permissions(permissions).is_empty()
+    }
+
+    /// Permissions in `permissions` the reviewed version did not declare
+    pub fn new_permissions(&self, permissions: &[String]) -> Vec<String> {
+        permissions
+            .iter()
+            .filter(|permission| !self.permissions.contains(permission))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Major component of a semver version
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decision_lapses_on_major_bump_or_new_permission() {
+        let consent = AppConsent {
+            app_id: "com.test.app".to_string(),
+            version: "1.2.0".to_string(),
+            accepted: true,
+            acknowledged_warnings: vec![],
+            permissions: vec!["uriScheme:mailto".to_string()],
+            decided_at: 100,
+        };
+        let mailto = vec!["uriScheme:mailto".to_string()];
+
+        assert!(consent.applies_to("1.9.3", &mailto));
+        assert!(consent.applies_to("1.0.0", &[]));
+        assert!(!consent.applies_to("2.0.0", &mailto));
+
+        let expanded = vec!["backend".to_string(), "uriScheme:mailto".to_string()];
+        assert!(!consent.applies_to("1.3.0", &expanded));
+        assert_eq!(consent.new_permissions(&expanded), vec!["backend"]);
+    }
+}
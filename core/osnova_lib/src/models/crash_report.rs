@@ -0,0 +1,77 @@
+//! Crash report models for Osnova
+//!
+//! This module provides the CrashReport type, recorded when a backend
+//! component process exits without being asked to. It keeps what a
+//! developer needs to tell why:
+//! - Which app, component, and version crashed
+//! - The exit code, or the signal that killed the process
+//! - The last lines the process wrote to stderr
+//! - When the process started and when it was found dead
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::components::symbols::{parse_frames, NativeFrame};
+
+/// A backend component process that exited unexpectedly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    /// Report identifier
+    pub id: String,
+    /// Process registry owner: the app ID, or `shared:<sharedId>`
+    pub app_id: String,
+    /// Component that crashed, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_id: Option<String>,
+    /// Version of the component that crashed, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_version: Option<String>,
+    /// Process id of the dead backend
+    pub pid: u32,
+    /// Exit code, if the process exited on its own
+    pub exit_code: Option<i32>,
+    /// Signal that killed the process, if any
+    pub signal: Option<i32>,
+    /// Unix timestamp when the process was registered
+    pub started_at: u64,
+    /// Unix timestamp when the process was found dead
+    pub crashed_at: u64,
+    /// Last lines the process wrote to stderr, oldest first
+    pub stderr_tail: Vec<String>,
+}
+
+impl CrashReport {
+    /// Seconds the process ran before it was found dead
+    pub fn uptime_secs(&self) -> u64 {
+        self.crashed_at.saturating_sub(self.started_at)
+    }
+
+    /// Conventional name of the signal that killed the process
+    pub fn signal_name(&self) -> Option<&'static str> {
+        #[cfg(unix)]
+        {
+            let name = match self.signal? {
+                libc::SIGSEGV => "SIGSEGV",
+                libc::SIGABRT => "SIGABRT",
+                libc::SIGBUS => "SIGBUS",
+                libc::SIGILL => "SIGILL",
+                libc::SIGFPE => "SIGFPE",
+                libc::SIGKILL => "SIGKILL",
+                libc::SIGTERM => "SIGTERM",
+                libc::SIGPIPE => "SIGPIPE",
+                _ => return None,
+            };
+            Some(name)
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// Native backtrace frames found in the stderr tail
+    pub fn frames(&self) -> Vec<NativeFrame> {
+        parse_frames(&self.stderr_tail)
+    }
+}
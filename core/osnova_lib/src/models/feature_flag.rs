@@ -0,0 +1,109 @@
+//! Feature flag declarations for Osnova apps
+//!
+//! An app declares its flags in the manifest's `featureFlags` so it can ship
+//! features dark and turn them on later without republishing components.
+//! Each flag is a boolean with a default; a publisher's flag document (see
+//! [`crate::manifest::flags`]) and the user's local overrides are layered on
+//! top by [`crate::services::features::FeatureFlagService`].
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::error::{OsnovaError, Result};
+
+/// Longest flag name accepted
+pub const MAX_FLAG_NAME_LEN: usize = 64;
+
+/// A feature flag an app declares
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::models::feature_flag::{validate_flags, FeatureFlag};
+/// use serde_json::json;
+///
+/// let flags: Vec<FeatureFlag> = serde_json::from_value(json!([
+///     {"name": "newComposer", "description": "Rich text composer", "default": false}
+/// ]))
+/// .unwrap();
+/// assert!(validate_flags(&flags).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlag {
+    /// Flag name, unique within the app
+    pub name: String,
+    /// What the flag turns on, shown in the app's settings
+    #[serde(default)]
+    pub description: String,
+    /// Value when neither the publisher nor the user set one
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// Check an app's flag declarations
+///
+/// Names are 1 to [`MAX_FLAG_NAME_LEN`] ASCII letters, digits, `.`, `_` or
+/// `-`, and unique.
+///
+/// # Errors
+///
+/// Returns an error naming the first invalid declaration
+pub fn validate_flags(flags: &[FeatureFlag]) -> Result<()> {
+    let invalid = |message: String| {
+        Err(OsnovaError::Other(format!(
+            "Invalid featureFlags: {}",
+            message
+        )))
+    };
+
+    let mut names = HashSet::new();
+    for flag in flags {
+        let valid_name = !flag.name.is_empty()
+            && flag.name.len() <= MAX_FLAG_NAME_LEN
+            && flag
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !valid_name {
+            return invalid(format!("invalid flag name '{}'", flag.name));
+        }
+        if !names.insert(flag.name.as_str()) {
+            return invalid(format!("duplicate flag '{}'", flag.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(name: &str) -> FeatureFlag {
+        FeatureFlag {
+            name: name.to_string(),
+            description: String::new(),
+            default: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_flags() {
+        assert!(validate_flags(&[flag("newComposer"), flag("sync.v2"), flag("beta_ui-2")]).is_ok());
+        assert!(validate_flags(&[]).is_ok());
+
+        assert!(validate_flags(&[flag("")]).is_err());
+        assert!(validate_flags(&[flag("has space")]).is_err());
+        assert!(validate_flags(&[flag(&"x".repeat(MAX_FLAG_NAME_LEN + 1))]).is_err());
+        let error = validate_flags(&[flag("beta"), flag("beta")]).unwrap_err();
+        assert!(error.to_string().contains("duplicate flag 'beta'"));
+    }
+
+    #[test]
+    fn test_declaration_defaults() {
+        let flag: FeatureFlag = serde_json::from_str(r#"{"name": "beta"}"#).unwrap();
+        assert_eq!(flag.description, "");
+        assert!(!flag.default);
+    }
+}
@@ -0,0 +1,80 @@
+//! Backend health models for Osnova
+//!
+//! The health of running backends as checked by the
+//! [`HealthMonitor`](crate::services::health::HealthMonitor), and the
+//! transitions kept in each app's health history.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Health transitions kept per app
+pub const HEALTH_HISTORY: usize = 50;
+
+/// Health of a backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    /// Not checked since it was started
+    #[default]
+    Unknown,
+    /// Passed its last check
+    Healthy,
+    /// Failed its threshold of checks in a row
+    Unhealthy,
+    /// Stopped for good after too many restarts
+    Blocked,
+}
+
+/// A backend's health, as shown in launch descriptors and app details
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendHealth {
+    /// Component identifier
+    pub component_id: String,
+    /// Current health
+    pub status: HealthStatus,
+    /// Checks failed in a row
+    pub consecutive_failures: u32,
+    /// Restarts since the app was launched
+    pub restarts: u32,
+    /// Why the last check failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Unix timestamp of the last check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<u64>,
+}
+
+impl BackendHealth {
+    /// A backend not checked yet
+    pub fn unknown(component_id: impl Into<String>) -> Self {
+        Self {
+            component_id: component_id.into(),
+            status: HealthStatus::Unknown,
+            consecutive_failures: 0,
+            restarts: 0,
+            last_error: None,
+            checked_at: None,
+        }
+    }
+}
+
+/// A backend became unhealthy, recovered, was restarted or blocked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthTransition {
+    /// Application identifier
+    pub app_id: String,
+    /// Component identifier
+    pub component_id: String,
+    /// Health before
+    pub from: HealthStatus,
+    /// Health after
+    pub to: HealthStatus,
+    /// What caused the transition
+    pub reason: String,
+    /// Restarts since the app was launched
+    pub restarts: u32,
+    /// Unix timestamp of the transition
+    pub at: u64,
+}
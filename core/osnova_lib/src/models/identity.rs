@@ -4,6 +4,7 @@
 //! - 12-word BIP-39 seed phrases
 //! - Derived master keys (256-bit)
 //! - Device key management
+//! - Purpose-separated key derivation ([`KeyPurpose`])
 //!
 //! # Example
 //!
@@ -30,6 +31,84 @@ use hkdf::Hkdf;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 
+/// Purpose of a key derived from the root identity
+///
+/// Every derivation from the master key names its purpose, and the purpose's
+/// wire string is mixed into the HKDF salt so keys for different purposes can
+/// never collide. Wire strings are part of the key derivation and must never
+/// change once shipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyPurpose {
+    /// Signing keys (`"signing"`)
+    ///
+    /// Consumers: component signing keys (e.g. wallet transaction signing).
+    Signing,
+    /// Encryption keys (`"encryption"`)
+    ///
+    /// Consumers: component data encryption.
+    Encryption,
+    /// App configuration storage keys (`"config-storage"`)
+    ///
+    /// Consumers: per-user app configuration encryption in ConfigService.
+    ConfigStorage,
+    /// Key cocoon wrapping keys (`"cocoon-wrap"`)
+    ///
+    /// Consumers: [`RootIdentity::derive_cocoon_key`], which encrypts the
+    /// KeyService cocoon.
+    CocoonWrap,
+    /// Device identity keys (`"device-identity"`)
+    ///
+    /// Consumers: device keys used for pairing.
+    DeviceIdentity,
+    /// Backup encryption keys (`"backup"`)
+    ///
+    /// Consumers: backup and export flows.
+    Backup,
+    /// Audit log MAC keys (`"audit-log"`)
+    ///
+    /// Consumers: [`crate::audit::AuditLog`] entry hash chain.
+    AuditLog,
+    /// Search index keys (`"search-index"`)
+    ///
+    /// Consumers: the encrypted config value index in
+    /// [`crate::services::SearchService`].
+    SearchIndex,
+}
+
+impl KeyPurpose {
+    /// All key purposes
+    pub const ALL: [KeyPurpose; 8] = [
+        KeyPurpose::Signing,
+        KeyPurpose::Encryption,
+        KeyPurpose::ConfigStorage,
+        KeyPurpose::CocoonWrap,
+        KeyPurpose::DeviceIdentity,
+        KeyPurpose::Backup,
+        KeyPurpose::AuditLog,
+        KeyPurpose::SearchIndex,
+    ];
+
+    /// Stable wire string used in key derivation
+    pub fn as_wire_str(&self) -> &'static str {
+        match self {
+            KeyPurpose::Signing => "signing",
+            KeyPurpose::Encryption => "encryption",
+            KeyPurpose::ConfigStorage => "config-storage",
+            KeyPurpose::CocoonWrap => "cocoon-wrap",
+            KeyPurpose::DeviceIdentity => "device-identity",
+            KeyPurpose::Backup => "backup",
+            KeyPurpose::AuditLog => "audit-log",
+            KeyPurpose::SearchIndex => "search-index",
+        }
+    }
+
+    /// Parse a wire string, returning None for unknown purposes
+    pub fn from_wire_str(purpose: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_wire_str() == purpose)
+    }
+}
+
 /// Root identity for an Osnova user
 ///
 /// Contains the 12-word seed phrase and derived master key.
@@ -139,15 +218,16 @@ impl RootIdentity {
     ///
     /// * `component_id` - Unique component identifier (e.g., "com.osnova.wallet")
     /// * `index` - Key derivation index (for BIP-44 wallet paths, etc.)
-    /// * `purpose` - Purpose string (e.g., "encryption", "signing")
+    /// * `purpose` - What the key will be used for
     ///
     /// # Example
     ///
     /// ```
-    /// use osnova_lib::models::identity::RootIdentity;
+    /// use osnova_lib::models::identity::{KeyPurpose, RootIdentity};
     ///
     /// let identity = RootIdentity::generate().expect("Failed to generate");
-    /// let wallet_key = identity.derive_component_key("com.osnova.wallet", 0, "signing")
+    /// let wallet_key = identity
+    ///     .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
     ///     .expect("Failed to derive key");
     /// assert_eq!(wallet_key.len(), 32);
     /// ```
@@ -155,10 +235,10 @@ impl RootIdentity {
         &self,
         component_id: &str,
         index: u32,
-        purpose: &str,
+        purpose: KeyPurpose,
     ) -> Result<[u8; 32]> {
         // Create salt from component_id
-        let salt_data = format!("{}-{}", component_id, purpose);
+        let salt_data = format!("{}-{}", component_id, purpose.as_wire_str());
 
         // Create info from index
         let info = index.to_le_bytes();
@@ -172,6 +252,51 @@ impl RootIdentity {
         Ok(component_key)
     }
 
+    /// Derive a component-specific key from a purpose string
+    ///
+    /// Only purposes with a [`KeyPurpose`] wire string are accepted; unknown
+    /// strings are rejected rather than silently creating a new domain.
+    #[deprecated(note = "use derive_component_key with a KeyPurpose")]
+    pub fn derive_component_key_str(
+        &self,
+        component_id: &str,
+        index: u32,
+        purpose: &str,
+    ) -> Result<[u8; 32]> {
+        let purpose = KeyPurpose::from_wire_str(purpose)
+            .ok_or_else(|| OsnovaError::Crypto(format!("Unknown key purpose: {}", purpose)))?;
+        self.derive_component_key(component_id, index, purpose)
+    }
+
+    /// Derive the key that encrypts a user's key cocoon
+    ///
+    /// Uses the component key derivation with [`KeyPurpose::CocoonWrap`].
+    /// Cocoons written with [`legacy_cocoon_key`](Self::legacy_cocoon_key)
+    /// are migrated by `KeyService::migrate_cocoon_key`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User identifier
+    pub fn derive_cocoon_key(&self, user_id: &str) -> Result<[u8; 32]> {
+        self.derive_component_key(
+            &format!("osnova-key-service:{}", user_id),
+            0,
+            KeyPurpose::CocoonWrap,
+        )
+    }
+
+    /// Cocoon key from the former BLAKE3-based derivation
+    ///
+    /// Only used to open and migrate cocoons created before
+    /// [`derive_cocoon_key`](Self::derive_cocoon_key) existed.
+    pub fn legacy_cocoon_key(&self, user_id: &str) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        hasher.update(b"osnova-key-service-cocoon:");
+        hasher.update(user_id.as_bytes());
+        hasher.update(&self.master_key);
+        *hasher.finalize().as_bytes()
+    }
+
     /// Generate a deterministic identity fingerprint
     ///
     /// This is a BLAKE3 hash of the master key, useful for:
@@ -268,7 +393,7 @@ mod tests {
         let identity = RootIdentity::generate().expect("Failed to generate");
 
         let wallet_key = identity
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed to derive key");
 
         assert_eq!(wallet_key.len(), 32);
@@ -280,10 +405,10 @@ mod tests {
         let identity = RootIdentity::generate().expect("Failed to generate");
 
         let wallet_key = identity
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed to derive wallet key");
         let storage_key = identity
-            .derive_component_key("com.osnova.storage", 0, "encryption")
+            .derive_component_key("com.osnova.storage", 0, KeyPurpose::Encryption)
             .expect("Failed to derive storage key");
 
         // Different components should have different keys
@@ -295,10 +420,10 @@ mod tests {
         let identity = RootIdentity::generate().expect("Failed to generate");
 
         let key0 = identity
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed");
         let key1 = identity
-            .derive_component_key("com.osnova.wallet", 1, "signing")
+            .derive_component_key("com.osnova.wallet", 1, KeyPurpose::Signing)
             .expect("Failed");
 
         // Different indexes should produce different keys
@@ -310,10 +435,10 @@ mod tests {
         let identity = RootIdentity::generate().expect("Failed to generate");
 
         let signing_key = identity
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed");
         let encryption_key = identity
-            .derive_component_key("com.osnova.wallet", 0, "encryption")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Encryption)
             .expect("Failed");
 
         // Different purposes should produce different keys
@@ -327,16 +452,110 @@ mod tests {
         let identity2 = RootIdentity::from_seed(seed).expect("Failed");
 
         let key1 = identity1
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed");
         let key2 = identity2
-            .derive_component_key("com.osnova.wallet", 0, "signing")
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
             .expect("Failed");
 
         // Same identity should produce same component keys
         assert_eq!(key1, key2);
     }
 
+    #[test]
+    fn test_key_purpose_wire_strings() {
+        // These strings are part of key derivation and must never change
+        let vectors = [
+            (KeyPurpose::Signing, "signing"),
+            (KeyPurpose::Encryption, "encryption"),
+            (KeyPurpose::ConfigStorage, "config-storage"),
+            (KeyPurpose::CocoonWrap, "cocoon-wrap"),
+            (KeyPurpose::DeviceIdentity, "device-identity"),
+            (KeyPurpose::Backup, "backup"),
+            (KeyPurpose::AuditLog, "audit-log"),
+            (KeyPurpose::SearchIndex, "search-index"),
+        ];
+        assert_eq!(vectors.len(), KeyPurpose::ALL.len());
+
+        for (purpose, wire) in vectors {
+            assert_eq!(purpose.as_wire_str(), wire);
+            assert_eq!(KeyPurpose::from_wire_str(wire), Some(purpose));
+            assert_eq!(
+                serde_json::to_string(&purpose).unwrap(),
+                format!("\"{}\"", wire)
+            );
+        }
+    }
+
+    #[test]
+    fn test_component_key_vector() {
+        let seed = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let identity = RootIdentity::from_seed(seed).expect("Failed");
+
+        // Matches keys derived with the former "signing" purpose string
+        let key = identity
+            .derive_component_key("com.osnova.wallet", 0, KeyPurpose::Signing)
+            .expect("Failed");
+        assert_eq!(
+            hex::encode(key),
+            "84b107ce9db5a7f88040f5a8c239d03867d47f39bc93557558dfd42f23d77485"
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_derive_component_key_str_shim() {
+        let identity = RootIdentity::generate().expect("Failed to generate");
+
+        for purpose in KeyPurpose::ALL {
+            let via_shim = identity
+                .derive_component_key_str("com.osnova.wallet", 3, purpose.as_wire_str())
+                .expect("Failed");
+            let via_enum = identity
+                .derive_component_key("com.osnova.wallet", 3, purpose)
+                .expect("Failed");
+            assert_eq!(via_shim, via_enum);
+        }
+
+        let result = identity.derive_component_key_str("com.osnova.wallet", 0, "made-up");
+        assert!(matches!(result, Err(OsnovaError::Crypto(_))));
+    }
+
+    #[test]
+    fn test_legacy_cocoon_key_compatibility() {
+        // Former AppState::derive_cocoon_key from the Tauri app
+        fn tauri_derive_cocoon_key(user_id: &str, master_key: &[u8; 32]) -> [u8; 32] {
+            let mut hasher = Hasher::new();
+            hasher.update(b"osnova-key-service-cocoon:");
+            hasher.update(user_id.as_bytes());
+            hasher.update(master_key);
+            let hash = hasher.finalize();
+            let mut key = [0u8; 32];
+            key.copy_from_slice(hash.as_bytes());
+            key
+        }
+
+        let seed = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let identity = RootIdentity::from_seed(seed).expect("Failed");
+
+        let legacy = identity.legacy_cocoon_key("user-123");
+        assert_eq!(
+            legacy,
+            tauri_derive_cocoon_key("user-123", identity.master_key())
+        );
+        assert_eq!(
+            hex::encode(legacy),
+            "16ce0b9d04cfd4f7040219deec4a672f8742044f7722fde93475f7e468424256"
+        );
+
+        let cocoon_key = identity.derive_cocoon_key("user-123").expect("Failed");
+        assert_eq!(
+            hex::encode(cocoon_key),
+            "f3c4548ee6255c442bff0d361b372c8b2144812f258c55851e2cf15c8bb6f026"
+        );
+        assert_ne!(cocoon_key, legacy);
+    }
+
     #[test]
     fn test_fingerprint() {
         let identity = RootIdentity::generate().expect("Failed to generate");
@@ -373,15 +592,15 @@ mod tests {
         #[test]
         fn test_component_key_always_32_bytes(index in 0u32..1000u32) {
             let identity = RootIdentity::generate().unwrap();
-            let key = identity.derive_component_key("test.component", index, "test").unwrap();
+            let key = identity.derive_component_key("test.component", index, KeyPurpose::Signing).unwrap();
             assert_eq!(key.len(), 32);
         }
 
         #[test]
         fn test_different_indexes_different_keys(index1 in 0u32..100u32, index2 in 100u32..200u32) {
             let identity = RootIdentity::generate().unwrap();
-            let key1 = identity.derive_component_key("test", index1, "test").unwrap();
-            let key2 = identity.derive_component_key("test", index2, "test").unwrap();
+            let key1 = identity.derive_component_key("test", index1, KeyPurpose::Signing).unwrap();
+            let key2 = identity.derive_component_key("test", index2, KeyPurpose::Signing).unwrap();
             assert_ne!(key1, key2);
         }
     }
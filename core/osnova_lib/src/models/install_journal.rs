@@ -0,0 +1,68 @@
+//! Install journal models for Osnova
+//!
+//! An install writes a journal entry before it touches the cache or the
+//! application table and clears it once the app is recorded. An entry
+//! that survives a restart marks an install interrupted by a crash; the
+//! apps service finishes or undoes it on the next start.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::ManifestSchema;
+
+/// Last install step that completed
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum InstallStep {
+    /// Journal written, nothing changed yet
+    Started,
+    /// Every component downloaded and verified
+    ComponentsFetched,
+    /// Application row written
+    Recorded,
+}
+
+/// An install in progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallJournalEntry {
+    /// Application being installed
+    pub app_id: String,
+    /// Manifest being installed
+    pub manifest: ManifestSchema,
+    /// Last step that completed
+    pub step: InstallStep,
+    /// Whether another version of the app was installed when the first
+    /// attempt started; undoing the install then keeps its row
+    pub previously_installed: bool,
+    /// Unix timestamp of the first attempt
+    pub started_at: u64,
+    /// Unix timestamp of the last step
+    pub updated_at: u64,
+}
+
+/// How an interrupted install was resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum InstallResolution {
+    /// Every component verified; the app was recorded as installed
+    RolledForward,
+    /// Partial state was removed; the app must be installed again
+    RolledBack,
+}
+
+/// Outcome of recovering one interrupted install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRecovery {
+    /// Application whose install was interrupted
+    pub app_id: String,
+    /// Version that was being installed
+    pub version: String,
+    /// Step the install had reached
+    pub step: InstallStep,
+    /// What recovery did
+    pub resolution: InstallResolution,
+}
@@ -2,12 +2,26 @@
 //!
 //! This module provides the key cocoon structure and related types for storing
 //! derived cryptographic keys in encrypted storage.
-
+//!
+//! Cocoons of format version 1 were written by development builds, which
+//! stored an X25519 entry's secret key in place of its public key.
+//! Migrating one ([`KeyCocoon::migrate_public_keys`]) stores the real
+//! public keys and keeps a [`LegacyKeyMap`], so keys are still found by the
+//! values components were given then.
+
+use base64::{engine::general_purpose, Engine as _};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::crypto::key_derivation;
+use crate::error::{OsnovaError, Result};
+
+/// Version of the cocoon format written by this build
+pub const KEY_COCOON_VERSION: u32 = 2;
+
 /// Type of cryptographic key
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum KeyType {
     /// Ed25519 signature key
     Ed25519,
@@ -48,10 +62,7 @@ impl DerivedKeyEntry {
             secret_key,
             component_id,
             index,
-            created_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            created_at: crate::time::now_unix(),
             key_type,
         }
     }
@@ -71,6 +82,68 @@ pub struct KeyCocoon {
     pub derived_keys: HashMap<String, DerivedKeyEntry>,
     /// Metadata about the cocoon
     pub metadata: KeyMetadata,
+    /// Public keys a migrated version 1 cocoon stored before migration
+    #[serde(default, skip_serializing_if = "LegacyKeyMap::is_empty")]
+    pub legacy_keys: LegacyKeyMap,
+}
+
+/// Public keys stored by development builds, mapped to the real public
+/// keys of the same entries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LegacyKeyMap(HashMap<String, String>);
+
+impl LegacyKeyMap {
+    /// Map a public key stored before migration to the real one
+    pub fn insert(&mut self, legacy: String, public_key: String) {
+        self.0.insert(legacy, public_key);
+    }
+
+    /// Real public key of a public key stored before migration
+    pub fn current(&self, legacy: &str) -> Option<&str> {
+        self.0.get(legacy).map(String::as_str)
+    }
+
+    /// Number of legacy keys mapped
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether no legacy key is mapped
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A key whose public key changed when its cocoon was migrated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigratedKey {
+    /// Component ID that owns the key
+    pub component_id: String,
+    /// Derivation index
+    pub index: u64,
+    /// Type of key
+    pub key_type: KeyType,
+    /// Public key stored before migration (base64-encoded), still resolved
+    pub legacy_public_key: String,
+    /// Real public key (base64-encoded)
+    pub public_key: String,
+}
+
+/// Report of a cocoon migrated to real public keys
+///
+/// Produced once, by the migration, so components can update the public
+/// keys they stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyMigration {
+    /// Cocoon format version migrated from
+    pub from_version: u32,
+    /// Unix timestamp of the migration
+    pub migrated_at: u64,
+    /// Affected keys, by component and index
+    pub keys: Vec<MigratedKey>,
 }
 
 /// Metadata about the key cocoon
@@ -87,20 +160,82 @@ pub struct KeyMetadata {
 impl KeyCocoon {
     /// Create a new key cocoon with a master key
     pub fn new(master_key: [u8; 32]) -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = crate::time::now_unix();
 
         Self {
             master_key,
             derived_keys: HashMap::new(),
             metadata: KeyMetadata {
-                version: 1,
+                version: KEY_COCOON_VERSION,
                 created_at: now,
                 updated_at: now,
             },
+            legacy_keys: LegacyKeyMap::default(),
+        }
+    }
+
+    /// Migrate a cocoon from before real public key derivation
+    ///
+    /// Every Ed25519 and X25519 entry whose stored public key is not its
+    /// real one gets the real key, and the stored value is mapped to it.
+    /// Version 1 Ed25519 entries already held real keys and are left out of
+    /// the report; X25519 entries held their secret key in place of a
+    /// public key. Returns `None` for a cocoon already at
+    /// [`KEY_COCOON_VERSION`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a secret key is not 32 base64-encoded bytes
+    pub fn migrate_public_keys(&mut self) -> Result<Option<PublicKeyMigration>> {
+        if self.metadata.version >= KEY_COCOON_VERSION {
+            return Ok(None);
+        }
+
+        let mut keys = Vec::new();
+        for entry in self.derived_keys.values_mut() {
+            let key_type = match entry.key_type {
+                KeyType::Ed25519 => key_derivation::KeyType::Ed25519,
+                KeyType::X25519 => key_derivation::KeyType::X25519,
+                // Never derived by any build
+                KeyType::Secp256k1 => continue,
+            };
+            let secret_key: [u8; 32] = general_purpose::STANDARD
+                .decode(&entry.secret_key)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| {
+                    OsnovaError::Crypto(format!(
+                        "Key {} has a malformed secret key",
+                        entry.key_id()
+                    ))
+                })?;
+
+            let keypair = key_derivation::generate_keypair(&secret_key, key_type)?;
+            let public_key = general_purpose::STANDARD.encode(&keypair.public_key);
+            if entry.public_key == public_key {
+                continue;
+            }
+
+            let legacy = std::mem::replace(&mut entry.public_key, public_key.clone());
+            self.legacy_keys.insert(legacy.clone(), public_key.clone());
+            keys.push(MigratedKey {
+                component_id: entry.component_id.clone(),
+                index: entry.index,
+                key_type: entry.key_type.clone(),
+                legacy_public_key: legacy,
+                public_key,
+            });
         }
+        keys.sort_by(|a, b| (&a.component_id, a.index).cmp(&(&b.component_id, b.index)));
+
+        let from_version = self.metadata.version;
+        self.metadata.version = KEY_COCOON_VERSION;
+        self.update_timestamp();
+        Ok(Some(PublicKeyMigration {
+            from_version,
+            migrated_at: self.metadata.updated_at,
+            keys,
+        }))
     }
 
     /// Add a derived key to the cocoon
@@ -117,7 +252,11 @@ impl KeyCocoon {
     }
 
     /// Get a key by public key
+    ///
+    /// Public keys a migrated cocoon stored before migration find their
+    /// entry too.
     pub fn get_by_public_key(&self, public_key: &str) -> Option<&DerivedKeyEntry> {
+        let public_key = self.legacy_keys.current(public_key).unwrap_or(public_key);
         self.derived_keys
             .values()
             .find(|entry| entry.public_key == public_key)
@@ -141,10 +280,7 @@ impl KeyCocoon {
 
     /// Update the timestamp
     fn update_timestamp(&mut self) {
-        self.metadata.updated_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.metadata.updated_at = crate::time::now_unix();
     }
 }
 
@@ -159,7 +295,8 @@ mod tests {
 
         assert_eq!(cocoon.master_key, master_key);
         assert_eq!(cocoon.derived_keys.len(), 0);
-        assert_eq!(cocoon.metadata.version, 1);
+        assert_eq!(cocoon.metadata.version, KEY_COCOON_VERSION);
+        assert!(cocoon.legacy_keys.is_empty());
     }
 
     #[test]
@@ -256,4 +393,101 @@ mod tests {
         assert_eq!(cocoon.highest_index("com.test.wallet"), Some(5));
         assert_eq!(cocoon.highest_index("com.other.app"), None);
     }
+
+    /// Base64 real public key of a seed
+    fn real(seed: &[u8; 32], key_type: key_derivation::KeyType) -> String {
+        let keypair = key_derivation::generate_keypair(seed, key_type).unwrap();
+        general_purpose::STANDARD.encode(keypair.public_key)
+    }
+
+    /// A cocoon as development builds wrote it: an Ed25519 entry with its
+    /// real public key and an X25519 entry holding its secret key as the
+    /// public key
+    fn v1_fixture() -> (KeyCocoon, [u8; 32], [u8; 32]) {
+        let encode = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        let ed25519_seed = [3u8; 32];
+        let x25519_seed = [4u8; 32];
+
+        let mut cocoon = KeyCocoon::new([0u8; 32]);
+        cocoon.metadata.version = 1;
+        cocoon.add_key(DerivedKeyEntry::new(
+            real(&ed25519_seed, key_derivation::KeyType::Ed25519),
+            encode(&ed25519_seed),
+            "com.test.wallet".to_string(),
+            0,
+            KeyType::Ed25519,
+        ));
+        cocoon.add_key(DerivedKeyEntry::new(
+            encode(&x25519_seed),
+            encode(&x25519_seed),
+            "com.test.chat".to_string(),
+            3,
+            KeyType::X25519,
+        ));
+        (cocoon, ed25519_seed, x25519_seed)
+    }
+
+    #[test]
+    fn test_migrate_v1_cocoon() -> Result<()> {
+        let (cocoon, ed25519_seed, x25519_seed) = v1_fixture();
+        let json = serde_json::to_string(&cocoon)?;
+        // Stored before the legacy map existed
+        assert!(!json.contains("legacy_keys"));
+        let mut cocoon: KeyCocoon = serde_json::from_str(&json)?;
+
+        let migration = cocoon.migrate_public_keys()?.unwrap();
+        assert_eq!(cocoon.metadata.version, KEY_COCOON_VERSION);
+        assert_eq!(migration.from_version, 1);
+        assert_eq!(cocoon.legacy_keys.len(), 1);
+
+        // The Ed25519 key was already real and is not reported
+        let x25519 = key_derivation::KeyType::X25519;
+        assert_eq!(
+            migration.keys,
+            vec![MigratedKey {
+                component_id: "com.test.chat".to_string(),
+                index: 3,
+                key_type: KeyType::X25519,
+                legacy_public_key: general_purpose::STANDARD.encode(x25519_seed),
+                public_key: real(&x25519_seed, x25519),
+            }]
+        );
+        let wallet = real(&ed25519_seed, key_derivation::KeyType::Ed25519);
+        assert_eq!(
+            cocoon.get_by_public_key(&wallet).unwrap().component_id,
+            "com.test.wallet"
+        );
+
+        // Found by both public keys
+        for key in &migration.keys {
+            for public_key in [&key.legacy_public_key, &key.public_key] {
+                let entry = cocoon.get_by_public_key(public_key).unwrap();
+                assert_eq!(entry.component_id, key.component_id);
+                assert_eq!(entry.public_key, key.public_key);
+            }
+        }
+
+        // The map survives storage, and a second migration does nothing
+        let mut cocoon: KeyCocoon = serde_json::from_str(&serde_json::to_string(&cocoon)?)?;
+        assert_eq!(cocoon.legacy_keys.len(), 1);
+        assert!(cocoon.migrate_public_keys()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_cocoon_has_no_legacy_map() -> Result<()> {
+        let mut cocoon = KeyCocoon::new([0u8; 32]);
+        cocoon.add_key(DerivedKeyEntry::new(
+            "pubkey1".to_string(),
+            "seckey1".to_string(),
+            "com.test.wallet".to_string(),
+            0,
+            KeyType::Ed25519,
+        ));
+
+        assert!(cocoon.migrate_public_keys()?.is_none());
+        assert!(cocoon.legacy_keys.is_empty());
+        assert!(!serde_json::to_string(&cocoon)?.contains("legacy_keys"));
+        Ok(())
+    }
 }
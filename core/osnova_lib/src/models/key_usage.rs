@@ -0,0 +1,171 @@
+//! Key usage models for Osnova
+//!
+//! Every derive, secret key lookup, and signature made with a component's
+//! derived keys is counted, per component and per key index, in hourly and
+//! daily buckets. A component that suddenly uses its keys far more often
+//! than it used to is flagged as an anomaly.
+//!
+//! Only counts and timestamps are kept: never message contents, public or
+//! secret keys, or any other key material.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An operation on a component's derived keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyOperation {
+    /// A key was derived, or an existing one returned by a derive call
+    Derive,
+    /// A secret key was looked up by its public key
+    GetByPublicKey,
+    /// A message was signed with a derived key
+    Sign,
+}
+
+impl KeyOperation {
+    /// Name used in storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Derive => "derive",
+            Self::GetByPublicKey => "getByPublicKey",
+            Self::Sign => "sign",
+        }
+    }
+
+    /// Parse a name from storage
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "derive" => Some(Self::Derive),
+            "getByPublicKey" => Some(Self::GetByPublicKey),
+            "sign" => Some(Self::Sign),
+            _ => None,
+        }
+    }
+}
+
+/// Length of a usage bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UsageGranularity {
+    /// One hour, starting on the hour (UTC)
+    Hour,
+    /// One day, starting at midnight UTC
+    Day,
+}
+
+impl UsageGranularity {
+    /// Bucket length in seconds
+    pub fn seconds(&self) -> u64 {
+        match self {
+            Self::Hour => 3600,
+            Self::Day => 86_400,
+        }
+    }
+
+    /// Start of the bucket holding a Unix timestamp
+    pub fn bucket_start(&self, unix: u64) -> u64 {
+        unix - unix % self.seconds()
+    }
+
+    /// Name used in storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+}
+
+/// Operation count for one key in one bucket, as stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyUsageRow {
+    /// Unix timestamp the bucket starts at
+    pub bucket: u64,
+    /// Component the key belongs to
+    pub component_id: String,
+    /// Derivation index of the key
+    pub key_index: u64,
+    /// Operation counted
+    pub operation: KeyOperation,
+    /// Number of operations
+    pub count: u64,
+}
+
+/// Operation counts by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsageCounts {
+    /// Derive calls
+    pub derive: u64,
+    /// Secret key lookups
+    pub get_by_public_key: u64,
+    /// Signatures
+    pub sign: u64,
+}
+
+impl KeyUsageCounts {
+    /// Add `count` operations of one kind
+    pub fn add(&mut self, operation: KeyOperation, count: u64) {
+        match operation {
+            KeyOperation::Derive => self.derive += count,
+            KeyOperation::GetByPublicKey => self.get_by_public_key += count,
+            KeyOperation::Sign => self.sign += count,
+        }
+    }
+
+    /// Operations of every kind
+    pub fn total(&self) -> u64 {
+        self.derive + self.get_by_public_key + self.sign
+    }
+}
+
+/// A component using its keys far more often than it used to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsageAnomaly {
+    /// Component that used its keys
+    pub component_id: String,
+    /// Unix timestamp of the hour the episode started in
+    pub hour: u64,
+    /// Operations in that hour when the anomaly was detected
+    pub hourly_count: u64,
+    /// Operations in the trailing hours before it
+    pub trailing_count: u64,
+    /// Number of trailing hours compared against
+    pub trailing_hours: u64,
+    /// Unix timestamp of the detection
+    pub detected_at: u64,
+}
+
+impl KeyUsageAnomaly {
+    /// Average operations per hour over the trailing hours
+    pub fn trailing_average(&self) -> f64 {
+        self.trailing_count as f64 / self.trailing_hours.max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_names_round_trip() {
+        for operation in [
+            KeyOperation::Derive,
+            KeyOperation::GetByPublicKey,
+            KeyOperation::Sign,
+        ] {
+            assert_eq!(KeyOperation::from_name(operation.as_str()), Some(operation));
+        }
+        assert_eq!(KeyOperation::from_name("export"), None);
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        assert_eq!(UsageGranularity::Hour.bucket_start(7_199), 3_600);
+        assert_eq!(UsageGranularity::Hour.bucket_start(7_200), 7_200);
+        assert_eq!(UsageGranularity::Day.bucket_start(86_399), 0);
+        assert_eq!(UsageGranularity::Day.bucket_start(90_000), 86_400);
+    }
+}
@@ -0,0 +1,204 @@
+//! Launcher change models for Osnova
+//!
+//! Every change to what the launcher grid shows (an app installed, removed,
+//! restored or relisted, or a user's layout saved) is numbered with the next
+//! launcher generation. A frontend that remembers the generation it last
+//! rendered can ask what changed since then instead of reloading the app
+//! list and layout.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Changes kept for summaries; callers further behind reload everything
+pub const LAUNCHER_CHANGE_HISTORY: u64 = 1000;
+
+/// What a launcher change did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LauncherChangeKind {
+    /// An app appeared in the app list (installed or restored)
+    Added,
+    /// An app left the app list (uninstalled)
+    Removed,
+    /// An installed app changed (updated or relisted)
+    Updated,
+    /// A user's layout was saved
+    Layout,
+}
+
+impl LauncherChangeKind {
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Updated => "updated",
+            Self::Layout => "layout",
+        }
+    }
+
+    /// Parse a stored name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "added" => Some(Self::Added),
+            "removed" => Some(Self::Removed),
+            "updated" => Some(Self::Updated),
+            "layout" => Some(Self::Layout),
+            _ => None,
+        }
+    }
+}
+
+/// One numbered launcher change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherChange {
+    /// Generation the change produced
+    pub generation: u64,
+    /// What changed
+    pub kind: LauncherChangeKind,
+    /// App that changed; absent for layout changes
+    pub app_id: Option<String>,
+    /// User whose layout changed; absent for app changes
+    pub user_id: Option<String>,
+}
+
+/// What changed in a user's launcher grid between two generations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSummary {
+    /// Generation the summary starts after
+    pub since: u64,
+    /// Current generation
+    pub generation: u64,
+    /// Apps in the list now that were not at `since`
+    pub added: Vec<String>,
+    /// Apps in the list at `since` that are gone now
+    pub removed: Vec<String>,
+    /// Apps in the list at both that changed in between
+    pub updated: Vec<String>,
+    /// Whether the user's layout was saved in between
+    pub layout_changed: bool,
+    /// Whether `since` is older than the kept change history, or newer
+    /// than the current generation; reload the app list and layout
+    pub full_reload: bool,
+}
+
+impl ChangeSummary {
+    /// Summarize `changes`, oldest first, for `user_id`
+    ///
+    /// An app added and removed again in between is left out; one removed
+    /// and added again (restored) counts as updated.
+    pub fn from_changes(
+        since: u64,
+        generation: u64,
+        user_id: &str,
+        changes: &[LauncherChange],
+    ) -> Self {
+        let mut summary = Self {
+            since,
+            generation,
+            ..Self::default()
+        };
+        // App ID with its first and last change
+        let mut apps: Vec<(&str, LauncherChangeKind, LauncherChangeKind)> = Vec::new();
+
+        for change in changes.iter().filter(|change| change.generation > since) {
+            match (&change.app_id, change.kind) {
+                (_, LauncherChangeKind::Layout) => {
+                    summary.layout_changed |= change.user_id.as_deref() == Some(user_id);
+                }
+                (Some(app_id), kind) => match apps.iter_mut().find(|(id, ..)| id == app_id) {
+                    Some((_, _, last)) => *last = kind,
+                    None => apps.push((app_id, kind, kind)),
+                },
+                (None, _) => {}
+            }
+        }
+
+        for (app_id, first, last) in apps {
+            let was_listed = first != LauncherChangeKind::Added;
+            let is_listed = last != LauncherChangeKind::Removed;
+            let list = match (was_listed, is_listed) {
+                (false, true) => &mut summary.added,
+                (true, false) => &mut summary.removed,
+                (true, true) => &mut summary.updated,
+                (false, false) => continue,
+            };
+            list.push(app_id.to_string());
+        }
+        summary
+    }
+
+    /// A summary telling the caller to reload everything
+    pub fn reload(since: u64, generation: u64) -> Self {
+        Self {
+            since,
+            generation,
+            full_reload: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        !self.full_reload
+            && !self.layout_changed
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.updated.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(generation: u64, kind: LauncherChangeKind, app_id: &str) -> LauncherChange {
+        LauncherChange {
+            generation,
+            kind,
+            app_id: Some(app_id.to_string()),
+            user_id: None,
+        }
+    }
+
+    fn layout(generation: u64, user_id: &str) -> LauncherChange {
+        LauncherChange {
+            generation,
+            kind: LauncherChangeKind::Layout,
+            app_id: None,
+            user_id: Some(user_id.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_summary_nets_out_each_app() {
+        use LauncherChangeKind::*;
+        let changes = vec![
+            app(1, Added, "com.kept"),
+            app(2, Added, "com.brief"),
+            app(3, Removed, "com.brief"),
+            app(4, Removed, "com.restored"),
+            app(5, Added, "com.restored"),
+            app(6, Updated, "com.kept"),
+            app(7, Removed, "com.old"),
+            layout(8, "other-user"),
+        ];
+
+        let summary = ChangeSummary::from_changes(0, 8, "user", &changes);
+        assert_eq!(summary.added, vec!["com.kept"]);
+        assert_eq!(summary.removed, vec!["com.old"]);
+        assert_eq!(summary.updated, vec!["com.restored"]);
+        assert!(!summary.layout_changed);
+
+        let summary = ChangeSummary::from_changes(5, 8, "other-user", &changes);
+        assert_eq!(summary.updated, vec!["com.kept"]);
+        assert_eq!(summary.removed, vec!["com.old"]);
+        assert!(summary.added.is_empty());
+        assert!(summary.layout_changed);
+
+        assert!(ChangeSummary::from_changes(8, 8, "user", &changes).is_empty());
+        assert!(!ChangeSummary::reload(0, 8).is_empty());
+    }
+}
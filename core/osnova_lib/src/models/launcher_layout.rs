@@ -0,0 +1,723 @@
+//! Launcher layout models for Osnova
+//!
+//! A user's launcher layout is edited on each of their devices and merged
+//! when the devices sync. So that edits made offline on different devices
+//! all survive, the layout is a set of items rather than an array of app
+//! IDs. Each item has three registers, its placement, folder and pin, and
+//! each register carries the time and device of its last edit. Merging
+//! keeps the latest edit of every register (last writer wins).
+//!
+//! Placement is a fractional [`OrderKey`] instead of an index, so moving
+//! one app never renumbers the others, and two devices moving different
+//! apps both keep their moves. Only the same register edited on both sides
+//! since they last synced is a genuine conflict; [`MergeReport`] lists
+//! those with the side that won.
+//!
+//! Layouts saved before ordering keys hold only `app_ids`. They are
+//! migrated on load, with keys and stamps derived from the list alone, so
+//! two devices migrating the same list agree on every item.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Values of one digit of an [`OrderKey`]
+const KEY_BASE: u32 = 1 << 16;
+
+/// Gap between the keys given to a migrated list, leaving room to insert
+const MIGRATION_SPACING: u32 = 1 << 4;
+
+/// Position of an item in the launcher grid
+///
+/// A fraction in base 2^16, one digit per element, compared digit by digit.
+/// There is always another key between two distinct keys.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(transparent)]
+pub struct OrderKey(Vec<u32>);
+
+impl OrderKey {
+    /// A key after `low` and before `high`; `None` leaves that end open
+    ///
+    /// If `high` is not after `low`, it is ignored.
+    pub fn between(low: Option<&OrderKey>, high: Option<&OrderKey>) -> Self {
+        let high = high.filter(|high| low.is_none_or(|low| low < *high));
+        let mut digits = Vec::new();
+        let mut below_high = high.is_none();
+
+        for depth in 0.. {
+            let lo = low.and_then(|key| key.0.get(depth)).copied().unwrap_or(0);
+            let hi = match high {
+                Some(high) if !below_high => high.0.get(depth).copied().unwrap_or(0),
+                _ => KEY_BASE,
+            };
+            if hi > lo + 1 {
+                digits.push(lo + (hi - lo) / 2);
+                break;
+            }
+            digits.push(lo);
+            below_high |= lo < hi;
+        }
+        Self(digits)
+    }
+}
+
+/// When and where a register was last edited
+///
+/// Stamps order by time, then device, then sequence number.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub struct Stamp {
+    /// Unix timestamp of the edit
+    pub modified_at: u64,
+    /// Device that made the edit; empty for migrated layouts
+    pub device_id: String,
+    /// The device's edit counter at the edit
+    pub seq: u64,
+}
+
+/// A value with the stamp of its last edit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Register<T> {
+    /// Current value
+    pub value: T,
+    /// Last edit
+    pub stamp: Stamp,
+}
+
+impl<T: Clone + PartialEq> Register<T> {
+    fn new(value: T, stamp: Stamp) -> Self {
+        Self { value, stamp }
+    }
+
+    /// Record a local edit; returns whether the value changed
+    ///
+    /// The stamp is always later than the one it replaces, even if the
+    /// clock went back.
+    fn set(&mut self, value: T, device_id: &str, seq: u64, now: u64) -> bool {
+        if self.value == value {
+            return false;
+        }
+        self.value = value;
+        self.stamp = Stamp {
+            modified_at: now.max(self.stamp.modified_at + 1),
+            device_id: device_id.to_string(),
+            seq,
+        };
+        true
+    }
+
+    /// Merge the remote copy of the register
+    fn merge(
+        &mut self,
+        remote: &Self,
+        local_seen: &BTreeMap<String, u64>,
+        remote_seen: &BTreeMap<String, u64>,
+    ) -> RegisterMerge {
+        if self.value == remote.value {
+            // Converge on the same stamp, so later merges agree
+            if remote.stamp > self.stamp {
+                self.stamp = remote.stamp.clone();
+            }
+            return RegisterMerge::Unchanged;
+        }
+
+        let concurrent = !knows(local_seen, &remote.stamp) && !knows(remote_seen, &self.stamp);
+        let take_remote = remote.stamp > self.stamp;
+        if take_remote {
+            *self = remote.clone();
+        }
+        match (concurrent, take_remote) {
+            (true, true) => RegisterMerge::Conflict(ConflictResolution::TookRemote),
+            (true, false) => RegisterMerge::Conflict(ConflictResolution::KeptLocal),
+            (false, true) => RegisterMerge::TookRemote,
+            (false, false) => RegisterMerge::Unchanged,
+        }
+    }
+}
+
+/// Outcome of merging one register
+enum RegisterMerge {
+    Unchanged,
+    TookRemote,
+    Conflict(ConflictResolution),
+}
+
+/// Whether a layout has seen the edit that made `stamp`
+fn knows(seen: &BTreeMap<String, u64>, stamp: &Stamp) -> bool {
+    seen.get(&stamp.device_id).copied().unwrap_or(0) >= stamp.seq
+}
+
+/// One app's place in the layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LayoutItem {
+    /// Application ID
+    pub app_id: String,
+    /// Position in the grid; `None` once the app was removed from it
+    pub placement: Register<Option<OrderKey>>,
+    /// Folder the app is in, if any
+    pub folder: Register<Option<String>>,
+    /// Whether the app is pinned
+    pub pinned: Register<bool>,
+}
+
+impl LayoutItem {
+    fn new(app_id: &str) -> Self {
+        Self {
+            app_id: app_id.to_string(),
+            placement: Register::new(None, Stamp::default()),
+            folder: Register::new(None, Stamp::default()),
+            pinned: Register::new(false, Stamp::default()),
+        }
+    }
+}
+
+/// A register of a layout item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LayoutField {
+    /// Position in the grid, or removal from it
+    Placement,
+    /// Folder membership
+    Folder,
+    /// Pin
+    Pinned,
+}
+
+/// Which side won a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    /// The local edit was later
+    KeptLocal,
+    /// The remote edit was later
+    TookRemote,
+}
+
+/// A register edited on both sides since they last synced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutConflict {
+    /// Application ID
+    pub app_id: String,
+    /// Register edited on both sides
+    pub field: LayoutField,
+    /// Side whose edit was kept
+    pub resolution: ConflictResolution,
+    /// Stamp of the kept edit
+    pub winner: Stamp,
+}
+
+/// What merging a remote layout changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeReport {
+    /// Apps with a register taken from the remote layout
+    pub changed: Vec<String>,
+    /// Registers edited on both sides, with the resolution taken
+    pub conflicts: Vec<LayoutConflict>,
+}
+
+impl MergeReport {
+    /// Whether the merge changed nothing
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// Launcher layout of one user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LauncherLayout {
+    /// Application IDs on the grid, in order
+    pub app_ids: Vec<String>,
+    /// Placement, folder and pin of every app laid out, by app ID
+    #[serde(default)]
+    pub items: Vec<LayoutItem>,
+    /// Latest edit counter seen from each device
+    #[serde(default)]
+    pub seen: BTreeMap<String, u64>,
+    /// Last updated timestamp
+    pub updated_at: u64,
+}
+
+impl LauncherLayout {
+    /// Create a new empty layout
+    pub fn new() -> Self {
+        Self {
+            app_ids: Vec::new(),
+            items: Vec::new(),
+            seen: BTreeMap::new(),
+            updated_at: crate::time::now_unix(),
+        }
+    }
+
+    /// Create a layout with app IDs in order, as if migrated from a layout
+    /// saved before ordering keys
+    pub fn with_apps(app_ids: Vec<String>) -> Self {
+        let mut layout = Self {
+            app_ids,
+            ..Self::new()
+        };
+        layout.migrate();
+        layout
+    }
+
+    /// Update timestamp
+    pub fn touch(&mut self) {
+        self.updated_at = crate::time::now_unix();
+    }
+
+    /// The item of an app, if it was ever laid out
+    pub fn item(&self, app_id: &str) -> Option<&LayoutItem> {
+        self.items.iter().find(|item| item.app_id == app_id)
+    }
+
+    /// Give a layout saved before ordering keys an item for each app
+    ///
+    /// Keys are spaced by position and stamped with the layout's update
+    /// time and no device, so every device migrating the same list gets the
+    /// same items. Layouts that have items are left alone.
+    pub fn migrate(&mut self) {
+        if !self.items.is_empty() || self.app_ids.is_empty() {
+            return;
+        }
+
+        let stamp = Stamp {
+            modified_at: self.updated_at,
+            ..Stamp::default()
+        };
+        let mut previous: Option<OrderKey> = None;
+        for (index, app_id) in dedup(&self.app_ids).into_iter().enumerate() {
+            let key = u32::try_from(index + 1)
+                .ok()
+                .and_then(|n| n.checked_mul(MIGRATION_SPACING))
+                .filter(|digit| *digit < KEY_BASE)
+                .map(|digit| OrderKey(vec![digit]))
+                .unwrap_or_else(|| OrderKey::between(previous.as_ref(), None));
+            let mut item = LayoutItem::new(app_id);
+            item.placement = Register::new(Some(key.clone()), stamp.clone());
+            item.folder.stamp = stamp.clone();
+            item.pinned.stamp = stamp.clone();
+            self.items.push(item);
+            previous = Some(key);
+        }
+        self.items.sort_by(|a, b| a.app_id.cmp(&b.app_id));
+        self.refresh_app_ids();
+    }
+
+    /// Lay out apps in the given order, as an edit made on `device_id`
+    ///
+    /// Apps keep their key where their order relative to the others did not
+    /// change; only moved and new apps get new keys. Apps left out are
+    /// removed from the grid. Returns whether anything changed.
+    pub fn set_order(&mut self, app_ids: &[String], device_id: &str, now: u64) -> bool {
+        self.migrate();
+        let order = dedup(app_ids);
+
+        let rank: HashMap<&str, usize> = self
+            .app_ids
+            .iter()
+            .enumerate()
+            .map(|(rank, id)| (id.as_str(), rank))
+            .collect();
+        let kept = longest_increasing(
+            &order
+                .iter()
+                .map(|id| rank.get(id).copied())
+                .collect::<Vec<_>>(),
+        );
+        let placed_key = |id: &str| self.item(id).and_then(|item| item.placement.value.clone());
+
+        let mut keys: Vec<OrderKey> = Vec::with_capacity(order.len());
+        for (index, id) in order.iter().enumerate() {
+            let key = match placed_key(id).filter(|_| kept[index]) {
+                Some(key) => key,
+                None => {
+                    let high = (index + 1..order.len())
+                        .find(|next| kept[*next])
+                        .and_then(|next| placed_key(order[next]));
+                    OrderKey::between(keys.last(), high.as_ref())
+                }
+            };
+            keys.push(key);
+        }
+
+        let removed: Vec<String> = self
+            .app_ids
+            .iter()
+            .filter(|id| !order.contains(&id.as_str()))
+            .cloned()
+            .collect();
+        let seq = self.next_seq(device_id);
+        let mut changed = false;
+        for (id, key) in order.iter().zip(keys) {
+            changed |= self
+                .item_mut(id)
+                .placement
+                .set(Some(key), device_id, seq, now);
+        }
+        for id in removed {
+            changed |= self.item_mut(&id).placement.set(None, device_id, seq, now);
+        }
+        self.finish_edit(changed, device_id, seq, now)
+    }
+
+    /// Remove an app from the grid, as an edit made on `device_id`
+    ///
+    /// Returns whether the app was on the grid.
+    pub fn remove(&mut self, app_id: &str, device_id: &str, now: u64) -> bool {
+        self.migrate();
+        if self.item(app_id).is_none() {
+            return false;
+        }
+        let seq = self.next_seq(device_id);
+        let changed = self
+            .item_mut(app_id)
+            .placement
+            .set(None, device_id, seq, now);
+        self.finish_edit(changed, device_id, seq, now)
+    }
+
+    /// Put an app back on the grid at `key`, as an edit made on `device_id`
+    ///
+    /// Its folder and pin are kept from before it was removed. Returns
+    /// whether anything changed.
+    pub fn place(&mut self, app_id: &str, key: OrderKey, device_id: &str, now: u64) -> bool {
+        self.migrate();
+        let seq = self.next_seq(device_id);
+        let changed = self
+            .item_mut(app_id)
+            .placement
+            .set(Some(key), device_id, seq, now);
+        self.finish_edit(changed, device_id, seq, now)
+    }
+
+    /// Put an app in a folder, or take it out with `None`, as an edit made
+    /// on `device_id`
+    ///
+    /// Returns whether anything changed.
+    pub fn set_folder(
+        &mut self,
+        app_id: &str,
+        folder: Option<&str>,
+        device_id: &str,
+        now: u64,
+    ) -> bool {
+        self.migrate();
+        let seq = self.next_seq(device_id);
+        let changed =
+            self.item_mut(app_id)
+                .folder
+                .set(folder.map(str::to_string), device_id, seq, now);
+        self.finish_edit(changed, device_id, seq, now)
+    }
+
+    /// Pin or unpin an app, as an edit made on `device_id`
+    ///
+    /// Returns whether anything changed.
+    pub fn set_pinned(&mut self, app_id: &str, pinned: bool, device_id: &str, now: u64) -> bool {
+        self.migrate();
+        let seq = self.next_seq(device_id);
+        let changed = self
+            .item_mut(app_id)
+            .pinned
+            .set(pinned, device_id, seq, now);
+        self.finish_edit(changed, device_id, seq, now)
+    }
+
+    /// Merge a layout from another device into this one
+    ///
+    /// Each register keeps its latest edit. Merging the same remote layout
+    /// again changes nothing.
+    pub fn merge(&mut self, remote: &LauncherLayout) -> MergeReport {
+        self.migrate();
+        let mut remote = remote.clone();
+        remote.migrate();
+        let mut report = MergeReport::default();
+
+        for theirs in &remote.items {
+            let Some(ours) = self
+                .items
+                .iter_mut()
+                .find(|item| item.app_id == theirs.app_id)
+            else {
+                self.items.push(theirs.clone());
+                report.changed.push(theirs.app_id.clone());
+                continue;
+            };
+
+            let outcomes = [
+                (
+                    LayoutField::Placement,
+                    ours.placement
+                        .merge(&theirs.placement, &self.seen, &remote.seen),
+                    ours.placement.stamp.clone(),
+                ),
+                (
+                    LayoutField::Folder,
+                    ours.folder.merge(&theirs.folder, &self.seen, &remote.seen),
+                    ours.folder.stamp.clone(),
+                ),
+                (
+                    LayoutField::Pinned,
+                    ours.pinned.merge(&theirs.pinned, &self.seen, &remote.seen),
+                    ours.pinned.stamp.clone(),
+                ),
+            ];
+            let mut changed = false;
+            for (field, outcome, winner) in outcomes {
+                let resolution = match outcome {
+                    RegisterMerge::Unchanged => continue,
+                    RegisterMerge::TookRemote => ConflictResolution::TookRemote,
+                    RegisterMerge::Conflict(resolution) => {
+                        report.conflicts.push(LayoutConflict {
+                            app_id: ours.app_id.clone(),
+                            field,
+                            resolution,
+                            winner,
+                        });
+                        resolution
+                    }
+                };
+                changed |= resolution == ConflictResolution::TookRemote;
+            }
+            if changed {
+                report.changed.push(ours.app_id.clone());
+            }
+        }
+
+        for (device_id, seq) in remote.seen {
+            let seen = self.seen.entry(device_id).or_default();
+            *seen = (*seen).max(seq);
+        }
+        self.updated_at = self.updated_at.max(remote.updated_at);
+        self.items.sort_by(|a, b| a.app_id.cmp(&b.app_id));
+        self.refresh_app_ids();
+        report
+    }
+
+    /// The item of an app, created unplaced if missing
+    fn item_mut(&mut self, app_id: &str) -> &mut LayoutItem {
+        let index = match self
+            .items
+            .binary_search_by(|item| item.app_id.as_str().cmp(app_id))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.items.insert(index, LayoutItem::new(app_id));
+                index
+            }
+        };
+        &mut self.items[index]
+    }
+
+    /// Edit counter for the next edit on `device_id`
+    fn next_seq(&self, device_id: &str) -> u64 {
+        self.seen.get(device_id).copied().unwrap_or(0) + 1
+    }
+
+    /// Record an edit that used `seq`, if it changed anything
+    fn finish_edit(&mut self, changed: bool, device_id: &str, seq: u64, now: u64) -> bool {
+        if changed {
+            self.seen.insert(device_id.to_string(), seq);
+            self.updated_at = now;
+            self.refresh_app_ids();
+        }
+        changed
+    }
+
+    /// Rebuild `app_ids` from the placed items
+    fn refresh_app_ids(&mut self) {
+        let mut placed: Vec<(&OrderKey, &String)> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.placement.value.as_ref()?, &item.app_id)))
+            .collect();
+        placed.sort();
+        self.app_ids = placed.into_iter().map(|(_, id)| id.clone()).collect();
+    }
+}
+
+impl Default for LauncherLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// IDs in order, without repeats
+fn dedup(ids: &[String]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    ids.iter()
+        .map(String::as_str)
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+/// Which ranks form a longest increasing run, ignoring `None`s
+fn longest_increasing(ranks: &[Option<usize>]) -> Vec<bool> {
+    // Length of the longest run ending at each index, and its previous index
+    let mut best: Vec<(usize, Option<usize>)> = Vec::with_capacity(ranks.len());
+    for (index, rank) in ranks.iter().enumerate() {
+        let Some(rank) = rank else {
+            best.push((0, None));
+            continue;
+        };
+        let previous = (0..index)
+            .filter(|earlier| ranks[*earlier].is_some_and(|earlier| earlier < *rank))
+            .max_by_key(|earlier| best[*earlier].0);
+        let length = previous.map_or(0, |previous| best[previous].0) + 1;
+        best.push((length, previous));
+    }
+
+    let mut kept = vec![false; ranks.len()];
+    let mut next = (0..ranks.len())
+        .filter(|index| best[*index].0 > 0)
+        .max_by_key(|index| best[*index].0);
+    while let Some(index) = next {
+        kept[index] = true;
+        next = best[index].1;
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    /// The same migrated layout on two devices
+    fn synced(apps: &[&str]) -> (LauncherLayout, LauncherLayout) {
+        let mut layout = LauncherLayout {
+            app_ids: ids(apps),
+            updated_at: 50,
+            ..LauncherLayout::new()
+        };
+        layout.migrate();
+        (layout.clone(), layout)
+    }
+
+    #[test]
+    fn test_order_keys_fit_between() {
+        let low = OrderKey(vec![3]);
+        let high = OrderKey(vec![4]);
+        let middle = OrderKey::between(Some(&low), Some(&high));
+        assert!(low < middle && middle < high);
+
+        let mut key = OrderKey::between(None, None);
+        for _ in 0..100 {
+            let next = OrderKey::between(Some(&key), None);
+            assert!(next > key);
+            let first = OrderKey::between(None, Some(&key));
+            assert!(first < key);
+            key = next;
+        }
+    }
+
+    #[test]
+    fn test_disjoint_reorders_both_survive() {
+        let (mut phone, mut laptop) = synced(&["a", "b", "c", "d", "e", "f"]);
+        phone.set_order(&ids(&["b", "c", "a", "d", "e", "f"]), "phone", 100);
+        laptop.set_order(&ids(&["a", "b", "c", "f", "d", "e"]), "laptop", 100);
+
+        let from_laptop = phone.merge(&laptop);
+        let from_phone = laptop.merge(&phone);
+
+        assert_eq!(phone.app_ids, ids(&["b", "c", "a", "f", "d", "e"]));
+        assert_eq!(laptop.app_ids, phone.app_ids);
+        assert!(from_laptop.conflicts.is_empty() && from_phone.conflicts.is_empty());
+        assert_eq!(from_laptop.changed, vec!["f"]);
+        assert_eq!(phone, laptop);
+    }
+
+    #[test]
+    fn test_same_item_conflict_resolved_by_timestamp() {
+        let (mut phone, mut laptop) = synced(&["a", "b", "c", "d"]);
+        phone.set_order(&ids(&["b", "c", "a", "d"]), "phone", 100);
+        laptop.set_order(&ids(&["b", "c", "d", "a"]), "laptop", 200);
+
+        let report = phone.merge(&laptop);
+        assert_eq!(phone.app_ids, ids(&["b", "c", "d", "a"]));
+        assert_eq!(
+            report.conflicts,
+            vec![LayoutConflict {
+                app_id: "a".to_string(),
+                field: LayoutField::Placement,
+                resolution: ConflictResolution::TookRemote,
+                winner: laptop.item("a").unwrap().placement.stamp.clone(),
+            }]
+        );
+
+        let report = laptop.merge(&phone);
+        assert_eq!(report.conflicts.len(), 0);
+        assert_eq!(laptop, phone);
+
+        // An edit made after seeing the other side is not a conflict
+        phone.set_order(&ids(&["a", "b", "c", "d"]), "phone", 300);
+        let report = laptop.merge(&phone);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(laptop.app_ids, ids(&["a", "b", "c", "d"]));
+    }
+
+    #[test]
+    fn test_folder_and_pin_merges() {
+        let (mut phone, mut laptop) = synced(&["a", "b", "c"]);
+        phone.set_folder("a", Some("Games"), "phone", 100);
+        laptop.set_folder("b", Some("Games"), "laptop", 100);
+        laptop.set_pinned("a", true, "laptop", 110);
+        // Removal on one side and a folder change on the other both apply
+        laptop.remove("c", "laptop", 120);
+        phone.set_folder("c", Some("Tools"), "phone", 130);
+
+        phone.merge(&laptop);
+        laptop.merge(&phone);
+        assert_eq!(phone, laptop);
+
+        let folder = |id: &str| phone.item(id).unwrap().folder.value.clone();
+        assert_eq!(folder("a").as_deref(), Some("Games"));
+        assert_eq!(folder("b").as_deref(), Some("Games"));
+        assert!(phone.item("a").unwrap().pinned.value);
+        assert_eq!(phone.app_ids, ids(&["a", "b"]));
+    }
+
+    #[test]
+    fn test_migration_from_index_format() -> crate::error::Result<()> {
+        let legacy: LauncherLayout =
+            serde_json::from_str(r#"{"app_ids":["b","a","c"],"updated_at":42}"#)?;
+        assert!(legacy.items.is_empty());
+
+        let mut migrated = legacy.clone();
+        migrated.migrate();
+        assert_eq!(migrated.app_ids, ids(&["b", "a", "c"]));
+        assert_eq!(migrated.items.len(), 3);
+        assert_eq!(migrated.item("b").unwrap().placement.stamp.modified_at, 42);
+
+        // Two devices migrating the same list agree, even merging unmigrated
+        let mut other = legacy.clone();
+        assert!(other.merge(&legacy).is_empty());
+        assert_eq!(other, migrated);
+
+        let json = serde_json::to_string(&migrated)?;
+        assert_eq!(serde_json::from_str::<LauncherLayout>(&json)?, migrated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remerge_is_idempotent() {
+        let (mut phone, mut laptop) = synced(&["a", "b", "c"]);
+        phone.set_order(&ids(&["c", "a", "b", "d"]), "phone", 100);
+        laptop.set_order(&ids(&["a", "c", "b"]), "laptop", 90);
+        laptop.set_pinned("b", true, "laptop", 95);
+
+        let first = phone.merge(&laptop);
+        assert!(!first.is_empty());
+        let merged = phone.clone();
+
+        assert!(phone.merge(&laptop).is_empty());
+        assert_eq!(phone, merged);
+    }
+}
@@ -0,0 +1,84 @@
+//! Materialization models for Osnova
+//!
+//! An installed app is an application row plus its components in the
+//! cache. The two can drift apart: a backup restored on a new device brings
+//! the rows but not the cache, and the cache can be cleared or evicted. An
+//! app whose components are all present and match their manifest hashes is
+//! materialized; one missing some or all of them is installed but has to be
+//! downloaded again before it can launch.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Whether an installed app's components are in the cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum Materialization {
+    /// Every component is cached and matches its manifest hash
+    Materialized,
+    /// Some components are missing or fail verification
+    #[serde(rename_all = "camelCase")]
+    PartiallyMaterialized {
+        /// IDs of the missing components
+        missing: Vec<String>,
+    },
+    /// No component is usable, or the app was restored without its cache
+    NotMaterialized,
+}
+
+impl Materialization {
+    /// State of an app with `total` components, `missing` of which are
+    /// absent or fail verification
+    pub fn from_missing(total: usize, missing: Vec<String>) -> Self {
+        if missing.is_empty() {
+            Self::Materialized
+        } else if missing.len() >= total {
+            Self::NotMaterialized
+        } else {
+            Self::PartiallyMaterialized { missing }
+        }
+    }
+
+    /// Whether the app can launch without downloading anything
+    pub fn is_materialized(&self) -> bool {
+        matches!(self, Self::Materialized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_missing() {
+        assert_eq!(
+            Materialization::from_missing(2, vec![]),
+            Materialization::Materialized
+        );
+        assert_eq!(
+            Materialization::from_missing(2, vec!["a".to_string()]),
+            Materialization::PartiallyMaterialized {
+                missing: vec!["a".to_string()]
+            }
+        );
+        assert_eq!(
+            Materialization::from_missing(2, vec!["a".to_string(), "b".to_string()]),
+            Materialization::NotMaterialized
+        );
+    }
+
+    #[test]
+    fn test_serialization() {
+        let state = Materialization::PartiallyMaterialized {
+            missing: vec!["backend".to_string()],
+        };
+        assert_eq!(
+            serde_json::to_value(&state).unwrap(),
+            serde_json::json!({ "state": "partiallyMaterialized", "missing": ["backend"] })
+        );
+        assert_eq!(
+            serde_json::to_value(Materialization::NotMaterialized).unwrap(),
+            serde_json::json!({ "state": "notMaterialized" })
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Mutation receipts for optimistic UI updates
+//!
+//! The frontend applies a toggle or a drag locally before the core has
+//! answered, then reconciles. Each mutation carries an id chosen by the
+//! caller; the core answers with a [`MutationReceipt`] naming the
+//! generation the mutation produced. Re-sending the same id (a retry after
+//! a lost response) returns the first receipt without applying anything
+//! again. A failed mutation is reported as a [`MutationRejected`] carrying
+//! the last generation known to be good, so the frontend can roll back to
+//! it, and [`Since`] lets it fetch the authoritative state only when that
+//! generation moved on.
+
+use bip39::rand::{thread_rng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Proof that a mutation was applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MutationReceipt {
+    /// Generation of the state once the mutation was applied
+    pub generation: u64,
+    /// Id the mutation was sent with
+    pub mutation_id: String,
+}
+
+/// A mutation that was not applied
+///
+/// Attached as context to the error that stopped it, so the underlying
+/// error (such as [`OsnovaError::InvalidSettings`](crate::error::OsnovaError::InvalidSettings))
+/// can still be downcast.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, thiserror::Error)]
+#[serde(rename_all = "camelCase")]
+#[error("Mutation {mutation_id} was not applied: {reason}")]
+pub struct MutationRejected {
+    /// Id the mutation was sent with
+    pub mutation_id: String,
+    /// Generation of the state as it still is; `None` if it could not be
+    /// read either
+    pub last_good_generation: Option<u64>,
+    /// Why the mutation failed
+    pub reason: String,
+}
+
+impl MutationRejected {
+    /// Attach rollback information to the error that stopped a mutation
+    pub fn wrap(
+        error: anyhow::Error,
+        mutation_id: &str,
+        last_good_generation: Option<u64>,
+    ) -> anyhow::Error {
+        let rejection = Self {
+            mutation_id: mutation_id.to_string(),
+            last_good_generation,
+            reason: format!("{:#}", error),
+        };
+        error.context(rejection)
+    }
+}
+
+/// Authoritative state, sent only when it changed since a generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Since<T> {
+    /// The caller's generation is current; nothing to send
+    Unchanged {
+        /// Current generation
+        generation: u64,
+    },
+    /// The state moved on since the caller's generation
+    Changed {
+        /// Current generation
+        generation: u64,
+        /// Current state
+        state: T,
+    },
+}
+
+impl<T> Since<T> {
+    /// Compare a caller's generation with the current one, loading the
+    /// state only when they differ
+    pub fn check<E>(
+        known: u64,
+        current: u64,
+        load: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Self, E> {
+        if known == current {
+            Ok(Self::Unchanged {
+                generation: current,
+            })
+        } else {
+            Ok(Self::Changed {
+                generation: current,
+                state: load()?,
+            })
+        }
+    }
+
+    /// Current generation
+    pub fn generation(&self) -> u64 {
+        match self {
+            Self::Unchanged { generation } | Self::Changed { generation, .. } => *generation,
+        }
+    }
+}
+
+/// Fresh mutation id, for callers that do not retry
+pub fn new_mutation_id() -> String {
+    let mut id = [0u8; 16];
+    thread_rng().fill_bytes(&mut id);
+    hex::encode(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since() {
+        let unchanged =
+            Since::<&str>::check(3, 3, || -> Result<_, ()> { panic!("loaded") }).unwrap();
+        assert_eq!(
+            serde_json::to_value(&unchanged).unwrap(),
+            serde_json::json!({ "status": "unchanged", "generation": 3 })
+        );
+
+        let changed = Since::check(2, 3, || Ok::<_, ()>("state")).unwrap();
+        assert_eq!(changed.generation(), 3);
+        assert_eq!(
+            serde_json::to_value(&changed).unwrap(),
+            serde_json::json!({ "status": "changed", "generation": 3, "state": "state" })
+        );
+    }
+
+    #[test]
+    fn test_rejection_keeps_underlying_error() {
+        let error = MutationRejected::wrap(
+            crate::error::OsnovaError::Unauthorized("no session".to_string()).into(),
+            "m-1",
+            Some(4),
+        );
+        let rejection = error.downcast_ref::<MutationRejected>().unwrap();
+        assert_eq!(rejection.last_good_generation, Some(4));
+        assert_eq!(rejection.reason, "Unauthorized: no session");
+        assert!(matches!(
+            error.downcast_ref::<crate::error::OsnovaError>(),
+            Some(crate::error::OsnovaError::Unauthorized(_))
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Mutation m-1 was not applied: Unauthorized: no session"
+        );
+    }
+
+    #[test]
+    fn test_new_mutation_id() {
+        assert_eq!(new_mutation_id().len(), 32);
+        assert_ne!(new_mutation_id(), new_mutation_id());
+    }
+}
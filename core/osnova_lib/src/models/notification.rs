@@ -0,0 +1,117 @@
+//! Notification models for Osnova
+//!
+//! This module provides the Notification type posted by apps, and the
+//! StoredNotification record kept in each user's notification center.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How prominently a notification is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    /// Informational (default)
+    #[default]
+    Info,
+    /// Something completed successfully
+    Success,
+    /// Needs the user's attention
+    Warning,
+    /// Something failed
+    Error,
+}
+
+/// A notification posted by an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Short title
+    pub title: String,
+    /// Body text
+    #[serde(default)]
+    pub body: String,
+    /// Severity
+    #[serde(default)]
+    pub level: NotificationLevel,
+    /// Route inside the app to open when the notification is clicked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_route: Option<String>,
+    /// Deduplication key; a second post with the same tag is dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+impl Notification {
+    /// Create an informational notification
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            level: NotificationLevel::Info,
+            action_route: None,
+            tag: None,
+        }
+    }
+
+    /// Set the severity
+    pub fn with_level(mut self, level: NotificationLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the route opened when the notification is clicked
+    pub fn with_action_route(mut self, route: impl Into<String>) -> Self {
+        self.action_route = Some(route.into());
+        self
+    }
+
+    /// Set the deduplication key
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+}
+
+/// A notification in a user's notification center
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredNotification {
+    /// Notification identifier
+    pub id: i64,
+    /// App that posted the notification
+    pub app_id: String,
+    /// Unix timestamp when the notification was posted
+    pub posted_at: u64,
+    /// Whether the user has read the notification
+    pub read: bool,
+    /// The notification itself
+    #[serde(flatten)]
+    pub notification: Notification,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_notification_serializes_flat() {
+        let stored = StoredNotification {
+            id: 7,
+            app_id: "com.osnova.wallet".to_string(),
+            posted_at: 100,
+            read: false,
+            notification: Notification::new("Payment request", "5 ANT from alice")
+                .with_level(NotificationLevel::Warning)
+                .with_action_route("/payments/abc"),
+        };
+
+        let json = serde_json::to_value(&stored).unwrap();
+        assert_eq!(json["title"], "Payment request");
+        assert_eq!(json["level"], "warning");
+        assert_eq!(json["actionRoute"], "/payments/abc");
+        assert!(json.get("tag").is_none());
+
+        let parsed: StoredNotification = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, stored);
+    }
+}
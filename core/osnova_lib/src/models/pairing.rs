@@ -3,8 +3,9 @@
 //! This module provides the PairingSession type which manages:
 //! - Client-server pairing sessions
 //! - QR code-based pairing with short-lived codes
-//! - Session lifecycle (pending -> established | failed)
+//! - Session lifecycle (pending -> awaiting confirmation -> established | failed)
 //! - Public key exchange for mutual authentication
+//! - The confirmation code both users compare before a session is trusted
 //!
 //! # Example
 //!
@@ -24,26 +25,78 @@
 //! assert_eq!(session.status(), PairingStatus::Established);
 //! ```
 
+use crate::time::{self, Clock};
 use crate::{OsnovaError, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Pairing session status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum PairingStatus {
     /// Pairing initiated but not yet completed
     Pending,
+    /// Keys exchanged; waiting for the users to confirm the codes match
+    #[serde(rename = "awaiting_confirmation")]
+    AwaitingConfirmation,
     /// Pairing successfully established
     Established,
     /// Pairing failed
     Failed,
 }
 
+impl PairingStatus {
+    /// Name used in storage and on the wire
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PairingStatus::Pending => "pending",
+            PairingStatus::AwaitingConfirmation => "awaiting_confirmation",
+            PairingStatus::Established => "established",
+            PairingStatus::Failed => "failed",
+        }
+    }
+}
+
+impl std::str::FromStr for PairingStatus {
+    type Err = OsnovaError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(PairingStatus::Pending),
+            "awaiting_confirmation" => Ok(PairingStatus::AwaitingConfirmation),
+            "established" => Ok(PairingStatus::Established),
+            "failed" => Ok(PairingStatus::Failed),
+            other => Err(OsnovaError::Identity(format!(
+                "Unknown pairing status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Confirmation code step of a pairing
+///
+/// Once both sides have exchanged keys, each derives the same short code
+/// from the key exchange and shows it. The session is established only
+/// after the users of both devices confirm that the codes match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingConfirmation {
+    /// Code shown to the user (6 digits)
+    pub code: String,
+    /// Unix timestamp after which the session fails unconfirmed
+    pub deadline: u64,
+    /// Whether the user of this device confirmed the codes match
+    pub local_confirmed: bool,
+    /// Whether the user of the other device confirmed the codes match
+    pub peer_confirmed: bool,
+}
+
 /// Pairing session for client-server authentication
 ///
 /// Represents a pairing session between a client device and a server.
-/// Sessions progress through states: pending -> established | failed.
+/// Sessions progress through states: pending -> awaiting confirmation ->
+/// established | failed.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PairingSession {
     /// Unique session identifier
@@ -63,6 +116,10 @@ pub struct PairingSession {
 
     /// Session status
     status: PairingStatus,
+
+    /// Confirmation code step (None until keys were exchanged)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirmation: Option<PairingConfirmation>,
 }
 
 impl PairingSession {
@@ -113,6 +170,7 @@ impl PairingSession {
             established_at: None,
             expires_at: None,
             status: PairingStatus::Pending,
+            confirmation: None,
         })
     }
 
@@ -165,11 +223,36 @@ impl PairingSession {
         self.status
     }
 
+    /// Get the confirmation code step, once keys were exchanged
+    pub fn confirmation(&self) -> Option<&PairingConfirmation> {
+        self.confirmation.as_ref()
+    }
+
+    /// Rebuild a session read back from storage
+    pub(crate) fn restored(
+        mut self,
+        status: PairingStatus,
+        established_at: Option<u64>,
+        expires_at: Option<u64>,
+        confirmation: Option<PairingConfirmation>,
+    ) -> Self {
+        self.status = status;
+        self.established_at = established_at;
+        self.expires_at = expires_at;
+        self.confirmation = confirmation;
+        self
+    }
+
     /// Check if the session is pending
     pub fn is_pending(&self) -> bool {
         self.status == PairingStatus::Pending
     }
 
+    /// Check if the session is waiting for the users to confirm the codes
+    pub fn is_awaiting_confirmation(&self) -> bool {
+        self.status == PairingStatus::AwaitingConfirmation
+    }
+
     /// Check if the session is established
     pub fn is_established(&self) -> bool {
         self.status == PairingStatus::Established
@@ -201,11 +284,70 @@ impl PairingSession {
     /// assert!(!session.is_expired());
     /// ```
     pub fn is_expired(&self) -> bool {
-        if let Some(expires_at) = self.expires_at {
-            let now = Self::current_timestamp();
-            now > expires_at
-        } else {
-            false
+        self.is_expired_at(time::now_unix())
+    }
+
+    /// Check if the session has expired according to `clock`
+    pub fn is_expired_with(&self, clock: &dyn Clock) -> bool {
+        self.is_expired_at(clock.now_unix())
+    }
+
+    /// Check if the session has expired at Unix time `now`
+    fn is_expired_at(&self, now: u64) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now > expires_at,
+            None => false,
+        }
+    }
+
+    /// Check if the confirmation deadline passed according to `clock`
+    pub fn is_confirmation_overdue_with(&self, clock: &dyn Clock) -> bool {
+        self.is_awaiting_confirmation()
+            && self
+                .confirmation
+                .as_ref()
+                .is_some_and(|confirmation| clock.now_unix() > confirmation.deadline)
+    }
+
+    /// Move a pending session to the confirmation code step
+    ///
+    /// Has no effect unless the session is pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Code derived from the key exchange, shown to the user
+    /// * `deadline` - Unix timestamp after which the session fails unconfirmed
+    pub fn await_confirmation(&mut self, code: impl Into<String>, deadline: u64) {
+        if self.status == PairingStatus::Pending {
+            self.status = PairingStatus::AwaitingConfirmation;
+            self.confirmation = Some(PairingConfirmation {
+                code: code.into(),
+                deadline,
+                local_confirmed: false,
+                peer_confirmed: false,
+            });
+        }
+    }
+
+    /// Record that the user of this device (`local`) or of the other device
+    /// confirmed the codes match
+    ///
+    /// The session is established at `timestamp` once both users have
+    /// confirmed. Has no effect unless the session awaits confirmation.
+    pub fn record_confirmation(&mut self, local: bool, timestamp: u64) {
+        if self.status != PairingStatus::AwaitingConfirmation {
+            return;
+        }
+        if let Some(confirmation) = self.confirmation.as_mut() {
+            if local {
+                confirmation.local_confirmed = true;
+            } else {
+                confirmation.peer_confirmed = true;
+            }
+            if confirmation.local_confirmed && confirmation.peer_confirmed {
+                self.status = PairingStatus::Established;
+                self.established_at = Some(timestamp);
+            }
         }
     }
 
@@ -246,7 +388,8 @@ impl PairingSession {
 
     /// Mark the session as failed
     ///
-    /// Updates the status to Failed.
+    /// Updates the status of a pending session, or one awaiting
+    /// confirmation, to Failed.
     /// This is idempotent - marking an already-failed session has no effect.
     ///
     /// # Example
@@ -262,23 +405,26 @@ impl PairingSession {
     /// assert_eq!(session.status(), PairingStatus::Failed);
     /// ```
     pub fn mark_failed(&mut self) {
-        if self.status == PairingStatus::Pending {
+        if matches!(
+            self.status,
+            PairingStatus::Pending | PairingStatus::AwaitingConfirmation
+        ) {
             self.status = PairingStatus::Failed;
         }
     }
 
     /// Get current Unix timestamp
     fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before Unix epoch")
-            .as_secs()
+        time::now_unix()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::{HybridClock, MockClock};
+    use std::sync::Arc;
+    use std::time::Duration;
 
     fn sample_server_key() -> Vec<u8> {
         vec![1u8; 32]
@@ -302,6 +448,56 @@ mod tests {
             serde_json::to_string(&PairingStatus::Failed).unwrap(),
             "\"failed\""
         );
+        assert_eq!(
+            serde_json::to_string(&PairingStatus::AwaitingConfirmation).unwrap(),
+            "\"awaiting_confirmation\""
+        );
+        for status in [
+            PairingStatus::Pending,
+            PairingStatus::AwaitingConfirmation,
+            PairingStatus::Established,
+            PairingStatus::Failed,
+        ] {
+            assert_eq!(status.as_str().parse::<PairingStatus>().unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn test_confirmation_needs_both_users() {
+        let mut session =
+            PairingSession::new("session-123", &sample_server_key(), &sample_device_key())
+                .expect("Failed to create session");
+
+        // Confirmations before the code step are ignored
+        session.record_confirmation(true, 100);
+        assert!(session.is_pending());
+
+        session.await_confirmation("123456", 500);
+        assert!(session.is_awaiting_confirmation());
+        assert_eq!(session.confirmation().unwrap().code, "123456");
+
+        session.record_confirmation(true, 200);
+        assert!(session.is_awaiting_confirmation());
+        session.record_confirmation(false, 300);
+        assert!(session.is_established());
+        assert_eq!(session.established_at(), Some(300));
+
+        // The deadline only matters while awaiting confirmation
+        let clock = MockClock::new(1_000);
+        assert!(!session.is_confirmation_overdue_with(&clock));
+
+        let mut session =
+            PairingSession::new("session-456", &sample_server_key(), &sample_device_key())
+                .expect("Failed to create session");
+        session.await_confirmation("654321", 500);
+        assert!(session.is_confirmation_overdue_with(&clock));
+        session.mark_failed();
+        assert!(session.is_failed());
+
+        let json = serde_json::to_string(&session).expect("Failed to serialize");
+        let deserialized: PairingSession =
+            serde_json::from_str(&json).expect("Failed to deserialize");
+        assert_eq!(session, deserialized);
     }
 
     #[test]
@@ -451,16 +647,42 @@ mod tests {
             .expect("Failed to create session");
         assert!(session.is_expired());
 
-        // Future expiry
-        let far_future = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            + 3600;
+        // Session expiring an hour from now
+        let clock = MockClock::new(10_000);
         let session =
-            PairingSession::with_expiry("session-456", &server_key, &device_key, far_future)
+            PairingSession::with_expiry("session-456", &server_key, &device_key, 10_000 + 3600)
                 .expect("Failed to create session");
-        assert!(!session.is_expired());
+        assert!(!session.is_expired_with(&clock));
+
+        // Still valid at the expiry instant, expired one second later
+        clock.advance(Duration::from_secs(3600));
+        assert!(!session.is_expired_with(&clock));
+        clock.advance(Duration::from_secs(1));
+        assert!(session.is_expired_with(&clock));
+    }
+
+    #[test]
+    fn test_fresh_session_survives_backwards_clock_jump() {
+        let wall = Arc::new(MockClock::new(10_000));
+        let clock = HybridClock::with_source(wall.clone());
+
+        // Invite created with a five minute lifetime
+        let expires_at = clock.now_unix() + 300;
+        let session = PairingSession::with_expiry(
+            "session-123",
+            &sample_server_key(),
+            &sample_device_key(),
+            expires_at,
+        )
+        .expect("Failed to create session");
+
+        // The wall clock jumping back does not make time run backwards ...
+        wall.set_unix(10_000 - 7_200);
+        assert!(!session.is_expired_with(&clock));
+
+        // ... and the invite still expires once its lifetime has elapsed
+        wall.advance(Duration::from_secs(301));
+        assert!(session.is_expired_with(&clock));
     }
 
     #[test]
@@ -0,0 +1,97 @@
+//! Payment record models for Osnova
+//!
+//! Storing data on the Autonomi network costs tokens. Every upload is
+//! recorded with its size, the address it was stored at, the quoted and
+//! the actual cost, and the wallet payment request that approved it, when
+//! the wallet flow was used, so users can account for what they paid.
+//!
+//! Costs are in AttoTokens (10^-18 ANT).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// AttoTokens in one ANT
+pub const ATTOS_PER_ANT: u64 = 1_000_000_000_000_000_000;
+
+/// Decimal places of an ANT amount counted in AttoTokens
+pub const ANT_DECIMALS: u8 = 18;
+
+/// Symbol of the Autonomi network token
+pub const ANT_SYMBOL: &str = "ANT";
+
+/// How an upload ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PaymentStatus {
+    /// Stored and paid for
+    Completed,
+    /// Not stored; nothing was paid unless the network says otherwise
+    Failed,
+}
+
+impl PaymentStatus {
+    /// Name used in exports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// One upload to the network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRecord {
+    /// Record identifier, assigned when stored
+    pub id: i64,
+    /// Unix timestamp of the upload
+    pub recorded_at: u64,
+    /// Component or manifest the data belongs to, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_id: Option<String>,
+    /// Bytes uploaded
+    pub size: u64,
+    /// ant:// address the data was stored at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Cost quoted before the upload, in AttoTokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted_cost: Option<u64>,
+    /// Cost actually paid, in AttoTokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual_cost: Option<u64>,
+    /// Wallet payment request that approved the upload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_request_id: Option<String>,
+    /// How the upload ended
+    pub status: PaymentStatus,
+}
+
+/// Format AttoTokens as a decimal ANT amount
+///
+/// Always uses `.` as the decimal separator and no grouping, whatever the
+/// locale, and drops trailing zeros.
+pub fn format_ant(attos: u64) -> String {
+    let whole = attos / ATTOS_PER_ANT;
+    let fraction = attos % ATTOS_PER_ANT;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let digits = format!("{:018}", fraction);
+    format!("{}.{}", whole, digits.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_ant() {
+        assert_eq!(format_ant(0), "0");
+        assert_eq!(format_ant(ATTOS_PER_ANT), "1");
+        assert_eq!(format_ant(1_500_000_000_000_000_000), "1.5");
+        assert_eq!(format_ant(1_234), "0.000000000000001234");
+        assert_eq!(format_ant(12 * ATTOS_PER_ANT + 5), "12.000000000000000005");
+    }
+}
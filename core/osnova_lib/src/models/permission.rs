@@ -0,0 +1,126 @@
+//! Runtime permission models for Osnova
+//!
+//! This module provides the Capability taxonomy for permissions granted at
+//! runtime rather than at install, the PermissionPrompt shown to the user,
+//! and the PermissionGrant record kept when the user asks to remember a
+//! decision.
+//!
+//! Capabilities are named like the OpenRPC methods they guard
+//! (`namespace.action`), so the same names can be used wherever methods
+//! are allowed or denied per component.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::OsnovaError;
+
+/// A capability that must be granted by the user at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Capability {
+    /// Read the system clipboard
+    #[serde(rename = "clipboard.read")]
+    ClipboardRead,
+    /// Initiate a payment above the wallet's confirmation threshold
+    #[serde(rename = "payments.send")]
+    PaymentsSend,
+    /// Read data another app shares
+    #[serde(rename = "apps.readSharedData")]
+    ReadSharedData,
+}
+
+impl Capability {
+    /// Every capability, in display order
+    pub const ALL: [Capability; 3] = [
+        Capability::ClipboardRead,
+        Capability::PaymentsSend,
+        Capability::ReadSharedData,
+    ];
+
+    /// Capability name as used over OpenRPC and in storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::ClipboardRead => "clipboard.read",
+            Capability::PaymentsSend => "payments.send",
+            Capability::ReadSharedData => "apps.readSharedData",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Capability {
+    type Err = OsnovaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.as_str() == s)
+            .ok_or_else(|| OsnovaError::Other(format!("Unknown capability: {}", s)))
+    }
+}
+
+/// A permission request waiting for the user's answer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionPrompt {
+    /// Prompt identifier, passed back with the answer
+    pub id: String,
+    /// App whose component asked
+    pub app_id: String,
+    /// Component that asked
+    pub component_id: String,
+    /// Capability requested
+    pub capability: Capability,
+    /// Why the component needs it, shown to the user
+    pub rationale: String,
+    /// What the capability is for when it is granted per target (e.g. one
+    /// data offer); such decisions are kept by the service that asked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Unix timestamp when the prompt was raised
+    pub requested_at: u64,
+    /// Unix timestamp after which the prompt counts as denied
+    pub expires_at: u64,
+}
+
+/// A remembered permission decision for one component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionGrant {
+    /// Application identifier
+    pub app_id: String,
+    /// Component identifier
+    pub component_id: String,
+    /// Capability decided
+    pub capability: Capability,
+    /// Whether the capability was granted or denied
+    pub granted: bool,
+    /// Unix timestamp of the decision
+    pub decided_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capability_names_round_trip() {
+        for capability in Capability::ALL {
+            assert_eq!(
+                capability.as_str().parse::<Capability>().unwrap(),
+                capability
+            );
+            assert_eq!(
+                serde_json::to_string(&capability).unwrap(),
+                format!("\"{}\"", capability)
+            );
+        }
+        assert!("clipboard.write".parse::<Capability>().is_err());
+    }
+}
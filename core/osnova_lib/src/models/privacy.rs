@@ -0,0 +1,61 @@
+//! Network privacy models for Osnova
+//!
+//! What backends declare about their network access, and the hosts the
+//! [`NetworkProxy`](crate::services::network_proxy::NetworkProxy) saw them
+//! reach, side by side in an app's privacy report.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::components::network::NetworkDeclaration;
+
+/// A host a backend asked the network proxy for
+///
+/// Counts accumulate over every launch of the backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservedHost {
+    /// Backend component identifier
+    pub component_id: String,
+    /// Host name or address, lowercased
+    pub host: String,
+    /// Connections the proxy let through
+    pub allowed: u64,
+    /// Connections the proxy refused
+    pub denied: u64,
+    /// Bytes the backend sent to the host
+    pub bytes_sent: u64,
+    /// Bytes the backend received from the host
+    pub bytes_received: u64,
+    /// Unix timestamp of the first connection
+    pub first_seen: u64,
+    /// Unix timestamp of the latest connection
+    pub last_seen: u64,
+}
+
+/// Network access of one backend: declared and observed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendNetworkReport {
+    /// Backend component identifier
+    pub component_id: String,
+    /// Backend name from the manifest
+    pub name: String,
+    /// What the manifest declares; `None` means no network access
+    pub declared: Option<NetworkDeclaration>,
+    /// Hosts the backend reached or tried to reach through the proxy,
+    /// by host
+    pub observed: Vec<ObservedHost>,
+    /// Observed hosts the declaration does not cover
+    pub undeclared: Vec<String>,
+}
+
+/// Declared against observed network access of an app's backends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyReport {
+    /// Application reported on
+    pub app_id: String,
+    /// One entry per backend component
+    pub backends: Vec<BackendNetworkReport>,
+}
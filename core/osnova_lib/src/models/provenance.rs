@@ -0,0 +1,151 @@
+//! Component provenance models for Osnova
+//!
+//! This module provides the ProvenanceRecord type which records, for every
+//! component fetched from its source:
+//! - What was downloaded (component id, version, and content hash)
+//! - Where it came from (source URI and, when known, the manifest, its
+//!   publisher, and its signature)
+//! - When, whether the content was checked against a declared hash, and
+//!   which downloader version fetched it
+//! - For frontends, what the content policy found in the extracted bundle
+//! - For backends, the network access they declare
+//!
+//! Records are append-only; a re-download adds a new record so the history
+//! of a component is preserved.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::models::provenance::ManifestOrigin;
+//!
+//! let origin = ManifestOrigin::from(&app);
+//! let path = downloader.download_for(&component, &origin, &options).await?;
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::components::content_policy::ContentCheck;
+use crate::components::network::NetworkDeclaration;
+use crate::models::application::OsnovaApplication;
+use crate::models::signature::ManifestSignatures;
+
+/// Version of the downloader recorded with each fetch
+pub const DOWNLOADER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How downloaded content was checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum VerificationOutcome {
+    /// Content matched the hash declared in the manifest
+    HashVerified,
+    /// The manifest declared no hash, so the content was not checked
+    NoHashDeclared,
+}
+
+/// Manifest a component was downloaded for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestOrigin {
+    /// Manifest (application) id
+    pub manifest_id: String,
+    /// Manifest (application) version
+    pub manifest_version: String,
+    /// Publisher identifier, if the manifest names one
+    pub publisher: Option<String>,
+    /// Detached manifest signatures, if present
+    pub signature: Option<ManifestSignatures>,
+}
+
+impl From<&OsnovaApplication> for ManifestOrigin {
+    fn from(app: &OsnovaApplication) -> Self {
+        Self {
+            manifest_id: app.id().to_string(),
+            manifest_version: app.version().to_string(),
+            publisher: app.publisher().map(str::to_string),
+            signature: app.signature().cloned(),
+        }
+    }
+}
+
+/// Where and when a component was downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceRecord {
+    /// Component id as listed in the manifest
+    pub component_id: String,
+    /// Component version
+    pub component_version: String,
+    /// Content hash of the downloaded bytes (base64 BLAKE3)
+    pub component_hash: String,
+    /// URI the bytes were fetched from
+    pub source_uri: String,
+    /// Manifest the download was made for, if known
+    pub origin: Option<ManifestOrigin>,
+    /// Unix timestamp of the download
+    pub downloaded_at: u64,
+    /// How the content was verified
+    pub verification: VerificationOutcome,
+    /// Version of the downloader that fetched the component
+    pub downloader_version: String,
+    /// Content policy check of the extracted bundle (frontends only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_check: Option<ContentCheck>,
+    /// Network access the component declares (backends only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkDeclaration>,
+}
+
+impl ProvenanceRecord {
+    /// Publisher of the manifest the component was downloaded for
+    pub fn publisher(&self) -> Option<&str> {
+        self.origin.as_ref()?.publisher.as_deref()
+    }
+}
+
+/// A component the user rebuilt from source and found to match its
+/// manifest hash
+///
+/// Recorded per app version, so an update drops the marker until the new
+/// version is verified again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReproducibilityCheck {
+    /// Application the component belongs to
+    pub app_id: String,
+    /// App version the component was verified for
+    pub app_version: String,
+    /// Component id as listed in the manifest
+    pub component_id: String,
+    /// Hash the local build reproduced
+    pub hash: String,
+    /// Unix timestamp of the verification
+    pub verified_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::{ComponentKind, ComponentRef};
+
+    #[test]
+    fn test_origin_from_application() {
+        let app = OsnovaApplication::new(
+            "ant://manifest",
+            "Wallet",
+            "2.1.0",
+            "icon",
+            "A wallet",
+            vec![ComponentRef::new("ant://ui", "UI", ComponentKind::Frontend, "2.1.0").unwrap()],
+        )
+        .unwrap()
+        .with_publisher("osnova-labs")
+        .with_signature("sig");
+
+        let origin = ManifestOrigin::from(&app);
+        assert_eq!(origin.manifest_id, "ant://manifest");
+        assert_eq!(origin.manifest_version, "2.1.0");
+        assert_eq!(origin.publisher.as_deref(), Some("osnova-labs"));
+        assert_eq!(origin.signature, Some("sig".into()));
+    }
+}
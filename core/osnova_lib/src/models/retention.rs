@@ -0,0 +1,257 @@
+//! Retention models for Osnova
+//!
+//! Stores that grow with use (notifications, crash reports, the audit log,
+//! key usage statistics, provenance records and configuration snapshots)
+//! are each kept under a retention [`Policy`] bounding the age, number and
+//! size of what they hold. The policy of each store is configurable; the
+//! engine applying them lives in [`crate::services::retention`].
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::audit::log::DEFAULT_MAX_ENTRIES;
+use crate::services::key_usage::DAILY_RETENTION_SECS;
+use crate::services::notifications::DEFAULT_NOTIFICATION_CAPACITY;
+use crate::services::provenance::DEFAULT_PROVENANCE_RETENTION_DAYS;
+
+/// Seconds in one day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A store kept under a retention policy
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum RetentionStore {
+    /// Each user's notification center
+    Notifications,
+    /// Crash reports of backend processes
+    CrashReports,
+    /// The hash-chained audit log
+    AuditLog,
+    /// Key usage statistics
+    KeyUsage,
+    /// Component provenance records
+    Provenance,
+    /// Snapshots of per-app configurations; the policy bounds the
+    /// snapshots of each app and user
+    ConfigSnapshots,
+}
+
+impl RetentionStore {
+    /// Every store, in display order
+    pub const ALL: [Self; 6] = [
+        Self::Notifications,
+        Self::CrashReports,
+        Self::AuditLog,
+        Self::KeyUsage,
+        Self::Provenance,
+        Self::ConfigSnapshots,
+    ];
+
+    /// Name shown in the settings screen
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Notifications => "Notifications",
+            Self::CrashReports => "Crash reports",
+            Self::AuditLog => "Audit log",
+            Self::KeyUsage => "Key usage statistics",
+            Self::Provenance => "Provenance records",
+            Self::ConfigSnapshots => "Settings snapshots",
+        }
+    }
+
+    /// Policy used until the user configures one
+    pub fn default_policy(&self) -> Policy {
+        let days = |days: u64| Some(Duration::from_secs(days * SECONDS_PER_DAY));
+        match self {
+            Self::Notifications => Policy {
+                max_age: days(180),
+                max_count: Some(DEFAULT_NOTIFICATION_CAPACITY as u32),
+                max_bytes: Some(4 * 1024 * 1024),
+            },
+            Self::CrashReports => Policy {
+                max_age: days(90),
+                max_count: Some(200),
+                max_bytes: Some(16 * 1024 * 1024),
+            },
+            // Security history: bounded by count, never by age
+            Self::AuditLog => Policy {
+                max_age: None,
+                max_count: Some(DEFAULT_MAX_ENTRIES as u32),
+                max_bytes: None,
+            },
+            Self::KeyUsage => Policy {
+                max_age: Some(Duration::from_secs(DAILY_RETENTION_SECS)),
+                max_count: Some(100_000),
+                max_bytes: None,
+            },
+            Self::Provenance => Policy {
+                max_age: days(u64::from(DEFAULT_PROVENANCE_RETENTION_DAYS)),
+                max_count: Some(50_000),
+                max_bytes: None,
+            },
+            // Per app and user
+            Self::ConfigSnapshots => Policy {
+                max_age: days(90),
+                max_count: Some(30),
+                max_bytes: Some(256 * 1024),
+            },
+        }
+    }
+}
+
+/// Bounds on what a store keeps; every limit is optional
+///
+/// A store over any limit drops items, oldest first in its own removal
+/// order, until it is within all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Policy {
+    /// Items older than this are removed
+    #[serde(default, rename = "maxAgeSecs", with = "age_secs")]
+    #[schemars(with = "Option<u64>")]
+    pub max_age: Option<Duration>,
+    /// Most items kept
+    #[serde(default)]
+    pub max_count: Option<u32>,
+    /// Most bytes kept
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+impl Policy {
+    /// Policy keeping at most `max_count` items
+    pub fn count(max_count: u32) -> Self {
+        Self {
+            max_count: Some(max_count),
+            ..Self::default()
+        }
+    }
+
+    /// Whether a store holding `footprint` is over the count limit
+    pub fn over_count(&self, footprint: &Footprint) -> bool {
+        self.max_count
+            .is_some_and(|max| footprint.items > u64::from(max))
+    }
+
+    /// Whether a store holding `footprint` is over the count or size limit
+    pub fn over_size(&self, footprint: &Footprint) -> bool {
+        self.over_count(footprint) || self.max_bytes.is_some_and(|max| footprint.bytes > max)
+    }
+}
+
+/// Maximum age stored as whole seconds
+mod age_secs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        age: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        age.map(|age| age.as_secs()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
+}
+
+/// How much a store holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Footprint {
+    /// Items stored
+    pub items: u64,
+    /// Bytes of stored data
+    pub bytes: u64,
+}
+
+/// One stored item a retention policy may remove
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetainedItem {
+    /// Row identifier in its table
+    pub id: i64,
+    /// Unix timestamp the item was written at
+    pub timestamp: u64,
+    /// Bytes of stored data
+    pub bytes: u64,
+}
+
+/// What applying a retention policy to one store removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    /// Store the policy was applied to
+    pub store: RetentionStore,
+    /// Items removed
+    pub removed: u64,
+    /// Bytes removed
+    pub removed_bytes: u64,
+    /// What the store holds afterwards
+    pub remaining: Footprint,
+}
+
+/// A store's footprint, for the settings screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreFootprint {
+    /// Store measured
+    pub store: RetentionStore,
+    /// Display name of the store
+    pub label: String,
+    /// What the store holds
+    pub footprint: Footprint,
+    /// Policy the store is kept under
+    pub policy: Policy,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_serialization() {
+        let policy = Policy {
+            max_age: Some(Duration::from_secs(3600)),
+            max_count: Some(10),
+            max_bytes: None,
+        };
+        let json = serde_json::to_value(policy).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "maxAgeSecs": 3600, "maxCount": 10, "maxBytes": null })
+        );
+        assert_eq!(serde_json::from_value::<Policy>(json).unwrap(), policy);
+        assert_eq!(
+            serde_json::from_value::<Policy>(serde_json::json!({})).unwrap(),
+            Policy::default()
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let policy = Policy {
+            max_bytes: Some(100),
+            ..Policy::count(2)
+        };
+        let within = Footprint {
+            items: 2,
+            bytes: 100,
+        };
+        assert!(!policy.over_count(&within) && !policy.over_size(&within));
+        assert!(policy.over_count(&Footprint { items: 3, bytes: 0 }));
+        assert!(policy.over_size(&Footprint {
+            items: 1,
+            bytes: 101
+        }));
+        assert!(!Policy::default().over_size(&Footprint {
+            items: u64::MAX,
+            bytes: u64::MAX
+        }));
+    }
+}
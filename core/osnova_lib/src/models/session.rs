@@ -0,0 +1,49 @@
+//! Remote client session models for Osnova
+//!
+//! This module provides the RemoteSession type, the server-side record of a
+//! session token issued to a paired client device in Client-Server mode.
+//!
+//! The token itself is never stored; sessions are keyed by a hash of the
+//! token, so a copy of the database cannot be used to impersonate a device.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A session issued to a paired client device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSession {
+    /// Device the session is bound to
+    pub device_id: String,
+    /// Unix timestamp when the session was issued
+    pub created_at: u64,
+    /// Unix timestamp when the session stops being accepted
+    pub expires_at: u64,
+    /// Unix timestamp of the last authenticated request
+    pub last_seen: u64,
+}
+
+impl RemoteSession {
+    /// Whether the session has expired at `now` (Unix timestamp)
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_boundary() {
+        let session = RemoteSession {
+            device_id: "device-1".to_string(),
+            created_at: 100,
+            expires_at: 200,
+            last_seen: 100,
+        };
+
+        assert!(!session.is_expired_at(199));
+        assert!(session.is_expired_at(200));
+    }
+}
@@ -0,0 +1,502 @@
+//! App settings schema models for Osnova
+//!
+//! An app declares its settings in the manifest's `settingsSchema`: typed
+//! fields with labels, defaults, and constraints, grouped into sections.
+//! Osnova's Config screen renders the form, and `config.setAppConfig`
+//! rejects values that do not fit the declared field.
+//!
+//! The schema is a deliberately small subset: strings, numbers with
+//! optional bounds, booleans, and enums with a fixed list of options.
+//! Settings the schema does not declare are stored as before, unchecked.
+//!
+//! Fields marked `secret` (an API token the user enters, say) are kept
+//! apart from the configuration: the Config screen can set or clear them
+//! but never read them back, and only the app's backends receive them, at
+//! launch (see [`crate::services::secret_channel`]).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{OsnovaError, Result};
+use crate::models::config_cache::AppConfiguration;
+
+/// Settings an app declares for the Config screen
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::models::settings_schema::SettingsSchema;
+/// use serde_json::json;
+///
+/// let schema: SettingsSchema = serde_json::from_value(json!({
+///     "sections": [{
+///         "id": "appearance",
+///         "label": "Appearance",
+///         "fields": [
+///             {"key": "fontSize", "label": "Font size", "type": "number",
+///              "min": 8, "max": 32, "default": 14}
+///         ]
+///     }]
+/// }))
+/// .unwrap();
+/// assert!(schema.validate().is_ok());
+/// assert!(schema.field("fontSize").unwrap().check(&json!(40)).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSchema {
+    /// Groups of fields, in display order
+    pub sections: Vec<SettingsSection>,
+}
+
+/// A titled group of settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSection {
+    /// Section identifier, unique within the schema
+    pub id: String,
+    /// Section title
+    pub label: String,
+    /// Text shown under the title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Fields in display order
+    pub fields: Vec<SettingField>,
+}
+
+/// One declared setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingField {
+    /// Configuration key the value is stored under
+    pub key: String,
+    /// Field label
+    pub label: String,
+    /// Help text shown with the field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Value type and constraints
+    #[serde(flatten)]
+    pub kind: SettingKind,
+    /// Value used until the user sets one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    /// Keys an earlier version of the app stored this setting under
+    ///
+    /// On upgrade, a stored value under one of these keys moves to `key`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed_from: Vec<String>,
+    /// Keep the value out of the configuration and hand it to the app's
+    /// backends only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+}
+
+/// Value type of a setting, with its constraints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SettingKind {
+    /// Free text
+    #[serde(rename_all = "camelCase")]
+    String {
+        /// Longest value allowed, in characters
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_length: Option<usize>,
+    },
+    /// A number, optionally bounded (inclusive)
+    Number {
+        /// Smallest value allowed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        /// Largest value allowed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+    },
+    /// On or off
+    Boolean,
+    /// One of a fixed list of string values
+    Enum {
+        /// Allowed values, in display order
+        options: Vec<EnumOption>,
+    },
+}
+
+/// An allowed value of an enum setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnumOption {
+    /// Stored value
+    pub value: String,
+    /// Label shown for the value
+    pub label: String,
+}
+
+/// Why a value was rejected for a declared setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingFieldError {
+    /// Configuration key of the field
+    pub key: String,
+    /// What is wrong with the value
+    pub message: String,
+}
+
+impl std::fmt::Display for SettingFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl SettingField {
+    /// Check a value against the field's type and constraints
+    ///
+    /// # Errors
+    ///
+    /// Returns a message describing why the value does not fit
+    pub fn check(&self, value: &Value) -> std::result::Result<(), String> {
+        match &self.kind {
+            SettingKind::String { max_length } => {
+                let text = value.as_str().ok_or("must be a string")?;
+                match max_length {
+                    Some(max) if text.chars().count() > *max => {
+                        Err(format!("must be at most {} characters", max))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            SettingKind::Number { min, max } => {
+                let number = value.as_f64().ok_or("must be a number")?;
+                if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                    return Err(format!("must be {}", describe_range(*min, *max)));
+                }
+                Ok(())
+            }
+            SettingKind::Boolean => match value.is_boolean() {
+                true => Ok(()),
+                false => Err("must be true or false".to_string()),
+            },
+            SettingKind::Enum { options } => {
+                let text = value.as_str();
+                match options
+                    .iter()
+                    .any(|option| Some(option.value.as_str()) == text)
+                {
+                    true => Ok(()),
+                    false => {
+                        let values: Vec<&str> =
+                            options.iter().map(|option| option.value.as_str()).collect();
+                        Err(format!("must be one of: {}", values.join(", ")))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Validate the field declaration
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.label.trim().is_empty() {
+            return Err("needs a label".to_string());
+        }
+        match &self.kind {
+            SettingKind::Number {
+                min: Some(min),
+                max: Some(max),
+            } if min > max => return Err(format!("min {} is greater than max {}", min, max)),
+            SettingKind::Enum { options } => {
+                if options.is_empty() {
+                    return Err("enum needs at least one option".to_string());
+                }
+                let mut values = HashSet::new();
+                for option in options {
+                    if !values.insert(option.value.as_str()) {
+                        return Err(format!("duplicate option '{}'", option.value));
+                    }
+                }
+            }
+            _ => {}
+        }
+        if self.secret && self.default.is_some() {
+            return Err("is secret and cannot have a default".to_string());
+        }
+        if let Some(default) = &self.default {
+            self.check(default).map_err(|e| format!("default {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+impl SettingsSchema {
+    /// Validate the schema
+    ///
+    /// Section ids and field keys must be unique, and a key may not also
+    /// be listed as another field's former key. Number bounds must be
+    /// ordered, enums need distinct options, and defaults must fit their
+    /// field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid section or field
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| OsnovaError::Other(format!("settingsSchema: {}", message));
+
+        let mut sections = HashSet::new();
+        let mut keys = HashSet::new();
+        for section in &self.sections {
+            if section.id.trim().is_empty() {
+                return Err(invalid("section needs an id".to_string()));
+            }
+            if !sections.insert(section.id.as_str()) {
+                return Err(invalid(format!("duplicate section '{}'", section.id)));
+            }
+            for field in &section.fields {
+                if field.key.trim().is_empty() {
+                    return Err(invalid(format!(
+                        "field in section '{}' needs a key",
+                        section.id
+                    )));
+                }
+                if !keys.insert(field.key.as_str()) {
+                    return Err(invalid(format!("duplicate key '{}'", field.key)));
+                }
+                field
+                    .validate()
+                    .map_err(|e| invalid(format!("field '{}' {}", field.key, e)))?;
+            }
+        }
+
+        for field in self.fields() {
+            if let Some(old) = field
+                .renamed_from
+                .iter()
+                .find(|old| keys.contains(old.as_str()))
+            {
+                return Err(invalid(format!(
+                    "field '{}' is renamed from '{}', which is still declared",
+                    field.key, old
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every declared field, in display order
+    pub fn fields(&self) -> impl Iterator<Item = &SettingField> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.fields.iter())
+    }
+
+    /// Find a declared field by key
+    pub fn field(&self, key: &str) -> Option<&SettingField> {
+        self.fields().find(|field| field.key == key)
+    }
+
+    /// Whether a key is declared as a secret field
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.field(key).is_some_and(|field| field.secret)
+    }
+
+    /// Check settings about to be written
+    ///
+    /// Keys the schema does not declare are not checked, and secret fields
+    /// are refused since they are not stored with the configuration.
+    /// Returns one error per rejected field, sorted by key; empty when
+    /// every value fits.
+    pub fn check_values(&self, settings: &HashMap<String, Value>) -> Vec<SettingFieldError> {
+        let mut errors: Vec<SettingFieldError> = settings
+            .iter()
+            .filter_map(|(key, value)| {
+                let field = self.field(key)?;
+                let message = match field.secret {
+                    true => "is secret; set it with config.setAppSecret".to_string(),
+                    false => field.check(value).err()?,
+                };
+                Some(SettingFieldError {
+                    key: key.clone(),
+                    message,
+                })
+            })
+            .collect();
+        errors.sort_by(|a, b| a.key.cmp(&b.key));
+        errors
+    }
+
+    /// Move and drop stored values to fit this schema after an upgrade
+    ///
+    /// A value stored under a field's former key moves to the field when
+    /// the field has no value yet. Values of declared fields that no longer
+    /// fit are removed, so the default applies, and so are values of fields
+    /// that became secret. Returns the keys changed.
+    pub fn migrate(&self, config: &mut AppConfiguration) -> Vec<String> {
+        let mut changed = Vec::new();
+        for field in self.fields() {
+            for old in &field.renamed_from {
+                let Some(value) = config.remove_setting(old) else {
+                    continue;
+                };
+                if config.get_setting(&field.key).is_none() {
+                    config.set_setting(&field.key, value);
+                    changed.push(field.key.clone());
+                }
+                changed.push(old.clone());
+            }
+
+            let fits = config
+                .get_setting(&field.key)
+                .is_none_or(|value| !field.secret && field.check(value).is_ok());
+            if !fits {
+                config.remove_setting(&field.key);
+                changed.push(field.key.clone());
+            }
+        }
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
+/// Describe a number range for error messages
+fn describe_range(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("between {} and {}", min, max),
+        (Some(min), None) => format!("at least {}", min),
+        (None, Some(max)) => format!("at most {}", max),
+        (None, None) => "a number".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> SettingsSchema {
+        serde_json::from_value(json!({
+            "sections": [{
+                "id": "general",
+                "label": "General",
+                "fields": [
+                    {"key": "nickname", "label": "Nickname", "type": "string", "maxLength": 8},
+                    {"key": "fontSize", "label": "Font size", "type": "number",
+                     "min": 8, "max": 32, "default": 14, "renamedFrom": ["font"]},
+                    {"key": "sounds", "label": "Sounds", "type": "boolean", "default": true},
+                    {"key": "theme", "label": "Theme", "type": "enum", "default": "light",
+                     "options": [
+                        {"value": "light", "label": "Light"},
+                        {"value": "dark", "label": "Dark"}
+                     ]}
+                ]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_schema_validation() {
+        assert!(schema().validate().is_ok());
+
+        let mut bad_default = schema();
+        bad_default.sections[0].fields[1].default = Some(json!(64));
+        let err = bad_default.validate().unwrap_err().to_string();
+        assert!(err.contains("field 'fontSize' default must be between 8 and 32"));
+
+        let mut inverted = schema();
+        inverted.sections[0].fields[1].kind = SettingKind::Number {
+            min: Some(10.0),
+            max: Some(1.0),
+        };
+        inverted.sections[0].fields[1].default = None;
+        assert!(inverted
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("greater"));
+
+        let mut duplicate = schema();
+        let field = duplicate.sections[0].fields[0].clone();
+        duplicate.sections[0].fields.push(field);
+        assert!(duplicate
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("duplicate key 'nickname'"));
+
+        let mut no_options = schema();
+        no_options.sections[0].fields[3].kind = SettingKind::Enum { options: vec![] };
+        no_options.sections[0].fields[3].default = None;
+        assert!(no_options.validate().is_err());
+
+        let mut stale_rename = schema();
+        stale_rename.sections[0].fields[1].renamed_from = vec!["sounds".to_string()];
+        assert!(stale_rename.validate().is_err());
+    }
+
+    #[test]
+    fn test_check_values() {
+        let settings = HashMap::from([
+            ("nickname".to_string(), json!("far too long")),
+            ("fontSize".to_string(), json!(12)),
+            ("sounds".to_string(), json!("yes")),
+            ("theme".to_string(), json!("sepia")),
+            ("undeclared".to_string(), json!({"any": "thing"})),
+        ]);
+
+        let errors = schema().check_values(&settings);
+        let keys: Vec<&str> = errors.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["nickname", "sounds", "theme"]);
+        assert_eq!(errors[2].message, "must be one of: light, dark");
+    }
+
+    #[test]
+    fn test_migrate_renames_and_drops_invalid_values() {
+        let mut config = AppConfiguration::new("app", "user");
+        config.set_setting("font", json!(20));
+        config.set_setting("theme", json!("sepia"));
+        config.set_setting("sounds", json!(false));
+
+        let changed = schema().migrate(&mut config);
+        assert_eq!(changed, ["font", "fontSize", "theme"]);
+        assert_eq!(config.get_setting("fontSize"), Some(&json!(20)));
+        assert_eq!(config.get_setting("font"), None);
+        assert_eq!(config.get_setting("theme"), None);
+        assert_eq!(config.get_setting("sounds"), Some(&json!(false)));
+
+        // Nothing left to migrate
+        assert!(schema().migrate(&mut config).is_empty());
+    }
+
+    #[test]
+    fn test_secret_fields_stay_out_of_the_configuration() {
+        let mut schema = schema();
+        schema.sections[0].fields.push(
+            serde_json::from_value(json!({
+                "key": "apiToken", "label": "API token", "type": "string", "secret": true
+            }))
+            .unwrap(),
+        );
+        assert!(schema.validate().is_ok());
+        assert!(schema.is_secret("apiToken"));
+        assert!(!schema.is_secret("nickname"));
+
+        let settings = HashMap::from([("apiToken".to_string(), json!("abc"))]);
+        let errors = schema.check_values(&settings);
+        assert_eq!(errors[0].key, "apiToken");
+        assert!(errors[0].message.contains("config.setAppSecret"));
+
+        // A value stored before the field became secret is dropped
+        let mut config = AppConfiguration::new("app", "user");
+        config.set_setting("apiToken", json!("abc"));
+        assert_eq!(schema.migrate(&mut config), ["apiToken"]);
+        assert_eq!(config.get_setting("apiToken"), None);
+
+        let mut with_default = schema.clone();
+        with_default.sections[0].fields[4].default = Some(json!("abc"));
+        assert!(with_default
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("field 'apiToken' is secret and cannot have a default"));
+    }
+}
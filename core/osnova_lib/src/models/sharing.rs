@@ -0,0 +1,169 @@
+//! Data sharing contract models for Osnova
+//!
+//! This module provides the DataOffer an app declares in its manifest to
+//! expose part of its data to other apps, and the SharedDataGrant recorded
+//! when the user lets a consuming app read an offer.
+//!
+//! An offer maps to a path prefix inside the offering app's own data
+//! storage. Consumers never see that storage; reads go through the
+//! `shared.*` OpenRPC methods, confined to the prefix.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path};
+
+use crate::error::{OsnovaError, Result};
+
+/// Exportable data an app offers to other apps
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::models::sharing::DataOffer;
+///
+/// let offer = DataOffer::new("album", "Photos in the shared album", "image", "albums/shared");
+/// assert!(offer.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataOffer {
+    /// Offer identifier, unique within the app
+    pub id: String,
+    /// What the offer contains, shown in the permission prompt
+    pub description: String,
+    /// Kind of data (e.g. "image", "document"), for consumers to filter on
+    pub kind: String,
+    /// Directory within the app's data storage the offer exposes
+    pub path_prefix: String,
+}
+
+impl DataOffer {
+    /// Create a new data offer
+    pub fn new(
+        id: impl Into<String>,
+        description: impl Into<String>,
+        kind: impl Into<String>,
+        path_prefix: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            description: description.into(),
+            kind: kind.into(),
+            path_prefix: path_prefix.into(),
+        }
+    }
+
+    /// Validate the offer
+    ///
+    /// The id may use letters, digits, `.`, `-`, and `_`. The path prefix
+    /// must be a relative path without `.` or `..` components, so an offer
+    /// cannot reach outside the app's data storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid field
+    pub fn validate(&self) -> Result<()> {
+        let id_valid = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !id_valid {
+            return Err(OsnovaError::Other(format!(
+                "Invalid data offer id: '{}'",
+                self.id
+            )));
+        }
+        if self.description.trim().is_empty() {
+            return Err(OsnovaError::Other(format!(
+                "Data offer '{}' needs a description",
+                self.id
+            )));
+        }
+        if self.kind.trim().is_empty() {
+            return Err(OsnovaError::Other(format!(
+                "Data offer '{}' needs a data kind",
+                self.id
+            )));
+        }
+        if !is_confined_path(&self.path_prefix) {
+            return Err(OsnovaError::Other(format!(
+                "Data offer '{}' has an invalid path prefix: '{}'",
+                self.id, self.path_prefix
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Validate a list of offers, including that ids are unique
+///
+/// # Errors
+///
+/// Returns an error for the first invalid or duplicate offer
+pub fn validate_offers(offers: &[DataOffer]) -> Result<()> {
+    let mut ids = HashSet::new();
+    for offer in offers {
+        offer.validate()?;
+        if !ids.insert(offer.id.as_str()) {
+            return Err(OsnovaError::Other(format!(
+                "Duplicate data offer id: '{}'",
+                offer.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is a non-empty relative path made only of normal
+/// components
+pub fn is_confined_path(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// A consuming app's access to another app's data offer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedDataGrant {
+    /// App that declares the offer
+    pub provider_app_id: String,
+    /// Offer identifier
+    pub offer_id: String,
+    /// App allowed to read the offer
+    pub consumer_app_id: String,
+    /// Unix timestamp when access was granted
+    pub granted_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_validation() {
+        let offer = DataOffer::new("photos.album", "Shared album", "image", "albums/shared");
+        assert!(offer.validate().is_ok());
+
+        let invalid = [
+            DataOffer::new("", "Shared album", "image", "albums"),
+            DataOffer::new("album/1", "Shared album", "image", "albums"),
+            DataOffer::new("album", " ", "image", "albums"),
+            DataOffer::new("album", "Shared album", "", "albums"),
+            DataOffer::new("album", "Shared album", "image", ""),
+            DataOffer::new("album", "Shared album", "image", "/etc"),
+            DataOffer::new("album", "Shared album", "image", "albums/../keys"),
+            DataOffer::new("album", "Shared album", "image", "./albums"),
+        ];
+        for offer in invalid {
+            assert!(offer.validate().is_err(), "{:?} should be invalid", offer);
+        }
+
+        let duplicate = [offer.clone(), offer];
+        assert!(validate_offers(&duplicate).is_err());
+    }
+}
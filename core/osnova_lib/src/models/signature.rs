@@ -0,0 +1,199 @@
+//! Manifest signature models for Osnova
+//!
+//! A manifest may carry detached Ed25519 signatures from several people,
+//! such as the developer and a release manager, each tagged with the
+//! signer's public key and role. A manifest that declares a
+//! [`SignaturePolicy`] is only accepted once enough of the signers it allows
+//! have signed, so one compromised key cannot push a release on its own.
+//!
+//! Manifests from before co-signing carry a single signature string. It
+//! still parses and is kept, but it names no key, so it never counts toward
+//! a policy.
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::error::{OsnovaError, Result};
+
+/// One co-signer's detached signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestSignature {
+    /// Signer's Ed25519 public key (base64)
+    pub public_key: String,
+    /// What the signer vouches for as (e.g. "developer", "release-manager")
+    pub role: String,
+    /// Signature over the canonical payload (base64)
+    pub signature: String,
+}
+
+impl ManifestSignature {
+    /// Sign `payload` as `role`
+    pub fn sign(payload: &[u8], signing_key: &SigningKey, role: &str) -> Self {
+        let engine = base64::engine::general_purpose::STANDARD;
+        Self {
+            public_key: engine.encode(signing_key.verifying_key().to_bytes()),
+            role: role.to_string(),
+            signature: engine.encode(signing_key.sign(payload).to_bytes()),
+        }
+    }
+
+    /// Check the signature over `payload`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the key or signature is malformed
+    /// or the signature does not verify
+    pub fn verify(&self, payload: &[u8]) -> Result<()> {
+        let crypto_error = |message: &str| {
+            OsnovaError::Crypto(format!("Manifest signature by {} {}", self.role, message))
+        };
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let public_key: [u8; 32] = engine
+            .decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("has a malformed public key"))?;
+        let signature: [u8; 64] = engine
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crypto_error("is malformed"))?;
+
+        VerifyingKey::from_bytes(&public_key)
+            .map_err(|_| crypto_error("has an invalid public key"))?
+            .verify(payload, &Signature::from_bytes(&signature))
+            .map_err(|_| crypto_error("failed verification"))
+    }
+}
+
+/// The signatures a manifest carries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ManifestSignatures {
+    /// Co-signatures, in the order they were added
+    CoSigned(Vec<ManifestSignature>),
+    /// Single signature from before co-signing; it names no key, so it is
+    /// kept but never verified
+    Detached(String),
+}
+
+impl ManifestSignatures {
+    /// The co-signatures; none for a detached signature
+    pub fn co_signatures(&self) -> &[ManifestSignature] {
+        match self {
+            Self::CoSigned(signatures) => signatures,
+            Self::Detached(_) => &[],
+        }
+    }
+}
+
+impl From<String> for ManifestSignatures {
+    fn from(signature: String) -> Self {
+        Self::Detached(signature)
+    }
+}
+
+impl From<&str> for ManifestSignatures {
+    fn from(signature: &str) -> Self {
+        Self::Detached(signature.to_string())
+    }
+}
+
+/// How many of which signers a manifest needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignaturePolicy {
+    /// Distinct allowed signers needed
+    pub required: u32,
+    /// Public keys (base64) whose signatures count
+    pub allowed_signers: Vec<String>,
+}
+
+impl SignaturePolicy {
+    /// Check the policy can be met
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no signature is required, or more are required
+    /// than there are distinct allowed signers
+    pub fn validate(&self) -> Result<()> {
+        let signers = self.allowed_signers.iter().collect::<BTreeSet<_>>().len();
+        if self.required == 0 || self.required as usize > signers {
+            return Err(OsnovaError::Other(format!(
+                "Signature policy requires {} of {} allowed signers",
+                self.required, signers
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that verified signatures satisfy the policy
+    ///
+    /// Each allowed key counts once; signatures by keys outside the
+    /// allowlist do not count.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if too few allowed signers signed
+    pub fn check(&self, verified: &[ManifestSignature]) -> Result<()> {
+        self.validate()?;
+
+        let (allowed, outside): (Vec<_>, Vec<_>) = verified
+            .iter()
+            .partition(|signature| self.allowed_signers.contains(&signature.public_key));
+        let signers = allowed
+            .iter()
+            .map(|signature| &signature.public_key)
+            .collect::<BTreeSet<_>>()
+            .len();
+        if signers >= self.required as usize {
+            return Ok(());
+        }
+
+        let mut message = format!(
+            "Manifest has {} of the {} required allowed signatures",
+            signers, self.required
+        );
+        if !outside.is_empty() {
+            let roles = outside
+                .iter()
+                .map(|signature| signature.role.as_str())
+                .collect::<Vec<_>>();
+            message.push_str(&format!(
+                "; signers not in the allowlist: {}",
+                roles.join(", ")
+            ));
+        }
+        Err(OsnovaError::Crypto(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_signature_round_trip_and_legacy_format() -> Result<()> {
+        let signature = ManifestSignature::sign(b"payload", &key(1), "developer");
+        signature.verify(b"payload")?;
+        assert!(signature.verify(b"tampered").is_err());
+
+        let legacy: ManifestSignatures = serde_json::from_str(r#""c2lnbmF0dXJl""#)?;
+        assert_eq!(legacy, ManifestSignatures::from("c2lnbmF0dXJl"));
+        assert!(legacy.co_signatures().is_empty());
+
+        let signed = ManifestSignatures::CoSigned(vec![signature.clone()]);
+        let json = serde_json::to_string(&signed)?;
+        assert_eq!(serde_json::from_str::<ManifestSignatures>(&json)?, signed);
+        Ok(())
+    }
+}
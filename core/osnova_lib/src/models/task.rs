@@ -0,0 +1,134 @@
+//! Task models for Osnova
+//!
+//! A task is one long-running operation (a component download, a backup
+//! upload, a cache collection) as the activity center shows it: what it
+//! is, how far along it is, whether it can be cancelled, and how it ended.
+//! Tasks are tracked by [`TaskRegistry`](crate::services::tasks::TaskRegistry).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Number of finished tasks kept in the history
+pub const TASK_HISTORY: usize = 100;
+
+/// What kind of operation a task is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskCategory {
+    /// A component download
+    Download,
+    /// An upload of a backup or archive to the network
+    Backup,
+    /// A server-to-server migration
+    Migration,
+    /// Downloading the missing components of installed apps
+    Materialization,
+    /// A refresh of app metadata or the catalog
+    CatalogRefresh,
+    /// Collection of cached files no app needs
+    CacheGc,
+    /// Warming the cache for apps likely to be launched soon
+    CacheWarming,
+}
+
+/// Where a task stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    /// Still running
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Stopped by its cancellation token
+    Cancelled,
+}
+
+impl TaskState {
+    /// Whether the task has ended
+    pub fn is_finished(self) -> bool {
+        self != TaskState::Running
+    }
+}
+
+/// A long-running operation, as shown in the activity center
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    /// Task identifier
+    pub id: String,
+    /// What the task does, for display
+    pub name: String,
+    /// Kind of operation
+    pub category: TaskCategory,
+    /// Whether the task can be cancelled
+    pub cancellable: bool,
+    /// Where the task stands
+    pub state: TaskState,
+    /// Units of work done (bytes, files, batches, ...)
+    pub completed: u64,
+    /// Units of work in total, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    /// Latest status message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Why the task failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unix timestamp when the task started
+    pub started_at: u64,
+    /// Unix timestamp when the task ended
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+}
+
+impl TaskInfo {
+    /// Fraction of the work done, if the total is known
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.completed.min(total)) as f64 / total as f64),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        let task = TaskInfo {
+            id: "t1".to_string(),
+            name: "Download Wallet".to_string(),
+            category: TaskCategory::CacheGc,
+            cancellable: false,
+            state: TaskState::Running,
+            completed: 1,
+            total: Some(4),
+            message: None,
+            error: None,
+            started_at: 100,
+            finished_at: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "id": "t1",
+                "name": "Download Wallet",
+                "category": "cacheGc",
+                "cancellable": false,
+                "state": "running",
+                "completed": 1,
+                "total": 4,
+                "startedAt": 100,
+            })
+        );
+        assert_eq!(task.fraction(), Some(0.25));
+        assert!(!task.state.is_finished());
+        assert!(TaskState::Cancelled.is_finished());
+    }
+}
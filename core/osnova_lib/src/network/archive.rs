@@ -0,0 +1,1023 @@
+//! # Chunked Archive Upload
+//!
+//! Upload an archive of many files in batches, each quoted and paid for on
+//! its own, so a failure late in a multi-GB upload costs at most the batch
+//! it happened in.
+//!
+//! For each batch of roughly [`ArchivePolicy::batch_size_bytes`], the
+//! [`ArchiveUploader`] quotes the files not yet stored, asks the wallet to
+//! pay through a [`BatchPayer`], and stores them. Payments and stored files
+//! are recorded in the database as they happen:
+//!
+//! - Running the upload again with the same id resumes it. A batch that was
+//!   paid for is not paid for again, and files already stored are skipped.
+//! - Contents stored by any earlier upload are found in the dedup ledger
+//!   by hash and not stored, or paid for, again.
+//! - With a [`spend cap`](ArchivePolicy::spend_cap), the upload stops
+//!   before a batch whose payment would exceed it, leaving the upload
+//!   ready to resume under a higher cap.
+//!
+//! The archive index, naming the address of every file, is stored last and
+//! only once every batch succeeded; its address is the archive's address.
+//! With a [`TaskRegistry`] attached, each upload shows as a backup task.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use std::sync::Arc;
+//! use osnova_lib::network::archive::{ArchiveFile, ArchivePolicy, ArchiveUploader};
+//! use osnova_lib::network::{AutonomiClient, NetworkOptions};
+//! use osnova_lib::storage::SqlStorage;
+//!
+//! let client = Arc::new(AutonomiClient::connect().await?);
+//! let uploader = ArchiveUploader::new(SqlStorage::new("/tmp/osnova.db")?, client, wallet);
+//! let files = vec![ArchiveFile::new("photos/1.jpg", "/home/me/photos/1.jpg")];
+//! let status = uploader
+//!     .upload("photos-2024", &files, &ArchivePolicy::default(), &NetworkOptions::default(), |p| {
+//!         println!("batch {}/{} done, {} spent", p.batch + 1, p.batches, p.spent);
+//!     })
+//!     .await?;
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::upload::{put_data, quote_cost, UploadReceipt};
+use super::{AutonomiClient, NetworkOptions, PaymentLedger, UploadContext};
+use crate::error::{OsnovaError, Result};
+use crate::models::archive_upload::{ArchiveBatchRecord, ArchiveEntry};
+use crate::models::task::TaskCategory;
+use crate::services::TaskRegistry;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Batch size used when a caller does not set one (64 MiB)
+pub const DEFAULT_ARCHIVE_BATCH_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Future returned by [`ArchiveTransport`] and [`BatchPayer`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Where archive contents are quoted and stored
+pub trait ArchiveTransport: Send + Sync {
+    /// Cost of storing `data`, in AttoTokens
+    fn quote<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<u64>>;
+
+    /// Store `data`, paid for by the wallet payment request
+    fn put<'a>(
+        &'a self,
+        data: &'a [u8],
+        payment_request_id: &'a str,
+    ) -> BoxFuture<'a, Result<UploadReceipt>>;
+}
+
+impl ArchiveTransport for AutonomiClient {
+    fn quote<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(quote_cost(self, data))
+    }
+
+    fn put<'a>(&'a self, data: &'a [u8], _: &'a str) -> BoxFuture<'a, Result<UploadReceipt>> {
+        Box::pin(put_data(self, data))
+    }
+}
+
+/// Wallet approval for paying a batch
+pub trait BatchPayer: Send + Sync {
+    /// Pay for `quote`, returning the wallet payment request that did
+    ///
+    /// An error (the user declined, the wallet is short) stops the upload
+    /// before anything of the batch is stored.
+    fn request_payment<'a>(&'a self, quote: &'a BatchQuote) -> BoxFuture<'a, Result<String>>;
+}
+
+/// How an archive is split and how much it may cost
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchivePolicy {
+    /// Bytes per batch; a file larger than this is a batch of its own
+    pub batch_size_bytes: u64,
+    /// Most the whole upload may be paid, in AttoTokens, counting batches
+    /// paid before a resume
+    pub spend_cap: Option<u64>,
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        Self {
+            batch_size_bytes: DEFAULT_ARCHIVE_BATCH_BYTES,
+            spend_cap: None,
+        }
+    }
+}
+
+/// A file to include in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveFile {
+    /// Name within the archive, unique in it
+    pub name: String,
+    /// Where the contents are read from when its batch is uploaded
+    pub path: PathBuf,
+}
+
+impl ArchiveFile {
+    /// File `name` in the archive, read from `path`
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+        }
+    }
+}
+
+/// A batch the wallet is asked to pay for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchQuote {
+    /// Archive upload the batch belongs to
+    pub upload_id: String,
+    /// Position of the batch; equal to `batches` for the index
+    pub batch: usize,
+    /// Batches of files in the upload
+    pub batches: usize,
+    /// Files to store
+    pub files: usize,
+    /// Bytes to store
+    pub bytes: u64,
+    /// Quoted cost, in AttoTokens
+    pub cost: u64,
+}
+
+/// A batch of files finished
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveProgress {
+    /// Archive upload
+    pub upload_id: String,
+    /// Position of the batch that finished
+    pub batch: usize,
+    /// Batches of files in the upload
+    pub batches: usize,
+    /// Files stored by this batch, not counting ones already stored
+    pub stored: usize,
+    /// Files skipped because their contents were already stored
+    pub skipped: usize,
+    /// Whether the batch had been finished by an earlier run
+    pub resumed: bool,
+    /// Paid so far for the whole upload, in AttoTokens
+    pub spent: u64,
+}
+
+/// Cost of one batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReceipt {
+    /// Position of the batch
+    pub batch: usize,
+    /// Files in the batch
+    pub files: usize,
+    /// Bytes in the batch
+    pub bytes: u64,
+    /// Wallet payment request that paid for it; `None` when every file in
+    /// it was already stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_request_id: Option<String>,
+    /// Quoted cost, in AttoTokens
+    pub quoted_cost: u64,
+    /// Cost of storing its files, in AttoTokens
+    pub cost: u64,
+}
+
+/// A completed archive upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveReceipt {
+    /// Archive upload
+    pub upload_id: String,
+    /// ant:// address of the archive index
+    pub address: String,
+    /// Files in the archive
+    pub files: usize,
+    /// Bytes in the archive, not counting the index
+    pub bytes: u64,
+    /// Cost of every batch of files
+    pub batches: Vec<BatchReceipt>,
+    /// Cost of storing the index, in AttoTokens
+    pub index_cost: u64,
+    /// Cost of the whole upload, in AttoTokens
+    pub total_cost: u64,
+}
+
+/// How far an archive upload got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ArchiveUploadStatus {
+    /// Every batch and the index are stored
+    Completed(ArchiveReceipt),
+    /// Stopped before paying for a batch that would exceed the spend cap;
+    /// run the upload again with a higher cap to resume
+    SpendCapReached {
+        /// Paid so far, in AttoTokens
+        spent: u64,
+        /// Quoted cost of the batch that was not paid for
+        next_cost: u64,
+        /// Cap that stopped the upload
+        cap: u64,
+        /// Position of the batch that was not paid for
+        batch: usize,
+        /// Batches of files in the upload
+        batches: usize,
+    },
+}
+
+/// Index stored as the archive's address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveIndex {
+    /// Every file of the archive, in archive order
+    pub files: Vec<ArchiveEntry>,
+}
+
+/// A file of a batch with its position in the archive
+struct PlannedFile<'a> {
+    position: usize,
+    file: &'a ArchiveFile,
+    size: u64,
+}
+
+/// A file read for upload
+struct PendingFile<'a> {
+    planned: &'a PlannedFile<'a>,
+    data: Vec<u8>,
+    hash: String,
+    quote: u64,
+}
+
+/// Uploads archives batch by batch, resuming where an earlier run stopped
+pub struct ArchiveUploader {
+    storage: Mutex<SqlStorage>,
+    transport: Arc<dyn ArchiveTransport>,
+    payer: Arc<dyn BatchPayer>,
+    ledger: Option<Arc<PaymentLedger>>,
+    tasks: Option<Arc<TaskRegistry>>,
+    clock: SharedClock,
+}
+
+impl ArchiveUploader {
+    /// Create an uploader recording its progress in `storage`
+    pub fn new(
+        storage: SqlStorage,
+        transport: Arc<dyn ArchiveTransport>,
+        payer: Arc<dyn BatchPayer>,
+    ) -> Self {
+        Self {
+            storage: Mutex::new(storage),
+            transport,
+            payer,
+            ledger: None,
+            tasks: None,
+            clock: time::default_clock(),
+        }
+    }
+
+    /// Record every stored file in the wallet's payment history
+    pub fn with_ledger(mut self, ledger: Arc<PaymentLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Show every upload as a task in `tasks`, cancelled through the
+    /// cancellation token of its [`NetworkOptions`]
+    pub fn with_tasks(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Replace the clock (for testing)
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Upload `files` as the archive `upload_id`, or resume that upload
+    ///
+    /// Resume with the same files in the same order. Once completed,
+    /// running it again returns the receipt without storing anything.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a file cannot be read, the wallet does not pay, or the
+    /// network fails. What was paid for and stored until then is kept for
+    /// the next run.
+    pub async fn upload<F>(
+        &self,
+        upload_id: &str,
+        files: &[ArchiveFile],
+        policy: &ArchivePolicy,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<ArchiveUploadStatus>
+    where
+        F: FnMut(&ArchiveProgress),
+    {
+        let Some(tasks) = &self.tasks else {
+            return self
+                .upload_batches(upload_id, files, policy, options, on_progress)
+                .await;
+        };
+        let task = tasks.register_cancellable(
+            format!("Upload archive {}", upload_id),
+            TaskCategory::Backup,
+            options.cancellation.clone(),
+        );
+        let result = self
+            .upload_batches(upload_id, files, policy, options, |progress| {
+                task.progress(progress.batch as u64 + 1, Some(progress.batches as u64));
+                on_progress(progress);
+            })
+            .await;
+        match &result {
+            Ok(ArchiveUploadStatus::SpendCapReached { .. }) => {
+                task.complete_with("Stopped at the spend cap; resume with a higher cap")
+            }
+            _ => task.finish(&result),
+        }
+        result
+    }
+
+    /// [`upload`](Self::upload) without a task
+    async fn upload_batches<F>(
+        &self,
+        upload_id: &str,
+        files: &[ArchiveFile],
+        policy: &ArchivePolicy,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<ArchiveUploadStatus>
+    where
+        F: FnMut(&ArchiveProgress),
+    {
+        let plan = plan_batches(files, policy.batch_size_bytes)?;
+        if let Some(address) = self.storage(|s| s.archive_index(upload_id))? {
+            return self.receipt(upload_id, &plan, address);
+        }
+
+        let batches = plan.len();
+        let mut paid: HashMap<usize, ArchiveBatchRecord> = self
+            .storage(|s| s.archive_batches(upload_id))?
+            .into_iter()
+            .map(|record| (record.batch, record))
+            .collect();
+        let mut spent: u64 = paid.values().map(|record| record.quoted_cost).sum();
+        let stored: HashSet<String> = self
+            .storage(|s| s.archive_files(upload_id))?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+
+        for (batch, planned) in plan.iter().enumerate() {
+            if paid.get(&batch).is_some_and(|record| record.completed) {
+                on_progress(&ArchiveProgress {
+                    upload_id: upload_id.to_string(),
+                    batch,
+                    batches,
+                    stored: 0,
+                    skipped: 0,
+                    resumed: true,
+                    spent,
+                });
+                continue;
+            }
+
+            // Read what is left of the batch; contents stored before are
+            // taken from the dedup ledger
+            let mut pending = Vec::new();
+            let mut skipped = Vec::new();
+            for file in planned.iter().filter(|f| !stored.contains(&f.file.name)) {
+                let data = std::fs::read(&file.file.path).map_err(|e| {
+                    OsnovaError::Storage(format!(
+                        "Failed to read {}: {}",
+                        file.file.path.display(),
+                        e
+                    ))
+                })?;
+                let hash = blake3::hash(&data).to_hex().to_string();
+                match self.storage(|s| s.uploaded_content(&hash))? {
+                    Some((address, _)) => skipped.push((file, address, hash)),
+                    None => pending.push(PendingFile {
+                        planned: file,
+                        data,
+                        hash,
+                        quote: 0,
+                    }),
+                }
+            }
+
+            let record = match paid.remove(&batch) {
+                Some(record) => record,
+                None => {
+                    for file in &mut pending {
+                        file.quote = options
+                            .run("archive quote", self.transport.quote(&file.data))
+                            .await?;
+                    }
+                    let quote = BatchQuote {
+                        upload_id: upload_id.to_string(),
+                        batch,
+                        batches,
+                        files: pending.len(),
+                        bytes: pending.iter().map(|f| f.planned.size).sum(),
+                        cost: pending.iter().map(|f| f.quote).sum(),
+                    };
+                    if let Some(status) = cap_reached(policy, spent, &quote) {
+                        return Ok(status);
+                    }
+                    let record = self.pay(&quote).await?;
+                    spent += record.quoted_cost;
+                    record
+                }
+            };
+
+            for (file, address, hash) in &skipped {
+                let entry = ArchiveEntry {
+                    name: file.file.name.clone(),
+                    address: address.clone(),
+                    size: file.size,
+                };
+                self.storage(|s| {
+                    s.record_archive_file(upload_id, batch, file.position, &entry, hash, 0)
+                })?;
+            }
+            for file in &pending {
+                let payment = record.payment_request_id.as_deref().unwrap_or_default();
+                let receipt = self
+                    .put(upload_id, &file.data, file.quote, payment, options)
+                    .await?;
+                let entry = ArchiveEntry {
+                    name: file.planned.file.name.clone(),
+                    address: receipt.address,
+                    size: file.planned.size,
+                };
+                self.storage(|s| {
+                    s.record_archive_file(
+                        upload_id,
+                        batch,
+                        file.planned.position,
+                        &entry,
+                        &file.hash,
+                        receipt.cost,
+                    )
+                })?;
+            }
+            self.storage(|s| s.complete_archive_batch(upload_id, batch))?;
+
+            on_progress(&ArchiveProgress {
+                upload_id: upload_id.to_string(),
+                batch,
+                batches,
+                stored: pending.len(),
+                skipped: skipped.len(),
+                resumed: false,
+                spent,
+            });
+        }
+
+        // Every batch is stored; the index makes the archive
+        let index = ArchiveIndex {
+            files: self.storage(|s| s.archive_files(upload_id))?,
+        };
+        let data = serde_json::to_vec(&index)?;
+        let record = match paid.remove(&batches) {
+            Some(record) => record,
+            None => {
+                let cost = options
+                    .run("archive quote", self.transport.quote(&data))
+                    .await?;
+                let quote = BatchQuote {
+                    upload_id: upload_id.to_string(),
+                    batch: batches,
+                    batches,
+                    files: 1,
+                    bytes: data.len() as u64,
+                    cost,
+                };
+                if let Some(status) = cap_reached(policy, spent, &quote) {
+                    return Ok(status);
+                }
+                self.pay(&quote).await?
+            }
+        };
+        let payment = record.payment_request_id.as_deref().unwrap_or_default();
+        let receipt = self
+            .put(upload_id, &data, record.quoted_cost, payment, options)
+            .await?;
+        self.storage(|s| {
+            s.finish_archive_upload(
+                upload_id,
+                batches,
+                &receipt.address,
+                receipt.cost,
+                self.clock.now_unix(),
+            )
+        })?;
+
+        self.receipt(upload_id, &plan, receipt.address)
+    }
+
+    /// Ask the wallet to pay for a batch and record the payment
+    async fn pay(&self, quote: &BatchQuote) -> Result<ArchiveBatchRecord> {
+        let payment_request_id = if quote.files == 0 {
+            None
+        } else {
+            Some(self.payer.request_payment(quote).await?)
+        };
+        self.storage(|s| {
+            s.record_archive_batch_payment(
+                &quote.upload_id,
+                quote.batch,
+                payment_request_id.as_deref(),
+                quote.cost,
+            )
+        })?;
+        Ok(ArchiveBatchRecord {
+            batch: quote.batch,
+            payment_request_id,
+            quoted_cost: quote.cost,
+            actual_cost: 0,
+            completed: false,
+        })
+    }
+
+    /// Store `data`, recording the outcome in the payment history
+    async fn put(
+        &self,
+        upload_id: &str,
+        data: &[u8],
+        quote: u64,
+        payment_request_id: &str,
+        options: &NetworkOptions,
+    ) -> Result<UploadReceipt> {
+        let result = options
+            .run(
+                "archive upload",
+                self.transport.put(data, payment_request_id),
+            )
+            .await;
+        if let Some(ledger) = &self.ledger {
+            let context = UploadContext::for_component(format!("archive:{}", upload_id))
+                .with_quote(quote)
+                .with_payment_request(payment_request_id);
+            if let Err(e) = ledger.record(data.len() as u64, &context, result.as_ref()) {
+                crate::log!(Warn, "Failed to record upload payment: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Receipt of a completed upload, from what was recorded
+    fn receipt(
+        &self,
+        upload_id: &str,
+        plan: &[Vec<PlannedFile>],
+        address: String,
+    ) -> Result<ArchiveUploadStatus> {
+        let records = self.storage(|s| s.archive_batches(upload_id))?;
+        let mut batches: Vec<BatchReceipt> = plan
+            .iter()
+            .enumerate()
+            .map(|(batch, files)| BatchReceipt {
+                batch,
+                files: files.len(),
+                bytes: files.iter().map(|f| f.size).sum(),
+                payment_request_id: None,
+                quoted_cost: 0,
+                cost: 0,
+            })
+            .collect();
+        let mut index_cost = 0;
+        for record in records {
+            match batches.get_mut(record.batch) {
+                Some(receipt) => {
+                    receipt.payment_request_id = record.payment_request_id;
+                    receipt.quoted_cost = record.quoted_cost;
+                    receipt.cost = record.actual_cost;
+                }
+                None => index_cost += record.actual_cost,
+            }
+        }
+
+        let total_cost = batches.iter().map(|b| b.cost).sum::<u64>() + index_cost;
+        Ok(ArchiveUploadStatus::Completed(ArchiveReceipt {
+            upload_id: upload_id.to_string(),
+            address,
+            files: batches.iter().map(|b| b.files).sum(),
+            bytes: batches.iter().map(|b| b.bytes).sum(),
+            batches,
+            index_cost,
+            total_cost,
+        }))
+    }
+
+    fn storage<T>(&self, f: impl FnOnce(&SqlStorage) -> anyhow::Result<T>) -> Result<T> {
+        f(&self.storage.lock().unwrap()).map_err(|e| OsnovaError::Storage(format!("{:#}", e)))
+    }
+}
+
+/// Stop before paying for `quote` when it would exceed the spend cap
+fn cap_reached(
+    policy: &ArchivePolicy,
+    spent: u64,
+    quote: &BatchQuote,
+) -> Option<ArchiveUploadStatus> {
+    let cap = policy.spend_cap?;
+    (spent.saturating_add(quote.cost) > cap).then_some(ArchiveUploadStatus::SpendCapReached {
+        spent,
+        next_cost: quote.cost,
+        cap,
+        batch: quote.batch,
+        batches: quote.batches,
+    })
+}
+
+/// Split files into batches of at most `batch_size` bytes, in order
+fn plan_batches(files: &[ArchiveFile], batch_size: u64) -> Result<Vec<Vec<PlannedFile<'_>>>> {
+    let mut batches: Vec<Vec<PlannedFile>> = Vec::new();
+    let mut batch_bytes = 0u64;
+    for (position, file) in files.iter().enumerate() {
+        let size = std::fs::metadata(&file.path)
+            .map_err(|e| {
+                OsnovaError::Storage(format!("Failed to read {}: {}", file.path.display(), e))
+            })?
+            .len();
+        match batches.last_mut() {
+            Some(batch) if batch_bytes + size <= batch_size => {
+                batch_bytes += size;
+                batch.push(PlannedFile {
+                    position,
+                    file,
+                    size,
+                });
+            }
+            _ => {
+                batch_bytes = size;
+                batches.push(vec![PlannedFile {
+                    position,
+                    file,
+                    size,
+                }]);
+            }
+        }
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Network charging a token per byte, addressing data by its hash
+    #[derive(Default)]
+    struct MockNetwork {
+        puts: Mutex<Vec<Vec<u8>>>,
+        /// Fail the put with this position, once
+        fail_put: Mutex<Option<usize>>,
+    }
+
+    impl ArchiveTransport for MockNetwork {
+        fn quote<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<u64>> {
+            Box::pin(async move { Ok(data.len() as u64) })
+        }
+
+        fn put<'a>(&'a self, data: &'a [u8], _: &'a str) -> BoxFuture<'a, Result<UploadReceipt>> {
+            Box::pin(async move {
+                let mut puts = self.puts.lock().unwrap();
+                let mut fail_put = self.fail_put.lock().unwrap();
+                if *fail_put == Some(puts.len()) {
+                    fail_put.take();
+                    return Err(OsnovaError::Network("connection reset".to_string()));
+                }
+                puts.push(data.to_vec());
+                Ok(UploadReceipt {
+                    address: format!("ant://{}", blake3::hash(data).to_hex()),
+                    size: data.len() as u64,
+                    cost: data.len() as u64,
+                })
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct MockWallet {
+        payments: Mutex<Vec<BatchQuote>>,
+    }
+
+    impl BatchPayer for MockWallet {
+        fn request_payment<'a>(&'a self, quote: &'a BatchQuote) -> BoxFuture<'a, Result<String>> {
+            Box::pin(async move {
+                let mut payments = self.payments.lock().unwrap();
+                payments.push(quote.clone());
+                Ok(format!("req-{}", payments.len()))
+            })
+        }
+    }
+
+    struct Fixture {
+        temp: TempDir,
+        network: Arc<MockNetwork>,
+        wallet: Arc<MockWallet>,
+        uploader: ArchiveUploader,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self::with_ledger(None)
+        }
+
+        fn with_ledger(ledger: Option<Arc<PaymentLedger>>) -> Self {
+            let temp = TempDir::new().unwrap();
+            let network = Arc::new(MockNetwork::default());
+            let wallet = Arc::new(MockWallet::default());
+            let storage = SqlStorage::new(temp.path().join("osnova.db")).unwrap();
+            let mut uploader = ArchiveUploader::new(storage, network.clone(), wallet.clone());
+            if let Some(ledger) = ledger {
+                uploader = uploader.with_ledger(ledger);
+            }
+            Self {
+                temp,
+                network,
+                wallet,
+                uploader,
+            }
+        }
+
+        /// Write files of 40 bytes each, filled with the given byte
+        fn files(&self, files: &[(&str, u8)]) -> Vec<ArchiveFile> {
+            files
+                .iter()
+                .map(|(name, fill)| {
+                    let path = self.temp.path().join(name);
+                    std::fs::write(&path, [*fill; 40]).unwrap();
+                    ArchiveFile::new(*name, path)
+                })
+                .collect()
+        }
+
+        async fn upload(
+            &self,
+            upload_id: &str,
+            files: &[ArchiveFile],
+            spend_cap: Option<u64>,
+        ) -> (Result<ArchiveUploadStatus>, Vec<ArchiveProgress>) {
+            let policy = ArchivePolicy {
+                batch_size_bytes: 100,
+                spend_cap,
+            };
+            let mut progress = Vec::new();
+            let status = self
+                .uploader
+                .upload(upload_id, files, &policy, &NetworkOptions::default(), |p| {
+                    progress.push(p.clone())
+                })
+                .await;
+            (status, progress)
+        }
+
+        fn payments(&self) -> usize {
+            self.wallet.payments.lock().unwrap().len()
+        }
+
+        fn puts(&self) -> usize {
+            self.network.puts.lock().unwrap().len()
+        }
+
+        fn stored_index(&self) -> ArchiveIndex {
+            serde_json::from_slice(self.network.puts.lock().unwrap().last().unwrap()).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_is_a_cancellable_task() {
+        use crate::models::task::TaskState;
+
+        let tasks = Arc::new(TaskRegistry::new());
+        let fixture = Fixture::new();
+        let fixture = Fixture {
+            uploader: fixture.uploader.with_tasks(tasks.clone()),
+            ..fixture
+        };
+        let files = fixture.files(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+
+        // Cancel from the activity center once the first batch is stored
+        let options = NetworkOptions::default();
+        let policy = ArchivePolicy {
+            batch_size_bytes: 100,
+            spend_cap: None,
+        };
+        let status = fixture
+            .uploader
+            .upload("photos", &files, &policy, &options, |_| {
+                let task = &tasks.list_active()[0];
+                assert_eq!(task.category, TaskCategory::Backup);
+                assert_eq!((task.completed, task.total), (1, Some(2)));
+                tasks.cancel(&task.id).unwrap();
+            })
+            .await;
+        assert!(matches!(status, Err(OsnovaError::Cancelled { .. })));
+        assert!(options.cancellation.is_cancelled());
+        assert_eq!(fixture.payments(), 1);
+        let task = &tasks.history(1)[0];
+        assert_eq!(task.name, "Upload archive photos");
+        assert_eq!(task.state, TaskState::Cancelled);
+
+        let (status, _) = fixture.upload("photos", &files, None).await;
+        completed(status.unwrap());
+        assert_eq!(tasks.history(1)[0].state, TaskState::Completed);
+        // The stored batch was not paid for again; the index is paid alone
+        assert_eq!(fixture.payments(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_offline_mode_stops_upload_until_resumed() {
+        use crate::network::NetworkKillSwitch;
+
+        let fixture = Fixture::new();
+        let files = fixture.files(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let options = NetworkOptions::default().with_kill_switch(switch.clone());
+        let policy = ArchivePolicy {
+            batch_size_bytes: 100,
+            spend_cap: None,
+        };
+
+        // Going offline once the first batch is stored stops the upload
+        let status = fixture
+            .uploader
+            .upload("photos", &files, &policy, &options, |_| switch.engage())
+            .await;
+        assert!(matches!(status, Err(OsnovaError::OfflineMode { .. })));
+        assert_eq!((fixture.payments(), fixture.puts()), (1, 2));
+
+        // Nothing reaches the network while offline
+        let status = fixture
+            .uploader
+            .upload("photos", &files, &policy, &options, |_| {})
+            .await;
+        assert!(matches!(status, Err(OsnovaError::OfflineMode { .. })));
+        assert_eq!((fixture.payments(), fixture.puts()), (1, 2));
+
+        switch.release();
+        let status = fixture
+            .uploader
+            .upload("photos", &files, &policy, &options, |_| {})
+            .await;
+        completed(status.unwrap());
+        assert_eq!(fixture.payments(), 3);
+    }
+
+    fn completed(status: ArchiveUploadStatus) -> ArchiveReceipt {
+        match status {
+            ArchiveUploadStatus::Completed(receipt) => receipt,
+            other => panic!("upload did not complete: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_failure_reuses_payments() {
+        let fixture = Fixture::new();
+        let files = fixture.files(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+
+        // The second file of the second batch fails after both batches
+        // were paid for
+        *fixture.network.fail_put.lock().unwrap() = Some(3);
+        let (status, progress) = fixture.upload("photos", &files, None).await;
+        assert!(matches!(status, Err(OsnovaError::Network(_))));
+        assert_eq!(fixture.payments(), 2);
+        assert_eq!(fixture.puts(), 3);
+        assert_eq!(progress.len(), 1);
+
+        let (status, progress) = fixture.upload("photos", &files, None).await;
+        let receipt = completed(status.unwrap());
+        // Only the index was paid for again, and only d and the index stored
+        assert_eq!(fixture.payments(), 3);
+        assert_eq!(fixture.puts(), 5);
+        assert!(progress[0].resumed);
+        assert_eq!((progress[1].stored, progress[1].resumed), (1, false));
+        assert_eq!(
+            receipt.batches[1].payment_request_id.as_deref(),
+            Some("req-2")
+        );
+        assert_eq!(receipt.batches[1].cost, 80);
+
+        let index = fixture.stored_index();
+        let names: Vec<&str> = index.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d"]);
+        assert_eq!(
+            receipt.address,
+            format!(
+                "ant://{}",
+                blake3::hash(&serde_json::to_vec(&index).unwrap()).to_hex()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spend_cap_stops_between_batches() {
+        let fixture = Fixture::new();
+        let files = fixture.files(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+
+        let (status, progress) = fixture.upload("photos", &files, Some(100)).await;
+        assert_eq!(
+            status.unwrap(),
+            ArchiveUploadStatus::SpendCapReached {
+                spent: 80,
+                next_cost: 80,
+                cap: 100,
+                batch: 1,
+                batches: 2,
+            }
+        );
+        assert_eq!(fixture.payments(), 1);
+        assert_eq!(fixture.puts(), 2);
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].spent, 80);
+
+        // The cap covers the whole upload, not each run
+        let (status, _) = fixture.upload("photos", &files, Some(150)).await;
+        assert!(matches!(
+            status.unwrap(),
+            ArchiveUploadStatus::SpendCapReached { spent: 80, .. }
+        ));
+
+        let (status, _) = fixture.upload("photos", &files, Some(1_000)).await;
+        let receipt = completed(status.unwrap());
+        assert_eq!(fixture.payments(), 3);
+        assert_eq!(fixture.puts(), 5);
+        assert_eq!(receipt.files, 4);
+    }
+
+    #[tokio::test]
+    async fn test_receipt_adds_up_costs() {
+        let ledger = Arc::new(PaymentLedger::new(SqlStorage::new_in_memory().unwrap()));
+        let fixture = Fixture::with_ledger(Some(ledger.clone()));
+        let files = fixture.files(&[("a", 1), ("b", 2), ("c", 3)]);
+
+        let (status, _) = fixture.upload("photos", &files, None).await;
+        let receipt = completed(status.unwrap());
+        let index_len = serde_json::to_vec(&fixture.stored_index()).unwrap().len() as u64;
+        assert_eq!(receipt.bytes, 120);
+        assert_eq!(
+            receipt
+                .batches
+                .iter()
+                .map(|b| (b.files, b.bytes, b.quoted_cost, b.cost))
+                .collect::<Vec<_>>(),
+            [(2, 80, 80, 80), (1, 40, 40, 40)]
+        );
+        assert_eq!(receipt.index_cost, index_len);
+        assert_eq!(receipt.total_cost, 120 + index_len);
+
+        // Every stored file is in the wallet history under its batch's payment
+        let records = ledger.list(None, None).unwrap();
+        assert_eq!(records.len(), 4);
+        let recorded: u64 = records.iter().filter_map(|r| r.actual_cost).sum();
+        assert_eq!(recorded, receipt.total_cost);
+        assert!(records
+            .iter()
+            .all(|r| r.component_id.as_deref() == Some("archive:photos")));
+
+        // Running a completed upload again stores nothing
+        let (status, _) = fixture.upload("photos", &files, None).await;
+        assert_eq!(completed(status.unwrap()), receipt);
+        assert_eq!(fixture.puts(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_skips_stored_contents() {
+        let fixture = Fixture::new();
+        let first = fixture.files(&[("a", 1), ("b", 2)]);
+        completed(fixture.upload("first", &first, None).await.0.unwrap());
+        let first_index = fixture.stored_index();
+        let puts = fixture.puts();
+
+        // Same contents as a under another name, and one new file
+        let second = fixture.files(&[("copy-of-a", 1), ("e", 5)]);
+        let (status, progress) = fixture.upload("second", &second, None).await;
+        let receipt = completed(status.unwrap());
+
+        assert_eq!(fixture.puts(), puts + 2);
+        assert_eq!((progress[0].stored, progress[0].skipped), (1, 1));
+        let payments = fixture.wallet.payments.lock().unwrap();
+        let batch = &payments[payments.len() - 2];
+        assert_eq!((batch.files, batch.bytes, batch.cost), (1, 40, 40));
+        assert_eq!(receipt.batches[0].cost, 40);
+        assert_eq!(
+            fixture.stored_index().files[0].address,
+            first_index.files[0].address
+        );
+    }
+}
@@ -24,6 +24,7 @@
 //! }
 //! ```
 
+use super::{NetworkOptions, PaymentLedger};
 use crate::error::{OsnovaError, Result};
 use autonomi::client::Client;
 use std::sync::Arc;
@@ -37,6 +38,8 @@ use tokio::sync::RwLock;
 pub struct AutonomiClient {
     /// Internal Autonomi client (wrapped in Arc for thread safety)
     pub(crate) client: Arc<RwLock<Option<Client>>>,
+    /// Ledger recording uploads and their cost, when attached
+    pub(crate) ledger: Option<Arc<PaymentLedger>>,
 }
 
 impl AutonomiClient {
@@ -63,6 +66,7 @@ impl AutonomiClient {
 
         Ok(Self {
             client: Arc::new(RwLock::new(Some(client))),
+            ledger: None,
         })
     }
 
@@ -88,9 +92,37 @@ impl AutonomiClient {
 
         Ok(Self {
             client: Arc::new(RwLock::new(Some(client))),
+            ledger: None,
         })
     }
 
+    /// Connect in local mode with a timeout and cancellation
+    ///
+    /// Like [`connect`](Self::connect), but fails with
+    /// `OsnovaError::Timeout` or `OsnovaError::Cancelled` according to
+    /// `options`.
+    pub async fn connect_with(options: &NetworkOptions) -> Result<Self> {
+        options.run("connect", Self::connect()).await
+    }
+
+    /// Connect to the Alphanet with a timeout and cancellation
+    pub async fn connect_alpha_with(options: &NetworkOptions) -> Result<Self> {
+        options.run("connect", Self::connect_alpha()).await
+    }
+
+    /// Record uploads made through this client in `ledger`
+    ///
+    /// See [`upload_data_for`](super::upload_data_for).
+    pub fn with_payment_ledger(mut self, ledger: Arc<PaymentLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Ledger recording uploads made through this client, if attached
+    pub fn payment_ledger(&self) -> Option<&Arc<PaymentLedger>> {
+        self.ledger.as_ref()
+    }
+
     /// Check if client is connected
     ///
     /// # Returns
@@ -216,6 +248,7 @@ mod tests {
         // Test that a newly created client (without connect) is not connected
         let client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
         assert!(!client.is_connected());
     }
@@ -225,6 +258,7 @@ mod tests {
         // Test that health check fails on unconnected client
         let client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
         let result = client.health_check().await;
         assert!(result.is_err());
@@ -236,6 +270,7 @@ mod tests {
         // Test that disconnect fails when already disconnected
         let mut client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
         let result = client.disconnect().await;
         assert!(result.is_err());
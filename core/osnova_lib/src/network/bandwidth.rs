@@ -0,0 +1,582 @@
+//! # Bandwidth Accounting
+//!
+//! Meter network transfers and enforce download limits on metered connections.
+//!
+//! This module provides:
+//! - Per-category byte accounting (component downloads, prefetch, uploads, backups,
+//!   app backend traffic)
+//! - Daily and monthly usage rollups persisted in SqlStorage
+//! - A [`BandwidthPolicy`] that defers work instead of failing it
+//! - Deferring all work while the
+//!   [network kill switch](super::kill_switch) is engaged
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::network::bandwidth::{
+//!     BandwidthMeter, BandwidthPolicy, TransferCategory, TransferDecision,
+//! };
+//! use osnova_lib::storage::SqlStorage;
+//!
+//! let storage = SqlStorage::new("osnova.db")?;
+//! let meter = BandwidthMeter::new(storage, BandwidthPolicy::DailyLimit {
+//!     bytes_per_day: 50 * 1024 * 1024,
+//! });
+//!
+//! if meter.check()? == TransferDecision::Allowed {
+//!     // ... transfer ...
+//!     meter.record(TransferCategory::ComponentDownload, 1024)?;
+//! }
+//! ```
+
+use crate::error::{OsnovaError, Result};
+use crate::network::kill_switch::{self, NetworkKillSwitch};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+use crate::util::format::{DisplayContext, Localize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Seconds in one day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Category of network transfer being metered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferCategory {
+    /// Application component downloads
+    ComponentDownload,
+    /// Manifest and icon prefetching
+    Prefetch,
+    /// Data uploads
+    Upload,
+    /// Backup transfers
+    Backup,
+    /// App backend traffic through the network proxy
+    AppTraffic,
+}
+
+impl TransferCategory {
+    /// All transfer categories
+    pub const ALL: [TransferCategory; 5] = [
+        TransferCategory::ComponentDownload,
+        TransferCategory::Prefetch,
+        TransferCategory::Upload,
+        TransferCategory::Backup,
+        TransferCategory::AppTraffic,
+    ];
+
+    /// Storage identifier for the category
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferCategory::ComponentDownload => "component_download",
+            TransferCategory::Prefetch => "prefetch",
+            TransferCategory::Upload => "upload",
+            TransferCategory::Backup => "backup",
+            TransferCategory::AppTraffic => "app_traffic",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == s)
+    }
+}
+
+/// Connection type as reported by the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionType {
+    /// The platform cannot report the connection type
+    #[default]
+    Unknown,
+    /// Wi-Fi connection
+    Wifi,
+    /// Wired connection
+    Ethernet,
+    /// Cellular (metered) connection
+    Cellular,
+}
+
+/// Bandwidth policy for metered connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum BandwidthPolicy {
+    /// No limits on transfers
+    #[default]
+    Unlimited,
+    /// Limit total transferred bytes per day
+    DailyLimit {
+        /// Maximum bytes per day across all categories
+        bytes_per_day: u64,
+    },
+    /// Only transfer on Wi-Fi or wired connections
+    ///
+    /// Where the platform cannot report the connection type, the user-set
+    /// metered mode toggle decides instead.
+    WifiOnly,
+}
+
+/// Reason a transfer was deferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum DeferReason {
+    /// The daily limit has been reached
+    DailyLimitReached {
+        /// Bytes transferred today
+        used: u64,
+        /// Configured daily limit
+        limit: u64,
+    },
+    /// The connection is metered and the policy is Wi-Fi only
+    MeteredConnection,
+    /// The user switched Osnova offline
+    OfflineByUser,
+}
+
+/// Result of checking a transfer against the bandwidth policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransferDecision {
+    /// The transfer may proceed
+    Allowed,
+    /// The transfer should be deferred until the policy allows it
+    Deferred(DeferReason),
+}
+
+/// Outcome of a metered transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferStatus<T> {
+    /// The transfer completed
+    Completed(T),
+    /// The transfer was deferred by the bandwidth policy
+    Deferred(DeferReason),
+}
+
+/// Usage for a single period
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRollup {
+    /// Period label (`YYYY-MM-DD` for days, `YYYY-MM` for months)
+    pub period: String,
+    /// Bytes transferred per category
+    pub by_category: BTreeMap<TransferCategory, u64>,
+    /// Total bytes transferred
+    pub total: u64,
+}
+
+/// Daily and monthly bandwidth usage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthUsage {
+    /// Usage for the current day
+    pub today: UsageRollup,
+    /// Usage for the current calendar month
+    pub month: UsageRollup,
+    /// Active policy
+    pub policy: BandwidthPolicy,
+    /// Totals formatted for display, once localized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<BandwidthUsageDisplay>,
+}
+
+/// [`BandwidthUsage`] totals formatted for display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthUsageDisplay {
+    /// Transferred today
+    pub today: String,
+    /// Transferred this month
+    pub month: String,
+    /// Daily limit of the policy, if it sets one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_limit: Option<String>,
+}
+
+impl Localize for BandwidthUsage {
+    fn localize(&mut self, context: &DisplayContext) {
+        let daily_limit = match self.policy {
+            BandwidthPolicy::DailyLimit { bytes_per_day } => Some(context.bytes(bytes_per_day)),
+            BandwidthPolicy::Unlimited | BandwidthPolicy::WifiOnly => None,
+        };
+        self.display = Some(BandwidthUsageDisplay {
+            today: context.bytes(self.today.total),
+            month: context.bytes(self.month.total),
+            daily_limit,
+        });
+    }
+}
+
+/// Mutable policy state of a meter
+#[derive(Debug, Clone, Copy)]
+struct PolicyState {
+    policy: BandwidthPolicy,
+    metered_mode: bool,
+    connection: ConnectionType,
+}
+
+/// Bandwidth meter and policy enforcer
+///
+/// Shared between the downloader, prefetcher and upload paths (wrap in `Arc`).
+/// Usage is persisted per day and category in SqlStorage.
+pub struct BandwidthMeter {
+    storage: Mutex<SqlStorage>,
+    state: Mutex<PolicyState>,
+    clock: SharedClock,
+    kill_switch: Arc<NetworkKillSwitch>,
+}
+
+impl BandwidthMeter {
+    /// Create a new bandwidth meter
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - SQL storage holding usage records
+    /// * `policy` - Initial bandwidth policy
+    pub fn new(storage: SqlStorage, policy: BandwidthPolicy) -> Self {
+        Self {
+            storage: Mutex::new(storage),
+            state: Mutex::new(PolicyState {
+                policy,
+                metered_mode: false,
+                connection: ConnectionType::Unknown,
+            }),
+            clock: time::default_clock(),
+            kill_switch: kill_switch::shared(),
+        }
+    }
+
+    /// Use a specific clock for the usage days
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Use `kill_switch` instead of the process-wide one
+    pub fn with_kill_switch(mut self, kill_switch: Arc<NetworkKillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Get the active policy
+    pub fn policy(&self) -> BandwidthPolicy {
+        self.state.lock().unwrap().policy
+    }
+
+    /// Set the active policy
+    pub fn set_policy(&self, policy: BandwidthPolicy) {
+        self.state.lock().unwrap().policy = policy;
+    }
+
+    /// Set the user-controlled metered mode toggle
+    pub fn set_metered_mode(&self, metered: bool) {
+        self.state.lock().unwrap().metered_mode = metered;
+    }
+
+    /// Set the connection type reported by the platform
+    pub fn set_connection_type(&self, connection: ConnectionType) {
+        self.state.lock().unwrap().connection = connection;
+    }
+
+    /// Check whether a transfer may proceed under the current policy
+    ///
+    /// The daily limit applies to the total across all categories. Every
+    /// transfer is deferred while the kill switch is engaged, whatever the
+    /// policy.
+    pub fn check(&self) -> Result<TransferDecision> {
+        if self.kill_switch.is_engaged() {
+            return Ok(TransferDecision::Deferred(DeferReason::OfflineByUser));
+        }
+        let state = *self.state.lock().unwrap();
+
+        match state.policy {
+            BandwidthPolicy::Unlimited => Ok(TransferDecision::Allowed),
+            BandwidthPolicy::DailyLimit { bytes_per_day } => {
+                let used = self
+                    .usage_for_days(day_index(self.clock.now_unix()), 1)?
+                    .total;
+                if used >= bytes_per_day {
+                    Ok(TransferDecision::Deferred(DeferReason::DailyLimitReached {
+                        used,
+                        limit: bytes_per_day,
+                    }))
+                } else {
+                    Ok(TransferDecision::Allowed)
+                }
+            }
+            BandwidthPolicy::WifiOnly => {
+                let metered = match state.connection {
+                    ConnectionType::Wifi | ConnectionType::Ethernet => false,
+                    ConnectionType::Cellular => true,
+                    ConnectionType::Unknown => state.metered_mode,
+                };
+                if metered {
+                    Ok(TransferDecision::Deferred(DeferReason::MeteredConnection))
+                } else {
+                    Ok(TransferDecision::Allowed)
+                }
+            }
+        }
+    }
+
+    /// Record bytes transferred in a category
+    pub fn record(&self, category: TransferCategory, bytes: u64) -> Result<()> {
+        if bytes == 0 {
+            return Ok(());
+        }
+
+        let day = day_index(self.clock.now_unix());
+        self.storage
+            .lock()
+            .unwrap()
+            .add_bandwidth_usage(day, category.as_str(), bytes)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to record bandwidth: {}", e)))
+    }
+
+    /// Get daily and monthly usage rollups
+    pub fn usage(&self) -> Result<BandwidthUsage> {
+        let today = day_index(self.clock.now_unix());
+        let (year, month, day) = civil_from_days(today);
+        let month_start = today - (u64::from(day) - 1);
+
+        let mut today_rollup = self.usage_for_days(today, 1)?;
+        today_rollup.period = format!("{:04}-{:02}-{:02}", year, month, day);
+
+        let mut month_rollup = self.usage_for_days(month_start, today - month_start + 1)?;
+        month_rollup.period = format!("{:04}-{:02}", year, month);
+
+        Ok(BandwidthUsage {
+            today: today_rollup,
+            month: month_rollup,
+            policy: self.policy(),
+            display: None,
+        })
+    }
+
+    /// Sum usage over `count` days starting at `start_day`
+    fn usage_for_days(&self, start_day: u64, count: u64) -> Result<UsageRollup> {
+        let rows = self
+            .storage
+            .lock()
+            .unwrap()
+            .get_bandwidth_usage(start_day, start_day + count - 1)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to read bandwidth usage: {}", e)))?;
+
+        let mut rollup = UsageRollup::default();
+        for (_, category, bytes) in rows {
+            if let Some(category) = TransferCategory::from_str(&category) {
+                *rollup.by_category.entry(category).or_insert(0) += bytes;
+                rollup.total += bytes;
+            }
+        }
+
+        Ok(rollup)
+    }
+}
+
+/// Convert a Unix timestamp into a day index (days since the epoch, UTC)
+fn day_index(timestamp: u64) -> u64 {
+    timestamp / SECONDS_PER_DAY
+}
+
+/// Convert a day index into a (year, month, day) civil date (UTC)
+pub(crate) fn civil_from_days(days: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+
+    // 2024-03-31 23:59:00 UTC
+    const END_OF_MARCH: u64 = 1_711_929_540;
+
+    fn create_meter(policy: BandwidthPolicy, clock: Arc<MockClock>) -> BandwidthMeter {
+        let storage = SqlStorage::new_in_memory().unwrap();
+        BandwidthMeter::new(storage, policy).with_clock(clock)
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(day_index(END_OF_MARCH)), (2024, 3, 31));
+        assert_eq!(civil_from_days(day_index(END_OF_MARCH) + 1), (2024, 4, 1));
+        // Leap day
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn test_accounting_across_categories() -> Result<()> {
+        let meter = create_meter(
+            BandwidthPolicy::Unlimited,
+            Arc::new(MockClock::new(END_OF_MARCH)),
+        );
+
+        meter.record(TransferCategory::ComponentDownload, 1_000)?;
+        meter.record(TransferCategory::ComponentDownload, 500)?;
+        meter.record(TransferCategory::Prefetch, 200)?;
+        meter.record(TransferCategory::Upload, 30)?;
+        meter.record(TransferCategory::Backup, 4)?;
+
+        let usage = meter.usage()?;
+        assert_eq!(usage.today.total, 1_734);
+        assert_eq!(
+            usage.today.by_category[&TransferCategory::ComponentDownload],
+            1_500
+        );
+        assert_eq!(usage.today.by_category[&TransferCategory::Prefetch], 200);
+        assert_eq!(usage.today.by_category[&TransferCategory::Upload], 30);
+        assert_eq!(usage.today.by_category[&TransferCategory::Backup], 4);
+        assert_eq!(usage.month.total, 1_734);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_display_matches_totals() -> Result<()> {
+        use crate::util::format::{format_bytes, Locale};
+
+        let meter = create_meter(
+            BandwidthPolicy::DailyLimit {
+                bytes_per_day: 50 * 1024 * 1024,
+            },
+            Arc::new(MockClock::new(END_OF_MARCH)),
+        );
+        meter.record(TransferCategory::ComponentDownload, 3 * 1024 * 1024 / 2)?;
+
+        let usage = meter.usage()?;
+        assert!(usage.display.is_none());
+        let french = Locale::new("fr-FR");
+        let usage = usage.localized(&DisplayContext::new(french.clone(), END_OF_MARCH));
+        let display = usage.display.clone().unwrap();
+        assert_eq!(display.today, "1,5 Mio");
+        assert_eq!(display.month, format_bytes(usage.month.total, &french));
+        assert_eq!(display.daily_limit.as_deref(), Some("50 Mio"));
+        assert_eq!(serde_json::to_value(&usage)?["display"]["today"], "1,5 Mio");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollup_boundaries() -> Result<()> {
+        let clock = Arc::new(MockClock::new(END_OF_MARCH));
+        let meter = create_meter(BandwidthPolicy::Unlimited, clock.clone());
+
+        meter.record(TransferCategory::ComponentDownload, 100)?;
+        let usage = meter.usage()?;
+        assert_eq!(usage.today.period, "2024-03-31");
+        assert_eq!(usage.month.period, "2024-03");
+
+        // Cross midnight into a new day and month
+        clock.set_unix(END_OF_MARCH + 120);
+        meter.record(TransferCategory::ComponentDownload, 40)?;
+
+        let usage = meter.usage()?;
+        assert_eq!(usage.today.period, "2024-04-01");
+        assert_eq!(usage.today.total, 40);
+        assert_eq!(usage.month.period, "2024-04");
+        assert_eq!(usage.month.total, 40);
+
+        // Later in the same month accumulates into the monthly rollup
+        clock.set_unix(END_OF_MARCH + 3 * SECONDS_PER_DAY);
+        meter.record(TransferCategory::Upload, 2)?;
+
+        let usage = meter.usage()?;
+        assert_eq!(usage.today.total, 2);
+        assert_eq!(usage.month.total, 42);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_daily_limit_defers_and_resets() -> Result<()> {
+        let clock = Arc::new(MockClock::new(END_OF_MARCH));
+        let meter = create_meter(
+            BandwidthPolicy::DailyLimit { bytes_per_day: 100 },
+            clock.clone(),
+        );
+
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+
+        meter.record(TransferCategory::ComponentDownload, 100)?;
+        assert_eq!(
+            meter.check()?,
+            TransferDecision::Deferred(DeferReason::DailyLimitReached {
+                used: 100,
+                limit: 100
+            })
+        );
+
+        // The next day the limit resets
+        clock.set_unix(END_OF_MARCH + 60);
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deferral_resumes_when_policy_allows() -> Result<()> {
+        let meter = create_meter(
+            BandwidthPolicy::WifiOnly,
+            Arc::new(MockClock::new(END_OF_MARCH)),
+        );
+
+        // Unknown connection falls back to the metered mode toggle
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+        meter.set_metered_mode(true);
+        assert_eq!(
+            meter.check()?,
+            TransferDecision::Deferred(DeferReason::MeteredConnection)
+        );
+
+        // A reported Wi-Fi connection overrides the toggle
+        meter.set_connection_type(ConnectionType::Wifi);
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+
+        meter.set_connection_type(ConnectionType::Cellular);
+        assert!(matches!(meter.check()?, TransferDecision::Deferred(_)));
+
+        meter.set_policy(BandwidthPolicy::Unlimited);
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kill_switch_defers_whatever_the_policy() -> Result<()> {
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let meter = create_meter(
+            BandwidthPolicy::Unlimited,
+            Arc::new(MockClock::new(END_OF_MARCH)),
+        )
+        .with_kill_switch(switch.clone());
+
+        switch.engage();
+        assert_eq!(
+            meter.check()?,
+            TransferDecision::Deferred(DeferReason::OfflineByUser)
+        );
+
+        switch.release();
+        assert_eq!(meter.check()?, TransferDecision::Allowed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_serialization() {
+        let policy = BandwidthPolicy::DailyLimit { bytes_per_day: 42 };
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, r#"{"mode":"daily_limit","bytes_per_day":42}"#);
+
+        let parsed: BandwidthPolicy = serde_json::from_str(r#"{"mode":"wifi_only"}"#).unwrap();
+        assert_eq!(parsed, BandwidthPolicy::WifiOnly);
+    }
+}
@@ -0,0 +1,867 @@
+//! # Local Server Discovery
+//!
+//! Find Osnova servers on the local network with mDNS (DNS-SD), so a client
+//! can pick a server instead of typing its address.
+//!
+//! This module provides:
+//! - Announcing a `_osnova._tcp` service carrying the server's fingerprint
+//!   and whether it accepts pairing
+//! - Browsing for announced servers with a timeout, keeping whatever
+//!   answered in time
+//! - An [`MdnsTransport`] trait, implemented over UDP multicast by
+//!   [`MdnsSocket`], so tests can use an in-process responder
+//!
+//! Neither announcing nor browsing happens while the
+//! [network kill switch](super::kill_switch) is engaged.
+//!
+//! An announcement is only a hint: anyone on the LAN can announce any
+//! fingerprint. The fingerprint shown in the picker must be checked against
+//! the key the server presents during pairing, with
+//! [`DiscoveredServer::verify_pairing`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::network::discovery::LanDiscovery;
+//! use std::time::Duration;
+//!
+//! let discovery = LanDiscovery::mdns()?;
+//! for server in discovery.discover(Duration::from_secs(3))? {
+//!     println!("{} at {} ({})", server.name, server.address, server.fingerprint);
+//! }
+//! ```
+
+use crate::error::{OsnovaError, Result};
+use crate::models::pairing::PairingSession;
+use crate::network::kill_switch::{self, NetworkKillSwitch};
+use hickory_proto::op::{Message, MessageType, Query};
+use hickory_proto::rr::rdata::{A, AAAA, PTR, SRV, TXT};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// DNS-SD service type announced by Osnova servers
+pub const SERVICE_TYPE: &str = "_osnova._tcp.local.";
+
+/// mDNS multicast group and port (RFC 6762)
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+/// TTL of announced records, in seconds
+const RECORD_TTL_SECS: u32 = 120;
+
+/// Longest instance name that fits in one DNS label
+const MAX_INSTANCE_LEN: usize = 63;
+
+/// How often the responder thread checks whether it should stop
+const RESPONDER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// TXT keys
+const TXT_VERSION: &str = "v=1";
+const TXT_FINGERPRINT: &str = "fp=";
+const TXT_PAIRING: &str = "pair=";
+
+/// Fingerprint of a server's Ed25519 public key, as announced and as shown
+/// to the user (hex BLAKE3)
+pub fn server_fingerprint(server_public_key: &[u8]) -> String {
+    blake3::hash(server_public_key).to_hex().to_string()
+}
+
+/// One announced server, as carried over mDNS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceRecord {
+    /// Human-readable instance name (at most 63 bytes)
+    pub instance: String,
+    /// Host name the addresses belong to (e.g. `osnova-1a2b3c4d.local.`)
+    pub host: String,
+    /// Addresses of the host; filled in by the transport if empty
+    pub addresses: Vec<IpAddr>,
+    /// Port the server listens on
+    pub port: u16,
+    /// Server fingerprint (see [`server_fingerprint`])
+    pub fingerprint: String,
+    /// Whether the server accepts new pairings
+    pub pairing: bool,
+}
+
+/// A server found on the local network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredServer {
+    /// Name the server announced
+    pub name: String,
+    /// Address to connect to
+    pub address: SocketAddr,
+    /// Fingerprint the server announced; unverified until pairing
+    pub fingerprint: String,
+    /// Whether the server accepts new pairings
+    pub accepts_pairing: bool,
+}
+
+impl DiscoveredServer {
+    /// Check the announced fingerprint against the server key presented
+    /// during pairing
+    ///
+    /// # Errors
+    ///
+    /// Returns `OsnovaError::Identity` if they differ, meaning the
+    /// announcement did not come from the server that answered the pairing.
+    pub fn verify_pairing(&self, session: &PairingSession) -> Result<()> {
+        let presented = server_fingerprint(session.server_public_key());
+        if presented != self.fingerprint {
+            return Err(OsnovaError::Identity(format!(
+                "Server fingerprint mismatch: {} announced {}, but pairing presented {}",
+                self.name, self.fingerprint, presented
+            )));
+        }
+        Ok(())
+    }
+
+    fn from_record(record: ServiceRecord) -> Option<Self> {
+        // Prefer IPv4; link-local IPv6 addresses need a scope to be usable
+        let ip = record
+            .addresses
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .or(record.addresses.first())?;
+
+        Some(Self {
+            name: record.instance,
+            address: SocketAddr::new(*ip, record.port),
+            fingerprint: record.fingerprint,
+            accepts_pairing: record.pairing,
+        })
+    }
+}
+
+/// Sends and receives service records on the local network
+pub trait MdnsTransport: Send + Sync {
+    /// Start answering queries with `record`, replacing an announcement with
+    /// the same instance name
+    fn announce(&self, record: &ServiceRecord) -> Result<()>;
+
+    /// Stop answering for an instance and tell listeners it is gone
+    fn withdraw(&self, instance: &str) -> Result<()>;
+
+    /// Ask the network for announced servers
+    fn query(&self) -> Result<()>;
+
+    /// Wait until `deadline` for the next answer; `None` once it passes
+    fn recv(&self, deadline: Instant) -> Result<Option<ServiceRecord>>;
+}
+
+/// Announces this server and finds others on the local network
+///
+/// Announcing can be turned off for privacy (see
+/// `ConfigService::set_lan_announcements_enabled`); browsing is always
+/// allowed.
+pub struct LanDiscovery {
+    transport: Arc<dyn MdnsTransport>,
+    announcements_enabled: AtomicBool,
+    announced: Mutex<Option<ServiceRecord>>,
+    kill_switch: Arc<NetworkKillSwitch>,
+}
+
+impl LanDiscovery {
+    /// Create a discovery handle over a transport
+    pub fn new(transport: Arc<dyn MdnsTransport>) -> Self {
+        Self {
+            transport,
+            announcements_enabled: AtomicBool::new(true),
+            announced: Mutex::new(None),
+            kill_switch: kill_switch::shared(),
+        }
+    }
+
+    /// Create a discovery handle over UDP multicast
+    pub fn mdns() -> Result<Self> {
+        Ok(Self::new(Arc::new(MdnsSocket::new()?)))
+    }
+
+    /// Set whether this server may announce itself
+    pub fn with_announcements(self, enabled: bool) -> Self {
+        self.announcements_enabled.store(enabled, Ordering::SeqCst);
+        self
+    }
+
+    /// Use `kill_switch` instead of the process-wide one
+    pub fn with_kill_switch(mut self, kill_switch: Arc<NetworkKillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Turn announcements on or off, withdrawing an active announcement
+    /// when turned off
+    pub fn set_announcements_enabled(&self, enabled: bool) -> Result<()> {
+        self.announcements_enabled.store(enabled, Ordering::SeqCst);
+        if !enabled {
+            self.withdraw()?;
+        }
+        Ok(())
+    }
+
+    /// Announce this server
+    ///
+    /// Returns `false`, without announcing, if announcements are disabled
+    /// or the kill switch is engaged. Engaging the switch does not withdraw
+    /// an active announcement; the caller does, with
+    /// [`withdraw`](Self::withdraw).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name shown in client pickers (truncated to 63 bytes)
+    /// * `port` - Port the server listens on
+    /// * `server_public_key` - Key the server presents during pairing
+    /// * `pairing` - Whether the server accepts new pairings
+    pub fn announce(
+        &self,
+        name: &str,
+        port: u16,
+        server_public_key: &[u8],
+        pairing: bool,
+    ) -> Result<bool> {
+        if !self.announcements_enabled.load(Ordering::SeqCst) || self.kill_switch.is_engaged() {
+            return Ok(false);
+        }
+
+        let fingerprint = server_fingerprint(server_public_key);
+        let record = ServiceRecord {
+            instance: truncate_label(name).to_string(),
+            host: format!("osnova-{}.local.", &fingerprint[..8]),
+            addresses: Vec::new(),
+            port,
+            fingerprint,
+            pairing,
+        };
+
+        let mut announced = self.announced.lock().unwrap();
+        if let Some(previous) = announced.as_ref() {
+            if previous.instance != record.instance {
+                self.transport.withdraw(&previous.instance)?;
+            }
+        }
+        self.transport.announce(&record)?;
+        *announced = Some(record);
+
+        Ok(true)
+    }
+
+    /// Stop announcing this server
+    pub fn withdraw(&self) -> Result<()> {
+        if let Some(record) = self.announced.lock().unwrap().take() {
+            self.transport.withdraw(&record.instance)?;
+        }
+        Ok(())
+    }
+
+    /// Find servers on the local network
+    ///
+    /// Waits the full `timeout` for answers and returns the servers that
+    /// answered in time, sorted by name. A server announcing twice is
+    /// listed once, with its latest answer. Fails with
+    /// [`OsnovaError::OfflineMode`] while the kill switch is engaged.
+    pub fn discover(&self, timeout: Duration) -> Result<Vec<DiscoveredServer>> {
+        self.kill_switch.ensure_online("LAN discovery")?;
+        let deadline = Instant::now() + timeout;
+        // Multicast is lossy; ask once more halfway through
+        let mut requery_at = Some(Instant::now() + timeout / 2);
+        let mut found = HashMap::new();
+
+        self.transport.query()?;
+        loop {
+            let wait_until = requery_at.map_or(deadline, |at| at.min(deadline));
+            match self.transport.recv(wait_until)? {
+                Some(record) => {
+                    if let Some(server) = DiscoveredServer::from_record(record) {
+                        found.insert(server.name.clone(), server);
+                    }
+                }
+                None if wait_until < deadline => {
+                    requery_at = None;
+                    self.transport.query()?;
+                }
+                None => break,
+            }
+        }
+
+        let mut servers: Vec<_> = found.into_values().collect();
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(servers)
+    }
+}
+
+/// Truncate a name to one DNS label, on a character boundary
+fn truncate_label(name: &str) -> &str {
+    let mut end = name.len().min(MAX_INSTANCE_LEN);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+/// [`MdnsTransport`] over UDP multicast on 224.0.0.251:5353
+///
+/// Queries go out on the caller's thread. Announced records are served by
+/// a responder thread, started on the first announcement and stopped (with
+/// goodbye packets) when the socket is dropped.
+pub struct MdnsSocket {
+    socket: UdpSocket,
+    inbox: Mutex<VecDeque<ServiceRecord>>,
+    announced: Arc<Mutex<HashMap<String, ServiceRecord>>>,
+    stop: Arc<AtomicBool>,
+    responder: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl MdnsSocket {
+    /// Join the mDNS multicast group
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            socket: open_multicast_socket()?,
+            inbox: Mutex::new(VecDeque::new()),
+            announced: Arc::new(Mutex::new(HashMap::new())),
+            stop: Arc::new(AtomicBool::new(false)),
+            responder: Mutex::new(None),
+        })
+    }
+
+    fn send(&self, records: &[ServiceRecord], ttl: u32) -> Result<()> {
+        let packet = encode_response(records, ttl)?;
+        self.socket
+            .send_to(&packet, (MDNS_ADDR, MDNS_PORT))
+            .map_err(|e| OsnovaError::Network(format!("Failed to send mDNS packet: {}", e)))?;
+        Ok(())
+    }
+
+    /// Start the responder thread if it is not running
+    fn ensure_responder(&self) -> Result<()> {
+        let mut responder = self.responder.lock().unwrap();
+        if responder.is_some() {
+            return Ok(());
+        }
+
+        let socket = open_multicast_socket()?;
+        socket
+            .set_read_timeout(Some(RESPONDER_POLL_INTERVAL))
+            .map_err(|e| OsnovaError::Network(format!("Failed to configure socket: {}", e)))?;
+        let announced = self.announced.clone();
+        let stop = self.stop.clone();
+
+        *responder = Some(std::thread::spawn(move || {
+            let mut buf = [0u8; 9000];
+            while !stop.load(Ordering::SeqCst) {
+                let Ok((len, _)) = socket.recv_from(&mut buf) else {
+                    continue;
+                };
+                if !is_service_query(&buf[..len]) {
+                    continue;
+                }
+
+                let records: Vec<_> = announced.lock().unwrap().values().cloned().collect();
+                if records.is_empty() {
+                    continue;
+                }
+                if let Ok(packet) = encode_response(&records, RECORD_TTL_SECS) {
+                    let _ = socket.send_to(&packet, (MDNS_ADDR, MDNS_PORT));
+                }
+            }
+        }));
+
+        Ok(())
+    }
+}
+
+impl MdnsTransport for MdnsSocket {
+    fn announce(&self, record: &ServiceRecord) -> Result<()> {
+        let mut record = record.clone();
+        if record.addresses.is_empty() {
+            record.addresses.extend(local_ipv4().map(IpAddr::V4));
+        }
+
+        self.announced
+            .lock()
+            .unwrap()
+            .insert(record.instance.clone(), record.clone());
+        self.ensure_responder()?;
+
+        // Unsolicited announcement, so browsers already listening see it
+        self.send(&[record], RECORD_TTL_SECS)
+    }
+
+    fn withdraw(&self, instance: &str) -> Result<()> {
+        let record = self.announced.lock().unwrap().remove(instance);
+        match record {
+            // A zero TTL tells caches the record is gone
+            Some(record) => self.send(&[record], 0),
+            None => Ok(()),
+        }
+    }
+
+    fn query(&self) -> Result<()> {
+        let mut message = Message::new();
+        message.set_message_type(MessageType::Query);
+        message.add_query(Query::query(service_name()?, RecordType::PTR));
+
+        let packet = message.to_vec().map_err(network_error)?;
+        self.socket
+            .send_to(&packet, (MDNS_ADDR, MDNS_PORT))
+            .map_err(|e| OsnovaError::Network(format!("Failed to send mDNS query: {}", e)))?;
+        Ok(())
+    }
+
+    fn recv(&self, deadline: Instant) -> Result<Option<ServiceRecord>> {
+        let mut buf = [0u8; 9000];
+        loop {
+            if let Some(record) = self.inbox.lock().unwrap().pop_front() {
+                return Ok(Some(record));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .map_err(|e| OsnovaError::Network(format!("Failed to configure socket: {}", e)))?;
+
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, _)) => self
+                    .inbox
+                    .lock()
+                    .unwrap()
+                    .extend(decode_response(&buf[..len])),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => {
+                    return Err(OsnovaError::Network(format!(
+                        "Failed to receive mDNS packet: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for MdnsSocket {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let records: Vec<_> = self
+            .announced
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, r)| r)
+            .collect();
+        if !records.is_empty() {
+            let _ = self.send(&records, 0);
+        }
+        if let Some(responder) = self.responder.lock().unwrap().take() {
+            let _ = responder.join();
+        }
+    }
+}
+
+/// Bind a UDP socket to the mDNS port, shared with other responders
+fn open_multicast_socket() -> Result<UdpSocket> {
+    let configure = || -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+        socket.join_multicast_v4(&MDNS_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+        Ok(socket.into())
+    };
+
+    configure().map_err(|e| OsnovaError::Network(format!("Failed to open mDNS socket: {}", e)))
+}
+
+/// Address of the interface multicast traffic leaves from
+fn local_ipv4() -> Option<Ipv4Addr> {
+    // Connecting a UDP socket sends nothing; it only selects a route
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((MDNS_ADDR, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+fn service_name() -> Result<Name> {
+    Name::from_ascii(SERVICE_TYPE).map_err(network_error)
+}
+
+fn network_error(e: hickory_proto::ProtoError) -> OsnovaError {
+    OsnovaError::Network(format!("Invalid mDNS packet: {}", e))
+}
+
+/// Encode an mDNS response announcing `records`
+fn encode_response(records: &[ServiceRecord], ttl: u32) -> Result<Vec<u8>> {
+    let service = service_name()?;
+    let mut message = Message::new();
+    message
+        .set_message_type(MessageType::Response)
+        .set_authoritative(true);
+
+    for record in records {
+        let instance = service
+            .prepend_label(record.instance.as_bytes())
+            .map_err(network_error)?;
+        let host = Name::from_ascii(&record.host).map_err(network_error)?;
+
+        let mut txt = vec![
+            TXT_VERSION.to_string(),
+            format!("{}{}", TXT_FINGERPRINT, record.fingerprint),
+        ];
+        txt.push(format!(
+            "{}{}",
+            TXT_PAIRING,
+            if record.pairing { 1 } else { 0 }
+        ));
+
+        message.add_answer(Record::from_rdata(
+            service.clone(),
+            ttl,
+            RData::PTR(PTR(instance.clone())),
+        ));
+        message.add_additional(Record::from_rdata(
+            instance.clone(),
+            ttl,
+            RData::SRV(SRV::new(0, 0, record.port, host.clone())),
+        ));
+        message.add_additional(Record::from_rdata(instance, ttl, RData::TXT(TXT::new(txt))));
+        for ip in &record.addresses {
+            let rdata = match ip {
+                IpAddr::V4(ip) => RData::A(A(*ip)),
+                IpAddr::V6(ip) => RData::AAAA(AAAA(*ip)),
+            };
+            message.add_additional(Record::from_rdata(host.clone(), ttl, rdata));
+        }
+    }
+
+    message.to_vec().map_err(network_error)
+}
+
+/// Decode the Osnova servers announced in an mDNS response
+///
+/// Anything else, including goodbye (zero TTL) records and malformed
+/// packets, yields nothing.
+fn decode_response(packet: &[u8]) -> Vec<ServiceRecord> {
+    let (Ok(message), Ok(service)) = (Message::from_vec(packet), service_name()) else {
+        return Vec::new();
+    };
+    if message.message_type() != MessageType::Response {
+        return Vec::new();
+    }
+
+    let records: Vec<&Record> = message
+        .answers()
+        .iter()
+        .chain(message.additionals())
+        .filter(|record| record.ttl() > 0)
+        .collect();
+
+    let mut instances = Vec::new();
+    let mut srv = HashMap::new();
+    let mut txt = HashMap::new();
+    let mut addresses: HashMap<Name, Vec<IpAddr>> = HashMap::new();
+    for record in &records {
+        let name = record.name().to_lowercase();
+        match record.data() {
+            RData::PTR(PTR(instance)) if name == service.to_lowercase() => {
+                instances.push(instance.clone());
+            }
+            RData::SRV(data) => {
+                srv.insert(name, (data.port(), data.target().clone()));
+            }
+            RData::TXT(data) => {
+                txt.insert(name, data.clone());
+            }
+            RData::A(A(ip)) => addresses.entry(name).or_default().push(IpAddr::V4(*ip)),
+            RData::AAAA(AAAA(ip)) => addresses.entry(name).or_default().push(IpAddr::V6(*ip)),
+            _ => {}
+        }
+    }
+
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let key = instance.to_lowercase();
+            let (port, host) = srv.get(&key)?;
+            let entries: Vec<String> = txt
+                .get(&key)?
+                .iter()
+                .map(|entry| String::from_utf8_lossy(entry).into_owned())
+                .collect();
+            let fingerprint = entries
+                .iter()
+                .find_map(|entry| entry.strip_prefix(TXT_FINGERPRINT))?
+                .to_string();
+            let pairing = entries.iter().any(|entry| entry == "pair=1");
+            let label = instance.iter().next()?;
+
+            Some(ServiceRecord {
+                instance: String::from_utf8_lossy(label).into_owned(),
+                host: host.to_ascii(),
+                addresses: addresses
+                    .get(&host.to_lowercase())
+                    .cloned()
+                    .unwrap_or_default(),
+                port: *port,
+                fingerprint,
+                pairing,
+            })
+        })
+        .collect()
+}
+
+/// Whether a packet is a query for Osnova servers
+fn is_service_query(packet: &[u8]) -> bool {
+    let (Ok(message), Ok(service)) = (Message::from_vec(packet), service_name()) else {
+        return false;
+    };
+
+    message.message_type() == MessageType::Query
+        && message.queries().iter().any(|query| {
+            query.name().to_lowercase() == service.to_lowercase()
+                && matches!(query.query_type(), RecordType::PTR | RecordType::ANY)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In-process stand-in for the LAN: every handle sees every
+    /// announcement, answering after its configured latency
+    #[derive(Default)]
+    struct FakeNetwork {
+        announced: Mutex<HashMap<String, ServiceRecord>>,
+        latency: Mutex<HashMap<String, Duration>>,
+    }
+
+    struct FakeResponder {
+        network: Arc<FakeNetwork>,
+        inbox: Mutex<Vec<(Instant, ServiceRecord)>>,
+    }
+
+    impl FakeResponder {
+        fn new(network: &Arc<FakeNetwork>) -> Arc<Self> {
+            Arc::new(Self {
+                network: network.clone(),
+                inbox: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    impl MdnsTransport for FakeResponder {
+        fn announce(&self, record: &ServiceRecord) -> Result<()> {
+            let mut record = record.clone();
+            record.addresses = vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20))];
+            self.network
+                .announced
+                .lock()
+                .unwrap()
+                .insert(record.instance.clone(), record);
+            Ok(())
+        }
+
+        fn withdraw(&self, instance: &str) -> Result<()> {
+            self.network.announced.lock().unwrap().remove(instance);
+            Ok(())
+        }
+
+        fn query(&self) -> Result<()> {
+            let latency = self.network.latency.lock().unwrap();
+            let now = Instant::now();
+            let announced = self.network.announced.lock().unwrap();
+            let answers = announced.values().map(|record| {
+                let delay = latency.get(&record.instance).copied().unwrap_or_default();
+                (now + delay, record.clone())
+            });
+            self.inbox.lock().unwrap().extend(answers);
+            Ok(())
+        }
+
+        fn recv(&self, deadline: Instant) -> Result<Option<ServiceRecord>> {
+            let next = {
+                let mut inbox = self.inbox.lock().unwrap();
+                inbox.sort_by_key(|(at, _)| *at);
+                match inbox.first() {
+                    Some((at, _)) if *at <= deadline => Some(inbox.remove(0)),
+                    _ => None,
+                }
+            };
+
+            match next {
+                Some((at, record)) => {
+                    std::thread::sleep(at.saturating_duration_since(Instant::now()));
+                    Ok(Some(record))
+                }
+                None => {
+                    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    const SERVER_KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_announce_discover_round_trip() -> Result<()> {
+        let network = Arc::new(FakeNetwork::default());
+        let server = LanDiscovery::new(FakeResponder::new(&network));
+        let client = LanDiscovery::new(FakeResponder::new(&network));
+
+        assert!(server.announce("Living room", 8443, &SERVER_KEY, true)?);
+
+        let servers = client.discover(Duration::from_millis(50))?;
+        assert_eq!(
+            servers,
+            [DiscoveredServer {
+                name: "Living room".to_string(),
+                address: "192.168.1.20:8443".parse().unwrap(),
+                fingerprint: server_fingerprint(&SERVER_KEY),
+                accepts_pairing: true,
+            }]
+        );
+
+        server.withdraw()?;
+        assert!(client.discover(Duration::from_millis(50))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_mismatch_is_detected_at_pairing() -> Result<()> {
+        let network = Arc::new(FakeNetwork::default());
+        let rogue = LanDiscovery::new(FakeResponder::new(&network));
+        let client = LanDiscovery::new(FakeResponder::new(&network));
+
+        // A rogue announcer copies the real server's name and fingerprint
+        rogue.announce("Living room", 8443, &SERVER_KEY, true)?;
+        let server = client.discover(Duration::from_millis(50))?.remove(0);
+
+        let genuine = PairingSession::new("pairing-1", &SERVER_KEY, &[2u8; 32])?;
+        server.verify_pairing(&genuine)?;
+
+        let impostor = PairingSession::new("pairing-2", &[9u8; 32], &[2u8; 32])?;
+        let err = server.verify_pairing(&impostor).unwrap_err();
+        assert!(matches!(err, OsnovaError::Identity(_)));
+        assert!(err.to_string().contains("mismatch"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disabled_announcements_are_suppressed() -> Result<()> {
+        let network = Arc::new(FakeNetwork::default());
+        let server = LanDiscovery::new(FakeResponder::new(&network)).with_announcements(false);
+        let client = LanDiscovery::new(FakeResponder::new(&network));
+
+        assert!(!server.announce("Living room", 8443, &SERVER_KEY, true)?);
+        assert!(client.discover(Duration::from_millis(50))?.is_empty());
+
+        // Turning announcements off withdraws an active announcement
+        server.set_announcements_enabled(true)?;
+        server.announce("Living room", 8443, &SERVER_KEY, true)?;
+        assert_eq!(client.discover(Duration::from_millis(50))?.len(), 1);
+        server.set_announcements_enabled(false)?;
+        assert!(client.discover(Duration::from_millis(50))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_offline_mode_blocks_announcing_and_browsing() -> Result<()> {
+        let network = Arc::new(FakeNetwork::default());
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let server =
+            LanDiscovery::new(FakeResponder::new(&network)).with_kill_switch(switch.clone());
+        let client =
+            LanDiscovery::new(FakeResponder::new(&network)).with_kill_switch(switch.clone());
+
+        switch.engage();
+        assert!(!server.announce("Living room", 8443, &SERVER_KEY, true)?);
+        assert!(network.announced.lock().unwrap().is_empty());
+        let err = client.discover(Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(err, OsnovaError::OfflineMode { .. }));
+
+        switch.release();
+        assert!(server.announce("Living room", 8443, &SERVER_KEY, true)?);
+        assert_eq!(client.discover(Duration::from_millis(50))?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout_returns_partial_results() -> Result<()> {
+        let network = Arc::new(FakeNetwork::default());
+        let fast = LanDiscovery::new(FakeResponder::new(&network));
+        let slow = LanDiscovery::new(FakeResponder::new(&network));
+        let client = LanDiscovery::new(FakeResponder::new(&network));
+
+        fast.announce("Desk", 8443, &SERVER_KEY, true)?;
+        slow.announce("Attic", 8443, &[8u8; 32], false)?;
+        network
+            .latency
+            .lock()
+            .unwrap()
+            .insert("Attic".to_string(), Duration::from_secs(5));
+
+        let started = Instant::now();
+        let servers = client.discover(Duration::from_millis(100))?;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, "Desk");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packet_round_trip() -> Result<()> {
+        let record = ServiceRecord {
+            instance: "Alice's desktop".to_string(),
+            host: "osnova-1a2b3c4d.local.".to_string(),
+            addresses: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))],
+            port: 8443,
+            fingerprint: server_fingerprint(&SERVER_KEY),
+            pairing: false,
+        };
+
+        let packet = encode_response(std::slice::from_ref(&record), RECORD_TTL_SECS)?;
+        assert_eq!(decode_response(&packet), std::slice::from_ref(&record));
+
+        // Goodbye packets and garbage carry no servers
+        assert!(decode_response(&encode_response(&[record], 0)?).is_empty());
+        assert!(decode_response(b"not dns").is_empty());
+
+        let mut query = Message::new();
+        query.add_query(Query::query(service_name()?, RecordType::PTR));
+        assert!(is_service_query(&query.to_vec().unwrap()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_names_are_truncated_on_a_char_boundary() {
+        let name = "é".repeat(40);
+        let truncated = truncate_label(&name);
+        assert!(truncated.len() <= MAX_INSTANCE_LEN);
+        assert_eq!(truncated.chars().count(), 31);
+    }
+}
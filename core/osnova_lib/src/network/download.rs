@@ -24,7 +24,7 @@
 //! }
 //! ```
 
-use super::AutonomiClient;
+use super::{AutonomiClient, NetworkOptions};
 use crate::error::{OsnovaError, Result};
 use bytes::Bytes;
 
@@ -54,6 +54,23 @@ use bytes::Bytes;
 /// println!("Downloaded {} bytes", data.len());
 /// ```
 pub async fn download_data(client: &AutonomiClient, uri: &str) -> Result<Vec<u8>> {
+    download_data_with(client, uri, &NetworkOptions::default()).await
+}
+
+/// Download data from the Autonomi Network with a timeout and cancellation
+///
+/// Like [`download_data`], but fails with `OsnovaError::Timeout` or
+/// `OsnovaError::Cancelled` according to `options`.
+pub async fn download_data_with(
+    client: &AutonomiClient,
+    uri: &str,
+    options: &NetworkOptions,
+) -> Result<Vec<u8>> {
+    options.run("download", fetch_data(client, uri)).await
+}
+
+/// Fetch and reassemble data without a timeout
+async fn fetch_data(client: &AutonomiClient, uri: &str) -> Result<Vec<u8>> {
     // Parse ant:// URI
     let xorname = parse_ant_uri(uri)?;
 
@@ -139,6 +156,7 @@ mod tests {
         // Test that download fails when client is not connected
         let client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
 
         let uri = "ant://0000000000000000000000000000000000000000000000000000000000000000";
@@ -0,0 +1,217 @@
+//! # Network Kill Switch
+//!
+//! Soft-offline mode: a switch the user sets to guarantee Osnova makes no
+//! network requests at all, stronger than any bandwidth policy.
+//!
+//! Every outbound path consults a [`NetworkKillSwitch`]:
+//! - Autonomi operations (through [`NetworkOptions`](super::NetworkOptions))
+//!   and HTTP(S) fetches fail fast with [`OsnovaError::OfflineMode`]; those
+//!   in flight are aborted the moment the switch engages
+//! - Queue-based work checked against a
+//!   [`BandwidthMeter`](super::BandwidthMeter) (prefetching, metered
+//!   downloads and uploads, backend traffic through the network proxy) is
+//!   deferred with
+//!   [`DeferReason::OfflineByUser`](super::bandwidth::DeferReason::OfflineByUser)
+//!   and picked up again once the switch is released
+//! - LAN discovery neither announces this server nor browses for others
+//!
+//! Local work is unaffected: cached components still install and launch.
+//!
+//! Components use the process-wide switch from [`shared`] unless given
+//! their own. The shell sets it from the stored setting at startup and
+//! whenever the user toggles it, cancelling the tokens of the requests it
+//! has in flight; the state is a watch channel, so the change is seen
+//! immediately, without a restart.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::network::kill_switch;
+//!
+//! let switch = kill_switch::shared();
+//! switch.engage();
+//!
+//! // Fails with OsnovaError::OfflineMode, without touching the network
+//! let result = download_data(&client, "ant://...").await;
+//! ```
+
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::watch;
+
+use crate::error::{OsnovaError, Result};
+
+/// User-set switch blocking all network activity
+#[derive(Debug)]
+pub struct NetworkKillSwitch {
+    engaged: watch::Sender<bool>,
+}
+
+impl NetworkKillSwitch {
+    /// Create a released switch
+    pub fn new() -> Self {
+        let (engaged, _) = watch::channel(false);
+        Self { engaged }
+    }
+
+    /// Engage or release the switch
+    pub fn set(&self, engaged: bool) {
+        self.engaged.send_if_modified(|current| {
+            let changed = *current != engaged;
+            *current = engaged;
+            changed
+        });
+    }
+
+    /// Block all network activity, aborting operations in flight
+    pub fn engage(&self) {
+        self.set(true);
+    }
+
+    /// Allow network activity again
+    pub fn release(&self) {
+        self.set(false);
+    }
+
+    /// Whether network activity is blocked
+    pub fn is_engaged(&self) -> bool {
+        *self.engaged.borrow()
+    }
+
+    /// Watch the switch, e.g. to resume deferred work once it is released
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.engaged.subscribe()
+    }
+
+    /// Wait until the switch is engaged; immediately if it already is
+    pub async fn engaged(&self) {
+        // The sender lives as long as `self`, so waiting cannot fail
+        let _ = self.subscribe().wait_for(|engaged| *engaged).await;
+    }
+
+    /// Fail if the switch is engaged
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::OfflineMode`] naming `operation`.
+    pub fn ensure_online(&self, operation: &str) -> Result<()> {
+        if self.is_engaged() {
+            return Err(OsnovaError::OfflineMode {
+                operation: operation.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run a network operation unless the switch is engaged
+    ///
+    /// The future is not polled while the switch is engaged, and is dropped
+    /// as soon as it engages.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::OfflineMode`] naming `operation`, or the
+    /// future's own error.
+    pub async fn guard<T, F>(&self, operation: &str, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.ensure_online(operation)?;
+        tokio::select! {
+            biased;
+            _ = self.engaged() => Err(OsnovaError::OfflineMode {
+                operation: operation.to_string(),
+            }),
+            result = future => result,
+        }
+    }
+}
+
+impl Default for NetworkKillSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static SHARED: OnceLock<Arc<NetworkKillSwitch>> = OnceLock::new();
+
+/// The process-wide switch
+///
+/// Released until the shell applies the user's setting.
+pub fn shared() -> Arc<NetworkKillSwitch> {
+    SHARED
+        .get_or_init(|| Arc::new(NetworkKillSwitch::new()))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_engaged_switch_refuses_without_polling() {
+        let switch = NetworkKillSwitch::new();
+        let calls = AtomicUsize::new(0);
+        switch.engage();
+
+        let result: Result<()> = switch
+            .guard("fetch", async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+
+        match result {
+            Err(OsnovaError::OfflineMode { operation }) => assert_eq!(operation, "fetch"),
+            other => panic!("Expected offline mode, got {:?}", other),
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(switch.ensure_online("fetch").is_err());
+
+        switch.release();
+        assert_eq!(switch.guard("fetch", async { Ok(7) }).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_engaging_aborts_operation_in_flight() {
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let engaging = switch.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            engaging.engage();
+        });
+
+        let result: Result<()> = tokio::time::timeout(
+            Duration::from_secs(5),
+            switch.guard("upload", std::future::pending()),
+        )
+        .await
+        .expect("engaging must abort the operation");
+
+        assert!(matches!(result, Err(OsnovaError::OfflineMode { .. })));
+        assert!(result.unwrap_err().is_interrupted());
+    }
+
+    #[tokio::test]
+    async fn test_watchers_see_changes_immediately() {
+        let switch = NetworkKillSwitch::new();
+        let mut watcher = switch.subscribe();
+        assert!(!*watcher.borrow());
+
+        switch.engage();
+        watcher.changed().await.unwrap();
+        assert!(*watcher.borrow_and_update());
+
+        // Setting the same state again is not a change
+        switch.engage();
+        assert!(!watcher.has_changed().unwrap());
+
+        switch.release();
+        watcher.changed().await.unwrap();
+        assert!(!*watcher.borrow());
+        assert!(!switch.is_engaged());
+    }
+}
@@ -6,6 +6,14 @@
 //! - Autonomi client connection management
 //! - Data upload and download operations
 //! - Component caching and retrieval
+//! - Bandwidth accounting and metered-connection policies
+//! - Payment history of uploads
+//! - Archive uploads paid for batch by batch, resumable
+//! - Per-request timeouts and cancellation
+//! - A user-set kill switch blocking all network activity (soft-offline mode)
+//! - Retry budgets shared per destination, with circuit breaking
+//! - Local network discovery of Osnova servers (mDNS)
+//! - Diagnosis of the network environment for users who cannot connect
 //!
 //! ## Example
 //!
@@ -22,10 +30,29 @@
 //! }
 //! ```
 
+pub mod archive;
 pub mod autonomi_client;
+pub mod bandwidth;
+pub mod discovery;
+pub mod doctor;
 pub mod download;
+pub mod kill_switch;
+pub mod options;
+pub mod payments;
+pub mod retry_budget;
 pub mod upload;
 
+pub use archive::{ArchiveFile, ArchivePolicy, ArchiveUploadStatus, ArchiveUploader};
 pub use autonomi_client::AutonomiClient;
-pub use download::download_data;
-pub use upload::{estimate_upload_cost, upload_data};
+pub use bandwidth::{BandwidthMeter, BandwidthPolicy, TransferCategory, TransferStatus};
+pub use discovery::{DiscoveredServer, LanDiscovery, MdnsSocket, MdnsTransport};
+pub use doctor::{NetworkDiagnosis, NetworkDoctor};
+pub use download::{download_data, download_data_with};
+pub use kill_switch::NetworkKillSwitch;
+pub use options::{CancellationToken, NetworkOptions, DEFAULT_NETWORK_TIMEOUT};
+pub use payments::{PaymentLedger, UploadContext};
+pub use retry_budget::{Backoff, Destination, RetryBudgetMetrics, RetryBudgetPolicy, RetryBudgets};
+pub use upload::{
+    estimate_upload_cost, estimate_upload_cost_with, upload_data, upload_data_for,
+    upload_data_metered, upload_data_metered_with, upload_data_with, UploadReceipt,
+};
@@ -0,0 +1,246 @@
+//! # Network Request Options
+//!
+//! Per-request timeout and cancellation for network operations.
+//!
+//! Every async network entry point has a `_with` variant taking
+//! [`NetworkOptions`]; the plain variant applies [`NetworkOptions::default`].
+//! A timed-out operation fails with [`OsnovaError::Timeout`] and a cancelled
+//! one with [`OsnovaError::Cancelled`], so callers can tell both apart from
+//! other network failures. While the options' [`NetworkKillSwitch`] is
+//! engaged, operations fail with [`OsnovaError::OfflineMode`] instead.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use osnova_lib::network::{download_data_with, CancellationToken, NetworkOptions};
+//!
+//! let token = CancellationToken::new();
+//! let options = NetworkOptions::default()
+//!     .with_timeout(Duration::from_secs(10))
+//!     .with_cancellation(token.clone());
+//!
+//! // Elsewhere: token.cancel() aborts the download promptly
+//! let data = download_data_with(&client, "ant://...", &options).await?;
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{OsnovaError, Result};
+use crate::network::kill_switch::{self, NetworkKillSwitch};
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Timeout applied when a caller does not specify one
+pub const DEFAULT_NETWORK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeout and cancellation for a single network request
+#[derive(Debug, Clone)]
+pub struct NetworkOptions {
+    /// Maximum time the whole operation may take
+    pub timeout: Duration,
+    /// Token that aborts the operation when cancelled
+    pub cancellation: CancellationToken,
+    /// Switch refusing the operation while the user has Osnova offline
+    pub kill_switch: Arc<NetworkKillSwitch>,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_NETWORK_TIMEOUT,
+            cancellation: CancellationToken::new(),
+            kill_switch: kill_switch::shared(),
+        }
+    }
+}
+
+impl NetworkOptions {
+    /// Set the timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the cancellation token
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Use `kill_switch` instead of the process-wide one
+    pub fn with_kill_switch(mut self, kill_switch: Arc<NetworkKillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Run `future` under these options
+    ///
+    /// The future is dropped as soon as the timeout elapses, the token is
+    /// cancelled or the kill switch engages. A cancelled token or an engaged
+    /// switch fails without polling the future.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::OfflineMode`], [`OsnovaError::Cancelled`] or
+    /// [`OsnovaError::Timeout`] naming `operation`, or the future's own
+    /// error.
+    pub async fn run<T, F>(&self, operation: &str, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.run_watching(operation, future, true).await
+    }
+
+    /// Run `future`, which may be answered from local data, under these
+    /// options
+    ///
+    /// Like [`run`](Self::run), but not refused while the kill switch is
+    /// engaged: the network requests inside `future` are refused instead,
+    /// so cached and local data stay available offline. An operation
+    /// started online is still aborted when the switch engages.
+    pub async fn run_local_first<T, F>(&self, operation: &str, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let watch_switch = !self.kill_switch.is_engaged();
+        self.run_watching(operation, future, watch_switch).await
+    }
+
+    async fn run_watching<T, F>(&self, operation: &str, future: F, watch_switch: bool) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let engaged = async {
+            if watch_switch {
+                self.kill_switch.engaged().await
+            } else {
+                std::future::pending::<()>().await
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = engaged => Err(OsnovaError::OfflineMode {
+                operation: operation.to_string(),
+            }),
+            _ = self.cancellation.cancelled() => Err(OsnovaError::Cancelled {
+                operation: operation.to_string(),
+            }),
+            result = tokio::time::timeout(self.timeout, future) => {
+                result.unwrap_or_else(|_| {
+                    Err(OsnovaError::Timeout {
+                        operation: operation.to_string(),
+                        after: self.timeout,
+                    })
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_timeout_fires_on_stalled_operation() {
+        let options = NetworkOptions::default().with_timeout(Duration::from_millis(50));
+
+        let result: Result<()> = options.run("stalled fetch", std::future::pending()).await;
+
+        match result {
+            Err(OsnovaError::Timeout { operation, after }) => {
+                assert_eq!(operation, "stalled fetch");
+                assert_eq!(after, Duration::from_millis(50));
+            }
+            other => panic!("Expected timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_aborts_promptly() {
+        let token = CancellationToken::new();
+        let options = NetworkOptions::default().with_cancellation(token.clone());
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            token.cancel();
+        });
+
+        let started = Instant::now();
+        let result: Result<()> = options.run("stalled fetch", std::future::pending()).await;
+
+        assert!(matches!(result, Err(OsnovaError::Cancelled { .. })));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_token_skips_operation() {
+        let options = NetworkOptions::default();
+        options.cancellation.cancel();
+
+        let result: Result<()> = options
+            .run("fetch", async { panic!("must not be polled") })
+            .await;
+
+        assert!(matches!(result, Err(OsnovaError::Cancelled { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_engaged_kill_switch_refuses_or_runs_locally() {
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let options = NetworkOptions::default().with_kill_switch(switch.clone());
+        switch.engage();
+
+        let result: Result<()> = options
+            .run("download", async { panic!("must not be polled") })
+            .await;
+        assert!(matches!(result, Err(OsnovaError::OfflineMode { .. })));
+
+        // Local-first work runs; only its network requests are refused
+        let result = options.run_local_first("component download", async { Ok(1) });
+        assert_eq!(result.await.unwrap(), 1);
+        assert!(!options.cancellation.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_engaging_aborts_operation_in_flight() {
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let options = NetworkOptions::default().with_kill_switch(switch.clone());
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            switch.engage();
+        });
+
+        let started = Instant::now();
+        let result: Result<()> = options
+            .run_local_first("component download", std::future::pending())
+            .await;
+
+        assert!(matches!(result, Err(OsnovaError::OfflineMode { .. })));
+        // The token may be long-lived, e.g. a scheduler's shutdown token
+        assert!(!options.cancellation.is_cancelled());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_completed_operation_passes_through() {
+        let options = NetworkOptions::default();
+        assert_eq!(options.timeout, DEFAULT_NETWORK_TIMEOUT);
+
+        let result = options.run("fetch", async { Ok(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+
+        let result: Result<()> = options
+            .run("fetch", async {
+                Err(OsnovaError::Network("refused".into()))
+            })
+            .await;
+        assert!(!result.unwrap_err().is_interrupted());
+    }
+}
@@ -0,0 +1,175 @@
+//! # Payment Ledger
+//!
+//! Records every upload to the Autonomi Network in the `payments_history`
+//! table, so the wallet can show and export what storage cost.
+//!
+//! Attach a [`PaymentLedger`] to an [`AutonomiClient`](super::AutonomiClient)
+//! with `with_payment_ledger`; [`upload_data_for`](super::upload_data_for)
+//! then records each upload, successful or not, together with the
+//! [`UploadContext`] the caller supplied.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use osnova_lib::network::{AutonomiClient, PaymentLedger, UploadContext, upload_data_for};
+//! use osnova_lib::network::NetworkOptions;
+//! use osnova_lib::storage::SqlStorage;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let ledger = Arc::new(PaymentLedger::new(SqlStorage::new("/tmp/osnova.db")?));
+//! let client = AutonomiClient::connect().await?.with_payment_ledger(ledger.clone());
+//!
+//! let context = UploadContext::for_component("com.example.app").with_quote(1_000);
+//! let receipt = upload_data_for(&client, b"data", &context, &NetworkOptions::default()).await?;
+//! println!("{} cost {} AttoTokens", receipt.address, receipt.cost);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Mutex;
+
+use super::upload::UploadReceipt;
+use crate::error::{OsnovaError, Result};
+use crate::models::payment_record::{PaymentRecord, PaymentStatus};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// What an upload is for, as recorded in the payment history
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadContext {
+    /// Component or manifest the data belongs to
+    pub component_id: Option<String>,
+    /// Cost quoted before the upload, in AttoTokens
+    pub quoted_cost: Option<u64>,
+    /// Wallet payment request that approved the upload
+    pub payment_request_id: Option<String>,
+}
+
+impl UploadContext {
+    /// Context for data belonging to `component_id`
+    pub fn for_component(component_id: impl Into<String>) -> Self {
+        Self {
+            component_id: Some(component_id.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the quoted cost, in AttoTokens
+    pub fn with_quote(mut self, attos: u64) -> Self {
+        self.quoted_cost = Some(attos);
+        self
+    }
+
+    /// Set the wallet payment request that approved the upload
+    pub fn with_payment_request(mut self, request_id: impl Into<String>) -> Self {
+        self.payment_request_id = Some(request_id.into());
+        self
+    }
+}
+
+/// Persistent record of uploads and what they cost
+pub struct PaymentLedger {
+    storage: Mutex<SqlStorage>,
+    clock: SharedClock,
+}
+
+impl PaymentLedger {
+    /// Create a ledger over the given storage
+    pub fn new(storage: SqlStorage) -> Self {
+        Self {
+            storage: Mutex::new(storage),
+            clock: time::default_clock(),
+        }
+    }
+
+    /// Replace the clock (for testing)
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record the outcome of uploading `size` bytes
+    ///
+    /// A failed upload is recorded without an address or actual cost.
+    pub fn record(
+        &self,
+        size: u64,
+        context: &UploadContext,
+        outcome: std::result::Result<&UploadReceipt, &OsnovaError>,
+    ) -> Result<PaymentRecord> {
+        let (address, actual_cost, status) = match outcome {
+            Ok(receipt) => (
+                Some(receipt.address.clone()),
+                Some(receipt.cost),
+                PaymentStatus::Completed,
+            ),
+            Err(_) => (None, None, PaymentStatus::Failed),
+        };
+        let mut record = PaymentRecord {
+            id: 0,
+            recorded_at: self.clock.now_unix(),
+            component_id: context.component_id.clone(),
+            size,
+            address,
+            quoted_cost: context.quoted_cost,
+            actual_cost,
+            payment_request_id: context.payment_request_id.clone(),
+            status,
+        };
+        record.id = self
+            .storage
+            .lock()
+            .unwrap()
+            .insert_payment_record(&record)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to record payment: {}", e)))?;
+        Ok(record)
+    }
+
+    /// List recorded uploads between `since` and `until` (inclusive), newest first
+    pub fn list(&self, since: Option<u64>, until: Option<u64>) -> Result<Vec<PaymentRecord>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_payment_records(since, until)
+            .map_err(|e| OsnovaError::Storage(format!("Failed to read payments: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_record_success_and_failure() {
+        let storage = SqlStorage::new_in_memory().unwrap();
+        let ledger = PaymentLedger::new(storage).with_clock(Arc::new(MockClock::new(1_000)));
+
+        let receipt = UploadReceipt {
+            address: "ant://abc".to_string(),
+            size: 4,
+            cost: 120,
+        };
+        let context = UploadContext::for_component("com.example.app")
+            .with_quote(100)
+            .with_payment_request("req-1");
+        let paid = ledger.record(4, &context, Ok(&receipt)).unwrap();
+        assert_eq!(paid.status, PaymentStatus::Completed);
+        assert_eq!(paid.actual_cost, Some(120));
+
+        let error = OsnovaError::Network("offline".to_string());
+        let failed = ledger
+            .record(8, &UploadContext::default(), Err(&error))
+            .unwrap();
+        assert_eq!(failed.status, PaymentStatus::Failed);
+        assert_eq!(failed.address, None);
+
+        let records = ledger.list(None, None).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.contains(&paid));
+        assert!(records.contains(&failed));
+        assert_eq!(ledger.list(Some(1_001), None).unwrap(), Vec::new());
+    }
+}
@@ -0,0 +1,791 @@
+//! # Retry Budgets
+//!
+//! Retries share one budget per destination across the network stack, so a
+//! network that is down does not set every subsystem backing off on its own
+//! and draining the battery.
+//!
+//! Each [`Destination`] (the Autonomi network, an HTTP origin, the Osnova
+//! server) has a token bucket in [`RetryBudgets`]. First attempts are free;
+//! every retry, whichever subsystem makes it, takes a token, and tokens come
+//! back one per refill interval. A retry that finds the bucket empty opens
+//! the destination's circuit:
+//!
+//! - **Open**: for the cooldown, callers fail fast with
+//!   [`OsnovaError::CircuitOpen`], which says when to try again, so queued
+//!   work can defer rather than fail
+//! - **Half-open**: after the cooldown, one caller is admitted as the probe
+//!   while the others keep failing fast. A successful probe closes the
+//!   circuit with a full bucket; a failed one opens it for another cooldown
+//!
+//! Operations keep their own [`Backoff`] between attempts; they only check
+//! the shared budget before each retry. Components use the process-wide
+//! budgets from [`shared`] unless given their own. Their state is reported
+//! by [`RetryBudgets::metrics`] and through the status service.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use osnova_lib::network::retry_budget::{self, Backoff, Destination};
+//!
+//! let budgets = retry_budget::shared();
+//! let data = budgets
+//!     .run(&Destination::Autonomi, "download", &Backoff::default(), || {
+//!         download_data(&client, "ant://...")
+//!     })
+//!     .await?;
+//! ```
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OsnovaError, Result};
+use crate::time::{self, SharedClock};
+
+/// Retries a destination allows in a burst
+pub const DEFAULT_RETRY_CAPACITY: u32 = 10;
+
+/// Time for one spent retry to come back
+pub const DEFAULT_REFILL_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Time an open circuit fails fast before admitting a probe
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Class of destination sharing a retry budget
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(tag = "kind", content = "origin", rename_all = "camelCase")]
+pub enum Destination {
+    /// The Autonomi network
+    Autonomi,
+    /// An HTTP(S) origin (`scheme://host:port`)
+    Http(String),
+    /// The Osnova server of client-server mode
+    Server,
+}
+
+impl Destination {
+    /// Destination of an HTTP(S) request, by the origin of its URL
+    ///
+    /// URLs that do not parse are their own destination.
+    pub fn http(url: &str) -> Self {
+        let origin = reqwest::Url::parse(url)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_else(|_| url.to_string());
+        Self::Http(origin)
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Autonomi => write!(f, "the Autonomi network"),
+            Self::Http(origin) => write!(f, "{}", origin),
+            Self::Server => write!(f, "the Osnova server"),
+        }
+    }
+}
+
+/// Size and timing of each destination's budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryBudgetPolicy {
+    /// Retries allowed in a burst
+    pub capacity: u32,
+    /// Time for one spent retry to come back
+    pub refill_interval: Duration,
+    /// Time an open circuit fails fast before admitting a probe
+    pub cooldown: Duration,
+}
+
+impl Default for RetryBudgetPolicy {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_RETRY_CAPACITY,
+            refill_interval: DEFAULT_REFILL_INTERVAL,
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+}
+
+/// Local backoff of an operation between its attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the first retry
+    pub initial: Duration,
+    /// Cap on the delay, which doubles after every retry
+    pub max: Duration,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+}
+
+impl Backoff {
+    /// Delay before retry number `retry` (0 for the first)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.initial
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            max_retries: 5,
+        }
+    }
+}
+
+/// State of a destination's circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Requests and retries go through while the budget lasts
+    Closed,
+    /// Requests fail fast until the cooldown ends
+    Open,
+    /// One probe request tests whether the destination recovered
+    HalfOpen,
+}
+
+/// Budget and circuit of a destination, for metrics and the status screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryBudgetMetrics {
+    /// Destination
+    pub destination: Destination,
+    /// State of its circuit
+    pub state: CircuitState,
+    /// Retries left in the budget
+    pub tokens: u32,
+    /// Size of the budget
+    pub capacity: u32,
+    /// Retries made
+    pub retries: u64,
+    /// Requests and retries refused while the circuit was not closed
+    pub fast_failures: u64,
+    /// Times the circuit opened
+    pub opened: u64,
+    /// Probes admitted
+    pub probes: u64,
+    /// Seconds until an open circuit admits a probe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Circuit {
+    Closed,
+    /// Open until the clock's elapsed time reaches `until`
+    Open {
+        until: Duration,
+    },
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Budget {
+    tokens: u32,
+    refilled_at: Duration,
+    circuit: Circuit,
+    retries: u64,
+    fast_failures: u64,
+    opened: u64,
+    probes: u64,
+}
+
+/// Retry budgets and circuits of every destination
+pub struct RetryBudgets {
+    policy: RetryBudgetPolicy,
+    clock: SharedClock,
+    budgets: Mutex<BTreeMap<Destination, Budget>>,
+}
+
+impl fmt::Debug for RetryBudgets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryBudgets")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An operation admitted to a destination
+///
+/// Report success with [`succeeded`](Self::succeeded). An admitted probe
+/// dropped without succeeding counts as failed and opens the circuit again.
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    budgets: &'a RetryBudgets,
+    destination: Destination,
+    operation: String,
+    probe: bool,
+    resolved: Cell<bool>,
+}
+
+impl Attempt<'_> {
+    /// Whether this is the probe of a half-open circuit
+    pub fn is_probe(&self) -> bool {
+        self.probe
+    }
+
+    /// Take a token from the shared budget before retrying
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::CircuitOpen`] if the budget is empty, which
+    /// opens the circuit, if the circuit is not closed, or if this is a
+    /// probe, whose failure opens the circuit again.
+    pub fn retry(&self) -> Result<()> {
+        if self.probe && !self.resolved.replace(true) {
+            return Err(self.budgets.reopen(&self.destination, &self.operation));
+        }
+        self.budgets.spend(&self.destination, &self.operation)
+    }
+
+    /// The operation succeeded; a probe closes the circuit
+    pub fn succeeded(self) {
+        if self.probe && !self.resolved.replace(true) {
+            self.budgets.close(&self.destination);
+        }
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.resolved.get() {
+            self.budgets.reopen(&self.destination, &self.operation);
+        }
+    }
+}
+
+impl RetryBudgets {
+    /// Create budgets under `policy`, every circuit closed
+    pub fn new(policy: RetryBudgetPolicy) -> Self {
+        Self {
+            policy,
+            clock: time::default_clock(),
+            budgets: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Use a specific clock for refills and cooldowns
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The policy of every destination's budget
+    pub fn policy(&self) -> RetryBudgetPolicy {
+        self.policy
+    }
+
+    /// Admit an operation's first attempt at a destination
+    ///
+    /// Once an open circuit's cooldown is over, the first caller is
+    /// admitted as its probe.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::CircuitOpen`] while the circuit is open or
+    /// its probe is in flight
+    pub fn admit(&self, destination: &Destination, operation: &str) -> Result<Attempt<'_>> {
+        let now = self.clock.elapsed();
+        let mut budgets = self.budgets.lock().unwrap();
+        let budget = self.budget(&mut budgets, destination, now);
+
+        let probe = match budget.circuit {
+            Circuit::Closed => false,
+            Circuit::Open { until } if now >= until => {
+                budget.circuit = Circuit::HalfOpen;
+                budget.probes += 1;
+                true
+            }
+            Circuit::Open { until } => {
+                budget.fast_failures += 1;
+                return Err(circuit_open(destination, operation, until - now));
+            }
+            Circuit::HalfOpen => {
+                budget.fast_failures += 1;
+                return Err(circuit_open(destination, operation, Duration::ZERO));
+            }
+        };
+
+        Ok(Attempt {
+            budgets: self,
+            destination: destination.clone(),
+            operation: operation.to_string(),
+            probe,
+            resolved: Cell::new(false),
+        })
+    }
+
+    /// Run an operation, retrying failures under its local backoff while
+    /// the destination's budget allows
+    ///
+    /// Cancelled operations, those refused in offline mode, and refusals
+    /// of an open circuit are not retried.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::CircuitOpen`] when the circuit is or becomes
+    /// open, or the operation's last error.
+    pub async fn run<T, F, Fut>(
+        &self,
+        destination: &Destination,
+        operation: &str,
+        backoff: &Backoff,
+        mut attempt: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let admitted = self.admit(destination, operation)?;
+        let mut retries = 0;
+        loop {
+            match attempt().await {
+                Ok(value) => {
+                    admitted.succeeded();
+                    return Ok(value);
+                }
+                Err(error) if retries < backoff.max_retries && is_retryable(&error) => {
+                    admitted.retry()?;
+                    tokio::time::sleep(backoff.delay(retries)).await;
+                    retries += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Budget and circuit of every destination seen, by destination
+    pub fn metrics(&self) -> Vec<RetryBudgetMetrics> {
+        let now = self.clock.elapsed();
+        let mut budgets = self.budgets.lock().unwrap();
+        let destinations: Vec<Destination> = budgets.keys().cloned().collect();
+        destinations
+            .into_iter()
+            .map(|destination| {
+                let budget = self.budget(&mut budgets, &destination, now);
+                let (state, retry_after) = match budget.circuit {
+                    Circuit::Closed => (CircuitState::Closed, None),
+                    Circuit::Open { until } => (
+                        CircuitState::Open,
+                        Some(until.saturating_sub(now).as_secs()),
+                    ),
+                    Circuit::HalfOpen => (CircuitState::HalfOpen, None),
+                };
+                RetryBudgetMetrics {
+                    state,
+                    tokens: budget.tokens,
+                    capacity: self.policy.capacity,
+                    retries: budget.retries,
+                    fast_failures: budget.fast_failures,
+                    opened: budget.opened,
+                    probes: budget.probes,
+                    retry_after_secs: retry_after,
+                    destination,
+                }
+            })
+            .collect()
+    }
+
+    /// Destinations whose circuit is not closed
+    pub fn unavailable(&self) -> Vec<Destination> {
+        self.metrics()
+            .into_iter()
+            .filter(|metrics| metrics.state != CircuitState::Closed)
+            .map(|metrics| metrics.destination)
+            .collect()
+    }
+
+    /// A destination's budget, refilled up to `now`
+    fn budget<'b>(
+        &self,
+        budgets: &'b mut BTreeMap<Destination, Budget>,
+        destination: &Destination,
+        now: Duration,
+    ) -> &'b mut Budget {
+        let budget = budgets.entry(destination.clone()).or_insert(Budget {
+            tokens: self.policy.capacity,
+            refilled_at: now,
+            circuit: Circuit::Closed,
+            retries: 0,
+            fast_failures: 0,
+            opened: 0,
+            probes: 0,
+        });
+
+        let interval = self.policy.refill_interval;
+        if budget.tokens >= self.policy.capacity || interval.is_zero() {
+            budget.tokens = self.policy.capacity.max(budget.tokens);
+            budget.refilled_at = now;
+        } else {
+            let earned = (now.saturating_sub(budget.refilled_at).as_nanos() / interval.as_nanos())
+                .min(u128::from(self.policy.capacity)) as u32;
+            budget.tokens = (budget.tokens + earned).min(self.policy.capacity);
+            budget.refilled_at += interval * earned;
+            if budget.tokens == self.policy.capacity {
+                budget.refilled_at = now;
+            }
+        }
+        budget
+    }
+
+    /// Take a token for a retry, opening the circuit if there is none
+    fn spend(&self, destination: &Destination, operation: &str) -> Result<()> {
+        let now = self.clock.elapsed();
+        let mut budgets = self.budgets.lock().unwrap();
+        let budget = self.budget(&mut budgets, destination, now);
+
+        match budget.circuit {
+            Circuit::Closed => {}
+            Circuit::Open { until } => {
+                budget.fast_failures += 1;
+                return Err(circuit_open(
+                    destination,
+                    operation,
+                    until.saturating_sub(now),
+                ));
+            }
+            Circuit::HalfOpen => {
+                budget.fast_failures += 1;
+                return Err(circuit_open(destination, operation, Duration::ZERO));
+            }
+        }
+
+        if budget.tokens == 0 {
+            budget.circuit = Circuit::Open {
+                until: now + self.policy.cooldown,
+            };
+            budget.opened += 1;
+            budget.fast_failures += 1;
+            return Err(circuit_open(destination, operation, self.policy.cooldown));
+        }
+        budget.tokens -= 1;
+        budget.retries += 1;
+        Ok(())
+    }
+
+    /// Open the circuit for another cooldown after a failed probe
+    fn reopen(&self, destination: &Destination, operation: &str) -> OsnovaError {
+        let now = self.clock.elapsed();
+        let mut budgets = self.budgets.lock().unwrap();
+        let budget = self.budget(&mut budgets, destination, now);
+        budget.circuit = Circuit::Open {
+            until: now + self.policy.cooldown,
+        };
+        budget.opened += 1;
+        circuit_open(destination, operation, self.policy.cooldown)
+    }
+
+    /// Close the circuit with a full budget after a successful probe
+    fn close(&self, destination: &Destination) {
+        let now = self.clock.elapsed();
+        let mut budgets = self.budgets.lock().unwrap();
+        let budget = self.budget(&mut budgets, destination, now);
+        budget.circuit = Circuit::Closed;
+        budget.tokens = self.policy.capacity;
+        budget.refilled_at = now;
+    }
+}
+
+impl Default for RetryBudgets {
+    fn default() -> Self {
+        Self::new(RetryBudgetPolicy::default())
+    }
+}
+
+/// Whether a failed attempt may be retried
+fn is_retryable(error: &OsnovaError) -> bool {
+    !matches!(
+        error,
+        OsnovaError::Cancelled { .. }
+            | OsnovaError::OfflineMode { .. }
+            | OsnovaError::CircuitOpen { .. }
+    )
+}
+
+fn circuit_open(destination: &Destination, operation: &str, retry_after: Duration) -> OsnovaError {
+    OsnovaError::CircuitOpen {
+        operation: operation.to_string(),
+        destination: destination.to_string(),
+        retry_after,
+    }
+}
+
+static SHARED: OnceLock<Arc<RetryBudgets>> = OnceLock::new();
+
+/// The process-wide budgets, under the default policy
+pub fn shared() -> Arc<RetryBudgets> {
+    SHARED
+        .get_or_init(|| Arc::new(RetryBudgets::default()))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Backend failing its first `failures` calls
+    struct FaultyBackend {
+        failures: AtomicU32,
+        calls: AtomicU32,
+    }
+
+    impl FaultyBackend {
+        fn failing(failures: u32) -> Arc<Self> {
+            Arc::new(Self {
+                failures: AtomicU32::new(failures),
+                calls: AtomicU32::new(0),
+            })
+        }
+
+        fn down() -> Arc<Self> {
+            Self::failing(u32::MAX)
+        }
+
+        async fn call(&self) -> Result<&'static str> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok();
+            if failing {
+                Err(OsnovaError::Network("connection reset".to_string()))
+            } else {
+                Ok("data")
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    fn budgets(capacity: u32) -> (Arc<RetryBudgets>, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let budgets = RetryBudgets::new(RetryBudgetPolicy {
+            capacity,
+            refill_interval: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        })
+        .with_clock(clock.clone());
+        (Arc::new(budgets), clock)
+    }
+
+    fn eager(max_retries: u32) -> Backoff {
+        Backoff {
+            initial: Duration::ZERO,
+            max: Duration::ZERO,
+            max_retries,
+        }
+    }
+
+    fn metrics(budgets: &RetryBudgets, destination: &Destination) -> RetryBudgetMetrics {
+        budgets
+            .metrics()
+            .into_iter()
+            .find(|metrics| &metrics.destination == destination)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subsystems_share_one_budget() {
+        let (budgets, _clock) = budgets(4);
+        let backend = FaultyBackend::down();
+
+        // Downloads and uploads each allow 10 retries of their own
+        let subsystems: Vec<_> = ["download", "upload"]
+            .into_iter()
+            .map(|operation| {
+                let (budgets, backend) = (budgets.clone(), backend.clone());
+                tokio::spawn(async move {
+                    budgets
+                        .run(&Destination::Autonomi, operation, &eager(10), || {
+                            backend.call()
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for subsystem in subsystems {
+            let result = subsystem.await.unwrap();
+            assert!(matches!(result, Err(OsnovaError::CircuitOpen { .. })));
+        }
+
+        // Two first attempts and the four retries of the shared budget
+        assert_eq!(backend.calls(), 6);
+        let metrics = metrics(&budgets, &Destination::Autonomi);
+        assert_eq!(metrics.retries, 4);
+        assert_eq!(metrics.tokens, 0);
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_fast() {
+        let (budgets, clock) = budgets(2);
+        let backend = FaultyBackend::down();
+        let result = budgets
+            .run(&Destination::Server, "sync", &eager(5), || backend.call())
+            .await;
+        match result {
+            Err(OsnovaError::CircuitOpen {
+                operation,
+                destination,
+                retry_after,
+            }) => {
+                assert_eq!(operation, "sync");
+                assert_eq!(destination, "the Osnova server");
+                assert_eq!(retry_after, Duration::from_secs(30));
+            }
+            other => panic!("Expected an open circuit, got {:?}", other),
+        }
+        assert_eq!(backend.calls(), 3);
+
+        // Refused without touching the backend, saying when to come back
+        clock.advance(Duration::from_secs(12));
+        let result = budgets
+            .run(&Destination::Server, "rpc", &eager(5), || backend.call())
+            .await;
+        match result {
+            Err(OsnovaError::CircuitOpen { retry_after, .. }) => {
+                assert_eq!(retry_after, Duration::from_secs(18));
+            }
+            other => panic!("Expected an open circuit, got {:?}", other),
+        }
+        assert_eq!(backend.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_interruptions_are_not_retried() {
+        let (budgets, _clock) = budgets(2);
+        let offline = budgets
+            .run(&Destination::Autonomi, "download", &eager(5), || async {
+                Err::<(), _>(OsnovaError::OfflineMode {
+                    operation: "download".to_string(),
+                })
+            })
+            .await;
+        assert!(matches!(offline, Err(OsnovaError::OfflineMode { .. })));
+        assert_eq!(metrics(&budgets, &Destination::Autonomi).retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_recovers() {
+        let (budgets, clock) = budgets(1);
+        let backend = FaultyBackend::failing(3);
+        let autonomi = Destination::Autonomi;
+        let result = budgets
+            .run(&autonomi, "download", &eager(5), || backend.call())
+            .await;
+        assert!(matches!(result, Err(OsnovaError::CircuitOpen { .. })));
+
+        // After the cooldown one caller probes; the others still fail fast
+        clock.advance(Duration::from_secs(30));
+        let probe = budgets.admit(&autonomi, "download").unwrap();
+        assert!(probe.is_probe());
+        assert_eq!(metrics(&budgets, &autonomi).state, CircuitState::HalfOpen);
+        assert!(matches!(
+            budgets.admit(&autonomi, "upload"),
+            Err(OsnovaError::CircuitOpen { .. })
+        ));
+
+        // A failed probe opens the circuit for another cooldown
+        assert!(backend.call().await.is_err());
+        drop(probe);
+        let reopened = metrics(&budgets, &autonomi);
+        assert_eq!(reopened.state, CircuitState::Open);
+        assert_eq!(reopened.opened, 2);
+        assert_eq!(reopened.retry_after_secs, Some(30));
+
+        // A successful one closes it with a full budget
+        clock.advance(Duration::from_secs(30));
+        let data = budgets
+            .run(&autonomi, "download", &eager(5), || backend.call())
+            .await
+            .unwrap();
+        assert_eq!(data, "data");
+        let closed = metrics(&budgets, &autonomi);
+        assert_eq!(closed.state, CircuitState::Closed);
+        assert_eq!(closed.tokens, 1);
+        assert_eq!(closed.probes, 2);
+        assert!(budgets.admit(&autonomi, "upload").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_destinations_are_isolated() {
+        let (budgets, _clock) = budgets(1);
+        let down = FaultyBackend::down();
+        let up = FaultyBackend::failing(1);
+        let gateway = Destination::http("https://gateway.example/ant/abc?x=1");
+        assert_eq!(
+            gateway,
+            Destination::Http("https://gateway.example".to_string())
+        );
+
+        let result = budgets
+            .run(&Destination::Autonomi, "download", &eager(5), || {
+                down.call()
+            })
+            .await;
+        assert!(matches!(result, Err(OsnovaError::CircuitOpen { .. })));
+
+        // Other origins keep their own budget and closed circuit
+        let data = budgets
+            .run(&gateway, "fetch", &eager(5), || up.call())
+            .await
+            .unwrap();
+        assert_eq!(data, "data");
+        assert_eq!(
+            budgets.unavailable(),
+            vec![Destination::Autonomi],
+            "only the Autonomi circuit is open"
+        );
+        assert_eq!(
+            Destination::http("https://gateway.example:443/other"),
+            gateway
+        );
+        assert_ne!(Destination::http("https://mirror.example/"), gateway);
+    }
+
+    #[test]
+    fn test_budget_refills_over_time() {
+        let (budgets, clock) = budgets(3);
+        let attempt = budgets.admit(&Destination::Server, "sync").unwrap();
+        for _ in 0..3 {
+            attempt.retry().unwrap();
+        }
+        assert_eq!(metrics(&budgets, &Destination::Server).tokens, 0);
+
+        clock.advance(Duration::from_secs(25));
+        assert_eq!(metrics(&budgets, &Destination::Server).tokens, 2);
+        clock.advance(Duration::from_secs(100));
+        assert_eq!(metrics(&budgets, &Destination::Server).tokens, 3);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let backoff = Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+            max_retries: 5,
+        };
+        assert_eq!(
+            [0, 1, 2, 3, 40].map(|retry| backoff.delay(retry).as_secs()),
+            [1, 2, 4, 8, 10]
+        );
+    }
+}
@@ -7,6 +7,7 @@
 //! - Automatic chunking for files >1MB
 //! - ant:// URI generation
 //! - Cost estimation
+//! - Payment history recording through an attached [`PaymentLedger`](super::PaymentLedger)
 //!
 //! ## Example
 //!
@@ -25,10 +26,22 @@
 //! }
 //! ```
 
-use super::AutonomiClient;
+use super::bandwidth::{BandwidthMeter, TransferCategory, TransferDecision, TransferStatus};
+use super::{AutonomiClient, NetworkOptions, UploadContext};
 use crate::error::{OsnovaError, Result};
 use bytes::Bytes;
 
+/// Result of a completed upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadReceipt {
+    /// ant:// URI where the data can be retrieved
+    pub address: String,
+    /// Bytes uploaded
+    pub size: u64,
+    /// Cost paid, in AttoTokens
+    pub cost: u64,
+}
+
 /// Upload data to the Autonomi Network
 ///
 /// Uploads arbitrary data to the Autonomi Network and returns the content address.
@@ -55,6 +68,46 @@ use bytes::Bytes;
 /// println!("Uploaded to: {}", address);
 /// ```
 pub async fn upload_data(client: &AutonomiClient, data: &[u8]) -> Result<String> {
+    upload_data_with(client, data, &NetworkOptions::default()).await
+}
+
+/// Upload data to the Autonomi Network with a timeout and cancellation
+///
+/// Like [`upload_data`], but fails with `OsnovaError::Timeout` or
+/// `OsnovaError::Cancelled` according to `options`.
+pub async fn upload_data_with(
+    client: &AutonomiClient,
+    data: &[u8],
+    options: &NetworkOptions,
+) -> Result<String> {
+    upload_data_for(client, data, &UploadContext::default(), options)
+        .await
+        .map(|receipt| receipt.address)
+}
+
+/// Upload data and record it in the client's payment history
+///
+/// Like [`upload_data_with`], but returns the cost as well. When the client
+/// has a [`PaymentLedger`](super::PaymentLedger) attached, the upload is
+/// recorded with `context` whether it succeeds or not; failing to record
+/// is logged and does not fail the upload.
+pub async fn upload_data_for(
+    client: &AutonomiClient,
+    data: &[u8],
+    context: &UploadContext,
+    options: &NetworkOptions,
+) -> Result<UploadReceipt> {
+    let result = options.run("upload", put_data(client, data)).await;
+    if let Some(ledger) = client.payment_ledger() {
+        if let Err(e) = ledger.record(data.len() as u64, context, result.as_ref()) {
+            crate::log!(Warn, "Failed to record upload payment: {}", e);
+        }
+    }
+    result
+}
+
+/// Upload data without a timeout
+pub(super) async fn put_data(client: &AutonomiClient, data: &[u8]) -> Result<UploadReceipt> {
     use autonomi::client::payment::PaymentOption;
     use autonomi::client::payment::Receipt;
 
@@ -73,7 +126,7 @@ pub async fn upload_data(client: &AutonomiClient, data: &[u8]) -> Result<String>
     let payment = PaymentOption::Receipt(Receipt::default());
 
     // Upload data to network
-    let (_cost, data_address) = autonomi_client
+    let (cost, data_address) = autonomi_client
         .data_put_public(bytes, payment)
         .await
         .map_err(|e| OsnovaError::Network(format!("Failed to upload data: {}", e)))?;
@@ -81,7 +134,53 @@ pub async fn upload_data(client: &AutonomiClient, data: &[u8]) -> Result<String>
     // Convert address to ant:// URI
     let uri = format!("ant://{}", hex::encode(data_address.xorname().0));
 
-    Ok(uri)
+    Ok(UploadReceipt {
+        address: uri,
+        size: data.len() as u64,
+        // Lowest 64 bits, as for quotes
+        cost: cost.as_atto().as_limbs()[0],
+    })
+}
+
+/// Upload data to the Autonomi Network, honouring the bandwidth policy
+///
+/// Returns `TransferStatus::Deferred` without uploading when the policy does
+/// not currently allow the transfer. Completed uploads are recorded against
+/// `category`.
+///
+/// # Arguments
+///
+/// * `client` - Connected Autonomi client
+/// * `data` - Byte slice to upload
+/// * `meter` - Bandwidth meter enforcing the policy
+/// * `category` - Category to account the upload under (e.g. uploads or backups)
+pub async fn upload_data_metered(
+    client: &AutonomiClient,
+    data: &[u8],
+    meter: &BandwidthMeter,
+    category: TransferCategory,
+) -> Result<TransferStatus<String>> {
+    upload_data_metered_with(client, data, meter, category, &NetworkOptions::default()).await
+}
+
+/// Metered upload with a timeout and cancellation
+///
+/// Like [`upload_data_metered`]; interrupted uploads are not recorded.
+pub async fn upload_data_metered_with(
+    client: &AutonomiClient,
+    data: &[u8],
+    meter: &BandwidthMeter,
+    category: TransferCategory,
+    options: &NetworkOptions,
+) -> Result<TransferStatus<String>> {
+    if let TransferDecision::Deferred(reason) = meter.check()? {
+        return Ok(TransferStatus::Deferred(reason));
+    }
+
+    let address = upload_data_with(client, data, options).await?;
+    meter.record(category, data.len() as u64)?;
+
+    Ok(TransferStatus::Completed(address))
 }
 
 /// Estimate the cost of uploading data
@@ -110,6 +209,22 @@ pub async fn upload_data(client: &AutonomiClient, data: &[u8]) -> Result<String>
 /// println!("Upload will cost: {} AttoTokens", cost);
 /// ```
 pub async fn estimate_upload_cost(client: &AutonomiClient, data: &[u8]) -> Result<u64> {
+    estimate_upload_cost_with(client, data, &NetworkOptions::default()).await
+}
+
+/// Estimate the cost of uploading data with a timeout and cancellation
+pub async fn estimate_upload_cost_with(
+    client: &AutonomiClient,
+    data: &[u8],
+    options: &NetworkOptions,
+) -> Result<u64> {
+    options
+        .run("upload cost estimate", quote_cost(client, data))
+        .await
+}
+
+/// Request an upload quote without a timeout
+pub(super) async fn quote_cost(client: &AutonomiClient, data: &[u8]) -> Result<u64> {
     // Get the underlying Autonomi client
     let client_arc = client.client();
     let client_guard = client_arc.read().await;
@@ -144,6 +259,7 @@ mod tests {
         // Test that upload fails when client is not connected
         let client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
 
         let data = b"test data";
@@ -153,11 +269,94 @@ mod tests {
         assert!(matches!(result.unwrap_err(), OsnovaError::Network(_)));
     }
 
+    #[tokio::test]
+    async fn test_metered_upload_deferred_by_policy() {
+        use crate::network::bandwidth::{BandwidthPolicy, DeferReason};
+        use crate::storage::SqlStorage;
+
+        let client = AutonomiClient {
+            client: Arc::new(RwLock::new(None)),
+            ledger: None,
+        };
+        let meter = BandwidthMeter::new(
+            SqlStorage::new_in_memory().unwrap(),
+            BandwidthPolicy::WifiOnly,
+        );
+        meter.set_metered_mode(true);
+
+        // Deferred before touching the (disconnected) client
+        let result = upload_data_metered(&client, b"test data", &meter, TransferCategory::Backup)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            TransferStatus::Deferred(DeferReason::MeteredConnection)
+        );
+        assert_eq!(meter.usage().unwrap().today.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_upload_refused_or_deferred_while_offline() {
+        use crate::network::bandwidth::{BandwidthPolicy, DeferReason};
+        use crate::network::NetworkKillSwitch;
+        use crate::storage::SqlStorage;
+
+        let client = AutonomiClient {
+            client: Arc::new(RwLock::new(None)),
+            ledger: None,
+        };
+        let switch = Arc::new(NetworkKillSwitch::new());
+        switch.engage();
+
+        // Refused before reaching the (disconnected) client
+        let options = NetworkOptions::default().with_kill_switch(switch.clone());
+        let result = upload_data_with(&client, b"test data", &options).await;
+        assert!(matches!(result, Err(OsnovaError::OfflineMode { .. })));
+
+        // Queued uploads wait for the switch to be released
+        let meter = BandwidthMeter::new(
+            SqlStorage::new_in_memory().unwrap(),
+            BandwidthPolicy::Unlimited,
+        )
+        .with_kill_switch(switch);
+        let result = upload_data_metered(&client, b"test data", &meter, TransferCategory::Upload)
+            .await
+            .unwrap();
+        assert_eq!(result, TransferStatus::Deferred(DeferReason::OfflineByUser));
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_recorded_in_ledger() {
+        use crate::models::payment_record::PaymentStatus;
+        use crate::network::PaymentLedger;
+        use crate::storage::SqlStorage;
+
+        let ledger = Arc::new(PaymentLedger::new(SqlStorage::new_in_memory().unwrap()));
+        let client = AutonomiClient {
+            client: Arc::new(RwLock::new(None)),
+            ledger: None,
+        }
+        .with_payment_ledger(ledger.clone());
+
+        let context = UploadContext::for_component("com.example.app").with_quote(42);
+        let result =
+            upload_data_for(&client, b"test data", &context, &NetworkOptions::default()).await;
+        assert!(result.is_err());
+
+        let records = ledger.list(None, None).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, PaymentStatus::Failed);
+        assert_eq!(records[0].size, 9);
+        assert_eq!(records[0].component_id.as_deref(), Some("com.example.app"));
+        assert_eq!(records[0].quoted_cost, Some(42));
+    }
+
     #[tokio::test]
     async fn test_estimate_cost_fails_when_not_connected() {
         // Test that cost estimation fails when client is not connected
         let client = AutonomiClient {
             client: Arc::new(RwLock::new(None)),
+            ledger: None,
         };
 
         let data = b"test data";
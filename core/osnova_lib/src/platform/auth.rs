@@ -0,0 +1,345 @@
+//! OS user re-authentication
+//!
+//! Asks the operating system to confirm the person at the device is the
+//! signed-in user (fingerprint, face, or system password) before a
+//! sensitive operation. [`default_authenticator`] picks the best backend the
+//! platform offers:
+//! - Linux: fingerprint through `fprintd-verify` when the user has enrolled
+//!   a finger, else the polkit agent through `pkcheck` when Osnova's polkit
+//!   action ([`POLKIT_ACTION_ID`]) is installed
+//! - Other platforms: none yet; Windows Hello, macOS LocalAuthentication and
+//!   the mobile keystores need native bindings the core does not link
+//!
+//! Without a backend the [`Unavailable`] authenticator is used, which
+//! reports itself as such and fails every request, so callers can tell "not
+//! supported here" from "the user failed to authenticate".
+
+use base64::Engine;
+use bip39::rand::{thread_rng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc};
+
+use crate::error::{OsnovaError, Result};
+
+/// Polkit action the polkit backend checks
+///
+/// Its policy, shipped with the Linux packages, asks the user for their own
+/// password (`auth_self`) and shows the reason given to
+/// [`OsAuthenticator::authenticate`].
+pub const POLKIT_ACTION_ID: &str = "org.osnova.reauthenticate";
+
+/// Where polkit looks for the policy declaring [`POLKIT_ACTION_ID`]
+const POLKIT_ACTIONS_DIR: &str = "/usr/share/polkit-1/actions";
+
+/// Mechanism an authenticator uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthBackend {
+    /// Fingerprint reader through fprintd (Linux)
+    Fprintd,
+    /// Polkit authentication agent (Linux)
+    Polkit,
+    /// No OS authentication on this device
+    Unavailable,
+}
+
+/// Evidence that the OS authenticated the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthProof {
+    /// Random token identifying this authentication
+    pub token: String,
+    /// Backend that authenticated the user
+    pub backend: AuthBackend,
+    /// Unix timestamp of the authentication
+    pub authenticated_at: u64,
+}
+
+impl AuthProof {
+    /// A proof with a fresh random token
+    pub fn new(backend: AuthBackend, authenticated_at: u64) -> Self {
+        let mut token = [0u8; 24];
+        thread_rng().fill_bytes(&mut token);
+        Self {
+            token: base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token),
+            backend,
+            authenticated_at,
+        }
+    }
+}
+
+/// Asks the OS to authenticate the user
+pub trait OsAuthenticator: Send + Sync {
+    /// Mechanism used
+    fn backend(&self) -> AuthBackend;
+
+    /// Prompt the user, showing `reason`, and wait for the outcome
+    ///
+    /// Instructions the backend gives while it waits, such as which finger
+    /// to scan, are passed to `on_prompt` for the UI to show.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the user failed or
+    /// cancelled, and another error if the backend is unavailable
+    fn authenticate(&self, reason: &str, on_prompt: &dyn Fn(&str)) -> Result<AuthProof>;
+}
+
+/// Authenticator for devices without OS authentication
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unavailable;
+
+impl OsAuthenticator for Unavailable {
+    fn backend(&self) -> AuthBackend {
+        AuthBackend::Unavailable
+    }
+
+    fn authenticate(&self, _reason: &str, _on_prompt: &dyn Fn(&str)) -> Result<AuthProof> {
+        Err(OsnovaError::Other(
+            "OS authentication is not available on this device".to_string(),
+        ))
+    }
+}
+
+/// Authenticator backed by a system command that exits 0 on success
+///
+/// Every line the command prints is passed on as a prompt; a command that
+/// shows its own dialog, like the polkit agent, may print nothing.
+#[derive(Debug, Clone)]
+pub struct CommandAuthenticator {
+    backend: AuthBackend,
+    program: PathBuf,
+    args: Vec<String>,
+    reason_detail: Option<String>,
+}
+
+impl CommandAuthenticator {
+    /// Authenticate by running `program` with `args`
+    pub fn new<P: Into<PathBuf>>(backend: AuthBackend, program: P, args: &[&str]) -> Self {
+        Self {
+            backend,
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            reason_detail: None,
+        }
+    }
+
+    /// Pass the reason to the command as `--detail <key> <reason>`
+    fn with_reason_detail(mut self, key: &str) -> Self {
+        self.reason_detail = Some(key.to_string());
+        self
+    }
+
+    /// Fingerprint verification of the current user through fprintd
+    ///
+    /// `None` unless fprintd is installed and the user has enrolled at
+    /// least one finger, so users without a reader get the polkit prompt.
+    pub fn fprintd() -> Option<Self> {
+        let verify = find_program("fprintd-verify")?;
+        let list = find_program("fprintd-list")?;
+        let user = std::env::var("USER").ok()?;
+        let output = Command::new(list)
+            .arg(&user)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        (output.status.success() && has_enrolled_fingers(&String::from_utf8_lossy(&output.stdout)))
+            .then(|| Self::new(AuthBackend::Fprintd, verify, &[]))
+    }
+
+    /// Password prompt from the polkit agent for [`POLKIT_ACTION_ID`],
+    /// checked against this process
+    ///
+    /// `None` unless `pkcheck` and the action's policy are installed.
+    pub fn polkit() -> Option<Self> {
+        let policy = Path::new(POLKIT_ACTIONS_DIR).join(format!("{}.policy", POLKIT_ACTION_ID));
+        if !policy.is_file() {
+            return None;
+        }
+        let pid = std::process::id().to_string();
+        find_program("pkcheck").map(|program| {
+            Self::new(
+                AuthBackend::Polkit,
+                program,
+                &[
+                    "--action-id",
+                    POLKIT_ACTION_ID,
+                    "--process",
+                    &pid,
+                    "--allow-user-interaction",
+                ],
+            )
+            .with_reason_detail("reason")
+        })
+    }
+}
+
+impl OsAuthenticator for CommandAuthenticator {
+    fn backend(&self) -> AuthBackend {
+        self.backend
+    }
+
+    fn authenticate(&self, reason: &str, on_prompt: &dyn Fn(&str)) -> Result<AuthProof> {
+        crate::log!(
+            Info,
+            "Requesting OS authentication via {:?}: {}",
+            self.backend,
+            reason
+        );
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(key) = &self.reason_detail {
+            command.args(["--detail", key, reason]);
+        }
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                OsnovaError::Other(format!("Failed to run {}: {}", self.program.display(), e))
+            })?;
+
+        // Both streams are read on their own threads so neither can fill up
+        // and block the command; lines reach the caller on this thread
+        let (lines, received) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, lines.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, lines);
+        }
+        for line in received {
+            on_prompt(&line);
+        }
+
+        let status = child.wait().map_err(|e| {
+            OsnovaError::Other(format!("Failed to run {}: {}", self.program.display(), e))
+        })?;
+        if !status.success() {
+            return Err(OsnovaError::PermissionDenied(
+                "OS authentication failed or was cancelled".to_string(),
+            ));
+        }
+        Ok(AuthProof::new(self.backend, crate::time::now_unix()))
+    }
+}
+
+/// The best authenticator this platform offers
+pub fn default_authenticator() -> Arc<dyn OsAuthenticator> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(fprintd) = CommandAuthenticator::fprintd() {
+            return Arc::new(fprintd);
+        }
+        if let Some(polkit) = CommandAuthenticator::polkit() {
+            return Arc::new(polkit);
+        }
+    }
+    Arc::new(Unavailable)
+}
+
+/// Send each non-empty line of `stream` to `lines` until it closes
+fn forward_lines<R: Read + Send + 'static>(stream: R, lines: mpsc::Sender<String>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim();
+            if !line.is_empty() && lines.send(line.to_string()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Whether `fprintd-list` output lists an enrolled finger
+///
+/// Enrolled fingers are listed as ` - #0: right-index-finger`.
+fn has_enrolled_fingers(listing: &str) -> bool {
+    listing
+        .lines()
+        .any(|line| line.trim_start().starts_with("- #"))
+}
+
+/// Locate an executable on `PATH`
+fn find_program(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticators_report_their_outcome() -> Result<()> {
+        assert_eq!(Unavailable.backend(), AuthBackend::Unavailable);
+        assert!(matches!(
+            Unavailable.authenticate("reveal", &|_| {}),
+            Err(OsnovaError::Other(_))
+        ));
+
+        let accepting = CommandAuthenticator::new(AuthBackend::Polkit, "true", &[]);
+        let proof = accepting.authenticate("reveal", &|_| {})?;
+        assert_eq!(proof.backend, AuthBackend::Polkit);
+        assert_ne!(
+            proof.token,
+            accepting.authenticate("reveal", &|_| {})?.token
+        );
+
+        let refusing = CommandAuthenticator::new(AuthBackend::Polkit, "false", &[]);
+        assert!(matches!(
+            refusing.authenticate("reveal", &|_| {}),
+            Err(OsnovaError::PermissionDenied(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_output_and_reason_reach_the_prompt() -> Result<()> {
+        // Prints its arguments, one per line, then a finger prompt on stderr
+        let script = r#"for arg in "$@"; do echo "$arg"; done; echo "Swipe your finger" >&2"#;
+        let authenticator = CommandAuthenticator::new(
+            AuthBackend::Fprintd,
+            "sh",
+            &["-c", script, "sh", "--action-id", POLKIT_ACTION_ID],
+        )
+        .with_reason_detail("reason");
+
+        let prompts = std::cell::RefCell::new(Vec::new());
+        authenticator.authenticate("Osnova wants to show your seed phrase", &|line| {
+            prompts.borrow_mut().push(line.to_string())
+        })?;
+        let mut prompts = prompts.into_inner();
+        prompts.sort();
+        let mut expected = vec![
+            "--action-id",
+            POLKIT_ACTION_ID,
+            "--detail",
+            "reason",
+            "Osnova wants to show your seed phrase",
+            "Swipe your finger",
+        ];
+        expected.sort();
+        assert_eq!(prompts, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_enrolled_fingers_are_detected() {
+        assert!(has_enrolled_fingers(
+            "Fingerprints for user alice on Synaptics (press):\n - #0: right-index-finger\n"
+        ));
+        assert!(!has_enrolled_fingers(
+            "User alice has no fingers enrolled for Synaptics (press).\n"
+        ));
+        assert!(!has_enrolled_fingers(""));
+    }
+}
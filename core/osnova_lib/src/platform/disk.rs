@@ -0,0 +1,281 @@
+//! Free disk space checks
+//!
+//! Large writes (component downloads, cache stores, database backups, and
+//! archive extraction) are refused when they would leave less than a
+//! minimum amount of free space on the volume, so a download cannot fill
+//! the disk and take the rest of the system down with it.
+//!
+//! Free space comes from `statvfs` on Unix and `GetDiskFreeSpaceExW` on
+//! Windows. Checks go through a [`DiskSpaceProvider`] so tests can inject
+//! fixed numbers.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{OsnovaError, Result};
+
+/// Free space kept in reserve by default (500 MiB)
+pub const DEFAULT_MIN_FREE_BYTES: u64 = 500 * 1024 * 1024;
+
+/// Size assumed for a download whose size is not known up front (64 MiB)
+pub const DEFAULT_DOWNLOAD_ESTIMATE: u64 = 64 * 1024 * 1024;
+
+/// Source of free space figures
+pub trait DiskSpaceProvider: Send + Sync {
+    /// Bytes available to this process on the volume holding `path`
+    fn free_space(&self, path: &Path) -> Result<u64>;
+}
+
+/// Shared handle to a disk space provider
+pub type SharedDiskSpace = Arc<dyn DiskSpaceProvider>;
+
+/// Provider that asks the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemDiskSpace;
+
+impl DiskSpaceProvider for SystemDiskSpace {
+    fn free_space(&self, path: &Path) -> Result<u64> {
+        free_space(path)
+    }
+}
+
+/// Bytes available to this process on the volume holding `path`
+///
+/// `path` need not exist yet; its nearest existing ancestor is queried.
+///
+/// # Errors
+///
+/// Returns an error if no ancestor of `path` exists or the query fails.
+pub fn free_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .ok_or_else(|| {
+            OsnovaError::Storage(format!("No existing directory above {}", path.display()))
+        })?;
+
+    imp::free_space(existing)
+}
+
+/// Refuses writes that would leave too little free space
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use osnova_lib::platform::disk::DiskGuard;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let guard = DiskGuard::new(500 * 1024 * 1024);
+/// guard.check("/tmp/osnova/cache", 10 * 1024 * 1024)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DiskGuard {
+    provider: SharedDiskSpace,
+    min_free: u64,
+}
+
+impl DiskGuard {
+    /// Create a guard keeping `min_free` bytes free, using the OS figures
+    pub fn new(min_free: u64) -> Self {
+        Self {
+            provider: Arc::new(SystemDiskSpace),
+            min_free,
+        }
+    }
+
+    /// Use a specific free space provider
+    pub fn with_provider(mut self, provider: SharedDiskSpace) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// Bytes kept free
+    pub fn min_free(&self) -> u64 {
+        self.min_free
+    }
+
+    /// Bytes available on the volume holding `path`
+    pub fn free_space<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        self.provider.free_space(path.as_ref())
+    }
+
+    /// Check that `bytes` can be written under `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::DiskSpaceLow`] if fewer than `bytes` plus the
+    /// reserve are available.
+    pub fn check<P: AsRef<Path>>(&self, path: P, bytes: u64) -> Result<()> {
+        let path = path.as_ref();
+        let available = self.free_space(path)?;
+        let required = bytes.saturating_add(self.min_free);
+
+        if available < required {
+            return Err(OsnovaError::DiskSpaceLow {
+                path: path.to_path_buf(),
+                available,
+                required,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DiskGuard {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_FREE_BYTES)
+    }
+}
+
+impl std::fmt::Debug for DiskGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiskGuard")
+            .field("min_free", &self.min_free)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Provider reporting a fixed amount of free space (for testing)
+#[derive(Debug, Default)]
+pub struct FixedDiskSpace {
+    free: std::sync::atomic::AtomicU64,
+}
+
+impl FixedDiskSpace {
+    /// Report `free` bytes on every volume
+    pub fn new(free: u64) -> Self {
+        Self {
+            free: std::sync::atomic::AtomicU64::new(free),
+        }
+    }
+
+    /// Change the reported free space
+    pub fn set(&self, free: u64) {
+        self.free.store(free, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+impl DiskSpaceProvider for FixedDiskSpace {
+    fn free_space(&self, _path: &Path) -> Result<u64> {
+        Ok(self.free.load(std::sync::atomic::Ordering::SeqCst))
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    pub fn free_space(path: &Path) -> Result<u64> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| OsnovaError::Storage(format!("Invalid path: {}", path.display())))?;
+
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c_path is NUL-terminated and stat is a valid out pointer
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return Err(OsnovaError::Io(std::io::Error::last_os_error()));
+        }
+
+        // Blocks available to unprivileged users, in fragment-size units
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::*;
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+    }
+
+    pub fn free_space(path: &Path) -> Result<u64> {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free_to_caller = 0u64;
+
+        // SAFETY: wide is NUL-terminated; null pointers are allowed for the
+        // totals we do not need
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                wide.as_ptr(),
+                &mut free_to_caller,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(OsnovaError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(free_to_caller)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use super::*;
+
+    /// Free space is unknown; never block writes
+    pub fn free_space(_path: &Path) -> Result<u64> {
+        Ok(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn guard(free: u64) -> DiskGuard {
+        DiskGuard::new(500 * MIB).with_provider(Arc::new(FixedDiskSpace::new(free)))
+    }
+
+    #[test]
+    fn test_refuses_below_threshold() {
+        let err = guard(600 * MIB).check("/data", 200 * MIB).unwrap_err();
+
+        match err {
+            OsnovaError::DiskSpaceLow {
+                path,
+                available,
+                required,
+            } => {
+                assert_eq!(path, PathBuf::from("/data"));
+                assert_eq!(available, 600 * MIB);
+                assert_eq!(required, 700 * MIB);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_passes_above_threshold() {
+        assert!(guard(700 * MIB).check("/data", 200 * MIB).is_ok());
+        assert!(guard(500 * MIB).check("/data", 0).is_ok());
+        // Huge requests saturate instead of overflowing
+        assert!(guard(u64::MAX - 1).check("/data", u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_system_free_space() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let free = free_space(temp.path()).unwrap();
+        assert!(free > 0);
+
+        // Paths that do not exist yet use their nearest existing ancestor
+        let missing = temp.path().join("not/yet/created");
+        assert!(free_space(&missing).is_ok());
+    }
+}
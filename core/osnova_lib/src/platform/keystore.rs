@@ -0,0 +1,139 @@
+//! Platform keystore holding the key that seals the identity
+//!
+//! The identity file is encrypted with a key kept by the operating system
+//! (DPAPI, Keychain, Secret Service, or a mobile keystore). Keystores can
+//! lose entries, for example after an OS reinstall that kept the home
+//! directory, so callers must handle [`Keystore::key`] returning `None` or
+//! a key that no longer opens the identity.
+//!
+//! No native keystore is linked yet: [`DevelopmentKeystore`] returns the
+//! deterministic development key. [`MemoryKeystore`] holds a key in memory
+//! for tests and scripted scenarios, and can simulate losing it.
+
+use bip39::rand::{thread_rng, RngCore};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{OsnovaError, Result};
+
+/// Store for the key that seals the identity
+pub trait Keystore: Send + Sync {
+    /// The stored key, or `None` if the keystore has no entry
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keystore cannot be reached
+    fn key(&self) -> Result<Option<[u8; 32]>>;
+
+    /// Store a fresh key in place of the current one and return it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keystore cannot be written
+    fn replace(&self) -> Result<[u8; 32]>;
+}
+
+/// Shared handle to a keystore
+pub type SharedKeystore = Arc<dyn Keystore>;
+
+/// Deterministic development key, used until native keystores are linked
+///
+/// It cannot lose its key, and cannot hold another one: [`Keystore::replace`]
+/// returns the same key.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DevelopmentKeystore;
+
+impl DevelopmentKeystore {
+    /// The development key
+    pub fn development_key() -> [u8; 32] {
+        *blake3::hash(b"osnova-platform-key-v1").as_bytes()
+    }
+}
+
+impl Keystore for DevelopmentKeystore {
+    fn key(&self) -> Result<Option<[u8; 32]>> {
+        Ok(Some(Self::development_key()))
+    }
+
+    fn replace(&self) -> Result<[u8; 32]> {
+        Ok(Self::development_key())
+    }
+}
+
+/// Keystore holding its key in memory
+#[derive(Debug, Default)]
+pub struct MemoryKeystore {
+    key: Mutex<Option<[u8; 32]>>,
+}
+
+impl MemoryKeystore {
+    /// A keystore holding `key`
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            key: Mutex::new(Some(key)),
+        }
+    }
+
+    /// Drop the stored key, as a keystore reset would
+    pub fn lose(&self) {
+        *self.lock() = None;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<[u8; 32]>> {
+        self.key.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Keystore for MemoryKeystore {
+    fn key(&self) -> Result<Option<[u8; 32]>> {
+        Ok(*self.lock())
+    }
+
+    fn replace(&self) -> Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        thread_rng().fill_bytes(&mut key);
+        *self.lock() = Some(key);
+        Ok(key)
+    }
+}
+
+/// The stored key, failing if the keystore has no entry
+///
+/// # Errors
+///
+/// Returns an error if the keystore cannot be reached or has lost its key
+pub fn require_key(keystore: &dyn Keystore) -> Result<[u8; 32]> {
+    keystore
+        .key()?
+        .ok_or_else(|| OsnovaError::Identity("platform keystore has no key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_keystore_loses_and_replaces_key() -> Result<()> {
+        let keystore = MemoryKeystore::new([7u8; 32]);
+        assert_eq!(require_key(&keystore)?, [7u8; 32]);
+
+        keystore.lose();
+        assert_eq!(keystore.key()?, None);
+        assert!(require_key(&keystore).is_err());
+
+        let fresh = keystore.replace()?;
+        assert_ne!(fresh, [7u8; 32]);
+        assert_eq!(keystore.key()?, Some(fresh));
+        Ok(())
+    }
+
+    #[test]
+    fn test_development_keystore_is_stable() -> Result<()> {
+        let keystore = DevelopmentKeystore;
+        assert_eq!(
+            keystore.key()?,
+            Some(DevelopmentKeystore::development_key())
+        );
+        assert_eq!(keystore.replace()?, DevelopmentKeystore::development_key());
+        Ok(())
+    }
+}
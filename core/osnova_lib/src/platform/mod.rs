@@ -2,6 +2,12 @@
 //!
 //! Cross-platform utilities for file paths and system integration.
 
+pub mod auth;
+pub mod disk;
+pub mod keystore;
 pub mod paths;
+pub mod process;
 
+pub use disk::{free_space, DiskGuard};
+pub use keystore::{DevelopmentKeystore, Keystore, MemoryKeystore, SharedKeystore};
 pub use paths::{get_cache_dir, get_component_cache_dir, get_config_dir, get_data_dir};
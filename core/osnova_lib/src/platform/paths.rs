@@ -2,6 +2,10 @@
 //!
 //! Provides cross-platform directory paths for data, cache, and config.
 //!
+//! Each directory below gets a subdirectory named for the OS user (see
+//! [`user_dir`]), so OS accounts sharing a base directory don't collide.
+//! Installs from before per-user directories keep their data directory.
+//!
 //! ## Platform Directories
 //!
 //! ### Data Directory (`get_data_dir()`)
@@ -49,11 +53,72 @@
 //! ```
 
 use crate::error::{OsnovaError, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Database whose presence marks a data directory from before per-user
+/// directories
+const LEGACY_DATABASE: &str = "osnova.db";
+
+/// Name of the current OS user
+///
+/// Taken from `USER` (`USERNAME` on Windows, `LOGNAME` as a fallback);
+/// `"default"` if none is set, as in single-user mobile sandboxes.
+pub fn current_username() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// The per-user directory for `username` under an Osnova base directory
+///
+/// The username is reduced to characters that are safe in a path
+/// component on every platform.
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::platform::paths::user_dir;
+/// use std::path::Path;
+///
+/// let base = Path::new("/srv/osnova");
+/// assert_eq!(user_dir(base, "alice"), base.join("alice"));
+/// assert_ne!(user_dir(base, "alice"), user_dir(base, "bob"));
+/// ```
+pub fn user_dir(base: &Path, username: &str) -> PathBuf {
+    let mut name: String = username
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '_') {
+        name = "default".to_string();
+    }
+    base.join(name)
+}
+
+/// The data directory under `base` for `username`
+///
+/// A legacy directory holding data directly keeps being used, so existing
+/// installs don't lose their data; the owner lock in
+/// [`crate::storage::ownership`] still keeps other OS users out of it.
+fn data_dir_for(base: &Path, username: &str) -> PathBuf {
+    if base.join(LEGACY_DATABASE).exists() {
+        return base.to_path_buf();
+    }
+    user_dir(base, username)
+}
 
 /// Get application data directory
 ///
-/// Returns platform-specific locations:
+/// Returns platform-specific locations, plus a per-user subdirectory:
 /// - Linux: `~/.local/share/osnova/`
 /// - macOS: `~/Library/Application Support/osnova/`
 /// - Windows: `%LOCALAPPDATA%\osnova\`
@@ -83,12 +148,12 @@ pub fn get_data_dir() -> Result<PathBuf> {
         )
     })?;
     path.push("osnova");
-    Ok(path)
+    Ok(data_dir_for(&path, &current_username()))
 }
 
 /// Get application cache directory
 ///
-/// Returns platform-specific locations:
+/// Returns platform-specific locations, plus a per-user subdirectory:
 /// - Linux: `~/.cache/osnova/`
 /// - macOS: `~/Library/Caches/osnova/`
 /// - Windows: `%LOCALAPPDATA%\osnova\Cache\`
@@ -118,12 +183,12 @@ pub fn get_cache_dir() -> Result<PathBuf> {
         )
     })?;
     path.push("osnova");
-    Ok(path)
+    Ok(user_dir(&path, &current_username()))
 }
 
 /// Get application config directory
 ///
-/// Returns platform-specific locations:
+/// Returns platform-specific locations, plus a per-user subdirectory:
 /// - Linux: `~/.config/osnova/`
 /// - macOS: `~/Library/Application Support/osnova/`
 /// - Windows: `%APPDATA%\osnova\`
@@ -153,7 +218,7 @@ pub fn get_config_dir() -> Result<PathBuf> {
         )
     })?;
     path.push("osnova");
-    Ok(path)
+    Ok(user_dir(&path, &current_username()))
 }
 
 /// Get component cache directory
@@ -190,7 +255,8 @@ mod tests {
     #[test]
     fn test_get_data_dir() {
         let path = get_data_dir().unwrap();
-        assert!(path.ends_with("osnova"));
+        let base = dirs::data_local_dir().unwrap().join("osnova");
+        assert_eq!(path, data_dir_for(&base, &current_username()));
 
         // Verify platform-specific base
         #[cfg(target_os = "linux")]
@@ -209,7 +275,8 @@ mod tests {
     #[test]
     fn test_get_cache_dir() {
         let path = get_cache_dir().unwrap();
-        assert!(path.ends_with("osnova"));
+        assert!(path.parent().unwrap().ends_with("osnova"));
+        assert_eq!(path.file_name(), user_dir(Path::new(""), &current_username()).file_name());
 
         // Verify platform-specific base
         #[cfg(target_os = "linux")]
@@ -228,7 +295,7 @@ mod tests {
     #[test]
     fn test_get_config_dir() {
         let path = get_config_dir().unwrap();
-        assert!(path.ends_with("osnova"));
+        assert!(path.parent().unwrap().ends_with("osnova"));
 
         // Verify platform-specific base
         #[cfg(target_os = "linux")]
@@ -264,4 +331,24 @@ mod tests {
         // (On macOS, data and config are the same, which is fine)
         assert_ne!(data, cache);
     }
+
+    #[test]
+    fn test_default_paths_differ_per_user() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let base = temp.path();
+
+        let alice = data_dir_for(base, "alice");
+        let bob = data_dir_for(base, "bob");
+        assert_eq!(alice, base.join("alice"));
+        assert_ne!(alice, bob);
+
+        // Usernames are reduced to one safe path component
+        assert_eq!(user_dir(base, "DOMAIN\\alice"), base.join("DOMAIN_alice"));
+        assert_eq!(user_dir(base, "../.."), base.join("default"));
+        assert_eq!(user_dir(base, ""), base.join("default"));
+
+        // A data directory from before per-user directories stays in use
+        std::fs::write(base.join(LEGACY_DATABASE), b"").unwrap();
+        assert_eq!(data_dir_for(base, "alice"), base);
+    }
 }
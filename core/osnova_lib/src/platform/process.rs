@@ -0,0 +1,297 @@
+//! Process inspection and termination
+//!
+//! Provides the OS-level primitives used to supervise backend component
+//! processes:
+//! - Reading a process's start time and command line, so a registry entry
+//!   can be matched against the process that currently owns a pid
+//! - Graceful termination (SIGTERM, then SIGKILL after a grace period)
+//! - Telling which signal, if any, ended a child process
+//! - Reading a process's resident memory, for the idle backend budget
+//!
+//! On Linux process details come from `/proc`; other Unix platforms use
+//! `ps`. Non-Unix platforms report every process as not running.
+
+use crate::error::{OsnovaError, Result};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+/// Interval between liveness checks while waiting for a process to exit
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Identifying details of a running process
+///
+/// A pid alone is not enough to identify a process because pids are
+/// recycled; the start time and command line together are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Opaque, platform-specific process start time
+    pub start_time: String,
+    /// Full command line, arguments separated by spaces
+    pub command_line: String,
+}
+
+/// How a process was terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The process was not running
+    NotRunning,
+    /// The process exited after SIGTERM
+    Graceful,
+    /// The process had to be killed with SIGKILL
+    Forced,
+}
+
+/// Get identifying details of a running process
+///
+/// Returns `None` if no process with `pid` exists or it has already exited
+/// and is only waiting to be reaped (zombie). The command line of a process
+/// that was just spawned may be empty until it has finished executing.
+pub fn process_info(pid: u32) -> Option<ProcessInfo> {
+    imp::process_info(pid)
+}
+
+/// Get the resident memory of a running process, in bytes
+///
+/// Returns `None` if the process does not exist or the platform cannot
+/// tell.
+pub fn resident_memory(pid: u32) -> Option<u64> {
+    imp::resident_memory(pid)
+}
+
+/// Check whether a process is running
+pub fn is_running(pid: u32) -> bool {
+    process_info(pid).is_some()
+}
+
+/// Terminate a process gracefully
+///
+/// Sends SIGTERM and waits up to `grace_period` for the process to exit,
+/// then sends SIGKILL.
+///
+/// # Errors
+///
+/// Returns `OsnovaError::Other` if the process is still running after
+/// SIGKILL.
+pub fn terminate(pid: u32, grace_period: Duration) -> Result<Termination> {
+    if !is_running(pid) {
+        return Ok(Termination::NotRunning);
+    }
+
+    imp::signal(pid, "TERM");
+    if wait_for_exit(pid, grace_period) {
+        return Ok(Termination::Graceful);
+    }
+
+    imp::signal(pid, "KILL");
+    if wait_for_exit(pid, Duration::from_secs(5)) {
+        return Ok(Termination::Forced);
+    }
+
+    Err(OsnovaError::Other(format!(
+        "Process {} did not exit after SIGKILL",
+        pid
+    )))
+}
+
+/// Get the signal that terminated a child process, if any
+///
+/// Always `None` on non-Unix platforms, which have no signals.
+pub fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Wait until a process is no longer running, up to `timeout`
+fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !is_running(pid) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::ProcessInfo;
+
+    pub fn process_info(pid: u32) -> Option<ProcessInfo> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+        // The command name is wrapped in parentheses and may itself contain
+        // spaces or parentheses, so parse from the last closing one
+        let rest = &stat[stat.rfind(')')? + 1..];
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+
+        // fields[0] is the state (field 3); starttime is field 22
+        if matches!(fields.first(), Some(&"Z") | Some(&"X")) {
+            return None;
+        }
+        let start_time = fields.get(19)?.to_string();
+
+        let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        let command_line = cmdline
+            .split(|b| *b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(String::from_utf8_lossy)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(ProcessInfo {
+            start_time,
+            command_line,
+        })
+    }
+
+    pub fn resident_memory(pid: u32) -> Option<u64> {
+        // Second field of statm: resident pages
+        let statm = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+
+    pub fn signal(pid: u32, signal: &str) {
+        super::unix::signal(pid, signal);
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod imp {
+    use super::ProcessInfo;
+    use std::process::Command;
+
+    pub fn process_info(pid: u32) -> Option<ProcessInfo> {
+        if ps_field(pid, "stat")?.starts_with('Z') {
+            return None;
+        }
+
+        Some(ProcessInfo {
+            start_time: ps_field(pid, "lstart")?,
+            command_line: ps_field(pid, "command")?,
+        })
+    }
+
+    pub fn resident_memory(pid: u32) -> Option<u64> {
+        // Reported in KiB
+        let kib: u64 = ps_field(pid, "rss")?.parse().ok()?;
+        Some(kib * 1024)
+    }
+
+    pub fn signal(pid: u32, signal: &str) {
+        super::unix::signal(pid, signal);
+    }
+
+    fn ps_field(pid: u32, field: &str) -> Option<String> {
+        let output = Command::new("ps")
+            .args(["-o", &format!("{}=", field), "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::process::{Command, Stdio};
+
+    /// Send a signal by name using `kill`
+    pub fn signal(pid: u32, signal: &str) {
+        // Failure means the process already exited, which callers detect
+        let _ = Command::new("kill")
+            .args([format!("-{}", signal), pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use super::ProcessInfo;
+
+    pub fn process_info(_pid: u32) -> Option<ProcessInfo> {
+        None
+    }
+
+    pub fn resident_memory(_pid: u32) -> Option<u64> {
+        None
+    }
+
+    pub fn signal(_pid: u32, _signal: &str) {}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_process_info_for_running_process() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let info = process_info(child.id()).expect("process should be running");
+        assert!(info.command_line.contains("sleep 30"));
+
+        // Start time is stable across reads
+        assert_eq!(process_info(child.id()).unwrap(), info);
+        assert!(resident_memory(child.id()).is_some_and(|bytes| bytes > 0));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!is_running(child.id()));
+    }
+
+    #[test]
+    fn test_terminate_graceful() -> Result<()> {
+        let mut child = Command::new("sleep").arg("30").spawn()?;
+        let pid = child.id();
+
+        // The unreaped child is a zombie after exit and counts as stopped
+        assert_eq!(
+            terminate(pid, Duration::from_secs(5))?,
+            Termination::Graceful
+        );
+        assert!(!is_running(pid));
+        assert_eq!(
+            terminate(pid, Duration::from_secs(5))?,
+            Termination::NotRunning
+        );
+        child.wait()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminate_forces_after_grace_period() -> Result<()> {
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; while true; do sleep 0.1; done"])
+            .spawn()?;
+        let pid = child.id();
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(
+            terminate(pid, Duration::from_millis(200))?,
+            Termination::Forced
+        );
+        child.wait()?;
+
+        Ok(())
+    }
+}
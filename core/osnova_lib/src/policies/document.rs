@@ -0,0 +1,427 @@
+//! Signed policy and removal documents, and the rules they carry
+//!
+//! A policy is authored on the admin's device and signed with the admin
+//! key derived from the admin's identity (see [`admin_signing_key`]). The
+//! signature covers the document in canonical JSON, behind a tag naming
+//! the kind of document, so a signed policy can never be passed off as a
+//! removal or the other way round.
+//!
+//! Rules are checked in a fixed order, so the denylists always win:
+//!
+//! 1. [`PolicyRule::DenyApps`], then [`PolicyRule::DenyPublishers`]
+//! 2. [`PolicyRule::BlockUnsigned`]
+//! 3. [`PolicyRule::AllowList`]: when either allowlist is non-empty, the
+//!    app must be on one of them
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::{OsnovaError, Result};
+use crate::models::application::OsnovaApplication;
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::util::canonical_json;
+
+/// Component the admin signing key is derived for
+const ADMIN_KEY_COMPONENT: &str = "osnova-policy-admin";
+
+/// Tag signed ahead of a policy document
+const POLICY_DOMAIN: &[u8] = b"osnova-policy:";
+
+/// Tag signed ahead of a removal document
+const REMOVAL_DOMAIN: &[u8] = b"osnova-policy-removal:";
+
+/// Admin key policies are signed with, derived from the admin's identity
+///
+/// The same identity always derives the same key, so the admin can sign
+/// updates and removals from any of their devices.
+///
+/// # Errors
+///
+/// Returns [`OsnovaError::Crypto`] if key derivation fails
+pub fn admin_signing_key(identity: &RootIdentity) -> Result<SigningKey> {
+    let seed = identity.derive_component_key(ADMIN_KEY_COMPONENT, 0, KeyPurpose::Signing)?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Rule of a policy, as named in [`OsnovaError::PolicyBlocked`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PolicyRule {
+    /// The app is on the app denylist
+    DenyApps,
+    /// The app's publisher is on the publisher denylist
+    DenyPublishers,
+    /// Unsigned apps are blocked
+    BlockUnsigned,
+    /// The app is on neither allowlist
+    AllowList,
+    /// The payment would take the app over its wallet cap
+    WalletCap,
+}
+
+impl PolicyRule {
+    /// Name of the rule, as in the policy document
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyRule::DenyApps => "denyApps",
+            PolicyRule::DenyPublishers => "denyPublishers",
+            PolicyRule::BlockUnsigned => "blockUnsigned",
+            PolicyRule::AllowList => "allowList",
+            PolicyRule::WalletCap => "walletCaps",
+        }
+    }
+}
+
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Install, launch and wallet rules of a policy
+///
+/// App rules apply both when an app is installed and when it is launched,
+/// so tightening a policy also stops apps installed before.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PolicyRules {
+    /// App IDs that may be installed; empty allows any not denied
+    pub allow_apps: Vec<String>,
+    /// Publishers whose apps may be installed; empty allows any not denied
+    pub allow_publishers: Vec<String>,
+    /// App IDs that may not be installed or launched
+    pub deny_apps: Vec<String>,
+    /// Publishers whose apps may not be installed or launched
+    pub deny_publishers: Vec<String>,
+    /// Whether apps without a publisher signature are blocked
+    pub block_unsigned: bool,
+    /// Most each app may spend through the wallet in
+    /// [`WALLET_CAP_WINDOW_SECS`](super::WALLET_CAP_WINDOW_SECS), in
+    /// AttoTokens, by app ID
+    pub wallet_caps: BTreeMap<String, u64>,
+}
+
+impl PolicyRules {
+    /// The rule blocking an app, if any, and why
+    pub fn blocking_rule(
+        &self,
+        app_id: &str,
+        publisher: Option<&str>,
+        signed: bool,
+    ) -> Option<(PolicyRule, String)> {
+        let listed = |list: &[String], value: &str| list.iter().any(|entry| entry == value);
+
+        if listed(&self.deny_apps, app_id) {
+            return Some((PolicyRule::DenyApps, format!("{} is denied", app_id)));
+        }
+        if let Some(publisher) = publisher.filter(|p| listed(&self.deny_publishers, p)) {
+            return Some((
+                PolicyRule::DenyPublishers,
+                format!("{} is published by {}, which is denied", app_id, publisher),
+            ));
+        }
+        if self.block_unsigned && !signed {
+            return Some((PolicyRule::BlockUnsigned, format!("{} is unsigned", app_id)));
+        }
+        let restricted = !self.allow_apps.is_empty() || !self.allow_publishers.is_empty();
+        let allowed = listed(&self.allow_apps, app_id)
+            || publisher.is_some_and(|p| listed(&self.allow_publishers, p));
+        if restricted && !allowed {
+            return Some((
+                PolicyRule::AllowList,
+                format!("{} is not on the allowlist", app_id),
+            ));
+        }
+        None
+    }
+
+    /// The rule blocking an installed or resolved app, if any, and why
+    pub fn blocking_rule_for(&self, app: &OsnovaApplication) -> Option<(PolicyRule, String)> {
+        self.blocking_rule(app.id(), app.publisher(), app.signature().is_some())
+    }
+}
+
+/// Policy document, as authored on the admin's device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyDocument {
+    /// Identifies the policy across updates
+    pub policy_id: String,
+    /// Increases with every update; an older policy is never applied over
+    /// a newer one
+    pub sequence: u64,
+    /// Unix timestamp the policy was signed at
+    pub issued_at: u64,
+    /// Admin's Ed25519 public key (base64), set when signing
+    #[serde(default)]
+    pub admin_key: String,
+    /// Rules to enforce
+    pub rules: PolicyRules,
+}
+
+impl PolicyDocument {
+    /// Create an unsigned policy
+    pub fn new(policy_id: &str, sequence: u64, issued_at: u64, rules: PolicyRules) -> Self {
+        Self {
+            policy_id: policy_id.to_string(),
+            sequence,
+            issued_at,
+            admin_key: String::new(),
+            rules,
+        }
+    }
+
+    /// Sign the policy with the admin key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document cannot be encoded
+    pub fn sign(mut self, admin_key: &SigningKey) -> Result<SignedPolicy> {
+        self.admin_key = encode_key(&admin_key.verifying_key());
+        let payload = signing_payload(POLICY_DOMAIN, &self)?;
+        Ok(SignedPolicy {
+            signature: sign(&payload, admin_key),
+            policy: self,
+        })
+    }
+}
+
+/// Policy document with the admin's signature, as distributed to managed
+/// devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedPolicy {
+    /// The policy
+    pub policy: PolicyDocument,
+    /// Signature by the admin key over the policy (base64)
+    pub signature: String,
+}
+
+impl SignedPolicy {
+    /// Check the admin's signature
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the key or signature is malformed
+    /// or the signature does not verify
+    pub fn verify(&self) -> Result<()> {
+        let payload = signing_payload(POLICY_DOMAIN, &self.policy)?;
+        verify(&payload, &self.policy.admin_key, &self.signature, "Policy")
+    }
+}
+
+/// Request to lift a policy from managed devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyRemoval {
+    /// Policy to remove
+    pub policy_id: String,
+    /// Unix timestamp the removal was signed at
+    pub issued_at: u64,
+    /// Admin's Ed25519 public key (base64), set when signing
+    #[serde(default)]
+    pub admin_key: String,
+}
+
+impl PolicyRemoval {
+    /// Create an unsigned removal
+    pub fn new(policy_id: &str, issued_at: u64) -> Self {
+        Self {
+            policy_id: policy_id.to_string(),
+            issued_at,
+            admin_key: String::new(),
+        }
+    }
+
+    /// Sign the removal with the admin key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document cannot be encoded
+    pub fn sign(mut self, admin_key: &SigningKey) -> Result<SignedRemoval> {
+        self.admin_key = encode_key(&admin_key.verifying_key());
+        let payload = signing_payload(REMOVAL_DOMAIN, &self)?;
+        Ok(SignedRemoval {
+            signature: sign(&payload, admin_key),
+            removal: self,
+        })
+    }
+}
+
+/// Removal document with the admin's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedRemoval {
+    /// The removal
+    pub removal: PolicyRemoval,
+    /// Signature by the admin key over the removal (base64)
+    pub signature: String,
+}
+
+impl SignedRemoval {
+    /// Check the admin's signature
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the key or signature is malformed
+    /// or the signature does not verify
+    pub fn verify(&self) -> Result<()> {
+        let payload = signing_payload(REMOVAL_DOMAIN, &self.removal)?;
+        verify(
+            &payload,
+            &self.removal.admin_key,
+            &self.signature,
+            "Policy removal",
+        )
+    }
+}
+
+/// Short fingerprint of an admin public key, for display
+pub fn key_fingerprint(admin_key: &str) -> String {
+    blake3::hash(admin_key.as_bytes()).to_hex()[..16].to_string()
+}
+
+fn encode_key(key: &VerifyingKey) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.to_bytes())
+}
+
+fn signing_payload<T: Serialize>(domain: &[u8], document: &T) -> Result<Vec<u8>> {
+    let mut payload = domain.to_vec();
+    payload.extend(canonical_json::to_canonical_vec(document)?);
+    Ok(payload)
+}
+
+fn sign(payload: &[u8], key: &SigningKey) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.sign(payload).to_bytes())
+}
+
+fn verify(payload: &[u8], admin_key: &str, signature: &str, what: &str) -> Result<()> {
+    let crypto_error = |message: &str| OsnovaError::Crypto(format!("{} {}", what, message));
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let public_key: [u8; 32] = engine
+        .decode(admin_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| crypto_error("has a malformed admin key"))?;
+    let signature: [u8; 64] = engine
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| crypto_error("signature is malformed"))?;
+
+    VerifyingKey::from_bytes(&public_key)
+        .map_err(|_| crypto_error("has an invalid admin key"))?
+        .verify(payload, &Signature::from_bytes(&signature))
+        .map_err(|_| crypto_error("signature failed verification"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> PolicyRules {
+        PolicyRules {
+            allow_apps: vec!["com.example.maths".to_string()],
+            allow_publishers: vec!["Trusted Co".to_string()],
+            deny_apps: vec!["com.example.casino".to_string()],
+            deny_publishers: vec!["Shady Ltd".to_string()],
+            block_unsigned: true,
+            wallet_caps: BTreeMap::new(),
+        }
+    }
+
+    fn rule(result: Option<(PolicyRule, String)>) -> Option<PolicyRule> {
+        result.map(|(rule, _)| rule)
+    }
+
+    #[test]
+    fn test_each_app_rule() {
+        let rules = rules();
+        assert_eq!(
+            rule(rules.blocking_rule("com.example.maths", None, true)),
+            None
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.trusted.chess", Some("Trusted Co"), true)),
+            None
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.example.casino", None, true)),
+            Some(PolicyRule::DenyApps)
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.shady.game", Some("Shady Ltd"), true)),
+            Some(PolicyRule::DenyPublishers)
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.example.maths", None, false)),
+            Some(PolicyRule::BlockUnsigned)
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.example.other", Some("Other"), true)),
+            Some(PolicyRule::AllowList)
+        );
+
+        // Without allowlists anything not denied is allowed
+        let open = PolicyRules::default();
+        assert_eq!(
+            rule(open.blocking_rule("com.example.other", None, false)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_denylist_wins_over_allowlist() {
+        let mut rules = rules();
+        rules.allow_apps.push("com.example.casino".to_string());
+        rules.allow_publishers.push("Shady Ltd".to_string());
+
+        assert_eq!(
+            rule(rules.blocking_rule("com.example.casino", None, true)),
+            Some(PolicyRule::DenyApps)
+        );
+        assert_eq!(
+            rule(rules.blocking_rule("com.shady.game", Some("Shady Ltd"), true)),
+            Some(PolicyRule::DenyPublishers)
+        );
+    }
+
+    #[test]
+    fn test_signatures_cover_the_document() -> Result<()> {
+        let identity = RootIdentity::generate()?;
+        let key = admin_signing_key(&identity)?;
+        assert_eq!(
+            key.to_bytes(),
+            admin_signing_key(&identity)?.to_bytes(),
+            "the admin key is derived, not random"
+        );
+
+        let signed = PolicyDocument::new("family", 1, 100, rules()).sign(&key)?;
+        signed.verify()?;
+        let mut changed = signed.clone();
+        changed.policy.rules.block_unsigned = false;
+        assert!(matches!(changed.verify(), Err(OsnovaError::Crypto(_))));
+
+        let removal = PolicyRemoval::new("family", 200).sign(&key)?;
+        removal.verify()?;
+        let mut changed = removal.clone();
+        changed.removal.policy_id = "other".to_string();
+        assert!(changed.verify().is_err());
+
+        // A policy signature is not a removal signature
+        let forged = SignedRemoval {
+            removal: PolicyRemoval {
+                admin_key: signed.policy.admin_key.clone(),
+                ..PolicyRemoval::new("family", 100)
+            },
+            signature: signed.signature.clone(),
+        };
+        assert!(forged.verify().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! # Group Policies Module
+//!
+//! Restrictions a parent or small-org admin places on a managed device.
+//!
+//! This module provides:
+//! - Policy documents with install and launch rules (allow and deny lists
+//!   by app ID or publisher, blocking unsigned apps) and per-app wallet
+//!   caps, signed with an admin key derived from the admin's identity
+//! - Signed removal documents, the only way to lift a policy
+//! - Encrypted storage of the policy in force, verified on every read so
+//!   a modified policy is detected
+//! - The checks enforced by [`AppsService`](crate::services::AppsService)
+//!   on install preview, install and launch, and by
+//!   [`WalletService`](crate::services::WalletService) on payment approval,
+//!   each failing with [`OsnovaError::PolicyBlocked`](crate::OsnovaError::PolicyBlocked)
+//!   naming the rule
+//!
+//! Documents are plain JSON, so they travel over any channel between the
+//! admin's devices and the managed ones; the signature, not the channel,
+//! is what is trusted.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::models::identity::RootIdentity;
+//! use osnova_lib::policies::{
+//!     admin_signing_key, PolicyDocument, PolicyRemoval, PolicyRules, PolicyStore,
+//! };
+//!
+//! # fn main() -> osnova_lib::Result<()> {
+//! let admin_key = admin_signing_key(&RootIdentity::generate()?)?;
+//! let rules = PolicyRules {
+//!     deny_apps: vec!["com.example.casino".to_string()],
+//!     ..PolicyRules::default()
+//! };
+//!
+//! let store = PolicyStore::new("/tmp/osnova", "user-123", &RootIdentity::generate()?)?;
+//! store.apply(&PolicyDocument::new("family", 1, 1_700_000_000, rules).sign(&admin_key)?)?;
+//! store.remove(&PolicyRemoval::new("family", 1_700_100_000).sign(&admin_key)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod document;
+pub mod store;
+
+pub use document::{
+    admin_signing_key, PolicyDocument, PolicyRemoval, PolicyRule, PolicyRules, SignedPolicy,
+    SignedRemoval,
+};
+pub use store::{PolicyStatus, PolicyStore, PolicySummary};
+
+/// Window wallet caps apply to, in seconds
+pub const WALLET_CAP_WINDOW_SECS: u64 = 30 * 24 * 60 * 60;
@@ -0,0 +1,495 @@
+//! The policy in force on a managed device
+//!
+//! The signed policy is kept encrypted under a key derived from the user's
+//! identity, and its signature is checked every time it is read, so a
+//! stored policy that was modified is refused rather than enforced as
+//! modified. While it does not verify, every check fails: a managed device
+//! is never opened up by damaging its policy.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::document::{key_fingerprint, PolicyRules, SignedPolicy, SignedRemoval};
+use crate::audit::{AuditAction, AuditLog};
+use crate::context::{GuestGate, GuestRestriction};
+use crate::error::{OsnovaError, Result};
+use crate::models::application::OsnovaApplication;
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::storage::classification::DataClass;
+use crate::storage::FileStorage;
+
+/// Component the policy encryption key is derived for, per user
+const POLICY_KEY_COMPONENT: &str = "osnova-policies";
+
+/// Data class of the stored policy
+pub const POLICY_CLASS: DataClass = DataClass::Preferences;
+
+/// Active policy, as shown in settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicySummary {
+    /// Policy identifier
+    pub policy_id: String,
+    /// Policy update number
+    pub sequence: u64,
+    /// Unix timestamp the admin signed the policy at
+    pub issued_at: u64,
+    /// Fingerprint of the admin key that signed it
+    pub admin_fingerprint: String,
+    /// Rules in force
+    pub rules: PolicyRules,
+}
+
+/// Whether a device is managed, for the settings screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum PolicyStatus {
+    /// No policy applies
+    Unmanaged,
+    /// A policy is enforced
+    Active(PolicySummary),
+    /// The stored policy was modified or cannot be read; every install,
+    /// launch and payment is refused until the admin applies or removes a
+    /// policy
+    Tampered {
+        /// What failed
+        reason: String,
+    },
+}
+
+/// Stored policy of a managed device, and the checks it enforces
+///
+/// Provides OpenRPC methods:
+/// - `policies.apply` - Apply a policy signed by the admin
+/// - `policies.remove` - Lift the policy with a removal signed by the admin
+/// - `policies.summary` - The policy in force, for settings
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::identity::RootIdentity;
+/// use osnova_lib::policies::{admin_signing_key, PolicyDocument, PolicyRules, PolicyStore};
+///
+/// # fn main() -> osnova_lib::Result<()> {
+/// // On the admin's device
+/// let admin = RootIdentity::generate()?;
+/// let rules = PolicyRules {
+///     block_unsigned: true,
+///     ..PolicyRules::default()
+/// };
+/// let signed = PolicyDocument::new("family", 1, 1_700_000_000, rules)
+///     .sign(&admin_signing_key(&admin)?)?;
+///
+/// // On the managed device, once the document arrives
+/// let identity = RootIdentity::generate()?;
+/// let store = PolicyStore::new("/tmp/osnova", "user-123", &identity)?;
+/// store.apply(&signed)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PolicyStore {
+    files: FileStorage,
+    path: PathBuf,
+    key: [u8; 32],
+    audit: Option<Arc<AuditLog>>,
+    guest: GuestGate,
+    lock: Mutex<()>,
+}
+
+impl PolicyStore {
+    /// Create the policy store of a user
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User the policy applies to
+    /// * `identity` - The user's identity, which the encryption key is
+    ///   derived from
+    pub fn new<P: AsRef<Path>>(
+        storage_path: P,
+        user_id: &str,
+        identity: &RootIdentity,
+    ) -> Result<Self> {
+        let component = format!("{}:{}", POLICY_KEY_COMPONENT, user_id);
+        let key = identity.derive_component_key(&component, 0, KeyPurpose::Encryption)?;
+        let files = FileStorage::new(storage_path.as_ref())
+            .map_err(|e| OsnovaError::Storage(format!("{:#}", e)))?;
+
+        Ok(Self {
+            files,
+            path: Path::new("policies").join(user_id).join("policy.json"),
+            key,
+            audit: None,
+            guest: GuestGate::default(),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Record applied and removed policies in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Refuse policy changes while a guest session runs
+    pub fn with_guest_gate(mut self, guest: GuestGate) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Apply a policy signed by the admin (OpenRPC: policies.apply)
+    ///
+    /// A device under a policy only takes updates signed by the same admin
+    /// key, with a higher sequence number when they update the same policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the signature does not verify,
+    /// or an error if another admin manages the device or the policy is
+    /// not newer than the one in force, and
+    /// [`OsnovaError::GuestModeRestricted`] while a guest session runs
+    pub fn apply(&self, signed: &SignedPolicy) -> Result<PolicySummary> {
+        self.guest.require_host(GuestRestriction::PolicyChange)?;
+        signed.verify()?;
+        let policy = &signed.policy;
+
+        let _guard = self.lock.lock().unwrap();
+        if let Some(current) = self.stored()? {
+            let current = &current.policy;
+            if current.admin_key != policy.admin_key {
+                return Err(OsnovaError::PermissionDenied(format!(
+                    "Policy {} is signed by another admin than policy {} in force",
+                    policy.policy_id, current.policy_id
+                )));
+            }
+            if current.policy_id == policy.policy_id && policy.sequence <= current.sequence {
+                return Err(OsnovaError::Other(format!(
+                    "Policy {} update {} is not newer than update {} in force",
+                    policy.policy_id, policy.sequence, current.sequence
+                )));
+            }
+        }
+
+        let bytes = serde_json::to_vec(signed)?;
+        self.files
+            .write_classified(&self.path, &bytes, &self.key, POLICY_CLASS)
+            .map_err(|e| OsnovaError::Storage(format!("{:#}", e)))?;
+        if let Some(audit) = &self.audit {
+            audit.append(
+                AuditAction::PolicyApplied,
+                json!({
+                    "policyId": policy.policy_id,
+                    "sequence": policy.sequence,
+                    "admin": key_fingerprint(&policy.admin_key),
+                }),
+            )?;
+        }
+        Ok(Self::summary_of(signed))
+    }
+
+    /// Lift the policy in force with a removal signed by its admin
+    /// (OpenRPC: policies.remove)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the signature does not verify,
+    /// or an error if no such policy is in force or another admin signed
+    /// the removal
+    pub fn remove(&self, removal: &SignedRemoval) -> Result<()> {
+        self.guest.require_host(GuestRestriction::PolicyChange)?;
+        removal.verify()?;
+        let removal = &removal.removal;
+
+        let _guard = self.lock.lock().unwrap();
+        let Some(current) = self.stored()? else {
+            return Err(OsnovaError::Other("No policy is in force".to_string()));
+        };
+        let current = &current.policy;
+        if current.policy_id != removal.policy_id {
+            return Err(OsnovaError::Other(format!(
+                "Policy {} is in force, not {}",
+                current.policy_id, removal.policy_id
+            )));
+        }
+        if current.admin_key != removal.admin_key {
+            return Err(OsnovaError::PermissionDenied(format!(
+                "Removal of policy {} is not signed by its admin",
+                removal.policy_id
+            )));
+        }
+        // A removal signed before the policy in force cannot lift it
+        if removal.issued_at < current.issued_at {
+            return Err(OsnovaError::Other(format!(
+                "Removal of policy {} predates the update in force",
+                removal.policy_id
+            )));
+        }
+
+        self.files
+            .delete(&self.path)
+            .map_err(|e| OsnovaError::Storage(format!("{:#}", e)))?;
+        if let Some(audit) = &self.audit {
+            audit.append(
+                AuditAction::PolicyRemoved,
+                json!({
+                    "policyId": removal.policy_id,
+                    "admin": key_fingerprint(&removal.admin_key),
+                }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The policy in force, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Crypto`] if the stored policy was modified
+    /// or cannot be read
+    pub fn active(&self) -> Result<Option<SignedPolicy>> {
+        let stored = self.stored()?;
+        if let Some(signed) = &stored {
+            signed.verify().map_err(|e| {
+                OsnovaError::Crypto(format!("Stored policy failed verification: {}", e))
+            })?;
+        }
+        Ok(stored)
+    }
+
+    /// The policy in force, for settings (OpenRPC: policies.summary)
+    pub fn summary(&self) -> PolicyStatus {
+        match self.active() {
+            Ok(None) => PolicyStatus::Unmanaged,
+            Ok(Some(signed)) => PolicyStatus::Active(Self::summary_of(&signed)),
+            Err(e) => PolicyStatus::Tampered {
+                reason: e.to_string(),
+            },
+        }
+    }
+
+    /// Refuse an app the policy blocks from being installed or launched
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PolicyBlocked`] naming the rule, or
+    /// [`OsnovaError::Crypto`] if the stored policy does not verify
+    pub fn check_app(&self, app: &OsnovaApplication) -> Result<()> {
+        let Some(signed) = self.active()? else {
+            return Ok(());
+        };
+        match signed.policy.rules.blocking_rule_for(app) {
+            Some((rule, reason)) => Err(OsnovaError::PolicyBlocked {
+                policy_id: signed.policy.policy_id,
+                rule,
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuse a payment that would take an app over its wallet cap
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - App the payment is for
+    /// * `spent` - What the app spent in the cap window, in AttoTokens
+    /// * `amount` - Payment to approve, in AttoTokens
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PolicyBlocked`] naming the wallet cap rule,
+    /// or [`OsnovaError::Crypto`] if the stored policy does not verify
+    pub fn check_payment(&self, app_id: &str, spent: u64, amount: u64) -> Result<()> {
+        let Some(signed) = self.active()? else {
+            return Ok(());
+        };
+        let Some(&cap) = signed.policy.rules.wallet_caps.get(app_id) else {
+            return Ok(());
+        };
+        if spent.saturating_add(amount) > cap {
+            return Err(OsnovaError::PolicyBlocked {
+                policy_id: signed.policy.policy_id,
+                rule: super::PolicyRule::WalletCap,
+                reason: format!(
+                    "paying {} AttoTokens would take {} to {} of its {} AttoTokens cap",
+                    amount,
+                    app_id,
+                    spent.saturating_add(amount),
+                    cap
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// The stored policy, without checking its signature
+    fn stored(&self) -> Result<Option<SignedPolicy>> {
+        if !self.files.exists(&self.path) {
+            return Ok(None);
+        }
+        let bytes = self
+            .files
+            .read(&self.path, &self.key)
+            .map_err(|e| OsnovaError::Crypto(format!("Stored policy cannot be read: {:#}", e)))?;
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    fn summary_of(signed: &SignedPolicy) -> PolicySummary {
+        let policy = &signed.policy;
+        PolicySummary {
+            policy_id: policy.policy_id.clone(),
+            sequence: policy.sequence,
+            issued_at: policy.issued_at,
+            admin_fingerprint: key_fingerprint(&policy.admin_key),
+            rules: policy.rules.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::policies::{admin_signing_key, PolicyDocument, PolicyRemoval, PolicyRule};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn rules() -> PolicyRules {
+        PolicyRules {
+            deny_apps: vec!["com.example.casino".to_string()],
+            wallet_caps: BTreeMap::from([("com.example.photos".to_string(), 1_000)]),
+            ..PolicyRules::default()
+        }
+    }
+
+    fn app(id: &str) -> OsnovaApplication {
+        OsnovaApplication::new(id, "App", "1.0.0", "https://icon.url", "App", vec![]).unwrap()
+    }
+
+    fn blocked_rule(result: Result<()>) -> PolicyRule {
+        match result {
+            Err(OsnovaError::PolicyBlocked { rule, .. }) => rule,
+            other => panic!("Expected a policy block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_apply_enforce_and_remove() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let identity = RootIdentity::generate()?;
+        let audit = Arc::new(AuditLog::new(dir.path(), &identity, "user")?);
+        let store = PolicyStore::new(dir.path(), "user", &identity)?.with_audit(audit.clone());
+        let admin = admin_signing_key(&RootIdentity::generate()?)?;
+        assert_eq!(store.summary(), PolicyStatus::Unmanaged);
+
+        let summary = store.apply(&PolicyDocument::new("family", 1, 100, rules()).sign(&admin)?)?;
+        assert_eq!(summary.policy_id, "family");
+        assert!(matches!(store.summary(), PolicyStatus::Active(_)));
+        store.check_app(&app("com.example.maths"))?;
+        assert_eq!(
+            blocked_rule(store.check_app(&app("com.example.casino"))),
+            PolicyRule::DenyApps
+        );
+
+        // Wallet caps count what was spent already
+        store.check_payment("com.example.photos", 600, 400)?;
+        assert_eq!(
+            blocked_rule(store.check_payment("com.example.photos", 600, 401)),
+            PolicyRule::WalletCap
+        );
+        store.check_payment("com.example.uncapped", 600, 1_000_000)?;
+
+        // Survives a restart
+        let reopened = PolicyStore::new(dir.path(), "user", &identity)?;
+        assert_eq!(reopened.summary(), store.summary());
+
+        store.remove(&PolicyRemoval::new("family", 200).sign(&admin)?)?;
+        assert_eq!(store.summary(), PolicyStatus::Unmanaged);
+        store.check_app(&app("com.example.casino"))?;
+
+        let actions: Vec<AuditAction> = audit
+            .list(&AuditFilter::default(), PageRequest::default())?
+            .entries
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert!(actions.contains(&AuditAction::PolicyApplied));
+        assert!(actions.contains(&AuditAction::PolicyRemoved));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_and_remove_require_the_admin_signature() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let store = PolicyStore::new(dir.path(), "user", &RootIdentity::generate()?)?;
+        let admin = admin_signing_key(&RootIdentity::generate()?)?;
+        let other = admin_signing_key(&RootIdentity::generate()?)?;
+
+        let mut forged = PolicyDocument::new("family", 1, 100, rules()).sign(&admin)?;
+        forged.policy.rules.deny_apps.clear();
+        assert!(matches!(store.apply(&forged), Err(OsnovaError::Crypto(_))));
+        assert_eq!(store.summary(), PolicyStatus::Unmanaged);
+
+        store.apply(&PolicyDocument::new("family", 1, 100, rules()).sign(&admin)?)?;
+
+        // Another admin can neither replace nor remove the policy
+        let takeover =
+            PolicyDocument::new("family", 2, 150, PolicyRules::default()).sign(&other)?;
+        assert!(store.apply(&takeover).is_err());
+        assert!(store
+            .remove(&PolicyRemoval::new("family", 200).sign(&other)?)
+            .is_err());
+
+        // Nor can a removal that was modified after signing, or an old update
+        let mut removal = PolicyRemoval::new("family", 200).sign(&admin)?;
+        removal.removal.issued_at += 1;
+        assert!(matches!(
+            store.remove(&removal),
+            Err(OsnovaError::Crypto(_))
+        ));
+        let replayed =
+            PolicyDocument::new("family", 1, 100, PolicyRules::default()).sign(&admin)?;
+        assert!(store.apply(&replayed).is_err());
+        assert!(matches!(store.summary(), PolicyStatus::Active(_)));
+
+        store
+            .apply(&PolicyDocument::new("family", 2, 150, PolicyRules::default()).sign(&admin)?)?;
+        store.check_app(&app("com.example.casino"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_stored_policy_is_detected() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let store = PolicyStore::new(dir.path(), "user", &RootIdentity::generate()?)?;
+        let admin = admin_signing_key(&RootIdentity::generate()?)?;
+        store.apply(&PolicyDocument::new("family", 1, 100, rules()).sign(&admin)?)?;
+
+        // Someone with the storage key lifts the denylist in place
+        let mut stored = store.stored()?.unwrap();
+        stored.policy.rules.deny_apps.clear();
+        store
+            .files
+            .write(&store.path, &serde_json::to_vec(&stored)?, &store.key)
+            .unwrap();
+
+        assert!(matches!(store.summary(), PolicyStatus::Tampered { .. }));
+        // Fails closed: nothing passes while the policy does not verify
+        assert!(matches!(
+            store.check_app(&app("com.example.maths")),
+            Err(OsnovaError::Crypto(_))
+        ));
+        assert!(store.check_payment("com.example.other", 0, 1).is_err());
+
+        // The admin can put a good policy back
+        store.apply(&PolicyDocument::new("family", 2, 200, rules()).sign(&admin)?)?;
+        assert_eq!(
+            blocked_rule(store.check_app(&app("com.example.casino"))),
+            PolicyRule::DenyApps
+        );
+        Ok(())
+    }
+}
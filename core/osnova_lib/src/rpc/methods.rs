@@ -0,0 +1,1162 @@
+//! The core service methods, as registered for the OpenRPC document
+//!
+//! Parameters mirror the service method arguments, in camelCase. A method
+//! documented with an `(OpenRPC: name)` marker in a service must be
+//! registered here; the tests check this against the sources.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use super::registry::MethodRegistry;
+use crate::audit::PageRequest;
+use crate::components::integrity::VerifyReport;
+use crate::context::guest::GuestSessionInfo;
+use crate::logs::{LogFilter, LogRecord};
+use crate::manifest::launcher::SourcedCatalog;
+use crate::manifest::reproducibility::ReproVerdict;
+use crate::models::annotation::{Annotation, Subject};
+use crate::models::config_cache::{AppCache, AppConfiguration};
+use crate::models::config_snapshot::{ConfigSnapshot, RestoreMode};
+use crate::models::consent::AppConsent;
+use crate::models::crash_report::CrashReport;
+use crate::models::health::HealthTransition;
+use crate::models::key_cocoon::KeyType;
+use crate::models::launcher_change::ChangeSummary;
+use crate::models::launcher_layout::MergeReport;
+use crate::models::materialization::Materialization;
+use crate::models::mutation::{MutationReceipt, Since};
+use crate::models::notification::{Notification, StoredNotification};
+use crate::models::pairing::PairingStatus;
+use crate::models::permission::{Capability, PermissionGrant};
+use crate::models::privacy::PrivacyReport;
+use crate::models::provenance::ProvenanceRecord;
+use crate::models::retention::{Policy, RetentionStore, StoreFootprint};
+use crate::models::session::RemoteSession;
+use crate::models::settings_schema::SettingsSchema;
+use crate::models::sharing::SharedDataGrant;
+use crate::models::task::TaskInfo;
+use crate::network::retry_budget::RetryBudgetMetrics;
+use crate::platform::auth::AuthProof;
+use crate::policies::{PolicyStatus, PolicySummary, SignedPolicy, SignedRemoval};
+use crate::services::apps::{
+    AppInfo, AppListItem, AppStatusItem, BatchInstallPreview, BatchInstallReport, BatchOptions,
+    ConsentReview, FrontendEntry, HandlerInfo, InstallRequest, MaterializeOutcome,
+    MaterializePolicy, SymbolicatedReport, UriSchemeHandlers,
+};
+use crate::services::badges::BadgeState;
+use crate::services::config::SettingsPayload;
+use crate::services::config_cache::ConfigCacheMetrics;
+use crate::services::features::FlagValue;
+use crate::services::handoff::{Handoff, HandoffPayload, SentHandoff};
+use crate::services::handshake::{LaunchDescriptor, ReadinessState};
+use crate::services::identity::{IdentityStatus, RecoveryReport};
+use crate::services::key_usage::{KeyUsageReport, UsageWindow};
+use crate::services::keys::{KeyDerivationResponse, KeyInfo, SecretKeyResponse};
+use crate::services::launcher::{LauncherLayout, LauncherMutation};
+use crate::services::metadata::MetadataRefresh;
+use crate::services::migration::{
+    ExportBatch, ExportOffer, ExportStreamRequest, MigrationMarker, MigrationReceipt,
+};
+use crate::services::notifications::{NotificationFilter, PostOutcome};
+use crate::services::reauth::{ReauthStatus, SensitiveOperation};
+use crate::services::retention::RetentionRun;
+use crate::services::security::AuditReport;
+use crate::services::status::{DiskHealth, NetworkStatus, ServerStatusResponse};
+use crate::services::storage::VolumeSpace;
+use crate::services::updates::UpdateCheck;
+use crate::services::wallet::{ExportFormat, HistoryFilter, PaymentHistory};
+use crate::services::{BottomMenuTab, Theme};
+use crate::storage::CompactReport;
+use crate::util::format::Locale;
+
+/// Register every core service method
+pub fn register_core_methods(registry: &mut MethodRegistry) {
+    register_identity(registry);
+    register_keys(registry);
+    register_config(registry);
+    register_apps(registry);
+    register_components(registry);
+    register_launcher(registry);
+    register_ui(registry);
+    register_status(registry);
+    register_notifications(registry);
+    register_permissions(registry);
+    register_auth(registry);
+    register_sharing(registry);
+    register_sessions(registry);
+    register_pairing(registry);
+    register_handoff(registry);
+    register_migration(registry);
+    register_provenance(registry);
+    register_storage(registry);
+    register_retention(registry);
+    register_logs(registry);
+    register_wallet(registry);
+    register_tasks(registry);
+    register_annotations(registry);
+    register_policies(registry);
+    register_features(registry);
+}
+
+fn register_identity(registry: &mut MethodRegistry) {
+    registry
+        .register("identity.status", "Check identity status")
+        .result::<IdentityStatus>("status");
+    registry
+        .register(
+            "identity.create",
+            "Create a new identity, returning the seed phrase and address",
+        )
+        .result::<(String, String)>("identity");
+    registry
+        .register(
+            "identity.importWithPhrase",
+            "Import identity from seed phrase",
+        )
+        .param::<String>("seedPhrase")
+        .result::<String>("address");
+    registry
+        .register(
+            "identity.recoverWithPhrase",
+            "Reseal an identity whose keystore key was lost, after checking its seed phrase",
+        )
+        .param::<String>("seedPhrase")
+        .result::<RecoveryReport>("report");
+}
+
+fn register_keys(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "keys.derive",
+            "Derive a new key at the next available index",
+        )
+        .param::<String>("componentId")
+        .param::<KeyType>("keyType")
+        .result::<KeyDerivationResponse>("key");
+    registry
+        .register(
+            "keys.deriveAtIndex",
+            "Derive or retrieve a key at a specific index",
+        )
+        .param::<String>("componentId")
+        .param::<u64>("index")
+        .param::<KeyType>("keyType")
+        .result::<KeyDerivationResponse>("key");
+    registry
+        .register(
+            "keys.deriveBatch",
+            "Derive or retrieve a contiguous range of keys",
+        )
+        .param::<String>("componentId")
+        .param::<u64>("startIndex")
+        .param::<u64>("count")
+        .param::<KeyType>("keyType")
+        .result::<Vec<KeyDerivationResponse>>("keys");
+    registry
+        .register("keys.getByPublicKey", "Retrieve secret key by public key")
+        .param::<String>("publicKey")
+        .result::<SecretKeyResponse>("key");
+    registry
+        .register("keys.listForComponent", "List all keys for a component")
+        .param::<String>("componentId")
+        .result::<Vec<KeyInfo>>("keys");
+    registry
+        .register(
+            "keys.usageReport",
+            "Operations made with a component's keys",
+        )
+        .param::<String>("componentId")
+        .param::<UsageWindow>("window")
+        .result::<KeyUsageReport>("report");
+}
+
+fn register_config(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "config.getLauncherManifest",
+            "Get the configured launcher manifest address",
+        )
+        .result::<Option<String>>("manifestAddress");
+    registry
+        .register(
+            "config.setLauncherManifest",
+            "Set the launcher manifest address",
+        )
+        .param::<String>("manifestAddress")
+        .result::<()>("ok");
+    registry
+        .register(
+            "config.setServer",
+            "Configure server address for Client-Server mode",
+        )
+        .param::<String>("serverAddress")
+        .result::<()>("ok");
+    registry
+        .register("config.getAppConfig", "Get per-app configuration data")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .result::<AppConfiguration>("config");
+    registry
+        .register("config.setAppConfig", "Update per-app configuration data")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<HashMap<String, Value>>("settings")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register(
+            "config.mutateAppConfig",
+            "Update per-app configuration at most once per mutation id",
+        )
+        .param::<String>("mutationId")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<HashMap<String, Value>>("settings")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register(
+            "config.getSince",
+            "Per-app configuration, only if it changed since a generation",
+        )
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<u64>("generation")
+        .result::<Since<AppConfiguration>>("since");
+    registry
+        .register(
+            "config.cacheMetrics",
+            "Hit and miss counters of the per-app configuration cache",
+        )
+        .result::<ConfigCacheMetrics>("metrics");
+    registry
+        .register("config.getAppCache", "Get per-app cache metadata")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .result::<Option<AppCache>>("cache");
+    registry
+        .register("config.clearAppCache", "Clear cache for a specific app")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .result::<()>("ok");
+    registry
+        .register("config.settingsSchema", "Get the settings an app declares")
+        .param::<String>("appId")
+        .result::<Option<SettingsSchema>>("schema");
+    registry
+        .register(
+            "config.getSettings",
+            "Get an app's settings schema with the current values",
+        )
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .result::<SettingsPayload>("settings");
+    registry
+        .register(
+            "config.setAppSecret",
+            "Set a secret setting of an app; it cannot be read back",
+        )
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<String>("key")
+        .param::<Value>("value")
+        .result::<()>("ok");
+    registry
+        .register("config.clearAppSecret", "Clear a secret setting of an app")
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<String>("key")
+        .result::<bool>("cleared");
+    registry
+        .register(
+            "config.listSnapshots",
+            "Snapshots of an app's configuration, newest first",
+        )
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .result::<Vec<ConfigSnapshot>>("snapshots");
+    registry
+        .register(
+            "config.restoreSnapshot",
+            "Restore an app's configuration from a snapshot, returning the snapshot that undoes it",
+        )
+        .param::<String>("appId")
+        .param::<String>("userId")
+        .param::<i64>("snapshotId")
+        .param::<RestoreMode>("mode")
+        .result::<ConfigSnapshot>("undo");
+    registry
+        .register(
+            "config.snapshotUsage",
+            "Bytes of configuration snapshots stored per app",
+        )
+        .param::<String>("userId")
+        .result::<BTreeMap<String, u64>>("usage");
+}
+
+fn register_apps(registry: &mut MethodRegistry) {
+    registry
+        .register("apps.list", "List all installed applications")
+        .result::<Vec<AppListItem>>("apps");
+    registry
+        .register("apps.info", "Get details of an installed application")
+        .param::<String>("appId")
+        .result::<AppInfo>("info");
+    registry
+        .register("apps.launch", "Launch an application by ID")
+        .param::<String>("appId")
+        .result::<()>("ok");
+    registry
+        .register(
+            "apps.close",
+            "Close an application, keeping its backends warm if the warm pool allows",
+        )
+        .param::<String>("appId")
+        .result::<bool>("warm");
+    registry
+        .register(
+            "apps.consentReview",
+            "What the user should review before launching an application",
+        )
+        .param::<String>("appId")
+        .result::<ConsentReview>("review");
+    registry
+        .register(
+            "apps.recordConsent",
+            "Record whether the user agrees to run an application",
+        )
+        .param::<String>("appId")
+        .param::<bool>("accepted")
+        .param::<Vec<String>>("acknowledgedWarnings")
+        .result::<AppConsent>("consent");
+    registry
+        .register(
+            "apps.verify",
+            "Verify the integrity of an installed application",
+        )
+        .param::<String>("appId")
+        .result::<VerifyReport>("report");
+    registry
+        .register("apps.repair", "Repair an installed application")
+        .param::<String>("appId")
+        .result::<VerifyReport>("report");
+    registry
+        .register(
+            "apps.materialization",
+            "Check which of an installed app's components are cached",
+        )
+        .param::<String>("appId")
+        .result::<Materialization>("state");
+    registry
+        .register(
+            "apps.materialize",
+            "Download an installed app's missing components",
+        )
+        .param::<String>("appId")
+        .result::<MaterializeOutcome>("outcome");
+    registry
+        .register(
+            "apps.materializeAll",
+            "Download the missing components of every installed app",
+        )
+        .param::<MaterializePolicy>("policy")
+        .result::<Vec<MaterializeOutcome>>("outcomes");
+    registry
+        .register(
+            "apps.previewInstallMany",
+            "Download size and permissions of a list of apps to install",
+        )
+        .param::<Vec<InstallRequest>>("requests")
+        .result::<BatchInstallPreview>("preview");
+    registry
+        .register(
+            "apps.installMany",
+            "Install a list of apps, reporting the outcome of each",
+        )
+        .param::<Vec<InstallRequest>>("requests")
+        .param::<BatchOptions>("options")
+        .result::<BatchInstallReport>("report");
+    registry
+        .register(
+            "apps.install",
+            "Install a new application from manifest URI",
+        )
+        .param::<String>("manifestUri")
+        .result::<()>("ok");
+    registry
+        .register("apps.uninstall", "Uninstall an application")
+        .param::<String>("appId")
+        .optional_param::<Option<u32>>("keepDataDays")
+        .result::<()>("ok");
+    registry
+        .register("apps.restore", "Restore a recently uninstalled application")
+        .param::<String>("appId")
+        .result::<()>("ok");
+    registry
+        .register("apps.listTrash", "List recently uninstalled applications")
+        .result::<Vec<AppStatusItem>>("apps");
+    registry
+        .register("apps.badges", "Icon badge of every installed application")
+        .result::<HashMap<String, BadgeState>>("badges");
+    registry
+        .register(
+            "apps.listUriHandlers",
+            "URI schemes claimed by installed apps",
+        )
+        .result::<Vec<UriSchemeHandlers>>("handlers");
+    registry
+        .register(
+            "apps.setUriHandler",
+            "Choose which app handles a URI scheme",
+        )
+        .param::<String>("scheme")
+        .optional_param::<Option<String>>("appId")
+        .result::<()>("ok");
+    registry
+        .register(
+            "apps.checkUpdates",
+            "List installed apps with a newer published version",
+        )
+        .result::<UpdateCheck>("updates");
+    registry
+        .register("apps.applyUpdate", "Install a downloaded update")
+        .param::<String>("appId")
+        .result::<String>("version");
+    registry
+        .register(
+            "apps.rollback",
+            "Restore the version the last update replaced",
+        )
+        .param::<String>("appId")
+        .result::<String>("version");
+    registry
+        .register("apps.pin", "Hold an app at its installed version")
+        .param::<String>("appId")
+        .param::<String>("version")
+        .result::<()>("ok");
+    registry
+        .register("apps.unpin", "Let a pinned app update again")
+        .param::<String>("appId")
+        .result::<()>("ok");
+    registry
+        .register(
+            "apps.refreshMetadata",
+            "Update an app's name, icon and description from its manifest",
+        )
+        .param::<String>("appId")
+        .result::<MetadataRefresh>("refresh");
+    registry
+        .register(
+            "apps.launchDescriptor",
+            "Backends of a launched app and their readiness",
+        )
+        .param::<String>("appId")
+        .result::<Option<LaunchDescriptor>>("descriptor");
+    registry
+        .register(
+            "apps.frontendEntry",
+            "Entry page of an app's frontend, for launch and asset serving",
+        )
+        .param::<String>("appId")
+        .result::<Option<FrontendEntry>>("entry");
+    registry
+        .register(
+            "apps.crashReports",
+            "Crash reports of an app's backends, newest first",
+        )
+        .param::<String>("appId")
+        .result::<Vec<CrashReport>>("reports");
+    registry
+        .register(
+            "apps.healthHistory",
+            "Health transitions of an app's backends, newest first",
+        )
+        .param::<String>("appId")
+        .result::<Vec<HealthTransition>>("transitions");
+    registry
+        .register(
+            "apps.verifyReproducible",
+            "Compare a local build of an installed component with its manifest hash",
+        )
+        .param::<String>("appId")
+        .param::<String>("componentId")
+        .param::<String>("localArtifactPath")
+        .result::<ReproVerdict>("verdict");
+    registry
+        .register(
+            "apps.privacyReport",
+            "Declared and observed network access of an app's backends",
+        )
+        .param::<String>("appId")
+        .result::<PrivacyReport>("report");
+    registry
+        .register(
+            "apps.symbolicate",
+            "Resolve the native backtrace in a crash report against debug symbols",
+        )
+        .param::<String>("reportId")
+        .result::<SymbolicatedReport>("report");
+}
+
+fn register_components(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "component.ready",
+            "Report a launched backend ready to accept requests",
+        )
+        .param::<String>("componentId")
+        .param::<String>("version")
+        .optional_param::<Vec<String>>("capabilities")
+        .result::<ReadinessState>("state");
+}
+
+fn register_launcher(registry: &mut MethodRegistry) {
+    registry
+        .register("launcher.getLayout", "Get the current launcher layout")
+        .result::<LauncherLayout>("layout");
+    registry
+        .register("launcher.setLayout", "Set the launcher layout")
+        .param::<Vec<String>>("appIds")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register(
+            "launcher.mutate",
+            "Apply a layout edit at most once per mutation id",
+        )
+        .param::<String>("mutationId")
+        .param::<LauncherMutation>("mutation")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register(
+            "launcher.getSince",
+            "The launcher layout, only if the launcher changed since a generation",
+        )
+        .param::<u64>("generation")
+        .result::<Since<LauncherLayout>>("since");
+    registry
+        .register(
+            "launcher.generation",
+            "Generation of the latest change to the launcher grid",
+        )
+        .result::<u64>("generation");
+    registry
+        .register(
+            "launcher.changesSince",
+            "Apps added, removed or updated, and whether the layout changed, since a generation",
+        )
+        .param::<u64>("generation")
+        .result::<ChangeSummary>("changes");
+    registry
+        .register(
+            "launcher.setFolder",
+            "Put an app in a folder, or take it out",
+        )
+        .param::<String>("appId")
+        .optional_param::<Option<String>>("folder")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register("launcher.setPinned", "Pin or unpin an app")
+        .param::<String>("appId")
+        .param::<bool>("pinned")
+        .result::<MutationReceipt>("receipt");
+    registry
+        .register(
+            "launcher.mergeLayout",
+            "Merge the launcher layout from another of the user's devices",
+        )
+        .param::<LauncherLayout>("layout")
+        .result::<MergeReport>("report");
+    registry
+        .register("launcher.getCatalog", "Catalog to show")
+        .optional_param::<Option<String>>("manifestUri")
+        .result::<SourcedCatalog>("catalog");
+}
+
+fn register_ui(registry: &mut MethodRegistry) {
+    registry
+        .register("ui.getTheme", "Get the current theme setting")
+        .result::<Theme>("theme");
+    registry
+        .register("ui.setTheme", "Set the theme")
+        .param::<Theme>("theme")
+        .result::<()>("ok");
+    registry
+        .register("ui.getLocale", "Get the locale of formatted display fields")
+        .result::<Locale>("locale");
+    registry
+        .register("ui.setLocale", "Set the locale of formatted display fields")
+        .param::<Locale>("locale")
+        .result::<()>("ok");
+    registry
+        .register(
+            "ui.state.save",
+            "Save an app's UI state snapshot, base64-encoded",
+        )
+        .param::<String>("appId")
+        .param::<String>("snapshot")
+        .result::<()>("ok");
+    registry
+        .register(
+            "ui.state.load",
+            "An app's UI state snapshot, base64-encoded; null if none is kept",
+        )
+        .param::<String>("appId")
+        .result::<Option<String>>("snapshot");
+    registry
+        .register("ui.state.clear", "Remove an app's UI state snapshot")
+        .param::<String>("appId")
+        .result::<bool>("cleared");
+    registry
+        .register("ui.state.usage", "Bytes of UI state stored per app")
+        .result::<BTreeMap<String, u64>>("usage");
+    registry
+        .register(
+            "navigation.getBottomMenu",
+            "Get the current bottom menu tab",
+        )
+        .result::<BottomMenuTab>("tab");
+    registry
+        .register("navigation.setBottomMenu", "Set the bottom menu tab")
+        .param::<BottomMenuTab>("tab")
+        .result::<()>("ok");
+}
+
+fn register_status(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "status.getServer",
+            "Get the current server connection status",
+        )
+        .result::<ServerStatusResponse>("status");
+    registry
+        .register("status.getDiskHealth", "Free disk space on the data volume")
+        .result::<Option<DiskHealth>>("health");
+    registry
+        .register(
+            "status.getNetwork",
+            "Whether the user switched Osnova offline",
+        )
+        .result::<NetworkStatus>("network");
+    registry
+        .register(
+            "status.getRetryBudgets",
+            "Retry budget and circuit of each network destination",
+        )
+        .result::<Vec<RetryBudgetMetrics>>("budgets");
+    registry
+        .register(
+            "status.getGuestSession",
+            "The guest session running, if any",
+        )
+        .result::<Option<GuestSessionInfo>>("session");
+    registry
+        .register("security.audit", "At-rest encryption audit")
+        .result::<AuditReport>("report");
+}
+
+fn register_notifications(registry: &mut MethodRegistry) {
+    registry
+        .register("notifications.post", "Post a notification from an app")
+        .param::<String>("appId")
+        .param::<Notification>("notification")
+        .result::<PostOutcome>("outcome");
+    registry
+        .register("notifications.list", "List notifications, newest first")
+        .param::<NotificationFilter>("filter")
+        .result::<Vec<StoredNotification>>("notifications");
+    registry
+        .register("notifications.markRead", "Mark notifications read")
+        .param::<Vec<i64>>("ids")
+        .result::<usize>("updated");
+    registry
+        .register(
+            "notifications.clear",
+            "Remove every notification from an app",
+        )
+        .param::<String>("appId")
+        .result::<usize>("removed");
+    registry
+        .register(
+            "notifications.setEnabled",
+            "Turn an app's notifications on or off",
+        )
+        .param::<String>("appId")
+        .param::<bool>("enabled")
+        .result::<()>("ok");
+}
+
+fn register_permissions(registry: &mut MethodRegistry) {
+    registry
+        .register("permissions.request", "Ask the user for a capability")
+        .param::<String>("appId")
+        .param::<String>("componentId")
+        .param::<Capability>("capability")
+        .param::<String>("rationale")
+        .result::<()>("ok");
+    registry
+        .register("permissions.respond", "Answer a pending prompt")
+        .param::<String>("promptId")
+        .param::<bool>("granted")
+        .param::<bool>("remember")
+        .optional_param::<Option<AuthProof>>("proof")
+        .result::<()>("ok");
+    registry
+        .register(
+            "permissions.list",
+            "List remembered decisions, optionally for one app",
+        )
+        .optional_param::<Option<String>>("appId")
+        .result::<Vec<PermissionGrant>>("grants");
+    registry
+        .register(
+            "permissions.revoke",
+            "Forget a remembered decision, so the next request prompts again",
+        )
+        .param::<String>("appId")
+        .param::<String>("componentId")
+        .param::<Capability>("capability")
+        .result::<bool>("revoked");
+}
+
+fn register_auth(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "auth.status",
+            "OS re-authentication backend and the operations requiring it",
+        )
+        .result::<ReauthStatus>("status");
+    registry
+        .register(
+            "auth.authenticate",
+            "Authenticate with the OS for one sensitive operation",
+        )
+        .param::<SensitiveOperation>("operation")
+        .result::<AuthProof>("proof");
+    registry
+        .register(
+            "auth.setRequired",
+            "Require OS re-authentication for an operation, or stop",
+        )
+        .param::<SensitiveOperation>("operation")
+        .param::<bool>("required")
+        .optional_param::<Option<AuthProof>>("proof")
+        .result::<()>("ok");
+}
+
+fn register_sharing(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "shared.request",
+            "Ask for access to another app's data offer",
+        )
+        .param::<String>("consumerAppId")
+        .param::<String>("componentId")
+        .param::<String>("providerAppId")
+        .param::<String>("offerId")
+        .result::<SharedDataGrant>("grant");
+    registry
+        .register("shared.list", "List keys under a granted offer")
+        .param::<String>("consumerAppId")
+        .param::<String>("providerAppId")
+        .param::<String>("offerId")
+        .result::<Vec<String>>("keys");
+    registry
+        .register("shared.read", "Read a key under a granted offer")
+        .param::<String>("consumerAppId")
+        .param::<String>("providerAppId")
+        .param::<String>("offerId")
+        .param::<String>("key")
+        .result::<Vec<u8>>("data");
+    registry
+        .register("shared.revoke", "Withdraw a consumer's access to an offer")
+        .param::<String>("providerAppId")
+        .param::<String>("offerId")
+        .param::<String>("consumerAppId")
+        .result::<bool>("revoked");
+}
+
+fn register_pairing(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "pairing.confirmationCode",
+            "Code to compare with the one shown on the other device",
+        )
+        .param::<String>("sessionId")
+        .result::<String>("code");
+    registry
+        .register(
+            "pairing.confirmMatch",
+            "Record whether the user saw matching codes on both devices",
+        )
+        .param::<String>("sessionId")
+        .param::<bool>("userConfirmed")
+        .result::<PairingStatus>("status");
+}
+
+fn register_handoff(registry: &mut MethodRegistry) {
+    registry
+        .register("handoff.send", "Send a payload to a paired device")
+        .param::<String>("pairingId")
+        .param::<HandoffPayload>("payload")
+        .result::<SentHandoff>("sent");
+    registry
+        .register(
+            "handoff.pending",
+            "Handoffs received and not yet dismissed, oldest first",
+        )
+        .result::<Vec<Handoff>>("handoffs");
+    registry
+        .register("handoff.dismiss", "Drop a received handoff")
+        .param::<String>("handoffId")
+        .result::<bool>("dismissed");
+    registry
+        .register(
+            "handoff.open",
+            "Open a received deep link with the app that handles it",
+        )
+        .param::<String>("handoffId")
+        .result::<HandlerInfo>("handler");
+}
+
+fn register_migration(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "migration.begin_export",
+            "Snapshot this server for a paired device and issue a migration key",
+        )
+        .param::<String>("deviceId")
+        .result::<ExportOffer>("offer");
+    registry
+        .register(
+            "migration.export_stream",
+            "Next chunks of the pending export",
+        )
+        .param::<ExportStreamRequest>("request")
+        .result::<ExportBatch>("batch");
+    registry
+        .register(
+            "migration.confirm",
+            "Acknowledge an import and retire this server",
+        )
+        .param::<MigrationReceipt>("receipt")
+        .result::<MigrationMarker>("marker");
+    registry
+        .register("migration.cancel", "Discard the pending export")
+        .result::<bool>("cancelled");
+}
+
+fn register_sessions(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "sessions.renew",
+            "Extend a session by the session lifetime from now",
+        )
+        .param::<String>("token")
+        .result::<RemoteSession>("session");
+    registry
+        .register("sessions.revoke", "End every session of a device")
+        .param::<String>("deviceId")
+        .result::<usize>("revoked");
+    registry
+        .register("sessions.list", "Unexpired sessions, oldest first")
+        .result::<Vec<RemoteSession>>("sessions");
+    registry
+        .register(
+            "sessions.grantPermission",
+            "Grant a permission to a paired device",
+        )
+        .param::<String>("deviceId")
+        .param::<String>("permission")
+        .result::<bool>("granted");
+    registry
+        .register(
+            "sessions.revokePermission",
+            "Revoke a permission from a paired device",
+        )
+        .param::<String>("deviceId")
+        .param::<String>("permission")
+        .result::<bool>("revoked");
+    registry
+        .register(
+            "sessions.permissions",
+            "Permissions granted to a paired device",
+        )
+        .param::<String>("deviceId")
+        .result::<Vec<String>>("permissions");
+}
+
+fn register_provenance(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "provenance.forComponent",
+            "Download history of a content hash, newest first",
+        )
+        .param::<String>("componentHash")
+        .result::<Vec<ProvenanceRecord>>("records");
+    registry
+        .register(
+            "provenance.forApp",
+            "Download history of every component of an installed app, newest first",
+        )
+        .param::<String>("appId")
+        .result::<Vec<ProvenanceRecord>>("records");
+}
+
+fn register_storage(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "storage.overview",
+            "Free space on the volume of each storage root",
+        )
+        .result::<Vec<VolumeSpace>>("volumes");
+    registry
+        .register(
+            "storage.compact",
+            "Report or remove files the encrypted storage no longer uses",
+        )
+        .param::<bool>("reportOnly")
+        .result::<CompactReport>("report");
+    registry
+        .register(
+            "storage.lastCompaction",
+            "Report of the last compaction run since startup",
+        )
+        .result::<Option<CompactReport>>("report");
+}
+
+fn register_retention(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "retention.footprints",
+            "What each retained store holds, with its retention policy",
+        )
+        .result::<Vec<StoreFootprint>>("stores");
+    registry
+        .register(
+            "retention.setPolicy",
+            "Configure a store's retention policy, or restore its default",
+        )
+        .param::<RetentionStore>("store")
+        .param::<Option<Policy>>("policy")
+        .result::<()>("ok");
+    registry
+        .register("retention.run", "Apply every store's retention policy now")
+        .result::<RetentionRun>("run");
+    registry
+        .register(
+            "retention.lastRun",
+            "Reports of the last retention pass since startup",
+        )
+        .result::<Option<RetentionRun>>("run");
+}
+
+fn register_logs(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "logs.tail",
+            "Stream log records matching a filter as `logs.tail` notifications; needs logs.read",
+        )
+        .param::<String>("token")
+        .optional_param::<LogFilter>("filter")
+        .result::<String>("subscription");
+    registry
+        .register(
+            "logs.query",
+            "Most recent persisted log records matching a filter, oldest first; needs logs.read",
+        )
+        .param::<String>("token")
+        .optional_param::<LogFilter>("filter")
+        .optional_param::<Option<usize>>("limit")
+        .result::<Vec<LogRecord>>("records");
+}
+
+fn register_wallet(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "wallet.history",
+            "Upload payments matching a filter, newest first, with totals per month",
+        )
+        .optional_param::<HistoryFilter>("filter")
+        .optional_param::<PageRequest>("page")
+        .result::<PaymentHistory>("history");
+    registry
+        .register(
+            "wallet.exportHistory",
+            "Export every upload payment to a CSV or JSON file",
+        )
+        .param::<String>("path")
+        .param::<ExportFormat>("format")
+        .result::<usize>("exported");
+    registry
+        .register(
+            "wallet.approvePayment",
+            "Check a payment an app asks for against the device policy's wallet caps",
+        )
+        .param::<String>("appId")
+        .param::<u64>("amount")
+        .result::<()>("ok");
+}
+
+fn register_tasks(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "tasks.list",
+            "Long-running operations in flight, oldest first",
+        )
+        .result::<Vec<TaskInfo>>("tasks");
+    registry
+        .register(
+            "tasks.history",
+            "Recently finished operations, newest first",
+        )
+        .param::<usize>("limit")
+        .result::<Vec<TaskInfo>>("tasks");
+    registry
+        .register("tasks.cancel", "Cancel a running operation")
+        .param::<String>("taskId")
+        .result::<()>("ok");
+}
+
+fn register_annotations(registry: &mut MethodRegistry) {
+    registry
+        .register("annotations.set", "Attach a note to an app or device")
+        .param::<Subject>("subject")
+        .param::<String>("text")
+        .result::<Annotation>("annotation");
+    registry
+        .register("annotations.get", "The note on an app or device")
+        .param::<Subject>("subject")
+        .result::<Option<Annotation>>("annotation");
+    registry
+        .register(
+            "annotations.delete",
+            "Remove the note from an app or device",
+        )
+        .param::<Subject>("subject")
+        .result::<bool>("deleted");
+    registry
+        .register(
+            "annotations.search",
+            "Notes containing every word of a query, ignoring case",
+        )
+        .param::<String>("query")
+        .result::<Vec<Annotation>>("annotations");
+    registry
+        .register("annotations.list", "Every note on apps and devices")
+        .result::<Vec<Annotation>>("annotations");
+}
+
+fn register_policies(registry: &mut MethodRegistry) {
+    registry
+        .register("policies.apply", "Apply a group policy signed by the admin")
+        .param::<SignedPolicy>("policy")
+        .result::<PolicySummary>("summary");
+    registry
+        .register(
+            "policies.remove",
+            "Lift the group policy with a removal signed by its admin",
+        )
+        .param::<SignedRemoval>("removal")
+        .result::<()>("ok");
+    registry
+        .register(
+            "policies.summary",
+            "The group policy in force on this device",
+        )
+        .result::<PolicyStatus>("status");
+}
+
+fn register_features(registry: &mut MethodRegistry) {
+    registry
+        .register(
+            "features.get",
+            "Effective value of an app's flag, with the layers it was evaluated from",
+        )
+        .param::<String>("appId")
+        .param::<String>("flag")
+        .result::<FlagValue>("flag");
+    registry
+        .register(
+            "features.all",
+            "Effective values of every flag an app declares",
+        )
+        .param::<String>("appId")
+        .result::<Vec<FlagValue>>("flags");
+    registry
+        .register(
+            "features.setOverride",
+            "Override a flag locally, or clear the override with null",
+        )
+        .param::<String>("appId")
+        .param::<String>("flag")
+        .param::<Option<bool>>("value")
+        .result::<FlagValue>("flag");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::registry::validate_document;
+    use std::path::Path;
+
+    /// Method names from `(OpenRPC: name)` markers under `dir`
+    fn documented_methods(dir: &Path, methods: &mut Vec<String>) {
+        const MARKER: &str = "(OpenRPC: ";
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                documented_methods(&path, methods);
+                continue;
+            }
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            for (start, _) in source.match_indices(MARKER) {
+                let rest = &source[start + MARKER.len()..];
+                if let Some(end) = rest.find(')') {
+                    methods.push(rest[..end].to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_documented_methods_are_registered() {
+        let mut documented = Vec::new();
+        documented_methods(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src/services"),
+            &mut documented,
+        );
+        assert!(!documented.is_empty());
+
+        let mut registry = MethodRegistry::new();
+        register_core_methods(&mut registry);
+        for method in &documented {
+            assert!(
+                registry.contains(method),
+                "{} is documented but not registered",
+                method
+            );
+        }
+    }
+
+    #[test]
+    fn test_core_document_is_complete_and_valid() {
+        let mut registry = MethodRegistry::new();
+        register_core_methods(&mut registry);
+        let document = registry.document();
+        validate_document(&document).unwrap();
+
+        let described: Vec<&str> = document["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|method| method["name"].as_str())
+            .collect();
+        assert_eq!(described, registry.names().collect::<Vec<_>>());
+
+        let schemas = &document["components"]["schemas"];
+        for name in ["AppInfo", "Capability", "KeyType", "SharedDataGrant"] {
+            assert!(schemas[name].is_object(), "{} schema missing", name);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! # RPC Module
+//!
+//! Machine-readable description of the OpenRPC surface, for tooling such as
+//! SDK code generators and API explorers.
+//!
+//! This module provides:
+//! - [`MethodRegistry`], where each method is registered with its name,
+//!   summary, and parameter and result types; JSON Schemas are derived from
+//!   the serde types with `schemars`
+//! - [`openrpc_document`], the OpenRPC document for the core services,
+//!   including the error codes
+//! - [`RpcServer`], a JSON-RPC server that answers `rpc.discover` with that
+//!   document
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::rpc;
+//!
+//! let document = rpc::openrpc_document();
+//! assert!(rpc::validate_document(&document).is_ok());
+//! println!("{}", serde_json::to_string_pretty(&document).unwrap());
+//! ```
+
+pub mod methods;
+pub mod registry;
+pub mod server;
+
+pub use registry::{
+    validate_document, ContentDescriptor, MethodBuilder, MethodDescriptor, MethodRegistry,
+    OPENRPC_VERSION,
+};
+pub use server::RpcServer;
+
+use serde_json::Value;
+use std::path::Path;
+
+use crate::error::Result;
+
+/// Method answering with the server's OpenRPC document
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Registry of every core service method
+pub fn core_registry() -> MethodRegistry {
+    let mut registry = MethodRegistry::new();
+    methods::register_core_methods(&mut registry);
+    registry
+}
+
+/// The OpenRPC document of the core services
+pub fn openrpc_document() -> Value {
+    core_registry().document()
+}
+
+/// Write the OpenRPC document of the core services as pretty-printed JSON
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written
+pub fn write_openrpc_document<P: AsRef<Path>>(path: P) -> Result<()> {
+    let mut json = serde_json::to_string_pretty(&openrpc_document())?;
+    json.push('\n');
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_openrpc_document() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("openrpc.json");
+        write_openrpc_document(&path).unwrap();
+
+        let written: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, openrpc_document());
+        assert_eq!(written["openrpc"], OPENRPC_VERSION);
+    }
+}
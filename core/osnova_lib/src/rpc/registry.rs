@@ -0,0 +1,486 @@
+//! Registry of OpenRPC methods and the OpenRPC document built from it
+//!
+//! Each method is registered with its name, a summary, and the Rust types
+//! of its parameters and result. JSON Schemas are derived from those types
+//! with `schemars`, so the document follows the serde representation the
+//! services actually use. Shared types are emitted once under
+//! `components.schemas` and referenced from the methods.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::{INTERNAL_ERROR_CODE, PERMISSION_DENIED_ERROR_CODE, UNAUTHORIZED_ERROR_CODE};
+
+/// OpenRPC specification version of generated documents
+pub const OPENRPC_VERSION: &str = "1.3.2";
+
+/// Where shared schemas live in the document
+const SCHEMAS_PATH: &str = "#/components/schemas/";
+
+/// Where shared errors live in the document
+const ERRORS_PATH: &str = "#/components/errors/";
+
+/// Prefix reserved for methods describing the RPC server itself
+const RESERVED_PREFIX: &str = "rpc.";
+
+/// Errors any method may return, keyed by component name
+const ERRORS: &[(&str, i64, &str)] = &[
+    (
+        "Unauthorized",
+        UNAUTHORIZED_ERROR_CODE,
+        "Missing or expired session",
+    ),
+    (
+        "PermissionDenied",
+        PERMISSION_DENIED_ERROR_CODE,
+        "The user did not grant the required capability",
+    ),
+    ("InternalError", INTERNAL_ERROR_CODE, "Internal error"),
+];
+
+/// A named parameter or result of a method (an OpenRPC content descriptor)
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentDescriptor {
+    /// Parameter or result name
+    pub name: String,
+    /// Whether the parameter must be given
+    pub required: bool,
+    /// JSON Schema of the value
+    pub schema: Schema,
+}
+
+/// One registered method
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodDescriptor {
+    /// Method name, e.g. `apps.list`
+    pub name: String,
+    /// One-line description
+    pub summary: String,
+    /// Parameters, passed by name
+    pub params: Vec<ContentDescriptor>,
+    /// Result
+    pub result: ContentDescriptor,
+}
+
+/// Registry of OpenRPC methods
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::rpc::MethodRegistry;
+/// use osnova_lib::services::Theme;
+///
+/// let mut registry = MethodRegistry::new();
+/// registry
+///     .register("ui.setTheme", "Set the theme")
+///     .param::<Theme>("theme")
+///     .result::<()>("ok");
+///
+/// let document = registry.document();
+/// assert_eq!(document["methods"][0]["name"], "ui.setTheme");
+/// ```
+pub struct MethodRegistry {
+    generator: SchemaGenerator,
+    methods: BTreeMap<String, MethodDescriptor>,
+}
+
+impl MethodRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        let settings = SchemaSettings::draft07().with(|settings| {
+            settings.definitions_path = SCHEMAS_PATH.to_string();
+        });
+
+        Self {
+            generator: settings.into_generator(),
+            methods: BTreeMap::new(),
+        }
+    }
+
+    /// Start registering a method; finish with [`MethodBuilder::result`]
+    ///
+    /// # Panics
+    ///
+    /// [`MethodBuilder::result`] panics if the name is already registered
+    /// or uses the reserved `rpc.` prefix, since either is a mistake in the
+    /// method table rather than a runtime condition.
+    pub fn register(&mut self, name: &str, summary: &str) -> MethodBuilder<'_> {
+        MethodBuilder {
+            registry: self,
+            name: name.to_string(),
+            summary: summary.to_string(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Whether a method is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.methods.contains_key(name)
+    }
+
+    /// Get a registered method
+    pub fn get(&self, name: &str) -> Option<&MethodDescriptor> {
+        self.methods.get(name)
+    }
+
+    /// Registered method names, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.methods.keys().map(String::as_str)
+    }
+
+    /// Number of registered methods
+    pub fn len(&self) -> usize {
+        self.methods.len()
+    }
+
+    /// Whether no methods are registered
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+
+    /// Assemble the OpenRPC document describing every registered method
+    pub fn document(&self) -> Value {
+        let error_refs: Vec<Value> = ERRORS
+            .iter()
+            .map(|(name, _, _)| json!({ "$ref": format!("{}{}", ERRORS_PATH, name) }))
+            .collect();
+        let methods: Vec<Value> = self
+            .methods
+            .values()
+            .map(|method| {
+                let mut value = serde_json::to_value(method).unwrap_or(Value::Null);
+                value["paramStructure"] = json!("by-name");
+                value["errors"] = json!(error_refs);
+                value
+            })
+            .collect();
+
+        let errors: Map<String, Value> = ERRORS
+            .iter()
+            .map(|(name, code, message)| {
+                (
+                    name.to_string(),
+                    json!({ "code": code, "message": message }),
+                )
+            })
+            .collect();
+
+        json!({
+            "openrpc": OPENRPC_VERSION,
+            "info": {
+                "title": "Osnova",
+                "description": "Core services of the Osnova application framework",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "methods": methods,
+            "components": {
+                "schemas": self.generator.definitions(),
+                "errors": errors,
+            },
+        })
+    }
+
+    fn insert(&mut self, method: MethodDescriptor) {
+        assert!(
+            !method.name.starts_with(RESERVED_PREFIX),
+            "RPC method {} uses the reserved '{}' prefix",
+            method.name,
+            RESERVED_PREFIX
+        );
+        let name = method.name.clone();
+        assert!(
+            self.methods.insert(name.clone(), method).is_none(),
+            "RPC method {} registered twice",
+            name
+        );
+    }
+}
+
+impl Default for MethodRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A method being registered
+pub struct MethodBuilder<'a> {
+    registry: &'a mut MethodRegistry,
+    name: String,
+    summary: String,
+    params: Vec<ContentDescriptor>,
+}
+
+impl MethodBuilder<'_> {
+    /// Add a required parameter
+    pub fn param<T: JsonSchema>(self, name: &str) -> Self {
+        self.push_param::<T>(name, true)
+    }
+
+    /// Add a parameter that may be omitted
+    ///
+    /// Optional parameters must follow the required ones.
+    pub fn optional_param<T: JsonSchema>(self, name: &str) -> Self {
+        self.push_param::<T>(name, false)
+    }
+
+    /// Set the result and finish registering the method
+    ///
+    /// Methods without a return value use `()`, described as `null`.
+    pub fn result<T: JsonSchema>(self, name: &str) {
+        let schema = self.registry.generator.subschema_for::<T>();
+        let method = MethodDescriptor {
+            name: self.name,
+            summary: self.summary,
+            params: self.params,
+            result: ContentDescriptor {
+                name: name.to_string(),
+                required: true,
+                schema,
+            },
+        };
+        self.registry.insert(method);
+    }
+
+    fn push_param<T: JsonSchema>(mut self, name: &str, required: bool) -> Self {
+        let schema = self.registry.generator.subschema_for::<T>();
+        self.params.push(ContentDescriptor {
+            name: name.to_string(),
+            required,
+            schema,
+        });
+        self
+    }
+}
+
+/// Check a document against the OpenRPC meta-schema
+///
+/// Covers the meta-schema's structural rules for the parts of the
+/// specification generated documents use: the version and info objects,
+/// method and content descriptor shapes, unique method and parameter names,
+/// required parameters before optional ones, error objects, and that every
+/// `$ref` resolves inside the document.
+///
+/// # Errors
+///
+/// Returns a description of the first violation
+pub fn validate_document(document: &Value) -> std::result::Result<(), String> {
+    let version = document["openrpc"]
+        .as_str()
+        .ok_or("'openrpc' must be a string")?;
+    let mut parts = version.split('.');
+    let is_semver = parts.next() == Some("1")
+        && parts.clone().count() == 2
+        && parts.all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+    if !is_semver {
+        return Err(format!("Unsupported OpenRPC version: {}", version));
+    }
+
+    let info = document["info"]
+        .as_object()
+        .ok_or("'info' must be an object")?;
+    for field in ["title", "version"] {
+        if !info.get(field).is_some_and(Value::is_string) {
+            return Err(format!("'info.{}' must be a string", field));
+        }
+    }
+
+    let methods = document["methods"]
+        .as_array()
+        .ok_or("'methods' must be an array")?;
+    let mut names = HashSet::new();
+    for method in methods {
+        let name = method["name"]
+            .as_str()
+            .ok_or("Every method needs a string 'name'")?;
+        if !names.insert(name) {
+            return Err(format!("Duplicate method name: {}", name));
+        }
+        if name.starts_with(RESERVED_PREFIX) {
+            return Err(format!("Method {} uses the reserved rpc. prefix", name));
+        }
+        if !method["summary"].is_string() {
+            return Err(format!("Method {}: 'summary' must be a string", name));
+        }
+        if !matches!(
+            method.get("paramStructure").and_then(Value::as_str),
+            None | Some("by-name" | "by-position" | "either")
+        ) {
+            return Err(format!("Method {}: invalid 'paramStructure'", name));
+        }
+
+        let params = method["params"]
+            .as_array()
+            .ok_or_else(|| format!("Method {}: 'params' must be an array", name))?;
+        let mut param_names = HashSet::new();
+        let mut seen_optional = false;
+        for param in params {
+            validate_content_descriptor(name, param)?;
+            let param_name = param["name"].as_str().unwrap_or_default();
+            if !param_names.insert(param_name) {
+                return Err(format!("Method {}: duplicate param {}", name, param_name));
+            }
+            let required = param["required"].as_bool().unwrap_or(false);
+            if required && seen_optional {
+                return Err(format!(
+                    "Method {}: required param {} follows an optional one",
+                    name, param_name
+                ));
+            }
+            seen_optional |= !required;
+        }
+        validate_content_descriptor(name, &method["result"])?;
+
+        for error in method["errors"].as_array().into_iter().flatten() {
+            let error = resolve(document, error)?;
+            if !error["code"].is_i64() || !error["message"].is_string() {
+                return Err(format!(
+                    "Method {}: errors need an integer 'code' and string 'message'",
+                    name
+                ));
+            }
+        }
+    }
+
+    validate_refs(document, document)
+}
+
+fn validate_content_descriptor(
+    method: &str,
+    descriptor: &Value,
+) -> std::result::Result<(), String> {
+    if !descriptor["name"].is_string() {
+        return Err(format!(
+            "Method {}: content descriptors need a string 'name'",
+            method
+        ));
+    }
+    if !(descriptor["schema"].is_object() || descriptor["schema"].is_boolean()) {
+        return Err(format!(
+            "Method {}: content descriptor {} needs a schema",
+            method, descriptor["name"]
+        ));
+    }
+    if !matches!(descriptor.get("required"), None | Some(Value::Bool(_))) {
+        return Err(format!("Method {}: 'required' must be a boolean", method));
+    }
+    Ok(())
+}
+
+/// Follow a `$ref` inside the document, or return the value itself
+fn resolve<'a>(document: &'a Value, value: &'a Value) -> std::result::Result<&'a Value, String> {
+    let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+        return Ok(value);
+    };
+    reference
+        .strip_prefix('#')
+        .and_then(|pointer| document.pointer(pointer))
+        .ok_or_else(|| format!("Unresolved reference: {}", reference))
+}
+
+fn validate_refs(document: &Value, value: &Value) -> std::result::Result<(), String> {
+    match value {
+        Value::Object(map) => {
+            if map.contains_key("$ref") {
+                resolve(document, value)?;
+            }
+            map.values()
+                .try_for_each(|value| validate_refs(document, value))
+        }
+        Value::Array(values) => values
+            .iter()
+            .try_for_each(|value| validate_refs(document, value)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::notification::StoredNotification;
+    use crate::services::Theme;
+
+    #[test]
+    fn test_schema_generation() {
+        let mut registry = MethodRegistry::new();
+        registry
+            .register("notifications.list", "List notifications")
+            .optional_param::<Option<String>>("appId")
+            .result::<Vec<StoredNotification>>("notifications");
+        registry
+            .register("ui.setTheme", "Set the theme")
+            .param::<Theme>("theme")
+            .result::<()>("ok");
+
+        let document = registry.document();
+        validate_document(&document).unwrap();
+
+        // Shared types are referenced, and described once
+        let list = &document["methods"][0];
+        assert_eq!(list["name"], "notifications.list");
+        assert_eq!(list["params"][0]["required"], false);
+        assert_eq!(
+            list["result"]["schema"]["items"]["$ref"],
+            "#/components/schemas/StoredNotification"
+        );
+        let schemas = &document["components"]["schemas"];
+        let stored = &schemas["StoredNotification"];
+        assert_eq!(stored["type"], "object");
+        assert!(stored["properties"]["appId"].is_object());
+        assert!(stored["properties"]["postedAt"].is_object());
+
+        // Enums follow their serde representation, keeping variant docs
+        let variants: Vec<&str> = schemas["Theme"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|variant| variant["enum"][0].as_str())
+            .collect();
+        assert_eq!(variants, vec!["light", "dark", "system"]);
+
+        // Methods without a value return null
+        assert_eq!(document["methods"][1]["result"]["schema"]["type"], "null");
+    }
+
+    #[test]
+    fn test_validation_rejects_malformed_documents() {
+        let mut registry = MethodRegistry::new();
+        registry
+            .register("apps.info", "Get details of an application")
+            .param::<String>("appId")
+            .result::<String>("info");
+        let valid = registry.document();
+        validate_document(&valid).unwrap();
+
+        let mut duplicate = valid.clone();
+        let method = duplicate["methods"][0].clone();
+        duplicate["methods"].as_array_mut().unwrap().push(method);
+        assert!(validate_document(&duplicate).is_err());
+
+        let mut missing_schema = valid.clone();
+        missing_schema["methods"][0]["params"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("schema");
+        assert!(validate_document(&missing_schema).is_err());
+
+        let mut dangling = valid.clone();
+        dangling["methods"][0]["result"]["schema"] = json!({ "$ref": "#/components/schemas/Nope" });
+        assert!(validate_document(&dangling).is_err());
+
+        let mut misordered = valid.clone();
+        misordered["methods"][0]["params"] = json!([
+            { "name": "a", "required": false, "schema": {} },
+            { "name": "b", "required": true, "schema": {} },
+        ]);
+        assert!(validate_document(&misordered).is_err());
+
+        let mut version = valid;
+        version["openrpc"] = json!("2.0");
+        assert!(validate_document(&version).is_err());
+    }
+}
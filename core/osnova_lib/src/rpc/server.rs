@@ -0,0 +1,487 @@
+//! JSON-RPC 2.0 server answering OpenRPC requests over a socket
+//!
+//! Requests and responses are newline-delimited JSON objects. The server
+//! always answers `rpc.discover` with the OpenRPC document of its registry;
+//! other methods are dispatched to handlers, which can only be attached to
+//! registered methods so the document stays a complete description of what
+//! the server answers.
+//!
+//! A subscription method answers with a subscription id, then pushes each
+//! item to the same connection as a notification named after the method,
+//! with params `{ "subscription": id, "result": item }`, until the
+//! connection closes or the subscription ends.
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::registry::MethodRegistry;
+use super::DISCOVER_METHOD;
+use crate::error::{OsnovaError, Result, INTERNAL_ERROR_CODE};
+
+/// JSON-RPC error code for a request that is not valid JSON
+pub const PARSE_ERROR_CODE: i64 = -32700;
+
+/// JSON-RPC error code for a malformed request object
+pub const INVALID_REQUEST_ERROR_CODE: i64 = -32600;
+
+/// JSON-RPC error code for a method the server does not answer
+pub const METHOD_NOT_FOUND_ERROR_CODE: i64 = -32601;
+
+/// Notifications queued per connection before subscriptions wait
+const NOTIFICATION_BUFFER: usize = 64;
+
+/// A method handler, taking the request params
+pub type Handler = Arc<dyn Fn(Value) -> anyhow::Result<Value> + Send + Sync>;
+
+/// A subscription handler, taking the request params and returning the
+/// items to push; the subscription ends when the sender is dropped
+pub type SubscriptionHandler =
+    Arc<dyn Fn(Value) -> anyhow::Result<mpsc::Receiver<Value>> + Send + Sync>;
+
+/// A subscription opened by a request, not yet forwarding
+struct OpenedSubscription {
+    method: String,
+    id: String,
+    items: mpsc::Receiver<Value>,
+}
+
+/// Forwarding tasks of one connection, stopped when it closes
+#[derive(Default)]
+struct Forwarders(Vec<JoinHandle<()>>);
+
+impl Drop for Forwarders {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// OpenRPC server
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use osnova_lib::rpc::{self, RpcServer};
+/// use std::sync::Arc;
+/// use tokio::net::TcpListener;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let server = Arc::new(RpcServer::new(rpc::core_registry()));
+/// let listener = TcpListener::bind("127.0.0.1:0").await?;
+/// server.serve(listener).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RpcServer {
+    methods: HashSet<String>,
+    document: Value,
+    handlers: HashMap<String, Handler>,
+    subscriptions: HashMap<String, SubscriptionHandler>,
+    next_subscription: AtomicU64,
+}
+
+impl RpcServer {
+    /// Create a server describing the methods of a registry
+    pub fn new(registry: MethodRegistry) -> Self {
+        Self {
+            methods: registry.names().map(str::to_string).collect(),
+            document: registry.document(),
+            handlers: HashMap::new(),
+            subscriptions: HashMap::new(),
+            next_subscription: AtomicU64::new(1),
+        }
+    }
+
+    /// Answer a registered method with a handler
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method is not registered, since it would be
+    /// missing from the `rpc.discover` document
+    pub fn with_handler<F>(mut self, method: &str, handler: F) -> Result<Self>
+    where
+        F: Fn(Value) -> anyhow::Result<Value> + Send + Sync + 'static,
+    {
+        if !self.methods.contains(method) {
+            return Err(OsnovaError::Other(format!(
+                "Cannot serve unregistered RPC method: {}",
+                method
+            )));
+        }
+        self.handlers.insert(method.to_string(), Arc::new(handler));
+        Ok(self)
+    }
+
+    /// Answer a registered method by opening a subscription
+    ///
+    /// The handler runs on the connection's runtime, so it may spawn the
+    /// task that feeds the subscription.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method is not registered
+    pub fn with_subscription<F>(mut self, method: &str, handler: F) -> Result<Self>
+    where
+        F: Fn(Value) -> anyhow::Result<mpsc::Receiver<Value>> + Send + Sync + 'static,
+    {
+        if !self.methods.contains(method) {
+            return Err(OsnovaError::Other(format!(
+                "Cannot serve unregistered RPC method: {}",
+                method
+            )));
+        }
+        self.subscriptions
+            .insert(method.to_string(), Arc::new(handler));
+        Ok(self)
+    }
+
+    /// The OpenRPC document returned by `rpc.discover`
+    pub fn document(&self) -> &Value {
+        &self.document
+    }
+
+    /// Accept connections until the listener fails
+    ///
+    /// Each connection is served on its own task.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                // A dropped connection only ends that client's session
+                let _ = server.serve_connection(stream).await;
+            });
+        }
+    }
+
+    async fn serve_connection(&self, stream: TcpStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let (notify, mut notifications) = mpsc::channel::<Value>(NOTIFICATION_BUFFER);
+        let mut forwarders = Forwarders::default();
+
+        loop {
+            let message = tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else {
+                        break;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let mut opened = Vec::new();
+                    let response = self.handle_line_in(&line, Some(&mut opened));
+                    // The subscription id is written before any item
+                    if let Some(response) = response {
+                        write_message(&mut writer, &response).await?;
+                    }
+                    for subscription in opened {
+                        forwarders.0.push(tokio::spawn(forward(subscription, notify.clone())));
+                    }
+                    continue;
+                }
+                Some(notification) = notifications.recv() => notification,
+            };
+            write_message(&mut writer, &message).await?;
+        }
+        Ok(())
+    }
+
+    /// Handle one request line
+    ///
+    /// Returns `None` for notifications (requests without an id).
+    pub fn handle_line(&self, line: &str) -> Option<Value> {
+        self.handle_line_in(line, None)
+    }
+
+    fn handle_line_in(
+        &self,
+        line: &str,
+        opened: Option<&mut Vec<OpenedSubscription>>,
+    ) -> Option<Value> {
+        match serde_json::from_str(line) {
+            Ok(request) => self.handle_in(request, opened),
+            Err(e) => Some(error_response(
+                Value::Null,
+                PARSE_ERROR_CODE,
+                &format!("Parse error: {}", e),
+            )),
+        }
+    }
+
+    /// Handle one parsed request
+    ///
+    /// Returns `None` for notifications (requests without an id).
+    /// Subscription methods are refused, since there is no connection to
+    /// push their items to.
+    pub fn handle(&self, request: Value) -> Option<Value> {
+        self.handle_in(request, None)
+    }
+
+    fn handle_in(
+        &self,
+        request: Value,
+        opened: Option<&mut Vec<OpenedSubscription>>,
+    ) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = match (&request["jsonrpc"], &request["method"]) {
+            (Value::String(version), Value::String(method)) if version == "2.0" => method,
+            _ => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST_ERROR_CODE,
+                    "Invalid request",
+                ))
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let outcome = if method == DISCOVER_METHOD {
+            Ok(self.document.clone())
+        } else if let Some(handler) = self.handlers.get(method) {
+            handler(params).map_err(error_outcome)
+        } else if let Some(handler) = self.subscriptions.get(method) {
+            match opened {
+                Some(opened) => handler(params).map_err(error_outcome).map(|items| {
+                    let id = self
+                        .next_subscription
+                        .fetch_add(1, Ordering::Relaxed)
+                        .to_string();
+                    opened.push(OpenedSubscription {
+                        method: method.clone(),
+                        id: id.clone(),
+                        items,
+                    });
+                    Value::String(id)
+                }),
+                None => Err((
+                    INVALID_REQUEST_ERROR_CODE,
+                    format!("Subscription {} needs a connection", method),
+                )),
+            }
+        } else {
+            Err((
+                METHOD_NOT_FOUND_ERROR_CODE,
+                format!("Method not found: {}", method),
+            ))
+        };
+
+        let id = id?;
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+}
+
+/// Bind a listener on a free loopback port, for serving local components
+pub async fn bind_local() -> Result<TcpListener> {
+    Ok(TcpListener::bind("127.0.0.1:0").await?)
+}
+
+/// Bind a listener on a configured address
+pub async fn bind(addr: std::net::SocketAddr) -> Result<TcpListener> {
+    Ok(TcpListener::bind(addr).await?)
+}
+
+/// Push a subscription's items to its connection as notifications
+async fn forward(mut subscription: OpenedSubscription, notify: mpsc::Sender<Value>) {
+    while let Some(item) = subscription.items.recv().await {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": subscription.method,
+            "params": { "subscription": subscription.id, "result": item },
+        });
+        if notify.send(notification).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn write_message(
+    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    message: &Value,
+) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// JSON-RPC error code and message of a handler error
+fn error_outcome(error: anyhow::Error) -> (i64, String) {
+    let code = error
+        .downcast_ref::<OsnovaError>()
+        .map_or(INTERNAL_ERROR_CODE, OsnovaError::rpc_code);
+    (code, error.to_string())
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PERMISSION_DENIED_ERROR_CODE;
+    use crate::rpc;
+    use crate::services::Theme;
+
+    async fn call(stream: &mut BufReader<TcpStream>, request: Value) -> Value {
+        let mut line = serde_json::to_vec(&request).unwrap();
+        line.push(b'\n');
+        stream.get_mut().write_all(&line).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_line(&mut response).await.unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_discover_round_trip() {
+        let server = Arc::new(
+            RpcServer::new(rpc::core_registry())
+                .with_handler("ui.getTheme", |_| Ok(serde_json::to_value(Theme::Dark)?))
+                .unwrap()
+                .with_handler("permissions.revoke", |_| {
+                    Err(OsnovaError::PermissionDenied("not the owner".to_string()).into())
+                })
+                .unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let serving = tokio::spawn(server.clone().serve(listener));
+
+        let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let response = call(
+            &mut stream,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "rpc.discover" }),
+        )
+        .await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], rpc::openrpc_document());
+        rpc::validate_document(&response["result"]).unwrap();
+
+        let response = call(
+            &mut stream,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "ui.getTheme" }),
+        )
+        .await;
+        assert_eq!(response["result"], "dark");
+
+        let response = call(
+            &mut stream,
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "permissions.revoke", "params": {} }),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], PERMISSION_DENIED_ERROR_CODE);
+
+        // Described but not served, and not described at all
+        for method in ["apps.list", "apps.explode"] {
+            let response = call(
+                &mut stream,
+                json!({ "jsonrpc": "2.0", "id": 4, "method": method }),
+            )
+            .await;
+            assert_eq!(response["error"]["code"], METHOD_NOT_FOUND_ERROR_CODE);
+        }
+
+        serving.abort();
+    }
+
+    #[tokio::test]
+    async fn test_subscription_pushes_notifications() {
+        let server = Arc::new(
+            RpcServer::new(rpc::core_registry())
+                .with_subscription("logs.tail", |params| {
+                    let (items, receiver) = mpsc::channel(1);
+                    let count = params["count"].as_u64().unwrap_or(0);
+                    tokio::spawn(async move {
+                        for n in 0..count {
+                            let _ = items.send(json!(n)).await;
+                        }
+                    });
+                    Ok(receiver)
+                })
+                .unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let serving = tokio::spawn(server.clone().serve(listener));
+
+        let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let response = call(
+            &mut stream,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "logs.tail", "params": { "count": 3 } }),
+        )
+        .await;
+        let subscription = response["result"].clone();
+        assert!(subscription.is_string());
+
+        for n in 0..3 {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let notification: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(notification["method"], "logs.tail");
+            assert!(notification.get("id").is_none());
+            assert_eq!(notification["params"]["subscription"], subscription);
+            assert_eq!(notification["params"]["result"], n);
+        }
+
+        // Requests are still answered on a connection with subscriptions
+        let response = call(
+            &mut stream,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "rpc.discover" }),
+        )
+        .await;
+        assert_eq!(response["id"], 2);
+
+        // Without a connection there is nowhere to push items
+        let response = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 3, "method": "logs.tail" }))
+            .unwrap();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST_ERROR_CODE);
+
+        serving.abort();
+    }
+
+    #[test]
+    fn test_malformed_requests() {
+        let server = RpcServer::new(rpc::core_registry());
+
+        let response = server.handle_line("{not json").unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR_CODE);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = server
+            .handle(json!({ "jsonrpc": "1.0", "id": 7, "method": "rpc.discover" }))
+            .unwrap();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST_ERROR_CODE);
+        assert_eq!(response["id"], 7);
+
+        // Notifications get no response
+        assert!(server
+            .handle(json!({ "jsonrpc": "2.0", "method": "rpc.discover" }))
+            .is_none());
+
+        // Handlers can only be attached to described methods
+        assert!(server
+            .with_handler("apps.explode", |_| Ok(Value::Null))
+            .is_err());
+        let server = RpcServer::new(rpc::core_registry());
+        assert!(server
+            .with_subscription("apps.explode", |_| Ok(mpsc::channel(1).1))
+            .is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Private annotations on apps and devices
+//!
+//! Admins of a family or small-org deployment attach notes to installed
+//! apps and paired devices ("approved for kids", "pinned because v2 breaks
+//! plugin X"). Notes are shown read-only in app details and on the devices
+//! screen.
+//!
+//! Handles:
+//! - One plain-text note per subject, capped at [`MAX_ANNOTATION_CHARS`];
+//!   HTML tags and control characters are refused, so notes are safe to
+//!   render as markdown
+//! - Encryption at rest under a key derived from the user's identity
+//! - Case-insensitive search across every subject
+//! - Classification as [`DataClass::Personal`]: notes stay out of
+//!   diagnostics bundles and, unless the user opts in with
+//!   [`ConfigService::set_annotations_network_backup`], out of network
+//!   backups. They travel with local exports of the storage.
+
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::models::annotation::{Annotation, Subject};
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::services::config::ConfigService;
+use crate::storage::classification::{self, DataClass, Purpose};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Longest note, in characters
+pub const MAX_ANNOTATION_CHARS: usize = 2000;
+
+/// Longest app or device ID a note can be attached to, in characters
+pub const MAX_SUBJECT_ID_CHARS: usize = 256;
+
+/// Data class of every annotation
+pub const ANNOTATION_CLASS: DataClass = DataClass::Personal;
+
+/// Component the annotation key is derived for, per user
+const ANNOTATION_KEY_COMPONENT: &str = "osnova-annotations";
+
+/// Annotation service
+///
+/// Provides OpenRPC methods:
+/// - `annotations.set` - Attach a note to an app or device
+/// - `annotations.get` - The note on an app or device
+/// - `annotations.delete` - Remove the note from an app or device
+/// - `annotations.search` - Notes containing every word of a query
+/// - `annotations.list` - Every note
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::annotation::Subject;
+/// use osnova_lib::models::identity::RootIdentity;
+/// use osnova_lib::services::AnnotationService;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let identity = RootIdentity::generate()?;
+/// let service = AnnotationService::new("/tmp/osnova", "user-123", &identity)?;
+///
+/// let subject = Subject::App("com.example.app".to_string());
+/// service.set(&subject, "Approved for kids")?;
+/// assert_eq!(service.search("kids")?.len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct AnnotationService {
+    sql_storage: Mutex<SqlStorage>,
+    config: Mutex<ConfigService>,
+    user_id: String,
+    key: [u8; 32],
+    clock: SharedClock,
+}
+
+impl AnnotationService {
+    /// Create an annotation service for a user
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose notes are kept
+    /// * `identity` - The user's identity, which the encryption key is
+    ///   derived from
+    pub fn new<P: Into<PathBuf>>(
+        storage_path: P,
+        user_id: &str,
+        identity: &RootIdentity,
+    ) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let component = format!("{}:{}", ANNOTATION_KEY_COMPONENT, user_id);
+        let key = identity.derive_component_key(&component, 0, KeyPurpose::Encryption)?;
+
+        Ok(Self {
+            sql_storage: Mutex::new(SqlStorage::new(storage_path.join("osnova.db"))?),
+            config: Mutex::new(ConfigService::new(&storage_path)?),
+            user_id: user_id.to_string(),
+            key,
+            clock: time::default_clock(),
+        })
+    }
+
+    /// Use a specific clock for change timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Attach a note to an app or device, replacing any earlier one
+    /// (OpenRPC: annotations.set)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note is empty, longer than
+    /// [`MAX_ANNOTATION_CHARS`], or holds HTML tags or control characters
+    pub fn set(&self, subject: &Subject, text: &str) -> Result<Annotation> {
+        validate_subject(subject)?;
+        let text = validate_text(text)?;
+
+        let annotation = Annotation {
+            subject: subject.clone(),
+            text,
+            updated_at: self.clock.now_unix(),
+        };
+        self.sql_storage
+            .lock()
+            .unwrap()
+            .set_annotation(&self.user_id, &annotation, &self.key)?;
+        Ok(annotation)
+    }
+
+    /// The note on an app or device (OpenRPC: annotations.get)
+    pub fn get(&self, subject: &Subject) -> Result<Option<Annotation>> {
+        self.sql_storage
+            .lock()
+            .unwrap()
+            .get_annotation(&self.user_id, subject, &self.key)
+    }
+
+    /// Remove the note from an app or device; returns whether there was one
+    /// (OpenRPC: annotations.delete)
+    pub fn delete(&self, subject: &Subject) -> Result<bool> {
+        self.sql_storage
+            .lock()
+            .unwrap()
+            .delete_annotation(&self.user_id, subject)
+    }
+
+    /// Every note, apps first, each ordered by ID (OpenRPC: annotations.list)
+    pub fn list(&self) -> Result<Vec<Annotation>> {
+        self.sql_storage
+            .lock()
+            .unwrap()
+            .list_annotations(&self.user_id, &self.key)
+    }
+
+    /// Notes on apps and devices containing every word of `query`,
+    /// ignoring case (OpenRPC: annotations.search)
+    ///
+    /// A blank query matches nothing.
+    pub fn search(&self, query: &str) -> Result<Vec<Annotation>> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|annotation| {
+                let text = annotation.text.to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .collect())
+    }
+
+    /// Notes that may go to `purpose`
+    ///
+    /// Network backups get notes only when the user opted in; otherwise
+    /// none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`ANNOTATION_CLASS`] does not permit `purpose`,
+    /// as for diagnostics bundles
+    pub fn entries_for(&self, purpose: Purpose) -> Result<Vec<Annotation>> {
+        classification::check("Annotations", Some(ANNOTATION_CLASS), purpose)?;
+        if purpose == Purpose::NetworkBackup
+            && !self
+                .config
+                .lock()
+                .unwrap()
+                .get_annotations_network_backup()?
+        {
+            return Ok(Vec::new());
+        }
+        self.list()
+    }
+}
+
+/// Refuse subjects without an ID or with an oversized one
+fn validate_subject(subject: &Subject) -> Result<()> {
+    let length = subject.id().chars().count();
+    if length == 0 {
+        bail!("Annotations need an app or device ID");
+    }
+    if length > MAX_SUBJECT_ID_CHARS {
+        bail!(
+            "{} ID is {} characters; the limit is {}",
+            subject.kind(),
+            length,
+            MAX_SUBJECT_ID_CHARS
+        );
+    }
+    Ok(())
+}
+
+/// Check a note and trim surrounding whitespace
+fn validate_text(text: &str) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("Annotation is empty; delete it instead");
+    }
+
+    let length = text.chars().count();
+    if length > MAX_ANNOTATION_CHARS {
+        bail!(
+            "Annotation is {} characters; the limit is {}",
+            length,
+            MAX_ANNOTATION_CHARS
+        );
+    }
+    if text
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t')
+    {
+        bail!("Annotations may not contain control characters");
+    }
+    if has_html_tag(text) {
+        bail!("Annotations are plain text and may not contain HTML");
+    }
+    Ok(text.to_string())
+}
+
+/// Whether text holds something a browser would parse as a tag, comment
+/// or declaration (`<b`, `</`, `<!`, `<?`); a bare `<` as in "a < b" is
+/// plain text
+fn has_html_tag(text: &str) -> bool {
+    text.char_indices().any(|(i, c)| {
+        c == '<'
+            && text[i + 1..]
+                .chars()
+                .next()
+                .is_some_and(|next| next.is_ascii_alphabetic() || matches!(next, '/' | '!' | '?'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn service(temp_dir: &TempDir, identity: &RootIdentity) -> Result<AnnotationService> {
+        Ok(AnnotationService::new(temp_dir.path(), "user-1", identity)?
+            .with_clock(Arc::new(MockClock::new(1_700_000_000))))
+    }
+
+    fn app(id: &str) -> Subject {
+        Subject::App(id.to_string())
+    }
+
+    fn device(id: &str) -> Subject {
+        Subject::Device(id.to_string())
+    }
+
+    #[test]
+    fn test_crud_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp_dir, &identity)?;
+
+        assert!(service.get(&app("com.test.app"))?.is_none());
+
+        let set = service.set(&app("com.test.app"), "  Approved for kids\n")?;
+        assert_eq!(set.text, "Approved for kids");
+        assert_eq!(set.updated_at, 1_700_000_000);
+        assert_eq!(service.get(&app("com.test.app"))?, Some(set));
+
+        service.set(&app("com.test.app"), "Pinned: v2 breaks plugin X")?;
+        let text = service.get(&app("com.test.app"))?.map(|a| a.text);
+        assert_eq!(text.as_deref(), Some("Pinned: v2 breaks plugin X"));
+
+        // The same ID as a device is a different subject
+        assert!(service.get(&device("com.test.app"))?.is_none());
+
+        assert!(service.delete(&app("com.test.app"))?);
+        assert!(!service.delete(&app("com.test.app"))?);
+        assert!(service.get(&app("com.test.app"))?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_validation() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp_dir, &identity)?;
+        let subject = app("com.test.app");
+
+        assert!(service.set(&subject, "   ").is_err());
+        assert!(service
+            .set(&subject, &"x".repeat(MAX_ANNOTATION_CHARS + 1))
+            .is_err());
+        assert!(service
+            .set(&subject, &"é".repeat(MAX_ANNOTATION_CHARS))
+            .is_ok());
+        assert!(service.set(&subject, "<b>approved</b>").is_err());
+        assert!(service.set(&subject, "ok <!-- hidden -->").is_err());
+        assert!(service.set(&subject, "bell\u{7}").is_err());
+        assert!(service.set(&app(""), "note").is_err());
+
+        // Markdown and bare angle brackets are plain text
+        assert!(service
+            .set(&subject, "**v1 only**, since v2 < v1 <3")
+            .is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_across_subjects() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp_dir, &identity)?;
+
+        service.set(&app("com.test.games"), "Approved for kids on weekends")?;
+        service.set(&app("com.test.chat"), "Not for kids")?;
+        service.set(&device("tablet-1"), "Kids' tablet in the kitchen")?;
+        service.set(&device("laptop-1"), "Work laptop")?;
+
+        let subjects = |results: Vec<Annotation>| -> Vec<Subject> {
+            results.into_iter().map(|a| a.subject).collect()
+        };
+        assert_eq!(
+            subjects(service.search("KIDS")?),
+            [
+                app("com.test.chat"),
+                app("com.test.games"),
+                device("tablet-1")
+            ]
+        );
+        assert_eq!(
+            subjects(service.search("kids weekends")?),
+            [app("com.test.games")]
+        );
+        assert!(service.search("   ")?.is_empty());
+        assert!(service.search("server")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_at_rest() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        service(&temp_dir, &identity)?.set(&device("tablet-1"), "Kitchen tablet")?;
+
+        let database = std::fs::read(temp_dir.path().join("osnova.db"))?;
+        assert!(!database.windows(7).any(|w| w == b"Kitchen"));
+
+        // Another identity cannot read the note
+        let other = AnnotationService::new(temp_dir.path(), "user-1", &RootIdentity::generate()?)?;
+        assert!(other.get(&device("tablet-1")).is_err());
+
+        // The same identity can, after reopening
+        let reopened = service(&temp_dir, &identity)?;
+        let text = reopened.get(&device("tablet-1"))?.map(|a| a.text);
+        assert_eq!(text.as_deref(), Some("Kitchen tablet"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_backup_opt_in() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp_dir, &identity)?;
+        service.set(&app("com.test.app"), "Approved for kids")?;
+
+        // Excluded by default, never in diagnostics bundles
+        assert!(service.entries_for(Purpose::NetworkBackup)?.is_empty());
+        assert!(service.entries_for(Purpose::DiagnosticsBundle).is_err());
+
+        ConfigService::new(temp_dir.path())?.set_annotations_network_backup(true)?;
+        let included = service.entries_for(Purpose::NetworkBackup)?;
+        assert_eq!(included.len(), 1);
+        assert_eq!(included[0].subject, app("com.test.app"));
+        assert!(service.entries_for(Purpose::DiagnosticsBundle).is_err());
+
+        Ok(())
+    }
+}
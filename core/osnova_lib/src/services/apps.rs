@@ -1,11 +1,100 @@
 use anyhow::{Context, Result};
+use futures::StreamExt;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
 
-use crate::storage::SqlStorage;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cache::GcReport;
+use crate::components::{
+    binary, entry, health, ComponentDownloader, ComponentIntegrity, HealthCheck,
+    NetworkDeclaration, ResolvedFrame, SymbolFile, VerifyReport,
+};
+use crate::error::OsnovaError;
+use crate::manifest::condition::{resolve_config, ConditionContext};
+use crate::manifest::reproducibility::{self, ReproVerdict};
+use crate::manifest::{
+    check_signature_policy, resolve_manifest, validate_uri_scheme, ComponentSchema, ManifestSchema,
+};
+use crate::models::annotation::{Annotation, Subject};
+use crate::models::application::{
+    ComponentKind, ComponentRef, OsnovaApplication, SharedComponentKey,
+};
+use crate::models::backend_process::{BackendProcess, ComponentOwner, ProcessState};
+use crate::models::consent::AppConsent;
+use crate::models::crash_report::CrashReport;
+use crate::models::health::{BackendHealth, HealthTransition, HEALTH_HISTORY};
+use crate::models::identity::RootIdentity;
+use crate::models::install_journal::{
+    InstallJournalEntry, InstallRecovery, InstallResolution, InstallStep,
+};
+use crate::models::launcher_change::LauncherChangeKind;
+use crate::models::launcher_layout::OrderKey;
+use crate::models::materialization::Materialization;
+use crate::models::notification::{Notification, NotificationLevel};
+use crate::models::privacy::{BackendNetworkReport, PrivacyReport};
+use crate::models::provenance::{ManifestOrigin, ReproducibilityCheck};
+use crate::models::task::TaskCategory;
+use crate::network::{CancellationToken, NetworkOptions, TransferStatus};
+use crate::policies::PolicyStore;
+use crate::services::annotations::AnnotationService;
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::handshake::{
+    self, BackendReadiness, LaunchDescriptor, LaunchHandshake, COMPONENT_ID_ENV, RPC_ADDR_ENV,
+    SOCKET_PATH_ENV,
+};
+use crate::services::health::{HealthMonitor, HealthProber, HealthStep};
+use crate::services::metadata::{MetadataRefresh, MetadataService};
+use crate::services::network_proxy::{NetworkProxy, PROXY_URL_ENV, STANDARD_PROXY_ENVS};
+use crate::services::prediction::LaunchPredictor;
+use crate::services::secret_channel::DEFAULT_SECRET_TIMEOUT;
+#[cfg(unix)]
+use crate::services::secret_channel::{SecretChannel, SecretDelivery, SECRETS_SOCKET_ENV};
+use crate::services::updates::ManifestFetcher;
+use crate::services::{
+    ComponentProvenance, ConfigService, LauncherService, NotificationService, ProcessService,
+    RetentionService, StorageService, TaskRegistry,
+};
+use crate::storage::{FileStorage, SqlStorage};
+use crate::time::{self, SharedClock};
+use crate::util::format::{DisplayContext, Localize};
+
+/// Seconds in one day, used to compute trash retention windows
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How often scheduled cache garbage collection runs
+pub const DEFAULT_GC_INTERVAL: Duration = Duration::from_secs(7 * SECONDS_PER_DAY);
+
+/// Review warning: the manifest names no publisher
+pub const WARNING_NO_PUBLISHER: &str = "no-publisher";
+
+/// Review warning: the manifest carries no publisher signature
+pub const WARNING_UNSIGNED: &str = "unsigned";
+
+/// Review warning: the app declares permissions the user has not reviewed
+pub const WARNING_NEW_PERMISSIONS: &str = "new-permissions";
+
+/// Event emitted to the frontend as missing components are downloaded
+pub const APPS_MATERIALIZE_PROGRESS_EVENT: &str = "apps-materialize-progress";
+
+/// Components downloaded at once by [`AppsService::materialize_all`]
+pub const DEFAULT_MATERIALIZE_CONCURRENCY: usize = 3;
+
+/// Event emitted to the frontend as apps of a batch install finish
+pub const APPS_INSTALL_PROGRESS_EVENT: &str = "apps-install-progress";
+
+/// Apps installed at once by [`AppsService::install_many`]
+pub const DEFAULT_INSTALL_CONCURRENCY: usize = 2;
 
 /// Application list response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AppListItem {
     /// Application ID
     pub id: String,
@@ -19,13 +108,551 @@ pub struct AppListItem {
     pub manifest_uri: String,
 }
 
+impl From<&OsnovaApplication> for AppListItem {
+    fn from(app: &OsnovaApplication) -> Self {
+        Self {
+            id: app.id().to_string(),
+            name: app.name().to_string(),
+            version: app.version().to_string(),
+            icon_uri: app.icon_uri().to_string(),
+            manifest_uri: app.id().to_string(), // TODO: Store manifest URI separately
+        }
+    }
+}
+
+/// Install state of an application
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AppInstallState {
+    /// Application is installed and launchable
+    Installed,
+    /// Application was uninstalled and is awaiting purge (restorable)
+    Trashed,
+    /// Application is installed, but the user declined to run it
+    Declined,
+}
+
+/// Application list entry with install state
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppStatusItem {
+    /// Application details
+    #[serde(flatten)]
+    pub app: AppListItem,
+    /// Install state
+    pub state: AppInstallState,
+    /// Unix timestamp when the app was trashed (trashed apps only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<u64>,
+    /// Unix timestamp after which the app is purged (trashed apps only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purge_after: Option<u64>,
+    /// Whether the app's components are cached, as last checked (installed
+    /// apps only; absent until first checked)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub materialization: Option<Materialization>,
+}
+
+/// Application details response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppInfo {
+    /// Application details
+    #[serde(flatten)]
+    pub app: AppListItem,
+    /// Application description
+    pub description: String,
+    /// Publisher identifier, if the manifest names one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    /// Version the app is pinned to, if updates are held back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_version: Option<String>,
+    /// Whether the app's components are cached, as last checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub materialization: Option<Materialization>,
+    /// Provenance summary of each component
+    pub components: Vec<ComponentProvenance>,
+    /// Health of the running backends that declare a health check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backend_health: Vec<BackendHealth>,
+    /// The admin's private note on the app, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<Annotation>,
+    /// Components of the installed version the user rebuilt from source
+    /// and found to match their manifest hashes ("verified locally")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reproducibility: Vec<ReproducibilityCheck>,
+    /// Details formatted for display, once localized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<AppInfoDisplay>,
+}
+
+impl AppInfo {
+    /// Unix timestamp of the most recent download of any component
+    pub fn last_downloaded(&self) -> Option<u64> {
+        self.components
+            .iter()
+            .filter_map(|component| component.latest.as_ref())
+            .map(|record| record.downloaded_at)
+            .max()
+    }
+}
+
+/// [`AppInfo`] details formatted for display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AppInfoDisplay {
+    /// When a component was last downloaded, relative to now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_downloaded: Option<String>,
+}
+
+impl Localize for AppInfo {
+    fn localize(&mut self, context: &DisplayContext) {
+        self.display = Some(AppInfoDisplay {
+            last_downloaded: self
+                .last_downloaded()
+                .map(|timestamp| context.relative_time(timestamp)),
+        });
+    }
+}
+
+/// Which apps [`AppsService::materialize_all`] downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializePolicy {
+    /// Components downloaded at once
+    pub concurrency: usize,
+    /// Only apps last recorded as not (fully) materialized, such as those
+    /// marked after a restore; otherwise every installed app is checked
+    pub unmaterialized_only: bool,
+}
+
+impl Default for MaterializePolicy {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_MATERIALIZE_CONCURRENCY,
+            unmaterialized_only: true,
+        }
+    }
+}
+
+/// A missing component finished downloading, or failed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializeProgress {
+    /// Application the component belongs to
+    pub app_id: String,
+    /// Component identifier
+    pub component_id: String,
+    /// Components finished so far
+    pub completed: usize,
+    /// Missing components to download
+    pub total: usize,
+    /// Why the download failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Materialization of one app after downloading its missing components
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializeOutcome {
+    /// Application identifier
+    pub app_id: String,
+    /// State after the downloads
+    pub state: Materialization,
+    /// Whether the bandwidth policy deferred a download it needed
+    pub deferred: bool,
+}
+
+/// A missing component queued for download
+struct MissingComponent {
+    app_id: String,
+    origin: ManifestOrigin,
+    component: ComponentSchema,
+}
+
+/// An app to install with [`AppsService::install_many`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRequest {
+    /// Manifest URI, which is also the app's ID
+    pub manifest_uri: String,
+    /// Version to pin the app to once installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_version: Option<String>,
+}
+
+/// How [`AppsService::install_many`] runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BatchOptions {
+    /// Apps installed at once
+    pub concurrency: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_INSTALL_CONCURRENCY,
+        }
+    }
+}
+
+/// Whether a request of a batch install will be installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchPreviewStatus {
+    /// The app will be installed
+    Ready,
+    /// The app is installed already, or requested twice; it is skipped
+    AlreadyInstalled,
+    /// The app cannot be installed
+    Invalid,
+    /// The app needs a newer version of Osnova
+    RequiresNewerOsnova,
+    /// The device policy does not allow the app
+    BlockedByPolicy,
+}
+
+/// A request of a batch install, as previewed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPreviewItem {
+    /// Manifest URI requested
+    pub manifest_uri: String,
+    /// Whether the app will be installed
+    pub status: BatchPreviewStatus,
+    /// Application name, once the manifest resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Published version, once the manifest resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Estimated bytes to download
+    pub download_bytes: u64,
+    /// Permissions the app declares, as in [`ConsentReview::permissions`]
+    pub permissions: Vec<String>,
+    /// Why the app cannot be installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Oldest Osnova version the app declares it needs, when this one is
+    /// too old and the manifest says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_core_version: Option<String>,
+    /// Manifest fields this version of Osnova ignores
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// What a batch install will do, for the user to confirm once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchInstallPreview {
+    /// One item per request, in request order
+    pub apps: Vec<BatchPreviewItem>,
+    /// Estimated bytes to download for the apps to install
+    pub download_bytes: u64,
+    /// Permissions declared by the apps to install, sorted
+    pub permissions: Vec<String>,
+}
+
+/// What happened to a request of a batch install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum BatchInstallOutcome {
+    /// The app was installed
+    Installed {
+        /// Warnings about the installed frontends
+        warnings: Vec<String>,
+    },
+    /// The app was installed already and left as it was
+    SkippedAlreadyInstalled,
+    /// The app could not be installed
+    Failed {
+        /// Why
+        reason: String,
+    },
+    /// The batch was cancelled before the install started
+    Cancelled,
+}
+
+/// Outcome of one request of a batch install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchInstallResult {
+    /// Manifest URI requested
+    pub manifest_uri: String,
+    /// What happened
+    pub outcome: BatchInstallOutcome,
+}
+
+/// Outcome of [`AppsService::install_many`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchInstallReport {
+    /// Preview the batch was installed from
+    pub preview: BatchInstallPreview,
+    /// One result per request, in request order
+    pub results: Vec<BatchInstallResult>,
+}
+
+impl BatchInstallReport {
+    /// Apps installed
+    pub fn installed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, BatchInstallOutcome::Installed { .. }))
+    }
+
+    /// Apps that failed to install
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, BatchInstallOutcome::Failed { .. }))
+    }
+
+    fn count(&self, filter: impl Fn(&BatchInstallOutcome) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|result| filter(&result.outcome))
+            .count()
+    }
+}
+
+/// A request of a batch install finished
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchInstallProgress {
+    /// Manifest URI requested
+    pub manifest_uri: String,
+    /// What happened
+    pub outcome: BatchInstallOutcome,
+    /// Requests finished so far
+    pub completed: usize,
+    /// Requests in the batch
+    pub total: usize,
+}
+
+/// What the user reviews before an app's first launch
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentReview {
+    /// Application details
+    pub info: AppInfo,
+    /// Whether the manifest carries a publisher signature
+    pub signed: bool,
+    /// Permissions the installed version declares, such as
+    /// `uriScheme:mailto`, `dataOffer:photos` or `backend`
+    pub permissions: Vec<String>,
+    /// Declared permissions the last decision did not cover
+    pub new_permissions: Vec<String>,
+    /// Network access each backend declares
+    pub network: Vec<BackendNetwork>,
+    /// Warnings to acknowledge (e.g. [`WARNING_UNSIGNED`])
+    pub warnings: Vec<String>,
+    /// The user's last decision for this app, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<AppConsent>,
+}
+
+impl ConsentReview {
+    /// Whether the user declined the installed version
+    pub fn declined(&self) -> bool {
+        self.previous.as_ref().is_some_and(|previous| {
+            !previous.accepted && previous.applies_to(&self.info.app.version, &self.permissions)
+        })
+    }
+}
+
+/// Network access a backend declares, as shown for review
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendNetwork {
+    /// Backend component identifier
+    pub component_id: String,
+    /// Backend name from the manifest
+    pub name: String,
+    /// What the manifest declares; `None` means no network access
+    pub declared: Option<NetworkDeclaration>,
+}
+
+/// Launch refused because the user has not agreed to run the installed
+/// version of an app
+///
+/// Carries the review to show, so the caller can ask for consent and
+/// record it with [`AppsService::record_consent`].
+#[derive(Debug, Clone)]
+pub struct ConsentRequired {
+    /// What the user should review
+    pub review: ConsentReview,
+}
+
+impl fmt::Display for ConsentRequired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let app = &self.review.info.app;
+        if self.review.declined() {
+            write!(f, "Launching {} {} was declined", app.id, app.version)
+        } else {
+            write!(
+                f,
+                "{} {} needs review before it can launch",
+                app.id, app.version
+            )
+        }
+    }
+}
+
+impl std::error::Error for ConsentRequired {}
+
+/// Entry page of an installed frontend, for launch and asset serving
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendEntry {
+    /// Frontend component ID
+    pub component_id: String,
+    /// Directory the frontend bundle is extracted to
+    pub root: PathBuf,
+    /// Entry page, relative to `root` with `/` separators
+    pub entry: String,
+}
+
+impl FrontendEntry {
+    /// Path of the entry page
+    pub fn entry_file(&self) -> PathBuf {
+        entry::entry_file(&self.root, &self.entry)
+    }
+
+    /// File that serves `request`, a path relative to the entry page
+    ///
+    /// An empty request serves the entry page. Returns `None` for requests
+    /// that leave the bundle.
+    pub fn asset_path(&self, request: &str) -> Option<PathBuf> {
+        let request = request.split(['?', '#']).next().unwrap_or_default();
+        let request = request.trim_start_matches('/');
+        if request.is_empty() {
+            return Some(self.entry_file());
+        }
+        entry::validate_entry(request).ok()?;
+
+        let base = match self.entry.rsplit_once('/') {
+            Some((dir, _)) => entry::entry_file(&self.root, dir),
+            None => self.root.clone(),
+        };
+        Some(entry::entry_file(&base, request))
+    }
+}
+
+/// Native backtrace of a crash report, resolved against debug symbols
+///
+/// Symbolication is best-effort: when no symbols can be found the frames
+/// are returned unresolved and `symbolicated` is `false`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolicatedReport {
+    /// Crash report the frames are from
+    pub report_id: String,
+    /// Whether symbols were found and applied
+    pub symbolicated: bool,
+    /// Why symbols are unavailable, when they are
+    pub unavailable_reason: Option<String>,
+    /// Backtrace frames found in the report's stderr tail, innermost first
+    pub frames: Vec<ResolvedFrame>,
+}
+
+/// Builds the command that runs a backend component
+pub type BackendCommand = Arc<dyn Fn(&ComponentSchema) -> Command + Send + Sync>;
+
+/// Called after each install step is journaled; an error stops the
+/// install there, as a crash would (for testing recovery)
+pub type InstallFailpoint = Arc<dyn Fn(InstallStep) -> Result<()> + Send + Sync>;
+
+/// State of a shared component known to the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedComponentStatus {
+    /// Shared component identity
+    pub key: SharedComponentKey,
+    /// Installed apps whose manifests reference the component
+    pub installed_by: Vec<String>,
+    /// Process id of the running instance, if any
+    pub pid: Option<u32>,
+    /// Running apps holding a reference to the instance
+    pub active_apps: Vec<String>,
+}
+
+/// App that opens a URI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HandlerInfo {
+    /// Scheme of the URI (lowercase)
+    pub scheme: String,
+    /// Application that handles the scheme
+    pub app_id: String,
+    /// Application name
+    pub app_name: String,
+    /// Route to open the app at: the full URI
+    pub initial_route: String,
+}
+
+/// Apps registered for a URI scheme
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UriSchemeHandlers {
+    /// URI scheme (lowercase)
+    pub scheme: String,
+    /// Application that currently handles the scheme
+    pub app_id: String,
+    /// Installed apps claiming the scheme, first installed first
+    pub candidates: Vec<String>,
+    /// Whether the handler was chosen by the user rather than install order
+    pub overridden: bool,
+}
+
+/// Running instance of a shared backend component
+struct SharedInstance {
+    pid: u32,
+    apps: BTreeSet<String>,
+}
+
 /// Application management service
 ///
 /// Provides OpenRPC methods:
 /// - `apps.list` - List all installed applications
+/// - `apps.info` - Details and component provenance of an installed app
 /// - `apps.launch` - Launch an application by ID
 /// - `apps.install` - Install a new application from manifest URI
 /// - `apps.uninstall` - Remove an installed application
+/// - `apps.restore` - Restore a recently uninstalled application
+/// - `apps.listTrash` - List recently uninstalled applications
+/// - `apps.verify` - Check installed components for corruption
+/// - `apps.repair` - Re-download components that fail verification
+/// - `apps.listUriHandlers` - URI schemes claimed by installed apps
+/// - `apps.setUriHandler` - Choose which app handles a URI scheme
+/// - `apps.launchDescriptor` - Backends of a launched app and their readiness
+/// - `apps.healthHistory` - Health transitions of an app's backends
+/// - `apps.pin` - Hold an app at its installed version
+/// - `apps.unpin` - Let an app update again
+///
+/// Uninstalled apps are kept in a recently-deleted (trash) state for a
+/// configurable number of days. Their configuration and keys are retained
+/// so that a restore brings the app back as it was.
+///
+/// Each installed app keeps a snapshot of the manifest of its installed
+/// version; verification and repair work from it, so they are unaffected
+/// by what is currently published or whether the app is pinned.
+///
+/// Components marked `shared` in app manifests are cached once per
+/// `sharedId` and version. With a [`ProcessService`] attached, a shared
+/// backend runs as a single process no matter how many apps use it; it is
+/// reference-counted by the running apps and stopped with the last one.
+///
+/// Apps may claim URI schemes (`uriSchemes` in the manifest) so deep links
+/// route to them. When several installed apps claim a scheme, the first
+/// installed handles it unless the user picks another in settings.
+///
+/// With a [`LaunchHandshake`] attached, each launch records the backends
+/// the frontend depends on, and [`launch_and_wait`](Self::launch_and_wait)
+/// fails with a clear error when one never reports ready or reports the
+/// wrong version. With a [`HealthMonitor`] attached, backends declaring a
+/// health check are checked while they run (see
+/// [`check_backend_health`](Self::check_backend_health)).
 ///
 /// # Example
 ///
@@ -44,7 +671,33 @@ pub struct AppListItem {
 /// # }
 /// ```
 pub struct AppsService {
+    storage_path: PathBuf,
     sql_storage: SqlStorage,
+    config: ConfigService,
+    downloader: Option<Arc<ComponentDownloader>>,
+    events: Option<EventBus>,
+    processes: Option<Arc<ProcessService>>,
+    handshake: Option<Arc<LaunchHandshake>>,
+    metadata: Option<Arc<MetadataService>>,
+    backend_command: BackendCommand,
+    shared_instances: Mutex<HashMap<SharedComponentKey, SharedInstance>>,
+    user_id: String,
+    audit: Option<Arc<AuditLog>>,
+    notifications: Option<Arc<NotificationService>>,
+    install_failpoint: Option<InstallFailpoint>,
+    conditions: ConditionContext,
+    storage: Option<Arc<StorageService>>,
+    retention: Option<Arc<RetentionService>>,
+    tasks: Option<Arc<TaskRegistry>>,
+    health: Option<Arc<HealthMonitor>>,
+    health_prober: HealthProber,
+    annotations: Option<Arc<AnnotationService>>,
+    network_proxy: Option<Arc<NetworkProxy>>,
+    policies: Option<Arc<PolicyStore>>,
+    predictor: Option<Arc<LaunchPredictor>>,
+    fetch_manifest: ManifestFetcher,
+    secret_timeout: Duration,
+    clock: SharedClock,
 }
 
 impl AppsService {
@@ -56,8 +709,245 @@ impl AppsService {
     pub fn new<P: Into<PathBuf>>(storage_path: P) -> Result<Self> {
         let storage_path = storage_path.into();
         let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let config = ConfigService::new(&storage_path)?;
+
+        Ok(Self {
+            storage_path,
+            sql_storage,
+            config,
+            downloader: None,
+            events: None,
+            processes: None,
+            handshake: None,
+            metadata: None,
+            backend_command: Arc::new(|component| {
+                let prepared = ComponentDownloader::prepared_path(component);
+                match &component.interpreter {
+                    Some(interpreter) => {
+                        let mut command = Command::new(interpreter);
+                        command.arg(prepared);
+                        command
+                    }
+                    None => Command::new(prepared),
+                }
+            }),
+            shared_instances: Mutex::new(HashMap::new()),
+            user_id: String::new(),
+            audit: None,
+            notifications: None,
+            install_failpoint: None,
+            conditions: ConditionContext::current(),
+            storage: None,
+            retention: None,
+            tasks: None,
+            health: None,
+            health_prober: Arc::new(|check, socket| {
+                Box::pin(async move {
+                    health::probe(&check, &socket)
+                        .await
+                        .map_err(|e| e.to_string())
+                })
+            }),
+            annotations: None,
+            network_proxy: None,
+            policies: None,
+            predictor: None,
+            fetch_manifest: Arc::new(|uri| {
+                Box::pin(async move { Ok(resolve_manifest(&uri, None).await?) })
+            }),
+            secret_timeout: DEFAULT_SECRET_TIMEOUT,
+            clock: time::default_clock(),
+        })
+    }
+
+    /// Enforce the group policy of a managed device on installs and
+    /// launches
+    pub fn with_policies(mut self, policies: Arc<PolicyStore>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Teach a launch predictor when apps are launched
+    pub fn with_predictor(mut self, predictor: Arc<LaunchPredictor>) -> Self {
+        self.predictor = Some(predictor);
+        self
+    }
+
+    /// Keep launch consent for a specific user
+    pub fn with_user(mut self, user_id: &str) -> Self {
+        self.user_id = user_id.to_string();
+        self
+    }
+
+    /// Record consent decisions and secret settings changes in an audit
+    /// log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.config = self.config.with_audit(audit.clone());
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Read and move secret settings under a key derived from the user's
+    /// identity
+    ///
+    /// Without it, launching an app with secret settings set is refused.
+    pub fn with_identity(mut self, identity: &RootIdentity) -> Result<Self> {
+        self.config = self.config.with_identity(identity)?;
+        Ok(self)
+    }
+
+    /// Use a specific clock for launch, consent, install and trash
+    /// timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long a launched backend has to collect its secret settings
+    ///
+    /// Defaults to [`DEFAULT_SECRET_TIMEOUT`].
+    pub fn with_secret_timeout(mut self, timeout: Duration) -> Self {
+        self.secret_timeout = timeout;
+        self
+    }
+
+    /// Publish install state changes on an event bus
+    ///
+    /// Settings migrations of installs are published on it too.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.config = self.config.with_events(events.clone());
+        self.events = Some(events);
+        self
+    }
+
+    /// Attach the component downloader used for verification and repair
+    pub fn with_downloader(mut self, downloader: ComponentDownloader) -> Self {
+        self.downloader = Some(Arc::new(downloader));
+        self
+    }
+
+    /// Attach the process supervisor used to run backend components
+    pub fn with_processes(mut self, processes: Arc<ProcessService>) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
+    /// Record launched backends and their readiness in a handshake
+    pub fn with_handshake(mut self, handshake: Arc<LaunchHandshake>) -> Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
+    /// Report recovered installs in the notification center
+    pub fn with_notifications(mut self, notifications: Arc<NotificationService>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    /// Add a report-only storage compaction to scheduled maintenance
+    pub fn with_storage(mut self, storage: Arc<StorageService>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Add a pass over the retention policies to scheduled maintenance
+    pub fn with_retention(mut self, retention: Arc<RetentionService>) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Show materializing every app as a task in `tasks`
+    ///
+    /// Its progress also goes out as the `apps-materialize-progress`
+    /// events published before tasks existed.
+    pub fn with_tasks(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Check the health of running backends that declare a health check
+    ///
+    /// See [`check_backend_health`](Self::check_backend_health).
+    pub fn with_health(mut self, health: Arc<HealthMonitor>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Show the user's notes on apps in [`info`](Self::info)
+    pub fn with_annotations(mut self, annotations: Arc<AnnotationService>) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Route the network access of launched backends through a proxy
+    /// enforcing what they declare
+    ///
+    /// Also where [`privacy_report`](Self::privacy_report) finds the hosts
+    /// backends reached.
+    pub fn with_network_proxy(mut self, proxy: Arc<NetworkProxy>) -> Self {
+        self.network_proxy = Some(proxy);
+        self
+    }
+
+    /// Override how backends are health-checked (for testing)
+    ///
+    /// Defaults to running the declared probe against the backend.
+    pub fn with_health_prober<F, Fut>(mut self, prober: F) -> Self
+    where
+        F: Fn(HealthCheck, PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), String>> + Send + 'static,
+    {
+        self.health_prober = Arc::new(move |check, socket| Box::pin(prober(check, socket)));
+        self
+    }
+
+    /// Resolve conditional config values against `context` instead of this
+    /// device (for testing)
+    pub fn with_condition_context(mut self, context: ConditionContext) -> Self {
+        self.conditions = context;
+        self
+    }
+
+    /// Stop installs at a step, as a crash would (for testing)
+    pub fn with_install_failpoint<F>(mut self, failpoint: F) -> Self
+    where
+        F: Fn(InstallStep) -> Result<()> + Send + Sync + 'static,
+    {
+        self.install_failpoint = Some(Arc::new(failpoint));
+        self
+    }
 
-        Ok(Self { sql_storage })
+    /// Override how manifests of apps to install are fetched
+    ///
+    /// Defaults to resolving the manifest URI (file:// and https:// only).
+    pub fn with_manifest_fetcher<F, Fut>(mut self, fetch: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ManifestSchema>> + Send + 'static,
+    {
+        self.fetch_manifest = Arc::new(move |uri| Box::pin(fetch(uri)));
+        self
+    }
+
+    /// Refresh listings through a shared metadata service
+    ///
+    /// Without one, each refresh resolves manifests from the app ID and
+    /// publishes changes on this service's event bus.
+    pub fn with_metadata(mut self, metadata: Arc<MetadataService>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Override how backend components are started
+    ///
+    /// Defaults to executing the prepared component binary, or running it
+    /// with the component's declared interpreter.
+    pub fn with_backend_command<F>(mut self, backend_command: F) -> Self
+    where
+        F: Fn(&ComponentSchema) -> Command + Send + Sync + 'static,
+    {
+        self.backend_command = Arc::new(backend_command);
+        self
     }
 
     /// List all installed applications (OpenRPC: apps.list)
@@ -78,24 +968,113 @@ impl AppsService {
     pub fn list(&self) -> Result<Vec<AppListItem>> {
         let apps = self.sql_storage.list_applications()?;
 
-        Ok(apps
-            .into_iter()
-            .map(|app| AppListItem {
-                id: app.id().to_string(),
-                name: app.name().to_string(),
-                version: app.version().to_string(),
-                icon_uri: app.icon_uri().to_string(),
-                manifest_uri: app.id().to_string(), // TODO: Store manifest URI separately
+        Ok(apps.iter().map(AppListItem::from).collect())
+    }
+
+    /// Generation of the latest launcher change
+    ///
+    /// See [`LauncherService::generation`]. Read it before listing apps, so
+    /// a change made in between is reported again rather than missed.
+    pub fn generation(&self) -> Result<u64> {
+        self.sql_storage.launcher_generation()
+    }
+
+    /// Get details of an installed application (OpenRPC: apps.info)
+    ///
+    /// Includes a provenance summary for each component: where and when it
+    /// was last downloaded and how often it has been fetched.
+    pub fn info(&self, app_id: &str) -> Result<AppInfo> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+
+        let components = app
+            .components()
+            .iter()
+            .map(|component| ComponentProvenance::load(&self.sql_storage, component))
+            .collect::<Result<Vec<_>>>()?;
+
+        let backend_health = match &self.health {
+            Some(monitor) => app
+                .components_by_kind(ComponentKind::Backend)
+                .iter()
+                .filter_map(|component| monitor.health(app_id, component.id()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let annotation = match &self.annotations {
+            Some(annotations) => annotations.get(&Subject::App(app_id.to_string()))?,
+            None => None,
+        };
+
+        Ok(AppInfo {
+            app: AppListItem::from(&app),
+            description: app.description().to_string(),
+            publisher: app.publisher().map(str::to_string),
+            pinned_version: self.sql_storage.get_pinned_version(app_id)?,
+            materialization: self.sql_storage.get_materialization(app_id)?,
+            components,
+            backend_health,
+            annotation,
+            reproducibility: self
+                .sql_storage
+                .list_reproducibility_checks(app_id, app.version())?,
+            display: None,
+        })
+    }
+
+    /// List applications with their install state
+    ///
+    /// # Arguments
+    ///
+    /// * `include_trashed` - Also include apps in the recently-deleted state
+    pub fn list_with_status(&self, include_trashed: bool) -> Result<Vec<AppStatusItem>> {
+        let mut materialization = self.sql_storage.list_materialization()?;
+        let mut items: Vec<AppStatusItem> = self
+            .sql_storage
+            .list_applications()?
+            .iter()
+            .map(|app| {
+                let declined = self
+                    .applicable_consent(app)?
+                    .is_some_and(|consent| !consent.accepted);
+                Ok(AppStatusItem {
+                    app: AppListItem::from(app),
+                    state: if declined {
+                        AppInstallState::Declined
+                    } else {
+                        AppInstallState::Installed
+                    },
+                    deleted_at: None,
+                    purge_after: None,
+                    materialization: materialization.remove(app.id()),
+                })
             })
-            .collect())
+            .collect::<Result<_>>()?;
+
+        if include_trashed {
+            items.extend(self.list_trash()?);
+        }
+
+        Ok(items)
     }
 
     /// Launch an application by ID (OpenRPC: apps.launch)
     ///
+    /// Unless the requirement is turned off in [`ConfigService`], the user
+    /// must first have agreed to run the installed version; see
+    /// [`Self::record_consent`].
+    ///
     /// # Arguments
     ///
     /// * `app_id` - Application ID to launch
     ///
+    /// # Errors
+    ///
+    /// Returns [`ConsentRequired`] if the user has not agreed to run the
+    /// installed version, or declined it
+    /// or [`OsnovaError::PolicyBlocked`] if the device policy does not
+    /// allow the app
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -108,187 +1087,5537 @@ impl AppsService {
     /// ```
     pub fn launch(&self, app_id: &str) -> Result<()> {
         // Verify app exists
-        let _app = self
+        let app = self
             .sql_storage
             .get_application(app_id)?
             .context(format!("Application {} not found", app_id))?;
+        self.check_policy(&app)?;
+
+        if self.config.get_first_launch_consent_required()? {
+            let accepted = self
+                .applicable_consent(&app)?
+                .is_some_and(|consent| consent.accepted);
+            if !accepted {
+                let review = self.review_of(&app)?;
+                return Err(ConsentRequired { review }.into());
+            }
+        }
+
+        self.register_shared_components(&app)?;
+        self.sql_storage
+            .record_app_launch(app_id, self.clock.now_unix())?;
+        if let Some(predictor) = &self.predictor {
+            predictor.record_launch(app_id)?;
+        }
+
+        let backends = app.components_by_kind(ComponentKind::Backend);
+        let owner = ComponentOwner::App(app_id.to_string());
+        let mut own = match &self.processes {
+            Some(processes) => processes
+                .list()?
+                .into_iter()
+                .filter(|p| p.owner() == owner)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // Backends kept warm are reused unless the app changed since
+        let stale = own.iter().any(|process| {
+            process.is_idle()
+                && !backends.iter().any(|component| {
+                    process.component_id() == Some(component.id())
+                        && process.component_version() == Some(component.version())
+                })
+        });
+        if stale {
+            self.stop(app_id)?;
+            own.clear();
+        }
+        let running = !own.is_empty();
+        let warm = running && own.iter().all(BackendProcess::is_idle);
+        if let Some(monitor) = self.health.as_ref().filter(|_| !running) {
+            monitor.forget(app_id);
+        }
+        if let Some(processes) = self.processes.as_ref().filter(|_| warm) {
+            for process in &own {
+                processes.set_state(process.pid(), ProcessState::Active)?;
+            }
+        }
 
-        // TODO: Actually launch the application
-        // For now, this is a stub that just verifies the app exists
+        // Launching a running app again keeps its descriptor
+        if let Some(handshake) = &self.handshake {
+            let resumed = warm && handshake.resume(app_id).is_some();
+            if !resumed && (!running || handshake.descriptor(app_id).is_none()) {
+                let expected = backends
+                    .iter()
+                    .map(|component| {
+                        let socket = match component.shared_key() {
+                            Some(_) => {
+                                self.backend_socket(&Self::shared_owner(component), component)
+                            }
+                            None => self.backend_socket(&owner, component),
+                        };
+                        BackendReadiness::starting(
+                            component.id(),
+                            component.name(),
+                            component.version(),
+                        )
+                        .with_socket_path(socket)
+                        .with_shared(component.shared_key().is_some())
+                    })
+                    .collect();
+                handshake.begin(app_id, expected);
+            }
+        }
+
+        let Some(processes) = &self.processes else {
+            return Ok(());
+        };
+        for component in backends {
+            // Shared backends are started once and reference-counted
+            let started = match component.shared_key() {
+                Some(key) => self.acquire_shared(processes, app_id, &key, component),
+                None if running => Ok(()),
+                None => self.spawn_backend(processes, &owner, component).map(|_| ()),
+            };
+            if let Err(e) = started {
+                if let Some(handshake) = &self.handshake {
+                    handshake.fail(app_id, component.id(), &format!("failed to start: {:#}", e));
+                }
+                return Err(e);
+            }
+        }
+
+        // Fail here rather than open a blank window
+        self.frontend_entry_of(&app)?;
+        // TODO: Open the frontend window at its entry page
         Ok(())
     }
 
-    /// Install a new application from manifest URI (OpenRPC: apps.install)
-    ///
-    /// # Arguments
-    ///
-    /// * `manifest_uri` - URI to the application manifest (ant:// or local path)
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// # use osnova_lib::services::AppsService;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = AppsService::new("/tmp/storage")?;
-    /// service.install("ant://manifest-address")?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn install(&self, _manifest_uri: &str) -> Result<()> {
-        // TODO: Implement manifest fetching and parsing
-        // TODO: Download and cache components
-        // TODO: Store application in database
-        anyhow::bail!("Application installation not yet implemented")
+    /// What the user should review before launching an app
+    /// (OpenRPC: apps.consentReview)
+    pub fn consent_review(&self, app_id: &str) -> Result<ConsentReview> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        self.review_of(&app)
     }
 
-    /// Uninstall an application (OpenRPC: apps.uninstall)
-    ///
-    /// # Arguments
+    /// Record whether the user agrees to run an app (OpenRPC: apps.recordConsent)
     ///
-    /// * `app_id` - Application ID to uninstall
+    /// The decision covers the installed version and later versions with
+    /// the same major version that declare no new permissions. Declined
+    /// apps stay installed but do not launch.
     ///
-    /// # Example
+    /// # Arguments
     ///
-    /// ```no_run
-    /// # use osnova_lib::services::AppsService;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = AppsService::new("/tmp/storage")?;
-    /// service.uninstall("com.example.app")?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn uninstall(&self, app_id: &str) -> Result<()> {
-        let deleted = self.sql_storage.delete_application(app_id)?;
+    /// * `app_id` - Application reviewed
+    /// * `accepted` - Whether the user agreed to run it
+    /// * `acknowledged_warnings` - Review warnings the user acknowledged
+    pub fn record_consent(
+        &self,
+        app_id: &str,
+        accepted: bool,
+        acknowledged_warnings: Vec<String>,
+    ) -> Result<AppConsent> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let consent = AppConsent {
+            app_id: app_id.to_string(),
+            version: app.version().to_string(),
+            accepted,
+            acknowledged_warnings,
+            permissions: Self::declared_permissions(&app),
+            decided_at: self.clock.now_unix(),
+        };
+        self.sql_storage
+            .upsert_app_consent(&self.user_id, &consent)?;
 
-        if !deleted {
-            anyhow::bail!("Application {} not found", app_id);
+        if let Some(audit) = &self.audit {
+            let action = if accepted {
+                AuditAction::AppConsentGiven
+            } else {
+                AuditAction::AppConsentDeclined
+            };
+            audit.append(
+                action,
+                serde_json::json!({
+                    "appId": consent.app_id,
+                    "version": consent.version,
+                    "permissions": consent.permissions,
+                    "acknowledgedWarnings": consent.acknowledged_warnings,
+                }),
+            )?;
         }
+        Ok(consent)
+    }
 
-        // TODO: Clean up cached components
-        Ok(())
+    /// The user's decision covering the installed version of `app`
+    fn applicable_consent(&self, app: &OsnovaApplication) -> Result<Option<AppConsent>> {
+        let permissions = Self::declared_permissions(app);
+        Ok(self
+            .sql_storage
+            .get_app_consent(&self.user_id, app.id())?
+            .filter(|consent| consent.applies_to(app.version(), &permissions)))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::application::OsnovaApplication;
-    use tempfile::TempDir;
+    /// Build the consent review of an installed app
+    fn review_of(&self, app: &OsnovaApplication) -> Result<ConsentReview> {
+        let info = self.info(app.id())?;
+        let permissions = Self::declared_permissions(app);
+        let previous = self.sql_storage.get_app_consent(&self.user_id, app.id())?;
+        let new_permissions = match &previous {
+            Some(previous) => previous.new_permissions(&permissions),
+            None => permissions.clone(),
+        };
 
-    fn create_test_service() -> Result<(AppsService, TempDir)> {
+        let signed = app.signature().is_some();
+        let mut warnings = Vec::new();
+        if info.publisher.is_none() {
+            warnings.push(WARNING_NO_PUBLISHER.to_string());
+        }
+        if !signed {
+            warnings.push(WARNING_UNSIGNED.to_string());
+        }
+        if previous.is_some() && !new_permissions.is_empty() {
+            warnings.push(WARNING_NEW_PERMISSIONS.to_string());
+        }
+
+        Ok(ConsentReview {
+            info,
+            signed,
+            permissions,
+            new_permissions,
+            network: Self::declared_network(app),
+            warnings,
+            previous,
+        })
+    }
+
+    /// Permissions an app declares, sorted
+    fn declared_permissions(app: &OsnovaApplication) -> Vec<String> {
+        let mut permissions: BTreeSet<String> = app
+            .uri_schemes()
+            .iter()
+            .map(|scheme| format!("uriScheme:{}", scheme))
+            .chain(
+                app.data_offers()
+                    .iter()
+                    .map(|offer| format!("dataOffer:{}", offer.id)),
+            )
+            .collect();
+        if !app.components_by_kind(ComponentKind::Backend).is_empty() {
+            permissions.insert("backend".to_string());
+        }
+        for backend in Self::declared_network(app) {
+            permissions.extend(backend.declared.iter().flat_map(|d| d.permissions()));
+        }
+        permissions.into_iter().collect()
+    }
+
+    /// Network access each backend of `app` declares
+    fn declared_network(app: &OsnovaApplication) -> Vec<BackendNetwork> {
+        app.components_by_kind(ComponentKind::Backend)
+            .into_iter()
+            .map(|component| BackendNetwork {
+                component_id: component.id().to_string(),
+                name: component.name().to_string(),
+                declared: ComponentSchema::from(component).declared_network(),
+            })
+            .collect()
+    }
+
+    /// Entry page of an app's frontend (OpenRPC: apps.frontendEntry)
+    ///
+    /// Returns `None` if the app has no frontend or it has not been
+    /// extracted. Apps installed before entries were recorded are resolved
+    /// on demand.
+    pub fn frontend_entry(&self, app_id: &str) -> Result<Option<FrontendEntry>> {
+        let app = self
+            .sql_storage
+            .get_application(app_id)?
+            .context(format!("Application {} not found", app_id))?;
+        self.frontend_entry_of(&app)
+    }
+
+    /// Entry page of the first extracted frontend of `app`
+    fn frontend_entry_of(&self, app: &OsnovaApplication) -> Result<Option<FrontendEntry>> {
+        for component in app.components_by_kind(ComponentKind::Frontend) {
+            let schema = ComponentSchema::from(component);
+            let root = ComponentDownloader::prepared_path(&schema);
+            if !root.is_dir() {
+                continue;
+            }
+
+            let entry = match component.entry() {
+                Some(entry) => entry.to_string(),
+                None => {
+                    entry::resolve_entry(&root, schema.declared_entry())
+                        .with_context(|| format!("Frontend {} has no entry page", schema.name))?
+                        .path
+                }
+            };
+            return Ok(Some(FrontendEntry {
+                component_id: component.id().to_string(),
+                root,
+                entry,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Launch an application and wait for its backends to report ready
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the launch fails, or if a backend does not
+    /// report ready within the handshake timeout or reports a different
+    /// version than the manifest pins
+    pub async fn launch_and_wait(&self, app_id: &str) -> Result<LaunchDescriptor> {
+        self.launch(app_id)?;
+        self.wait_ready(app_id).await
+    }
+
+    /// Wait for the backends of a launched application to report ready
+    pub async fn wait_ready(&self, app_id: &str) -> Result<LaunchDescriptor> {
+        self.handshake
+            .as_ref()
+            .context("Launch handshake not configured")?
+            .wait_ready(app_id)
+            .await
+    }
+
+    /// Whether launches wait on a [`LaunchHandshake`]
+    pub fn has_handshake(&self) -> bool {
+        self.handshake.is_some()
+    }
+
+    /// Backends of a launched app and their readiness (OpenRPC: apps.launchDescriptor)
+    ///
+    /// Returns `None` if the app has not been launched, or no handshake is
+    /// attached.
+    pub fn launch_descriptor(&self, app_id: &str) -> Option<LaunchDescriptor> {
+        self.handshake.as_ref()?.descriptor(app_id)
+    }
+
+    /// Close an application's UI (OpenRPC: apps.close)
+    ///
+    /// With the warm pool on (see
+    /// [`RuntimeSettings`](crate::services::RuntimeSettings)) and the app among
+    /// the most launched, its own backends keep running idle and its shared
+    /// backends stay referenced, so the next launch reuses them and only
+    /// reloads the frontend. Otherwise the app is stopped. Idle backends
+    /// over the pool's limits are then reaped.
+    ///
+    /// Returns whether the app's backends were kept warm.
+    pub fn close(&self, app_id: &str) -> Result<bool> {
+        let Some(processes) = &self.processes else {
+            self.stop(app_id)?;
+            return Ok(false);
+        };
+        let settings = self.config.get_runtime_settings()?;
+        let owner = ComponentOwner::App(app_id.to_string());
+        let own: Vec<u32> = processes
+            .list()?
+            .iter()
+            .filter(|p| p.owner() == owner)
+            .map(BackendProcess::pid)
+            .collect();
+
+        let eligible = settings.warm_pool
+            && !own.is_empty()
+            && self
+                .sql_storage
+                .most_launched_apps(settings.warm_pool_size)?
+                .iter()
+                .any(|id| id == app_id);
+        if !eligible {
+            self.stop(app_id)?;
+            return Ok(false);
+        }
+
+        for pid in own {
+            processes.set_state(pid, ProcessState::Idle)?;
+        }
+        let reaped = self.reap_idle_backends()?;
+        Ok(!reaped.iter().any(|id| id == app_id))
+    }
+
+    /// Stop idle backends past the warm pool's idle timeout or memory
+    /// budget, or all of them once the pool is off
+    ///
+    /// Runs when an app closes and with scheduled maintenance. Returns the
+    /// apps stopped.
+    pub fn reap_idle_backends(&self) -> Result<Vec<String>> {
+        let Some(processes) = &self.processes else {
+            return Ok(Vec::new());
+        };
+        let settings = self.config.get_runtime_settings()?;
+        let reaped = processes.idle_apps_to_reap(&settings)?;
+        for app_id in &reaped {
+            self.stop(app_id)?;
+        }
+        Ok(reaped)
+    }
+
+    /// Stop a running application
+    ///
+    /// Stops the app's own backend processes and releases its references to
+    /// shared backends; a shared backend stops when its last app stops.
+    pub fn stop(&self, app_id: &str) -> Result<()> {
+        if let Some(handshake) = &self.handshake {
+            handshake.end(app_id);
+        }
+        if let Some(monitor) = &self.health {
+            monitor.forget(app_id);
+        }
+        let Some(processes) = &self.processes else {
+            return Ok(());
+        };
+
+        let mut released = Vec::new();
+        self.shared_instances.lock().unwrap().retain(|_, instance| {
+            if instance.apps.remove(app_id) && instance.apps.is_empty() {
+                released.push(instance.pid);
+                return false;
+            }
+            true
+        });
+
+        for pid in released {
+            if processes.owner_of(pid)?.is_some() {
+                processes.stop(pid)?;
+            }
+        }
+
+        processes.stop_app(app_id)
+    }
+
+    /// Check the health of running backends that declare a health check
+    ///
+    /// Runs the checks that are due, then restarts or blocks unhealthy
+    /// backends as the [`HealthPolicy`](crate::services::health::HealthPolicy)
+    /// in the runtime settings says. Each transition is kept in the app's
+    /// health history, published as [`AppEvent::BackendHealthChanged`], and
+    /// the backend's health recorded in its launch descriptor. Shared
+    /// backends are not checked.
+    ///
+    /// Returns the transitions.
+    pub async fn check_backend_health(&self) -> Result<Vec<HealthTransition>> {
+        let (Some(monitor), Some(processes)) = (&self.health, &self.processes) else {
+            return Ok(Vec::new());
+        };
+        let policy = self.config.get_runtime_settings()?.health_policy;
+
+        let mut transitions = Vec::new();
+        for process in processes.list()? {
+            let owner = process.owner();
+            let (ComponentOwner::App(app_id), Some(component_id)) =
+                (&owner, process.component_id())
+            else {
+                continue;
+            };
+            let Some(app) = self.sql_storage.get_application(app_id)? else {
+                continue;
+            };
+            let Some(component) = app.components().iter().find(|c| c.id() == component_id) else {
+                continue;
+            };
+            let Some(check) = ComponentSchema::from(component).declared_health() else {
+                continue;
+            };
+
+            match monitor.next_step(app_id, component_id, &policy) {
+                HealthStep::Wait => {}
+                HealthStep::Probe => {
+                    let socket = match process.socket_path() {
+                        Some(socket) => socket.to_path_buf(),
+                        None => self.backend_socket(&owner, component),
+                    };
+                    let result = (self.health_prober)(check.clone(), socket).await;
+                    transitions.extend(monitor.record_probe(app_id, component_id, &check, result));
+                }
+                HealthStep::Restart => {
+                    processes.stop(process.pid())?;
+                    transitions.push(monitor.record_restart(app_id, component_id, &check));
+                    if let Some(handshake) = &self.handshake {
+                        handshake.restart(app_id, component_id);
+                    }
+                    if let Err(e) = self.spawn_backend(processes, &owner, component) {
+                        if let Some(handshake) = &self.handshake {
+                            handshake.fail(
+                                app_id,
+                                component_id,
+                                &format!("failed to restart: {:#}", e),
+                            );
+                        }
+                    }
+                }
+                HealthStep::Block => {
+                    processes.stop(process.pid())?;
+                    transitions.push(monitor.record_block(app_id, component_id));
+                    if let Some(handshake) = &self.handshake {
+                        handshake.fail(
+                            app_id,
+                            component_id,
+                            "was stopped after failing its health checks",
+                        );
+                    }
+                }
+            }
+            if let (Some(handshake), Some(health)) =
+                (&self.handshake, monitor.health(app_id, component_id))
+            {
+                handshake.set_health(app_id, health);
+            }
+        }
+
+        for transition in &transitions {
+            self.sql_storage
+                .record_health_transition(transition, HEALTH_HISTORY)?;
+            if let Some(events) = &self.events {
+                events.publish(AppEvent::BackendHealthChanged {
+                    transition: transition.clone(),
+                });
+            }
+        }
+        Ok(transitions)
+    }
+
+    /// Check backend health every `interval` until `shutdown` is cancelled
+    ///
+    /// `interval` only bounds how late a check runs; each backend is
+    /// checked at the interval its manifest declares.
+    pub async fn run_health_checks(
+        self: Arc<Self>,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            // A failed round is retried on the next tick
+            let _ = self.check_backend_health().await;
+        }
+    }
+
+    /// Compare a local build of an installed component with the hash its
+    /// manifest declares (OpenRPC: apps.verifyReproducible)
+    ///
+    /// The build is normalized as packaging does (see
+    /// [`reproducibility`]). A match is recorded for the installed version
+    /// and shown in [`info`](Self::info) until the app is updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Installed application
+    /// * `component_id` - Component as listed in the manifest
+    /// * `local_artifact_path` - Build output directory, archive, or binary
+    pub fn verify_reproducible(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        local_artifact_path: &Path,
+    ) -> Result<ReproVerdict> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let manifest = match self.sql_storage.get_manifest_snapshot(app_id)? {
+            Some(manifest) => manifest,
+            None => ManifestSchema::from(&app),
+        };
+
+        let verdict =
+            reproducibility::verify_reproducible(&manifest, component_id, local_artifact_path);
+        if let ReproVerdict::Match { hash } = &verdict {
+            self.sql_storage
+                .record_reproducibility_check(&ReproducibilityCheck {
+                    app_id: app_id.to_string(),
+                    app_version: app.version().to_string(),
+                    component_id: component_id.to_string(),
+                    hash: hash.clone(),
+                    verified_at: self.clock.now_unix(),
+                })?;
+        }
+        Ok(verdict)
+    }
+
+    /// Network access of an app's backends: what they declare, and the
+    /// hosts they reached through the network proxy (OpenRPC: apps.privacyReport)
+    ///
+    /// Observed hosts accumulate across launches. Shared backends report
+    /// the hosts seen for every app using them.
+    pub fn privacy_report(&self, app_id: &str) -> Result<PrivacyReport> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let mut backends = Vec::new();
+        for component in app.components_by_kind(ComponentKind::Backend) {
+            let declared = ComponentSchema::from(component).declared_network();
+            let observed: Vec<_> = self
+                .sql_storage
+                .list_network_observations(&Self::backend_owner(app_id, component).registry_id())?
+                .into_iter()
+                .filter(|observed| observed.component_id == component.id())
+                .collect();
+            let undeclared = observed
+                .iter()
+                .filter(|observed| {
+                    !declared
+                        .as_ref()
+                        .is_some_and(|declared| declared.allows_host(&observed.host))
+                })
+                .map(|observed| observed.host.clone())
+                .collect();
+            backends.push(BackendNetworkReport {
+                component_id: component.id().to_string(),
+                name: component.name().to_string(),
+                declared,
+                observed,
+                undeclared,
+            });
+        }
+
+        Ok(PrivacyReport {
+            app_id: app_id.to_string(),
+            backends,
+        })
+    }
+
+    /// Health transitions of an app's backends, newest first
+    /// (OpenRPC: apps.healthHistory)
+    pub fn health_history(&self, app_id: &str) -> Result<Vec<HealthTransition>> {
+        self.sql_storage.list_health_transitions(app_id)
+    }
+
+    /// Crash reports of an app's backends, newest first (OpenRPC: apps.crashReports)
+    ///
+    /// Includes crashes of the shared components the app uses.
+    pub fn crash_reports(&self, app_id: &str) -> Result<Vec<CrashReport>> {
+        let mut owners = BTreeSet::from([ComponentOwner::App(app_id.to_string()).registry_id()]);
+        if let Some(app) = self.sql_storage.get_application(app_id)? {
+            owners.extend(
+                app.components()
+                    .iter()
+                    .filter(|component| component.shared_key().is_some())
+                    .map(|component| Self::shared_owner(component).registry_id()),
+            );
+        }
+
+        let mut reports = Vec::new();
+        for owner in owners {
+            reports.extend(self.sql_storage.list_crash_reports(&owner)?);
+        }
+        reports.sort_by_key(|report| std::cmp::Reverse(report.crashed_at));
+        Ok(reports)
+    }
+
+    /// Most recent crash reports of every app, newest first, for diagnostics
+    pub fn recent_crash_reports(&self, limit: usize) -> Result<Vec<CrashReport>> {
+        self.sql_storage.list_recent_crash_reports(limit)
+    }
+
+    /// Resolve the native backtrace in a crash report (OpenRPC: apps.symbolicate)
+    ///
+    /// Uses the `.sym` file shipped next to the component binary, or
+    /// downloads the symbols its `config.symbols` points at. When neither
+    /// is available the frames are returned unresolved, with the reason.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the report does not exist
+    pub async fn symbolicate(&self, report_id: &str) -> Result<SymbolicatedReport> {
+        let report = self
+            .sql_storage
+            .get_crash_report(report_id)?
+            .with_context(|| format!("Crash report {} not found", report_id))?;
+        let frames = report.frames();
+
+        let (frames, unavailable_reason) = match self.crash_symbols(&report).await {
+            Ok(symbols) => (symbols.symbolicate(&frames), None),
+            Err(reason) => {
+                let bare = frames
+                    .into_iter()
+                    .map(|frame| ResolvedFrame {
+                        frame,
+                        symbol: None,
+                    })
+                    .collect();
+                (bare, Some(reason))
+            }
+        };
+
+        Ok(SymbolicatedReport {
+            report_id: report.id,
+            symbolicated: unavailable_reason.is_none(),
+            unavailable_reason,
+            frames,
+        })
+    }
+
+    /// Load the debug symbols of a crashed component, or say why not
+    async fn crash_symbols(&self, report: &CrashReport) -> std::result::Result<SymbolFile, String> {
+        let component = self
+            .crashed_component(report)
+            .map_err(|e| format!("{:#}", e))?
+            .ok_or("The crashed component is not installed")?;
+
+        let path = match &self.downloader {
+            Some(downloader) => downloader
+                .fetch_symbols(&component)
+                .await
+                .map_err(|e| format!("Failed to fetch symbols: {}", e))?,
+            None => ComponentDownloader::local_symbols(&component),
+        }
+        .ok_or("The component ships no symbols")?;
+
+        let data = std::fs::read(&path).map_err(|e| format!("Failed to read symbols: {}", e))?;
+        SymbolFile::parse(&data).map_err(|e| e.to_string())
+    }
+
+    /// Find the installed component a crash report names
+    fn crashed_component(&self, report: &CrashReport) -> Result<Option<ComponentSchema>> {
+        let (Some(id), Some(version)) = (&report.component_id, &report.component_version) else {
+            return Ok(None);
+        };
+
+        for app in self.sql_storage.list_applications()? {
+            let component = self
+                .app_components(app.id())?
+                .into_iter()
+                .find(|c| &c.id == id && &c.version == version);
+            if component.is_some() {
+                return Ok(component);
+            }
+        }
+        Ok(None)
+    }
+
+    /// List shared components with their references and running instances
+    pub fn shared_components(&self) -> Result<Vec<SharedComponentStatus>> {
+        let apps = self.sql_storage.list_applications()?;
+        let instances = self.shared_instances.lock().unwrap();
+
+        self.sql_storage
+            .list_shared_components()?
+            .iter()
+            .filter_map(ComponentRef::shared_key)
+            .map(|key| {
+                let instance = instances.get(&key);
+                Ok(SharedComponentStatus {
+                    installed_by: Self::referencing_apps(&apps, &key),
+                    pid: instance.map(|i| i.pid),
+                    active_apps: instance
+                        .map(|i| i.apps.iter().cloned().collect())
+                        .unwrap_or_default(),
+                    key,
+                })
+            })
+            .collect()
+    }
+
+    /// Remove shared component artifacts no installed app references
+    ///
+    /// Uninstalling an app drops its references; once the last referencing
+    /// app is gone (trashed apps do not count, as their artifacts are
+    /// re-fetched on restore), the cached artifact is deleted. Returns the
+    /// removed components.
+    pub async fn remove_unused_shared_components(&self) -> Result<Vec<SharedComponentKey>> {
+        let apps = self.sql_storage.list_applications()?;
+        let downloader = self.downloader()?;
+        let mut removed = Vec::new();
+
+        for component in self.sql_storage.list_shared_components()? {
+            let Some(key) = component.shared_key() else {
+                continue;
+            };
+            let running = self.shared_instances.lock().unwrap().contains_key(&key);
+            if running || !Self::referencing_apps(&apps, &key).is_empty() {
+                continue;
+            }
+
+            downloader
+                .remove(&ComponentSchema::from(&component))
+                .await
+                .with_context(|| format!("Failed to remove shared component {}", key))?;
+            self.sql_storage.remove_shared_component(&key)?;
+            removed.push(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove cached and extracted component files no app still needs
+    ///
+    /// Components of installed and trashed apps, their downloaded updates
+    /// and the versions kept for rollback, and shared components are all
+    /// owned. If the owners cannot be listed, only interrupted downloads
+    /// are removed.
+    pub async fn collect_garbage(&self) -> Result<GcReport> {
+        let owners = match self.cache_owners() {
+            Ok(owners) => Some(owners),
+            Err(e) => {
+                crate::log!(Warn, "Cache owners unknown, keeping owned files: {:#}", e);
+                None
+            }
+        };
+        Ok(self.downloader()?.gc(owners.as_deref()).await?)
+    }
+
+    /// Run [`Self::run_maintenance`] every `interval` until `shutdown` is
+    /// cancelled
+    ///
+    /// Spawn this on the async runtime. A failed step is retried on the
+    /// next tick.
+    pub async fn run_gc_scheduler(
+        self: Arc<Self>,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown.cancelled() => break,
+            }
+            self.run_maintenance().await;
+        }
+    }
+
+    /// Scheduled maintenance
+    ///
+    /// Collects cache garbage, stops idle backends past the warm pool's
+    /// limits, applies the retention policies of growing stores (their
+    /// reports are kept as [`RetentionService::last_run`]), then, with a
+    /// storage service and a user attached, reports what a storage
+    /// compaction would remove. Nothing is removed from the
+    /// encrypted storage; the user does that from the storage screen.
+    /// Failures are logged.
+    pub async fn run_maintenance(&self) {
+        if let Err(e) = self.collect_garbage().await {
+            crate::log!(Warn, "Cache garbage collection failed: {:#}", e);
+        }
+
+        match self.reap_idle_backends() {
+            Ok(reaped) if !reaped.is_empty() => {
+                crate::log!(Info, "Stopped idle backends of {} apps", reaped.len())
+            }
+            Ok(_) => {}
+            Err(e) => crate::log!(Warn, "Reaping idle backends failed: {:#}", e),
+        }
+
+        if let Some(retention) = &self.retention {
+            match retention.run() {
+                Ok(run) if run.removed() > 0 => crate::log!(
+                    Info,
+                    "Retention removed {} items from {} stores",
+                    run.removed(),
+                    run.reports.iter().filter(|r| r.removed > 0).count()
+                ),
+                Ok(_) => {}
+                Err(e) => crate::log!(Warn, "Retention failed: {:#}", e),
+            }
+        }
+
+        // Without a user, every per-user directory would look orphaned
+        let Some(storage) = self.storage.as_ref().filter(|_| !self.user_id.is_empty()) else {
+            return;
+        };
+        match storage.compact(std::slice::from_ref(&self.user_id), true) {
+            Ok(report) if !report.items.is_empty() => crate::log!(
+                Info,
+                "Storage compaction could remove {} paths ({} bytes)",
+                report.items.len(),
+                report.reclaimable_bytes()
+            ),
+            Ok(_) => {}
+            Err(e) => crate::log!(Warn, "Storage compaction report failed: {:#}", e),
+        }
+    }
+
+    /// Every component whose cached files must be kept
+    fn cache_owners(&self) -> Result<Vec<ComponentSchema>> {
+        let mut apps = self.sql_storage.list_applications()?;
+        apps.extend(
+            self.sql_storage
+                .list_trashed_applications()?
+                .iter()
+                .map(|trashed| trashed.application().clone()),
+        );
+
+        let mut versions = Vec::new();
+        for app in &apps {
+            versions.extend(self.sql_storage.get_pending_update(app.id())?);
+            versions.extend(
+                self.sql_storage
+                    .list_app_versions(app.id())?
+                    .into_iter()
+                    .map(|(_, previous, _)| previous),
+            );
+        }
+
+        let mut owners: Vec<ComponentSchema> = apps
+            .iter()
+            .chain(&versions)
+            .flat_map(|app| app.components().iter().map(ComponentSchema::from))
+            .collect();
+        owners.extend(
+            self.sql_storage
+                .list_shared_components()?
+                .iter()
+                .map(ComponentSchema::from),
+        );
+        Ok(owners)
+    }
+
+    /// Launch an application after a quick integrity check
+    ///
+    /// Checks that every component is present in the cache (manifest-level
+    /// only, no hashing) and runs [`repair`](Self::repair) first if any is
+    /// missing.
+    pub async fn launch_verified(&self, app_id: &str) -> Result<()> {
+        self.launch_verified_with(app_id, &NetworkOptions::default())
+            .await
+    }
+
+    /// Launch after a quick integrity check, with a timeout and cancellation
+    /// on any repair downloads
+    pub async fn launch_verified_with(&self, app_id: &str, options: &NetworkOptions) -> Result<()> {
+        self.launch_materialized(app_id, options, |_| {}).await
+    }
+
+    /// Launch an application, downloading its missing components first
+    ///
+    /// An app not recorded as anything but materialized gets the quick
+    /// check of [`launch_verified`](Self::launch_verified). An app recorded
+    /// as not (fully) materialized, or failing the quick check, is
+    /// [materialized](Self::materialize) first, with `on_progress` called
+    /// as each missing component finishes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a missing component could not be downloaded,
+    /// including when the bandwidth policy defers it. The app stays
+    /// installed and launches once its components are in the cache.
+    pub async fn launch_materialized<F>(
+        &self,
+        app_id: &str,
+        options: &NetworkOptions,
+        on_progress: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&MaterializeProgress),
+    {
+        let components = self.app_components(app_id)?;
+        let downloader = self.downloader()?;
+
+        let mut intact = self
+            .sql_storage
+            .get_materialization(app_id)?
+            .is_none_or(|state| state.is_materialized());
+        for component in &components {
+            if !intact {
+                break;
+            }
+            intact = downloader.quick_check(component).await;
+        }
+
+        if !intact {
+            let outcome = self.materialize(app_id, options, on_progress).await?;
+            if outcome.deferred {
+                anyhow::bail!(
+                    "Application {} is not downloaded yet; the bandwidth policy deferred it",
+                    app_id
+                );
+            }
+            if !outcome.state.is_materialized() {
+                anyhow::bail!("Application {} could not be repaired", app_id);
+            }
+        }
+
+        self.launch(app_id)
+    }
+
+    /// Expected bytes to download before an installed application is
+    /// fully cached; zero when it already is
+    ///
+    /// Every cached component is verified, as for
+    /// [`check_materialization`](Self::check_materialization), whose
+    /// result is recorded too.
+    pub async fn missing_bytes(&self, app_id: &str) -> Result<u64> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let components = self.app_components(app_id)?;
+        let missing = self.missing_components(&app, &components).await?;
+
+        let mut bytes = 0;
+        for m in &missing {
+            bytes += ComponentDownloader::estimated_size(&m.component).await;
+        }
+        let state = Materialization::from_missing(
+            components.len(),
+            missing.into_iter().map(|m| m.component.id).collect(),
+        );
+        self.sql_storage.set_materialization(app_id, &state)?;
+        Ok(bytes)
+    }
+
+    /// Whether an installed application's components were cached when last
+    /// checked; `None` if it never was
+    pub fn materialization(&self, app_id: &str) -> Result<Option<Materialization>> {
+        self.sql_storage.get_materialization(app_id)
+    }
+
+    /// Check which of an installed application's components are cached and
+    /// record the result (OpenRPC: apps.materialization)
+    ///
+    /// Like [`verify`](Self::verify), every cached component is hashed; a
+    /// component failing verification counts as missing.
+    pub async fn check_materialization(&self, app_id: &str) -> Result<Materialization> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let components = self.app_components(app_id)?;
+        let missing = self.missing_components(&app, &components).await?;
+
+        let state = Materialization::from_missing(
+            components.len(),
+            missing.into_iter().map(|m| m.component.id).collect(),
+        );
+        self.sql_storage.set_materialization(app_id, &state)?;
+        Ok(state)
+    }
+
+    /// Download an installed application's missing components
+    /// (OpenRPC: apps.materialize)
+    ///
+    /// Components absent from the cache or failing verification are fetched
+    /// through the normal download pipeline, honouring the bandwidth policy.
+    /// `on_progress` is called as each one finishes, and the resulting
+    /// state is recorded.
+    pub async fn materialize<F>(
+        &self,
+        app_id: &str,
+        options: &NetworkOptions,
+        on_progress: F,
+    ) -> Result<MaterializeOutcome>
+    where
+        F: FnMut(&MaterializeProgress),
+    {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let mut outcomes = self
+            .materialize_apps(&[app], 1, options, on_progress)
+            .await?;
+        outcomes
+            .pop()
+            .context("Materialization produced no outcome")
+    }
+
+    /// Download the missing components of every installed application,
+    /// `policy.concurrency` at a time (OpenRPC: apps.materializeAll)
+    ///
+    /// For bringing a restored install back offline-ready in one go.
+    /// Components shared by several apps are downloaded once. Once the
+    /// bandwidth policy defers a download no further downloads start, and
+    /// apps still missing components are reported as deferred; run it again
+    /// when the policy allows transfers.
+    pub async fn materialize_all<F>(
+        &self,
+        policy: &MaterializePolicy,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<Vec<MaterializeOutcome>>
+    where
+        F: FnMut(&MaterializeProgress),
+    {
+        let recorded = self.sql_storage.list_materialization()?;
+        let apps: Vec<OsnovaApplication> = self
+            .sql_storage
+            .list_applications()?
+            .into_iter()
+            .filter(|app| {
+                !policy.unmaterialized_only
+                    || recorded
+                        .get(app.id())
+                        .is_some_and(|state| !state.is_materialized())
+            })
+            .collect();
+
+        let Some(tasks) = &self.tasks else {
+            return self
+                .materialize_apps(&apps, policy.concurrency, options, on_progress)
+                .await;
+        };
+        let task = tasks.register_cancellable(
+            "Download missing app components",
+            TaskCategory::Materialization,
+            options.cancellation.clone(),
+        );
+        let result = self
+            .materialize_apps(&apps, policy.concurrency, options, |progress| {
+                let (completed, total) = (progress.completed as u64, progress.total as u64);
+                task.progress_with(completed, Some(total), progress.clone());
+                on_progress(progress);
+            })
+            .await;
+        task.finish(&result);
+        result
+    }
+
+    /// Resolve and check a list of apps to install
+    /// (OpenRPC: apps.previewInstallMany)
+    ///
+    /// Nothing is installed. The preview adds up the download size and the
+    /// permissions of every app that would be installed, for the user to
+    /// confirm once; see [`install_many`](Self::install_many).
+    pub async fn preview_install_many(
+        &self,
+        requests: &[InstallRequest],
+    ) -> Result<BatchInstallPreview> {
+        Ok(self.plan_installs(requests).await?.0)
+    }
+
+    /// Install a list of apps, such as those of a device being restored
+    /// (OpenRPC: apps.installMany)
+    ///
+    /// Every request is resolved and checked first, as
+    /// [`preview_install_many`](Self::preview_install_many) does, then the
+    /// installable apps are installed `batch.concurrency` at a time. A
+    /// failed install does not stop the others, and apps already installed
+    /// are skipped. Apps requested with a version to pin are pinned once
+    /// installed. `on_progress` is called as each request finishes.
+    ///
+    /// Cancelling `options` stops the installs not yet started; those under
+    /// way run to the end, so no app is left half installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no component downloader is configured or the
+    /// database fails; failures of single apps are reported per app
+    pub async fn install_many<F>(
+        &self,
+        requests: &[InstallRequest],
+        batch: &BatchOptions,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<BatchInstallReport>
+    where
+        F: FnMut(&BatchInstallProgress),
+    {
+        let (preview, manifests) = self.plan_installs(requests).await?;
+        let total = requests.len();
+        let mut completed = 0;
+        let mut outcomes = vec![None; total];
+        let mut pending = Vec::new();
+        for (index, (item, manifest)) in preview.apps.iter().zip(manifests).enumerate() {
+            let outcome = match (item.status, manifest) {
+                (BatchPreviewStatus::Ready, Some(manifest)) => {
+                    pending.push((index, manifest));
+                    continue;
+                }
+                (BatchPreviewStatus::AlreadyInstalled, _) => {
+                    BatchInstallOutcome::SkippedAlreadyInstalled
+                }
+                _ => BatchInstallOutcome::Failed {
+                    reason: item.reason.clone().unwrap_or_default(),
+                },
+            };
+            completed += 1;
+            on_progress(&BatchInstallProgress {
+                manifest_uri: item.manifest_uri.clone(),
+                outcome: outcome.clone(),
+                completed,
+                total,
+            });
+            outcomes[index] = Some(outcome);
+        }
+
+        // Cancellation only keeps installs from starting
+        let in_flight = NetworkOptions::default().with_timeout(options.timeout);
+        let mut installs = futures::stream::iter(pending)
+            .map(|(index, manifest)| {
+                let in_flight = &in_flight;
+                async move {
+                    if options.cancellation.is_cancelled() {
+                        return (index, BatchInstallOutcome::Cancelled);
+                    }
+                    let pin = requests[index].pin_version.as_deref();
+                    let outcome = match self.install_pinned(&manifest, pin, in_flight).await {
+                        Ok(warnings) => BatchInstallOutcome::Installed { warnings },
+                        Err(e) => BatchInstallOutcome::Failed {
+                            reason: format!("{:#}", e),
+                        },
+                    };
+                    (index, outcome)
+                }
+            })
+            .buffer_unordered(batch.concurrency.max(1));
+        while let Some((index, outcome)) = installs.next().await {
+            completed += 1;
+            on_progress(&BatchInstallProgress {
+                manifest_uri: requests[index].manifest_uri.clone(),
+                outcome: outcome.clone(),
+                completed,
+                total,
+            });
+            outcomes[index] = Some(outcome);
+        }
+        drop(installs);
+
+        let results = requests
+            .iter()
+            .zip(outcomes)
+            .map(|(request, outcome)| BatchInstallResult {
+                manifest_uri: request.manifest_uri.clone(),
+                outcome: outcome.expect("every request has an outcome"),
+            })
+            .collect();
+        Ok(BatchInstallReport { preview, results })
+    }
+
+    /// Installed applications as install requests, with their pins
+    ///
+    /// The app list to record with a backup or settings export, so that a
+    /// restored device installs the same apps with
+    /// [`install_many`](Self::install_many).
+    pub fn install_requests(&self) -> Result<Vec<InstallRequest>> {
+        self.sql_storage
+            .list_applications()?
+            .into_iter()
+            .map(|app| {
+                Ok(InstallRequest {
+                    pin_version: self.sql_storage.get_pinned_version(app.id())?,
+                    manifest_uri: app.id().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Verify the integrity of an installed application (OpenRPC: apps.verify)
+    ///
+    /// For each component, checks that the cache entry exists and matches the
+    /// manifest hash. Extracted frontends are checked file by file against the
+    /// content manifest recorded at extraction time.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::AppsService;
+    /// # async fn example(service: AppsService) -> anyhow::Result<()> {
+    /// let report = service.verify("com.example.app").await?;
+    /// for component in report.failing() {
+    ///     println!("{}: {:?}", component.name, component.integrity);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn verify(&self, app_id: &str) -> Result<VerifyReport> {
+        let components = self.app_components(app_id)?;
+        let downloader = self.downloader()?;
+
+        let mut results = Vec::with_capacity(components.len());
+        for component in &components {
+            results.push(downloader.verify(component).await?);
+        }
+
+        Ok(VerifyReport {
+            app_id: app_id.to_string(),
+            components: results,
+        })
+    }
+
+    /// Repair an installed application (OpenRPC: apps.repair)
+    ///
+    /// Evicts components that fail verification and re-downloads them through
+    /// the normal download pipeline. Returns a fresh verification report.
+    pub async fn repair(&self, app_id: &str) -> Result<VerifyReport> {
+        self.repair_with(app_id, &NetworkOptions::default()).await
+    }
+
+    /// Repair with a timeout and cancellation on each component download
+    pub async fn repair_with(
+        &self,
+        app_id: &str,
+        options: &NetworkOptions,
+    ) -> Result<VerifyReport> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let origin = ManifestOrigin::from(&app);
+        let downloader = self.downloader()?;
+
+        for component in self.app_components(app_id)? {
+            let verification = downloader.verify(&component).await?;
+            if verification.integrity != crate::components::ComponentIntegrity::Ok {
+                downloader
+                    .repair_for(&component, &origin, options)
+                    .await
+                    .with_context(|| format!("Failed to repair component {}", component.name))?;
+            }
+        }
+
+        self.verify(app_id).await
+    }
+
+    /// Pin an application to its installed version (OpenRPC: apps.pin)
+    ///
+    /// Update checks report updates to a pinned app separately and never
+    /// apply them; applying an update or rolling back is refused until the
+    /// app is unpinned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed, or `version` is not
+    /// the installed version; roll back to an earlier version before
+    /// pinning it.
+    pub fn pin_version(&self, app_id: &str, version: &str) -> Result<()> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        if app.version() != version {
+            anyhow::bail!(
+                "{} {} is not installed (installed: {}); roll back to it before pinning",
+                app_id,
+                version,
+                app.version()
+            );
+        }
+
+        self.sql_storage.set_pinned_version(app_id, Some(version))?;
+        Ok(())
+    }
+
+    /// Let a pinned application update again (OpenRPC: apps.unpin)
+    pub fn unpin(&self, app_id: &str) -> Result<()> {
+        if !self.sql_storage.set_pinned_version(app_id, None)? {
+            anyhow::bail!("Application {} not found", app_id);
+        }
+        Ok(())
+    }
+
+    /// Version an application is pinned to, if any
+    pub fn pinned_version(&self, app_id: &str) -> Result<Option<String>> {
+        self.sql_storage.get_pinned_version(app_id)
+    }
+
+    /// Re-resolve an application's manifest and update its name, icon and
+    /// description if the publisher changed them (OpenRPC: apps.refreshMetadata)
+    ///
+    /// See [`MetadataService::refresh`].
+    pub async fn refresh_metadata(&self, app_id: &str) -> Result<MetadataRefresh> {
+        let metadata = match &self.metadata {
+            Some(metadata) => metadata.clone(),
+            None => {
+                let mut metadata = MetadataService::new(&self.storage_path)?;
+                if let Some(events) = &self.events {
+                    metadata = metadata.with_events(events.clone());
+                }
+                Arc::new(metadata)
+            }
+        };
+        metadata.refresh(app_id, &NetworkOptions::default()).await
+    }
+
+    /// Install a new application from manifest URI (OpenRPC: apps.install)
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest_uri` - URI to the application manifest (ant:// or local path)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::AppsService;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = AppsService::new("/tmp/storage")?;
+    /// service.install("ant://manifest-address")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn install(&self, _manifest_uri: &str) -> Result<()> {
+        // TODO: Implement manifest fetching and parsing
+        // TODO: Download and cache components
+        // TODO: Store application in database
+        anyhow::bail!("Application installation not yet implemented")
+    }
+
+    /// Record a resolved application as installed
+    ///
+    /// Stores the application and registers the URI schemes it claims.
+    /// Called once the manifest has been fetched and its components cached.
+    /// The entry page of each extracted frontend is resolved and stored
+    /// with the app. Conditional config values (`$when`) are resolved for
+    /// this device and stored resolved, so they are never evaluated at run
+    /// time; the stored manifest keeps them, as signed.
+    ///
+    /// The manifest's co-signatures must verify and satisfy its signature
+    /// policy, and when the app is already installed, the policy of the
+    /// installed version too. In strict mode (see
+    /// [`ConfigService::set_signature_policy_required`]) a manifest without
+    /// a policy is refused.
+    ///
+    /// Returns warnings about the installed frontends, such as entry pages
+    /// that reference assets by absolute path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app claims a malformed or reserved scheme,
+    /// its signatures do not meet the policy, or an extracted frontend has
+    /// no entry page, and [`OsnovaError::PolicyBlocked`] if the device
+    /// policy does not allow the app.
+    pub fn install_application(&self, app: &OsnovaApplication) -> Result<Vec<String>> {
+        self.check_policy(app)?;
+        self.record_application(app, &ManifestSchema::from(app))
+    }
+
+    /// Download a manifest's components and record the app as installed
+    ///
+    /// Crash-safe: the install is journaled before anything changes and
+    /// each step is recorded as it completes, so an install interrupted by
+    /// a crash is finished or undone by [`recover_installs`] on the next
+    /// start. Safe to call again over a partial install: components that
+    /// already verify are not downloaded again and the application row is
+    /// overwritten.
+    ///
+    /// Returns warnings about the installed frontends, as
+    /// [`install_application`](Self::install_application) does.
+    ///
+    /// [`recover_installs`]: Self::recover_installs
+    pub async fn install_manifest(&self, manifest: &ManifestSchema) -> Result<Vec<String>> {
+        self.install_manifest_with(manifest, &NetworkOptions::default())
+            .await
+    }
+
+    /// Install with a timeout and cancellation on each component download
+    pub async fn install_manifest_with(
+        &self,
+        manifest: &ManifestSchema,
+        options: &NetworkOptions,
+    ) -> Result<Vec<String>> {
+        manifest.capabilities().check()?;
+        let app = OsnovaApplication::try_from(manifest)?;
+        self.check_policy(&app)?;
+        let downloader = self.downloader()?;
+
+        // A retry keeps what the first attempt saw as installed before it
+        let now = self.clock.now_unix();
+        let previous = self.sql_storage.get_install_journal(app.id())?;
+        let mut journal = InstallJournalEntry {
+            app_id: app.id().to_string(),
+            manifest: manifest.clone(),
+            step: InstallStep::Started,
+            previously_installed: match &previous {
+                Some(previous) => previous.previously_installed,
+                None => self.sql_storage.get_application(app.id())?.is_some(),
+            },
+            started_at: previous.map_or(now, |previous| previous.started_at),
+            updated_at: now,
+        };
+        self.journal_step(&mut journal, InstallStep::Started)?;
+
+        let origin = ManifestOrigin::from(&app);
+        for component in &manifest.components {
+            let verification = downloader.verify(component).await?;
+            if verification.integrity == ComponentIntegrity::Ok {
+                continue;
+            }
+            downloader
+                .repair_for(component, &origin, options)
+                .await
+                .with_context(|| format!("Failed to download component {}", component.name))?;
+        }
+        self.journal_step(&mut journal, InstallStep::ComponentsFetched)?;
+
+        let warnings = self.record_application(&app, manifest)?;
+        self.sql_storage
+            .set_materialization(app.id(), &Materialization::Materialized)?;
+        self.journal_step(&mut journal, InstallStep::Recorded)?;
+
+        self.sql_storage.delete_install_journal(app.id())?;
+        Ok(warnings)
+    }
+
+    /// Finish or undo installs interrupted by a crash
+    ///
+    /// Run once at startup, before apps are listed. An install whose
+    /// components all verify is rolled forward: the app is recorded as
+    /// installed. Otherwise it is rolled back: its application row is
+    /// removed unless another version was installed before, and its cached
+    /// components are removed unless an installed app uses them. Each
+    /// outcome is posted as a notification under the app's ID.
+    pub async fn recover_installs(&self) -> Result<Vec<InstallRecovery>> {
+        let mut recoveries = Vec::new();
+        for journal in self.sql_storage.list_install_journal()? {
+            let resolution = match self.try_roll_forward(&journal).await? {
+                true => InstallResolution::RolledForward,
+                false => {
+                    self.roll_back(&journal).await?;
+                    InstallResolution::RolledBack
+                }
+            };
+            self.sql_storage.delete_install_journal(&journal.app_id)?;
+
+            let recovery = InstallRecovery {
+                app_id: journal.app_id.clone(),
+                version: journal.manifest.version.clone(),
+                step: journal.step,
+                resolution,
+            };
+            crate::log!(
+                Warn,
+                "Recovered interrupted install of {} {}: {:?}",
+                recovery.app_id,
+                recovery.version,
+                recovery.resolution
+            );
+            self.notify_recovery(&journal, &recovery)?;
+            recoveries.push(recovery);
+        }
+        Ok(recoveries)
+    }
+
+    /// Record the install journal reaching `step`, then run the failpoint
+    fn journal_step(&self, journal: &mut InstallJournalEntry, step: InstallStep) -> Result<()> {
+        journal.step = step;
+        journal.updated_at = self.clock.now_unix();
+        self.sql_storage.put_install_journal(journal)?;
+        match &self.install_failpoint {
+            Some(failpoint) => failpoint(step),
+            None => Ok(()),
+        }
+    }
+
+    /// Record an interrupted install if every component verifies
+    async fn try_roll_forward(&self, journal: &InstallJournalEntry) -> Result<bool> {
+        let downloader = self.downloader()?;
+        for component in &journal.manifest.components {
+            if downloader.verify(component).await?.integrity != ComponentIntegrity::Ok {
+                return Ok(false);
+            }
+        }
+
+        let app = OsnovaApplication::try_from(&journal.manifest)?;
+        // Rows already written from this manifest are written again
+        self.record_application(&app, &journal.manifest)?;
+        Ok(true)
+    }
+
+    /// Remove what an interrupted install left behind
+    async fn roll_back(&self, journal: &InstallJournalEntry) -> Result<()> {
+        let app_id = journal.app_id.as_str();
+        if !journal.previously_installed && self.sql_storage.delete_application(app_id)? {
+            self.sql_storage.remove_uri_scheme_claims(app_id)?;
+            let generation = self.sql_storage.record_launcher_change(
+                LauncherChangeKind::Removed,
+                Some(app_id),
+                None,
+            )?;
+            self.publish(AppEvent::AppUninstalled {
+                app_id: app_id.to_string(),
+                generation,
+            });
+        }
+
+        let mut in_use = Vec::new();
+        for app in self.sql_storage.list_applications()? {
+            in_use.extend(self.app_components(app.id())?);
+        }
+        let downloader = self.downloader()?;
+        for component in &journal.manifest.components {
+            let used = in_use
+                .iter()
+                .any(|c| c.id == component.id && c.version == component.version);
+            if !used {
+                downloader.remove(component).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Post the notification for a recovered install
+    ///
+    /// Tagged by attempt, so a later interrupted install of the same
+    /// version is reported too.
+    fn notify_recovery(
+        &self,
+        journal: &InstallJournalEntry,
+        recovery: &InstallRecovery,
+    ) -> Result<()> {
+        let Some(notifications) = &self.notifications else {
+            return Ok(());
+        };
+
+        let name = &journal.manifest.name;
+        let version = &recovery.version;
+        let notification = match recovery.resolution {
+            InstallResolution::RolledForward => Notification::new(
+                "Install completed",
+                format!(
+                    "{} {} was interrupted while installing and has been completed.",
+                    name, version
+                ),
+            )
+            .with_level(NotificationLevel::Success),
+            InstallResolution::RolledBack => Notification::new(
+                "Install undone",
+                format!(
+                    "{} {} was interrupted while installing and has been removed. \
+                     Install it again to use it.",
+                    name, version
+                ),
+            )
+            .with_level(NotificationLevel::Warning),
+        };
+        notifications.post(
+            &recovery.app_id,
+            notification.with_tag(format!(
+                "install-recovered:{}:{}",
+                version, journal.started_at
+            )),
+        )?;
+        Ok(())
+    }
+
+    /// Store an application with the manifest it was installed from
+    fn record_application(
+        &self,
+        app: &OsnovaApplication,
+        manifest: &ManifestSchema,
+    ) -> Result<Vec<String>> {
+        for scheme in app.uri_schemes() {
+            validate_uri_scheme(scheme).map_err(|e| anyhow::anyhow!(e))?;
+        }
+
+        let installed = self.sql_storage.get_application(app.id())?;
+        check_signature_policy(
+            &ManifestSchema::from(app),
+            installed
+                .as_ref()
+                .and_then(OsnovaApplication::signature_policy),
+            self.config.get_signature_policy_required()?,
+        )?;
+
+        let mut app = app.clone();
+        for component in app.components_mut() {
+            if let Some(config) = component.config() {
+                let resolved = resolve_config(config, &self.conditions)
+                    .map_err(|e| anyhow::anyhow!("Component {}: {}", component.name(), e))?;
+                *component = component.clone().with_config(resolved);
+            }
+        }
+        let warnings = entry::record_entries(&mut app)?;
+
+        let kind = match installed {
+            Some(_) => LauncherChangeKind::Updated,
+            None => LauncherChangeKind::Added,
+        };
+        self.sql_storage
+            .upsert_application_with_manifest(&app, manifest)?;
+        if let Some(schema) = app.settings_schema() {
+            if installed
+                .as_ref()
+                .and_then(OsnovaApplication::settings_schema)
+                != Some(schema)
+            {
+                self.config.migrate_app_settings(app.id(), schema)?;
+            }
+        }
+        self.register_uri_schemes(&app)?;
+        let generation = self
+            .sql_storage
+            .record_launcher_change(kind, Some(app.id()), None)?;
+        self.publish(AppEvent::AppInstalled {
+            app_id: app.id().to_string(),
+            generation,
+        });
+        Ok(warnings)
+    }
+
+    /// Uninstall an application (OpenRPC: apps.uninstall)
+    ///
+    /// The app is moved to the trash for `keep_data_days` days, keeping its
+    /// configuration, keys and cached artifacts. Launcher entries are
+    /// removed immediately, remembering their positions for a restore. A
+    /// value of 0 uninstalls permanently; the app's cached artifacts are
+    /// then removed by the next [`collect_garbage`].
+    ///
+    /// Running backends are stopped. Shared components the app used are
+    /// removed by [`remove_unused_shared_components`] once no other installed
+    /// app references them.
+    ///
+    /// [`remove_unused_shared_components`]: Self::remove_unused_shared_components
+    /// [`collect_garbage`]: Self::collect_garbage
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application ID to uninstall
+    /// * `keep_data_days` - Days to keep the app restorable (None uses the
+    ///   configured trash retention)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::AppsService;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = AppsService::new("/tmp/storage")?;
+    /// service.uninstall("com.example.app", None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn uninstall(&self, app_id: &str, keep_data_days: Option<u32>) -> Result<()> {
+        let keep_data_days = match keep_data_days {
+            Some(days) => days,
+            None => self.config.get_trash_retention_days()?,
+        };
+
+        // Release running processes and make sure shared artifacts are
+        // tracked so they can be removed once unreferenced
+        if let Some(app) = self.sql_storage.get_application(app_id)? {
+            self.register_shared_components(&app)?;
+            self.stop(app_id)?;
+        }
+
+        if keep_data_days == 0 {
+            let deleted = self.sql_storage.delete_application(app_id)?;
+
+            if !deleted {
+                anyhow::bail!("Application {} not found", app_id);
+            }
+        } else {
+            let now = self.clock.now_unix();
+            let purge_after = now + u64::from(keep_data_days) * SECONDS_PER_DAY;
+            let trashed = self
+                .sql_storage
+                .trash_application(app_id, now, purge_after)?;
+
+            if !trashed {
+                anyhow::bail!("Application {} not found", app_id);
+            }
+        }
+
+        self.sql_storage.remove_uri_scheme_claims(app_id)?;
+        let positions = self.remove_from_launcher_layouts(app_id)?;
+        if keep_data_days > 0 {
+            self.sql_storage
+                .set_trashed_launcher_positions(app_id, &positions)?;
+        }
+        let generation = self.sql_storage.record_launcher_change(
+            LauncherChangeKind::Removed,
+            Some(app_id),
+            None,
+        )?;
+        self.publish(AppEvent::AppUninstalled {
+            app_id: app_id.to_string(),
+            generation,
+        });
+        Ok(())
+    }
+
+    /// Restore a recently uninstalled application (OpenRPC: apps.restore)
+    ///
+    /// The app returns to the launcher positions it had when it was
+    /// uninstalled.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application ID to restore
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not in the trash or its retention
+    /// window has already elapsed.
+    pub fn restore(&self, app_id: &str) -> Result<()> {
+        let trashed = self
+            .sql_storage
+            .get_trashed_application(app_id)?
+            .context(format!("Application {} is not in the trash", app_id))?;
+
+        if trashed.is_expired(self.clock.now_unix()) {
+            anyhow::bail!("Application {} can no longer be restored", app_id);
+        }
+
+        self.sql_storage.restore_application(app_id)?;
+        self.register_uri_schemes(trashed.application())?;
+        for (user_id, position) in trashed.launcher_positions() {
+            LauncherService::new(&self.storage_path, user_id)?
+                .restore_app(app_id, position.clone())?;
+        }
+        let generation = self.sql_storage.record_launcher_change(
+            LauncherChangeKind::Added,
+            Some(app_id),
+            None,
+        )?;
+        self.publish(AppEvent::AppRestored {
+            app_id: app_id.to_string(),
+            generation,
+        });
+        Ok(())
+    }
+
+    /// List recently uninstalled applications (OpenRPC: apps.listTrash)
+    pub fn list_trash(&self) -> Result<Vec<AppStatusItem>> {
+        let trashed = self.sql_storage.list_trashed_applications()?;
+
+        Ok(trashed
+            .iter()
+            .map(|t| AppStatusItem {
+                app: AppListItem::from(t.application()),
+                state: AppInstallState::Trashed,
+                deleted_at: Some(t.deleted_at()),
+                purge_after: Some(t.purge_after()),
+                materialization: None,
+            })
+            .collect())
+    }
+
+    /// Permanently remove trashed apps whose retention window has elapsed
+    ///
+    /// Intended to run as a scheduled job (e.g. on startup). Returns the IDs
+    /// of purged apps.
+    pub fn purge_expired_trash(&self) -> Result<Vec<String>> {
+        self.purge_expired_trash_at(self.clock.now_unix())
+    }
+
+    /// Purge trashed apps expired as of `now` (for testing/scheduling)
+    pub fn purge_expired_trash_at(&self, now: u64) -> Result<Vec<String>> {
+        let mut purged = Vec::new();
+
+        for trashed in self.sql_storage.list_trashed_applications()? {
+            if trashed.is_expired(now) {
+                let app_id = trashed.application().id();
+                self.sql_storage.purge_trashed_application(app_id)?;
+                purged.push(app_id.to_string());
+            }
+        }
+
+        Ok(purged)
+    }
+
+    /// URI schemes claimed by installed apps (OpenRPC: apps.listUriHandlers)
+    pub fn uri_handlers(&self) -> Result<Vec<UriSchemeHandlers>> {
+        let mut handlers: BTreeMap<String, UriSchemeHandlers> = BTreeMap::new();
+        for (scheme, app_id) in self.sql_storage.list_uri_scheme_claims()? {
+            handlers
+                .entry(scheme.clone())
+                .or_insert_with(|| UriSchemeHandlers {
+                    scheme,
+                    app_id: app_id.clone(),
+                    candidates: Vec::new(),
+                    overridden: false,
+                })
+                .candidates
+                .push(app_id);
+        }
+
+        // A choice only applies while the chosen app still claims the scheme
+        for (scheme, app_id) in self.config.get_uri_handler_overrides()? {
+            if let Some(handler) = handlers.get_mut(&scheme) {
+                if handler.candidates.contains(&app_id) {
+                    handler.app_id = app_id;
+                    handler.overridden = true;
+                }
+            }
+        }
+
+        Ok(handlers.into_values().collect())
+    }
+
+    /// Choose which app handles a URI scheme (OpenRPC: apps.setUriHandler)
+    ///
+    /// `None` restores the default (first installed claimant).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `app_id` does not claim the scheme.
+    pub fn set_uri_handler(&self, scheme: &str, app_id: Option<&str>) -> Result<()> {
+        let scheme = scheme.to_ascii_lowercase();
+        if let Some(app_id) = app_id {
+            let claimed = self
+                .sql_storage
+                .list_uri_scheme_claims()?
+                .iter()
+                .any(|(s, a)| *s == scheme && a == app_id);
+            if !claimed {
+                anyhow::bail!("Application {} does not handle {}: URIs", app_id, scheme);
+            }
+        }
+
+        self.config.set_uri_handler_override(&scheme, app_id)
+    }
+
+    /// Find the app that opens a URI
+    ///
+    /// Returns `None` if the URI has no scheme or no installed app claims it.
+    pub fn resolve_uri_handler(&self, uri: &str) -> Result<Option<HandlerInfo>> {
+        let Some((scheme, _)) = uri.split_once(':') else {
+            return Ok(None);
+        };
+        let scheme = scheme.to_ascii_lowercase();
+
+        let Some(handler) = self
+            .uri_handlers()?
+            .into_iter()
+            .find(|h| h.scheme == scheme)
+        else {
+            return Ok(None);
+        };
+        let app = installed_app(&self.sql_storage, &handler.app_id)?;
+
+        Ok(Some(HandlerInfo {
+            scheme,
+            app_id: handler.app_id,
+            app_name: app.name().to_string(),
+            initial_route: uri.to_string(),
+        }))
+    }
+
+    /// Launch the app that handles a deep link
+    ///
+    /// The returned [`HandlerInfo`] carries the full URI as the route the
+    /// app's frontend should open.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no installed app handles the URI's scheme.
+    pub fn open_uri(&self, uri: &str) -> Result<HandlerInfo> {
+        let handler = self
+            .resolve_uri_handler(uri)?
+            .with_context(|| format!("No installed app handles {}", uri))?;
+        self.launch(&handler.app_id)?;
+        Ok(handler)
+    }
+
+    /// Record the URI schemes an app claims
+    fn register_uri_schemes(&self, app: &OsnovaApplication) -> Result<()> {
+        for scheme in app.uri_schemes() {
+            self.sql_storage.add_uri_scheme_claim(scheme, app.id())?;
+        }
+        Ok(())
+    }
+
+    /// Record the shared components an app references
+    fn register_shared_components(&self, app: &OsnovaApplication) -> Result<()> {
+        for component in app.components() {
+            if component.shared_key().is_some() {
+                self.sql_storage.register_shared_component(component)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a reference to a shared backend, starting it if needed
+    fn acquire_shared(
+        &self,
+        processes: &ProcessService,
+        app_id: &str,
+        key: &SharedComponentKey,
+        component: &ComponentRef,
+    ) -> Result<()> {
+        let mut instances = self.shared_instances.lock().unwrap();
+
+        // Reuse the running instance unless the watchdog saw it die
+        let mut apps = BTreeSet::new();
+        if let Some(instance) = instances.remove(key) {
+            if processes.owner_of(instance.pid)?.is_some() {
+                let mut instance = instance;
+                instance.apps.insert(app_id.to_string());
+                instances.insert(key.clone(), instance);
+                return Ok(());
+            }
+            apps = instance.apps;
+        }
+
+        let pid = self
+            .spawn_backend(processes, &Self::shared_owner(component), component)
+            .with_context(|| format!("Failed to start shared component {}", key))?;
+
+        apps.insert(app_id.to_string());
+        instances.insert(key.clone(), SharedInstance { pid, apps });
+
+        Ok(())
+    }
+
+    /// Start a backend, telling it where to listen and where to report ready
+    fn spawn_backend(
+        &self,
+        processes: &ProcessService,
+        owner: &ComponentOwner,
+        component: &ComponentRef,
+    ) -> Result<u32> {
+        let socket = self.backend_socket(owner, component);
+        if let Some(dir) = socket.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create socket directory")?;
+        }
+
+        let schema = ComponentSchema::from(component);
+        let prepared = ComponentDownloader::prepared_path(&schema);
+        if prepared.exists() {
+            binary::verify_prepared(&schema, &prepared)?;
+        }
+
+        let mut command = (self.backend_command)(&schema);
+        command
+            .env(COMPONENT_ID_ENV, component.id())
+            .env(SOCKET_PATH_ENV, &socket);
+        if let Some(address) = self.handshake.as_ref().and_then(|h| h.rpc_address()) {
+            command.env(RPC_ADDR_ENV, address.to_string());
+        }
+        let proxy = self.network_proxy.as_ref().and_then(|proxy| {
+            proxy.grant(
+                &owner.registry_id(),
+                component.id(),
+                schema.declared_network(),
+            )
+        });
+        if let Some(url) = proxy {
+            command.env(PROXY_URL_ENV, &url);
+            for name in STANDARD_PROXY_ENVS {
+                command.env(name, &url);
+            }
+        }
+        #[cfg_attr(not(unix), allow(unused_variables))]
+        let secrets = self.offer_secrets(owner, &mut command)?;
+        let pid = processes.spawn_component(
+            &owner.registry_id(),
+            (component.id(), component.version()),
+            &mut command,
+            Some(&socket),
+        )?;
+        #[cfg(unix)]
+        if let Some(channel) = secrets {
+            let timeout = self.secret_timeout;
+            let component_id = component.id().to_string();
+            std::thread::spawn(move || match channel.serve(timeout) {
+                SecretDelivery::Delivered => {
+                    crate::log!(Info, "Delivered secret settings to {}", component_id)
+                }
+                SecretDelivery::TimedOut => crate::log!(
+                    Warn,
+                    "{} did not collect its secret settings within {:?}; they were withdrawn",
+                    component_id,
+                    timeout
+                ),
+            });
+        }
+        Ok(pid)
+    }
+
+    /// Offer the secret settings of an app's backend on a one-time channel
+    ///
+    /// Only the channel's socket path goes into the backend's environment.
+    /// Shared backends serve several apps and get no secrets.
+    #[cfg(unix)]
+    fn offer_secrets(
+        &self,
+        owner: &ComponentOwner,
+        command: &mut Command,
+    ) -> Result<Option<SecretChannel>> {
+        let ComponentOwner::App(app_id) = owner else {
+            return Ok(None);
+        };
+        let secrets = self.config.app_secrets(app_id, &self.user_id)?;
+        if secrets.is_empty() {
+            return Ok(None);
+        }
+
+        let dir = self.storage_path.join("sockets").join("secrets");
+        let channel = SecretChannel::open(&dir, &secrets)?;
+        command.env(SECRETS_SOCKET_ENV, channel.path());
+        Ok(Some(channel))
+    }
+
+    /// Secret settings are only delivered over Unix sockets
+    #[cfg(not(unix))]
+    fn offer_secrets(&self, owner: &ComponentOwner, _command: &mut Command) -> Result<Option<()>> {
+        if let ComponentOwner::App(app_id) = owner {
+            if !self.config.app_secrets(app_id, &self.user_id)?.is_empty() {
+                anyhow::bail!(
+                    "Secret settings of {} cannot be delivered on this platform",
+                    app_id
+                );
+            }
+        }
+        Ok(None)
+    }
+
+    /// Socket a backend listens on
+    fn backend_socket(&self, owner: &ComponentOwner, component: &ComponentRef) -> PathBuf {
+        let dir = self.storage_path.join("sockets");
+        handshake::socket_path(&dir, &owner.registry_id(), component.id())
+    }
+
+    /// Process owner of a backend of `app_id`
+    fn backend_owner(app_id: &str, component: &ComponentRef) -> ComponentOwner {
+        match component.shared_key() {
+            Some(_) => Self::shared_owner(component),
+            None => ComponentOwner::App(app_id.to_string()),
+        }
+    }
+
+    /// Process owner of a shared backend
+    fn shared_owner(component: &ComponentRef) -> ComponentOwner {
+        ComponentOwner::Shared(component.shared_id().unwrap_or_default().to_string())
+    }
+
+    /// IDs of installed apps whose manifests reference a shared component
+    fn referencing_apps(apps: &[OsnovaApplication], key: &SharedComponentKey) -> Vec<String> {
+        apps.iter()
+            .filter(|app| {
+                app.components()
+                    .iter()
+                    .any(|c| c.shared_key().as_ref() == Some(key))
+            })
+            .map(|app| app.id().to_string())
+            .collect()
+    }
+
+    /// Get the component schemas of an installed application
+    ///
+    /// Read from the manifest snapshot of the installed version, falling
+    /// back to the application record for apps installed before snapshots
+    /// were recorded.
+    fn app_components(&self, app_id: &str) -> Result<Vec<ComponentSchema>> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        if let Some(manifest) = self.sql_storage.get_manifest_snapshot(app_id)? {
+            return Ok(manifest.components);
+        }
+
+        Ok(app.components().iter().map(ComponentSchema::from).collect())
+    }
+
+    /// Components of `app` absent from the cache or failing verification
+    async fn missing_components(
+        &self,
+        app: &OsnovaApplication,
+        components: &[ComponentSchema],
+    ) -> Result<Vec<MissingComponent>> {
+        let downloader = self.downloader()?;
+        let origin = ManifestOrigin::from(app);
+
+        let mut missing = Vec::new();
+        for component in components {
+            if downloader.verify(component).await?.integrity != ComponentIntegrity::Ok {
+                missing.push(MissingComponent {
+                    app_id: app.id().to_string(),
+                    origin: origin.clone(),
+                    component: component.clone(),
+                });
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Resolve every request of a batch install, keeping the manifests of
+    /// those to install
+    async fn plan_installs(
+        &self,
+        requests: &[InstallRequest],
+    ) -> Result<(BatchInstallPreview, Vec<Option<ManifestSchema>>)> {
+        let downloader = self.downloader()?;
+        let mut seen = BTreeSet::new();
+        let mut apps = Vec::with_capacity(requests.len());
+        let mut manifests = Vec::with_capacity(requests.len());
+        for request in requests {
+            let mut item = BatchPreviewItem {
+                manifest_uri: request.manifest_uri.clone(),
+                status: BatchPreviewStatus::AlreadyInstalled,
+                name: None,
+                version: None,
+                download_bytes: 0,
+                permissions: Vec::new(),
+                reason: None,
+                required_core_version: None,
+                warnings: Vec::new(),
+            };
+            if !seen.insert(request.manifest_uri.clone())
+                || self
+                    .sql_storage
+                    .get_application(&request.manifest_uri)?
+                    .is_some()
+            {
+                apps.push(item);
+                manifests.push(None);
+                continue;
+            }
+
+            let (manifest, app) = match self.resolve_install(request).await {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    item.status = BatchPreviewStatus::Invalid;
+                    match e.downcast_ref::<OsnovaError>() {
+                        Some(OsnovaError::RequiresNewerCore { required, .. }) => {
+                            item.status = BatchPreviewStatus::RequiresNewerOsnova;
+                            item.required_core_version = required.clone();
+                        }
+                        Some(OsnovaError::PolicyBlocked { .. }) => {
+                            item.status = BatchPreviewStatus::BlockedByPolicy;
+                        }
+                        _ => {}
+                    }
+                    item.reason = Some(format!("{:#}", e));
+                    apps.push(item);
+                    manifests.push(None);
+                    continue;
+                }
+            };
+            item.name = Some(manifest.name.clone());
+            item.version = Some(manifest.version.clone());
+            item.warnings = manifest.capabilities().warnings();
+            // The manifest may name the app differently from the request
+            if app.id() != request.manifest_uri
+                && (!seen.insert(app.id().to_string())
+                    || self.sql_storage.get_application(app.id())?.is_some())
+            {
+                apps.push(item);
+                manifests.push(None);
+                continue;
+            }
+
+            item.status = BatchPreviewStatus::Ready;
+            item.permissions = Self::declared_permissions(&app);
+            for component in &manifest.components {
+                if downloader.verify(component).await?.integrity != ComponentIntegrity::Ok {
+                    item.download_bytes += ComponentDownloader::estimated_size(component).await;
+                }
+            }
+            apps.push(item);
+            manifests.push(Some(manifest));
+        }
+
+        let ready = apps
+            .iter()
+            .filter(|item| item.status == BatchPreviewStatus::Ready);
+        let preview = BatchInstallPreview {
+            download_bytes: ready.clone().map(|item| item.download_bytes).sum(),
+            permissions: ready
+                .flat_map(|item| item.permissions.iter().cloned())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            apps,
+        };
+        Ok((preview, manifests))
+    }
+
+    /// Fetch and check the manifest of an app to install
+    async fn resolve_install(
+        &self,
+        request: &InstallRequest,
+    ) -> Result<(ManifestSchema, OsnovaApplication)> {
+        let manifest = (self.fetch_manifest)(request.manifest_uri.clone()).await?;
+        manifest.capabilities().check()?;
+        manifest
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid manifest: {}", e))?;
+        let app = OsnovaApplication::try_from(&manifest)?;
+        self.check_policy(&app)?;
+        if let Some(pin) = &request.pin_version {
+            if *pin != manifest.version {
+                anyhow::bail!(
+                    "Pinned to version {}, but version {} is published",
+                    pin,
+                    manifest.version
+                );
+            }
+        }
+        Ok((manifest, app))
+    }
+
+    /// Refuse an app the device policy does not allow
+    fn check_policy(&self, app: &OsnovaApplication) -> Result<()> {
+        if let Some(policies) = &self.policies {
+            policies.check_app(app)?;
+        }
+        Ok(())
+    }
+
+    /// Install a manifest and pin it to `pin`, if given
+    async fn install_pinned(
+        &self,
+        manifest: &ManifestSchema,
+        pin: Option<&str>,
+        options: &NetworkOptions,
+    ) -> Result<Vec<String>> {
+        let warnings = self.install_manifest_with(manifest, options).await?;
+        if let Some(pin) = pin {
+            self.pin_version(&manifest.id, pin)?;
+        }
+        Ok(warnings)
+    }
+
+    /// Download the missing components of `apps`, `concurrency` at a time,
+    /// and record each app's state
+    async fn materialize_apps<F>(
+        &self,
+        apps: &[OsnovaApplication],
+        concurrency: usize,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<Vec<MaterializeOutcome>>
+    where
+        F: FnMut(&MaterializeProgress),
+    {
+        let downloader = self
+            .downloader
+            .clone()
+            .context("Component downloader not configured")?;
+
+        // What each app is missing; a component shared by apps is queued once
+        let mut needed = Vec::with_capacity(apps.len());
+        let mut queued = BTreeSet::new();
+        let mut pending = Vec::new();
+        for app in apps {
+            let components = self.app_components(app.id())?;
+            let missing = self.missing_components(app, &components).await?;
+            let ids: Vec<String> = missing.iter().map(|m| m.component.id.clone()).collect();
+            needed.push((app.id().to_string(), components.len(), ids));
+            pending.extend(
+                missing
+                    .into_iter()
+                    .filter(|m| queued.insert(m.component.id.clone())),
+            );
+        }
+        pending.reverse();
+        let total = pending.len();
+
+        let mut fetched = BTreeSet::new();
+        let mut deferred = BTreeSet::new();
+        let mut completed = 0;
+        let mut tasks = JoinSet::new();
+        loop {
+            while deferred.is_empty() && tasks.len() < concurrency.max(1) {
+                let Some(missing) = pending.pop() else {
+                    break;
+                };
+                let downloader = downloader.clone();
+                let options = options.clone();
+                tasks.spawn(async move {
+                    let result = Self::fetch_missing(&downloader, &missing, &options).await;
+                    (missing, result)
+                });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+
+            let (missing, result) = joined.context("Component download task failed")?;
+            let error = match result {
+                Ok(TransferStatus::Completed(_)) => {
+                    fetched.insert(missing.component.id.clone());
+                    None
+                }
+                Ok(TransferStatus::Deferred(_)) => {
+                    deferred.insert(missing.component.id);
+                    continue;
+                }
+                Err(e) => Some(format!("{:#}", e)),
+            };
+            completed += 1;
+            on_progress(&MaterializeProgress {
+                app_id: missing.app_id,
+                component_id: missing.component.id,
+                completed,
+                total,
+                error,
+            });
+        }
+        // Downloads never started once the policy deferred one
+        deferred.extend(pending.into_iter().map(|m| m.component.id));
+
+        let mut outcomes = Vec::with_capacity(needed.len());
+        for (app_id, total, missing) in needed {
+            let missing: Vec<String> = missing
+                .into_iter()
+                .filter(|id| !fetched.contains(id))
+                .collect();
+            let was_deferred = missing.iter().any(|id| deferred.contains(id));
+            let state = Materialization::from_missing(total, missing);
+            self.sql_storage.set_materialization(&app_id, &state)?;
+            outcomes.push(MaterializeOutcome {
+                app_id,
+                state,
+                deferred: was_deferred,
+            });
+        }
+        Ok(outcomes)
+    }
+
+    /// Download one missing component, clearing what is left of a previous
+    /// copy first
+    async fn fetch_missing(
+        downloader: &ComponentDownloader,
+        missing: &MissingComponent,
+        options: &NetworkOptions,
+    ) -> Result<TransferStatus<PathBuf>> {
+        downloader.remove(&missing.component).await?;
+        downloader
+            .try_download_for(&missing.component, &missing.origin, options)
+            .await
+            .with_context(|| format!("Failed to download component {}", missing.component.name))
+    }
+
+    /// Get the attached component downloader
+    fn downloader(&self) -> Result<&ComponentDownloader> {
+        self.downloader
+            .as_deref()
+            .context("Component downloader not configured")
+    }
+
+    /// Remove an app from every user's launcher layout
+    ///
+    /// Returns where it was on each user's grid, by user ID.
+    fn remove_from_launcher_layouts(&self, app_id: &str) -> Result<BTreeMap<String, OrderKey>> {
+        let file_storage = FileStorage::new(&self.storage_path)?;
+        let mut positions = BTreeMap::new();
+
+        for path in file_storage.list_files("launcher")? {
+            // Layouts are stored at launcher/{user_id}/layout.json
+            let user_id = match path.parent().and_then(|p| p.file_name()) {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            };
+
+            if let Some(position) =
+                LauncherService::new(&self.storage_path, &user_id)?.remove_app(app_id)?
+            {
+                positions.insert(user_id, position);
+            }
+        }
+
+        Ok(positions)
+    }
+
+    /// Publish an event if an event bus is attached
+    fn publish(&self, event: AppEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+}
+
+/// Load an installed application
+///
+/// Shared by the services that act on installed apps.
+pub(crate) fn installed_app(storage: &SqlStorage, app_id: &str) -> Result<OsnovaApplication> {
+    storage
+        .get_application(app_id)?
+        .with_context(|| format!("Application {} not found", app_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::cache::CacheManager;
+    use crate::components::ComponentIntegrity;
+    use crate::events::OsnovaEvent;
+    use crate::models::health::HealthStatus;
+    use crate::models::identity::RootIdentity;
+    use crate::models::key_cocoon::KeyType;
+    use crate::models::task::TaskState;
+    use crate::rpc::RpcServer;
+    use crate::services::handshake::{ReadinessState, StartKind, COMPONENT_READY_METHOD};
+    use crate::services::health::{HealthAction, HealthPolicy};
+    use crate::services::metadata::MetadataField;
+    use crate::services::KeyService;
+    use crate::services::RuntimeSettings;
+    use crate::time::MockClock;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> Result<(AppsService, TempDir)> {
         let temp_dir = TempDir::new()?;
         let service = AppsService::new(temp_dir.path())?;
         Ok((service, temp_dir))
     }
 
     #[test]
-    fn test_list_empty() -> Result<()> {
+    fn test_list_empty() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let apps = service.list()?;
+        assert_eq!(apps.len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_apps() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        // Add some test apps
+        let app1 = OsnovaApplication::new(
+            "com.test.app1",
+            "Test App 1",
+            "1.0.0",
+            "https://icon1.url",
+            "Test app 1",
+            vec![],
+        )?;
+        let app2 = OsnovaApplication::new(
+            "com.test.app2",
+            "Test App 2",
+            "2.0.0",
+            "https://icon2.url",
+            "Test app 2",
+            vec![],
+        )?;
+
+        service.sql_storage.upsert_application(&app1)?;
+        service.sql_storage.upsert_application(&app2)?;
+
+        let apps = service.list()?;
+        assert_eq!(apps.len(), 2);
+        assert!(apps.iter().any(|a| a.id == "com.test.app1"));
+        assert!(apps.iter().any(|a| a.id == "com.test.app2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_existing_app() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent("com.test.app", true, vec![])?;
+
+        // Should not error
+        service.launch("com.test.app")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_teaches_predictor() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_704_096_000));
+        let predictor = Arc::new(LaunchPredictor::new(temp.path())?.with_clock(clock));
+        let service = AppsService::new(temp.path())?.with_predictor(predictor.clone());
+        let app = OsnovaApplication::new("com.test.app", "App", "1.0.0", "icon", "app", vec![])?;
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent("com.test.app", true, vec![])?;
+
+        service.launch("com.test.app")?;
+        service.launch("com.test.app")?;
+
+        let predicted = predictor.predict(Duration::from_secs(60 * 60), 5)?;
+        assert_eq!(predicted.len(), 1);
+        assert_eq!(predicted[0].app_id, "com.test.app");
+        assert!((predicted[0].score - 2.0).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    /// Service keeping consent for "user", audited, with one unsigned app
+    fn create_consent_service() -> Result<(AppsService, Arc<AuditLog>, TempDir)> {
+        let temp = TempDir::new()?;
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &RootIdentity::generate()?,
+            "user",
+        )?);
+        let service = AppsService::new(temp.path())?
+            .with_user("user")
+            .with_audit(audit.clone());
+        service.install_application(&scheme_app("com.test.app", "App", &[])?)?;
+        Ok((service, audit, temp))
+    }
+
+    fn consent_required(result: Result<()>) -> ConsentReview {
+        let err = result.unwrap_err();
+        err.downcast_ref::<ConsentRequired>()
+            .unwrap_or_else(|| panic!("expected ConsentRequired, got {:#}", err))
+            .review
+            .clone()
+    }
+
+    fn audited(audit: &AuditLog, action: AuditAction) -> Result<usize> {
+        let filter = AuditFilter {
+            action: Some(action),
+            ..AuditFilter::default()
+        };
+        Ok(audit.list(&filter, PageRequest::default())?.total)
+    }
+
+    #[test]
+    fn test_launch_requires_consent() -> Result<()> {
+        let (service, audit, _temp) = create_consent_service()?;
+        let service = service.with_clock(Arc::new(MockClock::new(1_704_096_000)));
+
+        let review = consent_required(service.launch("com.test.app"));
+        assert_eq!(review.info.app.id, "com.test.app");
+        assert!(!review.signed);
+        assert_eq!(
+            review.warnings,
+            vec![WARNING_NO_PUBLISHER, WARNING_UNSIGNED]
+        );
+        assert!(review.previous.is_none());
+        assert!(!review.declined());
+
+        let warnings = review.warnings.clone();
+        let consent = service.record_consent("com.test.app", true, warnings)?;
+        assert_eq!(consent.version, "1.0.0");
+        assert_eq!(consent.decided_at, 1_704_096_000);
+        service.launch("com.test.app")?;
+        assert_eq!(audited(&audit, AuditAction::AppConsentGiven)?, 1);
+
+        // Consent is kept per user
+        let other = AppsService::new(&service.storage_path)?.with_user("other");
+        consent_required(other.launch("com.test.app"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consent_retriggers_on_permission_expansion() -> Result<()> {
+        let (service, _audit, _temp) = create_consent_service()?;
+        service.record_consent("com.test.app", true, vec![])?;
+
+        let update = |version: &str, schemes: &[&str]| {
+            let app = OsnovaApplication::new(
+                "com.test.app",
+                "App",
+                version,
+                "https://icon.url",
+                "App",
+                vec![],
+            )?
+            .with_uri_schemes(schemes);
+            service.sql_storage.upsert_application(&app)
+        };
+
+        // A minor update with the same permissions keeps the decision
+        update("1.1.0", &[])?;
+        service.launch("com.test.app")?;
+
+        update("1.2.0", &["mailto"])?;
+        let review = consent_required(service.launch("com.test.app"));
+        assert_eq!(review.new_permissions, vec!["uriScheme:mailto"]);
+        assert!(review
+            .warnings
+            .contains(&WARNING_NEW_PERMISSIONS.to_string()));
+        assert_eq!(review.previous.unwrap().version, "1.0.0");
+
+        service.record_consent("com.test.app", true, vec![])?;
+        service.launch("com.test.app")?;
+
+        update("2.0.0", &["mailto"])?;
+        let review = consent_required(service.launch("com.test.app"));
+        assert!(review.new_permissions.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_declined_app_stays_installed_but_blocked() -> Result<()> {
+        let (service, audit, _temp) = create_consent_service()?;
+
+        service.record_consent("com.test.app", false, vec![])?;
+        assert_eq!(audited(&audit, AuditAction::AppConsentDeclined)?, 1);
+
+        let status = service.list_with_status(false)?;
+        assert_eq!(status[0].state, AppInstallState::Declined);
+        let review = consent_required(service.launch("com.test.app"));
+        assert!(review.declined());
+
+        service.record_consent("com.test.app", true, vec![])?;
+        assert_eq!(
+            service.list_with_status(false)?[0].state,
+            AppInstallState::Installed
+        );
+        service.launch("com.test.app")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consent_gate_can_be_disabled() -> Result<()> {
+        let (service, audit, _temp) = create_consent_service()?;
+        service.record_consent("com.test.app", false, vec![])?;
+
+        service.config.set_first_launch_consent_required(false)?;
+        service.launch("com.test.app")?;
+        assert_eq!(
+            audit
+                .list(&AuditFilter::default(), PageRequest::default())?
+                .total,
+            1
+        );
+
+        service.config.set_first_launch_consent_required(true)?;
+        consent_required(service.launch("com.test.app"));
+
+        Ok(())
+    }
+
+    /// Launcher generation an app event carries
+    fn event_generation(event: AppEvent) -> u64 {
+        match event {
+            AppEvent::AppInstalled { generation, .. }
+            | AppEvent::AppUninstalled { generation, .. }
+            | AppEvent::AppRestored { generation, .. } => generation,
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_launcher_generation_bumps_once_per_mutation() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service.with_events(events);
+        let launcher = LauncherService::new(temp.path(), "user-1")?;
+        assert_eq!(service.generation()?, 0);
+
+        service.install_application(&scheme_app("com.test.a", "A", &[])?)?;
+        assert_eq!(event_generation(receiver.try_recv()?), 1);
+        launcher.set_layout(vec!["com.test.a".to_string()])?;
+        assert_eq!(launcher.generation()?, 2);
+        service.install_application(&scheme_app("com.test.a", "A", &["mailto"])?)?;
+        assert_eq!(event_generation(receiver.try_recv()?), 3);
+        // Dropping the app from layouts is part of the uninstall
+        service.uninstall("com.test.a", None)?;
+        assert_eq!(event_generation(receiver.try_recv()?), 4);
+        assert!(launcher.get_layout()?.app_ids.is_empty());
+        service.restore("com.test.a")?;
+        assert_eq!(event_generation(receiver.try_recv()?), 5);
+
+        // Reads and failed mutations leave it alone
+        service.list()?;
+        launcher.changes_since(0)?;
+        assert!(service.uninstall("com.test.missing", None).is_err());
+        assert_eq!(service.generation()?, 5);
+
+        drop(service);
+        assert_eq!(AppsService::new(temp.path())?.generation()?, 5);
+        assert_eq!(
+            LauncherService::new(temp.path(), "user-1")?.generation()?,
+            5
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_launcher_changes_since() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        let launcher = LauncherService::new(temp.path(), "user-1")?;
+        let other = LauncherService::new(temp.path(), "user-2")?;
+        for id in ["com.test.a", "com.test.b"] {
+            service.install_application(&scheme_app(id, id, &[])?)?;
+        }
+        launcher.set_layout(vec!["com.test.a".to_string(), "com.test.b".to_string()])?;
+        let rendered = launcher.generation()?;
+
+        service.install_application(&scheme_app("com.test.c", "C", &[])?)?;
+        service.install_application(&scheme_app("com.test.b", "B 2", &[])?)?;
+        service.uninstall("com.test.a", None)?;
+        // Installed and removed in between, so never seen
+        service.install_application(&scheme_app("com.test.d", "D", &[])?)?;
+        service.uninstall("com.test.d", Some(0))?;
+        other.set_layout(vec![])?;
+
+        let changes = launcher.changes_since(rendered)?;
+        assert_eq!(changes.since, rendered);
+        assert_eq!(changes.generation, rendered + 6);
+        assert_eq!(changes.added, vec!["com.test.c"]);
+        assert_eq!(changes.updated, vec!["com.test.b"]);
+        assert_eq!(changes.removed, vec!["com.test.a"]);
+        assert!(!changes.layout_changed);
+        assert!(!changes.full_reload);
+        assert!(other.changes_since(rendered)?.layout_changed);
+
+        // Restored since it was rendered, so it may have changed
+        service.restore("com.test.a")?;
+        let changes = launcher.changes_since(rendered)?;
+        assert_eq!(changes.updated, vec!["com.test.b", "com.test.a"]);
+        assert!(changes.removed.is_empty());
+
+        assert!(launcher.changes_since(launcher.generation()?)?.is_empty());
+        assert!(launcher.changes_since(rendered + 100)?.full_reload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_nonexistent_app() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let result = service.launch("com.nonexistent.app");
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        // Verify app exists
+        let apps = service.list()?;
+        assert_eq!(apps.len(), 1);
+
+        // Uninstall
+        service.uninstall("com.test.app", Some(0))?;
+
+        // Verify app is gone
+        let apps = service.list()?;
+        assert_eq!(apps.len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_owners_cover_every_kept_version() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let app_with = |id: &str, version: &str, component: &str| -> Result<OsnovaApplication> {
+            let component =
+                ComponentRef::new(component, component, ComponentKind::Backend, version)?;
+            Ok(OsnovaApplication::new(
+                id,
+                id,
+                version,
+                "https://icon.url",
+                "Test app",
+                vec![component],
+            )?)
+        };
+
+        let installed = app_with("com.test.installed", "2.0.0", "installed")?;
+        service.sql_storage.upsert_application(&installed)?;
+        let previous = app_with("com.test.installed", "1.0.0", "previous")?;
+        service.sql_storage.push_app_version(&previous, 1, 5)?;
+        let update = app_with("com.test.installed", "3.0.0", "update")?;
+        service
+            .sql_storage
+            .set_pending_update("com.test.installed", Some(&update))?;
+
+        let trashed = app_with("com.test.trashed", "1.0.0", "trashed")?;
+        service.sql_storage.upsert_application(&trashed)?;
+        service.uninstall("com.test.trashed", None)?;
+
+        let owners: BTreeSet<String> = service
+            .cache_owners()?
+            .into_iter()
+            .map(|component| component.id)
+            .collect();
+        assert_eq!(
+            owners,
+            BTreeSet::from(["installed", "previous", "update", "trashed"].map(String::from))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall_nonexistent() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let result = service.uninstall("com.nonexistent.app", None);
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hard_uninstall_removes_config() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        let config = ConfigService::new(temp.path())?;
+        let mut settings = HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        config.set_app_config("com.test.app", "user-123", settings)?;
+
+        service.uninstall("com.test.app", Some(0))?;
+
+        assert!(service.list_trash()?.is_empty());
+        assert!(service.restore("com.test.app").is_err());
+        let config = config.get_app_config("com.test.app", "user-123")?;
+        assert!(config.settings().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_migrates_settings_to_new_schema() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        let reader = |version: &str, themes: &[&str]| -> Result<OsnovaApplication> {
+            let options: Vec<_> = themes
+                .iter()
+                .map(|theme| serde_json::json!({"value": theme, "label": theme}))
+                .collect();
+            let schema = serde_json::from_value(serde_json::json!({"sections": [{
+                "id": "display",
+                "label": "Display",
+                "fields": [{"key": "theme", "label": "Theme", "type": "enum",
+                            "default": "light", "options": options}]
+            }]}))?;
+            Ok(OsnovaApplication::new(
+                "com.test.reader",
+                "Reader",
+                version,
+                "https://icon.url",
+                "Reader",
+                vec![],
+            )?
+            .with_settings_schema(schema))
+        };
+
+        service.install_application(&reader("1.0.0", &["light", "sepia"])?)?;
+        let config = ConfigService::new(temp.path())?;
+        let mut settings = HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("sepia"));
+        config.set_app_config("com.test.reader", "user-123", settings)?;
+
+        // Same schema: stored values are left alone
+        service.install_application(&reader("1.1.0", &["light", "sepia"])?)?;
+        let stored = config.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(
+            stored.get_setting("theme"),
+            Some(&serde_json::json!("sepia"))
+        );
+
+        // Sepia is gone in 2.0.0, so the default applies again
+        service.install_application(&reader("2.0.0", &["light", "dark"])?)?;
+        let stored = config.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(
+            stored.get_setting("theme"),
+            Some(&serde_json::json!("light"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_restore_round_trip() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        let config = ConfigService::new(temp.path())?;
+        let mut settings = HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        config.set_app_config("com.test.app", "user-123", settings)?;
+
+        let keys = KeyService::new(temp.path(), &[9u8; 32])?;
+        keys.initialize(&[7u8; 32])?;
+        let derived = keys.derive("com.test.app", KeyType::Ed25519)?;
+
+        let launcher = LauncherService::new(temp.path(), "user-123")?;
+        launcher.set_layout(vec!["com.test.app".to_string(), "com.other".to_string()])?;
+
+        // Uninstall using the configured default retention
+        service.uninstall("com.test.app", None)?;
+        assert!(service.list()?.is_empty());
+        assert_eq!(
+            launcher.get_layout()?.app_ids,
+            vec!["com.other".to_string()]
+        );
+
+        let trash = service.list_trash()?;
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].state, AppInstallState::Trashed);
+        assert_eq!(service.list_with_status(false)?.len(), 0);
+        assert_eq!(service.list_with_status(true)?.len(), 1);
+
+        service.restore("com.test.app")?;
+        assert_eq!(service.list()?.len(), 1);
+        assert!(service.list_trash()?.is_empty());
+
+        // Back where it was on the launcher
+        assert_eq!(
+            launcher.get_layout()?.app_ids,
+            vec!["com.test.app".to_string(), "com.other".to_string()]
+        );
+
+        // Config and keys survived the round trip
+        let config = config.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+        assert!(keys.get_by_public_key(&derived.public_key).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_expired_trash() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        let config = ConfigService::new(temp.path())?;
+        let mut settings = HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        config.set_app_config("com.test.app", "user-123", settings)?;
+
+        service.uninstall("com.test.app", Some(1))?;
+        let purge_after = service.list_trash()?[0].purge_after.unwrap();
+
+        // Nothing expires inside the window
+        assert!(service.purge_expired_trash_at(purge_after - 1)?.is_empty());
+        assert_eq!(service.list_trash()?.len(), 1);
+
+        let purged = service.purge_expired_trash_at(purge_after)?;
+        assert_eq!(purged, vec!["com.test.app".to_string()]);
+        assert!(service.list_trash()?.is_empty());
+        assert!(service.restore("com.test.app").is_err());
+
+        let config = config.get_app_config("com.test.app", "user-123")?;
+        assert!(config.settings().is_empty());
+
+        Ok(())
+    }
+
+    /// Write a frontend tarball holding `files` and reference it
+    fn frontend_bundle(dir: &Path, name: &str, files: &[(&str, &str)]) -> Result<ComponentRef> {
+        let tarball = dir.join(format!("{}.tar.gz", name));
+        let encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&tarball)?,
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+
+        let hash = crate::components::integrity::content_hash(&std::fs::read(&tarball)?);
+        Ok(ComponentRef::new(
+            format!("file://{}", tarball.display()),
+            name,
+            ComponentKind::Frontend,
+            "1.0.0",
+        )?
+        .with_hash(hash))
+    }
+
+    /// Write a frontend tarball and install an app that references it
+    fn install_frontend_app(
+        service: &AppsService,
+        dir: &Path,
+        name: &str,
+    ) -> Result<ComponentSchema> {
+        let component = frontend_bundle(
+            dir,
+            name,
+            &[
+                ("index.html", "<html></html>"),
+                ("assets/app.js", "console.log('app')"),
+                ("assets/style.css", "body {}"),
+            ],
+        )?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![component.clone()],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent(app.id(), true, vec![])?;
+
+        Ok(ComponentSchema::from(&component))
+    }
+
+    fn create_service_with_downloader(temp: &TempDir) -> Result<AppsService> {
+        let cache = CacheManager::new(temp.path().join("cache"), 10 * 1024 * 1024)?;
+        Ok(AppsService::new(temp.path())?.with_downloader(ComponentDownloader::new(cache, None)))
+    }
+
+    /// Manifest of an app with one frontend per name
+    fn bundle_manifest(dir: &Path, names: &[&str]) -> Result<ManifestSchema> {
+        let components = names
+            .iter()
+            .map(|name| frontend_bundle(dir, name, &[("index.html", "<html></html>")]))
+            .collect::<Result<Vec<_>>>()?;
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            components,
+        )?;
+        Ok(ManifestSchema::from(&app))
+    }
+
+    /// Service that stops installs at `crash_at`, as a crash would
+    fn crashing_service(temp: &TempDir, crash_at: InstallStep) -> Result<AppsService> {
+        Ok(
+            create_service_with_downloader(temp)?.with_install_failpoint(move |step| {
+                match step == crash_at {
+                    true => anyhow::bail!("simulated crash after {:?}", step),
+                    false => Ok(()),
+                }
+            }),
+        )
+    }
+
+    /// Service started after the crash, reporting to the notification center
+    fn restarted_service(temp: &TempDir) -> Result<(AppsService, Arc<NotificationService>)> {
+        let notifications = Arc::new(NotificationService::new(temp.path(), "user")?);
+        let service =
+            create_service_with_downloader(temp)?.with_notifications(notifications.clone());
+        Ok((service, notifications))
+    }
+
+    #[tokio::test]
+    async fn test_recover_install_after_each_step() -> Result<()> {
+        use crate::services::NotificationFilter;
+
+        let cases = [
+            (InstallStep::Started, InstallResolution::RolledBack),
+            (
+                InstallStep::ComponentsFetched,
+                InstallResolution::RolledForward,
+            ),
+            (InstallStep::Recorded, InstallResolution::RolledForward),
+        ];
+        for (index, (crash_at, expected)) in cases.into_iter().enumerate() {
+            let temp = TempDir::new()?;
+            let name = format!("journal-step-test-{}", index);
+            let manifest = bundle_manifest(temp.path(), &[&name])?;
+
+            let crashing = crashing_service(&temp, crash_at)?;
+            assert!(crashing.install_manifest(&manifest).await.is_err());
+            drop(crashing);
+
+            let (service, notifications) = restarted_service(&temp)?;
+            let recoveries = service.recover_installs().await?;
+            assert_eq!(
+                recoveries,
+                vec![InstallRecovery {
+                    app_id: "com.test.app".to_string(),
+                    version: "1.0.0".to_string(),
+                    step: crash_at,
+                    resolution: expected,
+                }]
+            );
+            assert!(service.sql_storage.list_install_journal()?.is_empty());
+
+            let installed = service.sql_storage.get_application("com.test.app")?;
+            let notified = notifications.list(&NotificationFilter::default())?;
+            assert_eq!(notified.len(), 1);
+            match expected {
+                InstallResolution::RolledForward => {
+                    assert!(installed.is_some());
+                    assert!(service.verify("com.test.app").await?.is_healthy());
+                    assert_eq!(notified[0].notification.title, "Install completed");
+                }
+                InstallResolution::RolledBack => {
+                    assert!(installed.is_none());
+                    assert_eq!(notified[0].notification.title, "Install undone");
+                }
+            }
+
+            // Nothing left to recover on the next start
+            assert!(service.recover_installs().await?.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_rolls_back_partial_state() -> Result<()> {
+        let temp = TempDir::new()?;
+        let manifest = bundle_manifest(temp.path(), &["journal-partial-a", "journal-partial-b"])?;
+
+        // The second download fails after the first was cached
+        std::fs::remove_file(temp.path().join("journal-partial-b.tar.gz"))?;
+        let service = create_service_with_downloader(&temp)?;
+        assert!(service.install_manifest(&manifest).await.is_err());
+        let first = &manifest.components[0];
+        assert!(service.downloader()?.quick_check(first).await);
+
+        let (service, _notifications) = restarted_service(&temp)?;
+        let recoveries = service.recover_installs().await?;
+        assert_eq!(recoveries[0].resolution, InstallResolution::RolledBack);
+        assert!(!service.downloader()?.quick_check(first).await);
+
+        // A written row whose components no longer verify is removed too
+        let temp = TempDir::new()?;
+        let manifest = bundle_manifest(temp.path(), &["journal-partial-row"])?;
+        let crashing = crashing_service(&temp, InstallStep::Recorded)?;
+        assert!(crashing.install_manifest(&manifest).await.is_err());
+        crashing
+            .downloader()?
+            .remove(&manifest.components[0])
+            .await?;
+
+        let (service, _notifications) = restarted_service(&temp)?;
+        let recoveries = service.recover_installs().await?;
+        assert_eq!(recoveries[0].resolution, InstallResolution::RolledBack);
+        assert!(service
+            .sql_storage
+            .get_application("com.test.app")?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_is_idempotent_over_partial_state() -> Result<()> {
+        let temp = TempDir::new()?;
+        let manifest = bundle_manifest(temp.path(), &["journal-idempotent-test"])?;
+
+        let crashing = crashing_service(&temp, InstallStep::ComponentsFetched)?;
+        assert!(crashing.install_manifest(&manifest).await.is_err());
+        drop(crashing);
+
+        // The verified component is not fetched again
+        std::fs::remove_file(temp.path().join("journal-idempotent-test.tar.gz"))?;
+        let service = create_service_with_downloader(&temp)?;
+        service.install_manifest(&manifest).await?;
+        service.install_manifest(&manifest).await?;
+
+        assert!(service.sql_storage.list_install_journal()?.is_empty());
+        assert_eq!(service.list()?.len(), 1);
+        assert_eq!(
+            service.sql_storage.get_manifest_snapshot("com.test.app")?,
+            Some(manifest)
+        );
+        assert!(service.recover_installs().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_reports_storage_compaction() -> Result<()> {
+        use crate::services::StorageRoots;
+
+        let temp = TempDir::new()?;
+        let storage = FileStorage::new(temp.path())?;
+        storage.write("ui/alice/theme.json", b"alice", &[0u8; 32])?;
+        storage.write("ui/bob/theme.json", b"bob", &[0u8; 32])?;
+        let roots = StorageRoots {
+            data: temp.path().to_path_buf(),
+            cache: temp.path().join("cache"),
+            config: temp.path().join("config"),
+        };
+        let storage_service = Arc::new(StorageService::new(roots));
+        let service = create_service_with_downloader(&temp)?
+            .with_user("alice")
+            .with_storage(storage_service.clone());
+
+        service.run_maintenance().await;
+        let report = storage_service.last_compaction().unwrap();
+        assert!(report.report_only);
+        assert_eq!(report.items[0].path, PathBuf::from("ui/bob/theme.json"));
+        assert!(storage.exists("ui/bob/theme.json"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_applies_retention() -> Result<()> {
+        use crate::models::notification::Notification;
+        use crate::models::retention::{Policy, RetentionStore};
+        use crate::services::RetainedStore;
+        use crate::time::MockClock;
+
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let notifications =
+            Arc::new(NotificationService::new(temp.path(), "alice")?.with_clock(clock.clone()));
+        let retention = Arc::new(
+            RetentionService::new(ConfigService::new(temp.path())?)
+                .with_store(notifications.clone())
+                .with_clock(clock.clone()),
+        );
+        retention.set_policy(
+            RetentionStore::Notifications,
+            Some(Policy {
+                max_age: Some(Duration::from_secs(60)),
+                ..Policy::default()
+            }),
+        )?;
+        let service = create_service_with_downloader(&temp)?.with_retention(retention.clone());
+
+        notifications.post("com.test.app", Notification::new("old", ""))?;
+        clock.advance(Duration::from_secs(120));
+        notifications.post("com.test.app", Notification::new("new", ""))?;
+        // Writes only check count and size, so the expired one is still there
+        assert_eq!(notifications.footprint()?.items, 2);
+
+        service.run_maintenance().await;
+        let run = retention.last_run().unwrap();
+        assert_eq!(run.removed(), 1);
+        assert_eq!(run.reports[0].remaining.items, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_resolves_platform_config() -> Result<()> {
+        let temp = TempDir::new()?;
+        let mut manifest = bundle_manifest(temp.path(), &["condition-config-test"])?;
+        manifest.components[0].config = Some(HashMap::from([
+            (
+                "theme".to_string(),
+                serde_json::json!({
+                    "$when": "platform == 'iOS'", "value": "cupertino", "else": "material"
+                }),
+            ),
+            (
+                "haptics".to_string(),
+                serde_json::json!({"$when": "platform != 'desktop'", "value": true}),
+            ),
+        ]));
+        let manifest = crate::manifest::validate_manifest(&serde_json::to_string(&manifest)?)?;
+
+        let service = create_service_with_downloader(&temp)?
+            .with_condition_context(ConditionContext::current().with_platform("desktop"));
+        service.install_manifest(&manifest).await?;
+        let stored = service
+            .sql_storage
+            .get_application("com.test.app")?
+            .unwrap();
+        let config = stored.components()[0].config().unwrap();
+        assert_eq!(config["theme"], serde_json::json!("material"));
+        assert!(!config.contains_key("haptics"));
+        // The manifest is kept as signed, conditions included
+        assert_eq!(
+            service.sql_storage.get_manifest_snapshot("com.test.app")?,
+            Some(manifest.clone())
+        );
+
+        let service = create_service_with_downloader(&temp)?
+            .with_condition_context(ConditionContext::current().with_platform("iOS"));
+        service.install_manifest(&manifest).await?;
+        let stored = service
+            .sql_storage
+            .get_application("com.test.app")?
+            .unwrap();
+        let config = stored.components()[0].config().unwrap();
+        assert_eq!(config["theme"], serde_json::json!("cupertino"));
+        assert_eq!(config["haptics"], serde_json::json!(true));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_records_frontend_entry() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        // Built output under dist/, inside the folder the tarball was made from
+        let component = frontend_bundle(
+            temp.path(),
+            "entry-wrapper-test",
+            &[
+                (
+                    "my-app/dist/index.html",
+                    r#"<script src="/assets/app.js"></script>"#,
+                ),
+                ("my-app/dist/assets/app.js", "console.log('app')"),
+                ("my-app/package.json", "{}"),
+            ],
+        )?;
+        service
+            .downloader()?
+            .download(&ComponentSchema::from(&component))
+            .await?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![component],
+        )?;
+        let warnings = service.install_application(&app)?;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'/assets/app.js'"), "{}", warnings[0]);
+
+        let stored = service
+            .sql_storage
+            .get_application("com.test.app")?
+            .unwrap();
+        assert_eq!(
+            stored.components()[0].entry(),
+            Some("my-app/dist/index.html")
+        );
+
+        let frontend = service.frontend_entry("com.test.app")?.unwrap();
+        assert_eq!(frontend.entry, "my-app/dist/index.html");
+        assert!(frontend.entry_file().is_file());
+        assert_eq!(frontend.asset_path(""), Some(frontend.entry_file()));
+        assert!(frontend.asset_path("/assets/app.js?v=1").unwrap().is_file());
+        assert_eq!(frontend.asset_path("../package.json"), None);
+
+        service.record_consent("com.test.app", true, vec![])?;
+        service.launch("com.test.app")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_fails_without_frontend_entry() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let component = frontend_bundle(
+            temp.path(),
+            "entry-missing-test",
+            &[("src/pages/home.html", "<html></html>"), ("README.md", "")],
+        )?;
+        service
+            .downloader()?
+            .download(&ComponentSchema::from(&component))
+            .await?;
+
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![component],
+        )?;
+        let message = service.install_application(&app).unwrap_err().to_string();
+        assert!(
+            message.contains("Frontend entry-missing-test"),
+            "{}",
+            message
+        );
+        assert!(message.contains("No index.html found"), "{}", message);
+        assert!(message.contains("src/pages/home.html"), "{}", message);
+        assert!(service
+            .sql_storage
+            .get_application("com.test.app")?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_flags_corrupted_file() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let component = install_frontend_app(&service, temp.path(), "verify-corrupt-test")?;
+        let downloader = service.downloader()?;
+        let extracted = downloader.download(&component).await?;
+
+        assert!(service.verify("com.test.app").await?.is_healthy());
+
+        std::fs::write(extracted.join("assets/app.js"), b"garbage")?;
+
+        let report = service.verify("com.test.app").await?;
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.components[0].integrity,
+            ComponentIntegrity::FilesCorrupted {
+                files: vec!["assets/app.js".to_string()]
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_repair_restores_identical_tree() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let component = install_frontend_app(&service, temp.path(), "verify-repair-test")?;
+        let extracted = service.downloader()?.download(&component).await?;
+
+        let original = crate::components::integrity::ContentManifest::from_dir(&extracted)?;
+
+        std::fs::write(extracted.join("index.html"), b"broken")?;
+        std::fs::remove_file(extracted.join("assets/style.css"))?;
+        std::fs::write(extracted.join("stray.txt"), b"stray")?;
+
+        let report = service.repair("com.test.app").await?;
+        assert!(report.is_healthy());
+
+        let restored = crate::components::integrity::ContentManifest::from_dir(&extracted)?;
+        assert_eq!(restored, original);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_info_includes_provenance_history() -> Result<()> {
+        use crate::models::provenance::VerificationOutcome;
+        use crate::services::ProvenanceService;
+
+        let temp = TempDir::new()?;
+        let provenance = Arc::new(ProvenanceService::new(temp.path())?);
+        let cache = CacheManager::new(temp.path().join("cache"), 10 * 1024 * 1024)?;
+        let downloader = ComponentDownloader::new(cache, None).with_provenance(provenance.clone());
+        let service = AppsService::new(temp.path())?.with_downloader(downloader);
+        let component = install_frontend_app(&service, temp.path(), "provenance-info-test")?;
+
+        let extracted = service.downloader()?.download(&component).await?;
+        let info = service.info("com.test.app")?;
+        assert_eq!(info.components.len(), 1);
+        assert_eq!(info.components[0].download_count, 1);
+        let first = info.components[0].latest.clone().unwrap();
+        assert!(first.origin.is_none());
+        let check = first.content_check.unwrap();
+        assert!(check.passed() && check.findings.is_empty());
+
+        // Re-download through repair appends, attributed to the manifest
+        std::fs::write(extracted.join("index.html"), b"broken")?;
+        service.repair("com.test.app").await?;
+
+        let info = service.info("com.test.app")?;
+        assert_eq!(info.app.id, "com.test.app");
+        assert_eq!(info.components[0].download_count, 2);
+        let latest = info.components[0].latest.clone().unwrap();
+        assert_eq!(latest.source_uri, component.id);
+        assert_eq!(latest.component_hash, component.hash.clone().unwrap());
+        assert_eq!(latest.verification, VerificationOutcome::HashVerified);
+        let origin = latest.origin.unwrap();
+        assert_eq!(origin.manifest_id, "com.test.app");
+        assert_eq!(origin.manifest_version, "1.0.0");
+
+        assert_eq!(provenance.for_app("com.test.app")?.len(), 2);
+
+        // Display fields are filled in for the user's locale on request
+        assert!(info.display.is_none());
+        let downloaded_at = info.last_downloaded().unwrap();
+        assert_eq!(downloaded_at, latest.downloaded_at);
+        let context = DisplayContext::new(
+            crate::util::format::Locale::new("es-ES"),
+            downloaded_at + 3 * 3_600,
+        );
+        let info = info.localized(&context);
+        assert_eq!(
+            info.display.unwrap().last_downloaded.as_deref(),
+            Some("hace 3 horas")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reproducibility_marker_per_version() -> Result<()> {
+        use crate::manifest::normalize_artifact;
+
+        let temp = TempDir::new()?;
+        let service = AppsService::new(temp.path())?;
+        let build = temp.path().join("dist");
+        std::fs::create_dir_all(&build)?;
+        std::fs::write(build.join("index.html"), "<html></html>")?;
+
+        let published = normalize_artifact(&build)?;
+        let component = ComponentRef::new(
+            "ant://frontend",
+            "Frontend",
+            ComponentKind::Frontend,
+            "1.0.0",
+        )?
+        .with_hash(published.hash.clone());
+        let app = |version: &str| {
+            OsnovaApplication::new(
+                "com.test.app",
+                "Test App",
+                version,
+                "https://icon.url",
+                "Test app",
+                vec![component.clone()],
+            )
+        };
+        service.sql_storage.upsert_application(&app("1.0.0")?)?;
+        assert!(service.info("com.test.app")?.reproducibility.is_empty());
+
+        let verdict = service.verify_reproducible("com.test.app", "ant://frontend", &build)?;
+        assert_eq!(
+            verdict,
+            ReproVerdict::Match {
+                hash: published.hash.clone()
+            }
+        );
+        let marker = service.info("com.test.app")?.reproducibility;
+        assert_eq!(marker.len(), 1);
+        assert_eq!(marker[0].app_version, "1.0.0");
+        assert_eq!(marker[0].hash, published.hash);
+
+        // A changed build is a mismatch and records nothing
+        std::fs::write(build.join("index.html"), "<html>!</html>")?;
+        let verdict = service.verify_reproducible("com.test.app", "ant://frontend", &build)?;
+        assert!(matches!(verdict, ReproVerdict::Mismatch { .. }));
+        assert_eq!(service.info("com.test.app")?.reproducibility.len(), 1);
+
+        // The marker belongs to the version it was verified for
+        service.sql_storage.upsert_application(&app("2.0.0")?)?;
+        assert!(service.info("com.test.app")?.reproducibility.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_shows_annotation() -> Result<()> {
+        use crate::models::identity::RootIdentity;
+
+        let temp = TempDir::new()?;
+        let annotations = Arc::new(AnnotationService::new(
+            temp.path(),
+            "test-user",
+            &RootIdentity::generate()?,
+        )?);
+        let service = AppsService::new(temp.path())?.with_annotations(annotations.clone());
+        install_frontend_app(&service, temp.path(), "annotation-info-test")?;
+        assert!(service.info("com.test.app")?.annotation.is_none());
+
+        let subject = Subject::App("com.test.app".to_string());
+        annotations.set(&subject, "Approved for kids")?;
+        annotations.set(&Subject::Device("com.test.app".to_string()), "A device")?;
+        let annotation = service.info("com.test.app")?.annotation.unwrap();
+        assert_eq!(annotation.subject, subject);
+        assert_eq!(annotation.text, "Approved for kids");
+
+        annotations.delete(&subject)?;
+        assert!(service.info("com.test.app")?.annotation.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_launch_verified_repairs_missing_cache_entry() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let component = install_frontend_app(&service, temp.path(), "verify-launch-test")?;
+        let downloader = service.downloader()?;
+        downloader.download(&component).await?;
+
+        // Simulate a partial eviction
+        std::fs::remove_dir_all(temp.path().join("cache"))?;
+        std::fs::create_dir_all(temp.path().join("cache"))?;
+        assert!(!downloader.quick_check(&component).await);
+
+        service.launch_verified("com.test.app").await?;
+
+        assert!(downloader.quick_check(&component).await);
+        assert!(service.verify("com.test.app").await?.is_healthy());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_materialization_states() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let manifest = bundle_manifest(temp.path(), &["state-test-a", "state-test-b"])?;
+        service.install_manifest(&manifest).await?;
+        let [first, second] = [&manifest.components[0], &manifest.components[1]];
+
+        // An install leaves every component cached
+        let status = service.list_with_status(false)?;
+        assert_eq!(
+            status[0].materialization,
+            Some(Materialization::Materialized)
+        );
+        assert_eq!(
+            service.check_materialization("com.test.app").await?,
+            Materialization::Materialized
+        );
+
+        let downloader = service.downloader()?;
+        downloader.remove(first).await?;
+        let partial = Materialization::PartiallyMaterialized {
+            missing: vec![first.id.clone()],
+        };
+        assert_eq!(
+            service.check_materialization("com.test.app").await?,
+            partial
+        );
+        assert_eq!(service.info("com.test.app")?.materialization, Some(partial));
+
+        downloader.remove(second).await?;
+        assert_eq!(
+            service.check_materialization("com.test.app").await?,
+            Materialization::NotMaterialized
+        );
+        let status = service.list_with_status(false)?;
+        assert_eq!(
+            status[0].materialization,
+            Some(Materialization::NotMaterialized)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_launch_materializes_restored_app() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = create_service_with_downloader(&temp)?;
+        let manifest = bundle_manifest(temp.path(), &["restored-a", "restored-b"])?;
+        service.install_manifest(&manifest).await?;
+        service.record_consent("com.test.app", true, vec![])?;
+
+        // A restore brings the app without its cache
+        std::fs::remove_dir_all(temp.path().join("cache"))?;
+        std::fs::create_dir_all(temp.path().join("cache"))?;
+        service.sql_storage.mark_unmaterialized()?;
+
+        let mut progress = Vec::new();
+        service
+            .launch_materialized("com.test.app", &NetworkOptions::default(), |p| {
+                progress.push(p.clone())
+            })
+            .await?;
+
+        let mut fetched: Vec<&str> = progress.iter().map(|p| p.component_id.as_str()).collect();
+        fetched.sort();
+        let mut expected: Vec<&str> = manifest.components.iter().map(|c| c.id.as_str()).collect();
+        expected.sort();
+        assert_eq!(fetched, expected);
+        assert_eq!(
+            progress.iter().map(|p| p.completed).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(progress.iter().all(|p| p.total == 2 && p.error.is_none()));
+
+        assert_eq!(
+            service.sql_storage.get_materialization("com.test.app")?,
+            Some(Materialization::Materialized)
+        );
+        assert!(service.verify("com.test.app").await?.is_healthy());
+
+        Ok(())
+    }
+
+    /// Serve a script body over HTTP, counting requests and the most
+    /// handled at once
+    async fn spawn_counting_server(counter: Arc<Mutex<(usize, usize, usize)>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    {
+                        let mut counter = counter.lock().unwrap();
+                        counter.0 += 1;
+                        counter.1 = counter.1.max(counter.0);
+                        counter.2 += 1;
+                    }
+                    let mut buf = [0u8; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let body = b"exit 0\n";
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = socket.write_all(header.as_bytes()).await;
+                    let _ = socket.write_all(body).await;
+                    counter.lock().unwrap().0 -= 1;
+                });
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_materialize_all_bounded_and_deferred() -> Result<()> {
+        use crate::network::{BandwidthMeter, BandwidthPolicy};
+
+        let temp = TempDir::new()?;
+        let counter = Arc::new(Mutex::new((0usize, 0usize, 0usize)));
+        let base = spawn_counting_server(counter.clone()).await;
+
+        let meter = Arc::new(BandwidthMeter::new(
+            SqlStorage::new_in_memory()?,
+            BandwidthPolicy::WifiOnly,
+        ));
+        meter.set_metered_mode(true);
+        let tasks = Arc::new(TaskRegistry::new());
+        let cache = CacheManager::new(temp.path().join("cache"), 10 * 1024 * 1024)?;
+        let downloader = ComponentDownloader::new(cache, None)
+            .with_bandwidth_meter(meter.clone())
+            .with_http(crate::http::local_fetcher())
+            .with_tasks(tasks.clone());
+        let service = AppsService::new(temp.path())?
+            .with_downloader(downloader)
+            .with_tasks(tasks.clone());
+        let mut events = tasks.subscribe();
+
+        // Six restored apps; the last two share a backend
+        for index in 0..6 {
+            let backend = ComponentRef::new(
+                format!("{}/backend-{}", base, index.min(4)),
+                format!("materialize-all-{}", index.min(4)),
+                ComponentKind::Backend,
+                "1.0.0",
+            )?
+            .with_interpreter("sh");
+            let app_id = format!("com.test.app{}", index);
+            let app =
+                OsnovaApplication::new(&app_id, "App", "1.0.0", "icon", "app", vec![backend])?;
+            service.sql_storage.upsert_application(&app)?;
+        }
+        service.sql_storage.mark_unmaterialized()?;
+
+        // A metered connection defers every download
+        let policy = MaterializePolicy {
+            concurrency: 2,
+            unmaterialized_only: true,
+        };
+        let mut progress = Vec::new();
+        let outcomes = service
+            .materialize_all(&policy, &NetworkOptions::default(), |p| {
+                progress.push(p.clone())
+            })
+            .await?;
+        assert_eq!(outcomes.len(), 6);
+        assert!(outcomes
+            .iter()
+            .all(|o| o.deferred && o.state == Materialization::NotMaterialized));
+        assert!(progress.is_empty());
+        assert_eq!(counter.lock().unwrap().2, 0);
+
+        // Once allowed, shared components download once, two at a time
+        meter.set_policy(BandwidthPolicy::Unlimited);
+        let outcomes = service
+            .materialize_all(&policy, &NetworkOptions::default(), |p| {
+                progress.push(p.clone())
+            })
+            .await?;
+        assert!(outcomes
+            .iter()
+            .all(|o| !o.deferred && o.state.is_materialized()));
+        assert_eq!(
+            progress.iter().map(|p| p.completed).collect::<Vec<_>>(),
+            (1..=5).collect::<Vec<_>>()
+        );
+        let (_, max_in_flight, requests) = *counter.lock().unwrap();
+        assert_eq!(requests, 5);
+        assert_eq!(max_in_flight, 2);
+
+        // Both runs and every download show as tasks, and the progress
+        // events of before still go out
+        let history = tasks.history(10);
+        let categories: Vec<TaskCategory> = history.iter().map(|task| task.category).collect();
+        assert_eq!(categories.len(), 7);
+        assert_eq!(categories[0], TaskCategory::Materialization);
+        assert_eq!(categories[6], TaskCategory::Materialization);
+        assert!(categories[1..6]
+            .iter()
+            .all(|c| *c == TaskCategory::Download));
+        assert!(history
+            .iter()
+            .all(|task| task.state == TaskState::Completed));
+        assert_eq!((history[0].completed, history[0].total), (5, Some(5)));
+        let mut legacy = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let OsnovaEvent::AppsMaterializeProgress(p) = event {
+                legacy.push(p);
+            }
+        }
+        assert_eq!(legacy, progress);
+        assert!(tasks.list_active().is_empty());
+
+        // Nothing is left to materialize
+        let outcomes = service
+            .materialize_all(&policy, &NetworkOptions::default(), |_| {})
+            .await?;
+        assert!(outcomes.is_empty());
+
+        Ok(())
+    }
+
+    /// Service installing from `published` manifests, by ID; any other
+    /// manifest URI fails to resolve
+    fn batch_service(temp: &TempDir, published: Vec<ManifestSchema>) -> Result<AppsService> {
+        let cache = CacheManager::new(temp.path().join("cache"), 10 * 1024 * 1024)?;
+        let downloader =
+            ComponentDownloader::new(cache, None).with_http(crate::http::local_fetcher());
+        let published = Arc::new(published);
+        Ok(AppsService::new(temp.path())?
+            .with_downloader(downloader)
+            .with_manifest_fetcher(move |uri| {
+                let published = published.clone();
+                async move {
+                    published
+                        .iter()
+                        .find(|manifest| manifest.id == uri)
+                        .cloned()
+                        .with_context(|| format!("HTTP error 404: {}", uri))
+                }
+            }))
+    }
+
+    /// Manifest of a frontend app published under `id`
+    fn frontend_manifest(dir: &Path, id: &str, schemes: &[&str]) -> Result<ManifestSchema> {
+        let name = id.rsplit('.').next().unwrap_or(id);
+        let frontend = frontend_bundle(dir, name, &[("index.html", "<html></html>")])?;
+        let app = OsnovaApplication::new(id, name, "1.0.0", "icon", name, vec![frontend])?
+            .with_uri_schemes(schemes);
+        Ok(ManifestSchema::from(&app))
+    }
+
+    /// Manifests of apps whose backends are served from `base`
+    fn served_manifests(base: &str, count: usize) -> Result<Vec<ManifestSchema>> {
+        (0..count)
+            .map(|index| {
+                let backend = ComponentRef::new(
+                    format!("{}/batch-{}", base, index),
+                    format!("batch-backend-{}", index),
+                    ComponentKind::Backend,
+                    "1.0.0",
+                )?
+                .with_interpreter("sh");
+                let app_id = format!("com.test.batch{}", index);
+                let app =
+                    OsnovaApplication::new(&app_id, "App", "1.0.0", "icon", "app", vec![backend])?;
+                Ok(ManifestSchema::from(&app))
+            })
+            .collect()
+    }
+
+    fn install_request(manifest_uri: &str) -> InstallRequest {
+        InstallRequest {
+            manifest_uri: manifest_uri.to_string(),
+            pin_version: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_install_many_reports_each_app() -> Result<()> {
+        let temp = TempDir::new()?;
+        let notes = frontend_manifest(temp.path(), "com.test.notes", &["notes"])?;
+        let mail = frontend_manifest(temp.path(), "com.test.mail", &["mailto"])?;
+        let broken = {
+            let frontend = ComponentRef::new(
+                format!("file://{}", temp.path().join("gone.tar.gz").display()),
+                "gone",
+                ComponentKind::Frontend,
+                "1.0.0",
+            )?;
+            let app = OsnovaApplication::new(
+                "com.test.broken",
+                "Broken",
+                "1.0.0",
+                "icon",
+                "app",
+                vec![frontend],
+            )?;
+            ManifestSchema::from(&app)
+        };
+        let service = batch_service(&temp, vec![notes, mail.clone(), broken])?;
+        service.install_manifest(&mail).await?;
+
+        let requests: Vec<InstallRequest> = [
+            "com.test.notes",
+            "com.test.mail",
+            "com.test.broken",
+            "com.test.unpublished",
+            "com.test.notes",
+        ]
+        .into_iter()
+        .map(install_request)
+        .collect();
+
+        let preview = service.preview_install_many(&requests).await?;
+        let statuses: Vec<BatchPreviewStatus> =
+            preview.apps.iter().map(|item| item.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BatchPreviewStatus::Ready,
+                BatchPreviewStatus::AlreadyInstalled,
+                BatchPreviewStatus::Ready,
+                BatchPreviewStatus::Invalid,
+                BatchPreviewStatus::AlreadyInstalled,
+            ]
+        );
+        // Only apps to install count: not mail's scheme
+        assert_eq!(preview.permissions, vec!["uriScheme:notes".to_string()]);
+        assert!(preview.apps[0].download_bytes > 0);
+        assert_eq!(
+            preview.download_bytes,
+            preview.apps[0].download_bytes + preview.apps[2].download_bytes
+        );
+        assert!(service
+            .sql_storage
+            .get_application("com.test.notes")?
+            .is_none());
+
+        let mut progress = Vec::new();
+        let report = service
+            .install_many(
+                &requests,
+                &BatchOptions::default(),
+                &NetworkOptions::default(),
+                |p| progress.push(p.clone()),
+            )
+            .await?;
+        assert_eq!(report.preview, preview);
+        assert!(matches!(
+            report.results[0].outcome,
+            BatchInstallOutcome::Installed { .. }
+        ));
+        assert_eq!(
+            report.results[1].outcome,
+            BatchInstallOutcome::SkippedAlreadyInstalled
+        );
+        assert!(matches!(
+            &report.results[2].outcome,
+            BatchInstallOutcome::Failed { reason } if reason.contains("gone")
+        ));
+        assert!(matches!(
+            &report.results[3].outcome,
+            BatchInstallOutcome::Failed { reason } if reason.contains("404")
+        ));
+        assert_eq!(
+            report.results[4].outcome,
+            BatchInstallOutcome::SkippedAlreadyInstalled
+        );
+        assert_eq!((report.installed(), report.failed()), (1, 2));
+        assert!(service
+            .sql_storage
+            .get_application("com.test.notes")?
+            .is_some());
+        assert!(service
+            .sql_storage
+            .get_application("com.test.broken")?
+            .is_none());
+
+        // One event per request, counting up to the whole batch
+        assert_eq!(
+            progress
+                .iter()
+                .map(|p| (p.completed, p.total))
+                .collect::<Vec<_>>(),
+            (1..=5).map(|completed| (completed, 5)).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preview_flags_apps_requiring_newer_osnova() -> Result<()> {
+        let temp = TempDir::new()?;
+        let mut gated = frontend_manifest(temp.path(), "com.test.gated", &[])?;
+        gated.min_core_version = Some("999.0.0".to_string());
+        let mut worker = frontend_manifest(temp.path(), "com.test.worker", &[])?;
+        worker.components[0].kind = "worker".to_string();
+        let mut annotated = frontend_manifest(temp.path(), "com.test.annotated", &[])?;
+        annotated.extra.insert(
+            "expressions".to_string(),
+            serde_json::json!({"engine": "cel"}),
+        );
+        let service = batch_service(&temp, vec![gated, worker, annotated.clone()])?;
+
+        let requests: Vec<InstallRequest> =
+            ["com.test.gated", "com.test.worker", "com.test.annotated"]
+                .into_iter()
+                .map(install_request)
+                .collect();
+        let preview = service.preview_install_many(&requests).await?;
+        let statuses: Vec<BatchPreviewStatus> =
+            preview.apps.iter().map(|item| item.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BatchPreviewStatus::RequiresNewerOsnova,
+                BatchPreviewStatus::RequiresNewerOsnova,
+                BatchPreviewStatus::Ready,
+            ]
+        );
+        assert_eq!(
+            preview.apps[0].required_core_version.as_deref(),
+            Some("999.0.0")
+        );
+        assert_eq!(preview.apps[1].required_core_version, None);
+        assert!(preview.apps[1]
+            .reason
+            .as_deref()
+            .is_some_and(|reason| reason.contains("component kind 'worker'")));
+        assert_eq!(preview.apps[2].warnings.len(), 1);
+
+        let report = service
+            .install_many(
+                &requests,
+                &BatchOptions::default(),
+                &NetworkOptions::default(),
+                |_| {},
+            )
+            .await?;
+        assert!(matches!(
+            &report.results[0].outcome,
+            BatchInstallOutcome::Failed { reason } if reason.contains("Osnova 999.0.0 or later")
+        ));
+        assert_eq!(report.installed(), 1);
+
+        // The stored manifest keeps the fields this version ignores
+        assert_eq!(
+            service
+                .sql_storage
+                .get_manifest_snapshot("com.test.annotated")?,
+            Some(annotated)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_device_policy_blocks_install_and_launch() -> Result<()> {
+        use crate::models::identity::RootIdentity;
+        use crate::policies::{
+            admin_signing_key, PolicyDocument, PolicyRule, PolicyRules, PolicyStore,
+        };
+
+        let temp = TempDir::new()?;
+        let notes = frontend_manifest(temp.path(), "com.test.notes", &[])?;
+        let casino = frontend_manifest(temp.path(), "com.test.casino", &[])?;
+        let policies = Arc::new(PolicyStore::new(
+            temp.path(),
+            "user",
+            &RootIdentity::generate()?,
+        )?);
+        let admin = admin_signing_key(&RootIdentity::generate()?)?;
+        let rules = PolicyRules {
+            deny_apps: vec!["com.test.casino".to_string()],
+            ..PolicyRules::default()
+        };
+        policies.apply(&PolicyDocument::new("family", 1, 100, rules).sign(&admin)?)?;
+        let service =
+            batch_service(&temp, vec![notes, casino.clone()])?.with_policies(policies.clone());
+
+        let requests: Vec<InstallRequest> = ["com.test.notes", "com.test.casino"]
+            .into_iter()
+            .map(install_request)
+            .collect();
+        let preview = service.preview_install_many(&requests).await?;
+        assert_eq!(preview.apps[0].status, BatchPreviewStatus::Ready);
+        assert_eq!(preview.apps[1].status, BatchPreviewStatus::BlockedByPolicy);
+        assert!(preview.apps[1]
+            .reason
+            .as_deref()
+            .is_some_and(|reason| reason.contains("denyApps")));
+
+        let report = service
+            .install_many(
+                &requests,
+                &BatchOptions::default(),
+                &NetworkOptions::default(),
+                |_| {},
+            )
+            .await?;
+        assert_eq!(report.installed(), 1);
+        let blocked = service.install_manifest(&casino).await.unwrap_err();
+        assert!(matches!(
+            blocked.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::PolicyBlocked {
+                rule: PolicyRule::DenyApps,
+                ..
+            })
+        ));
+
+        // Launch rules apply to apps installed before the policy changed
+        let rules = PolicyRules {
+            allow_apps: vec!["com.test.other".to_string()],
+            ..PolicyRules::default()
+        };
+        policies.apply(&PolicyDocument::new("family", 2, 200, rules).sign(&admin)?)?;
+        let blocked = service.launch("com.test.notes").unwrap_err();
+        assert!(matches!(
+            blocked.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::PolicyBlocked {
+                rule: PolicyRule::AllowList,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_many_bounded_concurrency() -> Result<()> {
+        let temp = TempDir::new()?;
+        let counter = Arc::new(Mutex::new((0usize, 0usize, 0usize)));
+        let base = spawn_counting_server(counter.clone()).await;
+        let published = served_manifests(&base, 5)?;
+        let requests: Vec<InstallRequest> = published
+            .iter()
+            .map(|manifest| install_request(&manifest.id))
+            .collect();
+        let service = batch_service(&temp, published)?;
+
+        let batch = BatchOptions { concurrency: 2 };
+        let report = service
+            .install_many(&requests, &batch, &NetworkOptions::default(), |_| {})
+            .await?;
+        assert_eq!(report.installed(), 5);
+        let (_, max_in_flight, requests) = *counter.lock().unwrap();
+        assert_eq!(requests, 5);
+        assert_eq!(max_in_flight, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_many_cancel_finishes_in_flight() -> Result<()> {
+        let temp = TempDir::new()?;
+        let counter = Arc::new(Mutex::new((0usize, 0usize, 0usize)));
+        let base = spawn_counting_server(counter.clone()).await;
+        let published = served_manifests(&base, 5)?;
+        let requests: Vec<InstallRequest> = published
+            .iter()
+            .map(|manifest| install_request(&manifest.id))
+            .collect();
+        let service = batch_service(&temp, published)?;
+
+        // Cancel as soon as the first install finishes
+        let cancellation = CancellationToken::new();
+        let options = NetworkOptions::default().with_cancellation(cancellation.clone());
+        let batch = BatchOptions { concurrency: 2 };
+        let report = service
+            .install_many(&requests, &batch, &options, |_| cancellation.cancel())
+            .await?;
+
+        // The install under way finished; none started after
+        assert_eq!(report.installed(), 2);
+        let cancelled = report
+            .results
+            .iter()
+            .filter(|result| result.outcome == BatchInstallOutcome::Cancelled)
+            .count();
+        assert_eq!(cancelled, 3);
+        assert_eq!(counter.lock().unwrap().2, 2);
+        for result in &report.results {
+            let installed = service
+                .sql_storage
+                .get_application(&result.manifest_uri)?
+                .is_some();
+            assert_eq!(
+                installed,
+                matches!(result.outcome, BatchInstallOutcome::Installed { .. })
+            );
+        }
+        assert!(service.sql_storage.list_install_journal()?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_installs_recorded_app_list() -> Result<()> {
+        let published_dir = TempDir::new()?;
+        let published = vec![
+            frontend_manifest(published_dir.path(), "com.test.notes", &[])?,
+            frontend_manifest(published_dir.path(), "com.test.mail", &[])?,
+        ];
+
+        // The old device records its apps, one of them pinned
+        let old_temp = TempDir::new()?;
+        let old = batch_service(&old_temp, published.clone())?;
+        for manifest in &published {
+            old.install_manifest(manifest).await?;
+        }
+        old.pin_version("com.test.mail", "1.0.0")?;
+        let recorded = serde_json::to_string(&old.install_requests()?)?;
+
+        // The restored device installs the same apps
+        let new_temp = TempDir::new()?;
+        let restored = batch_service(&new_temp, published)?;
+        let requests: Vec<InstallRequest> = serde_json::from_str(&recorded)?;
+        let report = restored
+            .install_many(
+                &requests,
+                &BatchOptions::default(),
+                &NetworkOptions::default(),
+                |_| {},
+            )
+            .await?;
+        assert_eq!(report.installed(), 2);
+        assert_eq!(
+            restored.pinned_version("com.test.mail")?,
+            Some("1.0.0".to_string())
+        );
+        assert_eq!(restored.pinned_version("com.test.notes")?, None);
+
+        // A pin the publisher moved past is reported, not silently dropped
+        let third_temp = TempDir::new()?;
+        let third = batch_service(
+            &third_temp,
+            vec![frontend_manifest(
+                published_dir.path(),
+                "com.test.notes",
+                &[],
+            )?],
+        )?;
+        let mut pinned = install_request("com.test.notes");
+        pinned.pin_version = Some("0.9.0".to_string());
+        let report = third
+            .install_many(
+                &[pinned],
+                &BatchOptions::default(),
+                &NetworkOptions::default(),
+                |_| {},
+            )
+            .await?;
+        assert!(matches!(
+            &report.results[0].outcome,
+            BatchInstallOutcome::Failed { reason } if reason.contains("0.9.0")
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_requires_downloader() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        assert!(service.verify("com.test.app").await.is_err());
+
+        Ok(())
+    }
+
+    /// Install an app whose backend is the shared component `shared_id`
+    fn install_shared_app(
+        service: &AppsService,
+        app_id: &str,
+        shared_id: &str,
+        artifact: &Path,
+    ) -> Result<ComponentRef> {
+        let hash = crate::components::integrity::content_hash(&std::fs::read(artifact)?);
+        let component = ComponentRef::new(
+            format!("file://{}", artifact.display()),
+            format!("{} sync", app_id),
+            ComponentKind::Backend,
+            "1.0.0",
+        )?
+        .with_hash(hash)
+        .with_shared_id(shared_id);
+
+        let app = OsnovaApplication::new(
+            app_id,
+            app_id,
+            "1.0.0",
+            "https://icon.url",
+            "Shared component test app",
+            vec![component.clone()],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent(app_id, true, vec![])?;
+
+        Ok(component)
+    }
+
+    /// Service whose backends are stub `sleep` processes
+    fn create_service_with_processes(
+        temp: &TempDir,
+    ) -> Result<(AppsService, Arc<ProcessService>, PathBuf)> {
+        let processes = Arc::new(ProcessService::new(temp.path())?);
+        let service = create_service_with_downloader(temp)?
+            .with_processes(processes.clone())
+            .with_backend_command(|_| {
+                let mut command = Command::new("sleep");
+                command.arg("30");
+                command
+            });
+
+        let artifact = temp.path().join("sync-agent");
+        std::fs::write(&artifact, binary::host_stub(b"sync agent stub"))?;
+
+        Ok((service, processes, artifact))
+    }
+
+    #[test]
+    fn test_shared_backend_runs_once_for_two_apps() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, artifact) = create_service_with_processes(&temp)?;
+        install_shared_app(&service, "com.test.a", "org.test.sync", &artifact)?;
+        install_shared_app(&service, "com.test.b", "org.test.sync", &artifact)?;
+
+        service.launch("com.test.a")?;
+        service.launch("com.test.b")?;
+        service.launch("com.test.a")?;
+
+        let running = processes.list()?;
+        assert_eq!(running.len(), 1);
+        assert_eq!(
+            running[0].owner(),
+            ComponentOwner::Shared("org.test.sync".to_string())
+        );
+
+        let status = service.shared_components()?;
+        assert_eq!(status.len(), 1);
+        assert_eq!(
+            status[0].key,
+            SharedComponentKey::new("org.test.sync", "1.0.0")
+        );
+        assert_eq!(status[0].pid, Some(running[0].pid()));
+        assert_eq!(status[0].installed_by, vec!["com.test.a", "com.test.b"]);
+        assert_eq!(status[0].active_apps, vec!["com.test.a", "com.test.b"]);
+
+        processes.stop_all()?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crash_reports_and_symbolication() -> Result<()> {
+        let temp = TempDir::new()?;
+        let processes = Arc::new(ProcessService::new(temp.path())?);
+        let service = create_service_with_downloader(&temp)?
+            .with_processes(processes.clone())
+            .with_backend_command(|_| {
+                // Stub that prints a glibc-style backtrace and segfaults
+                let mut command = Command::new("sh");
+                command.args([
+                    "-c",
+                    "echo 'fatal: bad pointer' >&2; \
+                     echo './crash-stub(+0x1148) [0x55d0c0a01148]' >&2; kill -SEGV $$",
+                ]);
+                command
+            });
+
+        // The backend binary ships Breakpad symbols next to it
+        let artifact = temp.path().join("crash-stub");
+        std::fs::write(&artifact, binary::host_stub(b"crash stub"))?;
+        let symbols_path = crate::components::symbols::symbol_path(&artifact);
+        std::fs::write(&symbols_path, include_str!("../components/crash_stub.sym"))?;
+        let component = ComponentRef::new(
+            format!("file://{}", artifact.display()),
+            "crash-report-stub",
+            ComponentKind::Backend,
+            "1.0.0",
+        )?;
+        let app = OsnovaApplication::new(
+            "com.test.crashy",
+            "Crashy",
+            "1.0.0",
+            "https://icon.url",
+            "Crash report test app",
+            vec![component.clone()],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent("com.test.crashy", true, vec![])?;
+
+        service.launch("com.test.crashy")?;
+        let mut crashed = Vec::new();
+        for _ in 0..500 {
+            crashed = processes.check_processes()?;
+            if !crashed.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(crashed.len(), 1);
+
+        let reports = service.crash_reports("com.test.crashy")?;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].id, crashed[0].report_id);
+        assert_eq!(reports[0].component_id.as_deref(), Some(component.id()));
+        assert_eq!(reports[0].signal, Some(libc::SIGSEGV));
+        assert_eq!(reports[0].stderr_tail[0], "fatal: bad pointer");
+        assert_eq!(service.recent_crash_reports(5)?, reports);
+
+        let symbolicated = service.symbolicate(&reports[0].id).await?;
+        assert!(symbolicated.symbolicated);
+        let symbol = symbolicated.frames[0].symbol.clone().unwrap();
+        assert_eq!(
+            symbol.function.as_deref(),
+            Some("crash_stub::worker::explode")
+        );
+        assert_eq!(symbol.line, Some(18));
+
+        // Without symbols the frames come back unresolved, and say why
+        std::fs::remove_file(&symbols_path)?;
+        let bare = service.symbolicate(&reports[0].id).await?;
+        assert!(!bare.symbolicated);
+        assert_eq!(
+            bare.unavailable_reason.as_deref(),
+            Some("The component ships no symbols")
+        );
+        assert_eq!(bare.frames[0].frame.address, 0x1148);
+        assert_eq!(bare.frames[0].symbol, None);
+
+        assert!(service.symbolicate("missing").await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_backend_stops_with_last_app() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, artifact) = create_service_with_processes(&temp)?;
+        install_shared_app(&service, "com.test.a", "org.test.sync", &artifact)?;
+        install_shared_app(&service, "com.test.b", "org.test.sync", &artifact)?;
+
+        service.launch("com.test.a")?;
+        service.launch("com.test.b")?;
+        let pid = processes.list()?[0].pid();
+
+        service.stop("com.test.a")?;
+        assert_eq!(processes.list()?.len(), 1);
+        assert!(crate::platform::process::is_running(pid));
+        assert_eq!(
+            service.shared_components()?[0].active_apps,
+            vec!["com.test.b"]
+        );
+
+        service.stop("com.test.b")?;
+        assert!(processes.list()?.is_empty());
+        assert!(!crate::platform::process::is_running(pid));
+        assert_eq!(service.shared_components()?[0].pid, None);
+
+        // Launching again starts a fresh instance
+        service.launch("com.test.b")?;
+        assert_eq!(processes.list()?.len(), 1);
+
+        processes.stop_all()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_last_app_removes_shared_artifact() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, artifact) = create_service_with_processes(&temp)?;
+        let component =
+            install_shared_app(&service, "com.test.a", "org.test.sync-cleanup", &artifact)?;
+        install_shared_app(&service, "com.test.b", "org.test.sync-cleanup", &artifact)?;
+
+        let schema = ComponentSchema::from(&component);
+        let downloader = service.downloader()?;
+        downloader.download(&schema).await?;
+        service.launch("com.test.a")?;
+        service.launch("com.test.b")?;
+
+        // The other app still references and runs the component
+        service.uninstall("com.test.a", Some(0))?;
+        assert!(service.remove_unused_shared_components().await?.is_empty());
+        assert!(downloader.quick_check(&schema).await);
+        assert_eq!(processes.list()?.len(), 1);
+        assert_eq!(
+            service.shared_components()?[0].installed_by,
+            vec!["com.test.b"]
+        );
+
+        // Uninstalling the last user stops the process and frees the artifact
+        service.uninstall("com.test.b", Some(0))?;
+        assert!(processes.list()?.is_empty());
+        assert_eq!(
+            service.remove_unused_shared_components().await?,
+            vec![SharedComponentKey::new("org.test.sync-cleanup", "1.0.0")]
+        );
+        assert!(!downloader.quick_check(&schema).await);
+        assert!(!ComponentDownloader::prepared_path(&schema).exists());
+        assert!(service.shared_components()?.is_empty());
+
+        Ok(())
+    }
+
+    fn scheme_app(id: &str, name: &str, schemes: &[&str]) -> Result<OsnovaApplication> {
+        Ok(
+            OsnovaApplication::new(id, name, "1.0.0", "https://icon.url", name, vec![])?
+                .with_uri_schemes(schemes),
+        )
+    }
+
+    #[test]
+    fn test_install_registers_uri_schemes() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service.with_events(events);
 
-        let apps = service.list()?;
-        assert_eq!(apps.len(), 0);
+        service.install_application(&scheme_app("com.test.mail", "Mail", &["MailTo"])?)?;
+
+        assert_eq!(
+            receiver.try_recv()?,
+            AppEvent::AppInstalled {
+                app_id: "com.test.mail".to_string(),
+                generation: 1,
+            }
+        );
+        assert_eq!(
+            service.uri_handlers()?,
+            vec![UriSchemeHandlers {
+                scheme: "mailto".to_string(),
+                app_id: "com.test.mail".to_string(),
+                candidates: vec!["com.test.mail".to_string()],
+                overridden: false,
+            }]
+        );
 
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_refresh_metadata_from_published_manifest() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service.with_events(events);
+
+        let path = temp.path().join("manifest.json");
+        let app_id = format!("file://{}", path.display());
+        let installed =
+            OsnovaApplication::new(&app_id, "Notes", "1.0.0", "icon-v1", "Notes", vec![])?;
+        service.install_application(&installed)?;
+        receiver.try_recv()?;
+
+        let published = serde_json::json!({
+            "id": app_id,
+            "name": "Notes",
+            "version": "1.0.0",
+            "iconUri": "icon-v2",
+            "description": "Notes",
+            "components": []
+        });
+        std::fs::write(&path, published.to_string())?;
+
+        let refresh = service.refresh_metadata(&app_id).await?;
+        assert_eq!(refresh.changed, vec![MetadataField::IconUri]);
+        assert_eq!(
+            service
+                .sql_storage
+                .get_application(&app_id)?
+                .unwrap()
+                .icon_uri(),
+            "icon-v2"
+        );
+        assert!(matches!(
+            receiver.try_recv()?,
+            AppEvent::AppMetadataChanged { .. }
+        ));
+
+        // Nothing left to change
+        assert!(service.refresh_metadata(&app_id).await?.changed.is_empty());
+        Ok(())
+    }
+
     #[test]
-    fn test_list_apps() -> Result<()> {
+    fn test_install_rejects_reserved_uri_scheme() -> Result<()> {
         let (service, _temp) = create_test_service()?;
 
-        // Add some test apps
-        let app1 = OsnovaApplication::new(
-            "com.test.app1",
-            "Test App 1",
+        let app = scheme_app("com.test.browser", "Browser", &["https"])?;
+        let err = service.install_application(&app).unwrap_err();
+        assert!(err.to_string().contains("reserved"));
+        assert!(service.list()?.is_empty());
+        assert!(service.uri_handlers()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_enforces_signature_policy() -> Result<()> {
+        use crate::manifest::add_signature;
+        use crate::models::signature::SignaturePolicy;
+        use base64::Engine;
+        use ed25519_dalek::SigningKey;
+
+        let (service, _temp) = create_test_service()?;
+        let keys: Vec<_> = (1..=3)
+            .map(|seed| SigningKey::from_bytes(&[seed; 32]))
+            .collect();
+        let policy = SignaturePolicy {
+            required: 2,
+            allowed_signers: keys
+                .iter()
+                .map(|key| {
+                    base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes())
+                })
+                .collect(),
+        };
+        let app = scheme_app("com.test.wallet", "Wallet", &[])?.with_signature_policy(policy);
+
+        let once = add_signature(&ManifestSchema::from(&app), &keys[0], "developer")?;
+        let err = service
+            .install_application(&OsnovaApplication::try_from(&once)?)
+            .unwrap_err();
+        assert!(err.to_string().contains("1 of the 2"));
+        assert!(service.list()?.is_empty());
+
+        let twice = add_signature(&once, &keys[2], "release-manager")?;
+        service.install_application(&OsnovaApplication::try_from(&twice)?)?;
+
+        // Reinstalling without the policy is still held to the installed one
+        assert!(service
+            .install_application(&scheme_app("com.test.wallet", "Wallet", &[])?)
+            .is_err());
+
+        // Strict mode refuses manifests without a policy
+        service.config.set_signature_policy_required(true)?;
+        let unsigned = scheme_app("com.test.notes", "Notes", &[])?;
+        assert!(service.install_application(&unsigned).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uri_handler_conflict_and_override() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.install_application(&scheme_app("com.test.mail", "Mail", &["mailto"])?)?;
+        service.install_application(&scheme_app("com.test.other", "Other", &["mailto"])?)?;
+
+        // First installed wins
+        let handler = &service.uri_handlers()?[0];
+        assert_eq!(handler.app_id, "com.test.mail");
+        assert_eq!(handler.candidates, vec!["com.test.mail", "com.test.other"]);
+
+        // The user can pick another claimant, but not an unrelated app
+        assert!(service
+            .set_uri_handler("mailto", Some("com.test.none"))
+            .is_err());
+        service.set_uri_handler("mailto", Some("com.test.other"))?;
+        let handler = &service.uri_handlers()?[0];
+        assert_eq!(handler.app_id, "com.test.other");
+        assert!(handler.overridden);
+
+        service.set_uri_handler("mailto", None)?;
+        assert_eq!(service.uri_handlers()?[0].app_id, "com.test.mail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_uri_routes_to_handler() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.install_application(&scheme_app("com.test.mail", "Mail", &["mailto"])?)?;
+        service.install_application(&scheme_app("com.test.wallet", "Wallet", &["ethereum"])?)?;
+        service.record_consent("com.test.wallet", true, vec![])?;
+
+        let uri = "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359?value=2.014e18";
+        let handler = service.open_uri(uri)?;
+        assert_eq!(handler.app_id, "com.test.wallet");
+        assert_eq!(handler.app_name, "Wallet");
+        assert_eq!(handler.initial_route, uri);
+
+        assert_eq!(
+            service
+                .resolve_uri_handler("MAILTO:someone@example.com")?
+                .map(|h| h.app_id),
+            Some("com.test.mail".to_string())
+        );
+        assert!(service.resolve_uri_handler("tel:+15555550100")?.is_none());
+        assert!(service.resolve_uri_handler("no scheme")?.is_none());
+        assert!(service.open_uri("tel:+15555550100").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall_removes_uri_handlers() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.install_application(&scheme_app("com.test.mail", "Mail", &["mailto"])?)?;
+        service.install_application(&scheme_app("com.test.other", "Other", &["mailto"])?)?;
+
+        // The next claimant takes over
+        service.uninstall("com.test.mail", Some(7))?;
+        let handlers = service.uri_handlers()?;
+        assert_eq!(handlers[0].app_id, "com.test.other");
+        assert_eq!(handlers[0].candidates, vec!["com.test.other"]);
+
+        // Restoring re-registers behind the current handler
+        service.restore("com.test.mail")?;
+        assert_eq!(
+            service.uri_handlers()?[0].candidates,
+            vec!["com.test.other", "com.test.mail"]
+        );
+
+        service.uninstall("com.test.mail", Some(0))?;
+        service.uninstall("com.test.other", Some(0))?;
+        assert!(service.uri_handlers()?.is_empty());
+        assert!(service.resolve_uri_handler("mailto:a@b.c")?.is_none());
+
+        Ok(())
+    }
+
+    /// Install an app with one backend of its own
+    fn install_backend_app(service: &AppsService, app_id: &str) -> Result<ComponentRef> {
+        let component =
+            ComponentRef::new("ant://backend", "Backend", ComponentKind::Backend, "1.0.0")?;
+        let app = OsnovaApplication::new(
+            app_id,
+            app_id,
             "1.0.0",
-            "https://icon1.url",
-            "Test app 1",
-            vec![],
+            "https://icon.url",
+            "Handshake test app",
+            vec![component.clone()],
         )?;
-        let app2 = OsnovaApplication::new(
-            "com.test.app2",
-            "Test App 2",
-            "2.0.0",
-            "https://icon2.url",
-            "Test app 2",
-            vec![],
+        service.sql_storage.upsert_application(&app)?;
+        service.record_consent(app_id, true, vec![])?;
+
+        Ok(component)
+    }
+
+    /// Service whose backends are a stub reporting `version` over the core
+    /// RPC socket, or never reporting without one
+    async fn create_handshake_service(
+        temp: &TempDir,
+        version: Option<&'static str>,
+        timeout: Duration,
+    ) -> Result<(AppsService, Arc<ProcessService>, EventBus)> {
+        let events = EventBus::new();
+        let listener = crate::rpc::server::bind_local().await?;
+        let handshake = Arc::new(
+            LaunchHandshake::new()
+                .with_events(events.clone())
+                .with_timeout(timeout)
+                .with_rpc_address(listener.local_addr()?),
+        );
+        let ready = handshake.clone();
+        let server = RpcServer::new(crate::rpc::core_registry())
+            .with_handler(COMPONENT_READY_METHOD, move |params| {
+                ready.handle_ready(params)
+            })?;
+        tokio::spawn(Arc::new(server).serve(listener));
+
+        let processes = Arc::new(ProcessService::new(temp.path())?);
+        let service = AppsService::new(temp.path())?
+            .with_processes(processes.clone())
+            .with_handshake(handshake)
+            .with_backend_command(move |_| {
+                let script = match version {
+                    Some(version) => format!(
+                        r#"printf '{{"jsonrpc":"2.0","id":1,"method":"component.ready","params":{{"componentId":"%s","version":"{}","capabilities":["sync"]}}}}\n' "$OSNOVA_COMPONENT_ID" > "/dev/tcp/${{OSNOVA_RPC_ADDR%:*}}/${{OSNOVA_RPC_ADDR##*:}}"; sleep 30"#,
+                        version
+                    ),
+                    None => "sleep 30".to_string(),
+                };
+                let mut command = Command::new("bash");
+                command.arg("-c").arg(script);
+                command
+            });
+
+        Ok((service, processes, events))
+    }
+
+    #[tokio::test]
+    async fn test_launch_waits_for_backend_ready() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, events) =
+            create_handshake_service(&temp, Some("1.0.0"), Duration::from_secs(10)).await?;
+        let mut events = events.subscribe();
+        install_backend_app(&service, "com.test.app")?;
+
+        let descriptor = service.launch_and_wait("com.test.app").await?;
+        assert!(descriptor.is_ready());
+        let backend = &descriptor.backends[0];
+        assert_eq!(backend.component_id, "ant://backend");
+        assert_eq!(backend.capabilities, vec!["sync"]);
+        assert_eq!(
+            processes.list()?[0].socket_path(),
+            backend.socket_path.as_deref()
+        );
+
+        let mut states = Vec::new();
+        while let Ok(AppEvent::BackendReadinessChanged { app_id, state, .. }) = events.try_recv() {
+            assert_eq!(app_id, "com.test.app");
+            states.push(state);
+        }
+        assert_eq!(
+            states,
+            vec![ReadinessState::Starting, ReadinessState::Ready]
+        );
+
+        // Launching the running app again neither respawns nor resets it
+        service.launch("com.test.app")?;
+        assert_eq!(processes.list()?.len(), 1);
+        assert!(service
+            .launch_descriptor("com.test.app")
+            .unwrap()
+            .is_ready());
+
+        service.stop("com.test.app")?;
+        assert!(service.launch_descriptor("com.test.app").is_none());
+        Ok(())
+    }
+
+    /// Service whose backends are a stub recording their environment in
+    /// `env` and never reading their secrets
+    #[cfg(unix)]
+    fn create_secret_service(temp: &TempDir, timeout: Duration) -> Result<(AppsService, PathBuf)> {
+        let env = temp.path().join("env");
+        let script = format!("env > {}; sleep 30", env.display());
+        let service = AppsService::new(temp.path())?
+            .with_user("user")
+            .with_identity(&RootIdentity::generate()?)?
+            .with_processes(Arc::new(ProcessService::new(temp.path())?))
+            .with_secret_timeout(timeout)
+            .with_backend_command(move |_| {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(&script);
+                command
+            });
+
+        install_backend_app(&service, "com.test.app")?;
+        let app = installed_app(&service.sql_storage, "com.test.app")?.with_settings_schema(
+            serde_json::from_value(serde_json::json!({"sections": [{
+                "id": "account", "label": "Account", "fields": [
+                    {"key": "apiToken", "label": "API token", "type": "string", "secret": true}
+                ]
+            }]}))?,
+        );
+        service.sql_storage.upsert_application(&app)?;
+        service.config.set_app_secret(
+            "com.test.app",
+            "user",
+            "apiToken",
+            serde_json::json!("t0ken"),
         )?;
+        Ok((service, env))
+    }
 
-        service.sql_storage.upsert_application(&app1)?;
-        service.sql_storage.upsert_application(&app2)?;
+    /// The secrets socket the stub backend was handed
+    #[cfg(unix)]
+    fn secrets_socket(env: &Path) -> Result<PathBuf> {
+        for _ in 0..500 {
+            if let Ok(env) = std::fs::read_to_string(env) {
+                if let Some(line) = env
+                    .lines()
+                    .find_map(|line| line.strip_prefix("OSNOVA_SECRETS_SOCKET="))
+                {
+                    assert!(!env.contains("t0ken"));
+                    return Ok(PathBuf::from(line));
+                }
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        anyhow::bail!("backend was not handed a secrets socket")
+    }
 
-        let apps = service.list()?;
-        assert_eq!(apps.len(), 2);
-        assert!(apps.iter().any(|a| a.id == "com.test.app1"));
-        assert!(apps.iter().any(|a| a.id == "com.test.app2"));
+    #[cfg(unix)]
+    #[test]
+    fn test_backend_collects_secrets_once_at_launch() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, env) = create_secret_service(&temp, Duration::from_secs(10))?;
+
+        service.launch("com.test.app")?;
+        let socket = secrets_socket(&env)?;
+        assert_eq!(
+            crate::services::secret_channel::receive(&socket)?,
+            BTreeMap::from([("apiToken".to_string(), serde_json::json!("t0ken"))])
+        );
+        assert!(crate::services::secret_channel::receive(&socket).is_err());
 
+        service.stop("com.test.app")?;
         Ok(())
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_launch_existing_app() -> Result<()> {
-        let (service, _temp) = create_test_service()?;
+    fn test_uncollected_secrets_are_withdrawn_after_launch() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, env) = create_secret_service(&temp, Duration::from_millis(100))?;
+
+        service.launch("com.test.app")?;
+        let socket = secrets_socket(&env)?;
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(!socket.exists());
+        assert!(crate::services::secret_channel::receive(&socket).is_err());
+
+        service.stop("com.test.app")?;
+        Ok(())
+    }
 
+    /// Launch an app whose backend declares a socket health check,
+    /// answered by a stub prober that passes while `healthy` is set
+    async fn launch_health_checked_app(
+        temp: &TempDir,
+        policy: HealthPolicy,
+    ) -> Result<(AppsService, Arc<AtomicBool>, Arc<MockClock>, EventBus)> {
+        let (service, _processes, events) =
+            create_handshake_service(temp, Some("1.0.0"), Duration::from_secs(10)).await?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let healthy = Arc::new(AtomicBool::new(true));
+        let answers = healthy.clone();
+        let service = service
+            .with_events(events.clone())
+            .with_health(Arc::new(HealthMonitor::new().with_clock(clock.clone())))
+            .with_health_prober(move |_, _| {
+                let healthy = answers.load(Ordering::SeqCst);
+                async move {
+                    healthy
+                        .then_some(())
+                        .ok_or_else(|| "event loop stalled".to_string())
+                }
+            });
+        service.config.set_runtime_settings(RuntimeSettings {
+            health_policy: policy,
+            ..RuntimeSettings::default()
+        })?;
+
+        let component =
+            ComponentRef::new("ant://backend", "Backend", ComponentKind::Backend, "1.0.0")?
+                .with_config(HashMap::from([(
+                    "health".to_string(),
+                    serde_json::json!({
+                        "probe": "socket", "intervalSecs": 10, "timeoutSecs": 1,
+                        "failureThreshold": 2
+                    }),
+                )]));
         let app = OsnovaApplication::new(
             "com.test.app",
             "Test App",
             "1.0.0",
             "https://icon.url",
-            "Test app",
-            vec![],
+            "Health checked app",
+            vec![component],
         )?;
         service.sql_storage.upsert_application(&app)?;
+        service.record_consent("com.test.app", true, vec![])?;
+        service.launch_and_wait("com.test.app").await?;
 
-        // Should not error
+        Ok((service, healthy, clock, events))
+    }
+
+    /// Run health checks until the backend is unhealthy
+    async fn fail_health_checks(service: &AppsService, clock: &MockClock) -> Result<()> {
+        let first = service.check_backend_health().await?;
+        assert!(first.is_empty(), "{:?}", first);
+        clock.advance(Duration::from_secs(10));
+        let second = service.check_backend_health().await?;
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].to, HealthStatus::Unhealthy);
+        Ok(())
+    }
+
+    fn backend_pid(service: &AppsService) -> Option<u32> {
+        let processes = service.processes.as_ref().unwrap();
+        processes.list().unwrap().first().map(BackendProcess::pid)
+    }
+
+    #[tokio::test]
+    async fn test_backend_health_restart_then_block() -> Result<()> {
+        let temp = TempDir::new()?;
+        let policy = HealthPolicy {
+            action: HealthAction::RestartThenBlock,
+            restart_backoff_secs: 5,
+            max_restarts: 1,
+            ..HealthPolicy::default()
+        };
+        let (service, healthy, clock, events) = launch_health_checked_app(&temp, policy).await?;
+        let mut events = events.subscribe();
+
+        assert!(service.check_backend_health().await?.is_empty());
+        let health = |service: &AppsService| {
+            service.launch_descriptor("com.test.app").unwrap().backends[0]
+                .health
+                .clone()
+                .unwrap()
+        };
+        assert_eq!(health(&service).status, HealthStatus::Healthy);
+        assert_eq!(
+            service.info("com.test.app")?.backend_health,
+            vec![health(&service)]
+        );
+
+        // Detected after the threshold, restarted after the backoff
+        healthy.store(false, Ordering::SeqCst);
+        clock.advance(Duration::from_secs(10));
+        fail_health_checks(&service, &clock).await?;
+        assert_eq!(health(&service).consecutive_failures, 2);
+        let pid = backend_pid(&service);
+        clock.advance(Duration::from_secs(4));
+        assert!(service.check_backend_health().await?.is_empty());
+        clock.advance(Duration::from_secs(1));
+        let restarted = service.check_backend_health().await?;
+        assert_eq!(restarted[0].to, HealthStatus::Unknown);
+        assert_eq!(restarted[0].restarts, 1);
+        assert!(backend_pid(&service).is_some());
+        assert_ne!(backend_pid(&service), pid);
+
+        // Blocked once it was restarted max_restarts times
+        clock.advance(Duration::from_secs(10));
+        fail_health_checks(&service, &clock).await?;
+        let blocked = service.check_backend_health().await?;
+        assert_eq!(blocked[0].to, HealthStatus::Blocked);
+        assert_eq!(backend_pid(&service), None);
+        let backend = &service.launch_descriptor("com.test.app").unwrap().backends[0];
+        assert_eq!(
+            backend.health.as_ref().unwrap().status,
+            HealthStatus::Blocked
+        );
+        assert!(matches!(backend.state, ReadinessState::Failed { .. }));
+
+        let history: Vec<HealthStatus> = service
+            .health_history("com.test.app")?
+            .iter()
+            .map(|transition| transition.to)
+            .collect();
+        assert_eq!(
+            history,
+            [
+                HealthStatus::Blocked,
+                HealthStatus::Unhealthy,
+                HealthStatus::Unknown,
+                HealthStatus::Unhealthy
+            ]
+        );
+        let mut published = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let AppEvent::BackendHealthChanged { transition } = event {
+                published.push(transition.to);
+            }
+        }
+        published.reverse();
+        assert_eq!(published, history);
+
+        // Launching again starts over
         service.launch("com.test.app")?;
+        assert!(service.info("com.test.app")?.backend_health.is_empty());
+        service.stop("com.test.app")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backend_health_notify_only() -> Result<()> {
+        let temp = TempDir::new()?;
+        let policy = HealthPolicy {
+            action: HealthAction::Notify,
+            ..HealthPolicy::default()
+        };
+        let (service, healthy, clock, _events) = launch_health_checked_app(&temp, policy).await?;
+        let pid = backend_pid(&service);
 
+        healthy.store(false, Ordering::SeqCst);
+        fail_health_checks(&service, &clock).await?;
+        clock.advance(Duration::from_secs(3_600));
+        assert!(service.check_backend_health().await?.is_empty());
+        assert_eq!(backend_pid(&service), pid);
+
+        // Recovers without a restart
+        healthy.store(true, Ordering::SeqCst);
+        clock.advance(Duration::from_secs(10));
+        let recovered = service.check_backend_health().await?;
+        assert_eq!(recovered[0].to, HealthStatus::Healthy);
+        assert_eq!(service.info("com.test.app")?.backend_health[0].restarts, 0);
+
+        service.stop("com.test.app")?;
         Ok(())
     }
 
+    /// Service with the warm pool on, whose backends are stub `sleep`
+    /// processes each using `memory` bytes
+    fn create_warm_pool_service(
+        temp: &TempDir,
+        clock: Arc<MockClock>,
+        memory: u64,
+        settings: RuntimeSettings,
+    ) -> Result<(AppsService, Arc<ProcessService>)> {
+        let processes = Arc::new(
+            ProcessService::new(temp.path())?
+                .with_clock(clock)
+                .with_memory_probe(move |_| Some(memory)),
+        );
+        let service = AppsService::new(temp.path())?
+            .with_processes(processes.clone())
+            .with_backend_command(|_| {
+                let mut command = Command::new("sleep");
+                command.arg("30");
+                command
+            });
+        service.config.set_runtime_settings(RuntimeSettings {
+            warm_pool: true,
+            ..settings
+        })?;
+        Ok((service, processes))
+    }
+
     #[test]
-    fn test_launch_nonexistent_app() -> Result<()> {
-        let (service, _temp) = create_test_service()?;
+    fn test_warm_pool_reuses_backend() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let (service, processes) =
+            create_warm_pool_service(&temp, clock, 1024, RuntimeSettings::default())?;
+        install_backend_app(&service, "com.test.app")?;
 
-        let result = service.launch("com.nonexistent.app");
-        assert!(result.is_err());
+        service.launch("com.test.app")?;
+        let pid = processes.list()?[0].pid();
+        assert_eq!(processes.list()?[0].state(), ProcessState::Active);
+
+        assert!(service.close("com.test.app")?);
+        let idle = processes.list()?;
+        assert_eq!(idle[0].pid(), pid);
+        assert_eq!(idle[0].state(), ProcessState::Idle);
+        assert_eq!(idle[0].idle_since(), Some(1_000));
+
+        service.launch("com.test.app")?;
+        let warm = processes.list()?;
+        assert_eq!(warm.len(), 1);
+        assert_eq!(warm[0].pid(), pid);
+        assert_eq!(warm[0].state(), ProcessState::Active);
+        assert_eq!(warm[0].warm_starts(), 1);
 
+        // With the pool off, closing stops the app
+        service
+            .config
+            .set_runtime_settings(RuntimeSettings::default())?;
+        assert!(!service.close("com.test.app")?);
+        assert!(processes.list()?.is_empty());
         Ok(())
     }
 
     #[test]
-    fn test_uninstall() -> Result<()> {
-        let (service, _temp) = create_test_service()?;
+    fn test_warm_pool_keeps_most_launched_apps() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let settings = RuntimeSettings {
+            warm_pool_size: 1,
+            ..RuntimeSettings::default()
+        };
+        let (service, processes) = create_warm_pool_service(&temp, clock, 1024, settings)?;
+        install_backend_app(&service, "com.test.a")?;
+        install_backend_app(&service, "com.test.b")?;
+
+        service.launch("com.test.a")?;
+        service.launch("com.test.a")?;
+        service.launch("com.test.b")?;
+
+        assert!(!service.close("com.test.b")?);
+        assert!(service.close("com.test.a")?);
+        let remaining = processes.list()?;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].app_id(), "com.test.a");
+
+        processes.stop_all()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_warm_pool_reaps_over_budget_and_timeout() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let settings = RuntimeSettings {
+            idle_memory_budget_bytes: 250,
+            idle_timeout_secs: 60,
+            ..RuntimeSettings::default()
+        };
+        let (service, processes) = create_warm_pool_service(&temp, clock.clone(), 100, settings)?;
+        for app_id in ["com.test.a", "com.test.b", "com.test.c"] {
+            install_backend_app(&service, app_id)?;
+            service.launch(app_id)?;
+        }
+
+        // Closed in order b, a, c; the third pushes the pool over budget
+        for app_id in ["com.test.b", "com.test.a", "com.test.c"] {
+            assert!(service.close(app_id)?);
+            clock.advance(Duration::from_secs(10));
+        }
+        let idle_apps = |processes: &ProcessService| -> Result<Vec<String>> {
+            let mut apps: Vec<String> = processes
+                .list()?
+                .iter()
+                .map(|p| p.app_id().to_string())
+                .collect();
+            apps.sort();
+            Ok(apps)
+        };
+        assert_eq!(idle_apps(&processes)?, vec!["com.test.a", "com.test.c"]);
+
+        // Launching takes an app out of the pool; maintenance reaps the
+        // rest once they time out
+        service.launch("com.test.a")?;
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(service.reap_idle_backends()?, vec!["com.test.c"]);
+        assert_eq!(idle_apps(&processes)?, vec!["com.test.a"]);
+
+        processes.stop_all()?;
+        Ok(())
+    }
 
+    #[test]
+    fn test_warm_pool_invalidated_by_update() -> Result<()> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let (service, processes) =
+            create_warm_pool_service(&temp, clock, 1024, RuntimeSettings::default())?;
+        install_backend_app(&service, "com.test.app")?;
+        service.launch("com.test.app")?;
+        let pid = processes.list()?[0].pid();
+        assert!(service.close("com.test.app")?);
+
+        // The app was updated while its backend sat idle
+        let component =
+            ComponentRef::new("ant://backend", "Backend", ComponentKind::Backend, "2.0.0")?;
         let app = OsnovaApplication::new(
             "com.test.app",
-            "Test App",
-            "1.0.0",
+            "com.test.app",
+            "2.0.0",
             "https://icon.url",
-            "Test app",
-            vec![],
+            "Handshake test app",
+            vec![component],
         )?;
         service.sql_storage.upsert_application(&app)?;
+        service.record_consent("com.test.app", true, vec![])?;
 
-        // Verify app exists
-        let apps = service.list()?;
-        assert_eq!(apps.len(), 1);
+        service.launch("com.test.app")?;
+        let running = processes.list()?;
+        assert_eq!(running.len(), 1);
+        assert_ne!(running[0].pid(), pid);
+        assert_eq!(running[0].component_version(), Some("2.0.0"));
+        assert_eq!(running[0].warm_starts(), 0);
+        assert!(!crate::platform::process::is_running(pid));
 
-        // Uninstall
-        service.uninstall("com.test.app")?;
+        processes.stop_all()?;
+        Ok(())
+    }
 
-        // Verify app is gone
-        let apps = service.list()?;
-        assert_eq!(apps.len(), 0);
+    #[tokio::test]
+    async fn test_warm_launch_descriptor() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, _events) =
+            create_handshake_service(&temp, Some("1.0.0"), Duration::from_secs(10)).await?;
+        service.config.set_runtime_settings(RuntimeSettings {
+            warm_pool: true,
+            ..RuntimeSettings::default()
+        })?;
+        install_backend_app(&service, "com.test.app")?;
+
+        let cold = service.launch_and_wait("com.test.app").await?;
+        assert_eq!(cold.start, StartKind::Cold);
+        assert!(service.close("com.test.app")?);
+
+        // The warm backend already reported ready; no new handshake is needed
+        let warm = service.launch_and_wait("com.test.app").await?;
+        assert_eq!(warm.start, StartKind::Warm);
+        assert!(warm.is_ready());
+        assert_eq!(warm.backends, cold.backends);
+
+        processes.stop_all()?;
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn test_launch_fails_when_backend_never_reports() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, _) =
+            create_handshake_service(&temp, None, Duration::from_millis(200)).await?;
+        install_backend_app(&service, "com.test.app")?;
+
+        let err = service.launch_and_wait("com.test.app").await.unwrap_err();
+        assert!(err.to_string().contains("did not report ready"));
+        assert!(matches!(
+            service.launch_descriptor("com.test.app").unwrap().backends[0].state,
+            ReadinessState::Failed { .. }
+        ));
+
+        processes.stop_all()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_launch_detects_backend_version_mismatch() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, processes, _) =
+            create_handshake_service(&temp, Some("2.0.0"), Duration::from_secs(10)).await?;
+        install_backend_app(&service, "com.test.app")?;
+
+        let err = service.launch_and_wait("com.test.app").await.unwrap_err();
+        assert!(err.to_string().contains("requires 1.0.0"));
+        assert_eq!(
+            service.launch_descriptor("com.test.app").unwrap().backends[0].state,
+            ReadinessState::VersionMismatch {
+                expected: "1.0.0".to_string(),
+                actual: "2.0.0".to_string(),
+            }
+        );
+
+        processes.stop_all()?;
         Ok(())
     }
 
+    /// App at `version` whose backend declares `network`
+    fn network_app(version: &str, network: serde_json::Value) -> Result<OsnovaApplication> {
+        let component =
+            ComponentRef::new("ant://backend", "Sync", ComponentKind::Backend, "1.0.0")?
+                .with_config(HashMap::from([("network".to_string(), network)]));
+        Ok(OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            version,
+            "https://icon.url",
+            "Networked app",
+            vec![component],
+        )?)
+    }
+
+    /// Open a `CONNECT` tunnel to `target` through the proxy at `url` and
+    /// read until it closes
+    async fn tunnel(url: &str, target: &str) -> Result<String> {
+        use base64::Engine;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (credentials, address) = url
+            .strip_prefix("http://")
+            .and_then(|rest| rest.split_once('@'))
+            .context("not a proxy URL")?;
+        let mut stream = tokio::net::TcpStream::connect(address).await?;
+        let head = format!(
+            "CONNECT {} HTTP/1.1\r\nProxy-Authorization: Basic {}\r\n\r\n",
+            target,
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        );
+        stream.write_all(head.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
     #[test]
-    fn test_uninstall_nonexistent() -> Result<()> {
+    fn test_consent_review_shows_network_declarations() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        service.install_application(&network_app(
+            "1.0.0",
+            serde_json::json!({"autonomi": true, "hosts": ["api.example.com"]}),
+        )?)?;
 
-        let result = service.uninstall("com.nonexistent.app");
-        assert!(result.is_err());
+        let review = service.consent_review("com.test.app")?;
+        assert_eq!(
+            review.network,
+            vec![BackendNetwork {
+                component_id: "ant://backend".to_string(),
+                name: "Sync".to_string(),
+                declared: Some(NetworkDeclaration {
+                    autonomi: true,
+                    hosts: vec!["api.example.com".to_string()],
+                    local_only: false,
+                }),
+            }]
+        );
+        assert_eq!(
+            review.permissions,
+            vec!["backend", "network:autonomi", "networkHost:api.example.com"]
+        );
+
+        // Declaring another host needs a new review
+        service.record_consent("com.test.app", true, vec![])?;
+        service.install_application(&network_app(
+            "1.1.0",
+            serde_json::json!({"autonomi": true, "hosts": ["api.example.com", "*.cdn.example.com"]}),
+        )?)?;
+        let review = service.consent_review("com.test.app")?;
+        assert_eq!(
+            review.new_permissions,
+            vec!["networkHost:*.cdn.example.com"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_privacy_report_lists_observed_hosts() -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let temp = TempDir::new()?;
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let upstream_port = upstream.local_addr()?.port();
+        tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            stream.write_all(b"hello").await.unwrap();
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let proxy = Arc::new(NetworkProxy::new(temp.path())?.with_address(listener.local_addr()?));
+        tokio::spawn(proxy.clone().serve(listener));
+
+        // The stub backend writes down the proxy it was given
+        let env_file = temp.path().join("proxy-env");
+        let script = format!(
+            r#"printf '%s\n%s' "$OSNOVA_PROXY_URL" "$HTTPS_PROXY" > "{}"; sleep 30"#,
+            env_file.display()
+        );
+        let processes = Arc::new(ProcessService::new(temp.path())?);
+        let service = AppsService::new(temp.path())?
+            .with_processes(processes.clone())
+            .with_network_proxy(proxy)
+            .with_backend_command(move |_| {
+                let mut command = Command::new("bash");
+                command.arg("-c").arg(&script);
+                command
+            });
+        service.install_application(&network_app(
+            "1.0.0",
+            serde_json::json!({"hosts": ["127.0.0.1"]}),
+        )?)?;
+        service.record_consent("com.test.app", true, vec![])?;
+        service.launch("com.test.app")?;
+
+        let mut env = String::new();
+        for _ in 0..200 {
+            env = std::fs::read_to_string(&env_file).unwrap_or_default();
+            if env.contains('\n') {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (url, https_proxy) = env.split_once('\n').context("backend got no proxy")?;
+        assert_eq!(url, https_proxy);
+
+        // The backend reaches its declared host and is refused another
+        let allowed = tunnel(url, &format!("127.0.0.1:{}", upstream_port)).await?;
+        assert_eq!(allowed, "HTTP/1.1 200 Connection Established\r\n\r\nhello");
+        let denied = tunnel(url, "tracker.example.net:443").await?;
+        assert!(denied.starts_with("HTTP/1.1 403"), "{}", denied);
+
+        let mut report = service.privacy_report("com.test.app")?;
+        for _ in 0..100 {
+            if report.backends[0].observed.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            report = service.privacy_report("com.test.app")?;
+        }
+        let backend = &report.backends[0];
+        assert_eq!(backend.component_id, "ant://backend");
+        assert_eq!(
+            backend.declared.as_ref().unwrap().hosts,
+            vec!["127.0.0.1".to_string()]
+        );
+        let hosts: Vec<_> = backend
+            .observed
+            .iter()
+            .map(|o| (o.host.as_str(), o.allowed, o.denied, o.bytes_received))
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![("127.0.0.1", 1, 0, 5), ("tracker.example.net", 0, 1, 0)]
+        );
+        assert_eq!(backend.undeclared, vec!["tracker.example.net"]);
 
+        processes.stop_all()?;
         Ok(())
     }
 }
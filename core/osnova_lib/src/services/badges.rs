@@ -0,0 +1,567 @@
+//! App icon badges
+//!
+//! The launcher shows at-a-glance state on each app icon: the unread
+//! notification count, an update-available marker, and an attention
+//! indicator when the app's backend crashed on its last run or its data is
+//! syncing. [`AppBadgeService`] aggregates these per installed app from:
+//! - the user's notification center (unread per app)
+//! - the update checker ([`AppEvent::UpdateAvailable`]) and downloaded
+//!   updates waiting to be applied
+//! - the crash report store
+//! - sync drivers ([`AppEvent::SyncStateChanged`])
+//!
+//! [`AppBadgeService::badges_all`] serves the launcher's initial render.
+//! [`AppBadgeService::run`] follows the service event channels and then
+//! publishes [`AppEvent::AppBadgeChanged`] for each app whose badge
+//! changed, debounced so a burst of notifications yields one update; the
+//! shell forwards these as [`BADGE_CHANGED_EVENT`].
+//!
+//! Badges clear when the user reads or clears the app's notifications,
+//! applies its update, or launches it successfully. A successful launch,
+//! one whose backend reports ready, records the app's newest crash report
+//! as seen, so the crash flag stays cleared across restarts.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::network::CancellationToken;
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::handshake::ReadinessState;
+use crate::services::ProcessService;
+use crate::storage::SqlStorage;
+
+/// Event name used when surfacing badge changes to frontends
+pub const BADGE_CHANGED_EVENT: &str = "badge-changed";
+
+/// Default quiet period collecting a burst of changes into one update
+pub const DEFAULT_BADGE_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Why an app icon asks for attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AttentionReason {
+    /// The app's backend crashed on its last run
+    Crashed,
+    /// The app is syncing its data
+    Syncing,
+}
+
+/// State shown on an app icon
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BadgeState {
+    /// Unread notifications
+    pub unread: u32,
+    /// Whether a newer version can be installed
+    pub update_available: bool,
+    /// Why the app asks for attention; a crash outranks a sync
+    pub attention: Option<AttentionReason>,
+}
+
+impl BadgeState {
+    /// Whether the icon shows nothing
+    pub fn is_clear(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// An app's badge changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BadgeChanged {
+    /// Application identifier
+    pub app_id: String,
+    /// New badge; clear once the app is uninstalled
+    pub badge: BadgeState,
+}
+
+/// Badge aggregation service
+///
+/// Provides OpenRPC methods:
+/// - `apps.badges` - Badge of every installed app
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use osnova_lib::network::CancellationToken;
+/// use osnova_lib::services::badges::AppBadgeService;
+/// use osnova_lib::services::EventBus;
+/// use std::sync::Arc;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let events = EventBus::new();
+/// let badges = AppBadgeService::new("/tmp/osnova", "user-123")?.with_events(events.clone());
+/// let badges = Arc::new(badges);
+///
+/// let initial = badges.badges_all()?;
+/// tokio::spawn(badges.run(events.subscribe(), CancellationToken::new()));
+/// # Ok(())
+/// # }
+/// ```
+pub struct AppBadgeService {
+    storage: Mutex<SqlStorage>,
+    user_id: String,
+    processes: Option<Arc<ProcessService>>,
+    events: Option<EventBus>,
+    debounce: Duration,
+    /// Apps an update check found a newer version of
+    updates: Mutex<BTreeSet<String>>,
+    /// Apps syncing their data
+    syncing: Mutex<BTreeSet<String>>,
+    /// Badges as last handed to the frontend
+    shown: Mutex<BTreeMap<String, BadgeState>>,
+}
+
+impl AppBadgeService {
+    /// Create a new badge service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose notification center is counted
+    pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            user_id: user_id.to_string(),
+            processes: None,
+            events: None,
+            debounce: DEFAULT_BADGE_DEBOUNCE,
+            updates: Mutex::new(BTreeSet::new()),
+            syncing: Mutex::new(BTreeSet::new()),
+            shown: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Follow backend crashes reported by a process service
+    pub fn with_processes(mut self, processes: Arc<ProcessService>) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
+    /// Publish badge changes on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Override how long changes are collected before an update
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Badge of every installed app (OpenRPC: apps.badges)
+    ///
+    /// Later [`AppEvent::AppBadgeChanged`] events are relative to this.
+    pub fn badges_all(&self) -> Result<BTreeMap<String, BadgeState>> {
+        let badges = self.compute()?;
+        *self.shown.lock().unwrap() = badges.clone();
+        Ok(badges)
+    }
+
+    /// Record what a service event changes about badges
+    ///
+    /// Returns whether badges need to be recomputed.
+    pub fn handle_event(&self, event: &AppEvent) -> Result<bool> {
+        match event {
+            AppEvent::UpdateAvailable { app_id, .. } => {
+                self.updates.lock().unwrap().insert(app_id.clone());
+            }
+            // Applying an update reinstalls the app
+            AppEvent::AppInstalled { app_id, .. } => {
+                self.updates.lock().unwrap().remove(app_id);
+            }
+            AppEvent::AppUninstalled { app_id, .. } => {
+                self.updates.lock().unwrap().remove(app_id);
+                self.syncing.lock().unwrap().remove(app_id);
+            }
+            AppEvent::SyncStateChanged { app_id, syncing } => {
+                let mut apps = self.syncing.lock().unwrap();
+                if *syncing {
+                    apps.insert(app_id.clone());
+                } else {
+                    apps.remove(app_id);
+                }
+            }
+            AppEvent::BackendReadinessChanged {
+                app_id,
+                state: ReadinessState::Ready,
+                ..
+            } => {
+                let storage = self.storage.lock().unwrap();
+                if let Some(newest) = storage.list_crash_reports(app_id)?.first() {
+                    storage.acknowledge_crash(app_id, &newest.id)?;
+                }
+            }
+            AppEvent::NotificationPosted { user_id, .. }
+            | AppEvent::NotificationsRead { user_id } => return Ok(*user_id == self.user_id),
+            AppEvent::AppRestored { .. } => {}
+            AppEvent::AppMetadataChanged { .. }
+            | AppEvent::AppBadgeChanged { .. }
+            | AppEvent::ConfigChanged { .. }
+            | AppEvent::PermissionRequested { .. }
+            | AppEvent::PermissionResolved { .. }
+            | AppEvent::BackendReadinessChanged { .. }
+            | AppEvent::BackendHealthChanged { .. }
+            | AppEvent::ContextUnlocked { .. }
+            | AppEvent::KeyUsageAnomaly { .. }
+            | AppEvent::KeysMigrated { .. }
+            | AppEvent::FeatureFlagsChanged { .. }
+            | AppEvent::AuthPrompt { .. } => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Recompute badges and publish each one that changed since last shown
+    ///
+    /// Returns the changes.
+    pub fn refresh(&self) -> Result<Vec<BadgeChanged>> {
+        let badges = self.compute()?;
+        let mut shown = self.shown.lock().unwrap();
+        let app_ids: BTreeSet<&String> = badges.keys().chain(shown.keys()).collect();
+        let changes: Vec<BadgeChanged> = app_ids
+            .into_iter()
+            .filter_map(|app_id| {
+                let badge = badges.get(app_id).cloned().unwrap_or_default();
+                let previous = shown.get(app_id).cloned().unwrap_or_default();
+                (badge != previous).then(|| BadgeChanged {
+                    app_id: app_id.clone(),
+                    badge,
+                })
+            })
+            .collect();
+        *shown = badges;
+        drop(shown);
+
+        if let Some(events) = &self.events {
+            for change in &changes {
+                events.publish(AppEvent::AppBadgeChanged {
+                    change: change.clone(),
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Keep badges updated from an event stream until it closes or
+    /// `shutdown` is cancelled
+    ///
+    /// The first change starts a debounce window; changes arriving within
+    /// it are published together when it ends.
+    pub async fn run(
+        self: Arc<Self>,
+        mut events: broadcast::Receiver<AppEvent>,
+        shutdown: CancellationToken,
+    ) {
+        let mut crashes = self.processes.as_ref().map(|p| p.subscribe());
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let flush = async move {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let dirty = tokio::select! {
+                received = events.recv() => match received {
+                    // A failed update is repaired by the next recompute
+                    Ok(event) => self.handle_event(&event).unwrap_or(true),
+                    Err(broadcast::error::RecvError::Lagged(_)) => true,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                received = async { crashes.as_mut().unwrap().recv().await },
+                    if crashes.is_some() =>
+                {
+                    if matches!(received, Err(broadcast::error::RecvError::Closed)) {
+                        crashes = None;
+                    }
+                    crashes.is_some()
+                }
+                _ = flush => {
+                    deadline = None;
+                    let _ = self.refresh();
+                    false
+                }
+                _ = shutdown.cancelled() => break,
+            };
+            if dirty && deadline.is_none() {
+                deadline = Some(Instant::now() + self.debounce);
+            }
+        }
+    }
+
+    /// Badge of every installed app, from the current state of each source
+    fn compute(&self) -> Result<BTreeMap<String, BadgeState>> {
+        let updates = self.updates.lock().unwrap().clone();
+        let syncing = self.syncing.lock().unwrap().clone();
+
+        let storage = self.storage.lock().unwrap();
+        let mut unread: BTreeMap<String, u32> = BTreeMap::new();
+        for notification in storage.list_notifications(&self.user_id)? {
+            if !notification.read {
+                *unread.entry(notification.app_id).or_insert(0) += 1;
+            }
+        }
+        let mut badges = BTreeMap::new();
+        for app in storage.list_applications()? {
+            let app_id = app.id();
+            let crashed = match storage.list_crash_reports(app_id)?.first() {
+                Some(newest) => {
+                    storage.get_acknowledged_crash(app_id)?.as_deref() != Some(newest.id.as_str())
+                }
+                None => false,
+            };
+            let attention = if crashed {
+                Some(AttentionReason::Crashed)
+            } else if syncing.contains(app_id) {
+                Some(AttentionReason::Syncing)
+            } else {
+                None
+            };
+            let update_available =
+                updates.contains(app_id) || storage.get_pending_update(app_id)?.is_some();
+
+            badges.insert(
+                app_id.to_string(),
+                BadgeState {
+                    unread: unread.get(app_id).copied().unwrap_or(0),
+                    update_available,
+                    attention,
+                },
+            );
+        }
+
+        Ok(badges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::crash_report::CrashReport;
+    use crate::models::notification::Notification;
+    use crate::services::NotificationService;
+    use tempfile::TempDir;
+
+    const WALLET: &str = "com.test.wallet";
+    const NOTES: &str = "com.test.notes";
+
+    struct Fixture {
+        badges: AppBadgeService,
+        notifications: Arc<NotificationService>,
+        storage: SqlStorage,
+        events: EventBus,
+        _temp: TempDir,
+    }
+
+    fn fixture() -> Result<Fixture> {
+        let temp = TempDir::new()?;
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        for app_id in [WALLET, NOTES] {
+            storage.upsert_application(&OsnovaApplication::new(
+                app_id,
+                app_id,
+                "1.0.0",
+                "https://icon.url",
+                "Test app",
+                vec![],
+            )?)?;
+        }
+
+        let events = EventBus::new();
+        let notifications = Arc::new(
+            NotificationService::new(temp.path(), "user-123")?.with_events(events.clone()),
+        );
+        let badges = AppBadgeService::new(temp.path(), "user-123")?
+            .with_events(events.clone())
+            .with_debounce(Duration::from_millis(50));
+
+        Ok(Fixture {
+            badges,
+            notifications,
+            storage,
+            events,
+            _temp: temp,
+        })
+    }
+
+    fn crash(storage: &SqlStorage, id: &str, app_id: &str) -> Result<()> {
+        storage.insert_crash_report(&CrashReport {
+            id: id.to_string(),
+            app_id: app_id.to_string(),
+            component_id: None,
+            component_version: None,
+            pid: 4242,
+            exit_code: None,
+            signal: Some(11),
+            started_at: 100,
+            crashed_at: 200,
+            stderr_tail: vec![],
+        })
+    }
+
+    fn ready(app_id: &str) -> AppEvent {
+        AppEvent::BackendReadinessChanged {
+            app_id: app_id.to_string(),
+            component_id: "backend".to_string(),
+            state: ReadinessState::Ready,
+        }
+    }
+
+    fn update(app_id: &str) -> AppEvent {
+        AppEvent::UpdateAvailable {
+            app_id: app_id.to_string(),
+            version: "1.1.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_aggregates_sources_per_app() -> Result<()> {
+        let f = fixture()?;
+        assert!(f.badges.badges_all()?.values().all(BadgeState::is_clear));
+
+        f.notifications
+            .post(WALLET, Notification::new("Payment", "5 ANT"))?;
+        f.notifications
+            .post(WALLET, Notification::new("Payment", "7 ANT"))?;
+        assert!(f.badges.handle_event(&update(WALLET))?);
+        crash(&f.storage, "crash-1", WALLET)?;
+        f.badges.handle_event(&AppEvent::SyncStateChanged {
+            app_id: NOTES.to_string(),
+            syncing: true,
+        })?;
+
+        let badges = f.badges.badges_all()?;
+        assert_eq!(
+            badges[WALLET],
+            BadgeState {
+                unread: 2,
+                update_available: true,
+                attention: Some(AttentionReason::Crashed),
+            }
+        );
+        // Nothing of the wallet's shows on the other app
+        assert_eq!(
+            badges[NOTES],
+            BadgeState {
+                attention: Some(AttentionReason::Syncing),
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearing_conditions() -> Result<()> {
+        let f = fixture()?;
+        let posted = f
+            .notifications
+            .post(WALLET, Notification::new("Payment", "5 ANT"))?;
+        f.notifications
+            .post(NOTES, Notification::new("Synced", "3 notes"))?;
+        f.badges.handle_event(&update(WALLET))?;
+        crash(&f.storage, "crash-1", WALLET)?;
+        crash(&f.storage, "crash-2", NOTES)?;
+        f.badges.badges_all()?;
+
+        // Reading the notification clears the unread count
+        let id = posted.posted().unwrap().id;
+        f.notifications.mark_read(&[id])?;
+        let changes = f.badges.refresh()?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].app_id, WALLET);
+        assert_eq!(changes[0].badge.unread, 0);
+
+        // Applying the update reinstalls the app
+        f.badges.handle_event(&AppEvent::AppInstalled {
+            app_id: WALLET.to_string(),
+            generation: 2,
+        })?;
+        assert!(!f.badges.refresh()?[0].badge.update_available);
+
+        // A successful launch clears the crash flag, until the next crash
+        f.badges.handle_event(&ready(WALLET))?;
+        assert!(f.badges.refresh()?[0].badge.is_clear());
+        assert_eq!(
+            f.badges.badges_all()?[NOTES],
+            BadgeState {
+                unread: 1,
+                update_available: false,
+                attention: Some(AttentionReason::Crashed),
+            }
+        );
+        crash(&f.storage, "crash-3", WALLET)?;
+        assert_eq!(
+            f.badges.refresh()?[0].badge.attention,
+            Some(AttentionReason::Crashed)
+        );
+
+        // Uninstalling reports a clear badge once
+        f.storage.delete_application(NOTES)?;
+        let changes = f.badges.refresh()?;
+        assert_eq!(changes[0].app_id, NOTES);
+        assert!(changes[0].badge.is_clear());
+        assert!(f.badges.refresh()?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_burst_emits_one_update() -> Result<()> {
+        let f = fixture()?;
+        let events = f.events.clone();
+        let notifications = f.notifications.clone();
+        let badges = Arc::new(f.badges);
+        badges.badges_all()?;
+
+        let mut receiver = events.subscribe();
+        let shutdown = CancellationToken::new();
+        let runner = tokio::spawn(badges.clone().run(events.subscribe(), shutdown.clone()));
+
+        for n in 0..5 {
+            notifications.post(WALLET, Notification::new("Payment", format!("{} ANT", n)))?;
+        }
+
+        let mut changes = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while changes.is_empty() {
+            let event = tokio::time::timeout_at(deadline, receiver.recv()).await??;
+            if let AppEvent::AppBadgeChanged { change } = event {
+                changes.push(change);
+            }
+        }
+        // Anything else from the burst would have followed right away
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        while let Ok(event) = receiver.try_recv() {
+            if let AppEvent::AppBadgeChanged { change } = event {
+                changes.push(change);
+            }
+        }
+
+        assert_eq!(
+            changes,
+            [BadgeChanged {
+                app_id: WALLET.to_string(),
+                badge: BadgeState {
+                    unread: 5,
+                    ..Default::default()
+                },
+            }]
+        );
+
+        shutdown.cancel();
+        runner.await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+//! Launcher catalog service
+//!
+//! Decides which catalog the launcher shows:
+//! - The network catalog from the configured launcher manifest, merged over
+//!   the embedded default catalog (network wins per app id)
+//! - The last network catalog saved locally, when fetching fails
+//! - The embedded default catalog, when nothing else is available
+//!
+//! Results are labelled with their [`CatalogSource`] so the UI can show an
+//! offline banner, and list the apps this identity would see with a newer
+//! Osnova. When a network catalog replaces what was last shown, a
+//! `ConfigChanged` event for the launcher app tells the launcher to refresh.
+//!
+//! A network catalog naming a `featureFlagsUri` also refreshes the
+//! publisher's flag document, see [`FeatureFlagService`].
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::manifest::condition::ConditionContext;
+use crate::manifest::launcher::{
+    embedded_catalog, fetch_catalog, merge_catalogs, CatalogSource, LauncherCatalog, SourcedCatalog,
+};
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::network::{AutonomiClient, NetworkOptions};
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::features::FeatureFlagService;
+use crate::storage::{DataClass, FileStorage};
+
+/// App id the launcher's configuration events are published under
+pub const LAUNCHER_APP_ID: &str = "com.osnova.launcher";
+
+/// Component the catalog cache key is derived for, per user
+const CATALOG_KEY_COMPONENT: &str = "osnova-catalog-cache";
+
+/// Launcher catalog service
+///
+/// Provides OpenRPC methods:
+/// - `launcher.getCatalog` - Catalog to show, labelled with its source
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::identity::RootIdentity;
+/// use osnova_lib::network::NetworkOptions;
+/// use osnova_lib::services::CatalogService;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let identity = RootIdentity::generate()?;
+/// let service = CatalogService::new("/tmp/osnova", "user-123", &identity)?;
+///
+/// let shown = service
+///     .load(Some("https://example.com/catalog.json"), None, &NetworkOptions::default())
+///     .await;
+/// println!("{:?}: {} apps", shown.source, shown.catalog.entries.len());
+/// # Ok(())
+/// # }
+/// ```
+pub struct CatalogService {
+    file_storage: FileStorage,
+    cache_path: PathBuf,
+    encryption_key: [u8; 32],
+    user_id: String,
+    fingerprint: [u8; 32],
+    events: Option<EventBus>,
+    flags: Option<Arc<FeatureFlagService>>,
+    shown: Mutex<Option<LauncherCatalog>>,
+}
+
+impl CatalogService {
+    /// Create a new catalog service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User identifier (for the per-identity cache)
+    /// * `identity` - The user's identity, which the cache key and the
+    ///   staged rollout fingerprint are derived from
+    pub fn new<P: Into<PathBuf>>(
+        storage_path: P,
+        user_id: &str,
+        identity: &RootIdentity,
+    ) -> Result<Self> {
+        let file_storage = FileStorage::new(storage_path.into())?;
+        let cache_path = PathBuf::from(format!("catalog/{}/launcher.json", user_id));
+
+        Ok(Self {
+            file_storage,
+            cache_path,
+            encryption_key: Self::derive_cache_key(identity, user_id)?,
+            user_id: user_id.to_string(),
+            fingerprint: identity.fingerprint(),
+            events: None,
+            flags: None,
+            shown: Mutex::new(None),
+        })
+    }
+
+    /// Publish `ConfigChanged` events when a network catalog arrives
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Refresh the flag document a network catalog names
+    pub fn with_feature_flags(mut self, flags: Arc<FeatureFlagService>) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// The embedded default catalog, without touching the network
+    pub fn embedded(&self) -> SourcedCatalog {
+        self.show(CatalogSource::Embedded, embedded_catalog())
+    }
+
+    /// Catalog to show (OpenRPC: launcher.getCatalog)
+    ///
+    /// Never fails: without a configured manifest the embedded catalog is
+    /// returned, and a failed fetch falls back to the cache, then the
+    /// embedded catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest_uri` - Configured launcher manifest, if any
+    /// * `client` - Optional Autonomi client (required for ant:// URIs)
+    /// * `options` - Timeout and cancellation for the fetch
+    pub async fn load(
+        &self,
+        manifest_uri: Option<&str>,
+        client: Option<&AutonomiClient>,
+        options: &NetworkOptions,
+    ) -> SourcedCatalog {
+        let Some(uri) = manifest_uri else {
+            return self.embedded();
+        };
+
+        match fetch_catalog(uri, client, options).await {
+            Ok(network) => {
+                if let (Some(flags), Some(flags_uri)) = (&self.flags, &network.feature_flags_uri) {
+                    // The cached flag document stays in force on failure
+                    let publisher = network.public_key.as_deref();
+                    if let Err(e) = flags.refresh(flags_uri, publisher, client, options).await {
+                        crate::log!(Warn, "Failed to refresh feature flags: {:#}", e);
+                    }
+                }
+                let merged = merge_catalogs(&embedded_catalog(), &network);
+                // The fresh catalog is shown either way; a stale cache only
+                // matters when offline
+                let _ = self.save_cache(&merged);
+                self.show(CatalogSource::Network, merged)
+            }
+            Err(_) => match self.load_cache() {
+                Ok(Some(cached)) => self.show(
+                    CatalogSource::Cache,
+                    merge_catalogs(&embedded_catalog(), &cached),
+                ),
+                _ => self.embedded(),
+            },
+        }
+    }
+
+    // Private helper methods
+
+    /// Filter a catalog for this identity and device and remember it as shown
+    fn show(&self, source: CatalogSource, mut catalog: LauncherCatalog) -> SourcedCatalog {
+        let context = ConditionContext::current();
+        let requires_newer_osnova =
+            catalog.entries_requiring_newer_core(&self.fingerprint, &context);
+        catalog.entries = catalog.entries_for(&self.fingerprint, &context);
+
+        let previous = self.shown.lock().unwrap().replace(catalog.clone());
+        let replaced = previous.is_some_and(|previous| previous != catalog);
+        if source == CatalogSource::Network && replaced {
+            if let Some(events) = &self.events {
+                events.publish(AppEvent::ConfigChanged {
+                    app_id: LAUNCHER_APP_ID.to_string(),
+                    user_id: self.user_id.clone(),
+                });
+            }
+        }
+
+        SourcedCatalog {
+            source,
+            catalog,
+            requires_newer_osnova,
+        }
+    }
+
+    /// Load the last network catalog
+    fn load_cache(&self) -> Result<Option<LauncherCatalog>> {
+        if !self.file_storage.exists(&self.cache_path) {
+            return Ok(None);
+        }
+
+        let data = self
+            .file_storage
+            .read(&self.cache_path, &self.encryption_key)
+            .context("Failed to read catalog cache")?;
+        let catalog =
+            LauncherCatalog::from_bytes(&data).context("Failed to parse catalog cache")?;
+
+        Ok(Some(catalog))
+    }
+
+    /// Save a network catalog for offline use
+    fn save_cache(&self, catalog: &LauncherCatalog) -> Result<()> {
+        let data = serde_json::to_vec(catalog).context("Failed to serialize catalog")?;
+        self.file_storage
+            .write_classified(
+                &self.cache_path,
+                &data,
+                &self.encryption_key,
+                DataClass::CacheRegenerable,
+            )
+            .context("Failed to write catalog cache")?;
+        Ok(())
+    }
+
+    /// Derive the encryption key for the catalog cache
+    pub(crate) fn derive_cache_key(identity: &RootIdentity, user_id: &str) -> Result<[u8; 32]> {
+        let component = format!("{}:{}", CATALOG_KEY_COMPONENT, user_id);
+        Ok(identity.derive_component_key(&component, 0, KeyPurpose::Encryption)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::launcher::CatalogEntry;
+    use tempfile::TempDir;
+
+    fn network_catalog(temp: &TempDir) -> String {
+        let catalog = LauncherCatalog {
+            version: "2.0.0".to_string(),
+            entries: vec![
+                CatalogEntry {
+                    id: "com.osnova.wallet".to_string(),
+                    name: "Wallet 2".to_string(),
+                    manifest_uri: "ant://wallet".to_string(),
+                    description: String::new(),
+                    icon_uri: None,
+                    rollout_percent: 100,
+                    min_core_version: None,
+                    visible_when: None,
+                },
+                CatalogEntry {
+                    id: "com.example.music".to_string(),
+                    name: "Music".to_string(),
+                    manifest_uri: "ant://music".to_string(),
+                    description: String::new(),
+                    icon_uri: None,
+                    rollout_percent: 100,
+                    min_core_version: None,
+                    visible_when: None,
+                },
+                CatalogEntry {
+                    id: "com.example.studio".to_string(),
+                    name: "Studio".to_string(),
+                    manifest_uri: "ant://studio".to_string(),
+                    description: String::new(),
+                    icon_uri: None,
+                    rollout_percent: 100,
+                    min_core_version: Some("999.0.0".to_string()),
+                    visible_when: None,
+                },
+            ],
+            feature_flags_uri: None,
+            public_key: None,
+            signature: None,
+        };
+
+        let path = temp.path().join("published.json");
+        std::fs::write(&path, serde_json::to_vec(&catalog).unwrap()).unwrap();
+        format!("file://{}", path.display())
+    }
+
+    fn names(shown: &SourcedCatalog) -> Vec<&str> {
+        shown
+            .catalog
+            .entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_embedded() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = CatalogService::new(temp.path(), "user-123", &identity)?;
+        let options = NetworkOptions::default();
+
+        let unconfigured = service.load(None, None, &options).await;
+        assert_eq!(unconfigured.source, CatalogSource::Embedded);
+        assert_eq!(unconfigured.catalog.entries, embedded_catalog().entries);
+
+        // ant:// without a client cannot resolve
+        let failed = service.load(Some("ant://catalog"), None, &options).await;
+        assert_eq!(failed.source, CatalogSource::Embedded);
+        assert!(!failed.catalog.entries.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_network_merges_over_embedded_and_is_cached() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let uri = network_catalog(&temp);
+        let service = CatalogService::new(temp.path(), "user-123", &identity)?;
+        let options = NetworkOptions::default();
+
+        let shown = service.load(Some(&uri), None, &options).await;
+        assert_eq!(shown.source, CatalogSource::Network);
+        assert!(names(&shown).contains(&"Launcher"));
+        assert!(names(&shown).contains(&"Wallet 2"));
+        assert!(!names(&shown).contains(&"Osnova Wallet"));
+        assert_eq!(names(&shown).last(), Some(&"Music"));
+        // Listed apart, as requiring a newer Osnova
+        assert!(!names(&shown).contains(&"Studio"));
+        assert_eq!(shown.requires_newer_osnova.len(), 1);
+        assert_eq!(shown.requires_newer_osnova[0].name, "Studio");
+
+        // Offline later: the saved network catalog is used
+        std::fs::remove_file(temp.path().join("published.json"))?;
+        let offline = service.load(Some(&uri), None, &options).await;
+        assert_eq!(offline.source, CatalogSource::Cache);
+        assert_eq!(offline.catalog.entries, shown.catalog.entries);
+        assert_eq!(offline.requires_newer_osnova, shown.requires_newer_osnova);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_network_arrival_publishes_config_changed() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let uri = network_catalog(&temp);
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = CatalogService::new(temp.path(), "user-123", &identity)?.with_events(events);
+        let options = NetworkOptions::default();
+
+        service.embedded();
+        assert!(receiver.try_recv().is_err());
+
+        service.load(Some(&uri), None, &options).await;
+        assert_eq!(
+            receiver.try_recv()?,
+            AppEvent::ConfigChanged {
+                app_id: LAUNCHER_APP_ID.to_string(),
+                user_id: "user-123".to_string(),
+            }
+        );
+
+        // An unchanged network catalog does not refresh the launcher again
+        service.load(Some(&uri), None, &options).await;
+        assert!(receiver.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_network_catalog_refreshes_feature_flags() -> Result<()> {
+        use crate::manifest::flags::{FlagDocument, FlagRule};
+        use crate::models::application::OsnovaApplication;
+        use crate::models::feature_flag::FeatureFlag;
+        use crate::services::features::FlagSource;
+        use crate::storage::SqlStorage;
+        use ed25519_dalek::SigningKey;
+
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let app = OsnovaApplication::new("com.example.mail", "Mail", "1.0.0", "", "", vec![])?
+            .with_feature_flags(vec![FeatureFlag {
+                name: "threads".to_string(),
+                description: String::new(),
+                default: false,
+            }]);
+        SqlStorage::new(temp.path().join("osnova.db"))?.upsert_application(&app)?;
+
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let mut document = FlagDocument::new("1.0.0");
+        document.set_rule("com.example.mail", "threads", FlagRule::new(true));
+        let flags_path = temp.path().join("flags.json");
+        std::fs::write(&flags_path, serde_json::to_vec(&document.sign(&key)?)?)?;
+
+        let mut catalog = embedded_catalog();
+        catalog.feature_flags_uri = Some(format!("file://{}", flags_path.display()));
+        let catalog_path = temp.path().join("published.json");
+        std::fs::write(&catalog_path, serde_json::to_vec(&catalog.sign(&key)?)?)?;
+
+        let flags = Arc::new(FeatureFlagService::new(temp.path(), "user-123", &identity)?);
+        let service = CatalogService::new(temp.path(), "user-123", &identity)?
+            .with_feature_flags(flags.clone());
+        let uri = format!("file://{}", catalog_path.display());
+        service
+            .load(Some(&uri), None, &NetworkOptions::default())
+            .await;
+
+        let flag = flags.get("com.example.mail", "threads")?;
+        assert!(flag.value);
+        assert_eq!(flag.source, FlagSource::Remote);
+        Ok(())
+    }
+}
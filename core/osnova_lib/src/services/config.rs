@@ -1,10 +1,57 @@
 use anyhow::{Context, Result};
+use base64::Engine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::path::PathBuf;
-
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::context::{NotYetUnlocked, OsnovaContext, UnlockGate};
+use crate::error::OsnovaError;
+use crate::http::FetchPolicy;
 use crate::models::config_cache::{AppCache, AppConfiguration};
-use crate::storage::{FileStorage, SqlStorage};
+use crate::models::config_snapshot::{
+    decode_chain, ConfigSnapshot, RestoreMode, Settings, SnapshotContent, SnapshotReason,
+};
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::models::mutation::{new_mutation_id, MutationReceipt, MutationRejected, Since};
+use crate::models::retention::{Policy, RetentionStore};
+use crate::models::settings_schema::{SettingFieldError, SettingsSchema};
+use crate::network::bandwidth::BandwidthPolicy;
+use crate::platform::disk::{DiskGuard, DEFAULT_MIN_FREE_BYTES};
+use crate::services::apps::installed_app;
+use crate::services::config_cache::{AppConfigCache, ConfigCacheMetrics};
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::processes::RuntimeSettings;
+use crate::services::reauth::SensitiveOperation;
+use crate::services::security::KeySource;
+use crate::services::updates::{UpdatePolicy, DEFAULT_UPDATE_CHECK_INTERVAL_SECS};
+use crate::storage::chaos::{ChaosProfile, DebugGate};
+use crate::storage::sql::RetainedRows;
+use crate::storage::{DataClass, FileStorage, FileStore, SqlStorage};
+use crate::sync::diff::{self, ApplyOutcome, SyncPayload};
+use crate::time::{self, SharedClock};
+use crate::util::canonical_json;
+
+/// Default number of days an uninstalled app is kept in the trash
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Directory of secret app settings, relative to the storage root
+const SECRETS_DIR: &str = "secrets";
+
+/// Component the preset signing key is derived for
+const PRESET_KEY_COMPONENT: &str = "osnova-config-presets";
+
+/// Component the secret settings key is derived for
+const SECRET_KEY_COMPONENT: &str = "osnova-app-secrets";
+
+/// Settings written to an app's configuration between two snapshots
+pub const DEFAULT_SNAPSHOT_EVERY: u64 = 10;
+
+/// Longest time a changing configuration goes without a snapshot
+const SNAPSHOT_INTERVAL_SECS: u64 = 24 * 60 * 60;
 
 /// Configuration service for managing system and application settings
 ///
@@ -16,6 +63,38 @@ use crate::storage::{FileStorage, SqlStorage};
 /// - `config.setAppConfig` - Update per-app configuration data
 /// - `config.getAppCache` - Get per-app cache metadata
 /// - `config.clearAppCache` - Clear cache for a specific app
+/// - `config.settingsSchema` - Get the settings an app declares
+/// - `config.getSettings` - Get an app's settings schema with its values
+/// - `config.mutateAppConfig` - Update per-app configuration at most once
+///   per mutation id
+/// - `config.getSince` - Per-app configuration, only if it changed since a
+///   generation
+/// - `config.cacheMetrics` - Hit and miss counters of the per-app
+///   configuration cache
+/// - `config.setAppSecret` - Set a secret setting of an app
+/// - `config.clearAppSecret` - Clear a secret setting of an app
+/// - `config.listSnapshots` - Snapshots of an app's configuration
+/// - `config.restoreSnapshot` - Restore an app's configuration from a
+///   snapshot
+/// - `config.snapshotUsage` - Bytes of configuration snapshots per app
+///
+/// The generation of a per-app configuration is its
+/// [`version`](AppConfiguration::version); writes return it in a
+/// [`MutationReceipt`] (see [`crate::models::mutation`]).
+///
+/// Per-app configurations are read through an in-memory cache once the
+/// service is on an event bus (see [`crate::services::config_cache`]).
+///
+/// Settings an app declares `secret` are not part of its configuration.
+/// They are encrypted under `secrets/` tagged
+/// [`DataClass::Secret`], can be set and cleared but not read back, and
+/// are handed to the app's backends at launch only.
+///
+/// Before a write, the configuration as it was is kept as a snapshot once
+/// [`DEFAULT_SNAPSHOT_EVERY`] settings were written since the last one, or
+/// the last one is a day old (see [`crate::models::config_snapshot`]).
+/// Snapshots are encrypted like the configuration and kept under the
+/// [`RetentionStore::ConfigSnapshots`] policy.
 ///
 /// # Example
 ///
@@ -34,10 +113,138 @@ use crate::storage::{FileStorage, SqlStorage};
 /// # }
 /// ```
 pub struct ConfigService {
-    file_storage: FileStorage,
+    file_storage: Arc<dyn FileStore>,
     sql_storage: SqlStorage,
     system_config_path: PathBuf,
     encryption_key: [u8; 32],
+    events: Option<EventBus>,
+    overrides: SessionOverrides,
+    unlock: Option<UnlockGate>,
+    app_configs: AppConfigCache,
+    audit: Option<Arc<AuditLog>>,
+    clock: SharedClock,
+    snapshot_every: u64,
+    preset_key: Option<ed25519_dalek::SigningKey>,
+    secret_key: Option<[u8; 32]>,
+}
+
+/// System settings applied for this run only
+///
+/// Startup configuration from the environment can be applied without being
+/// persisted; the values here shadow the stored ones while the service
+/// lives and are never written back.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionOverrides {
+    /// Launcher manifest address to use instead of the stored one
+    pub launcher_manifest: Option<String>,
+    /// Server address to use instead of the stored one; `Some(None)` runs
+    /// stand-alone whatever is stored
+    pub server_address: Option<Option<String>>,
+}
+
+impl SessionOverrides {
+    /// Whether no setting is overridden
+    pub fn is_empty(&self) -> bool {
+        self.launcher_manifest.is_none() && self.server_address.is_none()
+    }
+}
+
+/// Current version of the app preset document format
+///
+/// Version 2 signs the canonical JSON payload; version 1 documents signed
+/// the payload fields in declaration order and are still accepted.
+pub const PRESET_FORMAT_VERSION: u32 = 2;
+
+/// Shareable, signed preset of selected app settings
+///
+/// Produced by [`ConfigService::export_app_preset`]. The signature covers
+/// every field except `signature` itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetDocument {
+    /// Preset document format version
+    pub format_version: u32,
+    /// Application the preset applies to
+    pub app_id: String,
+    /// Version of the application the preset was exported from
+    pub app_version: String,
+    /// Selected settings
+    pub settings: BTreeMap<String, Value>,
+    /// Unix timestamp when the preset was exported
+    pub created_at: u64,
+    /// Base64 Ed25519 public key of the exporter
+    pub public_key: String,
+    /// Base64 Ed25519 signature over the document
+    pub signature: String,
+}
+
+/// Signed portion of a preset document
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetPayload<'a> {
+    format_version: u32,
+    app_id: &'a str,
+    app_version: &'a str,
+    settings: &'a BTreeMap<String, Value>,
+    created_at: u64,
+    public_key: &'a str,
+}
+
+impl PresetDocument {
+    /// Bytes covered by the signature
+    fn signing_payload(&self) -> Result<Vec<u8>> {
+        let payload = PresetPayload {
+            format_version: self.format_version,
+            app_id: &self.app_id,
+            app_version: &self.app_version,
+            settings: &self.settings,
+            created_at: self.created_at,
+            public_key: &self.public_key,
+        };
+        match self.format_version {
+            1 => serde_json::to_vec(&payload).context("Failed to serialize preset payload"),
+            _ => canonical_json::to_canonical_vec(&payload)
+                .context("Failed to serialize preset payload"),
+        }
+    }
+}
+
+/// How an imported preset is applied to existing settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetImportPolicy {
+    /// Keep existing settings and overlay the preset
+    Merge,
+    /// Replace existing settings with the preset (sensitive keys are kept)
+    Overwrite,
+}
+
+/// Outcome of importing a preset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetImportResult {
+    /// Settings keys applied from the preset
+    pub applied_keys: Vec<String>,
+    /// Settings keys dropped because they are declared sensitive
+    pub filtered_keys: Vec<String>,
+    /// Non-fatal warnings (e.g. app version mismatch)
+    pub warnings: Vec<String>,
+}
+
+/// An app's settings schema with the current values, for the Config screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsPayload {
+    /// Application the settings belong to
+    pub app_id: String,
+    /// Settings the app declares, if any
+    pub schema: Option<SettingsSchema>,
+    /// Current value of every declared field that has one, defaults
+    /// included; secret fields are never included
+    pub values: BTreeMap<String, Value>,
+    /// Secret fields that have a value
+    #[serde(default)]
+    pub secrets_set: Vec<String>,
 }
 
 /// System-wide configuration (launcher manifest, server address, etc.)
@@ -47,27 +254,117 @@ struct SystemConfig {
     launcher_manifest: Option<String>,
     /// Server address for Client-Server mode
     server_address: Option<String>,
+    /// Days to keep uninstalled apps in the trash before purging
+    #[serde(default = "default_trash_retention_days")]
+    trash_retention_days: u32,
+    /// Bandwidth policy for metered connections
+    #[serde(default)]
+    bandwidth_policy: BandwidthPolicy,
+    /// User-set metered mode (used when the connection type is unknown)
+    #[serde(default)]
+    metered_mode: bool,
+    /// User-chosen handler app per URI scheme, overriding install order
+    #[serde(default)]
+    uri_handler_overrides: BTreeMap<String, String>,
+    /// Free disk space, in bytes, large writes must leave behind
+    #[serde(default = "default_min_free_disk_bytes")]
+    min_free_disk_bytes: u64,
+    /// Apps whose notifications are not delivered
+    #[serde(default)]
+    muted_notification_apps: BTreeSet<String>,
+    /// Update policy for apps without their own
+    #[serde(default)]
+    update_policy: UpdatePolicy,
+    /// Per-app update policies, overriding the default
+    #[serde(default)]
+    app_update_policies: BTreeMap<String, UpdatePolicy>,
+    /// Seconds between scheduled update checks
+    #[serde(default = "default_update_check_interval_secs")]
+    update_check_interval_secs: u64,
+    /// Whether this device stays silent on the local network (no mDNS
+    /// announcements)
+    #[serde(default)]
+    lan_announcements_disabled: bool,
+    /// Whether the user switched Osnova offline (no network activity)
+    #[serde(default)]
+    offline_mode: bool,
+    /// Whether apps may launch without a recorded review decision, for
+    /// development
+    #[serde(default)]
+    first_launch_consent_disabled: bool,
+    /// Whether every installed manifest must declare a signature policy
+    #[serde(default)]
+    signature_policy_required: bool,
+    /// Whether diagnostics summaries of upload payments include addresses
+    #[serde(default)]
+    diagnostics_payment_addresses: bool,
+    /// Whether annotations on apps and devices go into network backups
+    #[serde(default)]
+    annotations_network_backup: bool,
+    /// Operations that require OS re-authentication
+    #[serde(default)]
+    reauth_operations: BTreeSet<SensitiveOperation>,
+    /// Fault injection profile for storage, honoured only in builds whose
+    /// DebugGate allows chaos mode
+    #[serde(default)]
+    chaos_profile: Option<ChaosProfile>,
+    /// Retention policies of stores the user configured; others use their
+    /// defaults
+    #[serde(default)]
+    retention_policies: BTreeMap<RetentionStore, Policy>,
+    /// Policy applied to HTTP(S) fetches of remote content
+    #[serde(default)]
+    fetch_policy: FetchPolicy,
+    /// How backend processes are run (warm pool)
+    #[serde(default)]
+    runtime: RuntimeSettings,
     /// Last updated timestamp
     updated_at: u64,
 }
 
+fn default_trash_retention_days() -> u32 {
+    DEFAULT_TRASH_RETENTION_DAYS
+}
+
+fn default_min_free_disk_bytes() -> u64 {
+    DEFAULT_MIN_FREE_BYTES
+}
+
+fn default_update_check_interval_secs() -> u64 {
+    DEFAULT_UPDATE_CHECK_INTERVAL_SECS
+}
+
 impl SystemConfig {
     fn new() -> Self {
         Self {
             launcher_manifest: None,
             server_address: None,
-            updated_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            bandwidth_policy: BandwidthPolicy::default(),
+            metered_mode: false,
+            uri_handler_overrides: BTreeMap::new(),
+            min_free_disk_bytes: DEFAULT_MIN_FREE_BYTES,
+            muted_notification_apps: BTreeSet::new(),
+            update_policy: UpdatePolicy::default(),
+            app_update_policies: BTreeMap::new(),
+            update_check_interval_secs: DEFAULT_UPDATE_CHECK_INTERVAL_SECS,
+            lan_announcements_disabled: false,
+            offline_mode: false,
+            first_launch_consent_disabled: false,
+            signature_policy_required: false,
+            diagnostics_payment_addresses: false,
+            annotations_network_backup: false,
+            reauth_operations: BTreeSet::new(),
+            chaos_profile: None,
+            retention_policies: BTreeMap::new(),
+            fetch_policy: FetchPolicy::default(),
+            runtime: RuntimeSettings::default(),
+            updated_at: crate::time::now_unix(),
         }
     }
 
     fn update_timestamp(&mut self) {
-        self.updated_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.updated_at = crate::time::now_unix();
     }
 }
 
@@ -85,18 +382,101 @@ impl ConfigService {
         let storage_path = storage_path.into();
         let file_storage = FileStorage::new(&storage_path)?;
         let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
-        let system_config_path = PathBuf::from("config/system.json");
+        Ok(Self::with_storage(Arc::new(file_storage), sql_storage))
+    }
+
+    /// Create a configuration service on the storage of a context
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if storage cannot be initialized
+    pub fn from_context(context: &OsnovaContext) -> Result<Self> {
+        Ok(Self::with_storage(
+            context.file_store()?,
+            context.open_database()?,
+        ))
+    }
 
+    /// Create a configuration service on opened storage
+    fn with_storage(file_storage: Arc<dyn FileStore>, sql_storage: SqlStorage) -> Self {
         // Use a deterministic key for system config
         // TODO: In production, derive this from platform keystore
         let encryption_key = Self::derive_system_key();
 
-        Ok(Self {
+        Self {
             file_storage,
             sql_storage,
-            system_config_path,
+            system_config_path: PathBuf::from("config/system.json"),
             encryption_key,
-        })
+            events: None,
+            overrides: SessionOverrides::default(),
+            unlock: None,
+            app_configs: AppConfigCache::new(),
+            audit: None,
+            clock: time::default_clock(),
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+            preset_key: None,
+            secret_key: None,
+        }
+    }
+
+    /// Publish configuration changes on an event bus
+    ///
+    /// Per-app configurations are only cached on an event bus: changes
+    /// other instances publish on it invalidate this instance's entries.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.app_configs = self.app_configs.with_events(events.subscribe());
+        self.events = Some(events);
+        self
+    }
+
+    /// Refuse per-app configuration until the context is unlocked
+    ///
+    /// App configurations are per-user secrets; system settings stay
+    /// readable while locked.
+    pub fn with_unlock(mut self, gate: UnlockGate) -> Self {
+        self.app_configs = self.app_configs.with_unlock(gate.clone());
+        self.unlock = Some(gate);
+        self
+    }
+
+    /// Sign exported presets and encrypt secret settings with keys derived
+    /// from the user's identity
+    ///
+    /// The same identity yields the same keys on every device, so presets
+    /// exported on one device verify against
+    /// [`preset_author_key`](Self::preset_author_key) on another.
+    pub fn with_identity(mut self, identity: &RootIdentity) -> Result<Self> {
+        let seed = identity.derive_component_key(PRESET_KEY_COMPONENT, 0, KeyPurpose::Signing)?;
+        self.preset_key = Some(ed25519_dalek::SigningKey::from_bytes(&seed));
+        self.secret_key = Some(Self::derive_app_secret_key(identity)?);
+        Ok(self)
+    }
+
+    /// Record secret settings being set and cleared in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Use a specific clock for configuration snapshots and presets
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Snapshot an app's configuration every `every` settings written
+    ///
+    /// Defaults to [`DEFAULT_SNAPSHOT_EVERY`].
+    pub fn with_snapshot_every(mut self, every: u64) -> Self {
+        self.snapshot_every = every.max(1);
+        self
+    }
+
+    /// Shadow stored system settings with values for this run only
+    pub fn with_session_overrides(mut self, overrides: SessionOverrides) -> Self {
+        self.overrides = overrides;
+        self
     }
 
     /// Get the configured launcher manifest address (OpenRPC: config.getLauncherManifest)
@@ -117,6 +497,9 @@ impl ConfigService {
     /// # }
     /// ```
     pub fn get_launcher_manifest(&self) -> Result<Option<String>> {
+        if let Some(manifest) = &self.overrides.launcher_manifest {
+            return Ok(Some(manifest.clone()));
+        }
         let config = self.load_system_config()?;
         Ok(config.launcher_manifest)
     }
@@ -174,200 +557,1490 @@ impl ConfigService {
         Ok(())
     }
 
+    /// Forget the server address, returning to Stand-Alone mode
+    pub fn clear_server(&self) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.server_address = None;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
     /// Get server address
     ///
     /// Returns the configured server address, or None if not configured.
     pub fn get_server(&self) -> Result<Option<String>> {
+        if let Some(server_address) = &self.overrides.server_address {
+            return Ok(server_address.clone());
+        }
         let config = self.load_system_config()?;
         Ok(config.server_address)
     }
 
-    /// Get per-app configuration data (OpenRPC: config.getAppConfig)
-    ///
-    /// Returns the configuration settings for a specific app and user.
-    ///
-    /// # Arguments
-    ///
-    /// * `app_id` - Application identifier
-    /// * `user_id` - User identifier
-    ///
-    /// # Example
+    /// Get the number of days uninstalled apps are kept in the trash
     ///
-    /// ```no_run
-    /// # use osnova_lib::services::ConfigService;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = ConfigService::new("/tmp/storage")?;
-    /// let config = service.get_app_config("com.osnova.wallet", "user-123")?;
-    /// if let Some(theme) = config.get_setting("theme") {
-    ///     println!("Theme: {:?}", theme);
-    /// }
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn get_app_config(&self, app_id: &str, user_id: &str) -> Result<AppConfiguration> {
-        // Use a per-user encryption key derived from user_id
-        // TODO: In production, derive from user's master key
-        let encryption_key = Self::derive_user_config_key(user_id);
-
-        match self
-            .sql_storage
-            .get_app_config(app_id, user_id, &encryption_key)?
-        {
-            Some(config) => Ok(config),
-            None => Ok(AppConfiguration::new(app_id, user_id)),
-        }
+    /// Defaults to [`DEFAULT_TRASH_RETENTION_DAYS`] if not configured.
+    pub fn get_trash_retention_days(&self) -> Result<u32> {
+        let config = self.load_system_config()?;
+        Ok(config.trash_retention_days)
     }
 
-    /// Update per-app configuration data (OpenRPC: config.setAppConfig)
-    ///
-    /// Updates the configuration settings for a specific app and user.
-    ///
-    /// # Arguments
-    ///
-    /// * `app_id` - Application identifier
-    /// * `user_id` - User identifier
-    /// * `settings` - Configuration settings to update (partial or full)
-    ///
-    /// # Example
+    /// Set the number of days uninstalled apps are kept in the trash
     ///
-    /// ```no_run
-    /// # use osnova_lib::services::ConfigService;
-    /// # use serde_json::json;
-    /// # use std::collections::HashMap;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = ConfigService::new("/tmp/storage")?;
-    /// let mut settings = HashMap::new();
-    /// settings.insert("theme".to_string(), json!("dark"));
-    /// settings.insert("language".to_string(), json!("en"));
-    /// service.set_app_config("com.osnova.wallet", "user-123", settings)?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn set_app_config(
-        &self,
-        app_id: &str,
-        user_id: &str,
-        settings: std::collections::HashMap<String, Value>,
-    ) -> Result<()> {
-        // Get existing config or create new one
-        let mut config = self.get_app_config(app_id, user_id)?;
-
-        // Update settings
-        for (key, value) in settings {
-            config.set_setting(&key, value);
-        }
-
-        // Use a per-user encryption key
-        let encryption_key = Self::derive_user_config_key(user_id);
-
-        // Save to database
-        self.sql_storage
-            .set_app_config(app_id, user_id, &config, &encryption_key)?;
-
+    /// A value of 0 makes uninstall permanent.
+    pub fn set_trash_retention_days(&self, days: u32) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.trash_retention_days = days;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
         Ok(())
     }
 
-    /// Get per-app cache metadata (OpenRPC: config.getAppCache)
-    ///
-    /// Returns metadata about the cache for a specific app and user.
-    ///
-    /// # Arguments
-    ///
-    /// * `app_id` - Application identifier
-    /// * `user_id` - User identifier
+    /// Get the bandwidth policy
     ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// # use osnova_lib::services::ConfigService;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = ConfigService::new("/tmp/storage")?;
-    /// if let Some(cache) = service.get_app_cache("com.osnova.wallet", "user-123")? {
-    ///     println!("Cache size: {} bytes", cache.entries().len());
-    /// }
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn get_app_cache(&self, _app_id: &str, _user_id: &str) -> Result<Option<AppCache>> {
-        // TODO: Implement app cache storage
-        // For now, always return None
-        Ok(None)
+    /// Defaults to [`BandwidthPolicy::Unlimited`] if not configured.
+    pub fn get_bandwidth_policy(&self) -> Result<BandwidthPolicy> {
+        let config = self.load_system_config()?;
+        Ok(config.bandwidth_policy)
     }
 
-    /// Clear cache for a specific app (OpenRPC: config.clearAppCache)
-    ///
-    /// Deletes all cache data for a specific app and user.
-    ///
-    /// # Arguments
-    ///
-    /// * `app_id` - Application identifier
-    /// * `user_id` - User identifier
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// # use osnova_lib::services::ConfigService;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let service = ConfigService::new("/tmp/storage")?;
-    /// service.clear_app_cache("com.osnova.wallet", "user-123")?;
-    /// println!("Cache cleared");
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn clear_app_cache(&self, _app_id: &str, _user_id: &str) -> Result<()> {
-        // TODO: Implement app cache storage
-        // For now, this is a no-op
+    /// Set the bandwidth policy
+    pub fn set_bandwidth_policy(&self, policy: BandwidthPolicy) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.bandwidth_policy = policy;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
         Ok(())
     }
 
-    // Private helper methods
-
-    /// Load system configuration from encrypted file storage
-    fn load_system_config(&self) -> Result<SystemConfig> {
-        if !self.file_storage.exists(&self.system_config_path) {
-            return Ok(SystemConfig::new());
-        }
+    /// Get the user-set metered mode toggle
+    pub fn get_metered_mode(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(config.metered_mode)
+    }
 
-        let encrypted_data = self
-            .file_storage
-            .read(&self.system_config_path, &self.encryption_key)
-            .context("Failed to read system config")?;
+    /// Set the user-set metered mode toggle
+    ///
+    /// Used by the Wi-Fi only policy when the platform cannot report the
+    /// connection type.
+    pub fn set_metered_mode(&self, metered: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.metered_mode = metered;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
 
-        let config: SystemConfig = serde_json::from_slice(&encrypted_data)
-            .context("Failed to deserialize system config")?;
+    /// Get the free disk space, in bytes, large writes must leave behind
+    ///
+    /// Defaults to [`DEFAULT_MIN_FREE_BYTES`] if not configured.
+    pub fn get_min_free_disk_bytes(&self) -> Result<u64> {
+        let config = self.load_system_config()?;
+        Ok(config.min_free_disk_bytes)
+    }
 
-        Ok(config)
+    /// Set the free disk space, in bytes, large writes must leave behind
+    pub fn set_min_free_disk_bytes(&self, bytes: u64) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.min_free_disk_bytes = bytes;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
     }
 
-    /// Save system configuration to encrypted file storage
-    fn save_system_config(&self, config: &SystemConfig) -> Result<()> {
-        let config_json =
-            serde_json::to_vec(config).context("Failed to serialize system config")?;
+    /// Disk guard enforcing the configured free space threshold
+    pub fn disk_guard(&self) -> Result<DiskGuard> {
+        Ok(DiskGuard::new(self.get_min_free_disk_bytes()?))
+    }
 
-        self.file_storage
-            .write(&self.system_config_path, &config_json, &self.encryption_key)
-            .context("Failed to write system config")?;
+    /// Whether notifications from an app are delivered
+    ///
+    /// Apps are enabled unless turned off with
+    /// [`Self::set_notifications_enabled`].
+    pub fn get_notifications_enabled(&self, app_id: &str) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(!config.muted_notification_apps.contains(app_id))
+    }
 
+    /// Turn notifications from an app on or off
+    pub fn set_notifications_enabled(&self, app_id: &str, enabled: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        if enabled {
+            config.muted_notification_apps.remove(app_id);
+        } else {
+            config.muted_notification_apps.insert(app_id.to_string());
+        }
+        config.update_timestamp();
+        self.save_system_config(&config)?;
         Ok(())
     }
 
-    /// Derive a deterministic encryption key for system config
+    /// Get the update policy for apps without their own
     ///
-    /// TODO: In production, integrate with platform keystore
-    fn derive_system_key() -> [u8; 32] {
-        use blake3::Hasher;
-        let mut hasher = Hasher::new();
-        hasher.update(b"osnova-system-config-key-v1");
-        let hash = hasher.finalize();
-        let mut key = [0u8; 32];
-        key.copy_from_slice(hash.as_bytes());
-        key
+    /// Defaults to [`UpdatePolicy::Manual`] if not configured.
+    pub fn get_default_update_policy(&self) -> Result<UpdatePolicy> {
+        let config = self.load_system_config()?;
+        Ok(config.update_policy)
     }
 
-    /// Derive a per-user encryption key for app configurations
+    /// Set the update policy for apps without their own
+    pub fn set_default_update_policy(&self, policy: UpdatePolicy) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.update_policy = policy;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the update policy that applies to an app
+    ///
+    /// The app's own policy if set, otherwise the default.
+    pub fn get_update_policy(&self, app_id: &str) -> Result<UpdatePolicy> {
+        let config = self.load_system_config()?;
+        Ok(config
+            .app_update_policies
+            .get(app_id)
+            .copied()
+            .unwrap_or(config.update_policy))
+    }
+
+    /// Set an app's own update policy
+    ///
+    /// `None` makes the app follow the default policy again.
+    pub fn set_app_update_policy(&self, app_id: &str, policy: Option<UpdatePolicy>) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        match policy {
+            Some(policy) => config
+                .app_update_policies
+                .insert(app_id.to_string(), policy),
+            None => config.app_update_policies.remove(app_id),
+        };
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the seconds between scheduled update checks
+    ///
+    /// Defaults to [`DEFAULT_UPDATE_CHECK_INTERVAL_SECS`] if not configured.
+    pub fn get_update_check_interval_secs(&self) -> Result<u64> {
+        let config = self.load_system_config()?;
+        Ok(config.update_check_interval_secs)
+    }
+
+    /// Set the seconds between scheduled update checks
+    pub fn set_update_check_interval_secs(&self, secs: u64) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.update_check_interval_secs = secs;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether this server may announce itself on the local network
+    ///
+    /// Enabled by default.
+    pub fn get_lan_announcements_enabled(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(!config.lan_announcements_disabled)
+    }
+
+    /// Allow or forbid announcing this server on the local network
+    pub fn set_lan_announcements_enabled(&self, enabled: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.lan_announcements_disabled = !enabled;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether the user switched Osnova offline
+    ///
+    /// Off by default. Takes effect once applied to the process-wide
+    /// [network kill switch](crate::network::kill_switch::shared).
+    pub fn get_offline_mode(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(config.offline_mode)
+    }
+
+    /// Switch Osnova offline, blocking all network activity, or back online
+    pub fn set_offline_mode(&self, offline: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.offline_mode = offline;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether an app needs a recorded review decision before it launches
+    ///
+    /// Required by default.
+    pub fn get_first_launch_consent_required(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(!config.first_launch_consent_disabled)
+    }
+
+    /// Require a review decision before first launch, or let apps launch
+    /// without one while developing them
+    pub fn set_first_launch_consent_required(&self, required: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.first_launch_consent_disabled = !required;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether installs and updates need a manifest signature policy
+    ///
+    /// Off by default; manifests that declare a policy are held to it
+    /// either way.
+    pub fn get_signature_policy_required(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(config.signature_policy_required)
+    }
+
+    /// Refuse manifests without a signature policy (strict mode), or accept
+    /// them as before
+    pub fn set_signature_policy_required(&self, required: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.signature_policy_required = required;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether diagnostics summaries of upload payments include the
+    /// ant:// addresses paid for
+    ///
+    /// Off by default; summaries then report amounts only.
+    pub fn get_diagnostics_payment_addresses(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(config.diagnostics_payment_addresses)
+    }
+
+    /// Include addresses in payment diagnostics summaries, or stop
+    pub fn set_diagnostics_payment_addresses(&self, include: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.diagnostics_payment_addresses = include;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Whether annotations on apps and devices go into network backups
+    ///
+    /// Off by default; annotations then stay on this device and in local
+    /// exports.
+    pub fn get_annotations_network_backup(&self) -> Result<bool> {
+        let config = self.load_system_config()?;
+        Ok(config.annotations_network_backup)
+    }
+
+    /// Include annotations in network backups, or keep them local
+    pub fn set_annotations_network_backup(&self, include: bool) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.annotations_network_backup = include;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Operations that require OS re-authentication, in display order
+    ///
+    /// None by default. Change them through
+    /// [`ReauthService::set_required`](crate::services::reauth::ReauthService::set_required),
+    /// which asks for a proof before dropping one.
+    pub fn get_reauth_operations(&self) -> Result<Vec<SensitiveOperation>> {
+        let config = self.load_system_config()?;
+        Ok(config.reauth_operations.into_iter().collect())
+    }
+
+    /// Set the operations that require OS re-authentication
+    pub fn set_reauth_operations(&self, operations: &[SensitiveOperation]) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        config.reauth_operations = operations.iter().copied().collect();
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the active chaos profile, if chaos mode is on
+    ///
+    /// Always `None` in builds whose [`DebugGate`] forbids chaos mode, even
+    /// if a build that allowed it stored a profile.
+    pub fn get_chaos_profile(&self) -> Result<Option<ChaosProfile>> {
+        if !DebugGate::chaos_allowed() {
+            return Ok(None);
+        }
+        let config = self.load_system_config()?;
+        Ok(config.chaos_profile)
+    }
+
+    /// Turn chaos mode on with a profile, or off with `None`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a profile is given and the [`DebugGate`] forbids
+    /// chaos mode, or the profile is invalid
+    pub fn set_chaos_profile(&self, profile: Option<ChaosProfile>) -> Result<()> {
+        if let Some(profile) = &profile {
+            DebugGate::ensure_chaos_allowed()?;
+            profile.validate()?;
+        }
+        let mut config = self.load_system_config()?;
+        config.chaos_profile = profile;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the retention policy of a store
+    ///
+    /// Defaults to [`RetentionStore::default_policy`] if not configured.
+    pub fn get_retention_policy(&self, store: RetentionStore) -> Result<Policy> {
+        let config = self.load_system_config()?;
+        Ok(config
+            .retention_policies
+            .get(&store)
+            .copied()
+            .unwrap_or_else(|| store.default_policy()))
+    }
+
+    /// Set the retention policy of a store
+    ///
+    /// `None` restores the store's default policy.
+    pub fn set_retention_policy(
+        &self,
+        store: RetentionStore,
+        policy: Option<Policy>,
+    ) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        match policy {
+            Some(policy) => config.retention_policies.insert(store, policy),
+            None => config.retention_policies.remove(&store),
+        };
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the policy for HTTP(S) fetches of remote content
+    ///
+    /// Defaults to [`FetchPolicy::default`] if not configured. Takes effect
+    /// once installed with [`crate::http::set_shared_policy`].
+    pub fn get_fetch_policy(&self) -> Result<FetchPolicy> {
+        let config = self.load_system_config()?;
+        Ok(config.fetch_policy)
+    }
+
+    /// Set the policy for HTTP(S) fetches of remote content
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policy allows no connections per origin
+    pub fn set_fetch_policy(&self, policy: FetchPolicy) -> Result<()> {
+        if policy.max_connections_per_origin == 0 {
+            anyhow::bail!("Fetch policy must allow at least one connection per origin");
+        }
+        let mut config = self.load_system_config()?;
+        config.fetch_policy = policy;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get how backend processes are run
+    ///
+    /// Defaults to [`RuntimeSettings::default`], with the warm pool off.
+    pub fn get_runtime_settings(&self) -> Result<RuntimeSettings> {
+        let config = self.load_system_config()?;
+        Ok(config.runtime)
+    }
+
+    /// Set how backend processes are run and per-app configuration is read
+    ///
+    /// Cache settings apply to this instance at once, and to others the
+    /// next time they are created.
+    pub fn set_runtime_settings(&self, settings: RuntimeSettings) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        self.app_configs
+            .configure(settings.config_cache, settings.config_cache_entries);
+        config.runtime = settings;
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get the user-chosen handler app of each URI scheme
+    pub fn get_uri_handler_overrides(&self) -> Result<BTreeMap<String, String>> {
+        let config = self.load_system_config()?;
+        Ok(config.uri_handler_overrides)
+    }
+
+    /// Choose the app that handles a URI scheme
+    ///
+    /// `None` clears the choice, so the first-installed app handles the
+    /// scheme again.
+    pub fn set_uri_handler_override(&self, scheme: &str, app_id: Option<&str>) -> Result<()> {
+        let mut config = self.load_system_config()?;
+        let scheme = scheme.to_ascii_lowercase();
+        match app_id {
+            Some(app_id) => config
+                .uri_handler_overrides
+                .insert(scheme, app_id.to_string()),
+            None => config.uri_handler_overrides.remove(&scheme),
+        };
+        config.update_timestamp();
+        self.save_system_config(&config)?;
+        Ok(())
+    }
+
+    /// Get per-app configuration data (OpenRPC: config.getAppConfig)
+    ///
+    /// Returns the configuration settings for a specific app and user.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application identifier
+    /// * `user_id` - User identifier
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::ConfigService;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = ConfigService::new("/tmp/storage")?;
+    /// let config = service.get_app_config("com.osnova.wallet", "user-123")?;
+    /// if let Some(theme) = config.get_setting("theme") {
+    ///     println!("Theme: {:?}", theme);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_app_config(&self, app_id: &str, user_id: &str) -> Result<AppConfiguration> {
+        let mut config = self.cached_app_config(app_id, user_id)?;
+        if let Some(schema) = self.app_settings_schema(app_id)? {
+            for field in schema.fields() {
+                if let Some(default) = &field.default {
+                    config.fill_default(&field.key, default.clone());
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Update per-app configuration data (OpenRPC: config.setAppConfig)
+    ///
+    /// Updates the configuration settings for a specific app and user.
+    /// When the app declares a settings schema, values of declared fields
+    /// must fit it; otherwise nothing is written and the error is an
+    /// [`OsnovaError::InvalidSettings`] listing every rejected field.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application identifier
+    /// * `user_id` - User identifier
+    /// * `settings` - Configuration settings to update (partial or full)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::ConfigService;
+    /// # use serde_json::json;
+    /// # use std::collections::HashMap;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = ConfigService::new("/tmp/storage")?;
+    /// let mut settings = HashMap::new();
+    /// settings.insert("theme".to_string(), json!("dark"));
+    /// settings.insert("language".to_string(), json!("en"));
+    /// service.set_app_config("com.osnova.wallet", "user-123", settings)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_app_config(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        settings: std::collections::HashMap<String, Value>,
+    ) -> Result<MutationReceipt> {
+        self.mutate_app_config(&new_mutation_id(), app_id, user_id, settings)
+    }
+
+    /// Update per-app configuration at most once per mutation id
+    /// (OpenRPC: config.mutateAppConfig)
+    ///
+    /// Like [`set_app_config`](Self::set_app_config), but sending the same
+    /// `mutation_id` again returns the first receipt without writing the
+    /// settings twice.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error carrying a [`MutationRejected`] with the generation
+    /// the configuration is still at
+    pub fn mutate_app_config(
+        &self,
+        mutation_id: &str,
+        app_id: &str,
+        user_id: &str,
+        settings: std::collections::HashMap<String, Value>,
+    ) -> Result<MutationReceipt> {
+        let scope = format!("config:{}:{}", app_id, user_id);
+        let mut applied = false;
+        self.sql_storage
+            .apply_mutation(&scope, mutation_id, || {
+                self.write_app_config(app_id, user_id, settings)
+                    .inspect(|_| applied = true)
+            })
+            .inspect(|_| {
+                if applied {
+                    self.publish_config_changed(app_id, user_id);
+                }
+            })
+            .map_err(|e| {
+                let last_good = self
+                    .stored_app_config(app_id, user_id)
+                    .map(|config| config.version());
+                MutationRejected::wrap(e, mutation_id, last_good.ok())
+            })
+    }
+
+    /// Per-app configuration, only if it changed since `generation`
+    /// (OpenRPC: config.getSince)
+    pub fn get_since(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        generation: u64,
+    ) -> Result<Since<AppConfiguration>> {
+        let config = self.get_app_config(app_id, user_id)?;
+        Since::check(generation, config.version(), || Ok(config))
+    }
+
+    /// Hit and miss counters of the per-app configuration cache
+    /// (OpenRPC: config.cacheMetrics)
+    pub fn cache_metrics(&self) -> ConfigCacheMetrics {
+        self.app_configs.metrics()
+    }
+
+    /// Get per-app cache metadata (OpenRPC: config.getAppCache)
+    ///
+    /// Returns metadata about the cache for a specific app and user.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application identifier
+    /// * `user_id` - User identifier
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::ConfigService;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = ConfigService::new("/tmp/storage")?;
+    /// if let Some(cache) = service.get_app_cache("com.osnova.wallet", "user-123")? {
+    ///     println!("Cache size: {} bytes", cache.entries().len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_app_cache(&self, _app_id: &str, _user_id: &str) -> Result<Option<AppCache>> {
+        // TODO: Implement app cache storage
+        // For now, always return None
+        Ok(None)
+    }
+
+    /// Clear cache for a specific app (OpenRPC: config.clearAppCache)
+    ///
+    /// Deletes all cache data for a specific app and user.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application identifier
+    /// * `user_id` - User identifier
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::ConfigService;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let service = ConfigService::new("/tmp/storage")?;
+    /// service.clear_app_cache("com.osnova.wallet", "user-123")?;
+    /// println!("Cache cleared");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clear_app_cache(&self, _app_id: &str, _user_id: &str) -> Result<()> {
+        // TODO: Implement app cache storage
+        // For now, this is a no-op
+        Ok(())
+    }
+
+    /// Get the settings an app declares (OpenRPC: config.settingsSchema)
+    ///
+    /// Returns `None` when the installed app's manifest has no
+    /// `settingsSchema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed
+    pub fn settings_schema(&self, app_id: &str) -> Result<Option<SettingsSchema>> {
+        Ok(installed_app(&self.sql_storage, app_id)?
+            .settings_schema()
+            .cloned())
+    }
+
+    /// Get an app's settings schema with the current values
+    /// (OpenRPC: config.getSettings)
+    ///
+    /// Everything the Config screen needs to render an app's settings form
+    /// in one call. Fields without a stored value show their default.
+    pub fn settings_payload(&self, app_id: &str, user_id: &str) -> Result<SettingsPayload> {
+        let schema = self.settings_schema(app_id)?;
+        let config = self.get_app_config(app_id, user_id)?;
+        let values = schema
+            .iter()
+            .flat_map(SettingsSchema::fields)
+            .filter(|field| !field.secret)
+            .filter_map(|field| {
+                config
+                    .get_setting(&field.key)
+                    .map(|value| (field.key.clone(), value.clone()))
+            })
+            .collect();
+        let secrets_set = self.app_secrets(app_id, user_id)?.into_keys().collect();
+
+        Ok(SettingsPayload {
+            app_id: app_id.to_string(),
+            schema,
+            values,
+            secrets_set,
+        })
+    }
+
+    /// Set a secret setting of an app (OpenRPC: config.setAppSecret)
+    ///
+    /// The key must be a field the app's settings schema declares
+    /// `secret`, and the value must fit it. There is no way to read the
+    /// value back; the app's backends receive it at launch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is not a secret field of the installed
+    /// app, or an [`OsnovaError::InvalidSettings`] if the value does not
+    /// fit it
+    pub fn set_app_secret(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        key: &str,
+        value: Value,
+    ) -> Result<()> {
+        self.require_unlocked("config.setAppSecret")?;
+        let schema = self.app_settings_schema(app_id)?;
+        let field = schema
+            .as_ref()
+            .and_then(|schema| schema.field(key))
+            .filter(|field| field.secret)
+            .with_context(|| format!("{} is not a secret setting of {}", key, app_id))?;
+        if let Err(message) = field.check(&value) {
+            return Err(OsnovaError::InvalidSettings {
+                app_id: app_id.to_string(),
+                errors: vec![SettingFieldError {
+                    key: key.to_string(),
+                    message,
+                }],
+            }
+            .into());
+        }
+
+        let mut secrets = self.stored_app_secrets(app_id, user_id)?;
+        secrets.insert(key.to_string(), value);
+        self.write_app_secrets(app_id, user_id, &secrets)?;
+        self.audit_secret(AuditAction::AppSecretSet, app_id, key)?;
+        self.publish_config_changed(app_id, user_id);
+        Ok(())
+    }
+
+    /// Clear a secret setting of an app (OpenRPC: config.clearAppSecret)
+    ///
+    /// Returns whether the setting had a value.
+    pub fn clear_app_secret(&self, app_id: &str, user_id: &str, key: &str) -> Result<bool> {
+        self.require_unlocked("config.clearAppSecret")?;
+        let mut secrets = self.stored_app_secrets(app_id, user_id)?;
+        if secrets.remove(key).is_none() {
+            return Ok(false);
+        }
+
+        if secrets.is_empty() {
+            self.file_storage
+                .delete(&Self::secrets_path(app_id, user_id))?;
+        } else {
+            self.write_app_secrets(app_id, user_id, &secrets)?;
+        }
+        self.audit_secret(AuditAction::AppSecretCleared, app_id, key)?;
+        self.publish_config_changed(app_id, user_id);
+        Ok(true)
+    }
+
+    /// Secret settings of an app for its backends, limited to the fields
+    /// the installed version still declares secret
+    pub(crate) fn app_secrets(
+        &self,
+        app_id: &str,
+        user_id: &str,
+    ) -> Result<BTreeMap<String, Value>> {
+        let Some(schema) = self.app_settings_schema(app_id)? else {
+            return Ok(BTreeMap::new());
+        };
+        let mut secrets = self.stored_app_secrets(app_id, user_id)?;
+        secrets.retain(|key, _| schema.is_secret(key));
+        Ok(secrets)
+    }
+
+    /// Snapshots of an app's configuration, newest first
+    /// (OpenRPC: config.listSnapshots)
+    pub fn list_snapshots(&self, app_id: &str, user_id: &str) -> Result<Vec<ConfigSnapshot>> {
+        self.require_unlocked("config.listSnapshots")?;
+        let mut snapshots = self.sql_storage.list_config_snapshots(app_id, user_id)?;
+        snapshots.reverse();
+        Ok(snapshots)
+    }
+
+    /// Restore an app's configuration from a snapshot
+    /// (OpenRPC: config.restoreSnapshot)
+    ///
+    /// The configuration as it was is snapshotted first, so restoring that
+    /// snapshot undoes the restore; it is returned. Restored values that no
+    /// longer fit the app's settings schema are dropped as on an update.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app has no snapshot `snapshot_id`
+    pub fn restore_snapshot(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        snapshot_id: i64,
+        mode: RestoreMode,
+    ) -> Result<ConfigSnapshot> {
+        self.require_unlocked("config.restoreSnapshot")?;
+        let encryption_key = Self::derive_user_config_key(user_id);
+        let contents = self
+            .sql_storage
+            .config_snapshot_contents(app_id, user_id, &encryption_key)?;
+        let position = contents
+            .iter()
+            .position(|(id, _)| *id == snapshot_id)
+            .with_context(|| format!("Snapshot {} of {} not found", snapshot_id, app_id))?;
+        let restored = decode_chain(contents[..=position].iter().map(|(_, content)| content))?
+            .pop()
+            .unwrap_or_default();
+
+        let mut config = self.stored_app_config(app_id, user_id)?;
+        let before = self.take_snapshot(&config, SnapshotReason::BeforeRestore)?;
+
+        if mode == RestoreMode::Replace {
+            let dropped: Vec<String> = config
+                .settings()
+                .keys()
+                .filter(|key| !restored.contains_key(*key))
+                .cloned()
+                .collect();
+            for key in dropped {
+                config.remove_setting(&key);
+            }
+        }
+        for (key, value) in restored {
+            let keep = match mode {
+                RestoreMode::Replace => config.get_setting(&key) == Some(&value),
+                RestoreMode::MergeMissing => config.get_setting(&key).is_some(),
+            };
+            if !keep {
+                config.set_setting(key, value);
+            }
+        }
+        if let Some(schema) = self.app_settings_schema(app_id)? {
+            schema.migrate(&mut config);
+        }
+
+        self.sql_storage
+            .set_app_config(app_id, user_id, &config, &encryption_key)?;
+        self.app_configs.invalidate(app_id, user_id);
+        self.publish_config_changed(app_id, user_id);
+        Ok(before)
+    }
+
+    /// Bytes of configuration snapshots stored per app for a user
+    /// (OpenRPC: config.snapshotUsage)
+    ///
+    /// Counts snapshots as stored, encrypted, and is attributed to each
+    /// app's storage use.
+    pub fn snapshot_usage(&self, user_id: &str) -> Result<BTreeMap<String, u64>> {
+        self.sql_storage.config_snapshot_usage(user_id)
+    }
+
+    /// Fit every user's stored settings of an app to a new settings schema
+    ///
+    /// Run when an app is installed over another version with a different
+    /// schema: values under a field's former keys move to the field, and
+    /// values that no longer fit are removed so the default applies (see
+    /// [`SettingsSchema::migrate`]). Values of fields that became secret
+    /// move to the secret store. Returns the number of users whose
+    /// settings changed.
+    pub fn migrate_app_settings(&self, app_id: &str, schema: &SettingsSchema) -> Result<usize> {
+        let mut migrated = 0;
+        for user_id in self.sql_storage.list_app_config_users(app_id)? {
+            let encryption_key = Self::derive_user_config_key(&user_id);
+            let Some(mut config) =
+                self.sql_storage
+                    .get_app_config(app_id, &user_id, &encryption_key)?
+            else {
+                continue;
+            };
+            self.move_secret_values(app_id, &user_id, schema, &config)?;
+            let before = config.clone();
+            let changed = schema.migrate(&mut config);
+            if changed.is_empty() {
+                continue;
+            }
+            self.snapshot_if_due(&before)?;
+
+            self.sql_storage
+                .set_app_config(app_id, &user_id, &config, &encryption_key)?;
+            self.app_configs.invalidate(app_id, &user_id);
+            self.publish_config_changed(app_id, &user_id);
+            crate::log!(
+                Info,
+                "Migrated settings of {} for a new settings schema: {}",
+                app_id,
+                changed.join(", ")
+            );
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    /// Export selected app settings as a shareable preset
+    ///
+    /// Only the keys in `include_keys` are exported, and keys the app's
+    /// manifest declares in `sensitiveConfigKeys` are always left out, as
+    /// are secret settings. Requested keys without a value are skipped.
+    /// The preset is signed with the key from
+    /// [`with_identity`](Self::with_identity).
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Installed application identifier
+    /// * `user_id` - User identifier
+    /// * `include_keys` - Settings keys to include in the preset
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed, and [`NotYetUnlocked`]
+    /// if the service was not given the user's identity
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::models::identity::RootIdentity;
+    /// # use osnova_lib::services::ConfigService;
+    /// # fn example(identity: &RootIdentity) -> anyhow::Result<()> {
+    /// let service = ConfigService::new("/tmp/storage")?.with_identity(identity)?;
+    /// let keys = vec!["theme".to_string(), "fontSize".to_string()];
+    /// let preset = service.export_app_preset("com.osnova.editor", "user-123", &keys)?;
+    /// println!("{}", serde_json::to_string_pretty(&preset)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_app_preset(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        include_keys: &[String],
+    ) -> Result<PresetDocument> {
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let sensitive = app.sensitive_config_keys();
+        let config = self.get_app_config(app_id, user_id)?;
+
+        let schema = app.settings_schema();
+        let settings = include_keys
+            .iter()
+            .filter(|key| !is_sensitive_key(key, &sensitive))
+            .filter(|key| !schema.is_some_and(|schema| schema.is_secret(key)))
+            .filter_map(|key| {
+                config
+                    .get_setting(key)
+                    .map(|value| (key.clone(), value.clone()))
+            })
+            .collect();
+
+        let signing_key = self.preset_signing_key("config.exportPreset")?;
+        let public_key = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.verifying_key().to_bytes());
+
+        let mut preset = PresetDocument {
+            format_version: PRESET_FORMAT_VERSION,
+            app_id: app_id.to_string(),
+            app_version: app.version().to_string(),
+            settings,
+            created_at: self.clock.now_unix(),
+            public_key,
+            signature: String::new(),
+        };
+
+        use ed25519_dalek::Signer;
+        let signature = signing_key.sign(&preset.signing_payload()?);
+        preset.signature = base64::engine::general_purpose::STANDARD.encode(signature.to_bytes());
+
+        Ok(preset)
+    }
+
+    /// Base64 key this user's presets are signed with
+    ///
+    /// Shared with the people who import the user's presets. It is the same
+    /// on each of the user's devices.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotYetUnlocked`] if the service was not given the user's
+    /// identity
+    pub fn preset_author_key(&self) -> Result<String> {
+        let signing_key = self.preset_signing_key("config.presetAuthorKey")?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().to_bytes()))
+    }
+
+    /// Import a preset into an app's settings
+    ///
+    /// Verifies the document format, and the signature against the known
+    /// key of the preset's author (their
+    /// [`preset_author_key`](Self::preset_author_key)) rather than the key the
+    /// document carries. Drops keys the installed app declares sensitive or
+    /// secret, and applies the rest according to `policy`.
+    /// A major version difference between the preset and the installed app,
+    /// and values that do not fit the app's settings schema (which are
+    /// skipped), are reported as warnings rather than errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Installed application identifier
+    /// * `user_id` - User identifier
+    /// * `preset` - Preset document to import
+    /// * `author_key` - Base64 preset key of the user the preset is expected from
+    /// * `policy` - Merge into or overwrite the existing settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed, the preset targets a
+    /// different app, uses an unsupported format version, was not signed by
+    /// `author_key`, or fails signature verification.
+    pub fn import_app_preset(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        preset: &PresetDocument,
+        author_key: &str,
+        policy: PresetImportPolicy,
+    ) -> Result<PresetImportResult> {
+        Self::verify_preset(preset, author_key)?;
+
+        if preset.app_id != app_id {
+            anyhow::bail!("Preset is for app {}, not {}", preset.app_id, app_id);
+        }
+
+        let app = installed_app(&self.sql_storage, app_id)?;
+        let sensitive = app.sensitive_config_keys();
+        let mut result = PresetImportResult::default();
+
+        if major_version(&preset.app_version) != major_version(app.version()) {
+            result.warnings.push(format!(
+                "Preset was exported from version {} but version {} is installed",
+                preset.app_version,
+                app.version()
+            ));
+        }
+
+        let schema = app.settings_schema();
+        let mut config = self.stored_app_config(app_id, user_id)?;
+        self.snapshot_if_due(&config)?;
+
+        if policy == PresetImportPolicy::Overwrite {
+            let existing: Vec<String> = config.settings().keys().cloned().collect();
+            for key in existing {
+                if !is_sensitive_key(&key, &sensitive) {
+                    config.remove_setting(&key);
+                }
+            }
+        }
+
+        for (key, value) in &preset.settings {
+            let misfit = schema
+                .and_then(|schema| schema.field(key))
+                .and_then(|field| field.check(value).err());
+            let secret = schema.is_some_and(|schema| schema.is_secret(key));
+            if secret || is_sensitive_key(key, &sensitive) {
+                result.filtered_keys.push(key.clone());
+            } else if let Some(message) = misfit {
+                result
+                    .warnings
+                    .push(format!("Skipped setting {}: {}", key, message));
+            } else {
+                config.set_setting(key.clone(), value.clone());
+                result.applied_keys.push(key.clone());
+            }
+        }
+
+        let encryption_key = Self::derive_user_config_key(user_id);
+        self.sql_storage
+            .set_app_config(app_id, user_id, &config, &encryption_key)?;
+        self.app_configs.invalidate(app_id, user_id);
+        self.publish_config_changed(app_id, user_id);
+
+        Ok(result)
+    }
+
+    /// Build the sync payload for a peer device
+    ///
+    /// `since` is the version of this device's configuration the peer last
+    /// acknowledged (its [`AppConfiguration::synced_version`] for `origin`),
+    /// or `None` for the initial sync. Only keys written after `since` are
+    /// sent unless the change set is too large.
+    pub fn app_config_sync_payload(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        origin: &str,
+        since: Option<u64>,
+    ) -> Result<SyncPayload> {
+        let config = self.stored_app_config(app_id, user_id)?;
+        Ok(diff::payload(&config, since, origin)?)
+    }
+
+    /// Merge a sync payload from a peer device into the stored configuration
+    ///
+    /// Nothing is written when the payload needs a full resync instead.
+    pub fn apply_app_config_sync(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        payload: &SyncPayload,
+    ) -> Result<ApplyOutcome> {
+        let mut config = self.stored_app_config(app_id, user_id)?;
+        let before = config.clone();
+        let outcome = diff::apply_payload(&mut config, payload)?;
+
+        if let ApplyOutcome::Applied { applied, .. } = outcome {
+            if applied > 0 {
+                self.snapshot_if_due(&before)?;
+            }
+            let encryption_key = Self::derive_user_config_key(user_id);
+            self.sql_storage
+                .set_app_config(app_id, user_id, &config, &encryption_key)?;
+            self.app_configs.invalidate(app_id, user_id);
+            if applied > 0 {
+                self.publish_config_changed(app_id, user_id);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    // Private helper methods
+
+    /// Check settings against the app's schema and write them, returning
+    /// the configuration's new version
+    fn write_app_config(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        settings: std::collections::HashMap<String, Value>,
+    ) -> Result<u64> {
+        self.require_unlocked("config.setAppConfig")?;
+
+        if let Some(schema) = self.app_settings_schema(app_id)? {
+            let errors = schema.check_values(&settings);
+            if !errors.is_empty() {
+                return Err(OsnovaError::InvalidSettings {
+                    app_id: app_id.to_string(),
+                    errors,
+                }
+                .into());
+            }
+        }
+
+        // Get existing config or create new one
+        let mut config = self.stored_app_config(app_id, user_id)?;
+        self.snapshot_if_due(&config)?;
+
+        // Update settings
+        for (key, value) in settings {
+            config.set_setting(&key, value);
+        }
+
+        // Use a per-user encryption key
+        let encryption_key = Self::derive_user_config_key(user_id);
+
+        // Save to database
+        self.sql_storage
+            .set_app_config(app_id, user_id, &config, &encryption_key)?;
+        self.app_configs.invalidate(app_id, user_id);
+
+        Ok(config.version())
+    }
+
+    /// Stored configuration of an app through the read cache
+    ///
+    /// Only instances on an event bus cache, since others would not hear
+    /// of writes made through other instances. Write paths read
+    /// [`stored_app_config`](Self::stored_app_config) instead, so they
+    /// always start from storage.
+    fn cached_app_config(&self, app_id: &str, user_id: &str) -> Result<AppConfiguration> {
+        if self.events.is_none() {
+            return self.stored_app_config(app_id, user_id);
+        }
+        self.require_unlocked("config.getAppConfig")?;
+        if !self.app_configs.is_configured() {
+            let settings = self.get_runtime_settings()?;
+            self.app_configs
+                .configure(settings.config_cache, settings.config_cache_entries);
+        }
+        self.app_configs
+            .get_or_load(app_id, user_id, || self.stored_app_config(app_id, user_id))
+    }
+
+    /// Stored configuration of an app, without settings schema defaults
+    fn stored_app_config(&self, app_id: &str, user_id: &str) -> Result<AppConfiguration> {
+        self.require_unlocked("config.getAppConfig")?;
+
+        // Use a per-user encryption key derived from user_id
+        // TODO: In production, derive from user's master key
+        let encryption_key = Self::derive_user_config_key(user_id);
+
+        match self
+            .sql_storage
+            .get_app_config(app_id, user_id, &encryption_key)?
+        {
+            Some(config) => Ok(config),
+            None => Ok(AppConfiguration::new(app_id, user_id)),
+        }
+    }
+
+    /// Snapshot a configuration about to be written, if enough was written
+    /// since the last snapshot or it is a day old
+    ///
+    /// Nothing is taken for a configuration unchanged since its last
+    /// snapshot.
+    fn snapshot_if_due(&self, config: &AppConfiguration) -> Result<()> {
+        let last = self
+            .sql_storage
+            .list_config_snapshots(config.app_id(), config.user_id())?
+            .pop();
+        let written = config
+            .version()
+            .saturating_sub(last.as_ref().map_or(0, |last| last.version));
+        if written == 0 {
+            return Ok(());
+        }
+
+        let now = self.clock.now_unix();
+        let stale = last.is_none_or(|last| {
+            now.saturating_sub(last.created_at) >= SNAPSHOT_INTERVAL_SECS
+        });
+        if written >= self.snapshot_every || stale {
+            self.take_snapshot(config, SnapshotReason::Scheduled)?;
+        }
+        Ok(())
+    }
+
+    /// Store a snapshot of a configuration against the previous one, then
+    /// prune the app's snapshots to their retention policy
+    fn take_snapshot(
+        &self,
+        config: &AppConfiguration,
+        reason: SnapshotReason,
+    ) -> Result<ConfigSnapshot> {
+        let (app_id, user_id) = (config.app_id(), config.user_id());
+        let encryption_key = Self::derive_user_config_key(user_id);
+        let contents = self
+            .sql_storage
+            .config_snapshot_contents(app_id, user_id, &encryption_key)?;
+        let previous = decode_chain(contents.iter().map(|(_, content)| content))?.pop();
+        let settings: Settings = config
+            .settings()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let now = self.clock.now_unix();
+        let id = self.sql_storage.insert_config_snapshot(
+            app_id,
+            user_id,
+            now,
+            config.version(),
+            reason,
+            &SnapshotContent::encode(previous.as_ref(), &settings),
+            &encryption_key,
+        )?;
+        self.prune_snapshots(app_id, user_id, now)?;
+
+        self.sql_storage
+            .list_config_snapshots(app_id, user_id)?
+            .into_iter()
+            .find(|snapshot| snapshot.id == id)
+            .context("Snapshot was pruned as soon as it was taken")
+    }
+
+    /// Apply the snapshot retention policy to the snapshots of an app
+    ///
+    /// Like [`crate::services::retention::apply`], expired snapshots go, then the oldest
+    /// while over the count or size limit. A snapshot whose predecessor
+    /// went is re-encoded against the one now before it.
+    fn prune_snapshots(&self, app_id: &str, user_id: &str, now: u64) -> Result<()> {
+        let policy = self.get_retention_policy(RetentionStore::ConfigSnapshots)?;
+        let rows = RetainedRows::ConfigSnapshots { app_id, user_id };
+        let cutoff = policy.max_age.map(|age| now.saturating_sub(age.as_secs()));
+        let mut remaining = self.sql_storage.retained_footprint(rows)?;
+        let mut removed = BTreeSet::new();
+        for item in self.sql_storage.list_retained(rows)? {
+            let expired = cutoff.is_some_and(|cutoff| item.timestamp < cutoff);
+            if expired || policy.over_size(&remaining) {
+                remaining.items -= 1;
+                remaining.bytes = remaining.bytes.saturating_sub(item.bytes);
+                removed.insert(item.id);
+            }
+        }
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let encryption_key = Self::derive_user_config_key(user_id);
+        let contents = self
+            .sql_storage
+            .config_snapshot_contents(app_id, user_id, &encryption_key)?;
+        let decoded = decode_chain(contents.iter().map(|(_, content)| content))?;
+        let mut previous: Option<&Settings> = None;
+        let mut rebase = false;
+        for ((id, _), settings) in contents.iter().zip(&decoded) {
+            if removed.contains(id) {
+                rebase = true;
+                continue;
+            }
+            if rebase {
+                let content = SnapshotContent::encode(previous, settings);
+                self.sql_storage
+                    .update_config_snapshot(*id, &content, &encryption_key)?;
+                rebase = false;
+            }
+            previous = Some(settings);
+        }
+
+        let ids: Vec<i64> = removed.into_iter().collect();
+        self.sql_storage.delete_config_snapshots(&ids)?;
+        Ok(())
+    }
+
+    /// Every stored secret setting of an app
+    fn stored_app_secrets(&self, app_id: &str, user_id: &str) -> Result<BTreeMap<String, Value>> {
+        let path = Self::secrets_path(app_id, user_id);
+        if !self.file_storage.exists(&path) {
+            return Ok(BTreeMap::new());
+        }
+        let data = self.file_storage.read_keyed(
+            &path,
+            self.app_secret_key("config.appSecrets")?,
+            KeySource::IdentityDerived,
+        )?;
+        serde_json::from_slice(&data).context("Failed to parse secret settings")
+    }
+
+    fn write_app_secrets(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        secrets: &BTreeMap<String, Value>,
+    ) -> Result<()> {
+        self.file_storage.write_classified(
+            &Self::secrets_path(app_id, user_id),
+            &serde_json::to_vec(secrets)?,
+            self.app_secret_key("config.setAppSecret")?,
+            DataClass::Secret,
+        )
+    }
+
+    /// Copy plainly stored values of fields a schema declares secret into
+    /// the secret store, before [`SettingsSchema::migrate`] drops them
+    fn move_secret_values(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        schema: &SettingsSchema,
+        config: &AppConfiguration,
+    ) -> Result<()> {
+        let moved: BTreeMap<String, Value> = schema
+            .fields()
+            .filter(|field| field.secret)
+            .filter_map(|field| {
+                config
+                    .get_setting(&field.key)
+                    .filter(|value| field.check(value).is_ok())
+                    .map(|value| (field.key.clone(), value.clone()))
+            })
+            .collect();
+        if moved.is_empty() {
+            return Ok(());
+        }
+
+        let mut secrets = self.stored_app_secrets(app_id, user_id)?;
+        for (key, value) in moved {
+            secrets.entry(key).or_insert(value);
+        }
+        self.write_app_secrets(app_id, user_id, &secrets)
+    }
+
+    /// Where the secret settings of an app are kept
+    fn secrets_path(app_id: &str, user_id: &str) -> PathBuf {
+        Path::new(SECRETS_DIR)
+            .join(user_id)
+            .join(format!("{}.json", app_id))
+    }
+
+    /// Record a secret setting changing, naming the key but not the value
+    fn audit_secret(&self, action: AuditAction, app_id: &str, key: &str) -> Result<()> {
+        if let Some(audit) = &self.audit {
+            audit.append(action, serde_json::json!({ "appId": app_id, "key": key }))?;
+        }
+        Ok(())
+    }
+
+    /// Settings schema of an app, or `None` if it declares none or is not
+    /// installed
+    fn app_settings_schema(&self, app_id: &str) -> Result<Option<SettingsSchema>> {
+        Ok(self
+            .sql_storage
+            .get_application(app_id)?
+            .and_then(|app| app.settings_schema().cloned()))
+    }
+
+    /// Refuse an operation on per-user data while the context is locked
+    fn require_unlocked(&self, operation: &str) -> Result<()> {
+        if let Some(gate) = &self.unlock {
+            gate.require(operation)?;
+        }
+        Ok(())
+    }
+
+    fn publish_config_changed(&self, app_id: &str, user_id: &str) {
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::ConfigChanged {
+                app_id: app_id.to_string(),
+                user_id: user_id.to_string(),
+            });
+        }
+    }
+
+    /// Load system configuration from encrypted file storage
+    fn load_system_config(&self) -> Result<SystemConfig> {
+        if !self.file_storage.exists(&self.system_config_path) {
+            return Ok(SystemConfig::new());
+        }
+
+        let encrypted_data = self
+            .file_storage
+            .read_keyed(
+                &self.system_config_path,
+                &self.encryption_key,
+                KeySource::StaticDerived,
+            )
+            .context("Failed to read system config")?;
+
+        let config: SystemConfig = serde_json::from_slice(&encrypted_data)
+            .context("Failed to deserialize system config")?;
+
+        Ok(config)
+    }
+
+    /// Save system configuration to encrypted file storage
+    fn save_system_config(&self, config: &SystemConfig) -> Result<()> {
+        let config_json =
+            serde_json::to_vec(config).context("Failed to serialize system config")?;
+
+        self.file_storage
+            .write_classified(
+                &self.system_config_path,
+                &config_json,
+                &self.encryption_key,
+                DataClass::Preferences,
+            )
+            .context("Failed to write system config")?;
+
+        Ok(())
+    }
+
+    /// Check a preset's format version, and its signature against the
+    /// author's known key
+    fn verify_preset(preset: &PresetDocument, author_key: &str) -> Result<()> {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        if !(1..=PRESET_FORMAT_VERSION).contains(&preset.format_version) {
+            anyhow::bail!(
+                "Unsupported preset format version {}",
+                preset.format_version
+            );
+        }
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let public_key: [u8; 32] = engine
+            .decode(author_key)
+            .context("Invalid preset author key encoding")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid preset author key length"))?;
+        if preset.public_key != author_key {
+            anyhow::bail!("Preset was not signed by the expected author");
+        }
+        let signature: [u8; 64] = engine
+            .decode(&preset.signature)
+            .context("Invalid preset signature encoding")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid preset signature length"))?;
+
+        let verifying_key =
+            VerifyingKey::from_bytes(&public_key).context("Invalid preset author key")?;
+        verifying_key
+            .verify(
+                &preset.signing_payload()?,
+                &Signature::from_bytes(&signature),
+            )
+            .context("Preset signature verification failed")?;
+
+        Ok(())
+    }
+
+    /// Key exported presets are signed with
+    fn preset_signing_key(&self, operation: &str) -> Result<&ed25519_dalek::SigningKey> {
+        self.preset_key.as_ref().ok_or_else(|| {
+            NotYetUnlocked {
+                operation: operation.to_string(),
+            }
+            .into()
+        })
+    }
+
+    /// Derive a deterministic encryption key for system config
+    ///
+    /// TODO: In production, integrate with platform keystore
+    pub(crate) fn derive_system_key() -> [u8; 32] {
+        use blake3::Hasher;
+        let mut hasher = Hasher::new();
+        hasher.update(b"osnova-system-config-key-v1");
+        let hash = hasher.finalize();
+        let mut key = [0u8; 32];
+        key.copy_from_slice(hash.as_bytes());
+        key
+    }
+
+    /// Derive a per-user encryption key for app configurations
     ///
     /// TODO: In production, derive from user's master key
-    fn derive_user_config_key(user_id: &str) -> [u8; 32] {
+    pub(crate) fn derive_user_config_key(user_id: &str) -> [u8; 32] {
         use blake3::Hasher;
         let mut hasher = Hasher::new();
         hasher.update(b"osnova-user-config-key-v1:");
@@ -377,176 +2050,1301 @@ impl ConfigService {
         key.copy_from_slice(hash.as_bytes());
         key
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+    /// Derive the encryption key for secret app settings from the user's
+    /// identity
+    pub(crate) fn derive_app_secret_key(identity: &RootIdentity) -> Result<[u8; 32]> {
+        Ok(identity.derive_component_key(SECRET_KEY_COMPONENT, 0, KeyPurpose::Encryption)?)
+    }
+
+    /// Key secret settings are encrypted with
+    fn app_secret_key(&self, operation: &str) -> Result<&[u8; 32]> {
+        self.secret_key.as_ref().ok_or_else(|| {
+            NotYetUnlocked {
+                operation: operation.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Check a settings key against sensitive key patterns (`prefix*` wildcards)
+fn is_sensitive_key(key: &str, sensitive: &[String]) -> bool {
+    sensitive
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == pattern,
+        })
+}
+
+/// Major component of a semver version string
+fn major_version(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::time::{Clock, MockClock};
+    use tempfile::TempDir;
+
+    fn create_test_service() -> Result<(ConfigService, OsnovaContext)> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let service = ConfigService::from_context(&context)?.with_identity(&RootIdentity::generate()?)?;
+        Ok((service, context))
+    }
+
+    /// Preset signing key of another user
+    fn other_author() -> Result<ed25519_dalek::SigningKey> {
+        let seed = RootIdentity::generate()?.derive_component_key(
+            PRESET_KEY_COMPONENT,
+            0,
+            KeyPurpose::Signing,
+        )?;
+        Ok(ed25519_dalek::SigningKey::from_bytes(&seed))
+    }
+
+    #[test]
+    fn test_get_launcher_manifest_not_configured() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let manifest = service.get_launcher_manifest()?;
+        assert!(manifest.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_get_launcher_manifest() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        service.set_launcher_manifest("xor://test-manifest-address")?;
+
+        let manifest = service.get_launcher_manifest()?;
+        assert_eq!(manifest, Some("xor://test-manifest-address".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_get_server() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        service.set_server("https://server.example.com")?;
+
+        let server = service.get_server()?;
+        assert_eq!(server, Some("https://server.example.com".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_overrides_shadow_stored_settings() -> Result<()> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let stored = ConfigService::from_context(&context)?;
+        stored.set_server("https://stored.example.com")?;
+        stored.set_launcher_manifest("ant://stored-launcher")?;
+
+        let session =
+            ConfigService::from_context(&context)?.with_session_overrides(SessionOverrides {
+                launcher_manifest: Some("ant://session-launcher".to_string()),
+                server_address: Some(None),
+            });
+        assert_eq!(session.get_server()?, None);
+        assert_eq!(
+            session.get_launcher_manifest()?,
+            Some("ant://session-launcher".to_string())
+        );
+
+        // Nothing was written back
+        assert_eq!(
+            stored.get_server()?,
+            Some("https://stored.example.com".to_string())
+        );
+        stored.clear_server()?;
+        assert_eq!(stored.get_server()?, None);
+        assert_eq!(
+            stored.get_launcher_manifest()?,
+            Some("ant://stored-launcher".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_retention_days() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert_eq!(
+            service.get_trash_retention_days()?,
+            DEFAULT_TRASH_RETENTION_DAYS
+        );
+
+        service.set_trash_retention_days(7)?;
+        assert_eq!(service.get_trash_retention_days()?, 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_free_disk_bytes() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert_eq!(service.get_min_free_disk_bytes()?, DEFAULT_MIN_FREE_BYTES);
+
+        service.set_min_free_disk_bytes(1024)?;
+        assert_eq!(service.get_min_free_disk_bytes()?, 1024);
+        assert_eq!(service.disk_guard()?.min_free(), 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention_policy_per_store() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
 
-    fn create_test_service() -> Result<(ConfigService, TempDir)> {
-        let temp_dir = TempDir::new()?;
-        let service = ConfigService::new(temp_dir.path())?;
-        Ok((service, temp_dir))
+        assert_eq!(
+            service.get_retention_policy(RetentionStore::CrashReports)?,
+            RetentionStore::CrashReports.default_policy()
+        );
+
+        service.set_retention_policy(RetentionStore::CrashReports, Some(Policy::count(5)))?;
+        assert_eq!(
+            service.get_retention_policy(RetentionStore::CrashReports)?,
+            Policy::count(5)
+        );
+        // Other stores keep their defaults
+        assert_eq!(
+            service.get_retention_policy(RetentionStore::Notifications)?,
+            RetentionStore::Notifications.default_policy()
+        );
+
+        service.set_retention_policy(RetentionStore::CrashReports, None)?;
+        assert_eq!(
+            service.get_retention_policy(RetentionStore::CrashReports)?,
+            RetentionStore::CrashReports.default_policy()
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn test_get_launcher_manifest_not_configured() -> Result<()> {
+    fn test_chaos_profile_respects_debug_gate() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        let profile = ChaosProfile::new("flaky")
+            .with_rule(crate::storage::chaos::ChaosRule::new("cache").with_error_rate(0.1));
+
+        let result = service.set_chaos_profile(Some(profile.clone()));
+        if DebugGate::chaos_allowed() {
+            result?;
+            assert_eq!(service.get_chaos_profile()?, Some(profile));
+        } else {
+            assert!(result.is_err());
+            assert_eq!(service.get_chaos_profile()?, None);
+        }
 
-        let manifest = service.get_launcher_manifest()?;
-        assert!(manifest.is_none());
+        // Turning chaos mode off always works
+        service.set_chaos_profile(None)?;
+        assert_eq!(service.get_chaos_profile()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_policy_overrides_default() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert_eq!(
+            service.get_update_policy("com.test.app")?,
+            UpdatePolicy::Manual
+        );
+
+        service.set_default_update_policy(UpdatePolicy::DownloadOnly)?;
+        service.set_app_update_policy("com.test.app", Some(UpdatePolicy::AutoApply))?;
+        assert_eq!(
+            service.get_update_policy("com.test.app")?,
+            UpdatePolicy::AutoApply
+        );
+        assert_eq!(
+            service.get_update_policy("com.other")?,
+            UpdatePolicy::DownloadOnly
+        );
+
+        service.set_app_update_policy("com.test.app", None)?;
+        assert_eq!(
+            service.get_update_policy("com.test.app")?,
+            UpdatePolicy::DownloadOnly
+        );
+
+        assert_eq!(
+            service.get_update_check_interval_secs()?,
+            DEFAULT_UPDATE_CHECK_INTERVAL_SECS
+        );
+        service.set_update_check_interval_secs(600)?;
+        assert_eq!(service.get_update_check_interval_secs()?, 600);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_policy() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert_eq!(service.get_fetch_policy()?, FetchPolicy::default());
+
+        let mut policy = FetchPolicy::default().with_private_host("catalog.lan");
+        policy.spki_pins.insert(
+            "https://catalog.example".to_string(),
+            vec!["eTIgfAyIQYo2OuXx5Xv1wOcAQxK6+lQLmWYyq2M4+y4=".to_string()],
+        );
+        service.set_fetch_policy(policy.clone())?;
+        assert_eq!(service.get_fetch_policy()?, policy);
+
+        let refused = FetchPolicy {
+            max_connections_per_origin: 0,
+            ..FetchPolicy::default()
+        };
+        assert!(service.set_fetch_policy(refused).is_err());
+        assert_eq!(service.get_fetch_policy()?, policy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_offline_mode() -> Result<()> {
+        let (service, context) = create_test_service()?;
+
+        assert!(!service.get_offline_mode()?);
+        service.set_offline_mode(true)?;
+        assert!(service.get_offline_mode()?);
+
+        // Persisted across restarts
+        let reopened = ConfigService::from_context(&context)?;
+        assert!(reopened.get_offline_mode()?);
+        reopened.set_offline_mode(false)?;
+        assert!(!service.get_offline_mode()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_settings() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let settings = service.get_runtime_settings()?;
+        assert!(!settings.warm_pool);
+        assert_eq!(settings, RuntimeSettings::default());
+
+        let settings = RuntimeSettings {
+            warm_pool: true,
+            warm_pool_size: 2,
+            compression: crate::storage::CompressionSettings {
+                level: 19,
+                threshold: 64 * 1024,
+                ..Default::default()
+            },
+            ..RuntimeSettings::default()
+        };
+        service.set_runtime_settings(settings.clone())?;
+        assert_eq!(service.get_runtime_settings()?, settings);
+
+        // Settings saved before compression was configurable still load
+        let stored = serde_json::json!({ "warmPool": true });
+        let settings: RuntimeSettings = serde_json::from_value(stored)?;
+        assert_eq!(settings.compression, Default::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bandwidth_policy() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert_eq!(service.get_bandwidth_policy()?, BandwidthPolicy::Unlimited);
+        assert!(!service.get_metered_mode()?);
+
+        let policy = BandwidthPolicy::DailyLimit {
+            bytes_per_day: 1024,
+        };
+        service.set_bandwidth_policy(policy)?;
+        service.set_metered_mode(true)?;
+
+        assert_eq!(service.get_bandwidth_policy()?, policy);
+        assert!(service.get_metered_mode()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_app_config_new() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let config = service.get_app_config("com.test.app", "user-123")?;
+
+        assert_eq!(config.app_id(), "com.test.app");
+        assert_eq!(config.user_id(), "user-123");
+        assert!(config.settings().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_get_app_config() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        // Create application first (required for foreign key)
+        let app = crate::models::application::OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test application",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        settings.insert("language".to_string(), serde_json::json!("en"));
+
+        service.set_app_config("com.test.app", "user-123", settings)?;
+
+        let config = service.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+        assert_eq!(
+            config.get_setting("language"),
+            Some(&serde_json::json!("en"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_config_sync_between_devices() -> Result<()> {
+        let (laptop, _laptop_temp) = create_test_service()?;
+        let (phone, _phone_temp) = create_test_service()?;
+        for service in [&laptop, &phone] {
+            let app = crate::models::application::OsnovaApplication::new(
+                "com.test.app",
+                "Test App",
+                "1.0.0",
+                "https://icon.url",
+                "Test application",
+                vec![],
+            )?;
+            service.sql_storage.upsert_application(&app)?;
+        }
+
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        laptop.set_app_config("com.test.app", "user-123", settings)?;
+
+        // Initial sync sends everything
+        let payload = laptop.app_config_sync_payload("com.test.app", "user-123", "laptop", None)?;
+        assert!(matches!(payload, SyncPayload::Full { .. }));
+        phone.apply_app_config_sync("com.test.app", "user-123", &payload)?;
+
+        // Afterwards only the changed key travels
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("language".to_string(), serde_json::json!("en"));
+        laptop.set_app_config("com.test.app", "user-123", settings)?;
+
+        let acked = phone
+            .get_app_config("com.test.app", "user-123")?
+            .synced_version("laptop");
+        let payload =
+            laptop.app_config_sync_payload("com.test.app", "user-123", "laptop", Some(acked))?;
+        match &payload {
+            SyncPayload::Changes(changeset) => assert_eq!(changeset.changes.len(), 1),
+            other => panic!("expected a changeset, got {:?}", other),
+        }
+        phone.apply_app_config_sync("com.test.app", "user-123", &payload)?;
+
+        assert_eq!(
+            phone.get_app_config("com.test.app", "user-123")?.settings(),
+            laptop
+                .get_app_config("com.test.app", "user-123")?
+                .settings()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_app_config() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        // Create application first (required for foreign key)
+        let app = crate::models::application::OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test application",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        // Set initial config
+        let mut settings1 = std::collections::HashMap::new();
+        settings1.insert("theme".to_string(), serde_json::json!("dark"));
+        service.set_app_config("com.test.app", "user-123", settings1)?;
+
+        // Update config with new settings
+        let mut settings2 = std::collections::HashMap::new();
+        settings2.insert("language".to_string(), serde_json::json!("en"));
+        service.set_app_config("com.test.app", "user-123", settings2)?;
+
+        // Verify both settings exist
+        let config = service.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+        assert_eq!(
+            config.get_setting("language"),
+            Some(&serde_json::json!("en"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_config_per_user() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        // Create application first (required for foreign key)
+        let app = crate::models::application::OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test application",
+            vec![],
+        )?;
+        service.sql_storage.upsert_application(&app)?;
+
+        // Set config for user1
+        let mut settings1 = std::collections::HashMap::new();
+        settings1.insert("theme".to_string(), serde_json::json!("dark"));
+        service.set_app_config("com.test.app", "user-1", settings1)?;
+
+        // Set config for user2
+        let mut settings2 = std::collections::HashMap::new();
+        settings2.insert("theme".to_string(), serde_json::json!("light"));
+        service.set_app_config("com.test.app", "user-2", settings2)?;
+
+        // Verify configs are separate
+        let config1 = service.get_app_config("com.test.app", "user-1")?;
+        let config2 = service.get_app_config("com.test.app", "user-2")?;
+
+        assert_eq!(
+            config1.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
+        );
+        assert_eq!(
+            config2.get_setting("theme"),
+            Some(&serde_json::json!("light"))
+        );
+
+        Ok(())
+    }
+
+    fn install_app(service: &ConfigService, version: &str, sensitive: Value) -> Result<()> {
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("sensitiveConfigKeys".to_string(), sensitive);
+        let app = OsnovaApplication::new(
+            "com.test.editor",
+            "Test Editor",
+            version,
+            "https://icon.url",
+            "Test editor",
+            vec![],
+        )?
+        .with_metadata(metadata);
+        service.sql_storage.upsert_application(&app)?;
+        Ok(())
+    }
+
+    fn editor_settings() -> std::collections::HashMap<String, Value> {
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        settings.insert("fontSize".to_string(), serde_json::json!(14));
+        settings.insert("tabWidth".to_string(), serde_json::json!(4));
+        settings.insert("apiToken".to_string(), serde_json::json!("secret"));
+        settings.insert("sync.password".to_string(), serde_json::json!("hunter2"));
+        settings
+    }
+
+    #[test]
+    fn test_export_app_preset_selected_keys() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let service = service.with_clock(Arc::new(MockClock::new(1_700_000_000)));
+        install_app(&service, "1.2.0", serde_json::json!(["apiToken", "sync.*"]))?;
+        service.set_app_config("com.test.editor", "user-123", editor_settings())?;
+
+        let keys = [
+            "theme".to_string(),
+            "fontSize".to_string(),
+            "apiToken".to_string(),
+            "sync.password".to_string(),
+            "missing".to_string(),
+        ];
+        let preset = service.export_app_preset("com.test.editor", "user-123", &keys)?;
+
+        assert_eq!(preset.format_version, PRESET_FORMAT_VERSION);
+        assert_eq!(preset.app_id, "com.test.editor");
+        assert_eq!(preset.app_version, "1.2.0");
+        assert_eq!(preset.created_at, 1_700_000_000);
+        // Unselected, sensitive and missing keys are left out
+        assert_eq!(
+            preset.settings.keys().collect::<Vec<_>>(),
+            vec!["fontSize", "theme"]
+        );
+        assert!(ConfigService::verify_preset(&preset, &service.preset_author_key()?).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_app_preset_filters_sensitive_keys() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_app(&service, "1.0.0", serde_json::json!(["apiToken"]))?;
+
+        // A hand-built preset carrying a secret, signed by another user
+        let mut preset = PresetDocument {
+            format_version: PRESET_FORMAT_VERSION,
+            app_id: "com.test.editor".to_string(),
+            app_version: "1.0.0".to_string(),
+            settings: BTreeMap::from([
+                ("theme".to_string(), serde_json::json!("light")),
+                ("apiToken".to_string(), serde_json::json!("stolen")),
+            ]),
+            created_at: 0,
+            public_key: String::new(),
+            signature: String::new(),
+        };
+        let signing_key = other_author()?;
+        preset.public_key = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.verifying_key().to_bytes());
+        use ed25519_dalek::Signer;
+        preset.signature = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.sign(&preset.signing_payload()?).to_bytes());
+
+        let author = preset.public_key.clone();
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &author,
+            PresetImportPolicy::Merge,
+        )?;
+
+        assert_eq!(result.applied_keys, vec!["theme"]);
+        assert_eq!(result.filtered_keys, vec!["apiToken"]);
+        assert!(result.warnings.is_empty());
+
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("light"))
+        );
+        assert!(config.get_setting("apiToken").is_none());
 
         Ok(())
     }
 
     #[test]
-    fn test_set_and_get_launcher_manifest() -> Result<()> {
+    fn test_import_version_1_preset() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+
+        // Signed over the declaration-order payload by an older release
+        let mut preset = PresetDocument {
+            format_version: 1,
+            app_id: "com.test.editor".to_string(),
+            app_version: "1.0.0".to_string(),
+            settings: BTreeMap::from([("fontSize".to_string(), serde_json::json!(12.0))]),
+            created_at: 0,
+            public_key: String::new(),
+            signature: String::new(),
+        };
+        let signing_key = other_author()?;
+        preset.public_key = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.verifying_key().to_bytes());
+        let legacy_payload = format!(
+            concat!(
+                r#"{{"formatVersion":1,"appId":"com.test.editor","appVersion":"1.0.0","#,
+                r#""settings":{{"fontSize":12.0}},"createdAt":0,"publicKey":"{}"}}"#
+            ),
+            preset.public_key
+        );
+        use ed25519_dalek::Signer;
+        preset.signature = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.sign(legacy_payload.as_bytes()).to_bytes());
+
+        let author = preset.public_key.clone();
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &author,
+            PresetImportPolicy::Merge,
+        )?;
+        assert_eq!(result.applied_keys, vec!["fontSize"]);
 
-        service.set_launcher_manifest("xor://test-manifest-address")?;
-
-        let manifest = service.get_launcher_manifest()?;
-        assert_eq!(manifest, Some("xor://test-manifest-address".to_string()));
+        // The same signature does not verify as a version 2 document
+        preset.format_version = 2;
+        assert!(ConfigService::verify_preset(&preset, &author).is_err());
+        preset.format_version = 3;
+        assert!(ConfigService::verify_preset(&preset, &author).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_set_and_get_server() -> Result<()> {
+    fn test_import_app_preset_version_mismatch_warning() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        install_app(&service, "1.4.0", serde_json::json!([]))?;
+        service.set_app_config("com.test.editor", "user-123", editor_settings())?;
+
+        let keys = ["theme".to_string()];
+        let preset = service.export_app_preset("com.test.editor", "user-123", &keys)?;
+
+        // Same major version imports cleanly
+        install_app(&service, "1.9.0", serde_json::json!([]))?;
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-456",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        )?;
+        assert!(result.warnings.is_empty());
+
+        // Major version change is surfaced as a warning
+        install_app(&service, "2.0.0", serde_json::json!([]))?;
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-456",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        )?;
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.applied_keys, vec!["theme"]);
 
-        service.set_server("https://server.example.com")?;
+        Ok(())
+    }
 
-        let server = service.get_server()?;
-        assert_eq!(server, Some("https://server.example.com".to_string()));
+    #[test]
+    fn test_import_app_preset_rejects_tampering() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        service.set_app_config("com.test.editor", "user-123", editor_settings())?;
+
+        let keys = ["theme".to_string()];
+        let mut preset = service.export_app_preset("com.test.editor", "user-123", &keys)?;
+        preset
+            .settings
+            .insert("theme".to_string(), serde_json::json!("light"));
+
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        );
+        assert!(result.is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_get_app_config_new() -> Result<()> {
-        let (service, _temp) = create_test_service()?;
+    fn test_import_app_preset_rejects_other_authors() -> Result<()> {
+        use ed25519_dalek::Signer;
+
+        let (service, context) = create_test_service()?;
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        service.set_app_config("com.test.editor", "user-123", editor_settings())?;
+        let keys = ["theme".to_string()];
+        let genuine = service.export_app_preset("com.test.editor", "user-123", &keys)?;
+        let author = service.preset_author_key()?;
+
+        // A forger re-signs the preset with their own key and embeds it
+        let forger = other_author()?;
+        let mut forged = genuine.clone();
+        forged
+            .settings
+            .insert("theme".to_string(), serde_json::json!("light"));
+        forged.public_key = base64::engine::general_purpose::STANDARD
+            .encode(forger.verifying_key().to_bytes());
+        forged.signature = base64::engine::general_purpose::STANDARD
+            .encode(forger.sign(&forged.signing_payload()?).to_bytes());
+        let result = service.import_app_preset(
+            "com.test.editor",
+            "user-456",
+            &forged,
+            &author,
+            PresetImportPolicy::Merge,
+        );
+        assert!(result.is_err());
 
-        let config = service.get_app_config("com.test.app", "user-123")?;
+        // Claiming the expected author's key does not help without their signature
+        forged.public_key = author.clone();
+        assert!(ConfigService::verify_preset(&forged, &author).is_err());
 
-        assert_eq!(config.app_id(), "com.test.app");
-        assert_eq!(config.user_id(), "user-123");
-        assert!(config.settings().is_empty());
+        // Without an identity there is no key to export with
+        let locked = ConfigService::from_context(&context)?;
+        let err = locked
+            .export_app_preset("com.test.editor", "user-123", &keys)
+            .unwrap_err();
+        assert!(err.downcast_ref::<NotYetUnlocked>().is_some());
 
         Ok(())
     }
 
     #[test]
-    fn test_set_and_get_app_config() -> Result<()> {
+    fn test_import_app_preset_merge_vs_overwrite() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        install_app(&service, "1.0.0", serde_json::json!(["apiToken"]))?;
+        service.set_app_config("com.test.editor", "user-123", editor_settings())?;
+
+        let mut shared = std::collections::HashMap::new();
+        shared.insert("theme".to_string(), serde_json::json!("solarized"));
+        service.set_app_config("com.test.editor", "user-456", shared)?;
+        let keys = ["theme".to_string()];
+        let preset = service.export_app_preset("com.test.editor", "user-456", &keys)?;
+
+        // Merge keeps the other settings
+        service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        )?;
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("solarized"))
+        );
+        assert_eq!(config.get_setting("fontSize"), Some(&serde_json::json!(14)));
+
+        // Overwrite drops them, except for sensitive keys
+        service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Overwrite,
+        )?;
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            config.get_setting("theme"),
+            Some(&serde_json::json!("solarized"))
+        );
+        assert!(config.get_setting("fontSize").is_none());
+        assert_eq!(
+            config.get_setting("apiToken"),
+            Some(&serde_json::json!("secret"))
+        );
 
-        // Create application first (required for foreign key)
-        let app = crate::models::application::OsnovaApplication::new(
-            "com.test.app",
-            "Test App",
+        Ok(())
+    }
+
+    fn install_with_settings(service: &ConfigService, schema: Value) -> Result<()> {
+        let app = OsnovaApplication::new(
+            "com.test.reader",
+            "Test Reader",
             "1.0.0",
             "https://icon.url",
-            "Test application",
+            "Test reader",
             vec![],
-        )?;
+        )?
+        .with_settings_schema(serde_json::from_value(serde_json::json!({
+            "sections": [{"id": "display", "label": "Display", "fields": schema}]
+        }))?);
         service.sql_storage.upsert_application(&app)?;
+        Ok(())
+    }
+
+    fn reader_fields() -> Value {
+        serde_json::json!([
+            {"key": "fontSize", "label": "Font size", "type": "number",
+             "min": 8, "max": 32, "default": 14},
+            {"key": "theme", "label": "Theme", "type": "enum", "default": "light",
+             "options": [{"value": "light", "label": "Light"}, {"value": "dark", "label": "Dark"}]}
+        ])
+    }
+
+    #[test]
+    fn test_settings_schema_enforced_on_write() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_with_settings(&service, reader_fields())?;
 
         let mut settings = std::collections::HashMap::new();
+        settings.insert("fontSize".to_string(), serde_json::json!(64));
+        settings.insert("theme".to_string(), serde_json::json!("sepia"));
+        settings.insert("lastOpened".to_string(), serde_json::json!("book.epub"));
+        let err = service
+            .set_app_config("com.test.reader", "user-123", settings.clone())
+            .unwrap_err();
+        match err.downcast_ref::<OsnovaError>() {
+            Some(OsnovaError::InvalidSettings { app_id, errors }) => {
+                assert_eq!(app_id, "com.test.reader");
+                let keys: Vec<&str> = errors.iter().map(|e| e.key.as_str()).collect();
+                assert_eq!(keys, ["fontSize", "theme"]);
+                assert_eq!(errors[0].message, "must be between 8 and 32");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        // Nothing was written, not even the valid undeclared key
+        let config = service.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(config.get_setting("lastOpened"), None);
+
+        settings.insert("fontSize".to_string(), serde_json::json!(18));
         settings.insert("theme".to_string(), serde_json::json!("dark"));
-        settings.insert("language".to_string(), serde_json::json!("en"));
+        service.set_app_config("com.test.reader", "user-123", settings)?;
+        let config = service.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(config.get_setting("fontSize"), Some(&serde_json::json!(18)));
 
-        service.set_app_config("com.test.app", "user-123", settings)?;
+        // Apps without a schema are not checked
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        let mut anything = std::collections::HashMap::new();
+        anything.insert("fontSize".to_string(), serde_json::json!("huge"));
+        service.set_app_config("com.test.editor", "user-123", anything)?;
 
-        let config = service.get_app_config("com.test.app", "user-123")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_defaults_on_first_read() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_with_settings(&service, reader_fields())?;
+
+        let config = service.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(config.get_setting("fontSize"), Some(&serde_json::json!(14)));
         assert_eq!(
             config.get_setting("theme"),
-            Some(&serde_json::json!("dark"))
+            Some(&serde_json::json!("light"))
         );
+        assert_eq!(config.version(), 0);
+
+        // Writing one setting does not store the other's default
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("theme".to_string(), serde_json::json!("dark"));
+        service.set_app_config("com.test.reader", "user-123", settings)?;
+        let stored = service.stored_app_config("com.test.reader", "user-123")?;
+        assert_eq!(stored.get_setting("fontSize"), None);
+        let config = service.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(config.get_setting("fontSize"), Some(&serde_json::json!(14)));
         assert_eq!(
-            config.get_setting("language"),
-            Some(&serde_json::json!("en"))
+            config.get_setting("theme"),
+            Some(&serde_json::json!("dark"))
         );
 
         Ok(())
     }
 
     #[test]
-    fn test_update_app_config() -> Result<()> {
+    fn test_settings_payload() -> Result<()> {
         let (service, _temp) = create_test_service()?;
+        install_with_settings(&service, reader_fields())?;
 
-        // Create application first (required for foreign key)
-        let app = crate::models::application::OsnovaApplication::new(
-            "com.test.app",
-            "Test App",
-            "1.0.0",
-            "https://icon.url",
-            "Test application",
-            vec![],
-        )?;
-        service.sql_storage.upsert_application(&app)?;
+        let mut settings = std::collections::HashMap::new();
+        settings.insert("fontSize".to_string(), serde_json::json!(20));
+        settings.insert("lastOpened".to_string(), serde_json::json!("book.epub"));
+        service.set_app_config("com.test.reader", "user-123", settings)?;
+
+        let payload = service.settings_payload("com.test.reader", "user-123")?;
+        assert_eq!(payload.app_id, "com.test.reader");
+        assert_eq!(payload.schema, service.settings_schema("com.test.reader")?);
+        // Declared fields only, defaults included
+        assert_eq!(
+            serde_json::to_value(&payload.values)?,
+            serde_json::json!({"fontSize": 20, "theme": "light"})
+        );
 
-        // Set initial config
-        let mut settings1 = std::collections::HashMap::new();
-        settings1.insert("theme".to_string(), serde_json::json!("dark"));
-        service.set_app_config("com.test.app", "user-123", settings1)?;
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        let payload = service.settings_payload("com.test.editor", "user-123")?;
+        assert!(payload.schema.is_none());
+        assert!(payload.values.is_empty());
+        assert!(service
+            .settings_payload("com.test.missing", "user-123")
+            .is_err());
 
-        // Update config with new settings
-        let mut settings2 = std::collections::HashMap::new();
-        settings2.insert("language".to_string(), serde_json::json!("en"));
-        service.set_app_config("com.test.app", "user-123", settings2)?;
+        Ok(())
+    }
 
-        // Verify both settings exist
-        let config = service.get_app_config("com.test.app", "user-123")?;
+    fn secret_fields() -> Value {
+        let mut fields = reader_fields();
+        fields.as_array_mut().unwrap().push(serde_json::json!(
+            {"key": "apiToken", "label": "API token", "type": "string", "secret": true}
+        ));
+        fields
+    }
+
+    fn audited(audit: &AuditLog, action: AuditAction) -> Result<usize> {
+        let filter = crate::audit::AuditFilter {
+            action: Some(action),
+            ..Default::default()
+        };
+        Ok(audit.list(&filter, Default::default())?.total)
+    }
+
+    #[test]
+    fn test_secret_settings_are_write_only() -> Result<()> {
+        let temp = TempDir::new()?;
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &crate::models::identity::RootIdentity::generate()?,
+            "user-123",
+        )?);
+        let (service, context) = create_test_service()?;
+        let service = service.with_audit(audit.clone());
+        install_with_settings(&service, secret_fields())?;
+        let app_id = "com.test.reader";
+
+        service.set_app_secret(app_id, "user-123", "apiToken", serde_json::json!("t0ken"))?;
+        assert_eq!(audited(&audit, AuditAction::AppSecretSet)?, 1);
+        let entries = audit.list(&Default::default(), Default::default())?;
+        assert!(!serde_json::to_string(&entries)?.contains("t0ken"));
+
+        // Ordinary reads never see the value; the payload only says it is set
+        let config = service.get_app_config(app_id, "user-123")?;
+        assert_eq!(config.get_setting("apiToken"), None);
+        let payload = service.settings_payload(app_id, "user-123")?;
+        assert!(!payload.values.contains_key("apiToken"));
+        assert_eq!(payload.secrets_set, vec!["apiToken"]);
+        assert!(!serde_json::to_string(&payload)?.contains("t0ken"));
         assert_eq!(
-            config.get_setting("theme"),
-            Some(&serde_json::json!("dark"))
+            service.app_secrets(app_id, "user-123")?,
+            BTreeMap::from([("apiToken".to_string(), serde_json::json!("t0ken"))])
         );
+        let path = ConfigService::secrets_path(app_id, "user-123");
         assert_eq!(
-            config.get_setting("language"),
-            Some(&serde_json::json!("en"))
+            service.file_storage.classification(&path)?,
+            Some(DataClass::Secret)
+        );
+
+        // The key comes from the identity: another identity cannot open
+        // them and no identity cannot reach them
+        let stranger =
+            ConfigService::from_context(&context)?.with_identity(&RootIdentity::generate()?)?;
+        assert!(stranger.app_secrets(app_id, "user-123").is_err());
+        let locked = ConfigService::from_context(&context)?;
+        let err = locked.app_secrets(app_id, "user-123").unwrap_err();
+        assert!(err.downcast_ref::<NotYetUnlocked>().is_some());
+
+        // Secrets cannot be written as ordinary settings, nor ordinary
+        // settings as secrets
+        let settings =
+            std::collections::HashMap::from([("apiToken".to_string(), serde_json::json!("other"))]);
+        assert!(service
+            .set_app_config(app_id, "user-123", settings)
+            .is_err());
+        assert!(service
+            .set_app_secret(app_id, "user-123", "theme", serde_json::json!("dark"))
+            .is_err());
+        assert!(service
+            .set_app_secret(app_id, "user-123", "apiToken", serde_json::json!(7))
+            .is_err());
+
+        // Presets neither carry nor apply secrets
+        let keys = vec!["apiToken".to_string(), "fontSize".to_string()];
+        let mut preset = service.export_app_preset(app_id, "user-123", &keys)?;
+        assert!(!preset.settings.contains_key("apiToken"));
+        preset
+            .settings
+            .insert("apiToken".to_string(), serde_json::json!("planted"));
+        let signing_key = service.preset_key.clone().unwrap();
+        use ed25519_dalek::Signer;
+        preset.signature = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.sign(&preset.signing_payload()?).to_bytes());
+        let result = service.import_app_preset(
+            app_id,
+            "user-123",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        )?;
+        assert_eq!(result.filtered_keys, vec!["apiToken"]);
+        assert_eq!(
+            service.app_secrets(app_id, "user-123")?["apiToken"],
+            serde_json::json!("t0ken")
         );
 
+        assert!(service.clear_app_secret(app_id, "user-123", "apiToken")?);
+        assert!(!service.clear_app_secret(app_id, "user-123", "apiToken")?);
+        assert_eq!(audited(&audit, AuditAction::AppSecretCleared)?, 1);
+        assert!(service.app_secrets(app_id, "user-123")?.is_empty());
+        assert!(!service.file_storage.exists(&path));
+
         Ok(())
     }
 
     #[test]
-    fn test_app_config_per_user() -> Result<()> {
-        let (service, _temp) = create_test_service()?;
+    fn test_migrate_moves_values_of_new_secret_fields() -> Result<()> {
+        let (service, _context) = create_test_service()?;
+        let mut fields = reader_fields();
+        fields.as_array_mut().unwrap().push(serde_json::json!(
+            {"key": "apiToken", "label": "API token", "type": "string"}
+        ));
+        install_with_settings(&service, fields)?;
+        let settings =
+            std::collections::HashMap::from([("apiToken".to_string(), serde_json::json!("t0ken"))]);
+        service.set_app_config("com.test.reader", "user-123", settings)?;
+
+        let schema: SettingsSchema = serde_json::from_value(serde_json::json!({
+            "sections": [{"id": "display", "label": "Display", "fields": secret_fields()}]
+        }))?;
+        install_with_settings(&service, secret_fields())?;
+        assert_eq!(service.migrate_app_settings("com.test.reader", &schema)?, 1);
+
+        let stored = service.stored_app_config("com.test.reader", "user-123")?;
+        assert_eq!(stored.get_setting("apiToken"), None);
+        assert_eq!(
+            service.app_secrets("com.test.reader", "user-123")?["apiToken"],
+            serde_json::json!("t0ken")
+        );
 
-        // Create application first (required for foreign key)
-        let app = crate::models::application::OsnovaApplication::new(
-            "com.test.app",
-            "Test App",
-            "1.0.0",
-            "https://icon.url",
-            "Test application",
-            vec![],
+        Ok(())
+    }
+
+    /// Service on a mock clock with com.test.editor installed
+    fn create_snapshot_service(every: u64) -> Result<(ConfigService, Arc<MockClock>, OsnovaContext)> {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let (service, context) = create_test_service()?;
+        let service = service
+            .with_clock(clock.clone())
+            .with_snapshot_every(every);
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        Ok((service, clock, context))
+    }
+
+    fn write_setting(service: &ConfigService, key: &str, value: Value) -> Result<()> {
+        let settings = std::collections::HashMap::from([(key.to_string(), value)]);
+        service.set_app_config("com.test.editor", "user-123", settings)?;
+        Ok(())
+    }
+
+    fn snapshot_versions(service: &ConfigService) -> Result<Vec<u64>> {
+        Ok(service
+            .list_snapshots("com.test.editor", "user-123")?
+            .iter()
+            .map(|snapshot| snapshot.version)
+            .collect())
+    }
+
+    #[test]
+    fn test_snapshot_cadence() -> Result<()> {
+        let (service, clock, _context) = create_snapshot_service(3)?;
+
+        // Nothing to keep before the first write; the first change is kept
+        // at the next write since there is no snapshot yet
+        write_setting(&service, "fontSize", serde_json::json!(10))?;
+        assert!(snapshot_versions(&service)?.is_empty());
+        write_setting(&service, "fontSize", serde_json::json!(11))?;
+        assert_eq!(snapshot_versions(&service)?, vec![1]);
+
+        // Then every third setting written
+        write_setting(&service, "fontSize", serde_json::json!(12))?;
+        write_setting(&service, "fontSize", serde_json::json!(13))?;
+        assert_eq!(snapshot_versions(&service)?, vec![1]);
+        write_setting(&service, "fontSize", serde_json::json!(14))?;
+        assert_eq!(snapshot_versions(&service)?, vec![4, 1]);
+
+        // And at least daily while the settings change
+        clock.advance(std::time::Duration::from_secs(SNAPSHOT_INTERVAL_SECS));
+        write_setting(&service, "fontSize", serde_json::json!(15))?;
+        assert_eq!(snapshot_versions(&service)?, vec![5, 4, 1]);
+        let latest = &service.list_snapshots("com.test.editor", "user-123")?[0];
+        assert_eq!(latest.reason, SnapshotReason::Scheduled);
+        assert_eq!(latest.created_at, clock.now_unix());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_snapshot() -> Result<()> {
+        let (service, _clock, _context) = create_snapshot_service(1)?;
+        let events = EventBus::new();
+        let mut changes = events.subscribe();
+        let service = service.with_events(events);
+        write_setting(&service, "theme", serde_json::json!("dark"))?;
+        write_setting(&service, "fontSize", serde_json::json!(14))?;
+        write_setting(&service, "theme", serde_json::json!("neon"))?;
+        write_setting(&service, "tabWidth", serde_json::json!(8))?;
+        let snapshots = service.list_snapshots("com.test.editor", "user-123")?;
+        assert_eq!(snapshot_versions(&service)?, vec![3, 2, 1]);
+        let (neon, dark) = (snapshots[0].id, snapshots[2].id);
+        while changes.try_recv().is_ok() {}
+
+        // Replacing drops keys the snapshot does not have
+        let undo = service.restore_snapshot(
+            "com.test.editor",
+            "user-123",
+            dark,
+            RestoreMode::Replace,
         )?;
-        service.sql_storage.upsert_application(&app)?;
+        assert_eq!(undo.reason, SnapshotReason::BeforeRestore);
+        assert!(matches!(
+            changes.try_recv(),
+            Ok(AppEvent::ConfigChanged { .. })
+        ));
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            serde_json::to_value(config.settings())?,
+            serde_json::json!({"theme": "dark"})
+        );
 
-        // Set config for user1
-        let mut settings1 = std::collections::HashMap::new();
-        settings1.insert("theme".to_string(), serde_json::json!("dark"));
-        service.set_app_config("com.test.app", "user-1", settings1)?;
+        // Merging only brings back keys without a value
+        service.restore_snapshot(
+            "com.test.editor",
+            "user-123",
+            neon,
+            RestoreMode::MergeMissing,
+        )?;
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            serde_json::to_value(config.settings())?,
+            serde_json::json!({"theme": "dark", "fontSize": 14})
+        );
 
-        // Set config for user2
-        let mut settings2 = std::collections::HashMap::new();
-        settings2.insert("theme".to_string(), serde_json::json!("light"));
-        service.set_app_config("com.test.app", "user-2", settings2)?;
+        // Restoring the automatic snapshot undoes the first restore
+        service.restore_snapshot(
+            "com.test.editor",
+            "user-123",
+            undo.id,
+            RestoreMode::Replace,
+        )?;
+        let config = service.get_app_config("com.test.editor", "user-123")?;
+        assert_eq!(
+            serde_json::to_value(config.settings())?,
+            serde_json::json!({"theme": "neon", "fontSize": 14, "tabWidth": 8})
+        );
 
-        // Verify configs are separate
-        let config1 = service.get_app_config("com.test.app", "user-1")?;
-        let config2 = service.get_app_config("com.test.app", "user-2")?;
+        assert!(service
+            .restore_snapshot("com.test.editor", "user-123", 999, RestoreMode::Replace)
+            .is_err());
+        Ok(())
+    }
 
+    #[test]
+    fn test_snapshot_retention() -> Result<()> {
+        let (service, clock, _context) = create_snapshot_service(1)?;
+        service.set_retention_policy(RetentionStore::ConfigSnapshots, Some(Policy::count(2)))?;
+        for size in 10..16 {
+            write_setting(&service, "fontSize", serde_json::json!(size))?;
+        }
+        write_setting(&service, "theme", serde_json::json!("dark"))?;
+        assert_eq!(snapshot_versions(&service)?, vec![6, 5]);
+
+        // The oldest kept snapshot no longer has its predecessor to build on
+        let oldest = service.list_snapshots("com.test.editor", "user-123")?[1].clone();
+        service.restore_snapshot(
+            "com.test.editor",
+            "user-123",
+            oldest.id,
+            RestoreMode::Replace,
+        )?;
+        let config = service.get_app_config("com.test.editor", "user-123")?;
         assert_eq!(
-            config1.get_setting("theme"),
-            Some(&serde_json::json!("dark"))
+            serde_json::to_value(config.settings())?,
+            serde_json::json!({"fontSize": 14})
         );
+
+        // Snapshots past the age limit go on the next snapshot
+        service.set_retention_policy(
+            RetentionStore::ConfigSnapshots,
+            Some(Policy {
+                max_age: Some(std::time::Duration::from_secs(60)),
+                ..Policy::default()
+            }),
+        )?;
+        clock.advance(std::time::Duration::from_secs(120));
+        write_setting(&service, "theme", serde_json::json!("light"))?;
+        let snapshots = service.list_snapshots("com.test.editor", "user-123")?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].created_at, clock.now_unix());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_usage_is_attributed_per_app() -> Result<()> {
+        let (service, _clock, _context) = create_snapshot_service(1)?;
+        install_with_settings(&service, reader_fields())?;
+        for size in 10..14 {
+            write_setting(&service, "fontSize", serde_json::json!(size))?;
+        }
+        let settings =
+            std::collections::HashMap::from([("fontSize".to_string(), serde_json::json!(9))]);
+        service.set_app_config("com.test.reader", "user-123", settings.clone())?;
+        service.set_app_config("com.test.reader", "user-123", settings)?;
+
+        let usage = service.snapshot_usage("user-123")?;
+        assert_eq!(usage.keys().collect::<Vec<_>>(), ["com.test.editor", "com.test.reader"]);
+        for (app_id, bytes) in &usage {
+            let stored: u64 = service
+                .list_snapshots(app_id, "user-123")?
+                .iter()
+                .map(|snapshot| snapshot.bytes)
+                .sum();
+            assert_eq!(*bytes, stored);
+            assert!(stored > 0);
+        }
+        assert!(service.snapshot_usage("user-456")?.is_empty());
+
+        // Snapshots go with the app
+        service.sql_storage.delete_app_configs_for_app("com.test.editor")?;
+        assert!(!service
+            .snapshot_usage("user-123")?
+            .contains_key("com.test.editor"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_app_settings_for_new_schema() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let old_fields = serde_json::json!([
+            {"key": "size", "label": "Size", "type": "number", "min": 8, "max": 64},
+            {"key": "theme", "label": "Theme", "type": "enum",
+             "options": [{"value": "light", "label": "Light"}, {"value": "sepia", "label": "Sepia"}]}
+        ]);
+        install_with_settings(&service, old_fields)?;
+        for (user, size) in [("user-123", 48), ("user-456", 12)] {
+            let mut settings = std::collections::HashMap::new();
+            settings.insert("size".to_string(), serde_json::json!(size));
+            settings.insert("theme".to_string(), serde_json::json!("sepia"));
+            service.set_app_config("com.test.reader", user, settings)?;
+        }
+
+        // Version 2 renames size and drops the sepia theme
+        let mut new_fields = reader_fields();
+        new_fields[0]["renamedFrom"] = serde_json::json!(["size"]);
+        install_with_settings(&service, new_fields)?;
+        let schema = service.settings_schema("com.test.reader")?.unwrap();
+        assert_eq!(service.migrate_app_settings("com.test.reader", &schema)?, 2);
+
+        // Out of range after the move, so the default applies
+        let first = service.get_app_config("com.test.reader", "user-123")?;
+        assert_eq!(first.get_setting("fontSize"), Some(&serde_json::json!(14)));
+        assert_eq!(first.get_setting("size"), None);
         assert_eq!(
-            config2.get_setting("theme"),
+            first.get_setting("theme"),
             Some(&serde_json::json!("light"))
         );
+        let second = service.get_app_config("com.test.reader", "user-456")?;
+        assert_eq!(second.get_setting("fontSize"), Some(&serde_json::json!(12)));
+
+        assert_eq!(service.migrate_app_settings("com.test.reader", &schema)?, 0);
 
         Ok(())
     }
@@ -598,4 +3396,295 @@ mod tests {
 
         Ok(())
     }
+
+    fn install_test_app(service: &ConfigService, app_id: &str) -> Result<()> {
+        let app = OsnovaApplication::new(app_id, "Test App", "1.0.0", "", "Test app", vec![])?;
+        service.sql_storage.upsert_application(&app)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_mutate_app_config_retry_is_idempotent() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_test_app(&service, "com.test.app")?;
+        install_test_app(&service, "com.test.other")?;
+        let settings = |value: i64| {
+            std::collections::HashMap::from([("volume".to_string(), serde_json::json!(value))])
+        };
+
+        let receipt = service.mutate_app_config("m-1", "com.test.app", "user-123", settings(3))?;
+        assert_eq!(receipt.mutation_id, "m-1");
+        let config = service.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(receipt.generation, config.version());
+
+        // A retry neither writes again nor moves the generation, even if
+        // the settings sent differ
+        let retry = service.mutate_app_config("m-1", "com.test.app", "user-123", settings(9))?;
+        assert_eq!(retry, receipt);
+        let config = service.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(config.version(), receipt.generation);
+        assert_eq!(config.get_setting("volume"), Some(&serde_json::json!(3)));
+
+        // The same id for another app is a different mutation
+        service.mutate_app_config("m-1", "com.test.other", "user-123", settings(9))?;
+        let other = service.get_app_config("com.test.other", "user-123")?;
+        assert_eq!(other.get_setting("volume"), Some(&serde_json::json!(9)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mutate_app_config_failure_reports_last_good_generation() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = ConfigService::new(temp.path())?;
+        install_test_app(&service, "com.test.app")?;
+        let settings = |value: i64| {
+            std::collections::HashMap::from([("volume".to_string(), serde_json::json!(value))])
+        };
+        let generation = service
+            .set_app_config("com.test.app", "user-123", settings(3))?
+            .generation;
+
+        let conn = rusqlite::Connection::open(temp.path().join("osnova.db"))?;
+        conn.execute_batch(
+            "CREATE TRIGGER fail BEFORE UPDATE ON app_configurations
+             BEGIN SELECT RAISE(ABORT, 'injected'); END;",
+        )?;
+
+        let err = service
+            .mutate_app_config("m-1", "com.test.app", "user-123", settings(7))
+            .unwrap_err();
+        let rejection = err.downcast_ref::<MutationRejected>().unwrap();
+        assert_eq!(rejection.mutation_id, "m-1");
+        assert_eq!(rejection.last_good_generation, Some(generation));
+        assert!(rejection.reason.contains("injected"));
+        let config = service.get_app_config("com.test.app", "user-123")?;
+        assert_eq!(config.version(), generation);
+        assert_eq!(config.get_setting("volume"), Some(&serde_json::json!(3)));
+
+        // Validation errors are still reachable through the rejection
+        install_with_settings(&service, reader_fields())?;
+        let invalid =
+            std::collections::HashMap::from([("fontSize".to_string(), serde_json::json!(64))]);
+        let err = service
+            .mutate_app_config("m-2", "com.test.reader", "user-123", invalid)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MutationRejected>()
+                .unwrap()
+                .last_good_generation,
+            Some(0)
+        );
+        assert!(matches!(
+            err.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::InvalidSettings { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_since() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        install_test_app(&service, "com.test.app")?;
+        let settings =
+            std::collections::HashMap::from([("volume".to_string(), serde_json::json!(3))]);
+        let receipt = service.set_app_config("com.test.app", "user-123", settings)?;
+
+        assert_eq!(
+            service
+                .get_since("com.test.app", "user-123", receipt.generation)?
+                .generation(),
+            receipt.generation
+        );
+        assert!(matches!(
+            service.get_since("com.test.app", "user-123", receipt.generation)?,
+            Since::Unchanged { .. }
+        ));
+        match service.get_since("com.test.app", "user-123", 0)? {
+            Since::Changed { generation, state } => {
+                assert_eq!(generation, receipt.generation);
+                assert_eq!(state.get_setting("volume"), Some(&serde_json::json!(3)));
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        Ok(())
+    }
+
+    fn theme(service: &ConfigService, user_id: &str) -> Result<Option<Value>> {
+        Ok(service
+            .get_app_config("com.test.editor", user_id)?
+            .get_setting("theme")
+            .cloned())
+    }
+
+    #[test]
+    fn test_app_config_cache_invalidated_by_every_write() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let service = service.with_events(EventBus::new());
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        let set = |value: &str| {
+            std::collections::HashMap::from([("theme".to_string(), serde_json::json!(value))])
+        };
+
+        assert_eq!(theme(&service, "user-123")?, None);
+        assert_eq!(theme(&service, "user-123")?, None);
+        let metrics = service.cache_metrics();
+        assert_eq!((metrics.hits, metrics.misses), (1, 1));
+
+        service.set_app_config("com.test.editor", "user-123", set("dark"))?;
+        assert_eq!(
+            theme(&service, "user-123")?,
+            Some(serde_json::json!("dark"))
+        );
+
+        service.mutate_app_config("m-1", "com.test.editor", "user-123", set("light"))?;
+        assert_eq!(
+            theme(&service, "user-123")?,
+            Some(serde_json::json!("light"))
+        );
+
+        service.set_app_config("com.test.editor", "user-456", set("sepia"))?;
+        let preset =
+            service.export_app_preset("com.test.editor", "user-456", &["theme".to_string()])?;
+        service.import_app_preset(
+            "com.test.editor",
+            "user-123",
+            &preset,
+            &service.preset_author_key()?,
+            PresetImportPolicy::Merge,
+        )?;
+        assert_eq!(
+            theme(&service, "user-123")?,
+            Some(serde_json::json!("sepia"))
+        );
+
+        let (phone, _phone_temp) = create_test_service()?;
+        install_app(&phone, "1.0.0", serde_json::json!([]))?;
+        let language =
+            std::collections::HashMap::from([("language".to_string(), serde_json::json!("en"))]);
+        phone.set_app_config("com.test.editor", "user-123", language)?;
+        let payload =
+            phone.app_config_sync_payload("com.test.editor", "user-123", "phone", None)?;
+        service.apply_app_config_sync("com.test.editor", "user-123", &payload)?;
+        assert_eq!(
+            service
+                .get_app_config("com.test.editor", "user-123")?
+                .get_setting("language"),
+            Some(&serde_json::json!("en"))
+        );
+
+        let schema: SettingsSchema = serde_json::from_value(serde_json::json!({
+            "sections": [{"id": "display", "label": "Display", "fields": [
+                {"key": "colors", "label": "Colors", "type": "string", "renamedFrom": ["theme"]}
+            ]}]
+        }))?;
+        assert_eq!(service.migrate_app_settings("com.test.editor", &schema)?, 2);
+        assert_eq!(theme(&service, "user-123")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_config_cache_follows_other_instances() -> Result<()> {
+        let temp = TempDir::new()?;
+        let events = EventBus::new();
+        let writer = ConfigService::new(temp.path())?.with_events(events.clone());
+        let reader = ConfigService::new(temp.path())?.with_events(events);
+        install_app(&writer, "1.0.0", serde_json::json!([]))?;
+
+        assert_eq!(theme(&reader, "user-123")?, None);
+        let settings =
+            std::collections::HashMap::from([("theme".to_string(), serde_json::json!("dark"))]);
+        writer.set_app_config("com.test.editor", "user-123", settings)?;
+        assert_eq!(theme(&reader, "user-123")?, Some(serde_json::json!("dark")));
+
+        // Interleaved reads never go back to an older value, and see the
+        // last write once it is done
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for count in 1..=50 {
+                    let settings = std::collections::HashMap::from([(
+                        "count".to_string(),
+                        serde_json::json!(count),
+                    )]);
+                    writer
+                        .set_app_config("com.test.editor", "user-123", settings)
+                        .unwrap();
+                }
+            });
+            let mut last = 0;
+            while last < 50 {
+                let config = reader
+                    .get_app_config("com.test.editor", "user-123")
+                    .unwrap();
+                let count = config
+                    .get_setting("count")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0);
+                assert!(count >= last, "read {} after {}", count, last);
+                last = count;
+            }
+        });
+        assert!(reader.cache_metrics().hits > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_config_cache_cleared_on_identity_change() -> Result<()> {
+        let (service, context) = create_test_service()?;
+        let service = service
+            .with_events(EventBus::new())
+            .with_unlock(context.unlock_gate());
+        context.unlock(
+            &crate::models::identity::RootIdentity::generate()?,
+            "user-123",
+        )?;
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+
+        theme(&service, "user-123")?;
+        theme(&service, "user-123")?;
+        assert_eq!(service.cache_metrics().entries, 1);
+
+        context.lock();
+        assert!(theme(&service, "user-123").is_err());
+        assert_eq!(service.cache_metrics().entries, 0);
+
+        context.unlock(
+            &crate::models::identity::RootIdentity::generate()?,
+            "user-123",
+        )?;
+        theme(&service, "user-123")?;
+        assert_eq!(service.cache_metrics().misses, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_config_cache_kill_switch() -> Result<()> {
+        let (service, context) = create_test_service()?;
+        let service = service.with_events(EventBus::new());
+        install_app(&service, "1.0.0", serde_json::json!([]))?;
+        service.set_runtime_settings(RuntimeSettings {
+            config_cache: false,
+            ..RuntimeSettings::default()
+        })?;
+
+        theme(&service, "user-123")?;
+        theme(&service, "user-123")?;
+        assert_eq!(service.cache_metrics(), ConfigCacheMetrics::default());
+
+        // Stored settings apply to instances created afterwards
+        service.set_runtime_settings(RuntimeSettings {
+            config_cache_entries: 1,
+            ..RuntimeSettings::default()
+        })?;
+        let other = ConfigService::from_context(&context)?.with_events(EventBus::new());
+        theme(&other, "user-123")?;
+        theme(&other, "user-456")?;
+        assert_eq!(other.cache_metrics().evictions, 1);
+
+        // Without an event bus nothing is cached
+        let unshared = ConfigService::from_context(&context)?;
+        theme(&unshared, "user-123")?;
+        theme(&unshared, "user-123")?;
+        assert_eq!(unshared.cache_metrics(), ConfigCacheMetrics::default());
+        Ok(())
+    }
 }
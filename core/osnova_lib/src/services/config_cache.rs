@@ -0,0 +1,436 @@
+//! Read cache of decrypted per-app configurations
+//!
+//! Reading a per-app configuration decrypts and deserializes its whole
+//! settings map, which adds up for apps that read their settings on every
+//! render. A [`ConfigService`](crate::services::ConfigService) on an event
+//! bus keeps the configurations it read in an [`AppConfigCache`], keyed by
+//! app and user:
+//!
+//! - Every write path of the service invalidates the entry it wrote.
+//! - `ConfigChanged` events on the service's event bus invalidate entries
+//!   written by other service instances; a lagging subscription drops
+//!   everything.
+//! - The cache is emptied when the context is unlocked or locked, so it
+//!   never outlives a sign-out or an identity change.
+//! - Once full, the least recently read entry is evicted.
+//!
+//! Entries are handed out as clones. A read that raced with a write does
+//! not fill the cache with what it read, so a stale configuration is never
+//! cached.
+//!
+//! [`RuntimeSettings`](crate::services::RuntimeSettings) sets the number of
+//! entries and turns the cache off.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::context::UnlockGate;
+use crate::models::config_cache::AppConfiguration;
+use crate::services::events::AppEvent;
+
+/// Default number of per-app configurations kept in memory
+pub const DEFAULT_CONFIG_CACHE_ENTRIES: usize = 256;
+
+/// Hit and miss counters of the per-app configuration cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigCacheMetrics {
+    /// Reads served from memory
+    pub hits: u64,
+    /// Reads that went to storage
+    pub misses: u64,
+    /// Entries dropped to stay under the entry cap
+    pub evictions: u64,
+    /// Entries held now
+    pub entries: usize,
+}
+
+/// A cached configuration and when it was last read
+struct Entry {
+    config: AppConfiguration,
+    last_read: u64,
+}
+
+struct CacheState {
+    entries: HashMap<(String, String), Entry>,
+    enabled: bool,
+    capacity: usize,
+    /// Whether the stored runtime settings were applied
+    configured: bool,
+    /// Read counter ordering entries by recency
+    tick: u64,
+    /// Bumped by every invalidation, so racing reads do not fill the cache
+    generation: u64,
+    /// Unlock epoch the entries were read in
+    epoch: Option<u64>,
+    metrics: ConfigCacheMetrics,
+}
+
+impl CacheState {
+    fn invalidate(&mut self, app_id: &str, user_id: &str) {
+        self.generation += 1;
+        self.entries
+            .remove(&(app_id.to_string(), user_id.to_string()));
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+    }
+
+    /// Evict least recently read entries until the cap is met
+    fn evict_to(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_read)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.metrics.evictions += 1;
+        }
+    }
+}
+
+/// In-memory cache of decrypted per-app configurations
+///
+/// Owned by a [`ConfigService`](crate::services::ConfigService); see the
+/// [module documentation](self).
+pub struct AppConfigCache {
+    state: Mutex<CacheState>,
+    events: Option<Mutex<broadcast::Receiver<AppEvent>>>,
+    unlock: Option<UnlockGate>,
+}
+
+impl AppConfigCache {
+    /// Create an empty cache with the default settings
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                enabled: true,
+                capacity: DEFAULT_CONFIG_CACHE_ENTRIES,
+                configured: false,
+                tick: 0,
+                generation: 0,
+                epoch: None,
+                metrics: ConfigCacheMetrics::default(),
+            }),
+            events: None,
+            unlock: None,
+        }
+    }
+
+    /// Invalidate entries on the `ConfigChanged` events of a bus
+    ///
+    /// Only events published after this call are seen.
+    pub fn with_events(mut self, events: broadcast::Receiver<AppEvent>) -> Self {
+        self.events = Some(Mutex::new(events));
+        self
+    }
+
+    /// Empty the cache whenever the context is unlocked or locked
+    pub fn with_unlock(mut self, gate: UnlockGate) -> Self {
+        self.unlock = Some(gate);
+        self
+    }
+
+    /// Whether [`configure`](Self::configure) has been called
+    pub fn is_configured(&self) -> bool {
+        self.state.lock().unwrap().configured
+    }
+
+    /// Turn the cache on or off and set its entry cap
+    ///
+    /// Turning it off drops every entry; lowering the cap evicts the least
+    /// recently read ones.
+    pub fn configure(&self, enabled: bool, capacity: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.configured = true;
+        state.enabled = enabled;
+        state.capacity = capacity;
+        if !enabled {
+            state.clear();
+        }
+        state.evict_to(capacity);
+    }
+
+    /// Configuration of an app for a user, read through `load` on a miss
+    ///
+    /// With the cache off every read goes to `load` and is not counted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `load`; failed reads are not cached
+    pub fn get_or_load<E>(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        load: impl FnOnce() -> Result<AppConfiguration, E>,
+    ) -> Result<AppConfiguration, E> {
+        let key = (app_id.to_string(), user_id.to_string());
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            self.sync(&mut state);
+            if !state.enabled || state.capacity == 0 {
+                drop(state);
+                return load();
+            }
+
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                entry.last_read = tick;
+                let config = entry.config.clone();
+                state.metrics.hits += 1;
+                return Ok(config);
+            }
+            state.metrics.misses += 1;
+            state.generation
+        };
+
+        // Storage is read without holding the lock
+        let config = load()?;
+
+        let mut state = self.state.lock().unwrap();
+        self.sync(&mut state);
+        if state.enabled && state.generation == generation {
+            let last_read = state.tick;
+            state.entries.insert(
+                key,
+                Entry {
+                    config: config.clone(),
+                    last_read,
+                },
+            );
+            let capacity = state.capacity;
+            state.evict_to(capacity);
+        }
+        Ok(config)
+    }
+
+    /// Drop the entry of an app for a user after a write
+    pub fn invalidate(&self, app_id: &str, user_id: &str) {
+        self.state.lock().unwrap().invalidate(app_id, user_id);
+    }
+
+    /// Drop every entry
+    pub fn clear(&self) {
+        self.state.lock().unwrap().clear();
+    }
+
+    /// Hit and miss counters since the cache was created
+    pub fn metrics(&self) -> ConfigCacheMetrics {
+        let mut state = self.state.lock().unwrap();
+        self.sync(&mut state);
+        ConfigCacheMetrics {
+            entries: state.entries.len(),
+            ..state.metrics
+        }
+    }
+
+    /// Apply unlock changes and pending events
+    fn sync(&self, state: &mut CacheState) {
+        if let Some(gate) = &self.unlock {
+            let epoch = gate.epoch();
+            if state.epoch != Some(epoch) {
+                state.clear();
+                state.epoch = Some(epoch);
+            }
+        }
+
+        let Some(events) = &self.events else {
+            return;
+        };
+        let mut events = events.lock().unwrap();
+        loop {
+            match events.try_recv() {
+                Ok(AppEvent::ConfigChanged { app_id, user_id }) => {
+                    state.invalidate(&app_id, &user_id)
+                }
+                Ok(AppEvent::ContextUnlocked { .. }) => state.clear(),
+                Ok(_) => {}
+                // Changes were missed, so nothing held can be trusted
+                Err(TryRecvError::Lagged(_)) => state.clear(),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+impl Default for AppConfigCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OsnovaContext;
+    use crate::models::identity::RootIdentity;
+    use crate::services::events::EventBus;
+    use std::cell::Cell;
+
+    fn config(app_id: &str, user_id: &str, theme: &str) -> AppConfiguration {
+        let mut config = AppConfiguration::new(app_id, user_id);
+        config.set_setting("theme", serde_json::json!(theme));
+        config
+    }
+
+    /// Loader counting the reads that reached storage
+    fn counting<'a>(
+        reads: &'a Cell<usize>,
+        stored: &'a AppConfiguration,
+    ) -> impl FnOnce() -> anyhow::Result<AppConfiguration> + 'a {
+        move || {
+            reads.set(reads.get() + 1);
+            Ok(stored.clone())
+        }
+    }
+
+    #[test]
+    fn test_hit_avoids_storage_read() -> anyhow::Result<()> {
+        let cache = AppConfigCache::new();
+        let reads = Cell::new(0);
+        let stored = config("com.test.app", "user-1", "dark");
+
+        for _ in 0..3 {
+            let read = cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+            assert_eq!(read, stored);
+        }
+        assert_eq!(reads.get(), 1);
+
+        // Cached entries are copies
+        let mut read = cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+        read.set_setting("theme", serde_json::json!("light"));
+        let again = cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+        assert_eq!(again, stored);
+
+        assert_eq!(
+            cache.metrics(),
+            ConfigCacheMetrics {
+                hits: 4,
+                misses: 1,
+                evictions: 0,
+                entries: 1,
+            }
+        );
+
+        // Failed reads are not cached
+        let failed: anyhow::Result<_> =
+            cache.get_or_load("com.test.other", "user-1", || anyhow::bail!("unreadable"));
+        assert!(failed.is_err());
+        assert_eq!(cache.metrics().entries, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cap_evicts_least_recently_read() -> anyhow::Result<()> {
+        let cache = AppConfigCache::new();
+        cache.configure(true, 2);
+        let reads = Cell::new(0);
+        let a = config("com.test.a", "user-1", "dark");
+        let b = config("com.test.b", "user-1", "dark");
+        let c = config("com.test.c", "user-1", "dark");
+
+        cache.get_or_load("com.test.a", "user-1", counting(&reads, &a))?;
+        cache.get_or_load("com.test.b", "user-1", counting(&reads, &b))?;
+        // Reading a again makes b the least recently read
+        cache.get_or_load("com.test.a", "user-1", counting(&reads, &a))?;
+        cache.get_or_load("com.test.c", "user-1", counting(&reads, &c))?;
+        assert_eq!(reads.get(), 3);
+        assert_eq!(cache.metrics().evictions, 1);
+
+        cache.get_or_load("com.test.a", "user-1", counting(&reads, &a))?;
+        assert_eq!(reads.get(), 3);
+        cache.get_or_load("com.test.b", "user-1", counting(&reads, &b))?;
+        assert_eq!(reads.get(), 4);
+
+        // Lowering the cap evicts at once; turning the cache off empties it
+        cache.configure(true, 1);
+        assert_eq!(cache.metrics().entries, 1);
+        cache.configure(false, 1);
+        assert_eq!(cache.metrics().entries, 0);
+        cache.get_or_load("com.test.a", "user-1", counting(&reads, &a))?;
+        cache.get_or_load("com.test.a", "user-1", counting(&reads, &a))?;
+        assert_eq!(reads.get(), 6);
+        assert_eq!(cache.metrics().entries, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_invalidate_entries() -> anyhow::Result<()> {
+        let events = EventBus::new();
+        let cache = AppConfigCache::new().with_events(events.subscribe());
+        let reads = Cell::new(0);
+        let first = config("com.test.app", "user-1", "dark");
+        let other = config("com.test.app", "user-2", "dark");
+
+        cache.get_or_load("com.test.app", "user-1", counting(&reads, &first))?;
+        cache.get_or_load("com.test.app", "user-2", counting(&reads, &other))?;
+
+        // Another service instance wrote user-1's configuration
+        events.publish(AppEvent::ConfigChanged {
+            app_id: "com.test.app".to_string(),
+            user_id: "user-1".to_string(),
+        });
+        let changed = config("com.test.app", "user-1", "light");
+        let read = cache.get_or_load("com.test.app", "user-1", counting(&reads, &changed))?;
+        assert_eq!(read, changed);
+        cache.get_or_load("com.test.app", "user-2", counting(&reads, &other))?;
+        assert_eq!(reads.get(), 3);
+
+        // Missed events drop everything
+        for _ in 0..300 {
+            events.publish(AppEvent::NotificationsRead {
+                user_id: "user-1".to_string(),
+            });
+        }
+        assert_eq!(cache.metrics().entries, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleared_on_unlock_and_lock() -> anyhow::Result<()> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let cache = AppConfigCache::new().with_unlock(context.unlock_gate());
+        let reads = Cell::new(0);
+        let stored = config("com.test.app", "user-1", "dark");
+
+        cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+        context.unlock(&RootIdentity::generate()?, "user-1")?;
+        cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+        assert_eq!(reads.get(), 2);
+        cache.get_or_load("com.test.app", "user-1", counting(&reads, &stored))?;
+        assert_eq!(reads.get(), 2);
+
+        context.lock();
+        assert_eq!(cache.metrics().entries, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_racing_a_write_is_not_cached() -> anyhow::Result<()> {
+        let cache = AppConfigCache::new();
+        let old = config("com.test.app", "user-1", "dark");
+        let new = config("com.test.app", "user-1", "light");
+
+        // A write lands while the read is still in storage
+        let read = cache.get_or_load("com.test.app", "user-1", || {
+            cache.invalidate("com.test.app", "user-1");
+            anyhow::Ok(old.clone())
+        })?;
+        assert_eq!(read, old);
+
+        let read = cache.get_or_load("com.test.app", "user-1", || anyhow::Ok(new.clone()))?;
+        assert_eq!(read, new);
+        Ok(())
+    }
+}
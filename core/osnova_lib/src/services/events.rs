@@ -0,0 +1,214 @@
+//! In-process service events
+//!
+//! Services publish state changes on an [`EventBus`] so other services can
+//! react incrementally (e.g. the search index updating after an install)
+//! instead of polling or rebuilding from scratch.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::context::ContextUnlocked;
+use crate::models::health::HealthTransition;
+use crate::models::key_cocoon::PublicKeyMigration;
+use crate::models::key_usage::KeyUsageAnomaly;
+use crate::models::notification::StoredNotification;
+use crate::models::permission::PermissionPrompt;
+use crate::services::badges::BadgeChanged;
+use crate::services::features::FeatureFlagsChanged;
+use crate::services::handshake::ReadinessState;
+use crate::services::metadata::MetadataRefresh;
+use crate::services::reauth::AuthPrompt;
+
+/// Capacity of the event channel before slow subscribers start lagging
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// State change published by a service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AppEvent {
+    /// An application was installed or updated
+    AppInstalled {
+        /// Application identifier
+        app_id: String,
+        /// Launcher generation of the change
+        generation: u64,
+    },
+    /// An application was uninstalled (trashed or deleted)
+    AppUninstalled {
+        /// Application identifier
+        app_id: String,
+        /// Launcher generation of the change
+        generation: u64,
+    },
+    /// A trashed application was restored
+    AppRestored {
+        /// Application identifier
+        app_id: String,
+        /// Launcher generation of the change
+        generation: u64,
+    },
+    /// An installed application's name, icon or description changed
+    AppMetadataChanged {
+        /// The refresh that changed it
+        refresh: MetadataRefresh,
+    },
+    /// An application's icon badge changed
+    AppBadgeChanged {
+        /// The new badge
+        change: BadgeChanged,
+    },
+    /// A user's configuration for an application changed
+    ConfigChanged {
+        /// Application identifier
+        app_id: String,
+        /// User identifier
+        user_id: String,
+    },
+    /// A notification was delivered to a user's notification center
+    NotificationPosted {
+        /// User identifier
+        user_id: String,
+        /// The delivered notification
+        notification: StoredNotification,
+    },
+    /// A component asked for a capability and the user must answer
+    PermissionRequested {
+        /// User identifier
+        user_id: String,
+        /// The prompt to show
+        prompt: PermissionPrompt,
+    },
+    /// Notifications in a user's notification center were read or cleared
+    NotificationsRead {
+        /// User identifier
+        user_id: String,
+    },
+    /// A permission prompt was answered or timed out
+    PermissionResolved {
+        /// User identifier
+        user_id: String,
+        /// Prompt identifier
+        prompt_id: String,
+        /// Whether the capability was granted
+        granted: bool,
+    },
+    /// A launched backend's readiness changed
+    BackendReadinessChanged {
+        /// Application identifier
+        app_id: String,
+        /// Component identifier
+        component_id: String,
+        /// New readiness
+        state: ReadinessState,
+    },
+    /// A running backend became unhealthy, recovered, was restarted or
+    /// blocked
+    BackendHealthChanged {
+        /// The transition
+        transition: HealthTransition,
+    },
+    /// An update check found a newer version of an installed application
+    UpdateAvailable {
+        /// Application identifier
+        app_id: String,
+        /// Version available
+        version: String,
+    },
+    /// An application started or stopped syncing its data
+    SyncStateChanged {
+        /// Application identifier
+        app_id: String,
+        /// Whether a sync is in progress
+        syncing: bool,
+    },
+    /// The identity was unlocked and services needing key material started
+    ContextUnlocked {
+        /// The unlock
+        unlocked: ContextUnlocked,
+    },
+    /// A component started using its derived keys far more than usual
+    KeyUsageAnomaly {
+        /// The anomaly
+        anomaly: KeyUsageAnomaly,
+    },
+    /// The key cocoon was migrated to real public keys; components holding
+    /// the old public keys should store the new ones
+    KeysMigrated {
+        /// Report of the migration
+        migration: PublicKeyMigration,
+    },
+    /// An application's effective feature flag values changed
+    FeatureFlagsChanged {
+        /// User identifier
+        user_id: String,
+        /// The flags that changed
+        change: FeatureFlagsChanged,
+    },
+    /// OS authentication for a sensitive operation is asking the user
+    AuthPrompt {
+        /// What is asked
+        prompt: AuthPrompt,
+    },
+}
+
+/// Broadcast channel for [`AppEvent`]s
+///
+/// Cloning the bus yields another handle to the same channel.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<AppEvent>,
+}
+
+impl EventBus {
+    /// Create a new event bus
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publish an event to all current subscribers
+    pub fn publish(&self, event: AppEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribe to events published after this call
+    pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_reaches_all_subscribers() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+
+        let event = AppEvent::AppInstalled {
+            app_id: "com.test.app".to_string(),
+            generation: 1,
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(first.try_recv().unwrap(), event);
+        assert_eq!(second.try_recv().unwrap(), event);
+    }
+
+    #[test]
+    fn test_publish_without_subscribers() {
+        EventBus::new().publish(AppEvent::AppRestored {
+            app_id: "com.test.app".to_string(),
+            generation: 1,
+        });
+    }
+}
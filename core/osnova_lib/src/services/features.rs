@@ -0,0 +1,702 @@
+//! Feature flags of installed apps
+//!
+//! Apps declare flags in their manifest (see
+//! [`crate::models::feature_flag`]); a flag's effective value is evaluated
+//! from three layers, later layers winning:
+//!
+//! 1. Default: the value the manifest declares
+//! 2. Remote: the publisher's signed flag document (see
+//!    [`crate::manifest::flags`]), for identities its rollout reaches
+//! 3. Local: the user's override from the app's settings
+//!
+//! Every [`FlagValue`] lists the layers it was evaluated from, so an app
+//! developer can tell why a flag has its value. Rules for flags an app does
+//! not declare are ignored.
+//!
+//! The flag document is fetched with the launcher catalog and cached, so
+//! flags keep their remote values offline. When a new document, or a local
+//! override, changes an app's effective values, an
+//! [`AppEvent::FeatureFlagsChanged`] is published; the shell forwards it to
+//! frontends as [`FEATURE_FLAGS_CHANGED_EVENT`].
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::manifest::flags::{fetch_flag_document, flag_bucket, FlagDocument};
+use crate::models::application::OsnovaApplication;
+use crate::models::feature_flag::FeatureFlag;
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::network::{AutonomiClient, NetworkOptions};
+use crate::services::apps::installed_app;
+use crate::services::events::{AppEvent, EventBus};
+use crate::storage::{DataClass, FileStorage, SqlStorage};
+
+/// Event name used when surfacing flag changes to frontends
+pub const FEATURE_FLAGS_CHANGED_EVENT: &str = "feature-flags-changed";
+
+/// Component the flag cache and overrides key is derived for, per user
+const FLAG_KEY_COMPONENT: &str = "osnova-feature-flags";
+
+/// Layer a flag value comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FlagSource {
+    /// Declared in the app's manifest
+    Default,
+    /// Set by the publisher's flag document
+    Remote,
+    /// Overridden by the user
+    Local,
+}
+
+/// One layer of a flag's evaluation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagLayer {
+    /// Layer
+    pub source: FlagSource,
+    /// Value the layer sets, if it sets one
+    pub value: Option<bool>,
+    /// Why the layer does or does not set a value
+    pub detail: String,
+}
+
+/// Effective value of a flag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagValue {
+    /// Flag name
+    pub name: String,
+    /// Effective value
+    pub value: bool,
+    /// Layer the value comes from
+    pub source: FlagSource,
+    /// Layers in evaluation order: default, remote, local
+    pub evaluation: Vec<FlagLayer>,
+}
+
+/// An app's effective flag values changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlagsChanged {
+    /// Application identifier
+    pub app_id: String,
+    /// New values of the flags that changed
+    pub flags: Vec<FlagValue>,
+}
+
+/// Local overrides by app id, then flag name
+type Overrides = BTreeMap<String, BTreeMap<String, bool>>;
+
+/// Feature flag service
+///
+/// Provides OpenRPC methods:
+/// - `features.get` - Effective value of one flag of an app
+/// - `features.all` - Effective values of every flag an app declares
+/// - `features.setOverride` - Override a flag locally, or clear the override
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::identity::RootIdentity;
+/// use osnova_lib::services::FeatureFlagService;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let identity = RootIdentity::generate()?;
+/// let service = FeatureFlagService::new("/tmp/osnova", "user-123", &identity)?;
+///
+/// let flag = service.get("com.example.mail", "newComposer")?;
+/// println!("{} = {} ({:?})", flag.name, flag.value, flag.source);
+/// # Ok(())
+/// # }
+/// ```
+pub struct FeatureFlagService {
+    storage: Mutex<SqlStorage>,
+    file_storage: FileStorage,
+    document_path: PathBuf,
+    overrides_path: PathBuf,
+    encryption_key: [u8; 32],
+    user_id: String,
+    fingerprint: [u8; 32],
+    events: Option<EventBus>,
+    /// Flag document in force; `None` until the cache was read
+    document: Mutex<Option<Option<FlagDocument>>>,
+}
+
+impl FeatureFlagService {
+    /// Create a new feature flag service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose overrides and cached document are used
+    /// * `identity` - The user's identity, which the encryption key and the
+    ///   rollout fingerprint are derived from
+    pub fn new<P: Into<PathBuf>>(
+        storage_path: P,
+        user_id: &str,
+        identity: &RootIdentity,
+    ) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let file_storage = FileStorage::new(&storage_path)?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            file_storage,
+            document_path: PathBuf::from(format!("features/{}/document.json", user_id)),
+            overrides_path: PathBuf::from(format!("features/{}/overrides.json", user_id)),
+            encryption_key: Self::derive_storage_key(identity, user_id)?,
+            user_id: user_id.to_string(),
+            fingerprint: identity.fingerprint(),
+            events: None,
+            document: Mutex::new(None),
+        })
+    }
+
+    /// Publish `FeatureFlagsChanged` events when effective values change
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Effective value of one flag of an app (OpenRPC: features.get)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed or does not declare
+    /// the flag
+    pub fn get(&self, app_id: &str, flag: &str) -> Result<FlagValue> {
+        let declared = self.declared_flag(app_id, flag)?;
+        let document = self.document()?;
+        let overrides = self.load_overrides()?;
+        Ok(self.evaluate(app_id, &declared, document.as_ref(), &overrides))
+    }
+
+    /// Effective values of every flag an app declares (OpenRPC: features.all)
+    ///
+    /// In declaration order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed
+    pub fn all(&self, app_id: &str) -> Result<Vec<FlagValue>> {
+        let app = installed_app(&self.storage.lock().unwrap(), app_id)?;
+        let document = self.document()?;
+        let overrides = self.load_overrides()?;
+        Ok(self.evaluate_app(&app, document.as_ref(), &overrides))
+    }
+
+    /// Override a flag locally (OpenRPC: features.setOverride)
+    ///
+    /// `None` clears the override. The override outranks the publisher's
+    /// document and persists until cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed, does not declare the
+    /// flag, or the overrides cannot be saved
+    pub fn set_override(&self, app_id: &str, flag: &str, value: Option<bool>) -> Result<FlagValue> {
+        let app = installed_app(&self.storage.lock().unwrap(), app_id)?;
+        self.declared_flag(app_id, flag)?;
+        let document = self.document()?;
+        let mut overrides = self.load_overrides()?;
+        let before = self.evaluate_app(&app, document.as_ref(), &overrides);
+
+        let app_overrides = overrides.entry(app_id.to_string()).or_default();
+        match value {
+            Some(value) => {
+                app_overrides.insert(flag.to_string(), value);
+            }
+            None => {
+                app_overrides.remove(flag);
+            }
+        }
+        if app_overrides.is_empty() {
+            overrides.remove(app_id);
+        }
+        self.save_overrides(&overrides)?;
+
+        let after = self.evaluate_app(&app, document.as_ref(), &overrides);
+        self.publish_changes(app_id, &before, &after);
+        after
+            .into_iter()
+            .find(|value| value.name == flag)
+            .context("Overridden flag is missing from its app")
+    }
+
+    /// Fetch the publisher's flag document and put it in force
+    ///
+    /// Called with the launcher catalog's `featureFlagsUri`. On failure the
+    /// cached document stays in force.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - Flag document URI
+    /// * `publisher` - Public key (base64) of the catalog, which must also
+    ///   have signed the document; `None` for an unsigned catalog
+    /// * `client` - Optional Autonomi client (required for ant:// URIs)
+    /// * `options` - Timeout and cancellation for the fetch
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document cannot be fetched, does not verify,
+    /// or is signed by another publisher
+    pub async fn refresh(
+        &self,
+        uri: &str,
+        publisher: Option<&str>,
+        client: Option<&AutonomiClient>,
+        options: &NetworkOptions,
+    ) -> Result<()> {
+        let document = fetch_flag_document(uri, client, options).await?;
+        if let Some(publisher) = publisher {
+            document.verify_publisher(publisher)?;
+        }
+        self.apply_document(document)
+    }
+
+    /// Put a verified flag document in force and cache it
+    ///
+    /// Publishes a `FeatureFlagsChanged` event for each installed app whose
+    /// effective values change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document does not verify or cannot be cached
+    pub fn apply_document(&self, document: FlagDocument) -> Result<()> {
+        document.validate()?;
+        document.verify_signature()?;
+
+        let previous = self.document()?;
+        if previous.as_ref() == Some(&document) {
+            return Ok(());
+        }
+        let apps = self.storage.lock().unwrap().list_applications()?;
+        let overrides = self.load_overrides()?;
+        let before: Vec<Vec<FlagValue>> = apps
+            .iter()
+            .map(|app| self.evaluate_app(app, previous.as_ref(), &overrides))
+            .collect();
+
+        self.save_document(&document)?;
+        *self.document.lock().unwrap() = Some(Some(document.clone()));
+
+        for (app, before) in apps.iter().zip(before) {
+            let after = self.evaluate_app(app, Some(&document), &overrides);
+            self.publish_changes(app.id(), &before, &after);
+        }
+        Ok(())
+    }
+
+    // Private helper methods
+
+    /// Evaluate every flag an app declares
+    fn evaluate_app(
+        &self,
+        app: &OsnovaApplication,
+        document: Option<&FlagDocument>,
+        overrides: &Overrides,
+    ) -> Vec<FlagValue> {
+        app.feature_flags()
+            .iter()
+            .map(|flag| self.evaluate(app.id(), flag, document, overrides))
+            .collect()
+    }
+
+    /// Evaluate one flag through the default, remote and local layers
+    fn evaluate(
+        &self,
+        app_id: &str,
+        flag: &FeatureFlag,
+        document: Option<&FlagDocument>,
+        overrides: &Overrides,
+    ) -> FlagValue {
+        let default = FlagLayer {
+            source: FlagSource::Default,
+            value: Some(flag.default),
+            detail: "declared in the manifest".to_string(),
+        };
+
+        let remote = match document {
+            None => FlagLayer {
+                source: FlagSource::Remote,
+                value: None,
+                detail: "no flag document".to_string(),
+            },
+            Some(document) => match document.rule(app_id, &flag.name) {
+                None => FlagLayer {
+                    source: FlagSource::Remote,
+                    value: None,
+                    detail: format!("no rule in flag document {}", document.version),
+                },
+                Some(rule) => {
+                    let bucket = flag_bucket(&self.fingerprint, app_id, &flag.name);
+                    let reached = bucket < rule.rollout_percent;
+                    FlagLayer {
+                        source: FlagSource::Remote,
+                        value: reached.then_some(rule.enabled),
+                        detail: format!(
+                            "flag document {}, rollout {}% {} (bucket {})",
+                            document.version,
+                            rule.rollout_percent,
+                            if reached { "reached" } else { "not reached" },
+                            bucket
+                        ),
+                    }
+                }
+            },
+        };
+
+        let local = match overrides.get(app_id).and_then(|o| o.get(&flag.name)) {
+            Some(value) => FlagLayer {
+                source: FlagSource::Local,
+                value: Some(*value),
+                detail: "overridden in the app's settings".to_string(),
+            },
+            None => FlagLayer {
+                source: FlagSource::Local,
+                value: None,
+                detail: "not overridden".to_string(),
+            },
+        };
+
+        let evaluation = vec![default, remote, local];
+        let (value, source) = evaluation
+            .iter()
+            .rev()
+            .find_map(|layer| layer.value.map(|value| (value, layer.source)))
+            .unwrap_or((flag.default, FlagSource::Default));
+        FlagValue {
+            name: flag.name.clone(),
+            value,
+            source,
+            evaluation,
+        }
+    }
+
+    /// Publish the flags whose values differ between two evaluations
+    fn publish_changes(&self, app_id: &str, before: &[FlagValue], after: &[FlagValue]) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        let flags: Vec<FlagValue> = after
+            .iter()
+            .filter(|value| {
+                !before
+                    .iter()
+                    .any(|old| old.name == value.name && old.value == value.value)
+            })
+            .cloned()
+            .collect();
+        if flags.is_empty() {
+            return;
+        }
+        events.publish(AppEvent::FeatureFlagsChanged {
+            user_id: self.user_id.clone(),
+            change: FeatureFlagsChanged {
+                app_id: app_id.to_string(),
+                flags,
+            },
+        });
+    }
+
+    /// A flag an installed app declares
+    fn declared_flag(&self, app_id: &str, flag: &str) -> Result<FeatureFlag> {
+        installed_app(&self.storage.lock().unwrap(), app_id)?
+            .feature_flags()
+            .iter()
+            .find(|declared| declared.name == flag)
+            .cloned()
+            .with_context(|| format!("{} declares no feature flag {}", app_id, flag))
+    }
+
+    /// The flag document in force, read from the cache on first use
+    fn document(&self) -> Result<Option<FlagDocument>> {
+        let mut document = self.document.lock().unwrap();
+        if document.is_none() {
+            *document = Some(self.load_document()?);
+        }
+        Ok(document.clone().flatten())
+    }
+
+    /// Load the cached flag document
+    fn load_document(&self) -> Result<Option<FlagDocument>> {
+        if !self.file_storage.exists(&self.document_path) {
+            return Ok(None);
+        }
+
+        let data = self
+            .file_storage
+            .read(&self.document_path, &self.encryption_key)
+            .context("Failed to read cached flag document")?;
+        let document =
+            FlagDocument::from_bytes(&data).context("Failed to parse cached flag document")?;
+        Ok(Some(document))
+    }
+
+    /// Cache a flag document for offline use
+    fn save_document(&self, document: &FlagDocument) -> Result<()> {
+        let data = serde_json::to_vec(document).context("Failed to serialize flag document")?;
+        self.file_storage
+            .write_classified(
+                &self.document_path,
+                &data,
+                &self.encryption_key,
+                DataClass::CacheRegenerable,
+            )
+            .context("Failed to write cached flag document")?;
+        Ok(())
+    }
+
+    /// Load the user's local overrides
+    fn load_overrides(&self) -> Result<Overrides> {
+        if !self.file_storage.exists(&self.overrides_path) {
+            return Ok(Overrides::new());
+        }
+
+        let data = self
+            .file_storage
+            .read(&self.overrides_path, &self.encryption_key)
+            .context("Failed to read flag overrides")?;
+        serde_json::from_slice(&data).context("Failed to parse flag overrides")
+    }
+
+    /// Save the user's local overrides
+    fn save_overrides(&self, overrides: &Overrides) -> Result<()> {
+        let data = serde_json::to_vec(overrides).context("Failed to serialize flag overrides")?;
+        self.file_storage
+            .write_classified(
+                &self.overrides_path,
+                &data,
+                &self.encryption_key,
+                DataClass::Personal,
+            )
+            .context("Failed to write flag overrides")?;
+        Ok(())
+    }
+
+    /// Derive the encryption key for the cached document and overrides
+    pub(crate) fn derive_storage_key(identity: &RootIdentity, user_id: &str) -> Result<[u8; 32]> {
+        let component = format!("{}:{}", FLAG_KEY_COMPONENT, user_id);
+        Ok(identity.derive_component_key(&component, 0, KeyPurpose::Encryption)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::flags::FlagRule;
+    use ed25519_dalek::SigningKey;
+    use tempfile::TempDir;
+
+    const MAIL: &str = "com.example.mail";
+
+    fn install_mail(temp: &TempDir) -> Result<()> {
+        let flag = |name: &str, default: bool| FeatureFlag {
+            name: name.to_string(),
+            description: String::new(),
+            default,
+        };
+        let app = OsnovaApplication::new(MAIL, "Mail", "1.0.0", "ant://icon", "Mail", vec![])?
+            .with_feature_flags(vec![flag("newComposer", false), flag("threads", true)]);
+        SqlStorage::new(temp.path().join("osnova.db"))?.upsert_application(&app)?;
+        Ok(())
+    }
+
+    fn service(temp: &TempDir, identity: &RootIdentity) -> Result<FeatureFlagService> {
+        install_mail(temp)?;
+        FeatureFlagService::new(temp.path(), "user-123", identity)
+    }
+
+    fn signed(rules: &[(&str, FlagRule)]) -> FlagDocument {
+        let mut document = FlagDocument::new("1.0.0");
+        for (flag, rule) in rules {
+            document.set_rule(MAIL, flag, *rule);
+        }
+        document.sign(&SigningKey::from_bytes(&[9u8; 32])).unwrap()
+    }
+
+    fn publish(temp: &TempDir, document: &FlagDocument) -> String {
+        let path = temp.path().join("flags.json");
+        std::fs::write(&path, serde_json::to_vec(document).unwrap()).unwrap();
+        format!("file://{}", path.display())
+    }
+
+    #[test]
+    fn test_evaluation_precedence() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp, &identity)?;
+
+        let flag = service.get(MAIL, "newComposer")?;
+        assert!(!flag.value);
+        assert_eq!(flag.source, FlagSource::Default);
+        let sources: Vec<FlagSource> = flag.evaluation.iter().map(|l| l.source).collect();
+        assert_eq!(
+            sources,
+            vec![FlagSource::Default, FlagSource::Remote, FlagSource::Local]
+        );
+
+        // Remote outranks the default
+        service.apply_document(signed(&[("newComposer", FlagRule::new(true))]))?;
+        let flag = service.get(MAIL, "newComposer")?;
+        assert!(flag.value);
+        assert_eq!(flag.source, FlagSource::Remote);
+        assert_eq!(flag.evaluation[1].value, Some(true));
+
+        // Local outranks remote, and clearing it falls back
+        let flag = service.set_override(MAIL, "newComposer", Some(false))?;
+        assert!(!flag.value);
+        assert_eq!(flag.source, FlagSource::Local);
+        assert_eq!(flag.evaluation[1].value, Some(true));
+        let flag = service.set_override(MAIL, "newComposer", None)?;
+        assert_eq!(flag.source, FlagSource::Remote);
+
+        assert!(service.get(MAIL, "undeclared").is_err());
+        assert!(service
+            .set_override(MAIL, "undeclared", Some(true))
+            .is_err());
+        assert!(service.get("com.example.missing", "newComposer").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentage_rollout_is_deterministic() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        install_mail(&temp)?;
+        let document = signed(&[("newComposer", FlagRule::rollout(true, 30))]);
+
+        let mut reached = 0;
+        for i in 0..200u8 {
+            let fingerprint = *blake3::hash(&[i]).as_bytes();
+            let mut service = FeatureFlagService::new(temp.path(), "user-123", &identity)?;
+            service.fingerprint = fingerprint;
+            *service.document.lock().unwrap() = Some(Some(document.clone()));
+
+            let flag = service.get(MAIL, "newComposer")?;
+            let bucket = flag_bucket(&fingerprint, MAIL, "newComposer");
+            assert_eq!(flag.value, bucket < 30);
+            assert_eq!(service.get(MAIL, "newComposer")?, flag);
+            if flag.value {
+                reached += 1;
+                assert_eq!(flag.source, FlagSource::Remote);
+            } else {
+                // Outside the rollout the default stays in force
+                assert_eq!(flag.source, FlagSource::Default);
+                assert!(flag.evaluation[1].detail.contains("not reached"));
+            }
+        }
+        assert!((40..=80).contains(&reached), "{} of 200 reached", reached);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remote_document_is_verified_and_cached() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp, &identity)?;
+        let options = NetworkOptions::default();
+        let document = signed(&[("threads", FlagRule::new(false))]);
+        let uri = publish(&temp, &document);
+
+        // Signed by another publisher than the catalog
+        let catalog_key = SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        let catalog_key = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            catalog_key.to_bytes(),
+        );
+        assert!(service
+            .refresh(&uri, Some(&catalog_key), None, &options)
+            .await
+            .is_err());
+
+        // Unsigned
+        let mut unsigned = document.clone();
+        unsigned.signature = None;
+        let unsigned_uri = publish(&temp, &unsigned);
+        assert!(service
+            .refresh(&unsigned_uri, None, None, &options)
+            .await
+            .is_err());
+        assert_eq!(service.get(MAIL, "threads")?.source, FlagSource::Default);
+
+        let uri = publish(&temp, &document);
+        service
+            .refresh(&uri, document.public_key.as_deref(), None, &options)
+            .await?;
+        assert!(!service.get(MAIL, "threads")?.value);
+
+        // Offline after a restart: the cached document is in force
+        std::fs::remove_file(temp.path().join("flags.json"))?;
+        let restarted = FeatureFlagService::new(temp.path(), "user-123", &identity)?;
+        assert!(restarted.refresh(&uri, None, None, &options).await.is_err());
+        let flag = restarted.get(MAIL, "threads")?;
+        assert!(!flag.value);
+        assert_eq!(flag.source, FlagSource::Remote);
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_overrides_persist() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let service = service(&temp, &identity)?;
+        service.set_override(MAIL, "threads", Some(false))?;
+
+        let restarted = FeatureFlagService::new(temp.path(), "user-123", &identity)?;
+        let flag = restarted.get(MAIL, "threads")?;
+        assert!(!flag.value);
+        assert_eq!(flag.source, FlagSource::Local);
+
+        // Other users have their own overrides
+        let other = FeatureFlagService::new(temp.path(), "user-456", &identity)?;
+        assert!(other.get(MAIL, "threads")?.value);
+
+        // Overrides are unreadable without the user's identity
+        let stranger =
+            FeatureFlagService::new(temp.path(), "user-123", &RootIdentity::generate()?)?;
+        assert!(stranger.get(MAIL, "threads").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_update_publishes_change() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = RootIdentity::generate()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service(&temp, &identity)?.with_events(events);
+
+        service.apply_document(signed(&[("newComposer", FlagRule::new(true))]))?;
+        let AppEvent::FeatureFlagsChanged { user_id, change } = receiver.try_recv()? else {
+            panic!("expected a flag change");
+        };
+        assert_eq!(user_id, "user-123");
+        assert_eq!(change.app_id, MAIL);
+        assert_eq!(change.flags.len(), 1);
+        assert_eq!(change.flags[0].name, "newComposer");
+        assert!(change.flags[0].value);
+
+        // The same document again, or one that changes no value, is quiet
+        service.apply_document(signed(&[("newComposer", FlagRule::new(true))]))?;
+        service.apply_document(signed(&[
+            ("newComposer", FlagRule::new(true)),
+            ("threads", FlagRule::new(true)),
+        ]))?;
+        assert!(receiver.try_recv().is_err());
+
+        // A local override that changes a value is published too
+        service.set_override(MAIL, "threads", Some(false))?;
+        assert!(matches!(
+            receiver.try_recv()?,
+            AppEvent::FeatureFlagsChanged { .. }
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,830 @@
+//! Cross-device handoff
+//!
+//! Once two devices are paired, either can hand the other a small payload:
+//! a piece of text, a URL, or an app deep link ("send this to my other
+//! device").
+//!
+//! Handles:
+//! - Keying each pairing from its two device keys, with an X25519
+//!   agreement on the pairing's Ed25519 keys, so only the paired devices
+//!   can read what is sent over it
+//! - Delivering over the direct client-server connection when the target
+//!   is online
+//! - Otherwise parking the handoff in the pair's mailbox, an Autonomi
+//!   scratchpad at an address derived from the pair key, until the target
+//!   collects it
+//! - Expiring handoffs after a TTL, whichever way they travel
+//! - Notifying the user of received handoffs, and opening deep links with
+//!   [`AppsService::open_uri`]
+//!
+//! The network is behind [`HandoffTransport`], so tests can use in-memory
+//! devices and mailboxes.
+
+use anyhow::{bail, Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::encryption::CocoonEncryption;
+use crate::models::notification::Notification;
+use crate::services::apps::{AppsService, HandlerInfo};
+use crate::services::notifications::NotificationService;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Largest payload a handoff can carry, in bytes
+pub const MAX_HANDOFF_BYTES: usize = 64 * 1024;
+
+/// How long a handoff waits to be picked up, in seconds (1 day)
+pub const DEFAULT_HANDOFF_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// App id received handoffs are notified under
+pub const HANDOFF_APP_ID: &str = "com.osnova.handoff";
+
+/// Characters of a payload shown in its notification
+const SUMMARY_CHARS: usize = 120;
+
+/// Bytes of random salt prefixed to each sealed handoff or mailbox
+const SALT_BYTES: usize = 32;
+
+const PAIR_KEY_CONTEXT: &str = "osnova handoff 2025 pair key";
+const MAILBOX_CONTEXT: &str = "osnova handoff 2025 mailbox";
+
+/// What a handoff carries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum HandoffPayload {
+    /// Plain text
+    Text(String),
+    /// A web address
+    Url(String),
+    /// A URI opened by the installed app that handles its scheme
+    DeepLink(String),
+}
+
+impl HandoffPayload {
+    /// The payload's content
+    pub fn content(&self) -> &str {
+        match self {
+            HandoffPayload::Text(content)
+            | HandoffPayload::Url(content)
+            | HandoffPayload::DeepLink(content) => content,
+        }
+    }
+
+    /// Short description for a notification
+    fn summary(&self) -> String {
+        let content = self.content();
+        match content.char_indices().nth(SUMMARY_CHARS) {
+            Some((end, _)) => format!("{}…", &content[..end]),
+            None => content.to_string(),
+        }
+    }
+}
+
+/// A payload sent from one paired device to the other
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Handoff {
+    /// Unique handoff identifier
+    pub id: String,
+    /// Pairing the handoff was sent over
+    pub pairing_id: String,
+    /// Public key of the sending device (hex)
+    pub sender: String,
+    /// What was sent
+    pub payload: HandoffPayload,
+    /// Unix timestamp when the handoff was sent
+    pub sent_at: u64,
+    /// Unix timestamp after which the handoff is dropped
+    pub expires_at: u64,
+}
+
+impl Handoff {
+    fn is_expired_at(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// How a sent handoff left this device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// Handed to the target over the direct connection
+    Direct,
+    /// Parked in the pair's mailbox until the target collects it
+    Parked,
+}
+
+/// A handoff that was sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SentHandoff {
+    /// The handoff
+    pub handoff: Handoff,
+    /// How it was delivered
+    pub delivery: Delivery,
+}
+
+/// Address of a device pair's mailbox
+///
+/// Derived from the pair key, so only the two devices can find it. The
+/// bytes seed the owner key of the scratchpad that holds the mailbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MailboxAddress(pub [u8; 32]);
+
+impl fmt::Display for MailboxAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// Carries sealed handoffs between paired devices
+pub trait HandoffTransport: Send + Sync {
+    /// Hand sealed data to a paired device over the direct connection
+    ///
+    /// Returns `false`, not an error, when the device is not reachable.
+    fn deliver(&self, peer_public_key: &[u8], sealed: &[u8]) -> Result<bool>;
+
+    /// Read a mailbox; `None` if nothing was ever written to it
+    fn read_mailbox(&self, address: &MailboxAddress) -> Result<Option<Vec<u8>>>;
+
+    /// Replace a mailbox's content
+    fn write_mailbox(&self, address: &MailboxAddress, data: &[u8]) -> Result<()>;
+}
+
+/// Key shared by the two devices of a pairing
+struct PairKey {
+    key: [u8; 32],
+    peer_public_key: Vec<u8>,
+}
+
+impl PairKey {
+    fn mailbox(&self) -> MailboxAddress {
+        MailboxAddress(blake3::derive_key(MAILBOX_CONTEXT, &self.key))
+    }
+
+    /// Encrypt under a key derived from a fresh salt, so writes never
+    /// repeat a key and nonce
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_BYTES];
+        thread_rng().fill_bytes(&mut salt);
+
+        let mut sealed = salt.to_vec();
+        sealed.extend(self.cipher(&salt).encrypt(plaintext)?);
+        Ok(sealed)
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < SALT_BYTES {
+            bail!("Sealed handoff too short");
+        }
+        let (salt, ciphertext) = sealed.split_at(SALT_BYTES);
+        Ok(self.cipher(salt).decrypt(ciphertext)?)
+    }
+
+    fn cipher(&self, salt: &[u8]) -> CocoonEncryption {
+        CocoonEncryption::new(blake3::keyed_hash(&self.key, salt).as_bytes())
+    }
+}
+
+/// Cross-device handoff service
+///
+/// Provides OpenRPC methods:
+/// - `handoff.send` - Send a payload to a paired device
+/// - `handoff.pending` - Handoffs received and not yet dismissed
+/// - `handoff.dismiss` - Drop a received handoff
+/// - `handoff.open` - Open a received deep link
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::handoff::{HandoffPayload, HandoffService, HandoffTransport};
+/// use std::sync::Arc;
+///
+/// # fn example(transport: Arc<dyn HandoffTransport>) -> anyhow::Result<()> {
+/// let service = HandoffService::new("/tmp/osnova", &[7u8; 32], transport)?;
+///
+/// let url = HandoffPayload::Url("https://example.com".to_string());
+/// service.send("pairing-123", url)?;
+///
+/// // On the other device
+/// for handoff in service.pending_handoffs()? {
+///     println!("{}", handoff.payload.content());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct HandoffService {
+    storage: Mutex<SqlStorage>,
+    signing_key: SigningKey,
+    transport: Arc<dyn HandoffTransport>,
+    notifications: Option<Arc<NotificationService>>,
+    clock: SharedClock,
+    ttl_secs: u64,
+}
+
+impl HandoffService {
+    /// Create a new handoff service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database
+    /// * `device_secret` - Secret key (Ed25519 seed) of the public key this
+    ///   device presents in its pairings
+    /// * `transport` - Carries handoffs to paired devices and mailboxes
+    pub fn new<P: AsRef<Path>>(
+        storage_path: P,
+        device_secret: &[u8; 32],
+        transport: Arc<dyn HandoffTransport>,
+    ) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            signing_key: SigningKey::from_bytes(device_secret),
+            transport,
+            notifications: None,
+            clock: time::default_clock(),
+            ttl_secs: DEFAULT_HANDOFF_TTL_SECS,
+        })
+    }
+
+    /// Notify the user of received handoffs
+    pub fn with_notifications(mut self, notifications: Arc<NotificationService>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    /// Use a specific clock for send and expiry timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set how long sent handoffs wait to be picked up, in seconds
+    pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    /// Public key this device presents in its pairings
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// Send a payload to the device at the other end of a pairing (OpenRPC: handoff.send)
+    ///
+    /// Delivers directly when the device is online, and parks the handoff
+    /// in the pair's mailbox otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is larger than
+    /// [`MAX_HANDOFF_BYTES`], or the pairing is not an established pairing
+    /// of this device
+    pub fn send(&self, pairing_id: &str, payload: HandoffPayload) -> Result<SentHandoff> {
+        let size = payload.content().len();
+        if size > MAX_HANDOFF_BYTES {
+            bail!(
+                "Handoff payload is {} bytes; the limit is {}",
+                size,
+                MAX_HANDOFF_BYTES
+            );
+        }
+        let pair = self.pair_key(pairing_id)?;
+
+        let mut id = [0u8; 16];
+        thread_rng().fill_bytes(&mut id);
+        let now = self.clock.now_unix();
+        let handoff = Handoff {
+            id: hex::encode(id),
+            pairing_id: pairing_id.to_string(),
+            sender: hex::encode(self.public_key()),
+            payload,
+            sent_at: now,
+            expires_at: now + self.ttl_secs,
+        };
+
+        let sealed = pair.seal(&serde_json::to_vec(&handoff)?)?;
+        let delivery = if self.transport.deliver(&pair.peer_public_key, &sealed)? {
+            Delivery::Direct
+        } else {
+            let mut parked = self.read_mailbox(&pair)?;
+            parked.retain(|parked| !parked.is_expired_at(now));
+            parked.push(handoff.clone());
+            self.write_mailbox(&pair, &parked)?;
+            Delivery::Parked
+        };
+
+        Ok(SentHandoff { handoff, delivery })
+    }
+
+    /// Accept a handoff delivered over the direct connection
+    ///
+    /// Returns `None` if the handoff expired in transit or was already
+    /// received.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data was not sealed by the other device of
+    /// the pairing
+    pub fn receive(&self, pairing_id: &str, sealed: &[u8]) -> Result<Option<Handoff>> {
+        let pair = self.pair_key(pairing_id)?;
+        let plaintext = pair
+            .open(sealed)
+            .context("Handoff was not sealed for this pairing")?;
+        let handoff: Handoff =
+            serde_json::from_slice(&plaintext).context("Failed to parse handoff")?;
+
+        if handoff.pairing_id != pairing_id || handoff.sender != hex::encode(&pair.peer_public_key)
+        {
+            bail!("Handoff was not sent by the paired device");
+        }
+        self.accept(handoff)
+    }
+
+    /// Pick up the handoffs parked for this device in a pair's mailbox
+    ///
+    /// Expired handoffs are dropped from the mailbox; handoffs this device
+    /// parked for the other are left in place.
+    pub fn collect(&self, pairing_id: &str) -> Result<Vec<Handoff>> {
+        let pair = self.pair_key(pairing_id)?;
+        let now = self.clock.now_unix();
+        let peer = hex::encode(&pair.peer_public_key);
+
+        let parked = self.read_mailbox(&pair)?;
+        let count = parked.len();
+        let (incoming, outgoing): (Vec<Handoff>, Vec<Handoff>) = parked
+            .into_iter()
+            .filter(|handoff| !handoff.is_expired_at(now))
+            .partition(|handoff| handoff.sender == peer);
+        if outgoing.len() != count {
+            self.write_mailbox(&pair, &outgoing)?;
+        }
+
+        let mut received = Vec::new();
+        for handoff in incoming {
+            if let Some(handoff) = self.accept(handoff)? {
+                received.push(handoff);
+            }
+        }
+        Ok(received)
+    }
+
+    /// Pick up parked handoffs from every established pairing of this device
+    pub fn collect_all(&self) -> Result<Vec<Handoff>> {
+        let public_key = self.public_key();
+        let pairings = self
+            .storage
+            .lock()
+            .unwrap()
+            .list_pairing_sessions_by_status("established")?;
+
+        let mut received = Vec::new();
+        for pairing in pairings {
+            if pairing.server_public_key() == public_key
+                || pairing.device_public_key() == public_key
+            {
+                received.extend(self.collect(pairing.session_id())?);
+            }
+        }
+        Ok(received)
+    }
+
+    /// Handoffs received and not yet dismissed, oldest first (OpenRPC: handoff.pending)
+    ///
+    /// Expired handoffs are dropped.
+    pub fn pending_handoffs(&self) -> Result<Vec<Handoff>> {
+        let now = self.clock.now_unix();
+        let storage = self.storage.lock().unwrap();
+        storage.delete_expired_handoffs(now)?;
+
+        storage
+            .list_handoffs(now)?
+            .iter()
+            .map(|data| serde_json::from_str(data).context("Failed to parse handoff"))
+            .collect()
+    }
+
+    /// Drop a received handoff (OpenRPC: handoff.dismiss)
+    ///
+    /// Returns whether it was pending.
+    pub fn dismiss(&self, handoff_id: &str) -> Result<bool> {
+        self.storage.lock().unwrap().delete_handoff(handoff_id)
+    }
+
+    /// Open a received deep link with the app that handles it (OpenRPC: handoff.open)
+    ///
+    /// The handoff is dismissed once its app is launched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handoff is not pending, is not a deep link,
+    /// or no installed app handles its scheme
+    pub fn open(&self, handoff_id: &str, apps: &AppsService) -> Result<HandlerInfo> {
+        let data = self.storage.lock().unwrap().get_handoff(handoff_id)?;
+        let handoff: Handoff = match data {
+            Some(data) => serde_json::from_str(&data).context("Failed to parse handoff")?,
+            None => bail!("Handoff {} is not pending", handoff_id),
+        };
+        if handoff.is_expired_at(self.clock.now_unix()) {
+            bail!("Handoff {} has expired", handoff_id);
+        }
+        let HandoffPayload::DeepLink(uri) = &handoff.payload else {
+            bail!("Handoff {} is not a deep link", handoff_id);
+        };
+
+        let handler = apps.open_uri(uri)?;
+        self.dismiss(handoff_id)?;
+        Ok(handler)
+    }
+
+    /// Store a received handoff and notify the user
+    fn accept(&self, handoff: Handoff) -> Result<Option<Handoff>> {
+        if handoff.is_expired_at(self.clock.now_unix()) {
+            return Ok(None);
+        }
+        let data = serde_json::to_string(&handoff)?;
+        let inserted = self.storage.lock().unwrap().insert_handoff(
+            &handoff.id,
+            &handoff.pairing_id,
+            &data,
+            handoff.expires_at,
+        )?;
+        if !inserted {
+            return Ok(None);
+        }
+
+        if let Some(notifications) = &self.notifications {
+            let title = match handoff.payload {
+                HandoffPayload::Text(_) => "Text from your other device",
+                HandoffPayload::Url(_) => "Link from your other device",
+                HandoffPayload::DeepLink(_) => "Open from your other device",
+            };
+            // A full or muted notification center does not lose the handoff
+            let _ = notifications.post(
+                HANDOFF_APP_ID,
+                Notification::new(title, handoff.payload.summary())
+                    .with_action_route(format!("/handoff/{}", handoff.id))
+                    .with_tag(format!("handoff:{}", handoff.id)),
+            );
+        }
+        Ok(Some(handoff))
+    }
+
+    /// Derive the key this device shares with the other side of a pairing
+    fn pair_key(&self, pairing_id: &str) -> Result<PairKey> {
+        let pairing = self
+            .storage
+            .lock()
+            .unwrap()
+            .get_pairing_session(pairing_id)?
+            .with_context(|| format!("Pairing {} not found", pairing_id))?;
+        if !pairing.is_established() || pairing.is_expired_with(self.clock.as_ref()) {
+            bail!("Pairing {} is not established", pairing_id);
+        }
+
+        let public_key = self.public_key();
+        let peer_public_key = if pairing.server_public_key() == public_key {
+            pairing.device_public_key()
+        } else if pairing.device_public_key() == public_key {
+            pairing.server_public_key()
+        } else {
+            bail!("This device is not part of pairing {}", pairing_id);
+        };
+
+        // X25519 agreement on the Montgomery forms of the Ed25519 keys
+        let peer = VerifyingKey::try_from(peer_public_key)
+            .context("Paired device key is not a valid Ed25519 key")?;
+        let shared = peer
+            .to_montgomery()
+            .mul_clamped(self.signing_key.to_scalar_bytes());
+        if shared.to_bytes() == [0u8; 32] {
+            bail!("Paired device key has low order");
+        }
+
+        let mut material = shared.to_bytes().to_vec();
+        material.extend_from_slice(pairing_id.as_bytes());
+        Ok(PairKey {
+            key: blake3::derive_key(PAIR_KEY_CONTEXT, &material),
+            peer_public_key: peer_public_key.to_vec(),
+        })
+    }
+
+    fn read_mailbox(&self, pair: &PairKey) -> Result<Vec<Handoff>> {
+        let Some(sealed) = self.transport.read_mailbox(&pair.mailbox())? else {
+            return Ok(Vec::new());
+        };
+        let plaintext = pair
+            .open(&sealed)
+            .context("Failed to open handoff mailbox")?;
+        serde_json::from_slice(&plaintext).context("Failed to parse handoff mailbox")
+    }
+
+    fn write_mailbox(&self, pair: &PairKey, handoffs: &[Handoff]) -> Result<()> {
+        let sealed = pair.seal(&serde_json::to_vec(handoffs)?)?;
+        self.transport.write_mailbox(&pair.mailbox(), &sealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::notification::StoredNotification;
+    use crate::models::pairing::PairingSession;
+    use crate::services::notifications::NotificationFilter;
+    use crate::time::MockClock;
+    use std::collections::{HashMap, HashSet};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const START: u64 = 1_700_000_000;
+
+    /// Devices and mailboxes in memory; only online devices take deliveries
+    #[derive(Default)]
+    struct MockNetwork {
+        online: Mutex<HashSet<Vec<u8>>>,
+        delivered: Mutex<Vec<(Vec<u8>, Vec<u8>)>>,
+        mailboxes: Mutex<HashMap<MailboxAddress, Vec<u8>>>,
+    }
+
+    impl MockNetwork {
+        fn set_online(&self, device: &HandoffService, online: bool) {
+            let key = device.public_key().to_vec();
+            let mut devices = self.online.lock().unwrap();
+            if online {
+                devices.insert(key);
+            } else {
+                devices.remove(&key);
+            }
+        }
+
+        /// Sealed data delivered to a device
+        fn take_delivered(&self, device: &HandoffService) -> Vec<Vec<u8>> {
+            let key = device.public_key().to_vec();
+            let mut delivered = self.delivered.lock().unwrap();
+            let (taken, kept) = delivered.drain(..).partition(|(to, _)| *to == key);
+            *delivered = kept;
+            taken.into_iter().map(|(_, sealed)| sealed).collect()
+        }
+    }
+
+    impl HandoffTransport for MockNetwork {
+        fn deliver(&self, peer_public_key: &[u8], sealed: &[u8]) -> Result<bool> {
+            if !self.online.lock().unwrap().contains(peer_public_key) {
+                return Ok(false);
+            }
+            self.delivered
+                .lock()
+                .unwrap()
+                .push((peer_public_key.to_vec(), sealed.to_vec()));
+            Ok(true)
+        }
+
+        fn read_mailbox(&self, address: &MailboxAddress) -> Result<Option<Vec<u8>>> {
+            Ok(self.mailboxes.lock().unwrap().get(address).cloned())
+        }
+
+        fn write_mailbox(&self, address: &MailboxAddress, data: &[u8]) -> Result<()> {
+            self.mailboxes
+                .lock()
+                .unwrap()
+                .insert(*address, data.to_vec());
+            Ok(())
+        }
+    }
+
+    struct Device {
+        service: HandoffService,
+        temp: TempDir,
+    }
+
+    fn device(network: &Arc<MockNetwork>, secret: u8, clock: &Arc<MockClock>) -> Result<Device> {
+        let temp = TempDir::new()?;
+        let service = HandoffService::new(temp.path(), &[secret; 32], network.clone())?
+            .with_clock(clock.clone());
+        Ok(Device { service, temp })
+    }
+
+    /// Record an established pairing on both devices
+    fn pair(pairing_id: &str, server: &Device, client: &Device) -> Result<()> {
+        let mut pairing = PairingSession::new(
+            pairing_id,
+            &server.service.public_key(),
+            &client.service.public_key(),
+        )?;
+        pairing.mark_established_at(START);
+        for device in [server, client] {
+            device
+                .service
+                .storage
+                .lock()
+                .unwrap()
+                .upsert_pairing_session(&pairing)?;
+        }
+        Ok(())
+    }
+
+    fn setup() -> Result<(Arc<MockNetwork>, Arc<MockClock>, Device, Device)> {
+        let network = Arc::new(MockNetwork::default());
+        let clock = Arc::new(MockClock::new(START));
+        let server = device(&network, 1, &clock)?;
+        let client = device(&network, 2, &clock)?;
+        pair("pair-1", &server, &client)?;
+        Ok((network, clock, server, client))
+    }
+
+    fn text(content: &str) -> HandoffPayload {
+        HandoffPayload::Text(content.to_string())
+    }
+
+    #[test]
+    fn test_online_direct_delivery() -> Result<()> {
+        let (network, _clock, server, client) = setup()?;
+        let temp = TempDir::new()?;
+        let notifications = Arc::new(NotificationService::new(temp.path(), "user-1")?);
+        let client_service = client.service.with_notifications(notifications.clone());
+        network.set_online(&server.service, true);
+
+        let url = HandoffPayload::Url("https://example.com/article".to_string());
+        let sent = client_service.send("pair-1", url.clone())?;
+        assert_eq!(sent.delivery, Delivery::Direct);
+        assert_eq!(sent.handoff.expires_at, START + DEFAULT_HANDOFF_TTL_SECS);
+        assert!(network.mailboxes.lock().unwrap().is_empty());
+
+        let sealed = network.take_delivered(&server.service);
+        assert_eq!(sealed.len(), 1);
+        let received = server.service.receive("pair-1", &sealed[0])?.unwrap();
+        assert_eq!(received, sent.handoff);
+        assert_eq!(
+            server.service.pending_handoffs()?,
+            vec![sent.handoff.clone()]
+        );
+        // Redelivery is ignored
+        assert!(server.service.receive("pair-1", &sealed[0])?.is_none());
+
+        // Sending the other way notifies the client
+        network.set_online(&client_service, true);
+        server.service.send("pair-1", text("hello"))?;
+        let sealed = network.take_delivered(&client_service);
+        client_service.receive("pair-1", &sealed[0])?.unwrap();
+        let posted: Vec<StoredNotification> = notifications.list(&NotificationFilter::default())?;
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].app_id, HANDOFF_APP_ID);
+        assert_eq!(posted[0].notification.body, "hello");
+
+        // Oversized payloads are refused
+        let large = text(&"x".repeat(MAX_HANDOFF_BYTES + 1));
+        assert!(client_service.send("pair-1", large).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_offline_park_and_pickup() -> Result<()> {
+        let (network, _clock, server, client) = setup()?;
+
+        let first = client.service.send("pair-1", text("first"))?;
+        let second = client.service.send("pair-1", text("second"))?;
+        assert_eq!(first.delivery, Delivery::Parked);
+        assert_eq!(second.delivery, Delivery::Parked);
+        // Both devices of the pair use one mailbox
+        server.service.send("pair-1", text("for the client"))?;
+        assert_eq!(network.mailboxes.lock().unwrap().len(), 1);
+
+        // Parked contents are sealed
+        let mailbox = network.mailboxes.lock().unwrap().values().next().cloned();
+        let mailbox = String::from_utf8_lossy(&mailbox.unwrap()).to_string();
+        assert!(!mailbox.contains("first"));
+
+        let collected = server.service.collect_all()?;
+        assert_eq!(collected, vec![first.handoff, second.handoff]);
+        assert_eq!(server.service.pending_handoffs()?.len(), 2);
+        assert!(server.service.collect("pair-1")?.is_empty());
+
+        // The server's own handoff stays parked for the client
+        let collected = client.service.collect("pair-1")?;
+        assert_eq!(collected.len(), 1);
+        assert_eq!(collected[0].payload, text("for the client"));
+
+        let pending = server.service.pending_handoffs()?;
+        assert!(server.service.dismiss(&pending[0].id)?);
+        assert_eq!(server.service.pending_handoffs()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_third_device_cannot_decrypt() -> Result<()> {
+        let (network, clock, server, client) = setup()?;
+        let intruder = device(&network, 3, &clock)?;
+        pair("pair-2", &server, &intruder)?;
+        network.set_online(&server.service, true);
+
+        client.service.send("pair-1", text("private"))?;
+        let sealed = network.take_delivered(&server.service);
+
+        // Not part of the pairing
+        let mut pairing = PairingSession::new(
+            "pair-1",
+            &server.service.public_key(),
+            &client.service.public_key(),
+        )?;
+        pairing.mark_established_at(START);
+        intruder
+            .service
+            .storage
+            .lock()
+            .unwrap()
+            .upsert_pairing_session(&pairing)?;
+        assert!(intruder.service.receive("pair-1", &sealed[0]).is_err());
+        // Its own pairing's key does not open it either
+        assert!(intruder.service.receive("pair-2", &sealed[0]).is_err());
+        // Nor does the server's key for another pairing
+        assert!(server.service.receive("pair-2", &sealed[0]).is_err());
+        assert!(server.service.receive("pair-1", &sealed[0])?.is_some());
+
+        // Mailboxes of different pairs are distinct
+        network.set_online(&server.service, false);
+        client.service.send("pair-1", text("parked"))?;
+        intruder.service.send("pair-2", text("parked"))?;
+        assert_eq!(network.mailboxes.lock().unwrap().len(), 2);
+        assert!(intruder.service.collect("pair-2")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_expiry() -> Result<()> {
+        let (network, clock, server, client) = setup()?;
+        let client_service = client.service.with_ttl(600);
+
+        let parked = client_service.send("pair-1", text("soon stale"))?;
+        assert_eq!(parked.handoff.expires_at, START + 600);
+        network.set_online(&server.service, true);
+        client_service.send("pair-1", text("in flight"))?;
+        let sealed = network.take_delivered(&server.service);
+
+        clock.advance(Duration::from_secs(600));
+        // Expired in the mailbox and in transit
+        assert!(server.service.collect("pair-1")?.is_empty());
+        assert!(server.service.receive("pair-1", &sealed[0])?.is_none());
+        let mailbox = network.mailboxes.lock().unwrap().values().next().cloned();
+        assert!(mailbox.is_some());
+
+        // Expired after receipt
+        client_service.send("pair-1", text("read me"))?;
+        let sealed = network.take_delivered(&server.service);
+        server.service.receive("pair-1", &sealed[0])?;
+        assert_eq!(server.service.pending_handoffs()?.len(), 1);
+        clock.advance(Duration::from_secs(600));
+        assert!(server.service.pending_handoffs()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deep_link_opens_handler_on_receipt() -> Result<()> {
+        let (network, _clock, server, client) = setup()?;
+        let apps = AppsService::new(server.temp.path())?;
+        let wallet = OsnovaApplication::new(
+            "com.test.wallet",
+            "Wallet",
+            "1.0.0",
+            "ant://icon",
+            "Wallet",
+            vec![],
+        )?
+        .with_uri_schemes(["ethereum"]);
+        apps.install_application(&wallet)?;
+        apps.record_consent("com.test.wallet", true, vec![])?;
+        network.set_online(&server.service, true);
+
+        let uri = "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359?value=1e18";
+        let link = client
+            .service
+            .send("pair-1", HandoffPayload::DeepLink(uri.to_string()))?;
+        let plain = client.service.send("pair-1", text("not a link"))?;
+        for sealed in network.take_delivered(&server.service) {
+            server.service.receive("pair-1", &sealed)?;
+        }
+
+        assert!(server.service.open(&plain.handoff.id, &apps).is_err());
+        let handler = server.service.open(&link.handoff.id, &apps)?;
+        assert_eq!(handler.app_id, "com.test.wallet");
+        assert_eq!(handler.initial_route, uri);
+
+        // Opened handoffs are no longer pending
+        let pending = server.service.pending_handoffs()?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, plain.handoff.id);
+        assert!(server.service.open(&link.handoff.id, &apps).is_err());
+
+        Ok(())
+    }
+}
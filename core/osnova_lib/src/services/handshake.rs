@@ -0,0 +1,697 @@
+//! Launch handshake between frontends and backend components
+//!
+//! Without a handshake a frontend only finds out its backend is missing or
+//! outdated from opaque RPC failures. When an app is launched, the apps
+//! service records a [`LaunchDescriptor`] with the version and socket
+//! endpoint expected of each backend; the frontend reads it (the shell's
+//! `apps_launch_descriptor` command) and follows readiness changes as
+//! [`AppEvent::BackendReadinessChanged`], forwarded as
+//! [`BACKEND_READINESS_EVENT`].
+//!
+//! A backend reports itself with `component.ready` over the core RPC
+//! socket, whose address it receives in [`RPC_ADDR_ENV`]. A backend that
+//! does not report within the timeout is marked failed, and one reporting
+//! a different version than the manifest pins is marked as a version
+//! mismatch. Descriptors live in memory only; a restart relaunches
+//! everything anyway.
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::models::health::BackendHealth;
+use crate::services::events::{AppEvent, EventBus};
+use crate::time;
+
+/// How long backends have to report ready after a launch
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event name used when surfacing readiness changes to frontends
+pub const BACKEND_READINESS_EVENT: &str = "backend-readiness";
+
+/// RPC method backends call once they accept requests
+pub const COMPONENT_READY_METHOD: &str = "component.ready";
+
+/// Environment variable holding the component id a backend reports
+pub const COMPONENT_ID_ENV: &str = "OSNOVA_COMPONENT_ID";
+
+/// Environment variable holding the socket a backend should listen on
+pub const SOCKET_PATH_ENV: &str = "OSNOVA_SOCKET_PATH";
+
+/// Environment variable holding the address of the core RPC socket
+pub const RPC_ADDR_ENV: &str = "OSNOVA_RPC_ADDR";
+
+/// Readiness of a launched backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ReadinessState {
+    /// Launched, not reported yet
+    Starting,
+    /// Reported ready with the expected version
+    Ready,
+    /// Did not start or did not report in time
+    Failed {
+        /// What went wrong
+        reason: String,
+    },
+    /// Reported ready with a different version than the manifest pins
+    VersionMismatch {
+        /// Version from the app manifest
+        expected: String,
+        /// Version the backend reported
+        actual: String,
+    },
+}
+
+impl ReadinessState {
+    /// Whether the backend has reported or given up
+    pub fn is_settled(&self) -> bool {
+        !matches!(self, Self::Starting)
+    }
+}
+
+/// A backend expected by a launched app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendReadiness {
+    /// Component identifier the backend reports with
+    pub component_id: String,
+    /// Component name from the manifest
+    pub name: String,
+    /// Version the manifest pins
+    pub expected_version: String,
+    /// Socket the backend listens on
+    pub socket_path: Option<PathBuf>,
+    /// Whether the backend is a shared component used by other apps too
+    pub shared: bool,
+    /// Current readiness
+    pub state: ReadinessState,
+    /// Capabilities the backend reported
+    pub capabilities: Vec<String>,
+    /// Health of the running backend, once it declares a health check and
+    /// was checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<BackendHealth>,
+}
+
+impl BackendReadiness {
+    /// A backend that has just been launched
+    pub fn starting(
+        component_id: impl Into<String>,
+        name: impl Into<String>,
+        expected_version: impl Into<String>,
+    ) -> Self {
+        Self {
+            component_id: component_id.into(),
+            name: name.into(),
+            expected_version: expected_version.into(),
+            socket_path: None,
+            shared: false,
+            state: ReadinessState::Starting,
+            capabilities: Vec::new(),
+            health: None,
+        }
+    }
+
+    /// Set the socket the backend listens on
+    pub fn with_socket_path(mut self, socket_path: impl Into<PathBuf>) -> Self {
+        self.socket_path = Some(socket_path.into());
+        self
+    }
+
+    /// Mark the backend as a shared component
+    pub fn with_shared(mut self, shared: bool) -> Self {
+        self.shared = shared;
+        self
+    }
+}
+
+/// How a launch got its backends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StartKind {
+    /// Backends were spawned for the launch
+    #[default]
+    Cold,
+    /// Backends kept warm since the app's UI last closed were reused; only
+    /// the frontend was reloaded
+    Warm,
+}
+
+/// Backends of a launched app and their readiness
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchDescriptor {
+    /// Application identifier
+    pub app_id: String,
+    /// Unix timestamp of the launch
+    pub launched_at: u64,
+    /// Whether the backends were spawned or reused
+    #[serde(default)]
+    pub start: StartKind,
+    /// Backends the frontend depends on
+    pub backends: Vec<BackendReadiness>,
+}
+
+impl LaunchDescriptor {
+    /// Whether every backend reported ready
+    pub fn is_ready(&self) -> bool {
+        self.backends
+            .iter()
+            .all(|backend| backend.state == ReadinessState::Ready)
+    }
+
+    /// Whether every backend has reported or given up
+    pub fn is_settled(&self) -> bool {
+        self.backends
+            .iter()
+            .all(|backend| backend.state.is_settled())
+    }
+
+    /// Backends that failed or run the wrong version
+    pub fn failures(&self) -> Vec<&BackendReadiness> {
+        self.backends
+            .iter()
+            .filter(|backend| backend.state.is_settled() && backend.state != ReadinessState::Ready)
+            .collect()
+    }
+}
+
+/// Parameters of `component.ready`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentReady {
+    /// Component identifier, as passed in [`COMPONENT_ID_ENV`]
+    pub component_id: String,
+    /// Version the backend runs
+    pub version: String,
+    /// Capabilities the backend offers
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Socket a backend listens on, under `dir`
+///
+/// Named by a hash so the path stays within the platform's socket path
+/// limit whatever the owner and component ids are.
+pub fn socket_path(dir: &Path, owner: &str, component_id: &str) -> PathBuf {
+    let digest = blake3::hash(format!("{}\n{}", owner, component_id).as_bytes());
+    dir.join(format!("{}.sock", &digest.to_hex()[..16]))
+}
+
+/// Readiness of launched backends, shared by the apps service and the RPC
+/// server
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::handshake::{BackendReadiness, ComponentReady, LaunchHandshake};
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let handshake = LaunchHandshake::new();
+/// handshake.begin(
+///     "com.example.app",
+///     vec![BackendReadiness::starting("ant://backend", "Backend", "1.0.0")],
+/// );
+///
+/// // Reported by the backend over the RPC socket
+/// handshake.component_ready(ComponentReady {
+///     component_id: "ant://backend".to_string(),
+///     version: "1.0.0".to_string(),
+///     capabilities: vec![],
+/// })?;
+///
+/// assert!(handshake.wait_ready("com.example.app").await?.is_ready());
+/// # Ok(())
+/// # }
+/// ```
+pub struct LaunchHandshake {
+    descriptors: Mutex<HashMap<String, LaunchDescriptor>>,
+    changed: Notify,
+    events: Option<EventBus>,
+    timeout: Duration,
+    rpc_address: Option<SocketAddr>,
+}
+
+impl LaunchHandshake {
+    /// Create a handshake with the default ready timeout
+    pub fn new() -> Self {
+        Self {
+            descriptors: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+            events: None,
+            timeout: DEFAULT_READY_TIMEOUT,
+            rpc_address: None,
+        }
+    }
+
+    /// Publish readiness changes on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Override how long backends have to report ready
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Address of the RPC socket backends report to
+    pub fn with_rpc_address(mut self, rpc_address: SocketAddr) -> Self {
+        self.rpc_address = Some(rpc_address);
+        self
+    }
+
+    /// Address of the RPC socket backends report to, if one is served
+    pub fn rpc_address(&self) -> Option<SocketAddr> {
+        self.rpc_address
+    }
+
+    /// Record the backends of an app being launched
+    ///
+    /// A shared backend that already reported ready for another app keeps
+    /// its state, since the running instance is reused and will not report
+    /// again.
+    pub fn begin(&self, app_id: &str, backends: Vec<BackendReadiness>) -> LaunchDescriptor {
+        let mut descriptors = self.descriptors.lock().unwrap();
+        let backends = backends
+            .into_iter()
+            .map(|mut backend| {
+                if backend.shared {
+                    let running = descriptors.values().flat_map(|d| &d.backends).find(|b| {
+                        b.shared
+                            && b.component_id == backend.component_id
+                            && b.state == ReadinessState::Ready
+                    });
+                    if let Some(running) = running {
+                        backend.state = ReadinessState::Ready;
+                        backend.capabilities = running.capabilities.clone();
+                    }
+                }
+                backend
+            })
+            .collect();
+
+        let descriptor = LaunchDescriptor {
+            app_id: app_id.to_string(),
+            launched_at: time::now_unix(),
+            start: StartKind::Cold,
+            backends,
+        };
+        descriptors.insert(app_id.to_string(), descriptor.clone());
+        drop(descriptors);
+
+        for backend in &descriptor.backends {
+            self.publish(app_id, &backend.component_id, &backend.state);
+        }
+        self.changed.notify_waiters();
+        descriptor
+    }
+
+    /// Launch descriptor of an app, if it was launched
+    pub fn descriptor(&self, app_id: &str) -> Option<LaunchDescriptor> {
+        self.descriptors.lock().unwrap().get(app_id).cloned()
+    }
+
+    /// Record a launch that reuses an app's warm backends
+    ///
+    /// The backends keep the readiness they reported, since the live
+    /// processes will not report again. Returns `None` if the app has no
+    /// descriptor to resume.
+    pub fn resume(&self, app_id: &str) -> Option<LaunchDescriptor> {
+        let mut descriptors = self.descriptors.lock().unwrap();
+        let descriptor = descriptors.get_mut(app_id)?;
+        descriptor.launched_at = time::now_unix();
+        descriptor.start = StartKind::Warm;
+        let descriptor = descriptor.clone();
+        drop(descriptors);
+
+        self.changed.notify_waiters();
+        Some(descriptor)
+    }
+
+    /// Forget an app's descriptor, e.g. when it stops
+    pub fn end(&self, app_id: &str) {
+        self.descriptors.lock().unwrap().remove(app_id);
+        self.changed.notify_waiters();
+    }
+
+    /// Record a backend reporting ready (OpenRPC: component.ready)
+    ///
+    /// Updates every launched app waiting on the component and returns its
+    /// new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no launched app expects the component
+    pub fn component_ready(&self, report: ComponentReady) -> Result<ReadinessState> {
+        let mut changes = Vec::new();
+        let mut state = None;
+        {
+            let mut descriptors = self.descriptors.lock().unwrap();
+            for descriptor in descriptors.values_mut() {
+                for backend in &mut descriptor.backends {
+                    if backend.component_id != report.component_id {
+                        continue;
+                    }
+                    let next = if backend.expected_version == report.version {
+                        ReadinessState::Ready
+                    } else {
+                        ReadinessState::VersionMismatch {
+                            expected: backend.expected_version.clone(),
+                            actual: report.version.clone(),
+                        }
+                    };
+                    backend.capabilities = report.capabilities.clone();
+                    if backend.state != next {
+                        backend.state = next.clone();
+                        changes.push((descriptor.app_id.clone(), next.clone()));
+                    }
+                    state = Some(next);
+                }
+            }
+        }
+
+        let Some(state) = state else {
+            bail!(
+                "Component {} reported ready but no launched app expects it",
+                report.component_id
+            );
+        };
+        for (app_id, state) in &changes {
+            self.publish(app_id, &report.component_id, state);
+        }
+        self.changed.notify_waiters();
+        Ok(state)
+    }
+
+    /// Mark a backend of an app as failed, e.g. when it could not be spawned
+    pub fn fail(&self, app_id: &str, component_id: &str, reason: &str) {
+        let state = ReadinessState::Failed {
+            reason: reason.to_string(),
+        };
+        let changed = self.transition(app_id, component_id, state.clone());
+        if changed {
+            self.publish(app_id, component_id, &state);
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Mark a backend of an app as starting again, e.g. after it was
+    /// restarted for failing its health checks
+    pub fn restart(&self, app_id: &str, component_id: &str) {
+        let state = ReadinessState::Starting;
+        if self.transition(app_id, component_id, state.clone()) {
+            self.publish(app_id, component_id, &state);
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Record the health of a backend of an app
+    pub fn set_health(&self, app_id: &str, health: BackendHealth) {
+        let mut descriptors = self.descriptors.lock().unwrap();
+        if let Some(backend) = descriptors.get_mut(app_id).and_then(|d| {
+            d.backends
+                .iter_mut()
+                .find(|b| b.component_id == health.component_id)
+        }) {
+            backend.health = Some(health);
+        }
+    }
+
+    /// Handle a `component.ready` RPC request
+    pub fn handle_ready(&self, params: Value) -> Result<Value> {
+        let report: ComponentReady = serde_json::from_value(params)?;
+        Ok(serde_json::to_value(self.component_ready(report)?)?)
+    }
+
+    /// Wait until every backend of an app reported, or the timeout elapsed
+    ///
+    /// Backends still starting at the timeout are marked failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming each backend that failed or runs the wrong
+    /// version, or if the app was not launched
+    pub async fn wait_ready(&self, app_id: &str) -> Result<LaunchDescriptor> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            // Register for the wakeup before checking, so a change between
+            // the check and the wait is not missed
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let Some(descriptor) = self.descriptor(app_id) else {
+                bail!("Application {} was not launched", app_id);
+            };
+            if descriptor.is_settled() {
+                return Self::check_ready(descriptor);
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                break;
+            }
+        }
+
+        let reason = format!(
+            "did not report ready within {}s",
+            self.timeout.as_secs_f32()
+        );
+        let Some(descriptor) = self.descriptor(app_id) else {
+            bail!("Application {} was not launched", app_id);
+        };
+        for backend in &descriptor.backends {
+            if !backend.state.is_settled() {
+                self.fail(app_id, &backend.component_id, &reason);
+            }
+        }
+        match self.descriptor(app_id) {
+            Some(descriptor) => Self::check_ready(descriptor),
+            None => bail!("Application {} was not launched", app_id),
+        }
+    }
+
+    fn check_ready(descriptor: LaunchDescriptor) -> Result<LaunchDescriptor> {
+        let failures: Vec<String> = descriptor
+            .failures()
+            .iter()
+            .map(|backend| match &backend.state {
+                ReadinessState::VersionMismatch { expected, actual } => format!(
+                    "{} runs version {} but the app requires {}",
+                    backend.name, actual, expected
+                ),
+                ReadinessState::Failed { reason } => format!("{} {}", backend.name, reason),
+                _ => backend.name.clone(),
+            })
+            .collect();
+        if !failures.is_empty() {
+            bail!(
+                "Backend of {} is not available: {}",
+                descriptor.app_id,
+                failures.join("; ")
+            );
+        }
+        Ok(descriptor)
+    }
+
+    /// Set a backend's state, returning whether it changed
+    fn transition(&self, app_id: &str, component_id: &str, state: ReadinessState) -> bool {
+        let mut descriptors = self.descriptors.lock().unwrap();
+        let Some(backend) = descriptors.get_mut(app_id).and_then(|d| {
+            d.backends
+                .iter_mut()
+                .find(|b| b.component_id == component_id)
+        }) else {
+            return false;
+        };
+        if backend.state == state {
+            return false;
+        }
+        backend.state = state;
+        true
+    }
+
+    fn publish(&self, app_id: &str, component_id: &str, state: &ReadinessState) {
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::BackendReadinessChanged {
+                app_id: app_id.to_string(),
+                component_id: component_id.to_string(),
+                state: state.clone(),
+            });
+        }
+    }
+}
+
+impl Default for LaunchHandshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::Receiver;
+
+    fn ready(component_id: &str, version: &str) -> ComponentReady {
+        ComponentReady {
+            component_id: component_id.to_string(),
+            version: version.to_string(),
+            capabilities: vec!["sync".to_string()],
+        }
+    }
+
+    fn next_state(events: &mut Receiver<AppEvent>) -> ReadinessState {
+        match events.try_recv().unwrap() {
+            AppEvent::BackendReadinessChanged { state, .. } => state,
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ready_flow_emits_transitions() -> Result<()> {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let handshake = LaunchHandshake::new().with_events(bus);
+
+        handshake.begin(
+            "com.test.app",
+            vec![BackendReadiness::starting(
+                "ant://backend",
+                "Backend",
+                "1.0.0",
+            )],
+        );
+        assert_eq!(next_state(&mut events), ReadinessState::Starting);
+        assert!(!handshake.descriptor("com.test.app").unwrap().is_settled());
+
+        let state =
+            handshake.handle_ready(serde_json::to_value(ready("ant://backend", "1.0.0"))?)?;
+        assert_eq!(state, serde_json::json!({ "state": "ready" }));
+        assert_eq!(next_state(&mut events), ReadinessState::Ready);
+
+        let descriptor = handshake.wait_ready("com.test.app").await?;
+        assert!(descriptor.is_ready());
+        assert_eq!(descriptor.backends[0].capabilities, vec!["sync"]);
+
+        // Reporting again changes nothing and emits nothing
+        handshake.component_ready(ready("ant://backend", "1.0.0"))?;
+        assert!(events.try_recv().is_err());
+
+        assert!(handshake
+            .component_ready(ready("ant://other", "1.0.0"))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_marks_backend_failed() -> Result<()> {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let handshake = LaunchHandshake::new()
+            .with_events(bus)
+            .with_timeout(Duration::from_millis(50));
+        handshake.begin(
+            "com.test.app",
+            vec![BackendReadiness::starting(
+                "ant://backend",
+                "Backend",
+                "1.0.0",
+            )],
+        );
+        next_state(&mut events);
+
+        let err = handshake.wait_ready("com.test.app").await.unwrap_err();
+        assert!(err.to_string().contains("Backend did not report ready"));
+        assert!(matches!(
+            next_state(&mut events),
+            ReadinessState::Failed { .. }
+        ));
+        assert!(matches!(
+            handshake.descriptor("com.test.app").unwrap().backends[0].state,
+            ReadinessState::Failed { .. }
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_version_mismatch_is_detected() -> Result<()> {
+        let handshake = LaunchHandshake::new();
+        handshake.begin(
+            "com.test.app",
+            vec![BackendReadiness::starting(
+                "ant://backend",
+                "Backend",
+                "1.0.0",
+            )],
+        );
+
+        let waiting = handshake.wait_ready("com.test.app");
+        let report = async {
+            tokio::task::yield_now().await;
+            handshake.component_ready(ready("ant://backend", "2.0.0"))
+        };
+        let (waited, reported) = tokio::join!(waiting, report);
+
+        let expected = ReadinessState::VersionMismatch {
+            expected: "1.0.0".to_string(),
+            actual: "2.0.0".to_string(),
+        };
+        assert_eq!(reported?, expected);
+        let err = waited.unwrap_err().to_string();
+        assert!(err.contains("runs version 2.0.0 but the app requires 1.0.0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_backend_keeps_ready_state() -> Result<()> {
+        let handshake = LaunchHandshake::new();
+        let shared = || BackendReadiness::starting("ant://sync", "Sync", "1.0.0").with_shared(true);
+        handshake.begin("com.test.a", vec![shared()]);
+        handshake.component_ready(ready("ant://sync", "1.0.0"))?;
+
+        let descriptor = handshake.begin("com.test.b", vec![shared()]);
+        assert!(descriptor.is_ready());
+
+        assert_ne!(
+            socket_path(Path::new("/run"), "com.test.a", "ant://sync"),
+            socket_path(Path::new("/run"), "com.test.b", "ant://sync")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_reported_readiness() -> Result<()> {
+        let handshake = LaunchHandshake::new();
+        assert!(handshake.resume("com.test.app").is_none());
+
+        let descriptor = handshake.begin(
+            "com.test.app",
+            vec![BackendReadiness::starting(
+                "ant://backend",
+                "Backend",
+                "1.0.0",
+            )],
+        );
+        assert_eq!(descriptor.start, StartKind::Cold);
+        handshake.component_ready(ready("ant://backend", "1.0.0"))?;
+
+        // A warm launch is ready at once; the backend does not report again
+        let descriptor = handshake.resume("com.test.app").unwrap();
+        assert_eq!(descriptor.start, StartKind::Warm);
+        assert!(handshake.wait_ready("com.test.app").await?.is_ready());
+        Ok(())
+    }
+}
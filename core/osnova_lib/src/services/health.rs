@@ -0,0 +1,523 @@
+//! Health of running backends and what to do about unhealthy ones
+//!
+//! Backends declaring a [`HealthCheck`] are checked at its interval by
+//! [`AppsService::check_backend_health`](crate::services::AppsService::check_backend_health).
+//! The [`HealthMonitor`] keeps each backend's state and decides the next
+//! step:
+//!
+//! - A backend failing `failureThreshold` checks in a row becomes
+//!   [`HealthStatus::Unhealthy`]; a passing check makes it healthy again.
+//! - The [`HealthPolicy`] in the runtime settings says what follows: only
+//!   notify, restart with a backoff doubling after each restart, or restart
+//!   and block the backend once it was restarted `maxRestarts` times.
+//!
+//! Every change to or from unhealthy or blocked is a [`HealthTransition`],
+//! published as [`AppEvent::BackendHealthChanged`](crate::services::AppEvent)
+//! (forwarded to frontends as [`BACKEND_HEALTH_EVENT`]) and kept in the
+//! app's health history. Launching an app again starts its backends over.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::components::health::HealthCheck;
+use crate::models::health::{BackendHealth, HealthStatus, HealthTransition};
+use crate::time::{self, SharedClock};
+
+/// Event name used when surfacing health transitions to frontends
+pub const BACKEND_HEALTH_EVENT: &str = "backend-health";
+
+/// How often the shell looks for backends due for a health check
+pub const DEFAULT_HEALTH_TICK: Duration = Duration::from_secs(5);
+
+/// Default seconds before the first restart of an unhealthy backend
+pub const DEFAULT_RESTART_BACKOFF_SECS: u64 = 5;
+
+/// Default cap on the seconds between restarts
+pub const DEFAULT_MAX_RESTART_BACKOFF_SECS: u64 = 300;
+
+/// Default restarts before a backend is blocked
+pub const DEFAULT_MAX_RESTARTS: u32 = 3;
+
+/// Runs one health check of a backend listening on a socket
+pub type HealthProber = Arc<
+    dyn Fn(HealthCheck, PathBuf) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// What is done about an unhealthy backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthAction {
+    /// Only publish the transition
+    Notify,
+    /// Restart the backend, waiting longer after each restart
+    Restart,
+    /// Restart like [`Restart`](Self::Restart), then stop and block the
+    /// backend once it was restarted `max_restarts` times
+    RestartThenBlock,
+}
+
+/// How unhealthy backends are handled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HealthPolicy {
+    /// What is done about an unhealthy backend
+    pub action: HealthAction,
+    /// Seconds from becoming unhealthy to the first restart
+    pub restart_backoff_secs: u64,
+    /// Cap on the backoff, which doubles after every restart
+    pub max_restart_backoff_secs: u64,
+    /// Restarts before a backend is blocked, with
+    /// [`HealthAction::RestartThenBlock`]
+    pub max_restarts: u32,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            action: HealthAction::Restart,
+            restart_backoff_secs: DEFAULT_RESTART_BACKOFF_SECS,
+            max_restart_backoff_secs: DEFAULT_MAX_RESTART_BACKOFF_SECS,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+        }
+    }
+}
+
+impl HealthPolicy {
+    /// Seconds to wait before the next restart, after `restarts` restarts
+    pub fn backoff_secs(&self, restarts: u32) -> u64 {
+        let factor = 1u64.checked_shl(restarts).unwrap_or(u64::MAX);
+        self.restart_backoff_secs
+            .saturating_mul(factor)
+            .min(self.max_restart_backoff_secs)
+    }
+}
+
+/// What to do next about a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStep {
+    /// Nothing is due
+    Wait,
+    /// Run its health check
+    Probe,
+    /// Restart it
+    Restart,
+    /// Stop it and leave it stopped
+    Block,
+}
+
+/// State of one backend
+struct Tracker {
+    health: BackendHealth,
+    next_probe_at: u64,
+    unhealthy_since: Option<u64>,
+}
+
+/// Health state of running backends, keyed by app and component
+///
+/// # Example
+///
+/// ```rust
+/// use osnova_lib::components::health::HealthCheck;
+/// use osnova_lib::models::health::HealthStatus;
+/// use osnova_lib::services::health::{HealthMonitor, HealthPolicy, HealthStep};
+///
+/// let check = HealthCheck::from_config(&serde_json::json!({
+///     "probe": "socket", "failureThreshold": 1
+/// }))
+/// .unwrap();
+/// let policy = HealthPolicy::default();
+/// let monitor = HealthMonitor::new();
+///
+/// assert_eq!(monitor.next_step("app", "backend", &policy), HealthStep::Probe);
+/// let transition = monitor.record_probe("app", "backend", &check, Err("refused".into()));
+/// assert_eq!(transition.unwrap().to, HealthStatus::Unhealthy);
+/// ```
+pub struct HealthMonitor {
+    trackers: Mutex<HashMap<(String, String), Tracker>>,
+    clock: SharedClock,
+}
+
+impl HealthMonitor {
+    /// Create a monitor tracking no backends
+    pub fn new() -> Self {
+        Self {
+            trackers: Mutex::new(HashMap::new()),
+            clock: time::default_clock(),
+        }
+    }
+
+    /// Read time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Current Unix time of the monitor's clock
+    pub fn now(&self) -> u64 {
+        self.clock.now_unix()
+    }
+
+    /// What to do next about a backend under `policy`
+    ///
+    /// Backends not seen before are due for a check.
+    pub fn next_step(&self, app_id: &str, component_id: &str, policy: &HealthPolicy) -> HealthStep {
+        let now = self.now();
+        let trackers = self.trackers.lock().unwrap();
+        let Some(tracker) = trackers.get(&(app_id.to_string(), component_id.to_string())) else {
+            return HealthStep::Probe;
+        };
+        let probe_due = now >= tracker.next_probe_at;
+
+        match (tracker.health.status, tracker.unhealthy_since) {
+            (HealthStatus::Blocked, _) => HealthStep::Wait,
+            (HealthStatus::Unhealthy, Some(since)) if policy.action != HealthAction::Notify => {
+                let restarts = tracker.health.restarts;
+                if policy.action == HealthAction::RestartThenBlock
+                    && restarts >= policy.max_restarts
+                {
+                    HealthStep::Block
+                } else if now >= since.saturating_add(policy.backoff_secs(restarts)) {
+                    HealthStep::Restart
+                } else if probe_due {
+                    HealthStep::Probe
+                } else {
+                    HealthStep::Wait
+                }
+            }
+            _ if probe_due => HealthStep::Probe,
+            _ => HealthStep::Wait,
+        }
+    }
+
+    /// Record the result of a check, returning the transition it caused
+    pub fn record_probe(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        check: &HealthCheck,
+        result: std::result::Result<(), String>,
+    ) -> Option<HealthTransition> {
+        let now = self.now();
+        let mut trackers = self.trackers.lock().unwrap();
+        let tracker = Self::tracker(&mut trackers, app_id, component_id);
+        let health = &mut tracker.health;
+        health.checked_at = Some(now);
+        tracker.next_probe_at = now.saturating_add(check.interval_secs);
+
+        let from = health.status;
+        let (to, reason) = match result {
+            Ok(()) => {
+                health.consecutive_failures = 0;
+                health.last_error = None;
+                tracker.unhealthy_since = None;
+                (HealthStatus::Healthy, "Health check passed".to_string())
+            }
+            Err(error) => {
+                health.consecutive_failures += 1;
+                health.last_error = Some(error.clone());
+                if health.consecutive_failures < check.failure_threshold {
+                    return None;
+                }
+                tracker.unhealthy_since.get_or_insert(now);
+                (
+                    HealthStatus::Unhealthy,
+                    format!(
+                        "{} health checks failed in a row: {}",
+                        health.consecutive_failures, error
+                    ),
+                )
+            }
+        };
+        health.status = to;
+
+        // Only changes to or from unhealthy are transitions
+        let noted =
+            from != to && (from == HealthStatus::Unhealthy || to == HealthStatus::Unhealthy);
+        noted.then(|| Self::transition(app_id, health, from, reason, now))
+    }
+
+    /// Record that a backend was restarted
+    pub fn record_restart(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        check: &HealthCheck,
+    ) -> HealthTransition {
+        let now = self.now();
+        let mut trackers = self.trackers.lock().unwrap();
+        let tracker = Self::tracker(&mut trackers, app_id, component_id);
+        let from = tracker.health.status;
+        tracker.health.restarts += 1;
+        tracker.health.status = HealthStatus::Unknown;
+        tracker.health.consecutive_failures = 0;
+        tracker.unhealthy_since = None;
+        // The next check waits a full interval for the backend to start
+        tracker.next_probe_at = now.saturating_add(check.interval_secs);
+        let reason = format!("Restarted (restart {})", tracker.health.restarts);
+        Self::transition(app_id, &tracker.health, from, reason, now)
+    }
+
+    /// Record that a backend was stopped for good
+    pub fn record_block(&self, app_id: &str, component_id: &str) -> HealthTransition {
+        let now = self.now();
+        let mut trackers = self.trackers.lock().unwrap();
+        let tracker = Self::tracker(&mut trackers, app_id, component_id);
+        let from = tracker.health.status;
+        tracker.health.status = HealthStatus::Blocked;
+        let reason = format!(
+            "Blocked after {} restarts did not keep it healthy",
+            tracker.health.restarts
+        );
+        Self::transition(app_id, &tracker.health, from, reason, now)
+    }
+
+    /// Health of a backend, if it was checked
+    pub fn health(&self, app_id: &str, component_id: &str) -> Option<BackendHealth> {
+        self.trackers
+            .lock()
+            .unwrap()
+            .get(&(app_id.to_string(), component_id.to_string()))
+            .map(|tracker| tracker.health.clone())
+    }
+
+    /// Forget an app's backends, e.g. when it is launched again
+    pub fn forget(&self, app_id: &str) {
+        self.trackers
+            .lock()
+            .unwrap()
+            .retain(|(app, _), _| app != app_id);
+    }
+
+    fn tracker<'a>(
+        trackers: &'a mut HashMap<(String, String), Tracker>,
+        app_id: &str,
+        component_id: &str,
+    ) -> &'a mut Tracker {
+        trackers
+            .entry((app_id.to_string(), component_id.to_string()))
+            .or_insert_with(|| Tracker {
+                health: BackendHealth::unknown(component_id),
+                next_probe_at: 0,
+                unhealthy_since: None,
+            })
+    }
+
+    fn transition(
+        app_id: &str,
+        health: &BackendHealth,
+        from: HealthStatus,
+        reason: String,
+        at: u64,
+    ) -> HealthTransition {
+        HealthTransition {
+            app_id: app_id.to_string(),
+            component_id: health.component_id.clone(),
+            from,
+            to: health.status,
+            reason,
+            restarts: health.restarts,
+            at,
+        }
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+
+    fn monitor() -> (HealthMonitor, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new(1_000));
+        (HealthMonitor::new().with_clock(clock.clone()), clock)
+    }
+
+    fn check() -> HealthCheck {
+        HealthCheck::from_config(&serde_json::json!({
+            "probe": "socket", "intervalSecs": 10, "timeoutSecs": 1, "failureThreshold": 3
+        }))
+        .unwrap()
+    }
+
+    fn fail(monitor: &HealthMonitor) -> Option<HealthTransition> {
+        monitor.record_probe("app", "backend", &check(), Err("stalled".to_string()))
+    }
+
+    #[test]
+    fn test_unhealthy_after_threshold() {
+        let (monitor, clock) = monitor();
+        let policy = HealthPolicy::default();
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Probe
+        );
+
+        assert!(monitor
+            .record_probe("app", "backend", &check(), Ok(()))
+            .is_none());
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Wait
+        );
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Probe
+        );
+
+        assert!(fail(&monitor).is_none());
+        assert!(fail(&monitor).is_none());
+        let transition = fail(&monitor).unwrap();
+        assert_eq!(
+            (transition.from, transition.to),
+            (HealthStatus::Healthy, HealthStatus::Unhealthy)
+        );
+        assert!(transition.reason.contains("3 health checks failed"));
+        assert!(fail(&monitor).is_none());
+
+        let health = monitor.health("app", "backend").unwrap();
+        assert_eq!(health.consecutive_failures, 4);
+        assert_eq!(health.last_error.as_deref(), Some("stalled"));
+
+        let recovered = monitor
+            .record_probe("app", "backend", &check(), Ok(()))
+            .unwrap();
+        assert_eq!(recovered.to, HealthStatus::Healthy);
+        assert_eq!(
+            monitor
+                .health("app", "backend")
+                .unwrap()
+                .consecutive_failures,
+            0
+        );
+    }
+
+    #[test]
+    fn test_notify_only_keeps_checking() {
+        let (monitor, clock) = monitor();
+        let policy = HealthPolicy {
+            action: HealthAction::Notify,
+            ..HealthPolicy::default()
+        };
+        for _ in 0..3 {
+            fail(&monitor);
+        }
+        clock.advance(Duration::from_secs(3_600));
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Probe
+        );
+    }
+
+    #[test]
+    fn test_restart_backoff_doubles() {
+        let (monitor, clock) = monitor();
+        let policy = HealthPolicy {
+            restart_backoff_secs: 20,
+            max_restart_backoff_secs: 60,
+            ..HealthPolicy::default()
+        };
+        assert_eq!(
+            [0, 1, 2, 3, 64].map(|restarts| policy.backoff_secs(restarts)),
+            [20, 40, 60, 60, 60]
+        );
+
+        for restart in 1..=3u32 {
+            for _ in 0..3 {
+                fail(&monitor);
+            }
+            // Checks continue while the restart waits out its backoff
+            clock.advance(Duration::from_secs(10));
+            assert_eq!(
+                monitor.next_step("app", "backend", &policy),
+                HealthStep::Probe
+            );
+            let backoff = policy.backoff_secs(restart - 1);
+            clock.advance(Duration::from_secs(backoff - 11));
+            assert_ne!(
+                monitor.next_step("app", "backend", &policy),
+                HealthStep::Restart
+            );
+            clock.advance(Duration::from_secs(1));
+            assert_eq!(
+                monitor.next_step("app", "backend", &policy),
+                HealthStep::Restart
+            );
+
+            let transition = monitor.record_restart("app", "backend", &check());
+            assert_eq!(
+                (transition.from, transition.to, transition.restarts),
+                (HealthStatus::Unhealthy, HealthStatus::Unknown, restart)
+            );
+        }
+        // Plain restarts never block
+        for _ in 0..3 {
+            fail(&monitor);
+        }
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Restart
+        );
+    }
+
+    #[test]
+    fn test_blocked_after_max_restarts() {
+        let (monitor, clock) = monitor();
+        let policy = HealthPolicy {
+            action: HealthAction::RestartThenBlock,
+            restart_backoff_secs: 1,
+            max_restarts: 2,
+            ..HealthPolicy::default()
+        };
+        for _ in 0..2 {
+            for _ in 0..3 {
+                fail(&monitor);
+            }
+            clock.advance(Duration::from_secs(10));
+            assert_eq!(
+                monitor.next_step("app", "backend", &policy),
+                HealthStep::Restart
+            );
+            monitor.record_restart("app", "backend", &check());
+        }
+
+        for _ in 0..3 {
+            fail(&monitor);
+        }
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Block
+        );
+        let transition = monitor.record_block("app", "backend");
+        assert_eq!(transition.to, HealthStatus::Blocked);
+        assert_eq!(transition.restarts, 2);
+
+        clock.advance(Duration::from_secs(3_600));
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Wait
+        );
+
+        // A new launch starts over
+        monitor.forget("app");
+        assert!(monitor.health("app", "backend").is_none());
+        assert_eq!(
+            monitor.next_step("app", "backend", &policy),
+            HealthStep::Probe
+        );
+    }
+}
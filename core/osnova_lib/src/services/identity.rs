@@ -1,17 +1,118 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::address::{self, AddressScheme};
+use crate::audit::{AuditAction, AuditLog};
+use crate::context::{GuestGate, GuestRestriction};
 use crate::models::identity::RootIdentity;
-use crate::storage::FileStorage;
+use crate::platform::auth::AuthProof;
+use crate::platform::keystore::{self, DevelopmentKeystore, SharedKeystore};
+use crate::services::keys::KeyService;
+use crate::services::reauth::{ReauthService, SensitiveOperation};
+use crate::services::security::KeySource;
+use crate::storage::{
+    diagnose_decrypt_failure, DataClass, DecryptDiagnosis, DecryptError, DecryptLocation,
+    FileStorage, KeyCandidates,
+};
+
+/// Encrypted identity file, relative to the storage path
+const IDENTITY_FILE: &str = "identity/root.enc";
+
+/// Key cocoon, relative to the storage path
+const COCOON_FILE: &str = "identity/keys.cocoon";
+
+/// Where a cocoon that cannot be opened is set aside during recovery
+const UNRECOVERABLE_COCOON_FILE: &str = "identity/keys.cocoon.unrecoverable";
+
+/// Whether the stored identity can be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum IdentityState {
+    /// No identity is stored
+    Uninitialized,
+    /// The identity is stored and unseals with the platform keystore
+    Initialized,
+    /// Identity files are stored, but the platform keystore lost the key
+    /// that seals them; recover with the seed phrase
+    Recoverable,
+}
 
 /// Identity status response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IdentityStatus {
-    /// Whether an identity has been initialized
+    /// Whether identity files are stored, including ones that need recovery
     pub initialized: bool,
-    /// 4-word address if identity exists (None if not initialized)
+    /// 4-word address if the identity can be unsealed
     pub address: Option<String>,
+    /// Whether the stored identity can be used
+    pub state: IdentityState,
+    /// Which known key, if any, opens identity files that no longer
+    /// unseal, and why the others fail; only set when
+    /// [`IdentityState::Recoverable`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnosis: Option<DecryptDiagnosis>,
+}
+
+/// Outcome of recovering an identity with its seed phrase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    /// 4-word address of the recovered identity
+    pub address: String,
+    /// Derived keys whose indices were kept
+    pub keys_preserved: usize,
+    /// What could not be recovered, and why
+    pub unrecoverable: Vec<String>,
+}
+
+/// Where the user is in first-run onboarding
+///
+/// Derived from stored state, so wiping the data directory (e.g. a factory
+/// reset) returns onboarding to [`OnboardingState::NeedsIdentity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OnboardingState {
+    /// No identity yet; show the create/import screens
+    NeedsIdentity,
+    /// Identity files exist but the keystore lost their key; show the
+    /// seed phrase recovery screen
+    Recoverable,
+    /// An identity exists; onboarding is finished
+    Complete,
+}
+
+impl From<IdentityState> for OnboardingState {
+    fn from(state: IdentityState) -> Self {
+        match state {
+            IdentityState::Uninitialized => Self::NeedsIdentity,
+            IdentityState::Initialized => Self::Complete,
+            IdentityState::Recoverable => Self::Recoverable,
+        }
+    }
+}
+
+/// Unencrypted metadata stored next to the identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityMetadata {
+    /// Scheme used to display the identity's address
+    address_scheme: AddressScheme,
+    /// Hex fingerprint of the identity, checked by seed phrase recovery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
+}
+
+impl IdentityMetadata {
+    fn new(address_scheme: AddressScheme, identity: &RootIdentity) -> Self {
+        Self {
+            address_scheme,
+            fingerprint: Some(hex::encode(identity.fingerprint())),
+        }
+    }
 }
 
 /// Identity service for managing user identity
@@ -21,6 +122,21 @@ pub struct IdentityStatus {
 /// - `identity.create` - Create new identity
 /// - `identity.importWithPhrase` - Import existing identity
 ///
+/// Identity creation, import, deletion, and seed phrase reveals are recorded
+/// in the [`AuditLog`]. Deletion and reveals can also require OS
+/// re-authentication; see [`IdentityService::with_reauth`].
+///
+/// The identity is sealed with a key from the platform [`Keystore`]. If the
+/// keystore loses that key, [`IdentityService::status`] reports
+/// [`IdentityState::Recoverable`] and leaves the files alone until
+/// [`IdentityService::recover_with_phrase`] reseals them.
+///
+/// [`Keystore`]: crate::platform::keystore::Keystore
+///
+/// New identities get a checksummed address (see [`crate::address`]).
+/// Identities stored before checksummed addresses existed keep their legacy
+/// address until [`IdentityService::set_address_scheme`] opts them in.
+///
 /// # Example
 ///
 /// ```no_run
@@ -42,8 +158,13 @@ pub struct IdentityStatus {
 /// # }
 /// ```
 pub struct IdentityService {
+    storage_path: PathBuf,
     storage: FileStorage,
     identity_path: PathBuf,
+    metadata_path: PathBuf,
+    reauth: Option<Arc<ReauthService>>,
+    keystore: SharedKeystore,
+    guest: GuestGate,
 }
 
 impl IdentityService {
@@ -59,17 +180,53 @@ impl IdentityService {
     pub fn new<P: Into<PathBuf>>(storage_path: P) -> Result<Self> {
         let storage_path = storage_path.into();
         let storage = FileStorage::new(&storage_path)?;
-        let identity_path = PathBuf::from("identity/root.enc");
+        let identity_path = PathBuf::from(IDENTITY_FILE);
+        let metadata_path = PathBuf::from("identity/metadata.json");
 
         Ok(Self {
+            storage_path,
             storage,
             identity_path,
+            metadata_path,
+            reauth: None,
+            keystore: Arc::new(DevelopmentKeystore),
+            guest: GuestGate::default(),
         })
     }
 
+    /// Seal the identity with a key from `keystore` instead of the
+    /// development key
+    pub fn with_keystore(mut self, keystore: SharedKeystore) -> Self {
+        self.keystore = keystore;
+        self
+    }
+
+    /// Check OS re-authentication before deletion and seed phrase reveals,
+    /// where the policy asks
+    pub fn with_reauth(mut self, reauth: Arc<ReauthService>) -> Self {
+        self.reauth = Some(reauth);
+        self
+    }
+
+    /// Refuse seed phrase reveals while a guest session runs
+    pub fn with_guest_gate(mut self, guest: GuestGate) -> Self {
+        self.guest = guest;
+        self
+    }
+
     /// Check identity status (OpenRPC: identity.status)
     ///
     /// Returns whether an identity has been initialized and its 4-word address.
+    /// Identity files that no longer unseal, because the keystore lost its
+    /// key, are reported as [`IdentityState::Recoverable`] and left as they
+    /// are, with a diagnosis of which known key, if any, still opens them.
+    /// Nothing is written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keystore cannot be reached, or if the
+    /// identity cannot be read or parsed for any reason other than a failed
+    /// decryption
     ///
     /// # Example
     ///
@@ -89,28 +246,64 @@ impl IdentityService {
             return Ok(IdentityStatus {
                 initialized: false,
                 address: None,
+                state: IdentityState::Uninitialized,
+                diagnosis: None,
             });
         }
 
-        // Try to load identity to get address
-        // Note: We use a placeholder key here - in production this would come from
-        // platform keystore (DPAPI/Keychain/Secret Service)
-        let platform_key = Self::get_platform_key()?;
+        // A keystore that cannot be reached, or an identity file that cannot
+        // be read or parsed, is an error rather than a lost key
+        let error = match self.keystore.key()? {
+            None => anyhow::anyhow!("platform keystore has no key"),
+            Some(platform_key) => match self.load_identity(&platform_key) {
+                Ok(identity) => {
+                    return Ok(IdentityStatus {
+                        initialized: true,
+                        address: Some(self.derive_address(&identity)?),
+                        state: IdentityState::Initialized,
+                        diagnosis: None,
+                    })
+                }
+                Err(e) if e.downcast_ref::<DecryptError>().is_some() => e,
+                Err(e) => return Err(e),
+            },
+        };
 
-        match self.load_identity(&platform_key) {
-            Ok(identity) => Ok(IdentityStatus {
-                initialized: true,
-                address: Some(Self::derive_address(&identity)),
-            }),
+        crate::log!(Error, "Stored identity does not unseal: {:#}", error);
+        let diagnosis = match self.diagnose_identity() {
+            Ok(diagnosis) => {
+                crate::log!(Info, "{}", diagnosis);
+                Some(diagnosis)
+            }
             Err(e) => {
-                // For debugging: log the error
-                eprintln!("Failed to load identity: {}", e);
-                Ok(IdentityStatus {
-                    initialized: false,
-                    address: None,
-                })
+                crate::log!(Warn, "Stored identity could not be diagnosed: {:#}", e);
+                None
             }
-        }
+        };
+        Ok(IdentityStatus {
+            initialized: true,
+            address: None,
+            state: IdentityState::Recoverable,
+            diagnosis,
+        })
+    }
+
+    /// Try the known platform keys on the stored identity, read-only
+    fn diagnose_identity(&self) -> Result<DecryptDiagnosis> {
+        diagnose_decrypt_failure(
+            &self.storage,
+            &DecryptLocation::file(&self.identity_path),
+            &KeyCandidates::platform(self.keystore.as_ref())?,
+        )
+    }
+
+    /// Current onboarding state
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if identity status cannot be determined
+    pub fn onboarding_state(&self) -> Result<OnboardingState> {
+        Ok(self.status()?.state.into())
     }
 
     /// Create a new identity (OpenRPC: identity.create)
@@ -121,7 +314,7 @@ impl IdentityService {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - Identity already exists
+    /// - Identity already exists, including one that needs recovery
     /// - Identity cannot be generated
     /// - Identity cannot be saved
     ///
@@ -142,17 +335,28 @@ impl IdentityService {
     pub fn create(&self) -> Result<(String, String)> {
         // Check if identity already exists
         if self.storage.exists(&self.identity_path) {
+            if self.status()?.state == IdentityState::Recoverable {
+                anyhow::bail!(
+                    "An identity is stored but the platform keystore lost its key. \
+                     Recover it with its seed phrase instead of creating a new one."
+                );
+            }
             anyhow::bail!("Identity already exists. Use importWithPhrase to restore from backup.");
         }
 
         // Generate new identity
         let identity = RootIdentity::generate()?;
         let seed_phrase = identity.seed_phrase().to_string();
-        let address = Self::derive_address(&identity);
 
         // Save identity
-        let platform_key = Self::get_platform_key()?;
+        let platform_key = self.platform_key()?;
         self.save_identity(&identity, &platform_key)?;
+        self.save_metadata(&IdentityMetadata::new(
+            AddressScheme::Checksummed,
+            &identity,
+        ))?;
+        let address = self.derive_address(&identity)?;
+        self.audit(&identity, AuditAction::IdentityCreated)?;
 
         Ok((seed_phrase, address))
     }
@@ -194,15 +398,95 @@ impl IdentityService {
 
         // Create identity from seed phrase
         let identity = RootIdentity::from_seed(seed_phrase)?;
-        let address = Self::derive_address(&identity);
 
-        // Save identity
-        let platform_key = Self::get_platform_key()?;
+        // Save identity, keeping the scheme of a previously deleted identity
+        // so its address (and anything keyed by it) is unchanged
+        let platform_key = self.platform_key()?;
         self.save_identity(&identity, &platform_key)?;
+        let scheme = self
+            .load_metadata()?
+            .map_or(AddressScheme::Checksummed, |m| m.address_scheme);
+        self.save_metadata(&IdentityMetadata::new(scheme, &identity))?;
+        let address = self.derive_address(&identity)?;
+        self.audit(&identity, AuditAction::IdentityImported)?;
 
         Ok(address)
     }
 
+    /// Recover an identity whose keystore key was lost (OpenRPC: identity.recoverWithPhrase)
+    ///
+    /// The seed phrase must reproduce the fingerprint recorded when the
+    /// identity was stored. The identity is then resealed under a fresh
+    /// keystore key and the key cocoon is reopened, keeping the indices of
+    /// its derived keys. A cocoon that cannot be opened is set aside and
+    /// rebuilt empty from the seed; the report lists it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without modifying any file, if:
+    /// - The identity is not [`IdentityState::Recoverable`]
+    /// - No fingerprint was recorded for the stored identity
+    /// - The seed phrase is invalid or belongs to another identity
+    pub fn recover_with_phrase(&self, seed_phrase: &str) -> Result<RecoveryReport> {
+        if self.status()?.state != IdentityState::Recoverable {
+            anyhow::bail!("Identity does not need recovery");
+        }
+
+        let identity = RootIdentity::from_seed(seed_phrase)?;
+        let metadata = self.load_metadata()?;
+        let recorded = metadata.as_ref().and_then(|m| m.fingerprint.as_deref());
+        match recorded {
+            None => anyhow::bail!(
+                "No fingerprint was recorded for the stored identity, so the seed phrase \
+                 cannot be checked against it. Delete the identity and import the seed phrase."
+            ),
+            Some(recorded) if recorded != hex::encode(identity.fingerprint()) => {
+                anyhow::bail!("Seed phrase does not belong to the stored identity")
+            }
+            Some(_) => {}
+        }
+
+        let platform_key = self
+            .keystore
+            .replace()
+            .context("Failed to store a fresh key in the platform keystore")?;
+        self.save_identity(&identity, &platform_key)?;
+        let address = self.derive_address(&identity)?;
+
+        let keys = KeyService::new(&self.storage_path, &identity.derive_cocoon_key(&address)?)?;
+        let mut unrecoverable = Vec::new();
+        let keys_preserved = match keys
+            .migrate_cocoon_key(&identity.legacy_cocoon_key(&address))
+            .and_then(|_| keys.key_count())
+        {
+            Ok(count) => count,
+            Err(e) => {
+                let cocoon = self.storage.full_path(Path::new(COCOON_FILE));
+                if cocoon.exists() {
+                    fs::rename(
+                        &cocoon,
+                        self.storage.full_path(Path::new(UNRECOVERABLE_COCOON_FILE)),
+                    )
+                    .context("Failed to set aside the key cocoon")?;
+                    unrecoverable.push(format!(
+                        "key cocoon ({:#}); derived key indices restart at 0, the old cocoon \
+                         was kept as {}",
+                        e, UNRECOVERABLE_COCOON_FILE
+                    ));
+                }
+                keys.initialize(identity.master_key())?;
+                0
+            }
+        };
+
+        self.audit(&identity, AuditAction::IdentityRecovered)?;
+        Ok(RecoveryReport {
+            address,
+            keys_preserved,
+            unrecoverable,
+        })
+    }
+
     /// Get the root identity (if initialized)
     ///
     /// Returns the RootIdentity for internal use by other services.
@@ -211,29 +495,177 @@ impl IdentityService {
     ///
     /// Returns an error if identity is not initialized or cannot be loaded
     pub fn get_identity(&self) -> Result<RootIdentity> {
-        let platform_key = Self::get_platform_key()?;
+        let platform_key = self.platform_key()?;
         self.load_identity(&platform_key)
     }
 
+    /// Unseal the root identity when the user's session starts
+    ///
+    /// Like [`get_identity`](Self::get_identity), and also records the
+    /// fingerprint of an identity stored before fingerprints were, so that
+    /// it can later be recovered with its seed phrase.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identity cannot be loaded or its metadata
+    /// cannot be written
+    pub fn unlock(&self) -> Result<RootIdentity> {
+        let identity = self.get_identity()?;
+        self.record_fingerprint(&identity)?;
+        Ok(identity)
+    }
+
+    /// Check that the platform keystore can unseal the identity stored
+    /// under `storage_path`, without writing anything
+    ///
+    /// Returns whether an identity is stored. The storage directory is not
+    /// created when it does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keystore provides no key, or if the stored
+    /// identity does not decrypt with it
+    pub fn probe_keystore<P: AsRef<Path>>(storage_path: P) -> Result<bool> {
+        let platform_key = Self::get_platform_key().context("Platform keystore is unavailable")?;
+        let storage_path = storage_path.as_ref();
+        if !storage_path.join(IDENTITY_FILE).exists() {
+            return Ok(false);
+        }
+        Self::new(storage_path)?
+            .load_identity(&platform_key)
+            .context("Stored identity does not unseal with the platform key")?;
+        Ok(true)
+    }
+
+    /// Reveal the seed phrase for backup
+    ///
+    /// Every reveal is recorded in the audit log.
+    ///
+    /// # Arguments
+    ///
+    /// * `proof` - OS re-authentication, if the policy requires it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if identity is not initialized or cannot be loaded,
+    /// and [`OsnovaError::PermissionDenied`](crate::error::OsnovaError::PermissionDenied)
+    /// if the policy requires a valid proof and none was given, and
+    /// [`GuestModeRestricted`](crate::context::GuestModeRestricted) while a
+    /// guest session runs
+    pub fn reveal_seed_phrase(&self, proof: Option<&AuthProof>) -> Result<String> {
+        self.guest.require_host(GuestRestriction::IdentityExport)?;
+        self.require_reauth(SensitiveOperation::SeedReveal, proof)?;
+        let identity = self.get_identity()?;
+        self.audit(&identity, AuditAction::SeedRevealed)?;
+        Ok(identity.seed_phrase().to_string())
+    }
+
+    /// Scheme used to display the identity's address
+    ///
+    /// Identities stored without metadata predate checksummed addresses and
+    /// report [`AddressScheme::Legacy`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata cannot be read
+    pub fn address_scheme(&self) -> Result<AddressScheme> {
+        Ok(self
+            .load_metadata()?
+            .map_or(AddressScheme::Legacy, |m| m.address_scheme))
+    }
+
+    /// Switch the identity's address scheme and return the new address
+    ///
+    /// The address doubles as the user id that per-user keys are derived
+    /// from, so callers switching schemes must migrate anything keyed by
+    /// the old address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if identity is not initialized or the metadata
+    /// cannot be written
+    pub fn set_address_scheme(&self, scheme: AddressScheme) -> Result<String> {
+        let identity = self.get_identity()?;
+        self.save_metadata(&IdentityMetadata::new(scheme, &identity))?;
+        Ok(address::address_for_scheme(&identity.fingerprint(), scheme))
+    }
+
     /// Delete the identity
     ///
     /// WARNING: This permanently deletes the identity. Ensure seed phrase is backed up.
     ///
+    /// The deletion is recorded in the audit log while the identity's audit
+    /// key is still available.
+    ///
+    /// # Arguments
+    ///
+    /// * `proof` - OS re-authentication, if the policy requires it
+    ///
     /// # Errors
     ///
-    /// Returns an error if identity cannot be deleted
-    pub fn delete_identity(&self) -> Result<()> {
+    /// Returns an error if identity cannot be deleted, and
+    /// [`OsnovaError::PermissionDenied`](crate::error::OsnovaError::PermissionDenied)
+    /// if the policy requires a valid proof and none was given
+    pub fn delete_identity(&self, proof: Option<&AuthProof>) -> Result<()> {
+        self.require_reauth(SensitiveOperation::IdentityDeletion, proof)?;
+
+        // An unreadable identity can still be deleted, just not audited
+        if let Ok(identity) = self.get_identity() {
+            self.audit(&identity, AuditAction::IdentityDeleted)?;
+        }
+
         self.storage.delete(&self.identity_path)?;
         Ok(())
     }
 
     // Private helper methods
 
+    /// Check the re-authentication an operation needs, if any
+    fn require_reauth(
+        &self,
+        operation: SensitiveOperation,
+        proof: Option<&AuthProof>,
+    ) -> Result<()> {
+        match &self.reauth {
+            Some(reauth) => reauth.require(operation, proof),
+            None => Ok(()),
+        }
+    }
+
+    /// Key sealing the identity, from the platform keystore
+    fn platform_key(&self) -> Result<[u8; 32]> {
+        Ok(keystore::require_key(self.keystore.as_ref())?)
+    }
+
+    /// Record the fingerprint of an identity stored before fingerprints were
+    ///
+    /// Missing metadata means a legacy address, which is kept.
+    fn record_fingerprint(&self, identity: &RootIdentity) -> Result<()> {
+        let metadata = self.load_metadata()?;
+        if metadata.as_ref().is_some_and(|m| m.fingerprint.is_some()) {
+            return Ok(());
+        }
+        let scheme = metadata.map_or(AddressScheme::Legacy, |m| m.address_scheme);
+        self.save_metadata(&IdentityMetadata::new(scheme, identity))
+    }
+
+    /// Record an identity action in the audit log
+    fn audit(&self, identity: &RootIdentity, action: AuditAction) -> Result<()> {
+        let address = self.derive_address(identity)?;
+        AuditLog::new(&self.storage_path, identity, &address)?
+            .append(action, serde_json::json!({ "address": address }))?;
+        Ok(())
+    }
+
     /// Load identity from encrypted storage
     fn load_identity(&self, encryption_key: &[u8; 32]) -> Result<RootIdentity> {
         let encrypted_data = self
             .storage
-            .read(&self.identity_path, encryption_key)
+            .read_keyed(
+                &self.identity_path,
+                encryption_key,
+                KeySource::PlatformKeystore,
+            )
             .context("Failed to read identity from storage")?;
 
         // Deserialize the seed phrase
@@ -255,7 +687,12 @@ impl IdentityService {
             serde_json::to_vec(seed_phrase).context("Failed to serialize seed phrase")?;
 
         self.storage
-            .write(&self.identity_path, &seed_json, encryption_key)
+            .write_classified(
+                &self.identity_path,
+                &seed_json,
+                encryption_key,
+                DataClass::Secret,
+            )
             .context("Failed to write identity to storage")?;
 
         Ok(())
@@ -270,40 +707,53 @@ impl IdentityService {
     /// - Android/iOS: Platform keystores
     ///
     /// For now, we use a deterministic key for development.
-    fn get_platform_key() -> Result<[u8; 32]> {
+    pub(crate) fn get_platform_key() -> Result<[u8; 32]> {
         // TODO: Implement platform-specific keystore integration
         // For now, use a deterministic development key
         // In production, this should be stored in the platform keystore
-
-        use blake3::Hasher;
-        let mut hasher = Hasher::new();
-        hasher.update(b"osnova-platform-key-v1");
-
-        let hash = hasher.finalize();
-        let mut key = [0u8; 32];
-        key.copy_from_slice(hash.as_bytes());
-
-        Ok(key)
+        Ok(Self::development_platform_key())
     }
 
-    /// Derive a 4-word address from identity
+    /// The deterministic development key used until keystore integration lands
     ///
-    /// TODO: This should use saorsa-core to generate the proper 4-word address.
-    /// For now, we use a simple derivation from the fingerprint.
-    fn derive_address(identity: &RootIdentity) -> String {
-        use bip39::Mnemonic;
+    /// Kept separate from [`Self::get_platform_key`] so the security audit can
+    /// tell an identity sealed with this key from one sealed by a keystore.
+    pub(crate) fn development_platform_key() -> [u8; 32] {
+        DevelopmentKeystore::development_key()
+    }
 
-        // Use fingerprint to generate a 4-word mnemonic as the address
-        let fingerprint = identity.fingerprint();
+    /// Load identity metadata, if any has been stored
+    fn load_metadata(&self) -> Result<Option<IdentityMetadata>> {
+        if !self.storage.exists(&self.metadata_path) {
+            return Ok(None);
+        }
 
-        // Take first 11 bits (4 words * 11 bits/word = 44 bits needed, we'll use 128 bits and take first 4 words)
-        let entropy = &fingerprint[0..16];
+        let content = fs::read(self.storage.full_path(&self.metadata_path))
+            .context("Failed to read identity metadata")?;
+        let metadata =
+            serde_json::from_slice(&content).context("Failed to parse identity metadata")?;
+        Ok(Some(metadata))
+    }
 
-        // Generate a mnemonic and take first 4 words
-        let mnemonic = Mnemonic::from_entropy(entropy).expect("Valid entropy");
+    /// Save identity metadata
+    fn save_metadata(&self, metadata: &IdentityMetadata) -> Result<()> {
+        let path = self.storage.full_path(&self.metadata_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create identity directory")?;
+        }
 
-        let words: Vec<&str> = mnemonic.words().take(4).collect();
-        words.join(" ")
+        let content =
+            serde_json::to_vec_pretty(metadata).context("Failed to serialize identity metadata")?;
+        fs::write(&path, content).context("Failed to write identity metadata")?;
+        self.storage
+            .classify(&self.metadata_path, DataClass::Personal)?;
+        Ok(())
+    }
+
+    /// Derive the identity's 4-word address under its stored scheme
+    fn derive_address(&self, identity: &RootIdentity) -> Result<String> {
+        let scheme = self.address_scheme()?;
+        Ok(address::address_for_scheme(&identity.fingerprint(), scheme))
     }
 }
 
@@ -329,6 +779,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_onboarding_state_follows_identity() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        assert_eq!(service.onboarding_state()?, OnboardingState::NeedsIdentity);
+
+        service.create()?;
+        assert_eq!(service.onboarding_state()?, OnboardingState::Complete);
+
+        service.delete_identity(None)?;
+        assert_eq!(service.onboarding_state()?, OnboardingState::NeedsIdentity);
+
+        Ok(())
+    }
+
     #[test]
     fn test_create_identity() -> Result<()> {
         let (service, _temp) = create_test_service()?;
@@ -338,8 +802,11 @@ mod tests {
         // Verify seed phrase is 12 words
         assert_eq!(seed_phrase.split_whitespace().count(), 12);
 
-        // Verify address is 4 words
-        assert_eq!(address.split_whitespace().count(), 4);
+        // Verify address is 4 words and a check word
+        assert_eq!(
+            address.split_whitespace().count(),
+            address::ADDRESS_WORD_COUNT
+        );
 
         // Verify status shows initialized
         let status = service.status()?;
@@ -371,8 +838,11 @@ mod tests {
 
         let address = service.import_with_phrase(seed)?;
 
-        // Verify address is 4 words
-        assert_eq!(address.split_whitespace().count(), 4);
+        // Verify address is 4 words and a check word
+        assert_eq!(
+            address.split_whitespace().count(),
+            address::ADDRESS_WORD_COUNT
+        );
 
         // Verify status shows initialized
         let status = service.status()?;
@@ -415,7 +885,7 @@ mod tests {
 
         // Get identity
         let identity = service.get_identity()?;
-        let retrieved_address = IdentityService::derive_address(&identity);
+        let retrieved_address = service.derive_address(&identity)?;
         assert_eq!(retrieved_address, address);
 
         Ok(())
@@ -432,6 +902,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_probe_keystore() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        let missing = temp.path().join("missing");
+        assert!(!IdentityService::probe_keystore(&missing)?);
+        assert!(!missing.exists());
+        assert!(!IdentityService::probe_keystore(temp.path())?);
+
+        service.create()?;
+        assert!(IdentityService::probe_keystore(temp.path())?);
+
+        // Sealed by some other key
+        fs::write(
+            temp.path().join(IDENTITY_FILE),
+            b"not sealed by this keystore",
+        )?;
+        assert!(IdentityService::probe_keystore(temp.path()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_delete_identity() -> Result<()> {
         let (service, _temp) = create_test_service()?;
@@ -443,7 +933,7 @@ mod tests {
         assert!(status.initialized);
 
         // Delete
-        service.delete_identity()?;
+        service.delete_identity(None)?;
 
         // Verify not initialized
         let status = service.status()?;
@@ -471,10 +961,327 @@ mod tests {
 
         // Should be able to retrieve same identity
         let identity = service.get_identity()?;
-        let retrieved_address = IdentityService::derive_address(&identity);
+        let retrieved_address = service.derive_address(&identity)?;
         assert_eq!(retrieved_address, address);
         assert_eq!(identity.seed_phrase(), seed_phrase);
 
         Ok(())
     }
+
+    fn audit_entries(service: &IdentityService, identity: &RootIdentity) -> Vec<AuditAction> {
+        use crate::audit::{AuditFilter, PageRequest};
+
+        AuditLog::new(&service.storage_path, identity, "test")
+            .unwrap()
+            .list(&AuditFilter::default(), PageRequest::default())
+            .unwrap()
+            .entries
+            .iter()
+            .rev()
+            .map(|e| e.action)
+            .collect()
+    }
+
+    #[test]
+    fn test_sensitive_operations_are_audited() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        service.create()?;
+        let identity = service.get_identity()?;
+        assert_eq!(
+            audit_entries(&service, &identity),
+            vec![AuditAction::IdentityCreated]
+        );
+
+        let phrase = service.reveal_seed_phrase(None)?;
+        assert_eq!(phrase, identity.seed_phrase());
+        assert_eq!(
+            audit_entries(&service, &identity),
+            vec![AuditAction::IdentityCreated, AuditAction::SeedRevealed]
+        );
+
+        service.delete_identity(None)?;
+        assert_eq!(
+            audit_entries(&service, &identity),
+            vec![
+                AuditAction::IdentityCreated,
+                AuditAction::SeedRevealed,
+                AuditAction::IdentityDeleted
+            ]
+        );
+
+        service.import_with_phrase(&phrase)?;
+        assert_eq!(
+            audit_entries(&service, &identity).last(),
+            Some(&AuditAction::IdentityImported)
+        );
+        assert_eq!(audit_entries(&service, &identity).len(), 4);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reauth_policy_gates_reveal_and_deletion() -> Result<()> {
+        use crate::platform::auth::{AuthBackend, CommandAuthenticator};
+
+        let temp = TempDir::new()?;
+        let authenticator = CommandAuthenticator::new(AuthBackend::Polkit, "true", &[]);
+        let reauth = Arc::new(ReauthService::new(temp.path(), Arc::new(authenticator)));
+        let service = IdentityService::new(temp.path())?.with_reauth(reauth.clone());
+        service.create()?;
+
+        reauth.set_required(SensitiveOperation::SeedReveal, true, None)?;
+        assert!(service.reveal_seed_phrase(None).is_err());
+        let proof = reauth.authenticate(SensitiveOperation::SeedReveal)?;
+        service.reveal_seed_phrase(Some(&proof))?;
+
+        // Deletion is not required yet, and a reveal proof is not used by it
+        let proof = reauth.authenticate(SensitiveOperation::SeedReveal)?;
+        reauth.set_required(SensitiveOperation::IdentityDeletion, true, None)?;
+        assert!(service.delete_identity(Some(&proof)).is_err());
+        assert!(service.status()?.initialized);
+        let proof = reauth.authenticate(SensitiveOperation::IdentityDeletion)?;
+        service.delete_identity(Some(&proof))?;
+        assert!(!service.status()?.initialized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_identities_use_checksummed_address() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let (_, address) = service.create()?;
+        assert_eq!(service.address_scheme()?, AddressScheme::Checksummed);
+        assert!(address::validate_address(&address));
+        assert!(address::address_matches_identity(
+            &address,
+            &service.get_identity()?
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_identity_keeps_legacy_address_until_opt_in() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let seed = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        service.import_with_phrase(seed)?;
+
+        // Identities stored before address metadata existed
+        fs::remove_file(service.storage.full_path(&service.metadata_path))?;
+        assert_eq!(service.address_scheme()?, AddressScheme::Legacy);
+        assert_eq!(
+            service.status()?.address.as_deref(),
+            Some("battle believe alter obey")
+        );
+
+        let upgraded = service.set_address_scheme(AddressScheme::Checksummed)?;
+        assert_eq!(upgraded, "battle believe alter obey old");
+        assert_eq!(service.status()?.address, Some(upgraded));
+
+        // Re-importing after deletion keeps the chosen scheme
+        service.set_address_scheme(AddressScheme::Legacy)?;
+        service.delete_identity(None)?;
+        assert_eq!(
+            service.import_with_phrase(seed)?,
+            "battle believe alter obey"
+        );
+
+        Ok(())
+    }
+
+    use crate::models::key_cocoon::KeyType;
+    use crate::platform::keystore::{Keystore, MemoryKeystore};
+
+    const SEED: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    /// Service on a keystore the test can lose, with an identity and two
+    /// derived keys
+    fn keystore_service() -> Result<(IdentityService, Arc<MemoryKeystore>, TempDir)> {
+        let temp = TempDir::new()?;
+        let keystore = Arc::new(MemoryKeystore::new([3u8; 32]));
+        let service = IdentityService::new(temp.path())?.with_keystore(keystore.clone());
+        let address = service.import_with_phrase(SEED)?;
+
+        let identity = service.get_identity()?;
+        let keys = KeyService::new(temp.path(), &identity.derive_cocoon_key(&address)?)?;
+        keys.initialize(identity.master_key())?;
+        keys.derive("com.osnova.wallet", KeyType::Ed25519)?;
+        keys.derive("com.osnova.wallet", KeyType::Ed25519)?;
+        Ok((service, keystore, temp))
+    }
+
+    fn identity_files(service: &IdentityService) -> Result<Vec<Vec<u8>>> {
+        [IDENTITY_FILE, COCOON_FILE, "identity/metadata.json"]
+            .iter()
+            .map(|path| Ok(fs::read(service.storage.full_path(Path::new(path)))?))
+            .collect()
+    }
+
+    #[test]
+    fn test_lost_keystore_key_is_recoverable() -> Result<()> {
+        let (service, keystore, _temp) = keystore_service()?;
+        let before = identity_files(&service)?;
+
+        keystore.lose();
+        let status = service.status()?;
+        assert_eq!(status.state, IdentityState::Recoverable);
+        assert!(status.initialized);
+        assert!(status.address.is_none());
+        assert_eq!(service.onboarding_state()?, OnboardingState::Recoverable);
+        assert_eq!(identity_files(&service)?, before);
+
+        // No known key opens it, and the file itself is intact
+        let diagnosis = status.diagnosis.unwrap();
+        assert!(diagnosis.opened_with().is_none());
+        assert_eq!(
+            diagnosis.failure,
+            Some(crate::crypto::encryption::DecryptFailure::AuthenticationFailed)
+        );
+        assert_eq!(diagnosis.class, Some(DataClass::Secret));
+
+        // A key that no longer opens the identity is the same situation
+        keystore.replace()?;
+        assert_eq!(service.status()?.state, IdentityState::Recoverable);
+        assert_eq!(identity_files(&service)?, before);
+
+        // A truncated file is told apart from a lost key
+        let path = service.storage.full_path(Path::new(IDENTITY_FILE));
+        fs::write(&path, &before[0][..10])?;
+        let diagnosis = service.status()?.diagnosis.unwrap();
+        assert!(matches!(
+            diagnosis.failure,
+            Some(crate::crypto::encryption::DecryptFailure::TruncatedCiphertext { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovery_keeps_address_and_key_indices() -> Result<()> {
+        let (service, keystore, temp) = keystore_service()?;
+        let address = service.status()?.address.unwrap();
+        keystore.lose();
+
+        let report = service.recover_with_phrase(SEED)?;
+        assert_eq!(report.address, address);
+        assert_eq!(report.keys_preserved, 2);
+        assert!(report.unrecoverable.is_empty());
+
+        let status = service.status()?;
+        assert_eq!(status.state, IdentityState::Initialized);
+        assert_eq!(status.address, Some(address.clone()));
+
+        let identity = service.get_identity()?;
+        let keys = KeyService::new(temp.path(), &identity.derive_cocoon_key(&address)?)?;
+        let mut indices: Vec<u64> = keys
+            .list_for_component("com.osnova.wallet")?
+            .iter()
+            .map(|k| k.index)
+            .collect();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1]);
+        assert_eq!(keys.derive("com.osnova.wallet", KeyType::Ed25519)?.index, 2);
+        assert_eq!(
+            audit_entries(&service, &identity).last(),
+            Some(&AuditAction::IdentityRecovered)
+        );
+
+        // Recovery is only for identities that need it
+        assert!(service.recover_with_phrase(SEED).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovery_rebuilds_unreadable_cocoon() -> Result<()> {
+        let (service, keystore, _temp) = keystore_service()?;
+        keystore.lose();
+        fs::write(
+            service.storage.full_path(Path::new(COCOON_FILE)),
+            b"damaged",
+        )?;
+
+        let report = service.recover_with_phrase(SEED)?;
+        assert_eq!(report.keys_preserved, 0);
+        assert_eq!(report.unrecoverable.len(), 1);
+        assert!(service
+            .storage
+            .full_path(Path::new(UNRECOVERABLE_COCOON_FILE))
+            .exists());
+        assert_eq!(service.status()?.state, IdentityState::Initialized);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovery_rejects_wrong_phrase_without_writing() -> Result<()> {
+        let (service, keystore, _temp) = keystore_service()?;
+        keystore.lose();
+        let before = identity_files(&service)?;
+
+        let (other, _) = {
+            let temp = TempDir::new()?;
+            IdentityService::new(temp.path())?.create()?
+        };
+        assert!(service.recover_with_phrase(&other).is_err());
+        assert!(service.recover_with_phrase("not a seed phrase").is_err());
+
+        assert_eq!(identity_files(&service)?, before);
+        assert_eq!(keystore.key()?, None);
+        assert_eq!(service.status()?.state, IdentityState::Recoverable);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_refuses_to_overwrite_recoverable_identity() -> Result<()> {
+        let (service, keystore, _temp) = keystore_service()?;
+        keystore.lose();
+        let before = identity_files(&service)?;
+
+        let error = service.create().unwrap_err();
+        assert!(error.to_string().contains("seed phrase"));
+        assert!(service.import_with_phrase(SEED).is_err());
+        assert_eq!(identity_files(&service)?, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlock_records_missing_fingerprint() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.import_with_phrase(SEED)?;
+
+        // Identities stored before fingerprints were recorded
+        fs::remove_file(service.storage.full_path(&service.metadata_path))?;
+        service.status()?;
+        assert!(service.load_metadata()?.is_none());
+
+        let identity = service.unlock()?;
+        let metadata = service.load_metadata()?.unwrap();
+        assert_eq!(metadata.address_scheme, AddressScheme::Legacy);
+        assert_eq!(
+            metadata.fingerprint,
+            Some(hex::encode(identity.fingerprint()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_propagates_errors_other_than_decryption() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.import_with_phrase(SEED)?;
+
+        // An identity that decrypts but does not parse is not a lost key
+        let platform_key = service.platform_key()?;
+        service
+            .storage
+            .write(&service.identity_path, b"not a seed phrase", &platform_key)?;
+        assert!(service.status().is_err());
+
+        // Nor is one that cannot be read at all
+        let path = service.storage.full_path(&service.identity_path);
+        fs::remove_file(&path)?;
+        fs::create_dir(&path)?;
+        assert!(service.status().is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,637 @@
+//! Derived key usage statistics
+//!
+//! [`KeyUsageStats`] counts the operations made with each component's
+//! derived keys in hourly and daily buckets (see
+//! [`key_usage`](crate::models::key_usage)), keeping hourly buckets for
+//! [`HOURLY_RETENTION_SECS`] and daily ones for [`DAILY_RETENTION_SECS`].
+//! The counters as a whole are also kept under the key usage retention
+//! [`Policy`].
+//!
+//! After each operation the component's count for the current hour is
+//! compared with its average over the trailing hours. When it exceeds the
+//! configured multiple, a [`KeyUsageAnomaly`] is stored and published as
+//! [`AppEvent::KeyUsageAnomaly`], once per episode: an episode lasts as
+//! long as consecutive hours stay anomalous. The shell turns the event into
+//! a notification, and the security audit reports recent anomalies.
+//!
+//! Only counts and timestamps are recorded, never keys or messages.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::context::OsnovaContext;
+use crate::models::key_usage::{
+    KeyOperation, KeyUsageAnomaly, KeyUsageCounts, KeyUsageRow, UsageGranularity,
+};
+use crate::models::retention::{Footprint, Policy, RetainedItem, RetentionStore};
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::retention::{self, RetainedStore};
+use crate::storage::sql::RetainedRows;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// How long hourly buckets are kept, in seconds (7 days)
+pub const HOURLY_RETENTION_SECS: u64 = 7 * 86_400;
+
+/// How long daily buckets and anomalies are kept, in seconds (90 days)
+pub const DAILY_RETENTION_SECS: u64 = 90 * 86_400;
+
+/// When a component's hourly key usage counts as anomalous
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnomalyPolicy {
+    /// Multiple of the trailing hourly average the current hour must exceed
+    pub multiple: f64,
+    /// Hours before the current one the average is taken over
+    pub trailing_hours: u64,
+    /// Operations an hour must reach before it can be anomalous
+    pub min_hourly: u64,
+}
+
+impl Default for AnomalyPolicy {
+    fn default() -> Self {
+        Self {
+            multiple: 5.0,
+            trailing_hours: 24,
+            min_hourly: 100,
+        }
+    }
+}
+
+impl AnomalyPolicy {
+    /// Whether `hourly_count` is anomalous after `trailing_count` operations
+    /// in the trailing hours
+    pub fn is_anomalous(&self, hourly_count: u64, trailing_count: u64) -> bool {
+        let average = trailing_count as f64 / self.trailing_hours.max(1) as f64;
+        hourly_count >= self.min_hourly && hourly_count as f64 > self.multiple * average
+    }
+}
+
+/// Period a usage report covers, ending with the current bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UsageWindow {
+    /// The last 24 hours, in hourly buckets
+    Day,
+    /// The last 7 days, in daily buckets
+    Week,
+    /// The last 30 days, in daily buckets
+    Month,
+}
+
+impl UsageWindow {
+    /// Bucket length and number of buckets
+    fn buckets(&self) -> (UsageGranularity, u64) {
+        match self {
+            Self::Day => (UsageGranularity::Hour, 24),
+            Self::Week => (UsageGranularity::Day, 7),
+            Self::Month => (UsageGranularity::Day, 30),
+        }
+    }
+}
+
+/// Operations made with one key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsageSummary {
+    /// Derivation index of the key
+    pub key_index: u64,
+    /// Operations in the window
+    pub counts: KeyUsageCounts,
+}
+
+/// Operations in one bucket of a report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// Unix timestamp the bucket starts at
+    pub start: u64,
+    /// Operations in the bucket, over all keys
+    pub counts: KeyUsageCounts,
+}
+
+/// A component's key usage over a window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsageReport {
+    /// Component reported on
+    pub component_id: String,
+    /// Window covered
+    pub window: UsageWindow,
+    /// Length of the buckets
+    pub granularity: UsageGranularity,
+    /// Unix timestamp the first bucket starts at
+    pub from: u64,
+    /// Unix timestamp the last bucket ends at
+    pub to: u64,
+    /// Operations in the window, over all keys
+    pub totals: KeyUsageCounts,
+    /// Operations per key, by index
+    pub keys: Vec<KeyUsageSummary>,
+    /// Operations per bucket, oldest first; buckets without use count zero
+    pub buckets: Vec<UsageBucket>,
+    /// Anomalies detected in the window, newest first
+    pub anomalies: Vec<KeyUsageAnomaly>,
+}
+
+/// Durable counters of derived key usage
+///
+/// # Example
+///
+/// ```rust
+/// use osnova_lib::context::OsnovaContext;
+/// use osnova_lib::models::key_usage::KeyOperation;
+/// use osnova_lib::services::key_usage::{KeyUsageStats, UsageWindow};
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let context = OsnovaContext::new_ephemeral()?;
+/// let stats = KeyUsageStats::from_context(&context)?;
+///
+/// stats.record("com.osnova.wallet", KeyOperation::Sign, &[0])?;
+/// let report = stats.report("com.osnova.wallet", UsageWindow::Day)?;
+/// assert_eq!(report.totals.sign, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct KeyUsageStats {
+    storage: Mutex<SqlStorage>,
+    clock: SharedClock,
+    events: Option<EventBus>,
+    policy: AnomalyPolicy,
+    retention: Policy,
+    /// Last anomalous hour of each component in an episode
+    episodes: Mutex<HashMap<String, u64>>,
+    /// Hour the counters were last pruned in
+    pruned_hour: Mutex<Option<u64>>,
+}
+
+impl KeyUsageStats {
+    /// Open the counters in the database under `storage_path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened
+    pub fn new<P: AsRef<std::path::Path>>(storage_path: P) -> Result<Self> {
+        let storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self::with_storage(storage, time::default_clock()))
+    }
+
+    /// Open the counters in a context's database, on its clock and event bus
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened
+    pub fn from_context(context: &OsnovaContext) -> Result<Self> {
+        let stats = Self::with_storage(context.open_database()?, context.clock());
+        Ok(match context.events() {
+            Some(events) => stats.with_events(events),
+            None => stats,
+        })
+    }
+
+    fn with_storage(storage: SqlStorage, clock: SharedClock) -> Self {
+        Self {
+            storage: Mutex::new(storage),
+            clock,
+            events: None,
+            policy: AnomalyPolicy::default(),
+            retention: RetentionStore::KeyUsage.default_policy(),
+            episodes: Mutex::new(HashMap::new()),
+            pruned_hour: Mutex::new(None),
+        }
+    }
+
+    /// Publish anomalies on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for buckets and detection
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Flag anomalies under this policy instead of the default
+    pub fn with_policy(mut self, policy: AnomalyPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Keep the counters under `policy`
+    pub fn with_retention_policy(mut self, policy: Policy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Count one operation on each of a component's keys
+    ///
+    /// Returns the anomaly if this operation started an episode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the counters cannot be updated
+    pub fn record(
+        &self,
+        component_id: &str,
+        operation: KeyOperation,
+        key_indexes: &[u64],
+    ) -> Result<Option<KeyUsageAnomaly>> {
+        if key_indexes.is_empty() {
+            return Ok(None);
+        }
+
+        let now = self.clock.now_unix();
+        // Checked before writing, so retention never runs under the lock
+        retention::apply_on_write(self, &self.retention, now)?;
+
+        let hour = UsageGranularity::Hour.bucket_start(now);
+        let storage = self.storage.lock().unwrap();
+        for granularity in [UsageGranularity::Hour, UsageGranularity::Day] {
+            storage.add_key_usage(
+                granularity,
+                granularity.bucket_start(now),
+                component_id,
+                operation,
+                key_indexes,
+            )?;
+        }
+        self.prune_if_due(&storage, now)?;
+
+        let hourly_count =
+            storage.count_key_usage(UsageGranularity::Hour, component_id, hour, hour + 3600)?;
+        let trailing_from = hour.saturating_sub(self.policy.trailing_hours * 3600);
+        let trailing_count =
+            storage.count_key_usage(UsageGranularity::Hour, component_id, trailing_from, hour)?;
+        if !self.policy.is_anomalous(hourly_count, trailing_count) {
+            return Ok(None);
+        }
+
+        // Later anomalous hours continue the episode of an earlier one
+        let mut episodes = self.episodes.lock().unwrap();
+        let previous = episodes.insert(component_id.to_string(), hour);
+        if previous.is_some_and(|last| last == hour || last + 3600 == hour) {
+            return Ok(None);
+        }
+        drop(episodes);
+
+        let anomaly = KeyUsageAnomaly {
+            component_id: component_id.to_string(),
+            hour,
+            hourly_count,
+            trailing_count,
+            trailing_hours: self.policy.trailing_hours,
+            detected_at: now,
+        };
+        storage.insert_key_usage_anomaly(&anomaly)?;
+        drop(storage);
+
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::KeyUsageAnomaly {
+                anomaly: anomaly.clone(),
+            });
+        }
+        Ok(Some(anomaly))
+    }
+
+    /// A component's usage over a window ending now
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the counters cannot be read
+    pub fn report(&self, component_id: &str, window: UsageWindow) -> Result<KeyUsageReport> {
+        let (granularity, count) = window.buckets();
+        let now = self.clock.now_unix();
+        let to = granularity.bucket_start(now) + granularity.seconds();
+        let from = to.saturating_sub(count * granularity.seconds());
+
+        let storage = self.storage.lock().unwrap();
+        let rows = storage.list_key_usage(granularity, component_id, from, to)?;
+        let anomalies = storage.list_key_usage_anomalies(Some(component_id), from)?;
+        drop(storage);
+
+        Ok(aggregate(
+            component_id,
+            window,
+            granularity,
+            from,
+            to,
+            &rows,
+            anomalies,
+        ))
+    }
+
+    /// Anomalies of every component detected at or after `since`, newest
+    /// first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the anomalies cannot be read
+    pub fn anomalies_since(&self, since: u64) -> Result<Vec<KeyUsageAnomaly>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_key_usage_anomalies(None, since)
+    }
+
+    /// Delete buckets and anomalies past their retention
+    ///
+    /// Runs on its own once an hour while operations are recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the counters cannot be pruned
+    pub fn prune(&self) -> Result<usize> {
+        let storage = self.storage.lock().unwrap();
+        Self::prune_at(&storage, self.clock.now_unix())
+    }
+
+    fn prune_if_due(&self, storage: &SqlStorage, now: u64) -> Result<()> {
+        let hour = UsageGranularity::Hour.bucket_start(now);
+        let mut pruned_hour = self.pruned_hour.lock().unwrap();
+        if *pruned_hour != Some(hour) {
+            Self::prune_at(storage, now)?;
+            *pruned_hour = Some(hour);
+        }
+        Ok(())
+    }
+
+    fn prune_at(storage: &SqlStorage, now: u64) -> Result<usize> {
+        let hourly_cutoff = now.saturating_sub(HOURLY_RETENTION_SECS);
+        let daily_cutoff = now.saturating_sub(DAILY_RETENTION_SECS);
+        Ok(
+            storage.prune_key_usage(UsageGranularity::Hour, hourly_cutoff)?
+                + storage.prune_key_usage(UsageGranularity::Day, daily_cutoff)?
+                + storage.prune_key_usage_anomalies(daily_cutoff)?,
+        )
+    }
+}
+
+impl RetainedStore for KeyUsageStats {
+    fn store(&self) -> RetentionStore {
+        RetentionStore::KeyUsage
+    }
+
+    fn footprint(&self) -> Result<Footprint> {
+        self.storage
+            .lock()
+            .unwrap()
+            .retained_footprint(RetainedRows::KeyUsage)
+    }
+
+    fn items(&self) -> Result<Vec<RetainedItem>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_retained(RetainedRows::KeyUsage)
+    }
+
+    fn remove(&self, items: &[RetainedItem], _now: u64) -> Result<()> {
+        let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+        self.storage
+            .lock()
+            .unwrap()
+            .delete_retained(RetainedRows::KeyUsage, &ids)?;
+        Ok(())
+    }
+}
+
+/// Sum stored rows into a report
+fn aggregate(
+    component_id: &str,
+    window: UsageWindow,
+    granularity: UsageGranularity,
+    from: u64,
+    to: u64,
+    rows: &[KeyUsageRow],
+    anomalies: Vec<KeyUsageAnomaly>,
+) -> KeyUsageReport {
+    let mut totals = KeyUsageCounts::default();
+    let mut keys: BTreeMap<u64, KeyUsageCounts> = BTreeMap::new();
+    let mut buckets: BTreeMap<u64, KeyUsageCounts> = (from..to)
+        .step_by(granularity.seconds() as usize)
+        .map(|start| (start, KeyUsageCounts::default()))
+        .collect();
+
+    for row in rows {
+        totals.add(row.operation, row.count);
+        keys.entry(row.key_index)
+            .or_default()
+            .add(row.operation, row.count);
+        buckets
+            .entry(row.bucket)
+            .or_default()
+            .add(row.operation, row.count);
+    }
+
+    KeyUsageReport {
+        component_id: component_id.to_string(),
+        window,
+        granularity,
+        from,
+        to,
+        totals,
+        keys: keys
+            .into_iter()
+            .map(|(key_index, counts)| KeyUsageSummary { key_index, counts })
+            .collect(),
+        buckets: buckets
+            .into_iter()
+            .map(|(start, counts)| UsageBucket { start, counts })
+            .collect(),
+        anomalies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::EPHEMERAL_START_UNIX;
+    use crate::time::{Clock, MockClock};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const WALLET: &str = "com.osnova.wallet";
+
+    /// Stats on an ephemeral context whose clock sits on an hour boundary
+    fn stats_at_hour() -> Result<(KeyUsageStats, Arc<MockClock>, OsnovaContext)> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let clock = context.mock_clock().unwrap();
+        clock.set_unix(UsageGranularity::Day.bucket_start(EPHEMERAL_START_UNIX) + 10 * 3600);
+        let stats = KeyUsageStats::from_context(&context)?.with_policy(AnomalyPolicy {
+            multiple: 2.0,
+            trailing_hours: 4,
+            min_hourly: 10,
+        });
+        Ok((stats, clock, context))
+    }
+
+    fn record_times(stats: &KeyUsageStats, operation: KeyOperation, times: u64) -> Result<usize> {
+        let mut anomalies = 0;
+        for _ in 0..times {
+            anomalies += stats.record(WALLET, operation, &[0])?.iter().count();
+        }
+        Ok(anomalies)
+    }
+
+    #[test]
+    fn test_buckets_split_at_hour_boundary() -> Result<()> {
+        let (stats, clock, context) = stats_at_hour()?;
+        let hour = clock.now_unix();
+
+        clock.advance(Duration::from_secs(3599));
+        stats.record(WALLET, KeyOperation::Derive, &[0, 1])?;
+        clock.advance(Duration::from_secs(1));
+        stats.record(WALLET, KeyOperation::GetByPublicKey, &[1])?;
+
+        let storage = context.open_database()?;
+        let hourly = storage.list_key_usage(UsageGranularity::Hour, WALLET, 0, u64::MAX)?;
+        let buckets: Vec<(u64, u64, KeyOperation)> = hourly
+            .iter()
+            .map(|row| (row.bucket, row.key_index, row.operation))
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![
+                (hour, 0, KeyOperation::Derive),
+                (hour, 1, KeyOperation::Derive),
+                (hour + 3600, 1, KeyOperation::GetByPublicKey),
+            ]
+        );
+
+        // Both hours fall in the same day
+        let daily = storage.count_key_usage(UsageGranularity::Day, WALLET, 0, u64::MAX)?;
+        assert_eq!(daily, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention_prunes_old_buckets() -> Result<()> {
+        let (stats, clock, context) = stats_at_hour()?;
+        stats.record(WALLET, KeyOperation::Sign, &[0])?;
+        let storage = context.open_database()?;
+        let count = |granularity| storage.count_key_usage(granularity, WALLET, 0, u64::MAX);
+
+        clock.advance(Duration::from_secs(HOURLY_RETENTION_SECS + 3600));
+        stats.record(WALLET, KeyOperation::Sign, &[0])?;
+        assert_eq!(count(UsageGranularity::Hour)?, 1);
+        assert_eq!(count(UsageGranularity::Day)?, 2);
+
+        clock.advance(Duration::from_secs(DAILY_RETENTION_SECS));
+        assert!(stats.prune()? >= 2);
+        assert_eq!(count(UsageGranularity::Hour)?, 0);
+        assert_eq!(count(UsageGranularity::Day)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_limit_drops_hours_before_their_day() -> Result<()> {
+        let (stats, clock, context) = stats_at_hour()?;
+        let stats = stats.with_retention_policy(Policy::count(3));
+        let storage = context.open_database()?;
+        let count = |granularity| storage.count_key_usage(granularity, WALLET, 0, u64::MAX);
+
+        for _ in 0..3 {
+            stats.record(WALLET, KeyOperation::Sign, &[0])?;
+            clock.advance(Duration::from_secs(3600));
+        }
+        // One daily counter and three hourly ones, over the limit
+        assert_eq!(stats.footprint()?.items, 4);
+
+        stats.record(WALLET, KeyOperation::Sign, &[0])?;
+        assert_eq!(count(UsageGranularity::Day)?, 4);
+        assert_eq!(count(UsageGranularity::Hour)?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_anomaly_reported_once_per_episode() -> Result<()> {
+        let (stats, clock, context) = stats_at_hour()?;
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let stats = stats.with_events(events);
+        let hour = Duration::from_secs(3600);
+
+        // A steady baseline of 4 per hour is never anomalous
+        for _ in 0..4 {
+            assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 4)?, 0);
+            clock.advance(hour);
+        }
+
+        // A burst crosses the threshold once, and stays one episode
+        assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 9)?, 0);
+        assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 1)?, 1);
+        assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 50)?, 0);
+        clock.advance(hour);
+        assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 100)?, 0);
+
+        let AppEvent::KeyUsageAnomaly { anomaly } = received.try_recv()? else {
+            panic!("expected an anomaly event");
+        };
+        assert_eq!(anomaly.hourly_count, 10);
+        assert_eq!(anomaly.trailing_count, 16);
+        assert!(received.try_recv().is_err());
+
+        // A quiet hour ends the episode; the next burst is a new one
+        clock.advance(hour);
+        clock.advance(hour);
+        assert_eq!(record_times(&stats, KeyOperation::GetByPublicKey, 200)?, 1);
+
+        let stored = context.open_database()?.list_key_usage_anomalies(None, 0)?;
+        assert_eq!(stored.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_aggregates_scripted_usage() -> Result<()> {
+        let (stats, clock, _context) = stats_at_hour()?;
+        let start = clock.now_unix();
+
+        // Hour 0: derive keys 0-2, sign with key 0 twice
+        stats.record(WALLET, KeyOperation::Derive, &[0, 1, 2])?;
+        record_times(&stats, KeyOperation::Sign, 2)?;
+        // Hour 2: look up key 1
+        clock.advance(Duration::from_secs(2 * 3600));
+        stats.record(WALLET, KeyOperation::GetByPublicKey, &[1])?;
+        // Another component is not included
+        stats.record("com.other", KeyOperation::Sign, &[0])?;
+
+        let report = stats.report(WALLET, UsageWindow::Day)?;
+        assert_eq!(report.granularity, UsageGranularity::Hour);
+        assert_eq!(report.to, start + 3 * 3600);
+        assert_eq!(report.from, report.to - 24 * 3600);
+        assert_eq!(
+            report.totals,
+            KeyUsageCounts {
+                derive: 3,
+                get_by_public_key: 1,
+                sign: 2,
+            }
+        );
+        let per_key: Vec<(u64, u64)> = report
+            .keys
+            .iter()
+            .map(|key| (key.key_index, key.counts.total()))
+            .collect();
+        assert_eq!(per_key, vec![(0, 3), (1, 2), (2, 1)]);
+
+        assert_eq!(report.buckets.len(), 24);
+        let recent: Vec<u64> = report.buckets[21..]
+            .iter()
+            .map(|bucket| bucket.counts.total())
+            .collect();
+        assert_eq!(recent, vec![5, 0, 1]);
+        assert!(report.anomalies.is_empty());
+
+        let weekly = stats.report(WALLET, UsageWindow::Week)?;
+        assert_eq!(weekly.granularity, UsageGranularity::Day);
+        assert_eq!(weekly.buckets.len(), 7);
+        assert_eq!(weekly.totals.total(), 6);
+        assert_eq!(weekly.buckets[6].counts.total(), 6);
+        Ok(())
+    }
+}
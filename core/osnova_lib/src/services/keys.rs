@@ -1,13 +1,22 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::context::OsnovaContext;
 use crate::crypto::key_derivation;
-use crate::models::key_cocoon::{DerivedKeyEntry, KeyCocoon, KeyType};
-use crate::storage::FileStorage;
+use crate::models::key_cocoon::{DerivedKeyEntry, KeyCocoon, KeyType, PublicKeyMigration};
+use crate::models::key_usage::KeyOperation;
+use crate::services::key_usage::{KeyUsageReport, KeyUsageStats, UsageWindow};
+use crate::services::security::KeySource;
+use crate::storage::{DataClass, FileStorage, FileStore};
+
+/// Maximum number of keys derived by a single [`KeyService::derive_batch`] call
+pub const MAX_BATCH_DERIVATION: u64 = 1000;
 
 /// Response for key derivation methods
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct KeyDerivationResponse {
     /// Base64-encoded public key
     pub public_key: String,
@@ -18,7 +27,7 @@ pub struct KeyDerivationResponse {
 }
 
 /// Response for getByPublicKey method
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SecretKeyResponse {
     /// Base64-encoded secret key
     pub secret_key: String,
@@ -29,7 +38,7 @@ pub struct SecretKeyResponse {
 }
 
 /// Key info for listForComponent method
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct KeyInfo {
     /// Base64-encoded public key
     pub public_key: String,
@@ -46,8 +55,14 @@ pub struct KeyInfo {
 /// Provides OpenRPC methods:
 /// - `keys.derive` - Derive a new key at the next available index
 /// - `keys.deriveAtIndex` - Derive or retrieve a key at a specific index
+/// - `keys.deriveBatch` - Derive or retrieve a contiguous range of keys
 /// - `keys.getByPublicKey` - Retrieve secret key by public key
 /// - `keys.listForComponent` - List all keys for a component
+/// - `keys.usageReport` - Operations made with a component's keys
+///
+/// Derives and secret key lookups are counted in the
+/// [`KeyUsageStats`] given with [`with_usage_stats`](Self::with_usage_stats)
+/// (set up by [`from_context`](Self::from_context)).
 ///
 /// # Example
 ///
@@ -71,9 +86,10 @@ pub struct KeyInfo {
 /// # }
 /// ```
 pub struct KeyService {
-    storage: FileStorage,
+    storage: Arc<dyn FileStore>,
     cocoon_path: PathBuf,
     cocoon_key: [u8; 32],
+    usage: Option<KeyUsageStats>,
 }
 
 impl KeyService {
@@ -90,13 +106,40 @@ impl KeyService {
     pub fn new<P: Into<PathBuf>>(storage_path: P, cocoon_key: &[u8; 32]) -> Result<Self> {
         let storage_path = storage_path.into();
         let storage = FileStorage::new(&storage_path)?;
-        let cocoon_path = PathBuf::from("identity/keys.cocoon");
+        Ok(Self::with_store(Arc::new(storage), cocoon_key))
+    }
+
+    /// Create a key service on the file store of a context
+    ///
+    /// Key usage is counted in the context's database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if storage cannot be initialized
+    pub fn from_context(context: &OsnovaContext, cocoon_key: &[u8; 32]) -> Result<Self> {
+        Ok(Self::with_store(context.file_store()?, cocoon_key)
+            .with_usage_stats(KeyUsageStats::from_context(context)?))
+    }
+
+    /// Count operations on derived keys
+    pub fn with_usage_stats(mut self, usage: KeyUsageStats) -> Self {
+        self.usage = Some(usage);
+        self
+    }
 
-        Ok(Self {
+    /// The key usage counters, for callers signing with derived keys
+    pub fn usage_stats(&self) -> Option<&KeyUsageStats> {
+        self.usage.as_ref()
+    }
+
+    /// Create a key service on a file store
+    fn with_store(storage: Arc<dyn FileStore>, cocoon_key: &[u8; 32]) -> Self {
+        Self {
             storage,
-            cocoon_path,
+            cocoon_path: PathBuf::from("identity/keys.cocoon"),
             cocoon_key: *cocoon_key,
-        })
+            usage: None,
+        }
     }
 
     /// Initialize cocoon with master key if it doesn't exist
@@ -115,6 +158,57 @@ impl KeyService {
         Ok(())
     }
 
+    /// Re-encrypt a cocoon written with a legacy cocoon key
+    ///
+    /// If the cocoon cannot be opened with the current key but opens with
+    /// `legacy_key`, it is rewritten under the current key.
+    ///
+    /// Returns `true` if the cocoon was migrated.
+    pub fn migrate_cocoon_key(&self, legacy_key: &[u8; 32]) -> Result<bool> {
+        if !self.storage.exists(&self.cocoon_path) || self.load_cocoon().is_ok() {
+            return Ok(false);
+        }
+
+        let data = self
+            .storage
+            .read_keyed(&self.cocoon_path, legacy_key, KeySource::LegacyDerived)
+            .context("Key cocoon opens with neither the current nor the legacy key")?;
+
+        self.storage
+            .write_classified(
+                &self.cocoon_path,
+                &data,
+                &self.cocoon_key,
+                DataClass::Secret,
+            )
+            .context("Failed to write migrated key cocoon")?;
+
+        Ok(true)
+    }
+
+    /// Migrate a cocoon written before real public key derivation
+    ///
+    /// Stores the real public keys of its entries; the ones stored before stay
+    /// resolvable by [`get_by_public_key`](Self::get_by_public_key). Returns
+    /// the report of the migration, only on the call that migrated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cocoon cannot be read or written
+    pub fn migrate_public_keys(&self) -> Result<Option<PublicKeyMigration>> {
+        if !self.storage.exists(&self.cocoon_path) {
+            return Ok(None);
+        }
+
+        let mut cocoon = self.load_cocoon()?;
+        let Some(migration) = cocoon.migrate_public_keys()? else {
+            return Ok(None);
+        };
+        self.save_cocoon(&cocoon)?;
+
+        Ok(Some(migration))
+    }
+
     /// Derive a new key at the next available index (OpenRPC: keys.derive)
     ///
     /// # Arguments
@@ -152,7 +246,12 @@ impl KeyService {
             .unwrap_or(0);
 
         // Derive key at next index
-        self.derive_at_index_internal(&mut cocoon, component_id, next_index, key_type)
+        let response =
+            self.derive_at_index_internal(&mut cocoon, component_id, next_index, key_type)?;
+        self.save_cocoon(&cocoon)?;
+        self.record_usage(component_id, KeyOperation::Derive, &[response.index]);
+
+        Ok(response)
     }
 
     /// Derive or retrieve a key at a specific index (OpenRPC: keys.deriveAtIndex)
@@ -193,18 +292,97 @@ impl KeyService {
         key_type: KeyType,
     ) -> Result<KeyDerivationResponse> {
         let mut cocoon = self.load_cocoon()?;
+        self.record_usage(component_id, KeyOperation::Derive, &[index]);
 
         // Check if key already exists at this index
-        if let Some(entry) = cocoon.get_key(component_id, index) {
-            return Ok(KeyDerivationResponse {
-                public_key: entry.public_key.clone(),
-                index: entry.index,
-                created: entry.created_at,
-            });
+        if let Some(response) = Self::existing_key(&cocoon, component_id, index) {
+            return Ok(response);
         }
 
         // Derive new key at specified index
-        self.derive_at_index_internal(&mut cocoon, component_id, index, key_type)
+        let response = self.derive_at_index_internal(&mut cocoon, component_id, index, key_type)?;
+        self.save_cocoon(&cocoon)?;
+
+        Ok(response)
+    }
+
+    /// Derive or retrieve a contiguous range of keys (OpenRPC: keys.deriveBatch)
+    ///
+    /// Equivalent to calling [`derive_at_index`](Self::derive_at_index) for
+    /// each index in `start_index..start_index + count`, but the cocoon is
+    /// loaded once and persisted at most once. Intended for wallet account
+    /// discovery, which scans many consecutive indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `component_id` - Component requesting the keys
+    /// * `start_index` - First derivation index
+    /// * `count` - Number of keys to derive (at most [`MAX_BATCH_DERIVATION`])
+    /// * `key_type` - Type of key to derive
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `count` is zero or exceeds [`MAX_BATCH_DERIVATION`]
+    /// - The index range overflows
+    /// - Cocoon is not initialized
+    /// - Key derivation fails (nothing is persisted in that case)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::services::KeyService;
+    /// # use osnova_lib::models::key_cocoon::KeyType;
+    /// # use osnova_lib::platform::paths::get_data_dir;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let storage_path = get_data_dir()?;
+    /// let service = KeyService::new(&storage_path, &[0u8; 32])?;
+    /// let keys = service.derive_batch("com.osnova.wallet", 0, 20, KeyType::Ed25519)?;
+    /// assert_eq!(keys.len(), 20);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn derive_batch(
+        &self,
+        component_id: &str,
+        start_index: u64,
+        count: u64,
+        key_type: KeyType,
+    ) -> Result<Vec<KeyDerivationResponse>> {
+        if count == 0 || count > MAX_BATCH_DERIVATION {
+            anyhow::bail!(
+                "Batch size must be between 1 and {}, got {}",
+                MAX_BATCH_DERIVATION,
+                count
+            );
+        }
+        let end_index = start_index
+            .checked_add(count)
+            .context("Batch index range overflows")?;
+
+        let mut cocoon = self.load_cocoon()?;
+        let mut responses = Vec::with_capacity(count as usize);
+        let mut modified = false;
+
+        for index in start_index..end_index {
+            if let Some(response) = Self::existing_key(&cocoon, component_id, index) {
+                responses.push(response);
+                continue;
+            }
+
+            let response =
+                self.derive_at_index_internal(&mut cocoon, component_id, index, key_type.clone())?;
+            responses.push(response);
+            modified = true;
+        }
+
+        if modified {
+            self.save_cocoon(&cocoon)?;
+        }
+        let indexes: Vec<u64> = (start_index..end_index).collect();
+        self.record_usage(component_id, KeyOperation::Derive, &indexes);
+
+        Ok(responses)
     }
 
     /// Retrieve secret key by public key (OpenRPC: keys.getByPublicKey)
@@ -238,6 +416,11 @@ impl KeyService {
         let entry = cocoon
             .get_by_public_key(public_key)
             .context("Public key not found")?;
+        self.record_usage(
+            &entry.component_id,
+            KeyOperation::GetByPublicKey,
+            &[entry.index],
+        );
 
         Ok(SecretKeyResponse {
             secret_key: entry.secret_key.clone(),
@@ -288,9 +471,58 @@ impl KeyService {
         Ok(keys)
     }
 
+    /// Number of keys derived so far, across all components
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cocoon cannot be opened
+    pub fn key_count(&self) -> Result<usize> {
+        Ok(self.load_cocoon()?.derived_keys.len())
+    }
+
+    /// Operations made with a component's keys (OpenRPC: keys.usageReport)
+    ///
+    /// Counts per operation, per key, and per bucket over the window, with
+    /// the anomalies detected in it. Never contains key material.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if usage is not counted or cannot be read
+    pub fn usage_report(&self, component_id: &str, window: UsageWindow) -> Result<KeyUsageReport> {
+        self.usage
+            .as_ref()
+            .context("Key usage statistics are not enabled")?
+            .report(component_id, window)
+    }
+
     // Private helper methods
 
+    /// Count an operation; failing to count never blocks access to a key
+    fn record_usage(&self, component_id: &str, operation: KeyOperation, indexes: &[u64]) {
+        if let Some(usage) = &self.usage {
+            let _ = usage.record(component_id, operation, indexes);
+        }
+    }
+
+    /// Response for a key already stored at `index`, if any
+    fn existing_key(
+        cocoon: &KeyCocoon,
+        component_id: &str,
+        index: u64,
+    ) -> Option<KeyDerivationResponse> {
+        cocoon
+            .get_key(component_id, index)
+            .map(|entry| KeyDerivationResponse {
+                public_key: entry.public_key.clone(),
+                index: entry.index,
+                created: entry.created_at,
+            })
+    }
+
     /// Internal method to derive a key at a specific index
+    ///
+    /// Adds the key to `cocoon` without persisting it; callers save the
+    /// cocoon once they are done.
     fn derive_at_index_internal(
         &self,
         cocoon: &mut KeyCocoon,
@@ -324,41 +556,35 @@ impl KeyService {
             created: entry.created_at,
         };
 
-        // Save to cocoon
         cocoon.add_key(entry);
-        self.save_cocoon(cocoon)?;
 
         Ok(response)
     }
 
     /// Generate Ed25519 key pair from seed
     fn generate_ed25519(seed: &[u8; 32]) -> Result<(String, String)> {
-        use base64::{engine::general_purpose, Engine as _};
-        use ed25519_dalek::{SigningKey, VerifyingKey};
-
-        let signing_key = SigningKey::from_bytes(seed);
-        let verifying_key: VerifyingKey = (&signing_key).into();
-
-        let public_key = general_purpose::STANDARD.encode(verifying_key.as_bytes());
-        let secret_key = general_purpose::STANDARD.encode(signing_key.to_bytes());
-
-        Ok((public_key, secret_key))
+        Self::encode_keypair(key_derivation::generate_keypair(
+            seed,
+            key_derivation::KeyType::Ed25519,
+        )?)
     }
 
     /// Generate X25519 key pair from seed
     fn generate_x25519(seed: &[u8; 32]) -> Result<(String, String)> {
-        use base64::{engine::general_purpose, Engine as _};
-        use x25519_dalek::PublicKey;
-
-        // X25519: public key derived from scalar multiplication with base point
-        // The seed directly serves as the secret key
-        let secret_bytes = *seed;
-        let public = PublicKey::from(secret_bytes);
+        Self::encode_keypair(key_derivation::generate_keypair(
+            seed,
+            key_derivation::KeyType::X25519,
+        )?)
+    }
 
-        let public_key = general_purpose::STANDARD.encode(public.as_bytes());
-        let secret_key = general_purpose::STANDARD.encode(secret_bytes);
+    /// Base64-encoded public and secret keys of a key pair
+    fn encode_keypair(keypair: key_derivation::KeyPair) -> Result<(String, String)> {
+        use base64::{engine::general_purpose, Engine as _};
 
-        Ok((public_key, secret_key))
+        Ok((
+            general_purpose::STANDARD.encode(&keypair.public_key),
+            general_purpose::STANDARD.encode(&keypair.secret_key),
+        ))
     }
 
     /// Generate Secp256k1 key pair from seed
@@ -372,7 +598,11 @@ impl KeyService {
     fn load_cocoon(&self) -> Result<KeyCocoon> {
         let encrypted_data = self
             .storage
-            .read(&self.cocoon_path, &self.cocoon_key)
+            .read_keyed(
+                &self.cocoon_path,
+                &self.cocoon_key,
+                KeySource::IdentityDerived,
+            )
             .context("Failed to read key cocoon")?;
 
         let cocoon: KeyCocoon =
@@ -386,7 +616,12 @@ impl KeyService {
         let cocoon_json = serde_json::to_vec(cocoon).context("Failed to serialize key cocoon")?;
 
         self.storage
-            .write(&self.cocoon_path, &cocoon_json, &self.cocoon_key)
+            .write_classified(
+                &self.cocoon_path,
+                &cocoon_json,
+                &self.cocoon_key,
+                DataClass::Secret,
+            )
             .context("Failed to write key cocoon")?;
 
         Ok(())
@@ -398,16 +633,16 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
-    fn create_test_service() -> Result<(KeyService, TempDir)> {
-        let temp_dir = TempDir::new()?;
+    fn create_test_service() -> Result<(KeyService, OsnovaContext)> {
+        let context = OsnovaContext::new_ephemeral()?;
         let cocoon_key = [0u8; 32];
-        let service = KeyService::new(temp_dir.path(), &cocoon_key)?;
+        let service = KeyService::from_context(&context, &cocoon_key)?;
 
         // Initialize with a test master key
         let master_key = [1u8; 32];
         service.initialize(&master_key)?;
 
-        Ok((service, temp_dir))
+        Ok((service, context))
     }
 
     #[test]
@@ -583,4 +818,204 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_derive_batch_matches_individual_derivation() -> Result<()> {
+        let (batch_service, _batch_temp) = create_test_service()?;
+        let (single_service, _single_temp) = create_test_service()?;
+
+        let batch = batch_service.derive_batch("com.test.wallet", 10, 25, KeyType::Ed25519)?;
+        assert_eq!(batch.len(), 25);
+
+        for (offset, response) in batch.iter().enumerate() {
+            let index = 10 + offset as u64;
+            let single =
+                single_service.derive_at_index("com.test.wallet", index, KeyType::Ed25519)?;
+            assert_eq!(response.index, index);
+            assert_eq!(response.public_key, single.public_key);
+        }
+
+        // Batch-derived keys are persisted and retrievable
+        let secret = batch_service.get_by_public_key(&batch[24].public_key)?;
+        assert_eq!(secret.index, 34);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_batch_saves_once() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let writes_before = service.storage.write_count();
+
+        let batch = service.derive_batch("com.test.wallet", 0, 100, KeyType::Ed25519)?;
+
+        assert_eq!(batch.len(), 100);
+        assert_eq!(service.storage.write_count() - writes_before, 1);
+        assert_eq!(service.list_for_component("com.test.wallet")?.len(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_batch_reuses_existing_keys() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let existing = service.derive_at_index("com.test.wallet", 2, KeyType::Ed25519)?;
+        let batch = service.derive_batch("com.test.wallet", 0, 5, KeyType::Ed25519)?;
+
+        assert_eq!(batch[2].public_key, existing.public_key);
+        assert_eq!(batch[2].created, existing.created);
+        assert_eq!(service.list_for_component("com.test.wallet")?.len(), 5);
+
+        // A fully derived range does not rewrite the cocoon
+        let writes_before = service.storage.write_count();
+        let again = service.derive_batch("com.test.wallet", 0, 5, KeyType::Ed25519)?;
+        assert_eq!(service.storage.write_count(), writes_before);
+        assert_eq!(
+            again.iter().map(|k| &k.public_key).collect::<Vec<_>>(),
+            batch.iter().map(|k| &k.public_key).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_batch_rejects_invalid_count() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        assert!(service
+            .derive_batch("com.test.wallet", 0, 0, KeyType::Ed25519)
+            .is_err());
+        assert!(service
+            .derive_batch(
+                "com.test.wallet",
+                0,
+                MAX_BATCH_DERIVATION + 1,
+                KeyType::Ed25519
+            )
+            .is_err());
+        assert!(service
+            .derive_batch("com.test.wallet", u64::MAX, 2, KeyType::Ed25519)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_batch_failure_persists_nothing() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+
+        let result = service.derive_batch("com.test.wallet", 0, 3, KeyType::Secp256k1);
+
+        assert!(result.is_err());
+        assert!(service.list_for_component("com.test.wallet")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_report_counts_operations() -> Result<()> {
+        let (service, _context) = create_test_service()?;
+
+        let first = service.derive("com.test.wallet", KeyType::Ed25519)?;
+        service.derive_at_index("com.test.wallet", 0, KeyType::Ed25519)?;
+        service.derive_batch("com.test.wallet", 0, 3, KeyType::Ed25519)?;
+        service.get_by_public_key(&first.public_key)?;
+        service.derive("com.test.other", KeyType::Ed25519)?;
+
+        let report = service.usage_report("com.test.wallet", UsageWindow::Day)?;
+        assert_eq!(report.totals.derive, 5);
+        assert_eq!(report.totals.get_by_public_key, 1);
+        let per_key: Vec<(u64, u64)> = report
+            .keys
+            .iter()
+            .map(|key| (key.key_index, key.counts.total()))
+            .collect();
+        assert_eq!(per_key, vec![(0, 4), (1, 1), (2, 1)]);
+
+        // Counts and timestamps only
+        let json = serde_json::to_string(&report)?;
+        assert!(!json.contains(&first.public_key));
+
+        let without_stats = KeyService::new(TempDir::new()?.path(), &[0u8; 32])?;
+        assert!(without_stats
+            .usage_report("com.test.wallet", UsageWindow::Day)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_cocoon_from_legacy_key() -> Result<()> {
+        use crate::models::identity::RootIdentity;
+
+        let temp_dir = TempDir::new()?;
+        let seed = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let identity = RootIdentity::from_seed(seed)?;
+
+        // Cocoon created by the old Tauri-side key derivation
+        let legacy_key = identity.legacy_cocoon_key("user-123");
+        let legacy_service = KeyService::new(temp_dir.path(), &legacy_key)?;
+        legacy_service.initialize(identity.master_key())?;
+        let derived = legacy_service.derive("com.test.app", KeyType::Ed25519)?;
+
+        // Opened with the relocated derivation after migration
+        let cocoon_key = identity.derive_cocoon_key("user-123")?;
+        let service = KeyService::new(temp_dir.path(), &cocoon_key)?;
+        assert!(service.get_by_public_key(&derived.public_key).is_err());
+
+        assert!(service.migrate_cocoon_key(&legacy_key)?);
+        assert!(service.get_by_public_key(&derived.public_key).is_ok());
+
+        // Already migrated
+        assert!(!service.migrate_cocoon_key(&legacy_key)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_public_keys_of_v1_cocoon() -> Result<()> {
+        let (service, _context) = create_test_service()?;
+        let current = service.derive("com.test.wallet", KeyType::Ed25519)?;
+        let chat = service.derive("com.test.chat", KeyType::X25519)?;
+        // A new cocoon has nothing to migrate
+        assert!(service.migrate_public_keys()?.is_none());
+
+        // Rewrite it as development builds stored it
+        let mut cocoon = service.load_cocoon()?;
+        cocoon.metadata.version = 1;
+        for entry in cocoon.derived_keys.values_mut() {
+            if entry.key_type == KeyType::X25519 {
+                entry.public_key = entry.secret_key.clone();
+            }
+        }
+        service.save_cocoon(&cocoon)?;
+
+        let migration = service.migrate_public_keys()?.unwrap();
+        assert!(service.migrate_public_keys()?.is_none());
+
+        // Ed25519 keys were already real and are not reported; X25519 ones
+        // get theirs back
+        assert_eq!(migration.keys.len(), 1);
+        assert_eq!(migration.keys[0].component_id, "com.test.chat");
+        assert_eq!(
+            migration.keys[0].legacy_public_key,
+            service.get_by_public_key(&chat.public_key)?.secret_key
+        );
+        let keys = service.list_for_component("com.test.chat")?;
+        assert_eq!(keys[0].public_key, chat.public_key);
+        assert_ne!(
+            keys[0].public_key,
+            service.get_by_public_key(&chat.public_key)?.secret_key
+        );
+
+        // Old and new public keys both resolve
+        for key in &migration.keys {
+            let by_legacy = service.get_by_public_key(&key.legacy_public_key)?;
+            let by_current = service.get_by_public_key(&key.public_key)?;
+            assert_eq!(by_legacy.secret_key, by_current.secret_key);
+            assert_eq!(by_legacy.component_id, key.component_id);
+        }
+        assert!(service.get_by_public_key(&current.public_key).is_ok());
+        Ok(())
+    }
 }
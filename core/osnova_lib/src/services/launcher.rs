@@ -1,54 +1,42 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::path::PathBuf;
 
-use crate::storage::FileStorage;
-
-/// Launcher layout (ordered list of app IDs)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LauncherLayout {
-    /// Ordered list of application IDs
-    pub app_ids: Vec<String>,
-    /// Last updated timestamp
-    pub updated_at: u64,
-}
-
-impl LauncherLayout {
-    /// Create a new empty layout
-    pub fn new() -> Self {
-        Self {
-            app_ids: Vec::new(),
-            updated_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        }
-    }
-
-    /// Create layout with app IDs
-    pub fn with_apps(app_ids: Vec<String>) -> Self {
-        Self {
-            app_ids,
-            updated_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        }
-    }
-
-    /// Update timestamp
-    pub fn touch(&mut self) {
-        self.updated_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-    }
-}
-
-impl Default for LauncherLayout {
-    fn default() -> Self {
-        Self::new()
-    }
+use crate::models::launcher_change::{ChangeSummary, LauncherChangeKind};
+pub use crate::models::launcher_layout::LauncherLayout;
+use crate::models::launcher_layout::{MergeReport, OrderKey};
+use crate::models::mutation::{new_mutation_id, MutationReceipt, MutationRejected, Since};
+use crate::storage::{ownership, DataClass, FileStorage, SqlStorage};
+use crate::time::{self, SharedClock};
+
+/// One edit of the launcher layout (OpenRPC: launcher.mutate)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LauncherMutation {
+    /// Set the icon order
+    #[serde(rename_all = "camelCase")]
+    SetLayout {
+        /// Ordered application IDs
+        app_ids: Vec<String>,
+    },
+    /// Put an app in a folder, or take it out with `None`
+    #[serde(rename_all = "camelCase")]
+    SetFolder {
+        /// Application ID
+        app_id: String,
+        /// Folder name
+        folder: Option<String>,
+    },
+    /// Pin or unpin an app
+    #[serde(rename_all = "camelCase")]
+    SetPinned {
+        /// Application ID
+        app_id: String,
+        /// Whether the app is pinned
+        pinned: bool,
+    },
 }
 
 /// Launcher layout service
@@ -56,8 +44,26 @@ impl Default for LauncherLayout {
 /// Provides OpenRPC methods:
 /// - `launcher.getLayout` - Get the current icon order/placement
 /// - `launcher.setLayout` - Set the icon order/placement
+/// - `launcher.generation` - Generation of the latest launcher change
+/// - `launcher.changesSince` - What changed since a generation
+/// - `launcher.setFolder` - Put an app in a folder or take it out
+/// - `launcher.setPinned` - Pin or unpin an app
+/// - `launcher.mergeLayout` - Merge the layout from another device
+/// - `launcher.mutate` - Apply a layout edit at most once per mutation id
+/// - `launcher.getSince` - The layout, only if it changed since a generation
+///
+/// Layout is persisted per-identity and restored on relaunch. Every edit is
+/// stamped with this device and the time, so layouts edited on several
+/// devices merge item by item; see [`LauncherLayout`].
+///
+/// Saving a layout, like installing, uninstalling, restoring, updating or
+/// relisting an app, bumps the launcher generation shared with
+/// [`AppsService`](crate::services::AppsService), so a frontend can check
+/// whether its grid is current without reloading it.
 ///
-/// Layout is persisted per-identity and restored on relaunch.
+/// Layout edits return a [`MutationReceipt`] naming the generation they
+/// produced, so a frontend can apply them optimistically and reconcile; see
+/// [`crate::models::mutation`].
 ///
 /// # Example
 ///
@@ -78,8 +84,12 @@ impl Default for LauncherLayout {
 /// ```
 pub struct LauncherService {
     file_storage: FileStorage,
+    sql_storage: SqlStorage,
+    user_id: String,
     layout_path: PathBuf,
     encryption_key: [u8; 32],
+    device_id: String,
+    clock: SharedClock,
 }
 
 impl LauncherService {
@@ -92,6 +102,7 @@ impl LauncherService {
     pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
         let storage_path = storage_path.into();
         let file_storage = FileStorage::new(&storage_path)?;
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
         let layout_path = PathBuf::from(format!("launcher/{}/layout.json", user_id));
 
         // Derive encryption key from user_id
@@ -100,11 +111,27 @@ impl LauncherService {
 
         Ok(Self {
             file_storage,
+            sql_storage,
+            user_id: user_id.to_string(),
             layout_path,
             encryption_key,
+            device_id: ownership::local_install_id().to_string(),
+            clock: time::default_clock(),
         })
     }
 
+    /// Stamp edits as made on a specific device instead of this install
+    pub fn with_device(mut self, device_id: &str) -> Self {
+        self.device_id = device_id.to_string();
+        self
+    }
+
+    /// Use a specific clock for edit timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Get the current launcher layout (OpenRPC: launcher.getLayout)
     ///
     /// Returns the ordered list of application IDs representing the launcher icon layout.
@@ -130,8 +157,9 @@ impl LauncherService {
             .read(&self.layout_path, &self.encryption_key)
             .context("Failed to read launcher layout")?;
 
-        let layout: LauncherLayout = serde_json::from_slice(&encrypted_data)
+        let mut layout: LauncherLayout = serde_json::from_slice(&encrypted_data)
             .context("Failed to deserialize launcher layout")?;
+        layout.migrate();
 
         Ok(layout)
     }
@@ -139,6 +167,8 @@ impl LauncherService {
     /// Set the launcher layout (OpenRPC: launcher.setLayout)
     ///
     /// Updates the launcher icon order/placement. Changes are saved within 1s of drop.
+    /// Apps whose relative order is unchanged keep their place, so a merge
+    /// with another device's edits only sees the apps that moved.
     ///
     /// # Arguments
     ///
@@ -157,21 +187,197 @@ impl LauncherService {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn set_layout(&self, app_ids: Vec<String>) -> Result<()> {
-        let layout = LauncherLayout::with_apps(app_ids);
+    pub fn set_layout(&self, app_ids: Vec<String>) -> Result<MutationReceipt> {
+        self.mutate(&new_mutation_id(), LauncherMutation::SetLayout { app_ids })
+    }
+
+    /// Remove an app from the layout without bumping the generation
+    ///
+    /// For uninstalls, whose own change already covers the removal.
+    /// Returns where the app was on the grid, if it was.
+    pub(crate) fn remove_app(&self, app_id: &str) -> Result<Option<OrderKey>> {
+        let mut layout = self.get_layout()?;
+        let position = layout
+            .item(app_id)
+            .and_then(|item| item.placement.value.clone());
+        if !layout.remove(app_id, &self.device_id, self.clock.now_unix()) {
+            return Ok(None);
+        }
+        self.write_layout(&layout)?;
+        Ok(position)
+    }
+
+    /// Put an app back where [`remove_app`](Self::remove_app) found it,
+    /// without bumping the generation
+    ///
+    /// For restores, whose own change already covers the addition.
+    pub(crate) fn restore_app(&self, app_id: &str, position: OrderKey) -> Result<()> {
+        let mut layout = self.get_layout()?;
+        if layout.place(app_id, position, &self.device_id, self.clock.now_unix()) {
+            self.write_layout(&layout)?;
+        }
+        Ok(())
+    }
+
+    /// Put an app in a folder, or take it out with `None`
+    /// (OpenRPC: launcher.setFolder)
+    pub fn set_folder(&self, app_id: &str, folder: Option<&str>) -> Result<MutationReceipt> {
+        self.mutate(
+            &new_mutation_id(),
+            LauncherMutation::SetFolder {
+                app_id: app_id.to_string(),
+                folder: folder.map(str::to_string),
+            },
+        )
+    }
+
+    /// Pin or unpin an app (OpenRPC: launcher.setPinned)
+    pub fn set_pinned(&self, app_id: &str, pinned: bool) -> Result<MutationReceipt> {
+        self.mutate(
+            &new_mutation_id(),
+            LauncherMutation::SetPinned {
+                app_id: app_id.to_string(),
+                pinned,
+            },
+        )
+    }
+
+    /// Apply a layout edit at most once per mutation id
+    /// (OpenRPC: launcher.mutate)
+    ///
+    /// The layout is written together with its generation bump: if either
+    /// fails, neither takes effect. Sending the same `mutation_id` again
+    /// returns the first receipt without applying the edit twice. An edit
+    /// that changes nothing leaves the generation where it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error carrying a [`MutationRejected`] with the generation
+    /// the layout is still at
+    pub fn mutate(&self, mutation_id: &str, mutation: LauncherMutation) -> Result<MutationReceipt> {
+        let scope = format!("launcher:{}", self.user_id);
+        // Layout as it was before this mutation wrote over it
+        let overwritten = RefCell::new(None);
+        let result = self.sql_storage.apply_mutation(&scope, mutation_id, || {
+            let mut layout = self.get_layout()?;
+            let previous = layout.clone();
+            let now = self.clock.now_unix();
+            let changed = match &mutation {
+                LauncherMutation::SetLayout { app_ids } => {
+                    layout.set_order(app_ids, &self.device_id, now);
+                    true
+                }
+                LauncherMutation::SetFolder { app_id, folder } => {
+                    layout.set_folder(app_id, folder.as_deref(), &self.device_id, now)
+                }
+                LauncherMutation::SetPinned { app_id, pinned } => {
+                    layout.set_pinned(app_id, *pinned, &self.device_id, now)
+                }
+            };
+            if !changed {
+                return self.sql_storage.launcher_generation();
+            }
+            let generation = self.sql_storage.record_launcher_change(
+                LauncherChangeKind::Layout,
+                None,
+                Some(&self.user_id),
+            )?;
+            self.write_layout(&layout)?;
+            *overwritten.borrow_mut() = Some(previous);
+            Ok(generation)
+        });
+
+        result.map_err(|e| {
+            // The generation bump was rolled back; so is the layout
+            if let Some(previous) = overwritten.take() {
+                let _ = self.write_layout(&previous);
+            }
+            MutationRejected::wrap(e, mutation_id, self.generation().ok())
+        })
+    }
+
+    /// The layout, only if the launcher changed since `generation`
+    /// (OpenRPC: launcher.getSince)
+    pub fn get_since(&self, generation: u64) -> Result<Since<LauncherLayout>> {
+        Since::check(generation, self.generation()?, || self.get_layout())
+    }
 
+    /// Merge the layout from another of the user's devices
+    /// (OpenRPC: launcher.mergeLayout)
+    ///
+    /// Each app's placement, folder and pin keeps its latest edit, so edits
+    /// made offline on both devices survive unless they touched the same
+    /// thing. The report lists what was taken from `remote` and every
+    /// conflict with how it was resolved.
+    pub fn merge_layout(&self, remote: &LauncherLayout) -> Result<MergeReport> {
+        let mut layout = self.get_layout()?;
+        let before = layout.clone();
+        let report = layout.merge(remote);
+        if !report.changed.is_empty() {
+            self.save_edit(&layout)?;
+        } else if layout != before {
+            // Only stamps and seen edits moved on; nothing shown changed
+            self.write_layout(&layout)?;
+        }
+        Ok(report)
+    }
+
+    /// Generation of the latest launcher change (OpenRPC: launcher.generation)
+    ///
+    /// Persisted, and 0 before the first change.
+    pub fn generation(&self) -> Result<u64> {
+        self.sql_storage.launcher_generation()
+    }
+
+    /// What changed in this user's launcher grid since a generation
+    /// (OpenRPC: launcher.changesSince)
+    ///
+    /// Asks for a full reload when `generation` is older than the kept
+    /// change history or newer than the current generation.
+    pub fn changes_since(&self, generation: u64) -> Result<ChangeSummary> {
+        let current = self.sql_storage.launcher_generation()?;
+        let oldest = self.sql_storage.oldest_launcher_change()?;
+        if generation > current || oldest.is_some_and(|oldest| generation + 1 < oldest) {
+            return Ok(ChangeSummary::reload(generation, current));
+        }
+
+        let changes = self.sql_storage.list_launcher_changes_since(generation)?;
+        Ok(ChangeSummary::from_changes(
+            generation,
+            current,
+            &self.user_id,
+            &changes,
+        ))
+    }
+
+    fn save_edit(&self, layout: &LauncherLayout) -> Result<()> {
+        self.write_layout(layout)?;
+        self.sql_storage.record_launcher_change(
+            LauncherChangeKind::Layout,
+            None,
+            Some(&self.user_id),
+        )?;
+        Ok(())
+    }
+
+    fn write_layout(&self, layout: &LauncherLayout) -> Result<()> {
         let layout_json =
-            serde_json::to_vec(&layout).context("Failed to serialize launcher layout")?;
+            serde_json::to_vec(layout).context("Failed to serialize launcher layout")?;
 
         self.file_storage
-            .write(&self.layout_path, &layout_json, &self.encryption_key)
+            .write_classified(
+                &self.layout_path,
+                &layout_json,
+                &self.encryption_key,
+                DataClass::Preferences,
+            )
             .context("Failed to write launcher layout")?;
 
         Ok(())
     }
 
     /// Derive encryption key for launcher layout
-    fn derive_layout_key(user_id: &str) -> [u8; 32] {
+    pub(crate) fn derive_layout_key(user_id: &str) -> [u8; 32] {
         use blake3::Hasher;
         let mut hasher = Hasher::new();
         hasher.update(b"osnova-launcher-layout-key-v1:");
@@ -186,6 +392,8 @@ impl LauncherService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::MockClock;
+    use std::sync::Arc;
     use tempfile::TempDir;
 
     fn create_test_service() -> Result<(LauncherService, TempDir)> {
@@ -282,4 +490,183 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_merge_layouts_from_two_devices() -> Result<()> {
+        let phone_dir = TempDir::new()?;
+        let laptop_dir = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let phone = LauncherService::new(phone_dir.path(), "user-123")?
+            .with_device("phone")
+            .with_clock(clock.clone());
+        let laptop = LauncherService::new(laptop_dir.path(), "user-123")?
+            .with_device("laptop")
+            .with_clock(clock.clone());
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        phone.set_layout(ids(&["a", "b", "c"]))?;
+        laptop.merge_layout(&phone.get_layout()?)?;
+        assert_eq!(laptop.get_layout()?.app_ids, ids(&["a", "b", "c"]));
+
+        clock.set_unix(2_000);
+        phone.set_layout(ids(&["b", "a", "c"]))?;
+        laptop.set_folder("c", Some("Tools"))?;
+        laptop.set_pinned("a", true)?;
+
+        let generation = phone.generation()?;
+        let report = phone.merge_layout(&laptop.get_layout()?)?;
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.changed, vec!["a", "c"]);
+        assert_eq!(phone.generation()?, generation + 1);
+        laptop.merge_layout(&phone.get_layout()?)?;
+
+        let merged = phone.get_layout()?;
+        assert_eq!(merged.app_ids, ids(&["b", "a", "c"]));
+        assert_eq!(merged, laptop.get_layout()?);
+        assert!(merged.item("a").unwrap().pinned.value);
+
+        // Merging again changes nothing and leaves the generation alone
+        assert!(phone.merge_layout(&laptop.get_layout()?)?.is_empty());
+        assert_eq!(phone.generation()?, generation + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mutate_retry_is_idempotent() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        service.set_layout(vec!["a".to_string(), "b".to_string()])?;
+        let pin = || LauncherMutation::SetPinned {
+            app_id: "a".to_string(),
+            pinned: true,
+        };
+
+        let receipt = service.mutate("m-1", pin())?;
+        assert_eq!(receipt.mutation_id, "m-1");
+        assert_eq!(receipt.generation, service.generation()?);
+
+        // The response was lost; the retry gets the same receipt
+        assert_eq!(service.mutate("m-1", pin())?, receipt);
+        assert_eq!(service.generation()?, receipt.generation);
+
+        // A second id that changes nothing keeps the generation
+        assert_eq!(service.mutate("m-2", pin())?.generation, receipt.generation);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mutate_failure_reports_last_good_generation() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        service.set_layout(vec!["a".to_string(), "b".to_string()])?;
+        let generation = service.generation()?;
+        let layout = service.get_layout()?;
+
+        let conn = rusqlite::Connection::open(temp.path().join("osnova.db"))?;
+        conn.execute_batch(
+            "CREATE TRIGGER fail BEFORE INSERT ON mutation_receipts
+             BEGIN SELECT RAISE(ABORT, 'injected'); END;",
+        )?;
+
+        let err = service
+            .mutate(
+                "m-1",
+                LauncherMutation::SetFolder {
+                    app_id: "b".to_string(),
+                    folder: Some("Tools".to_string()),
+                },
+            )
+            .unwrap_err();
+        let rejection = err.downcast_ref::<MutationRejected>().unwrap();
+        assert_eq!(rejection.mutation_id, "m-1");
+        assert_eq!(rejection.last_good_generation, Some(generation));
+        assert!(rejection.reason.contains("injected"));
+
+        // Neither the generation bump nor the layout write stuck
+        assert_eq!(service.generation()?, generation);
+        assert_eq!(service.get_layout()?, layout);
+
+        // Once storage recovers, the same id applies
+        conn.execute_batch("DROP TRIGGER fail;")?;
+        let receipt = service.mutate(
+            "m-1",
+            LauncherMutation::SetFolder {
+                app_id: "b".to_string(),
+                folder: Some("Tools".to_string()),
+            },
+        )?;
+        assert_eq!(receipt.generation, generation + 1);
+        assert_eq!(
+            service
+                .get_layout()?
+                .item("b")
+                .unwrap()
+                .folder
+                .value
+                .as_deref(),
+            Some("Tools")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_since() -> Result<()> {
+        let (service, _temp) = create_test_service()?;
+        let receipt = service.set_layout(vec!["a".to_string()])?;
+
+        assert_eq!(
+            service.get_since(receipt.generation)?,
+            Since::Unchanged {
+                generation: receipt.generation
+            }
+        );
+        match service.get_since(0)? {
+            Since::Changed { generation, state } => {
+                assert_eq!(generation, receipt.generation);
+                assert_eq!(state.app_ids, vec!["a"]);
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_mutations_get_distinct_generations() -> Result<()> {
+        let temp = TempDir::new()?;
+        let apps: Vec<String> = (0..8).map(|i| format!("app-{}", i)).collect();
+        LauncherService::new(temp.path(), "user-123")?.set_layout(apps.clone())?;
+
+        let receipts = std::thread::scope(|scope| {
+            let handles: Vec<_> = apps
+                .iter()
+                .map(|app_id| {
+                    let path = temp.path();
+                    scope.spawn(move || {
+                        let service = LauncherService::new(path, "user-123")?;
+                        service.mutate(
+                            &format!("pin-{}", app_id),
+                            LauncherMutation::SetPinned {
+                                app_id: app_id.clone(),
+                                pinned: true,
+                            },
+                        )
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        let service = LauncherService::new(temp.path(), "user-123")?;
+        let mut generations: Vec<u64> = receipts.iter().map(|r| r.generation).collect();
+        generations.sort_unstable();
+        generations.dedup();
+        assert_eq!(generations.len(), apps.len());
+        assert_eq!(generations.last().copied(), Some(service.generation()?));
+
+        // No edit was lost to a concurrent one
+        let layout = service.get_layout()?;
+        assert!(apps.iter().all(|id| layout.item(id).unwrap().pinned.value));
+        Ok(())
+    }
 }
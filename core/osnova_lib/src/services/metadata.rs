@@ -0,0 +1,672 @@
+//! Metadata refresh for installed applications
+//!
+//! Publishers change an app's name, icon or description without shipping a
+//! new version, so installed listings drift from the published manifest.
+//! [`MetadataService::refresh`] re-resolves one app's manifest and updates
+//! the stored listing only when one of those fields changed, recording when
+//! the app was last refreshed either way.
+//!
+//! Rather than re-resolving every manifest at once, the scheduler
+//! ([`MetadataService::run_scheduler`]) refreshes a small batch of the
+//! stalest apps per tick and stops early when the bandwidth policy defers
+//! transfers or the user has switched Osnova offline. Progress lives in the
+//! refresh timestamps, so an interrupted run resumes with the apps it had
+//! not reached yet.
+//!
+//! The frontend uses `icon_uri` directly and there is no icon cache, so a
+//! changed icon is picked up by storing its new URI.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::manifest::{resolve_manifest, ManifestSchema};
+use crate::models::application::OsnovaApplication;
+use crate::models::launcher_change::LauncherChangeKind;
+use crate::network::bandwidth::TransferDecision;
+use crate::network::{BandwidthMeter, CancellationToken, NetworkOptions, TransferCategory};
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::updates::ManifestFetcher;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+use crate::OsnovaError;
+
+/// Event emitted to the frontend when an app's listing changed
+pub const APPS_METADATA_CHANGED_EVENT: &str = "apps-metadata-changed";
+
+/// Event emitted to the frontend as a manual refresh progresses
+pub const APPS_REFRESH_PROGRESS_EVENT: &str = "apps-refresh-progress";
+
+/// Apps refreshed per scheduler tick
+pub const DEFAULT_METADATA_BATCH: usize = 3;
+
+/// Age after which an app's listing is due for a refresh (1 day)
+pub const DEFAULT_METADATA_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+
+/// Seconds between scheduler ticks (15 minutes)
+pub const DEFAULT_METADATA_TICK_SECS: u64 = 15 * 60;
+
+/// Manifests resolved at once by a manual refresh
+pub const DEFAULT_REFRESH_CONCURRENCY: usize = 4;
+
+/// A listing field that can change between manifest resolutions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MetadataField {
+    /// Application name
+    Name,
+    /// Icon URI
+    IconUri,
+    /// Description
+    Description,
+}
+
+/// Outcome of refreshing one app's listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataRefresh {
+    /// Application identifier
+    pub app_id: String,
+    /// Fields that changed; empty when the listing was already current
+    pub changed: Vec<MetadataField>,
+    /// When the listing was refreshed (Unix seconds)
+    pub refreshed_at: u64,
+    /// Launcher generation of the change; absent when nothing changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<u64>,
+}
+
+/// Progress of a manual refresh of every installed app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshProgress {
+    /// Application that just finished
+    pub app_id: String,
+    /// Apps finished so far, including this one
+    pub completed: usize,
+    /// Apps being refreshed
+    pub total: usize,
+    /// Fields of this app that changed
+    pub changed: Vec<MetadataField>,
+    /// Why refreshing this app failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Installed application metadata refresh
+///
+/// Share it in an `Arc`: [`refresh_all`](Self::refresh_all) and
+/// [`run_scheduler`](Self::run_scheduler) need one.
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::network::NetworkOptions;
+/// use osnova_lib::services::MetadataService;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let service = MetadataService::new("/path/to/storage")?;
+/// let refresh = service.refresh("file:///apps/wallet.json", &NetworkOptions::default()).await?;
+/// println!("changed: {:?}", refresh.changed);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MetadataService {
+    storage: Mutex<SqlStorage>,
+    meter: Option<Arc<BandwidthMeter>>,
+    events: Option<EventBus>,
+    clock: SharedClock,
+    fetch_manifest: ManifestFetcher,
+    batch: usize,
+    max_age_secs: u64,
+}
+
+impl MetadataService {
+    /// Create a new metadata service
+    ///
+    /// Manifests are resolved from each app's ID (file:// and https:// only;
+    /// use [`with_manifest_fetcher`](Self::with_manifest_fetcher) for
+    /// ant:// manifests).
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    pub fn new<P: Into<PathBuf>>(storage_path: P) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            meter: None,
+            events: None,
+            clock: time::default_clock(),
+            fetch_manifest: Arc::new(|uri| {
+                Box::pin(async move { Ok(resolve_manifest(&uri, None).await?) })
+            }),
+            batch: DEFAULT_METADATA_BATCH,
+            max_age_secs: DEFAULT_METADATA_MAX_AGE_SECS,
+        })
+    }
+
+    /// Meter resolved manifests and defer scheduled refreshes by policy
+    pub fn with_bandwidth_meter(mut self, meter: Arc<BandwidthMeter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Publish changed listings on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for refresh timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Override how published manifests are fetched
+    pub fn with_manifest_fetcher<F, Fut>(mut self, fetch: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ManifestSchema>> + Send + 'static,
+    {
+        self.fetch_manifest = Arc::new(move |app_id| Box::pin(fetch(app_id)));
+        self
+    }
+
+    /// Set how many apps a scheduler tick refreshes (at least 1)
+    pub fn with_batch(mut self, batch: usize) -> Self {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Set how old a listing must be before the scheduler refreshes it
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age_secs = max_age.as_secs();
+        self
+    }
+
+    /// Re-resolve an installed app's manifest and update its listing
+    ///
+    /// Only the name, icon URI and description are compared; the version and
+    /// components change through updates. The refresh time is recorded even
+    /// when nothing changed, so the app moves to the back of the queue.
+    /// Fails with `OsnovaError::OfflineMode` while the options' kill switch
+    /// is engaged.
+    pub async fn refresh(&self, app_id: &str, options: &NetworkOptions) -> Result<MetadataRefresh> {
+        if self.storage().get_application(app_id)?.is_none() {
+            anyhow::bail!("Application {} not found", app_id);
+        }
+        options.kill_switch.ensure_online("metadata refresh")?;
+
+        let manifest = tokio::select! {
+            biased;
+            _ = options.kill_switch.engaged() => {
+                return Err(OsnovaError::OfflineMode {
+                    operation: "metadata refresh".to_string(),
+                }
+                .into());
+            }
+            _ = options.cancellation.cancelled() => anyhow::bail!("Metadata refresh cancelled"),
+            result = tokio::time::timeout(options.timeout, (self.fetch_manifest)(app_id.to_string())) => {
+                result.with_context(|| format!("Timed out resolving the manifest of {}", app_id))??
+            }
+        };
+        if let Some(meter) = &self.meter {
+            let bytes = serde_json::to_vec(&manifest).map_or(0, |json| json.len() as u64);
+            meter.record(TransferCategory::Prefetch, bytes)?;
+        }
+
+        self.apply(app_id, &manifest)
+    }
+
+    /// Refresh a batch of the stalest apps, as the scheduler does
+    ///
+    /// Refreshes up to the batch size of apps not refreshed within the
+    /// maximum age, least recently refreshed first, and stops early when the
+    /// bandwidth policy defers transfers or the options' kill switch is
+    /// engaged. A failure affecting one app is reported in the result and
+    /// does not stop the others.
+    pub async fn refresh_stale(
+        &self,
+        options: &NetworkOptions,
+    ) -> Result<Vec<(String, Result<MetadataRefresh>)>> {
+        let before = self.clock.now_unix().saturating_sub(self.max_age_secs);
+        let stale = self.storage().list_stale_applications(before, self.batch)?;
+
+        let mut results = Vec::new();
+        for app_id in stale {
+            if let Some(meter) = &self.meter {
+                if let TransferDecision::Deferred(_) = meter.check()? {
+                    break;
+                }
+            }
+            if options.cancellation.is_cancelled() || options.kill_switch.is_engaged() {
+                break;
+            }
+            let result = self.refresh(&app_id, options).await;
+            results.push((app_id, result));
+        }
+
+        Ok(results)
+    }
+
+    /// Refresh every installed app, `concurrency` at a time
+    ///
+    /// A manual refresh: it is not deferred by the bandwidth policy, though
+    /// its transfers are still metered. `on_progress` is called as each app
+    /// finishes.
+    pub async fn refresh_all<F>(
+        self: &Arc<Self>,
+        concurrency: usize,
+        options: &NetworkOptions,
+        mut on_progress: F,
+    ) -> Result<Vec<RefreshProgress>>
+    where
+        F: FnMut(&RefreshProgress),
+    {
+        let mut pending: Vec<String> = self
+            .storage()
+            .list_applications()?
+            .iter()
+            .map(|app| app.id().to_string())
+            .collect();
+        pending.reverse();
+        let total = pending.len();
+
+        let mut tasks = JoinSet::new();
+        let mut finished = Vec::with_capacity(total);
+        loop {
+            while tasks.len() < concurrency.max(1) {
+                let Some(app_id) = pending.pop() else {
+                    break;
+                };
+                let service = self.clone();
+                let options = options.clone();
+                tasks.spawn(async move {
+                    let result = service.refresh(&app_id, &options).await;
+                    (app_id, result)
+                });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+
+            let (app_id, result) = joined.context("Metadata refresh task failed")?;
+            let (changed, error) = match result {
+                Ok(refresh) => (refresh.changed, None),
+                Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+            };
+            let progress = RefreshProgress {
+                app_id,
+                completed: finished.len() + 1,
+                total,
+                changed,
+                error,
+            };
+            on_progress(&progress);
+            finished.push(progress);
+        }
+
+        Ok(finished)
+    }
+
+    /// Run scheduled refreshes until `shutdown` is cancelled
+    ///
+    /// Spawn this on the async runtime. Each tick refreshes one batch, so
+    /// refreshing many apps is spread over several ticks. Ticks while
+    /// offline refresh nothing; releasing the kill switch starts the next
+    /// tick right away.
+    pub async fn run_scheduler(self: Arc<Self>, shutdown: CancellationToken) {
+        let options = NetworkOptions::default().with_cancellation(shutdown.clone());
+        let mut offline = options.kill_switch.subscribe();
+        while !shutdown.is_cancelled() {
+            // A failed tick is retried on the next one
+            let _ = self.refresh_stale(&options).await;
+
+            offline.mark_unchanged();
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(DEFAULT_METADATA_TICK_SECS)) => {}
+                Ok(()) = offline.changed() => {}
+                _ = shutdown.cancelled() => break,
+            }
+        }
+    }
+
+    /// Store the changed fields of a resolved manifest
+    fn apply(&self, app_id: &str, manifest: &ManifestSchema) -> Result<MetadataRefresh> {
+        let storage = self.storage();
+        let mut app = storage
+            .get_application(app_id)?
+            .with_context(|| format!("Application {} not found", app_id))?;
+
+        let changed = changed_fields(&app, manifest);
+        let mut generation = None;
+        if !changed.is_empty() {
+            for field in &changed {
+                app = match field {
+                    MetadataField::Name => app.with_name(&manifest.name),
+                    MetadataField::IconUri => app.with_icon_uri(&manifest.icon_uri),
+                    MetadataField::Description => app.with_description(&manifest.description),
+                };
+            }
+            storage.update_application(&app)?;
+            generation = Some(storage.record_launcher_change(
+                LauncherChangeKind::Updated,
+                Some(app_id),
+                None,
+            )?);
+        }
+
+        let refreshed_at = self.clock.now_unix();
+        storage.set_last_refreshed(app_id, refreshed_at)?;
+        drop(storage);
+
+        let refresh = MetadataRefresh {
+            app_id: app_id.to_string(),
+            changed,
+            refreshed_at,
+            generation,
+        };
+        if !refresh.changed.is_empty() {
+            if let Some(events) = &self.events {
+                events.publish(AppEvent::AppMetadataChanged {
+                    refresh: refresh.clone(),
+                });
+            }
+        }
+
+        Ok(refresh)
+    }
+
+    fn storage(&self) -> MutexGuard<'_, SqlStorage> {
+        self.storage.lock().unwrap()
+    }
+}
+
+/// Listing fields that differ between an installed app and its manifest
+fn changed_fields(app: &OsnovaApplication, manifest: &ManifestSchema) -> Vec<MetadataField> {
+    let mut changed = Vec::new();
+    if app.name() != manifest.name {
+        changed.push(MetadataField::Name);
+    }
+    if app.icon_uri() != manifest.icon_uri {
+        changed.push(MetadataField::IconUri);
+    }
+    if app.description() != manifest.description {
+        changed.push(MetadataField::Description);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::BandwidthPolicy;
+    use crate::time::MockClock;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn manifest(app_id: &str, name: &str, icon_uri: &str, description: &str) -> ManifestSchema {
+        ManifestSchema {
+            id: app_id.to_string(),
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            icon_uri: icon_uri.to_string(),
+            description: description.to_string(),
+            publisher: None,
+            signature: None,
+            signature_policy: None,
+            components: vec![],
+            uri_schemes: vec![],
+            data_offers: Vec::new(),
+            searchable_config_keys: Vec::new(),
+            settings_schema: None,
+            feature_flags: Vec::new(),
+            metadata: None,
+            min_core_version: None,
+            extra: Default::default(),
+        }
+    }
+
+    struct Fixture {
+        temp: TempDir,
+        clock: Arc<MockClock>,
+        published: Arc<Mutex<HashMap<String, ManifestSchema>>>,
+        fetched: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Fixture {
+        /// Install apps whose published manifests match their listings
+        fn new(app_ids: &[&str]) -> Result<Self> {
+            let temp = TempDir::new()?;
+            let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+            let mut published = HashMap::new();
+            for app_id in app_ids {
+                let manifest = manifest(app_id, app_id, "https://icon/v1", "Old description");
+                storage.upsert_application(&OsnovaApplication::try_from(&manifest)?)?;
+                published.insert(app_id.to_string(), manifest);
+            }
+
+            Ok(Self {
+                temp,
+                clock: Arc::new(MockClock::new(10 * DAY)),
+                published: Arc::new(Mutex::new(published)),
+                fetched: Arc::new(Mutex::new(Vec::new())),
+            })
+        }
+
+        fn service(&self) -> Result<MetadataService> {
+            let published = self.published.clone();
+            let fetched = self.fetched.clone();
+            Ok(MetadataService::new(self.temp.path())?
+                .with_clock(self.clock.clone())
+                .with_manifest_fetcher(move |app_id| {
+                    fetched.lock().unwrap().push(app_id.clone());
+                    let manifest = published.lock().unwrap().get(&app_id).cloned();
+                    async move { manifest.context("not published") }
+                }))
+        }
+
+        fn publish(&self, manifest: ManifestSchema) {
+            self.published
+                .lock()
+                .unwrap()
+                .insert(manifest.id.clone(), manifest);
+        }
+
+        fn storage(&self) -> Result<SqlStorage> {
+            SqlStorage::new(self.temp.path().join("osnova.db"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_updates_only_changed_fields() -> Result<()> {
+        let fixture = Fixture::new(&["app-a"])?;
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let service = fixture.service()?.with_events(events);
+        fixture.publish(manifest(
+            "app-a",
+            "app-a",
+            "https://icon/v2",
+            "New description",
+        ));
+
+        let refresh = service.refresh("app-a", &NetworkOptions::default()).await?;
+        assert_eq!(
+            refresh.changed,
+            vec![MetadataField::IconUri, MetadataField::Description]
+        );
+        assert_eq!(refresh.refreshed_at, 10 * DAY);
+
+        let storage = fixture.storage()?;
+        let app = storage.get_application("app-a")?.unwrap();
+        assert_eq!(app.name(), "app-a");
+        assert_eq!(app.icon_uri(), "https://icon/v2");
+        assert_eq!(app.description(), "New description");
+        assert_eq!(storage.get_last_refreshed("app-a")?, Some(10 * DAY));
+        assert_eq!(refresh.generation, Some(storage.launcher_generation()?));
+
+        assert_eq!(rx.try_recv()?, AppEvent::AppMetadataChanged { refresh });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_changes_is_a_no_op() -> Result<()> {
+        let fixture = Fixture::new(&["app-a"])?;
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let service = fixture.service()?.with_events(events);
+        let before = fixture.storage()?.get_application("app-a")?;
+
+        let refresh = service.refresh("app-a", &NetworkOptions::default()).await?;
+        assert!(refresh.changed.is_empty());
+        assert_eq!(fixture.storage()?.get_application("app-a")?, before);
+        assert!(rx.try_recv().is_err());
+
+        // Still recorded, so the app goes to the back of the queue
+        assert_eq!(
+            fixture.storage()?.get_last_refreshed("app-a")?,
+            Some(10 * DAY)
+        );
+
+        assert!(service
+            .refresh("app-missing", &NetworkOptions::default())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_refreshes_stalest_first() -> Result<()> {
+        let fixture = Fixture::new(&["app-a", "app-b", "app-c", "app-d"])?;
+        let storage = fixture.storage()?;
+        storage.set_last_refreshed("app-a", 8 * DAY)?;
+        storage.set_last_refreshed("app-b", 5 * DAY)?;
+        // Refreshed within the maximum age, so not due
+        storage.set_last_refreshed("app-d", 10 * DAY - 60)?;
+        let service = fixture.service()?.with_batch(2);
+
+        let first = service.refresh_stale(&NetworkOptions::default()).await?;
+        let ids: Vec<&str> = first.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["app-c", "app-b"]);
+        assert!(first.iter().all(|(_, result)| result.is_ok()));
+
+        // The next tick picks up where the last one stopped
+        fixture.clock.advance(Duration::from_secs(60));
+        let second = service.refresh_stale(&NetworkOptions::default()).await?;
+        let ids: Vec<&str> = second.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["app-a"]);
+
+        assert!(service
+            .refresh_stale(&NetworkOptions::default())
+            .await?
+            .is_empty());
+        assert_eq!(
+            *fixture.fetched.lock().unwrap(),
+            vec!["app-c", "app-b", "app-a"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_respects_bandwidth_policy() -> Result<()> {
+        let fixture = Fixture::new(&["app-a", "app-b"])?;
+        let meter = Arc::new(BandwidthMeter::new(
+            fixture.storage()?,
+            BandwidthPolicy::DailyLimit { bytes_per_day: 1 },
+        ));
+        let service = fixture.service()?.with_bandwidth_meter(meter.clone());
+
+        // The first manifest uses up the daily limit
+        let refreshed = service.refresh_stale(&NetworkOptions::default()).await?;
+        assert_eq!(refreshed.len(), 1);
+        assert!(meter.usage()?.today.total > 0);
+        assert_eq!(fixture.fetched.lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_defers_while_offline() -> Result<()> {
+        use crate::network::NetworkKillSwitch;
+
+        let fixture = Fixture::new(&["app-a", "app-b"])?;
+        let service = fixture.service()?;
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let options = NetworkOptions::default().with_kill_switch(switch.clone());
+        switch.engage();
+
+        // Scheduled refreshes wait; a manual one fails outright
+        assert!(service.refresh_stale(&options).await?.is_empty());
+        let err = service.refresh("app-a", &options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::OfflineMode { .. })
+        ));
+        assert!(fixture.fetched.lock().unwrap().is_empty());
+        assert_eq!(fixture.storage()?.get_last_refreshed("app-a")?, None);
+
+        switch.release();
+        assert_eq!(service.refresh_stale(&options).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_all_respects_concurrency_bound() -> Result<()> {
+        let app_ids: Vec<String> = (0..8).map(|i| format!("app-{}", i)).collect();
+        let ids: Vec<&str> = app_ids.iter().map(String::as_str).collect();
+        let fixture = Fixture::new(&ids)?;
+        fixture.publish(manifest(
+            "app-3",
+            "Renamed",
+            "https://icon/v1",
+            "Old description",
+        ));
+        fixture.published.lock().unwrap().remove("app-5");
+
+        let in_flight = Arc::new(Mutex::new((0usize, 0usize)));
+        let published = fixture.published.clone();
+        let counter = in_flight.clone();
+        let service = Arc::new(fixture.service()?.with_manifest_fetcher(move |app_id| {
+            let manifest = published.lock().unwrap().get(&app_id).cloned();
+            let counter = counter.clone();
+            async move {
+                {
+                    let mut counter = counter.lock().unwrap();
+                    counter.0 += 1;
+                    counter.1 = counter.1.max(counter.0);
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                counter.lock().unwrap().0 -= 1;
+                manifest.context("not published")
+            }
+        }));
+
+        let mut seen = Vec::new();
+        let progress = service
+            .refresh_all(3, &NetworkOptions::default(), |progress| {
+                seen.push(progress.completed)
+            })
+            .await?;
+
+        assert_eq!(in_flight.lock().unwrap().1, 3);
+        assert_eq!(seen, (1..=8).collect::<Vec<_>>());
+        assert!(progress.iter().all(|progress| progress.total == 8));
+
+        let renamed = progress.iter().find(|p| p.app_id == "app-3").unwrap();
+        assert_eq!(renamed.changed, vec![MetadataField::Name]);
+        let failed = progress.iter().find(|p| p.app_id == "app-5").unwrap();
+        assert!(failed.error.is_some());
+        Ok(())
+    }
+}
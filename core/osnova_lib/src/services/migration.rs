@@ -0,0 +1,1502 @@
+//! Server-to-server migration for Client-Server mode
+//!
+//! Moves the entire Osnova state of a server to a new machine that takes
+//! over as the server.
+//!
+//! Handles:
+//! - Exporting: once the user confirms on the old server,
+//!   [`MigrationService::begin_export`] takes a consistent snapshot (an
+//!   SQLite online backup plus a copy of the file storage tree) and issues
+//!   a one-time [`MigrationKey`], shown to the user as words
+//! - Streaming: the new server, paired with the old one as a device, pulls
+//!   the snapshot in chunks through `migration.export_stream`. Chunks are
+//!   encrypted under the migration key, and every request proves the key
+//! - Importing: [`MigrationService::import_from`] stages the chunks, checks
+//!   every chunk and file hash and the SQLite integrity check, and swaps the
+//!   staged tree in as the storage root. An interrupted import resumes from
+//!   the last staged chunk
+//! - Retiring the old server: it marks itself migrated only when the new
+//!   server confirms the import, and from then on
+//!   [`MigrationService::ensure_not_migrated`] refuses to start it until a
+//!   factory reset sets it up again
+//!
+//! Only the data root moves; the cache is re-fetchable and the config root
+//! holds this OS user's install id. Owner locks are not copied, so the new
+//! install claims the imported storage as its own. Imported apps are marked
+//! not materialized, so they are downloaded again before they launch (see
+//! [`AppsService::materialize_all`](crate::services::AppsService::materialize_all)).
+//!
+//! An export lives in memory on the old server: restarting it cancels the
+//! export, and the user starts a new one with a new key.
+//!
+//! Import must run before any service opens the new server's storage root,
+//! since the root is replaced.
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::Engine;
+use bip39::rand::{thread_rng, RngCore};
+use bip39::{Language, Mnemonic};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::encryption::CocoonEncryption;
+use crate::error::OsnovaError;
+use crate::services::sessions::RequestContext;
+use crate::storage::{ownership, SqlStorage};
+use crate::time::{self, SharedClock};
+
+/// Event name of [`MigrationProgress`]
+pub const MIGRATION_PROGRESS_EVENT: &str = "migration-progress";
+
+/// Plaintext bytes per streamed chunk
+pub const DEFAULT_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Most chunks the old server returns per `migration.export_stream` call
+pub const MAX_BATCH_CHUNKS: usize = 16;
+
+/// Random bytes behind a migration key; 12 words
+const KEY_ENTROPY_BYTES: usize = 16;
+
+/// Bytes of random salt prefixed to every sealed message
+const SALT_BYTES: usize = 32;
+
+const SEAL_CONTEXT: &str = "osnova migration 2025 seal";
+const PROOF_CONTEXT: &str = "osnova migration 2025 proof";
+const EXPORT_ID_CONTEXT: &str = "osnova migration 2025 export id";
+
+/// Database file in the data root
+const DATABASE_FILE: &str = "osnova.db";
+
+/// Directory of the data root holding an export snapshot
+const SNAPSHOT_DIR: &str = "migration";
+
+/// Directory of the data root holding backend sockets
+const SOCKETS_DIR: &str = "sockets";
+
+/// Marker of a server whose data moved elsewhere
+const MIGRATED_FILE: &str = "migrated.json";
+
+/// Confirmation the new server still owes the old one
+const RECEIPT_FILE: &str = "migration-receipt.json";
+
+/// Entries at the top of the data root that are migration bookkeeping,
+/// not stored data
+pub(crate) const ROOT_BOOKKEEPING: [&str; 4] =
+    [SNAPSHOT_DIR, SOCKETS_DIR, MIGRATED_FILE, RECEIPT_FILE];
+
+/// Import state kept in the staging directory between attempts
+const PROGRESS_FILE: &str = ".migration-progress.json";
+
+/// Suffix of the sibling directory an import is staged in
+const STAGING_SUFFIX: &str = "migration-import";
+
+/// Suffix of the sibling directory the replaced storage root is moved to
+const PREVIOUS_SUFFIX: &str = "pre-migration";
+
+/// Suffix of files mid-write through an atomic rename
+const TEMP_SUFFIX: &str = ".osnova-tmp";
+
+/// One-time key protecting a migration
+///
+/// Shown to the user on the old server as twelve words and typed in on the
+/// new one. The export id, the request proof, and the encryption key are
+/// all derived from it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MigrationKey {
+    entropy: [u8; KEY_ENTROPY_BYTES],
+}
+
+impl MigrationKey {
+    /// Generate a fresh key
+    pub fn generate() -> Self {
+        let mut entropy = [0u8; KEY_ENTROPY_BYTES];
+        thread_rng().fill_bytes(&mut entropy);
+        Self { entropy }
+    }
+
+    /// Parse a key from the words shown on the old server
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the words are not a valid key
+    pub fn from_words(words: &str) -> Result<Self> {
+        let mnemonic = Mnemonic::parse_in(Language::English, words)
+            .map_err(|e| anyhow::anyhow!("Invalid migration key: {}", e))?;
+        let entropy: [u8; KEY_ENTROPY_BYTES] = mnemonic
+            .to_entropy()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid migration key: expected 12 words"))?;
+        Ok(Self { entropy })
+    }
+
+    /// The key as words to show the user
+    pub fn words(&self) -> String {
+        Mnemonic::from_entropy(&self.entropy)
+            .expect("16 bytes is valid mnemonic entropy")
+            .to_string()
+    }
+
+    /// Export the key unlocks
+    pub fn export_id(&self) -> String {
+        hex::encode(&blake3::derive_key(EXPORT_ID_CONTEXT, &self.entropy)[..8])
+    }
+
+    /// Proof of the key sent with every request to the old server
+    fn proof(&self) -> String {
+        let proof_key = blake3::derive_key(PROOF_CONTEXT, &self.entropy);
+        blake3::keyed_hash(&proof_key, self.export_id().as_bytes())
+            .to_hex()
+            .to_string()
+    }
+
+    /// Encrypt under a key derived from a fresh salt, so messages never
+    /// repeat a key and nonce
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_BYTES];
+        thread_rng().fill_bytes(&mut salt);
+
+        let mut sealed = salt.to_vec();
+        sealed.extend(self.cipher(&salt).encrypt(plaintext)?);
+        Ok(sealed)
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        ensure!(
+            sealed.len() >= SALT_BYTES,
+            "Sealed migration data too short"
+        );
+        let (salt, ciphertext) = sealed.split_at(SALT_BYTES);
+        Ok(self.cipher(salt).decrypt(ciphertext)?)
+    }
+
+    fn cipher(&self, salt: &[u8]) -> CocoonEncryption {
+        let seal_key = blake3::derive_key(SEAL_CONTEXT, &self.entropy);
+        CocoonEncryption::new(blake3::keyed_hash(&seal_key, salt).as_bytes())
+    }
+}
+
+/// An export the user confirmed on the old server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOffer {
+    /// Export identifier, derived from the key
+    pub export_id: String,
+    /// Migration key words to show the user; not retrievable later
+    pub words: String,
+    /// Paired device allowed to stream the export
+    pub device_id: String,
+    /// Files in the snapshot
+    pub files: usize,
+    /// Bytes in the snapshot
+    pub bytes: u64,
+    /// Chunks the snapshot streams as
+    pub chunks: usize,
+    /// Unix timestamp of the snapshot
+    pub created_at: u64,
+}
+
+/// Request for the next part of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStreamRequest {
+    /// Export identifier
+    pub export_id: String,
+    /// Proof of the migration key
+    pub proof: String,
+    /// First chunk wanted
+    pub from_chunk: usize,
+    /// Most chunks wanted; capped at [`MAX_BATCH_CHUNKS`]
+    pub max_chunks: usize,
+}
+
+/// Part of an export stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportBatch {
+    /// Export identifier
+    pub export_id: String,
+    /// Chunks in the export
+    pub total_chunks: usize,
+    /// Sealed snapshot manifest (base64), sent with the batch starting at
+    /// chunk 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// Consecutive chunks from the requested one
+    pub chunks: Vec<ExportChunk>,
+}
+
+/// One sealed chunk of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportChunk {
+    /// Chunk number
+    pub index: usize,
+    /// Sealed chunk data (base64)
+    pub data: String,
+}
+
+/// The new server's confirmation that an import succeeded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReceipt {
+    /// Export identifier
+    pub export_id: String,
+    /// Proof of the migration key
+    pub proof: String,
+    /// BLAKE3 hash of the imported snapshot manifest
+    pub manifest_hash: String,
+}
+
+/// Record that a server's data moved to another server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationMarker {
+    /// Export that moved the data
+    pub export_id: String,
+    /// Paired device that imported it
+    pub device_id: String,
+    /// Unix timestamp of the confirmation
+    pub migrated_at: u64,
+}
+
+/// Side of a migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MigrationDirection {
+    /// The old server, sending its data
+    Export,
+    /// The new server, receiving it
+    Import,
+}
+
+/// Step a migration is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MigrationStage {
+    /// Chunks are being transferred
+    Streaming,
+    /// The staged data is being checked
+    Validating,
+    /// The staged data is replacing the storage root
+    Swapping,
+    /// The new server confirmed the import
+    Completed,
+}
+
+/// Progress of a migration, on either server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationProgress {
+    /// Export identifier
+    pub export_id: String,
+    /// Which server this is
+    pub direction: MigrationDirection,
+    /// Current step
+    pub stage: MigrationStage,
+    /// Chunks transferred so far
+    pub chunks_done: usize,
+    /// Chunks in the export
+    pub chunks_total: usize,
+    /// Plaintext bytes transferred so far
+    pub bytes_done: u64,
+}
+
+/// Outcome of an import on the new server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Export identifier
+    pub export_id: String,
+    /// Files imported
+    pub files: usize,
+    /// Bytes imported
+    pub bytes: u64,
+    /// Where the replaced storage root was moved, if it held anything
+    pub previous_root: Option<PathBuf>,
+    /// Whether the old server acknowledged the import; if not, retry with
+    /// [`MigrationService::resend_confirmation`]
+    pub confirmed: bool,
+    /// Installed apps whose components are not in this server's cache yet
+    pub unmaterialized: Vec<String>,
+}
+
+/// Connection from the new server to the old one
+pub trait MigrationTransport: Send + Sync {
+    /// Call `migration.export_stream` on the server at `address`
+    fn export_stream(&self, address: &str, request: &ExportStreamRequest) -> Result<ExportBatch>;
+
+    /// Call `migration.confirm` on the server at `address`
+    fn confirm(&self, address: &str, receipt: &MigrationReceipt) -> Result<MigrationMarker>;
+}
+
+/// Files and chunks of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotManifest {
+    export_id: String,
+    files: Vec<SnapshotFile>,
+    chunks: Vec<SnapshotChunk>,
+}
+
+impl SnapshotManifest {
+    fn bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    fn hash(&self) -> Result<String> {
+        Ok(blake3::hash(&serde_json::to_vec(self)?)
+            .to_hex()
+            .to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotFile {
+    /// Path relative to the data root, `/`-separated
+    path: String,
+    size: u64,
+    hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotChunk {
+    /// Index into the manifest's files
+    file: usize,
+    offset: u64,
+    len: usize,
+    hash: String,
+}
+
+/// An export waiting to be streamed
+struct PendingExport {
+    key: MigrationKey,
+    device_id: String,
+    manifest: SnapshotManifest,
+    sealed_manifest: String,
+    /// Chunks streamed at least once
+    served: Vec<bool>,
+}
+
+/// Import state persisted between attempts
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProgress {
+    manifest: SnapshotManifest,
+    next_chunk: usize,
+}
+
+type ProgressCallback = Arc<dyn Fn(&MigrationProgress) + Send + Sync>;
+
+/// Server-to-server migration service
+///
+/// Provides OpenRPC methods:
+/// - `migration.begin_export` - Snapshot this server for a paired device
+/// - `migration.export_stream` - Next chunks of the snapshot
+/// - `migration.confirm` - Acknowledge the import and retire this server
+/// - `migration.cancel` - Discard the pending export
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::migration::{MigrationKey, MigrationService};
+/// use osnova_lib::services::sessions::RequestContext;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// // On the old server, after the user confirms
+/// let old = MigrationService::new("/tmp/osnova-old");
+/// let offer = old.begin_export(&RequestContext::local(), "new-server")?;
+/// println!("Enter these words on the new server: {}", offer.words);
+///
+/// // The new server, paired as "new-server", then runs
+/// // `MigrationService::import_from(address, &MigrationKey::from_words(..)?)`
+/// # Ok(())
+/// # }
+/// ```
+pub struct MigrationService {
+    storage_path: PathBuf,
+    clock: SharedClock,
+    chunk_bytes: usize,
+    transport: Option<Arc<dyn MigrationTransport>>,
+    on_progress: Option<ProgressCallback>,
+    export: Mutex<Option<PendingExport>>,
+}
+
+impl MigrationService {
+    /// Create a migration service for a data root
+    ///
+    /// Nothing is opened until an export or import starts.
+    pub fn new<P: Into<PathBuf>>(storage_path: P) -> Self {
+        Self {
+            storage_path: storage_path.into(),
+            clock: time::default_clock(),
+            chunk_bytes: DEFAULT_CHUNK_BYTES,
+            transport: None,
+            on_progress: None,
+            export: Mutex::new(None),
+        }
+    }
+
+    /// Use a specific clock for snapshot and migration timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Split exports into chunks of a specific size, in bytes
+    pub fn with_chunk_size(mut self, chunk_bytes: usize) -> Self {
+        self.chunk_bytes = chunk_bytes.max(1);
+        self
+    }
+
+    /// Reach old servers through a transport, for imports
+    pub fn with_transport(mut self, transport: Arc<dyn MigrationTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Report progress, e.g. as a [`MIGRATION_PROGRESS_EVENT`] shell event
+    pub fn with_progress<F>(mut self, on_progress: F) -> Self
+    where
+        F: Fn(&MigrationProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+
+    /// Marker left by a completed migration away from a data root
+    pub fn migrated<P: AsRef<Path>>(storage_path: P) -> Result<Option<MigrationMarker>> {
+        match fs::read(storage_path.as_ref().join(MIGRATED_FILE)) {
+            Ok(bytes) => Ok(Some(
+                serde_json::from_slice(&bytes).context("Unreadable migration marker")?,
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Failed to read migration marker"),
+        }
+    }
+
+    /// Refuse to run a server whose data moved to another server
+    ///
+    /// Call before initializing services. A factory reset removes the
+    /// marker along with the rest of the data root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the export if the data root was migrated
+    pub fn ensure_not_migrated<P: AsRef<Path>>(storage_path: P) -> Result<()> {
+        if let Some(marker) = Self::migrated(storage_path)? {
+            bail!(
+                "This server's data moved to device {} (migration {}); \
+                 reset it to set it up again",
+                marker.device_id,
+                marker.export_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Snapshot this server for a paired device (OpenRPC: migration.begin_export)
+    ///
+    /// Only the local user can start an export, and only for a device with
+    /// an active device key. Replaces any export started earlier.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] for a remote caller, and an
+    /// error if the device is unknown or revoked or the snapshot fails
+    pub fn begin_export(&self, context: &RequestContext, device_id: &str) -> Result<ExportOffer> {
+        if context.is_remote() {
+            return Err(OsnovaError::Unauthorized(
+                "A migration export must be confirmed on the server itself".to_string(),
+            )
+            .into());
+        }
+        Self::ensure_not_migrated(&self.storage_path)?;
+
+        let storage = SqlStorage::new(self.storage_path.join(DATABASE_FILE))?;
+        let device_key = storage
+            .get_device_key(device_id)?
+            .with_context(|| format!("Device {} is not paired", device_id))?;
+        if device_key.is_revoked() {
+            bail!("Device {} has been revoked", device_id);
+        }
+
+        let key = MigrationKey::generate();
+        let snapshot = self.snapshot_path();
+        if snapshot.exists() {
+            fs::remove_dir_all(&snapshot).context("Failed to clear old snapshot")?;
+        }
+        fs::create_dir_all(&snapshot).context("Failed to create snapshot directory")?;
+        storage.backup_to(snapshot.join(DATABASE_FILE))?;
+        drop(storage);
+        Self::copy_tree(&self.storage_path, &snapshot, true)?;
+
+        let manifest = self.build_manifest(&key.export_id(), &snapshot)?;
+        let sealed_manifest = encode(&key.seal(&serde_json::to_vec(&manifest)?)?);
+        let offer = ExportOffer {
+            export_id: key.export_id(),
+            words: key.words(),
+            device_id: device_id.to_string(),
+            files: manifest.files.len(),
+            bytes: manifest.bytes(),
+            chunks: manifest.chunks.len(),
+            created_at: self.clock.now_unix(),
+        };
+
+        *self.export.lock().unwrap() = Some(PendingExport {
+            key,
+            device_id: device_id.to_string(),
+            served: vec![false; manifest.chunks.len()],
+            manifest,
+            sealed_manifest,
+        });
+        Ok(offer)
+    }
+
+    /// Next chunks of the pending export (OpenRPC: migration.export_stream)
+    ///
+    /// Chunks can be requested again from any point, so an interrupted
+    /// stream resumes where it left off.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] unless the caller is the paired
+    /// device the export was started for and proves the migration key, and
+    /// an error if no such export is pending
+    pub fn export_stream(
+        &self,
+        context: &RequestContext,
+        request: &ExportStreamRequest,
+    ) -> Result<ExportBatch> {
+        let mut export = self.export.lock().unwrap();
+        let pending = Self::authorize(&mut export, context, &request.export_id, &request.proof)?;
+
+        let total_chunks = pending.manifest.chunks.len();
+        let end = request
+            .from_chunk
+            .saturating_add(request.max_chunks.clamp(1, MAX_BATCH_CHUNKS))
+            .min(total_chunks);
+        let mut chunks = Vec::new();
+        for index in request.from_chunk..end {
+            let data = self.read_chunk(&pending.manifest, index)?;
+            chunks.push(ExportChunk {
+                index,
+                data: encode(&pending.key.seal(&data)?),
+            });
+            pending.served[index] = true;
+        }
+
+        let served: Vec<usize> = (0..total_chunks).filter(|i| pending.served[*i]).collect();
+        self.report(MigrationProgress {
+            export_id: request.export_id.clone(),
+            direction: MigrationDirection::Export,
+            stage: MigrationStage::Streaming,
+            chunks_done: served.len(),
+            chunks_total: total_chunks,
+            bytes_done: served
+                .iter()
+                .map(|i| pending.manifest.chunks[*i].len as u64)
+                .sum(),
+        });
+
+        Ok(ExportBatch {
+            export_id: request.export_id.clone(),
+            total_chunks,
+            manifest: (request.from_chunk == 0).then(|| pending.sealed_manifest.clone()),
+            chunks,
+        })
+    }
+
+    /// Acknowledge an import and retire this server (OpenRPC: migration.confirm)
+    ///
+    /// Marks the data root migrated, so [`Self::ensure_not_migrated`]
+    /// refuses it from now on, and removes the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] under the same conditions as
+    /// [`Self::export_stream`], and an error if the receipt names another
+    /// manifest or some chunk was never streamed
+    pub fn confirm_export(
+        &self,
+        context: &RequestContext,
+        receipt: &MigrationReceipt,
+    ) -> Result<MigrationMarker> {
+        let mut export = self.export.lock().unwrap();
+        let pending = Self::authorize(&mut export, context, &receipt.export_id, &receipt.proof)?;
+
+        if receipt.manifest_hash != pending.manifest.hash()? {
+            bail!(
+                "Migration receipt does not match export {}",
+                receipt.export_id
+            );
+        }
+        if let Some(missing) = pending.served.iter().position(|served| !served) {
+            bail!(
+                "Export {} cannot be confirmed: chunk {} was never streamed",
+                receipt.export_id,
+                missing
+            );
+        }
+
+        let marker = MigrationMarker {
+            export_id: receipt.export_id.clone(),
+            device_id: pending.device_id.clone(),
+            migrated_at: self.clock.now_unix(),
+        };
+        write_atomically(
+            &self.storage_path.join(MIGRATED_FILE),
+            &serde_json::to_vec_pretty(&marker)?,
+        )?;
+        let chunks_total = pending.manifest.chunks.len();
+        let bytes_done = pending.manifest.bytes();
+        *export = None;
+        drop(export);
+
+        // The data has moved; the copy only takes up space now
+        let _ = fs::remove_dir_all(self.snapshot_path());
+        self.report(MigrationProgress {
+            export_id: receipt.export_id.clone(),
+            direction: MigrationDirection::Export,
+            stage: MigrationStage::Completed,
+            chunks_done: chunks_total,
+            chunks_total,
+            bytes_done,
+        });
+        Ok(marker)
+    }
+
+    /// Discard the pending export and its snapshot (OpenRPC: migration.cancel)
+    ///
+    /// Returns whether an export was pending.
+    pub fn cancel_export(&self) -> Result<bool> {
+        let cancelled = self.export.lock().unwrap().take().is_some();
+        let snapshot = self.snapshot_path();
+        if snapshot.exists() {
+            fs::remove_dir_all(&snapshot).context("Failed to remove snapshot")?;
+        }
+        Ok(cancelled)
+    }
+
+    /// Import the state of the old server at `address`, replacing this
+    /// server's data root
+    ///
+    /// Chunks are staged next to the data root and checked against the
+    /// snapshot manifest; the staged tree only replaces the root once every
+    /// file hash and the database integrity check pass. A failed attempt
+    /// keeps what was staged, and the next attempt with the same key
+    /// resumes from there. The old server is told once the new data is in
+    /// place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no transport is configured, the old server
+    /// refuses the key, the key does not open the export, or the staged
+    /// data fails validation. The data root is untouched in every case.
+    pub fn import_from(&self, address: &str, key: &MigrationKey) -> Result<ImportReport> {
+        let transport = self
+            .transport
+            .as_ref()
+            .context("Migration transport not configured")?;
+        let export_id = key.export_id();
+        let staging = self.sibling(STAGING_SUFFIX);
+
+        let mut progress = match Self::load_progress(&staging)? {
+            Some(progress) if progress.manifest.export_id == export_id => Some(progress),
+            _ => {
+                if staging.exists() {
+                    fs::remove_dir_all(&staging).context("Failed to clear staging directory")?;
+                }
+                None
+            }
+        };
+
+        loop {
+            let from_chunk = progress.as_ref().map_or(0, |p| p.next_chunk);
+            if progress
+                .as_ref()
+                .is_some_and(|p| from_chunk >= p.manifest.chunks.len())
+            {
+                break;
+            }
+
+            let batch = transport.export_stream(
+                address,
+                &ExportStreamRequest {
+                    export_id: export_id.clone(),
+                    proof: key.proof(),
+                    from_chunk,
+                    max_chunks: MAX_BATCH_CHUNKS,
+                },
+            )?;
+            let progress = match &mut progress {
+                Some(progress) => progress,
+                None => progress.insert(Self::start_import(&staging, key, &batch)?),
+            };
+
+            ensure!(
+                !batch.chunks.is_empty(),
+                "Export stream stopped at chunk {}",
+                from_chunk
+            );
+            for chunk in &batch.chunks {
+                ensure!(
+                    chunk.index == progress.next_chunk,
+                    "Export stream skipped from chunk {} to {}",
+                    progress.next_chunk,
+                    chunk.index
+                );
+                Self::stage_chunk(&staging, key, &progress.manifest, chunk)?;
+                progress.next_chunk += 1;
+            }
+            Self::save_progress(&staging, progress)?;
+
+            let chunks = &progress.manifest.chunks[..progress.next_chunk];
+            self.report(MigrationProgress {
+                export_id: export_id.clone(),
+                direction: MigrationDirection::Import,
+                stage: MigrationStage::Streaming,
+                chunks_done: progress.next_chunk,
+                chunks_total: progress.manifest.chunks.len(),
+                bytes_done: chunks.iter().map(|c| c.len as u64).sum(),
+            });
+        }
+
+        let manifest = progress
+            .map(|progress| progress.manifest)
+            .context("Export stream sent no manifest")?;
+        let stage = |stage| MigrationProgress {
+            export_id: export_id.clone(),
+            direction: MigrationDirection::Import,
+            stage,
+            chunks_done: manifest.chunks.len(),
+            chunks_total: manifest.chunks.len(),
+            bytes_done: manifest.bytes(),
+        };
+
+        self.report(stage(MigrationStage::Validating));
+        Self::validate_staging(&staging, &manifest)?;
+        fs::remove_file(staging.join(PROGRESS_FILE)).context("Failed to clear import progress")?;
+
+        self.report(stage(MigrationStage::Swapping));
+        let previous_root = self.swap_in(&staging)?;
+        let unmaterialized = SqlStorage::new(self.storage_path.join(DATABASE_FILE))?
+            .mark_unmaterialized()
+            .context("Failed to mark imported apps for download")?;
+
+        let receipt = MigrationReceipt {
+            export_id: export_id.clone(),
+            proof: key.proof(),
+            manifest_hash: manifest.hash()?,
+        };
+        write_atomically(
+            &self.storage_path.join(RECEIPT_FILE),
+            &serde_json::to_vec_pretty(&receipt)?,
+        )?;
+        let confirmed = self.send_receipt(transport.as_ref(), address, &receipt)?;
+        if confirmed {
+            self.report(stage(MigrationStage::Completed));
+        }
+
+        Ok(ImportReport {
+            export_id,
+            files: manifest.files.len(),
+            bytes: manifest.bytes(),
+            previous_root,
+            confirmed,
+            unmaterialized,
+        })
+    }
+
+    /// Tell the old server again that the import succeeded
+    ///
+    /// Returns `false` if no confirmation is owed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no transport is configured or the old server
+    /// still cannot be reached
+    pub fn resend_confirmation(&self, address: &str) -> Result<bool> {
+        let transport = self
+            .transport
+            .as_ref()
+            .context("Migration transport not configured")?;
+        let receipt: MigrationReceipt = match fs::read(self.storage_path.join(RECEIPT_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes).context("Unreadable migration receipt")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e).context("Failed to read migration receipt"),
+        };
+
+        transport.confirm(address, &receipt)?;
+        fs::remove_file(self.storage_path.join(RECEIPT_FILE))
+            .context("Failed to clear migration receipt")?;
+        Ok(true)
+    }
+
+    // Private helper methods
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.storage_path.join(SNAPSHOT_DIR)
+    }
+
+    /// Directory next to the data root, named after it
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self
+            .storage_path
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string();
+        name.push(".");
+        name.push(suffix);
+        self.storage_path.with_file_name(name)
+    }
+
+    fn report(&self, progress: MigrationProgress) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(&progress);
+        }
+    }
+
+    /// The pending export, if `context` and `proof` may use it
+    fn authorize<'a>(
+        export: &'a mut Option<PendingExport>,
+        context: &RequestContext,
+        export_id: &str,
+        proof: &str,
+    ) -> Result<&'a mut PendingExport> {
+        let unauthorized = |reason: &str| -> anyhow::Error {
+            OsnovaError::Unauthorized(reason.to_string()).into()
+        };
+        let Some(pending) = export.as_mut() else {
+            bail!("No migration export is pending");
+        };
+        if context.device_id.as_deref() != Some(pending.device_id.as_str()) {
+            return Err(unauthorized("Export was started for another device"));
+        }
+        if export_id != pending.key.export_id() || proof != pending.key.proof() {
+            return Err(unauthorized("Migration key does not match"));
+        }
+        Ok(pending)
+    }
+
+    /// Copy a directory tree without following symlinks
+    ///
+    /// At the top of the data root the database, snapshot, sockets, and
+    /// migration files are skipped; owner locks and unfinished writes are
+    /// skipped everywhere.
+    fn copy_tree(from: &Path, to: &Path, root: bool) -> Result<()> {
+        for entry in fs::read_dir(from).context("Failed to read storage directory")? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let skipped_at_root =
+                name.starts_with(DATABASE_FILE) || ROOT_BOOKKEEPING.contains(&name.as_str());
+            if (root && skipped_at_root)
+                || ownership::is_owner_lock(&path)
+                || name.ends_with(TEMP_SUFFIX)
+            {
+                continue;
+            }
+
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                fs::create_dir_all(to.join(&name))?;
+                Self::copy_tree(&path, &to.join(&name), false)?;
+            } else if file_type.is_file() {
+                fs::copy(&path, to.join(&name))
+                    .with_context(|| format!("Failed to copy {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// List and chunk every file of a snapshot
+    fn build_manifest(&self, export_id: &str, snapshot: &Path) -> Result<SnapshotManifest> {
+        let mut paths = Vec::new();
+        Self::collect_files(snapshot, snapshot, &mut paths)?;
+        paths.sort();
+
+        let mut manifest = SnapshotManifest {
+            export_id: export_id.to_string(),
+            files: Vec::new(),
+            chunks: Vec::new(),
+        };
+        for path in paths {
+            let data = fs::read(snapshot.join(&path))?;
+            for (i, chunk) in data.chunks(self.chunk_bytes).enumerate() {
+                manifest.chunks.push(SnapshotChunk {
+                    file: manifest.files.len(),
+                    offset: (i * self.chunk_bytes) as u64,
+                    len: chunk.len(),
+                    hash: blake3::hash(chunk).to_hex().to_string(),
+                });
+            }
+            manifest.files.push(SnapshotFile {
+                path,
+                size: data.len() as u64,
+                hash: blake3::hash(&data).to_hex().to_string(),
+            });
+        }
+        Ok(manifest)
+    }
+
+    fn collect_files(root: &Path, dir: &Path, paths: &mut Vec<String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                Self::collect_files(root, &entry.path(), paths)?;
+            } else {
+                let relative = entry.path().strip_prefix(root)?.to_path_buf();
+                let parts: Vec<String> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                paths.push(parts.join("/"));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_chunk(&self, manifest: &SnapshotManifest, index: usize) -> Result<Vec<u8>> {
+        let chunk = manifest.chunks.get(index).context("No such chunk")?;
+        let file = &manifest.files[chunk.file];
+        let mut reader = fs::File::open(self.snapshot_path().join(&file.path))
+            .context("Snapshot file missing")?;
+        reader.seek(SeekFrom::Start(chunk.offset))?;
+        let mut data = vec![0u8; chunk.len];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Open the manifest from the first batch and lay out the staging tree
+    fn start_import(
+        staging: &Path,
+        key: &MigrationKey,
+        batch: &ExportBatch,
+    ) -> Result<ImportProgress> {
+        let sealed = batch
+            .manifest
+            .as_deref()
+            .context("Export stream did not start with its manifest")?;
+        let plaintext = key
+            .open(&decode(sealed)?)
+            .context("Migration key does not open this export")?;
+        let manifest: SnapshotManifest = serde_json::from_slice(&plaintext)?;
+        ensure!(
+            manifest.export_id == key.export_id() && batch.total_chunks == manifest.chunks.len(),
+            "Export manifest does not match export {}",
+            key.export_id()
+        );
+        ensure!(
+            manifest.files.iter().any(|file| file.path == DATABASE_FILE),
+            "Export has no database"
+        );
+
+        fs::create_dir_all(staging).context("Failed to create staging directory")?;
+        for file in &manifest.files {
+            let path = Self::staged_path(staging, &file.path)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::File::create(&path)?.set_len(file.size)?;
+        }
+
+        let progress = ImportProgress {
+            manifest,
+            next_chunk: 0,
+        };
+        Self::save_progress(staging, &progress)?;
+        Ok(progress)
+    }
+
+    /// Where a manifest path is staged, refusing paths that leave the tree
+    fn staged_path(staging: &Path, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        let contained = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        ensure!(
+            contained && !path.is_empty() && path != PROGRESS_FILE,
+            "Export contains an invalid path: {}",
+            path
+        );
+        Ok(staging.join(relative))
+    }
+
+    fn stage_chunk(
+        staging: &Path,
+        key: &MigrationKey,
+        manifest: &SnapshotManifest,
+        chunk: &ExportChunk,
+    ) -> Result<()> {
+        let expected = manifest
+            .chunks
+            .get(chunk.index)
+            .context("Export stream sent a chunk past its end")?;
+        let data = key.open(&decode(&chunk.data)?)?;
+        ensure!(
+            data.len() == expected.len && blake3::hash(&data).to_hex().as_str() == expected.hash,
+            "Chunk {} is corrupted",
+            chunk.index
+        );
+
+        let file = &manifest.files[expected.file];
+        let mut writer = fs::OpenOptions::new()
+            .write(true)
+            .open(Self::staged_path(staging, &file.path)?)?;
+        writer.seek(SeekFrom::Start(expected.offset))?;
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    fn validate_staging(staging: &Path, manifest: &SnapshotManifest) -> Result<()> {
+        for file in &manifest.files {
+            let data = fs::read(Self::staged_path(staging, &file.path)?)?;
+            ensure!(
+                data.len() as u64 == file.size
+                    && blake3::hash(&data).to_hex().as_str() == file.hash,
+                "Imported file {} does not match the export",
+                file.path
+            );
+        }
+        SqlStorage::check_integrity(staging.join(DATABASE_FILE))
+            .context("Imported database is damaged")
+    }
+
+    /// Replace the data root with the staged tree
+    ///
+    /// A root holding anything is moved aside rather than deleted.
+    fn swap_in(&self, staging: &Path) -> Result<Option<PathBuf>> {
+        let occupied = fs::read_dir(&self.storage_path)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        let previous = self.sibling(PREVIOUS_SUFFIX);
+        if occupied {
+            if previous.exists() {
+                fs::remove_dir_all(&previous).context("Failed to clear previous root")?;
+            }
+            fs::rename(&self.storage_path, &previous).context("Failed to move storage root")?;
+        } else if self.storage_path.exists() {
+            fs::remove_dir(&self.storage_path).context("Failed to remove empty storage root")?;
+        }
+
+        if let Err(e) = fs::rename(staging, &self.storage_path) {
+            if occupied {
+                let _ = fs::rename(&previous, &self.storage_path);
+            }
+            return Err(e).context("Failed to move imported data into place");
+        }
+        Ok(occupied.then_some(previous))
+    }
+
+    /// Confirm to the old server, keeping the receipt if it is unreachable
+    fn send_receipt(
+        &self,
+        transport: &dyn MigrationTransport,
+        address: &str,
+        receipt: &MigrationReceipt,
+    ) -> Result<bool> {
+        match transport.confirm(address, receipt) {
+            Ok(_) => {
+                fs::remove_file(self.storage_path.join(RECEIPT_FILE))
+                    .context("Failed to clear migration receipt")?;
+                Ok(true)
+            }
+            Err(e) => {
+                crate::log!(Warn, "Old server did not confirm migration: {:#}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    fn load_progress(staging: &Path) -> Result<Option<ImportProgress>> {
+        match fs::read(staging.join(PROGRESS_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Failed to read import progress"),
+        }
+    }
+
+    fn save_progress(staging: &Path, progress: &ImportProgress) -> Result<()> {
+        write_atomically(&staging.join(PROGRESS_FILE), &serde_json::to_vec(progress)?)
+    }
+}
+
+fn encode(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+fn decode(data: &str) -> Result<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .context("Malformed migration data")
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(TEMP_SUFFIX);
+    fs::write(&temp, data).with_context(|| format!("Failed to write {}", path.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OsnovaContext;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::device_key::DeviceKey;
+    use crate::models::materialization::Materialization;
+    use crate::models::pairing::PairingSession;
+    use crate::services::sessions::SessionService;
+    use crate::storage::FileStorage;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::TempDir;
+
+    const NEW_SERVER: &str = "new-server";
+    const OLD_ADDRESS: &str = "old.local:7777";
+    const FILE_KEY: [u8; 32] = [9u8; 32];
+    const APP_ID: &str = "com.osnova.notes";
+
+    /// An old server's migration service, its sessions, and a session token
+    type OldServer = (Arc<MigrationService>, Arc<SessionService>, String);
+
+    /// In-process connection to old servers, authenticated like RPC calls
+    #[derive(Default)]
+    struct Loopback {
+        servers: Mutex<HashMap<String, OldServer>>,
+        /// Stream calls left before the connection drops
+        fail_after: Mutex<Option<usize>>,
+        /// `from_chunk` of every stream call that got through
+        requests: Mutex<Vec<usize>>,
+        confirm_down: AtomicBool,
+    }
+
+    impl Loopback {
+        fn server(&self, address: &str) -> Result<(Arc<MigrationService>, RequestContext)> {
+            let servers = self.servers.lock().unwrap();
+            let (service, sessions, token) = servers.get(address).context("Unreachable")?;
+            Ok((service.clone(), sessions.authenticate(token)?))
+        }
+    }
+
+    impl MigrationTransport for Loopback {
+        fn export_stream(
+            &self,
+            address: &str,
+            request: &ExportStreamRequest,
+        ) -> Result<ExportBatch> {
+            if let Some(left) = self.fail_after.lock().unwrap().as_mut() {
+                if *left == 0 {
+                    bail!("Connection reset");
+                }
+                *left -= 1;
+            }
+            let (service, context) = self.server(address)?;
+            self.requests.lock().unwrap().push(request.from_chunk);
+            service.export_stream(&context, request)
+        }
+
+        fn confirm(&self, address: &str, receipt: &MigrationReceipt) -> Result<MigrationMarker> {
+            if self.confirm_down.load(Ordering::SeqCst) {
+                bail!("Connection reset");
+            }
+            let (service, context) = self.server(address)?;
+            service.confirm_export(&context, receipt)
+        }
+    }
+
+    struct Servers {
+        old: Arc<MigrationService>,
+        old_context: OsnovaContext,
+        new: MigrationService,
+        new_context: OsnovaContext,
+        network: Arc<Loopback>,
+        progress: Arc<Mutex<Vec<MigrationProgress>>>,
+        _temp: TempDir,
+    }
+
+    /// An old server with an app, some files, and the new server paired as
+    /// a device, and an empty new server
+    fn servers() -> Result<Servers> {
+        let temp = TempDir::new()?;
+        let old_context = OsnovaContext::new(temp.path().join("old"));
+        let new_context = OsnovaContext::new(temp.path().join("new"));
+        fs::create_dir_all(old_context.storage_path())?;
+        fs::create_dir_all(new_context.storage_path())?;
+
+        let storage = SqlStorage::new(old_context.storage_path().join(DATABASE_FILE))?;
+        storage.insert_device_key(&DeviceKey::new(NEW_SERVER, &[2u8; 32])?)?;
+        let mut pairing = PairingSession::new("pairing-1", &[1u8; 32], &[2u8; 32])?;
+        pairing.mark_established();
+        storage.upsert_pairing_session(&pairing)?;
+        storage.set_encrypted_blob("notes/today", b"buy milk", &FILE_KEY)?;
+        let app = OsnovaApplication::new(APP_ID, "Notes", "1.0.0", "icon", "Notes", vec![])?;
+        storage.upsert_application(&app)?;
+        storage.set_materialization(APP_ID, &Materialization::Materialized)?;
+        drop(storage);
+
+        let files = FileStorage::new(old_context.storage_path())?;
+        files.write("launcher/user/layout.json", b"{\"order\":[]}", &FILE_KEY)?;
+        // Large enough to span several chunks
+        let large: Vec<u8> = (0..5000u32).flat_map(|i| i.to_le_bytes()).collect();
+        files.write("cache/blob.bin", &large, &FILE_KEY)?;
+
+        let sessions = Arc::new(SessionService::new(old_context.storage_path())?);
+        let token = sessions.issue("pairing-1", NEW_SERVER)?.token;
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let recorded = progress.clone();
+        let old = Arc::new(
+            MigrationService::new(old_context.storage_path())
+                .with_chunk_size(1024)
+                .with_progress(move |p| recorded.lock().unwrap().push(p.clone())),
+        );
+        let network = Arc::new(Loopback::default());
+        network
+            .servers
+            .lock()
+            .unwrap()
+            .insert(OLD_ADDRESS.to_string(), (old.clone(), sessions, token));
+
+        let recorded = progress.clone();
+        let new = MigrationService::new(new_context.storage_path())
+            .with_transport(network.clone())
+            .with_progress(move |p| recorded.lock().unwrap().push(p.clone()));
+
+        Ok(Servers {
+            old,
+            old_context,
+            new,
+            new_context,
+            network,
+            progress,
+            _temp: temp,
+        })
+    }
+
+    #[test]
+    fn test_full_round_trip() -> Result<()> {
+        let servers = servers()?;
+        let offer = servers
+            .old
+            .begin_export(&RequestContext::local(), NEW_SERVER)?;
+        assert!(offer.chunks > 4);
+        assert_eq!(offer.words.split(' ').count(), 12);
+
+        let key = MigrationKey::from_words(&offer.words)?;
+        let report = servers.new.import_from(OLD_ADDRESS, &key)?;
+        assert!(report.confirmed);
+        assert_eq!(report.export_id, offer.export_id);
+        assert_eq!(report.files, offer.files);
+        assert!(report.previous_root.is_none());
+        assert_eq!(report.unmaterialized, vec![APP_ID.to_string()]);
+
+        // Everything arrived, and the new install owns it
+        let new_root = servers.new_context.storage_path();
+        let storage = SqlStorage::new(new_root.join(DATABASE_FILE))?;
+        assert_eq!(
+            storage.get_encrypted_blob("notes/today", &FILE_KEY)?,
+            Some(b"buy milk".to_vec())
+        );
+
+        // The app came without its components
+        assert_eq!(
+            storage.get_materialization(APP_ID)?,
+            Some(Materialization::NotMaterialized)
+        );
+        let files = FileStorage::new(new_root)?;
+        assert_eq!(
+            files.read("launcher/user/layout.json", &FILE_KEY)?,
+            b"{\"order\":[]}"
+        );
+        assert_eq!(files.read("cache/blob.bin", &FILE_KEY)?.len(), 20_000);
+        assert!(!new_root.join(RECEIPT_FILE).exists());
+        assert!(!servers.new.sibling(STAGING_SUFFIX).exists());
+
+        // The old server is retired and its snapshot gone
+        let old_root = servers.old_context.storage_path();
+        let marker = MigrationService::migrated(old_root)?.unwrap();
+        assert_eq!(marker.device_id, NEW_SERVER);
+        assert!(MigrationService::ensure_not_migrated(old_root).is_err());
+        assert!(MigrationService::ensure_not_migrated(new_root).is_ok());
+        assert!(!old_root.join(SNAPSHOT_DIR).exists());
+
+        // Both ends reported progress through to completion
+        let progress = servers.progress.lock().unwrap();
+        for direction in [MigrationDirection::Export, MigrationDirection::Import] {
+            let last = progress.iter().rfind(|p| p.direction == direction).unwrap();
+            assert_eq!(last.stage, MigrationStage::Completed);
+            assert_eq!(last.chunks_done, offer.chunks);
+            assert_eq!(last.bytes_done, offer.bytes);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_key_is_rejected() -> Result<()> {
+        let servers = servers()?;
+        let offer = servers
+            .old
+            .begin_export(&RequestContext::local(), NEW_SERVER)?;
+
+        let error = servers
+            .new
+            .import_from(OLD_ADDRESS, &MigrationKey::generate())
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::Unauthorized(_))
+        ));
+
+        // Another paired device cannot stream it even with the key
+        let key = MigrationKey::from_words(&offer.words)?;
+        let intruder = RequestContext {
+            device_id: Some("other-device".to_string()),
+        };
+        let request = ExportStreamRequest {
+            export_id: offer.export_id.clone(),
+            proof: key.proof(),
+            from_chunk: 0,
+            max_chunks: 1,
+        };
+        assert!(servers.old.export_stream(&intruder, &request).is_err());
+        // Nor can a remote caller start an export
+        assert!(servers.old.begin_export(&intruder, NEW_SERVER).is_err());
+
+        // A key that passes the server's check but seals differently
+        // (an impostor server) fails to open the manifest
+        let mut batch = servers.old.export_stream(
+            &RequestContext {
+                device_id: Some(NEW_SERVER.to_string()),
+            },
+            &request,
+        )?;
+        batch.manifest = Some(encode(&MigrationKey::generate().seal(b"{}")?));
+        let staging = servers.new.sibling(STAGING_SUFFIX);
+        assert!(MigrationService::start_import(&staging, &key, &batch).is_err());
+
+        // Nothing was staged or replaced, and the old server is untouched
+        assert!(fs::read_dir(servers.new_context.storage_path())?
+            .next()
+            .is_none());
+        assert!(MigrationService::migrated(servers.old_context.storage_path())?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_stream_resumes() -> Result<()> {
+        let servers = servers()?;
+        let offer = servers
+            .old
+            .begin_export(&RequestContext::local(), NEW_SERVER)?;
+        let key = MigrationKey::from_words(&offer.words)?;
+        assert!(offer.chunks > MAX_BATCH_CHUNKS);
+
+        // The connection drops after the first batch
+        *servers.network.fail_after.lock().unwrap() = Some(1);
+        assert!(servers.new.import_from(OLD_ADDRESS, &key).is_err());
+        assert!(fs::read_dir(servers.new_context.storage_path())?
+            .next()
+            .is_none());
+
+        *servers.network.fail_after.lock().unwrap() = None;
+        let report = servers.new.import_from(OLD_ADDRESS, &key)?;
+        assert!(report.confirmed);
+
+        // The second attempt picked up after the staged batch
+        let requests = servers.network.requests.lock().unwrap();
+        assert_eq!(requests[0], 0);
+        assert_eq!(requests[1], MAX_BATCH_CHUNKS);
+        assert_eq!(requests.iter().filter(|from| **from == 0).count(), 1);
+
+        let storage = SqlStorage::new(servers.new_context.storage_path().join(DATABASE_FILE))?;
+        assert!(storage
+            .get_encrypted_blob("notes/today", &FILE_KEY)?
+            .is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_server_retires_only_on_confirmation() -> Result<()> {
+        let servers = servers()?;
+        let offer = servers
+            .old
+            .begin_export(&RequestContext::local(), NEW_SERVER)?;
+        let key = MigrationKey::from_words(&offer.words)?;
+        let device = RequestContext {
+            device_id: Some(NEW_SERVER.to_string()),
+        };
+        let old_root = servers.old_context.storage_path();
+
+        // Confirming before every chunk was streamed is refused
+        let manifest = servers
+            .old
+            .export
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .manifest
+            .clone();
+        let receipt = MigrationReceipt {
+            export_id: offer.export_id.clone(),
+            proof: key.proof(),
+            manifest_hash: manifest.hash()?,
+        };
+        let error = servers.old.confirm_export(&device, &receipt).unwrap_err();
+        assert!(error.to_string().contains("never streamed"));
+        // A forged receipt is refused outright
+        let forged = MigrationReceipt {
+            proof: MigrationKey::generate().proof(),
+            ..receipt.clone()
+        };
+        assert!(servers.old.confirm_export(&device, &forged).is_err());
+        assert!(MigrationService::migrated(old_root)?.is_none());
+
+        // The import lands but the confirmation is lost on the way back
+        servers.network.confirm_down.store(true, Ordering::SeqCst);
+        let report = servers.new.import_from(OLD_ADDRESS, &key)?;
+        assert!(!report.confirmed);
+        assert!(MigrationService::migrated(old_root)?.is_none());
+        assert!(MigrationService::ensure_not_migrated(old_root).is_ok());
+
+        servers.network.confirm_down.store(false, Ordering::SeqCst);
+        assert!(servers.new.resend_confirmation(OLD_ADDRESS)?);
+        assert!(!servers.new.resend_confirmation(OLD_ADDRESS)?);
+        assert!(MigrationService::ensure_not_migrated(old_root).is_err());
+
+        // A retired server refuses to export again
+        assert!(servers
+            .old
+            .begin_export(&RequestContext::local(), NEW_SERVER)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_words_round_trip() -> Result<()> {
+        let key = MigrationKey::generate();
+        let parsed = MigrationKey::from_words(&key.words())?;
+        assert!(parsed == key);
+        assert_eq!(parsed.export_id(), key.export_id());
+        assert!(MigrationKey::from_words("not a migration key").is_err());
+
+        let staging = Path::new("/staging");
+        assert!(MigrationService::staged_path(staging, "a/b.json").is_ok());
+        for path in ["../escape", "/etc/passwd", "a/../../b", "", PROGRESS_FILE] {
+            assert!(
+                MigrationService::staged_path(staging, path).is_err(),
+                "{}",
+                path
+            );
+        }
+
+        Ok(())
+    }
+}
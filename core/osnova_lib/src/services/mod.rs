@@ -5,7 +5,23 @@
 //! - Key derivation and management
 //! - Configuration management
 //! - Application management
-//! - Storage operations
+//! - Storage operations (factory reset)
+//! - Notifications
+//! - Security (at-rest encryption audit)
+//! - Application updates
+//! - Runtime permission prompts
+//! - Data sharing contracts between apps
+//! - Launch handshake with backend components
+//! - Secret settings delivered to backend components at launch
+//! - Device pairing confirmation codes
+//! - App icon badges
+//! - Wallet payment history
+//! - Retention of stores that grow with use
+//! - Activity center of long-running operations
+//! - Private annotations on apps and devices
+//! - Network proxy enforcing what backends declare
+//! - Launch prediction and cache warming
+//! - Feature flags of installed apps
 
 /// Identity management service
 pub mod identity;
@@ -13,15 +29,23 @@ pub mod identity;
 /// Key derivation and management service
 pub mod keys;
 
+pub mod key_usage;
+
 /// Configuration management service
 pub mod config;
 
+/// Read cache of decrypted per-app configurations
+pub mod config_cache;
+
 /// Application management service
 pub mod apps;
 
 /// Launcher layout service
 pub mod launcher;
 
+/// Launcher catalog with offline fallback
+pub mod catalog;
+
 /// UI management service
 pub mod ui;
 
@@ -31,11 +55,136 @@ pub mod navigation;
 /// Status management service
 pub mod status;
 
-pub use apps::AppsService;
-pub use config::ConfigService;
-pub use identity::IdentityService;
+/// Backend process supervision service
+pub mod processes;
+
+/// Health checks of running backends
+pub mod health;
+
+/// In-process service events
+pub mod events;
+
+/// Search across apps, catalog, and settings
+pub mod search;
+
+/// Local storage management (factory reset)
+pub mod storage;
+
+/// Component download provenance
+pub mod provenance;
+
+/// Remote client sessions (Client-Server mode)
+pub mod sessions;
+
+/// Per-user notification center
+pub mod notifications;
+
+/// At-rest encryption audit
+pub mod security;
+
+/// Application updates and rollback
+pub mod updates;
+
+/// Runtime permission prompts
+pub mod permissions;
+
+/// Data sharing contracts between apps
+pub mod sharing;
+
+/// Launch handshake between frontends and backends
+pub mod handshake;
+
+/// One-time delivery of secret settings to backends at launch
+pub mod secret_channel;
+
+/// Installed application metadata refresh
+pub mod metadata;
+
+/// Cross-device handoff between paired devices
+pub mod handoff;
+
+/// Confirmation codes that guard device pairing
+pub mod pairing;
+
+/// Server-to-server migration in Client-Server mode
+pub mod migration;
+
+/// Remote access to the shell's log
+pub mod logs;
+
+/// OS re-authentication for sensitive operations
+pub mod reauth;
+
+/// App icon badges aggregated from notifications, updates and crashes
+pub mod badges;
+
+/// Payment history of uploads for the wallet
+pub mod wallet;
+
+/// Retention policies of growing stores
+pub mod retention;
+
+/// Registry of long-running operations for the activity center
+pub mod tasks;
+
+/// Private annotations on apps and devices
+pub mod annotations;
+
+/// Proxy for backend network access, checked against declarations
+pub mod network_proxy;
+
+/// Launch prediction from local usage
+pub mod prediction;
+
+/// Cache warming for apps about to be launched
+pub mod warming;
+
+/// Feature flags of installed apps (manifest defaults, publisher rollouts, local overrides)
+pub mod features;
+
+pub use annotations::AnnotationService;
+pub use apps::{
+    AppInstallState, AppStatusItem, AppsService, BackendNetwork, BatchInstallOutcome,
+    BatchInstallPreview, BatchInstallProgress, BatchInstallReport, BatchInstallResult,
+    BatchOptions, BatchPreviewItem, BatchPreviewStatus, ConsentRequired, ConsentReview,
+    FrontendEntry, HandlerInfo, InstallRequest, MaterializeOutcome, MaterializePolicy,
+    MaterializeProgress, SharedComponentStatus, SymbolicatedReport, UriSchemeHandlers,
+};
+pub use badges::{AppBadgeService, AttentionReason, BadgeState};
+pub use catalog::CatalogService;
+pub use config::{
+    ConfigService, PresetDocument, PresetImportPolicy, PresetImportResult, SessionOverrides,
+};
+pub use events::{AppEvent, EventBus};
+pub use features::{FeatureFlagService, FlagSource, FlagValue};
+pub use handoff::{Handoff, HandoffPayload, HandoffService, HandoffTransport};
+pub use handshake::{LaunchDescriptor, LaunchHandshake, ReadinessState};
+pub use identity::{IdentityService, IdentityState, OnboardingState, RecoveryReport};
 pub use keys::KeyService;
 pub use launcher::LauncherService;
+pub use logs::LogsService;
+pub use metadata::{MetadataField, MetadataRefresh, MetadataService, RefreshProgress};
+pub use migration::{MigrationKey, MigrationProgress, MigrationService, MigrationTransport};
 pub use navigation::{BottomMenuTab, NavigationService};
-pub use status::{ServerStatus, ServerStatusResponse, StatusService};
+pub use network_proxy::NetworkProxy;
+pub use notifications::{NotificationFilter, NotificationService, PostOutcome};
+pub use pairing::PairingService;
+pub use permissions::PermissionService;
+pub use prediction::{LaunchPredictor, PredictedLaunch};
+pub use processes::{AppCrashed, OrphanReport, ProcessService, RuntimeSettings};
+pub use provenance::{ComponentProvenance, ProvenanceService};
+pub use reauth::{ReauthService, SensitiveOperation};
+pub use retention::{RetainedStore, RetentionRun, RetentionService};
+pub use search::{SearchResult, SearchScope, SearchService};
+pub use security::{AuditContext, AuditReport, EncryptionAudit};
+pub use sessions::{Caller, IssuedSession, RequestContext, SessionService};
+pub use sharing::SharingService;
+pub use status::{NetworkMode, NetworkStatus, ServerStatus, ServerStatusResponse, StatusService};
+pub use storage::{ResetChallenge, ResetReport, StorageRoots, StorageService, VolumeSpace};
+pub use tasks::TaskRegistry;
 pub use ui::{Theme, UIService};
+pub use updates::{
+    AvailableUpdate, UpdateAction, UpdateCheck, UpdateOutcome, UpdatePolicy, UpdateService,
+};
+pub use wallet::{ExportFormat, HistoryFilter, PaymentHistory, PaymentSummary, WalletService};
+pub use warming::{CacheWarmer, WarmOutcome, WarmedApp, WarmingPolicy, WarmingReport, WarmingSkip};
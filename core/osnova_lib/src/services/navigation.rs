@@ -1,14 +1,18 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::storage::FileStorage;
+use crate::context::OsnovaContext;
+use crate::storage::{DataClass, FileStorage, FileStore};
 
 /// Bottom menu tab identifiers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BottomMenuTab {
     /// Launcher tab (app grid)
+    #[default]
     Launcher,
     /// Wallet tab
     Wallet,
@@ -16,12 +20,6 @@ pub enum BottomMenuTab {
     Config,
 }
 
-impl Default for BottomMenuTab {
-    fn default() -> Self {
-        Self::Launcher
-    }
-}
-
 /// Bottom menu configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BottomMenuConfig {
@@ -95,7 +93,7 @@ impl Default for BottomMenuConfig {
 /// # }
 /// ```
 pub struct NavigationService {
-    file_storage: FileStorage,
+    file_storage: Arc<dyn FileStore>,
     nav_path: PathBuf,
     encryption_key: [u8; 32],
 }
@@ -110,17 +108,31 @@ impl NavigationService {
     pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
         let storage_path = storage_path.into();
         let file_storage = FileStorage::new(&storage_path)?;
+        Ok(Self::with_storage(Arc::new(file_storage), user_id))
+    }
+
+    /// Create a navigation service on the file store of a context
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if storage cannot be initialized
+    pub fn from_context(context: &OsnovaContext, user_id: &str) -> Result<Self> {
+        Ok(Self::with_storage(context.file_store()?, user_id))
+    }
+
+    /// Create a navigation service on an opened file store
+    fn with_storage(file_storage: Arc<dyn FileStore>, user_id: &str) -> Self {
         let nav_path = PathBuf::from(format!("navigation/{}/bottom_menu.json", user_id));
 
         // Derive encryption key from user_id
         // TODO: In production, use user's master key
         let encryption_key = Self::derive_nav_key(user_id);
 
-        Ok(Self {
+        Self {
             file_storage,
             nav_path,
             encryption_key,
-        })
+        }
     }
 
     /// Get the current bottom menu tab (OpenRPC: navigation.getBottomMenu)
@@ -179,14 +191,19 @@ impl NavigationService {
             serde_json::to_vec(&config).context("Failed to serialize navigation config")?;
 
         self.file_storage
-            .write(&self.nav_path, &config_json, &self.encryption_key)
+            .write_classified(
+                &self.nav_path,
+                &config_json,
+                &self.encryption_key,
+                DataClass::Preferences,
+            )
             .context("Failed to write navigation config")?;
 
         Ok(())
     }
 
     /// Derive encryption key for navigation config
-    fn derive_nav_key(user_id: &str) -> [u8; 32] {
+    pub(crate) fn derive_nav_key(user_id: &str) -> [u8; 32] {
         use blake3::Hasher;
         let mut hasher = Hasher::new();
         hasher.update(b"osnova-navigation-key-v1:");
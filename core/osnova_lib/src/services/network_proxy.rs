@@ -0,0 +1,872 @@
+//! Core network proxy enforcing backend network declarations
+//!
+//! Backends get their outbound HTTP(S) access through this proxy. At
+//! launch each backend is [granted](NetworkProxy::grant) a proxy URL with
+//! its own credentials, passed in [`PROXY_URL_ENV`] and the standard
+//! `HTTP(S)_PROXY` variables most HTTP clients follow without changes.
+//!
+//! For every request the proxy checks the destination host against the
+//! backend's [`NetworkDeclaration`], the bandwidth policy and the network
+//! kill switch. A refused request gets a `403` (or `503` when the bandwidth
+//! policy or offline mode defers it), is logged, and is recorded in the
+//! audit log. Engaging the kill switch also closes open connections. Allowed traffic is counted
+//! against the bandwidth meter as app traffic. Either way the host is
+//! recorded for the backend, which is what an app's privacy report lists
+//! as observed.
+//!
+//! `HTTPS` goes through `CONNECT` tunnels; plain `HTTP` requests are
+//! forwarded one per connection, so a kept-alive connection cannot reach a
+//! second host unchecked. Backends opening sockets themselves are not
+//! stopped: that needs OS sandboxing. What they declare is therefore also
+//! recorded in each component's provenance.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use bip39::rand::{thread_rng, RngCore};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::components::network::NetworkDeclaration;
+use crate::models::privacy::ObservedHost;
+use crate::network::bandwidth::{DeferReason, TransferDecision};
+use crate::network::kill_switch::{self, NetworkKillSwitch};
+use crate::network::{BandwidthMeter, TransferCategory};
+use crate::storage::SqlStorage;
+use crate::time;
+
+/// Environment variable holding a backend's proxy URL
+pub const PROXY_URL_ENV: &str = "OSNOVA_PROXY_URL";
+
+/// Standard proxy variables also set to a backend's proxy URL
+pub const STANDARD_PROXY_ENVS: [&str; 4] =
+    ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"];
+
+/// User name in proxy URLs; the password identifies the backend
+const PROXY_USER: &str = "osnova";
+
+/// Longest request head the proxy reads
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// Buffer size for relaying traffic
+const RELAY_BUFFER: usize = 16 * 1024;
+
+/// A launched backend allowed to use the proxy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyGrant {
+    /// Process owner of the backend (an app ID, or a shared component's
+    /// registry ID)
+    pub owner_id: String,
+    /// Backend component identifier
+    pub component_id: String,
+    /// What the manifest declares; `None` allows no hosts
+    pub declaration: Option<NetworkDeclaration>,
+}
+
+/// What the proxy does with a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyDecision {
+    /// Connect to the host
+    Allow,
+    /// The backend does not declare the host
+    Undeclared,
+    /// The bandwidth policy or offline mode defers traffic
+    Deferred(DeferReason),
+}
+
+/// A request read from a backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProxyRequest {
+    /// `CONNECT` tunnel or plain HTTP request
+    tunnel: bool,
+    /// Destination host, as requested
+    host: String,
+    /// Destination port
+    port: u16,
+    /// Credentials from `Proxy-Authorization`
+    token: Option<String>,
+    /// Head to send upstream for plain HTTP requests
+    upstream_head: Vec<u8>,
+}
+
+/// HTTP(S) proxy for app backends
+pub struct NetworkProxy {
+    storage: Mutex<SqlStorage>,
+    grants: Mutex<HashMap<String, ProxyGrant>>,
+    address: Option<SocketAddr>,
+    meter: Option<Arc<BandwidthMeter>>,
+    audit: Option<Arc<AuditLog>>,
+    kill_switch: Arc<NetworkKillSwitch>,
+}
+
+impl NetworkProxy {
+    /// Create a proxy recording observed hosts under `storage_path`
+    pub fn new<P: AsRef<Path>>(storage_path: P) -> Result<Self> {
+        let storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            storage: Mutex::new(storage),
+            grants: Mutex::new(HashMap::new()),
+            address: None,
+            meter: None,
+            audit: None,
+            kill_switch: kill_switch::shared(),
+        })
+    }
+
+    /// Set the address the proxy listens on, which granted URLs point to
+    pub fn with_address(mut self, address: SocketAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Check traffic against and count it in a bandwidth meter
+    pub fn with_bandwidth_meter(mut self, meter: Arc<BandwidthMeter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Record refused requests in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Use `kill_switch` instead of the process-wide one
+    pub fn with_kill_switch(mut self, kill_switch: Arc<NetworkKillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Address the proxy listens on, if set
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.address
+    }
+
+    /// Let a backend use the proxy, replacing its earlier grant
+    ///
+    /// Returns the proxy URL to pass to the backend, or `None` if the
+    /// proxy has no address.
+    pub fn grant(
+        &self,
+        owner_id: &str,
+        component_id: &str,
+        declaration: Option<NetworkDeclaration>,
+    ) -> Option<String> {
+        let address = self.address?;
+        self.revoke(owner_id, component_id);
+
+        let mut raw = [0u8; 24];
+        thread_rng().fill_bytes(&mut raw);
+        let token = hex::encode(raw);
+        self.grants.lock().unwrap().insert(
+            token.clone(),
+            ProxyGrant {
+                owner_id: owner_id.to_string(),
+                component_id: component_id.to_string(),
+                declaration,
+            },
+        );
+        Some(format!("http://{}:{}@{}", PROXY_USER, token, address))
+    }
+
+    /// Withdraw a backend's grant
+    pub fn revoke(&self, owner_id: &str, component_id: &str) {
+        self.grants
+            .lock()
+            .unwrap()
+            .retain(|_, grant| grant.owner_id != owner_id || grant.component_id != component_id);
+    }
+
+    /// Decide whether a backend may reach `host`
+    ///
+    /// The declaration is checked first, so undeclared hosts are refused
+    /// whatever the bandwidth policy. Declared hosts are deferred while the
+    /// kill switch is engaged, with or without a bandwidth meter.
+    pub fn decide(&self, grant: &ProxyGrant, host: &str) -> Result<ProxyDecision> {
+        let declared = grant
+            .declaration
+            .as_ref()
+            .is_some_and(|declaration| declaration.allows_host(host));
+        if !declared {
+            return Ok(ProxyDecision::Undeclared);
+        }
+        if self.kill_switch.is_engaged() {
+            return Ok(ProxyDecision::Deferred(DeferReason::OfflineByUser));
+        }
+        if let Some(meter) = &self.meter {
+            if let TransferDecision::Deferred(reason) = meter.check()? {
+                return Ok(ProxyDecision::Deferred(reason));
+            }
+        }
+        Ok(ProxyDecision::Allow)
+    }
+
+    /// Serve backends connecting to `listener` until the task is dropped
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let proxy = self.clone();
+            tokio::spawn(async move {
+                // A failed request only ends that connection
+                let _ = proxy.serve_connection(stream).await;
+            });
+        }
+    }
+
+    async fn serve_connection(&self, mut client: TcpStream) -> Result<()> {
+        let (head, rest) = read_head(&mut client).await?;
+        let request = match parse_request(&head) {
+            Ok(request) => request,
+            Err(e) => return respond(&mut client, "400 Bad Request", &e.to_string()).await,
+        };
+        let grant = request
+            .token
+            .as_ref()
+            .and_then(|token| self.grants.lock().unwrap().get(token).cloned());
+        let Some(grant) = grant else {
+            let response = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                 Proxy-Authenticate: Basic realm=\"osnova\"\r\n\
+                 Content-Length: 0\r\nConnection: close\r\n\r\n";
+            client.write_all(response.as_bytes()).await?;
+            return Ok(());
+        };
+
+        let host = normalize_host(&request.host);
+        match self.decide(&grant, &host)? {
+            ProxyDecision::Allow => {}
+            ProxyDecision::Undeclared => {
+                let message = format!(
+                    "{} does not declare network access to {}",
+                    grant.component_id, host
+                );
+                self.deny(&grant, &host, &message);
+                return respond(&mut client, "403 Forbidden", &message).await;
+            }
+            ProxyDecision::Deferred(DeferReason::OfflineByUser) => {
+                let message = "Osnova is in offline mode".to_string();
+                self.deny(&grant, &host, &message);
+                return respond(&mut client, "503 Service Unavailable", &message).await;
+            }
+            ProxyDecision::Deferred(reason) => {
+                let message = format!("The bandwidth policy defers traffic: {:?}", reason);
+                self.deny(&grant, &host, &message);
+                return respond(&mut client, "503 Service Unavailable", &message).await;
+            }
+        }
+
+        let mut upstream = match TcpStream::connect((host.as_str(), request.port)).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                self.observe(&grant, &host, true, 0, 0);
+                let message = format!("Failed to connect to {}: {}", host, e);
+                return respond(&mut client, "502 Bad Gateway", &message).await;
+            }
+        };
+
+        let mut sent = 0;
+        if request.tunnel {
+            client
+                .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                .await?;
+        } else {
+            upstream.write_all(&request.upstream_head).await?;
+            sent += request.upstream_head.len() as u64;
+        }
+        upstream.write_all(&rest).await?;
+        sent += rest.len() as u64;
+
+        let (client_read, client_write) = client.into_split();
+        let (upstream_read, upstream_write) = upstream.into_split();
+        let (up, down) = (AtomicU64::new(0), AtomicU64::new(0));
+        // Engaging the kill switch drops both relays, closing the connections
+        tokio::select! {
+            _ = async {
+                tokio::join!(
+                    relay(client_read, upstream_write, &up),
+                    relay(upstream_read, client_write, &down)
+                )
+            } => {}
+            _ = self.kill_switch.engaged() => {}
+        }
+        let down = down.into_inner();
+        sent += up.into_inner();
+
+        if let Some(meter) = &self.meter {
+            meter.record(TransferCategory::AppTraffic, sent + down)?;
+        }
+        self.observe(&grant, &host, true, sent, down);
+        Ok(())
+    }
+
+    /// Log, audit and record a refused request
+    fn deny(&self, grant: &ProxyGrant, host: &str, message: &str) {
+        crate::log!(
+            Warn,
+            "Network proxy refused {} of {}: {}",
+            grant.component_id,
+            grant.owner_id,
+            message
+        );
+        if let Some(audit) = &self.audit {
+            let details = serde_json::json!({
+                "ownerId": grant.owner_id,
+                "componentId": grant.component_id,
+                "host": host,
+                "reason": message,
+            });
+            if let Err(e) = audit.append(AuditAction::NetworkAccessDenied, details) {
+                crate::log!(Warn, "Failed to audit refused network access: {}", e);
+            }
+        }
+        self.observe(grant, host, false, 0, 0);
+    }
+
+    /// Add a connection to the backend's totals for `host`
+    fn observe(&self, grant: &ProxyGrant, host: &str, allowed: bool, sent: u64, received: u64) {
+        let now = time::now_unix();
+        let observed = ObservedHost {
+            component_id: grant.component_id.clone(),
+            host: host.to_string(),
+            allowed: u64::from(allowed),
+            denied: u64::from(!allowed),
+            bytes_sent: sent,
+            bytes_received: received,
+            first_seen: now,
+            last_seen: now,
+        };
+        let storage = self.storage.lock().unwrap();
+        if let Err(e) = storage.add_network_observation(&grant.owner_id, &observed) {
+            crate::log!(Warn, "Failed to record network access: {}", e);
+        }
+    }
+}
+
+/// Read up to the end of the request head, returning the head and any
+/// bytes read past it
+async fn read_head(stream: &mut TcpStream) -> Result<(String, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let rest = buffer.split_off(end + 4);
+            let head = String::from_utf8(buffer).context("Request head is not UTF-8")?;
+            return Ok((head, rest));
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            bail!("Request head exceeds {} bytes", MAX_HEAD_BYTES);
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("Connection closed before the request head ended");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+}
+
+/// Parse a `CONNECT` or absolute-form HTTP request head
+fn parse_request(head: &str) -> Result<ProxyRequest> {
+    let mut lines = head.trim_end().split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("Malformed request line");
+    };
+
+    let mut token = None;
+    let mut headers = Vec::new();
+    for line in lines {
+        let (name, value) = line.split_once(':').context("Malformed header")?;
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "proxy-authorization" => token = proxy_token(value),
+            "proxy-connection" | "connection" | "keep-alive" => {}
+            _ => headers.push(line),
+        }
+    }
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        let (host, port) = split_authority(target, None).context("Malformed CONNECT target")?;
+        return Ok(ProxyRequest {
+            tunnel: true,
+            host,
+            port,
+            token,
+            upstream_head: Vec::new(),
+        });
+    }
+
+    let Some(rest) = target.strip_prefix("http://") else {
+        bail!("Only CONNECT and absolute http:// requests are proxied");
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let (host, port) = split_authority(authority, Some(80)).context("Malformed request host")?;
+
+    let mut upstream_head = format!("{} {} {}\r\n", method, path, version);
+    for header in headers {
+        upstream_head.push_str(header);
+        upstream_head.push_str("\r\n");
+    }
+    upstream_head.push_str("Connection: close\r\n\r\n");
+    Ok(ProxyRequest {
+        tunnel: false,
+        host,
+        port,
+        token,
+        upstream_head: upstream_head.into_bytes(),
+    })
+}
+
+/// Token from a `Basic` proxy authorization for [`PROXY_USER`]
+fn proxy_token(value: &str) -> Option<String> {
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    let (user, token) = credentials.split_once(':')?;
+    (user == PROXY_USER).then(|| token.to_string())
+}
+
+/// Split `host:port`, with the port optional when a default is given
+fn split_authority(authority: &str, default_port: Option<u16>) -> Option<(String, u16)> {
+    let (host, port) = match authority.strip_prefix('[') {
+        // [v6]:port
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port?,
+    };
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+/// Lowercase `host` and strip a trailing dot, as observed hosts are kept
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Write a plain-text response and close the connection
+async fn respond(client: &mut TcpStream, status: &str, message: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        message.len(),
+        message
+    );
+    client.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// Copy `from` into `to` until either side closes, counting the bytes
+/// copied in `copied`
+async fn relay<R, W>(mut from: R, mut to: W, copied: &AtomicU64)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; RELAY_BUFFER];
+    loop {
+        let read = match from.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        if to.write_all(&buffer[..read]).await.is_err() {
+            break;
+        }
+        copied.fetch_add(read as u64, Ordering::Relaxed);
+    }
+    let _ = to.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::models::identity::RootIdentity;
+    use crate::network::BandwidthPolicy;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const APP: &str = "com.test.app";
+    const BACKEND: &str = "ant://backend";
+
+    fn declaration(hosts: &[&str]) -> NetworkDeclaration {
+        NetworkDeclaration {
+            hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            ..NetworkDeclaration::default()
+        }
+    }
+
+    fn grant(declaration: Option<NetworkDeclaration>) -> ProxyGrant {
+        ProxyGrant {
+            owner_id: APP.to_string(),
+            component_id: BACKEND.to_string(),
+            declaration,
+        }
+    }
+
+    /// A proxy serving on loopback, and the URL granted to the backend
+    async fn start(
+        temp: &TempDir,
+        configure: impl FnOnce(NetworkProxy) -> NetworkProxy,
+        declaration: NetworkDeclaration,
+    ) -> Result<(Arc<NetworkProxy>, String)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let proxy = NetworkProxy::new(temp.path())?.with_address(listener.local_addr()?);
+        let proxy = Arc::new(configure(proxy));
+        let url = proxy.grant(APP, BACKEND, Some(declaration)).unwrap();
+        tokio::spawn(proxy.clone().serve(listener));
+        Ok((proxy, url))
+    }
+
+    /// Send `head` to the proxy behind `url`, authenticated, and return the
+    /// connection
+    async fn connect(url: &str, head: &str) -> Result<TcpStream> {
+        let (credentials, address) = url
+            .strip_prefix("http://")
+            .and_then(|rest| rest.split_once('@'))
+            .unwrap();
+        let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+        let mut stream = TcpStream::connect(address).await?;
+        let head = head.replace(
+            "\r\n\r\n",
+            &format!("\r\nProxy-Authorization: Basic {}\r\n\r\n", credentials),
+        );
+        stream.write_all(head.as_bytes()).await?;
+        Ok(stream)
+    }
+
+    async fn read_all(stream: &mut TcpStream) -> Result<String> {
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok(String::from_utf8(response)?)
+    }
+
+    /// Observations of the test backend once the proxy recorded `count` hosts
+    async fn observed(proxy: &NetworkProxy, count: usize) -> Result<Vec<ObservedHost>> {
+        for _ in 0..100 {
+            let observed = proxy
+                .storage
+                .lock()
+                .unwrap()
+                .list_network_observations(APP)?;
+            if observed.len() == count {
+                return Ok(observed);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        bail!("the proxy did not record {} hosts", count)
+    }
+
+    #[test]
+    fn test_decide_per_declaration() -> Result<()> {
+        let temp = TempDir::new()?;
+        let proxy = NetworkProxy::new(temp.path())?;
+        let declared = grant(Some(declaration(&["api.example.com", "*.cdn.example.com"])));
+
+        assert_eq!(
+            proxy.decide(&declared, "api.example.com")?,
+            ProxyDecision::Allow
+        );
+        assert_eq!(
+            proxy.decide(&declared, "eu.cdn.example.com")?,
+            ProxyDecision::Allow
+        );
+        assert_eq!(
+            proxy.decide(&declared, "cdn.example.com")?,
+            ProxyDecision::Undeclared
+        );
+        assert_eq!(
+            proxy.decide(&declared, "tracker.example.net")?,
+            ProxyDecision::Undeclared
+        );
+        assert_eq!(
+            proxy.decide(&grant(None), "api.example.com")?,
+            ProxyDecision::Undeclared
+        );
+
+        // Declared hosts still wait for the bandwidth policy
+        let meter = Arc::new(BandwidthMeter::new(
+            SqlStorage::new_in_memory()?,
+            BandwidthPolicy::WifiOnly,
+        ));
+        meter.set_metered_mode(true);
+        let proxy = proxy.with_bandwidth_meter(meter);
+        assert_eq!(
+            proxy.decide(&declared, "api.example.com")?,
+            ProxyDecision::Deferred(DeferReason::MeteredConnection)
+        );
+        assert_eq!(
+            proxy.decide(&declared, "tracker.example.net")?,
+            ProxyDecision::Undeclared
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_grants() -> Result<()> {
+        let temp = TempDir::new()?;
+        assert!(NetworkProxy::new(temp.path())?
+            .grant(APP, BACKEND, None)
+            .is_none());
+
+        let proxy = NetworkProxy::new(temp.path())?.with_address("127.0.0.1:9000".parse()?);
+        let first = proxy.grant(APP, BACKEND, None).unwrap();
+        let second = proxy.grant(APP, BACKEND, None).unwrap();
+        assert!(second.starts_with("http://osnova:"));
+        assert!(second.ends_with("@127.0.0.1:9000"));
+        assert_ne!(first, second);
+        assert_eq!(proxy.grants.lock().unwrap().len(), 1);
+
+        proxy.revoke(APP, BACKEND);
+        assert!(proxy.grants.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_requests() -> Result<()> {
+        let credentials = base64::engine::general_purpose::STANDARD.encode("osnova:secret");
+        let connect = parse_request(&format!(
+            "CONNECT api.example.com:443 HTTP/1.1\r\nProxy-Authorization: Basic {}\r\n\r\n",
+            credentials
+        ))?;
+        assert!(connect.tunnel);
+        assert_eq!(
+            (connect.host.as_str(), connect.port),
+            ("api.example.com", 443)
+        );
+        assert_eq!(connect.token.as_deref(), Some("secret"));
+
+        let get = parse_request(
+            "GET http://[::1]:8080/a?b HTTP/1.1\r\nHost: [::1]:8080\r\n\
+             Proxy-Connection: keep-alive\r\nAccept: */*\r\n\r\n",
+        )?;
+        assert!(!get.tunnel);
+        assert_eq!((get.host.as_str(), get.port), ("::1", 8080));
+        assert_eq!(get.token, None);
+        assert_eq!(
+            String::from_utf8(get.upstream_head)?,
+            "GET /a?b HTTP/1.1\r\nHost: [::1]:8080\r\nAccept: */*\r\nConnection: close\r\n\r\n"
+        );
+        assert_eq!(
+            parse_request("GET http://example.com HTTP/1.1\r\n\r\n")?.port,
+            80
+        );
+
+        assert!(parse_request("GET /relative HTTP/1.1\r\n\r\n").is_err());
+        assert!(parse_request("CONNECT example.com HTTP/1.1\r\n\r\n").is_err());
+        assert!(parse_request("garbage\r\n\r\n").is_err());
+        assert_eq!(proxy_token("Basic b3RoZXI6c2VjcmV0"), None); // other:secret
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_to_declared_host_is_attributed() -> Result<()> {
+        let temp = TempDir::new()?;
+        let upstream = TcpListener::bind("127.0.0.1:0").await?;
+        let port = upstream.local_addr()?.port();
+        tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            let mut ping = [0u8; 4];
+            stream.read_exact(&mut ping).await.unwrap();
+            stream.write_all(b"pong!").await.unwrap();
+        });
+
+        let meter = Arc::new(BandwidthMeter::new(
+            SqlStorage::new_in_memory()?,
+            BandwidthPolicy::Unlimited,
+        ));
+        let (proxy, url) = start(
+            &temp,
+            |proxy| proxy.with_bandwidth_meter(meter.clone()),
+            declaration(&["127.0.0.1"]),
+        )
+        .await?;
+
+        let mut stream = connect(
+            &url,
+            &format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n\r\n", port),
+        )
+        .await?;
+        let mut established = [0u8; 39];
+        stream.read_exact(&mut established).await?;
+        assert_eq!(&established, b"HTTP/1.1 200 Connection Established\r\n\r\n");
+        stream.write_all(b"ping").await?;
+        assert_eq!(read_all(&mut stream).await?, "pong!");
+        drop(stream);
+
+        let observed = observed(&proxy, 1).await?;
+        assert_eq!(observed[0].component_id, BACKEND);
+        assert_eq!(observed[0].host, "127.0.0.1");
+        assert_eq!((observed[0].allowed, observed[0].denied), (1, 0));
+        assert_eq!((observed[0].bytes_sent, observed[0].bytes_received), (4, 5));
+        assert_eq!(
+            meter.usage()?.today.by_category[&TransferCategory::AppTraffic],
+            9
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_is_forwarded_in_origin_form() -> Result<()> {
+        let temp = TempDir::new()?;
+        let upstream = TcpListener::bind("127.0.0.1:0").await?;
+        let port = upstream.local_addr()?.port();
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            let (head, _) = read_head(&mut stream).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            head
+        });
+
+        let (_proxy, url) = start(&temp, |proxy| proxy, declaration(&["localhost"])).await?;
+        let mut stream = connect(
+            &url,
+            &format!(
+                "GET http://LOCALHOST:{}/status HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                port
+            ),
+        )
+        .await?;
+        assert_eq!(
+            read_all(&mut stream).await?,
+            "HTTP/1.1 204 No Content\r\n\r\n"
+        );
+
+        let head = received.await?;
+        assert!(head.starts_with("GET /status HTTP/1.1\r\n"), "{}", head);
+        assert!(head.contains("Connection: close"));
+        assert!(!head.contains("Proxy-Authorization"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_host_is_refused_and_audited() -> Result<()> {
+        let temp = TempDir::new()?;
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &RootIdentity::generate()?,
+            "user",
+        )?);
+        let (proxy, url) = start(
+            &temp,
+            |proxy| proxy.with_audit(audit.clone()),
+            declaration(&["api.example.com"]),
+        )
+        .await?;
+
+        let mut stream = connect(&url, "CONNECT tracker.example.net:443 HTTP/1.1\r\n\r\n").await?;
+        let response = read_all(&mut stream).await?;
+        assert!(
+            response.starts_with("HTTP/1.1 403 Forbidden"),
+            "{}",
+            response
+        );
+        assert!(response.contains("does not declare network access to tracker.example.net"));
+
+        let observed = observed(&proxy, 1).await?;
+        assert_eq!(observed[0].host, "tracker.example.net");
+        assert_eq!((observed[0].allowed, observed[0].denied), (0, 1));
+
+        let filter = AuditFilter {
+            action: Some(AuditAction::NetworkAccessDenied),
+            ..AuditFilter::default()
+        };
+        let entries = audit.list(&filter, PageRequest::default())?.entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].details["host"], "tracker.example.net");
+        assert_eq!(entries[0].details["componentId"], BACKEND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_refuses_and_closes_tunnels() -> Result<()> {
+        let temp = TempDir::new()?;
+        let upstream = TcpListener::bind("127.0.0.1:0").await?;
+        let port = upstream.local_addr()?.port();
+        let accepted = Arc::new(AtomicU64::new(0));
+        {
+            let accepted = accepted.clone();
+            tokio::spawn(async move {
+                let mut held = Vec::new();
+                while let Ok((stream, _)) = upstream.accept().await {
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    held.push(stream);
+                }
+            });
+        }
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let (_, url) = start(
+            &temp,
+            |proxy| proxy.with_kill_switch(switch.clone()),
+            declaration(&["127.0.0.1"]),
+        )
+        .await?;
+        let head = format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n\r\n", port);
+
+        // An open tunnel is closed when the switch engages
+        let mut tunnel = connect(&url, &head).await?;
+        let mut established = [0u8; 39];
+        tunnel.read_exact(&mut established).await?;
+        switch.engage();
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_all(&mut tunnel)).await;
+        assert_eq!(closed??, "");
+
+        // New requests are refused without reaching the host
+        let mut stream = connect(&url, &head).await?;
+        let response = read_all(&mut stream).await?;
+        assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+        assert!(response.contains("offline mode"));
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_grant() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (proxy, url) = start(&temp, |proxy| proxy, declaration(&["127.0.0.1"])).await?;
+        let address = proxy.address().unwrap();
+
+        let mut stream = TcpStream::connect(address).await?;
+        stream
+            .write_all(b"CONNECT 127.0.0.1:443 HTTP/1.1\r\n\r\n")
+            .await?;
+        let response = read_all(&mut stream).await?;
+        assert!(response.starts_with("HTTP/1.1 407"), "{}", response);
+
+        proxy.revoke(APP, BACKEND);
+        let mut stream = connect(&url, "CONNECT 127.0.0.1:443 HTTP/1.1\r\n\r\n").await?;
+        assert!(read_all(&mut stream).await?.starts_with("HTTP/1.1 407"));
+        assert!(proxy
+            .storage
+            .lock()
+            .unwrap()
+            .list_network_observations(APP)?
+            .is_empty());
+        Ok(())
+    }
+}
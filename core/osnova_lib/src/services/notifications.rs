@@ -0,0 +1,676 @@
+//! Per-user notification center
+//!
+//! Apps post notifications (sync finished, payment request pending) that
+//! outlive their windows. The frontend lists and badges them from a single
+//! place.
+//!
+//! Handles:
+//! - Persisting notifications per user in SQL storage
+//! - Per-app enable/disable, stored in the system config
+//! - Per-app rate limiting, so a misbehaving app cannot flood the center
+//! - Keeping the store under its retention policy, pruning the oldest read
+//!   notifications first
+//! - Publishing [`AppEvent::NotificationPosted`] for each delivered
+//!   notification; the shell forwards these to the frontend as
+//!   [`NOTIFICATION_POSTED_EVENT`] and decides whether to raise an OS
+//!   notification
+//! - Publishing [`AppEvent::NotificationsRead`] when notifications are read
+//!   or cleared, so unread badges follow
+//!
+//! Backend crashes, wallet payment requests, and key usage anomalies are
+//! posted through here too.
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::models::key_usage::KeyUsageAnomaly;
+use crate::models::notification::{Notification, NotificationLevel, StoredNotification};
+use crate::models::retention::{Footprint, Policy, RetainedItem, RetentionStore};
+use crate::services::config::ConfigService;
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::processes::AppCrashed;
+use crate::services::retention::{self, RetainedStore};
+use crate::storage::sql::RetainedRows;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Event name used when surfacing a delivered notification to frontends
+pub const NOTIFICATION_POSTED_EVENT: &str = "notification-posted";
+
+/// App id wallet notifications are posted under
+pub const WALLET_APP_ID: &str = "com.osnova.wallet";
+
+/// App id security warnings are posted under
+pub const SECURITY_APP_ID: &str = "com.osnova.security";
+
+/// Notifications kept per user before pruning
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 500;
+
+/// Posts an app may make per rate limit window
+pub const DEFAULT_RATE_LIMIT_POSTS: usize = 10;
+
+/// Length of the rate limit window, in seconds
+pub const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+/// What happened to a posted notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "outcome", content = "notification", rename_all = "snake_case")]
+pub enum PostOutcome {
+    /// Stored and delivered
+    Posted(StoredNotification),
+    /// The user turned off notifications from this app
+    Disabled,
+    /// The app posted too many notifications recently
+    RateLimited,
+    /// A notification with the same tag already exists
+    Duplicate,
+}
+
+impl PostOutcome {
+    /// The stored notification, if it was delivered
+    pub fn posted(&self) -> Option<&StoredNotification> {
+        match self {
+            Self::Posted(notification) => Some(notification),
+            _ => None,
+        }
+    }
+}
+
+/// Which notifications to list
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationFilter {
+    /// Only notifications from this app
+    pub app_id: Option<String>,
+    /// Only unread notifications
+    pub unread_only: bool,
+}
+
+/// Notification service
+///
+/// Provides OpenRPC methods:
+/// - `notifications.post` - Post a notification from an app
+/// - `notifications.list` - List notifications, newest first
+/// - `notifications.markRead` - Mark notifications read
+/// - `notifications.clear` - Remove an app's notifications
+/// - `notifications.setEnabled` - Turn an app's notifications on or off
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::notification::Notification;
+/// use osnova_lib::services::{NotificationFilter, NotificationService};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = NotificationService::new("/tmp/osnova", "user-123")?;
+///
+/// service.post("com.example.sync", Notification::new("Sync finished", "42 files"))?;
+///
+/// let unread = service.list(&NotificationFilter { unread_only: true, ..Default::default() })?;
+/// let ids: Vec<i64> = unread.iter().map(|n| n.id).collect();
+/// service.mark_read(&ids)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct NotificationService {
+    storage: Mutex<SqlStorage>,
+    config: Mutex<ConfigService>,
+    user_id: String,
+    clock: SharedClock,
+    events: Option<EventBus>,
+    retention: Option<Policy>,
+    rate_limit_posts: usize,
+    rate_limit_window_secs: u64,
+    recent_posts: Mutex<HashMap<String, VecDeque<u64>>>,
+}
+
+impl NotificationService {
+    /// Create a new notification service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose notification center this is
+    pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let config = ConfigService::new(&storage_path)?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            config: Mutex::new(config),
+            user_id: user_id.to_string(),
+            clock: time::default_clock(),
+            events: None,
+            retention: None,
+            rate_limit_posts: DEFAULT_RATE_LIMIT_POSTS,
+            rate_limit_window_secs: DEFAULT_RATE_LIMIT_WINDOW_SECS,
+            recent_posts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Publish delivered notifications on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for timestamps and rate limiting
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keep at most `capacity` notifications, within the default age and
+    /// size limits
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.retention = Some(Policy {
+            max_count: Some(capacity as u32),
+            ..RetentionStore::Notifications.default_policy()
+        });
+        self
+    }
+
+    /// Keep notifications under `policy` instead of the configured one
+    pub fn with_retention_policy(mut self, policy: Policy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Allow each app `posts` notifications per `window_secs` seconds
+    pub fn with_rate_limit(mut self, posts: usize, window_secs: u64) -> Self {
+        self.rate_limit_posts = posts;
+        self.rate_limit_window_secs = window_secs;
+        self
+    }
+
+    /// Post a notification from an app (OpenRPC: notifications.post)
+    ///
+    /// Notifications from disabled apps, over the rate limit, or repeating
+    /// an existing tag are dropped; the outcome says which.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the title is empty or storage fails.
+    pub fn post(&self, app_id: &str, notification: Notification) -> Result<PostOutcome> {
+        if notification.title.trim().is_empty() {
+            bail!("Notification title must not be empty");
+        }
+
+        if !self
+            .config
+            .lock()
+            .unwrap()
+            .get_notifications_enabled(app_id)?
+        {
+            return Ok(PostOutcome::Disabled);
+        }
+
+        let storage = self.storage.lock().unwrap();
+        if let Some(tag) = &notification.tag {
+            if storage.has_notification_tag(&self.user_id, app_id, tag)? {
+                return Ok(PostOutcome::Duplicate);
+            }
+        }
+
+        let now = self.clock.now_unix();
+        if !self.take_rate_limit_slot(app_id, now) {
+            return Ok(PostOutcome::RateLimited);
+        }
+
+        let id = storage.insert_notification(&self.user_id, app_id, now, &notification)?;
+        drop(storage);
+
+        let policy = match self.retention {
+            Some(policy) => policy,
+            None => self
+                .config
+                .lock()
+                .unwrap()
+                .get_retention_policy(RetentionStore::Notifications)?,
+        };
+        retention::apply_on_write(self, &policy, now)?;
+
+        let stored = StoredNotification {
+            id,
+            app_id: app_id.to_string(),
+            posted_at: now,
+            read: false,
+            notification,
+        };
+
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::NotificationPosted {
+                user_id: self.user_id.clone(),
+                notification: stored.clone(),
+            });
+        }
+
+        Ok(PostOutcome::Posted(stored))
+    }
+
+    /// List notifications, newest first (OpenRPC: notifications.list)
+    pub fn list(&self, filter: &NotificationFilter) -> Result<Vec<StoredNotification>> {
+        let notifications = self
+            .storage
+            .lock()
+            .unwrap()
+            .list_notifications(&self.user_id)?;
+
+        Ok(notifications
+            .into_iter()
+            .filter(|n| {
+                filter
+                    .app_id
+                    .as_ref()
+                    .is_none_or(|app_id| n.app_id == *app_id)
+            })
+            .filter(|n| !filter.unread_only || !n.read)
+            .collect())
+    }
+
+    /// Unread notification count per app, for badges
+    pub fn unread_counts(&self) -> Result<BTreeMap<String, usize>> {
+        let mut counts = BTreeMap::new();
+        for notification in self.list(&NotificationFilter {
+            app_id: None,
+            unread_only: true,
+        })? {
+            *counts.entry(notification.app_id).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Mark notifications read (OpenRPC: notifications.markRead)
+    ///
+    /// Returns how many were unread before.
+    pub fn mark_read(&self, ids: &[i64]) -> Result<usize> {
+        let marked = self
+            .storage
+            .lock()
+            .unwrap()
+            .mark_notifications_read(&self.user_id, ids)?;
+        self.publish_read(marked);
+        Ok(marked)
+    }
+
+    /// Remove every notification from an app (OpenRPC: notifications.clear)
+    pub fn clear(&self, app_id: &str) -> Result<usize> {
+        let removed = self
+            .storage
+            .lock()
+            .unwrap()
+            .delete_notifications_for_app(&self.user_id, app_id)?;
+        self.publish_read(removed);
+        Ok(removed)
+    }
+
+    /// Whether notifications from an app are delivered
+    pub fn is_enabled(&self, app_id: &str) -> Result<bool> {
+        self.config
+            .lock()
+            .unwrap()
+            .get_notifications_enabled(app_id)
+    }
+
+    /// Turn an app's notifications on or off (OpenRPC: notifications.setEnabled)
+    pub fn set_enabled(&self, app_id: &str, enabled: bool) -> Result<()> {
+        self.config
+            .lock()
+            .unwrap()
+            .set_notifications_enabled(app_id, enabled)
+    }
+
+    /// Tell the user an app's backend stopped unexpectedly
+    ///
+    /// Posts at most one notification per crash report.
+    pub fn notify_crash(&self, crash: &AppCrashed) -> Result<PostOutcome> {
+        let body = match (crash.exit_code, crash.signal) {
+            (Some(code), _) => format!("The app's backend exited with code {}.", code),
+            (None, Some(signal)) => format!("The app's backend was killed by signal {}.", signal),
+            (None, None) => "The app's backend stopped unexpectedly.".to_string(),
+        };
+
+        self.post(
+            &crash.app_id,
+            Notification::new("App stopped", format!("{} A crash report was saved.", body))
+                .with_level(NotificationLevel::Error)
+                .with_tag(format!("crash:{}", crash.report_id)),
+        )
+    }
+
+    /// Tell the user a payment request is waiting in the wallet
+    ///
+    /// Posts at most one notification per `request_id`, however often the
+    /// request is seen.
+    pub fn notify_payment_request(&self, request_id: &str, summary: &str) -> Result<PostOutcome> {
+        self.post(
+            WALLET_APP_ID,
+            Notification::new("Payment request", summary)
+                .with_level(NotificationLevel::Warning)
+                .with_action_route(format!("/payments/{}", request_id))
+                .with_tag(format!("payment-request:{}", request_id)),
+        )
+    }
+
+    /// Warn the user a component is using its keys far more than usual
+    ///
+    /// Posts at most one notification per anomaly episode, under
+    /// [`SECURITY_APP_ID`] so the rate limit is shared by every component.
+    pub fn notify_key_usage_anomaly(&self, anomaly: &KeyUsageAnomaly) -> Result<PostOutcome> {
+        self.post(
+            SECURITY_APP_ID,
+            Notification::new(
+                "Unusual key use",
+                format!(
+                    "{} used its keys {} times this hour, against an average of {:.1} per hour.",
+                    anomaly.component_id,
+                    anomaly.hourly_count,
+                    anomaly.trailing_average()
+                ),
+            )
+            .with_level(NotificationLevel::Warning)
+            .with_tag(format!(
+                "key-usage:{}:{}",
+                anomaly.component_id, anomaly.hour
+            )),
+        )
+    }
+
+    // Private helper methods
+
+    /// This user's notification rows
+    fn rows(&self) -> RetainedRows<'_> {
+        RetainedRows::Notifications {
+            user_id: &self.user_id,
+        }
+    }
+
+    /// Publish [`AppEvent::NotificationsRead`] if any notification changed
+    fn publish_read(&self, changed: usize) {
+        if changed == 0 {
+            return;
+        }
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::NotificationsRead {
+                user_id: self.user_id.clone(),
+            });
+        }
+    }
+
+    /// Record a post at `now` if the app is under its rate limit
+    fn take_rate_limit_slot(&self, app_id: &str, now: u64) -> bool {
+        let mut recent_posts = self.recent_posts.lock().unwrap();
+        let recent = recent_posts.entry(app_id.to_string()).or_default();
+
+        while recent
+            .front()
+            .is_some_and(|posted| now.saturating_sub(*posted) >= self.rate_limit_window_secs)
+        {
+            recent.pop_front();
+        }
+
+        if recent.len() >= self.rate_limit_posts {
+            return false;
+        }
+        recent.push_back(now);
+        true
+    }
+}
+
+impl RetainedStore for NotificationService {
+    fn store(&self) -> RetentionStore {
+        RetentionStore::Notifications
+    }
+
+    fn footprint(&self) -> Result<Footprint> {
+        self.storage.lock().unwrap().retained_footprint(self.rows())
+    }
+
+    fn items(&self) -> Result<Vec<RetainedItem>> {
+        self.storage.lock().unwrap().list_retained(self.rows())
+    }
+
+    fn remove(&self, items: &[RetainedItem], _now: u64) -> Result<()> {
+        let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+        self.storage
+            .lock()
+            .unwrap()
+            .delete_retained(self.rows(), &ids)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> Result<(NotificationService, Arc<MockClock>, TempDir)> {
+        let temp = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let service = NotificationService::new(temp.path(), "user-123")?.with_clock(clock.clone());
+        Ok((service, clock, temp))
+    }
+
+    #[test]
+    fn test_post_list_read_lifecycle() -> Result<()> {
+        let (service, _clock, _temp) = create_test_service()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service.with_events(events);
+
+        let outcome = service.post("com.example.sync", Notification::new("Synced", "42 files"))?;
+        let posted = outcome.posted().expect("delivered").clone();
+        assert_eq!(posted.posted_at, 1_000);
+        assert!(!posted.read);
+        assert_eq!(
+            receiver.try_recv()?,
+            AppEvent::NotificationPosted {
+                user_id: "user-123".to_string(),
+                notification: posted.clone(),
+            }
+        );
+
+        service.post("com.example.chat", Notification::new("New message", ""))?;
+        assert_eq!(service.list(&NotificationFilter::default())?.len(), 2);
+        assert_eq!(
+            service.unread_counts()?,
+            BTreeMap::from([
+                ("com.example.chat".to_string(), 1),
+                ("com.example.sync".to_string(), 1),
+            ])
+        );
+
+        assert_eq!(service.mark_read(&[posted.id])?, 1);
+        let unread = service.list(&NotificationFilter {
+            unread_only: true,
+            ..Default::default()
+        })?;
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].app_id, "com.example.chat");
+
+        assert_eq!(service.clear("com.example.chat")?, 1);
+        let remaining = service.list(&NotificationFilter::default())?;
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].read);
+
+        assert!(service
+            .post("com.example.chat", Notification::new(" ", ""))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disabled_app_is_not_delivered() -> Result<()> {
+        let (service, _clock, _temp) = create_test_service()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let service = service.with_events(events);
+
+        service.set_enabled("com.example.noisy", false)?;
+        assert!(!service.is_enabled("com.example.noisy")?);
+        assert_eq!(
+            service.post("com.example.noisy", Notification::new("Hi", ""))?,
+            PostOutcome::Disabled
+        );
+        assert!(service.list(&NotificationFilter::default())?.is_empty());
+        assert!(receiver.try_recv().is_err());
+
+        service.set_enabled("com.example.noisy", true)?;
+        assert!(service
+            .post("com.example.noisy", Notification::new("Hi", ""))?
+            .posted()
+            .is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit_per_app() -> Result<()> {
+        let (service, clock, _temp) = create_test_service()?;
+        let service = service.with_rate_limit(3, 60);
+
+        for _ in 0..3 {
+            assert!(service
+                .post("com.example.spam", Notification::new("Hi", ""))?
+                .posted()
+                .is_some());
+        }
+        assert_eq!(
+            service.post("com.example.spam", Notification::new("Hi", ""))?,
+            PostOutcome::RateLimited
+        );
+
+        // Other apps are unaffected
+        assert!(service
+            .post("com.example.quiet", Notification::new("Hi", ""))?
+            .posted()
+            .is_some());
+
+        // The window slides
+        clock.advance(Duration::from_secs(60));
+        assert!(service
+            .post("com.example.spam", Notification::new("Hi", ""))?
+            .posted()
+            .is_some());
+        assert_eq!(service.list(&NotificationFilter::default())?.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capacity_prunes_oldest_read() -> Result<()> {
+        let (service, _clock, _temp) = create_test_service()?;
+        let service = service.with_capacity(3);
+
+        let mut ids = Vec::new();
+        for title in ["one", "two", "three"] {
+            let outcome = service.post("com.example.app", Notification::new(title, ""))?;
+            ids.push(outcome.posted().unwrap().id);
+        }
+        service.mark_read(&[ids[1]])?;
+
+        service.post("com.example.app", Notification::new("four", ""))?;
+        let titles: Vec<String> = service
+            .list(&NotificationFilter::default())?
+            .into_iter()
+            .map(|n| n.notification.title)
+            .collect();
+        assert_eq!(titles, vec!["four", "three", "one"]);
+
+        // With nothing read, the oldest unread goes
+        service.post("com.example.app", Notification::new("five", ""))?;
+        let titles: Vec<String> = service
+            .list(&NotificationFilter::default())?
+            .into_iter()
+            .map(|n| n.notification.title)
+            .collect();
+        assert_eq!(titles, vec!["five", "four", "three"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payment_request_posts_once() -> Result<()> {
+        let (service, _clock, _temp) = create_test_service()?;
+
+        let first = service.notify_payment_request("req-1", "5 ANT requested by alice")?;
+        let posted = first.posted().expect("delivered");
+        assert_eq!(posted.app_id, WALLET_APP_ID);
+        assert_eq!(
+            posted.notification.action_route.as_deref(),
+            Some("/payments/req-1")
+        );
+
+        assert_eq!(
+            service.notify_payment_request("req-1", "5 ANT requested by alice")?,
+            PostOutcome::Duplicate
+        );
+        assert!(service
+            .notify_payment_request("req-2", "1 ANT requested by bob")?
+            .posted()
+            .is_some());
+
+        let wallet = service.list(&NotificationFilter {
+            app_id: Some(WALLET_APP_ID.to_string()),
+            unread_only: false,
+        })?;
+        assert_eq!(wallet.len(), 2);
+
+        let crashed = AppCrashed {
+            app_id: "com.example.sync".to_string(),
+            pid: 42,
+            exit_code: None,
+            signal: Some(11),
+            report_id: "report-1".to_string(),
+        };
+        let crash = service.notify_crash(&crashed)?;
+        let posted = crash.posted().unwrap();
+        assert_eq!(posted.notification.level, NotificationLevel::Error);
+        assert_eq!(
+            posted.notification.body,
+            "The app's backend was killed by signal 11. A crash report was saved."
+        );
+        // One notification per crash report
+        assert!(service.notify_crash(&crashed)?.posted().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_usage_anomaly_posts_once_per_episode() -> Result<()> {
+        let (service, _clock, _temp) = create_test_service()?;
+        let anomaly = KeyUsageAnomaly {
+            component_id: "com.example.sync".to_string(),
+            hour: 3600,
+            hourly_count: 480,
+            trailing_count: 48,
+            trailing_hours: 24,
+            detected_at: 3700,
+        };
+
+        let outcome = service.notify_key_usage_anomaly(&anomaly)?;
+        let posted = outcome.posted().unwrap();
+        assert_eq!(posted.app_id, SECURITY_APP_ID);
+        assert_eq!(posted.notification.level, NotificationLevel::Warning);
+        assert_eq!(
+            posted.notification.body,
+            "com.example.sync used its keys 480 times this hour, against an average of 2.0 per hour."
+        );
+        assert_eq!(
+            service.notify_key_usage_anomaly(&anomaly)?,
+            PostOutcome::Duplicate
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,920 @@
+//! Pairing confirmation codes
+//!
+//! A pairing starts from a QR code, and a QR code can be swapped: an
+//! attacker showing their own code on a shared screen would have the phone
+//! pair with them, relaying to the real server. So after the two devices
+//! exchange ephemeral X25519 keys, each derives a 6-digit code from the
+//! key agreement and the session transcript and shows it, like Bluetooth
+//! numeric comparison. An attacker in the middle holds a different shared
+//! secret with each side, so the two codes differ.
+//!
+//! The server commits to its ephemeral key with a hash over the key and a
+//! random nonce before it sees the device's key, and opens the commitment
+//! only after. An attacker therefore has to fix their key towards the
+//! device before learning anything that would let them search for keys
+//! giving both sides the same code; one guess matches one time in a
+//! million.
+//!
+//! Handles:
+//! - The committed ephemeral key exchange and code derivation on either
+//!   side
+//! - Recording the user's answer on this device and the peer's answer
+//!   relayed from the other device; the session is established once both
+//!   users confirmed the codes match
+//! - Failing the session on a mismatch, or when no answer came within the
+//!   confirmation timeout, and auditing both outcomes
+//! - Keeping the awaiting-confirmation step in the database, so a restart
+//!   mid-pairing shows the same code again
+//!
+//! Ephemeral secrets live in memory only. A restart before the key
+//! exchange finished loses them, and that pairing must start over.
+
+use anyhow::{bail, Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::context::{GuestGate, GuestRestriction};
+use crate::models::pairing::{PairingSession, PairingStatus};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// How long the users have to compare the codes, in seconds
+pub const DEFAULT_CONFIRMATION_TIMEOUT_SECS: u64 = 120;
+
+/// Digits in a confirmation code
+pub const CONFIRMATION_CODE_DIGITS: u32 = 6;
+
+const CONFIRMATION_CONTEXT: &str = "osnova pairing 2025 confirmation code";
+
+const COMMITMENT_CONTEXT: &str = "osnova pairing 2025 ephemeral key commitment";
+
+/// The server's ephemeral key with the nonce it was committed with
+///
+/// Sent to the device once the server received the device's key; the
+/// device checks it against the commitment in
+/// [`PairingService::exchange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyOpening {
+    /// The server's ephemeral X25519 public key
+    pub ephemeral_key: [u8; 32],
+    /// Random nonce the commitment was made with
+    pub nonce: [u8; 32],
+}
+
+/// Key exchange state of a session not awaiting confirmation yet
+enum Exchange {
+    /// Server side: committed, waiting for the device's key
+    Committed {
+        secret: EphemeralSecret,
+        public: PublicKey,
+        nonce: [u8; 32],
+    },
+    /// Device side: key sent, waiting for the server to open its commitment
+    Responded {
+        secret: EphemeralSecret,
+        public: PublicKey,
+        commitment: [u8; 32],
+    },
+}
+
+/// Why a pairing failed its confirmation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureReason {
+    /// The user of this device said the codes differ
+    Mismatch,
+    /// The user of the other device said the codes differ
+    PeerMismatch,
+    /// Nobody answered before the deadline
+    Timeout,
+    /// The server's key did not open its commitment
+    Commitment,
+}
+
+impl FailureReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            FailureReason::Mismatch => "mismatch",
+            FailureReason::PeerMismatch => "peer_mismatch",
+            FailureReason::Timeout => "timeout",
+            FailureReason::Commitment => "commitment",
+        }
+    }
+}
+
+/// Pairing confirmation service
+///
+/// Runs on both the server and the device; which side this is follows from
+/// which of a session's public keys is this device's own.
+///
+/// Provides OpenRPC methods:
+/// - `pairing.confirmationCode` - Code to compare with the other device
+/// - `pairing.confirmMatch` - Record whether the user saw matching codes
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::pairing::PairingService;
+///
+/// # fn example(server_key: [u8; 32], device_key: [u8; 32]) -> anyhow::Result<()> {
+/// # let server = PairingService::new("/tmp/osnova-server", &server_key)?;
+/// let service = PairingService::new("/tmp/osnova", &device_key)?;
+///
+/// // The server commits to its ephemeral key; we answer with ours, after
+/// // which it opens the commitment
+/// # let commitment = server.start("session-123", &server_key, &device_key)?;
+/// let ours = service.respond("session-123", &server_key, &device_key, &commitment)?;
+/// # let opening = server.reveal("session-123", &ours)?;
+/// let code = service.exchange("session-123", &opening)?;
+/// println!("Check the server shows {}", code);
+///
+/// service.confirm_match("session-123", true)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PairingService {
+    storage: Mutex<SqlStorage>,
+    public_key: [u8; 32],
+    ephemeral: Mutex<HashMap<String, Exchange>>,
+    audit: Option<Arc<AuditLog>>,
+    clock: SharedClock,
+    confirmation_timeout_secs: u64,
+    guest: GuestGate,
+}
+
+impl PairingService {
+    /// Create a new pairing service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database
+    /// * `public_key` - Public key this device presents in its pairings
+    pub fn new<P: AsRef<Path>>(storage_path: P, public_key: &[u8; 32]) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            public_key: *public_key,
+            ephemeral: Mutex::new(HashMap::new()),
+            audit: None,
+            clock: time::default_clock(),
+            confirmation_timeout_secs: DEFAULT_CONFIRMATION_TIMEOUT_SECS,
+            guest: GuestGate::default(),
+        })
+    }
+
+    /// Record confirmed and failed pairings in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Use a specific clock for confirmation deadlines
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set how long the users have to compare the codes, in seconds
+    pub fn with_confirmation_timeout(mut self, timeout_secs: u64) -> Self {
+        self.confirmation_timeout_secs = timeout_secs;
+        self
+    }
+
+    /// Refuse to start pairings while a guest session runs
+    pub fn with_guest_gate(mut self, guest: GuestGate) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Start a pairing session on the server and return the commitment to
+    /// its ephemeral key
+    ///
+    /// The commitment is sent to the device, which passes it to
+    /// [`respond`](Self::respond).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys are not 32 bytes, this device is not
+    /// the session's server, or the session was already started and moved
+    /// past the key exchange, and
+    /// [`GuestModeRestricted`](crate::context::GuestModeRestricted) while a
+    /// guest session runs
+    pub fn start(
+        &self,
+        session_id: &str,
+        server_public_key: &[u8],
+        device_public_key: &[u8],
+    ) -> Result<[u8; 32]> {
+        let session = self.begin(session_id, server_public_key, device_public_key)?;
+        if !self.is_server(&session)? {
+            bail!("Only the server starts pairing {}", session_id);
+        }
+        self.storage
+            .lock()
+            .unwrap()
+            .upsert_pairing_session(&session)?;
+
+        let secret = EphemeralSecret::random_from_rng(thread_rng());
+        let public = PublicKey::from(&secret);
+        let mut nonce = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce);
+        let commitment = commitment(&session, public.as_bytes(), &nonce);
+        self.ephemeral.lock().unwrap().insert(
+            session_id.to_string(),
+            Exchange::Committed {
+                secret,
+                public,
+                nonce,
+            },
+        );
+        Ok(commitment)
+    }
+
+    /// Join a pairing session on the device with the server's commitment
+    /// and return the device's ephemeral public key
+    ///
+    /// The key is sent to the server, which passes it to
+    /// [`reveal`](Self::reveal).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys or the commitment are not 32 bytes,
+    /// this device is not the session's device, or the session was already
+    /// started and moved past the key exchange, and
+    /// [`GuestModeRestricted`](crate::context::GuestModeRestricted) while a
+    /// guest session runs
+    pub fn respond(
+        &self,
+        session_id: &str,
+        server_public_key: &[u8],
+        device_public_key: &[u8],
+        commitment: &[u8],
+    ) -> Result<[u8; 32]> {
+        let commitment: [u8; 32] = commitment
+            .try_into()
+            .context("Commitment must be 32 bytes")?;
+        let session = self.begin(session_id, server_public_key, device_public_key)?;
+        if self.is_server(&session)? {
+            bail!("Only the device responds to pairing {}", session_id);
+        }
+        self.storage
+            .lock()
+            .unwrap()
+            .upsert_pairing_session(&session)?;
+
+        let secret = EphemeralSecret::random_from_rng(thread_rng());
+        let public = PublicKey::from(&secret);
+        self.ephemeral.lock().unwrap().insert(
+            session_id.to_string(),
+            Exchange::Responded {
+                secret,
+                public,
+                commitment,
+            },
+        );
+        Ok(public.to_bytes())
+    }
+
+    /// Complete the key exchange on the server with the device's ephemeral
+    /// public key and open the commitment
+    ///
+    /// The opening is sent to the device, which passes it to
+    /// [`exchange`](Self::exchange). The session then awaits confirmation
+    /// until the timeout; its code is read with
+    /// [`confirmation_code`](Self::confirmation_code).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not pending, it was not started
+    /// on this server since this service was created, or the device key is
+    /// not a valid, contributory X25519 key
+    pub fn reveal(&self, session_id: &str, device_ephemeral_key: &[u8]) -> Result<KeyOpening> {
+        let device: [u8; 32] = device_ephemeral_key
+            .try_into()
+            .context("Ephemeral key must be 32 bytes")?;
+        let storage = self.storage.lock().unwrap();
+        let mut session = Self::pending(&storage, session_id)?;
+        let Some(Exchange::Committed {
+            secret,
+            public,
+            nonce,
+        }) = self.take_exchange(session_id, true)
+        else {
+            bail!("Pairing {} must be started again", session_id);
+        };
+
+        let device = PublicKey::from(device);
+        let shared = secret.diffie_hellman(&device);
+        if !shared.was_contributory() {
+            bail!("Ephemeral key of the other device has low order");
+        }
+        let code = confirmation_code(
+            shared.as_bytes(),
+            &session,
+            public.as_bytes(),
+            device.as_bytes(),
+            &nonce,
+        );
+        self.await_confirmation(&storage, &mut session, code)?;
+        Ok(KeyOpening {
+            ephemeral_key: public.to_bytes(),
+            nonce,
+        })
+    }
+
+    /// Complete the key exchange on the device with the server's opened
+    /// commitment and return the confirmation code to show the user
+    ///
+    /// The session then awaits confirmation until the timeout. An opening
+    /// that does not match the commitment fails the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not pending, this device did not
+    /// respond to it since this service was created, the opening does not
+    /// match the commitment, or the server key is not a valid, contributory
+    /// X25519 key
+    pub fn exchange(&self, session_id: &str, opening: &KeyOpening) -> Result<String> {
+        let storage = self.storage.lock().unwrap();
+        let mut session = Self::pending(&storage, session_id)?;
+        let Some(Exchange::Responded {
+            secret,
+            public,
+            commitment: committed,
+        }) = self.take_exchange(session_id, false)
+        else {
+            bail!("Pairing {} must be started again", session_id);
+        };
+
+        if commitment(&session, &opening.ephemeral_key, &opening.nonce) != committed {
+            self.fail(&storage, &mut session, FailureReason::Commitment)?;
+            bail!(
+                "Ephemeral key of the server does not match its commitment in pairing {}",
+                session_id
+            );
+        }
+        let server = PublicKey::from(opening.ephemeral_key);
+        let shared = secret.diffie_hellman(&server);
+        if !shared.was_contributory() {
+            bail!("Ephemeral key of the other device has low order");
+        }
+        let code = confirmation_code(
+            shared.as_bytes(),
+            &session,
+            server.as_bytes(),
+            public.as_bytes(),
+            &opening.nonce,
+        );
+        self.await_confirmation(&storage, &mut session, code.clone())?;
+        Ok(code)
+    }
+
+    /// Code to compare with the one on the other device (OpenRPC: pairing.confirmationCode)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not awaiting confirmation,
+    /// including when its deadline passed; the session then fails
+    pub fn confirmation_code(&self, session_id: &str) -> Result<String> {
+        let storage = self.storage.lock().unwrap();
+        let session = self.awaiting(&storage, session_id)?;
+        Ok(session
+            .confirmation()
+            .map(|confirmation| confirmation.code.clone())
+            .unwrap_or_default())
+    }
+
+    /// Record whether the user saw matching codes on both devices (OpenRPC: pairing.confirmMatch)
+    ///
+    /// A mismatch fails the session. A match is recorded, and the session
+    /// is established once the other device's user confirmed too (see
+    /// [`record_peer_confirmation`](Self::record_peer_confirmation)).
+    /// Returns the session's status afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not awaiting confirmation,
+    /// including when its deadline passed; the session then fails
+    pub fn confirm_match(&self, session_id: &str, user_confirmed: bool) -> Result<PairingStatus> {
+        self.record(session_id, true, user_confirmed)
+    }
+
+    /// Record the answer of the other device's user, relayed over the
+    /// pairing connection
+    ///
+    /// Behaves like [`confirm_match`](Self::confirm_match) for the other side.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not awaiting confirmation,
+    /// including when its deadline passed; the session then fails
+    pub fn record_peer_confirmation(
+        &self,
+        session_id: &str,
+        peer_confirmed: bool,
+    ) -> Result<PairingStatus> {
+        self.record(session_id, false, peer_confirmed)
+    }
+
+    /// Get a pairing session
+    pub fn session(&self, session_id: &str) -> Result<Option<PairingSession>> {
+        self.storage.lock().unwrap().get_pairing_session(session_id)
+    }
+
+    /// Fail the sessions whose confirmation deadline passed
+    ///
+    /// Runs at startup, so pairings abandoned before a restart do not wait
+    /// forever. Sessions still within their deadline keep awaiting
+    /// confirmation. Returns the failed session IDs.
+    pub fn expire_overdue(&self) -> Result<Vec<String>> {
+        let storage = self.storage.lock().unwrap();
+        let mut expired = Vec::new();
+        for mut session in
+            storage.list_pairing_sessions_by_status(PairingStatus::AwaitingConfirmation.as_str())?
+        {
+            if session.is_confirmation_overdue_with(self.clock.as_ref()) {
+                self.fail(&storage, &mut session, FailureReason::Timeout)?;
+                expired.push(session.session_id().to_string());
+            }
+        }
+        Ok(expired)
+    }
+
+    fn record(&self, session_id: &str, local: bool, confirmed: bool) -> Result<PairingStatus> {
+        let storage = self.storage.lock().unwrap();
+        let mut session = self.awaiting(&storage, session_id)?;
+        if !confirmed {
+            let reason = if local {
+                FailureReason::Mismatch
+            } else {
+                FailureReason::PeerMismatch
+            };
+            self.fail(&storage, &mut session, reason)?;
+            return Ok(session.status());
+        }
+
+        session.record_confirmation(local, self.clock.now_unix());
+        storage.upsert_pairing_session(&session)?;
+        if session.is_established() {
+            self.audit(
+                AuditAction::PairingConfirmed,
+                serde_json::json!({
+                    "sessionId": session.session_id(),
+                    "peerPublicKey": hex::encode(self.peer_key(&session)?),
+                }),
+            )?;
+        }
+        Ok(session.status())
+    }
+
+    /// Load a session awaiting confirmation, failing it if overdue
+    fn awaiting(&self, storage: &SqlStorage, session_id: &str) -> Result<PairingSession> {
+        let mut session = Self::load(storage, session_id)?;
+        if session.is_confirmation_overdue_with(self.clock.as_ref()) {
+            self.fail(storage, &mut session, FailureReason::Timeout)?;
+            bail!("Pairing {} was not confirmed in time", session_id);
+        }
+        if !session.is_awaiting_confirmation() {
+            bail!(
+                "Pairing {} is {}, not awaiting confirmation",
+                session_id,
+                session.status().as_str()
+            );
+        }
+        Ok(session)
+    }
+
+    fn fail(
+        &self,
+        storage: &SqlStorage,
+        session: &mut PairingSession,
+        reason: FailureReason,
+    ) -> Result<()> {
+        session.mark_failed();
+        storage.upsert_pairing_session(session)?;
+        self.audit(
+            AuditAction::PairingFailed,
+            serde_json::json!({
+                "sessionId": session.session_id(),
+                "reason": reason.as_str(),
+            }),
+        )
+    }
+
+    fn audit(&self, action: AuditAction, details: serde_json::Value) -> Result<()> {
+        if let Some(audit) = &self.audit {
+            audit.append(action, details)?;
+        }
+        Ok(())
+    }
+
+    /// New session for `start` and `respond`, refusing sessions past the
+    /// key exchange
+    fn begin(
+        &self,
+        session_id: &str,
+        server_public_key: &[u8],
+        device_public_key: &[u8],
+    ) -> Result<PairingSession> {
+        self.guest.require_host(GuestRestriction::Pairing)?;
+        let session = PairingSession::new(session_id, server_public_key, device_public_key)?;
+        if let Some(existing) = self
+            .storage
+            .lock()
+            .unwrap()
+            .get_pairing_session(session_id)?
+        {
+            if !existing.is_pending() {
+                bail!(
+                    "Pairing {} is already {}",
+                    session_id,
+                    existing.status().as_str()
+                );
+            }
+        }
+        Ok(session)
+    }
+
+    /// Load a session still in its key exchange
+    fn pending(storage: &SqlStorage, session_id: &str) -> Result<PairingSession> {
+        let session = Self::load(storage, session_id)?;
+        if !session.is_pending() {
+            bail!(
+                "Pairing {} is {}, not pending",
+                session_id,
+                session.status().as_str()
+            );
+        }
+        Ok(session)
+    }
+
+    /// Remove the key exchange state of a session if it is on the expected
+    /// side
+    fn take_exchange(&self, session_id: &str, server: bool) -> Option<Exchange> {
+        let mut ephemeral = self.ephemeral.lock().unwrap();
+        match ephemeral.get(session_id)? {
+            Exchange::Committed { .. } if server => ephemeral.remove(session_id),
+            Exchange::Responded { .. } if !server => ephemeral.remove(session_id),
+            _ => None,
+        }
+    }
+
+    fn await_confirmation(
+        &self,
+        storage: &SqlStorage,
+        session: &mut PairingSession,
+        code: String,
+    ) -> Result<()> {
+        let deadline = self.clock.now_unix() + self.confirmation_timeout_secs;
+        session.await_confirmation(code, deadline);
+        storage.upsert_pairing_session(session)
+    }
+
+    fn load(storage: &SqlStorage, session_id: &str) -> Result<PairingSession> {
+        storage
+            .get_pairing_session(session_id)?
+            .with_context(|| format!("Pairing {} not found", session_id))
+    }
+
+    /// Whether this device is the server of `session`
+    fn is_server(&self, session: &PairingSession) -> Result<bool> {
+        if session.server_public_key() == self.public_key {
+            Ok(true)
+        } else if session.device_public_key() == self.public_key {
+            Ok(false)
+        } else {
+            bail!(
+                "This device is not part of pairing {}",
+                session.session_id()
+            )
+        }
+    }
+
+    fn peer_key<'a>(&self, session: &'a PairingSession) -> Result<&'a [u8]> {
+        Ok(if self.is_server(session)? {
+            session.device_public_key()
+        } else {
+            session.server_public_key()
+        })
+    }
+}
+
+/// Commitment to the server's ephemeral key, bound to the session
+fn commitment(session: &PairingSession, server_ephemeral: &[u8; 32], nonce: &[u8; 32]) -> [u8; 32] {
+    let mut committed = session.session_id().as_bytes().to_vec();
+    committed.extend_from_slice(session.server_public_key());
+    committed.extend_from_slice(session.device_public_key());
+    committed.extend_from_slice(server_ephemeral);
+    committed.extend_from_slice(nonce);
+    blake3::derive_key(COMMITMENT_CONTEXT, &committed)
+}
+
+/// Derive the code both sides show from the shared secret and the transcript
+///
+/// The transcript binds the session, both devices' long-term and ephemeral
+/// keys in server-then-device order on both sides, and the commitment
+/// nonce.
+fn confirmation_code(
+    shared_secret: &[u8; 32],
+    session: &PairingSession,
+    server_ephemeral: &[u8; 32],
+    device_ephemeral: &[u8; 32],
+    nonce: &[u8; 32],
+) -> String {
+    let mut transcript = shared_secret.to_vec();
+    transcript.extend_from_slice(session.session_id().as_bytes());
+    transcript.extend_from_slice(session.server_public_key());
+    transcript.extend_from_slice(session.device_public_key());
+    transcript.extend_from_slice(server_ephemeral);
+    transcript.extend_from_slice(device_ephemeral);
+    transcript.extend_from_slice(nonce);
+
+    let digest = blake3::derive_key(CONFIRMATION_CONTEXT, &transcript);
+    let value = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    let modulus = 10u64.pow(CONFIRMATION_CODE_DIGITS);
+    format!(
+        "{:0width$}",
+        value % modulus,
+        width = CONFIRMATION_CODE_DIGITS as usize
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::models::identity::RootIdentity;
+    use crate::time::MockClock;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const SERVER_KEY: [u8; 32] = [1u8; 32];
+    const DEVICE_KEY: [u8; 32] = [2u8; 32];
+
+    struct Side {
+        service: PairingService,
+        audit: Arc<AuditLog>,
+        _temp: TempDir,
+    }
+
+    fn side(public_key: &[u8; 32], clock: &Arc<MockClock>) -> Result<Side> {
+        let temp = TempDir::new()?;
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &RootIdentity::generate()?,
+            "user",
+        )?);
+        let service = PairingService::new(temp.path(), public_key)?
+            .with_audit(audit.clone())
+            .with_clock(clock.clone());
+        Ok(Side {
+            service,
+            audit,
+            _temp: temp,
+        })
+    }
+
+    /// Server and device sides with their keys exchanged, and their codes
+    fn exchanged(clock: &Arc<MockClock>) -> Result<(Side, Side, String, String)> {
+        let server = side(&SERVER_KEY, clock)?;
+        let device = side(&DEVICE_KEY, clock)?;
+        let commitment = server.service.start("s1", &SERVER_KEY, &DEVICE_KEY)?;
+        let device_ephemeral =
+            device
+                .service
+                .respond("s1", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        let opening = server.service.reveal("s1", &device_ephemeral)?;
+        let server_code = server.service.confirmation_code("s1")?;
+        let device_code = device.service.exchange("s1", &opening)?;
+        Ok((server, device, server_code, device_code))
+    }
+
+    fn audited(audit: &AuditLog, action: AuditAction) -> Result<Vec<serde_json::Value>> {
+        let filter = AuditFilter {
+            action: Some(action),
+            ..AuditFilter::default()
+        };
+        Ok(audit
+            .list(&filter, PageRequest::default())?
+            .entries
+            .into_iter()
+            .map(|entry| entry.details)
+            .collect())
+    }
+
+    #[test]
+    fn test_both_sides_derive_the_same_code() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let (server, device, server_code, device_code) = exchanged(&clock)?;
+
+        assert_eq!(server_code, device_code);
+        assert_eq!(server_code.len(), CONFIRMATION_CODE_DIGITS as usize);
+        assert!(server_code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(server.service.confirmation_code("s1")?, server_code);
+
+        // Established only once both users confirmed
+        assert_eq!(
+            server.service.confirm_match("s1", true)?,
+            PairingStatus::AwaitingConfirmation
+        );
+        assert_eq!(
+            server.service.record_peer_confirmation("s1", true)?,
+            PairingStatus::Established
+        );
+        assert_eq!(
+            device.service.record_peer_confirmation("s1", true)?,
+            PairingStatus::AwaitingConfirmation
+        );
+        assert_eq!(
+            device.service.confirm_match("s1", true)?,
+            PairingStatus::Established
+        );
+        assert!(server.service.confirm_match("s1", true).is_err());
+
+        let confirmed = audited(&server.audit, AuditAction::PairingConfirmed)?;
+        assert_eq!(
+            confirmed,
+            vec![serde_json::json!({
+                "sessionId": "s1",
+                "peerPublicKey": hex::encode(DEVICE_KEY),
+            })]
+        );
+        assert_eq!(
+            audited(&device.audit, AuditAction::PairingConfirmed)?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_substitution_changes_the_codes() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let server = side(&SERVER_KEY, &clock)?;
+        let device = side(&DEVICE_KEY, &clock)?;
+
+        // An attacker in the middle runs the exchange with each side under
+        // their own ephemeral keys, committing towards the device before
+        // seeing its key
+        let as_device = side(&DEVICE_KEY, &clock)?;
+        let as_server = side(&SERVER_KEY, &clock)?;
+        let commitment = server.service.start("s1", &SERVER_KEY, &DEVICE_KEY)?;
+        let to_server = as_device
+            .service
+            .respond("s1", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        let commitment = as_server.service.start("s1", &SERVER_KEY, &DEVICE_KEY)?;
+        let to_attacker = device
+            .service
+            .respond("s1", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        server.service.reveal("s1", &to_server)?;
+        let opening = as_server.service.reveal("s1", &to_attacker)?;
+        let server_code = server.service.confirmation_code("s1")?;
+        let device_code = device.service.exchange("s1", &opening)?;
+        assert_ne!(server_code, device_code);
+
+        // The user sees different codes and says so
+        assert_eq!(
+            device.service.confirm_match("s1", false)?,
+            PairingStatus::Failed
+        );
+        assert_eq!(
+            server.service.record_peer_confirmation("s1", false)?,
+            PairingStatus::Failed
+        );
+        assert!(device.service.confirmation_code("s1").is_err());
+
+        assert_eq!(
+            audited(&device.audit, AuditAction::PairingFailed)?,
+            vec![serde_json::json!({"sessionId": "s1", "reason": "mismatch"})]
+        );
+        assert_eq!(
+            audited(&server.audit, AuditAction::PairingFailed)?,
+            vec![serde_json::json!({"sessionId": "s1", "reason": "peer_mismatch"})]
+        );
+        assert!(audited(&server.audit, AuditAction::PairingConfirmed)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unconfirmed_session_times_out() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let (server, device, _, _) = exchanged(&clock)?;
+        server.service.confirm_match("s1", true)?;
+
+        // Still open at the deadline, failed one second later
+        clock.advance(Duration::from_secs(DEFAULT_CONFIRMATION_TIMEOUT_SECS));
+        assert!(server.service.confirmation_code("s1").is_ok());
+        clock.advance(Duration::from_secs(1));
+        assert!(server.service.record_peer_confirmation("s1", true).is_err());
+        assert!(server.service.session("s1")?.unwrap().is_failed());
+
+        assert_eq!(device.service.expire_overdue()?, vec!["s1".to_string()]);
+        assert!(device.service.expire_overdue()?.is_empty());
+
+        for side in [&server, &device] {
+            assert_eq!(
+                audited(&side.audit, AuditAction::PairingFailed)?,
+                vec![serde_json::json!({"sessionId": "s1", "reason": "timeout"})]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_awaiting_confirmation_survives_restart() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let (server, device, code, _) = exchanged(&clock)?;
+        server.service.confirm_match("s1", true)?;
+        let Side { audit, _temp, .. } = server;
+
+        // The app restarts mid-pairing
+        let restarted = PairingService::new(_temp.path(), &SERVER_KEY)?
+            .with_audit(audit.clone())
+            .with_clock(clock.clone());
+        assert!(restarted.expire_overdue()?.is_empty());
+        assert_eq!(restarted.confirmation_code("s1")?, code);
+        assert_eq!(
+            restarted.record_peer_confirmation("s1", true)?,
+            PairingStatus::Established
+        );
+        assert_eq!(audited(&audit, AuditAction::PairingConfirmed)?.len(), 1);
+
+        // A key exchange cut off by a restart has to start over
+        let commitment = restarted.start("s2", &SERVER_KEY, &DEVICE_KEY)?;
+        let device_ephemeral =
+            device
+                .service
+                .respond("s2", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        let restarted = PairingService::new(_temp.path(), &SERVER_KEY)?;
+        assert!(restarted.reveal("s2", &device_ephemeral).is_err());
+        let commitment = restarted.start("s2", &SERVER_KEY, &DEVICE_KEY)?;
+        let device_ephemeral =
+            device
+                .service
+                .respond("s2", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        let opening = restarted.reveal("s2", &device_ephemeral)?;
+        assert_eq!(
+            device.service.exchange("s2", &opening)?,
+            restarted.confirmation_code("s2")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_sessions_of_other_devices() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let outsider = side(&[9u8; 32], &clock)?;
+        assert!(outsider
+            .service
+            .start("s1", &SERVER_KEY, &DEVICE_KEY)
+            .is_err());
+
+        // Each side only takes its own part
+        let server = side(&SERVER_KEY, &clock)?;
+        let device = side(&DEVICE_KEY, &clock)?;
+        assert!(device
+            .service
+            .start("s1", &SERVER_KEY, &DEVICE_KEY)
+            .is_err());
+        assert!(server
+            .service
+            .respond("s1", &SERVER_KEY, &DEVICE_KEY, &[0u8; 32])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_swapped_key_does_not_open_the_commitment() -> Result<()> {
+        let clock = Arc::new(MockClock::new(10_000));
+        let server = side(&SERVER_KEY, &clock)?;
+        let device = side(&DEVICE_KEY, &clock)?;
+        let commitment = server.service.start("s1", &SERVER_KEY, &DEVICE_KEY)?;
+        let device_ephemeral =
+            device
+                .service
+                .respond("s1", &SERVER_KEY, &DEVICE_KEY, &commitment)?;
+        let opening = server.service.reveal("s1", &device_ephemeral)?;
+
+        // An attacker relays the commitment but swaps in their own key,
+        // chosen after seeing the device's
+        let attacker = EphemeralSecret::random_from_rng(thread_rng());
+        let swapped = KeyOpening {
+            ephemeral_key: PublicKey::from(&attacker).to_bytes(),
+            ..opening
+        };
+        assert!(device.service.exchange("s1", &swapped).is_err());
+        assert!(device.service.session("s1")?.unwrap().is_failed());
+        assert_eq!(
+            audited(&device.audit, AuditAction::PairingFailed)?,
+            vec![serde_json::json!({"sessionId": "s1", "reason": "commitment"})]
+        );
+
+        // The genuine opening comes too late
+        assert!(device.service.exchange("s1", &opening).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,660 @@
+//! Runtime permission prompts
+//!
+//! Some capabilities are not granted at install; a component asks for them
+//! when it needs them, and the user answers a prompt.
+//!
+//! Handles:
+//! - Raising a [`PermissionPrompt`] for a `permissions.request` call and
+//!   publishing [`AppEvent::PermissionRequested`]; the shell forwards it to
+//!   the frontend as [`PERMISSION_PROMPT_EVENT`] and shows a modal
+//! - Resolving the prompt from `permissions.respond`, optionally
+//!   remembering the decision for the component
+//! - Answering later requests from remembered decisions without a prompt
+//! - Treating a prompt nobody answers within the timeout as denied once
+//! - Scoped prompts for capabilities granted per target, which are never
+//!   remembered here; the asking service keeps its own record
+//!
+//! Denials are reported as [`OsnovaError::PermissionDenied`], which maps to
+//! [`PERMISSION_DENIED_ERROR_CODE`](crate::error::PERMISSION_DENIED_ERROR_CODE).
+//! Remembered decisions are stored per user; pending prompts are not
+//! persisted, so a restart denies them.
+
+use anyhow::{bail, Result};
+use bip39::rand::{thread_rng, RngCore};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::error::OsnovaError;
+use crate::models::permission::{Capability, PermissionGrant, PermissionPrompt};
+use crate::platform::auth::AuthProof;
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::reauth::{ReauthService, SensitiveOperation};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// How long a prompt waits for an answer before counting as denied
+pub const DEFAULT_PROMPT_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+/// Event name used when surfacing a permission prompt to frontends
+pub const PERMISSION_PROMPT_EVENT: &str = "permission-prompt";
+
+/// Event name used when a prompt is answered or times out
+pub const PERMISSION_RESOLVED_EVENT: &str = "permission-resolved";
+
+/// A raised prompt and the request waiting on it
+struct PendingPrompt {
+    prompt: PermissionPrompt,
+    answer: oneshot::Sender<bool>,
+}
+
+/// Permission prompt service
+///
+/// Provides OpenRPC methods:
+/// - `permissions.request` - Ask the user for a capability
+/// - `permissions.respond` - Answer a pending prompt
+/// - `permissions.list` - List remembered decisions
+/// - `permissions.revoke` - Forget a remembered decision
+///
+/// Share the service through an `Arc`: [`request`](Self::request) waits
+/// until [`respond`](Self::respond) is called for its prompt.
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::models::permission::Capability;
+/// use osnova_lib::services::PermissionService;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let service = PermissionService::new("/path/to/storage", "user-123")?;
+/// service
+///     .request(
+///         "com.example.app",
+///         "ant://backend",
+///         Capability::ClipboardRead,
+///         "Paste the address you copied",
+///     )
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PermissionService {
+    storage: Mutex<SqlStorage>,
+    user_id: String,
+    clock: SharedClock,
+    events: Option<EventBus>,
+    timeout: Duration,
+    reauth: Option<Arc<ReauthService>>,
+    pending: Mutex<HashMap<String, PendingPrompt>>,
+}
+
+impl PermissionService {
+    /// Create a new permission service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User who answers the prompts
+    pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.into().join("osnova.db"))?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            user_id: user_id.to_string(),
+            clock: time::default_clock(),
+            events: None,
+            timeout: DEFAULT_PROMPT_TIMEOUT,
+            reauth: None,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Publish prompts and their resolution on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Wait `timeout` for an answer before denying
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Check OS re-authentication before granting, where the policy asks
+    pub fn with_reauth(mut self, reauth: Arc<ReauthService>) -> Self {
+        self.reauth = Some(reauth);
+        self
+    }
+
+    /// Ask the user for a capability (OpenRPC: permissions.request)
+    ///
+    /// Returns once the capability is granted. A remembered decision
+    /// answers immediately; otherwise a prompt is raised and this waits for
+    /// the answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - App whose component is asking
+    /// * `component_id` - Component that is asking
+    /// * `capability` - Capability requested
+    /// * `rationale` - Why the component needs it, shown to the user
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the user denies the
+    /// request, has denied it before and asked to remember, or does not
+    /// answer within the timeout.
+    pub async fn request(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+        rationale: &str,
+    ) -> Result<()> {
+        if rationale.trim().is_empty() {
+            bail!("Permission rationale must not be empty");
+        }
+
+        let remembered = self.storage.lock().unwrap().get_permission_grant(
+            &self.user_id,
+            app_id,
+            component_id,
+            capability,
+        )?;
+        if let Some(grant) = remembered {
+            return if grant.granted {
+                Ok(())
+            } else {
+                Err(Self::denied(capability, component_id))
+            };
+        }
+
+        self.ask(app_id, component_id, capability, None, rationale)
+            .await
+    }
+
+    /// Ask the user for a capability on one target
+    ///
+    /// Always prompts, and the answer is never remembered: a component
+    /// granted one target must not gain the capability for others. The
+    /// caller records the decision for `scope` itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the user denies the
+    /// request or does not answer within the timeout.
+    pub async fn request_scoped(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+        scope: &str,
+        rationale: &str,
+    ) -> Result<()> {
+        if rationale.trim().is_empty() {
+            bail!("Permission rationale must not be empty");
+        }
+
+        self.ask(app_id, component_id, capability, Some(scope), rationale)
+            .await
+    }
+
+    /// Raise a prompt and wait for its answer
+    async fn ask(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+        scope: Option<&str>,
+        rationale: &str,
+    ) -> Result<()> {
+        let (answer, answered) = oneshot::channel();
+        let prompt = self.raise(app_id, component_id, capability, scope, rationale, answer);
+
+        match tokio::time::timeout(self.timeout, answered).await {
+            Ok(Ok(true)) => Ok(()),
+            Ok(_) => Err(Self::denied(capability, component_id)),
+            Err(_) => {
+                // Unanswered prompts deny once and are not remembered
+                if self.pending.lock().unwrap().remove(&prompt.id).is_some() {
+                    self.publish_resolved(&prompt.id, false);
+                }
+                Err(Self::denied(capability, component_id))
+            }
+        }
+    }
+
+    /// Answer a pending prompt (OpenRPC: permissions.respond)
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_id` - Prompt being answered
+    /// * `granted` - Whether the user granted the capability
+    /// * `remember` - Answer later requests from this component the same way
+    ///   (ignored for scoped prompts)
+    /// * `proof` - OS re-authentication, if the policy requires it for
+    ///   granting the capability (see [`ReauthService`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt is unknown or has already been
+    /// resolved, and [`OsnovaError::PermissionDenied`] if granting needs a
+    /// valid proof and none was given; the prompt then stays pending.
+    pub fn respond(
+        &self,
+        prompt_id: &str,
+        granted: bool,
+        remember: bool,
+        proof: Option<&AuthProof>,
+    ) -> Result<()> {
+        if granted {
+            self.require_reauth(prompt_id, proof)?;
+        }
+        let Some(pending) = self.pending.lock().unwrap().remove(prompt_id) else {
+            bail!("Permission prompt {} is not pending", prompt_id);
+        };
+
+        if remember && pending.prompt.scope.is_none() {
+            let grant = PermissionGrant {
+                app_id: pending.prompt.app_id.clone(),
+                component_id: pending.prompt.component_id.clone(),
+                capability: pending.prompt.capability,
+                granted,
+                decided_at: self.clock.now_unix(),
+            };
+            self.storage
+                .lock()
+                .unwrap()
+                .set_permission_grant(&self.user_id, &grant)?;
+        }
+
+        // The request may have given up in the meantime
+        let _ = pending.answer.send(granted);
+        self.publish_resolved(prompt_id, granted);
+
+        Ok(())
+    }
+
+    /// List prompts waiting for an answer, oldest first
+    ///
+    /// Lets a frontend that missed the event show outstanding prompts.
+    pub fn pending(&self) -> Vec<PermissionPrompt> {
+        let mut prompts: Vec<_> = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|pending| pending.prompt.clone())
+            .collect();
+        prompts.sort_by(|a, b| a.requested_at.cmp(&b.requested_at).then(a.id.cmp(&b.id)));
+        prompts
+    }
+
+    /// List remembered decisions, optionally for one app
+    /// (OpenRPC: permissions.list)
+    pub fn grants(&self, app_id: Option<&str>) -> Result<Vec<PermissionGrant>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_permission_grants(&self.user_id, app_id)
+    }
+
+    /// Forget a remembered decision, so the next request prompts again
+    /// (OpenRPC: permissions.revoke)
+    ///
+    /// Returns `false` if no decision was remembered.
+    pub fn revoke(&self, app_id: &str, component_id: &str, capability: Capability) -> Result<bool> {
+        self.storage.lock().unwrap().delete_permission_grant(
+            &self.user_id,
+            app_id,
+            component_id,
+            capability,
+        )
+    }
+
+    /// Record a new prompt and announce it
+    fn raise(
+        &self,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+        scope: Option<&str>,
+        rationale: &str,
+        answer: oneshot::Sender<bool>,
+    ) -> PermissionPrompt {
+        let mut raw = [0u8; 16];
+        thread_rng().fill_bytes(&mut raw);
+
+        let requested_at = self.clock.now_unix();
+        let prompt = PermissionPrompt {
+            id: hex::encode(raw),
+            app_id: app_id.to_string(),
+            component_id: component_id.to_string(),
+            capability,
+            rationale: rationale.to_string(),
+            scope: scope.map(String::from),
+            requested_at,
+            expires_at: requested_at + self.timeout.as_secs(),
+        };
+
+        self.pending.lock().unwrap().insert(
+            prompt.id.clone(),
+            PendingPrompt {
+                prompt: prompt.clone(),
+                answer,
+            },
+        );
+
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::PermissionRequested {
+                user_id: self.user_id.clone(),
+                prompt: prompt.clone(),
+            });
+        }
+
+        prompt
+    }
+
+    /// Check the re-authentication a grant of a pending prompt needs
+    fn require_reauth(&self, prompt_id: &str, proof: Option<&AuthProof>) -> Result<()> {
+        let Some(reauth) = &self.reauth else {
+            return Ok(());
+        };
+        let capability = self
+            .pending
+            .lock()
+            .unwrap()
+            .get(prompt_id)
+            .map(|pending| pending.prompt.capability);
+        let operation = match capability {
+            Some(Capability::PaymentsSend) => SensitiveOperation::PaymentApproval,
+            Some(_) => SensitiveOperation::PermissionGrant,
+            // Unknown prompts fail in the caller
+            None => return Ok(()),
+        };
+        reauth.require(operation, proof)
+    }
+
+    fn publish_resolved(&self, prompt_id: &str, granted: bool) {
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::PermissionResolved {
+                user_id: self.user_id.clone(),
+                prompt_id: prompt_id.to_string(),
+                granted,
+            });
+        }
+    }
+
+    /// Denial reported to the requesting component
+    fn denied(capability: Capability, component_id: &str) -> anyhow::Error {
+        OsnovaError::PermissionDenied(format!("{} for {}", capability, component_id)).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PERMISSION_DENIED_ERROR_CODE;
+    use crate::models::application::OsnovaApplication;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::sync::broadcast;
+
+    const APP_ID: &str = "com.test.app";
+    const COMPONENT_ID: &str = "ant://backend";
+
+    fn create_service(temp: &TempDir) -> Result<PermissionService> {
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        storage.upsert_application(&OsnovaApplication::new(
+            APP_ID,
+            "Test",
+            "1.0.0",
+            "",
+            "",
+            vec![],
+        )?)?;
+        PermissionService::new(temp.path(), "user")
+    }
+
+    /// Start a request in the background and wait for its prompt
+    async fn raise(
+        service: &Arc<PermissionService>,
+        events: &mut broadcast::Receiver<AppEvent>,
+        capability: Capability,
+    ) -> (tokio::task::JoinHandle<Result<()>>, PermissionPrompt) {
+        let requester = service.clone();
+        let request = tokio::spawn(async move {
+            requester
+                .request(APP_ID, COMPONENT_ID, capability, "Needed for the test")
+                .await
+        });
+
+        loop {
+            if let AppEvent::PermissionRequested { prompt, .. } = events.recv().await.unwrap() {
+                return (request, prompt);
+            }
+        }
+    }
+
+    fn assert_denied(result: Result<()>) {
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<OsnovaError>().unwrap();
+        assert!(matches!(err, OsnovaError::PermissionDenied(_)));
+        assert_eq!(err.rpc_code(), PERMISSION_DENIED_ERROR_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_lifecycle() -> Result<()> {
+        let temp = TempDir::new()?;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let service = Arc::new(create_service(&temp)?.with_events(bus));
+
+        let (request, prompt) = raise(&service, &mut events, Capability::ClipboardRead).await;
+        assert_eq!(prompt.capability, Capability::ClipboardRead);
+        assert_eq!(prompt.rationale, "Needed for the test");
+        assert_eq!(service.pending(), std::slice::from_ref(&prompt));
+
+        service.respond(&prompt.id, true, false, None)?;
+        request.await??;
+        assert!(service.pending().is_empty());
+        assert!(matches!(
+            events.recv().await?,
+            AppEvent::PermissionResolved { granted: true, .. }
+        ));
+
+        // Answered once, so the next request prompts again
+        assert!(service.grants(None)?.is_empty());
+        let (request, prompt) = raise(&service, &mut events, Capability::ClipboardRead).await;
+        service.respond(&prompt.id, false, false, None)?;
+        assert_denied(request.await?);
+
+        assert!(service.respond(&prompt.id, true, false, None).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_payment_approval_needs_reauth() -> Result<()> {
+        use crate::platform::auth::{AuthBackend, CommandAuthenticator};
+
+        let temp = TempDir::new()?;
+        let authenticator = CommandAuthenticator::new(AuthBackend::Polkit, "true", &[]);
+        let reauth = Arc::new(ReauthService::new(temp.path(), Arc::new(authenticator)));
+        reauth.set_required(SensitiveOperation::PaymentApproval, true, None)?;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let service = Arc::new(
+            create_service(&temp)?
+                .with_events(bus)
+                .with_reauth(reauth.clone()),
+        );
+
+        let (request, prompt) = raise(&service, &mut events, Capability::PaymentsSend).await;
+        assert_denied(service.respond(&prompt.id, true, false, None));
+        // Still pending, and denying needs no proof
+        assert_eq!(service.pending(), std::slice::from_ref(&prompt));
+        let proof = reauth.authenticate(SensitiveOperation::PaymentApproval)?;
+        service.respond(&prompt.id, true, false, Some(&proof))?;
+        request.await??;
+
+        // Other capabilities follow their own policy
+        let (request, prompt) = raise(&service, &mut events, Capability::ClipboardRead).await;
+        service.respond(&prompt.id, true, false, None)?;
+        request.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remembered_decisions_skip_the_prompt() -> Result<()> {
+        let temp = TempDir::new()?;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let service = Arc::new(create_service(&temp)?.with_events(bus));
+
+        let (request, prompt) = raise(&service, &mut events, Capability::ClipboardRead).await;
+        service.respond(&prompt.id, true, true, None)?;
+        request.await??;
+
+        let (request, prompt) = raise(&service, &mut events, Capability::PaymentsSend).await;
+        service.respond(&prompt.id, false, true, None)?;
+        assert_denied(request.await?);
+        events.recv().await?;
+
+        service
+            .request(APP_ID, COMPONENT_ID, Capability::ClipboardRead, "Again")
+            .await?;
+        assert_denied(
+            service
+                .request(APP_ID, COMPONENT_ID, Capability::PaymentsSend, "Again")
+                .await,
+        );
+        assert!(events.try_recv().is_err());
+
+        // Decisions are per component
+        let grants = service.grants(Some(APP_ID))?;
+        assert_eq!(grants.len(), 2);
+        assert!(grants
+            .iter()
+            .all(|grant| grant.component_id == COMPONENT_ID));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scoped_prompts_are_not_remembered() -> Result<()> {
+        let temp = TempDir::new()?;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let service = Arc::new(create_service(&temp)?.with_events(bus));
+
+        let requester = service.clone();
+        let request = tokio::spawn(async move {
+            requester
+                .request_scoped(
+                    APP_ID,
+                    COMPONENT_ID,
+                    Capability::ReadSharedData,
+                    "com.photos#album",
+                    "Publish the album",
+                )
+                .await
+        });
+        let prompt = loop {
+            if let AppEvent::PermissionRequested { prompt, .. } = events.recv().await? {
+                break prompt;
+            }
+        };
+        assert_eq!(prompt.scope.as_deref(), Some("com.photos#album"));
+
+        service.respond(&prompt.id, true, true, None)?;
+        request.await??;
+        assert!(service.grants(None)?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_prompt_denies_once() -> Result<()> {
+        let temp = TempDir::new()?;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let service = create_service(&temp)?
+            .with_events(bus)
+            .with_timeout(Duration::from_millis(50));
+
+        assert_denied(
+            service
+                .request(APP_ID, COMPONENT_ID, Capability::ReadSharedData, "Sync")
+                .await,
+        );
+        assert!(service.pending().is_empty());
+        assert!(service.grants(None)?.is_empty());
+
+        let AppEvent::PermissionRequested { prompt, .. } = events.recv().await? else {
+            panic!("expected a permission prompt");
+        };
+        assert_eq!(
+            events.recv().await?,
+            AppEvent::PermissionResolved {
+                user_id: "user".to_string(),
+                prompt_id: prompt.id.clone(),
+                granted: false,
+            }
+        );
+
+        // Too late to answer
+        assert!(service.respond(&prompt.id, true, true, None).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remembered_decisions_survive_restart() -> Result<()> {
+        let temp = TempDir::new()?;
+        {
+            let bus = EventBus::new();
+            let mut events = bus.subscribe();
+            let service = Arc::new(create_service(&temp)?.with_events(bus));
+            let (request, prompt) = raise(&service, &mut events, Capability::ClipboardRead).await;
+            service.respond(&prompt.id, true, true, None)?;
+            request.await??;
+        }
+
+        let service =
+            PermissionService::new(temp.path(), "user")?.with_timeout(Duration::from_millis(50));
+        service
+            .request(APP_ID, COMPONENT_ID, Capability::ClipboardRead, "Paste")
+            .await?;
+
+        // Another user has not decided
+        let other =
+            PermissionService::new(temp.path(), "other")?.with_timeout(Duration::from_millis(50));
+        assert_denied(
+            other
+                .request(APP_ID, COMPONENT_ID, Capability::ClipboardRead, "Paste")
+                .await,
+        );
+
+        // Revoked decisions prompt again
+        assert!(service.revoke(APP_ID, COMPONENT_ID, Capability::ClipboardRead)?);
+        assert_denied(
+            service
+                .request(APP_ID, COMPONENT_ID, Capability::ClipboardRead, "Paste")
+                .await,
+        );
+
+        Ok(())
+    }
+}
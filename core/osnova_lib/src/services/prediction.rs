@@ -0,0 +1,302 @@
+//! Launch prediction from local usage
+//!
+//! [`LaunchPredictor`] learns when each installed app tends to be opened,
+//! so the [`CacheWarmer`](super::warming::CacheWarmer) can get apps ready
+//! before the user reaches for them.
+//!
+//! The week is split into 168 hourly slots (UTC hour of day by day of
+//! week). Every launch adds one to the app's score in the slot it falls
+//! in, after the stored score has decayed by half for every half-life
+//! since it was last updated, so old habits fade without any batch job.
+//! A prediction for a time window adds up each app's decayed scores in
+//! the slots the window covers.
+//!
+//! The model lives in the local database next to the launch counts it
+//! extends and never leaves the device. [`LaunchPredictor::clear_usage`]
+//! forgets both.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::services::prediction::LaunchPredictor;
+//! use std::time::Duration;
+//!
+//! # fn example() -> anyhow::Result<()> {
+//! let predictor = LaunchPredictor::new("/tmp/storage")?;
+//! for predicted in predictor.predict(Duration::from_secs(2 * 60 * 60), 5)? {
+//!     println!("{}: {:.2}", predicted.app_id, predicted.score);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Hourly slots in a week
+pub const SLOTS_PER_WEEK: u32 = 7 * 24;
+
+/// Time after which a launch counts half as much (two weeks)
+pub const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+const SECS_PER_HOUR: u64 = 60 * 60;
+
+/// An app expected to be launched, with how strongly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PredictedLaunch {
+    /// Application identifier
+    pub app_id: String,
+    /// Decayed launches in the window's slots; higher is likelier
+    pub score: f64,
+}
+
+/// Local model of when apps are launched
+pub struct LaunchPredictor {
+    sql_storage: Mutex<SqlStorage>,
+    half_life: Duration,
+    clock: SharedClock,
+}
+
+impl LaunchPredictor {
+    /// Open the model in the storage directory's database
+    pub fn new<P: AsRef<Path>>(storage_path: P) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            sql_storage: Mutex::new(sql_storage),
+            half_life: DEFAULT_HALF_LIFE,
+            clock: time::default_clock(),
+        })
+    }
+
+    /// Let launches fade by half over `half_life` instead of the default
+    pub fn with_half_life(mut self, half_life: Duration) -> Self {
+        self.half_life = half_life;
+        self
+    }
+
+    /// Replace the clock (for testing)
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Weekly slot of a Unix time: the UTC hour, counted from Monday 00:00
+    pub fn slot_of(at: u64) -> u32 {
+        // 1970-01-01 was a Thursday
+        let weekday = (at / (24 * SECS_PER_HOUR) + 3) % 7;
+        let hour = at % (24 * SECS_PER_HOUR) / SECS_PER_HOUR;
+        (weekday * 24 + hour) as u32
+    }
+
+    /// Learn from a launch of `app_id` now
+    pub fn record_launch(&self, app_id: &str) -> Result<()> {
+        self.record_launch_at(app_id, self.clock.now_unix())
+    }
+
+    /// Learn from a launch of `app_id` at Unix time `at`
+    pub fn record_launch_at(&self, app_id: &str, at: u64) -> Result<()> {
+        let slot = Self::slot_of(at);
+        let storage = self.sql_storage.lock().unwrap();
+        let score = match storage.get_launch_score(app_id, slot)? {
+            Some((score, updated_at)) => score * self.decay(at.saturating_sub(updated_at)),
+            None => 0.0,
+        };
+        storage.set_launch_score(app_id, slot, score + 1.0, at)
+    }
+
+    /// Installed apps likeliest to be launched within `window` from now,
+    /// at most `limit`, likeliest first
+    pub fn predict(&self, window: Duration, limit: usize) -> Result<Vec<PredictedLaunch>> {
+        self.predict_at(self.clock.now_unix(), window, limit)
+    }
+
+    /// Installed apps likeliest to be launched within `window` from Unix
+    /// time `now`, at most `limit`, likeliest first
+    ///
+    /// Apps never launched in the window's slots are left out; ties go to
+    /// the app ID.
+    pub fn predict_at(
+        &self,
+        now: u64,
+        window: Duration,
+        limit: usize,
+    ) -> Result<Vec<PredictedLaunch>> {
+        let end = now.saturating_add(window.as_secs().max(1));
+        let mut slots = Vec::new();
+        let mut hour = now - now % SECS_PER_HOUR;
+        while hour < end && slots.len() < SLOTS_PER_WEEK as usize {
+            slots.push(Self::slot_of(hour));
+            hour += SECS_PER_HOUR;
+        }
+
+        let mut scores: BTreeMap<String, f64> = BTreeMap::new();
+        let stored = self
+            .sql_storage
+            .lock()
+            .unwrap()
+            .list_launch_scores(&slots)?;
+        for (app_id, _, score, updated_at) in stored {
+            *scores.entry(app_id).or_default() +=
+                score * self.decay(now.saturating_sub(updated_at));
+        }
+
+        let mut predicted: Vec<PredictedLaunch> = scores
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(app_id, score)| PredictedLaunch { app_id, score })
+            .collect();
+        predicted.sort_by(|a, b| b.score.total_cmp(&a.score));
+        predicted.truncate(limit);
+        Ok(predicted)
+    }
+
+    /// Forget every recorded launch, both the counts and the model
+    pub fn clear_usage(&self) -> Result<()> {
+        self.sql_storage.lock().unwrap().clear_app_launches()
+    }
+
+    /// Weight left to a launch `elapsed` seconds ago
+    fn decay(&self, elapsed: u64) -> f64 {
+        let half_life = self.half_life.as_secs_f64().max(1.0);
+        0.5f64.powf(elapsed as f64 / half_life)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use tempfile::TempDir;
+
+    /// Monday 2024-01-01 00:00 UTC
+    const MONDAY: u64 = 1_704_067_200;
+    const DAY: u64 = 24 * SECS_PER_HOUR;
+
+    fn predictor_with_apps(temp: &TempDir, apps: &[&str]) -> Result<LaunchPredictor> {
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        for id in apps {
+            let app = OsnovaApplication::new(*id, "App", "1.0.0", "icon", "app", vec![])?;
+            storage.upsert_application(&app)?;
+        }
+        LaunchPredictor::new(temp.path())
+    }
+
+    fn ids(predicted: &[PredictedLaunch]) -> Vec<&str> {
+        predicted.iter().map(|p| p.app_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_slots_follow_the_utc_week() {
+        assert_eq!(LaunchPredictor::slot_of(MONDAY), 0);
+        assert_eq!(LaunchPredictor::slot_of(MONDAY + 8 * SECS_PER_HOUR + 59), 8);
+        assert_eq!(
+            LaunchPredictor::slot_of(MONDAY + DAY + 20 * SECS_PER_HOUR),
+            44
+        );
+        assert_eq!(LaunchPredictor::slot_of(MONDAY + 7 * DAY - 1), 167);
+        assert_eq!(LaunchPredictor::slot_of(MONDAY + 7 * DAY), 0);
+    }
+
+    #[test]
+    fn test_predictions_follow_usage_patterns() -> Result<()> {
+        let temp = TempDir::new()?;
+        let predictor = predictor_with_apps(&temp, &["com.test.mail", "com.test.games"])?;
+
+        // Three weeks: mail on weekday mornings, games on evenings, plus a
+        // launch of an app since uninstalled
+        for week in 0..3 {
+            for day in 0..5 {
+                let morning = MONDAY + week * 7 * DAY + day * DAY + 8 * SECS_PER_HOUR;
+                predictor.record_launch_at("com.test.mail", morning + 600)?;
+                predictor.record_launch_at("com.test.games", morning + 12 * SECS_PER_HOUR)?;
+            }
+            let tuesday = MONDAY + week * 7 * DAY + DAY;
+            predictor.record_launch_at("com.test.gone", tuesday + 8 * SECS_PER_HOUR)?;
+        }
+        predictor.record_launch_at("com.test.games", MONDAY + 15 * DAY + 8 * SECS_PER_HOUR)?;
+
+        // Tuesday of the fourth week, half an hour before mail time
+        let tuesday = MONDAY + 22 * DAY;
+        let hour = Duration::from_secs(SECS_PER_HOUR);
+        let morning = predictor.predict_at(tuesday + 7 * SECS_PER_HOUR + 1800, hour, 5)?;
+        assert_eq!(ids(&morning), vec!["com.test.mail", "com.test.games"]);
+        assert!(morning[0].score > 1.5 && morning[0].score < 1.6);
+        assert!(morning[0].score > 2.0 * morning[1].score);
+
+        let evening = predictor.predict_at(tuesday + 19 * SECS_PER_HOUR + 1800, hour, 5)?;
+        assert_eq!(ids(&evening), vec!["com.test.games"]);
+
+        // Nobody launches anything at night, and the limit applies
+        let night = predictor.predict_at(tuesday + 3 * SECS_PER_HOUR, hour, 5)?;
+        assert!(night.is_empty());
+        let day = predictor.predict_at(tuesday, Duration::from_secs(DAY), 1)?;
+        assert_eq!(ids(&day), vec!["com.test.games"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_habits_decay() -> Result<()> {
+        let temp = TempDir::new()?;
+        let predictor = predictor_with_apps(&temp, &["com.test.old", "com.test.new"])?
+            .with_half_life(Duration::from_secs(7 * DAY));
+        let nine = 9 * SECS_PER_HOUR;
+
+        // Eight Mondays of the old app, then two of the new one
+        for week in 0..8 {
+            predictor.record_launch_at("com.test.old", MONDAY + week * 7 * DAY + nine)?;
+        }
+        for week in 8..10 {
+            predictor.record_launch_at("com.test.new", MONDAY + week * 7 * DAY + nine)?;
+        }
+
+        let window = Duration::from_secs(SECS_PER_HOUR);
+        let now = MONDAY + 10 * 7 * DAY + nine;
+        let predicted = predictor.predict_at(now, window, 5)?;
+        assert_eq!(ids(&predicted), vec!["com.test.new", "com.test.old"]);
+        // Two weeks on, the new app's scores have halved twice
+        assert!((predicted[0].score - (0.25 + 0.5)).abs() < 1e-9);
+
+        // The score of a slot decays before each launch is added
+        let stored = predictor
+            .sql_storage
+            .lock()
+            .unwrap()
+            .get_launch_score("com.test.old", 9)?
+            .unwrap();
+        assert!((stored.0 - (2.0 - 0.5f64.powi(7))).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearing_usage_resets_the_model() -> Result<()> {
+        let temp = TempDir::new()?;
+        let predictor = predictor_with_apps(&temp, &["com.test.app"])?;
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        predictor.record_launch_at("com.test.app", MONDAY)?;
+        storage.record_app_launch("com.test.app", MONDAY)?;
+        let window = Duration::from_secs(SECS_PER_HOUR);
+        assert_eq!(predictor.predict_at(MONDAY, window, 5)?.len(), 1);
+
+        predictor.clear_usage()?;
+        assert!(predictor.predict_at(MONDAY, window, 5)?.is_empty());
+        assert!(storage.most_launched_apps(5)?.is_empty());
+
+        // Learning starts over from nothing
+        predictor.record_launch_at("com.test.app", MONDAY + 60)?;
+        let predicted = predictor.predict_at(MONDAY + 60, window, 5)?;
+        assert!((predicted[0].score - 1.0).abs() < 1e-9);
+
+        Ok(())
+    }
+}
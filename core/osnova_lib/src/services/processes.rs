@@ -0,0 +1,1173 @@
+//! Backend process supervision
+//!
+//! Tracks backend component processes spawned by Osnova so they can be
+//! cleaned up if Osnova crashes.
+//!
+//! Handles:
+//! - Persisting a registry of spawned processes (app, pid, start time,
+//!   socket path) in SQL storage, removing entries on clean exit
+//! - Terminating orphans left behind by a previous run at startup, without
+//!   touching unrelated processes that reused a recorded pid
+//! - A watchdog that detects processes that died unexpectedly, records a
+//!   crash report (exit code or signal, stderr tail, timing), and emits
+//!   app-crashed events
+//! - Keeping crash reports under a per-app cap and the store's retention
+//!   policy
+//! - Capturing each backend's stderr into a per-process ring buffer file,
+//!   so the last lines before a crash survive it
+//! - The warm pool: backends of frequently launched apps can stay idle
+//!   after the app's UI closes, and are reaped after an idle timeout or,
+//!   least recently closed first, when idle backends exceed their memory
+//!   budget ([`RuntimeSettings`])
+
+use anyhow::{Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::models::backend_process::{BackendProcess, ComponentOwner, ProcessState};
+use crate::models::crash_report::CrashReport;
+use crate::models::retention::{Footprint, Policy, RetainedItem, RetentionStore};
+use crate::platform::process::{self, Termination};
+use crate::services::config_cache::DEFAULT_CONFIG_CACHE_ENTRIES;
+use crate::services::health::HealthPolicy;
+use crate::services::retention::{self, RetainedStore};
+use crate::storage::sql::RetainedRows;
+use crate::storage::{CompressionSettings, SqlStorage};
+use crate::time::{self, SharedClock};
+
+/// Event name used when surfacing [`AppCrashed`] to frontends
+pub const APP_CRASHED_EVENT: &str = "app-crashed";
+
+/// Default time a process is given to exit after SIGTERM
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Default interval between watchdog checks
+pub const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default number of stderr lines kept in a crash report
+pub const DEFAULT_STDERR_TAIL_LINES: usize = 50;
+
+/// Crash reports kept per app; older ones are pruned
+pub const MAX_CRASH_REPORTS_PER_APP: usize = 20;
+
+/// Default number of most launched apps whose backends are kept warm
+pub const DEFAULT_WARM_POOL_SIZE: usize = 3;
+
+/// Default memory idle backends may hold together, in bytes (256 MiB)
+pub const DEFAULT_IDLE_MEMORY_BUDGET: u64 = 256 * 1024 * 1024;
+
+/// Default time an idle backend is kept, in seconds (10 minutes)
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 10 * 60;
+
+/// Capacity of the crash event channel
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Size past which a stderr ring file is cut back to its newest half
+const STDERR_RING_BYTES: u64 = 64 * 1024;
+
+/// How long a crash check waits for a dead process's stderr to drain
+///
+/// Bounded because a grandchild may still hold the pipe open.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Emitted when a registered backend process dies unexpectedly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AppCrashed {
+    /// Application whose backend died
+    pub app_id: String,
+    /// Process id of the dead backend
+    pub pid: u32,
+    /// Exit code, if the process was our child and exited normally
+    pub exit_code: Option<i32>,
+    /// Signal that killed the process, if it was our child
+    pub signal: Option<i32>,
+    /// Crash report recorded for the exit
+    pub report_id: String,
+}
+
+/// How backend processes are run and per-app configuration is read
+///
+/// The warm pool is opt-in. With it on, the backends of the
+/// `warm_pool_size` most launched apps keep running idle after the app's
+/// UI closes, so the next launch reuses them and only reloads the frontend.
+///
+/// Decrypted per-app configurations are cached by default (see
+/// [`crate::services::config_cache`]); `config_cache` is its kill switch.
+///
+/// `health_policy` says what happens to backends failing their declared
+/// health checks (see [`crate::services::health`]).
+///
+/// `compression` sets the level and threshold of the component cache and
+/// encrypted blob compression (see [`crate::storage::compression`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuntimeSettings {
+    /// Keep backends of frequently launched apps warm
+    pub warm_pool: bool,
+    /// Number of most launched apps eligible for the warm pool
+    pub warm_pool_size: usize,
+    /// Memory idle backends may hold together, in bytes
+    pub idle_memory_budget_bytes: u64,
+    /// Seconds an idle backend is kept before it is stopped
+    pub idle_timeout_secs: u64,
+    /// Cache decrypted per-app configurations in memory
+    pub config_cache: bool,
+    /// Per-app configurations the cache holds before evicting the least
+    /// recently read
+    pub config_cache_entries: usize,
+    /// What happens to backends failing their health checks
+    pub health_policy: HealthPolicy,
+    /// Compression of cached components and encrypted blobs
+    pub compression: CompressionSettings,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            warm_pool: false,
+            warm_pool_size: DEFAULT_WARM_POOL_SIZE,
+            idle_memory_budget_bytes: DEFAULT_IDLE_MEMORY_BUDGET,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            config_cache: true,
+            config_cache_entries: DEFAULT_CONFIG_CACHE_ENTRIES,
+            health_policy: HealthPolicy::default(),
+            compression: CompressionSettings::default(),
+        }
+    }
+}
+
+/// Reads the resident memory of a process, in bytes
+pub type MemoryProbe = Arc<dyn Fn(u32) -> Option<u64> + Send + Sync>;
+
+/// What startup cleanup did with a registry entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanOutcome {
+    /// The orphan exited after SIGTERM
+    Terminated,
+    /// The orphan ignored SIGTERM and was killed
+    Killed,
+    /// The process had already exited
+    AlreadyExited,
+    /// The pid now belongs to an unrelated process, which was left alone
+    PidRecycled,
+}
+
+/// Cleanup result for one registry entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanup {
+    /// Application the process belonged to
+    pub app_id: String,
+    /// Recorded process id
+    pub pid: u32,
+    /// What was done
+    pub outcome: OrphanOutcome,
+    /// Socket file that was removed, if any
+    pub removed_socket: Option<PathBuf>,
+}
+
+/// Report of startup orphan cleanup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrphanReport {
+    /// One entry per stale registry record
+    pub entries: Vec<OrphanCleanup>,
+}
+
+impl OrphanReport {
+    /// Number of orphan processes that had to be terminated
+    pub fn terminated_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.outcome, OrphanOutcome::Terminated | OrphanOutcome::Killed))
+            .count()
+    }
+}
+
+/// Backend process supervisor
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::ProcessService;
+/// use std::process::Command;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = ProcessService::new("/tmp/osnova")?;
+///
+/// // Terminate anything left over from a crashed run
+/// let report = service.cleanup_orphans()?;
+/// println!("Terminated {} orphans", report.terminated_count());
+///
+/// let pid = service.spawn("com.osnova.wallet", &mut Command::new("wallet-backend"), None)?;
+/// service.stop(pid)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProcessService {
+    storage: Mutex<SqlStorage>,
+    children: Mutex<HashMap<u32, Child>>,
+    stderr_readers: Mutex<HashMap<u32, JoinHandle<()>>>,
+    events: broadcast::Sender<AppCrashed>,
+    grace_period: Duration,
+    logs_dir: PathBuf,
+    stderr_tail_lines: usize,
+    retention: Policy,
+    clock: SharedClock,
+    memory_probe: MemoryProbe,
+}
+
+impl ProcessService {
+    /// Create a new process service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database; backend
+    ///   stderr is captured under `logs/backends` inside it
+    pub fn new<P: AsRef<Path>>(storage_path: P) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            children: Mutex::new(HashMap::new()),
+            stderr_readers: Mutex::new(HashMap::new()),
+            events,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            logs_dir: storage_path.as_ref().join("logs").join("backends"),
+            stderr_tail_lines: DEFAULT_STDERR_TAIL_LINES,
+            retention: RetentionStore::CrashReports.default_policy(),
+            clock: time::default_clock(),
+            memory_probe: Arc::new(process::resident_memory),
+        })
+    }
+
+    /// Set how long processes are given to exit after SIGTERM
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Set how many stderr lines a crash report keeps
+    pub fn with_stderr_tail_lines(mut self, lines: usize) -> Self {
+        self.stderr_tail_lines = lines;
+        self
+    }
+
+    /// Keep crash reports of all apps under `policy`
+    pub fn with_retention_policy(mut self, policy: Policy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Use a specific clock for registration, idle and crash times
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Measure the memory of idle backends with `probe` instead of asking
+    /// the operating system
+    pub fn with_memory_probe<F>(mut self, probe: F) -> Self
+    where
+        F: Fn(u32) -> Option<u64> + Send + Sync + 'static,
+    {
+        self.memory_probe = Arc::new(probe);
+        self
+    }
+
+    /// Subscribe to app-crashed events
+    pub fn subscribe(&self) -> broadcast::Receiver<AppCrashed> {
+        self.events.subscribe()
+    }
+
+    /// Spawn a backend process and record it in the registry
+    ///
+    /// The process's stderr is captured (and still echoed to Osnova's own
+    /// stderr) so a crash report can include its last lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Application the backend belongs to
+    /// * `command` - Command to spawn
+    /// * `socket_path` - Socket the backend will listen on, if any
+    ///
+    /// # Returns
+    ///
+    /// The process id of the spawned backend
+    pub fn spawn(
+        &self,
+        app_id: &str,
+        command: &mut Command,
+        socket_path: Option<&Path>,
+    ) -> Result<u32> {
+        self.spawn_process(app_id, None, command, socket_path)
+    }
+
+    /// Spawn a backend component process and record it in the registry
+    ///
+    /// Like [`spawn`](Self::spawn), but crash reports of the process name
+    /// the component and version it ran.
+    pub fn spawn_component(
+        &self,
+        app_id: &str,
+        component: (&str, &str),
+        command: &mut Command,
+        socket_path: Option<&Path>,
+    ) -> Result<u32> {
+        self.spawn_process(app_id, Some(component), command, socket_path)
+    }
+
+    /// Spawn a process, capture its stderr, and record it in the registry
+    fn spawn_process(
+        &self,
+        app_id: &str,
+        component: Option<(&str, &str)>,
+        command: &mut Command,
+        socket_path: Option<&Path>,
+    ) -> Result<u32> {
+        std::fs::create_dir_all(&self.logs_dir)
+            .context("Failed to create backend log directory")?;
+
+        let mut child = command
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to spawn backend for {}", app_id))?;
+        let pid = child.id();
+        if let Some(stderr) = child.stderr.take() {
+            let reader = capture_stderr(stderr, self.stderr_path(pid));
+            self.stderr_readers.lock().unwrap().insert(pid, reader);
+        }
+
+        // The command line is taken from the command rather than read back,
+        // since the child may still be mid-exec. A process that already
+        // exited gets no start time, so the watchdog reports it as crashed.
+        let start_time = process::process_info(pid)
+            .map(|info| info.start_time)
+            .unwrap_or_default();
+        let command_line = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut record =
+            BackendProcess::new(app_id, pid, start_time, command_line, self.clock.now_unix());
+        if let Some(socket_path) = socket_path {
+            record = record.with_socket_path(socket_path);
+        }
+        if let Some((component_id, version)) = component {
+            record = record.with_component(component_id, version);
+        }
+
+        self.storage
+            .lock()
+            .unwrap()
+            .register_backend_process(&record)?;
+        self.children.lock().unwrap().insert(pid, child);
+
+        Ok(pid)
+    }
+
+    /// List registered backend processes
+    pub fn list(&self) -> Result<Vec<BackendProcess>> {
+        self.storage.lock().unwrap().list_backend_processes()
+    }
+
+    /// Get the principal a registered backend process acts for
+    ///
+    /// Used to attribute RPC calls: processes of shared components resolve
+    /// to [`ComponentOwner::Shared`] rather than to any single app.
+    pub fn owner_of(&self, pid: u32) -> Result<Option<ComponentOwner>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|p| p.pid() == pid)
+            .map(|p| p.owner()))
+    }
+
+    /// Stop a backend process cleanly
+    ///
+    /// The registry entry is removed before the process is signalled so the
+    /// watchdog does not report the exit as a crash.
+    pub fn stop(&self, pid: u32) -> Result<Termination> {
+        let record = self
+            .list()?
+            .into_iter()
+            .find(|p| p.pid() == pid)
+            .with_context(|| format!("Backend process {} is not registered", pid))?;
+        self.storage.lock().unwrap().remove_backend_process(pid)?;
+
+        let child = self.children.lock().unwrap().remove(&pid);
+        let termination = match child {
+            Some(mut child) => {
+                let termination = process::terminate(pid, self.grace_period)?;
+                child.wait().context("Failed to reap backend process")?;
+                termination
+            }
+            // Only signal processes we did not spawn if they are still ours
+            None if Self::is_same_process(&record) => process::terminate(pid, self.grace_period)?,
+            None => Termination::NotRunning,
+        };
+        Self::remove_socket(&record)?;
+        self.discard_stderr(pid);
+
+        Ok(termination)
+    }
+
+    /// Move a backend process between the warm pool and active use
+    ///
+    /// Idle processes stay registered, so the watchdog still reports them
+    /// if they die.
+    pub fn set_state(&self, pid: u32, state: ProcessState) -> Result<BackendProcess> {
+        let storage = self.storage.lock().unwrap();
+        let mut record = storage
+            .list_backend_processes()?
+            .into_iter()
+            .find(|p| p.pid() == pid)
+            .with_context(|| format!("Backend process {} is not registered", pid))?;
+        match state {
+            ProcessState::Idle => record.mark_idle(self.clock.now_unix()),
+            ProcessState::Active => record.mark_active(),
+        }
+        storage.register_backend_process(&record)?;
+        Ok(record)
+    }
+
+    /// Apps whose idle backends should be stopped under `settings`, in the
+    /// order to stop them
+    ///
+    /// All of them if the warm pool is off. Otherwise first those idle for
+    /// longer than the idle timeout, then, least recently closed first, as
+    /// many as it takes to bring the memory of the remaining idle backends
+    /// within the budget. A process whose memory cannot be read counts as
+    /// using none.
+    pub fn idle_apps_to_reap(&self, settings: &RuntimeSettings) -> Result<Vec<String>> {
+        // Latest close and total memory of each app's idle backends
+        let mut idle: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for record in self.list()? {
+            let Some(idle_since) = record.idle_since() else {
+                continue;
+            };
+            let memory = (self.memory_probe)(record.pid()).unwrap_or(0);
+            let entry = idle.entry(record.app_id().to_string()).or_default();
+            entry.0 = entry.0.max(idle_since);
+            entry.1 += memory;
+        }
+
+        let now = self.clock.now_unix();
+        let (mut reaped, mut kept): (Vec<_>, Vec<_>) =
+            idle.into_iter().partition(|(_, (idle_since, _))| {
+                !settings.warm_pool || now.saturating_sub(*idle_since) > settings.idle_timeout_secs
+            });
+        reaped.sort_by_key(|(_, (idle_since, _))| *idle_since);
+
+        kept.sort_by_key(|(_, (idle_since, _))| *idle_since);
+        let mut total: u64 = kept.iter().map(|(_, (_, memory))| memory).sum();
+        let mut kept = kept.into_iter();
+        while total > settings.idle_memory_budget_bytes {
+            let Some(oldest) = kept.next() else {
+                break;
+            };
+            total -= oldest.1 .1;
+            reaped.push(oldest);
+        }
+
+        Ok(reaped.into_iter().map(|(app_id, _)| app_id).collect())
+    }
+
+    /// Stop the idle backends of an application, e.g. when it updates
+    ///
+    /// Returns the number of processes stopped.
+    pub fn stop_idle(&self, app_id: &str) -> Result<usize> {
+        let idle: Vec<u32> = self
+            .list()?
+            .iter()
+            .filter(|p| p.app_id() == app_id && p.is_idle())
+            .map(BackendProcess::pid)
+            .collect();
+        for pid in &idle {
+            self.stop(*pid)?;
+        }
+        Ok(idle.len())
+    }
+
+    /// Stop every backend process belonging to an application
+    pub fn stop_app(&self, app_id: &str) -> Result<()> {
+        for record in self.list()?.iter().filter(|p| p.app_id() == app_id) {
+            self.stop(record.pid())?;
+        }
+        Ok(())
+    }
+
+    /// Stop all registered backend processes, e.g. on clean exit
+    pub fn stop_all(&self) -> Result<()> {
+        for record in self.list()? {
+            self.stop(record.pid())?;
+        }
+        Ok(())
+    }
+
+    /// Clean up processes left behind by a previous run
+    ///
+    /// Call once at startup, before any backends are spawned. Each registry
+    /// entry is checked against the process currently holding its pid:
+    /// - If the start time and command line match, the orphan is terminated
+    ///   (SIGTERM, then SIGKILL after the grace period)
+    /// - If the pid is unused or now belongs to another process, nothing is
+    ///   signalled
+    ///
+    /// In every case the socket file is removed and the entry dropped.
+    pub fn cleanup_orphans(&self) -> Result<OrphanReport> {
+        let mut report = OrphanReport::default();
+        let owned: Vec<u32> = self.children.lock().unwrap().keys().copied().collect();
+
+        for record in self.list()? {
+            if owned.contains(&record.pid()) {
+                continue;
+            }
+
+            let outcome = match process::process_info(record.pid()) {
+                None => OrphanOutcome::AlreadyExited,
+                Some(info) if !record.matches(&info.start_time, &info.command_line) => {
+                    OrphanOutcome::PidRecycled
+                }
+                Some(_) => match process::terminate(record.pid(), self.grace_period)? {
+                    Termination::Forced => OrphanOutcome::Killed,
+                    Termination::Graceful => OrphanOutcome::Terminated,
+                    Termination::NotRunning => OrphanOutcome::AlreadyExited,
+                },
+            };
+
+            let removed_socket = Self::remove_socket(&record)?;
+            self.discard_stderr(record.pid());
+            self.storage
+                .lock()
+                .unwrap()
+                .remove_backend_process(record.pid())?;
+
+            report.entries.push(OrphanCleanup {
+                app_id: record.app_id().to_string(),
+                pid: record.pid(),
+                outcome,
+                removed_socket,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Detect registered processes that died unexpectedly
+    ///
+    /// For each dead process a [`CrashReport`] is stored, the process is
+    /// removed from the registry, its socket cleaned up, and an
+    /// [`AppCrashed`] event is emitted.
+    pub fn check_processes(&self) -> Result<Vec<AppCrashed>> {
+        let mut crashed = Vec::new();
+
+        for record in self.list()? {
+            let pid = record.pid();
+
+            let exit = {
+                let mut children = self.children.lock().unwrap();
+                match children.get_mut(&pid) {
+                    Some(child) => match child.try_wait()? {
+                        Some(status) => {
+                            children.remove(&pid);
+                            Some((status.code(), process::exit_signal(&status)))
+                        }
+                        None => None,
+                    },
+                    None => (!Self::is_same_process(&record)).then_some((None, None)),
+                }
+            };
+
+            let Some((exit_code, signal)) = exit else {
+                continue;
+            };
+
+            let report = self.crash_report(&record, exit_code, signal);
+            {
+                let storage = self.storage.lock().unwrap();
+                storage.insert_crash_report(&report)?;
+                storage.prune_crash_reports(&report.app_id, MAX_CRASH_REPORTS_PER_APP)?;
+                storage.remove_backend_process(pid)?;
+            }
+            retention::apply_on_write(self, &self.retention, report.crashed_at)?;
+            Self::remove_socket(&record)?;
+
+            let event = AppCrashed {
+                app_id: record.app_id().to_string(),
+                pid,
+                exit_code,
+                signal,
+                report_id: report.id,
+            };
+            // Sending only fails when nobody is subscribed
+            let _ = self.events.send(event.clone());
+            crashed.push(event);
+        }
+
+        Ok(crashed)
+    }
+
+    /// Run the watchdog until `shutdown` is cancelled
+    ///
+    /// Spawn this on the async runtime; it checks the registry every
+    /// `interval` and emits events for processes that died.
+    pub async fn run_watchdog(self: Arc<Self>, interval: Duration, shutdown: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            // A failed check is retried on the next tick
+            let _ = self.check_processes();
+        }
+    }
+
+    /// List an owner's crash reports, newest first
+    pub fn crash_reports(&self, app_id: &str) -> Result<Vec<CrashReport>> {
+        self.storage.lock().unwrap().list_crash_reports(app_id)
+    }
+
+    /// Build the crash report of a dead process, consuming its stderr file
+    fn crash_report(
+        &self,
+        record: &BackendProcess,
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+    ) -> CrashReport {
+        let pid = record.pid();
+        self.drain_stderr(pid);
+        let stderr_tail = read_tail(&self.stderr_path(pid), self.stderr_tail_lines);
+        self.discard_stderr(pid);
+
+        let mut id = [0u8; 16];
+        thread_rng().fill_bytes(&mut id);
+        CrashReport {
+            id: hex::encode(id),
+            app_id: record.app_id().to_string(),
+            component_id: record.component_id().map(str::to_string),
+            component_version: record.component_version().map(str::to_string),
+            pid,
+            exit_code,
+            signal,
+            started_at: record.registered_at(),
+            crashed_at: self.clock.now_unix(),
+            stderr_tail,
+        }
+    }
+
+    /// Ring buffer file a process's stderr is captured to
+    fn stderr_path(&self, pid: u32) -> PathBuf {
+        self.logs_dir.join(format!("{}.stderr", pid))
+    }
+
+    /// Wait, briefly, for a dead process's stderr to be fully captured
+    fn drain_stderr(&self, pid: u32) {
+        let Some(reader) = self.stderr_readers.lock().unwrap().remove(&pid) else {
+            return;
+        };
+
+        let deadline = Instant::now() + STDERR_DRAIN_TIMEOUT;
+        while !reader.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if reader.is_finished() {
+            let _ = reader.join();
+        }
+    }
+
+    /// Stop tracking a process's stderr and delete its ring file
+    fn discard_stderr(&self, pid: u32) {
+        // The reader thread ends by itself once the pipe closes
+        self.stderr_readers.lock().unwrap().remove(&pid);
+        let _ = std::fs::remove_file(self.stderr_path(pid));
+    }
+
+    /// Check whether the live process at a recorded pid is the recorded one
+    fn is_same_process(record: &BackendProcess) -> bool {
+        process::process_info(record.pid())
+            .map(|info| record.matches(&info.start_time, &info.command_line))
+            .unwrap_or(false)
+    }
+
+    /// Remove a record's socket file, returning its path if one was removed
+    fn remove_socket(record: &BackendProcess) -> Result<Option<PathBuf>> {
+        let Some(socket_path) = record.socket_path() else {
+            return Ok(None);
+        };
+
+        match std::fs::remove_file(socket_path) {
+            Ok(()) => Ok(Some(socket_path.to_path_buf())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to remove socket {}", socket_path.display()))
+            }
+        }
+    }
+}
+
+impl RetainedStore for ProcessService {
+    fn store(&self) -> RetentionStore {
+        RetentionStore::CrashReports
+    }
+
+    fn footprint(&self) -> Result<Footprint> {
+        self.storage
+            .lock()
+            .unwrap()
+            .retained_footprint(RetainedRows::CrashReports)
+    }
+
+    fn items(&self) -> Result<Vec<RetainedItem>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_retained(RetainedRows::CrashReports)
+    }
+
+    fn remove(&self, items: &[RetainedItem], _now: u64) -> Result<()> {
+        let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+        self.storage
+            .lock()
+            .unwrap()
+            .delete_retained(RetainedRows::CrashReports, &ids)?;
+        Ok(())
+    }
+}
+
+/// Copy a child's stderr into a ring buffer file on a background thread
+///
+/// Each line is also echoed to our own stderr, where backend output went
+/// before it was captured. The file is cut back to its newest half whenever
+/// it grows past [`STDERR_RING_BYTES`].
+fn capture_stderr(stderr: ChildStderr, path: PathBuf) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut file = File::create(&path).ok();
+        let mut written = 0u64;
+
+        for line in BufReader::new(stderr).split(b'\n') {
+            let Ok(mut line) = line else {
+                break;
+            };
+            eprintln!("{}", String::from_utf8_lossy(&line));
+
+            let Some(out) = file.as_mut() else {
+                continue;
+            };
+            line.push(b'\n');
+            if out.write_all(&line).is_err() {
+                file = None;
+                continue;
+            }
+            written += line.len() as u64;
+            if written > STDERR_RING_BYTES {
+                file = compact_ring(&path, STDERR_RING_BYTES / 2).ok();
+                written = STDERR_RING_BYTES / 2;
+            }
+        }
+    })
+}
+
+/// Keep only about the last `keep` bytes of a ring file, on a line boundary
+///
+/// Returns the file reopened for appending.
+fn compact_ring(path: &Path, keep: u64) -> std::io::Result<File> {
+    let mut contents = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+
+    // Start after the last newline before the cut, dropping a partial line
+    let cut = contents.len().saturating_sub(keep as usize);
+    let start = match cut {
+        0 => 0,
+        _ => contents[cut - 1..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(contents.len(), |newline| cut + newline),
+    };
+    std::fs::write(path, &contents[start..])?;
+
+    OpenOptions::new().append(true).open(path)
+}
+
+/// Read the last `lines` lines of a ring file, empty if there is none
+fn read_tail(path: &Path, lines: usize) -> Vec<String> {
+    let Ok(contents) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&contents);
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use tempfile::TempDir;
+
+    fn sleeper() -> Command {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+        command
+    }
+
+    /// Stub backend that writes to stderr and then crashes with SIGSEGV
+    fn crasher(stderr_lines: usize) -> Command {
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            &format!(
+                "echo starting >&2; i=0; while [ $i -lt {} ]; do echo line $i >&2; \
+                 i=$((i+1)); done; kill -SEGV $$",
+                stderr_lines
+            ),
+        ]);
+        command
+    }
+
+    /// Run crash checks until the watchdog reports something
+    fn wait_for_crash(service: &ProcessService) -> Result<Vec<AppCrashed>> {
+        for _ in 0..500 {
+            let crashed = service.check_processes()?;
+            if !crashed.is_empty() {
+                return Ok(crashed);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        anyhow::bail!("No crash detected")
+    }
+
+    #[test]
+    fn test_spawn_and_stop_removes_entry() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let socket = temp_dir.path().join("backend.sock");
+        std::fs::write(&socket, b"")?;
+
+        let service = ProcessService::new(temp_dir.path())?;
+        let pid = service.spawn("com.test.app", &mut sleeper(), Some(&socket))?;
+        assert_eq!(service.list()?.len(), 1);
+
+        assert!(service.stderr_path(pid).exists());
+
+        assert_eq!(service.stop(pid)?, Termination::Graceful);
+        assert!(service.list()?.is_empty());
+        assert!(!socket.exists());
+        assert!(!service.stderr_path(pid).exists());
+
+        // A clean stop is not a crash
+        assert!(service.check_processes()?.is_empty());
+        assert!(service.crash_reports("com.test.app")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_apps_to_reap() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let clock = Arc::new(MockClock::new(1_000));
+        let service = ProcessService::new(temp_dir.path())?
+            .with_clock(clock.clone())
+            .with_memory_probe(|_| Some(100));
+
+        let mut pids = Vec::new();
+        for app_id in ["com.test.a", "com.test.b", "com.test.c"] {
+            pids.push(service.spawn(app_id, &mut sleeper(), None)?);
+        }
+        for pid in &pids {
+            service.set_state(*pid, ProcessState::Idle)?;
+            clock.advance(Duration::from_secs(10));
+        }
+        // Marking an idle process idle again keeps when it was closed
+        service.set_state(pids[0], ProcessState::Idle)?;
+
+        let mut settings = RuntimeSettings {
+            warm_pool: true,
+            idle_memory_budget_bytes: 300,
+            ..RuntimeSettings::default()
+        };
+        assert!(service.idle_apps_to_reap(&settings)?.is_empty());
+
+        // Least recently closed first, until the rest fit the budget
+        settings.idle_memory_budget_bytes = 150;
+        assert_eq!(
+            service.idle_apps_to_reap(&settings)?,
+            vec!["com.test.a", "com.test.b"]
+        );
+
+        // Timed out apps go regardless of the budget
+        settings.idle_memory_budget_bytes = 1_000;
+        settings.idle_timeout_secs = 15;
+        assert_eq!(
+            service.idle_apps_to_reap(&settings)?,
+            vec!["com.test.a", "com.test.b"]
+        );
+
+        // An active process is never reaped
+        let active = service.set_state(pids[1], ProcessState::Active)?;
+        assert_eq!(active.warm_starts(), 1);
+        assert_eq!(active.registered_at(), 1_000);
+        assert_eq!(service.idle_apps_to_reap(&settings)?, vec!["com.test.a"]);
+
+        settings.warm_pool = false;
+        assert_eq!(
+            service.idle_apps_to_reap(&settings)?,
+            vec!["com.test.a", "com.test.c"]
+        );
+
+        assert_eq!(service.stop_idle("com.test.a")?, 1);
+        assert_eq!(service.stop_idle("com.test.b")?, 0);
+        assert_eq!(service.list()?.len(), 2);
+        service.stop_all()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_watchdog_reports_idle_crash() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = ProcessService::new(temp_dir.path())?;
+        let pid = service.spawn("com.test.app", &mut sleeper(), None)?;
+        service.set_state(pid, ProcessState::Idle)?;
+
+        Command::new("kill")
+            .args(["-KILL", &pid.to_string()])
+            .status()?;
+
+        let crashed = wait_for_crash(&service)?;
+        assert_eq!(crashed[0].pid, pid);
+        assert!(service.list()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_owner_of_attributes_shared_components() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = ProcessService::new(temp_dir.path())?;
+
+        let shared = ComponentOwner::Shared("org.test.sync".to_string());
+        let app_pid = service.spawn("com.test.app", &mut sleeper(), None)?;
+        let shared_pid = service.spawn(&shared.registry_id(), &mut sleeper(), None)?;
+
+        assert_eq!(
+            service.owner_of(app_pid)?,
+            Some(ComponentOwner::App("com.test.app".to_string()))
+        );
+        assert_eq!(service.owner_of(shared_pid)?, Some(shared));
+        assert_eq!(service.owner_of(1)?, None);
+
+        service.stop_all()?;
+        Ok(())
+    }
+
+    #[test]
+    // The orphan is reaped at the end; a failed assertion ends the test run
+    #[allow(clippy::zombie_processes)]
+    fn test_cleanup_orphans_after_restart() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let socket = temp_dir.path().join("backend.sock");
+        std::fs::write(&socket, b"")?;
+
+        // First run spawns a backend and then "crashes" without stopping it
+        let crashed_run = ProcessService::new(temp_dir.path())?;
+        let pid = crashed_run.spawn("com.test.app", &mut sleeper(), Some(&socket))?;
+        let mut child = crashed_run.children.lock().unwrap().remove(&pid).unwrap();
+        drop(crashed_run);
+        assert!(process::is_running(pid));
+
+        // Next run finds and terminates the orphan
+        let service = ProcessService::new(temp_dir.path())?;
+        let report = service.cleanup_orphans()?;
+
+        assert_eq!(
+            report.entries,
+            vec![OrphanCleanup {
+                app_id: "com.test.app".to_string(),
+                pid,
+                outcome: OrphanOutcome::Terminated,
+                removed_socket: Some(socket.clone()),
+            }]
+        );
+        assert_eq!(report.terminated_count(), 1);
+        assert!(!process::is_running(pid));
+        assert!(!socket.exists());
+        assert!(service.list()?.is_empty());
+
+        child.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_leaves_recycled_pid_alone() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        // An unrelated process now holds a pid recorded by a previous run
+        let mut unrelated = sleeper().spawn()?;
+        let info = process::process_info(unrelated.id()).unwrap();
+        let stale = BackendProcess::new(
+            "com.test.app",
+            unrelated.id(),
+            format!("{}0", info.start_time),
+            info.command_line,
+            1_700_000_000,
+        );
+
+        let service = ProcessService::new(temp_dir.path())?;
+        service
+            .storage
+            .lock()
+            .unwrap()
+            .register_backend_process(&stale)?;
+
+        let report = service.cleanup_orphans()?;
+        assert_eq!(report.entries[0].outcome, OrphanOutcome::PidRecycled);
+        assert_eq!(report.terminated_count(), 0);
+        assert!(process::is_running(unrelated.id()));
+        assert!(service.list()?.is_empty());
+
+        unrelated.kill()?;
+        unrelated.wait()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_emits_crash_event() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = Arc::new(ProcessService::new(temp_dir.path())?);
+        let mut events = service.subscribe();
+
+        let pid = service.spawn("com.test.app", &mut sleeper(), None)?;
+        let shutdown = CancellationToken::new();
+        let watchdog = tokio::spawn(
+            service
+                .clone()
+                .run_watchdog(Duration::from_millis(20), shutdown.clone()),
+        );
+
+        // Kill the backend behind the service's back
+        Command::new("kill")
+            .args(["-KILL", &pid.to_string()])
+            .status()?;
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await??;
+        assert_eq!(event.app_id, "com.test.app");
+        assert_eq!(event.pid, pid);
+        assert_eq!(event.exit_code, None);
+        assert_eq!(event.signal, Some(libc::SIGKILL));
+        assert!(service.list()?.is_empty());
+        assert_eq!(
+            service.crash_reports("com.test.app")?[0].id,
+            event.report_id
+        );
+
+        // The watchdog stops on cancellation
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), watchdog).await??;
+        Ok(())
+    }
+
+    #[test]
+    fn test_crash_report_captures_stderr_tail_and_signal() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = ProcessService::new(temp_dir.path())?.with_stderr_tail_lines(10);
+
+        let pid = service.spawn_component(
+            "com.test.app",
+            ("ant://backend", "1.2.0"),
+            &mut crasher(80),
+            None,
+        )?;
+        let crashed = wait_for_crash(&service)?;
+        assert_eq!(crashed.len(), 1);
+        assert_eq!(crashed[0].signal, Some(libc::SIGSEGV));
+
+        let reports = service.crash_reports("com.test.app")?;
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.id, crashed[0].report_id);
+        assert_eq!(report.pid, pid);
+        assert_eq!(report.exit_code, None);
+        assert_eq!(report.signal_name(), Some("SIGSEGV"));
+        assert_eq!(report.component_id.as_deref(), Some("ant://backend"));
+        assert_eq!(report.component_version.as_deref(), Some("1.2.0"));
+        assert!(report.crashed_at >= report.started_at);
+
+        // Only the last lines are kept, oldest first
+        let expected: Vec<String> = (70..80).map(|i| format!("line {}", i)).collect();
+        assert_eq!(report.stderr_tail, expected);
+        assert!(!service.stderr_path(pid).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crash_reports_are_pruned_per_app() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = ProcessService::new(temp_dir.path())?;
+
+        for _ in 0..MAX_CRASH_REPORTS_PER_APP + 2 {
+            let mut command = Command::new("sh");
+            command.args(["-c", "exit 3"]);
+            service.spawn("com.test.app", &mut command, None)?;
+            assert_eq!(wait_for_crash(&service)?[0].exit_code, Some(3));
+        }
+
+        assert_eq!(
+            service.crash_reports("com.test.app")?.len(),
+            MAX_CRASH_REPORTS_PER_APP
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_crash_reports_are_pruned_across_apps() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let service = ProcessService::new(temp_dir.path())?.with_retention_policy(Policy::count(2));
+
+        for app_id in ["com.test.one", "com.test.two", "com.test.three"] {
+            let mut command = Command::new("sh");
+            command.args(["-c", "exit 3"]);
+            service.spawn(app_id, &mut command, None)?;
+            wait_for_crash(&service)?;
+        }
+
+        assert!(service.crash_reports("com.test.one")?.is_empty());
+        assert_eq!(service.crash_reports("com.test.three")?.len(), 1);
+        assert_eq!(service.footprint()?.items, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stderr_ring_keeps_newest_whole_lines() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("1.stderr");
+        let lines: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, lines)?;
+
+        let mut file = compact_ring(&path, 40)?;
+        writeln!(file, "after")?;
+
+        let tail = read_tail(&path, 100);
+        assert_eq!(tail.first().map(String::as_str), Some("line 95"));
+        assert_eq!(tail.last().map(String::as_str), Some("after"));
+        assert_eq!(read_tail(&path, 2), ["line 99", "after"]);
+        assert!(read_tail(&temp_dir.path().join("missing"), 5).is_empty());
+        Ok(())
+    }
+}
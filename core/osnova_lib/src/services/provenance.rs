@@ -0,0 +1,281 @@
+//! Component provenance tracking
+//!
+//! Answers "where did this component come from, and when" for
+//! supply-chain auditing.
+//!
+//! Handles:
+//! - Appending a record for every component fetched from its source
+//!   (written by [`ComponentDownloader`](crate::components::ComponentDownloader))
+//! - Looking up the download history of a content hash or an installed app
+//! - Pruning records older than a retention window, or beyond the store's
+//!   retention policy, always keeping the newest record of each component
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::models::application::ComponentRef;
+use crate::models::provenance::ProvenanceRecord;
+use crate::models::retention::{Footprint, Policy, RetainedItem, RetentionStore};
+use crate::services::retention::{self, RetainedStore};
+use crate::storage::sql::RetainedRows;
+use crate::storage::SqlStorage;
+use crate::time;
+
+/// Days provenance records are kept by default
+pub const DEFAULT_PROVENANCE_RETENTION_DAYS: u32 = 365;
+
+/// Seconds in one day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Provenance summary for one component of an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentProvenance {
+    /// Component id as listed in the manifest
+    pub component_id: String,
+    /// Component name
+    pub name: String,
+    /// Most recent download, if any was recorded
+    pub latest: Option<ProvenanceRecord>,
+    /// Number of recorded downloads
+    pub download_count: usize,
+}
+
+impl ComponentProvenance {
+    /// Summarize the recorded history of `component`
+    pub(crate) fn load(storage: &SqlStorage, component: &ComponentRef) -> Result<Self> {
+        let history = storage.list_provenance_by_component(component.id())?;
+
+        Ok(Self {
+            component_id: component.id().to_string(),
+            name: component.name().to_string(),
+            download_count: history.len(),
+            latest: history.into_iter().next(),
+        })
+    }
+}
+
+/// Component provenance service
+///
+/// Provides OpenRPC methods:
+/// - `provenance.forComponent` - Download history of a content hash
+/// - `provenance.forApp` - Download history of an installed app's components
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::ProvenanceService;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = ProvenanceService::new("/tmp/osnova")?;
+///
+/// for record in service.for_app("com.osnova.wallet")? {
+///     println!("{} from {} at {}", record.component_id, record.source_uri, record.downloaded_at);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProvenanceService {
+    storage: Mutex<SqlStorage>,
+    retention: Policy,
+}
+
+impl ProvenanceService {
+    /// Create a new provenance service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database
+    pub fn new<P: AsRef<Path>>(storage_path: P) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            retention: RetentionStore::Provenance.default_policy(),
+        })
+    }
+
+    /// Keep records under `policy`
+    pub fn with_retention_policy(mut self, policy: Policy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Append a download record
+    pub fn record(&self, record: &ProvenanceRecord) -> Result<()> {
+        self.storage.lock().unwrap().append_provenance(record)?;
+        retention::apply_on_write(self, &self.retention, time::now_unix())?;
+        Ok(())
+    }
+
+    /// Download history of a content hash, newest first
+    /// (OpenRPC: provenance.forComponent)
+    pub fn for_component(&self, component_hash: &str) -> Result<Vec<ProvenanceRecord>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_provenance_by_hash(component_hash)
+    }
+
+    /// Download history of every component of an installed app, newest
+    /// first (OpenRPC: provenance.forApp)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the app is not installed
+    pub fn for_app(&self, app_id: &str) -> Result<Vec<ProvenanceRecord>> {
+        let storage = self.storage.lock().unwrap();
+        let app = storage
+            .get_application(app_id)?
+            .with_context(|| format!("Application {} not found", app_id))?;
+
+        let mut records = Vec::new();
+        for component in app.components() {
+            records.extend(storage.list_provenance_by_component(component.id())?);
+        }
+        records.sort_by_key(|r| std::cmp::Reverse(r.downloaded_at));
+
+        Ok(records)
+    }
+
+    /// Prune records older than the retention window
+    ///
+    /// Returns the number of records removed.
+    pub fn prune(&self, retention_days: u32) -> Result<usize> {
+        self.prune_at(time::now_unix(), retention_days)
+    }
+
+    /// Prune relative to an explicit `now`
+    pub fn prune_at(&self, now: u64, retention_days: u32) -> Result<usize> {
+        let cutoff = now.saturating_sub(u64::from(retention_days) * SECONDS_PER_DAY);
+        self.storage.lock().unwrap().prune_provenance(cutoff)
+    }
+}
+
+impl RetainedStore for ProvenanceService {
+    fn store(&self) -> RetentionStore {
+        RetentionStore::Provenance
+    }
+
+    fn footprint(&self) -> Result<Footprint> {
+        self.storage
+            .lock()
+            .unwrap()
+            .retained_footprint(RetainedRows::Provenance)
+    }
+
+    fn items(&self) -> Result<Vec<RetainedItem>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_retained(RetainedRows::Provenance)
+    }
+
+    fn remove(&self, items: &[RetainedItem], _now: u64) -> Result<()> {
+        let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+        self.storage
+            .lock()
+            .unwrap()
+            .delete_retained(RetainedRows::Provenance, &ids)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::{ComponentKind, OsnovaApplication};
+    use crate::models::provenance::VerificationOutcome;
+    use tempfile::TempDir;
+
+    fn record(component_id: &str, hash: &str, downloaded_at: u64) -> ProvenanceRecord {
+        ProvenanceRecord {
+            component_id: component_id.to_string(),
+            component_version: "1.0.0".to_string(),
+            component_hash: hash.to_string(),
+            source_uri: component_id.to_string(),
+            origin: None,
+            downloaded_at,
+            verification: VerificationOutcome::HashVerified,
+            downloader_version: "0.1.0".to_string(),
+            content_check: None,
+            network: None,
+        }
+    }
+
+    fn install_app(temp: &TempDir) -> Result<()> {
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test",
+            "1.0.0",
+            "icon",
+            "Test app",
+            vec![
+                ComponentRef::new("ant://ui", "UI", ComponentKind::Frontend, "1.0.0")?,
+                ComponentRef::new("ant://backend", "Backend", ComponentKind::Backend, "1.0.0")?,
+            ],
+        )?;
+        SqlStorage::new(temp.path().join("osnova.db"))?.upsert_application(&app)
+    }
+
+    #[test]
+    fn test_for_app_aggregates_component_history() -> Result<()> {
+        let temp = TempDir::new()?;
+        install_app(&temp)?;
+        let service = ProvenanceService::new(temp.path())?;
+
+        service.record(&record("ant://ui", "h1", 100))?;
+        service.record(&record("ant://backend", "h2", 200))?;
+        service.record(&record("ant://ui", "h1", 300))?;
+        service.record(&record("ant://other", "h3", 400))?;
+
+        let history = service.for_app("com.test.app")?;
+        assert_eq!(
+            history.iter().map(|r| r.downloaded_at).collect::<Vec<_>>(),
+            vec![300, 200, 100]
+        );
+        assert_eq!(service.for_component("h1")?.len(), 2);
+        assert!(service.for_app("com.test.missing").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_respects_retention_window() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = ProvenanceService::new(temp.path())?;
+        let now = 100 * SECONDS_PER_DAY;
+
+        service.record(&record("ant://ui", "old", now - 40 * SECONDS_PER_DAY))?;
+        service.record(&record("ant://ui", "recent", now - 10 * SECONDS_PER_DAY))?;
+        service.record(&record("ant://ui", "latest", now))?;
+        service.record(&record("ant://backend", "only", now - 90 * SECONDS_PER_DAY))?;
+
+        assert_eq!(service.prune_at(now, 30)?, 1);
+        assert!(service.for_component("old")?.is_empty());
+        assert_eq!(service.for_component("recent")?.len(), 1);
+        // Sole record of a component survives however old it is
+        assert_eq!(service.for_component("only")?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_limit_keeps_newest_of_each_component() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = ProvenanceService::new(temp.path())?.with_retention_policy(Policy::count(2));
+
+        service.record(&record("ant://backend", "only", 100))?;
+        service.record(&record("ant://ui", "old", 200))?;
+        service.record(&record("ant://ui", "latest", 300))?;
+
+        // The oldest record is the backend's only one, so the UI's goes
+        assert!(service.for_component("old")?.is_empty());
+        assert_eq!(service.for_component("only")?.len(), 1);
+        assert_eq!(service.footprint()?.items, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,499 @@
+//! OS re-authentication for sensitive operations
+//!
+//! Revealing the seed phrase, approving a payment, granting a permission
+//! and deleting the identity are confirmed with a two-step challenge in the
+//! UI. The user can also require, per operation, that the OS authenticates
+//! them (fingerprint, face, or password, see [`crate::platform::auth`])
+//! shortly before.
+//!
+//! Handles:
+//! - Prompting through the [`OsAuthenticator`] for one operation and
+//!   issuing an [`AuthProof`] for it, publishing the reason and the
+//!   backend's instructions as [`AuthPrompt`] events for the UI to show
+//! - Checking the proof in the service performing the operation: it must
+//!   have been issued here, for that operation, within the maximum age, and
+//!   is used up by the check. The service's own record of when it was
+//!   issued counts, not the timestamp the caller sends back, so a webview
+//!   can neither forge nor refresh a proof
+//! - The per-operation policy, kept in the system configuration. Turning
+//!   an operation's requirement off needs a proof for that operation, so
+//!   the requirement cannot be dropped to get around it
+//!
+//! Issued proofs are held in memory only; a restart invalidates them.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::error::OsnovaError;
+use crate::platform::auth::{AuthBackend, AuthProof, OsAuthenticator};
+use crate::services::config::ConfigService;
+use crate::services::events::{AppEvent, EventBus};
+use crate::time::{self, SharedClock};
+
+/// Event name used when surfacing OS authentication prompts to frontends
+pub const AUTH_PROMPT_EVENT: &str = "auth-prompt";
+
+/// Seconds a proof stays usable after the user authenticated
+pub const DEFAULT_PROOF_MAX_AGE_SECS: u64 = 60;
+
+/// An operation that can require OS re-authentication
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum SensitiveOperation {
+    /// Showing the seed phrase
+    SeedReveal,
+    /// Allowing a payment prompt
+    PaymentApproval,
+    /// Allowing any other permission prompt
+    PermissionGrant,
+    /// Deleting the identity
+    IdentityDeletion,
+}
+
+impl SensitiveOperation {
+    /// Every operation, in display order
+    pub const ALL: [SensitiveOperation; 4] = [
+        SensitiveOperation::SeedReveal,
+        SensitiveOperation::PaymentApproval,
+        SensitiveOperation::PermissionGrant,
+        SensitiveOperation::IdentityDeletion,
+    ];
+
+    /// Reason shown in the OS prompt
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SensitiveOperation::SeedReveal => "Osnova wants to show your seed phrase",
+            SensitiveOperation::PaymentApproval => "Osnova wants to approve a payment",
+            SensitiveOperation::PermissionGrant => "Osnova wants to grant an app a permission",
+            SensitiveOperation::IdentityDeletion => "Osnova wants to delete your identity",
+        }
+    }
+}
+
+/// Re-authentication backend and policy, for settings and diagnostics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReauthStatus {
+    /// Backend in use; [`AuthBackend::Unavailable`] if the device has none
+    pub backend: AuthBackend,
+    /// Operations that require a proof
+    pub required: Vec<SensitiveOperation>,
+    /// Seconds a proof stays usable
+    pub max_age_secs: u64,
+}
+
+/// What the OS authentication in progress asks of the user
+///
+/// Published once with no message when the prompt opens, then once for
+/// every instruction the backend gives.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthPrompt {
+    /// Operation being authenticated
+    pub operation: SensitiveOperation,
+    /// Backend asking
+    pub backend: AuthBackend,
+    /// Why the user is asked
+    pub reason: String,
+    /// Instruction from the backend, such as which finger to scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A proof issued here and not yet used
+struct IssuedProof {
+    operation: SensitiveOperation,
+    issued_at: u64,
+}
+
+/// OS re-authentication service
+///
+/// Provides OpenRPC methods:
+/// - `auth.status` - Backend in use and operations requiring a proof
+/// - `auth.authenticate` - Authenticate with the OS for one operation
+/// - `auth.setRequired` - Require a proof for an operation, or stop
+///
+/// Services performing a sensitive operation hold the service through an
+/// `Arc` and call [`require`](Self::require) before acting.
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::platform::auth::default_authenticator;
+/// use osnova_lib::services::reauth::{ReauthService, SensitiveOperation};
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let reauth = ReauthService::new("/path/to/storage", default_authenticator());
+/// let proof = reauth.authenticate(SensitiveOperation::SeedReveal)?;
+/// reauth.require(SensitiveOperation::SeedReveal, Some(&proof))?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReauthService {
+    storage_path: PathBuf,
+    authenticator: Arc<dyn OsAuthenticator>,
+    clock: SharedClock,
+    max_age_secs: u64,
+    issued: Mutex<HashMap<String, IssuedProof>>,
+    events: Option<EventBus>,
+}
+
+impl ReauthService {
+    /// Create a re-authentication service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage, holding the policy
+    /// * `authenticator` - OS authenticator, usually
+    ///   [`default_authenticator`](crate::platform::auth::default_authenticator)
+    pub fn new<P: Into<PathBuf>>(storage_path: P, authenticator: Arc<dyn OsAuthenticator>) -> Self {
+        Self {
+            storage_path: storage_path.into(),
+            authenticator,
+            clock: time::default_clock(),
+            max_age_secs: DEFAULT_PROOF_MAX_AGE_SECS,
+            issued: Mutex::new(HashMap::new()),
+            events: None,
+        }
+    }
+
+    /// Publish `AuthPrompt` events while the OS prompt is shown
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for proof freshness
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Accept proofs up to `max_age_secs` old
+    pub fn with_max_age(mut self, max_age_secs: u64) -> Self {
+        self.max_age_secs = max_age_secs;
+        self
+    }
+
+    /// Backend in use; [`AuthBackend::Unavailable`] if the device has none
+    pub fn backend(&self) -> AuthBackend {
+        self.authenticator.backend()
+    }
+
+    /// Backend in use and operations requiring a proof (OpenRPC: auth.status)
+    pub fn status(&self) -> Result<ReauthStatus> {
+        Ok(ReauthStatus {
+            backend: self.backend(),
+            required: self.config()?.get_reauth_operations()?,
+            max_age_secs: self.max_age_secs,
+        })
+    }
+
+    /// Whether an operation requires a proof
+    pub fn is_required(&self, operation: SensitiveOperation) -> Result<bool> {
+        Ok(self.config()?.get_reauth_operations()?.contains(&operation))
+    }
+
+    /// Authenticate with the OS for one operation (OpenRPC: auth.authenticate)
+    ///
+    /// Blocks while the OS prompt is shown, publishing what it asks of the
+    /// user as [`AuthPrompt`] events. The proof can be used once, for
+    /// `operation`, until it is older than the maximum age.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the user failed or
+    /// cancelled, and an error if the device has no OS authentication
+    pub fn authenticate(&self, operation: SensitiveOperation) -> Result<AuthProof> {
+        let prompt = |message: Option<&str>| {
+            if let Some(events) = &self.events {
+                events.publish(AppEvent::AuthPrompt {
+                    prompt: AuthPrompt {
+                        operation,
+                        backend: self.authenticator.backend(),
+                        reason: operation.reason().to_string(),
+                        message: message.map(str::to_string),
+                    },
+                });
+            }
+        };
+        prompt(None);
+        let proof = self
+            .authenticator
+            .authenticate(operation.reason(), &|message| prompt(Some(message)))?;
+        let issued_at = self.clock.now_unix();
+
+        let mut issued = self.issued.lock().unwrap();
+        // Used or not, stale proofs are of no use to anyone
+        let max_age_secs = self.max_age_secs;
+        issued.retain(|_, p| issued_at.saturating_sub(p.issued_at) <= max_age_secs);
+        issued.insert(
+            proof.token.clone(),
+            IssuedProof {
+                operation,
+                issued_at,
+            },
+        );
+        Ok(proof)
+    }
+
+    /// Check the proof for an operation, if the policy requires one
+    ///
+    /// A proof passed for an operation that does not require one is left
+    /// unused.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the operation requires a
+    /// proof and `proof` is missing, was not issued here for `operation`,
+    /// was already used, or has expired
+    pub fn require(&self, operation: SensitiveOperation, proof: Option<&AuthProof>) -> Result<()> {
+        if !self.is_required(operation)? {
+            return Ok(());
+        }
+        self.redeem(operation, proof)
+    }
+
+    /// Require a proof for an operation, or stop requiring one
+    /// (OpenRPC: auth.setRequired)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if turning a requirement on when the device has no
+    /// OS authentication, and [`OsnovaError::PermissionDenied`] if turning
+    /// one off without a valid proof for that operation
+    pub fn set_required(
+        &self,
+        operation: SensitiveOperation,
+        required: bool,
+        proof: Option<&AuthProof>,
+    ) -> Result<()> {
+        let config = self.config()?;
+        let mut operations = config.get_reauth_operations()?;
+        if operations.contains(&operation) == required {
+            return Ok(());
+        }
+
+        let available = self.backend() != AuthBackend::Unavailable;
+        if required {
+            if !available {
+                return Err(OsnovaError::Other(
+                    "OS authentication is not available on this device".to_string(),
+                )
+                .into());
+            }
+            operations.push(operation);
+        } else {
+            // Without a backend no proof can exist, and nothing is enforced
+            if available {
+                self.redeem(operation, proof)?;
+            }
+            operations.retain(|o| *o != operation);
+        }
+        config.set_reauth_operations(&operations)
+    }
+
+    /// Use up a proof for an operation
+    fn redeem(&self, operation: SensitiveOperation, proof: Option<&AuthProof>) -> Result<()> {
+        let denied = |reason: &str| -> anyhow::Error {
+            OsnovaError::PermissionDenied(format!(
+                "{:?} requires OS authentication: {}",
+                operation, reason
+            ))
+            .into()
+        };
+
+        let Some(proof) = proof else {
+            return Err(denied("no proof was given"));
+        };
+        // Single use, whether or not it matches
+        let Some(issued) = self.issued.lock().unwrap().remove(&proof.token) else {
+            return Err(denied("the proof is unknown or was already used"));
+        };
+        if issued.operation != operation {
+            return Err(denied("the proof is for another operation"));
+        }
+        if self.clock.now_unix().saturating_sub(issued.issued_at) > self.max_age_secs {
+            return Err(denied("the proof has expired"));
+        }
+        Ok(())
+    }
+
+    fn config(&self) -> Result<ConfigService> {
+        ConfigService::new(&self.storage_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::auth::Unavailable;
+    use crate::time::MockClock;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Authenticator whose user passes or fails on demand
+    #[derive(Default)]
+    struct MockAuthenticator {
+        refuse: AtomicBool,
+    }
+
+    impl OsAuthenticator for MockAuthenticator {
+        fn backend(&self) -> AuthBackend {
+            AuthBackend::Fprintd
+        }
+
+        fn authenticate(
+            &self,
+            _reason: &str,
+            on_prompt: &dyn Fn(&str),
+        ) -> crate::error::Result<AuthProof> {
+            on_prompt("Place your finger on the reader");
+            if self.refuse.load(Ordering::SeqCst) {
+                return Err(OsnovaError::PermissionDenied("no match".to_string()));
+            }
+            Ok(AuthProof::new(AuthBackend::Fprintd, 0))
+        }
+    }
+
+    fn setup(temp: &TempDir) -> (ReauthService, Arc<MockAuthenticator>, Arc<MockClock>) {
+        let authenticator = Arc::new(MockAuthenticator::default());
+        let clock = Arc::new(MockClock::new(1_000));
+        let service =
+            ReauthService::new(temp.path(), authenticator.clone()).with_clock(clock.clone());
+        (service, authenticator, clock)
+    }
+
+    fn is_denied(result: Result<()>) -> bool {
+        matches!(
+            result.map_err(|e| e.downcast::<OsnovaError>()),
+            Err(Ok(OsnovaError::PermissionDenied(_)))
+        )
+    }
+
+    #[test]
+    fn test_required_operation_needs_a_matching_proof() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, authenticator, _clock) = setup(&temp);
+        let seed = SensitiveOperation::SeedReveal;
+
+        // Not required yet: no proof needed
+        service.require(seed, None)?;
+
+        service.set_required(seed, true, None)?;
+        assert!(is_denied(service.require(seed, None)));
+
+        let proof = service.authenticate(seed)?;
+        service.require(seed, Some(&proof))?;
+        // Used up
+        assert!(is_denied(service.require(seed, Some(&proof))));
+
+        // A proof for another operation, or one never issued, does not count
+        let other = service.authenticate(SensitiveOperation::IdentityDeletion)?;
+        assert!(is_denied(service.require(seed, Some(&other))));
+        let forged = AuthProof::new(AuthBackend::Fprintd, 1_000);
+        assert!(is_denied(service.require(seed, Some(&forged))));
+
+        authenticator.refuse.store(true, Ordering::SeqCst);
+        assert!(service.authenticate(seed).is_err());
+
+        // Other operations are unaffected
+        service.require(SensitiveOperation::PaymentApproval, None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_expires() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _authenticator, clock) = setup(&temp);
+        let payment = SensitiveOperation::PaymentApproval;
+        service.set_required(payment, true, None)?;
+
+        let proof = service.authenticate(payment)?;
+        clock.advance(Duration::from_secs(DEFAULT_PROOF_MAX_AGE_SECS));
+        service.require(payment, Some(&proof))?;
+
+        let proof = service.authenticate(payment)?;
+        clock.advance(Duration::from_secs(DEFAULT_PROOF_MAX_AGE_SECS + 1));
+        assert!(is_denied(service.require(payment, Some(&proof))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_is_configured_per_operation() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _authenticator, _clock) = setup(&temp);
+        let grant = SensitiveOperation::PermissionGrant;
+        service.set_required(grant, true, None)?;
+        service.set_required(SensitiveOperation::IdentityDeletion, true, None)?;
+
+        let status = service.status()?;
+        assert_eq!(status.backend, AuthBackend::Fprintd);
+        assert_eq!(
+            status.required,
+            vec![grant, SensitiveOperation::IdentityDeletion]
+        );
+
+        // Persisted for the next service
+        let (reopened, _, _) = setup(&temp);
+        assert!(reopened.is_required(grant)?);
+
+        // Dropping a requirement takes a proof for it
+        assert!(is_denied(service.set_required(grant, false, None)));
+        let proof = service.authenticate(grant)?;
+        service.set_required(grant, false, Some(&proof))?;
+        assert_eq!(
+            service.status()?.required,
+            vec![SensitiveOperation::IdentityDeletion]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompts_are_published() -> Result<()> {
+        let temp = TempDir::new()?;
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let service = ReauthService::new(temp.path(), Arc::new(MockAuthenticator::default()))
+            .with_events(events);
+
+        let operation = SensitiveOperation::SeedReveal;
+        service.authenticate(operation)?;
+        let mut messages = Vec::new();
+        while let Ok(AppEvent::AuthPrompt { prompt }) = received.try_recv() {
+            assert_eq!(prompt.operation, operation);
+            assert_eq!(prompt.backend, AuthBackend::Fprintd);
+            assert_eq!(prompt.reason, operation.reason());
+            messages.push(prompt.message);
+        }
+        assert_eq!(
+            messages,
+            vec![None, Some("Place your finger on the reader".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unavailable_backend_is_reported() -> Result<()> {
+        let temp = TempDir::new()?;
+        let service = ReauthService::new(temp.path(), Arc::new(Unavailable));
+
+        let status = service.status()?;
+        assert_eq!(status.backend, AuthBackend::Unavailable);
+        assert!(status.required.is_empty());
+        assert!(service
+            .set_required(SensitiveOperation::SeedReveal, true, None)
+            .is_err());
+        assert!(service
+            .authenticate(SensitiveOperation::SeedReveal)
+            .is_err());
+        Ok(())
+    }
+}
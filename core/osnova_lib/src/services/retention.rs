@@ -0,0 +1,466 @@
+//! Retention of stores that grow with use
+//!
+//! Notifications, crash reports, the audit log, key usage statistics and
+//! provenance records each implement [`RetainedStore`] and are kept under a
+//! [`Policy`] configured per store in the system config.
+//!
+//! Handles:
+//! - Applying a policy to a store: removing expired items, then the oldest
+//!   in the store's removal order until it is within its count and size
+//!   limits ([`apply`])
+//! - A cheap check stores run on every write, pruning only when the write
+//!   took them over their count or size limit ([`apply_on_write`])
+//! - The thorough pass of scheduled maintenance over every store, including
+//!   expiry, whose reports are kept as the last run
+//! - Reporting each store's footprint for the settings screen
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::models::retention::{
+    Footprint, Policy, PruneReport, RetainedItem, RetentionStore, StoreFootprint,
+};
+use crate::services::config::ConfigService;
+use crate::time::{self, SharedClock};
+
+/// A store kept under a retention policy
+pub trait RetainedStore: Send + Sync {
+    /// Which store this is
+    fn store(&self) -> RetentionStore;
+
+    /// What the store holds
+    fn footprint(&self) -> Result<Footprint>;
+
+    /// Items a policy may remove, in the order they should go
+    fn items(&self) -> Result<Vec<RetainedItem>>;
+
+    /// Remove items chosen from [`Self::items`]
+    fn remove(&self, items: &[RetainedItem], now: u64) -> Result<()>;
+
+    /// Items a removal adds back, such as a truncation marker
+    fn removal_overhead(&self) -> u64 {
+        0
+    }
+}
+
+/// Apply `policy` to `store` at `now`
+///
+/// Expired items are removed wherever they fall in the removal order; the
+/// rest go in order while the store is over its count or size limit.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read or pruned
+pub fn apply(store: &dyn RetainedStore, policy: &Policy, now: u64) -> Result<PruneReport> {
+    let footprint = store.footprint()?;
+    let cutoff = policy.max_age.map(|age| now.saturating_sub(age.as_secs()));
+    let items = store.items()?;
+    let expired = |item: &RetainedItem| cutoff.is_some_and(|cutoff| item.timestamp < cutoff);
+
+    let mut report = PruneReport {
+        store: store.store(),
+        removed: 0,
+        removed_bytes: 0,
+        remaining: footprint,
+    };
+    if !policy.over_size(&footprint) && !items.iter().any(expired) {
+        return Ok(report);
+    }
+
+    let mut remaining = Footprint {
+        items: footprint.items + store.removal_overhead(),
+        ..footprint
+    };
+    let mut removed = Vec::new();
+    for item in items {
+        if expired(&item) || policy.over_size(&remaining) {
+            remaining.items -= 1;
+            remaining.bytes = remaining.bytes.saturating_sub(item.bytes);
+            report.removed_bytes += item.bytes;
+            removed.push(item);
+        }
+    }
+
+    if !removed.is_empty() {
+        store.remove(&removed, now)?;
+        report.removed = removed.len() as u64;
+        report.remaining = store.footprint()?;
+    }
+    Ok(report)
+}
+
+/// Apply `policy` after a write, if the write took `store` over its count
+/// or size limit
+///
+/// Expiry is left to scheduled maintenance, so a write within the limits
+/// costs one count query.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read or pruned
+pub fn apply_on_write(
+    store: &dyn RetainedStore,
+    policy: &Policy,
+    now: u64,
+) -> Result<Option<PruneReport>> {
+    if !policy.over_size(&store.footprint()?) {
+        return Ok(None);
+    }
+    apply(store, policy, now).map(Some)
+}
+
+/// Reports of one scheduled retention pass
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRun {
+    /// Unix timestamp of the pass
+    pub ran_at: u64,
+    /// One report per store
+    pub reports: Vec<PruneReport>,
+}
+
+impl RetentionRun {
+    /// Items removed across all stores
+    pub fn removed(&self) -> u64 {
+        self.reports.iter().map(|report| report.removed).sum()
+    }
+}
+
+/// Retention service
+///
+/// Provides OpenRPC methods:
+/// - `retention.footprints` - What each store holds and its policy
+/// - `retention.setPolicy` - Configure a store's policy
+/// - `retention.run` - Apply every store's policy now
+/// - `retention.lastRun` - Reports of the last pass
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use osnova_lib::services::{ConfigService, NotificationService, RetentionService};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let notifications = Arc::new(NotificationService::new("/tmp/osnova", "user-123")?);
+/// let service =
+///     RetentionService::new(ConfigService::new("/tmp/osnova")?).with_store(notifications);
+///
+/// for store in service.footprints()? {
+///     println!("{}: {} items, {} bytes", store.label, store.footprint.items, store.footprint.bytes);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct RetentionService {
+    config: Mutex<ConfigService>,
+    stores: Vec<Arc<dyn RetainedStore>>,
+    clock: SharedClock,
+    last_run: Mutex<Option<RetentionRun>>,
+}
+
+impl RetentionService {
+    /// Create a retention service reading policies from `config`
+    pub fn new(config: ConfigService) -> Self {
+        Self {
+            config: Mutex::new(config),
+            stores: Vec::new(),
+            clock: time::default_clock(),
+            last_run: Mutex::new(None),
+        }
+    }
+
+    /// Keep `store` under its configured policy
+    pub fn with_store(mut self, store: Arc<dyn RetainedStore>) -> Self {
+        self.stores.push(store);
+        self
+    }
+
+    /// Use a specific clock for expiry
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Apply every store's configured policy (OpenRPC: retention.run)
+    ///
+    /// A store that fails is logged and left out of the reports.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policies cannot be read
+    pub fn run(&self) -> Result<RetentionRun> {
+        let now = self.clock.now_unix();
+        let mut reports = Vec::with_capacity(self.stores.len());
+        for store in &self.stores {
+            let policy = self
+                .config
+                .lock()
+                .unwrap()
+                .get_retention_policy(store.store())?;
+            match apply(store.as_ref(), &policy, now) {
+                Ok(report) => reports.push(report),
+                Err(e) => crate::log!(
+                    Warn,
+                    "Retention of {} failed: {:#}",
+                    store.store().label(),
+                    e
+                ),
+            }
+        }
+
+        let run = RetentionRun {
+            ran_at: now,
+            reports,
+        };
+        *self.last_run.lock().unwrap() = Some(run.clone());
+        Ok(run)
+    }
+
+    /// Reports of the last pass since startup (OpenRPC: retention.lastRun)
+    pub fn last_run(&self) -> Option<RetentionRun> {
+        self.last_run.lock().unwrap().clone()
+    }
+
+    /// What each store holds, with its policy (OpenRPC: retention.footprints)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a store cannot be measured
+    pub fn footprints(&self) -> Result<Vec<StoreFootprint>> {
+        self.stores
+            .iter()
+            .map(|store| {
+                let kind = store.store();
+                Ok(StoreFootprint {
+                    store: kind,
+                    label: kind.label().to_string(),
+                    footprint: store.footprint()?,
+                    policy: self.config.lock().unwrap().get_retention_policy(kind)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Configure a store's policy; `None` restores its default
+    /// (OpenRPC: retention.setPolicy)
+    ///
+    /// Takes effect on the next pass, and on writes once the store is
+    /// reopened.
+    pub fn set_policy(&self, store: RetentionStore, policy: Option<Policy>) -> Result<()> {
+        self.config
+            .lock()
+            .unwrap()
+            .set_retention_policy(store, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OsnovaContext;
+    use std::time::Duration;
+
+    /// In-memory store removing items by ID
+    struct TestStore {
+        items: Mutex<Vec<RetainedItem>>,
+        overhead: u64,
+    }
+
+    impl TestStore {
+        /// Items with IDs 0.., written at the given timestamps, 10 bytes each
+        fn new(timestamps: &[u64]) -> Self {
+            let items = timestamps
+                .iter()
+                .enumerate()
+                .map(|(id, &timestamp)| RetainedItem {
+                    id: id as i64,
+                    timestamp,
+                    bytes: 10,
+                })
+                .collect();
+            Self {
+                items: Mutex::new(items),
+                overhead: 0,
+            }
+        }
+
+        fn ids(&self) -> Vec<i64> {
+            self.items.lock().unwrap().iter().map(|i| i.id).collect()
+        }
+    }
+
+    impl RetainedStore for TestStore {
+        fn store(&self) -> RetentionStore {
+            RetentionStore::Notifications
+        }
+
+        fn footprint(&self) -> Result<Footprint> {
+            let items = self.items.lock().unwrap();
+            Ok(Footprint {
+                items: items.len() as u64,
+                bytes: items.iter().map(|i| i.bytes).sum(),
+            })
+        }
+
+        fn items(&self) -> Result<Vec<RetainedItem>> {
+            Ok(self.items.lock().unwrap().clone())
+        }
+
+        fn remove(&self, removed: &[RetainedItem], now: u64) -> Result<()> {
+            let mut items = self.items.lock().unwrap();
+            items.retain(|item| !removed.contains(item));
+            for _ in 0..self.overhead {
+                items.push(RetainedItem {
+                    id: 100,
+                    timestamp: now,
+                    bytes: 0,
+                });
+            }
+            Ok(())
+        }
+
+        fn removal_overhead(&self) -> u64 {
+            self.overhead
+        }
+    }
+
+    #[test]
+    fn test_max_age() -> Result<()> {
+        let store = TestStore::new(&[100, 50, 200, 300]);
+        let policy = Policy {
+            max_age: Some(Duration::from_secs(150)),
+            ..Policy::default()
+        };
+
+        // Expired items go wherever they are in the removal order
+        let report = apply(&store, &policy, 320)?;
+        assert_eq!(store.ids(), vec![2, 3]);
+        assert_eq!(report.removed, 2);
+        assert_eq!(report.removed_bytes, 20);
+        assert_eq!(
+            report.remaining,
+            Footprint {
+                items: 2,
+                bytes: 20
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_count() -> Result<()> {
+        let store = TestStore::new(&[1, 2, 3, 4, 5]);
+
+        let report = apply(&store, &Policy::count(3), 10)?;
+        assert_eq!(store.ids(), vec![2, 3, 4]);
+        assert_eq!(report.removed, 2);
+
+        // Within the limit nothing changes
+        assert_eq!(apply(&store, &Policy::count(3), 10)?.removed, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_bytes() -> Result<()> {
+        let store = TestStore::new(&[1, 2, 3, 4, 5]);
+        let policy = Policy {
+            max_bytes: Some(25),
+            ..Policy::default()
+        };
+
+        let report = apply(&store, &policy, 10)?;
+        assert_eq!(store.ids(), vec![3, 4]);
+        assert_eq!(report.removed_bytes, 30);
+        assert_eq!(report.remaining.bytes, 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_limits() -> Result<()> {
+        let store = TestStore::new(&[1, 2, 30, 40, 50, 60]);
+        let policy = Policy {
+            max_age: Some(Duration::from_secs(50)),
+            max_count: Some(3),
+            max_bytes: Some(25),
+        };
+
+        // Age takes two, count a third, size a fourth
+        apply(&store, &policy, 55)?;
+        assert_eq!(store.ids(), vec![4, 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overhead_leaves_room_for_marker() -> Result<()> {
+        let mut store = TestStore::new(&[1, 2, 3, 4, 5]);
+        store.overhead = 1;
+
+        let report = apply(&store, &Policy::count(3), 10)?;
+        assert_eq!(store.ids(), vec![3, 4, 100]);
+        assert_eq!(report.removed, 3);
+        assert_eq!(report.remaining.items, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_write_checks_limits_not_age() -> Result<()> {
+        let store = TestStore::new(&[1, 2, 3]);
+        let policy = Policy {
+            max_age: Some(Duration::from_secs(1)),
+            max_count: Some(3),
+            max_bytes: None,
+        };
+
+        // Everything is expired, but only scheduled passes expire items
+        assert_eq!(apply_on_write(&store, &policy, 100)?, None);
+        assert_eq!(store.ids(), vec![0, 1, 2]);
+
+        store.items.lock().unwrap().push(RetainedItem {
+            id: 3,
+            timestamp: 100,
+            bytes: 10,
+        });
+        let report = apply_on_write(&store, &policy, 100)?.unwrap();
+        assert_eq!(report.removed, 3);
+        assert_eq!(store.ids(), vec![3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_uses_configured_policies() -> Result<()> {
+        let context = OsnovaContext::new_ephemeral()?;
+        let store = Arc::new(TestStore::new(&[1, 2, 3, 4]));
+        let service = RetentionService::new(ConfigService::from_context(&context)?)
+            .with_store(store.clone())
+            .with_clock(Arc::new(crate::time::MockClock::new(10)));
+        assert_eq!(service.last_run(), None);
+
+        service.set_policy(RetentionStore::Notifications, Some(Policy::count(1)))?;
+        let footprints = service.footprints()?;
+        assert_eq!(footprints[0].label, "Notifications");
+        assert_eq!(
+            footprints[0].footprint,
+            Footprint {
+                items: 4,
+                bytes: 40
+            }
+        );
+        assert_eq!(footprints[0].policy, Policy::count(1));
+
+        let run = service.run()?;
+        assert_eq!(run.ran_at, 10);
+        assert_eq!(run.removed(), 3);
+        assert_eq!(service.last_run(), Some(run));
+        assert_eq!(store.ids(), vec![3]);
+
+        Ok(())
+    }
+}
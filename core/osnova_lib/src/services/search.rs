@@ -0,0 +1,1259 @@
+//! Search service
+//!
+//! Unified search across installed apps, the launcher catalog, and the app
+//! settings a user has configured.
+//!
+//! Handles:
+//! - An in-memory inverted index, persisted to SQL storage for warm starts
+//! - TF-IDF ranking with per-field weights and prefix matching
+//! - Case folding and basic diacritic stripping, so "cafe" finds "Café"
+//! - Incremental updates from [`AppEvent`]s instead of full rebuilds
+//! - Substring search over the values of settings an app declares
+//!   searchable, through an encrypted blinded trigram index
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::crypto::blind_index::{BlindIndex, BlindIndexKey, MIN_QUERY_CHARS};
+use crate::manifest::ManifestSchema;
+use crate::models::application::OsnovaApplication;
+use crate::models::identity::{KeyPurpose, RootIdentity};
+use crate::services::events::AppEvent;
+use crate::services::ConfigService;
+use crate::storage::SqlStorage;
+
+/// Score multiplier when the whole query equals a document's name
+const EXACT_NAME_BOOST: f64 = 2.0;
+
+/// Score multiplier for a query token that only prefixes an indexed term
+const PREFIX_MATCH_FACTOR: f64 = 0.5;
+
+/// Score of a setting matched by its value rather than its key
+const VALUE_MATCH_SCORE: f64 = 1.0;
+
+/// Component the value index key is derived for, per user
+const VALUE_INDEX_COMPONENT: &str = "osnova-search-index";
+
+/// Default cap on the size of an app's value index, in trigram postings
+pub const DEFAULT_VALUE_INDEX_CAP: usize = 4096;
+
+/// Which documents a query searches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    /// Everything
+    #[default]
+    All,
+    /// Installed applications
+    Installed,
+    /// Launcher catalog entries
+    Catalog,
+    /// Configured app settings
+    Settings,
+}
+
+impl SearchScope {
+    /// Whether documents of `kind` are searched in this scope
+    pub fn includes(self, kind: DocumentKind) -> bool {
+        match self {
+            SearchScope::All => true,
+            SearchScope::Installed => kind == DocumentKind::Installed,
+            SearchScope::Catalog => kind == DocumentKind::Catalog,
+            SearchScope::Settings => kind == DocumentKind::Setting,
+        }
+    }
+}
+
+/// Kind of indexed document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    /// An installed application
+    Installed,
+    /// A launcher catalog entry
+    Catalog,
+    /// A configured setting key of an installed application
+    Setting,
+}
+
+impl DocumentKind {
+    /// Stable identifier used in storage
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DocumentKind::Installed => "installed",
+            DocumentKind::Catalog => "catalog",
+            DocumentKind::Setting => "setting",
+        }
+    }
+}
+
+/// Searchable field of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    /// App name, or setting key
+    Name,
+    /// Publisher
+    Publisher,
+    /// Description, or owning app name for settings
+    Description,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 3] = [
+        SearchField::Name,
+        SearchField::Publisher,
+        SearchField::Description,
+    ];
+
+    /// Relative weight of a match in this field
+    fn weight(self) -> f64 {
+        match self {
+            SearchField::Name => 3.0,
+            SearchField::Publisher => 2.0,
+            SearchField::Description => 1.0,
+        }
+    }
+}
+
+/// A document in the search index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchDocument {
+    /// Document kind
+    pub kind: DocumentKind,
+    /// Application the document belongs to
+    pub app_id: String,
+    /// Setting key, for setting documents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// User the setting belongs to, for setting documents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// App name, or setting key
+    pub name: String,
+    /// Publisher, if known
+    pub publisher: Option<String>,
+    /// Description, or owning app name for settings
+    pub description: String,
+}
+
+impl SearchDocument {
+    /// Build a document for an installed application
+    pub fn installed(app: &OsnovaApplication) -> Self {
+        Self {
+            kind: DocumentKind::Installed,
+            app_id: app.id().to_string(),
+            key: None,
+            user_id: None,
+            name: app.name().to_string(),
+            publisher: app.publisher().map(str::to_string),
+            description: app.description().to_string(),
+        }
+    }
+
+    /// Build a document for a launcher catalog entry
+    pub fn catalog(manifest: &ManifestSchema) -> Self {
+        Self {
+            kind: DocumentKind::Catalog,
+            app_id: manifest.id.clone(),
+            key: None,
+            user_id: None,
+            name: manifest.name.clone(),
+            publisher: manifest.publisher.clone(),
+            description: manifest.description.clone(),
+        }
+    }
+
+    /// Build a document for a configured setting key
+    pub fn setting(app: &OsnovaApplication, user_id: &str, key: &str) -> Self {
+        Self {
+            kind: DocumentKind::Setting,
+            app_id: app.id().to_string(),
+            key: Some(key.to_string()),
+            user_id: Some(user_id.to_string()),
+            name: key.to_string(),
+            publisher: None,
+            description: app.name().to_string(),
+        }
+    }
+
+    /// Unique document identifier
+    pub fn id(&self) -> String {
+        match self.kind {
+            DocumentKind::Setting => format!(
+                "setting:{}:{}:{}",
+                self.user_id.as_deref().unwrap_or_default(),
+                self.app_id,
+                self.key.as_deref().unwrap_or_default()
+            ),
+            kind => format!("{}:{}", kind.as_str(), self.app_id),
+        }
+    }
+
+    /// Text of a field
+    pub fn field(&self, field: SearchField) -> &str {
+        match field {
+            SearchField::Name => &self.name,
+            SearchField::Publisher => self.publisher.as_deref().unwrap_or_default(),
+            SearchField::Description => &self.description,
+        }
+    }
+}
+
+/// Matched character range within a result field
+///
+/// Offsets count Unicode scalar values (chars) in the original field text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Highlight {
+    /// Field containing the match
+    pub field: SearchField,
+    /// Start offset (inclusive)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+}
+
+/// A ranked search result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// The matched document
+    #[serde(flatten)]
+    pub document: SearchDocument,
+    /// Relevance score (higher is better)
+    pub score: f64,
+    /// Matched ranges for highlighting
+    pub highlights: Vec<Highlight>,
+}
+
+/// Indexed document with per-field term frequencies
+struct IndexedDocument {
+    document: SearchDocument,
+    terms: HashMap<String, [u32; 3]>,
+}
+
+/// In-memory inverted index
+#[derive(Default)]
+struct SearchIndex {
+    documents: HashMap<String, IndexedDocument>,
+    postings: HashMap<String, HashSet<String>>,
+}
+
+impl SearchIndex {
+    fn insert(&mut self, document: SearchDocument) {
+        let id = document.id();
+        self.remove(&id);
+
+        let mut terms: HashMap<String, [u32; 3]> = HashMap::new();
+        for (i, field) in SearchField::ALL.iter().enumerate() {
+            for token in tokenize(document.field(*field)) {
+                terms.entry(token.term).or_default()[i] += 1;
+            }
+        }
+
+        for term in terms.keys() {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(id.clone());
+        }
+        self.documents
+            .insert(id, IndexedDocument { document, terms });
+    }
+
+    fn remove(&mut self, id: &str) {
+        let Some(indexed) = self.documents.remove(id) else {
+            return;
+        };
+
+        for term in indexed.terms.keys() {
+            if let Some(ids) = self.postings.get_mut(term) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+    }
+
+    fn remove_where(&mut self, kind: DocumentKind, app_id: Option<&str>) {
+        let ids: Vec<String> = self
+            .documents
+            .iter()
+            .filter(|(_, d)| {
+                d.document.kind == kind && app_id.is_none_or(|app_id| d.document.app_id == app_id)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in ids {
+            self.remove(&id);
+        }
+    }
+
+    fn query(&self, text: &str, scope: SearchScope, limit: usize) -> Vec<SearchResult> {
+        let query_terms: Vec<String> = tokenize(text).into_iter().map(|t| t.term).collect();
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        // Best contribution of each query term to each document
+        let total = self.documents.len() as f64;
+        let mut contributions: HashMap<&str, Vec<f64>> = HashMap::new();
+
+        for (q, query_term) in query_terms.iter().enumerate() {
+            for (term, ids) in &self.postings {
+                if !term.starts_with(query_term.as_str()) {
+                    continue;
+                }
+
+                let factor = if term == query_term {
+                    1.0
+                } else {
+                    PREFIX_MATCH_FACTOR
+                };
+                let idf = (1.0 + total / ids.len() as f64).ln();
+
+                for id in ids {
+                    let indexed = &self.documents[id];
+                    if !scope.includes(indexed.document.kind) {
+                        continue;
+                    }
+
+                    let frequencies = indexed.terms[term];
+                    let weighted: f64 = SearchField::ALL
+                        .iter()
+                        .zip(frequencies)
+                        .filter(|(_, tf)| *tf > 0)
+                        .map(|(field, tf)| field.weight() * (1.0 + f64::from(tf).ln()))
+                        .sum();
+
+                    let scores = contributions
+                        .entry(id.as_str())
+                        .or_insert_with(|| vec![0.0; query_terms.len()]);
+                    scores[q] = scores[q].max(factor * idf * weighted);
+                }
+            }
+        }
+
+        let folded_query = fold(text.trim());
+        let mut results: Vec<SearchResult> = contributions
+            .into_iter()
+            .filter(|(_, scores)| scores.iter().all(|s| *s > 0.0))
+            .map(|(id, scores)| {
+                let document = self.documents[id].document.clone();
+                let mut score: f64 = scores.iter().sum();
+                if fold(document.name.trim()) == folded_query {
+                    score *= EXACT_NAME_BOOST;
+                }
+                let highlights = highlights(&document, &query_terms);
+
+                SearchResult {
+                    document,
+                    score,
+                    highlights,
+                }
+            })
+            .collect();
+
+        rank(&mut results, limit);
+        results
+    }
+}
+
+/// Order results by score, then name, keeping the best `limit`
+fn rank(results: &mut Vec<SearchResult>, limit: usize) {
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.document.name.cmp(&b.document.name))
+    });
+    results.truncate(limit);
+}
+
+/// Search service
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::search::{SearchScope, SearchService};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = SearchService::new("/tmp/storage", "user-123")?;
+/// service.index_all()?;
+///
+/// for result in service.query("wallet", SearchScope::All, 10) {
+///     println!("{} ({:.2})", result.document.name, result.score);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SearchService {
+    sql_storage: Mutex<SqlStorage>,
+    config: Mutex<ConfigService>,
+    user_id: String,
+    index: Mutex<SearchIndex>,
+    value_index: Option<BlindIndexKey>,
+    value_index_cap: usize,
+}
+
+impl SearchService {
+    /// Create a new search service
+    ///
+    /// Loads the persisted index, so queries work before [`Self::index_all`]
+    /// has run.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose settings are searched
+    pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let config = ConfigService::new(&storage_path)?;
+
+        let mut index = SearchIndex::default();
+        for data in sql_storage.list_search_documents()? {
+            let document: SearchDocument =
+                serde_json::from_str(&data).context("Failed to parse search document")?;
+
+            // Settings of other users sharing this storage are not searchable
+            if document.kind == DocumentKind::Setting
+                && document.user_id.as_deref() != Some(user_id)
+            {
+                continue;
+            }
+            index.insert(document);
+        }
+
+        Ok(Self {
+            sql_storage: Mutex::new(sql_storage),
+            config: Mutex::new(config),
+            user_id: user_id.to_string(),
+            index: Mutex::new(index),
+            value_index: None,
+            value_index_cap: DEFAULT_VALUE_INDEX_CAP,
+        })
+    }
+
+    /// Also search the values of settings apps declare searchable
+    ///
+    /// Values are indexed as blinded trigrams sealed under a key derived
+    /// from the user's identity, and only unsealed while a query runs.
+    /// Queries need at least three characters to match a value.
+    pub fn with_value_index(mut self, identity: &RootIdentity) -> Result<Self> {
+        let component = format!("{}:{}", VALUE_INDEX_COMPONENT, self.user_id);
+        let key = identity.derive_component_key(&component, 0, KeyPurpose::SearchIndex)?;
+        self.value_index = Some(BlindIndexKey::new(&key));
+        Ok(self)
+    }
+
+    /// Cap the size of each app's value index
+    ///
+    /// Values that would grow an index past the cap are left unindexed.
+    pub fn with_value_index_cap(mut self, cap: usize) -> Self {
+        self.value_index_cap = cap;
+        self
+    }
+
+    /// Number of indexed documents
+    pub fn document_count(&self) -> usize {
+        self.index.lock().unwrap().documents.len()
+    }
+
+    /// Rebuild the index of installed apps and settings from storage
+    ///
+    /// Catalog entries are kept; they are replaced by [`Self::index_catalog`].
+    /// Returns the number of documents indexed.
+    pub fn index_all(&self) -> Result<usize> {
+        self.remove_documents(DocumentKind::Installed, None)?;
+        self.remove_documents(DocumentKind::Setting, None)?;
+
+        let apps = self.sql_storage.lock().unwrap().list_applications()?;
+        let mut count = 0;
+        for app in &apps {
+            count += self.index_app(app)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Replace the indexed launcher catalog
+    pub fn index_catalog(&self, entries: &[ManifestSchema]) -> Result<()> {
+        self.remove_documents(DocumentKind::Catalog, None)?;
+
+        for entry in entries {
+            self.add_document(SearchDocument::catalog(entry))?;
+        }
+
+        Ok(())
+    }
+
+    /// Query the index
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Query text; every word must match a word prefix, or the
+    ///   whole text must occur in a searchable setting value
+    /// * `scope` - Documents to search
+    /// * `limit` - Maximum number of results
+    pub fn query(&self, text: &str, scope: SearchScope, limit: usize) -> Vec<SearchResult> {
+        let value_index = self
+            .value_index
+            .as_ref()
+            .filter(|_| scope.includes(DocumentKind::Setting));
+        let Some(key) = value_index else {
+            return self.index.lock().unwrap().query(text, scope, limit);
+        };
+
+        let mut results = self.index.lock().unwrap().query(text, scope, usize::MAX);
+        for document in self.value_matches(key, text) {
+            if results.iter().any(|result| result.document == document) {
+                continue;
+            }
+            results.push(SearchResult {
+                document,
+                score: VALUE_MATCH_SCORE,
+                highlights: Vec::new(),
+            });
+        }
+        rank(&mut results, limit);
+        results
+    }
+
+    /// Apply an incremental update for a service event
+    pub fn handle_event(&self, event: &AppEvent) -> Result<()> {
+        match event {
+            AppEvent::AppInstalled { app_id, .. } | AppEvent::AppRestored { app_id, .. } => {
+                self.reindex_app(app_id)?;
+            }
+            AppEvent::AppMetadataChanged { refresh } => {
+                self.reindex_app(&refresh.app_id)?;
+            }
+            AppEvent::AppUninstalled { app_id, .. } => {
+                self.remove_documents(DocumentKind::Installed, Some(app_id))?;
+                self.remove_documents(DocumentKind::Setting, Some(app_id))?;
+            }
+            AppEvent::ConfigChanged { app_id, user_id } if *user_id == self.user_id => {
+                self.remove_documents(DocumentKind::Setting, Some(app_id))?;
+                let app = self.sql_storage.lock().unwrap().get_application(app_id)?;
+                if let Some(app) = app {
+                    self.index_settings(&app)?;
+                }
+            }
+            AppEvent::ConfigChanged { .. }
+            | AppEvent::AppBadgeChanged { .. }
+            | AppEvent::NotificationPosted { .. }
+            | AppEvent::NotificationsRead { .. }
+            | AppEvent::PermissionRequested { .. }
+            | AppEvent::PermissionResolved { .. }
+            | AppEvent::BackendReadinessChanged { .. }
+            | AppEvent::BackendHealthChanged { .. }
+            | AppEvent::UpdateAvailable { .. }
+            | AppEvent::SyncStateChanged { .. }
+            | AppEvent::ContextUnlocked { .. }
+            | AppEvent::KeyUsageAnomaly { .. }
+            | AppEvent::KeysMigrated { .. }
+            | AppEvent::FeatureFlagsChanged { .. }
+            | AppEvent::AuthPrompt { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Keep the index updated from an event stream until it closes or
+    /// `shutdown` is cancelled
+    ///
+    /// Falls back to a full rebuild if events were missed.
+    pub async fn run_indexer(
+        self: Arc<Self>,
+        mut events: broadcast::Receiver<AppEvent>,
+        shutdown: CancellationToken,
+    ) {
+        loop {
+            let received = tokio::select! {
+                received = events.recv() => received,
+                _ = shutdown.cancelled() => break,
+            };
+            // Failed updates are repaired by the next full rebuild
+            match received {
+                Ok(event) => {
+                    let _ = self.handle_event(&event);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let _ = self.index_all();
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Re-read one installed app and its settings into the index
+    fn reindex_app(&self, app_id: &str) -> Result<()> {
+        self.remove_documents(DocumentKind::Installed, Some(app_id))?;
+        self.remove_documents(DocumentKind::Setting, Some(app_id))?;
+
+        let app = self.sql_storage.lock().unwrap().get_application(app_id)?;
+        if let Some(app) = app {
+            self.index_app(&app)?;
+        }
+
+        Ok(())
+    }
+
+    /// Index an installed app and its settings, returning the document count
+    fn index_app(&self, app: &OsnovaApplication) -> Result<usize> {
+        self.add_document(SearchDocument::installed(app))?;
+        Ok(1 + self.index_settings(app)?)
+    }
+
+    /// Index the setting keys the user has configured for an app
+    fn index_settings(&self, app: &OsnovaApplication) -> Result<usize> {
+        let config = self
+            .config
+            .lock()
+            .unwrap()
+            .get_app_config(app.id(), &self.user_id)?;
+
+        for key in config.settings().keys() {
+            self.add_document(SearchDocument::setting(app, &self.user_id, key))?;
+        }
+        self.index_values(app, config.settings())?;
+
+        Ok(config.settings().len())
+    }
+
+    /// Rebuild the value index of the settings an app declares searchable
+    fn index_values(
+        &self,
+        app: &OsnovaApplication,
+        settings: &HashMap<String, Value>,
+    ) -> Result<()> {
+        let Some(key) = &self.value_index else {
+            return Ok(());
+        };
+
+        let mut values = BlindIndex::default();
+        for setting in app.searchable_config_keys() {
+            let Some(value) = settings.get(setting) else {
+                continue;
+            };
+            for text in string_values(value) {
+                values.insert(key, setting, &value_text(text), self.value_index_cap);
+            }
+        }
+
+        let storage = self.sql_storage.lock().unwrap();
+        if values.is_empty() {
+            storage.delete_search_value_indexes(&self.user_id, Some(app.id()))?;
+        } else {
+            storage.upsert_search_value_index(&self.user_id, app.id(), &key.seal(&values)?)?;
+        }
+        Ok(())
+    }
+
+    /// Settings whose searchable values contain the query text
+    fn value_matches(&self, key: &BlindIndexKey, text: &str) -> Vec<SearchDocument> {
+        let query = value_text(text);
+        if query.chars().count() < MIN_QUERY_CHARS {
+            return Vec::new();
+        }
+        let sealed = self
+            .sql_storage
+            .lock()
+            .unwrap()
+            .list_search_value_indexes(&self.user_id);
+        let Ok(sealed) = sealed else {
+            return Vec::new();
+        };
+
+        let index = self.index.lock().unwrap();
+        let mut documents = Vec::new();
+        for (app_id, data) in sealed {
+            // Unsealed for this query only; an index sealed under another
+            // key is replaced on the next rebuild
+            let Ok(values) = key.open(&data) else {
+                continue;
+            };
+            for setting in values.search(key, &query) {
+                let id = format!("setting:{}:{}:{}", self.user_id, app_id, setting);
+                if let Some(indexed) = index.documents.get(&id) {
+                    documents.push(indexed.document.clone());
+                }
+            }
+        }
+        documents
+    }
+
+    fn add_document(&self, document: SearchDocument) -> Result<()> {
+        let data = serde_json::to_string(&document).context("Failed to serialize document")?;
+        self.sql_storage.lock().unwrap().upsert_search_document(
+            &document.id(),
+            &self.storage_kind(document.kind),
+            &document.app_id,
+            &data,
+        )?;
+        self.index.lock().unwrap().insert(document);
+        Ok(())
+    }
+
+    fn remove_documents(&self, kind: DocumentKind, app_id: Option<&str>) -> Result<()> {
+        let storage = self.sql_storage.lock().unwrap();
+        storage.delete_search_documents(&self.storage_kind(kind), app_id)?;
+        // Value indexes are derived from the settings they belong to
+        if kind == DocumentKind::Setting {
+            storage.delete_search_value_indexes(&self.user_id, app_id)?;
+        }
+        drop(storage);
+        self.index.lock().unwrap().remove_where(kind, app_id);
+        Ok(())
+    }
+
+    /// Storage kind, scoping settings to the current user
+    fn storage_kind(&self, kind: DocumentKind) -> String {
+        match kind {
+            DocumentKind::Setting => format!("{}:{}", kind.as_str(), self.user_id),
+            kind => kind.as_str().to_string(),
+        }
+    }
+}
+
+/// A word in a text
+struct Token {
+    /// Folded term
+    term: String,
+    /// Char offset of the first character
+    start: usize,
+}
+
+/// Split text into folded words, tracking char offsets
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+
+    for (i, c) in text.chars().enumerate() {
+        let folded = fold_char(c);
+        if folded.is_alphanumeric() {
+            if current.is_empty() {
+                start = i;
+            }
+            current.push(folded);
+        } else if !current.is_empty() {
+            tokens.push(Token {
+                term: std::mem::take(&mut current),
+                start,
+            });
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(Token {
+            term: current,
+            start,
+        });
+    }
+
+    tokens
+}
+
+/// String values within a setting value, including nested ones
+fn string_values(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items.iter().flat_map(string_values).collect(),
+        Value::Object(fields) => fields.values().flat_map(string_values).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fold a setting value or query for the value index, collapsing whitespace
+fn value_text(text: &str) -> String {
+    fold(text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Find the ranges of a document matched by query terms
+fn highlights(document: &SearchDocument, query_terms: &[String]) -> Vec<Highlight> {
+    let mut highlights = Vec::new();
+
+    for field in SearchField::ALL {
+        for token in tokenize(document.field(field)) {
+            let matched = query_terms
+                .iter()
+                .filter(|q| token.term.starts_with(q.as_str()))
+                .map(|q| q.chars().count())
+                .max();
+
+            if let Some(len) = matched {
+                highlights.push(Highlight {
+                    field,
+                    start: token.start,
+                    end: token.start + len,
+                });
+            }
+        }
+    }
+
+    highlights
+}
+
+/// Fold a string for comparison
+fn fold(text: &str) -> String {
+    text.chars().map(fold_char).collect()
+}
+
+/// Fold one character: strip diacritics and lowercase
+///
+/// Always maps one char to one char so highlight offsets stay valid.
+fn fold_char(c: char) -> char {
+    let base = strip_diacritic(c);
+    base.to_lowercase().next().unwrap_or(base)
+}
+
+/// Map accented Latin letters to their lowercase base letter
+fn strip_diacritic(c: char) -> char {
+    match c {
+        'À'..='Å' | 'à'..='å' | '\u{100}'..='\u{105}' => 'a',
+        'Ç' | 'ç' | '\u{106}'..='\u{10D}' => 'c',
+        'Ð' | '\u{10E}'..='\u{111}' => 'd',
+        'È'..='Ë' | 'è'..='ë' | '\u{112}'..='\u{11B}' => 'e',
+        '\u{11C}'..='\u{123}' => 'g',
+        '\u{124}'..='\u{127}' => 'h',
+        'Ì'..='Ï' | 'ì'..='ï' | '\u{128}'..='\u{131}' => 'i',
+        '\u{134}'..='\u{135}' => 'j',
+        '\u{136}'..='\u{137}' => 'k',
+        '\u{139}'..='\u{142}' => 'l',
+        'Ñ' | 'ñ' | '\u{143}'..='\u{148}' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | '\u{14C}'..='\u{151}' => 'o',
+        '\u{154}'..='\u{159}' => 'r',
+        '\u{15A}'..='\u{161}' => 's',
+        '\u{162}'..='\u{167}' => 't',
+        'Ù'..='Ü' | 'ù'..='ü' | '\u{168}'..='\u{173}' => 'u',
+        '\u{174}'..='\u{175}' => 'w',
+        'Ý' | 'ý' | 'ÿ' | '\u{176}'..='\u{178}' => 'y',
+        '\u{179}'..='\u{17E}' => 'z',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::{ComponentKind, ComponentRef};
+    use crate::services::events::EventBus;
+    use crate::services::metadata::{MetadataField, MetadataRefresh};
+    use crate::services::AppsService;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn create_app(id: &str, name: &str, description: &str) -> OsnovaApplication {
+        OsnovaApplication::new(
+            id,
+            name,
+            "1.0.0",
+            "ant://icon",
+            description,
+            vec![ComponentRef::new(
+                "ant://frontend",
+                "Frontend",
+                ComponentKind::Frontend,
+                "1.0.0",
+            )
+            .unwrap()],
+        )
+        .unwrap()
+    }
+
+    fn install(temp_dir: &TempDir, app: &OsnovaApplication) -> Result<()> {
+        SqlStorage::new(temp_dir.path().join("osnova.db"))?.upsert_application(app)
+    }
+
+    fn names(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.document.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_exact_name_beats_description_mention() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        install(
+            &temp_dir,
+            &create_app("com.test.notes", "Notes", "Keep notes next to your wallet"),
+        )?;
+        install(
+            &temp_dir,
+            &create_app("com.test.wallet", "Wallet", "Manage your funds"),
+        )?;
+
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        service.index_all()?;
+
+        let results = service.query("wallet", SearchScope::All, 10);
+        assert_eq!(names(&results), vec!["Wallet", "Notes"]);
+        assert_eq!(
+            results[0].highlights,
+            vec![Highlight {
+                field: SearchField::Name,
+                start: 0,
+                end: 6,
+            }]
+        );
+        assert_eq!(
+            results[1].highlights,
+            vec![Highlight {
+                field: SearchField::Description,
+                start: 24,
+                end: 30,
+            }]
+        );
+
+        // Prefixes match while typing; every query word must match
+        assert_eq!(
+            names(&service.query("wal", SearchScope::All, 10))[0],
+            "Wallet"
+        );
+        assert_eq!(
+            names(&service.query("wallet funds", SearchScope::All, 10)),
+            vec!["Wallet"]
+        );
+        assert!(service.query("   ", SearchScope::All, 10).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_update_after_install() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        service.index_all()?;
+        assert!(service.query("chess", SearchScope::All, 10).is_empty());
+
+        install(
+            &temp_dir,
+            &create_app("com.test.chess", "Chess", "Play chess"),
+        )?;
+        events.publish(AppEvent::AppInstalled {
+            app_id: "com.test.chess".to_string(),
+            generation: 1,
+        });
+        service.handle_event(&receiver.try_recv()?)?;
+        assert_eq!(
+            names(&service.query("chess", SearchScope::All, 10)),
+            vec!["Chess"]
+        );
+
+        // Uninstalling publishes an event that drops the app
+        let apps = AppsService::new(temp_dir.path())?.with_events(events.clone());
+        apps.uninstall("com.test.chess", None)?;
+        service.handle_event(&receiver.try_recv()?)?;
+        assert!(service.query("chess", SearchScope::All, 10).is_empty());
+
+        // Restoring brings it back
+        apps.restore("com.test.chess")?;
+        service.handle_event(&receiver.try_recv()?)?;
+        assert_eq!(service.query("chess", SearchScope::All, 10).len(), 1);
+
+        // A refreshed listing replaces the indexed name
+        let renamed = create_app("com.test.chess", "Chess Master", "Play chess");
+        SqlStorage::new(temp_dir.path().join("osnova.db"))?.update_application(&renamed)?;
+        events.publish(AppEvent::AppMetadataChanged {
+            refresh: MetadataRefresh {
+                app_id: "com.test.chess".to_string(),
+                changed: vec![MetadataField::Name],
+                refreshed_at: 0,
+                generation: Some(4),
+            },
+        });
+        service.handle_event(&receiver.try_recv()?)?;
+        assert_eq!(
+            names(&service.query("chess", SearchScope::All, 10)),
+            vec!["Chess Master"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_indexed_from_config_events() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+
+        let app = create_app("com.test.editor", "Editor", "Edit text");
+        install(&temp_dir, &app)?;
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        service.index_all()?;
+
+        let config = ConfigService::new(temp_dir.path())?.with_events(events);
+        let mut settings = HashMap::new();
+        settings.insert("fontSize".to_string(), json!(14));
+        config.set_app_config("com.test.editor", "user-123", settings)?;
+        service.handle_event(&receiver.try_recv()?)?;
+
+        let results = service.query("fontsize", SearchScope::Settings, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.key.as_deref(), Some("fontSize"));
+        assert_eq!(results[0].document.description, "Editor");
+
+        // Another user's settings are not indexed
+        let mut other = HashMap::new();
+        other.insert("tabWidth".to_string(), json!(4));
+        config.set_app_config("com.test.editor", "user-456", other)?;
+        service.handle_event(&receiver.try_recv()?)?;
+        assert!(service.query("tabwidth", SearchScope::All, 10).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scope_filtering() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let app = create_app("com.test.music", "Music", "Play music");
+        install(&temp_dir, &app)?;
+
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        service.index_all()?;
+
+        let catalog_entry: ManifestSchema = serde_json::from_value(json!({
+            "id": "com.test.radio",
+            "name": "Radio",
+            "version": "1.0.0",
+            "iconUri": "ant://icon",
+            "description": "Stream music",
+            "publisher": "Music Co",
+            "components": []
+        }))?;
+        service.index_catalog(&[catalog_entry])?;
+
+        assert_eq!(
+            names(&service.query("music", SearchScope::All, 10)),
+            vec!["Music", "Radio"]
+        );
+        assert_eq!(
+            names(&service.query("music", SearchScope::Installed, 10)),
+            vec!["Music"]
+        );
+        assert_eq!(
+            names(&service.query("music", SearchScope::Catalog, 10)),
+            vec!["Radio"]
+        );
+        assert!(service.query("music", SearchScope::Settings, 10).is_empty());
+        assert_eq!(service.query("music", SearchScope::All, 1).len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unicode_insensitive_matching() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        install(
+            &temp_dir,
+            &create_app("com.test.cafe", "Café Crème", "Ŝtrange Ñame"),
+        )?;
+
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        service.index_all()?;
+
+        for query in ["cafe", "CAFÉ", "creme", "CRÈME", "strange", "name"] {
+            assert_eq!(
+                names(&service.query(query, SearchScope::All, 10)),
+                vec!["Café Crème"],
+                "query {:?}",
+                query
+            );
+        }
+
+        let results = service.query("creme", SearchScope::All, 10);
+        assert_eq!(
+            results[0].highlights,
+            vec![Highlight {
+                field: SearchField::Name,
+                start: 5,
+                end: 10,
+            }]
+        );
+
+        Ok(())
+    }
+
+    /// An app declaring `bookmarks` searchable, with a value search service
+    fn value_search(temp_dir: &TempDir, cap: usize) -> Result<(SearchService, ConfigService)> {
+        let app = create_app("com.test.reader", "Reader", "Read articles")
+            .with_searchable_config_keys(["bookmarks"]);
+        install(temp_dir, &app)?;
+        install(temp_dir, &create_app("com.test.notes", "Notes", "Notes"))?;
+
+        let config = ConfigService::new(temp_dir.path())?;
+        let mut settings = HashMap::new();
+        settings.insert(
+            "bookmarks".to_string(),
+            json!([{ "title": "Grandma's Recipes" }, "Crème brûlée"]),
+        );
+        settings.insert("diary".to_string(), json!("secret plans"));
+        config.set_app_config("com.test.reader", "user-123", settings)?;
+        let mut undeclared = HashMap::new();
+        undeclared.insert("bookmarks".to_string(), json!("grandma's garden"));
+        config.set_app_config("com.test.notes", "user-123", undeclared)?;
+
+        let identity = RootIdentity::generate()?;
+        let service = SearchService::new(temp_dir.path(), "user-123")?
+            .with_value_index(&identity)?
+            .with_value_index_cap(cap);
+        service.index_all()?;
+        Ok((service, config))
+    }
+
+    fn setting_matches(service: &SearchService, query: &str) -> Vec<(String, String)> {
+        service
+            .query(query, SearchScope::Settings, 10)
+            .into_iter()
+            .map(|r| (r.document.app_id, r.document.name))
+            .collect()
+    }
+
+    #[test]
+    fn test_value_search_is_opt_in_per_key() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (service, _config) = value_search(&temp_dir, DEFAULT_VALUE_INDEX_CAP)?;
+
+        let reader_bookmarks = vec![("com.test.reader".to_string(), "bookmarks".to_string())];
+        assert_eq!(setting_matches(&service, "grandma"), reader_bookmarks);
+        // Undeclared keys, and undeclared apps, are not searched by value
+        assert!(setting_matches(&service, "secret").is_empty());
+        assert!(setting_matches(&service, "garden").is_empty());
+        // Other scopes do not search setting values
+        assert!(service
+            .query("grandma", SearchScope::Installed, 10)
+            .is_empty());
+
+        // Without a value index only setting keys are searchable
+        let plain = SearchService::new(temp_dir.path(), "user-123")?;
+        assert!(setting_matches(&plain, "grandma").is_empty());
+        assert_eq!(setting_matches(&plain, "bookmarks").len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_substring_queries() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (service, _config) = value_search(&temp_dir, DEFAULT_VALUE_INDEX_CAP)?;
+
+        let reader_bookmarks = vec![("com.test.reader".to_string(), "bookmarks".to_string())];
+        for query in ["andma", "S RECIP", "ma's  rec", "creme", "BRÛLÉE", "ème br"] {
+            assert_eq!(
+                setting_matches(&service, query),
+                reader_bookmarks,
+                "query {:?}",
+                query
+            );
+        }
+        for query in ["ma", "recipez", "grandpa", "brulee creme"] {
+            assert!(
+                setting_matches(&service, query).is_empty(),
+                "query {:?}",
+                query
+            );
+        }
+
+        // A key match and a value match yield one result
+        let results = service.query("bookmarks", SearchScope::All, 10);
+        assert_eq!(results.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_index_holds_no_plaintext() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (service, _config) = value_search(&temp_dir, DEFAULT_VALUE_INDEX_CAP)?;
+        drop(service);
+
+        let storage = SqlStorage::new(temp_dir.path().join("osnova.db"))?;
+        let sealed = storage.list_search_value_indexes("user-123")?;
+        assert_eq!(sealed.len(), 1);
+        assert_eq!(sealed[0].0, "com.test.reader");
+        drop(storage);
+
+        let mut stored = sealed[0].1.clone();
+        for entry in std::fs::read_dir(temp_dir.path())? {
+            let path = entry?.path();
+            if path.is_file() {
+                stored.extend(std::fs::read(path)?);
+            }
+        }
+        let stored = String::from_utf8_lossy(&stored).to_lowercase();
+        for plaintext in ["grandma", "recipes", "brulee", "brûlée", "secret plans"] {
+            assert!(!stored.contains(plaintext), "{:?} stored", plaintext);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_index_invalidated_on_config_change() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (service, config) = value_search(&temp_dir, DEFAULT_VALUE_INDEX_CAP)?;
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let config = config.with_events(events);
+        assert_eq!(setting_matches(&service, "recipes").len(), 1);
+
+        let mut settings = HashMap::new();
+        settings.insert("bookmarks".to_string(), json!(["Sourdough starter"]));
+        config.set_app_config("com.test.reader", "user-123", settings)?;
+        service.handle_event(&receiver.try_recv()?)?;
+        assert!(setting_matches(&service, "recipes").is_empty());
+        assert_eq!(setting_matches(&service, "dough").len(), 1);
+
+        // Uninstalling drops the app's value index
+        service.handle_event(&AppEvent::AppUninstalled {
+            app_id: "com.test.reader".to_string(),
+            generation: 2,
+        })?;
+        assert!(setting_matches(&service, "dough").is_empty());
+        let storage = SqlStorage::new(temp_dir.path().join("osnova.db"))?;
+        assert!(storage.list_search_value_indexes("user-123")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_index_size_cap() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        // "grandma's recipes" has 15 trigrams, "creme brulee" 10
+        let (service, _config) = value_search(&temp_dir, 20)?;
+
+        assert_eq!(setting_matches(&service, "recipes").len(), 1);
+        assert!(setting_matches(&service, "brulee").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_warm_start_from_persisted_index() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        install(
+            &temp_dir,
+            &create_app("com.test.maps", "Maps", "Find places"),
+        )?;
+        SearchService::new(temp_dir.path(), "user-123")?.index_all()?;
+
+        let service = SearchService::new(temp_dir.path(), "user-123")?;
+        assert_eq!(service.document_count(), 1);
+        assert_eq!(
+            names(&service.query("maps", SearchScope::All, 10)),
+            vec!["Maps"]
+        );
+
+        Ok(())
+    }
+}
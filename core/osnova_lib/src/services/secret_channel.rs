@@ -0,0 +1,243 @@
+//! One-time delivery of secrets to backend components at launch
+//!
+//! Settings an app declares `secret` (see
+//! [`SettingField::secret`](crate::models::settings_schema::SettingField::secret))
+//! must reach its backend before it serves requests, but reading them over
+//! RPC after boot races the first request, and argv or the environment
+//! show up in process listings.
+//!
+//! Instead the supervisor opens a [`SecretChannel`]: a Unix socket under a
+//! random name in a directory only the user can enter. The backend finds
+//! its path in [`SECRETS_SOCKET_ENV`] (the only thing that goes into the
+//! environment), connects, reads the secrets as one JSON object and closes
+//! the connection. The channel serves exactly one connection and removes
+//! the socket, so a second read fails; if nothing collects the secrets
+//! within the timeout the channel is withdrawn the same way. The secrets
+//! never touch the disk.
+//!
+//! Backends written in Rust can use [`receive`]; others connect to the
+//! socket and read until the end of the stream. Channels exist only on
+//! Unix; elsewhere an app with secret settings cannot be launched.
+
+#[cfg(unix)]
+use anyhow::{Context, Result};
+#[cfg(unix)]
+use bip39::rand::{thread_rng, RngCore};
+#[cfg(unix)]
+use serde_json::Value;
+#[cfg(unix)]
+use std::collections::BTreeMap;
+#[cfg(unix)]
+use std::io::{ErrorKind, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::thread::JoinHandle;
+use std::time::Duration;
+#[cfg(unix)]
+use std::time::Instant;
+
+/// Environment variable holding the socket a backend reads its secrets from
+pub const SECRETS_SOCKET_ENV: &str = "OSNOVA_SECRETS_SOCKET";
+
+/// How long a backend has to collect its secrets after it is spawned
+pub const DEFAULT_SECRET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval between checks for the backend's connection
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What became of secrets offered on a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretDelivery {
+    /// The backend read the secrets and closed the connection
+    Delivered,
+    /// Nothing read the secrets to the end within the timeout; the channel
+    /// was withdrawn
+    TimedOut,
+}
+
+/// A socket serving one read of a backend's secrets
+///
+/// Dropping the channel withdraws it.
+#[cfg(unix)]
+pub struct SecretChannel {
+    path: PathBuf,
+    listener: UnixListener,
+    payload: Vec<u8>,
+}
+
+#[cfg(unix)]
+impl SecretChannel {
+    /// Offer `secrets` on a new socket in `dir`
+    ///
+    /// `dir` is created if needed and restricted to the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be created
+    pub fn open(dir: &Path, secrets: &BTreeMap<String, Value>) -> Result<Self> {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .context("Failed to create the secrets socket directory")?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .context("Failed to restrict the secrets socket directory")?;
+
+        let mut name = [0u8; 16];
+        thread_rng().fill_bytes(&mut name);
+        let path = dir.join(format!("{}.sock", hex::encode(name)));
+        let listener = UnixListener::bind(&path).context("Failed to open the secrets socket")?;
+        let channel = Self {
+            path,
+            listener,
+            payload: serde_json::to_vec(secrets)?,
+        };
+        std::fs::set_permissions(&channel.path, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to restrict the secrets socket")?;
+        channel.listener.set_nonblocking(true)?;
+        Ok(channel)
+    }
+
+    /// Socket path to hand the backend in [`SECRETS_SOCKET_ENV`]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait for one connection, hand it the secrets, then withdraw the
+    /// channel
+    ///
+    /// The whole exchange, including the backend closing its end, must
+    /// finish within `timeout`.
+    pub fn serve(self, timeout: Duration) -> SecretDelivery {
+        let deadline = Instant::now() + timeout;
+        let stream = loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                Err(_) => return SecretDelivery::TimedOut,
+            }
+        };
+        // No second connection can get in while the first is served
+        let _ = std::fs::remove_file(&self.path);
+
+        match self.hand_over(stream, deadline) {
+            Ok(()) => SecretDelivery::Delivered,
+            Err(_) => SecretDelivery::TimedOut,
+        }
+    }
+
+    /// [`serve`](Self::serve) on a thread of its own
+    pub fn spawn(self, timeout: Duration) -> JoinHandle<SecretDelivery> {
+        std::thread::spawn(move || self.serve(timeout))
+    }
+
+    /// Write the secrets and wait for the backend to close the connection
+    fn hand_over(&self, mut stream: UnixStream, deadline: Instant) -> std::io::Result<()> {
+        let remaining = || {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .max(Duration::from_millis(1))
+        };
+        stream.set_nonblocking(false)?;
+        stream.set_write_timeout(Some(remaining()))?;
+        stream.write_all(&self.payload)?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+
+        stream.set_read_timeout(Some(remaining()))?;
+        let mut rest = [0u8; 64];
+        while stream.read(&mut rest)? > 0 {}
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SecretChannel {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Read the secrets offered on a channel, as a backend does at start
+///
+/// # Errors
+///
+/// Returns an error if the channel is gone (already read or withdrawn) or
+/// sends something other than a JSON object
+#[cfg(unix)]
+pub fn receive<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, Value>> {
+    let mut stream = UnixStream::connect(path).context("Secrets are no longer available")?;
+    let mut payload = Vec::new();
+    stream
+        .read_to_end(&mut payload)
+        .context("Failed to read secrets")?;
+    drop(stream);
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn secrets() -> BTreeMap<String, Value> {
+        BTreeMap::from([("apiToken".to_string(), json!("t0ken"))])
+    }
+
+    #[test]
+    fn test_secrets_are_delivered_once() -> Result<()> {
+        let temp = TempDir::new()?;
+        let channel = SecretChannel::open(&temp.path().join("secrets"), &secrets())?;
+        let path = channel.path().to_path_buf();
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let server = channel.spawn(Duration::from_secs(5));
+        assert_eq!(receive(&path)?, secrets());
+        assert_eq!(server.join().unwrap(), SecretDelivery::Delivered);
+
+        // The socket is gone, so nobody can read the secrets again
+        assert!(!path.exists());
+        assert!(receive(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncollected_secrets_are_withdrawn() -> Result<()> {
+        let temp = TempDir::new()?;
+        let channel = SecretChannel::open(temp.path(), &secrets())?;
+        let path = channel.path().to_path_buf();
+
+        let started = Instant::now();
+        assert_eq!(
+            channel.serve(Duration::from_millis(50)),
+            SecretDelivery::TimedOut
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(receive(&path).is_err());
+
+        // A reader that connects but never closes its end times out too
+        let channel = SecretChannel::open(temp.path(), &secrets())?;
+        let stalled = UnixStream::connect(channel.path())?;
+        assert_eq!(
+            channel.serve(Duration::from_millis(50)),
+            SecretDelivery::TimedOut
+        );
+        drop(stalled);
+
+        // Dropping an unserved channel withdraws it as well
+        let channel = SecretChannel::open(temp.path(), &secrets())?;
+        let path = channel.path().to_path_buf();
+        drop(channel);
+        assert!(receive(&path).is_err());
+        Ok(())
+    }
+}
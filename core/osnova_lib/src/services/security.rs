@@ -0,0 +1,1035 @@
+//! At-rest encryption audit
+//!
+//! [`EncryptionAudit::run`] inspects the storage layout and reports, per data
+//! category, whether the data is encrypted, the scheme in use, where the key
+//! comes from, and any findings worth surfacing on the security settings
+//! screen.
+//!
+//! Components that used their derived keys anomalously often in the last
+//! [`KEY_USAGE_AUDIT_WINDOW_SECS`] are reported too (see
+//! [`key_usage`](crate::services::key_usage)), as are stored files and
+//! blobs without a data class (see [`crate::storage::classification`]).
+//!
+//! Files are tried against their candidate keys with
+//! [`diagnose_decrypt_failure`], so a file that opens with no known key is
+//! reported with why it failed (a wrong key, truncation, or another
+//! container format).
+//!
+//! The report only ever describes keys by their source; it never contains
+//! key material, ciphertext, or decrypted data.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::encryption::DecryptFailure;
+use crate::models::identity::RootIdentity;
+use crate::models::key_usage::KeyUsageAnomaly;
+use crate::platform::auth::AuthBackend;
+use crate::services::config::ConfigService;
+use crate::services::identity::IdentityService;
+use crate::storage::classification;
+use crate::storage::{
+    diagnose_decrypt_failure, DecryptLocation, FileStorage, KeyCandidates, SqlStorage,
+};
+use crate::time::{self, SharedClock};
+
+/// Identity file, relative to the storage root
+const IDENTITY_PATH: &str = "identity/root.enc";
+
+/// Key cocoon file, relative to the storage root
+const COCOON_PATH: &str = "identity/keys.cocoon";
+
+/// System configuration file, relative to the storage root
+const SYSTEM_CONFIG_PATH: &str = "config/system.json";
+
+/// How far back key usage anomalies are reported, in seconds (7 days)
+pub const KEY_USAGE_AUDIT_WINDOW_SECS: u64 = 7 * 86_400;
+
+/// A category of data kept at rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DataCategory {
+    /// The root identity (seed material)
+    Identity,
+    /// The key cocoon holding derived keys
+    KeyCocoon,
+    /// Per-app configurations
+    AppConfigs,
+    /// Encrypted blobs stored by components
+    EncryptedBlobs,
+    /// System-wide configuration
+    SystemConfig,
+    /// Downloaded component cache
+    CacheComponents,
+    /// Audit and diagnostic logs
+    Logs,
+    /// Stored data without a data class, whatever it is
+    Unclassified,
+}
+
+/// Encryption scheme protecting a category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EncryptionScheme {
+    /// Stored in plaintext
+    None,
+    /// Cocoon container (ChaCha20-Poly1305), the current on-disk format
+    Cocoon,
+    /// Present but could not be opened with any known key
+    Unknown,
+}
+
+/// Where the key protecting a category comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    /// Key held by the platform keystore
+    PlatformKeystore,
+    /// The built-in development platform key
+    DevelopmentKey,
+    /// Derived from the unlocked root identity
+    IdentityDerived,
+    /// Derived with a superseded derivation from the root identity
+    LegacyDerived,
+    /// Derived from static inputs only (no secret)
+    StaticDerived,
+    /// Supplied by the component storing the data
+    CallerSupplied,
+    /// Not encrypted
+    None,
+    /// Could not be determined
+    Unknown,
+}
+
+impl std::fmt::Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeySource::PlatformKeystore => "platform keystore",
+            KeySource::DevelopmentKey => "development platform key",
+            KeySource::IdentityDerived => "identity derivation",
+            KeySource::LegacyDerived => "legacy identity derivation",
+            KeySource::StaticDerived => "static derivation",
+            KeySource::CallerSupplied => "caller-supplied key",
+            KeySource::None => "no key",
+            KeySource::Unknown => "unknown source",
+        })
+    }
+}
+
+/// How urgently a finding should be addressed
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Informational
+    Info,
+    /// Should be addressed
+    Warning,
+    /// Data is effectively unprotected
+    Critical,
+}
+
+/// Something the audit wants the user to know about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// Category the finding applies to
+    pub category: DataCategory,
+    /// How urgent it is
+    pub severity: Severity,
+    /// What was found
+    pub message: String,
+    /// How to fix it, if anything can be done
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+/// At-rest status of one data category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryReport {
+    /// Category described
+    pub category: DataCategory,
+    /// Whether any data of this category exists
+    pub present: bool,
+    /// Number of items (files, rows, entries) found
+    pub items: usize,
+    /// Whether the data is encrypted at rest
+    pub encrypted: bool,
+    /// Encryption scheme in use
+    pub scheme: EncryptionScheme,
+    /// Where the key comes from
+    pub key_source: KeySource,
+}
+
+/// Result of an at-rest encryption audit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    /// Unix timestamp when the audit ran
+    pub generated_at: u64,
+    /// Status of each data category
+    pub categories: Vec<CategoryReport>,
+    /// Findings, most severe first
+    pub findings: Vec<Finding>,
+    /// OS re-authentication backend for sensitive operations, if the
+    /// caller said which is active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticator: Option<AuthBackend>,
+    /// Stored data without a data class
+    #[serde(default)]
+    pub unclassified: UnclassifiedData,
+}
+
+/// Stored data without a data class
+///
+/// Unclassified data is kept out of diagnostics bundles and backups, so
+/// each entry is data those may be missing or that was never reviewed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnclassifiedData {
+    /// Files relative to the storage root
+    pub files: Vec<PathBuf>,
+    /// Keys of encrypted blobs
+    pub blobs: Vec<String>,
+}
+
+impl UnclassifiedData {
+    /// Whether all stored data is classified
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.blobs.is_empty()
+    }
+}
+
+impl AuditReport {
+    /// Status of a category
+    pub fn category(&self, category: DataCategory) -> Option<&CategoryReport> {
+        self.categories.iter().find(|c| c.category == category)
+    }
+
+    /// Highest severity among the findings
+    pub fn worst_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+}
+
+/// What the audit inspects
+///
+/// Without an identity the key cocoon can only be reported as present.
+pub struct AuditContext<'a> {
+    storage_path: PathBuf,
+    user_id: String,
+    identity: Option<&'a RootIdentity>,
+    cache_dir: Option<PathBuf>,
+    authenticator: Option<AuthBackend>,
+    clock: SharedClock,
+}
+
+impl<'a> AuditContext<'a> {
+    /// Audit the storage under `storage_path` for a user
+    pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Self {
+        Self {
+            storage_path: storage_path.into(),
+            user_id: user_id.to_string(),
+            identity: None,
+            cache_dir: None,
+            authenticator: None,
+            clock: time::default_clock(),
+        }
+    }
+
+    /// Use the unlocked identity to check the key cocoon
+    pub fn with_identity(mut self, identity: &'a RootIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Include the component cache directory
+    pub fn with_cache_dir<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Report the active OS re-authentication backend
+    pub fn with_authenticator(mut self, backend: AuthBackend) -> Self {
+        self.authenticator = Some(backend);
+        self
+    }
+
+    /// Use a specific clock for the report timestamp
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+/// At-rest encryption audit (OpenRPC: security.audit)
+pub struct EncryptionAudit;
+
+impl EncryptionAudit {
+    /// Inspect the storage layout and build a report
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if storage cannot be opened or queried. Data that
+    /// cannot be decrypted is reported as a finding, not an error.
+    pub fn run(ctx: &AuditContext<'_>) -> Result<AuditReport> {
+        let files = FileStorage::new(&ctx.storage_path)?;
+        let sql = SqlStorage::new(ctx.storage_path.join("osnova.db"))?;
+        let mut findings = Vec::new();
+
+        let categories = vec![
+            Self::audit_identity(&files, &mut findings)?,
+            Self::audit_cocoon(ctx, &files, &mut findings)?,
+            Self::audit_app_configs(ctx, &sql, &mut findings)?,
+            Self::audit_blobs(&sql, &mut findings)?,
+            Self::audit_system_config(&files, &mut findings)?,
+            Self::audit_cache(ctx, &mut findings)?,
+            Self::audit_logs(&sql, &mut findings)?,
+        ];
+        Self::audit_key_usage(ctx, &sql, &mut findings)?;
+        let unclassified = Self::audit_classification(&files, &sql, &mut findings)?;
+
+        findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+
+        Ok(AuditReport {
+            generated_at: ctx.clock.now_unix(),
+            categories,
+            findings,
+            authenticator: ctx.authenticator,
+            unclassified,
+        })
+    }
+
+    fn audit_classification(
+        files: &FileStorage,
+        sql: &SqlStorage,
+        findings: &mut Vec<Finding>,
+    ) -> Result<UnclassifiedData> {
+        let classes = files.classifications()?;
+        let mut unclassified = UnclassifiedData {
+            files: files
+                .list_files("")?
+                .into_iter()
+                .filter(|path| classification::is_classifiable(path) && !classes.contains_key(path))
+                .collect(),
+            blobs: sql.list_unclassified_blobs()?,
+        };
+        unclassified.files.sort();
+        if !unclassified.is_empty() {
+            findings.push(Finding {
+                category: DataCategory::Unclassified,
+                severity: Severity::Warning,
+                message: format!(
+                    "{} files and {} blobs have no data class, so backups and diagnostics \
+                     leave them out",
+                    unclassified.files.len(),
+                    unclassified.blobs.len()
+                ),
+                remediation: Some(
+                    "Restart to classify the shell's own files; apps storing the rest should \
+                     write them with a data class"
+                        .to_string(),
+                ),
+            });
+        }
+        Ok(unclassified)
+    }
+
+    fn audit_identity(files: &FileStorage, findings: &mut Vec<Finding>) -> Result<CategoryReport> {
+        let category = DataCategory::Identity;
+        if !files.exists(IDENTITY_PATH) {
+            return Ok(absent(
+                category,
+                EncryptionScheme::Cocoon,
+                KeySource::PlatformKeystore,
+            ));
+        }
+
+        let mut candidates = KeyCandidates::new().with(
+            KeySource::DevelopmentKey,
+            "development platform key",
+            IdentityService::development_platform_key(),
+        );
+        if let Ok(key) = IdentityService::get_platform_key() {
+            candidates = candidates.with(KeySource::PlatformKeystore, "platform keystore key", key);
+        }
+        let diagnosis =
+            diagnose_decrypt_failure(files, &DecryptLocation::file(IDENTITY_PATH), &candidates)?;
+
+        let key_source = match diagnosis.opened_with().map(|attempt| attempt.key_source) {
+            Some(KeySource::DevelopmentKey) => {
+                findings.push(Finding {
+                    category,
+                    severity: Severity::Critical,
+                    message: "Identity is encrypted with the built-in development key".to_string(),
+                    remediation: Some(
+                        "Keep the seed phrase backed up; the identity is re-sealed automatically \
+                     once platform keystore support is available"
+                            .to_string(),
+                    ),
+                });
+                KeySource::DevelopmentKey
+            }
+            Some(key_source) => key_source,
+            None => {
+                return Ok(unreadable(
+                    category,
+                    1,
+                    findings,
+                    "Identity",
+                    diagnosis.failure,
+                    restore_from_phrase(),
+                ))
+            }
+        };
+
+        Ok(present(category, 1, EncryptionScheme::Cocoon, key_source))
+    }
+
+    fn audit_cocoon(
+        ctx: &AuditContext<'_>,
+        files: &FileStorage,
+        findings: &mut Vec<Finding>,
+    ) -> Result<CategoryReport> {
+        let category = DataCategory::KeyCocoon;
+        if !files.exists(COCOON_PATH) {
+            return Ok(absent(
+                category,
+                EncryptionScheme::Cocoon,
+                KeySource::IdentityDerived,
+            ));
+        }
+
+        let Some(identity) = ctx.identity else {
+            findings.push(Finding {
+                category,
+                severity: Severity::Info,
+                message: "Key cocoon was not checked because the identity is locked".to_string(),
+                remediation: None,
+            });
+            return Ok(present(
+                category,
+                1,
+                EncryptionScheme::Cocoon,
+                KeySource::Unknown,
+            ));
+        };
+
+        let candidates = KeyCandidates::new().with_identity(identity, &ctx.user_id)?;
+        let diagnosis =
+            diagnose_decrypt_failure(files, &DecryptLocation::file(COCOON_PATH), &candidates)?;
+        let key_source = match diagnosis.opened_with().map(|attempt| attempt.key_source) {
+            Some(KeySource::LegacyDerived) => {
+                findings.push(Finding {
+                    category,
+                    severity: Severity::Warning,
+                    message: "Key cocoon is still encrypted with the legacy cocoon key".to_string(),
+                    remediation: Some(
+                        "Run the cocoon key migration (KeyService::migrate_cocoon_key)".to_string(),
+                    ),
+                });
+                KeySource::LegacyDerived
+            }
+            Some(key_source) => key_source,
+            None => {
+                return Ok(unreadable(
+                    category,
+                    1,
+                    findings,
+                    "Key cocoon",
+                    diagnosis.failure,
+                    restore_from_phrase(),
+                ))
+            }
+        };
+
+        Ok(present(category, 1, EncryptionScheme::Cocoon, key_source))
+    }
+
+    fn audit_app_configs(
+        ctx: &AuditContext<'_>,
+        sql: &SqlStorage,
+        findings: &mut Vec<Finding>,
+    ) -> Result<CategoryReport> {
+        let category = DataCategory::AppConfigs;
+        let ids = sql.list_app_config_ids(&ctx.user_id)?;
+        if ids.is_empty() {
+            return Ok(absent(
+                category,
+                EncryptionScheme::Cocoon,
+                KeySource::StaticDerived,
+            ));
+        }
+
+        let key = ConfigService::derive_user_config_key(&ctx.user_id);
+        let unreadable_count = ids
+            .iter()
+            .filter(|id| sql.get_app_config(id, &ctx.user_id, &key).is_err())
+            .count();
+
+        if unreadable_count == ids.len() {
+            return Ok(unreadable(
+                category,
+                ids.len(),
+                findings,
+                "App configurations",
+                None,
+                "Reset the affected apps' settings".to_string(),
+            ));
+        }
+        if unreadable_count > 0 {
+            findings.push(Finding {
+                category,
+                severity: Severity::Warning,
+                message: format!(
+                    "{} of {} app configurations could not be decrypted",
+                    unreadable_count,
+                    ids.len()
+                ),
+                remediation: Some("Reset the affected apps' settings".to_string()),
+            });
+        }
+        findings.push(Finding {
+            category,
+            severity: Severity::Warning,
+            message: format!(
+                "{} app configurations are encrypted with a key derived from the user ID only",
+                ids.len() - unreadable_count
+            ),
+            remediation: Some(
+                "No action needed yet; configurations move to identity-derived keys in a \
+                 future migration"
+                    .to_string(),
+            ),
+        });
+
+        Ok(present(
+            category,
+            ids.len(),
+            EncryptionScheme::Cocoon,
+            KeySource::StaticDerived,
+        ))
+    }
+
+    fn audit_blobs(sql: &SqlStorage, findings: &mut Vec<Finding>) -> Result<CategoryReport> {
+        let category = DataCategory::EncryptedBlobs;
+        let count = sql.count_encrypted_blobs()?;
+        if count == 0 {
+            return Ok(absent(
+                category,
+                EncryptionScheme::Cocoon,
+                KeySource::CallerSupplied,
+            ));
+        }
+
+        findings.push(Finding {
+            category,
+            severity: Severity::Info,
+            message: format!(
+                "{} blobs are encrypted with keys held by the components that stored them",
+                count
+            ),
+            remediation: None,
+        });
+
+        Ok(present(
+            category,
+            count,
+            EncryptionScheme::Cocoon,
+            KeySource::CallerSupplied,
+        ))
+    }
+
+    fn audit_system_config(
+        files: &FileStorage,
+        findings: &mut Vec<Finding>,
+    ) -> Result<CategoryReport> {
+        let category = DataCategory::SystemConfig;
+        if !files.exists(SYSTEM_CONFIG_PATH) {
+            return Ok(absent(
+                category,
+                EncryptionScheme::Cocoon,
+                KeySource::StaticDerived,
+            ));
+        }
+
+        let candidates = KeyCandidates::new().with(
+            KeySource::StaticDerived,
+            "system configuration key",
+            ConfigService::derive_system_key(),
+        );
+        let diagnosis = diagnose_decrypt_failure(
+            files,
+            &DecryptLocation::file(SYSTEM_CONFIG_PATH),
+            &candidates,
+        )?;
+        if diagnosis.opened_with().is_none() {
+            return Ok(unreadable(
+                category,
+                1,
+                findings,
+                "System configuration",
+                diagnosis.failure,
+                "Reset system settings to defaults".to_string(),
+            ));
+        }
+
+        findings.push(Finding {
+            category,
+            severity: Severity::Warning,
+            message: "System configuration is encrypted with a static key".to_string(),
+            remediation: Some(
+                "Avoid storing secrets in system settings until platform keystore support \
+                 is available"
+                    .to_string(),
+            ),
+        });
+
+        Ok(present(
+            category,
+            1,
+            EncryptionScheme::Cocoon,
+            KeySource::StaticDerived,
+        ))
+    }
+
+    fn audit_cache(ctx: &AuditContext<'_>, findings: &mut Vec<Finding>) -> Result<CategoryReport> {
+        let category = DataCategory::CacheComponents;
+        let count = match &ctx.cache_dir {
+            Some(dir) => count_files(dir)?,
+            None => 0,
+        };
+        if count == 0 {
+            return Ok(absent(category, EncryptionScheme::None, KeySource::None));
+        }
+
+        findings.push(Finding {
+            category,
+            severity: Severity::Info,
+            message: format!(
+                "{} cached component files are stored unencrypted and verified by hash",
+                count
+            ),
+            remediation: None,
+        });
+
+        Ok(present(
+            category,
+            count,
+            EncryptionScheme::None,
+            KeySource::None,
+        ))
+    }
+
+    fn audit_key_usage(
+        ctx: &AuditContext<'_>,
+        sql: &SqlStorage,
+        findings: &mut Vec<Finding>,
+    ) -> Result<()> {
+        let since = ctx
+            .clock
+            .now_unix()
+            .saturating_sub(KEY_USAGE_AUDIT_WINDOW_SECS);
+
+        // Newest first, so the first anomaly seen per component is its latest
+        let mut components: BTreeMap<String, (usize, KeyUsageAnomaly)> = BTreeMap::new();
+        for anomaly in sql.list_key_usage_anomalies(None, since)? {
+            components
+                .entry(anomaly.component_id.clone())
+                .or_insert((0, anomaly))
+                .0 += 1;
+        }
+
+        for (component_id, (episodes, latest)) in components {
+            findings.push(Finding {
+                category: DataCategory::KeyCocoon,
+                severity: Severity::Warning,
+                message: format!(
+                    "{} used its keys unusually often {} time(s) in the last 7 days, most \
+                     recently {} times in an hour against an average of {:.1}",
+                    component_id,
+                    episodes,
+                    latest.hourly_count,
+                    latest.trailing_average()
+                ),
+                remediation: Some(
+                    "Check the component's key usage report; remove the app if the activity \
+                     is unexpected"
+                        .to_string(),
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn audit_logs(sql: &SqlStorage, findings: &mut Vec<Finding>) -> Result<CategoryReport> {
+        let category = DataCategory::Logs;
+        let count = sql.list_audit_entries()?.len();
+        if count == 0 {
+            return Ok(absent(category, EncryptionScheme::None, KeySource::None));
+        }
+
+        findings.push(Finding {
+            category,
+            severity: Severity::Info,
+            message: format!(
+                "{} audit log entries are tamper-evident but not encrypted",
+                count
+            ),
+            remediation: None,
+        });
+
+        Ok(present(
+            category,
+            count,
+            EncryptionScheme::None,
+            KeySource::None,
+        ))
+    }
+}
+
+fn present(
+    category: DataCategory,
+    items: usize,
+    scheme: EncryptionScheme,
+    key_source: KeySource,
+) -> CategoryReport {
+    CategoryReport {
+        category,
+        present: true,
+        items,
+        encrypted: scheme != EncryptionScheme::None,
+        scheme,
+        key_source,
+    }
+}
+
+fn absent(
+    category: DataCategory,
+    scheme: EncryptionScheme,
+    key_source: KeySource,
+) -> CategoryReport {
+    CategoryReport {
+        present: false,
+        ..present(category, 0, scheme, key_source)
+    }
+}
+
+/// Report a category whose data opens with no known key, and why if known
+fn unreadable(
+    category: DataCategory,
+    items: usize,
+    findings: &mut Vec<Finding>,
+    what: &str,
+    failure: Option<DecryptFailure>,
+    remediation: String,
+) -> CategoryReport {
+    let mut message = format!("{} could not be decrypted with any known key", what);
+    if let Some(failure) = failure {
+        message.push_str(&format!(": {}", failure));
+    }
+    findings.push(Finding {
+        category,
+        severity: Severity::Critical,
+        message,
+        remediation: Some(remediation),
+    });
+    present(
+        category,
+        items,
+        EncryptionScheme::Unknown,
+        KeySource::Unknown,
+    )
+}
+
+fn restore_from_phrase() -> String {
+    "Restore the identity from its seed phrase".to_string()
+}
+
+/// Count regular files under a directory, recursively
+fn count_files(dir: &Path) -> Result<usize> {
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            count += count_files(&path)?;
+        } else {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::models::config_cache::AppConfiguration;
+    use crate::services::KeyService;
+    use crate::time::MockClock;
+    use base64::Engine;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    const USER: &str = "user-123";
+
+    /// Identity and system config in the current layout, a key cocoon still
+    /// under the legacy key, and one app configuration under a foreign key
+    fn fixture() -> anyhow::Result<(TempDir, RootIdentity)> {
+        let temp = TempDir::new()?;
+        let storage = temp.path();
+
+        let identity_service = IdentityService::new(storage)?;
+        identity_service.create()?;
+        let identity = identity_service.get_identity()?;
+
+        let sql = SqlStorage::new(storage.join("osnova.db"))?;
+        for app_id in ["com.osnova.wallet", "com.example.other"] {
+            let app = OsnovaApplication::new(app_id, app_id, "1.0.0", "", "", vec![])?;
+            sql.upsert_application(&app)?;
+        }
+
+        let config = ConfigService::new(storage)?;
+        config.set_app_config("com.osnova.wallet", USER, HashMap::new())?;
+        config.set_trash_retention_days(7)?;
+
+        let legacy = KeyService::new(storage, &identity.legacy_cocoon_key(USER))?;
+        legacy.initialize(identity.master_key())?;
+
+        sql.set_app_config(
+            "com.example.other",
+            USER,
+            &AppConfiguration::new("com.example.other", USER),
+            &[9u8; 32],
+        )?;
+        sql.set_encrypted_blob("wallet/state", b"balance", &[5u8; 32])?;
+        sql.set_encrypted_blob("wallet/history", b"txs", &[5u8; 32])?;
+
+        fs::create_dir_all(storage.join("cache/components/ab"))?;
+        fs::write(storage.join("cache/components/ab/cd"), b"component")?;
+
+        Ok((temp, identity))
+    }
+
+    fn run(temp: &TempDir, identity: &RootIdentity) -> anyhow::Result<AuditReport> {
+        let ctx = AuditContext::new(temp.path(), USER)
+            .with_identity(identity)
+            .with_cache_dir(temp.path().join("cache"))
+            .with_clock(Arc::new(MockClock::new(1_000)));
+        EncryptionAudit::run(&ctx)
+    }
+
+    #[test]
+    fn test_report_categories_on_mixed_fixture() -> anyhow::Result<()> {
+        let (temp, identity) = fixture()?;
+        let report = run(&temp, &identity)?;
+
+        assert_eq!(report.generated_at, 1_000);
+        assert_eq!(report.categories.len(), 7);
+
+        let cocoon = report.category(DataCategory::KeyCocoon).unwrap();
+        assert_eq!(cocoon.key_source, KeySource::LegacyDerived);
+        assert!(report.findings.iter().any(|f| {
+            f.category == DataCategory::KeyCocoon
+                && f.remediation
+                    .as_deref()
+                    .unwrap()
+                    .contains("migrate_cocoon_key")
+        }));
+
+        let configs = report.category(DataCategory::AppConfigs).unwrap();
+        assert_eq!(configs.items, 2);
+        assert_eq!(configs.key_source, KeySource::StaticDerived);
+        assert!(report
+            .findings
+            .iter()
+            .any(|f| f.message.starts_with("1 of 2 app configurations")));
+
+        let blobs = report.category(DataCategory::EncryptedBlobs).unwrap();
+        assert_eq!(blobs.items, 2);
+        assert_eq!(blobs.key_source, KeySource::CallerSupplied);
+
+        let system = report.category(DataCategory::SystemConfig).unwrap();
+        assert!(system.present && system.encrypted);
+
+        let cache = report.category(DataCategory::CacheComponents).unwrap();
+        assert_eq!(cache.items, 1);
+        assert!(!cache.encrypted);
+
+        let logs = report.category(DataCategory::Logs).unwrap();
+        assert!(logs.present);
+        assert_eq!(logs.scheme, EncryptionScheme::None);
+
+        // Migrating the cocoon clears its finding
+        let current = identity.derive_cocoon_key(USER)?;
+        KeyService::new(temp.path(), &current)?
+            .migrate_cocoon_key(&identity.legacy_cocoon_key(USER))?;
+        let report = run(&temp, &identity)?;
+        let cocoon = report.category(DataCategory::KeyCocoon).unwrap();
+        assert_eq!(cocoon.key_source, KeySource::IdentityDerived);
+        assert!(!report
+            .findings
+            .iter()
+            .any(|f| f.category == DataCategory::KeyCocoon));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detects_development_platform_key() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let report = EncryptionAudit::run(&AuditContext::new(temp.path(), USER))?;
+        assert!(!report.category(DataCategory::Identity).unwrap().present);
+        assert_eq!(report.worst_severity(), None);
+        assert_eq!(report.authenticator, None);
+
+        let ctx = AuditContext::new(temp.path(), USER).with_authenticator(AuthBackend::Unavailable);
+        let report = EncryptionAudit::run(&ctx)?;
+        assert_eq!(report.authenticator, Some(AuthBackend::Unavailable));
+
+        IdentityService::new(temp.path())?.create()?;
+        let report = EncryptionAudit::run(&AuditContext::new(temp.path(), USER))?;
+
+        let identity = report.category(DataCategory::Identity).unwrap();
+        assert_eq!(identity.key_source, KeySource::DevelopmentKey);
+        assert_eq!(report.worst_severity(), Some(Severity::Critical));
+        assert_eq!(report.findings[0].category, DataCategory::Identity);
+
+        // Unreadable identities are reported, not returned as errors
+        fs::write(temp.path().join(IDENTITY_PATH), b"garbage")?;
+        let report = EncryptionAudit::run(&AuditContext::new(temp.path(), USER))?;
+        let identity = report.category(DataCategory::Identity).unwrap();
+        assert_eq!(identity.scheme, EncryptionScheme::Unknown);
+        assert_eq!(identity.key_source, KeySource::Unknown);
+        assert_eq!(
+            report.findings[0].message,
+            "Identity could not be decrypted with any known key: truncated ciphertext: 7 bytes, \
+             expected at least 36"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports_recent_key_usage_anomalies() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let sql = SqlStorage::new(temp.path().join("osnova.db"))?;
+        let now = KEY_USAGE_AUDIT_WINDOW_SECS + 10_000;
+        let anomaly = |component_id: &str, detected_at: u64, hourly_count| KeyUsageAnomaly {
+            component_id: component_id.to_string(),
+            hour: detected_at - detected_at % 3600,
+            hourly_count,
+            trailing_count: 24,
+            trailing_hours: 24,
+            detected_at,
+        };
+        // Too old to report
+        sql.insert_key_usage_anomaly(&anomaly("com.example.old", 100, 900))?;
+        sql.insert_key_usage_anomaly(&anomaly("com.example.sync", now - 7200, 300))?;
+        sql.insert_key_usage_anomaly(&anomaly("com.example.sync", now - 60, 400))?;
+
+        let ctx = AuditContext::new(temp.path(), USER).with_clock(Arc::new(MockClock::new(now)));
+        let report = EncryptionAudit::run(&ctx)?;
+        let findings: Vec<&Finding> = report
+            .findings
+            .iter()
+            .filter(|f| f.category == DataCategory::KeyCocoon)
+            .collect();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(
+            findings[0].message,
+            "com.example.sync used its keys unusually often 2 time(s) in the last 7 days, most \
+             recently 400 times in an hour against an average of 1.0"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports_unclassified_data() -> anyhow::Result<()> {
+        let (temp, identity) = fixture()?;
+        // Written before data classes existed
+        let files = FileStorage::new(temp.path())?;
+        files.write(format!("ui/{}/theme.json", USER), b"dark", &[1u8; 32])?;
+
+        let report = run(&temp, &identity)?;
+        assert_eq!(
+            report.unclassified.files,
+            vec![
+                PathBuf::from("cache/components/ab/cd"),
+                PathBuf::from(format!("ui/{}/theme.json", USER)),
+            ]
+        );
+        assert_eq!(
+            report.unclassified.blobs,
+            vec!["wallet/history", "wallet/state"]
+        );
+        let finding = report
+            .findings
+            .iter()
+            .find(|f| f.category == DataCategory::Unclassified)
+            .unwrap();
+        assert_eq!(finding.severity, Severity::Warning);
+        assert!(finding.message.starts_with("2 files and 2 blobs"));
+
+        // The startup migration tags the shell's own files
+        classification::classify_known_paths(&files)?;
+        let sql = SqlStorage::new(temp.path().join("osnova.db"))?;
+        for key in ["wallet/history", "wallet/state"] {
+            sql.set_encrypted_blob_classified(
+                key,
+                b"data",
+                &[5u8; 32],
+                crate::storage::DataClass::Personal,
+            )?;
+        }
+        fs::remove_file(temp.path().join("cache/components/ab/cd"))?;
+
+        let report = run(&temp, &identity)?;
+        assert!(report.unclassified.is_empty());
+        assert!(!report
+            .findings
+            .iter()
+            .any(|f| f.category == DataCategory::Unclassified));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_contains_no_key_material() -> anyhow::Result<()> {
+        let (temp, identity) = fixture()?;
+        let json = serde_json::to_string(&run(&temp, &identity)?)?;
+
+        let secrets = [
+            *identity.master_key(),
+            identity.derive_cocoon_key(USER)?,
+            identity.legacy_cocoon_key(USER),
+            IdentityService::development_platform_key(),
+            ConfigService::derive_system_key(),
+            ConfigService::derive_user_config_key(USER),
+        ];
+        let engine = base64::engine::general_purpose::STANDARD;
+        for secret in &secrets {
+            assert!(!json.contains(&hex::encode(secret)));
+            assert!(!json.contains(&engine.encode(secret)));
+            assert!(!json.contains(&serde_json::to_string(secret.as_slice())?));
+        }
+        for word in identity.seed_phrase().split_whitespace() {
+            assert!(!json.contains(&format!("\"{}\"", word)));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+//! Remote client sessions for Client-Server mode
+//!
+//! After a client device completes the pairing handshake, the server issues
+//! it a session token. The client sends the token with every OpenRPC
+//! request, and the server resolves it to the paired device.
+//!
+//! Handles:
+//! - Issuing a random 32-byte token bound to a device with an active
+//!   device key and an established pairing session
+//! - Authenticating tokens, recording when each session was last seen
+//! - Renewing and revoking sessions; revoking a device key revokes its
+//!   sessions
+//! - Per-device permissions for methods that paired devices may not call
+//!   by default, such as `logs.read`
+//!
+//! Only a BLAKE3 hash of each token is stored. Local callers (the UI in
+//! stand-alone mode) do not use sessions at all. Failed authentication is
+//! reported as [`OsnovaError::Unauthorized`], which maps to
+//! [`UNAUTHORIZED_ERROR_CODE`](crate::error::UNAUTHORIZED_ERROR_CODE).
+
+use anyhow::{bail, Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::OsnovaError;
+use crate::models::session::RemoteSession;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Session lifetime, in seconds, before it must be renewed (7 days)
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Length of a session token in bytes (before hex encoding)
+const TOKEN_LEN: usize = 32;
+
+/// A newly issued session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedSession {
+    /// Bearer token to hand to the client (hex); not retrievable later
+    pub token: String,
+    /// The stored session
+    pub session: RemoteSession,
+}
+
+/// Who is making a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// In-process caller (stand-alone mode UI); no session required
+    Local,
+    /// Remote client presenting a session token
+    Remote {
+        /// Session token sent with the request
+        token: String,
+    },
+}
+
+/// Identity attached to an authorized request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestContext {
+    /// Paired device making the request, `None` for local callers
+    pub device_id: Option<String>,
+}
+
+impl RequestContext {
+    /// Context for a local caller
+    pub fn local() -> Self {
+        Self { device_id: None }
+    }
+
+    /// Whether the request came from a remote device
+    pub fn is_remote(&self) -> bool {
+        self.device_id.is_some()
+    }
+}
+
+/// Remote session service
+///
+/// Provides OpenRPC methods:
+/// - `sessions.renew` - Extend the caller's session
+/// - `sessions.list` - Sessions of all paired devices
+/// - `sessions.revoke` - End every session of a device
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::sessions::{Caller, SessionService};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = SessionService::new("/tmp/osnova")?;
+///
+/// let issued = service.issue("pairing-123", "device-456")?;
+/// let context = service.authorize(&Caller::Remote { token: issued.token })?;
+/// assert_eq!(context.device_id.as_deref(), Some("device-456"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct SessionService {
+    storage: Mutex<SqlStorage>,
+    clock: SharedClock,
+    ttl_secs: u64,
+}
+
+impl SessionService {
+    /// Create a new session service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Directory holding the Osnova database
+    pub fn new<P: AsRef<Path>>(storage_path: P) -> Result<Self> {
+        let sql_storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            clock: time::default_clock(),
+            ttl_secs: DEFAULT_SESSION_TTL_SECS,
+        })
+    }
+
+    /// Use a specific clock for issue, expiry, and last-seen timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Use a specific session lifetime, in seconds
+    pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    /// Issue a session to a device that has just paired
+    ///
+    /// # Arguments
+    ///
+    /// * `pairing_session_id` - Pairing session the device completed
+    /// * `device_id` - Device to bind the session to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pairing session is not established, has
+    /// expired, or belongs to another device, or if the device key is
+    /// unknown or revoked.
+    pub fn issue(&self, pairing_session_id: &str, device_id: &str) -> Result<IssuedSession> {
+        let storage = self.storage.lock().unwrap();
+
+        let pairing = storage
+            .get_pairing_session(pairing_session_id)?
+            .with_context(|| format!("Pairing session {} not found", pairing_session_id))?;
+        if !pairing.is_established() {
+            bail!("Pairing session {} is not established", pairing_session_id);
+        }
+        if pairing.is_expired_with(self.clock.as_ref()) {
+            bail!("Pairing session {} has expired", pairing_session_id);
+        }
+
+        let device_key = storage
+            .get_device_key(device_id)?
+            .with_context(|| format!("Device {} is not paired", device_id))?;
+        if device_key.is_revoked() {
+            bail!("Device {} has been revoked", device_id);
+        }
+        if device_key.public_key() != pairing.device_public_key() {
+            bail!(
+                "Pairing session {} does not belong to device {}",
+                pairing_session_id,
+                device_id
+            );
+        }
+
+        let mut raw = [0u8; TOKEN_LEN];
+        thread_rng().fill_bytes(&mut raw);
+        let token = hex::encode(raw);
+
+        let now = self.clock.now_unix();
+        let session = RemoteSession {
+            device_id: device_id.to_string(),
+            created_at: now,
+            expires_at: now + self.ttl_secs,
+            last_seen: now,
+        };
+        storage.upsert_remote_session(&Self::hash_token(&token), &session)?;
+
+        Ok(IssuedSession { token, session })
+    }
+
+    /// Authorize a request, attaching the caller's device identity
+    ///
+    /// Local callers bypass sessions. Remote callers must present a valid
+    /// token; see [`Self::authenticate`].
+    pub fn authorize(&self, caller: &Caller) -> Result<RequestContext> {
+        match caller {
+            Caller::Local => Ok(RequestContext::local()),
+            Caller::Remote { token } => self.authenticate(token),
+        }
+    }
+
+    /// Resolve a session token to its device, updating last seen
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] if the token is unknown,
+    /// expired, or revoked, or its device key has been revoked.
+    pub fn authenticate(&self, token: &str) -> Result<RequestContext> {
+        let storage = self.storage.lock().unwrap();
+        let token_hash = Self::hash_token(token);
+        let mut session = self.active_session(&storage, &token_hash)?;
+
+        session.last_seen = self.clock.now_unix();
+        storage.upsert_remote_session(&token_hash, &session)?;
+
+        Ok(RequestContext {
+            device_id: Some(session.device_id),
+        })
+    }
+
+    /// Authorize a request that needs a device permission
+    ///
+    /// Local callers are always allowed. Remote callers must present a
+    /// valid token whose device has been granted `permission`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] if the token is not valid, or
+    /// [`OsnovaError::PermissionDenied`] if the device lacks the permission.
+    pub fn authorize_permission(
+        &self,
+        caller: &Caller,
+        permission: &str,
+    ) -> Result<RequestContext> {
+        let context = self.authorize(caller)?;
+        let Some(device_id) = &context.device_id else {
+            return Ok(context);
+        };
+
+        let storage = self.storage.lock().unwrap();
+        if !storage.has_device_permission(device_id, permission)? {
+            return Err(OsnovaError::PermissionDenied(format!(
+                "device {} lacks the {} permission",
+                device_id, permission
+            ))
+            .into());
+        }
+        Ok(context)
+    }
+
+    /// Grant a permission to a paired device
+    /// (OpenRPC: sessions.grantPermission)
+    ///
+    /// Returns `false` if the device already had it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device has no active device key
+    pub fn grant_permission(&self, device_id: &str, permission: &str) -> Result<bool> {
+        let storage = self.storage.lock().unwrap();
+        let active = storage
+            .get_device_key(device_id)?
+            .is_some_and(|key| !key.is_revoked());
+        if !active {
+            bail!("No active device key for device {}", device_id);
+        }
+        storage.grant_device_permission(device_id, permission, self.clock.now_unix())
+    }
+
+    /// Revoke a permission from a paired device
+    /// (OpenRPC: sessions.revokePermission)
+    ///
+    /// Returns `false` if the device did not have it. Open subscriptions
+    /// that were authorized with it are not ended.
+    pub fn revoke_permission(&self, device_id: &str, permission: &str) -> Result<bool> {
+        let revoked = self
+            .storage
+            .lock()
+            .unwrap()
+            .revoke_device_permission(device_id, Some(permission))?;
+        Ok(revoked > 0)
+    }
+
+    /// Permissions granted to a paired device, by name
+    /// (OpenRPC: sessions.permissions)
+    pub fn permissions(&self, device_id: &str) -> Result<Vec<String>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_device_permissions(device_id)
+    }
+
+    /// Extend a session by the session lifetime from now
+    /// (OpenRPC: sessions.renew)
+    ///
+    /// The token stays the same.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::Unauthorized`] if the session can no longer be
+    /// used; an expired session cannot be renewed.
+    pub fn renew(&self, token: &str) -> Result<RemoteSession> {
+        let storage = self.storage.lock().unwrap();
+        let token_hash = Self::hash_token(token);
+        let mut session = self.active_session(&storage, &token_hash)?;
+
+        let now = self.clock.now_unix();
+        session.last_seen = now;
+        session.expires_at = now + self.ttl_secs;
+        storage.upsert_remote_session(&token_hash, &session)?;
+
+        Ok(session)
+    }
+
+    /// End every session of a device (OpenRPC: sessions.revoke)
+    ///
+    /// Returns the number of sessions ended. The device stays paired and
+    /// may be issued a new session.
+    pub fn revoke(&self, device_id: &str) -> Result<usize> {
+        self.storage
+            .lock()
+            .unwrap()
+            .delete_remote_sessions_for_device(device_id)
+    }
+
+    /// Revoke a device key, its permissions, and its sessions
+    ///
+    /// Returns the number of sessions ended.
+    pub fn revoke_device(&self, device_id: &str) -> Result<usize> {
+        let storage = self.storage.lock().unwrap();
+        storage.revoke_device_key(device_id, self.clock.now_unix() as i64)?;
+        storage.revoke_device_permission(device_id, None)?;
+        storage.delete_remote_sessions_for_device(device_id)
+    }
+
+    /// Unexpired sessions, oldest first (OpenRPC: sessions.list)
+    ///
+    /// Expired sessions are removed as a side effect.
+    pub fn list_sessions(&self) -> Result<Vec<RemoteSession>> {
+        let storage = self.storage.lock().unwrap();
+        storage.delete_expired_remote_sessions(self.clock.now_unix())?;
+        storage.list_remote_sessions()
+    }
+
+    // Private helper methods
+
+    /// Load a session that may still be used
+    fn active_session(&self, storage: &SqlStorage, token_hash: &str) -> Result<RemoteSession> {
+        let Some(session) = storage.get_remote_session(token_hash)? else {
+            return Err(Self::unauthorized("unknown or revoked session"));
+        };
+
+        if session.is_expired_at(self.clock.now_unix()) {
+            storage.delete_remote_session(token_hash)?;
+            return Err(Self::unauthorized("session expired"));
+        }
+
+        let device_revoked = storage
+            .get_device_key(&session.device_id)?
+            .is_none_or(|key| key.is_revoked());
+        if device_revoked {
+            storage.delete_remote_sessions_for_device(&session.device_id)?;
+            return Err(Self::unauthorized("device revoked"));
+        }
+
+        Ok(session)
+    }
+
+    /// Hash a token for storage
+    fn hash_token(token: &str) -> String {
+        blake3::hash(token.as_bytes()).to_hex().to_string()
+    }
+
+    /// Authentication failure
+    fn unauthorized(reason: &str) -> anyhow::Error {
+        OsnovaError::Unauthorized(reason.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{PERMISSION_DENIED_ERROR_CODE, UNAUTHORIZED_ERROR_CODE};
+    use crate::models::device_key::DeviceKey;
+    use crate::models::pairing::PairingSession;
+    use crate::time::MockClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const START: u64 = 1_700_000_000;
+
+    /// Service with one paired device, "device-1"
+    fn setup(temp: &TempDir) -> Result<(SessionService, Arc<MockClock>)> {
+        let clock = Arc::new(MockClock::new(START));
+        let service = SessionService::new(temp.path())?
+            .with_clock(clock.clone())
+            .with_ttl(3600);
+
+        let storage = service.storage.lock().unwrap();
+        storage.insert_device_key(&DeviceKey::new("device-1", &[2u8; 32])?)?;
+        let mut pairing = PairingSession::new("pairing-1", &[1u8; 32], &[2u8; 32])?;
+        pairing.mark_established();
+        storage.upsert_pairing_session(&pairing)?;
+        drop(storage);
+
+        Ok((service, clock))
+    }
+
+    fn rpc_code(error: &anyhow::Error) -> Option<i64> {
+        error
+            .downcast_ref::<OsnovaError>()
+            .map(OsnovaError::rpc_code)
+    }
+
+    #[test]
+    fn test_issue_authenticate_renew() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, clock) = setup(&temp)?;
+
+        let issued = service.issue("pairing-1", "device-1")?;
+        assert_eq!(issued.token.len(), TOKEN_LEN * 2);
+        assert_eq!(issued.session.expires_at, START + 3600);
+
+        clock.advance(Duration::from_secs(600));
+        let context = service.authorize(&Caller::Remote {
+            token: issued.token.clone(),
+        })?;
+        assert_eq!(context.device_id.as_deref(), Some("device-1"));
+        assert_eq!(service.list_sessions()?[0].last_seen, START + 600);
+
+        let renewed = service.renew(&issued.token)?;
+        assert_eq!(renewed.expires_at, START + 600 + 3600);
+
+        // Past the original expiry, the renewed session still works
+        clock.advance(Duration::from_secs(3500));
+        assert!(service.authenticate(&issued.token)?.is_remote());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_issue_requires_established_pairing_for_device() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _clock) = setup(&temp)?;
+        {
+            let storage = service.storage.lock().unwrap();
+            storage.insert_device_key(&DeviceKey::new("device-2", &[3u8; 32])?)?;
+            storage
+                .upsert_pairing_session(&PairingSession::new("pending", &[1u8; 32], &[3u8; 32])?)?;
+        }
+
+        assert!(service.issue("missing", "device-1").is_err());
+        assert!(service.issue("pending", "device-2").is_err());
+        assert!(service.issue("pairing-1", "device-2").is_err());
+        assert!(service.issue("pairing-1", "unknown").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_revocation_cuts_off_active_token() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _clock) = setup(&temp)?;
+
+        let first = service.issue("pairing-1", "device-1")?;
+        assert_eq!(service.revoke("device-1")?, 1);
+        let error = service.authenticate(&first.token).unwrap_err();
+        assert_eq!(rpc_code(&error), Some(UNAUTHORIZED_ERROR_CODE));
+
+        // Revoking the device key ends sessions and blocks new ones
+        let second = service.issue("pairing-1", "device-1")?;
+        service.authenticate(&second.token)?;
+        assert_eq!(service.revoke_device("device-1")?, 1);
+        let error = service.renew(&second.token).unwrap_err();
+        assert_eq!(rpc_code(&error), Some(UNAUTHORIZED_ERROR_CODE));
+        assert!(service.issue("pairing-1", "device-1").is_err());
+
+        // Local callers are unaffected
+        assert_eq!(service.authorize(&Caller::Local)?, RequestContext::local());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_permissions_are_required_of_remote_callers() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _clock) = setup(&temp)?;
+        let caller = Caller::Remote {
+            token: service.issue("pairing-1", "device-1")?.token,
+        };
+
+        let error = service
+            .authorize_permission(&caller, "logs.read")
+            .unwrap_err();
+        assert_eq!(rpc_code(&error), Some(PERMISSION_DENIED_ERROR_CODE));
+        assert!(service
+            .authorize_permission(&Caller::Local, "logs.read")
+            .is_ok());
+
+        assert!(service.grant_permission("device-1", "logs.read")?);
+        assert!(!service.grant_permission("device-1", "logs.read")?);
+        assert!(service.grant_permission("unknown", "logs.read").is_err());
+        let context = service.authorize_permission(&caller, "logs.read")?;
+        assert_eq!(context.device_id.as_deref(), Some("device-1"));
+        assert!(service.authorize_permission(&caller, "apps.list").is_err());
+
+        // A bad token is unauthorized whatever the device may do
+        let bogus = Caller::Remote {
+            token: "not-a-token".to_string(),
+        };
+        let error = service
+            .authorize_permission(&bogus, "logs.read")
+            .unwrap_err();
+        assert_eq!(rpc_code(&error), Some(UNAUTHORIZED_ERROR_CODE));
+
+        assert!(service.revoke_permission("device-1", "logs.read")?);
+        assert!(service.authorize_permission(&caller, "logs.read").is_err());
+
+        service.grant_permission("device-1", "logs.read")?;
+        service.revoke_device("device-1")?;
+        assert!(service.permissions("device-1")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_token_is_unauthorized() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, clock) = setup(&temp)?;
+
+        let issued = service.issue("pairing-1", "device-1")?;
+        clock.advance(Duration::from_secs(3600));
+
+        let error = service.authenticate(&issued.token).unwrap_err();
+        assert_eq!(rpc_code(&error), Some(UNAUTHORIZED_ERROR_CODE));
+        assert!(error.to_string().contains("expired"));
+        assert!(service.renew(&issued.token).is_err());
+        assert!(service.list_sessions()?.is_empty());
+
+        let error = service.authenticate("not-a-token").unwrap_err();
+        assert_eq!(rpc_code(&error), Some(UNAUTHORIZED_ERROR_CODE));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_are_hashed_at_rest() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (service, _clock) = setup(&temp)?;
+
+        let issued = service.issue("pairing-1", "device-1")?;
+        drop(service);
+
+        let db = std::fs::read(temp.path().join("osnova.db"))?;
+        let contains = |needle: &[u8]| db.windows(needle.len()).any(|w| w == needle);
+        assert!(!contains(issued.token.as_bytes()));
+        assert!(contains(
+            SessionService::hash_token(&issued.token).as_bytes()
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,568 @@
+//! Data sharing contracts between installed apps
+//!
+//! An app declares [`DataOffer`]s in its manifest. Another app asks for one
+//! at runtime through a scoped permission prompt, and once the user grants
+//! it, reads the offer through this service. Reads are proxied from the
+//! offering app's data storage and confined to the offer's path prefix; the
+//! consuming app never gets a handle on that storage.
+//!
+//! Handles:
+//! - `shared.request` - Ask the user for access to an offer
+//! - `shared.list` - List the keys under an offer
+//! - `shared.read` - Read one key under an offer
+//! - `shared.revoke` - Withdraw a consumer's access (offering app)
+//!
+//! Grants are stored per user, recorded in the audit log when one is
+//! attached, and listed for the privacy settings screen. Revoking a grant
+//! cuts access on the next call.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::error::OsnovaError;
+use crate::models::application::OsnovaApplication;
+use crate::models::permission::Capability;
+use crate::models::sharing::{self, DataOffer, SharedDataGrant};
+use crate::services::config::ConfigService;
+use crate::services::permissions::PermissionService;
+use crate::storage::{FileStorage, SqlStorage};
+use crate::time::{self, SharedClock};
+
+/// Directory under the storage path holding per-app data
+const APP_DATA_DIR: &str = "app-data";
+
+/// Data sharing service
+///
+/// Provides OpenRPC methods:
+/// - `shared.request` - Ask for access to another app's data offer
+/// - `shared.list` - List keys under a granted offer
+/// - `shared.read` - Read a key under a granted offer
+/// - `shared.revoke` - Withdraw a consumer's access to an offer
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::{PermissionService, SharingService};
+/// use std::sync::Arc;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let permissions = Arc::new(PermissionService::new("/path/to/storage", "user-123")?);
+/// let sharing = SharingService::new("/path/to/storage", "user-123", permissions)?;
+///
+/// sharing
+///     .request_access("com.example.publisher", "ant://backend", "com.example.photos", "album")
+///     .await?;
+/// for key in sharing.list("com.example.publisher", "com.example.photos", "album")? {
+///     let data = sharing.read("com.example.publisher", "com.example.photos", "album", &key)?;
+///     println!("{}: {} bytes", key, data.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SharingService {
+    storage_path: PathBuf,
+    storage: Mutex<SqlStorage>,
+    permissions: Arc<PermissionService>,
+    user_id: String,
+    clock: SharedClock,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl SharingService {
+    /// Create a new sharing service
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    /// * `user_id` - User whose apps share data
+    /// * `permissions` - Prompts the user for access
+    pub fn new<P: Into<PathBuf>>(
+        storage_path: P,
+        user_id: &str,
+        permissions: Arc<PermissionService>,
+    ) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+
+        Ok(Self {
+            storage_path,
+            storage: Mutex::new(sql_storage),
+            permissions,
+            user_id: user_id.to_string(),
+            clock: time::default_clock(),
+            audit: None,
+        })
+    }
+
+    /// Record grants and revocations in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Use a specific clock for timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The data storage of an app, which its offers' path prefixes point
+    /// into
+    ///
+    /// Scoped to the user and app, and encrypted with the user's key.
+    pub fn app_storage(&self, app_id: &str) -> Result<FileStorage> {
+        let user_dir = blake3::hash(self.user_id.as_bytes()).to_hex();
+        let app_dir = blake3::hash(app_id.as_bytes()).to_hex();
+        FileStorage::new(
+            self.storage_path
+                .join(APP_DATA_DIR)
+                .join(&user_dir[..32])
+                .join(&app_dir[..32]),
+        )
+    }
+
+    /// Ask the user to let a consumer app read a data offer
+    /// (OpenRPC: shared.request)
+    ///
+    /// Returns the existing grant if there is one; otherwise prompts and
+    /// records the grant once the user allows it.
+    ///
+    /// # Arguments
+    ///
+    /// * `consumer_app_id` - App asking for access
+    /// * `component_id` - Component of the consumer that is asking
+    /// * `provider_app_id` - App that declares the offer
+    /// * `offer_id` - Offer requested
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either app is not installed or the offer is not
+    /// declared, and [`OsnovaError::PermissionDenied`] if the user denies
+    /// the request or does not answer in time.
+    pub async fn request_access(
+        &self,
+        consumer_app_id: &str,
+        component_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+    ) -> Result<SharedDataGrant> {
+        if consumer_app_id == provider_app_id {
+            bail!("Apps read their own data directly, not through a data offer");
+        }
+        let consumer = self.application(consumer_app_id)?;
+        let provider = self.application(provider_app_id)?;
+        let offer = Self::offer(&provider, offer_id)?;
+
+        if let Some(grant) = self.grant(provider_app_id, offer_id, consumer_app_id)? {
+            return Ok(grant);
+        }
+
+        let rationale = format!(
+            "{} wants to read \"{}\" from {}",
+            consumer.name(),
+            offer.description,
+            provider.name()
+        );
+        self.permissions
+            .request_scoped(
+                consumer_app_id,
+                component_id,
+                Capability::ReadSharedData,
+                &Self::scope(provider_app_id, offer_id),
+                &rationale,
+            )
+            .await?;
+
+        let grant = SharedDataGrant {
+            provider_app_id: provider_app_id.to_string(),
+            offer_id: offer_id.to_string(),
+            consumer_app_id: consumer_app_id.to_string(),
+            granted_at: self.clock.now_unix(),
+        };
+        self.storage
+            .lock()
+            .unwrap()
+            .set_shared_data_grant(&self.user_id, &grant)?;
+        self.audit(AuditAction::PermissionGranted, &grant)?;
+
+        Ok(grant)
+    }
+
+    /// List the keys under a granted offer, sorted (OpenRPC: shared.list)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the consumer has no
+    /// grant for the offer.
+    pub fn list(
+        &self,
+        consumer_app_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+    ) -> Result<Vec<String>> {
+        let offer = self.authorized_offer(consumer_app_id, provider_app_id, offer_id)?;
+        let prefix = Path::new(&offer.path_prefix);
+
+        let mut keys: Vec<String> = self
+            .app_storage(provider_app_id)?
+            .list_files(prefix)?
+            .iter()
+            .filter_map(|path| path.strip_prefix(prefix).ok())
+            .map(|key| key.to_string_lossy().replace('\\', "/"))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Read one key under a granted offer (OpenRPC: shared.read)
+    ///
+    /// `key` is relative to the offer's path prefix and may not contain `.`
+    /// or `..` components, so reads stay inside the offered data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PermissionDenied`] if the consumer has no
+    /// grant for the offer, and an error if the key is invalid or missing.
+    pub fn read(
+        &self,
+        consumer_app_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+        key: &str,
+    ) -> Result<Vec<u8>> {
+        let offer = self.authorized_offer(consumer_app_id, provider_app_id, offer_id)?;
+        if !sharing::is_confined_path(key) {
+            bail!("Invalid shared data key: '{}'", key);
+        }
+
+        self.app_storage(provider_app_id)?
+            .read(
+                Path::new(&offer.path_prefix).join(key),
+                &ConfigService::derive_user_config_key(&self.user_id),
+            )
+            .with_context(|| format!("Failed to read '{}' from offer {}", key, offer_id))
+    }
+
+    /// Withdraw a consumer's access to an offer (OpenRPC: shared.revoke)
+    ///
+    /// Called by the offering app or from the privacy settings screen.
+    /// Returns `false` if the consumer had no grant.
+    pub fn revoke(
+        &self,
+        provider_app_id: &str,
+        offer_id: &str,
+        consumer_app_id: &str,
+    ) -> Result<bool> {
+        let storage = self.storage.lock().unwrap();
+        let Some(grant) = storage.get_shared_data_grant(
+            &self.user_id,
+            provider_app_id,
+            offer_id,
+            consumer_app_id,
+        )?
+        else {
+            return Ok(false);
+        };
+        storage.delete_shared_data_grant(
+            &self.user_id,
+            provider_app_id,
+            offer_id,
+            consumer_app_id,
+        )?;
+        drop(storage);
+
+        self.audit(AuditAction::PermissionRevoked, &grant)?;
+        Ok(true)
+    }
+
+    /// List grants, optionally those where an app is provider or consumer
+    pub fn grants(&self, app_id: Option<&str>) -> Result<Vec<SharedDataGrant>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .list_shared_data_grants(&self.user_id, app_id)
+    }
+
+    /// The offer a consumer may read, or a denial
+    fn authorized_offer(
+        &self,
+        consumer_app_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+    ) -> Result<DataOffer> {
+        if self
+            .grant(provider_app_id, offer_id, consumer_app_id)?
+            .is_none()
+        {
+            return Err(OsnovaError::PermissionDenied(format!(
+                "{} has no access to {}",
+                consumer_app_id,
+                Self::scope(provider_app_id, offer_id)
+            ))
+            .into());
+        }
+
+        // The offer may have been dropped by an update since the grant
+        let provider = self.application(provider_app_id)?;
+        Self::offer(&provider, offer_id).cloned()
+    }
+
+    fn grant(
+        &self,
+        provider_app_id: &str,
+        offer_id: &str,
+        consumer_app_id: &str,
+    ) -> Result<Option<SharedDataGrant>> {
+        self.storage.lock().unwrap().get_shared_data_grant(
+            &self.user_id,
+            provider_app_id,
+            offer_id,
+            consumer_app_id,
+        )
+    }
+
+    fn application(&self, app_id: &str) -> Result<OsnovaApplication> {
+        self.storage
+            .lock()
+            .unwrap()
+            .get_application(app_id)?
+            .ok_or_else(|| anyhow!("Application not installed: {}", app_id))
+    }
+
+    fn offer<'a>(provider: &'a OsnovaApplication, offer_id: &str) -> Result<&'a DataOffer> {
+        provider
+            .find_data_offer(offer_id)
+            .ok_or_else(|| anyhow!("{} does not offer '{}'", provider.id(), offer_id))
+    }
+
+    /// Permission scope naming one offer
+    fn scope(provider_app_id: &str, offer_id: &str) -> String {
+        format!("{}#{}", provider_app_id, offer_id)
+    }
+
+    fn audit(&self, action: AuditAction, grant: &SharedDataGrant) -> Result<()> {
+        if let Some(audit) = &self.audit {
+            audit.append(
+                action,
+                serde_json::json!({
+                    "capability": Capability::ReadSharedData,
+                    "providerAppId": grant.provider_app_id,
+                    "offerId": grant.offer_id,
+                    "consumerAppId": grant.consumer_app_id,
+                }),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFilter, PageRequest};
+    use crate::error::PERMISSION_DENIED_ERROR_CODE;
+    use crate::models::identity::RootIdentity;
+    use crate::services::events::{AppEvent, EventBus};
+    use tempfile::TempDir;
+    use tokio::sync::broadcast;
+
+    const PHOTOS: &str = "com.test.photos";
+    const PUBLISHER: &str = "com.test.publisher";
+    const COMPONENT: &str = "ant://publisher-backend";
+
+    struct Fixture {
+        sharing: Arc<SharingService>,
+        permissions: Arc<PermissionService>,
+        events: broadcast::Receiver<AppEvent>,
+        audit: Arc<AuditLog>,
+        _temp: TempDir,
+    }
+
+    /// A photos app offering its shared album, and a publishing app
+    fn fixture() -> Result<Fixture> {
+        let temp = TempDir::new()?;
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        let photos = OsnovaApplication::new(PHOTOS, "Photos", "1.0.0", "", "", vec![])?
+            .with_data_offers(vec![DataOffer::new(
+                "album",
+                "Photos in the shared album",
+                "image",
+                "albums/shared",
+            )]);
+        storage.upsert_application(&photos)?;
+        storage.upsert_application(&OsnovaApplication::new(
+            PUBLISHER,
+            "Publisher",
+            "1.0.0",
+            "",
+            "",
+            vec![],
+        )?)?;
+
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let permissions = Arc::new(PermissionService::new(temp.path(), "user")?.with_events(bus));
+        let audit = Arc::new(AuditLog::new(
+            temp.path(),
+            &RootIdentity::generate()?,
+            "user",
+        )?);
+        let sharing = SharingService::new(temp.path(), "user", permissions.clone())?
+            .with_audit(audit.clone());
+
+        let key = ConfigService::derive_user_config_key("user");
+        let data = sharing.app_storage(PHOTOS)?;
+        data.write("albums/shared/beach.jpg", b"beach", &key)?;
+        data.write("albums/shared/2024/hike.jpg", b"hike", &key)?;
+        data.write("albums/private/secret.jpg", b"private", &key)?;
+        data.write("keys/signing", b"key material", &key)?;
+
+        Ok(Fixture {
+            sharing: Arc::new(sharing),
+            permissions,
+            events,
+            audit,
+            _temp: temp,
+        })
+    }
+
+    /// Request access to the album and answer the prompt
+    async fn request_album(fixture: &mut Fixture, allow: bool) -> Result<SharedDataGrant> {
+        let sharing = fixture.sharing.clone();
+        let request = tokio::spawn(async move {
+            sharing
+                .request_access(PUBLISHER, COMPONENT, PHOTOS, "album")
+                .await
+        });
+
+        let prompt = loop {
+            if let AppEvent::PermissionRequested { prompt, .. } = fixture.events.recv().await? {
+                break prompt;
+            }
+        };
+        assert_eq!(prompt.app_id, PUBLISHER);
+        assert!(prompt.rationale.contains("Photos in the shared album"));
+        fixture.permissions.respond(&prompt.id, allow, true, None)?;
+        request.await?
+    }
+
+    fn assert_denied<T: std::fmt::Debug>(result: Result<T>) {
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<OsnovaError>().unwrap();
+        assert_eq!(err.rpc_code(), PERMISSION_DENIED_ERROR_CODE);
+    }
+
+    fn audited(fixture: &Fixture, action: AuditAction) -> Result<usize> {
+        let filter = AuditFilter {
+            action: Some(action),
+            ..AuditFilter::default()
+        };
+        Ok(fixture.audit.list(&filter, PageRequest::default())?.total)
+    }
+
+    #[tokio::test]
+    async fn test_grant_then_read() -> Result<()> {
+        let mut fixture = fixture()?;
+        assert_denied(fixture.sharing.list(PUBLISHER, PHOTOS, "album"));
+
+        let grant = request_album(&mut fixture, true).await?;
+        assert_eq!(grant.consumer_app_id, PUBLISHER);
+
+        let sharing = &fixture.sharing;
+        assert_eq!(
+            sharing.list(PUBLISHER, PHOTOS, "album")?,
+            vec!["2024/hike.jpg", "beach.jpg"]
+        );
+        assert_eq!(
+            sharing.read(PUBLISHER, PHOTOS, "album", "2024/hike.jpg")?,
+            b"hike"
+        );
+        assert_eq!(sharing.grants(Some(PHOTOS))?, vec![grant.clone()]);
+        assert_eq!(audited(&fixture, AuditAction::PermissionGranted)?, 1);
+
+        // The grant is persisted, and asking again does not prompt
+        let restarted =
+            SharingService::new(fixture._temp.path(), "user", fixture.permissions.clone())?;
+        assert_eq!(
+            restarted
+                .request_access(PUBLISHER, COMPONENT, PHOTOS, "album")
+                .await?,
+            grant
+        );
+
+        // The remembered answer did not turn into a blanket grant
+        assert!(fixture.permissions.grants(None)?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_denied_request_grants_nothing() -> Result<()> {
+        let mut fixture = fixture()?;
+
+        assert_denied(request_album(&mut fixture, false).await);
+        assert!(fixture.sharing.grants(None)?.is_empty());
+        assert_denied(
+            fixture
+                .sharing
+                .read(PUBLISHER, PHOTOS, "album", "beach.jpg"),
+        );
+
+        // Unknown offers are rejected before prompting
+        assert!(fixture
+            .sharing
+            .request_access(PUBLISHER, COMPONENT, PHOTOS, "contacts")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_revocation_cuts_access_immediately() -> Result<()> {
+        let mut fixture = fixture()?;
+        request_album(&mut fixture, true).await?;
+        let sharing = &fixture.sharing;
+        sharing.read(PUBLISHER, PHOTOS, "album", "beach.jpg")?;
+
+        assert!(sharing.revoke(PHOTOS, "album", PUBLISHER)?);
+        assert_denied(sharing.read(PUBLISHER, PHOTOS, "album", "beach.jpg"));
+        assert_denied(sharing.list(PUBLISHER, PHOTOS, "album"));
+        assert!(!sharing.revoke(PHOTOS, "album", PUBLISHER)?);
+        assert_eq!(audited(&fixture, AuditAction::PermissionRevoked)?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_confined_to_the_offer_prefix() -> Result<()> {
+        let mut fixture = fixture()?;
+        request_album(&mut fixture, true).await?;
+        let sharing = &fixture.sharing;
+
+        for key in [
+            "../private/secret.jpg",
+            "../../keys/signing",
+            "2024/../../private/secret.jpg",
+            "/albums/private/secret.jpg",
+            "./beach.jpg",
+            "",
+        ] {
+            assert!(
+                sharing.read(PUBLISHER, PHOTOS, "album", key).is_err(),
+                "{} should be rejected",
+                key
+            );
+        }
+        assert!(sharing
+            .read(PUBLISHER, PHOTOS, "album", "secret.jpg")
+            .is_err());
+
+        // A grant for one app does not extend to another
+        assert_denied(sharing.read(PHOTOS, PHOTOS, "album", "beach.jpg"));
+
+        Ok(())
+    }
+}
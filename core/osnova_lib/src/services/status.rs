@@ -1,11 +1,20 @@
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::context::guest::{GuestGate, GuestSessionInfo};
+use crate::network::kill_switch::{self, NetworkKillSwitch};
+use crate::network::retry_budget::{self, Destination, RetryBudgetMetrics, RetryBudgets};
+use crate::platform::disk::DiskGuard;
 
 /// Server connection status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ServerStatus {
     /// Not connected to server (stand-alone mode)
+    #[default]
     Disconnected,
     /// Connected to server
     Connected,
@@ -15,14 +24,8 @@ pub enum ServerStatus {
     Failed,
 }
 
-impl Default for ServerStatus {
-    fn default() -> Self {
-        Self::Disconnected
-    }
-}
-
 /// Server status response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerStatusResponse {
     /// Current connection status
     pub status: ServerStatus,
@@ -81,10 +84,59 @@ impl ServerStatusResponse {
     }
 }
 
+/// Health of a checked resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    /// Operating normally
+    Healthy,
+    /// Working, but close to failing
+    Degraded,
+}
+
+/// Free disk space on the data volume
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealth {
+    /// Directory checked
+    pub path: PathBuf,
+    /// Bytes available
+    pub free_bytes: u64,
+    /// Free space large writes must leave behind
+    pub min_free_bytes: u64,
+    /// Degraded below twice the minimum, before writes start failing
+    pub state: HealthState,
+}
+
+/// Whether Osnova may use the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkMode {
+    /// Network activity is allowed
+    Online,
+    /// The user switched Osnova offline; no network activity
+    OfflineByUser,
+}
+
+/// Network status shown in the status bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    /// Whether Osnova may use the network
+    pub mode: NetworkMode,
+    /// Destinations failing fast after retries used up their budget
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unavailable: Vec<Destination>,
+}
+
 /// Status management service
 ///
 /// Provides OpenRPC methods:
 /// - `status.getServer` - Get current server connection status
+/// - `status.getDiskHealth` - Free disk space on the data volume
+/// - `status.getNetwork` - Whether the user switched Osnova offline
+/// - `status.getRetryBudgets` - Retry budget and circuit of each destination
+/// - `status.getGuestSession` - The guest session running, if any
 ///
 /// This service tracks the connection state between client and server.
 /// In stand-alone mode, status is always Disconnected.
@@ -108,6 +160,10 @@ pub struct StatusService {
     // In future: track actual server connection state
     status: ServerStatus,
     server_address: Option<String>,
+    disk: Option<(PathBuf, DiskGuard)>,
+    kill_switch: Arc<NetworkKillSwitch>,
+    retry_budgets: Arc<RetryBudgets>,
+    guest: GuestGate,
 }
 
 impl StatusService {
@@ -118,9 +174,93 @@ impl StatusService {
         Self {
             status: ServerStatus::Disconnected,
             server_address: None,
+            disk: None,
+            kill_switch: kill_switch::shared(),
+            retry_budgets: retry_budget::shared(),
+            guest: GuestGate::default(),
         }
     }
 
+    /// Report the state of `kill_switch` instead of the process-wide one
+    pub fn with_kill_switch(mut self, kill_switch: Arc<NetworkKillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Report the state of `retry_budgets` instead of the process-wide ones
+    pub fn with_retry_budgets(mut self, retry_budgets: Arc<RetryBudgets>) -> Self {
+        self.retry_budgets = retry_budgets;
+        self
+    }
+
+    /// Report the guest sessions of the context `guest` belongs to
+    pub fn with_guest_gate(mut self, guest: GuestGate) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Report disk health for the volume holding `path`
+    pub fn with_disk_check<P: Into<PathBuf>>(mut self, path: P, guard: DiskGuard) -> Self {
+        self.set_disk_check(path, guard);
+        self
+    }
+
+    /// Report disk health for the volume holding `path`
+    ///
+    /// Replaces any earlier check, e.g. when the threshold is reconfigured.
+    pub fn set_disk_check<P: Into<PathBuf>>(&mut self, path: P, guard: DiskGuard) {
+        self.disk = Some((path.into(), guard));
+    }
+
+    /// Free disk space on the data volume (OpenRPC: status.getDiskHealth)
+    ///
+    /// Returns `None` if no disk check is configured.
+    pub fn get_disk_health(&self) -> Result<Option<DiskHealth>> {
+        let Some((path, guard)) = &self.disk else {
+            return Ok(None);
+        };
+
+        let free_bytes = guard.free_space(path)?;
+        let state = if free_bytes < guard.min_free().saturating_mul(2) {
+            HealthState::Degraded
+        } else {
+            HealthState::Healthy
+        };
+
+        Ok(Some(DiskHealth {
+            path: path.clone(),
+            free_bytes,
+            min_free_bytes: guard.min_free(),
+            state,
+        }))
+    }
+
+    /// Whether Osnova may use the network (OpenRPC: status.getNetwork)
+    pub fn get_network(&self) -> Result<NetworkStatus> {
+        let mode = if self.kill_switch.is_engaged() {
+            NetworkMode::OfflineByUser
+        } else {
+            NetworkMode::Online
+        };
+        Ok(NetworkStatus {
+            mode,
+            unavailable: self.retry_budgets.unavailable(),
+        })
+    }
+
+    /// Retry budget and circuit of each destination (OpenRPC:
+    /// status.getRetryBudgets)
+    pub fn get_retry_budgets(&self) -> Result<Vec<RetryBudgetMetrics>> {
+        Ok(self.retry_budgets.metrics())
+    }
+
+    /// The guest session running, if any (OpenRPC: status.getGuestSession)
+    ///
+    /// The UI shows an indicator, with the time left, while one runs.
+    pub fn get_guest_session(&self) -> Result<Option<GuestSessionInfo>> {
+        Ok(self.guest.session())
+    }
+
     /// Get the current server connection status (OpenRPC: status.getServer)
     ///
     /// Returns the current connection state and server information.
@@ -301,4 +441,79 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_network_reports_offline_by_user() -> Result<()> {
+        let switch = Arc::new(NetworkKillSwitch::new());
+        let service = StatusService::new().with_kill_switch(switch.clone());
+        assert_eq!(service.get_network()?.mode, NetworkMode::Online);
+
+        switch.engage();
+        let status = service.get_network()?;
+        assert_eq!(status.mode, NetworkMode::OfflineByUser);
+        assert_eq!(
+            serde_json::to_value(&status)?,
+            serde_json::json!({"mode": "offlineByUser"})
+        );
+
+        switch.release();
+        assert_eq!(service.get_network()?.mode, NetworkMode::Online);
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_reports_open_circuits() -> Result<()> {
+        use crate::network::retry_budget::{CircuitState, RetryBudgetPolicy};
+
+        let budgets = Arc::new(RetryBudgets::new(RetryBudgetPolicy {
+            capacity: 1,
+            ..RetryBudgetPolicy::default()
+        }));
+        let service = StatusService::new().with_retry_budgets(budgets.clone());
+        assert!(service.get_network()?.unavailable.is_empty());
+        assert!(service.get_retry_budgets()?.is_empty());
+
+        let attempt = budgets.admit(&Destination::Server, "sync")?;
+        attempt.retry()?;
+        assert!(attempt.retry().is_err());
+
+        let status = service.get_network()?;
+        assert_eq!(status.unavailable, vec![Destination::Server]);
+        assert_eq!(
+            serde_json::to_value(&status)?,
+            serde_json::json!({"mode": "online", "unavailable": [{"kind": "server"}]})
+        );
+
+        let metrics = service.get_retry_budgets()?;
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].state, CircuitState::Open);
+        assert_eq!(metrics[0].retries, 1);
+        assert_eq!(metrics[0].opened, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_health_degrades_below_twice_threshold() -> Result<()> {
+        use crate::platform::disk::FixedDiskSpace;
+        use std::sync::Arc;
+
+        assert!(StatusService::new().get_disk_health()?.is_none());
+
+        let disk = Arc::new(FixedDiskSpace::new(2_000));
+        let guard = DiskGuard::new(1_000).with_provider(disk.clone());
+        let service = StatusService::new().with_disk_check("/data", guard);
+
+        let health = service.get_disk_health()?.unwrap();
+        assert_eq!(health.state, HealthState::Healthy);
+        assert_eq!(health.free_bytes, 2_000);
+        assert_eq!(health.min_free_bytes, 1_000);
+
+        disk.set(1_999);
+        assert_eq!(
+            service.get_disk_health()?.unwrap().state,
+            HealthState::Degraded
+        );
+
+        Ok(())
+    }
 }
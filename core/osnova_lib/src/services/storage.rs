@@ -0,0 +1,952 @@
+//! Local storage management
+//!
+//! Handles:
+//! - Factory reset: wiping the data, cache, and config directories back to a
+//!   pristine first-run state, as opposed to identity deletion which only
+//!   removes the root identity
+//! - Storage overview: free space on the volume of each directory
+//! - Adoption: taking over storage created by another install (see
+//!   [`crate::storage::ownership`]), for deliberate migrations between OS
+//!   accounts
+//! - Compaction: reporting, and on request removing, files the encrypted
+//!   storage no longer uses (see [`crate::storage::compaction`])
+//! - Classification: tagging files written before data classes existed
+//!   (see [`crate::storage::classification`])
+//!
+//! A reset is a two-step operation. [`StorageService::begin_factory_reset`]
+//! issues a short-lived [`ResetChallenge`] that the settings UI must hand
+//! back to [`StorageService::factory_reset`], so a single stray call can
+//! never wipe the device.
+
+use anyhow::{bail, Result};
+use bip39::rand::{thread_rng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::platform::disk::{SharedDiskSpace, SystemDiskSpace, DEFAULT_MIN_FREE_BYTES};
+use crate::platform::paths;
+use crate::services::identity::{IdentityService, OnboardingState};
+use crate::services::processes::ProcessService;
+use crate::storage::classification::{self, DataClass};
+use crate::storage::{ownership, CompactPolicy, CompactReport, FileStorage};
+use crate::time;
+use crate::util::format::{DisplayContext, Localize};
+
+/// Seconds a reset challenge stays valid after it is issued
+pub const RESET_CHALLENGE_TTL_SECS: u64 = 120;
+
+/// Subdirectory of the cache root holding downloaded components
+const COMPONENT_CACHE_SUBDIR: &str = "components";
+
+/// Directories of the data root holding one subdirectory per user
+pub const USER_NAMESPACE_AREAS: &[&str] = &["navigation", "ui", "launcher", "catalog"];
+
+/// One of the directories Osnova owns on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageRoot {
+    /// Application data (identity, databases, key cocoons)
+    Data,
+    /// Downloaded components and other re-fetchable data
+    Cache,
+    /// User configuration
+    Config,
+}
+
+/// Locations of the directories a factory reset wipes
+///
+/// Defaults to the platform directories; tests and custom storage paths
+/// override individual roots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageRoots {
+    /// Data directory
+    pub data: PathBuf,
+    /// Cache directory
+    pub cache: PathBuf,
+    /// Config directory
+    pub config: PathBuf,
+}
+
+impl StorageRoots {
+    /// Roots from [`crate::platform::paths`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a platform directory cannot be determined
+    pub fn from_platform() -> Result<Self> {
+        Ok(Self {
+            data: paths::get_data_dir()?,
+            cache: paths::get_cache_dir()?,
+            config: paths::get_config_dir()?,
+        })
+    }
+
+    /// Override the data directory
+    pub fn with_data_dir<P: Into<PathBuf>>(mut self, data: P) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    fn entries(&self) -> [(StorageRoot, &Path); 3] {
+        [
+            (StorageRoot::Data, &self.data),
+            (StorageRoot::Cache, &self.cache),
+            (StorageRoot::Config, &self.config),
+        ]
+    }
+}
+
+/// Free space on the volume holding a storage root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeSpace {
+    /// Storage root
+    pub root: StorageRoot,
+    /// Directory of the root
+    pub path: PathBuf,
+    /// Bytes available on its volume
+    pub free_bytes: u64,
+    /// Free space formatted for display, once localized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<VolumeSpaceDisplay>,
+}
+
+/// [`VolumeSpace`] formatted for display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeSpaceDisplay {
+    /// Space available on the volume
+    pub free: String,
+}
+
+impl Localize for VolumeSpace {
+    fn localize(&mut self, context: &DisplayContext) {
+        self.display = Some(VolumeSpaceDisplay {
+            free: context.bytes(self.free_bytes),
+        });
+    }
+}
+
+/// Confirmation token for a pending factory reset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetChallenge {
+    /// Random token that must be echoed back
+    pub token: String,
+    /// Unix time after which the challenge is rejected
+    pub expires_at: u64,
+}
+
+/// A path that could not be removed or recreated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathFailure {
+    /// Path the operation failed on
+    pub path: PathBuf,
+    /// Error description
+    pub error: String,
+}
+
+/// Result of wiping one storage root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryReport {
+    /// Which root this is
+    pub root: StorageRoot,
+    /// Location of the root
+    pub path: PathBuf,
+    /// Total size of the regular files removed
+    pub bytes_removed: u64,
+    /// Paths that could not be removed, or the root if it was refused
+    pub failures: Vec<PathFailure>,
+}
+
+/// Result of a factory reset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetReport {
+    /// One report per storage root
+    pub directories: Vec<DirectoryReport>,
+    /// Onboarding state after the reset
+    pub onboarding: OnboardingState,
+}
+
+impl ResetReport {
+    /// Whether every path was removed and every root recreated
+    pub fn is_complete(&self) -> bool {
+        self.directories.iter().all(|d| d.failures.is_empty())
+    }
+
+    /// Total bytes removed across all roots
+    pub fn bytes_removed(&self) -> u64 {
+        self.directories.iter().map(|d| d.bytes_removed).sum()
+    }
+}
+
+/// Storage management service
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::storage::{StorageRoots, StorageService};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let service = StorageService::new(StorageRoots::from_platform()?);
+///
+/// // The UI shows a confirmation dialog, then hands the challenge back
+/// let challenge = service.begin_factory_reset();
+/// let report = service.factory_reset(challenge)?;
+/// println!("Removed {} bytes", report.bytes_removed());
+/// # Ok(())
+/// # }
+/// ```
+pub struct StorageService {
+    roots: StorageRoots,
+    processes: Option<Arc<ProcessService>>,
+    disk_space: SharedDiskSpace,
+    pending: Mutex<Option<ResetChallenge>>,
+    install_id: String,
+    last_compaction: Mutex<Option<CompactReport>>,
+}
+
+impl StorageService {
+    /// Create a storage service over the given roots
+    pub fn new(roots: StorageRoots) -> Self {
+        Self {
+            roots,
+            processes: None,
+            disk_space: Arc::new(SystemDiskSpace),
+            pending: Mutex::new(None),
+            install_id: ownership::local_install_id().to_string(),
+            last_compaction: Mutex::new(None),
+        }
+    }
+
+    /// Adopt storage for a specific install instead of this OS user's
+    pub fn with_install_id(mut self, install_id: &str) -> Self {
+        self.install_id = install_id.to_string();
+        self
+    }
+
+    /// Stop supervised backend processes before a reset
+    pub fn with_processes(mut self, processes: Arc<ProcessService>) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
+    /// Use a specific free space provider for [`Self::overview`]
+    pub fn with_disk_space(mut self, disk_space: SharedDiskSpace) -> Self {
+        self.disk_space = disk_space;
+        self
+    }
+
+    /// Storage roots managed by this service
+    pub fn roots(&self) -> &StorageRoots {
+        &self.roots
+    }
+
+    /// Free space on the volume of each storage root
+    /// (OpenRPC: storage.overview)
+    pub fn overview(&self) -> Result<Vec<VolumeSpace>> {
+        self.roots
+            .entries()
+            .into_iter()
+            .map(|(root, path)| {
+                Ok(VolumeSpace {
+                    root,
+                    path: path.to_path_buf(),
+                    free_bytes: self.disk_space.free_space(path)?,
+                    display: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Report or remove files the encrypted storage no longer uses
+    /// (OpenRPC: storage.compact)
+    ///
+    /// Per-user directories of users other than `live_users` count as
+    /// orphaned. While the data volume has less than
+    /// [`DEFAULT_MIN_FREE_BYTES`] free, regenerable cache data counts as
+    /// removable too. With `report_only` nothing changes; removal is meant
+    /// for an explicit action on the storage screen. The report is kept for
+    /// [`Self::last_compaction`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data root belongs to another install or
+    /// cannot be read
+    pub fn compact(&self, live_users: &[String], report_only: bool) -> Result<CompactReport> {
+        let under_pressure = self
+            .disk_space
+            .free_space(&self.roots.data)
+            .is_ok_and(|free| free < DEFAULT_MIN_FREE_BYTES);
+        let policy = USER_NAMESPACE_AREAS
+            .iter()
+            .fold(CompactPolicy::default(), |policy, area| {
+                policy.with_namespaces(*area, live_users.iter().cloned())
+            })
+            .with_evict_regenerable(under_pressure);
+        let storage = FileStorage::new_for_install(&self.roots.data, &self.install_id)?;
+        let report = storage.compact(&policy, report_only)?;
+        *self.last_compaction.lock().unwrap() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Tag the data written before data classes existed
+    ///
+    /// Runs at every start; see [`classification::classify_known_paths`].
+    /// Returns the files tagged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data root belongs to another install or
+    /// its classification index cannot be updated
+    pub fn classify_known_paths(&self) -> Result<BTreeMap<PathBuf, DataClass>> {
+        let storage = FileStorage::new_for_install(&self.roots.data, &self.install_id)?;
+        classification::classify_known_paths(&storage)
+    }
+
+    /// Report of the last compaction run, if any since startup
+    /// (OpenRPC: storage.lastCompaction)
+    pub fn last_compaction(&self) -> Option<CompactReport> {
+        self.last_compaction.lock().unwrap().clone()
+    }
+
+    /// Take over storage created by another install
+    ///
+    /// For deliberate migrations, e.g. after copying a data directory from
+    /// another OS account. Every database and storage root under the data,
+    /// cache, and config roots owned by `install_id` is handed to this
+    /// install; storage owned by any other install is left alone. Naming
+    /// the previous owner keeps a takeover from happening by accident.
+    ///
+    /// Returns the owner locks that were rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `install_id` is this install, or owns nothing
+    /// under the roots
+    pub fn adopt(&self, install_id: &str) -> Result<Vec<PathBuf>> {
+        if install_id == self.install_id {
+            bail!(
+                "Storage owned by install {} already belongs to this install",
+                install_id
+            );
+        }
+
+        let mut locks = Vec::new();
+        for (index, (_, path)) in self.roots.entries().into_iter().enumerate() {
+            if !self.roots.entries()[..index]
+                .iter()
+                .any(|(_, p)| *p == path)
+            {
+                Self::collect_owner_locks(path, &mut locks)?;
+            }
+        }
+
+        let mut adopted = Vec::new();
+        for lock in locks {
+            if ownership::adopt(&lock, install_id, &self.install_id)? {
+                adopted.push(lock);
+            }
+        }
+        if adopted.is_empty() {
+            bail!(
+                "No storage owned by install {} under {}",
+                install_id,
+                self.roots.data.display()
+            );
+        }
+        Ok(adopted)
+    }
+
+    /// Issue a challenge for [`Self::factory_reset`]
+    ///
+    /// Replaces any challenge issued earlier.
+    pub fn begin_factory_reset(&self) -> ResetChallenge {
+        let mut token = [0u8; 16];
+        thread_rng().fill_bytes(&mut token);
+
+        let challenge = ResetChallenge {
+            token: hex::encode(token),
+            expires_at: time::now_unix() + RESET_CHALLENGE_TTL_SECS,
+        };
+        *self.pending.lock().unwrap() = Some(challenge.clone());
+        challenge
+    }
+
+    /// Wipe all local state back to a first-run install
+    ///
+    /// Stops running backends, then removes everything under the data,
+    /// cache, and config roots and recreates them empty. Symlinks are
+    /// removed without being followed, and a root that is itself a symlink
+    /// is refused, so nothing outside the roots is touched.
+    ///
+    /// Services holding SQLite connections into the data root must be
+    /// dropped before calling this so their connections close cleanly.
+    ///
+    /// A path that cannot be removed is recorded in the report and the
+    /// reset carries on with the rest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the challenge does not match the one issued by
+    /// [`Self::begin_factory_reset`] or has expired, or if running backends
+    /// cannot be stopped. Nothing is removed in either case.
+    pub fn factory_reset(&self, confirmation: ResetChallenge) -> Result<ResetReport> {
+        // A challenge is single-use, whether or not it matches
+        let pending = self.pending.lock().unwrap().take();
+        match pending {
+            Some(expected) if expected.token == confirmation.token => {
+                if time::now_unix() > expected.expires_at {
+                    bail!("Factory reset challenge expired");
+                }
+            }
+            _ => bail!("Invalid factory reset challenge"),
+        }
+
+        if let Some(processes) = &self.processes {
+            processes.stop_all()?;
+        }
+
+        // The install id names this OS user, not its data; a new one would
+        // make the storage recreated after the reset look foreign
+        let install_id_file = self.roots.config.join(ownership::INSTALL_ID_FILE);
+        let install_id = fs::read(&install_id_file).ok();
+
+        let mut directories = Vec::new();
+        for (index, (root, path)) in self.roots.entries().into_iter().enumerate() {
+            // Platforms may share one directory between roots
+            let seen = self.roots.entries()[..index]
+                .iter()
+                .any(|(_, p)| *p == path);
+            let mut report = DirectoryReport {
+                root,
+                path: path.to_path_buf(),
+                bytes_removed: 0,
+                failures: Vec::new(),
+            };
+            if !seen {
+                Self::wipe_root(path, &mut report);
+            }
+            directories.push(report);
+        }
+        if let Some(install_id) = install_id {
+            if let Err(e) = fs::write(&install_id_file, install_id) {
+                if let Some(config) = directories
+                    .iter_mut()
+                    .find(|d| d.root == StorageRoot::Config)
+                {
+                    config.fail(&install_id_file, e);
+                }
+            }
+        }
+
+        // An unusable data root cannot hold an identity either
+        let onboarding = IdentityService::new(&self.roots.data)
+            .and_then(|identity| identity.onboarding_state())
+            .unwrap_or(OnboardingState::NeedsIdentity);
+
+        Ok(ResetReport {
+            directories,
+            onboarding,
+        })
+    }
+
+    // Private helper methods
+
+    /// Find owner locks under `dir` without following symlinks
+    fn collect_owner_locks(dir: &Path, locks: &mut Vec<PathBuf>) -> Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                Self::collect_owner_locks(&entry.path(), locks)?;
+            } else if file_type.is_file() && ownership::is_owner_lock(&entry.path()) {
+                locks.push(entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty a root and recreate its skeleton
+    fn wipe_root(path: &Path, report: &mut DirectoryReport) {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                report.fail(path, "Refusing to reset a symlinked directory");
+                return;
+            }
+            Ok(meta) if !meta.is_dir() => {
+                report.fail(path, "Not a directory");
+                return;
+            }
+            Ok(_) => {
+                Self::remove_contents(path, report);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                report.fail(path, e);
+                return;
+            }
+        }
+
+        let mut skeleton = vec![path.to_path_buf()];
+        if report.root == StorageRoot::Cache {
+            skeleton.push(path.join(COMPONENT_CACHE_SUBDIR));
+        }
+        for dir in skeleton {
+            if let Err(e) = fs::create_dir_all(&dir) {
+                report.fail(&dir, e);
+            }
+        }
+    }
+
+    /// Remove everything inside `dir` without following symlinks
+    ///
+    /// Returns whether the directory is now empty.
+    fn remove_contents(dir: &Path, report: &mut DirectoryReport) -> bool {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                report.fail(dir, e);
+                return false;
+            }
+        };
+
+        let mut empty = true;
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    report.fail(dir, e);
+                    empty = false;
+                    continue;
+                }
+            };
+            // symlink_metadata reports links as links, never as their target
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    report.fail(&path, e);
+                    empty = false;
+                    continue;
+                }
+            };
+
+            let result = if meta.is_dir() {
+                if !Self::remove_contents(&path, report) {
+                    empty = false;
+                    continue;
+                }
+                fs::remove_dir(&path)
+            } else {
+                // Links count as nothing; their targets are never touched
+                let size = if meta.is_file() { meta.len() } else { 0 };
+                fs::remove_file(&path).map(|()| report.bytes_removed += size)
+            };
+            if let Err(e) = result {
+                report.fail(&path, e);
+                empty = false;
+            }
+        }
+        empty
+    }
+}
+
+impl DirectoryReport {
+    fn fail<E: ToString>(&mut self, path: &Path, error: E) {
+        self.failures.push(PathFailure {
+            path: path.to_path_buf(),
+            error: error.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::CompactCategory;
+    use tempfile::TempDir;
+
+    fn create_roots(temp: &TempDir) -> StorageRoots {
+        StorageRoots {
+            data: temp.path().join("data"),
+            cache: temp.path().join("cache"),
+            config: temp.path().join("config"),
+        }
+    }
+
+    fn populate(roots: &StorageRoots) -> Result<()> {
+        fs::create_dir_all(roots.data.join("apps/com.example"))?;
+        fs::write(roots.data.join("osnova.db"), vec![0u8; 100])?;
+        fs::write(
+            roots.data.join("apps/com.example/state.json"),
+            vec![0u8; 20],
+        )?;
+        fs::create_dir_all(roots.cache.join("components"))?;
+        fs::write(roots.cache.join("components/app.tar.gz"), vec![0u8; 1000])?;
+        fs::create_dir_all(&roots.config)?;
+        fs::write(roots.config.join("settings.json"), vec![0u8; 7])?;
+        Ok(())
+    }
+
+    fn is_empty_dir(path: &Path) -> bool {
+        fs::read_dir(path)
+            .map(|mut e| e.next().is_none())
+            .unwrap_or(false)
+    }
+
+    fn report_for(report: &ResetReport, root: StorageRoot) -> &DirectoryReport {
+        report.directories.iter().find(|d| d.root == root).unwrap()
+    }
+
+    #[test]
+    fn test_factory_reset_wipes_to_pristine_state() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        populate(&roots)?;
+        let service = StorageService::new(roots.clone());
+
+        let challenge = service.begin_factory_reset();
+        let report = service.factory_reset(challenge)?;
+
+        assert!(report.is_complete());
+        assert_eq!(report_for(&report, StorageRoot::Data).bytes_removed, 120);
+        assert_eq!(report_for(&report, StorageRoot::Cache).bytes_removed, 1000);
+        assert_eq!(report_for(&report, StorageRoot::Config).bytes_removed, 7);
+        assert_eq!(report.bytes_removed(), 1127);
+
+        assert!(is_empty_dir(&roots.data));
+        assert!(is_empty_dir(&roots.config));
+        assert!(is_empty_dir(&roots.cache.join("components")));
+        assert_eq!(fs::read_dir(&roots.cache)?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_factory_reset_requires_issued_challenge() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        populate(&roots)?;
+        let service = StorageService::new(roots.clone());
+
+        // No challenge issued
+        let forged = ResetChallenge {
+            token: "00".repeat(16),
+            expires_at: u64::MAX,
+        };
+        assert!(service.factory_reset(forged.clone()).is_err());
+
+        // Wrong token consumes the pending challenge
+        let challenge = service.begin_factory_reset();
+        assert!(service.factory_reset(forged).is_err());
+        assert!(service.factory_reset(challenge).is_err());
+
+        // Expired challenge
+        let challenge = service.begin_factory_reset();
+        service.pending.lock().unwrap().as_mut().unwrap().expires_at = 0;
+        assert!(service.factory_reset(challenge).is_err());
+
+        assert!(roots.data.join("osnova.db").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_factory_reset_does_not_follow_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        populate(&roots)?;
+
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(&outside)?;
+        fs::write(outside.join("precious.txt"), b"keep me")?;
+        symlink(&outside, roots.data.join("linked-dir"))?;
+        symlink(
+            outside.join("precious.txt"),
+            roots.config.join("linked-file"),
+        )?;
+
+        // A root that is itself a symlink is left alone
+        let elsewhere = temp.path().join("elsewhere");
+        fs::create_dir_all(&elsewhere)?;
+        fs::write(elsewhere.join("cached.bin"), b"keep me too")?;
+        fs::remove_dir_all(&roots.cache)?;
+        symlink(&elsewhere, &roots.cache)?;
+
+        let service = StorageService::new(roots.clone());
+        let report = service.factory_reset(service.begin_factory_reset())?;
+
+        assert!(is_empty_dir(&roots.data));
+        assert!(is_empty_dir(&roots.config));
+        assert_eq!(fs::read(outside.join("precious.txt"))?, b"keep me");
+        assert_eq!(fs::read(elsewhere.join("cached.bin"))?, b"keep me too");
+
+        let cache = report_for(&report, StorageRoot::Cache);
+        assert_eq!(cache.failures.len(), 1);
+        assert_eq!(cache.failures[0].path, roots.cache);
+        assert!(report_for(&report, StorageRoot::Data).failures.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_factory_reset_reports_partial_failure() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        populate(&roots)?;
+
+        // Config root replaced by a file cannot be reset
+        fs::remove_dir_all(&roots.config)?;
+        fs::write(&roots.config, b"not a directory")?;
+
+        let service = StorageService::new(roots.clone());
+        let report = service.factory_reset(service.begin_factory_reset())?;
+
+        assert!(!report.is_complete());
+        let config = report_for(&report, StorageRoot::Config);
+        assert_eq!(config.failures.len(), 1);
+        assert_eq!(config.failures[0].path, roots.config);
+        assert!(roots.config.is_file());
+
+        // The other roots were still wiped
+        assert!(is_empty_dir(&roots.data));
+        assert_eq!(report_for(&report, StorageRoot::Cache).bytes_removed, 1000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_factory_reset_handles_shared_roots() -> Result<()> {
+        let temp = TempDir::new()?;
+        let mut roots = create_roots(&temp);
+        roots.config = roots.data.clone();
+        populate(&roots)?;
+
+        let service = StorageService::new(roots.clone());
+        let report = service.factory_reset(service.begin_factory_reset())?;
+
+        assert!(report.is_complete());
+        assert_eq!(report_for(&report, StorageRoot::Data).bytes_removed, 127);
+        assert_eq!(report_for(&report, StorageRoot::Config).bytes_removed, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_factory_reset_restarts_onboarding() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+
+        let identity = IdentityService::new(&roots.data)?;
+        identity.create()?;
+        assert_eq!(identity.onboarding_state()?, OnboardingState::Complete);
+
+        let service = StorageService::new(roots.clone());
+        let report = service.factory_reset(service.begin_factory_reset())?;
+
+        assert_eq!(report.onboarding, OnboardingState::NeedsIdentity);
+        assert_eq!(identity.onboarding_state()?, OnboardingState::NeedsIdentity);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_removes_other_users_only_on_request() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        let storage = FileStorage::new(&roots.data)?;
+        let key = [1u8; 32];
+        storage.write("launcher/alice/layout.json", b"alice", &key)?;
+        storage.write("launcher/bob/layout.json", b"bob", &key)?;
+        storage.write("ui/bob/theme.json", b"bob", &key)?;
+        fs::write(roots.data.join("osnova.db"), b"db")?;
+        let service = StorageService::new(roots.clone());
+        let live = vec!["alice".to_string()];
+
+        assert_eq!(service.last_compaction(), None);
+        let report = service.compact(&live, true)?;
+        assert_eq!(
+            report.in_category(CompactCategory::OrphanedNamespace).len(),
+            2
+        );
+        assert!(storage.exists("launcher/bob/layout.json"));
+        assert_eq!(service.last_compaction(), Some(report));
+
+        let report = service.compact(&live, false)?;
+        assert!(!report.report_only);
+        assert!(report.items.iter().all(|item| item.removed));
+        assert!(!storage.exists("launcher/bob"));
+        assert!(!storage.exists("ui"));
+        assert_eq!(storage.read("launcher/alice/layout.json", &key)?, b"alice");
+        assert!(roots.data.join("osnova.db").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_evicts_regenerable_when_space_is_low() -> Result<()> {
+        use crate::platform::disk::FixedDiskSpace;
+
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        let storage = FileStorage::new(&roots.data)?;
+        let key = [1u8; 32];
+        storage.write("catalog/alice/launcher.json", b"catalog", &key)?;
+        storage.write("launcher/alice/layout.json", b"layout", &key)?;
+        let disk = Arc::new(FixedDiskSpace::new(DEFAULT_MIN_FREE_BYTES));
+        let service = StorageService::new(roots).with_disk_space(disk.clone());
+        let tagged = service.classify_known_paths()?;
+        assert_eq!(tagged.len(), 2);
+        let live = vec!["alice".to_string()];
+
+        assert!(service.compact(&live, false)?.items.is_empty());
+        disk.set(DEFAULT_MIN_FREE_BYTES - 1);
+        let report = service.compact(&live, false)?;
+        assert_eq!(
+            report
+                .in_category(CompactCategory::Regenerable)
+                .iter()
+                .map(|item| item.path.clone())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("catalog/alice/launcher.json")]
+        );
+        assert!(!storage.exists("catalog"));
+        assert!(storage.exists("launcher/alice/layout.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_overview_reports_free_space_per_root() -> Result<()> {
+        use crate::platform::disk::FixedDiskSpace;
+
+        let temp = TempDir::new()?;
+        let service = StorageService::new(create_roots(&temp))
+            .with_disk_space(Arc::new(FixedDiskSpace::new(4096)));
+
+        let overview = service.overview()?;
+        assert_eq!(
+            overview.iter().map(|v| v.root).collect::<Vec<_>>(),
+            vec![StorageRoot::Data, StorageRoot::Cache, StorageRoot::Config]
+        );
+        assert_eq!(overview[1].path, temp.path().join("cache"));
+        assert!(overview.iter().all(|v| v.free_bytes == 4096));
+
+        let context = DisplayContext::new(Default::default(), 0);
+        let cache = overview[1].clone().localized(&context);
+        assert_eq!(serde_json::to_value(&cache)?["display"]["free"], "4 KiB");
+        assert_eq!(cache.display.unwrap().free, "4 KiB");
+
+        // The real provider works on roots that do not exist yet
+        assert!(StorageService::new(create_roots(&temp)).overview().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_factory_reset_keeps_install_id() -> Result<()> {
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        populate(&roots)?;
+        let install_id = ownership::load_or_create_install_id(&roots.config)?;
+
+        let service = StorageService::new(roots.clone());
+        let report = service.factory_reset(service.begin_factory_reset())?;
+
+        assert!(report.is_complete());
+        assert!(!roots.config.join("settings.json").exists());
+        assert_eq!(
+            ownership::load_or_create_install_id(&roots.config)?,
+            install_id
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_installs_refuse_to_share_storage() -> Result<()> {
+        use crate::error::OsnovaError;
+        use crate::storage::{FileStorage, SqlStorage};
+
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        fs::create_dir_all(&roots.data)?;
+        let database = roots.data.join("osnova.db");
+
+        drop(SqlStorage::new_for_install(&database, "install-a")?);
+        FileStorage::new_for_install(&roots.data, "install-a")?.write("id", b"a", &[7; 32])?;
+
+        let refused = SqlStorage::new_for_install(&database, "install-b")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            refused.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::StorageOwnedElsewhere { owner, .. }) if owner == "install-a"
+        ));
+        assert!(FileStorage::new_for_install(&roots.data, "install-b").is_err());
+
+        // Locks outlive a crash; a stale lock of the same install is reused
+        fs::write(
+            FileStorage::temp_path(&ownership::database_lock_path(&database)),
+            b"{",
+        )?;
+        SqlStorage::new_for_install(&database, "install-a")?;
+        let files = FileStorage::new_for_install(&roots.data, "install-a")?;
+        assert_eq!(files.read("id", &[7; 32])?, b"a");
+        assert!(!files
+            .list_files("")?
+            .iter()
+            .any(|f| ownership::is_owner_lock(f)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adopt_hands_storage_to_this_install() -> Result<()> {
+        use crate::storage::{FileStorage, SqlStorage};
+
+        let temp = TempDir::new()?;
+        let roots = create_roots(&temp);
+        fs::create_dir_all(&roots.data)?;
+        let database = roots.data.join("osnova.db");
+        drop(SqlStorage::new_for_install(&database, "install-a")?);
+        FileStorage::new_for_install(&roots.data, "install-a")?.write("id", b"a", &[7; 32])?;
+        FileStorage::new_for_install(roots.cache.join("components"), "install-a")?.write(
+            "app.tar.gz",
+            b"app",
+            &[7; 32],
+        )?;
+        FileStorage::new_for_install(roots.data.join("other"), "install-c")?
+            .write("x", b"c", &[7; 32])?;
+
+        let service = StorageService::new(roots.clone()).with_install_id("install-b");
+        assert!(service.adopt("install-b").is_err());
+        assert!(service.adopt("install-d").is_err());
+
+        let adopted = service.adopt("install-a")?;
+        assert_eq!(adopted.len(), 3);
+        SqlStorage::new_for_install(&database, "install-b")?;
+        let files = FileStorage::new_for_install(&roots.data, "install-b")?;
+        assert_eq!(files.read("id", &[7; 32])?, b"a");
+
+        // The previous owner is now refused, and other installs' storage
+        // was left alone
+        assert!(SqlStorage::new_for_install(&database, "install-a").is_err());
+        assert!(FileStorage::new_for_install(roots.data.join("other"), "install-b").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,573 @@
+//! Long-running operations
+//!
+//! Downloads, backups, cache collection and the like register with the
+//! [`TaskRegistry`] so the shell can list everything in flight in one
+//! activity center. An operation gets a [`TaskHandle`] through which it
+//! reports progress and its outcome; the registry publishes every change
+//! as one `task-updated` event ([`TASK_UPDATED_EVENT`]), keeps the
+//! finished tasks as history, and cancels a task by cancelling the token
+//! the operation registered with.
+//!
+//! Operations that used to publish their own progress event pass it along
+//! with their progress ([`TaskHandle::progress_with`]); it is published
+//! right after the task update, so listeners of the old event keep working
+//! while the frontend moves to `task-updated`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use osnova_lib::models::task::{TaskCategory, TaskState};
+//! use osnova_lib::network::CancellationToken;
+//! use osnova_lib::services::TaskRegistry;
+//!
+//! let registry = Arc::new(TaskRegistry::new());
+//! let token = CancellationToken::new();
+//! let task = registry.register_cancellable("Back up photos", TaskCategory::Backup, token);
+//! task.progress(1, Some(4));
+//! registry.cancel(task.id()).unwrap();
+//! task.fail("cancelled");
+//!
+//! assert!(registry.list_active().is_empty());
+//! assert_eq!(registry.history(1)[0].state, TaskState::Cancelled);
+//! ```
+
+use anyhow::Result;
+use bip39::rand::{thread_rng, RngCore};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::events::OsnovaEvent;
+use crate::models::task::{TaskCategory, TaskInfo, TaskState, TASK_HISTORY};
+use crate::network::CancellationToken;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Event name under which task changes reach the frontend
+pub const TASK_UPDATED_EVENT: &str = "task-updated";
+
+/// Capacity of the task event channel before slow subscribers start lagging
+const TASK_CHANNEL_CAPACITY: usize = 256;
+
+/// Error recorded for a task whose handle was dropped without an outcome
+const ABANDONED: &str = "The operation ended without reporting an outcome";
+
+/// A registered task that has not ended
+struct ActiveTask {
+    info: TaskInfo,
+    cancellation: Option<CancellationToken>,
+}
+
+/// Registry of long-running operations
+///
+/// Provides OpenRPC methods:
+/// - `tasks.list` - Tasks in flight
+/// - `tasks.history` - Finished tasks, newest first
+/// - `tasks.cancel` - Cancel a task
+///
+/// Shared as `Arc<TaskRegistry>`; operations register through
+/// [`register`](Self::register) or
+/// [`register_cancellable`](Self::register_cancellable).
+pub struct TaskRegistry {
+    active: Mutex<BTreeMap<String, ActiveTask>>,
+    history: Mutex<VecDeque<TaskInfo>>,
+    storage: Option<Mutex<SqlStorage>>,
+    sender: broadcast::Sender<OsnovaEvent>,
+    clock: SharedClock,
+}
+
+impl TaskRegistry {
+    /// Create a registry keeping its history in memory
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(TASK_CHANNEL_CAPACITY);
+        Self {
+            active: Mutex::new(BTreeMap::new()),
+            history: Mutex::new(VecDeque::new()),
+            storage: None,
+            sender,
+            clock: time::default_clock(),
+        }
+    }
+
+    /// Keep the history in the database under `storage_path`, so it
+    /// survives restarts
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or read
+    pub fn with_persistence<P: AsRef<Path>>(mut self, storage_path: P) -> Result<Self> {
+        let storage = SqlStorage::new(storage_path.as_ref().join("osnova.db"))?;
+        let history = storage.list_task_history(TASK_HISTORY)?;
+        self.history = Mutex::new(history.into());
+        self.storage = Some(Mutex::new(storage));
+        Ok(self)
+    }
+
+    /// Replace the clock (for testing)
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Register an operation that cannot be cancelled
+    pub fn register(
+        self: &Arc<Self>,
+        name: impl Into<String>,
+        category: TaskCategory,
+    ) -> TaskHandle {
+        self.start(name.into(), category, None)
+    }
+
+    /// Register an operation that stops when `cancellation` is cancelled
+    pub fn register_cancellable(
+        self: &Arc<Self>,
+        name: impl Into<String>,
+        category: TaskCategory,
+        cancellation: CancellationToken,
+    ) -> TaskHandle {
+        self.start(name.into(), category, Some(cancellation))
+    }
+
+    /// Tasks in flight, oldest first (OpenRPC: tasks.list)
+    pub fn list_active(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self
+            .active
+            .lock()
+            .unwrap()
+            .values()
+            .map(|task| task.info.clone())
+            .collect();
+        tasks.sort_by(|a, b| a.started_at.cmp(&b.started_at).then(a.id.cmp(&b.id)));
+        tasks
+    }
+
+    /// Up to `limit` finished tasks, newest first (OpenRPC: tasks.history)
+    pub fn history(&self, limit: usize) -> Vec<TaskInfo> {
+        let history = self.history.lock().unwrap();
+        history.iter().take(limit).cloned().collect()
+    }
+
+    /// Cancel a task (OpenRPC: tasks.cancel)
+    ///
+    /// Cancels the token the operation registered with; the task ends as
+    /// cancelled once the operation stops.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no such task is running or it cannot be
+    /// cancelled
+    pub fn cancel(&self, task_id: &str) -> Result<()> {
+        let active = self.active.lock().unwrap();
+        let task = active
+            .get(task_id)
+            .ok_or_else(|| anyhow::anyhow!("No running task {}", task_id))?;
+        let Some(cancellation) = &task.cancellation else {
+            anyhow::bail!("Task {} cannot be cancelled", task_id);
+        };
+        cancellation.cancel();
+        Ok(())
+    }
+
+    /// Subscribe to `task-updated` events, each followed by the progress
+    /// event the operation published before tasks existed, if any
+    pub fn subscribe(&self) -> broadcast::Receiver<OsnovaEvent> {
+        self.sender.subscribe()
+    }
+
+    fn start(
+        self: &Arc<Self>,
+        name: String,
+        category: TaskCategory,
+        cancellation: Option<CancellationToken>,
+    ) -> TaskHandle {
+        let mut id = [0u8; 16];
+        thread_rng().fill_bytes(&mut id);
+        let info = TaskInfo {
+            id: hex::encode(id),
+            name,
+            category,
+            cancellable: cancellation.is_some(),
+            state: TaskState::Running,
+            completed: 0,
+            total: None,
+            message: None,
+            error: None,
+            started_at: self.clock.now_unix(),
+            finished_at: None,
+        };
+        self.publish(info.clone(), None);
+        self.active.lock().unwrap().insert(
+            info.id.clone(),
+            ActiveTask {
+                info: info.clone(),
+                cancellation: cancellation.clone(),
+            },
+        );
+
+        TaskHandle {
+            registry: self.clone(),
+            id: info.id,
+            cancellation,
+            finished: false,
+        }
+    }
+
+    /// Apply `change` to a running task and publish the result
+    fn update<F>(&self, task_id: &str, legacy: Option<OsnovaEvent>, change: F)
+    where
+        F: FnOnce(&mut TaskInfo),
+    {
+        let info = {
+            let mut active = self.active.lock().unwrap();
+            let Some(task) = active.get_mut(task_id) else {
+                return;
+            };
+            change(&mut task.info);
+            task.info.clone()
+        };
+        self.publish(info, legacy);
+    }
+
+    /// End a running task, moving it to the history
+    fn end(&self, task_id: &str, state: TaskState, message: Option<String>, error: Option<String>) {
+        let Some(task) = self.active.lock().unwrap().remove(task_id) else {
+            return;
+        };
+        let mut info = task.info;
+        info.state = state;
+        info.message = message.or(info.message);
+        info.error = error;
+        info.finished_at = Some(self.clock.now_unix());
+
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.lock().unwrap().record_task(&info, TASK_HISTORY) {
+                crate::log!(Warn, "Failed to record task {}: {:#}", info.id, e);
+            }
+        }
+        {
+            let mut history = self.history.lock().unwrap();
+            history.push_front(info.clone());
+            history.truncate(TASK_HISTORY);
+        }
+        self.publish(info, None);
+    }
+
+    fn publish(&self, info: TaskInfo, legacy: Option<OsnovaEvent>) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(OsnovaEvent::TaskUpdated(info));
+        if let Some(legacy) = legacy {
+            let _ = self.sender.send(legacy);
+        }
+    }
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An operation's handle on its task
+///
+/// Report progress as the operation advances, then end the task with
+/// [`complete`](Self::complete), [`fail`](Self::fail) or
+/// [`finish`](Self::finish). A handle dropped without an outcome, as when
+/// the operation's future is dropped, ends the task as cancelled if its
+/// token was cancelled and as failed otherwise.
+pub struct TaskHandle {
+    registry: Arc<TaskRegistry>,
+    id: String,
+    cancellation: Option<CancellationToken>,
+    finished: bool,
+}
+
+impl TaskHandle {
+    /// Task identifier
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the task was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Report `completed` units of work done out of `total`, if known
+    pub fn progress(&self, completed: u64, total: Option<u64>) {
+        self.registry.update(&self.id, None, |info| {
+            info.completed = completed;
+            info.total = total;
+        });
+    }
+
+    /// Report progress, publishing the operation's own progress event
+    /// right after the task update
+    pub fn progress_with(
+        &self,
+        completed: u64,
+        total: Option<u64>,
+        legacy: impl Into<OsnovaEvent>,
+    ) {
+        self.registry.update(&self.id, Some(legacy.into()), |info| {
+            info.completed = completed;
+            info.total = total;
+        });
+    }
+
+    /// Report what the operation is doing
+    pub fn message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.registry
+            .update(&self.id, None, |info| info.message = Some(message));
+    }
+
+    /// End the task successfully
+    pub fn complete(self) {
+        self.end(TaskState::Completed, None, None);
+    }
+
+    /// End the task successfully with a closing message
+    pub fn complete_with(self, message: impl Into<String>) {
+        self.end(TaskState::Completed, Some(message.into()), None);
+    }
+
+    /// End the task with an error, or as cancelled if its token was
+    /// cancelled
+    pub fn fail(self, error: impl Display) {
+        let state = match self.is_cancelled() {
+            true => TaskState::Cancelled,
+            false => TaskState::Failed,
+        };
+        self.end(state, None, Some(error.to_string()));
+    }
+
+    /// End the task according to the operation's result
+    pub fn finish<T, E: Display>(self, result: &std::result::Result<T, E>) {
+        match result {
+            Ok(_) => self.complete(),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn end(mut self, state: TaskState, message: Option<String>, error: Option<String>) {
+        self.finished = true;
+        self.registry.end(&self.id, state, message, error);
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let state = match self.is_cancelled() {
+            true => TaskState::Cancelled,
+            false => TaskState::Failed,
+        };
+        self.registry
+            .end(&self.id, state, None, Some(ABANDONED.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::ShellEvent;
+    use crate::services::apps::MaterializeProgress;
+    use crate::time::MockClock;
+    use tempfile::TempDir;
+
+    fn task_updates(events: &mut broadcast::Receiver<OsnovaEvent>) -> Vec<TaskInfo> {
+        let mut updates = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let OsnovaEvent::TaskUpdated(info) = event {
+                updates.push(info);
+            }
+        }
+        updates
+    }
+
+    #[test]
+    fn test_task_lifecycle() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let registry = Arc::new(TaskRegistry::new().with_clock(clock.clone()));
+        let mut events = registry.subscribe();
+
+        let task = registry.register("Collect cache garbage", TaskCategory::CacheGc);
+        let active = registry.list_active();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, task.id());
+        assert_eq!(active[0].state, TaskState::Running);
+        assert!(!active[0].cancellable);
+
+        task.progress(2, Some(5));
+        task.message("Sweeping extracted frontends");
+        assert_eq!(registry.list_active()[0].completed, 2);
+        clock.advance(std::time::Duration::from_secs(3));
+        task.complete_with("Freed 4 MB");
+
+        assert!(registry.list_active().is_empty());
+        let history = registry.history(10);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].state, TaskState::Completed);
+        assert_eq!(history[0].message.as_deref(), Some("Freed 4 MB"));
+        assert_eq!(history[0].finished_at, Some(1_003));
+
+        let states: Vec<(TaskState, u64)> = task_updates(&mut events)
+            .iter()
+            .map(|info| (info.state, info.completed))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (TaskState::Running, 0),
+                (TaskState::Running, 2),
+                (TaskState::Running, 2),
+                (TaskState::Completed, 2),
+            ]
+        );
+
+        // Failures keep their error; dropped handles end the task too
+        registry
+            .register("Download", TaskCategory::Download)
+            .finish(&Err::<(), _>("HTTP error 404"));
+        drop(registry.register("Refresh", TaskCategory::CatalogRefresh));
+        let history = registry.history(2);
+        assert_eq!(history[0].state, TaskState::Failed);
+        assert_eq!(history[0].error.as_deref(), Some(ABANDONED));
+        assert_eq!(history[1].error.as_deref(), Some("HTTP error 404"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_routes_to_token() {
+        let registry = Arc::new(TaskRegistry::new());
+        let token = CancellationToken::new();
+        let task =
+            registry.register_cancellable("Back up photos", TaskCategory::Backup, token.clone());
+
+        // The operation runs until its token is cancelled, then drops its
+        // handle on the way out
+        let operation = tokio::spawn(async move {
+            let _task = task;
+            token.cancelled().await;
+        });
+        let id = registry.list_active()[0].id.clone();
+        assert!(registry.list_active()[0].cancellable);
+        registry.cancel(&id).unwrap();
+        operation.await.unwrap();
+
+        assert!(registry.list_active().is_empty());
+        assert_eq!(registry.history(1)[0].state, TaskState::Cancelled);
+        assert!(registry.cancel(&id).is_err());
+
+        let gc = registry.register("Collect cache garbage", TaskCategory::CacheGc);
+        assert!(registry.cancel(gc.id()).is_err());
+        gc.complete();
+    }
+
+    #[test]
+    fn test_history_persists() -> Result<()> {
+        let temp = TempDir::new()?;
+        {
+            let registry = Arc::new(TaskRegistry::new().with_persistence(temp.path())?);
+            for n in 0..3 {
+                registry
+                    .register(format!("Download {}", n), TaskCategory::Download)
+                    .complete();
+            }
+            // Still running at exit; not part of the history
+            std::mem::forget(registry.register("Pending", TaskCategory::Download));
+        }
+
+        let registry = TaskRegistry::new().with_persistence(temp.path())?;
+        let names: Vec<String> = registry
+            .history(10)
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+        assert_eq!(names, vec!["Download 2", "Download 1", "Download 0"]);
+        assert_eq!(registry.history(1).len(), 1);
+        assert!(registry.list_active().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_progress_event_follows_update() {
+        let registry = Arc::new(TaskRegistry::new());
+        let task = registry.register("Download missing components", TaskCategory::Materialization);
+        let mut events = registry.subscribe();
+
+        let progress = MaterializeProgress {
+            app_id: "com.osnova.wallet".to_string(),
+            component_id: "ant://backend".to_string(),
+            completed: 2,
+            total: 5,
+            error: None,
+        };
+        task.progress_with(2, Some(5), progress.clone());
+
+        let update = events.try_recv().unwrap();
+        assert_eq!(update.name(), TASK_UPDATED_EVENT);
+        let legacy = events.try_recv().unwrap();
+        assert_eq!(legacy.name(), MaterializeProgress::NAME);
+        assert_eq!(
+            serde_json::to_value(&legacy).unwrap(),
+            serde_json::to_value(&progress).unwrap()
+        );
+        task.complete();
+    }
+
+    #[test]
+    fn test_concurrent_tasks_are_isolated() {
+        let registry = Arc::new(TaskRegistry::new());
+        let tokens: Vec<CancellationToken> = (0..8).map(|_| CancellationToken::new()).collect();
+
+        let workers: Vec<_> = tokens
+            .iter()
+            .enumerate()
+            .map(|(n, token)| {
+                let task = registry.register_cancellable(
+                    format!("Download {}", n),
+                    TaskCategory::Download,
+                    token.clone(),
+                );
+                std::thread::spawn(move || {
+                    for step in 1..=50 {
+                        task.progress(step * n as u64, Some(50 * n as u64));
+                    }
+                    task
+                })
+            })
+            .collect();
+        let tasks: Vec<TaskHandle> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        // Cancelling one task leaves the others alone
+        registry.cancel(tasks[3].id()).unwrap();
+        for (n, token) in tokens.iter().enumerate() {
+            assert_eq!(token.is_cancelled(), n == 3);
+        }
+        let active = registry.list_active();
+        assert_eq!(active.len(), 8);
+        for info in &active {
+            let n: u64 = info.name.trim_start_matches("Download ").parse().unwrap();
+            assert_eq!((info.completed, info.total), (50 * n, Some(50 * n)));
+        }
+
+        for task in tasks {
+            task.fail("stopped");
+        }
+        let history = registry.history(10);
+        let cancelled: Vec<&str> = history
+            .iter()
+            .filter(|info| info.state == TaskState::Cancelled)
+            .map(|info| info.name.as_str())
+            .collect();
+        assert_eq!(cancelled, vec!["Download 3"]);
+        assert_eq!(history.len(), 8);
+    }
+}
@@ -1,11 +1,37 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+use tokio::time::Instant;
 
-use crate::storage::FileStorage;
+use crate::network::CancellationToken;
+use crate::services::events::AppEvent;
+use crate::storage::{DataClass, FileStorage, SqlStorage};
+use crate::time::{self, SharedClock};
+use crate::util::format::{DisplayContext, Locale};
+
+/// Largest UI state snapshot an app may store
+pub const MAX_APP_STATE_BYTES: usize = 256 * 1024;
+
+/// Default quiet period collecting an app's saves into one write
+pub const DEFAULT_APP_STATE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Manifest metadata flag an app sets when it migrates UI state snapshots
+/// written by an older major version itself
+pub const UI_STATE_MIGRATES_KEY: &str = "uiStateMigrates";
+
+/// Class of UI state snapshots: not backed up, and left out of
+/// diagnostics bundles since they may hold drafts
+pub const APP_STATE_CLASS: DataClass = DataClass::SessionState;
 
 /// UI theme setting
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Theme {
     /// Light theme
@@ -13,15 +39,10 @@ pub enum Theme {
     /// Dark theme
     Dark,
     /// System-based theme (follows OS preference)
+    #[default]
     System,
 }
 
-impl Default for Theme {
-    fn default() -> Self {
-        Self::System
-    }
-}
-
 /// UI theme configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThemeConfig {
@@ -69,14 +90,49 @@ impl Default for ThemeConfig {
     }
 }
 
+/// A UI state snapshot as stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppStateRecord {
+    /// Version of the app that wrote the snapshot
+    app_version: String,
+    /// Unix timestamp of the save
+    saved_at: u64,
+    /// The snapshot, base64-encoded
+    snapshot: String,
+}
+
+/// Parameters of the `ui.state.*` RPC methods
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppStateParams {
+    app_id: String,
+    /// Base64-encoded snapshot, for `ui.state.save`
+    #[serde(default)]
+    snapshot: Option<String>,
+}
+
 /// UI management service
 ///
 /// Provides OpenRPC methods:
 /// - `ui.getTheme` - Get the current theme setting
 /// - `ui.setTheme` - Set the theme (light/dark/system)
+/// - `ui.getLocale` / `ui.setLocale` - Locale of formatted display fields
+/// - `ui.state.save` / `ui.state.load` / `ui.state.clear` - UI state
+///   snapshots of an app
+/// - `ui.state.usage` - Bytes of UI state stored per app
 ///
 /// Theme preference is persisted per-identity and restored on relaunch.
 ///
+/// Apps also keep small opaque UI state snapshots here (scroll positions,
+/// draft text, panel sizes) to restore when relaunched. A snapshot is
+/// encrypted at rest, at most [`MAX_APP_STATE_BYTES`], and tagged with the
+/// app version that wrote it: once the app moves to another major version
+/// it is dropped, unless the app's manifest sets [`UI_STATE_MIGRATES_KEY`]
+/// in its metadata. Apps save on every interaction, so saves are staged and
+/// [`UIService::run`] writes them once an app has been quiet for the
+/// debounce period; it also clears the snapshot of an uninstalled app.
+///
 /// # Example
 ///
 /// ```no_run
@@ -96,8 +152,16 @@ impl Default for ThemeConfig {
 /// ```
 pub struct UIService {
     file_storage: FileStorage,
+    storage: Mutex<SqlStorage>,
     theme_path: PathBuf,
+    locale_path: PathBuf,
+    state_dir: PathBuf,
     encryption_key: [u8; 32],
+    debounce: Duration,
+    /// Snapshots saved but not yet written
+    staged: Mutex<BTreeMap<String, AppStateRecord>>,
+    staged_changed: Notify,
+    clock: SharedClock,
 }
 
 impl UIService {
@@ -110,7 +174,10 @@ impl UIService {
     pub fn new<P: Into<PathBuf>>(storage_path: P, user_id: &str) -> Result<Self> {
         let storage_path = storage_path.into();
         let file_storage = FileStorage::new(&storage_path)?;
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
         let theme_path = PathBuf::from(format!("ui/{}/theme.json", user_id));
+        let locale_path = PathBuf::from(format!("ui/{}/locale.json", user_id));
+        let state_dir = PathBuf::from(format!("ui/{}/state", user_id));
 
         // Derive encryption key from user_id
         // TODO: In production, use user's master key
@@ -118,11 +185,30 @@ impl UIService {
 
         Ok(Self {
             file_storage,
+            storage: Mutex::new(sql_storage),
             theme_path,
+            locale_path,
+            state_dir,
             encryption_key,
+            debounce: DEFAULT_APP_STATE_DEBOUNCE,
+            staged: Mutex::new(BTreeMap::new()),
+            staged_changed: Notify::new(),
+            clock: time::default_clock(),
         })
     }
 
+    /// Override how long an app's saves are collected before a write
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Use a specific clock for display contexts and snapshot timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Get the current theme setting (OpenRPC: ui.getTheme)
     ///
     /// Returns the user's theme preference (light/dark/system).
@@ -179,14 +265,269 @@ impl UIService {
             serde_json::to_vec(&config).context("Failed to serialize theme config")?;
 
         self.file_storage
-            .write(&self.theme_path, &config_json, &self.encryption_key)
+            .write_classified(
+                &self.theme_path,
+                &config_json,
+                &self.encryption_key,
+                DataClass::Preferences,
+            )
             .context("Failed to write theme config")?;
 
         Ok(())
     }
 
+    /// Get the locale display fields are formatted for (OpenRPC: ui.getLocale)
+    ///
+    /// Until the user sets one, this is the operating system's locale.
+    pub fn get_locale(&self) -> Result<Locale> {
+        if !self.file_storage.exists(&self.locale_path) {
+            return Ok(Locale::system());
+        }
+
+        let data = self
+            .file_storage
+            .read(&self.locale_path, &self.encryption_key)
+            .context("Failed to read locale setting")?;
+        serde_json::from_slice(&data).context("Failed to deserialize locale setting")
+    }
+
+    /// Set the locale display fields are formatted for (OpenRPC: ui.setLocale)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `locale` has no language subtag
+    pub fn set_locale(&self, locale: &Locale) -> Result<()> {
+        if locale.language().is_empty() {
+            bail!("Locale '{}' has no language", locale);
+        }
+        let data = serde_json::to_vec(locale).context("Failed to serialize locale setting")?;
+        self.file_storage
+            .write_classified(
+                &self.locale_path,
+                &data,
+                &self.encryption_key,
+                DataClass::Preferences,
+            )
+            .context("Failed to write locale setting")
+    }
+
+    /// Context to fill in display fields for the user's locale, now
+    pub fn display_context(&self) -> Result<DisplayContext> {
+        Ok(DisplayContext::new(
+            self.get_locale()?,
+            self.clock.now_unix(),
+        ))
+    }
+
+    /// Save an app's UI state snapshot (OpenRPC: ui.state.save)
+    ///
+    /// The snapshot replaces the app's previous one. It is staged, and
+    /// written by [`UIService::run`] once the app stops saving for the
+    /// debounce period, or by [`UIService::flush_app_states`]. Over RPC
+    /// the snapshot travels base64-encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot is larger than
+    /// [`MAX_APP_STATE_BYTES`] or the app is not installed
+    pub fn save_app_state(&self, app_id: &str, snapshot: Vec<u8>) -> Result<()> {
+        if snapshot.len() > MAX_APP_STATE_BYTES {
+            bail!(
+                "UI state snapshot of {} is {} bytes; the limit is {}",
+                app_id,
+                snapshot.len(),
+                MAX_APP_STATE_BYTES
+            );
+        }
+        let app = self
+            .storage
+            .lock()
+            .unwrap()
+            .get_application(app_id)?
+            .with_context(|| format!("Application {} is not installed", app_id))?;
+
+        let record = AppStateRecord {
+            app_version: app.version().to_string(),
+            saved_at: self.clock.now_unix(),
+            snapshot: base64::engine::general_purpose::STANDARD.encode(snapshot),
+        };
+        self.staged
+            .lock()
+            .unwrap()
+            .insert(app_id.to_string(), record);
+        self.staged_changed.notify_one();
+        Ok(())
+    }
+
+    /// Load an app's UI state snapshot (OpenRPC: ui.state.load)
+    ///
+    /// Returns `None` if the app saved none, is not installed, or has moved
+    /// to another major version since the save without declaring that it
+    /// migrates its UI state; such a snapshot is removed.
+    pub fn load_app_state(&self, app_id: &str) -> Result<Option<Vec<u8>>> {
+        let staged = self.staged.lock().unwrap().get(app_id).cloned();
+        let record = match staged {
+            Some(record) => record,
+            None => {
+                let path = self.app_state_path(app_id);
+                if !self.file_storage.exists(&path) {
+                    return Ok(None);
+                }
+                let data = self
+                    .file_storage
+                    .read(&path, &self.encryption_key)
+                    .context("Failed to read UI state snapshot")?;
+                serde_json::from_slice(&data).context("Failed to deserialize UI state snapshot")?
+            }
+        };
+
+        let Some(app) = self.storage.lock().unwrap().get_application(app_id)? else {
+            return Ok(None);
+        };
+        let migrates = app
+            .metadata()
+            .and_then(|metadata| metadata.get(UI_STATE_MIGRATES_KEY))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if major(&record.app_version) != major(app.version()) && !migrates {
+            self.clear_app_state(app_id)?;
+            return Ok(None);
+        }
+
+        let snapshot = base64::engine::general_purpose::STANDARD
+            .decode(&record.snapshot)
+            .context("Failed to decode UI state snapshot")?;
+        Ok(Some(snapshot))
+    }
+
+    /// Remove an app's UI state snapshot (OpenRPC: ui.state.clear)
+    ///
+    /// Used from the storage screen, and when the app is uninstalled.
+    /// Returns whether there was one.
+    pub fn clear_app_state(&self, app_id: &str) -> Result<bool> {
+        let staged = self.staged.lock().unwrap().remove(app_id).is_some();
+        let written = self.file_storage.delete(self.app_state_path(app_id))?;
+        Ok(staged || written)
+    }
+
+    /// Bytes of UI state stored per app (OpenRPC: ui.state.usage)
+    ///
+    /// Counts written snapshots as stored, encrypted, on disk, and is
+    /// attributed to each app's storage use.
+    pub fn app_state_usage(&self) -> Result<BTreeMap<String, u64>> {
+        let mut usage = BTreeMap::new();
+        for path in self.file_storage.list_files(&self.state_dir)? {
+            let Some(app_id) = path
+                .file_stem()
+                .and_then(|stem| hex::decode(stem.to_string_lossy().as_ref()).ok())
+                .and_then(|id| String::from_utf8(id).ok())
+            else {
+                continue;
+            };
+            let size = std::fs::metadata(self.file_storage.full_path(&path))?.len();
+            usage.insert(app_id, size);
+        }
+        Ok(usage)
+    }
+
+    /// Write every staged UI state snapshot
+    ///
+    /// Returns how many were written.
+    pub fn flush_app_states(&self) -> Result<usize> {
+        let staged = std::mem::take(&mut *self.staged.lock().unwrap());
+        let count = staged.len();
+        for (app_id, record) in staged {
+            let data = serde_json::to_vec(&record).context("Failed to serialize UI state")?;
+            self.file_storage
+                .write_classified(
+                    self.app_state_path(&app_id),
+                    &data,
+                    &self.encryption_key,
+                    APP_STATE_CLASS,
+                )
+                .context("Failed to write UI state snapshot")?;
+        }
+        Ok(count)
+    }
+
+    /// Write staged UI state snapshots and follow uninstalls until the
+    /// event stream closes or `shutdown` is cancelled
+    ///
+    /// The first save starts a debounce window; saves arriving within it
+    /// are written together when it ends. Snapshots still staged when the
+    /// loop ends are written then.
+    pub async fn run(
+        self: Arc<Self>,
+        mut events: broadcast::Receiver<AppEvent>,
+        shutdown: CancellationToken,
+    ) {
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let flush = async move {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let staged = tokio::select! {
+                _ = self.staged_changed.notified() => true,
+                received = events.recv() => match received {
+                    Ok(AppEvent::AppUninstalled { app_id, .. }) => {
+                        let _ = self.clear_app_state(&app_id);
+                        false
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => false,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = flush => {
+                    deadline = None;
+                    let _ = self.flush_app_states();
+                    false
+                }
+                _ = shutdown.cancelled() => break,
+            };
+            if staged && deadline.is_none() {
+                deadline = Some(Instant::now() + self.debounce);
+            }
+        }
+        let _ = self.flush_app_states();
+    }
+
+    /// Handle a `ui.state.save` RPC request from a backend
+    pub fn handle_state_save(&self, params: Value) -> Result<Value> {
+        let params: AppStateParams = serde_json::from_value(params)?;
+        let snapshot = params.snapshot.context("Missing snapshot")?;
+        let snapshot = base64::engine::general_purpose::STANDARD
+            .decode(snapshot)
+            .context("Snapshot is not valid base64")?;
+        self.save_app_state(&params.app_id, snapshot)?;
+        Ok(Value::Null)
+    }
+
+    /// Handle a `ui.state.load` RPC request from a backend
+    pub fn handle_state_load(&self, params: Value) -> Result<Value> {
+        let params: AppStateParams = serde_json::from_value(params)?;
+        let snapshot = self
+            .load_app_state(&params.app_id)?
+            .map(|snapshot| base64::engine::general_purpose::STANDARD.encode(snapshot));
+        Ok(serde_json::to_value(snapshot)?)
+    }
+
+    /// Handle a `ui.state.clear` RPC request from a backend
+    pub fn handle_state_clear(&self, params: Value) -> Result<Value> {
+        let params: AppStateParams = serde_json::from_value(params)?;
+        Ok(Value::Bool(self.clear_app_state(&params.app_id)?))
+    }
+
+    /// Where an app's UI state snapshot is stored; the file name is the
+    /// hex-encoded app ID, so usage can be attributed without decrypting
+    fn app_state_path(&self, app_id: &str) -> PathBuf {
+        self.state_dir
+            .join(format!("{}.json", hex::encode(app_id.as_bytes())))
+    }
+
     /// Derive encryption key for theme config
-    fn derive_theme_key(user_id: &str) -> [u8; 32] {
+    pub(crate) fn derive_theme_key(user_id: &str) -> [u8; 32] {
         use blake3::Hasher;
         let mut hasher = Hasher::new();
         hasher.update(b"osnova-ui-theme-key-v1:");
@@ -198,11 +539,23 @@ impl UIService {
     }
 }
 
+/// Major component of a version string
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::application::OsnovaApplication;
+    use crate::services::EventBus;
+    use crate::storage::Purpose;
+    use std::collections::HashMap;
     use tempfile::TempDir;
 
+    const NOTES: &str = "com.test.notes";
+    const WALLET: &str = "com.test.wallet";
+
     fn create_test_service() -> Result<(UIService, TempDir)> {
         let temp_dir = TempDir::new()?;
         let service = UIService::new(temp_dir.path(), "user-123")?;
@@ -231,6 +584,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_set_and_get_locale() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        assert_eq!(service.get_locale()?, Locale::system());
+
+        service.set_locale(&Locale::new("fr-CA"))?;
+        assert_eq!(service.get_locale()?, Locale::new("fr-CA"));
+        assert!(service.set_locale(&Locale::new("")).is_err());
+
+        // Persisted per user, and used for display fields
+        let reopened = UIService::new(temp.path(), "user-123")?
+            .with_clock(Arc::new(crate::time::MockClock::new(1_700_000_000)));
+        let context = reopened.display_context()?;
+        assert_eq!(context.locale, Locale::new("fr-CA"));
+        assert_eq!(context.now, 1_700_000_000);
+        let other = UIService::new(temp.path(), "user-456")?;
+        assert_eq!(other.get_locale()?, Locale::system());
+
+        Ok(())
+    }
+
     #[test]
     fn test_update_theme() -> Result<()> {
         let (service, _temp) = create_test_service()?;
@@ -307,4 +681,181 @@ mod tests {
 
         Ok(())
     }
+
+    fn install(temp: &TempDir, app_id: &str, version: &str, migrates: bool) -> Result<()> {
+        let mut app =
+            OsnovaApplication::new(app_id, app_id, version, "https://icon.url", "Test", vec![])?;
+        if migrates {
+            app = app.with_metadata(HashMap::from([(
+                UI_STATE_MIGRATES_KEY.to_string(),
+                serde_json::json!(true),
+            )]));
+        }
+        SqlStorage::new(temp.path().join("osnova.db"))?.upsert_application(&app)
+    }
+
+    #[test]
+    fn test_app_state_round_trip() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        install(&temp, NOTES, "1.0.0", false)?;
+        assert_eq!(service.load_app_state(NOTES)?, None);
+
+        service.save_app_state(NOTES, b"scroll=42".to_vec())?;
+        // Staged snapshots are served before they are written
+        assert_eq!(service.load_app_state(NOTES)?, Some(b"scroll=42".to_vec()));
+        assert_eq!(service.flush_app_states()?, 1);
+
+        let path = service.app_state_path(NOTES);
+        let stored = std::fs::read(service.file_storage.full_path(&path))?;
+        assert!(!stored.windows(9).any(|window| window == b"scroll=42"));
+
+        let reopened = UIService::new(temp.path(), "user-123")?;
+        assert_eq!(reopened.load_app_state(NOTES)?, Some(b"scroll=42".to_vec()));
+        // Per identity, like the theme
+        let other = UIService::new(temp.path(), "user-456")?;
+        assert_eq!(other.load_app_state(NOTES)?, None);
+
+        // Backends use base64 over RPC
+        let params = serde_json::json!({ "appId": NOTES });
+        assert_eq!(
+            service.handle_state_load(params.clone())?,
+            serde_json::json!("c2Nyb2xsPTQy")
+        );
+        assert_eq!(service.handle_state_clear(params)?, Value::Bool(true));
+        assert_eq!(service.load_app_state(NOTES)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_state_size_cap() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        install(&temp, NOTES, "1.0.0", false)?;
+
+        let error = service
+            .save_app_state(NOTES, vec![0; MAX_APP_STATE_BYTES + 1])
+            .unwrap_err();
+        assert!(error.to_string().contains("limit"), "{}", error);
+        assert_eq!(service.load_app_state(NOTES)?, None);
+
+        service.save_app_state(NOTES, vec![0; MAX_APP_STATE_BYTES])?;
+        assert!(service.save_app_state(WALLET, vec![1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_major_version_change_invalidates_app_state() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        install(&temp, NOTES, "1.2.0", false)?;
+        install(&temp, WALLET, "1.0.0", true)?;
+        service.save_app_state(NOTES, b"notes".to_vec())?;
+        service.save_app_state(WALLET, b"wallet".to_vec())?;
+        service.flush_app_states()?;
+
+        install(&temp, NOTES, "1.3.0", false)?;
+        assert_eq!(service.load_app_state(NOTES)?, Some(b"notes".to_vec()));
+
+        install(&temp, NOTES, "2.0.0", false)?;
+        assert_eq!(service.load_app_state(NOTES)?, None);
+        assert!(!service.file_storage.exists(service.app_state_path(NOTES)));
+        // Going back does not bring it back
+        install(&temp, NOTES, "1.3.0", false)?;
+        assert_eq!(service.load_app_state(NOTES)?, None);
+
+        // An app declaring that it migrates its state keeps it
+        install(&temp, WALLET, "2.0.0", true)?;
+        assert_eq!(service.load_app_state(WALLET)?, Some(b"wallet".to_vec()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_clears_app_state() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        install(&temp, NOTES, "1.0.0", false)?;
+        install(&temp, WALLET, "1.0.0", false)?;
+        service.save_app_state(NOTES, b"notes".to_vec())?;
+        service.save_app_state(WALLET, b"wallet".to_vec())?;
+        service.flush_app_states()?;
+
+        let service = Arc::new(service);
+        let events = EventBus::new();
+        let shutdown = CancellationToken::new();
+        let runner = tokio::spawn(service.clone().run(events.subscribe(), shutdown.clone()));
+        events.publish(AppEvent::AppUninstalled {
+            app_id: NOTES.to_string(),
+            generation: 1,
+        });
+
+        let path = service.app_state_path(NOTES);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while service.file_storage.exists(&path) {
+            assert!(Instant::now() < deadline, "snapshot was not cleared");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(service.file_storage.exists(service.app_state_path(WALLET)));
+
+        shutdown.cancel();
+        runner.await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_app_state_writes_are_debounced() -> Result<()> {
+        let temp = TempDir::new()?;
+        install(&temp, NOTES, "1.0.0", false)?;
+        let service = Arc::new(
+            UIService::new(temp.path(), "user-123")?.with_debounce(Duration::from_millis(50)),
+        );
+        let events = EventBus::new();
+        let shutdown = CancellationToken::new();
+        let runner = tokio::spawn(service.clone().run(events.subscribe(), shutdown.clone()));
+
+        let path = service.app_state_path(NOTES);
+        for draft in ["d", "dr", "dra"] {
+            service.save_app_state(NOTES, draft.as_bytes().to_vec())?;
+        }
+        assert!(!service.file_storage.exists(&path));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(service.staged.lock().unwrap().is_empty());
+        let reopened = UIService::new(temp.path(), "user-123")?;
+        assert_eq!(reopened.load_app_state(NOTES)?, Some(b"dra".to_vec()));
+
+        // Whatever is staged at shutdown is written
+        service.save_app_state(NOTES, b"draft".to_vec())?;
+        shutdown.cancel();
+        runner.await?;
+        assert_eq!(reopened.load_app_state(NOTES)?, Some(b"draft".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_state_usage_is_attributed_per_app() -> Result<()> {
+        let (service, temp) = create_test_service()?;
+        install(&temp, NOTES, "1.0.0", false)?;
+        install(&temp, WALLET, "1.0.0", false)?;
+        service.save_app_state(NOTES, vec![0; 1000])?;
+        service.save_app_state(WALLET, vec![0; 10])?;
+        service.flush_app_states()?;
+
+        let usage = service.app_state_usage()?;
+        assert_eq!(usage.len(), 2);
+        assert!(usage[NOTES] >= 1000);
+        assert!(usage[WALLET] < usage[NOTES]);
+
+        // Session state: not backed up, nor put into diagnostics bundles
+        let path = service.app_state_path(NOTES);
+        assert_eq!(
+            service.file_storage.classification(&path)?,
+            Some(DataClass::SessionState)
+        );
+        for purpose in [Purpose::NetworkBackup, Purpose::DiagnosticsBundle] {
+            assert!(!service.file_storage.files_for(purpose)?.contains(&path));
+        }
+
+        service.clear_app_state(NOTES)?;
+        assert_eq!(
+            service.app_state_usage()?.keys().collect::<Vec<_>>(),
+            vec![WALLET]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,1005 @@
+//! Application updates
+//!
+//! [`UpdateService::check_updates`] compares each installed app with its
+//! published manifest. [`UpdateService::check_and_apply`] then acts on each
+//! update according to the app's [`UpdatePolicy`]:
+//!
+//! - `Manual` posts a notification that an update is available
+//! - `DownloadOnly` downloads the new components into the cache, so the
+//!   update can be applied with one click ([`UpdateService::apply_update`])
+//! - `AutoApply` downloads and installs the update, but never while the app
+//!   is running; a running app is updated on a later check after it exits
+//!
+//! Installing an update keeps the replaced version in the app's version
+//! history, so [`UpdateService::rollback`] can restore it while its
+//! components are still cached.
+//!
+//! A published version is only offered if its co-signatures meet its own
+//! signature policy and the policy of the installed version, so a release
+//! signed with one stolen key is never offered, let alone installed.
+//!
+//! Apps pinned to a version (`AppsService::pin_version`) are never updated
+//! or rolled back; their updates are reported separately in
+//! [`UpdateCheck::pinned`] so the UI can show them as available but pinned.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::components::{entry, ComponentDownloader};
+use crate::manifest::launcher::parse_version;
+use crate::manifest::{
+    check_signature_policy, resolve_manifest, validate_uri_scheme, ComponentSchema, ManifestSchema,
+};
+use crate::models::application::OsnovaApplication;
+use crate::models::launcher_change::LauncherChangeKind;
+use crate::models::notification::{Notification, NotificationLevel};
+use crate::models::provenance::ManifestOrigin;
+use crate::network::{CancellationToken, NetworkOptions, TransferStatus};
+use crate::services::apps::installed_app;
+use crate::services::events::{AppEvent, EventBus};
+use crate::services::{ConfigService, NotificationService, ProcessService};
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+
+/// Default seconds between scheduled update checks (6 hours)
+pub const DEFAULT_UPDATE_CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// Shortest interval the scheduler honours, whatever is configured
+const MIN_UPDATE_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Default number of replaced versions kept per app for rollback
+pub const DEFAULT_VERSION_HISTORY: usize = 3;
+
+/// What happens when an update to an app is found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdatePolicy {
+    /// Notify only (default)
+    #[default]
+    Manual,
+    /// Download in the background; the user applies the update
+    DownloadOnly,
+    /// Download and install once the app is not running
+    AutoApply,
+}
+
+/// An update found by [`UpdateService::check_updates`]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableUpdate {
+    /// Application identifier
+    pub app_id: String,
+    /// Installed version
+    pub current_version: String,
+    /// Published version
+    pub version: String,
+    /// Policy that applies to the app
+    pub policy: UpdatePolicy,
+    /// Published manifest
+    #[serde(skip)]
+    pub manifest: ManifestSchema,
+}
+
+/// Result of [`UpdateService::check_updates`]
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    /// Updates to offer or apply
+    pub updates: Vec<AvailableUpdate>,
+    /// Updates to apps pinned to their installed version
+    pub pinned: Vec<AvailableUpdate>,
+}
+
+/// What a scheduled check did with an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateAction {
+    /// The user was told an update is available
+    Notified,
+    /// Components were downloaded; the user applies the update
+    Downloaded,
+    /// Downloaded, but the app is running; installed on a later check
+    WaitingForExit,
+    /// The update was installed
+    Applied,
+    /// The bandwidth policy deferred the download; retried on the next check
+    Deferred,
+    /// Downloading or installing failed
+    Failed,
+}
+
+/// Result of handling one update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateOutcome {
+    /// Application identifier
+    pub app_id: String,
+    /// Version installed before the check
+    pub from_version: String,
+    /// Published version
+    pub to_version: String,
+    /// What was done
+    pub action: UpdateAction,
+    /// Why the update failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Fetches the published manifest of an installed app, given its ID
+pub type ManifestFetcher = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<ManifestSchema>> + Send>> + Send + Sync,
+>;
+
+/// Application update service
+///
+/// Provides OpenRPC methods:
+/// - `apps.checkUpdates` - List installed apps with a newer published version
+/// - `apps.applyUpdate` - Install a downloaded update
+/// - `apps.rollback` - Restore the version an update replaced
+///
+/// Run [`run_scheduler`](Self::run_scheduler) on the async runtime to check
+/// on the configured interval.
+///
+/// # Example
+///
+/// ```no_run
+/// use osnova_lib::services::UpdateService;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let service = UpdateService::new("/path/to/storage")?;
+/// for update in service.check_updates().await?.updates {
+///     println!("{}: {} -> {}", update.app_id, update.current_version, update.version);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct UpdateService {
+    storage: Mutex<SqlStorage>,
+    config: Mutex<ConfigService>,
+    downloader: Option<Arc<ComponentDownloader>>,
+    processes: Option<Arc<ProcessService>>,
+    notifications: Option<Arc<NotificationService>>,
+    events: Option<EventBus>,
+    clock: SharedClock,
+    fetch_manifest: ManifestFetcher,
+    history: usize,
+}
+
+impl UpdateService {
+    /// Create a new update service
+    ///
+    /// Manifests are resolved from each app's ID (file:// and https:// only;
+    /// use [`with_manifest_fetcher`](Self::with_manifest_fetcher) for
+    /// ant:// manifests).
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_path` - Base path for storage
+    pub fn new<P: Into<PathBuf>>(storage_path: P) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let config = ConfigService::new(&storage_path)?;
+
+        Ok(Self {
+            storage: Mutex::new(sql_storage),
+            config: Mutex::new(config),
+            downloader: None,
+            processes: None,
+            notifications: None,
+            events: None,
+            clock: time::default_clock(),
+            fetch_manifest: Arc::new(|uri| {
+                Box::pin(async move { Ok(resolve_manifest(&uri, None).await?) })
+            }),
+            history: DEFAULT_VERSION_HISTORY,
+        })
+    }
+
+    /// Attach the component downloader used to fetch updates
+    ///
+    /// Required by the `DownloadOnly` and `AutoApply` policies and by
+    /// rollback. Attach a bandwidth meter to the downloader to have update
+    /// downloads respect the bandwidth policy.
+    pub fn with_downloader(mut self, downloader: Arc<ComponentDownloader>) -> Self {
+        self.downloader = Some(downloader);
+        self
+    }
+
+    /// Attach the process supervisor used to tell whether an app is running
+    pub fn with_processes(mut self, processes: Arc<ProcessService>) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
+    /// Post update notifications to a notification center
+    pub fn with_notifications(mut self, notifications: Arc<NotificationService>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    /// Publish installed updates on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a specific clock for version history timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Override how published manifests are fetched
+    pub fn with_manifest_fetcher<F, Fut>(mut self, fetch: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ManifestSchema>> + Send + 'static,
+    {
+        self.fetch_manifest = Arc::new(move |app_id| Box::pin(fetch(app_id)));
+        self
+    }
+
+    /// Set how many replaced versions are kept per app (at least 1)
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history.max(1);
+        self
+    }
+
+    /// List installed apps with a newer published version
+    /// (OpenRPC: apps.checkUpdates)
+    ///
+    /// Apps whose manifest cannot be fetched are skipped, as is the version
+    /// an app was rolled back from and any version whose signatures do not
+    /// meet the signature policy. Updates to pinned apps are listed in
+    /// [`UpdateCheck::pinned`] instead of [`UpdateCheck::updates`]; the
+    /// others are published as [`AppEvent::UpdateAvailable`].
+    pub async fn check_updates(&self) -> Result<UpdateCheck> {
+        let apps = self.storage().list_applications()?;
+        let require_policy = self
+            .config
+            .lock()
+            .unwrap()
+            .get_signature_policy_required()?;
+        let mut check = UpdateCheck::default();
+
+        for app in apps {
+            // An unreachable manifest is retried on the next check
+            let Ok(manifest) = (self.fetch_manifest)(app.id().to_string()).await else {
+                continue;
+            };
+            if !self.is_update(&app, &manifest.version)? {
+                continue;
+            }
+            if let Err(e) =
+                check_signature_policy(&manifest, app.signature_policy(), require_policy)
+            {
+                crate::log!(
+                    Warn,
+                    "Not offering {} {}: {}",
+                    app.id(),
+                    manifest.version,
+                    e
+                );
+                continue;
+            }
+
+            let pinned = self.storage().get_pinned_version(app.id())?.is_some();
+            let update = AvailableUpdate {
+                app_id: app.id().to_string(),
+                current_version: app.version().to_string(),
+                version: manifest.version.clone(),
+                policy: self.config.lock().unwrap().get_update_policy(app.id())?,
+                manifest,
+            };
+            if pinned {
+                check.pinned.push(update);
+            } else {
+                if let Some(events) = &self.events {
+                    events.publish(AppEvent::UpdateAvailable {
+                        app_id: update.app_id.clone(),
+                        version: update.version.clone(),
+                    });
+                }
+                check.updates.push(update);
+            }
+        }
+
+        Ok(check)
+    }
+
+    /// Check for updates and handle each according to its policy
+    ///
+    /// A failure affecting one app is reported in its outcome and does not
+    /// stop the others.
+    pub async fn check_and_apply(&self, options: &NetworkOptions) -> Result<Vec<UpdateOutcome>> {
+        let mut outcomes = Vec::new();
+
+        for update in self.check_updates().await?.updates {
+            let (action, error) = match self.handle_update(&update, options).await {
+                Ok(action) => (action, None),
+                Err(e) => (UpdateAction::Failed, Some(format!("{:#}", e))),
+            };
+            self.notify(&update, action)?;
+
+            outcomes.push(UpdateOutcome {
+                app_id: update.app_id,
+                from_version: update.current_version,
+                to_version: update.version,
+                action,
+                error,
+            });
+        }
+
+        Ok(outcomes)
+    }
+
+    /// Run scheduled update checks until `shutdown` is cancelled
+    ///
+    /// Spawn this on the async runtime. The interval is re-read from the
+    /// configuration after every check. Cancelling also cancels an update
+    /// download in flight, which cleans up its partial files. No checks run
+    /// while the kill switch is engaged; releasing it checks right away.
+    pub async fn run_scheduler(self: Arc<Self>, shutdown: CancellationToken) {
+        let options = NetworkOptions::default().with_cancellation(shutdown.clone());
+        let mut offline = options.kill_switch.subscribe();
+        while !shutdown.is_cancelled() {
+            // A failed check is retried on the next tick
+            if !options.kill_switch.is_engaged() {
+                let _ = self.check_and_apply(&options).await;
+            }
+
+            let secs = self
+                .config
+                .lock()
+                .unwrap()
+                .get_update_check_interval_secs()
+                .unwrap_or(DEFAULT_UPDATE_CHECK_INTERVAL_SECS)
+                .max(MIN_UPDATE_CHECK_INTERVAL_SECS);
+            offline.mark_unchanged();
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(secs)) => {}
+                Ok(()) = offline.changed() => {}
+                _ = shutdown.cancelled() => break,
+            }
+        }
+    }
+
+    /// Get the downloaded update waiting to be applied, if any
+    pub fn pending_update(&self, app_id: &str) -> Result<Option<OsnovaApplication>> {
+        self.storage().get_pending_update(app_id)
+    }
+
+    /// Install a downloaded update (OpenRPC: apps.applyUpdate)
+    ///
+    /// Returns the installed version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no update has been downloaded, or the app is
+    /// running or pinned.
+    pub fn apply_update(&self, app_id: &str) -> Result<String> {
+        let update = self
+            .pending_update(app_id)?
+            .with_context(|| format!("No downloaded update for {}", app_id))?;
+        self.ensure_unpinned(app_id)?;
+        if self.is_running(app_id)? {
+            anyhow::bail!("Application {} is running; close it to update", app_id);
+        }
+
+        self.install(&update, true, None)?;
+        Ok(update.version().to_string())
+    }
+
+    /// Restore the version the last update replaced (OpenRPC: apps.rollback)
+    ///
+    /// The version rolled back from is skipped by later checks until a newer
+    /// one is published. Returns the restored version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no previous version, the app is running
+    /// or pinned, or the previous version's components are no longer cached.
+    pub async fn rollback(&self, app_id: &str) -> Result<String> {
+        self.ensure_unpinned(app_id)?;
+        if self.is_running(app_id)? {
+            anyhow::bail!("Application {} is running; close it to roll back", app_id);
+        }
+
+        let current = installed_app(&self.storage(), app_id)?;
+        let (history_id, previous, _) = self
+            .storage()
+            .list_app_versions(app_id)?
+            .into_iter()
+            .next()
+            .with_context(|| format!("No previous version of {} to roll back to", app_id))?;
+
+        let downloader = self.downloader()?;
+        for component in previous.components() {
+            if !downloader
+                .quick_check(&ComponentSchema::from(component))
+                .await
+            {
+                anyhow::bail!(
+                    "Components of {} {} are no longer cached",
+                    app_id,
+                    previous.version()
+                );
+            }
+        }
+
+        self.install(&previous, false, None)?;
+        let storage = self.storage();
+        storage.delete_app_version(history_id)?;
+        storage.set_skipped_update(app_id, Some(current.version()))?;
+
+        Ok(previous.version().to_string())
+    }
+
+    /// Download and, for `AutoApply`, install one update
+    async fn handle_update(
+        &self,
+        update: &AvailableUpdate,
+        options: &NetworkOptions,
+    ) -> Result<UpdateAction> {
+        if update.policy == UpdatePolicy::Manual {
+            return Ok(UpdateAction::Notified);
+        }
+
+        let next = OsnovaApplication::try_from(&update.manifest)?;
+        if !self.download(&next, options).await? {
+            return Ok(UpdateAction::Deferred);
+        }
+        self.storage()
+            .set_pending_update(&update.app_id, Some(&next))?;
+
+        if update.policy == UpdatePolicy::DownloadOnly {
+            return Ok(UpdateAction::Downloaded);
+        }
+        if self.is_running(&update.app_id)? {
+            return Ok(UpdateAction::WaitingForExit);
+        }
+
+        self.install(&next, true, Some(&update.manifest))?;
+        Ok(UpdateAction::Applied)
+    }
+
+    /// Download every component of a new version into the cache
+    ///
+    /// Returns `false` if the bandwidth policy deferred a download.
+    async fn download(&self, next: &OsnovaApplication, options: &NetworkOptions) -> Result<bool> {
+        let downloader = self.downloader()?;
+        let origin = ManifestOrigin::from(next);
+
+        for component in next.components() {
+            let component = ComponentSchema::from(component);
+            let status = downloader
+                .try_download_for(&component, &origin, options)
+                .await
+                .with_context(|| format!("Failed to download component {}", component.name))?;
+            if let TransferStatus::Deferred(_) = status {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Swap the installed application row to another version
+    ///
+    /// With `keep_history`, the replaced version is added to the history.
+    /// `manifest` is the resolved manifest of `next`, if at hand, recorded
+    /// as its snapshot.
+    fn install(
+        &self,
+        next: &OsnovaApplication,
+        keep_history: bool,
+        manifest: Option<&ManifestSchema>,
+    ) -> Result<()> {
+        for scheme in next.uri_schemes() {
+            validate_uri_scheme(scheme).map_err(|e| anyhow::anyhow!(e))?;
+        }
+
+        let mut next = next.clone();
+        for warning in entry::record_entries(&mut next)? {
+            crate::log!(Warn, "{}", warning);
+        }
+        let next = &next;
+
+        // Backends kept warm run the version being replaced
+        if let Some(processes) = &self.processes {
+            processes.stop_idle(next.id())?;
+        }
+
+        let current = installed_app(&self.storage(), next.id())?;
+        let storage = self.storage();
+        if keep_history {
+            storage.push_app_version(&current, self.clock.now_unix(), self.history)?;
+        }
+        match manifest {
+            Some(manifest) => storage.upsert_application_with_manifest(next, manifest)?,
+            None => storage.upsert_application(next)?,
+        }
+        storage.set_pending_update(next.id(), None)?;
+        storage.set_skipped_update(next.id(), None)?;
+
+        // Re-registering moves the app behind other claimants, so only do it
+        // when the claimed schemes changed
+        if current.uri_schemes() != next.uri_schemes() {
+            storage.remove_uri_scheme_claims(next.id())?;
+            for scheme in next.uri_schemes() {
+                storage.add_uri_scheme_claim(scheme, next.id())?;
+            }
+        }
+        let generation =
+            storage.record_launcher_change(LauncherChangeKind::Updated, Some(next.id()), None)?;
+        drop(storage);
+
+        if let Some(events) = &self.events {
+            events.publish(AppEvent::AppInstalled {
+                app_id: next.id().to_string(),
+                generation,
+            });
+        }
+        Ok(())
+    }
+
+    /// Post the notification for an update outcome
+    ///
+    /// Tagged by version, so repeated checks do not notify twice.
+    fn notify(&self, update: &AvailableUpdate, action: UpdateAction) -> Result<()> {
+        let Some(notifications) = &self.notifications else {
+            return Ok(());
+        };
+
+        let name = &update.manifest.name;
+        let version = &update.version;
+        let (tag, notification) = match action {
+            UpdateAction::Notified => (
+                "update-available",
+                Notification::new(
+                    "Update available",
+                    format!("{} {} is available.", name, version),
+                ),
+            ),
+            UpdateAction::Downloaded => (
+                "update-ready",
+                Notification::new(
+                    "Update ready",
+                    format!("{} {} is ready to install.", name, version),
+                ),
+            ),
+            UpdateAction::WaitingForExit => (
+                "update-ready",
+                Notification::new(
+                    "Update ready",
+                    format!(
+                        "{} {} will be installed after {} closes.",
+                        name, version, name
+                    ),
+                ),
+            ),
+            UpdateAction::Applied => (
+                "update-applied",
+                Notification::new(
+                    "App updated",
+                    format!("{} was updated to {}.", name, version),
+                )
+                .with_level(NotificationLevel::Success),
+            ),
+            UpdateAction::Failed => (
+                "update-failed",
+                Notification::new(
+                    "Update failed",
+                    format!("{} could not be updated to {}.", name, version),
+                )
+                .with_level(NotificationLevel::Error),
+            ),
+            UpdateAction::Deferred => return Ok(()),
+        };
+
+        notifications.post(
+            &update.app_id,
+            notification.with_tag(format!("{}:{}", tag, version)),
+        )?;
+        Ok(())
+    }
+
+    /// Whether a published version should be offered for an installed app
+    fn is_update(&self, app: &OsnovaApplication, version: &str) -> Result<bool> {
+        let (Some(published), Some(installed)) =
+            (parse_version(version), parse_version(app.version()))
+        else {
+            return Ok(false);
+        };
+
+        let skipped = self
+            .storage()
+            .get_skipped_update(app.id())?
+            .and_then(|skipped| parse_version(&skipped));
+        Ok(published > installed && skipped.is_none_or(|skipped| published > skipped))
+    }
+
+    /// Refuse to change the version of a pinned app
+    fn ensure_unpinned(&self, app_id: &str) -> Result<()> {
+        if let Some(version) = self.storage().get_pinned_version(app_id)? {
+            anyhow::bail!(
+                "Application {} is pinned to version {}; unpin it to change versions",
+                app_id,
+                version
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether any backend process of the app is running, other than
+    /// backends kept warm after the app closed
+    fn is_running(&self, app_id: &str) -> Result<bool> {
+        let Some(processes) = &self.processes else {
+            return Ok(false);
+        };
+        Ok(processes
+            .list()?
+            .iter()
+            .any(|p| p.app_id() == app_id && !p.is_idle()))
+    }
+
+    /// Get the attached component downloader
+    fn downloader(&self) -> Result<&ComponentDownloader> {
+        self.downloader
+            .as_deref()
+            .context("Component downloader not configured")
+    }
+
+    fn storage(&self) -> MutexGuard<'_, SqlStorage> {
+        self.storage.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use crate::components::binary;
+    use crate::models::notification::StoredNotification;
+    use crate::services::{AppsService, NotificationFilter};
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    const APP_ID: &str = "com.test.updates";
+
+    struct Fixture {
+        temp: TempDir,
+        service: UpdateService,
+        downloader: Arc<ComponentDownloader>,
+        processes: Arc<ProcessService>,
+        notifications: Arc<NotificationService>,
+        published: Arc<Mutex<ManifestSchema>>,
+    }
+
+    impl Fixture {
+        fn installed_version(&self) -> Result<String> {
+            Ok(installed_app(&self.service.storage(), APP_ID)?
+                .version()
+                .to_string())
+        }
+
+        fn notifications(&self) -> Result<Vec<StoredNotification>> {
+            self.notifications.list(&NotificationFilter::default())
+        }
+
+        fn publish(&self, version: &str) -> Result<()> {
+            *self.published.lock().unwrap() = manifest(self.temp.path(), version)?;
+            Ok(())
+        }
+    }
+
+    /// Manifest with one backend component unique to `dir` and `version`
+    fn manifest(dir: &std::path::Path, version: &str) -> Result<ManifestSchema> {
+        let artifact = dir.join(format!("backend-{}", version));
+        std::fs::write(
+            &artifact,
+            binary::host_stub(format!("backend {}", version).as_bytes()),
+        )?;
+        let hash = crate::components::integrity::content_hash(&std::fs::read(&artifact)?);
+
+        // The downloader prepares files under the shared temp dir by name
+        let unique = dir.file_name().unwrap().to_string_lossy();
+        Ok(ManifestSchema {
+            id: APP_ID.to_string(),
+            name: "Updates".to_string(),
+            version: version.to_string(),
+            icon_uri: "https://icon.url".to_string(),
+            description: "Update test app".to_string(),
+            publisher: None,
+            signature: None,
+            signature_policy: None,
+            components: vec![ComponentSchema {
+                id: format!("file://{}", artifact.display()),
+                name: format!("updates-{}", unique),
+                kind: "backend".to_string(),
+                platform: None,
+                target: None,
+                version: version.to_string(),
+                hash: Some(hash),
+                config: None,
+                shared: false,
+                shared_id: None,
+                interpreter: None,
+                extra: Default::default(),
+            }],
+            uri_schemes: vec![],
+            data_offers: Vec::new(),
+            searchable_config_keys: Vec::new(),
+            settings_schema: None,
+            feature_flags: Vec::new(),
+            metadata: None,
+            min_core_version: None,
+            extra: Default::default(),
+        })
+    }
+
+    /// App 1.0.0 installed and cached, with 1.1.0 published
+    async fn fixture(policy: UpdatePolicy) -> Result<Fixture> {
+        let temp = TempDir::new()?;
+        let cache = CacheManager::new(temp.path().join("cache"), 1024 * 1024)?;
+        let downloader = Arc::new(ComponentDownloader::new(cache, None));
+        let processes = Arc::new(ProcessService::new(temp.path())?);
+        let notifications = Arc::new(NotificationService::new(temp.path(), "user")?);
+        ConfigService::new(temp.path())?.set_default_update_policy(policy)?;
+
+        let installed = OsnovaApplication::try_from(&manifest(temp.path(), "1.0.0")?)?;
+        let origin = ManifestOrigin::from(&installed);
+        for component in installed.components() {
+            downloader
+                .try_download_for(&component.into(), &origin, &NetworkOptions::default())
+                .await?;
+        }
+
+        let published = Arc::new(Mutex::new(manifest(temp.path(), "1.1.0")?));
+        let fetched = published.clone();
+        let service = UpdateService::new(temp.path())?
+            .with_downloader(downloader.clone())
+            .with_processes(processes.clone())
+            .with_notifications(notifications.clone())
+            .with_manifest_fetcher(move |_| {
+                let manifest = fetched.lock().unwrap().clone();
+                async move { Ok(manifest) }
+            });
+        service.storage().upsert_application(&installed)?;
+
+        Ok(Fixture {
+            temp,
+            service,
+            downloader,
+            processes,
+            notifications,
+            published,
+        })
+    }
+
+    async fn check(fixture: &Fixture) -> Result<UpdateAction> {
+        let outcomes = fixture
+            .service
+            .check_and_apply(&NetworkOptions::default())
+            .await?;
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].error, None);
+        Ok(outcomes[0].action)
+    }
+
+    #[tokio::test]
+    async fn test_update_must_meet_installed_signature_policy() -> Result<()> {
+        use crate::manifest::add_signature;
+        use crate::models::signature::SignaturePolicy;
+        use base64::Engine;
+        use ed25519_dalek::SigningKey;
+
+        let fixture = fixture(UpdatePolicy::Manual).await?;
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let policy = SignaturePolicy {
+            required: 1,
+            allowed_signers: vec![
+                base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes())
+            ],
+        };
+        let installed =
+            installed_app(&fixture.service.storage(), APP_ID)?.with_signature_policy(policy);
+        fixture.service.storage().upsert_application(&installed)?;
+
+        // 1.1.0 is unsigned and declares no policy of its own
+        assert!(fixture.service.check_updates().await?.updates.is_empty());
+
+        let signed = add_signature(&fixture.published.lock().unwrap(), &key, "developer")?;
+        *fixture.published.lock().unwrap() = signed;
+        assert_eq!(fixture.service.check_updates().await?.updates.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manual_policy_only_notifies() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::Manual).await?;
+
+        let updates = fixture.service.check_updates().await?.updates;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].current_version, "1.0.0");
+        assert_eq!(updates[0].version, "1.1.0");
+
+        assert_eq!(check(&fixture).await?, UpdateAction::Notified);
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+        assert!(fixture.service.pending_update(APP_ID)?.is_none());
+
+        // Checking again does not repeat the notification
+        check(&fixture).await?;
+        let notifications = fixture.notifications()?;
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0].notification.tag.as_deref(),
+            Some("update-available:1.1.0")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_only_waits_for_user() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::DownloadOnly).await?;
+
+        assert_eq!(check(&fixture).await?, UpdateAction::Downloaded);
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+        let pending = fixture.service.pending_update(APP_ID)?.unwrap();
+        for component in pending.components() {
+            assert!(fixture.downloader.quick_check(&component.into()).await);
+        }
+
+        assert_eq!(fixture.service.apply_update(APP_ID)?, "1.1.0");
+        assert_eq!(fixture.installed_version()?, "1.1.0");
+        assert!(fixture.service.pending_update(APP_ID)?.is_none());
+        assert!(fixture.service.check_updates().await?.updates.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_per_app_policy_overrides_default() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::Manual).await?;
+        ConfigService::new(fixture.temp.path())?
+            .set_app_update_policy(APP_ID, Some(UpdatePolicy::AutoApply))?;
+
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+        assert_eq!(fixture.installed_version()?, "1.1.0");
+
+        let notifications = fixture.notifications()?;
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0].notification.level,
+            NotificationLevel::Success
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_apply_waits_for_running_app() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::AutoApply).await?;
+        let mut sleeper = Command::new("sleep");
+        sleeper.arg("30");
+        fixture.processes.spawn(APP_ID, &mut sleeper, None)?;
+
+        assert_eq!(check(&fixture).await?, UpdateAction::WaitingForExit);
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+        assert!(fixture.service.apply_update(APP_ID).is_err());
+        assert!(fixture.service.rollback(APP_ID).await.is_err());
+
+        fixture.processes.stop_app(APP_ID)?;
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+        assert_eq!(fixture.installed_version()?, "1.1.0");
+
+        let tags: Vec<_> = fixture
+            .notifications()?
+            .into_iter()
+            .filter_map(|n| n.notification.tag)
+            .collect();
+        assert_eq!(tags, ["update-applied:1.1.0", "update-ready:1.1.0"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_previous_version() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::AutoApply).await?;
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+
+        assert_eq!(fixture.service.rollback(APP_ID).await?, "1.0.0");
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+        assert!(fixture.service.rollback(APP_ID).await.is_err());
+
+        // The version rolled back from is not offered again...
+        assert!(fixture.service.check_updates().await?.updates.is_empty());
+
+        // ...but a newer one is
+        fixture.publish("1.2.0")?;
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+        assert_eq!(fixture.installed_version()?, "1.2.0");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rollback_requires_cached_components() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::AutoApply).await?;
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+
+        let previous = manifest(fixture.temp.path(), "1.0.0")?;
+        fixture.downloader.remove(&previous.components[0]).await?;
+
+        let err = fixture.service.rollback(APP_ID).await.unwrap_err();
+        assert!(err.to_string().contains("no longer cached"));
+        assert_eq!(fixture.installed_version()?, "1.1.0");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pinned_app_is_reported_but_not_updated() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::AutoApply).await?;
+        let apps = AppsService::new(fixture.temp.path())?;
+        assert!(apps.pin_version(APP_ID, "1.1.0").is_err());
+        apps.pin_version(APP_ID, "1.0.0")?;
+
+        let found = fixture.service.check_updates().await?;
+        assert!(found.updates.is_empty());
+        assert_eq!(found.pinned.len(), 1);
+        assert_eq!(found.pinned[0].version, "1.1.0");
+
+        let outcomes = fixture
+            .service
+            .check_and_apply(&NetworkOptions::default())
+            .await?;
+        assert!(outcomes.is_empty());
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+        assert!(fixture.service.pending_update(APP_ID)?.is_none());
+        assert!(fixture.notifications()?.is_empty());
+        let err = fixture.service.rollback(APP_ID).await.unwrap_err();
+        assert!(err.to_string().contains("pinned to version 1.0.0"));
+
+        // Unpinning resumes normal update behavior
+        apps.unpin(APP_ID)?;
+        assert_eq!(check(&fixture).await?, UpdateAction::Applied);
+        assert_eq!(fixture.installed_version()?, "1.1.0");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pinned_app_repairs_from_snapshot() -> Result<()> {
+        let fixture = fixture(UpdatePolicy::AutoApply).await?;
+        let cache = CacheManager::new(fixture.temp.path().join("cache"), 1024 * 1024)?;
+        let apps = AppsService::new(fixture.temp.path())?
+            .with_downloader(ComponentDownloader::new(cache, None));
+        apps.pin_version(APP_ID, "1.0.0")?;
+        assert_eq!(apps.info(APP_ID)?.pinned_version.as_deref(), Some("1.0.0"));
+
+        // 1.1.0 is published but never installed
+        fixture
+            .service
+            .check_and_apply(&NetworkOptions::default())
+            .await?;
+
+        let installed = manifest(fixture.temp.path(), "1.0.0")?;
+        fixture.downloader.remove(&installed.components[0]).await?;
+        assert!(!apps.verify(APP_ID).await?.is_healthy());
+
+        assert!(apps.repair(APP_ID).await?.is_healthy());
+        assert!(apps.verify(APP_ID).await?.is_healthy());
+        assert_eq!(fixture.installed_version()?, "1.0.0");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,739 @@
+//! Wallet payment history
+//!
+//! Every upload to the Autonomi network is recorded by the
+//! [`PaymentLedger`] attached to the uploading client. [`WalletService`]
+//! reads that history back for the wallet screen: filtered and paged
+//! listings with totals per month, exports to CSV or JSON for accounting,
+//! and an amounts-only summary of the last 30 days for diagnostics.
+//!
+//! On a managed device, payments are approved against the wallet caps of
+//! the device policy (see [`crate::policies`]).
+//!
+//! Amounts are kept in AttoTokens. Exports add the decimal ANT amount,
+//! formatted with `.` as the separator whatever the locale, and UTC
+//! timestamps, so files read the same on every machine.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::audit::PageRequest;
+use crate::context::{GuestGate, GuestRestriction};
+use crate::models::payment_record::{
+    format_ant, PaymentRecord, PaymentStatus, ANT_DECIMALS, ANT_SYMBOL,
+};
+use crate::network::bandwidth::civil_from_days;
+use crate::network::PaymentLedger;
+use crate::policies::{PolicyStore, WALLET_CAP_WINDOW_SECS};
+use crate::services::config::ConfigService;
+use crate::storage::SqlStorage;
+use crate::time::{self, SharedClock};
+use crate::util::format::{DisplayContext, Localize};
+
+/// Length of the window covered by diagnostics summaries, in seconds
+pub const DIAGNOSTICS_WINDOW_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Which payments to list
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryFilter {
+    /// Only uploads at or after this Unix timestamp
+    pub since: Option<u64>,
+    /// Only uploads at or before this Unix timestamp
+    pub until: Option<u64>,
+    /// Only uploads for this component
+    pub component_id: Option<String>,
+}
+
+/// Totals for one calendar month (UTC)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyTotal {
+    /// Month as `YYYY-MM`
+    pub month: String,
+    /// Completed uploads
+    pub uploads: usize,
+    /// Bytes stored
+    pub bytes: u64,
+    /// Sum of quoted costs, in AttoTokens
+    pub quoted_cost: u64,
+    /// Sum of actual costs, in AttoTokens
+    pub actual_cost: u64,
+}
+
+/// Totals over a set of payments
+///
+/// Failed uploads are counted but add nothing to the amounts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentSummary {
+    /// Completed uploads
+    pub uploads: usize,
+    /// Failed uploads
+    pub failed: usize,
+    /// Bytes stored
+    pub bytes: u64,
+    /// Sum of quoted costs, in AttoTokens
+    pub quoted_cost: u64,
+    /// Sum of actual costs, in AttoTokens
+    pub actual_cost: u64,
+    /// Totals per month, oldest first
+    pub monthly: Vec<MonthlyTotal>,
+    /// Totals formatted for display, once localized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<PaymentSummaryDisplay>,
+}
+
+/// [`PaymentSummary`] totals formatted for display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentSummaryDisplay {
+    /// Bytes stored
+    pub bytes: String,
+    /// Sum of quoted costs, in ANT
+    pub quoted_cost: String,
+    /// Sum of actual costs, in ANT
+    pub actual_cost: String,
+}
+
+impl Localize for PaymentSummary {
+    fn localize(&mut self, context: &DisplayContext) {
+        let ant = |attos: u64| context.token_amount(u128::from(attos), ANT_DECIMALS, ANT_SYMBOL);
+        self.display = Some(PaymentSummaryDisplay {
+            bytes: context.bytes(self.bytes),
+            quoted_cost: ant(self.quoted_cost),
+            actual_cost: ant(self.actual_cost),
+        });
+    }
+}
+
+impl PaymentSummary {
+    /// Summarize `records`
+    pub fn from_records(records: &[PaymentRecord]) -> Self {
+        let mut summary = Self::default();
+        let mut monthly: BTreeMap<String, MonthlyTotal> = BTreeMap::new();
+        for record in records {
+            if record.status == PaymentStatus::Failed {
+                summary.failed += 1;
+                continue;
+            }
+            let quoted = record.quoted_cost.unwrap_or(0);
+            let actual = record.actual_cost.unwrap_or(0);
+            summary.uploads += 1;
+            summary.bytes = summary.bytes.saturating_add(record.size);
+            summary.quoted_cost = summary.quoted_cost.saturating_add(quoted);
+            summary.actual_cost = summary.actual_cost.saturating_add(actual);
+
+            let month = month_of(record.recorded_at);
+            let total = monthly
+                .entry(month.clone())
+                .or_insert_with(|| MonthlyTotal {
+                    month,
+                    ..MonthlyTotal::default()
+                });
+            total.uploads += 1;
+            total.bytes = total.bytes.saturating_add(record.size);
+            total.quoted_cost = total.quoted_cost.saturating_add(quoted);
+            total.actual_cost = total.actual_cost.saturating_add(actual);
+        }
+        summary.monthly = monthly.into_values().collect();
+        summary
+    }
+}
+
+/// A page of payments, newest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentHistory {
+    /// Payments on this page
+    pub records: Vec<PaymentRecord>,
+    /// Total number of matching payments
+    pub total: usize,
+    /// Totals over all matching payments, not just this page
+    pub summary: PaymentSummary,
+}
+
+impl Localize for PaymentHistory {
+    fn localize(&mut self, context: &DisplayContext) {
+        self.summary.localize(context);
+    }
+}
+
+/// File format for [`WalletService::export_history`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row (RFC 4180 quoting)
+    Csv,
+    /// JSON document with the records and their summary
+    Json,
+}
+
+/// Payment summary included in diagnostics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentDiagnostics {
+    /// Start of the window, Unix timestamp
+    pub since: u64,
+    /// End of the window, Unix timestamp
+    pub until: u64,
+    /// Totals over the window
+    pub summary: PaymentSummary,
+    /// Addresses paid for, only when the user opted in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub addresses: Option<Vec<String>>,
+}
+
+/// Wallet service reading the upload payment history
+pub struct WalletService {
+    ledger: Arc<PaymentLedger>,
+    config: ConfigService,
+    policies: Option<Arc<PolicyStore>>,
+    guest: GuestGate,
+    clock: SharedClock,
+}
+
+impl WalletService {
+    /// Create a wallet service over the storage directory's ledger
+    pub fn new<P: Into<PathBuf>>(storage_path: P) -> Result<Self> {
+        let storage_path = storage_path.into();
+        let sql_storage = SqlStorage::new(storage_path.join("osnova.db"))?;
+        let config = ConfigService::new(&storage_path)?;
+
+        Ok(Self {
+            ledger: Arc::new(PaymentLedger::new(sql_storage)),
+            config,
+            policies: None,
+            guest: GuestGate::default(),
+            clock: time::default_clock(),
+        })
+    }
+
+    /// Share a ledger, e.g. the one attached to the uploading client
+    pub fn with_ledger(mut self, ledger: Arc<PaymentLedger>) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Approve payments against the wallet caps of the device policy
+    pub fn with_policies(mut self, policies: Arc<PolicyStore>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Refuse payment approvals while a guest session runs
+    pub fn with_guest_gate(mut self, guest: GuestGate) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Replace the clock (for testing)
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The ledger payments are read from
+    pub fn ledger(&self) -> Arc<PaymentLedger> {
+        self.ledger.clone()
+    }
+
+    /// List payments matching `filter`, newest first (OpenRPC: wallet.history)
+    pub fn history(&self, filter: &HistoryFilter, page: PageRequest) -> Result<PaymentHistory> {
+        let records = self.matching(filter)?;
+        let summary = PaymentSummary::from_records(&records);
+        let total = records.len();
+        let records = records
+            .into_iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .collect();
+
+        Ok(PaymentHistory {
+            records,
+            total,
+            summary,
+        })
+    }
+
+    /// Write every payment to `path` (OpenRPC: wallet.exportHistory)
+    ///
+    /// # Returns
+    ///
+    /// The number of payments exported
+    pub fn export_history<P: AsRef<Path>>(&self, path: P, format: ExportFormat) -> Result<usize> {
+        let path = path.as_ref();
+        let records = self.matching(&HistoryFilter::default())?;
+        let contents = match format {
+            ExportFormat::Csv => render_csv(&records),
+            ExportFormat::Json => {
+                let document = serde_json::json!({
+                    "records": records,
+                    "summary": PaymentSummary::from_records(&records),
+                });
+                serde_json::to_string_pretty(&document)?
+            }
+        };
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write payment history to {}", path.display()))?;
+        Ok(records.len())
+    }
+
+    /// Summary of the last 30 days of payments, for diagnostics
+    ///
+    /// Amounts only, unless the user allowed addresses with
+    /// [`ConfigService::set_diagnostics_payment_addresses`].
+    pub fn diagnostics_summary(&self) -> Result<PaymentDiagnostics> {
+        let until = self.clock.now_unix();
+        let since = until.saturating_sub(DIAGNOSTICS_WINDOW_SECS);
+        let records = self.ledger.list(Some(since), Some(until))?;
+        let addresses = if self.config.get_diagnostics_payment_addresses()? {
+            Some(
+                records
+                    .iter()
+                    .filter_map(|record| record.address.clone())
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        Ok(PaymentDiagnostics {
+            since,
+            until,
+            summary: PaymentSummary::from_records(&records),
+            addresses,
+        })
+    }
+
+    /// Check a payment an app asks for before it is approved
+    /// (OpenRPC: wallet.approvePayment)
+    ///
+    /// What the app spent in the cap window is read from the payment
+    /// history, counting completed uploads recorded under its ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::PolicyBlocked`](crate::OsnovaError::PolicyBlocked)
+    /// if the payment would take the app over its cap, or an error if the
+    /// device policy or the history cannot be read, and
+    /// [`GuestModeRestricted`](crate::context::GuestModeRestricted) while a
+    /// guest session runs
+    pub fn approve_payment(&self, app_id: &str, amount: u64) -> Result<()> {
+        self.guest.require_host(GuestRestriction::WalletApproval)?;
+        let Some(policies) = &self.policies else {
+            return Ok(());
+        };
+        let since = self.clock.now_unix().saturating_sub(WALLET_CAP_WINDOW_SECS);
+        let spent = self
+            .matching(&HistoryFilter {
+                since: Some(since),
+                until: None,
+                component_id: Some(app_id.to_string()),
+            })?
+            .iter()
+            .filter(|record| record.status == PaymentStatus::Completed)
+            .map(|record| record.actual_cost.or(record.quoted_cost).unwrap_or(0))
+            .sum();
+        policies.check_payment(app_id, spent, amount)?;
+        Ok(())
+    }
+
+    fn matching(&self, filter: &HistoryFilter) -> Result<Vec<PaymentRecord>> {
+        let mut records = self.ledger.list(filter.since, filter.until)?;
+        if let Some(component_id) = &filter.component_id {
+            records.retain(|record| record.component_id.as_ref() == Some(component_id));
+        }
+        Ok(records)
+    }
+}
+
+/// `YYYY-MM` of a Unix timestamp (UTC)
+fn month_of(timestamp: u64) -> String {
+    let (year, month, _) = civil_from_days(timestamp / 86_400);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// RFC 3339 UTC rendering of a Unix timestamp
+fn format_utc(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / 86_400);
+    let seconds = timestamp % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_csv(records: &[PaymentRecord]) -> String {
+    let mut csv = String::from(
+        "id,recorded_at,component_id,size,address,quoted_cost_atto,actual_cost_atto,\
+         actual_cost_ant,payment_request_id,status\r\n",
+    );
+    for record in records {
+        let fields = [
+            record.id.to_string(),
+            format_utc(record.recorded_at),
+            record.component_id.clone().unwrap_or_default(),
+            record.size.to_string(),
+            record.address.clone().unwrap_or_default(),
+            record
+                .quoted_cost
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            record
+                .actual_cost
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            record.actual_cost.map(format_ant).unwrap_or_default(),
+            record.payment_request_id.clone().unwrap_or_default(),
+            record.status.as_str().to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::OsnovaError;
+    use crate::models::payment_record::ATTOS_PER_ANT;
+    use crate::network::{UploadContext, UploadReceipt};
+    use crate::time::MockClock;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// 2024-03-31T12:00:00Z
+    const END_OF_MARCH: u64 = 1_711_886_400;
+
+    struct Fixture {
+        temp: TempDir,
+        clock: Arc<MockClock>,
+        service: WalletService,
+    }
+
+    fn fixture() -> Fixture {
+        let temp = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::new(END_OF_MARCH));
+        let storage = SqlStorage::new(temp.path().join("osnova.db")).unwrap();
+        let ledger = Arc::new(PaymentLedger::new(storage).with_clock(clock.clone()));
+        let service = WalletService::new(temp.path())
+            .unwrap()
+            .with_ledger(ledger)
+            .with_clock(clock.clone());
+        Fixture {
+            temp,
+            clock,
+            service,
+        }
+    }
+
+    /// Record a completed upload, as the upload path does
+    fn paid(service: &WalletService, context: &UploadContext, address: &str, cost: u64) {
+        let receipt = UploadReceipt {
+            address: address.to_string(),
+            size: 100,
+            cost,
+        };
+        service.ledger().record(100, context, Ok(&receipt)).unwrap();
+    }
+
+    #[test]
+    fn test_history_with_and_without_payment_request() {
+        let f = fixture();
+        let approved = UploadContext::for_component("com.example.app")
+            .with_quote(90)
+            .with_payment_request("req-7");
+        paid(&f.service, &approved, "ant://aa", 100);
+        let error = OsnovaError::Network("offline".to_string());
+        f.service
+            .ledger()
+            .record(50, &UploadContext::default(), Err(&error))
+            .unwrap();
+
+        let history = f
+            .service
+            .history(&HistoryFilter::default(), PageRequest::default())
+            .unwrap();
+        assert_eq!(history.total, 2);
+        let approved = history
+            .records
+            .iter()
+            .find(|r| r.status == PaymentStatus::Completed)
+            .unwrap();
+        assert_eq!(approved.payment_request_id.as_deref(), Some("req-7"));
+        assert_eq!(approved.quoted_cost, Some(90));
+        assert_eq!(approved.actual_cost, Some(100));
+        let failed = history
+            .records
+            .iter()
+            .find(|r| r.status == PaymentStatus::Failed)
+            .unwrap();
+        assert_eq!(failed.payment_request_id, None);
+        assert_eq!(failed.actual_cost, None);
+
+        assert_eq!(history.summary.uploads, 1);
+        assert_eq!(history.summary.failed, 1);
+        assert_eq!(history.summary.actual_cost, 100);
+    }
+
+    #[test]
+    fn test_approve_payment_enforces_wallet_cap() {
+        use crate::models::identity::RootIdentity;
+        use crate::policies::{admin_signing_key, PolicyDocument, PolicyRule, PolicyRules};
+
+        let f = fixture();
+        // Without a policy every payment is approved
+        f.service
+            .approve_payment("com.example.app", u64::MAX)
+            .unwrap();
+
+        let policies = Arc::new(
+            PolicyStore::new(f.temp.path(), "user", &RootIdentity::generate().unwrap()).unwrap(),
+        );
+        let admin = admin_signing_key(&RootIdentity::generate().unwrap()).unwrap();
+        let rules = PolicyRules {
+            wallet_caps: BTreeMap::from([("com.example.app".to_string(), 250)]),
+            ..PolicyRules::default()
+        };
+        policies
+            .apply(
+                &PolicyDocument::new("family", 1, 0, rules)
+                    .sign(&admin)
+                    .unwrap(),
+            )
+            .unwrap();
+        let service = WalletService::new(f.temp.path())
+            .unwrap()
+            .with_ledger(f.service.ledger())
+            .with_clock(f.clock.clone())
+            .with_policies(policies);
+
+        let app = UploadContext::for_component("com.example.app");
+        paid(&service, &app, "ant://old", 1_000);
+        f.clock
+            .advance(Duration::from_secs(WALLET_CAP_WINDOW_SECS + 1));
+        paid(&service, &app, "ant://a", 100);
+        paid(
+            &service,
+            &UploadContext::for_component("com.example.other"),
+            "ant://b",
+            500,
+        );
+
+        // Only this app's payments in the window count toward its cap
+        service.approve_payment("com.example.app", 150).unwrap();
+        match service.approve_payment("com.example.app", 151) {
+            Err(e) => match e.downcast_ref::<OsnovaError>() {
+                Some(OsnovaError::PolicyBlocked { rule, reason, .. }) => {
+                    assert_eq!(*rule, PolicyRule::WalletCap);
+                    assert!(reason.contains("251 of its 250"), "{}", reason);
+                }
+                other => panic!("Expected a policy block, got {:?}", other),
+            },
+            Ok(()) => panic!("Payment over the cap was approved"),
+        }
+        service
+            .approve_payment("com.example.other", 10_000)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_history_filter_and_pagination() {
+        let f = fixture();
+        for i in 0..5 {
+            paid(
+                &f.service,
+                &UploadContext::for_component("com.example.a"),
+                &format!("ant://a{}", i),
+                10,
+            );
+            f.clock.advance(Duration::from_secs(60));
+        }
+        paid(
+            &f.service,
+            &UploadContext::for_component("com.example.b"),
+            "ant://b",
+            10,
+        );
+
+        let filter = HistoryFilter {
+            component_id: Some("com.example.a".to_string()),
+            ..HistoryFilter::default()
+        };
+        let page = f
+            .service
+            .history(
+                &filter,
+                PageRequest {
+                    offset: 1,
+                    limit: 2,
+                },
+            )
+            .unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(page.summary.uploads, 5);
+        let addresses: Vec<_> = page
+            .records
+            .iter()
+            .map(|r| r.address.as_deref().unwrap())
+            .collect();
+        assert_eq!(addresses, vec!["ant://a3", "ant://a2"]);
+
+        let range = HistoryFilter {
+            since: Some(END_OF_MARCH + 60),
+            until: Some(END_OF_MARCH + 120),
+            ..HistoryFilter::default()
+        };
+        let page = f.service.history(&range, PageRequest::default()).unwrap();
+        assert_eq!(page.total, 2);
+    }
+
+    #[test]
+    fn test_monthly_aggregation() {
+        let f = fixture();
+        let context = UploadContext::default().with_quote(5);
+        paid(&f.service, &context, "ant://march1", 10);
+        paid(&f.service, &context, "ant://march2", 20);
+        // Twelve hours later is April 1st
+        f.clock.advance(Duration::from_secs(12 * 60 * 60));
+        paid(&f.service, &context, "ant://april", 40);
+
+        let summary = f
+            .service
+            .history(&HistoryFilter::default(), PageRequest::default())
+            .unwrap()
+            .summary;
+        assert_eq!(summary.actual_cost, 70);
+        assert_eq!(
+            summary.monthly,
+            vec![
+                MonthlyTotal {
+                    month: "2024-03".to_string(),
+                    uploads: 2,
+                    bytes: 200,
+                    quoted_cost: 10,
+                    actual_cost: 30,
+                },
+                MonthlyTotal {
+                    month: "2024-04".to_string(),
+                    uploads: 1,
+                    bytes: 100,
+                    quoted_cost: 5,
+                    actual_cost: 40,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_display_matches_amounts() {
+        use crate::models::payment_record::ATTOS_PER_ANT;
+        use crate::util::format::Locale;
+
+        let f = fixture();
+        let context = UploadContext::default().with_quote(ATTOS_PER_ANT);
+        paid(&f.service, &context, "ant://a", 12 * ATTOS_PER_ANT + 5);
+        paid(&f.service, &context, "ant://b", 250);
+
+        let history = f
+            .service
+            .history(&HistoryFilter::default(), PageRequest::default())
+            .unwrap();
+        assert!(history.summary.display.is_none());
+        let history = history.localized(&DisplayContext::new(Locale::new("de-DE"), 0));
+        let summary = history.summary;
+        let display = summary.display.clone().unwrap();
+        assert_eq!(display.bytes, "200 B");
+        assert_eq!(display.quoted_cost, "2 ANT");
+        assert_eq!(display.actual_cost, "12,000000000000000255 ANT");
+
+        // Formatted amounts are the raw ones, grouped and localized
+        let raw = display
+            .actual_cost
+            .trim_end_matches(" ANT")
+            .replace('.', "")
+            .replace(',', ".");
+        assert_eq!(raw, format_ant(summary.actual_cost));
+    }
+
+    #[test]
+    fn test_export_csv_quoting_and_decimal_separator() {
+        let f = fixture();
+        let context = UploadContext::for_component("odd,\"name\"");
+        paid(
+            &f.service,
+            &context,
+            "ant://cc",
+            ATTOS_PER_ANT + ATTOS_PER_ANT / 4,
+        );
+
+        let path = f.temp.path().join("payments.csv");
+        assert_eq!(
+            f.service.export_history(&path, ExportFormat::Csv).unwrap(),
+            1
+        );
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "1,2024-03-31T12:00:00Z,\"odd,\"\"name\"\"\",100,ant://cc,,\
+             1250000000000000000,1.25,,completed"
+        );
+
+        let path = f.temp.path().join("payments.json");
+        f.service.export_history(&path, ExportFormat::Json).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            json["records"][0]["actualCost"],
+            1_250_000_000_000_000_000u64
+        );
+        assert_eq!(json["summary"]["uploads"], 1);
+    }
+
+    #[test]
+    fn test_diagnostics_redacts_addresses_unless_opted_in() {
+        let f = fixture();
+        paid(&f.service, &UploadContext::default(), "ant://old", 10);
+        f.clock
+            .advance(Duration::from_secs(DIAGNOSTICS_WINDOW_SECS + 1));
+        paid(&f.service, &UploadContext::default(), "ant://recent", 20);
+
+        let diagnostics = f.service.diagnostics_summary().unwrap();
+        assert_eq!(diagnostics.summary.uploads, 1);
+        assert_eq!(diagnostics.summary.actual_cost, 20);
+        assert_eq!(diagnostics.addresses, None);
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert!(!json.contains("ant://"));
+
+        f.service
+            .config
+            .set_diagnostics_payment_addresses(true)
+            .unwrap();
+        let diagnostics = f.service.diagnostics_summary().unwrap();
+        assert_eq!(
+            diagnostics.addresses,
+            Some(vec!["ant://recent".to_string()])
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! Cache warming for apps about to be launched
+//!
+//! [`CacheWarmer`] gets the apps the [`LaunchPredictor`] expects in the
+//! coming hours ready ahead of time: their cached components are verified
+//! and the missing ones downloaded, their listings (icons included)
+//! refreshed, and their configuration loaded into the config cache. The
+//! shell runs it every [`DEFAULT_WARMING_INTERVAL`].
+//!
+//! Warming is opportunistic and never competes with the user:
+//! - It waits while any task is under way in the activity center
+//! - It does nothing while the kill switch is engaged, and stops as soon
+//!   as the bandwidth policy defers a download
+//! - Downloads stay within [`WarmingPolicy::budget_bytes`] per run and
+//!   leave the disk guard's reserve free
+//!
+//! A run shows in the activity center as a
+//! [`TaskCategory::CacheWarming`] task.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::OsnovaError;
+use crate::models::task::TaskCategory;
+use crate::network::bandwidth::{DeferReason, TransferDecision};
+use crate::network::{BandwidthMeter, CancellationToken, NetworkOptions};
+use crate::platform::disk::DiskGuard;
+use crate::services::apps::AppsService;
+use crate::services::metadata::MetadataService;
+use crate::services::prediction::{LaunchPredictor, PredictedLaunch};
+use crate::services::TaskRegistry;
+
+/// Loads an app's configuration into a config cache
+pub type ConfigPrimer = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// Time between warming runs
+pub const DEFAULT_WARMING_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Which apps to warm, and how much a run may download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmingPolicy {
+    /// How far ahead to predict launches, in seconds
+    pub window_secs: u64,
+    /// Most apps warmed per run
+    pub max_apps: usize,
+    /// Lowest prediction score worth warming for
+    pub min_score: f64,
+    /// Most bytes a run may add to the component cache
+    pub budget_bytes: u64,
+}
+
+impl Default for WarmingPolicy {
+    fn default() -> Self {
+        Self {
+            window_secs: 2 * 60 * 60,
+            max_apps: 5,
+            min_score: 1.0,
+            budget_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Why a warming run did nothing, or stopped early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WarmingSkip {
+    /// Other tasks were under way
+    Busy,
+    /// The user switched Osnova offline
+    Offline,
+    /// The bandwidth policy deferred downloads
+    Deferred,
+    /// Downloading would eat into the disk reserve
+    LowDiskSpace,
+}
+
+/// What a warming run did for one app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "outcome", rename_all = "camelCase")]
+pub enum WarmOutcome {
+    /// Every component was already cached
+    Ready,
+    /// The missing components were downloaded
+    Materialized {
+        /// Expected bytes downloaded
+        bytes: u64,
+    },
+    /// The missing components did not fit in what was left of the budget
+    OverBudget {
+        /// Expected bytes to download
+        bytes: u64,
+    },
+    /// The bandwidth policy deferred a download
+    Deferred,
+    /// Verifying or downloading failed
+    Failed {
+        /// What went wrong
+        reason: String,
+    },
+}
+
+/// An app a warming run looked at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmedApp {
+    /// Application identifier
+    pub app_id: String,
+    /// Prediction score the app was picked with
+    pub score: f64,
+    /// What was done
+    #[serde(flatten)]
+    pub outcome: WarmOutcome,
+}
+
+/// Result of a warming run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmingReport {
+    /// Why the run did nothing or stopped early
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<WarmingSkip>,
+    /// Predicted apps, likeliest first, as far as the run got
+    pub apps: Vec<WarmedApp>,
+    /// Expected bytes downloaded
+    pub bytes: u64,
+}
+
+impl WarmingReport {
+    /// Apps whose components are all cached after the run
+    pub fn warm_apps(&self) -> impl Iterator<Item = &str> {
+        self.apps
+            .iter()
+            .filter(|app| {
+                matches!(
+                    app.outcome,
+                    WarmOutcome::Ready | WarmOutcome::Materialized { .. }
+                )
+            })
+            .map(|app| app.app_id.as_str())
+    }
+}
+
+/// Readies the apps likely to be launched soon
+pub struct CacheWarmer {
+    apps: AppsService,
+    predictor: Arc<LaunchPredictor>,
+    policy: WarmingPolicy,
+    options: NetworkOptions,
+    meter: Option<Arc<BandwidthMeter>>,
+    disk: Option<(DiskGuard, PathBuf)>,
+    tasks: Option<Arc<TaskRegistry>>,
+    metadata: Option<Arc<MetadataService>>,
+    prime_config: Option<ConfigPrimer>,
+}
+
+impl CacheWarmer {
+    /// Create a warmer downloading through `apps`, with the default policy
+    pub fn new(apps: AppsService, predictor: Arc<LaunchPredictor>) -> Self {
+        Self {
+            apps,
+            predictor,
+            policy: WarmingPolicy::default(),
+            options: NetworkOptions::default(),
+            meter: None,
+            disk: None,
+            tasks: None,
+            metadata: None,
+            prime_config: None,
+        }
+    }
+
+    /// Warm apps under a specific policy
+    pub fn with_policy(mut self, policy: WarmingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Run downloads and refreshes with specific network options
+    pub fn with_network_options(mut self, options: NetworkOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Skip runs the bandwidth policy would defer
+    pub fn with_bandwidth_meter(mut self, meter: Arc<BandwidthMeter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Keep the guard's reserve free on the volume of `cache_dir`
+    pub fn with_disk_guard<P: Into<PathBuf>>(mut self, guard: DiskGuard, cache_dir: P) -> Self {
+        self.disk = Some((guard, cache_dir.into()));
+        self
+    }
+
+    /// Wait for idle in, and report runs to, the activity center
+    pub fn with_tasks(mut self, tasks: Arc<TaskRegistry>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Refresh the listings of warmed apps
+    pub fn with_metadata(mut self, metadata: Arc<MetadataService>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Load the configuration of warmed apps with `prime`, e.g. through
+    /// [`ConfigService::get_app_config`](super::ConfigService::get_app_config)
+    /// of a cached config service
+    pub fn with_config_primer<F>(mut self, prime: F) -> Self
+    where
+        F: Fn(&str) -> Result<()> + Send + Sync + 'static,
+    {
+        self.prime_config = Some(Box::new(prime));
+        self
+    }
+
+    /// Warm apps every `interval` until `shutdown` is cancelled
+    ///
+    /// Spawn this on the async runtime. A failed run is retried on the
+    /// next tick.
+    pub async fn run(self: Arc<Self>, interval: Duration, shutdown: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown.cancelled() => break,
+            }
+            match self.warm().await {
+                Ok(report) if report.bytes > 0 => {
+                    crate::log!(Info, "Prepared {} apps", report.warm_apps().count())
+                }
+                Ok(_) => {}
+                Err(e) => crate::log!(Warn, "Cache warming failed: {:#}", e),
+            }
+        }
+    }
+
+    /// Warm the apps predicted for the coming window
+    ///
+    /// Failures of single apps are reported per app.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bandwidth meter or the prediction model
+    /// cannot be read
+    pub async fn warm(&self) -> Result<WarmingReport> {
+        if let Some(skip) = self.gate()? {
+            return Ok(WarmingReport {
+                skipped: Some(skip),
+                ..WarmingReport::default()
+            });
+        }
+
+        let window = Duration::from_secs(self.policy.window_secs);
+        let predicted: Vec<PredictedLaunch> = self
+            .predictor
+            .predict(window, self.policy.max_apps)?
+            .into_iter()
+            .filter(|predicted| predicted.score >= self.policy.min_score)
+            .collect();
+        if predicted.is_empty() {
+            return Ok(WarmingReport::default());
+        }
+
+        let task = self.tasks.as_ref().map(|tasks| {
+            tasks.register_cancellable(
+                "Prepare apps likely to be opened soon",
+                TaskCategory::CacheWarming,
+                self.options.cancellation.clone(),
+            )
+        });
+        let total = predicted.len() as u64;
+        let mut report = WarmingReport::default();
+        for (index, predicted) in predicted.into_iter().enumerate() {
+            if self.options.cancellation.is_cancelled() {
+                break;
+            }
+            let outcome = match self.warm_app(&predicted.app_id, &mut report).await {
+                Ok(outcome) => outcome,
+                Err(e) => WarmOutcome::Failed {
+                    reason: format!("{:#}", e),
+                },
+            };
+            if let Some(task) = &task {
+                task.progress(index as u64 + 1, Some(total));
+            }
+            report.apps.push(WarmedApp {
+                app_id: predicted.app_id,
+                score: predicted.score,
+                outcome,
+            });
+            if report.skipped.is_some() {
+                break;
+            }
+        }
+
+        if let Some(task) = task {
+            match self.options.cancellation.is_cancelled() {
+                true => task.fail("Cancelled"),
+                false => {
+                    task.complete_with(format!("Prepared {} apps", report.warm_apps().count()))
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Why nothing should be warmed right now, if anything
+    fn gate(&self) -> Result<Option<WarmingSkip>> {
+        if self.options.kill_switch.is_engaged() {
+            return Ok(Some(WarmingSkip::Offline));
+        }
+        if let Some(meter) = &self.meter {
+            match meter.check()? {
+                TransferDecision::Allowed => {}
+                TransferDecision::Deferred(DeferReason::OfflineByUser) => {
+                    return Ok(Some(WarmingSkip::Offline))
+                }
+                TransferDecision::Deferred(_) => return Ok(Some(WarmingSkip::Deferred)),
+            }
+        }
+        if let Some(tasks) = &self.tasks {
+            if !tasks.list_active().is_empty() {
+                return Ok(Some(WarmingSkip::Busy));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Verify an app and download what it misses within the budget,
+    /// noting in `report` what was spent and whether to stop
+    async fn warm_app(&self, app_id: &str, report: &mut WarmingReport) -> Result<WarmOutcome> {
+        let missing = self.apps.missing_bytes(app_id).await?;
+        let outcome = if missing == 0 {
+            WarmOutcome::Ready
+        } else if report.bytes.saturating_add(missing) > self.policy.budget_bytes {
+            return Ok(WarmOutcome::OverBudget { bytes: missing });
+        } else {
+            if let Some((guard, cache_dir)) = &self.disk {
+                if let Err(e) = guard.check(cache_dir, missing) {
+                    if matches!(e, OsnovaError::DiskSpaceLow { .. }) {
+                        report.skipped = Some(WarmingSkip::LowDiskSpace);
+                    }
+                    return Err(e.into());
+                }
+            }
+            let materialized = self.apps.materialize(app_id, &self.options, |_| {}).await?;
+            if materialized.deferred {
+                report.skipped = Some(WarmingSkip::Deferred);
+                return Ok(WarmOutcome::Deferred);
+            }
+            if !materialized.state.is_materialized() {
+                anyhow::bail!("Some components could not be downloaded");
+            }
+            report.bytes += missing;
+            WarmOutcome::Materialized { bytes: missing }
+        };
+
+        if let Some(metadata) = &self.metadata {
+            if let Err(e) = metadata.refresh(app_id, &self.options).await {
+                crate::log!(Warn, "Listing of {} not refreshed: {:#}", app_id, e);
+            }
+        }
+        if let Some(prime) = &self.prime_config {
+            if let Err(e) = prime(app_id) {
+                crate::log!(Warn, "Configuration of {} not loaded: {:#}", app_id, e);
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use crate::components::ComponentDownloader;
+    use crate::models::application::{ComponentKind, ComponentRef, OsnovaApplication};
+    use crate::models::task::TaskState;
+    use crate::network::kill_switch::NetworkKillSwitch;
+    use crate::network::BandwidthPolicy;
+    use crate::platform::disk::FixedDiskSpace;
+    use crate::storage::SqlStorage;
+    use crate::time::MockClock;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Monday 2024-01-01 08:00 UTC
+    const MONDAY_MORNING: u64 = 1_704_096_000;
+    const MIB: u64 = 1024 * 1024;
+
+    /// Serve every path as a small script, recording the paths requested
+    async fn spawn_backend(requests: Arc<Mutex<Vec<String>>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let read = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..read]);
+                    if let Some(path) = request.split_whitespace().nth(1) {
+                        requests.lock().unwrap().push(path.to_string());
+                    }
+                    let body = b"exit 0\n";
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = socket.write_all(header.as_bytes()).await;
+                    let _ = socket.write_all(body).await;
+                });
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    struct Fixture {
+        temp: TempDir,
+        predictor: Arc<LaunchPredictor>,
+        apps: AppsService,
+        tasks: Arc<TaskRegistry>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    /// Apps each with one backend served by a mock server, and a predictor
+    /// that has seen `launches` launches of each this morning last week
+    async fn fixture(launches: &[(&str, usize)]) -> Result<Fixture> {
+        let temp = TempDir::new()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let base = spawn_backend(requests.clone()).await;
+
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?;
+        for (app_id, _) in launches {
+            let name = app_id.rsplit('.').next().unwrap();
+            let backend = ComponentRef::new(
+                format!("{}/{}", base, name),
+                format!("{}-backend", name),
+                ComponentKind::Backend,
+                "1.0.0",
+            )?
+            .with_interpreter("sh");
+            let app = OsnovaApplication::new(*app_id, name, "1.0.0", "icon", name, vec![backend])?;
+            storage.upsert_application(&app)?;
+        }
+
+        let clock = Arc::new(MockClock::new(MONDAY_MORNING));
+        let predictor = Arc::new(LaunchPredictor::new(temp.path())?.with_clock(clock));
+        let last_week = MONDAY_MORNING - 7 * 24 * 60 * 60;
+        for (app_id, count) in launches {
+            for _ in 0..*count {
+                predictor.record_launch_at(app_id, last_week)?;
+            }
+        }
+
+        let tasks = Arc::new(TaskRegistry::new());
+        let cache = CacheManager::new(temp.path().join("cache"), 512 * MIB as usize)?;
+        let downloader = ComponentDownloader::new(cache, None)
+            .with_http(crate::http::local_fetcher())
+            .with_tasks(tasks.clone());
+        let apps = AppsService::new(temp.path())?
+            .with_downloader(downloader)
+            .with_tasks(tasks.clone());
+        Ok(Fixture {
+            temp,
+            predictor,
+            apps,
+            tasks,
+            requests,
+        })
+    }
+
+    fn offline_capable_options() -> NetworkOptions {
+        NetworkOptions {
+            kill_switch: Arc::new(NetworkKillSwitch::new()),
+            ..NetworkOptions::default()
+        }
+    }
+
+    fn outcomes(report: &WarmingReport) -> Vec<(&str, &WarmOutcome)> {
+        report
+            .apps
+            .iter()
+            .map(|app| (app.app_id.as_str(), &app.outcome))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_warms_predicted_apps_within_budget() -> Result<()> {
+        let f = fixture(&[
+            ("com.test.mail", 5),
+            ("com.test.news", 3),
+            ("com.test.maps", 2),
+            ("com.test.rare", 1),
+        ])
+        .await?;
+        // Components of unknown size count as 64 MiB
+        let primed = Arc::new(Mutex::new(Vec::new()));
+        let priming = primed.clone();
+        let warmer = CacheWarmer::new(f.apps, f.predictor.clone())
+            .with_network_options(offline_capable_options())
+            .with_tasks(f.tasks.clone())
+            .with_config_primer(move |app_id| {
+                priming.lock().unwrap().push(app_id.to_string());
+                Ok(())
+            })
+            .with_policy(WarmingPolicy {
+                window_secs: 60 * 60,
+                max_apps: 3,
+                min_score: 1.0,
+                budget_bytes: 100 * MIB,
+            });
+
+        let report = warmer.warm().await?;
+        assert_eq!(report.skipped, None);
+        assert_eq!(
+            outcomes(&report),
+            vec![
+                (
+                    "com.test.mail",
+                    &WarmOutcome::Materialized { bytes: 64 * MIB }
+                ),
+                (
+                    "com.test.news",
+                    &WarmOutcome::OverBudget { bytes: 64 * MIB }
+                ),
+                (
+                    "com.test.maps",
+                    &WarmOutcome::OverBudget { bytes: 64 * MIB }
+                ),
+            ]
+        );
+        assert_eq!(report.bytes, 64 * MIB);
+        assert_eq!(
+            report.warm_apps().collect::<Vec<_>>(),
+            vec!["com.test.mail"]
+        );
+        assert_eq!(*f.requests.lock().unwrap(), vec!["/mail".to_string()]);
+        assert!(AppsService::new(f.temp.path())?
+            .materialization("com.test.mail")?
+            .unwrap()
+            .is_materialized());
+        assert_eq!(*primed.lock().unwrap(), vec!["com.test.mail".to_string()]);
+
+        // The run shows in the activity center
+        let run = &f.tasks.history(1)[0];
+        assert_eq!(run.category, TaskCategory::CacheWarming);
+        assert_eq!(run.state, TaskState::Completed);
+        assert_eq!((run.completed, run.total), (3, Some(3)));
+
+        // The next run finds mail ready and spends its budget on news
+        let report = warmer.warm().await?;
+        assert_eq!(
+            outcomes(&report)[..2],
+            [
+                ("com.test.mail", &WarmOutcome::Ready),
+                (
+                    "com.test.news",
+                    &WarmOutcome::Materialized { bytes: 64 * MIB }
+                ),
+            ]
+        );
+        assert_eq!(f.requests.lock().unwrap().len(), 2);
+        assert_eq!(primed.lock().unwrap().len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_warming_respects_policies() -> Result<()> {
+        let f = fixture(&[("com.test.mail", 2)]).await?;
+        let options = offline_capable_options();
+        let meter = Arc::new(
+            BandwidthMeter::new(SqlStorage::new_in_memory()?, BandwidthPolicy::WifiOnly)
+                .with_kill_switch(options.kill_switch.clone()),
+        );
+        let disk = Arc::new(FixedDiskSpace::new(10 * MIB));
+        let warmer = CacheWarmer::new(f.apps, f.predictor.clone())
+            .with_network_options(options.clone())
+            .with_bandwidth_meter(meter.clone())
+            .with_disk_guard(DiskGuard::new(MIB).with_provider(disk.clone()), "/cache")
+            .with_tasks(f.tasks.clone());
+
+        // The kill switch, a metered connection, and other tasks under
+        // way each keep the warmer from doing anything
+        options.kill_switch.engage();
+        assert_eq!(warmer.warm().await?.skipped, Some(WarmingSkip::Offline));
+        options.kill_switch.release();
+
+        meter.set_metered_mode(true);
+        assert_eq!(warmer.warm().await?.skipped, Some(WarmingSkip::Deferred));
+        meter.set_metered_mode(false);
+
+        let busy = f.tasks.register("Download Wallet", TaskCategory::Download);
+        assert_eq!(warmer.warm().await?.skipped, Some(WarmingSkip::Busy));
+        busy.complete();
+
+        // Too little disk space stops the run before any download
+        let report = warmer.warm().await?;
+        assert_eq!(report.skipped, Some(WarmingSkip::LowDiskSpace));
+        assert!(matches!(report.apps[0].outcome, WarmOutcome::Failed { .. }));
+        assert!(f.requests.lock().unwrap().is_empty());
+
+        disk.set(1024 * MIB);
+        let report = warmer.warm().await?;
+        assert_eq!(report.skipped, None);
+        assert_eq!(
+            report.warm_apps().collect::<Vec<_>>(),
+            vec!["com.test.mail"]
+        );
+
+        // Nothing is predicted once usage is cleared
+        f.predictor.clear_usage()?;
+        let report = warmer.warm().await?;
+        assert_eq!(report, WarmingReport::default());
+
+        Ok(())
+    }
+}
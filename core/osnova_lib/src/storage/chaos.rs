@@ -0,0 +1,655 @@
+//! Fault injection for resilience testing
+//!
+//! Slow disks and intermittent write failures cause bugs (truncated configs,
+//! half-written cocoons) that never show up on a developer machine. A
+//! [`ChaosProfile`] describes the faults to inject per path prefix: extra
+//! latency, transient I/O errors on a fraction of operations, and partial
+//! writes that put N bytes on disk and then fail.
+//!
+//! `ChaosFileStorage` wraps `FileStorage` with the same surface and
+//! applies a profile. It only exists with the `chaos` feature; the
+//! [`DebugGate`] refuses chaos mode in every other build, so a stored
+//! profile cannot switch it on in a release.
+//!
+//! The `chaos-tests` feature additionally runs the FileStorage atomic-write
+//! and cocoon concurrency scenarios under a flaky profile.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[cfg(feature = "chaos")]
+use anyhow::Context;
+#[cfg(feature = "chaos")]
+use std::{fs, path::PathBuf, sync::Mutex, time::Duration};
+
+#[cfg(feature = "chaos")]
+use crate::crypto::encryption::CocoonEncryption;
+#[cfg(feature = "chaos")]
+use crate::storage::FileStorage;
+
+/// Decides which developer-only facilities this build may switch on
+#[derive(Debug, Clone, Copy)]
+pub struct DebugGate;
+
+impl DebugGate {
+    /// Whether chaos mode can be enabled (builds with the `chaos` feature)
+    pub fn chaos_allowed() -> bool {
+        cfg!(feature = "chaos")
+    }
+
+    /// Fail unless chaos mode can be enabled
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in builds without the `chaos` feature
+    pub fn ensure_chaos_allowed() -> Result<()> {
+        if !Self::chaos_allowed() {
+            bail!("Chaos mode is not available in this build");
+        }
+        Ok(())
+    }
+
+    /// Whether support diagnostic queries can run (builds with the
+    /// `diagnostics` feature)
+    pub fn diagnostic_queries_allowed() -> bool {
+        cfg!(feature = "diagnostics")
+    }
+
+    /// Fail unless diagnostic queries can run
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in builds without the `diagnostics` feature
+    pub fn ensure_diagnostic_queries_allowed() -> Result<()> {
+        if !Self::diagnostic_queries_allowed() {
+            bail!("Diagnostic queries are not available in this build");
+        }
+        Ok(())
+    }
+}
+
+/// Extra latency added to an operation, uniformly distributed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Latency {
+    /// Shortest delay in milliseconds
+    pub min_ms: u64,
+    /// Longest delay in milliseconds
+    pub max_ms: u64,
+}
+
+/// Faults injected into operations on paths under one prefix
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosRule {
+    /// Path prefix relative to the storage root; empty matches every path
+    pub path_prefix: String,
+    /// Extra latency for every matching operation
+    #[serde(default)]
+    pub latency: Option<Latency>,
+    /// Fraction of operations failing with a transient I/O error (0.0-1.0)
+    #[serde(default)]
+    pub error_rate: f64,
+    /// Fraction of writes that put `partial_write_bytes` on disk and fail
+    #[serde(default)]
+    pub partial_write_rate: f64,
+    /// Bytes a partial write gets on disk before failing
+    #[serde(default)]
+    pub partial_write_bytes: usize,
+}
+
+impl ChaosRule {
+    /// A rule for paths under `path_prefix` that injects nothing yet
+    pub fn new(path_prefix: impl Into<String>) -> Self {
+        Self {
+            path_prefix: path_prefix.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Add latency between `min_ms` and `max_ms`
+    pub fn with_latency(mut self, min_ms: u64, max_ms: u64) -> Self {
+        self.latency = Some(Latency { min_ms, max_ms });
+        self
+    }
+
+    /// Fail this fraction of operations
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate;
+        self
+    }
+
+    /// Cut this fraction of writes off after `bytes` bytes
+    pub fn with_partial_writes(mut self, rate: f64, bytes: usize) -> Self {
+        self.partial_write_rate = rate;
+        self.partial_write_bytes = bytes;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, rate) in [
+            ("error rate", self.error_rate),
+            ("partial write rate", self.partial_write_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                bail!(
+                    "Chaos rule for '{}': {} {} is outside 0.0-1.0",
+                    self.path_prefix,
+                    name,
+                    rate
+                );
+            }
+        }
+        if let Some(latency) = self.latency {
+            if latency.min_ms > latency.max_ms {
+                bail!(
+                    "Chaos rule for '{}': latency minimum exceeds maximum",
+                    self.path_prefix
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A named set of chaos rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosProfile {
+    /// Profile name shown in diagnostics
+    pub name: String,
+    /// Seed for fault selection, so a run can be reproduced
+    #[serde(default)]
+    pub seed: u64,
+    /// Rules; the longest matching prefix applies
+    pub rules: Vec<ChaosRule>,
+}
+
+impl ChaosProfile {
+    /// An empty profile
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            seed: 0,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Add a rule
+    pub fn with_rule(mut self, rule: ChaosRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Use a fixed seed for fault selection
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The rule for a storage-relative path, if any
+    ///
+    /// Prefixes match whole path components, and the longest one wins.
+    pub fn rule_for(&self, path: &Path) -> Option<&ChaosRule> {
+        self.rules
+            .iter()
+            .filter(|rule| path.starts_with(&rule.path_prefix))
+            .max_by_key(|rule| Path::new(&rule.path_prefix).components().count())
+    }
+
+    /// Check every rule's rates and latency bounds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first invalid rule
+    pub fn validate(&self) -> Result<()> {
+        self.rules.iter().try_for_each(ChaosRule::validate)
+    }
+}
+
+/// Counts of injected faults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosStats {
+    /// Operations seen
+    pub operations: u64,
+    /// Operations delayed
+    pub delayed: u64,
+    /// Operations failed with an injected error
+    pub errors: u64,
+    /// Writes cut off part way
+    pub partial_writes: u64,
+}
+
+/// [`FileStorage`] with faults injected according to a [`ChaosProfile`]
+///
+/// Injected errors look like real I/O failures: they carry the same
+/// "Failed to ..." context as FileStorage's own errors.
+#[cfg(feature = "chaos")]
+pub struct ChaosFileStorage {
+    inner: FileStorage,
+    profile: ChaosProfile,
+    state: Mutex<ChaosState>,
+}
+
+#[cfg(feature = "chaos")]
+struct ChaosState {
+    rng: SplitMix64,
+    stats: ChaosStats,
+}
+
+#[cfg(feature = "chaos")]
+#[derive(Debug, PartialEq, Eq)]
+enum Fault {
+    None,
+    Error,
+    PartialWrite(usize),
+}
+
+#[cfg(feature = "chaos")]
+impl ChaosFileStorage {
+    /// Wrap `inner`, injecting the faults `profile` describes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`DebugGate`] forbids chaos mode or the
+    /// profile is invalid
+    pub fn new(inner: FileStorage, profile: ChaosProfile) -> Result<Self> {
+        DebugGate::ensure_chaos_allowed()?;
+        profile.validate()?;
+        let rng = SplitMix64(profile.seed);
+        Ok(Self {
+            inner,
+            profile,
+            state: Mutex::new(ChaosState {
+                rng,
+                stats: ChaosStats::default(),
+            }),
+        })
+    }
+
+    /// The profile in effect
+    pub fn profile(&self) -> &ChaosProfile {
+        &self.profile
+    }
+
+    /// Faults injected so far
+    pub fn stats(&self) -> ChaosStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// The wrapped storage, without fault injection
+    pub fn inner(&self) -> &FileStorage {
+        &self.inner
+    }
+
+    /// See [`FileStorage::write`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the write fails or a fault is injected
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        relative_path: P,
+        data: &[u8],
+        encryption_key: &[u8; 32],
+    ) -> Result<()> {
+        let relative_path = relative_path.as_ref();
+        let full_path = self.inner.full_path(relative_path);
+        match self.inject(relative_path, true) {
+            Fault::None => self.inner.write(relative_path, data, encryption_key),
+            Fault::Error => Err(injected("write", &full_path)),
+            Fault::PartialWrite(bytes) => {
+                // Put the first bytes where an interrupted write would
+                if let Some(parent) = full_path.parent() {
+                    fs::create_dir_all(parent).context("Failed to create parent directories")?;
+                }
+                let encrypted = CocoonEncryption::new(encryption_key)
+                    .encrypt(data)
+                    .context("Failed to encrypt data")?;
+                let bytes = bytes.min(encrypted.len());
+                fs::write(FileStorage::temp_path(&full_path), &encrypted[..bytes])?;
+                Err(injected("write", &full_path))
+            }
+        }
+    }
+
+    /// See [`FileStorage::read`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the read fails or a fault is injected
+    pub fn read<P: AsRef<Path>>(
+        &self,
+        relative_path: P,
+        encryption_key: &[u8; 32],
+    ) -> Result<Vec<u8>> {
+        let relative_path = relative_path.as_ref();
+        self.fail_or(relative_path, "read", || {
+            self.inner.read(relative_path, encryption_key)
+        })
+    }
+
+    /// See [`FileStorage::exists`]
+    pub fn exists<P: AsRef<Path>>(&self, relative_path: P) -> bool {
+        self.inner.exists(relative_path)
+    }
+
+    /// See [`FileStorage::delete`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delete fails or a fault is injected
+    pub fn delete<P: AsRef<Path>>(&self, relative_path: P) -> Result<bool> {
+        let relative_path = relative_path.as_ref();
+        self.fail_or(relative_path, "delete", || self.inner.delete(relative_path))
+    }
+
+    /// See [`FileStorage::list_files`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the listing fails or a fault is injected
+    pub fn list_files<P: AsRef<Path>>(&self, relative_path: P) -> Result<Vec<PathBuf>> {
+        let relative_path = relative_path.as_ref();
+        self.fail_or(relative_path, "list", || {
+            self.inner.list_files(relative_path)
+        })
+    }
+
+    /// See [`FileStorage::clear_directory`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if clearing fails or a fault is injected
+    pub fn clear_directory<P: AsRef<Path>>(&self, relative_path: P) -> Result<()> {
+        let relative_path = relative_path.as_ref();
+        self.fail_or(relative_path, "clear", || {
+            self.inner.clear_directory(relative_path)
+        })
+    }
+
+    /// See [`FileStorage::full_path`]
+    pub fn full_path<P: AsRef<Path>>(&self, relative_path: P) -> PathBuf {
+        self.inner.full_path(relative_path)
+    }
+
+    /// See [`FileStorage::base_path`]
+    pub fn base_path(&self) -> &Path {
+        self.inner.base_path()
+    }
+
+    fn fail_or<T>(&self, path: &Path, action: &str, op: impl FnOnce() -> Result<T>) -> Result<T> {
+        match self.inject(path, false) {
+            Fault::None => op(),
+            _ => Err(injected(action, &self.inner.full_path(path))),
+        }
+    }
+
+    /// Roll the dice for one operation, sleeping for any injected latency
+    fn inject(&self, path: &Path, write: bool) -> Fault {
+        let (delay, fault) = {
+            let mut state = self.state.lock().unwrap();
+            state.stats.operations += 1;
+            let Some(rule) = self.profile.rule_for(path) else {
+                return Fault::None;
+            };
+
+            let delay = rule.latency.map(|latency| {
+                let spread = latency.max_ms - latency.min_ms;
+                latency.min_ms + state.rng.next_u64() % (spread + 1)
+            });
+            let fault = if state.rng.chance(rule.error_rate) {
+                state.stats.errors += 1;
+                Fault::Error
+            } else if write && state.rng.chance(rule.partial_write_rate) {
+                state.stats.partial_writes += 1;
+                Fault::PartialWrite(rule.partial_write_bytes)
+            } else {
+                Fault::None
+            };
+            if delay.is_some() {
+                state.stats.delayed += 1;
+            }
+            (delay, fault)
+        };
+
+        if let Some(ms) = delay {
+            std::thread::sleep(Duration::from_millis(ms));
+        }
+        fault
+    }
+}
+
+#[cfg(feature = "chaos")]
+fn injected(action: &str, full_path: &Path) -> anyhow::Error {
+    anyhow::Error::new(std::io::Error::other("injected by chaos mode")).context(format!(
+        "Failed to {} file: {}",
+        action,
+        full_path.display()
+    ))
+}
+
+/// Small deterministic generator for fault selection
+#[cfg(feature = "chaos")]
+struct SplitMix64(u64);
+
+#[cfg(feature = "chaos")]
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_for_matches_longest_prefix() {
+        let profile = ChaosProfile::new("slow-cache")
+            .with_rule(ChaosRule::new("").with_latency(1, 2))
+            .with_rule(ChaosRule::new("cache").with_error_rate(0.5))
+            .with_rule(ChaosRule::new("cache/components").with_error_rate(1.0));
+
+        let rule = |path: &str| {
+            profile
+                .rule_for(Path::new(path))
+                .unwrap()
+                .path_prefix
+                .clone()
+        };
+        assert_eq!(rule("cache/components/abc"), "cache/components");
+        assert_eq!(rule("cache/icons/abc"), "cache");
+        // Prefixes match whole components
+        assert_eq!(rule("cachefile"), "");
+        assert_eq!(rule("config/system.json"), "");
+    }
+
+    #[test]
+    fn test_profile_validation() {
+        let valid = ChaosProfile::new("ok").with_rule(ChaosRule::new("").with_error_rate(0.1));
+        assert!(valid.validate().is_ok());
+
+        let rate = ChaosProfile::new("bad").with_rule(ChaosRule::new("").with_error_rate(1.5));
+        assert!(rate.validate().is_err());
+
+        let latency = ChaosProfile::new("bad").with_rule(ChaosRule::new("").with_latency(5, 1));
+        assert!(latency.validate().is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "chaos"))]
+    fn test_gate_blocks_chaos_in_normal_builds() {
+        assert!(!DebugGate::chaos_allowed());
+        assert!(DebugGate::ensure_chaos_allowed().is_err());
+    }
+
+    #[cfg(feature = "chaos")]
+    mod injection {
+        use super::super::*;
+        use tempfile::TempDir;
+
+        fn chaos_storage(profile: ChaosProfile) -> (ChaosFileStorage, TempDir) {
+            let temp_dir = TempDir::new().unwrap();
+            let inner = FileStorage::new(temp_dir.path()).unwrap();
+            (ChaosFileStorage::new(inner, profile).unwrap(), temp_dir)
+        }
+
+        #[test]
+        fn test_error_rate_is_honoured() {
+            let profile = ChaosProfile::new("flaky")
+                .with_seed(7)
+                .with_rule(ChaosRule::new("").with_error_rate(0.25));
+            let (storage, _temp) = chaos_storage(profile);
+            let key = [1u8; 32];
+            storage.inner().write("data", b"value", &key).unwrap();
+
+            let runs = 4000;
+            let failures = (0..runs)
+                .filter(|_| storage.read("data", &key).is_err())
+                .count();
+            let rate = failures as f64 / runs as f64;
+            assert!((0.22..0.28).contains(&rate), "observed error rate {}", rate);
+            assert_eq!(storage.stats().errors, failures as u64);
+        }
+
+        #[test]
+        fn test_latency_is_injected() {
+            let profile =
+                ChaosProfile::new("slow").with_rule(ChaosRule::new("").with_latency(5, 10));
+            let (storage, _temp) = chaos_storage(profile);
+            let key = [2u8; 32];
+
+            let started = std::time::Instant::now();
+            for _ in 0..4 {
+                storage.write("data", b"value", &key).unwrap();
+            }
+            assert!(started.elapsed() >= Duration::from_millis(20));
+            assert_eq!(storage.stats().delayed, 4);
+        }
+
+        #[test]
+        fn test_faults_are_scoped_to_path_prefix() {
+            let profile = ChaosProfile::new("broken-cache")
+                .with_rule(ChaosRule::new("cache").with_error_rate(1.0));
+            let (storage, _temp) = chaos_storage(profile);
+            let key = [3u8; 32];
+
+            for _ in 0..50 {
+                storage.write("config/system.json", b"{}", &key).unwrap();
+                assert!(storage.write("cache/entry", b"{}", &key).is_err());
+            }
+            assert_eq!(storage.stats().errors, 50);
+
+            let error = storage.read("cache/entry", &key).unwrap_err();
+            assert!(format!("{:#}", error).contains("injected by chaos mode"));
+        }
+
+        #[test]
+        fn test_partial_write_keeps_previous_contents() {
+            let profile = ChaosProfile::new("torn-writes")
+                .with_rule(ChaosRule::new("keys").with_partial_writes(1.0, 7));
+            let (storage, _temp) = chaos_storage(profile);
+            let key = [4u8; 32];
+            storage
+                .inner()
+                .write("keys/cocoon", b"original", &key)
+                .unwrap();
+
+            assert!(storage.write("keys/cocoon", b"updated", &key).is_err());
+            assert_eq!(storage.stats().partial_writes, 1);
+
+            // Seven bytes reached a temporary file, the cocoon is untouched
+            assert_eq!(storage.read("keys/cocoon", &key).unwrap(), b"original");
+            assert_eq!(storage.list_files("keys").unwrap().len(), 1);
+        }
+    }
+
+    /// FileStorage's atomic-write and cocoon concurrency scenarios, run
+    /// through a flaky profile
+    #[cfg(feature = "chaos-tests")]
+    mod resilience {
+        use super::super::*;
+        use std::sync::Arc;
+        use tempfile::TempDir;
+
+        fn flaky_storage(seed: u64) -> (ChaosFileStorage, TempDir) {
+            let profile = ChaosProfile::new("flaky-disk").with_seed(seed).with_rule(
+                ChaosRule::new("")
+                    .with_latency(0, 2)
+                    .with_error_rate(0.2)
+                    .with_partial_writes(0.2, 16),
+            );
+            let temp_dir = TempDir::new().unwrap();
+            let inner = FileStorage::new(temp_dir.path()).unwrap();
+            (ChaosFileStorage::new(inner, profile).unwrap(), temp_dir)
+        }
+
+        fn retry<T>(mut op: impl FnMut() -> Result<T>) -> T {
+            for _ in 0..100 {
+                if let Ok(value) = op() {
+                    return value;
+                }
+            }
+            panic!("operation kept failing under chaos");
+        }
+
+        #[test]
+        fn test_atomic_writes_under_chaos() {
+            let (storage, _temp) = flaky_storage(11);
+            let key = [5u8; 32];
+            let mut last_written = None;
+
+            for round in 0..50u8 {
+                let value = format!("config-{}", round).into_bytes();
+                if storage.write("config/system.json", &value, &key).is_ok() {
+                    last_written = Some(value);
+                }
+                // A failed write never corrupts what is on disk
+                if let Some(expected) = &last_written {
+                    let data = retry(|| storage.read("config/system.json", &key));
+                    assert_eq!(&data, expected);
+                }
+            }
+            assert!(storage.stats().partial_writes > 0);
+            assert_eq!(retry(|| storage.list_files("config")).len(), 1);
+        }
+
+        #[test]
+        fn test_concurrent_cocoon_writes_under_chaos() {
+            let (storage, _temp) = flaky_storage(13);
+            let storage = Arc::new(storage);
+            let key = [6u8; 32];
+
+            let handles: Vec<_> = (0..8u8)
+                .map(|writer| {
+                    let storage = storage.clone();
+                    std::thread::spawn(move || {
+                        for round in 0..10u8 {
+                            let path = format!("keys/cocoon-{}", writer % 4);
+                            retry(|| storage.write(&path, &[writer, round], &key));
+                            let data = retry(|| storage.read(&path, &key));
+                            assert_eq!(data.len(), 2);
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            assert!(storage.stats().errors > 0);
+            assert_eq!(retry(|| storage.list_files("keys")).len(), 4);
+        }
+    }
+}
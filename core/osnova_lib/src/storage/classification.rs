@@ -0,0 +1,344 @@
+//! Data classification of stored files and blobs
+//!
+//! Every write may carry a [`DataClass`] saying what kind of data it is,
+//! and the class, not the caller, decides how the data may be handled:
+//!
+//! | Class | Diagnostics bundle | Network backup | Logs | Under disk pressure |
+//! |-------|--------------------|----------------|------|---------------------|
+//! | [`Secret`](DataClass::Secret) | refused | never | path always redacted | kept |
+//! | [`Personal`](DataClass::Personal) | refused | included | | kept |
+//! | [`Preferences`](DataClass::Preferences) | included | included | | kept |
+//! | [`CacheRegenerable`](DataClass::CacheRegenerable) | included | | | removed first |
+//! | [`Telemetry`](DataClass::Telemetry) | included | | | kept |
+//! | [`SessionState`](DataClass::SessionState) | refused | | | removed first |
+//!
+//! Session state, such as the UI state snapshots apps keep for instant
+//! resume, is handled like regenerable cache in that losing it costs only
+//! convenience: it is not backed up and goes early under disk pressure. It
+//! may hold draft text, though, so unlike cache it stays out of bundles.
+//!
+//! Data without a class is handled as strictly as possible: it is left out
+//! of bundles and backups and is reported by the security audit.
+//!
+//! [`FileStorage`] keeps the classes of its files in an index at its root
+//! ([`CLASSIFICATION_INDEX`]), holding relative paths and classes only.
+//! [`classify_known_paths`] tags files written before classes existed.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::logs::LOG_DIR;
+use crate::services::migration::ROOT_BOOKKEEPING;
+use crate::storage::FileStorage;
+
+/// Index of file classes, relative to the storage root
+pub const CLASSIFICATION_INDEX: &str = ".osnova-classes.json";
+
+/// Classes of the files the shell itself writes, by path pattern; `*`
+/// stands for one path component (a user ID)
+pub const KNOWN_PATHS: &[(&str, DataClass)] = &[
+    ("identity/root.enc", DataClass::Secret),
+    ("identity/keys.cocoon", DataClass::Secret),
+    ("identity/metadata.json", DataClass::Personal),
+    ("config/system.json", DataClass::Preferences),
+    ("navigation/*/bottom_menu.json", DataClass::Preferences),
+    ("ui/*/theme.json", DataClass::Preferences),
+    ("launcher/*/layout.json", DataClass::Preferences),
+    ("catalog/*/launcher.json", DataClass::CacheRegenerable),
+    ("secrets/*/*", DataClass::Secret),
+];
+
+/// What kind of data a file or blob holds
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum DataClass {
+    /// Key material and anything that unlocks other data
+    Secret,
+    /// The user's own content and records
+    Personal,
+    /// Settings the user chose
+    Preferences,
+    /// Data that can be fetched or computed again
+    CacheRegenerable,
+    /// Usage and health measurements
+    Telemetry,
+    /// Transient state an app can do without, such as UI state snapshots
+    SessionState,
+}
+
+/// Where data is about to go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Purpose {
+    /// A diagnostics bundle the user sends to someone else
+    DiagnosticsBundle,
+    /// A backup stored on the network
+    NetworkBackup,
+}
+
+impl DataClass {
+    /// Every class
+    pub const ALL: [DataClass; 6] = [
+        DataClass::Secret,
+        DataClass::Personal,
+        DataClass::Preferences,
+        DataClass::CacheRegenerable,
+        DataClass::Telemetry,
+        DataClass::SessionState,
+    ];
+
+    /// Name used in storage and on the wire
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DataClass::Secret => "secret",
+            DataClass::Personal => "personal",
+            DataClass::Preferences => "preferences",
+            DataClass::CacheRegenerable => "cacheRegenerable",
+            DataClass::Telemetry => "telemetry",
+            DataClass::SessionState => "sessionState",
+        }
+    }
+
+    /// Whether data of this class may be used for `purpose`
+    pub fn permits(self, purpose: Purpose) -> bool {
+        match purpose {
+            Purpose::DiagnosticsBundle => !matches!(
+                self,
+                DataClass::Secret | DataClass::Personal | DataClass::SessionState
+            ),
+            Purpose::NetworkBackup => {
+                matches!(self, DataClass::Personal | DataClass::Preferences)
+            }
+        }
+    }
+
+    /// Whether the path of data of this class is redacted from logs
+    pub fn always_redacted(self) -> bool {
+        self == DataClass::Secret
+    }
+
+    /// Whether data of this class is removed first when disk space is low
+    pub fn evictable(self) -> bool {
+        matches!(self, DataClass::CacheRegenerable | DataClass::SessionState)
+    }
+}
+
+impl fmt::Display for DataClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DataClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        DataClass::ALL
+            .into_iter()
+            .find(|class| class.as_str() == s)
+            .with_context(|| format!("Unknown data class: {}", s))
+    }
+}
+
+impl fmt::Display for Purpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Purpose::DiagnosticsBundle => "a diagnostics bundle",
+            Purpose::NetworkBackup => "a network backup",
+        })
+    }
+}
+
+/// Refuse data of `class` (`None` if unclassified) for `purpose`
+///
+/// # Errors
+///
+/// Returns an error naming `what` unless the class permits the purpose
+pub fn check(what: &str, class: Option<DataClass>, purpose: Purpose) -> Result<()> {
+    match class {
+        Some(class) if class.permits(purpose) => Ok(()),
+        Some(class) => anyhow::bail!("{} is {} data and may not go into {}", what, class, purpose),
+        None => anyhow::bail!("{} is not classified and may not go into {}", what, purpose),
+    }
+}
+
+/// Class of a file the shell writes, from [`KNOWN_PATHS`]
+pub fn known_class(relative_path: &Path) -> Option<DataClass> {
+    KNOWN_PATHS
+        .iter()
+        .find(|(pattern, _)| matches_pattern(pattern, relative_path))
+        .map(|(_, class)| *class)
+}
+
+fn matches_pattern(pattern: &str, path: &Path) -> bool {
+    let mut components = path.components();
+    pattern.split('/').all(|part| {
+        components
+            .next()
+            .is_some_and(|c| part == "*" || c.as_os_str() == part)
+    }) && components.next().is_none()
+}
+
+/// Whether a file under the storage root is written through
+/// [`FileStorage`] and so should carry a class
+///
+/// Databases classify their rows themselves, log files are covered by log
+/// redaction, and migration bookkeeping is not stored data.
+pub fn is_classifiable(relative_path: &Path) -> bool {
+    let name = relative_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let database = [".db", ".db-wal", ".db-shm", ".db-journal"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix));
+    let top = relative_path
+        .components()
+        .next()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .unwrap_or_default();
+    !database && top != LOG_DIR && !ROOT_BOOKKEEPING.contains(&top.as_ref())
+}
+
+/// Tag the files the shell wrote before classes existed
+///
+/// Untagged files matching [`KNOWN_PATHS`] are tagged; files already
+/// tagged keep their class. The paths of all secret files are then
+/// registered for log redaction, so this runs at every start. Returns the
+/// files tagged.
+///
+/// # Errors
+///
+/// Returns an error if the storage tree or the index cannot be read or the
+/// index cannot be written
+pub fn classify_known_paths(storage: &FileStorage) -> Result<BTreeMap<PathBuf, DataClass>> {
+    let existing = storage.classifications()?;
+    let tagged: BTreeMap<PathBuf, DataClass> = storage
+        .list_files("")?
+        .into_iter()
+        .filter(|path| !existing.contains_key(path))
+        .filter_map(|path| known_class(&path).map(|class| (path, class)))
+        .collect();
+    storage.set_classifications(&tagged)?;
+
+    for (path, class) in existing.iter().chain(&tagged) {
+        if class.always_redacted() {
+            crate::logs::always_redact(&path.to_string_lossy());
+        }
+    }
+    Ok(tagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handling_rules() {
+        use DataClass::*;
+        let bundle: Vec<_> = DataClass::ALL
+            .into_iter()
+            .filter(|class| class.permits(Purpose::DiagnosticsBundle))
+            .collect();
+        assert_eq!(bundle, vec![Preferences, CacheRegenerable, Telemetry]);
+        let backup: Vec<_> = DataClass::ALL
+            .into_iter()
+            .filter(|class| class.permits(Purpose::NetworkBackup))
+            .collect();
+        assert_eq!(backup, vec![Personal, Preferences]);
+
+        for class in DataClass::ALL {
+            assert_eq!(class.as_str().parse::<DataClass>().unwrap(), class);
+            assert_eq!(
+                serde_json::to_value(class).unwrap(),
+                serde_json::json!(class.as_str())
+            );
+        }
+        assert!(check("x", None, Purpose::DiagnosticsBundle).is_err());
+        assert!(check("x", Some(Secret), Purpose::NetworkBackup).is_err());
+        assert!(check("x", Some(Telemetry), Purpose::DiagnosticsBundle).is_ok());
+        assert!(check("x", Some(SessionState), Purpose::DiagnosticsBundle).is_err());
+        let evictable: Vec<_> = DataClass::ALL
+            .into_iter()
+            .filter(|class| class.evictable())
+            .collect();
+        assert_eq!(evictable, vec![CacheRegenerable, SessionState]);
+    }
+
+    #[test]
+    fn test_known_paths() {
+        for (path, class) in [
+            ("identity/root.enc", Some(DataClass::Secret)),
+            ("identity/keys.cocoon", Some(DataClass::Secret)),
+            ("identity/metadata.json", Some(DataClass::Personal)),
+            ("config/system.json", Some(DataClass::Preferences)),
+            (
+                "navigation/alice/bottom_menu.json",
+                Some(DataClass::Preferences),
+            ),
+            ("ui/alice/theme.json", Some(DataClass::Preferences)),
+            ("launcher/alice/layout.json", Some(DataClass::Preferences)),
+            (
+                "catalog/alice/launcher.json",
+                Some(DataClass::CacheRegenerable),
+            ),
+            ("launcher/alice/nested/layout.json", None),
+            ("launcher/layout.json", None),
+            ("app-data/com.example/notes.json", None),
+        ] {
+            assert_eq!(known_class(Path::new(path)), class, "{}", path);
+        }
+        assert!(is_classifiable(Path::new("app-data/a/b")));
+        assert!(!is_classifiable(Path::new("osnova.db-wal")));
+        assert!(!is_classifiable(Path::new("logs/osnova.log")));
+        assert!(!is_classifiable(Path::new("migration/ui/alice/theme.json")));
+    }
+
+    #[test]
+    fn test_migration_tags_known_paths() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let storage = FileStorage::new(temp.path())?;
+        let key = [3u8; 32];
+        for (pattern, _) in KNOWN_PATHS {
+            storage.write(pattern.replace('*', "alice"), b"data", &key)?;
+        }
+        storage.write("app-data/com.example/notes.json", b"notes", &key)?;
+        // A tag written by its owner wins over the known class
+        storage.write_classified("config/system.json", b"{}", &key, DataClass::Personal)?;
+
+        let tagged = classify_known_paths(&storage)?;
+        assert_eq!(tagged.len(), KNOWN_PATHS.len() - 1);
+        for (pattern, class) in KNOWN_PATHS {
+            let path = PathBuf::from(pattern.replace('*', "alice"));
+            let expected = match path.to_str() {
+                Some("config/system.json") => DataClass::Personal,
+                _ => *class,
+            };
+            assert_eq!(
+                storage.classification(&path)?,
+                Some(expected),
+                "{}",
+                pattern
+            );
+        }
+        assert_eq!(
+            storage.classification("app-data/com.example/notes.json")?,
+            None
+        );
+        assert_eq!(
+            crate::logs::redact("opened identity/keys.cocoon"),
+            format!("opened {}", crate::logs::REDACTED)
+        );
+
+        // Nothing is left to tag the second time
+        assert!(classify_known_paths(&storage)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,609 @@
+//! Compaction of the encrypted file storage tree
+//!
+//! Heavy use leaves files behind that nothing reads any more: temporary
+//! files from interrupted atomic writes, backup generations beyond the
+//! retention, directories of users or apps that no longer exist, and the
+//! empty directories all of these leave. [`FileStorage::compact`] finds
+//! them and reports or removes them. When disk space runs low, files
+//! classified [`CacheRegenerable`](crate::storage::DataClass::CacheRegenerable) go too.
+//!
+//! Only files matching one of the categories below are touched. Anything
+//! else (databases, logs, owner locks, symlinks, files with unknown names)
+//! is left alone.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::storage::file::{FileStorage, TEMP_SUFFIX};
+use crate::storage::ownership;
+
+/// Age after which a temporary file is taken to be orphaned
+pub const DEFAULT_TEMP_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Backup generations kept per file
+pub const DEFAULT_BACKUP_RETENTION: usize = 3;
+
+/// Marker between a file name and its generation in backup names
+/// (`config.json.bak.4`)
+const BACKUP_MARKER: &str = ".bak.";
+
+/// Why a path can be removed
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum CompactCategory {
+    /// Temporary file of an atomic write that never completed
+    OrphanedTemp,
+    /// Backup generation beyond the retention
+    ExpiredBackup,
+    /// File under the directory of a user or app that no longer exists
+    OrphanedNamespace,
+    /// Regenerable cache data, removed only under disk pressure
+    Regenerable,
+    /// Directory that is, or will be, empty
+    EmptyDirectory,
+}
+
+/// What compaction may remove
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactPolicy {
+    /// Temporary files modified longer ago than this are orphaned
+    pub temp_max_age: Duration,
+    /// Newest backup generations kept per file
+    pub backup_retention: usize,
+    /// Directories whose subdirectories each belong to one user or app,
+    /// with the names of those still live
+    pub namespaces: BTreeMap<PathBuf, BTreeSet<String>>,
+    /// Also remove files classified as regenerable cache data
+    pub evict_regenerable: bool,
+}
+
+impl Default for CompactPolicy {
+    fn default() -> Self {
+        Self {
+            temp_max_age: DEFAULT_TEMP_MAX_AGE,
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            namespaces: BTreeMap::new(),
+            evict_regenerable: false,
+        }
+    }
+}
+
+impl CompactPolicy {
+    /// Treat subdirectories of `area` not named in `live` as orphaned
+    pub fn with_namespaces<P, I, S>(mut self, area: P, live: I) -> Self
+    where
+        P: Into<PathBuf>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.namespaces
+            .insert(area.into(), live.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the age after which temporary files are orphaned
+    pub fn with_temp_max_age(mut self, age: Duration) -> Self {
+        self.temp_max_age = age;
+        self
+    }
+
+    /// Set the number of backup generations kept per file
+    pub fn with_backup_retention(mut self, generations: usize) -> Self {
+        self.backup_retention = generations;
+        self
+    }
+
+    /// Set whether regenerable cache data is removed, as it should be when
+    /// disk space is low
+    pub fn with_evict_regenerable(mut self, evict: bool) -> Self {
+        self.evict_regenerable = evict;
+        self
+    }
+
+    /// Whether `relative` lies under a namespace that is not live
+    fn is_orphaned(&self, relative: &Path) -> bool {
+        self.namespaces.iter().any(|(area, live)| {
+            let Ok(rest) = relative.strip_prefix(area) else {
+                return false;
+            };
+            let mut components = rest.components();
+            match (components.next(), components.next()) {
+                (Some(name), Some(_)) => {
+                    !live.contains(name.as_os_str().to_string_lossy().as_ref())
+                }
+                // A file directly in the area is not in a namespace
+                _ => false,
+            }
+        })
+    }
+}
+
+/// A removable path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactItem {
+    /// Path relative to the storage root
+    pub path: PathBuf,
+    /// Why it can be removed
+    pub category: CompactCategory,
+    /// Size in bytes (0 for directories)
+    pub bytes: u64,
+    /// Whether it was removed
+    pub removed: bool,
+}
+
+/// Removable paths found by [`FileStorage::compact`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReport {
+    /// Whether this was a dry run
+    pub report_only: bool,
+    /// Removable paths, files before directories, each sorted by path
+    pub items: Vec<CompactItem>,
+}
+
+impl CompactReport {
+    /// Total size of the removable files
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.items.iter().map(|item| item.bytes).sum()
+    }
+
+    /// Total size of the files actually removed
+    pub fn removed_bytes(&self) -> u64 {
+        self.items
+            .iter()
+            .filter(|item| item.removed)
+            .map(|item| item.bytes)
+            .sum()
+    }
+
+    /// Removable paths in one category
+    pub fn in_category(&self, category: CompactCategory) -> Vec<&CompactItem> {
+        self.items
+            .iter()
+            .filter(|item| item.category == category)
+            .collect()
+    }
+}
+
+/// A backup generation found during the scan
+struct Backup {
+    generation: u64,
+    path: PathBuf,
+    bytes: u64,
+}
+
+impl FileStorage {
+    /// Find files nothing reads any more and report or remove them
+    ///
+    /// Removable are: temporary files of atomic writes older than the
+    /// policy's age, backup generations (`<name>.bak.<n>`, higher is newer)
+    /// beyond its retention, everything under namespaces it lists as not
+    /// live, files classified as regenerable cache data if it evicts them,
+    /// and directories left empty. Symlinks are never followed or removed,
+    /// and the storage root itself is kept.
+    ///
+    /// With `report_only`, nothing changes. Otherwise files are removed,
+    /// then directories deepest first; a path that cannot be removed stays
+    /// in the report with `removed: false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tree cannot be read
+    pub fn compact(&self, policy: &CompactPolicy, report_only: bool) -> Result<CompactReport> {
+        let mut files = Vec::new();
+        let mut backups: BTreeMap<PathBuf, Vec<Backup>> = BTreeMap::new();
+        let regenerable: HashSet<PathBuf> = if policy.evict_regenerable {
+            self.classifications()?
+                .into_iter()
+                .filter(|(_, class)| class.evictable())
+                .map(|(path, _)| path)
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        self.scan(
+            self.base_path(),
+            policy,
+            &regenerable,
+            SystemTime::now(),
+            &mut files,
+            &mut backups,
+        )?;
+
+        for mut generations in backups.into_values() {
+            generations.sort_by_key(|entry| std::cmp::Reverse(entry.generation));
+            for backup in generations.into_iter().skip(policy.backup_retention) {
+                files.push(item(
+                    backup.path,
+                    CompactCategory::ExpiredBackup,
+                    backup.bytes,
+                ));
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let removable: HashSet<PathBuf> = files.iter().map(|item| item.path.clone()).collect();
+        let mut directories = Vec::new();
+        self.find_empty(self.base_path(), &removable, &mut directories)?;
+        directories.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if !report_only {
+            for file in &mut files {
+                file.removed = fs::remove_file(self.full_path(&file.path)).is_ok();
+            }
+            // Deepest first, so parents are empty by the time they are reached
+            for directory in directories.iter_mut().rev() {
+                directory.removed = fs::remove_dir(self.full_path(&directory.path)).is_ok();
+            }
+            let removed: HashSet<&Path> = files
+                .iter()
+                .filter(|file| file.removed)
+                .map(|file| file.path.as_path())
+                .collect();
+            self.forget_classifications(|path| removed.contains(path))?;
+        }
+
+        files.extend(directories);
+        Ok(CompactReport {
+            report_only,
+            items: files,
+        })
+    }
+
+    /// Categorize the files under `dir`
+    fn scan(
+        &self,
+        dir: &Path,
+        policy: &CompactPolicy,
+        regenerable: &HashSet<PathBuf>,
+        now: SystemTime,
+        files: &mut Vec<CompactItem>,
+        backups: &mut BTreeMap<PathBuf, Vec<Backup>>,
+    ) -> Result<()> {
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_dir() {
+                self.scan(&path, policy, regenerable, now, files, backups)?;
+                continue;
+            }
+            if !metadata.is_file() || ownership::is_owner_lock(&path) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(self.base_path()) else {
+                continue;
+            };
+            let relative = relative.to_path_buf();
+            let bytes = metadata.len();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            if policy.is_orphaned(&relative) {
+                files.push(item(relative, CompactCategory::OrphanedNamespace, bytes));
+            } else if regenerable.contains(&relative) {
+                files.push(item(relative, CompactCategory::Regenerable, bytes));
+            } else if is_atomic_write_temp(&name) {
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .unwrap_or_default();
+                if age > policy.temp_max_age {
+                    files.push(item(relative, CompactCategory::OrphanedTemp, bytes));
+                }
+            } else if let Some((base, generation)) = parse_backup_name(&name) {
+                backups
+                    .entry(relative.with_file_name(base))
+                    .or_default()
+                    .push(Backup {
+                        generation,
+                        path: relative,
+                        bytes,
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect directories under `dir` left empty once `removable` is gone
+    ///
+    /// Returns whether `dir` itself would be empty.
+    fn find_empty(
+        &self,
+        dir: &Path,
+        removable: &HashSet<PathBuf>,
+        directories: &mut Vec<CompactItem>,
+    ) -> Result<bool> {
+        let mut empty = true;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let relative = path
+                .strip_prefix(self.base_path())
+                .unwrap_or(&path)
+                .to_path_buf();
+            let metadata = fs::symlink_metadata(&path)?;
+            let goes = if metadata.is_dir() {
+                let child_empty = self.find_empty(&path, removable, directories)?;
+                if child_empty {
+                    directories.push(item(relative, CompactCategory::EmptyDirectory, 0));
+                }
+                child_empty
+            } else {
+                removable.contains(&relative)
+            };
+            empty &= goes;
+        }
+        Ok(empty)
+    }
+}
+
+fn item(path: PathBuf, category: CompactCategory, bytes: u64) -> CompactItem {
+    CompactItem {
+        path,
+        category,
+        bytes,
+        removed: false,
+    }
+}
+
+/// Whether `name` is a temporary file of [`FileStorage`]'s atomic writes
+/// (`<name>.<pid>.<counter>.osnova-tmp`)
+fn is_atomic_write_temp(name: &str) -> bool {
+    let Some(stem) = name.strip_suffix(TEMP_SUFFIX) else {
+        return false;
+    };
+    let mut parts = stem.rsplitn(3, '.');
+    let numeric = |part: Option<&str>| {
+        part.is_some_and(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+    };
+    numeric(parts.next()) && numeric(parts.next()) && parts.next().is_some_and(|p| !p.is_empty())
+}
+
+/// Split a backup name (`<name>.bak.<n>`) into the name and generation
+fn parse_backup_name(name: &str) -> Option<(&str, u64)> {
+    let index = name.rfind(BACKUP_MARKER)?;
+    let (base, generation) = (&name[..index], &name[index + BACKUP_MARKER.len()..]);
+    if base.is_empty() || !generation.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((base, generation.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DataClass;
+    use tempfile::TempDir;
+
+    const KEY: [u8; 32] = [7u8; 32];
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn age(path: &Path, by: Duration) -> Result<()> {
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(SystemTime::now() - by)?;
+        Ok(())
+    }
+
+    fn raw(storage: &FileStorage, relative: &str, data: &[u8]) -> Result<PathBuf> {
+        let path = storage.full_path(relative);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, data)?;
+        Ok(path)
+    }
+
+    /// A tree with live data and one removable file of each category
+    fn fixture(temp: &TempDir) -> Result<FileStorage> {
+        let storage = FileStorage::new(temp.path())?;
+        storage.write("config/system.json", b"live config", &KEY)?;
+        storage.write("navigation/alice/bottom_menu.json", b"alice", &KEY)?;
+        storage.write("navigation/bob/bottom_menu.json", b"bob", &KEY)?;
+
+        let stale = raw(&storage, "config/system.json.123.4.osnova-tmp", b"half")?;
+        age(&stale, 2 * DAY)?;
+        raw(&storage, "config/system.json.123.5.osnova-tmp", b"fresh")?;
+        for generation in 1..=5 {
+            raw(
+                &storage,
+                &format!("config/system.json.bak.{}", generation),
+                b"old",
+            )?;
+        }
+        fs::create_dir_all(storage.full_path("ui/alice/empty"))?;
+        Ok(storage)
+    }
+
+    fn policy() -> CompactPolicy {
+        CompactPolicy::default().with_namespaces("navigation", ["alice"])
+    }
+
+    fn paths(report: &CompactReport, category: CompactCategory) -> Vec<String> {
+        report
+            .in_category(category)
+            .iter()
+            .map(|item| item.path.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_compact_detects_each_category() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = fixture(&temp)?;
+
+        let report = storage.compact(&policy(), true)?;
+        assert!(report.report_only);
+        assert_eq!(
+            paths(&report, CompactCategory::OrphanedTemp),
+            vec!["config/system.json.123.4.osnova-tmp"]
+        );
+        assert_eq!(
+            paths(&report, CompactCategory::ExpiredBackup),
+            vec!["config/system.json.bak.1", "config/system.json.bak.2"]
+        );
+        assert_eq!(
+            paths(&report, CompactCategory::OrphanedNamespace),
+            vec!["navigation/bob/bottom_menu.json"]
+        );
+        assert_eq!(
+            paths(&report, CompactCategory::EmptyDirectory),
+            vec!["navigation/bob", "ui", "ui/alice", "ui/alice/empty"]
+        );
+        let bob = fs::metadata(storage.full_path("navigation/bob/bottom_menu.json"))?.len();
+        assert_eq!(report.reclaimable_bytes(), 4 + 3 + 3 + bob);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_report_only_changes_nothing() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = fixture(&temp)?;
+
+        let before = storage.list_files("")?;
+        let report = storage.compact(&policy(), true)?;
+        assert!(!report.items.is_empty());
+        assert!(report.items.iter().all(|item| !item.removed));
+        assert_eq!(report.removed_bytes(), 0);
+        assert_eq!(storage.list_files("")?, before);
+        for item in &report.items {
+            assert!(storage.full_path(&item.path).exists(), "{:?}", item.path);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_removal_keeps_live_data() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = fixture(&temp)?;
+
+        let report = storage.compact(&policy(), false)?;
+        assert!(report.items.iter().all(|item| item.removed));
+        for item in &report.items {
+            assert!(!storage.full_path(&item.path).exists(), "{:?}", item.path);
+        }
+
+        assert_eq!(storage.read("config/system.json", &KEY)?, b"live config");
+        assert_eq!(
+            storage.read("navigation/alice/bottom_menu.json", &KEY)?,
+            b"alice"
+        );
+        for generation in 3..=5 {
+            assert!(storage.exists(format!("config/system.json.bak.{}", generation)));
+        }
+        assert!(storage.exists("config/system.json.123.5.osnova-tmp"));
+
+        // Nothing left to do
+        assert!(storage.compact(&policy(), false)?.items.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_leaves_unknown_files_alone() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = FileStorage::new(temp.path())?;
+        let unknown = [
+            "osnova.db",
+            "logs/backends/app.stderr",
+            "config/notes.bak",
+            "config/notes.bak.x",
+            "config/.bak.3",
+            "config/not-ours.osnova-tmp",
+            "config/x.1.osnova-tmp",
+            "navigation/loose.json",
+        ];
+        for path in unknown {
+            let full = raw(&storage, path, b"keep")?;
+            age(&full, 30 * DAY)?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(temp.path().join("osnova.db"), temp.path().join("link"))?;
+
+        let report = storage.compact(&policy().with_backup_retention(0), false)?;
+        assert_eq!(report.items, Vec::new());
+        for path in unknown {
+            assert!(storage.full_path(path).exists(), "{}", path);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_prunes_empty_directories() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = FileStorage::new(temp.path())?;
+        fs::create_dir_all(storage.full_path("a/b/c"))?;
+        fs::create_dir_all(storage.full_path("keep/empty"))?;
+        storage.write("keep/data.json", b"data", &KEY)?;
+        let stale = raw(&storage, "d/file.json.1.1.osnova-tmp", b"x")?;
+        age(&stale, 2 * DAY)?;
+
+        let report = storage.compact(&CompactPolicy::default(), false)?;
+        assert_eq!(
+            paths(&report, CompactCategory::EmptyDirectory),
+            vec!["a", "a/b", "a/b/c", "d", "keep/empty"]
+        );
+        for gone in ["a", "d", "keep/empty"] {
+            assert!(!storage.full_path(gone).exists(), "{}", gone);
+        }
+        assert!(storage.exists("keep/data.json"));
+        assert!(temp.path().exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_evicts_regenerable_only_under_pressure() -> Result<()> {
+        let temp = TempDir::new()?;
+        let storage = FileStorage::new(temp.path())?;
+        storage.write_classified(
+            "catalog/alice/launcher.json",
+            b"catalog",
+            &KEY,
+            DataClass::CacheRegenerable,
+        )?;
+        storage.write_classified("ui/alice/theme.json", b"dark", &KEY, DataClass::Preferences)?;
+        storage.write("app-data/notes.json", b"notes", &KEY)?;
+
+        let report = storage.compact(&CompactPolicy::default(), false)?;
+        assert!(report.in_category(CompactCategory::Regenerable).is_empty());
+        assert!(storage.exists("catalog/alice/launcher.json"));
+
+        let pressure = CompactPolicy::default().with_evict_regenerable(true);
+        let report = storage.compact(&pressure, false)?;
+        assert_eq!(
+            paths(&report, CompactCategory::Regenerable),
+            vec!["catalog/alice/launcher.json"]
+        );
+        assert_eq!(
+            paths(&report, CompactCategory::EmptyDirectory),
+            vec!["catalog", "catalog/alice"]
+        );
+        assert!(!storage.exists("catalog"));
+        assert!(storage.exists("ui/alice/theme.json"));
+        assert!(storage.exists("app-data/notes.json"));
+        // The tag goes with the file
+        assert_eq!(storage.classification("catalog/alice/launcher.json")?, None);
+        assert_eq!(
+            storage.classification("ui/alice/theme.json")?,
+            Some(DataClass::Preferences)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_patterns() {
+        assert!(is_atomic_write_temp("system.json.123.4.osnova-tmp"));
+        assert!(!is_atomic_write_temp("system.json.osnova-tmp"));
+        assert!(!is_atomic_write_temp(".123.4.osnova-tmp"));
+        assert_eq!(parse_backup_name("a.json.bak.12"), Some(("a.json", 12)));
+        assert_eq!(parse_backup_name("a.json.bak."), None);
+        assert_eq!(parse_backup_name("a.json.bak"), None);
+    }
+}
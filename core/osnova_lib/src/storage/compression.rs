@@ -0,0 +1,364 @@
+//! Transparent payload compression
+//!
+//! Used by the component cache and encrypted blob storage. Payloads at or
+//! above a size threshold are compressed with zstd and prefixed with a
+//! header:
+//!
+//! ```text
+//! "OSNZ" | method (1 byte) | original length (u64, little endian) | body
+//! ```
+//!
+//! Payloads without the header are returned as-is, so entries written before
+//! compression existed keep working. A payload that is stored uncompressed
+//! but happens to begin with the header magic is wrapped in a `stored`
+//! header so it cannot be mistaken for a compressed one. Bodies deflated
+//! with zlib by earlier builds still decode.
+//!
+//! Formats that are already compressed (gzip, zstd, PNG, JPEG) are detected
+//! by their magic bytes and never recompressed.
+//!
+//! The header is untrusted input: decoding never inflates past the length
+//! it declares, and rejects a body that does not inflate to exactly that
+//! length.
+//!
+//! Level and threshold are part of the
+//! [`RuntimeSettings`](crate::services::RuntimeSettings); the shell applies
+//! them process-wide with [`set_shared_settings`].
+
+use std::io::{Read, Write};
+use std::sync::RwLock;
+
+use flate2::read::ZlibDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OsnovaError, Result};
+
+/// Payloads smaller than this are stored uncompressed by default
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4 * 1024;
+
+/// Default zstd compression level
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// Most bytes reserved up front when decoding, whatever the header claims
+const MAX_PREALLOCATION: usize = 1024 * 1024;
+
+/// Header magic marking an encoded payload
+const MAGIC: &[u8; 4] = b"OSNZ";
+
+/// Header length (magic, method, original length); [`logical_size`] needs
+/// only this many leading bytes
+pub const HEADER_LEN: usize = MAGIC.len() + 1 + 8;
+
+/// Body stored uncompressed
+const METHOD_STORED: u8 = 0;
+
+/// Body deflated with zlib, written by earlier builds
+const METHOD_ZLIB: u8 = 1;
+
+/// Body compressed with zstd
+const METHOD_ZSTD: u8 = 2;
+
+/// Magic bytes of formats that do not benefit from recompression
+const PRECOMPRESSED_MAGIC: &[&[u8]] = &[
+    // gzip
+    &[0x1f, 0x8b],
+    // zstd
+    &[0x28, 0xb5, 0x2f, 0xfd],
+    // PNG
+    &[0x89, b'P', b'N', b'G'],
+    // JPEG
+    &[0xff, 0xd8, 0xff],
+];
+
+/// When and how hard to compress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompressionSettings {
+    /// Whether new payloads are compressed
+    pub enabled: bool,
+    /// zstd level, up to 22 (smallest); 0 is zstd's default, and out of
+    /// range levels are clamped
+    pub level: i32,
+    /// Smallest payload, in bytes, worth compressing
+    pub threshold: usize,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            level: DEFAULT_COMPRESSION_LEVEL,
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+}
+
+impl CompressionSettings {
+    /// Settings that never compress (existing compressed payloads still decode)
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    /// Encode a payload for storage
+    ///
+    /// Compresses when enabled, the payload reaches the threshold, is not in
+    /// an already-compressed format, and actually shrinks.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        if self.enabled && data.len() >= self.threshold && !is_precompressed(data) {
+            if let Some(body) = compress(data, self.level) {
+                if HEADER_LEN + body.len() < data.len() {
+                    return with_header(METHOD_ZSTD, data.len(), &body);
+                }
+            }
+        }
+
+        if data.starts_with(MAGIC) {
+            with_header(METHOD_STORED, data.len(), data)
+        } else {
+            data.to_vec()
+        }
+    }
+}
+
+static SHARED: RwLock<Option<CompressionSettings>> = RwLock::new(None);
+
+/// Process-wide compression settings
+///
+/// Storage opened without [`with_compression`] uses these. Defaults until
+/// [`set_shared_settings`] is called.
+///
+/// [`with_compression`]: crate::storage::SqlStorage::with_compression
+pub fn shared_settings() -> CompressionSettings {
+    SHARED
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .unwrap_or_default()
+}
+
+/// Replace the process-wide compression settings
+///
+/// Storage opened from now on uses them; open storage keeps its settings.
+pub fn set_shared_settings(settings: CompressionSettings) {
+    *SHARED.write().unwrap_or_else(|e| e.into_inner()) = Some(settings);
+}
+
+/// Whether `data` starts with the magic bytes of a compressed format
+pub fn is_precompressed(data: &[u8]) -> bool {
+    PRECOMPRESSED_MAGIC
+        .iter()
+        .any(|magic| data.starts_with(magic))
+}
+
+/// Decode a stored payload, compressed or not
+///
+/// # Errors
+///
+/// Returns an error if the header is truncated, or the body is corrupt or
+/// does not decode to the length the header declares.
+pub fn decode(stored: &[u8]) -> Result<Vec<u8>> {
+    let Some((method, original_len)) = parse_header(stored)? else {
+        return Ok(stored.to_vec());
+    };
+    let body = &stored[HEADER_LEN..];
+
+    let data = match method {
+        METHOD_STORED => body.to_vec(),
+        METHOD_ZLIB => inflate(ZlibDecoder::new(body), original_len)?,
+        _ => {
+            let decoder = zstd::stream::read::Decoder::new(body)
+                .map_err(|e| corrupt(&format!("decompression failed: {}", e)))?;
+            inflate(decoder, original_len)?
+        }
+    };
+
+    if data.len() != original_len {
+        return Err(corrupt("length mismatch"));
+    }
+    Ok(data)
+}
+
+/// Decoded (logical) size of a stored payload
+///
+/// Only the header is inspected, so `stored` may be just the first
+/// [`HEADER_LEN`] bytes of the payload when `stored_len` gives the full
+/// stored length.
+pub fn logical_size(stored: &[u8], stored_len: usize) -> usize {
+    match parse_header(stored) {
+        Ok(Some((_, original_len))) => original_len,
+        _ => stored_len,
+    }
+}
+
+/// Parse the header, if the payload has one
+fn parse_header(stored: &[u8]) -> Result<Option<(u8, usize)>> {
+    if !stored.starts_with(MAGIC) {
+        return Ok(None);
+    }
+    if stored.len() < HEADER_LEN {
+        return Err(corrupt("truncated header"));
+    }
+
+    let method = stored[MAGIC.len()];
+    if ![METHOD_STORED, METHOD_ZLIB, METHOD_ZSTD].contains(&method) {
+        return Err(corrupt(&format!("unknown method {}", method)));
+    }
+
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&stored[MAGIC.len() + 1..HEADER_LEN]);
+    let original_len =
+        usize::try_from(u64::from_le_bytes(len)).map_err(|_| corrupt("length out of range"))?;
+
+    Ok(Some((method, original_len)))
+}
+
+/// Prefix `body` with a header
+fn with_header(method: u8, original_len: usize, body: &[u8]) -> Vec<u8> {
+    let mut stored = Vec::with_capacity(HEADER_LEN + body.len());
+    stored.extend_from_slice(MAGIC);
+    stored.push(method);
+    stored.extend_from_slice(&(original_len as u64).to_le_bytes());
+    stored.extend_from_slice(body);
+    stored
+}
+
+/// zstd-compress `data`
+fn compress(data: &[u8], level: i32) -> Option<Vec<u8>> {
+    let levels = zstd::compression_level_range();
+    let level = level.clamp(*levels.start(), *levels.end());
+    let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), level).ok()?;
+    encoder.write_all(data).ok()?;
+    encoder.finish().ok()
+}
+
+/// Read a decompressed body, never past one byte more than `original_len`
+///
+/// The extra byte is how a body longer than its header claims is caught.
+fn inflate(decoder: impl Read, original_len: usize) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(original_len.min(MAX_PREALLOCATION));
+    decoder
+        .take((original_len as u64).saturating_add(1))
+        .read_to_end(&mut data)
+        .map_err(|e| corrupt(&format!("decompression failed: {}", e)))?;
+    Ok(data)
+}
+
+/// Error for an undecodable payload
+fn corrupt(message: &str) -> OsnovaError {
+    OsnovaError::Storage(format!("Corrupt compressed payload: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(len: usize) -> Vec<u8> {
+        br#"{"key":"value","list":[1,2,3]},"#.iter().copied().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn test_round_trip_compresses_large_payloads() {
+        let settings = CompressionSettings::default();
+        let data = text(64 * 1024);
+
+        let stored = settings.encode(&data);
+        assert!(stored.starts_with(MAGIC));
+        assert!(stored.len() < data.len() / 4);
+        assert_eq!(
+            logical_size(&stored[..HEADER_LEN], stored.len()),
+            data.len()
+        );
+        assert_eq!(decode(&stored).unwrap(), data);
+    }
+
+    #[test]
+    fn test_threshold_and_disabled() {
+        let settings = CompressionSettings {
+            threshold: 1024,
+            ..CompressionSettings::default()
+        };
+
+        let small = text(1023);
+        assert_eq!(settings.encode(&small), small);
+        assert!(settings.encode(&text(1024)).starts_with(MAGIC));
+
+        let large = text(8 * 1024);
+        assert_eq!(CompressionSettings::disabled().encode(&large), large);
+    }
+
+    #[test]
+    fn test_precompressed_formats_are_skipped() {
+        let settings = CompressionSettings::default();
+
+        for magic in PRECOMPRESSED_MAGIC {
+            let mut data = magic.to_vec();
+            data.extend(text(16 * 1024));
+
+            assert!(is_precompressed(&data));
+            let stored = settings.encode(&data);
+            assert_eq!(stored, data);
+            assert_eq!(decode(&stored).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_raw_payloads_decode_unchanged() {
+        // Written before compression existed
+        let legacy = text(10_000);
+        assert_eq!(decode(&legacy).unwrap(), legacy);
+        assert_eq!(logical_size(&legacy, legacy.len()), legacy.len());
+
+        // Raw data that happens to start with the magic survives a round trip
+        let mut tricky = MAGIC.to_vec();
+        tricky.extend_from_slice(b"\x07not a header");
+        let stored = CompressionSettings::default().encode(&tricky);
+        assert_eq!(decode(&stored).unwrap(), tricky);
+    }
+
+    #[test]
+    fn test_zlib_payloads_of_earlier_builds_decode() {
+        use flate2::write::ZlibEncoder;
+
+        let data = text(16 * 1024);
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&data).unwrap();
+        let stored = with_header(METHOD_ZLIB, data.len(), &encoder.finish().unwrap());
+
+        assert_eq!(decode(&stored).unwrap(), data);
+    }
+
+    #[test]
+    fn test_declared_length_bounds_decoding() {
+        let data = vec![0u8; 1024 * 1024];
+        let body = compress(&data, DEFAULT_COMPRESSION_LEVEL).unwrap();
+
+        // A bomb claiming to be small is cut off, not inflated
+        assert!(decode(&with_header(METHOD_ZSTD, 1024, &body)).is_err());
+        // A huge claimed length reserves nothing up front and still fails
+        assert!(decode(&with_header(METHOD_ZSTD, usize::MAX >> 1, &body)).is_err());
+        assert!(decode(&with_header(METHOD_ZSTD, data.len(), &body)).is_ok());
+    }
+
+    #[test]
+    fn test_level_is_clamped() {
+        let data = text(64 * 1024);
+        for level in [i32::MIN, 0, 22, i32::MAX] {
+            let settings = CompressionSettings {
+                level,
+                ..CompressionSettings::default()
+            };
+            assert_eq!(decode(&settings.encode(&data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_payload_is_rejected() {
+        let mut stored = CompressionSettings::default().encode(&text(8 * 1024));
+        stored.truncate(stored.len() / 2);
+        assert!(decode(&stored).is_err());
+        assert!(decode(&MAGIC[..]).is_err());
+    }
+}
@@ -0,0 +1,581 @@
+//! Context and diagnosis of stored data that does not decrypt
+//!
+//! When a file or database row does not decrypt, [`FileStorage`] and
+//! [`SqlStorage`] return a [`DecryptError`] (through `anyhow`, so callers
+//! can downcast it) saying where the data is, its data class, where the key
+//! came from when the caller said, its size and last modification time, and
+//! why it failed as a [`DecryptFailure`].
+//!
+//! [`diagnose_decrypt_failure`] goes further: it tries every key in a
+//! [`KeyCandidates`] set on the data and reports which, if any, opens it.
+//! It only reads, so it is safe on data the user still hopes to recover.
+//! The security audit and identity recovery detection both use it.
+//!
+//! Keys are only ever described by their source and a label; no output of
+//! this module contains key material or decrypted data.
+//!
+//! [`SqlStorage`]: crate::storage::SqlStorage
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::crypto::encryption::{CocoonEncryption, DecryptFailure};
+use crate::models::identity::RootIdentity;
+use crate::platform::keystore::{DevelopmentKeystore, Keystore};
+use crate::services::config::ConfigService;
+use crate::services::features::FeatureFlagService;
+use crate::services::security::KeySource;
+use crate::services::{CatalogService, LauncherService, NavigationService, UIService};
+use crate::storage::{DataClass, FileStorage, SqlStorage};
+
+/// Database file, relative to the storage root
+const DATABASE_FILE: &str = "osnova.db";
+
+/// Where stored data lives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DecryptLocation {
+    /// A file, relative to the storage root
+    File {
+        /// Relative path
+        path: PathBuf,
+    },
+    /// A row of a database table
+    ///
+    /// Keys of rows with composite keys join the parts with `/`, such as
+    /// `<app id>/<user id>` in `app_configurations`.
+    Row {
+        /// Table name
+        table: String,
+        /// Row key
+        key: String,
+    },
+}
+
+impl DecryptLocation {
+    /// A file, relative to the storage root
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        Self::File { path: path.into() }
+    }
+
+    /// A row of a database table
+    pub fn row(table: &str, key: impl Into<String>) -> Self {
+        Self::Row {
+            table: table.to_string(),
+            key: key.into(),
+        }
+    }
+}
+
+impl fmt::Display for DecryptLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File { path } => write!(f, "{}", path.display()),
+            Self::Row { table, key } => write!(f, "{} row {}", table, key),
+        }
+    }
+}
+
+/// Stored data that did not decrypt, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecryptError {
+    /// Where the data is
+    pub location: DecryptLocation,
+    /// Data class, if the data is tagged with one
+    pub class: Option<DataClass>,
+    /// Where the key that was tried comes from, if the caller said
+    pub key_source: Option<KeySource>,
+    /// Size of the stored ciphertext in bytes
+    pub size: u64,
+    /// Unix timestamp of the last write, if known
+    pub modified: Option<u64>,
+    /// Why it did not decrypt
+    pub failure: DecryptFailure,
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to decrypt {}: {} (", self.location, self.failure)?;
+        match self.class {
+            Some(class) => write!(f, "class {}", class)?,
+            None => f.write_str("unclassified")?,
+        }
+        if let Some(source) = self.key_source {
+            write!(f, ", key from {}", source)?;
+        }
+        write!(f, ", {} bytes", self.size)?;
+        if let Some(modified) = self.modified {
+            write!(f, ", modified at {}", modified)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+/// A key to try, named by where it comes from
+struct CandidateKey {
+    source: KeySource,
+    label: String,
+    key: [u8; 32],
+}
+
+/// Keys to try on data that does not decrypt
+///
+/// Built from the keys the shell itself derives; the `Debug` output names
+/// them without their material.
+#[derive(Default)]
+pub struct KeyCandidates {
+    keys: Vec<CandidateKey>,
+}
+
+impl KeyCandidates {
+    /// An empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The keys that do not depend on a user: the platform keystore's key,
+    /// the development platform key, and the system configuration key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keystore cannot be reached. A keystore that
+    /// lost its key adds nothing.
+    pub fn platform(keystore: &dyn Keystore) -> Result<Self> {
+        let mut candidates = Self::new();
+        if let Some(key) = keystore.key()? {
+            candidates = candidates.with(KeySource::PlatformKeystore, "platform keystore key", key);
+        }
+        Ok(candidates
+            .with(
+                KeySource::DevelopmentKey,
+                "development platform key",
+                DevelopmentKeystore::development_key(),
+            )
+            .with(
+                KeySource::StaticDerived,
+                "system configuration key",
+                ConfigService::derive_system_key(),
+            ))
+    }
+
+    /// Add the keys derived from a user ID alone
+    pub fn with_user(self, user_id: &str) -> Self {
+        self.with(
+            KeySource::StaticDerived,
+            "app configuration key",
+            ConfigService::derive_user_config_key(user_id),
+        )
+        .with(
+            KeySource::StaticDerived,
+            "navigation key",
+            NavigationService::derive_nav_key(user_id),
+        )
+        .with(
+            KeySource::StaticDerived,
+            "theme key",
+            UIService::derive_theme_key(user_id),
+        )
+        .with(
+            KeySource::StaticDerived,
+            "launcher layout key",
+            LauncherService::derive_layout_key(user_id),
+        )
+    }
+
+    /// Add the keys an unlocked identity derives for a user: the key
+    /// cocoon's current key, its legacy key, the secret settings key and
+    /// the catalog and feature flag cache keys
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the current key cannot be derived
+    pub fn with_identity(self, identity: &RootIdentity, user_id: &str) -> Result<Self> {
+        Ok(self
+            .with(
+                KeySource::IdentityDerived,
+                "key cocoon key",
+                identity.derive_cocoon_key(user_id)?,
+            )
+            .with(
+                KeySource::LegacyDerived,
+                "legacy key cocoon key",
+                identity.legacy_cocoon_key(user_id),
+            )
+            .with(
+                KeySource::IdentityDerived,
+                "app secret key",
+                ConfigService::derive_app_secret_key(identity)?,
+            )
+            .with(
+                KeySource::IdentityDerived,
+                "catalog cache key",
+                CatalogService::derive_cache_key(identity, user_id)?,
+            )
+            .with(
+                KeySource::IdentityDerived,
+                "feature flags key",
+                FeatureFlagService::derive_storage_key(identity, user_id)?,
+            ))
+    }
+
+    /// Add a key
+    ///
+    /// A key already in the set is not added again, so the first label
+    /// given for it is the one reported.
+    pub fn with(mut self, source: KeySource, label: &str, key: [u8; 32]) -> Self {
+        if !self.keys.iter().any(|candidate| candidate.key == key) {
+            self.keys.push(CandidateKey {
+                source,
+                label: label.to_string(),
+                key,
+            });
+        }
+        self
+    }
+
+    /// Number of keys in the set
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl fmt::Debug for KeyCandidates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.keys
+                    .iter()
+                    .map(|candidate| format!("{} ({})", candidate.label, candidate.source)),
+            )
+            .finish()
+    }
+}
+
+/// One candidate key tried by [`diagnose_decrypt_failure`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecryptAttempt {
+    /// Where the key comes from
+    pub key_source: KeySource,
+    /// Which key it was
+    pub label: String,
+    /// Whether it opened the data
+    pub opened: bool,
+}
+
+/// Result of [`diagnose_decrypt_failure`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecryptDiagnosis {
+    /// Where the data is
+    pub location: DecryptLocation,
+    /// Data class, if the data is tagged with one
+    pub class: Option<DataClass>,
+    /// Size of the stored ciphertext in bytes
+    pub size: u64,
+    /// Unix timestamp of the last write, if known
+    pub modified: Option<u64>,
+    /// Every key tried, in order
+    pub attempts: Vec<DecryptAttempt>,
+    /// Why no key opened the data; `None` if one did
+    pub failure: Option<DecryptFailure>,
+}
+
+impl DecryptDiagnosis {
+    /// The first key that opened the data, if any
+    pub fn opened_with(&self) -> Option<&DecryptAttempt> {
+        self.attempts.iter().find(|attempt| attempt.opened)
+    }
+}
+
+impl fmt::Display for DecryptDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.opened_with(), self.failure) {
+            (Some(attempt), _) => write!(
+                f,
+                "{} opens with the {} ({})",
+                self.location, attempt.label, attempt.key_source
+            ),
+            (None, Some(failure)) => write!(
+                f,
+                "{} opens with none of {} known keys: {}",
+                self.location,
+                self.attempts.len(),
+                failure
+            ),
+            (None, None) => write!(f, "{} was not tried with any key", self.location),
+        }
+    }
+}
+
+/// Try each candidate key on stored data and report which, if any, opens it
+///
+/// Files are read from `files`; rows are read from the database next to it,
+/// opened read-only. Rows of `encrypted_blobs`, `app_configurations`,
+/// `config_snapshots` and `annotations` can be diagnosed, with the keys a
+/// [`DecryptError`] names them by. Nothing is written, and the data is
+/// never returned.
+///
+/// # Errors
+///
+/// Returns an error if the data does not exist or cannot be read, or if
+/// the location is a row of another table
+pub fn diagnose_decrypt_failure(
+    files: &FileStorage,
+    location: &DecryptLocation,
+    candidates: &KeyCandidates,
+) -> Result<DecryptDiagnosis> {
+    let (data, class, modified) = match location {
+        DecryptLocation::File { path } => {
+            let full_path = files.full_path(path);
+            let data = fs::read(&full_path)
+                .with_context(|| format!("Failed to read file: {}", full_path.display()))?;
+            let class = files.classification(path)?;
+            (data, class, modified_at(&full_path))
+        }
+        DecryptLocation::Row { table, key } => {
+            let row =
+                SqlStorage::read_encrypted_row(files.base_path().join(DATABASE_FILE), table, key)?
+                    .with_context(|| format!("No {} row {}", table, key))?;
+            (row.value, row.class, row.updated_at)
+        }
+    };
+
+    let attempts: Vec<DecryptAttempt> = candidates
+        .keys
+        .iter()
+        .map(|candidate| DecryptAttempt {
+            key_source: candidate.source,
+            label: candidate.label.clone(),
+            opened: CocoonEncryption::new(&candidate.key)
+                .try_decrypt(&data)
+                .is_ok(),
+        })
+        .collect();
+    let failure = if attempts.iter().any(|attempt| attempt.opened) {
+        None
+    } else {
+        Some(DecryptFailure::classify(&data))
+    };
+
+    Ok(DecryptDiagnosis {
+        location: location.clone(),
+        class,
+        size: data.len() as u64,
+        modified,
+        attempts,
+        failure,
+    })
+}
+
+/// Unix timestamp of a file's last modification, if the platform reports it
+pub(crate) fn modified_at(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::keystore::MemoryKeystore;
+    use crate::services::KeyService;
+    use base64::Engine;
+    use tempfile::TempDir;
+
+    const USER: &str = "user-123";
+
+    const COCOON: &str = "identity/keys.cocoon";
+
+    fn identity() -> RootIdentity {
+        RootIdentity::from_seed(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon about",
+        )
+        .unwrap()
+    }
+
+    fn candidates(identity: &RootIdentity) -> KeyCandidates {
+        KeyCandidates::platform(&MemoryKeystore::new([3u8; 32]))
+            .unwrap()
+            .with_user(USER)
+            .with_identity(identity, USER)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_identifies_file_under_legacy_key() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = identity();
+        KeyService::new(temp.path(), &identity.legacy_cocoon_key(USER))?
+            .initialize(identity.master_key())?;
+        let files = FileStorage::new(temp.path())?;
+
+        // The current key fails with the full context
+        let current = identity.derive_cocoon_key(USER)?;
+        let err = files
+            .read_keyed(COCOON, &current, KeySource::IdentityDerived)
+            .unwrap_err();
+        let context = err.downcast_ref::<DecryptError>().unwrap();
+        assert_eq!(context.location, DecryptLocation::file(COCOON));
+        assert_eq!(context.class, Some(DataClass::Secret));
+        assert_eq!(context.key_source, Some(KeySource::IdentityDerived));
+        assert_eq!(context.failure, DecryptFailure::AuthenticationFailed);
+        assert_eq!(context.size, fs::metadata(temp.path().join(COCOON))?.len());
+        assert_eq!(context.modified, modified_at(&temp.path().join(COCOON)));
+        assert!(context.modified.is_some());
+
+        let diagnosis =
+            diagnose_decrypt_failure(&files, &context.location, &candidates(&identity))?;
+        let opened = diagnosis.opened_with().unwrap();
+        assert_eq!(opened.key_source, KeySource::LegacyDerived);
+        assert_eq!(opened.label, "legacy key cocoon key");
+        assert_eq!(diagnosis.failure, None);
+        assert_eq!(
+            diagnosis
+                .attempts
+                .iter()
+                .filter(|attempt| attempt.opened)
+                .count(),
+            1
+        );
+        assert_eq!(
+            diagnosis.to_string(),
+            "identity/keys.cocoon opens with the legacy key cocoon key (legacy identity derivation)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnoses_rows_read_only() -> Result<()> {
+        let temp = TempDir::new()?;
+        let db = temp.path().join(DATABASE_FILE);
+        let sql = SqlStorage::new(&db)?;
+        sql.set_encrypted_blob_classified(
+            "wallet/state",
+            b"balance",
+            &[5u8; 32],
+            DataClass::Personal,
+        )?;
+
+        let err = sql
+            .get_encrypted_blob("wallet/state", &[6u8; 32])
+            .unwrap_err();
+        let context = err.downcast_ref::<DecryptError>().unwrap();
+        assert_eq!(
+            context.location,
+            DecryptLocation::row("encrypted_blobs", "wallet/state")
+        );
+        assert_eq!(context.class, Some(DataClass::Personal));
+        assert_eq!(context.key_source, Some(KeySource::CallerSupplied));
+        assert!(context.modified.is_some());
+
+        let files = FileStorage::new(temp.path())?;
+        let before = fs::read(&db)?;
+        let diagnosis = diagnose_decrypt_failure(
+            &files,
+            &context.location,
+            &KeyCandidates::new().with(KeySource::CallerSupplied, "wallet key", [5u8; 32]),
+        )?;
+        assert!(diagnosis.opened_with().is_some());
+        assert_eq!(diagnosis.size, context.size);
+        assert_eq!(fs::read(&db)?, before);
+
+        let missing = DecryptLocation::row("encrypted_blobs", "wallet/missing");
+        assert!(diagnose_decrypt_failure(&files, &missing, &KeyCandidates::new()).is_err());
+        let unsupported = DecryptLocation::row("applications", "com.example.notes");
+        assert!(diagnose_decrypt_failure(&files, &unsupported, &KeyCandidates::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports_failure_when_no_key_opens() -> Result<()> {
+        let temp = TempDir::new()?;
+        let files = FileStorage::new(temp.path())?;
+        fs::create_dir_all(temp.path().join("identity"))?;
+        fs::write(temp.path().join("identity/root.enc"), b"short")?;
+
+        let diagnosis = diagnose_decrypt_failure(
+            &files,
+            &DecryptLocation::file("identity/root.enc"),
+            &candidates(&identity()),
+        )?;
+        assert!(diagnosis.opened_with().is_none());
+        assert_eq!(diagnosis.attempts.len(), candidates(&identity()).len());
+        assert_eq!(
+            diagnosis.failure,
+            Some(DecryptFailure::TruncatedCiphertext {
+                expected_min: 36,
+                got: 5
+            })
+        );
+        assert_eq!(diagnosis.class, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostic_output_contains_no_key_material() -> Result<()> {
+        let temp = TempDir::new()?;
+        let identity = identity();
+        KeyService::new(temp.path(), &identity.legacy_cocoon_key(USER))?
+            .initialize(identity.master_key())?;
+        let files = FileStorage::new(temp.path())?;
+        let candidates = candidates(&identity);
+
+        let err = files
+            .read_keyed(COCOON, &[3u8; 32], KeySource::PlatformKeystore)
+            .unwrap_err();
+        let context = err.downcast_ref::<DecryptError>().unwrap();
+        let diagnosis =
+            diagnose_decrypt_failure(&files, &DecryptLocation::file(COCOON), &candidates)?;
+        let outputs = [
+            format!("{:#}", err),
+            format!("{:?}", err),
+            serde_json::to_string(context)?,
+            diagnosis.to_string(),
+            format!("{:?}", diagnosis),
+            serde_json::to_string(&diagnosis)?,
+            format!("{:?}", candidates),
+        ];
+
+        let secrets = [
+            *identity.master_key(),
+            identity.derive_cocoon_key(USER)?,
+            identity.legacy_cocoon_key(USER),
+            [3u8; 32],
+            DevelopmentKeystore::development_key(),
+            ConfigService::derive_system_key(),
+            ConfigService::derive_user_config_key(USER),
+            ConfigService::derive_app_secret_key(&identity)?,
+        ];
+        let engine = base64::engine::general_purpose::STANDARD;
+        for output in &outputs {
+            for secret in &secrets {
+                assert!(!output.contains(&hex::encode(secret)));
+                assert!(!output.contains(&engine.encode(secret)));
+                assert!(!output.contains(&format!("{:?}", secret)));
+                assert!(!output.contains(&serde_json::to_string(secret.as_slice())?));
+            }
+        }
+
+        Ok(())
+    }
+}
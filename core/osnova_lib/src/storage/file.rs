@@ -1,8 +1,77 @@
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
 
-use crate::crypto::encryption::CocoonEncryption;
+use crate::crypto::encryption::{CocoonEncryption, DecryptFailure};
+use crate::services::security::KeySource;
+use crate::storage::classification::{self, DataClass, Purpose, CLASSIFICATION_INDEX};
+use crate::storage::decrypt::{self, DecryptError, DecryptLocation};
+use crate::storage::ownership;
+
+/// Suffix of the temporary file a write goes through before the rename
+pub(crate) const TEMP_SUFFIX: &str = ".osnova-tmp";
+
+/// Serializes updates of classification indexes, which several instances
+/// over the same root may make
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Encrypted file storage, on disk or in memory
+///
+/// Implemented by [`FileStorage`] and [`MemoryFileStorage`], so a service
+/// holding an `Arc<dyn FileStore>` runs against either. Paths are relative
+/// to the storage root.
+///
+/// [`MemoryFileStorage`]: crate::storage::MemoryFileStorage
+pub trait FileStore: Send + Sync {
+    /// Encrypt `data` and write it, replacing any previous contents
+    fn write(&self, relative_path: &Path, data: &[u8], encryption_key: &[u8; 32]) -> Result<()>;
+
+    /// Encrypt `data` and write it, tagging the file with its data class
+    fn write_classified(
+        &self,
+        relative_path: &Path,
+        data: &[u8],
+        encryption_key: &[u8; 32],
+        class: DataClass,
+    ) -> Result<()>;
+
+    /// Data class a file is tagged with, if any
+    fn classification(&self, relative_path: &Path) -> Result<Option<DataClass>>;
+
+    /// Read and decrypt a file
+    ///
+    /// Data that does not decrypt fails with a [`DecryptError`].
+    fn read(&self, relative_path: &Path, encryption_key: &[u8; 32]) -> Result<Vec<u8>>;
+
+    /// Read and decrypt a file, naming where its key comes from in the
+    /// [`DecryptError`] if it does not decrypt
+    fn read_keyed(
+        &self,
+        relative_path: &Path,
+        encryption_key: &[u8; 32],
+        key_source: KeySource,
+    ) -> Result<Vec<u8>>;
+
+    /// Whether a file or directory exists
+    fn exists(&self, relative_path: &Path) -> bool;
+
+    /// Delete a file, returning whether it existed
+    fn delete(&self, relative_path: &Path) -> Result<bool>;
+
+    /// Files under a directory, recursively, relative to the storage root
+    fn list_files(&self, relative_path: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Remove a directory and everything under it
+    fn clear_directory(&self, relative_path: &Path) -> Result<()>;
+
+    /// Number of files written through this store
+    #[cfg(test)]
+    fn write_count(&self) -> usize;
+}
 
 /// File-based encrypted storage for Osnova
 ///
@@ -12,7 +81,9 @@ use crate::crypto::encryption::CocoonEncryption;
 /// - Configuration files
 /// - Other sensitive data that needs to be persisted to disk
 ///
-/// All data is encrypted at rest using cocoon encryption.
+/// All data is encrypted at rest using cocoon encryption. Writes go to a
+/// temporary file that is renamed over the target, so a failed or
+/// interrupted write leaves the previous contents intact.
 ///
 /// # Example
 ///
@@ -33,6 +104,12 @@ use crate::crypto::encryption::CocoonEncryption;
 /// ```
 pub struct FileStorage {
     base_path: PathBuf,
+    install_id: String,
+    /// Whether this install's owner lock is known to be in place
+    claimed: AtomicBool,
+    /// Number of successful writes, so tests can assert on persistence cost
+    #[cfg(test)]
+    writes: std::sync::atomic::AtomicUsize,
 }
 
 impl FileStorage {
@@ -44,12 +121,34 @@ impl FileStorage {
     ///
     /// # Errors
     ///
-    /// Returns an error if the base directory cannot be created
+    /// Returns an error if the base directory cannot be created, or
+    /// [`OsnovaError::StorageOwnedElsewhere`](crate::error::OsnovaError::StorageOwnedElsewhere)
+    /// if it belongs to another install
     pub fn new<P: AsRef<Path>>(base_path: P) -> Result<Self> {
+        Self::new_for_install(base_path, ownership::local_install_id())
+    }
+
+    /// Create a file storage instance owned by a specific install
+    ///
+    /// A root owned by another install is refused here; an unclaimed root
+    /// is claimed on the first write, so read-only use leaves no trace.
+    /// See [`crate::storage::ownership`].
+    pub fn new_for_install<P: AsRef<Path>>(base_path: P, install_id: &str) -> Result<Self> {
         let base_path = base_path.as_ref().to_path_buf();
         fs::create_dir_all(&base_path).context("Failed to create storage directory")?;
-
-        Ok(Self { base_path })
+        let claimed = ownership::verify(
+            &ownership::root_lock_path(&base_path),
+            &base_path,
+            install_id,
+        )?;
+
+        Ok(Self {
+            base_path,
+            install_id: install_id.to_string(),
+            claimed: AtomicBool::new(claimed),
+            #[cfg(test)]
+            writes: std::sync::atomic::AtomicUsize::new(0),
+        })
     }
 
     /// Write encrypted data to a file
@@ -85,6 +184,7 @@ impl FileStorage {
         encryption_key: &[u8; 32],
     ) -> Result<()> {
         let full_path = self.base_path.join(relative_path.as_ref());
+        self.claim()?;
 
         // Create parent directories
         if let Some(parent) = full_path.parent() {
@@ -95,10 +195,185 @@ impl FileStorage {
         let encryption = CocoonEncryption::new(encryption_key);
         let encrypted = encryption.encrypt(data).context("Failed to encrypt data")?;
 
-        // Write to file
-        fs::write(&full_path, encrypted)
-            .with_context(|| format!("Failed to write file: {}", full_path.display()))?;
+        // Write to a temporary file and rename it over the target
+        let temp_path = Self::temp_path(&full_path);
+        let written = fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&encrypted)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp_path, &full_path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e)
+                .with_context(|| format!("Failed to write file: {}", full_path.display()));
+        }
+
+        #[cfg(test)]
+        self.writes
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        Ok(())
+    }
 
+    /// Write encrypted data to a file tagged with its data class
+    ///
+    /// The class is recorded in the storage's classification index and
+    /// decides how the file may be handled (see
+    /// [`crate::storage::classification`]). The path of a secret file is
+    /// redacted from log messages from then on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file or the index cannot be written
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use osnova_lib::storage::{DataClass, FileStorage};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let storage = FileStorage::new("/tmp/storage")?;
+    /// let key = [42u8; 32];
+    /// storage.write_classified("config/app.json", b"{}", &key, DataClass::Preferences)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_classified<P: AsRef<Path>>(
+        &self,
+        relative_path: P,
+        data: &[u8],
+        encryption_key: &[u8; 32],
+        class: DataClass,
+    ) -> Result<()> {
+        let relative_path = relative_path.as_ref();
+        self.write(relative_path, data, encryption_key)?;
+        self.classify(relative_path, class)?;
+        if class.always_redacted() {
+            crate::logs::always_redact(&relative_path.to_string_lossy());
+        }
+        Ok(())
+    }
+
+    /// Tag a file under the root written by other means, such as a
+    /// plaintext file, with its data class
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the classification index cannot be updated
+    pub fn classify<P: AsRef<Path>>(&self, relative_path: P, class: DataClass) -> Result<()> {
+        self.set_classifications(&BTreeMap::from([(
+            relative_path.as_ref().to_path_buf(),
+            class,
+        )]))
+    }
+
+    /// Data class a file is tagged with, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the classification index cannot be read
+    pub fn classification<P: AsRef<Path>>(&self, relative_path: P) -> Result<Option<DataClass>> {
+        Ok(self.classifications()?.get(relative_path.as_ref()).copied())
+    }
+
+    /// Classes of all tagged files
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the classification index cannot be read
+    pub fn classifications(&self) -> Result<BTreeMap<PathBuf, DataClass>> {
+        let path = self.base_path.join(CLASSIFICATION_INDEX);
+        match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Files whose class permits `purpose`, e.g. those a diagnostics
+    /// bundle may contain
+    ///
+    /// Unclassified files are never included.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the classification index cannot be read
+    pub fn files_for(&self, purpose: Purpose) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .classifications()?
+            .into_iter()
+            .filter(|(path, class)| class.permits(purpose) && self.exists(path))
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// Read and decrypt a file for `purpose`
+    ///
+    /// The read for a diagnostics bundle or a backup goes through here, so
+    /// that data the file's class keeps out of it never gets in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is unclassified or its class does not
+    /// permit `purpose`, or if [`read`](Self::read) fails
+    pub fn read_for<P: AsRef<Path>>(
+        &self,
+        relative_path: P,
+        encryption_key: &[u8; 32],
+        purpose: Purpose,
+    ) -> Result<Vec<u8>> {
+        let relative_path = relative_path.as_ref();
+        classification::check(
+            &relative_path.display().to_string(),
+            self.classification(relative_path)?,
+            purpose,
+        )?;
+        self.read(relative_path, encryption_key)
+    }
+
+    /// Tag files with classes, replacing their previous tags
+    pub(crate) fn set_classifications(&self, classes: &BTreeMap<PathBuf, DataClass>) -> Result<()> {
+        self.update_classifications(|index| {
+            let mut changed = false;
+            for (path, class) in classes {
+                changed |= index.insert(path.clone(), *class) != Some(*class);
+            }
+            changed
+        })
+    }
+
+    /// Drop the tags of files for which `gone` holds
+    pub(crate) fn forget_classifications(&self, gone: impl Fn(&Path) -> bool) -> Result<()> {
+        self.update_classifications(|index| {
+            let before = index.len();
+            index.retain(|path, _| !gone(path));
+            index.len() != before
+        })
+    }
+
+    /// Change the classification index, writing it back if `update` says
+    /// it changed
+    fn update_classifications(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<PathBuf, DataClass>) -> bool,
+    ) -> Result<()> {
+        let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.classifications()?;
+        if !update(&mut index) {
+            return Ok(());
+        }
+        self.claim()?;
+        let path = self.base_path.join(CLASSIFICATION_INDEX);
+        let temp_path = Self::temp_path(&path);
+        let written = serde_json::to_vec_pretty(&index)
+            .map_err(std::io::Error::other)
+            .and_then(|data| fs::write(&temp_path, data))
+            .and_then(|()| fs::rename(&temp_path, &path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+        }
         Ok(())
     }
 
@@ -114,7 +389,8 @@ impl FileStorage {
     /// Returns an error if:
     /// - File does not exist
     /// - File cannot be read
-    /// - Decryption fails (wrong key or corrupted data)
+    /// - Decryption fails (wrong key or corrupted data), as a
+    ///   [`DecryptError`] saying why
     ///
     /// # Example
     ///
@@ -132,7 +408,29 @@ impl FileStorage {
         relative_path: P,
         encryption_key: &[u8; 32],
     ) -> Result<Vec<u8>> {
-        let full_path = self.base_path.join(relative_path.as_ref());
+        self.read_as(relative_path.as_ref(), encryption_key, None)
+    }
+
+    /// Read and decrypt a file whose key comes from `key_source`
+    ///
+    /// Same as [`read`](Self::read), with the key's source named in the
+    /// [`DecryptError`] if the file does not decrypt.
+    pub fn read_keyed<P: AsRef<Path>>(
+        &self,
+        relative_path: P,
+        encryption_key: &[u8; 32],
+        key_source: KeySource,
+    ) -> Result<Vec<u8>> {
+        self.read_as(relative_path.as_ref(), encryption_key, Some(key_source))
+    }
+
+    fn read_as(
+        &self,
+        relative_path: &Path,
+        encryption_key: &[u8; 32],
+        key_source: Option<KeySource>,
+    ) -> Result<Vec<u8>> {
+        let full_path = self.base_path.join(relative_path);
 
         // Read encrypted file
         let encrypted = fs::read(&full_path)
@@ -140,13 +438,31 @@ impl FileStorage {
 
         // Decrypt data
         let encryption = CocoonEncryption::new(encryption_key);
-        let decrypted = encryption
-            .decrypt(&encrypted)
-            .context("Failed to decrypt data")?;
+        let decrypted = encryption.try_decrypt(&encrypted).map_err(|failure| {
+            self.decrypt_error(relative_path, encrypted.len(), key_source, failure)
+        })?;
 
         Ok(decrypted)
     }
 
+    /// Context of a file that did not decrypt
+    fn decrypt_error(
+        &self,
+        relative_path: &Path,
+        size: usize,
+        key_source: Option<KeySource>,
+        failure: DecryptFailure,
+    ) -> DecryptError {
+        DecryptError {
+            location: DecryptLocation::file(relative_path),
+            class: self.classification(relative_path).ok().flatten(),
+            key_source,
+            size: size as u64,
+            modified: decrypt::modified_at(&self.base_path.join(relative_path)),
+            failure,
+        }
+    }
+
     /// Check if a file exists
     ///
     /// # Arguments
@@ -175,6 +491,7 @@ impl FileStorage {
 
         fs::remove_file(&full_path)
             .with_context(|| format!("Failed to delete file: {}", full_path.display()))?;
+        self.forget_classifications(|path| path == relative_path.as_ref())?;
 
         Ok(true)
     }
@@ -211,6 +528,12 @@ impl FileStorage {
 
             if path.is_dir() {
                 self.collect_files(&path, base, files)?;
+            } else if Self::is_temp_path(&path)
+                || ownership::is_owner_lock(&path)
+                || path == base.join(CLASSIFICATION_INDEX)
+            {
+                // Leftover from an interrupted write, or not stored data
+                continue;
             } else {
                 // Store relative path
                 if let Ok(relative) = path.strip_prefix(base) {
@@ -238,6 +561,7 @@ impl FileStorage {
         if full_path.exists() {
             fs::remove_dir_all(&full_path)
                 .with_context(|| format!("Failed to clear directory: {}", full_path.display()))?;
+            self.forget_classifications(|path| path.starts_with(relative_path.as_ref()))?;
         }
 
         Ok(())
@@ -254,6 +578,100 @@ impl FileStorage {
     pub fn base_path(&self) -> &Path {
         &self.base_path
     }
+
+    /// Claim the root for this install before its first write
+    fn claim(&self) -> Result<()> {
+        if !self.claimed.load(Ordering::Acquire) {
+            ownership::claim(
+                &ownership::root_lock_path(&self.base_path),
+                &self.base_path,
+                &self.install_id,
+            )?;
+            self.claimed.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// A fresh temporary file for one write to `full_path`
+    ///
+    /// Each write gets its own name, so concurrent writers to the same
+    /// path never share a temporary file.
+    pub(crate) fn temp_path(full_path: &Path) -> PathBuf {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let mut name = full_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(
+            ".{}.{}{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+            TEMP_SUFFIX
+        ));
+        full_path.with_file_name(name)
+    }
+
+    fn is_temp_path(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(TEMP_SUFFIX))
+    }
+
+    /// Number of files written through this instance
+    #[cfg(test)]
+    pub(crate) fn write_count(&self) -> usize {
+        self.writes.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+impl FileStore for FileStorage {
+    fn write(&self, relative_path: &Path, data: &[u8], encryption_key: &[u8; 32]) -> Result<()> {
+        FileStorage::write(self, relative_path, data, encryption_key)
+    }
+
+    fn write_classified(
+        &self,
+        relative_path: &Path,
+        data: &[u8],
+        encryption_key: &[u8; 32],
+        class: DataClass,
+    ) -> Result<()> {
+        FileStorage::write_classified(self, relative_path, data, encryption_key, class)
+    }
+
+    fn classification(&self, relative_path: &Path) -> Result<Option<DataClass>> {
+        FileStorage::classification(self, relative_path)
+    }
+
+    fn read(&self, relative_path: &Path, encryption_key: &[u8; 32]) -> Result<Vec<u8>> {
+        FileStorage::read(self, relative_path, encryption_key)
+    }
+
+    fn read_keyed(
+        &self,
+        relative_path: &Path,
+        encryption_key: &[u8; 32],
+        key_source: KeySource,
+    ) -> Result<Vec<u8>> {
+        FileStorage::read_keyed(self, relative_path, encryption_key, key_source)
+    }
+
+    fn exists(&self, relative_path: &Path) -> bool {
+        FileStorage::exists(self, relative_path)
+    }
+
+    fn delete(&self, relative_path: &Path) -> Result<bool> {
+        FileStorage::delete(self, relative_path)
+    }
+
+    fn list_files(&self, relative_path: &Path) -> Result<Vec<PathBuf>> {
+        FileStorage::list_files(self, relative_path)
+    }
+
+    fn clear_directory(&self, relative_path: &Path) -> Result<()> {
+        FileStorage::clear_directory(self, relative_path)
+    }
+
+    #[cfg(test)]
+    fn write_count(&self) -> usize {
+        FileStorage::write_count(self)
+    }
 }
 
 #[cfg(test)]
@@ -383,6 +801,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_decrypt_failure_carries_context() -> Result<()> {
+        let (storage, temp) = create_temp_storage()?;
+        let key = [5u8; 32];
+        storage.write_classified("prefs.json", b"{}", &key, DataClass::Preferences)?;
+
+        let err = storage.read("prefs.json", &[6u8; 32]).unwrap_err();
+        let context = err.downcast_ref::<DecryptError>().unwrap();
+        assert_eq!(context.location, DecryptLocation::file("prefs.json"));
+        assert_eq!(context.class, Some(DataClass::Preferences));
+        assert_eq!(context.key_source, None);
+        assert_eq!(context.failure, DecryptFailure::AuthenticationFailed);
+
+        // A cut-off file, read with the right key
+        let full_path = temp.path().join("prefs.json");
+        let mut data = fs::read(&full_path)?;
+        data.truncate(20);
+        fs::write(&full_path, &data)?;
+        let err = storage
+            .read_keyed("prefs.json", &key, KeySource::StaticDerived)
+            .unwrap_err();
+        let context = err.downcast_ref::<DecryptError>().unwrap();
+        assert_eq!(
+            context.failure,
+            DecryptFailure::TruncatedCiphertext {
+                expected_min: 36,
+                got: 20
+            }
+        );
+        assert_eq!(context.size, 20);
+        assert_eq!(context.key_source, Some(KeySource::StaticDerived));
+        assert_eq!(context.modified, decrypt::modified_at(&full_path));
+        assert!(err.to_string().starts_with(
+            "Failed to decrypt prefs.json: truncated ciphertext: 20 bytes, expected at least 36 \
+             (class preferences, key from static derivation, 20 bytes, modified at "
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_overwrite_file() -> Result<()> {
         let (storage, _temp) = create_temp_storage()?;
@@ -399,6 +857,66 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_atomic_write_leaves_no_temp_file() -> Result<()> {
+        let (storage, _temp) = create_temp_storage()?;
+        let key = [11u8; 32];
+
+        storage.write("config/system.json", b"original", &key)?;
+        storage.write("config/system.json", b"updated", &key)?;
+
+        let entries = fs::read_dir(storage.full_path("config"))?.count();
+        assert_eq!(entries, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_write_keeps_previous_contents() -> Result<()> {
+        let (storage, _temp) = create_temp_storage()?;
+        let key = [12u8; 32];
+        storage.write("config/system.json", b"original", &key)?;
+
+        // A crash mid-write leaves a truncated temporary file behind
+        let temp_path = FileStorage::temp_path(&storage.full_path("config/system.json"));
+        fs::write(&temp_path, b"trunc")?;
+
+        assert_eq!(storage.read("config/system.json", &key)?, b"original");
+        assert_eq!(storage.list_files("config")?.len(), 1);
+
+        storage.write("config/system.json", b"updated", &key)?;
+        assert_eq!(storage.read("config/system.json", &key)?, b"updated");
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_cocoon_writes() -> Result<()> {
+        let (storage, _temp) = create_temp_storage()?;
+        let storage = std::sync::Arc::new(storage);
+        let key = [13u8; 32];
+
+        let handles: Vec<_> = (0..8u8)
+            .map(|writer| {
+                let storage = storage.clone();
+                std::thread::spawn(move || -> Result<()> {
+                    for round in 0..10u8 {
+                        let path = format!("keys/cocoon-{}", writer % 4);
+                        storage.write(&path, &[writer, round], &key)?;
+                        // Every read sees one whole write, never a torn one
+                        let data = storage.read(&path, &key)?;
+                        assert_eq!(data.len(), 2);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        assert_eq!(storage.list_files("keys")?.len(), 4);
+        Ok(())
+    }
+
     #[test]
     fn test_binary_data() -> Result<()> {
         let (storage, _temp) = create_temp_storage()?;
@@ -436,4 +954,64 @@ mod tests {
         assert_eq!(retrieved, large_data);
         Ok(())
     }
+
+    #[test]
+    fn test_classification_persists() -> Result<()> {
+        let (storage, temp) = create_temp_storage()?;
+        let key = [11u8; 32];
+        storage.write_classified("identity/root.enc", b"seed", &key, DataClass::Secret)?;
+        storage.write_classified("notes/a.json", b"note", &key, DataClass::Personal)?;
+        storage.write_classified("notes/b.json", b"note", &key, DataClass::Personal)?;
+        storage.write_classified("ui/theme.json", b"dark", &key, DataClass::Preferences)?;
+        storage.write("app-data/unknown", b"?", &key)?;
+
+        // A plain write keeps the tag; another instance sees it
+        storage.write("ui/theme.json", b"light", &key)?;
+        let reopened = FileStorage::new(temp.path())?;
+        assert_eq!(
+            reopened.classification("ui/theme.json")?,
+            Some(DataClass::Preferences)
+        );
+        assert_eq!(reopened.classification("app-data/unknown")?, None);
+        assert!(!reopened
+            .list_files("")?
+            .contains(&PathBuf::from(CLASSIFICATION_INDEX)));
+
+        assert_eq!(
+            reopened.files_for(Purpose::DiagnosticsBundle)?,
+            vec![PathBuf::from("ui/theme.json")]
+        );
+        assert_eq!(
+            reopened.files_for(Purpose::NetworkBackup)?,
+            vec![
+                PathBuf::from("notes/a.json"),
+                PathBuf::from("notes/b.json"),
+                PathBuf::from("ui/theme.json"),
+            ]
+        );
+        for refused in ["identity/root.enc", "notes/a.json", "app-data/unknown"] {
+            assert!(reopened
+                .read_for(refused, &key, Purpose::DiagnosticsBundle)
+                .is_err());
+        }
+        assert!(reopened
+            .read_for("identity/root.enc", &key, Purpose::NetworkBackup)
+            .is_err());
+        assert_eq!(
+            reopened.read_for("ui/theme.json", &key, Purpose::DiagnosticsBundle)?,
+            b"light"
+        );
+
+        // Tags go with the files
+        reopened.delete("notes/a.json")?;
+        reopened.clear_directory("identity")?;
+        assert_eq!(
+            storage.classifications()?,
+            BTreeMap::from([
+                (PathBuf::from("notes/b.json"), DataClass::Personal),
+                (PathBuf::from("ui/theme.json"), DataClass::Preferences),
+            ])
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,361 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::crypto::encryption::CocoonEncryption;
+use crate::services::security::KeySource;
+use crate::storage::{DataClass, DecryptError, DecryptLocation, FileStore};
+
+/// In-memory encrypted file storage
+///
+/// Mirrors [`FileStorage`](crate::storage::FileStorage) without touching
+/// the disk: files are held encrypted in a map keyed by relative path, so
+/// reads with the wrong key fail just as they do on disk. Directories exist
+/// while they hold a file. Used by ephemeral contexts and tests.
+///
+/// # Example
+///
+/// ```
+/// use osnova_lib::storage::{FileStore, MemoryFileStorage};
+/// use std::path::Path;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let storage = MemoryFileStorage::new();
+/// let key = [42u8; 32];
+///
+/// storage.write(Path::new("cache/app-001"), b"cached data", &key)?;
+/// assert_eq!(storage.read(Path::new("cache/app-001"), &key)?, b"cached data");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MemoryFileStorage {
+    files: RwLock<BTreeMap<PathBuf, Vec<u8>>>,
+    classes: RwLock<BTreeMap<PathBuf, DataClass>>,
+    /// Number of successful writes, so tests can assert on persistence cost
+    #[cfg(test)]
+    writes: std::sync::atomic::AtomicUsize,
+}
+
+impl MemoryFileStorage {
+    /// Create an empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_as(
+        &self,
+        relative_path: &Path,
+        encryption_key: &[u8; 32],
+        key_source: Option<KeySource>,
+    ) -> Result<Vec<u8>> {
+        let encrypted = self
+            .files
+            .read()
+            .unwrap()
+            .get(&Self::normalize(relative_path))
+            .cloned()
+            .with_context(|| format!("Failed to read file: {}", relative_path.display()))?;
+
+        let encryption = CocoonEncryption::new(encryption_key);
+        encryption.try_decrypt(&encrypted).map_err(|failure| {
+            DecryptError {
+                location: DecryptLocation::file(Self::normalize(relative_path)),
+                class: self.classification(relative_path).ok().flatten(),
+                key_source,
+                size: encrypted.len() as u64,
+                modified: None,
+                failure,
+            }
+            .into()
+        })
+    }
+
+    /// Key a relative path the same way however it is spelled
+    fn normalize(relative_path: &Path) -> PathBuf {
+        relative_path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect()
+    }
+}
+
+impl FileStore for MemoryFileStorage {
+    fn write(&self, relative_path: &Path, data: &[u8], encryption_key: &[u8; 32]) -> Result<()> {
+        let encryption = CocoonEncryption::new(encryption_key);
+        let encrypted = encryption.encrypt(data).context("Failed to encrypt data")?;
+
+        self.files
+            .write()
+            .unwrap()
+            .insert(Self::normalize(relative_path), encrypted);
+
+        #[cfg(test)]
+        self.writes
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn write_classified(
+        &self,
+        relative_path: &Path,
+        data: &[u8],
+        encryption_key: &[u8; 32],
+        class: DataClass,
+    ) -> Result<()> {
+        self.write(relative_path, data, encryption_key)?;
+        self.classes
+            .write()
+            .unwrap()
+            .insert(Self::normalize(relative_path), class);
+        if class.always_redacted() {
+            crate::logs::always_redact(&relative_path.to_string_lossy());
+        }
+        Ok(())
+    }
+
+    fn classification(&self, relative_path: &Path) -> Result<Option<DataClass>> {
+        Ok(self
+            .classes
+            .read()
+            .unwrap()
+            .get(&Self::normalize(relative_path))
+            .copied())
+    }
+
+    fn read(&self, relative_path: &Path, encryption_key: &[u8; 32]) -> Result<Vec<u8>> {
+        self.read_as(relative_path, encryption_key, None)
+    }
+
+    fn read_keyed(
+        &self,
+        relative_path: &Path,
+        encryption_key: &[u8; 32],
+        key_source: KeySource,
+    ) -> Result<Vec<u8>> {
+        self.read_as(relative_path, encryption_key, Some(key_source))
+    }
+
+    fn exists(&self, relative_path: &Path) -> bool {
+        let path = Self::normalize(relative_path);
+        self.files
+            .read()
+            .unwrap()
+            .keys()
+            .any(|file| file.starts_with(&path))
+    }
+
+    fn delete(&self, relative_path: &Path) -> Result<bool> {
+        self.classes
+            .write()
+            .unwrap()
+            .remove(&Self::normalize(relative_path));
+        Ok(self
+            .files
+            .write()
+            .unwrap()
+            .remove(&Self::normalize(relative_path))
+            .is_some())
+    }
+
+    fn list_files(&self, relative_path: &Path) -> Result<Vec<PathBuf>> {
+        let dir = Self::normalize(relative_path);
+        Ok(self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|file| file.starts_with(&dir))
+            .cloned()
+            .collect())
+    }
+
+    fn clear_directory(&self, relative_path: &Path) -> Result<()> {
+        let dir = Self::normalize(relative_path);
+        self.files
+            .write()
+            .unwrap()
+            .retain(|file, _| !file.starts_with(&dir));
+        self.classes
+            .write()
+            .unwrap()
+            .retain(|file, _| !file.starts_with(&dir));
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn write_count(&self) -> usize {
+        self.writes.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_write_and_read() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [42u8; 32];
+        let data = b"Hello, encrypted world!";
+
+        storage.write(Path::new("test.dat"), data, &key)?;
+        let retrieved = storage.read(Path::new("test.dat"), &key)?;
+
+        assert_eq!(retrieved, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_with_subdirectories() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [99u8; 32];
+
+        storage.write(Path::new("cache/app-001/config.json"), b"nested data", &key)?;
+
+        // Spelled differently, the path names the same file
+        let retrieved = storage.read(Path::new("./cache/app-001/config.json"), &key)?;
+        assert_eq!(retrieved, b"nested data");
+        Ok(())
+    }
+
+    #[test]
+    fn test_exists() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [1u8; 32];
+
+        assert!(!storage.exists(Path::new("nonexistent.dat")));
+
+        storage.write(Path::new("dir/existing.dat"), b"data", &key)?;
+        assert!(storage.exists(Path::new("dir/existing.dat")));
+        assert!(storage.exists(Path::new("dir")));
+        assert!(!storage.exists(Path::new("di")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [2u8; 32];
+
+        storage.write(Path::new("to-delete.dat"), b"data", &key)?;
+        assert!(storage.delete(Path::new("to-delete.dat"))?);
+        assert!(!storage.exists(Path::new("to-delete.dat")));
+
+        // Deleting again should return false
+        assert!(!storage.delete(Path::new("to-delete.dat"))?);
+        assert!(storage.read(Path::new("to-delete.dat"), &key).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_files() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [3u8; 32];
+
+        storage.write(Path::new("file1.dat"), b"data1", &key)?;
+        storage.write(Path::new("subdir/file2.dat"), b"data2", &key)?;
+        storage.write(Path::new("subdir/nested/file3.dat"), b"data3", &key)?;
+
+        assert_eq!(storage.list_files(Path::new(""))?.len(), 3);
+        assert_eq!(
+            storage.list_files(Path::new("subdir"))?,
+            vec![
+                PathBuf::from("subdir/file2.dat"),
+                PathBuf::from("subdir/nested/file3.dat")
+            ]
+        );
+        assert!(storage.list_files(Path::new("missing"))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_directory() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [4u8; 32];
+
+        storage.write(Path::new("cache/file1.dat"), b"data1", &key)?;
+        storage.write(Path::new("cache/subdir/file3.dat"), b"data3", &key)?;
+        storage.write(Path::new("config/kept.dat"), b"kept", &key)?;
+
+        storage.clear_directory(Path::new("cache"))?;
+
+        assert!(storage.list_files(Path::new("cache"))?.is_empty());
+        assert!(!storage.exists(Path::new("cache")));
+        assert_eq!(storage.list_files(Path::new(""))?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_key_fails() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+
+        storage.write(Path::new("encrypted.dat"), b"secret", &[5u8; 32])?;
+
+        assert!(storage
+            .read(Path::new("encrypted.dat"), &[6u8; 32])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_overwrite_file() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [7u8; 32];
+
+        storage.write(Path::new("overwrite.dat"), b"original", &key)?;
+        storage.write(Path::new("overwrite.dat"), b"updated", &key)?;
+
+        assert_eq!(storage.read(Path::new("overwrite.dat"), &key)?, b"updated");
+        assert_eq!(storage.write_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_cocoon_writes() -> Result<()> {
+        let storage = Arc::new(MemoryFileStorage::new());
+        let key = [13u8; 32];
+
+        let handles: Vec<_> = (0..8u8)
+            .map(|writer| {
+                let storage = storage.clone();
+                std::thread::spawn(move || -> Result<()> {
+                    for round in 0..10u8 {
+                        let path = PathBuf::from(format!("keys/cocoon-{}", writer % 4));
+                        storage.write(&path, &[writer, round], &key)?;
+                        // Every read sees one whole write, never a torn one
+                        assert_eq!(storage.read(&path, &key)?.len(), 2);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        assert_eq!(storage.list_files(Path::new("keys"))?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_and_empty_data() -> Result<()> {
+        let storage = MemoryFileStorage::new();
+        let key = [8u8; 32];
+        let binary_data: Vec<u8> = (0..=255).collect();
+
+        storage.write(Path::new("binary.dat"), &binary_data, &key)?;
+        storage.write(Path::new("empty.dat"), b"", &key)?;
+
+        assert_eq!(storage.read(Path::new("binary.dat"), &key)?, binary_data);
+        assert_eq!(storage.read(Path::new("empty.dat"), &key)?, b"");
+        Ok(())
+    }
+}
@@ -3,7 +3,13 @@
 //! This module provides storage implementations:
 //! - SQLite storage for structured data
 //! - File-based encrypted storage for cache and keys
+//! - Compaction of files the encrypted storage no longer uses
+//! - Data classification of stored files and blobs
+//! - In-memory file storage for ephemeral contexts and tests
 //! - Encrypted blob storage
+//! - Fault injection for resilience testing (`chaos` feature)
+//! - Install ownership, so OS accounts sharing a directory don't collide
+//! - Context and diagnosis of data that does not decrypt
 
 /// SQLite storage backend
 pub mod sql;
@@ -11,5 +17,34 @@ pub mod sql;
 /// File-based encrypted storage
 pub mod file;
 
-pub use file::FileStorage;
-pub use sql::SqlStorage;
+/// Compaction of the file storage tree
+pub mod compaction;
+
+/// Data classification and its handling rules
+pub mod classification;
+
+/// In-memory encrypted file storage
+pub mod memory;
+
+/// Transparent payload compression
+pub mod compression;
+
+/// Fault injection for resilience testing
+pub mod chaos;
+
+/// Install ownership of storage directories
+pub mod ownership;
+
+/// Context and diagnosis of data that does not decrypt
+pub mod decrypt;
+
+pub use classification::{DataClass, Purpose};
+pub use compaction::{CompactCategory, CompactItem, CompactPolicy, CompactReport};
+pub use compression::CompressionSettings;
+pub use decrypt::{
+    diagnose_decrypt_failure, DecryptAttempt, DecryptDiagnosis, DecryptError, DecryptLocation,
+    KeyCandidates,
+};
+pub use file::{FileStorage, FileStore};
+pub use memory::MemoryFileStorage;
+pub use sql::{ReadOnlyRows, SqlStorage, SCHEMA_VERSION};
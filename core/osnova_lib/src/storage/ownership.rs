@@ -0,0 +1,289 @@
+//! Install ownership of storage directories
+//!
+//! Two OS accounts pointed at one data directory (a shared
+//! `OSNOVA_STORAGE_PATH`, or a misconfigured shared data dir) would
+//! otherwise overwrite each other's databases and identity files. Each OS
+//! user gets a random install id, persisted in their config directory, and
+//! every SQLite database and file storage root records the install id that
+//! created it in an owner lock next to it. Opening storage owned by a
+//! different install id fails with
+//! [`OsnovaError::StorageOwnedElsewhere`] until it is adopted on purpose
+//! with [`crate::services::StorageService::adopt`].
+//!
+//! The lock is an ownership record, not a process lock: it is never removed
+//! on close, so a lock left behind by a crash of the same install is simply
+//! reused.
+
+use anyhow::{Context, Result};
+use bip39::rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::error::OsnovaError;
+use crate::platform::paths;
+use crate::storage::FileStorage;
+use crate::time;
+
+/// Suffix of owner lock files; a storage root's lock is named exactly this
+pub const OWNER_LOCK_SUFFIX: &str = ".osnova-owner";
+
+/// File in the config directory holding the install id
+pub const INSTALL_ID_FILE: &str = "install-id";
+
+/// Length of an install id in hex characters
+const INSTALL_ID_LEN: usize = 32;
+
+/// Owner lock contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerLock {
+    /// Install id that owns the storage
+    pub install_id: String,
+    /// OS user the install belonged to when it claimed the storage
+    pub username: String,
+    /// Unix timestamp of the claim
+    pub claimed_at: u64,
+}
+
+/// Install id of this OS user
+///
+/// Read from the user's config directory, and created there on first use.
+/// Falls back to an id derived from the OS username when no config
+/// directory is available.
+pub fn local_install_id() -> &'static str {
+    static LOCAL: OnceLock<String> = OnceLock::new();
+    LOCAL.get_or_init(|| {
+        paths::get_config_dir()
+            .ok()
+            .and_then(|dir| load_or_create_install_id(&dir).ok())
+            .unwrap_or_else(|| {
+                let derived = blake3::derive_key(
+                    "osnova install id v1",
+                    paths::current_username().as_bytes(),
+                );
+                hex::encode(&derived[..INSTALL_ID_LEN / 2])
+            })
+    })
+}
+
+/// Read the install id persisted in `config_dir`, creating one if missing
+///
+/// # Errors
+///
+/// Returns an error if the directory or id file cannot be written
+pub fn load_or_create_install_id(config_dir: &Path) -> Result<String> {
+    let path = config_dir.join(INSTALL_ID_FILE);
+    if let Some(id) = read_install_id(&path)? {
+        return Ok(id);
+    }
+
+    fs::create_dir_all(config_dir).context("Failed to create config directory")?;
+    let mut bytes = [0u8; INSTALL_ID_LEN / 2];
+    thread_rng().fill_bytes(&mut bytes);
+    let id = hex::encode(bytes);
+
+    // Another process creating the id at the same time must not win with a
+    // different one after this process has started using its own
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(mut file) => {
+            file.write_all(id.as_bytes())?;
+            file.sync_all()?;
+            Ok(id)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => match read_install_id(&path)? {
+            Some(existing) => Ok(existing),
+            None => {
+                // A truncated id from an interrupted first run was never used
+                write_atomically(&path, id.as_bytes())?;
+                Ok(id)
+            }
+        },
+        Err(e) => Err(e).context("Failed to create install id"),
+    }
+}
+
+fn read_install_id(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            let id = contents.trim();
+            let valid = id.len() == INSTALL_ID_LEN && id.bytes().all(|b| b.is_ascii_hexdigit());
+            Ok(valid.then(|| id.to_ascii_lowercase()))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to read install id"),
+    }
+}
+
+/// Owner lock of a SQLite database
+pub fn database_lock_path(database: &Path) -> PathBuf {
+    let mut name = database.file_name().unwrap_or_default().to_os_string();
+    name.push(OWNER_LOCK_SUFFIX);
+    database.with_file_name(name)
+}
+
+/// Owner lock of a file storage root
+pub fn root_lock_path(root: &Path) -> PathBuf {
+    root.join(OWNER_LOCK_SUFFIX)
+}
+
+/// Whether `path` is an owner lock
+pub fn is_owner_lock(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(OWNER_LOCK_SUFFIX))
+}
+
+/// Read an owner lock, if there is one
+///
+/// # Errors
+///
+/// Returns an error if the lock exists but cannot be read or parsed
+pub fn read_lock(lock_path: &Path) -> Result<Option<OwnerLock>> {
+    match fs::read(lock_path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+            OsnovaError::Storage(format!(
+                "Unreadable owner lock at {} ({}). It records which Osnova install owns this \
+                 storage; remove it only if no other OS account uses this directory",
+                lock_path.display(),
+                e
+            ))
+            .into()
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to read owner lock"),
+    }
+}
+
+/// Check that no other install owns the storage at `guarded`
+///
+/// Returns whether `install_id` has claimed it already.
+///
+/// # Errors
+///
+/// Returns [`OsnovaError::StorageOwnedElsewhere`] if a different install
+/// owns the storage
+pub fn verify(lock_path: &Path, guarded: &Path, install_id: &str) -> Result<bool> {
+    match read_lock(lock_path)? {
+        Some(lock) if lock.install_id == install_id => Ok(true),
+        Some(lock) => Err(OsnovaError::StorageOwnedElsewhere {
+            path: guarded.to_path_buf(),
+            owner: lock.install_id,
+            owner_user: lock.username,
+        }
+        .into()),
+        None => Ok(false),
+    }
+}
+
+/// Make sure `install_id` owns the storage at `guarded`
+///
+/// Unclaimed storage, including storage created before owner locks
+/// existed, is claimed for `install_id`.
+///
+/// # Errors
+///
+/// Returns [`OsnovaError::StorageOwnedElsewhere`] if a different install
+/// owns the storage
+pub fn claim(lock_path: &Path, guarded: &Path, install_id: &str) -> Result<()> {
+    if !verify(lock_path, guarded, install_id)? {
+        write_lock(lock_path, install_id)?;
+    }
+    Ok(())
+}
+
+/// Hand storage owned by `from` over to `to`
+///
+/// Returns `false`, changing nothing, if `from` does not own the storage.
+pub fn adopt(lock_path: &Path, from: &str, to: &str) -> Result<bool> {
+    match read_lock(lock_path)? {
+        Some(lock) if lock.install_id == from => {
+            write_lock(lock_path, to)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn write_lock(lock_path: &Path, install_id: &str) -> Result<()> {
+    let lock = OwnerLock {
+        install_id: install_id.to_string(),
+        username: paths::current_username(),
+        claimed_at: time::now_unix(),
+    };
+    write_atomically(lock_path, &serde_json::to_vec_pretty(&lock)?)
+        .with_context(|| format!("Failed to write owner lock {}", lock_path.display()))
+}
+
+/// Write through a temporary file, so a crash never leaves a partial file
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp = FileStorage::temp_path(path);
+    let result = (|| -> io::Result<()> {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_install_id_is_persisted() -> Result<()> {
+        let temp = TempDir::new()?;
+        let id = load_or_create_install_id(temp.path())?;
+        assert_eq!(id.len(), INSTALL_ID_LEN);
+        assert_eq!(load_or_create_install_id(temp.path())?, id);
+
+        // A truncated id from an interrupted first run is replaced
+        fs::write(temp.path().join(INSTALL_ID_FILE), "12ab")?;
+        let replaced = load_or_create_install_id(temp.path())?;
+        assert_ne!(replaced, id);
+        assert_eq!(load_or_create_install_id(temp.path())?, replaced);
+
+        let other = TempDir::new()?;
+        assert_ne!(load_or_create_install_id(other.path())?, replaced);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_claim_and_adopt() -> Result<()> {
+        let temp = TempDir::new()?;
+        let database = temp.path().join("osnova.db");
+        let lock = database_lock_path(&database);
+        assert!(lock.ends_with("osnova.db.osnova-owner"));
+        assert!(is_owner_lock(&lock));
+        assert!(is_owner_lock(&root_lock_path(temp.path())));
+
+        claim(&lock, &database, "install-a")?;
+        assert_eq!(read_lock(&lock)?.unwrap().install_id, "install-a");
+        claim(&lock, &database, "install-a")?;
+
+        let err = claim(&lock, &database, "install-b").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OsnovaError>(),
+            Some(OsnovaError::StorageOwnedElsewhere { owner, .. }) if owner == "install-a"
+        ));
+        assert!(err.to_string().contains("OSNOVA_STORAGE_PATH"));
+
+        assert!(!adopt(&lock, "install-c", "install-b")?);
+        assert!(adopt(&lock, "install-a", "install-b")?);
+        claim(&lock, &database, "install-b")?;
+        assert!(claim(&lock, &database, "install-a").is_err());
+
+        // A damaged lock is reported rather than silently replaced
+        fs::write(&lock, b"{\"installId\":")?;
+        assert!(claim(&lock, &database, "install-b").is_err());
+
+        Ok(())
+    }
+}
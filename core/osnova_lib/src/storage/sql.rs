@@ -1,12 +1,80 @@
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
-use crate::crypto::encryption::CocoonEncryption;
-use crate::models::application::OsnovaApplication;
+use crate::crypto::encryption::{CocoonEncryption, DecryptFailure};
+use crate::error::OsnovaError;
+use crate::manifest::ManifestSchema;
+use crate::models::annotation::{Annotation, Subject};
+use crate::models::application::{
+    ComponentRef, OsnovaApplication, SharedComponentKey, TrashedApplication,
+};
+use crate::models::archive_upload::{ArchiveBatchRecord, ArchiveEntry};
+use crate::models::backend_process::BackendProcess;
 use crate::models::config_cache::AppConfiguration;
+use crate::models::config_snapshot::{ConfigSnapshot, SnapshotContent, SnapshotReason};
+use crate::models::consent::AppConsent;
+use crate::models::crash_report::CrashReport;
 use crate::models::device_key::DeviceKey;
+use crate::models::health::HealthTransition;
+use crate::models::install_journal::InstallJournalEntry;
+use crate::models::key_usage::{KeyOperation, KeyUsageAnomaly, KeyUsageRow, UsageGranularity};
+use crate::models::launcher_change::{LauncherChange, LauncherChangeKind, LAUNCHER_CHANGE_HISTORY};
+use crate::models::launcher_layout::OrderKey;
+use crate::models::materialization::Materialization;
+use crate::models::mutation::MutationReceipt;
+use crate::models::notification::{Notification, StoredNotification};
 use crate::models::pairing::{PairingSession, PairingStatus};
+use crate::models::payment_record::PaymentRecord;
+use crate::models::permission::{Capability, PermissionGrant};
+use crate::models::privacy::ObservedHost;
+use crate::models::provenance::{ProvenanceRecord, ReproducibilityCheck};
+use crate::models::retention::{Footprint, RetainedItem};
+use crate::models::session::RemoteSession;
+use crate::models::sharing::SharedDataGrant;
+use crate::models::task::TaskInfo;
+use crate::platform::disk::DiskGuard;
+use crate::services::security::KeySource;
+use crate::storage::classification::{self, DataClass, Purpose};
+use crate::storage::compression::{self, CompressionSettings};
+use crate::storage::decrypt::{DecryptError, DecryptLocation};
+use crate::storage::ownership;
+
+/// Schema version written to `PRAGMA user_version`
+///
+/// Raised whenever [`SqlStorage`] changes its schema in a way an older
+/// release could not read. A database stamped with a newer version is
+/// refused with [`OsnovaError::SchemaTooNew`] rather than migrated.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How long a mutation receipt is kept for retries of the same mutation id
+pub const MUTATION_RECEIPT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Columns read by [`SqlStorage::pairing_session_from_row`], in order
+const PAIRING_SESSION_COLUMNS: &str = "session_id, server_public_key, device_public_key, \
+     established_at, expires_at, status, confirmation";
+
+/// Rows returned by [`SqlStorage::query_read_only`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnlyRows {
+    /// Column names, in order
+    pub columns: Vec<String>,
+    /// Row values, in column order
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// Whether more rows matched than were returned
+    pub truncated: bool,
+}
+
+/// Encrypted value of a row, read by [`SqlStorage::read_encrypted_row`]
+pub(crate) struct EncryptedRow {
+    /// Ciphertext
+    pub value: Vec<u8>,
+    /// Data class, if the row has one
+    pub class: Option<DataClass>,
+    /// Unix timestamp of the last write
+    pub updated_at: Option<u64>,
+}
 
 /// SQLite-based storage backend for Osnova
 ///
@@ -15,7 +83,8 @@ use crate::models::pairing::{PairingSession, PairingStatus};
 /// - Device keys
 /// - Pairing sessions
 /// - App configurations (encrypted at rest)
-/// - Encrypted blob storage
+/// - Encrypted blob storage (compressed before encryption, see
+///   [`CompressionSettings`])
 ///
 /// # Example
 ///
@@ -29,6 +98,8 @@ use crate::models::pairing::{PairingSession, PairingStatus};
 /// ```
 pub struct SqlStorage {
     conn: Connection,
+    compression: CompressionSettings,
+    disk_guard: Option<DiskGuard>,
 }
 
 impl SqlStorage {
@@ -40,10 +111,30 @@ impl SqlStorage {
     ///
     /// Returns an error if:
     /// - Database file cannot be created/opened
+    /// - The database belongs to another install
+    ///   ([`OsnovaError::StorageOwnedElsewhere`](crate::error::OsnovaError::StorageOwnedElsewhere))
+    /// - The database was written by a newer release
+    ///   ([`OsnovaError::SchemaTooNew`])
     /// - Schema initialization fails
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new_for_install(path, ownership::local_install_id())
+    }
+
+    /// Create or open a database owned by a specific install
+    ///
+    /// See [`crate::storage::ownership`].
+    pub fn new_for_install<P: AsRef<Path>>(path: P, install_id: &str) -> Result<Self> {
+        let path = path.as_ref();
         let conn = Connection::open(path).context("Failed to open database")?;
-        let storage = Self { conn };
+        // Checked before the schema is touched, so a foreign database is
+        // never migrated
+        ownership::claim(&ownership::database_lock_path(path), path, install_id)?;
+        check_schema_version(path, read_schema_version(&conn)?)?;
+        let storage = Self {
+            conn,
+            compression: compression::shared_settings(),
+            disk_guard: None,
+        };
         storage.initialize_schema()?;
         Ok(storage)
     }
@@ -51,11 +142,315 @@ impl SqlStorage {
     /// Create an in-memory database for testing
     pub fn new_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory().context("Failed to create in-memory database")?;
-        let storage = Self { conn };
+        let storage = Self {
+            conn,
+            compression: compression::shared_settings(),
+            disk_guard: None,
+        };
+        storage.initialize_schema()?;
+        Ok(storage)
+    }
+
+    /// Open a named in-memory database shared by every connection in the
+    /// process that uses the same name
+    ///
+    /// The database lives while at least one connection to it is open, so
+    /// services of an ephemeral context see each other's writes without a
+    /// file on disk.
+    pub fn new_shared_memory(name: &str) -> Result<Self> {
+        let uri = format!("file:{}?mode=memory&cache=shared", name);
+        let conn = Connection::open(uri).context("Failed to open shared in-memory database")?;
+        let storage = Self {
+            conn,
+            compression: compression::shared_settings(),
+            disk_guard: None,
+        };
         storage.initialize_schema()?;
         Ok(storage)
     }
 
+    /// Use specific compression settings for newly written blobs, instead
+    /// of the [process-wide ones](compression::shared_settings)
+    ///
+    /// Existing blobs are read back regardless of these settings.
+    pub fn with_compression(mut self, compression: CompressionSettings) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Refuse backups that would leave less free space than `guard` requires
+    pub fn with_disk_guard(mut self, guard: DiskGuard) -> Self {
+        self.disk_guard = Some(guard);
+        self
+    }
+
+    /// Write a consistent copy of the database to `dest`
+    ///
+    /// Returns the size of the backup in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dest` exists, or
+    /// [`OsnovaError::DiskSpaceLow`](crate::error::OsnovaError::DiskSpaceLow)
+    /// if the backup would leave too little free space.
+    pub fn backup_to<P: AsRef<Path>>(&self, dest: P) -> Result<u64> {
+        let dest = dest.as_ref();
+
+        if let Some(guard) = &self.disk_guard {
+            let size: i64 = self
+                .conn
+                .query_row(
+                    "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                    [],
+                    |row| row.get(0),
+                )
+                .context("Failed to measure database")?;
+            guard.check(dest.parent().unwrap_or(dest), size.max(0) as u64)?;
+        }
+
+        self.conn
+            .execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+            .context("Failed to back up database")?;
+
+        let size = std::fs::metadata(dest)
+            .context("Failed to read backup size")?
+            .len();
+        Ok(size)
+    }
+
+    /// Run SQLite's integrity check on a database file without opening it
+    /// as storage
+    ///
+    /// The file is opened read-only and no owner lock is claimed, so
+    /// databases copied from elsewhere can be checked before they are used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first problem found if the database is
+    /// damaged or is not a SQLite database
+    pub fn check_integrity<P: AsRef<Path>>(path: P) -> Result<()> {
+        let conn = Connection::open_with_flags(
+            path.as_ref(),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Failed to open database")?;
+        let result: String = conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .context("Failed to check database integrity")?;
+        if result != "ok" {
+            anyhow::bail!("Database integrity check failed: {}", result);
+        }
+        Ok(())
+    }
+
+    /// Schema version of a database file, without opening it as storage
+    ///
+    /// The file is opened read-only and no owner lock is claimed. Returns
+    /// `None` if the file does not exist yet.
+    pub fn schema_version<P: AsRef<Path>>(path: P) -> Result<Option<u32>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let conn = Connection::open_with_flags(
+            path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Failed to open database")?;
+        read_schema_version(&conn).map(Some)
+    }
+
+    /// Check that this release can open a database file, without touching it
+    ///
+    /// Returns the version the database would be migrated from, or `None`
+    /// if the file does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OsnovaError::SchemaTooNew`] if a newer release wrote the
+    /// database, or an error if it cannot be read
+    pub fn check_schema<P: AsRef<Path>>(path: P) -> Result<Option<u32>> {
+        let path = path.as_ref();
+        let version = Self::schema_version(path)?;
+        if let Some(version) = version {
+            check_schema_version(path, version)?;
+        }
+        Ok(version)
+    }
+
+    /// Run one read-only statement against a database file
+    ///
+    /// The file is opened read-only and no owner lock is claimed. `params`
+    /// bind named parameters (`:name`) to JSON nulls, strings or integers.
+    /// Returns the column names and at most `max_rows` rows; blob values
+    /// come back as their length in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened, `sql` is not a
+    /// single statement that only reads, or a parameter is not a null,
+    /// string or integer
+    pub fn query_read_only<P: AsRef<Path>>(
+        path: P,
+        sql: &str,
+        params: &[(&str, serde_json::Value)],
+        max_rows: usize,
+    ) -> Result<ReadOnlyRows> {
+        let conn = Connection::open_with_flags(
+            path.as_ref(),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Failed to open database")?;
+        let mut statements = rusqlite::Batch::new(&conn, sql);
+        let mut stmt = statements
+            .next()
+            .context("Failed to prepare query")?
+            .context("Empty query")?;
+        if statements
+            .next()
+            .context("Failed to prepare query")?
+            .is_some()
+        {
+            anyhow::bail!("Refusing more than one statement");
+        }
+        if !stmt.readonly() {
+            anyhow::bail!("Refusing a statement that writes to the database");
+        }
+
+        let params = params
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::Null => rusqlite::types::Value::Null,
+                    serde_json::Value::String(text) => rusqlite::types::Value::Text(text.clone()),
+                    serde_json::Value::Number(number) => match number.as_i64() {
+                        Some(integer) => rusqlite::types::Value::Integer(integer),
+                        None => anyhow::bail!("Parameter {} is not an integer", name),
+                    },
+                    _ => anyhow::bail!("Parameter {} must be a string or an integer", name),
+                };
+                Ok((*name, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let named: Vec<(&str, &dyn rusqlite::ToSql)> = params
+            .iter()
+            .map(|(name, value)| (*name, value as &dyn rusqlite::ToSql))
+            .collect();
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt
+            .query(named.as_slice())
+            .context("Failed to run query")?;
+        let mut result = ReadOnlyRows {
+            columns,
+            rows: Vec::new(),
+            truncated: false,
+        };
+        while let Some(row) = rows.next().context("Failed to read query row")? {
+            if result.rows.len() == max_rows {
+                result.truncated = true;
+                break;
+            }
+            let values = (0..result.columns.len())
+                .map(|index| {
+                    Ok(match row.get_ref(index)? {
+                        rusqlite::types::ValueRef::Null => serde_json::Value::Null,
+                        rusqlite::types::ValueRef::Integer(integer) => integer.into(),
+                        rusqlite::types::ValueRef::Real(real) => real.into(),
+                        rusqlite::types::ValueRef::Text(text) => {
+                            String::from_utf8_lossy(text).into_owned().into()
+                        }
+                        rusqlite::types::ValueRef::Blob(blob) => blob.len().into(),
+                    })
+                })
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to read query row")?;
+            result.rows.push(values);
+        }
+        Ok(result)
+    }
+
+    /// Encrypted value of a row, named as a [`DecryptError`] names it,
+    /// without opening the database as storage
+    ///
+    /// The file is opened read-only and no owner lock is claimed. Rows of
+    /// `encrypted_blobs`, `app_configurations`, `config_snapshots` and
+    /// `annotations` can be read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be read, the table holds no
+    /// encrypted values, or the key does not name a row of it
+    pub(crate) fn read_encrypted_row<P: AsRef<Path>>(
+        path: P,
+        table: &str,
+        key: &str,
+    ) -> Result<Option<EncryptedRow>> {
+        let conn = Connection::open_with_flags(
+            path.as_ref(),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Failed to open database")?;
+        let malformed = || anyhow::anyhow!("Malformed {} row key: {}", table, key);
+
+        let row: Option<(Vec<u8>, Option<String>, Option<u64>)> = match table {
+            "encrypted_blobs" => conn
+                .query_row(
+                    "SELECT value_encrypted, data_class, updated_at FROM encrypted_blobs
+                     WHERE key = ?1",
+                    params![key],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional(),
+            "app_configurations" => {
+                let (app_id, user_id) = key.split_once('/').ok_or_else(malformed)?;
+                conn.query_row(
+                    "SELECT settings_encrypted, NULL, updated_at FROM app_configurations
+                     WHERE app_id = ?1 AND user_id = ?2",
+                    params![app_id, user_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+            }
+            "config_snapshots" => {
+                let id: i64 = key.parse().map_err(|_| malformed())?;
+                conn.query_row(
+                    "SELECT content_encrypted, NULL, created_at FROM config_snapshots
+                     WHERE id = ?1",
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+            }
+            "annotations" => {
+                let mut parts = key.splitn(3, '/');
+                let (Some(user_id), Some(kind), Some(id)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(malformed());
+                };
+                conn.query_row(
+                    "SELECT value_encrypted, NULL, updated_at FROM annotations
+                     WHERE user_id = ?1 AND subject_kind = ?2 AND subject_id = ?3",
+                    params![user_id, kind, id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+            }
+            _ => anyhow::bail!("Table {} holds no encrypted values", table),
+        }
+        .with_context(|| format!("Failed to query {}", table))?;
+
+        row.map(|(value, class, updated_at)| {
+            Ok(EncryptedRow {
+                value,
+                class: class.map(|class| class.parse()).transpose()?,
+                updated_at,
+            })
+        })
+        .transpose()
+    }
+
     /// Initialize database schema
     fn initialize_schema(&self) -> Result<()> {
         self.conn
@@ -64,7 +459,33 @@ impl SqlStorage {
             CREATE TABLE IF NOT EXISTS applications (
                 id TEXT PRIMARY KEY,
                 data TEXT NOT NULL,
-                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                manifest TEXT,
+                pinned_version TEXT,
+                last_refreshed INTEGER,
+                materialization TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS deleted_applications (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                deleted_at INTEGER NOT NULL,
+                purge_after INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS app_versions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app_id TEXT NOT NULL,
+                replaced_at INTEGER NOT NULL,
+                data TEXT NOT NULL,
+                FOREIGN KEY (app_id) REFERENCES applications(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS app_updates (
+                app_id TEXT PRIMARY KEY,
+                pending TEXT,
+                skipped_version TEXT,
+                FOREIGN KEY (app_id) REFERENCES applications(id) ON DELETE CASCADE
             );
 
             CREATE TABLE IF NOT EXISTS device_keys (
@@ -78,7 +499,9 @@ impl SqlStorage {
                 device_public_key BLOB NOT NULL,
                 established_at INTEGER NOT NULL,
                 expires_at INTEGER NOT NULL,
-                status TEXT NOT NULL CHECK(status IN ('pending', 'established', 'failed'))
+                status TEXT NOT NULL CHECK(status IN
+                    ('pending', 'awaiting_confirmation', 'established', 'failed')),
+                confirmation TEXT
             );
 
             CREATE TABLE IF NOT EXISTS app_configurations (
@@ -90,18 +513,357 @@ impl SqlStorage {
                 FOREIGN KEY (app_id) REFERENCES applications(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS config_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                version INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                content_encrypted BLOB NOT NULL,
+                FOREIGN KEY (app_id) REFERENCES applications(id) ON DELETE CASCADE
+            );
+
             CREATE TABLE IF NOT EXISTS encrypted_blobs (
                 key TEXT PRIMARY KEY,
                 value_encrypted BLOB NOT NULL,
                 updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
             );
 
+            CREATE TABLE IF NOT EXISTS bandwidth_usage (
+                day INTEGER NOT NULL,
+                category TEXT NOT NULL,
+                bytes INTEGER NOT NULL,
+                PRIMARY KEY (day, category)
+            );
+
+            CREATE TABLE IF NOT EXISTS backend_processes (
+                pid INTEGER PRIMARY KEY,
+                app_id TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS app_launches (
+                app_id TEXT PRIMARY KEY,
+                launch_count INTEGER NOT NULL,
+                last_launched_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS launch_scores (
+                app_id TEXT NOT NULL,
+                slot INTEGER NOT NULL,
+                score REAL NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (app_id, slot)
+            );
+
+            CREATE TABLE IF NOT EXISTS shared_components (
+                shared_id TEXT NOT NULL,
+                version TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (shared_id, version)
+            );
+
+            CREATE TABLE IF NOT EXISTS component_provenance (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                component_id TEXT NOT NULL,
+                component_hash TEXT NOT NULL,
+                downloaded_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS reproducibility_checks (
+                app_id TEXT NOT NULL,
+                app_version TEXT NOT NULL,
+                component_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (app_id, app_version, component_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS network_observations (
+                owner_id TEXT NOT NULL,
+                component_id TEXT NOT NULL,
+                host TEXT NOT NULL,
+                allowed INTEGER NOT NULL,
+                denied INTEGER NOT NULL,
+                bytes_sent INTEGER NOT NULL,
+                bytes_received INTEGER NOT NULL,
+                first_seen INTEGER NOT NULL,
+                last_seen INTEGER NOT NULL,
+                PRIMARY KEY (owner_id, component_id, host)
+            );
+
+            CREATE TABLE IF NOT EXISTS remote_sessions (
+                token_hash TEXT PRIMARY KEY,
+                device_id TEXT NOT NULL,
+                expires_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS task_history (
+                id TEXT PRIMARY KEY,
+                finished_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS launcher_changes (
+                generation INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                app_id TEXT,
+                user_id TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS mutation_receipts (
+                scope TEXT NOT NULL,
+                mutation_id TEXT NOT NULL,
+                generation INTEGER NOT NULL,
+                recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (scope, mutation_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS app_consents (
+                user_id TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (user_id, app_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS device_permissions (
+                device_id TEXT NOT NULL,
+                permission TEXT NOT NULL,
+                granted_at INTEGER NOT NULL,
+                PRIMARY KEY (device_id, permission)
+            );
+
+            CREATE TABLE IF NOT EXISTS uri_scheme_claims (
+                sequence INTEGER PRIMARY KEY AUTOINCREMENT,
+                scheme TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                UNIQUE(scheme, app_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS permission_grants (
+                user_id TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                component_id TEXT NOT NULL,
+                capability TEXT NOT NULL,
+                granted INTEGER NOT NULL,
+                decided_at INTEGER NOT NULL,
+                PRIMARY KEY (user_id, app_id, component_id, capability),
+                FOREIGN KEY (app_id) REFERENCES applications(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS shared_data_grants (
+                user_id TEXT NOT NULL,
+                provider_app_id TEXT NOT NULL,
+                offer_id TEXT NOT NULL,
+                consumer_app_id TEXT NOT NULL,
+                granted_at INTEGER NOT NULL,
+                PRIMARY KEY (user_id, provider_app_id, offer_id, consumer_app_id),
+                FOREIGN KEY (provider_app_id) REFERENCES applications(id) ON DELETE CASCADE,
+                FOREIGN KEY (consumer_app_id) REFERENCES applications(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                tag TEXT,
+                posted_at INTEGER NOT NULL,
+                read INTEGER NOT NULL DEFAULT 0,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS audit_log (
+                sequence INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS audit_head (
+                id INTEGER PRIMARY KEY CHECK(id = 0),
+                sequence INTEGER NOT NULL,
+                mac TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS search_documents (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS search_value_indexes (
+                user_id TEXT NOT NULL,
+                app_id TEXT NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (user_id, app_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS handoffs (
+                handoff_id TEXT PRIMARY KEY,
+                pairing_id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS crash_reports (
+                id TEXT PRIMARY KEY,
+                app_id TEXT NOT NULL,
+                crashed_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS health_transitions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app_id TEXT NOT NULL,
+                at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS annotations (
+                user_id TEXT NOT NULL,
+                subject_kind TEXT NOT NULL,
+                subject_id TEXT NOT NULL,
+                value_encrypted BLOB NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (user_id, subject_kind, subject_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS crash_acknowledgements (
+                app_id TEXT PRIMARY KEY,
+                report_id TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS install_journal (
+                app_id TEXT PRIMARY KEY,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS payments_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS archive_batches (
+                upload_id TEXT NOT NULL,
+                batch INTEGER NOT NULL,
+                payment_request_id TEXT,
+                quoted_cost INTEGER NOT NULL,
+                actual_cost INTEGER NOT NULL DEFAULT 0,
+                completed INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (upload_id, batch)
+            );
+
+            CREATE TABLE IF NOT EXISTS archive_files (
+                upload_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                address TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                PRIMARY KEY (upload_id, name)
+            );
+
+            CREATE TABLE IF NOT EXISTS archive_uploads (
+                upload_id TEXT PRIMARY KEY,
+                index_address TEXT NOT NULL,
+                completed_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS upload_dedup (
+                content_hash TEXT PRIMARY KEY,
+                address TEXT NOT NULL,
+                size INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS key_usage (
+                granularity TEXT NOT NULL,
+                bucket INTEGER NOT NULL,
+                component_id TEXT NOT NULL,
+                key_index INTEGER NOT NULL,
+                operation TEXT NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (granularity, component_id, bucket, key_index, operation)
+            );
+
+            CREATE TABLE IF NOT EXISTS key_usage_anomalies (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                component_id TEXT NOT NULL,
+                detected_at INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_app_versions_app
+                ON app_versions(app_id);
+
             CREATE INDEX IF NOT EXISTS idx_pairing_sessions_status
                 ON pairing_sessions(status);
+
+            CREATE INDEX IF NOT EXISTS idx_component_provenance_hash
+                ON component_provenance(component_hash);
+
+            CREATE INDEX IF NOT EXISTS idx_component_provenance_component
+                ON component_provenance(component_id);
+
+            CREATE INDEX IF NOT EXISTS idx_remote_sessions_device
+                ON remote_sessions(device_id);
+
+            CREATE INDEX IF NOT EXISTS idx_notifications_user
+                ON notifications(user_id, app_id);
+
+            CREATE INDEX IF NOT EXISTS idx_crash_reports_app
+                ON crash_reports(app_id, crashed_at);
+
+            CREATE INDEX IF NOT EXISTS idx_health_transitions_app
+                ON health_transitions(app_id, at);
+
+            CREATE INDEX IF NOT EXISTS idx_payments_history_recorded
+                ON payments_history(recorded_at);
+
+            CREATE INDEX IF NOT EXISTS idx_key_usage_anomalies_detected
+                ON key_usage_anomalies(detected_at);
+
+            CREATE INDEX IF NOT EXISTS idx_config_snapshots_app
+                ON config_snapshots(app_id, user_id, id);
             "#,
             )
             .context("Failed to initialize schema")?;
 
+        // Columns added after the first release
+        self.add_column_if_missing("applications", "manifest", "TEXT")?;
+        self.add_column_if_missing("applications", "pinned_version", "TEXT")?;
+        self.add_column_if_missing("applications", "last_refreshed", "INTEGER")?;
+        self.add_column_if_missing("applications", "materialization", "TEXT")?;
+        self.add_column_if_missing("encrypted_blobs", "data_class", "TEXT")?;
+        self.add_column_if_missing("deleted_applications", "launcher_positions", "TEXT")?;
+        self.widen_pairing_sessions()?;
+
+        self.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+            .context("Failed to record schema version")?;
+
+        Ok(())
+    }
+
+    /// Add a column to a table created by an older schema
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                params![table, column],
+                |row| row.get(0),
+            )
+            .context("Failed to inspect schema")?;
+        if !exists {
+            self.conn
+                .execute(
+                    &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                    [],
+                )
+                .with_context(|| format!("Failed to add column {}.{}", table, column))?;
+        }
+
         Ok(())
     }
 
@@ -110,33 +872,266 @@ impl SqlStorage {
     // ========================================================================
 
     /// Insert or update an application
+    ///
+    /// The manifest snapshot is rebuilt from the record; use
+    /// [`upsert_application_with_manifest`](Self::upsert_application_with_manifest)
+    /// when the resolved manifest is at hand.
     pub fn upsert_application(&self, app: &OsnovaApplication) -> Result<()> {
+        self.upsert_application_with_manifest(app, &ManifestSchema::from(app))
+    }
+
+    /// Insert or update an application with the manifest it was installed
+    /// from
+    ///
+    /// The snapshot describes the installed version, so verification and
+    /// repair never depend on what is currently published. A pinned version
+    /// is kept.
+    pub fn upsert_application_with_manifest(
+        &self,
+        app: &OsnovaApplication,
+        manifest: &ManifestSchema,
+    ) -> Result<()> {
         let app_json = serde_json::to_string(app).context("Failed to serialize application")?;
+        let manifest_json =
+            serde_json::to_string(manifest).context("Failed to serialize manifest")?;
 
         self.conn
             .execute(
-                "INSERT INTO applications (id, data)
-             VALUES (?1, ?2)
+                "INSERT INTO applications (id, data, manifest)
+             VALUES (?1, ?2, ?3)
              ON CONFLICT(id) DO UPDATE SET
-                data = excluded.data",
-                params![app.id(), &app_json],
+                data = excluded.data,
+                manifest = excluded.manifest",
+                params![app.id(), &app_json, &manifest_json],
             )
             .context("Failed to upsert application")?;
 
         Ok(())
     }
 
-    /// Get an application by ID
-    pub fn get_application(&self, app_id: &str) -> Result<Option<OsnovaApplication>> {
-        let result = self
+    /// Get the manifest snapshot of an installed application
+    ///
+    /// Returns `None` if the app is not installed or was installed before
+    /// snapshots were recorded.
+    pub fn get_manifest_snapshot(&self, app_id: &str) -> Result<Option<ManifestSchema>> {
+        let manifest: Option<Option<String>> = self
             .conn
             .query_row(
-                "SELECT data FROM applications WHERE id = ?1",
+                "SELECT manifest FROM applications WHERE id = ?1",
                 params![app_id],
-                |row| {
-                    let data: String = row.get(0)?;
-                    let app: OsnovaApplication = serde_json::from_str(&data)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query manifest snapshot")?;
+
+        manifest
+            .flatten()
+            .map(|json| serde_json::from_str(&json).context("Failed to parse manifest snapshot"))
+            .transpose()
+    }
+
+    /// Pin an installed application to a version, or unpin it with `None`
+    ///
+    /// Returns `false` if the app is not installed.
+    pub fn set_pinned_version(&self, app_id: &str, version: Option<&str>) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE applications SET pinned_version = ?2 WHERE id = ?1",
+                params![app_id, version],
+            )
+            .context("Failed to set pinned version")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Get the version an installed application is pinned to
+    pub fn get_pinned_version(&self, app_id: &str) -> Result<Option<String>> {
+        let version: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT pinned_version FROM applications WHERE id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query pinned version")?;
+
+        Ok(version.flatten())
+    }
+
+    /// Replace an installed application's record, keeping its manifest
+    /// snapshot, pin and refresh time
+    ///
+    /// Returns `false` if the app is not installed.
+    pub fn update_application(&self, app: &OsnovaApplication) -> Result<bool> {
+        let app_json = serde_json::to_string(app).context("Failed to serialize application")?;
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE applications SET data = ?2 WHERE id = ?1",
+                params![app.id(), &app_json],
+            )
+            .context("Failed to update application")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Record when an installed application's listing was last re-resolved
+    ///
+    /// Returns `false` if the app is not installed.
+    pub fn set_last_refreshed(&self, app_id: &str, refreshed_at: u64) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE applications SET last_refreshed = ?2 WHERE id = ?1",
+                params![app_id, refreshed_at as i64],
+            )
+            .context("Failed to record refresh time")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Get when an installed application's listing was last re-resolved
+    pub fn get_last_refreshed(&self, app_id: &str) -> Result<Option<u64>> {
+        let refreshed_at: Option<Option<i64>> = self
+            .conn
+            .query_row(
+                "SELECT last_refreshed FROM applications WHERE id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query refresh time")?;
+
+        Ok(refreshed_at.flatten().map(|at| at as u64))
+    }
+
+    /// List installed applications last refreshed before `before`
+    ///
+    /// Apps never refreshed come first, then the least recently refreshed.
+    pub fn list_stale_applications(&self, before: u64, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id FROM applications
+                 WHERE last_refreshed IS NULL OR last_refreshed < ?1
+                 ORDER BY COALESCE(last_refreshed, 0), created_at, id
+                 LIMIT ?2",
+            )
+            .context("Failed to prepare statement")?;
+
+        let ids = stmt
+            .query_map(params![before as i64, limit as i64], |row| row.get(0))
+            .context("Failed to query stale applications")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to read stale applications")?;
+
+        Ok(ids)
+    }
+
+    /// Record whether an installed application's components are cached
+    ///
+    /// Returns `false` if the app is not installed.
+    pub fn set_materialization(&self, app_id: &str, state: &Materialization) -> Result<bool> {
+        let state_json =
+            serde_json::to_string(state).context("Failed to serialize materialization")?;
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE applications SET materialization = ?2 WHERE id = ?1",
+                params![app_id, &state_json],
+            )
+            .context("Failed to record materialization")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Get the recorded materialization of an installed application
+    ///
+    /// Returns `None` if the app is not installed or was never checked.
+    pub fn get_materialization(&self, app_id: &str) -> Result<Option<Materialization>> {
+        let state: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT materialization FROM applications WHERE id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query materialization")?;
+
+        state
+            .flatten()
+            .map(|json| serde_json::from_str(&json).context("Failed to parse materialization"))
+            .transpose()
+    }
+
+    /// Recorded materialization of every installed application that has one
+    pub fn list_materialization(&self) -> Result<HashMap<String, Materialization>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, materialization FROM applications
+                 WHERE materialization IS NOT NULL",
+            )
+            .context("Failed to prepare statement")?;
+
+        let states = stmt
+            .query_map([], |row| {
+                let id: String = row.get(0)?;
+                let data: String = row.get(1)?;
+                let state = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok((id, state))
+            })
+            .context("Failed to query materialization")?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("Failed to parse materialization")?;
+
+        Ok(states)
+    }
+
+    /// Mark every installed application as not materialized
+    ///
+    /// For data restored without its cache. Returns the marked app IDs.
+    pub fn mark_unmaterialized(&self) -> Result<Vec<String>> {
+        let state_json = serde_json::to_string(&Materialization::NotMaterialized)
+            .context("Failed to serialize materialization")?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE applications SET materialization = ?1",
+            params![&state_json],
+        )
+        .context("Failed to record materialization")?;
+
+        let ids = {
+            let mut stmt = tx
+                .prepare("SELECT id FROM applications ORDER BY id")
+                .context("Failed to prepare statement")?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))
+                .context("Failed to query applications")?
+                .collect::<Result<Vec<String>, _>>()
+                .context("Failed to read applications")?;
+            ids
+        };
+        tx.commit()?;
+
+        Ok(ids)
+    }
+
+    /// Get an application by ID
+    pub fn get_application(&self, app_id: &str) -> Result<Option<OsnovaApplication>> {
+        let result = self
+            .conn
+            .query_row(
+                "SELECT data FROM applications WHERE id = ?1",
+                params![app_id],
+                |row| {
+                    let data: String = row.get(0)?;
+                    let app: OsnovaApplication = serde_json::from_str(&data)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
                     Ok(app)
                 },
             )
@@ -178,454 +1173,4949 @@ impl SqlStorage {
     }
 
     // ========================================================================
-    // Device Key Management
+    // Recently Deleted Applications (Trash)
     // ========================================================================
 
-    /// Insert a device key
-    pub fn insert_device_key(&self, key: &DeviceKey) -> Result<()> {
-        let key_json = serde_json::to_string(key).context("Failed to serialize device key")?;
+    /// Move an installed application into the trash
+    ///
+    /// The application row is moved to `deleted_applications`; configurations
+    /// keyed by the app ID are left in place so a restore brings them back.
+    ///
+    /// Returns `false` if the application is not installed.
+    pub fn trash_application(
+        &self,
+        app_id: &str,
+        deleted_at: u64,
+        purge_after: u64,
+    ) -> Result<bool> {
+        // Suspend the ON DELETE CASCADE from app_configurations while moving
+        // the row. The pragma is a no-op inside a transaction, so it must wrap it.
+        self.conn
+            .execute_batch("PRAGMA foreign_keys = OFF")
+            .context("Failed to disable foreign keys")?;
+
+        let result = self.move_application_to_trash(app_id, deleted_at, purge_after);
 
         self.conn
-            .execute(
-                "INSERT INTO device_keys (device_id, data) VALUES (?1, ?2)",
-                params![key.device_id(), &key_json],
-            )
-            .context("Failed to insert device key")?;
+            .execute_batch("PRAGMA foreign_keys = ON")
+            .context("Failed to re-enable foreign keys")?;
 
-        Ok(())
+        result
     }
 
-    /// Get a device key by device ID
-    pub fn get_device_key(&self, device_id: &str) -> Result<Option<DeviceKey>> {
-        let result = self
+    /// Move the application row into `deleted_applications` in one transaction
+    fn move_application_to_trash(
+        &self,
+        app_id: &str,
+        deleted_at: u64,
+        purge_after: u64,
+    ) -> Result<bool> {
+        let tx = self
             .conn
+            .unchecked_transaction()
+            .context("Failed to begin transaction")?;
+
+        let data: Option<String> = tx
             .query_row(
-                "SELECT data FROM device_keys WHERE device_id = ?1",
-                params![device_id],
-                |row| {
-                    let data: String = row.get(0)?;
-                    let key: DeviceKey = serde_json::from_str(&data)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-                    Ok(key)
-                },
+                "SELECT data FROM applications WHERE id = ?1",
+                params![app_id],
+                |row| row.get(0),
             )
             .optional()
-            .context("Failed to query device key")?;
+            .context("Failed to query application")?;
 
-        Ok(result)
-    }
+        let data = match data {
+            Some(d) => d,
+            None => return Ok(false),
+        };
 
-    /// List all non-revoked device keys
-    pub fn list_active_device_keys(&self) -> Result<Vec<DeviceKey>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT data FROM device_keys")
-            .context("Failed to prepare statement")?;
+        tx.execute(
+            "INSERT INTO deleted_applications (id, data, deleted_at, purge_after)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                data = excluded.data,
+                deleted_at = excluded.deleted_at,
+                purge_after = excluded.purge_after,
+                launcher_positions = NULL",
+            params![app_id, &data, deleted_at as i64, purge_after as i64],
+        )
+        .context("Failed to insert deleted application")?;
 
-        let keys: Vec<DeviceKey> = stmt
-            .query_map([], |row| {
-                let data: String = row.get(0)?;
-                let key: DeviceKey = serde_json::from_str(&data)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-                Ok(key)
-            })
-            .context("Failed to query device keys")?
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to parse device keys")?;
+        tx.execute("DELETE FROM applications WHERE id = ?1", params![app_id])
+            .context("Failed to delete application")?;
 
-        // Filter to only active (non-revoked) keys
-        let active_keys: Vec<DeviceKey> = keys
-            .into_iter()
-            .filter(|k| k.revoked_at().is_none())
-            .collect();
+        tx.commit().context("Failed to commit trash transaction")?;
 
-        Ok(active_keys)
+        Ok(true)
     }
 
-    /// Revoke a device key
-    pub fn revoke_device_key(&self, device_id: &str, revoked_at: i64) -> Result<bool> {
-        // Get the key
-        let mut key = match self.get_device_key(device_id)? {
-            Some(k) => k,
-            None => return Ok(false),
-        };
-
-        // Already revoked?
-        if key.is_revoked() {
-            return Ok(false);
-        }
-
-        // Revoke and update
-        key.revoke_at(revoked_at as u64);
-        let key_json = serde_json::to_string(&key).context("Failed to serialize device key")?;
-
+    /// Remember where a trashed application was on each user's launcher
+    /// grid, by user ID, so a restore can put it back
+    ///
+    /// Returns `false` if the application is not in the trash.
+    pub fn set_trashed_launcher_positions(
+        &self,
+        app_id: &str,
+        positions: &BTreeMap<String, OrderKey>,
+    ) -> Result<bool> {
+        let positions =
+            serde_json::to_string(positions).context("Failed to serialize launcher positions")?;
         let rows_affected = self
             .conn
             .execute(
-                "UPDATE device_keys SET data = ?1 WHERE device_id = ?2",
-                params![&key_json, device_id],
+                "UPDATE deleted_applications SET launcher_positions = ?2 WHERE id = ?1",
+                params![app_id, positions],
             )
-            .context("Failed to revoke device key")?;
+            .context("Failed to save launcher positions")?;
 
         Ok(rows_affected > 0)
     }
 
-    // ========================================================================
-    // Pairing Session Management
-    // ========================================================================
+    /// Move a trashed application back into the installed applications
+    ///
+    /// Returns `false` if the application is not in the trash.
+    pub fn restore_application(&self, app_id: &str) -> Result<bool> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to begin transaction")?;
 
-    /// Insert or update a pairing session
-    pub fn upsert_pairing_session(&self, session: &PairingSession) -> Result<()> {
-        let status_str = match session.status() {
-            PairingStatus::Pending => "pending",
-            PairingStatus::Established => "established",
-            PairingStatus::Failed => "failed",
+        let data: Option<String> = tx
+            .query_row(
+                "SELECT data FROM deleted_applications WHERE id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query deleted application")?;
+
+        let data = match data {
+            Some(d) => d,
+            None => return Ok(false),
         };
 
-        self.conn
-            .execute(
-                "INSERT INTO pairing_sessions
-             (session_id, server_public_key, device_public_key, established_at, expires_at, status)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
-             ON CONFLICT(session_id) DO UPDATE SET
-                status = excluded.status",
-                params![
-                    session.session_id(),
-                    session.server_public_key(),
-                    session.device_public_key(),
-                    session.established_at().unwrap_or(0),
-                    session.expires_at().unwrap_or(0),
-                    status_str,
-                ],
-            )
-            .context("Failed to upsert pairing session")?;
+        tx.execute(
+            "INSERT INTO applications (id, data)
+             VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET
+                data = excluded.data",
+            params![app_id, &data],
+        )
+        .context("Failed to reinstate application")?;
 
-        Ok(())
+        tx.execute(
+            "DELETE FROM deleted_applications WHERE id = ?1",
+            params![app_id],
+        )
+        .context("Failed to remove application from trash")?;
+
+        tx.commit()
+            .context("Failed to commit restore transaction")?;
+
+        Ok(true)
     }
 
-    /// Get a pairing session by ID
-    pub fn get_pairing_session(&self, session_id: &str) -> Result<Option<PairingSession>> {
+    /// Get a trashed application by ID
+    pub fn get_trashed_application(&self, app_id: &str) -> Result<Option<TrashedApplication>> {
         let result = self
             .conn
             .query_row(
-                "SELECT session_id, server_public_key, device_public_key, established_at, expires_at, status
-                 FROM pairing_sessions WHERE session_id = ?1",
-                params![session_id],
-                |row| {
-                    let session_id: String = row.get(0)?;
-                    let server_key: Vec<u8> = row.get(1)?;
-                    let device_key: Vec<u8> = row.get(2)?;
-                    let status_str: String = row.get(5)?;
-
-                    let mut session = PairingSession::new(&session_id, &server_key, &device_key)
-                        .map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-                    // Set status based on string
-                    match status_str.as_str() {
-                        "established" => session.mark_established(),
-                        "failed" => session.mark_failed(),
-                        _ => {} // pending is default
-                    }
-
-                    Ok(session)
-                },
+                "SELECT data, deleted_at, purge_after, launcher_positions FROM deleted_applications
+                 WHERE id = ?1",
+                params![app_id],
+                Self::row_to_trashed_application,
             )
             .optional()
-            .context("Failed to query pairing session")?;
+            .context("Failed to query deleted application")?;
 
         Ok(result)
     }
 
-    /// List pairing sessions by status
-    pub fn list_pairing_sessions_by_status(&self, status: &str) -> Result<Vec<PairingSession>> {
+    /// List all applications currently in the trash
+    pub fn list_trashed_applications(&self) -> Result<Vec<TrashedApplication>> {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT session_id, server_public_key, device_public_key, established_at, expires_at, status
-                 FROM pairing_sessions WHERE status = ?1",
+                "SELECT data, deleted_at, purge_after, launcher_positions FROM deleted_applications
+                 ORDER BY deleted_at DESC",
             )
             .context("Failed to prepare statement")?;
 
-        let sessions = stmt
-            .query_map(params![status], |row| {
-                let session_id: String = row.get(0)?;
-                let server_key: Vec<u8> = row.get(1)?;
-                let device_key: Vec<u8> = row.get(2)?;
-                let status_str: String = row.get(5)?;
-
-                let mut session = PairingSession::new(&session_id, &server_key, &device_key)
-                    .map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-                // Set status based on string
-                match status_str.as_str() {
-                    "established" => session.mark_established(),
-                    "failed" => session.mark_failed(),
-                    _ => {} // pending is default
-                }
-
-                Ok(session)
-            })
-            .context("Failed to query pairing sessions")?
+        let apps = stmt
+            .query_map([], Self::row_to_trashed_application)
+            .context("Failed to query deleted applications")?
             .collect::<Result<Vec<_>, _>>()
-            .context("Failed to parse pairing sessions")?;
+            .context("Failed to parse deleted applications")?;
 
-        Ok(sessions)
+        Ok(apps)
+    }
+
+    /// Permanently remove a trashed application
+    ///
+    /// Uses the same cascade as a hard uninstall: the trash entry, all
+    /// configurations, the update history, remembered permission
+    /// decisions, and data sharing grants for the app are deleted.
+    pub fn purge_trashed_application(&self, app_id: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM deleted_applications WHERE id = ?1",
+                params![app_id],
+            )
+            .context("Failed to purge deleted application")?;
+
+        self.delete_app_configs_for_app(app_id)?;
+        self.delete_app_update_history(app_id)?;
+        self.delete_permission_grants_for_app(app_id)?;
+        self.delete_shared_data_grants_for_app(app_id)?;
+        self.delete_app_consents(app_id)?;
+        self.delete_reproducibility_checks(app_id)?;
+        self.delete_network_observations(app_id)?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Convert a `deleted_applications` row into a TrashedApplication
+    fn row_to_trashed_application(row: &rusqlite::Row<'_>) -> rusqlite::Result<TrashedApplication> {
+        let data: String = row.get(0)?;
+        let deleted_at: i64 = row.get(1)?;
+        let purge_after: i64 = row.get(2)?;
+        let positions: Option<String> = row.get(3)?;
+        let app: OsnovaApplication = serde_json::from_str(&data)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let positions = positions
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .unwrap_or_default();
+        Ok(TrashedApplication::new(app, deleted_at as u64, purge_after as u64)
+            .with_launcher_positions(positions))
     }
 
     // ========================================================================
-    // App Configuration (Encrypted)
+    // App Version History and Pending Updates
+    // ========================================================================
+
+    /// Record the version an update replaced, keeping the newest `keep` entries
+    pub fn push_app_version(
+        &self,
+        app: &OsnovaApplication,
+        replaced_at: u64,
+        keep: usize,
+    ) -> Result<()> {
+        let app_json = serde_json::to_string(app).context("Failed to serialize application")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO app_versions (app_id, replaced_at, data) VALUES (?1, ?2, ?3)",
+                params![app.id(), replaced_at as i64, &app_json],
+            )
+            .context("Failed to insert app version")?;
+
+        self.conn
+            .execute(
+                "DELETE FROM app_versions WHERE app_id = ?1 AND id NOT IN (
+                    SELECT id FROM app_versions WHERE app_id = ?1 ORDER BY id DESC LIMIT ?2
+                 )",
+                params![app.id(), keep as i64],
+            )
+            .context("Failed to prune app versions")?;
+
+        Ok(())
+    }
+
+    /// List previous versions of an application, newest first
+    ///
+    /// Returns `(id, application, replaced_at)` tuples.
+    pub fn list_app_versions(&self, app_id: &str) -> Result<Vec<(i64, OsnovaApplication, u64)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, data, replaced_at FROM app_versions
+                 WHERE app_id = ?1 ORDER BY id DESC",
+            )
+            .context("Failed to prepare statement")?;
+
+        let versions = stmt
+            .query_map(params![app_id], |row| {
+                let id: i64 = row.get(0)?;
+                let data: String = row.get(1)?;
+                let replaced_at: i64 = row.get(2)?;
+                let app: OsnovaApplication = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok((id, app, replaced_at as u64))
+            })
+            .context("Failed to query app versions")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse app versions")?;
+
+        Ok(versions)
+    }
+
+    /// Delete one entry from an application's version history
+    pub fn delete_app_version(&self, id: i64) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM app_versions WHERE id = ?1", params![id])
+            .context("Failed to delete app version")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Set or clear the downloaded update waiting to be applied
+    pub fn set_pending_update(
+        &self,
+        app_id: &str,
+        update: Option<&OsnovaApplication>,
+    ) -> Result<()> {
+        let update_json = update
+            .map(serde_json::to_string)
+            .transpose()
+            .context("Failed to serialize application")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO app_updates (app_id, pending) VALUES (?1, ?2)
+                 ON CONFLICT(app_id) DO UPDATE SET pending = excluded.pending",
+                params![app_id, update_json],
+            )
+            .context("Failed to upsert pending update")?;
+
+        Ok(())
+    }
+
+    /// Get the downloaded update waiting to be applied
+    pub fn get_pending_update(&self, app_id: &str) -> Result<Option<OsnovaApplication>> {
+        let data: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT pending FROM app_updates WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query pending update")?;
+
+        data.flatten()
+            .map(|data| serde_json::from_str(&data).context("Failed to parse pending update"))
+            .transpose()
+    }
+
+    /// Set or clear the version updates should skip (after a rollback)
+    pub fn set_skipped_update(&self, app_id: &str, version: Option<&str>) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO app_updates (app_id, skipped_version) VALUES (?1, ?2)
+                 ON CONFLICT(app_id) DO UPDATE SET skipped_version = excluded.skipped_version",
+                params![app_id, version],
+            )
+            .context("Failed to upsert skipped update")?;
+
+        Ok(())
+    }
+
+    /// Get the version updates should skip
+    pub fn get_skipped_update(&self, app_id: &str) -> Result<Option<String>> {
+        let version: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT skipped_version FROM app_updates WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query skipped update")?;
+
+        Ok(version.flatten())
+    }
+
+    /// Delete the version history and update state of an application
+    ///
+    /// Needed for trashed apps, which are no longer reached by the cascade
+    /// from `applications`.
+    pub fn delete_app_update_history(&self, app_id: &str) -> Result<usize> {
+        let versions = self
+            .conn
+            .execute(
+                "DELETE FROM app_versions WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete app versions")?;
+        self.conn
+            .execute("DELETE FROM app_updates WHERE app_id = ?1", params![app_id])
+            .context("Failed to delete app update state")?;
+
+        Ok(versions)
+    }
+
+    // ========================================================================
+    // Device Key Management
+    // ========================================================================
+
+    /// Insert a device key
+    pub fn insert_device_key(&self, key: &DeviceKey) -> Result<()> {
+        let key_json = serde_json::to_string(key).context("Failed to serialize device key")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO device_keys (device_id, data) VALUES (?1, ?2)",
+                params![key.device_id(), &key_json],
+            )
+            .context("Failed to insert device key")?;
+
+        Ok(())
+    }
+
+    /// Get a device key by device ID
+    pub fn get_device_key(&self, device_id: &str) -> Result<Option<DeviceKey>> {
+        let result = self
+            .conn
+            .query_row(
+                "SELECT data FROM device_keys WHERE device_id = ?1",
+                params![device_id],
+                |row| {
+                    let data: String = row.get(0)?;
+                    let key: DeviceKey = serde_json::from_str(&data)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                    Ok(key)
+                },
+            )
+            .optional()
+            .context("Failed to query device key")?;
+
+        Ok(result)
+    }
+
+    /// List all non-revoked device keys
+    pub fn list_active_device_keys(&self) -> Result<Vec<DeviceKey>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM device_keys")
+            .context("Failed to prepare statement")?;
+
+        let keys: Vec<DeviceKey> = stmt
+            .query_map([], |row| {
+                let data: String = row.get(0)?;
+                let key: DeviceKey = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(key)
+            })
+            .context("Failed to query device keys")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse device keys")?;
+
+        // Filter to only active (non-revoked) keys
+        let active_keys: Vec<DeviceKey> = keys
+            .into_iter()
+            .filter(|k| k.revoked_at().is_none())
+            .collect();
+
+        Ok(active_keys)
+    }
+
+    /// Revoke a device key
+    pub fn revoke_device_key(&self, device_id: &str, revoked_at: i64) -> Result<bool> {
+        // Get the key
+        let mut key = match self.get_device_key(device_id)? {
+            Some(k) => k,
+            None => return Ok(false),
+        };
+
+        // Already revoked?
+        if key.is_revoked() {
+            return Ok(false);
+        }
+
+        // Revoke and update
+        key.revoke_at(revoked_at as u64);
+        let key_json = serde_json::to_string(&key).context("Failed to serialize device key")?;
+
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE device_keys SET data = ?1 WHERE device_id = ?2",
+                params![&key_json, device_id],
+            )
+            .context("Failed to revoke device key")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Rebuild a pairing_sessions table created before the confirmation
+    /// code step, whose status check rejects `awaiting_confirmation`
+    fn widen_pairing_sessions(&self) -> Result<()> {
+        let table_sql: String = self
+            .conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'pairing_sessions'",
+                [],
+                |row| row.get(0),
+            )
+            .context("Failed to inspect pairing_sessions")?;
+        if table_sql.contains("awaiting_confirmation") {
+            return Ok(());
+        }
+
+        self.conn
+            .execute_batch(
+                r#"
+            BEGIN;
+            ALTER TABLE pairing_sessions RENAME TO pairing_sessions_old;
+            CREATE TABLE pairing_sessions (
+                session_id TEXT PRIMARY KEY,
+                server_public_key BLOB NOT NULL,
+                device_public_key BLOB NOT NULL,
+                established_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL,
+                status TEXT NOT NULL CHECK(status IN
+                    ('pending', 'awaiting_confirmation', 'established', 'failed')),
+                confirmation TEXT
+            );
+            INSERT INTO pairing_sessions
+                (session_id, server_public_key, device_public_key, established_at, expires_at, status)
+                SELECT session_id, server_public_key, device_public_key, established_at,
+                       expires_at, status
+                FROM pairing_sessions_old;
+            DROP TABLE pairing_sessions_old;
+            CREATE INDEX IF NOT EXISTS idx_pairing_sessions_status
+                ON pairing_sessions(status);
+            COMMIT;
+            "#,
+            )
+            .context("Failed to widen pairing_sessions")?;
+
+        Ok(())
+    }
+
     // ========================================================================
+    // Pairing Session Management
+    // ========================================================================
+
+    /// Insert or update a pairing session
+    pub fn upsert_pairing_session(&self, session: &PairingSession) -> Result<()> {
+        let confirmation = session
+            .confirmation()
+            .map(serde_json::to_string)
+            .transpose()
+            .context("Failed to serialize pairing confirmation")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO pairing_sessions
+             (session_id, server_public_key, device_public_key, established_at, expires_at, status,
+              confirmation)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(session_id) DO UPDATE SET
+                established_at = excluded.established_at,
+                status = excluded.status,
+                confirmation = excluded.confirmation",
+                params![
+                    session.session_id(),
+                    session.server_public_key(),
+                    session.device_public_key(),
+                    session.established_at().unwrap_or(0),
+                    session.expires_at().unwrap_or(0),
+                    session.status().as_str(),
+                    confirmation,
+                ],
+            )
+            .context("Failed to upsert pairing session")?;
+
+        Ok(())
+    }
+
+    /// Map a pairing_sessions row selected with [`PAIRING_SESSION_COLUMNS`]
+    fn pairing_session_from_row(row: &rusqlite::Row) -> rusqlite::Result<PairingSession> {
+        let session_id: String = row.get(0)?;
+        let server_key: Vec<u8> = row.get(1)?;
+        let device_key: Vec<u8> = row.get(2)?;
+        let established_at: u64 = row.get(3)?;
+        let expires_at: u64 = row.get(4)?;
+        let status: PairingStatus = row
+            .get::<_, String>(5)?
+            .parse()
+            .map_err(|_| rusqlite::Error::InvalidQuery)?;
+        let confirmation = row
+            .get::<_, Option<String>>(6)?
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|_| rusqlite::Error::InvalidQuery)?;
+
+        let session = PairingSession::new(&session_id, &server_key, &device_key)
+            .map_err(|_| rusqlite::Error::InvalidQuery)?;
+        Ok(session.restored(
+            status,
+            Some(established_at).filter(|&at| at != 0),
+            Some(expires_at).filter(|&at| at != 0),
+            confirmation,
+        ))
+    }
+
+    /// Get a pairing session by ID
+    pub fn get_pairing_session(&self, session_id: &str) -> Result<Option<PairingSession>> {
+        let result = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM pairing_sessions WHERE session_id = ?1",
+                    PAIRING_SESSION_COLUMNS
+                ),
+                params![session_id],
+                Self::pairing_session_from_row,
+            )
+            .optional()
+            .context("Failed to query pairing session")?;
+
+        Ok(result)
+    }
+
+    /// List pairing sessions by status
+    pub fn list_pairing_sessions_by_status(&self, status: &str) -> Result<Vec<PairingSession>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM pairing_sessions WHERE status = ?1",
+                PAIRING_SESSION_COLUMNS
+            ))
+            .context("Failed to prepare statement")?;
+
+        let sessions = stmt
+            .query_map(params![status], Self::pairing_session_from_row)
+            .context("Failed to query pairing sessions")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse pairing sessions")?;
+
+        Ok(sessions)
+    }
+
+    // ========================================================================
+    // App Configuration (Encrypted)
+    // ========================================================================
+
+    /// Set app configuration (encrypted at rest)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encryption or database write fails
+    pub fn set_app_config(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        config: &AppConfiguration,
+        encryption_key: &[u8; 32],
+    ) -> Result<()> {
+        let config_json = serde_json::to_vec(config).context("Failed to serialize config")?;
+        let encryption = CocoonEncryption::new(encryption_key);
+        let encrypted = encryption
+            .encrypt(&config_json)
+            .context("Failed to encrypt config")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO app_configurations (app_id, user_id, settings_encrypted, updated_at)
+             VALUES (?1, ?2, ?3, strftime('%s', 'now'))
+             ON CONFLICT(app_id, user_id) DO UPDATE SET
+                settings_encrypted = excluded.settings_encrypted,
+                updated_at = excluded.updated_at",
+                params![app_id, user_id, &encrypted],
+            )
+            .context("Failed to upsert app configuration")?;
+
+        Ok(())
+    }
+
+    /// Get app configuration (decrypted)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption fails or configuration doesn't exist
+    pub fn get_app_config(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        encryption_key: &[u8; 32],
+    ) -> Result<Option<AppConfiguration>> {
+        let encrypted: Option<(Vec<u8>, u64)> = self
+            .conn
+            .query_row(
+                "SELECT settings_encrypted, updated_at FROM app_configurations
+                 WHERE app_id = ?1 AND user_id = ?2",
+                params![app_id, user_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .context("Failed to query app configuration")?;
+
+        match encrypted {
+            Some((data, updated_at)) => {
+                let decrypted = decrypt_row(
+                    DecryptLocation::row("app_configurations", format!("{}/{}", app_id, user_id)),
+                    &data,
+                    encryption_key,
+                    None,
+                    None,
+                    Some(updated_at),
+                )?;
+                let config: AppConfiguration =
+                    serde_json::from_slice(&decrypted).context("Failed to deserialize config")?;
+                Ok(Some(config))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete app configuration
+    pub fn delete_app_config(&self, app_id: &str, user_id: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM app_configurations WHERE app_id = ?1 AND user_id = ?2",
+                params![app_id, user_id],
+            )
+            .context("Failed to delete app configuration")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// List the app IDs that have a stored configuration for a user
+    pub fn list_app_config_ids(&self, user_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT app_id FROM app_configurations WHERE user_id = ?1 ORDER BY app_id")
+            .context("Failed to prepare statement")?;
+
+        let ids = stmt
+            .query_map(params![user_id], |row| row.get(0))
+            .context("Failed to query app configurations")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse app configurations")?;
+
+        Ok(ids)
+    }
+
+    /// List the users that have a stored configuration for an app
+    pub fn list_app_config_users(&self, app_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT user_id FROM app_configurations WHERE app_id = ?1 ORDER BY user_id")
+            .context("Failed to prepare statement")?;
+
+        let users = stmt
+            .query_map(params![app_id], |row| row.get(0))
+            .context("Failed to query app configurations")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse app configurations")?;
+
+        Ok(users)
+    }
+
+    /// Delete all configurations for an app (all users)
+    ///
+    /// Needed for trashed apps, whose configurations are no longer reached by
+    /// the cascade from `applications`. Their snapshots go too. Returns the
+    /// number of configurations removed.
+    pub fn delete_app_configs_for_app(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM app_configurations WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete app configurations")?;
+        self.conn
+            .execute(
+                "DELETE FROM config_snapshots WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete configuration snapshots")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Configuration Snapshots
+    // ========================================================================
+
+    /// Store a snapshot of an app configuration, encrypted under
+    /// `encryption_key`, returning its ID
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_config_snapshot(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        created_at: u64,
+        version: u64,
+        reason: SnapshotReason,
+        content: &SnapshotContent,
+        encryption_key: &[u8; 32],
+    ) -> Result<i64> {
+        let encrypted = encrypt_snapshot(content, encryption_key)?;
+        self.conn
+            .execute(
+                "INSERT INTO config_snapshots
+                (app_id, user_id, created_at, version, reason, content_encrypted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    app_id,
+                    user_id,
+                    created_at as i64,
+                    version as i64,
+                    reason.as_str(),
+                    &encrypted
+                ],
+            )
+            .context("Failed to insert configuration snapshot")?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Replace the stored content of a snapshot
+    pub fn update_config_snapshot(
+        &self,
+        id: i64,
+        content: &SnapshotContent,
+        encryption_key: &[u8; 32],
+    ) -> Result<()> {
+        let encrypted = encrypt_snapshot(content, encryption_key)?;
+        self.conn
+            .execute(
+                "UPDATE config_snapshots SET content_encrypted = ?1 WHERE id = ?2",
+                params![&encrypted, id],
+            )
+            .context("Failed to update configuration snapshot")?;
+        Ok(())
+    }
+
+    /// Delete snapshots by ID
+    ///
+    /// Runs without a transaction of its own, so it can be part of a
+    /// configuration write.
+    pub fn delete_config_snapshots(&self, ids: &[i64]) -> Result<usize> {
+        let mut removed = 0;
+        for id in ids {
+            removed += self
+                .conn
+                .execute("DELETE FROM config_snapshots WHERE id = ?1", params![id])
+                .context("Failed to delete configuration snapshot")?;
+        }
+        Ok(removed)
+    }
+
+    /// Snapshots of an app configuration, oldest first
+    pub fn list_config_snapshots(&self, app_id: &str, user_id: &str) -> Result<Vec<ConfigSnapshot>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, created_at, version, reason, LENGTH(content_encrypted)
+                 FROM config_snapshots WHERE app_id = ?1 AND user_id = ?2 ORDER BY id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(params![app_id, user_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })
+            .context("Failed to query configuration snapshots")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read configuration snapshots")?;
+
+        rows.into_iter()
+            .map(|(id, created_at, version, reason, bytes)| {
+                Ok(ConfigSnapshot {
+                    id,
+                    app_id: app_id.to_string(),
+                    user_id: user_id.to_string(),
+                    created_at: created_at as u64,
+                    version: version as u64,
+                    reason: SnapshotReason::parse(&reason)
+                        .with_context(|| format!("Unknown snapshot reason {}", reason))?,
+                    bytes: bytes as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// Decrypted contents of the snapshots of an app configuration by ID,
+    /// oldest first
+    pub fn config_snapshot_contents(
+        &self,
+        app_id: &str,
+        user_id: &str,
+        encryption_key: &[u8; 32],
+    ) -> Result<Vec<(i64, SnapshotContent)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, content_encrypted, created_at FROM config_snapshots
+                 WHERE app_id = ?1 AND user_id = ?2 ORDER BY id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(params![app_id, user_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Vec<u8>>(1)?,
+                    row.get::<_, u64>(2)?,
+                ))
+            })
+            .context("Failed to query configuration snapshots")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read configuration snapshots")?;
+
+        rows.into_iter()
+            .map(|(id, encrypted, created_at)| {
+                let decrypted = decrypt_row(
+                    DecryptLocation::row("config_snapshots", id.to_string()),
+                    &encrypted,
+                    encryption_key,
+                    None,
+                    None,
+                    Some(created_at),
+                )?;
+                let content = serde_json::from_slice(&decrypted)
+                    .context("Failed to deserialize configuration snapshot")?;
+                Ok((id, content))
+            })
+            .collect()
+    }
+
+    /// Bytes of configuration snapshots stored per app for a user
+    pub fn config_snapshot_usage(&self, user_id: &str) -> Result<BTreeMap<String, u64>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT app_id, SUM(LENGTH(content_encrypted)) FROM config_snapshots
+                 WHERE user_id = ?1 GROUP BY app_id ORDER BY app_id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let usage = stmt
+            .query_map(params![user_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .context("Failed to query configuration snapshots")?
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .context("Failed to read configuration snapshots")?;
+
+        Ok(usage)
+    }
+
+    // ========================================================================
+    // Encrypted Blob Storage
+    // ========================================================================
+
+    /// Store an encrypted blob
+    ///
+    /// The plaintext is compressed before encryption when worthwhile; the
+    /// compression header travels inside the ciphertext. A blob replaced
+    /// this way keeps its data class.
+    pub fn set_encrypted_blob(
+        &self,
+        key: &str,
+        value: &[u8],
+        encryption_key: &[u8; 32],
+    ) -> Result<()> {
+        self.upsert_encrypted_blob(key, value, encryption_key, None)
+    }
+
+    /// Store an encrypted blob tagged with its data class
+    ///
+    /// The class decides how the blob may be handled (see
+    /// [`crate::storage::classification`]).
+    pub fn set_encrypted_blob_classified(
+        &self,
+        key: &str,
+        value: &[u8],
+        encryption_key: &[u8; 32],
+        class: DataClass,
+    ) -> Result<()> {
+        self.upsert_encrypted_blob(key, value, encryption_key, Some(class))
+    }
+
+    fn upsert_encrypted_blob(
+        &self,
+        key: &str,
+        value: &[u8],
+        encryption_key: &[u8; 32],
+        class: Option<DataClass>,
+    ) -> Result<()> {
+        let encryption = CocoonEncryption::new(encryption_key);
+        let encrypted = encryption
+            .encrypt(&self.compression.encode(value))
+            .context("Failed to encrypt blob")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO encrypted_blobs (key, value_encrypted, data_class, updated_at)
+             VALUES (?1, ?2, ?3, strftime('%s', 'now'))
+             ON CONFLICT(key) DO UPDATE SET
+                value_encrypted = excluded.value_encrypted,
+                data_class = COALESCE(excluded.data_class, encrypted_blobs.data_class),
+                updated_at = excluded.updated_at",
+                params![key, &encrypted, class.map(DataClass::as_str)],
+            )
+            .context("Failed to upsert encrypted blob")?;
+
+        Ok(())
+    }
+
+    /// Data class of a blob; `None` if it is missing or unclassified
+    pub fn encrypted_blob_class(&self, key: &str) -> Result<Option<DataClass>> {
+        Ok(self.stored_blob_class(key)?.flatten())
+    }
+
+    /// Class of a stored blob; `None` if there is no such blob
+    fn stored_blob_class(&self, key: &str) -> Result<Option<Option<DataClass>>> {
+        let class: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT data_class FROM encrypted_blobs WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query blob class")?;
+        class
+            .map(|class| class.map(|class| class.parse()).transpose())
+            .transpose()
+    }
+
+    /// Keys of the blobs whose class permits `purpose`
+    ///
+    /// Unclassified blobs are never included.
+    pub fn encrypted_blob_keys_for(&self, purpose: Purpose) -> Result<Vec<String>> {
+        Ok(self
+            .encrypted_blob_classes()?
+            .into_iter()
+            .filter(|(_, class)| class.is_some_and(|class| class.permits(purpose)))
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Retrieve and decrypt a blob for `purpose`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob is unclassified or its class does not
+    /// permit `purpose`
+    pub fn get_encrypted_blob_for(
+        &self,
+        key: &str,
+        encryption_key: &[u8; 32],
+        purpose: Purpose,
+    ) -> Result<Option<Vec<u8>>> {
+        if let Some(class) = self.stored_blob_class(key)? {
+            classification::check(&format!("Blob {}", key), class, purpose)?;
+        }
+        self.get_encrypted_blob(key, encryption_key)
+    }
+
+    /// Keys of blobs stored without a data class
+    pub fn list_unclassified_blobs(&self) -> Result<Vec<String>> {
+        Ok(self
+            .encrypted_blob_classes()?
+            .into_iter()
+            .filter(|(_, class)| class.is_none())
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Every blob key with its class, sorted by key
+    fn encrypted_blob_classes(&self) -> Result<Vec<(String, Option<DataClass>)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, data_class FROM encrypted_blobs ORDER BY key")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to list blob classes")?;
+        rows.into_iter()
+            .map(|(key, class)| Ok((key, class.map(|class| class.parse()).transpose()?)))
+            .collect()
+    }
+
+    /// Retrieve and decrypt a blob
+    pub fn get_encrypted_blob(
+        &self,
+        key: &str,
+        encryption_key: &[u8; 32],
+    ) -> Result<Option<Vec<u8>>> {
+        let encrypted: Option<(Vec<u8>, Option<String>, u64)> = self
+            .conn
+            .query_row(
+                "SELECT value_encrypted, data_class, updated_at FROM encrypted_blobs
+                 WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .context("Failed to query encrypted blob")?;
+
+        match encrypted {
+            Some((data, class, updated_at)) => {
+                let class = class.and_then(|class| class.parse().ok());
+                let decrypted = decrypt_row(
+                    DecryptLocation::row("encrypted_blobs", key),
+                    &data,
+                    encryption_key,
+                    class,
+                    Some(KeySource::CallerSupplied),
+                    Some(updated_at),
+                )?;
+                Ok(Some(compression::decode(&decrypted)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete an encrypted blob
+    pub fn delete_encrypted_blob(&self, key: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM encrypted_blobs WHERE key = ?1", params![key])
+            .context("Failed to delete encrypted blob")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Count stored encrypted blobs
+    pub fn count_encrypted_blobs(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM encrypted_blobs", [], |row| row.get(0))
+            .context("Failed to count encrypted blobs")?;
+
+        Ok(count as usize)
+    }
+
+    // ========================================================================
+    // Bandwidth Usage
+    // ========================================================================
+
+    /// Add transferred bytes to the usage counter for a day and category
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - Day index (days since the Unix epoch, UTC)
+    /// * `category` - Transfer category identifier
+    /// * `bytes` - Bytes to add
+    pub fn add_bandwidth_usage(&self, day: u64, category: &str, bytes: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO bandwidth_usage (day, category, bytes)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(day, category) DO UPDATE SET
+                bytes = bytes + excluded.bytes",
+                params![day as i64, category, bytes as i64],
+            )
+            .context("Failed to record bandwidth usage")?;
+
+        Ok(())
+    }
+
+    /// Get usage rows `(day, category, bytes)` for an inclusive day range
+    pub fn get_bandwidth_usage(
+        &self,
+        from_day: u64,
+        to_day: u64,
+    ) -> Result<Vec<(u64, String, u64)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT day, category, bytes FROM bandwidth_usage
+                 WHERE day >= ?1 AND day <= ?2
+                 ORDER BY day",
+            )
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(params![from_day as i64, to_day as i64], |row| {
+                let day: i64 = row.get(0)?;
+                let category: String = row.get(1)?;
+                let bytes: i64 = row.get(2)?;
+                Ok((day as u64, category, bytes as u64))
+            })
+            .context("Failed to query bandwidth usage")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse bandwidth usage")?;
+
+        Ok(rows)
+    }
+
+    // ========================================================================
+    // App Launch Counts
+    // ========================================================================
+
+    /// Count a launch of an application at Unix time `at`
+    pub fn record_app_launch(&self, app_id: &str, at: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO app_launches (app_id, launch_count, last_launched_at)
+             VALUES (?1, 1, ?2)
+             ON CONFLICT(app_id) DO UPDATE SET
+                launch_count = launch_count + 1,
+                last_launched_at = excluded.last_launched_at",
+                params![app_id, at],
+            )
+            .context("Failed to record app launch")?;
+
+        Ok(())
+    }
+
+    /// Number of recorded launches of an application
+    pub fn app_launch_count(&self, app_id: &str) -> Result<u64> {
+        let count = self
+            .conn
+            .query_row(
+                "SELECT launch_count FROM app_launches WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query app launches")?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Installed applications launched most often, at most `limit`
+    ///
+    /// Ties go to the app launched most recently.
+    pub fn most_launched_apps(&self, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT l.app_id FROM app_launches l
+                 JOIN applications a ON a.id = l.app_id
+                 ORDER BY l.launch_count DESC, l.last_launched_at DESC, l.app_id
+                 LIMIT ?1",
+            )
+            .context("Failed to prepare statement")?;
+
+        let apps = stmt
+            .query_map(params![limit as i64], |row| row.get(0))
+            .context("Failed to query app launches")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse app launches")?;
+
+        Ok(apps)
+    }
+
+    /// Decayed launch score of an app in a weekly time slot, with the Unix
+    /// time it was last updated
+    pub fn get_launch_score(&self, app_id: &str, slot: u32) -> Result<Option<(f64, u64)>> {
+        self.conn
+            .query_row(
+                "SELECT score, updated_at FROM launch_scores WHERE app_id = ?1 AND slot = ?2",
+                params![app_id, slot],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .context("Failed to query launch score")
+    }
+
+    /// Store the launch score of an app in a weekly time slot
+    pub fn set_launch_score(&self, app_id: &str, slot: u32, score: f64, at: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO launch_scores (app_id, slot, score, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(app_id, slot) DO UPDATE SET
+                score = excluded.score,
+                updated_at = excluded.updated_at",
+                params![app_id, slot, score, at],
+            )
+            .context("Failed to store launch score")?;
+
+        Ok(())
+    }
+
+    /// Launch scores of installed applications in the given weekly slots,
+    /// as `(app_id, slot, score, updated_at)`
+    pub fn list_launch_scores(&self, slots: &[u32]) -> Result<Vec<(String, u32, f64, u64)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT s.app_id, s.slot, s.score, s.updated_at FROM launch_scores s
+                 JOIN applications a ON a.id = s.app_id
+                 ORDER BY s.app_id, s.slot",
+            )
+            .context("Failed to prepare statement")?;
+
+        let scores = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .context("Failed to query launch scores")?
+            .collect::<Result<Vec<(String, u32, f64, u64)>, _>>()
+            .context("Failed to parse launch scores")?;
+
+        Ok(scores
+            .into_iter()
+            .filter(|(_, slot, _, _)| slots.contains(slot))
+            .collect())
+    }
+
+    /// Forget all launch counts and scores
+    pub fn clear_app_launches(&self) -> Result<()> {
+        self.conn
+            .execute_batch("DELETE FROM app_launches; DELETE FROM launch_scores;")
+            .context("Failed to clear app launches")?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Task History
+    // ========================================================================
+
+    /// Record a finished task, keeping only the newest `keep` tasks
+    pub fn record_task(&self, task: &TaskInfo, keep: usize) -> Result<()> {
+        let data = serde_json::to_string(task).context("Failed to serialize task")?;
+        let finished_at = task.finished_at.unwrap_or(task.started_at);
+
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO task_history (id, finished_at, data) VALUES (?1, ?2, ?3)",
+                params![task.id, finished_at as i64, data],
+            )
+            .context("Failed to record task")?;
+        self.conn
+            .execute(
+                "DELETE FROM task_history WHERE id NOT IN (
+                     SELECT id FROM task_history ORDER BY finished_at DESC, rowid DESC LIMIT ?1
+                 )",
+                params![keep as i64],
+            )
+            .context("Failed to prune task history")?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` finished tasks, newest first
+    pub fn list_task_history(&self, limit: usize) -> Result<Vec<TaskInfo>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM task_history ORDER BY finished_at DESC, rowid DESC LIMIT ?1")
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(params![limit as i64], |row| row.get::<_, String>(0))
+            .context("Failed to query task history")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read task history")?;
+
+        rows.iter()
+            .map(|data| serde_json::from_str(data).context("Failed to parse task"))
+            .collect()
+    }
+
+    // ========================================================================
+    // Backend Process Registry
+    // ========================================================================
+
+    /// Record a spawned backend process
+    ///
+    /// Replaces any stale entry with the same pid.
+    pub fn register_backend_process(&self, process: &BackendProcess) -> Result<()> {
+        let process_json =
+            serde_json::to_string(process).context("Failed to serialize backend process")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO backend_processes (pid, app_id, data)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(pid) DO UPDATE SET
+                app_id = excluded.app_id,
+                data = excluded.data",
+                params![process.pid(), process.app_id(), &process_json],
+            )
+            .context("Failed to register backend process")?;
+
+        Ok(())
+    }
+
+    /// Remove a backend process entry by pid
+    pub fn remove_backend_process(&self, pid: u32) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM backend_processes WHERE pid = ?1", params![pid])
+            .context("Failed to remove backend process")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// List all registered backend processes
+    pub fn list_backend_processes(&self) -> Result<Vec<BackendProcess>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM backend_processes ORDER BY pid")
+            .context("Failed to prepare statement")?;
+
+        let processes = stmt
+            .query_map([], |row| {
+                let data: String = row.get(0)?;
+                let process: BackendProcess = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(process)
+            })
+            .context("Failed to query backend processes")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse backend processes")?;
+
+        Ok(processes)
+    }
+
+    // ========================================================================
+    // Crash Reports
+    // ========================================================================
+
+    /// Store a crash report
+    pub fn insert_crash_report(&self, report: &CrashReport) -> Result<()> {
+        let report_json =
+            serde_json::to_string(report).context("Failed to serialize crash report")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO crash_reports (id, app_id, crashed_at, data)
+             VALUES (?1, ?2, ?3, ?4)",
+                params![&report.id, &report.app_id, report.crashed_at, &report_json],
+            )
+            .context("Failed to insert crash report")?;
+
+        Ok(())
+    }
+
+    /// Get a crash report by ID
+    pub fn get_crash_report(&self, report_id: &str) -> Result<Option<CrashReport>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM crash_reports WHERE id = ?1",
+                params![report_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to get crash report")?;
+
+        data.map(|data| serde_json::from_str(&data).context("Failed to parse crash report"))
+            .transpose()
+    }
+
+    /// List an owner's crash reports, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `app_id` - Process registry owner the reports are keyed by
+    pub fn list_crash_reports(&self, app_id: &str) -> Result<Vec<CrashReport>> {
+        self.query_crash_reports(
+            "SELECT data FROM crash_reports WHERE app_id = ?1
+             ORDER BY crashed_at DESC, rowid DESC",
+            params![app_id],
+        )
+    }
+
+    /// List the most recent crash reports of every app, newest first
+    pub fn list_recent_crash_reports(&self, limit: usize) -> Result<Vec<CrashReport>> {
+        self.query_crash_reports(
+            "SELECT data FROM crash_reports ORDER BY crashed_at DESC, rowid DESC LIMIT ?1",
+            params![limit as i64],
+        )
+    }
+
+    /// Delete all but an owner's `keep` newest crash reports
+    pub fn prune_crash_reports(&self, app_id: &str, keep: usize) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM crash_reports WHERE app_id = ?1 AND id NOT IN (
+                    SELECT id FROM crash_reports WHERE app_id = ?1
+                    ORDER BY crashed_at DESC, rowid DESC LIMIT ?2
+                )",
+                params![app_id, keep as i64],
+            )
+            .context("Failed to prune crash reports")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Record an owner's newest crash report as seen, e.g. after a
+    /// successful relaunch
+    pub fn acknowledge_crash(&self, app_id: &str, report_id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO crash_acknowledgements (app_id, report_id) VALUES (?1, ?2)
+                 ON CONFLICT(app_id) DO UPDATE SET report_id = excluded.report_id",
+                params![app_id, report_id],
+            )
+            .context("Failed to acknowledge crash")?;
+
+        Ok(())
+    }
+
+    /// Get the crash report of an owner last recorded as seen
+    pub fn get_acknowledged_crash(&self, app_id: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT report_id FROM crash_acknowledgements WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query crash acknowledgement")
+    }
+
+    // ========================================================================
+    // Backend Health
+    // ========================================================================
+
+    /// Append a backend health transition to its app's history, keeping
+    /// the newest `keep`
+    pub fn record_health_transition(
+        &self,
+        transition: &HealthTransition,
+        keep: usize,
+    ) -> Result<()> {
+        let data =
+            serde_json::to_string(transition).context("Failed to serialize health transition")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO health_transitions (app_id, at, data) VALUES (?1, ?2, ?3)",
+                params![&transition.app_id, transition.at, &data],
+            )
+            .context("Failed to insert health transition")?;
+        self.conn
+            .execute(
+                "DELETE FROM health_transitions WHERE app_id = ?1 AND id NOT IN (
+                    SELECT id FROM health_transitions WHERE app_id = ?1
+                    ORDER BY id DESC LIMIT ?2
+                )",
+                params![&transition.app_id, keep as i64],
+            )
+            .context("Failed to prune health transitions")?;
+
+        Ok(())
+    }
+
+    /// List an app's health transitions, newest first
+    pub fn list_health_transitions(&self, app_id: &str) -> Result<Vec<HealthTransition>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM health_transitions WHERE app_id = ?1 ORDER BY id DESC")
+            .context("Failed to prepare statement")?;
+
+        let transitions = stmt
+            .query_map(params![app_id], |row| {
+                let data: String = row.get(0)?;
+                serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+            })
+            .context("Failed to query health transitions")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse health transitions")?;
+
+        Ok(transitions)
+    }
+
+    // ========================================================================
+    // Annotations
+    // ========================================================================
+
+    /// Store a user's note on a subject, encrypted under `encryption_key`
+    pub fn set_annotation(
+        &self,
+        user_id: &str,
+        annotation: &Annotation,
+        encryption_key: &[u8; 32],
+    ) -> Result<()> {
+        let encryption = CocoonEncryption::new(encryption_key);
+        let encrypted = encryption
+            .encrypt(annotation.text.as_bytes())
+            .context("Failed to encrypt annotation")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO annotations
+                (user_id, subject_kind, subject_id, value_encrypted, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(user_id, subject_kind, subject_id) DO UPDATE SET
+                value_encrypted = excluded.value_encrypted,
+                updated_at = excluded.updated_at",
+                params![
+                    user_id,
+                    annotation.subject.kind(),
+                    annotation.subject.id(),
+                    &encrypted,
+                    annotation.updated_at
+                ],
+            )
+            .context("Failed to upsert annotation")?;
+
+        Ok(())
+    }
+
+    /// Retrieve and decrypt a user's note on a subject
+    pub fn get_annotation(
+        &self,
+        user_id: &str,
+        subject: &Subject,
+        encryption_key: &[u8; 32],
+    ) -> Result<Option<Annotation>> {
+        let row: Option<(Vec<u8>, u64)> = self
+            .conn
+            .query_row(
+                "SELECT value_encrypted, updated_at FROM annotations
+                 WHERE user_id = ?1 AND subject_kind = ?2 AND subject_id = ?3",
+                params![user_id, subject.kind(), subject.id()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .context("Failed to query annotation")?;
+
+        row.map(|(encrypted, updated_at)| {
+            decrypt_annotation(
+                user_id,
+                subject.clone(),
+                &encrypted,
+                updated_at,
+                encryption_key,
+            )
+        })
+        .transpose()
+    }
+
+    /// Delete a user's note on a subject
+    pub fn delete_annotation(&self, user_id: &str, subject: &Subject) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM annotations
+                 WHERE user_id = ?1 AND subject_kind = ?2 AND subject_id = ?3",
+                params![user_id, subject.kind(), subject.id()],
+            )
+            .context("Failed to delete annotation")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Retrieve and decrypt every note of a user, ordered by subject
+    pub fn list_annotations(
+        &self,
+        user_id: &str,
+        encryption_key: &[u8; 32],
+    ) -> Result<Vec<Annotation>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT subject_kind, subject_id, value_encrypted, updated_at FROM annotations
+                 WHERE user_id = ?1 ORDER BY subject_kind, subject_id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(params![user_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                    row.get::<_, u64>(3)?,
+                ))
+            })
+            .context("Failed to query annotations")?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to list annotations")?;
+
+        rows.into_iter()
+            .map(|(kind, id, encrypted, updated_at)| {
+                let subject = Subject::from_parts(&kind, &id)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown annotation subject kind: {}", kind))?;
+                decrypt_annotation(user_id, subject, &encrypted, updated_at, encryption_key)
+            })
+            .collect()
+    }
+
+    /// Run a crash report query selecting the `data` column
+    fn query_crash_reports(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<CrashReport>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .context("Failed to prepare statement")?;
+
+        let reports = stmt
+            .query_map(params, |row| {
+                let data: String = row.get(0)?;
+                let report: CrashReport = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(report)
+            })
+            .context("Failed to query crash reports")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse crash reports")?;
+
+        Ok(reports)
+    }
+
+    // ========================================================================
+    // Install Journal
+    // ========================================================================
+
+    /// Write an app's install journal entry, replacing any previous one
+    pub fn put_install_journal(&self, entry: &InstallJournalEntry) -> Result<()> {
+        let entry_json =
+            serde_json::to_string(entry).context("Failed to serialize install journal")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO install_journal (app_id, data) VALUES (?1, ?2)
+                 ON CONFLICT(app_id) DO UPDATE SET data = excluded.data",
+                params![&entry.app_id, &entry_json],
+            )
+            .context("Failed to write install journal")?;
+
+        Ok(())
+    }
+
+    /// Get an app's install journal entry
+    pub fn get_install_journal(&self, app_id: &str) -> Result<Option<InstallJournalEntry>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM install_journal WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query install journal")?;
+
+        data.map(|data| serde_json::from_str(&data).context("Failed to parse install journal"))
+            .transpose()
+    }
+
+    /// List install journal entries, in app ID order
+    pub fn list_install_journal(&self) -> Result<Vec<InstallJournalEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM install_journal ORDER BY app_id")
+            .context("Failed to prepare statement")?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                let data: String = row.get(0)?;
+                serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+            })
+            .context("Failed to query install journal")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse install journal")?;
+
+        Ok(entries)
+    }
+
+    /// Remove an app's install journal entry
+    pub fn delete_install_journal(&self, app_id: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM install_journal WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete install journal")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    // ========================================================================
+    // Payments History
+    // ========================================================================
+
+    /// Store an upload's payment record, returning its assigned ID
+    pub fn insert_payment_record(&self, record: &PaymentRecord) -> Result<i64> {
+        let record_json =
+            serde_json::to_string(record).context("Failed to serialize payment record")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO payments_history (recorded_at, data) VALUES (?1, ?2)",
+                params![record.recorded_at, &record_json],
+            )
+            .context("Failed to insert payment record")?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// List payment records, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - Only records at or after this timestamp
+    /// * `until` - Only records at or before this timestamp
+    pub fn list_payment_records(
+        &self,
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> Result<Vec<PaymentRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, data FROM payments_history
+                 WHERE recorded_at >= ?1 AND recorded_at <= ?2
+                 ORDER BY recorded_at DESC, id DESC",
+            )
+            .context("Failed to prepare statement")?;
+
+        let since = since.unwrap_or(0) as i64;
+        let until = until.map_or(i64::MAX, |until| until.min(i64::MAX as u64) as i64);
+        let records = stmt
+            .query_map(params![since, until], |row| {
+                let id: i64 = row.get(0)?;
+                let data: String = row.get(1)?;
+                let mut record: PaymentRecord = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                record.id = id;
+                Ok(record)
+            })
+            .context("Failed to query payment records")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse payment records")?;
+
+        Ok(records)
+    }
+
+    // ========================================================================
+    // Archive Uploads
+    // ========================================================================
+
+    /// Batches of an archive upload that were paid for, in batch order
+    pub fn archive_batches(&self, upload_id: &str) -> Result<Vec<ArchiveBatchRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT batch, payment_request_id, quoted_cost, actual_cost, completed
+                 FROM archive_batches WHERE upload_id = ?1 ORDER BY batch",
+            )
+            .context("Failed to prepare statement")?;
+
+        let batches = stmt
+            .query_map(params![upload_id], |row| {
+                Ok(ArchiveBatchRecord {
+                    batch: row.get::<_, i64>(0)? as usize,
+                    payment_request_id: row.get(1)?,
+                    quoted_cost: row.get::<_, i64>(2)? as u64,
+                    actual_cost: row.get::<_, i64>(3)? as u64,
+                    completed: row.get(4)?,
+                })
+            })
+            .context("Failed to query archive batches")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse archive batches")?;
+
+        Ok(batches)
+    }
+
+    /// Record the payment made for a batch of an archive upload
+    ///
+    /// A batch already recorded keeps its first payment.
+    pub fn record_archive_batch_payment(
+        &self,
+        upload_id: &str,
+        batch: usize,
+        payment_request_id: Option<&str>,
+        quoted_cost: u64,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO archive_batches
+                     (upload_id, batch, payment_request_id, quoted_cost)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    upload_id,
+                    batch as i64,
+                    payment_request_id,
+                    quoted_cost as i64
+                ],
+            )
+            .context("Failed to record archive batch payment")?;
+        Ok(())
+    }
+
+    /// Record a file of an archive as stored
+    ///
+    /// The contents are added to the dedup ledger under `content_hash`, and
+    /// `cost` to the batch's actual cost, in one transaction.
+    pub fn record_archive_file(
+        &self,
+        upload_id: &str,
+        batch: usize,
+        position: usize,
+        entry: &ArchiveEntry,
+        content_hash: &str,
+        cost: u64,
+    ) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to start transaction")?;
+        tx.execute(
+            "INSERT OR REPLACE INTO archive_files (upload_id, name, position, address, size)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                upload_id,
+                &entry.name,
+                position as i64,
+                &entry.address,
+                entry.size as i64
+            ],
+        )
+        .context("Failed to record archive file")?;
+        tx.execute(
+            "INSERT OR IGNORE INTO upload_dedup (content_hash, address, size)
+             VALUES (?1, ?2, ?3)",
+            params![content_hash, &entry.address, entry.size as i64],
+        )
+        .context("Failed to record uploaded content")?;
+        tx.execute(
+            "UPDATE archive_batches SET actual_cost = actual_cost + ?3
+             WHERE upload_id = ?1 AND batch = ?2",
+            params![upload_id, batch as i64, cost as i64],
+        )
+        .context("Failed to update archive batch cost")?;
+        tx.commit().context("Failed to commit archive file")?;
+        Ok(())
+    }
+
+    /// Mark a batch of an archive upload as fully stored
+    pub fn complete_archive_batch(&self, upload_id: &str, batch: usize) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE archive_batches SET completed = 1 WHERE upload_id = ?1 AND batch = ?2",
+                params![upload_id, batch as i64],
+            )
+            .context("Failed to complete archive batch")?;
+        Ok(())
+    }
+
+    /// Files of an archive stored so far, in archive order
+    pub fn archive_files(&self, upload_id: &str) -> Result<Vec<ArchiveEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT name, address, size FROM archive_files
+                 WHERE upload_id = ?1 ORDER BY position",
+            )
+            .context("Failed to prepare statement")?;
+
+        let files = stmt
+            .query_map(params![upload_id], |row| {
+                Ok(ArchiveEntry {
+                    name: row.get(0)?,
+                    address: row.get(1)?,
+                    size: row.get::<_, i64>(2)? as u64,
+                })
+            })
+            .context("Failed to query archive files")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse archive files")?;
+
+        Ok(files)
+    }
+
+    /// Address and size of contents already stored, by content hash
+    pub fn uploaded_content(&self, content_hash: &str) -> Result<Option<(String, u64)>> {
+        self.conn
+            .query_row(
+                "SELECT address, size FROM upload_dedup WHERE content_hash = ?1",
+                params![content_hash],
+                |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)),
+            )
+            .optional()
+            .context("Failed to query uploaded content")
+    }
+
+    /// Record the stored index of an archive, completing the upload
+    ///
+    /// `batch` is the batch the index was paid for under; `cost` is added
+    /// to it.
+    pub fn finish_archive_upload(
+        &self,
+        upload_id: &str,
+        batch: usize,
+        index_address: &str,
+        cost: u64,
+        completed_at: u64,
+    ) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to start transaction")?;
+        tx.execute(
+            "UPDATE archive_batches SET actual_cost = actual_cost + ?3, completed = 1
+             WHERE upload_id = ?1 AND batch = ?2",
+            params![upload_id, batch as i64, cost as i64],
+        )
+        .context("Failed to update archive batch cost")?;
+        tx.execute(
+            "INSERT OR REPLACE INTO archive_uploads (upload_id, index_address, completed_at)
+             VALUES (?1, ?2, ?3)",
+            params![upload_id, index_address, completed_at as i64],
+        )
+        .context("Failed to record archive index")?;
+        tx.commit().context("Failed to commit archive index")?;
+        Ok(())
+    }
+
+    /// Address of an archive's index, once the upload completed
+    pub fn archive_index(&self, upload_id: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT index_address FROM archive_uploads WHERE upload_id = ?1",
+                params![upload_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query archive index")
+    }
+
+    // ========================================================================
+    // Key Usage
+    // ========================================================================
+
+    /// Count one operation on each of a component's keys in a bucket
+    ///
+    /// All counters are updated in one transaction.
+    pub fn add_key_usage(
+        &self,
+        granularity: UsageGranularity,
+        bucket: u64,
+        component_id: &str,
+        operation: KeyOperation,
+        key_indexes: &[u64],
+    ) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to begin transaction")?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO key_usage
+                        (granularity, bucket, component_id, key_index, operation, count)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 1)
+                     ON CONFLICT(granularity, component_id, bucket, key_index, operation)
+                     DO UPDATE SET count = count + 1",
+                )
+                .context("Failed to prepare statement")?;
+            for key_index in key_indexes {
+                stmt.execute(params![
+                    granularity.as_str(),
+                    bucket as i64,
+                    component_id,
+                    *key_index as i64,
+                    operation.as_str()
+                ])
+                .context("Failed to record key usage")?;
+            }
+        }
+        tx.commit().context("Failed to commit key usage")?;
+
+        Ok(())
+    }
+
+    /// List a component's usage rows for buckets starting in `[from, to)`,
+    /// oldest first
+    pub fn list_key_usage(
+        &self,
+        granularity: UsageGranularity,
+        component_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<KeyUsageRow>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT bucket, key_index, operation, count FROM key_usage
+                 WHERE granularity = ?1 AND component_id = ?2
+                   AND bucket >= ?3 AND bucket < ?4
+                 ORDER BY bucket, key_index, operation",
+            )
+            .context("Failed to prepare statement")?;
+
+        let rows = stmt
+            .query_map(
+                params![
+                    granularity.as_str(),
+                    component_id,
+                    from as i64,
+                    to.min(i64::MAX as u64) as i64
+                ],
+                |row| {
+                    let bucket: i64 = row.get(0)?;
+                    let key_index: i64 = row.get(1)?;
+                    let operation: String = row.get(2)?;
+                    let count: i64 = row.get(3)?;
+                    Ok((bucket as u64, key_index as u64, operation, count as u64))
+                },
+            )
+            .context("Failed to query key usage")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse key usage")?;
+
+        // Rows of operations this version does not know are skipped
+        Ok(rows
+            .into_iter()
+            .filter_map(|(bucket, key_index, operation, count)| {
+                Some(KeyUsageRow {
+                    bucket,
+                    component_id: component_id.to_string(),
+                    key_index,
+                    operation: KeyOperation::from_name(&operation)?,
+                    count,
+                })
+            })
+            .collect())
+    }
+
+    /// Total operations of a component in buckets starting in `[from, to)`
+    pub fn count_key_usage(
+        &self,
+        granularity: UsageGranularity,
+        component_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(count), 0) FROM key_usage
+                 WHERE granularity = ?1 AND component_id = ?2
+                   AND bucket >= ?3 AND bucket < ?4",
+                params![
+                    granularity.as_str(),
+                    component_id,
+                    from as i64,
+                    to.min(i64::MAX as u64) as i64
+                ],
+                |row| row.get(0),
+            )
+            .context("Failed to count key usage")?;
+
+        Ok(count as u64)
+    }
+
+    /// Delete usage rows of one granularity in buckets before `cutoff`
+    pub fn prune_key_usage(&self, granularity: UsageGranularity, cutoff: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM key_usage WHERE granularity = ?1 AND bucket < ?2",
+                params![granularity.as_str(), cutoff as i64],
+            )
+            .context("Failed to prune key usage")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Store a detected key usage anomaly
+    pub fn insert_key_usage_anomaly(&self, anomaly: &KeyUsageAnomaly) -> Result<()> {
+        let anomaly_json =
+            serde_json::to_string(anomaly).context("Failed to serialize key usage anomaly")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO key_usage_anomalies (component_id, detected_at, data)
+                 VALUES (?1, ?2, ?3)",
+                params![
+                    &anomaly.component_id,
+                    anomaly.detected_at as i64,
+                    &anomaly_json
+                ],
+            )
+            .context("Failed to insert key usage anomaly")?;
+
+        Ok(())
+    }
+
+    /// List anomalies detected at or after `since`, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `component_id` - Only anomalies of this component
+    /// * `since` - Earliest detection time
+    pub fn list_key_usage_anomalies(
+        &self,
+        component_id: Option<&str>,
+        since: u64,
+    ) -> Result<Vec<KeyUsageAnomaly>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT data FROM key_usage_anomalies
+                 WHERE detected_at >= ?1 AND (?2 IS NULL OR component_id = ?2)
+                 ORDER BY detected_at DESC, id DESC",
+            )
+            .context("Failed to prepare statement")?;
+
+        let anomalies = stmt
+            .query_map(params![since as i64, component_id], |row| {
+                let data: String = row.get(0)?;
+                serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+            })
+            .context("Failed to query key usage anomalies")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse key usage anomalies")?;
+
+        Ok(anomalies)
+    }
+
+    /// Delete anomalies detected before `cutoff`
+    pub fn prune_key_usage_anomalies(&self, cutoff: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM key_usage_anomalies WHERE detected_at < ?1",
+                params![cutoff as i64],
+            )
+            .context("Failed to prune key usage anomalies")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Shared Component Registry
+    // ========================================================================
+
+    /// Record a shared component artifact
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the component is not shared.
+    pub fn register_shared_component(&self, component: &ComponentRef) -> Result<()> {
+        let key = component
+            .shared_key()
+            .context("Component is not a shared component")?;
+        let component_json =
+            serde_json::to_string(component).context("Failed to serialize shared component")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO shared_components (shared_id, version, data)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(shared_id, version) DO UPDATE SET
+                data = excluded.data",
+                params![&key.shared_id, &key.version, &component_json],
+            )
+            .context("Failed to register shared component")?;
+
+        Ok(())
+    }
+
+    /// Remove a shared component artifact record
+    pub fn remove_shared_component(&self, key: &SharedComponentKey) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM shared_components WHERE shared_id = ?1 AND version = ?2",
+                params![&key.shared_id, &key.version],
+            )
+            .context("Failed to remove shared component")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// List all recorded shared component artifacts
+    pub fn list_shared_components(&self) -> Result<Vec<ComponentRef>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM shared_components ORDER BY shared_id, version")
+            .context("Failed to prepare statement")?;
+
+        let components = stmt
+            .query_map([], |row| {
+                let data: String = row.get(0)?;
+                let component: ComponentRef = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(component)
+            })
+            .context("Failed to query shared components")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse shared components")?;
+
+        Ok(components)
+    }
+
+    // ========================================================================
+    // Component Provenance
+    // ========================================================================
+
+    /// Append a provenance record
+    ///
+    /// Records are never updated; a re-download appends a new one.
+    pub fn append_provenance(&self, record: &ProvenanceRecord) -> Result<()> {
+        let record_json =
+            serde_json::to_string(record).context("Failed to serialize provenance record")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO component_provenance
+                    (component_id, component_hash, downloaded_at, data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    &record.component_id,
+                    &record.component_hash,
+                    record.downloaded_at as i64,
+                    &record_json
+                ],
+            )
+            .context("Failed to append provenance record")?;
+
+        Ok(())
+    }
+
+    /// List provenance records for a content hash, newest first
+    pub fn list_provenance_by_hash(&self, component_hash: &str) -> Result<Vec<ProvenanceRecord>> {
+        self.query_provenance("component_hash", component_hash)
+    }
+
+    /// List provenance records for a component id, newest first
+    pub fn list_provenance_by_component(
+        &self,
+        component_id: &str,
+    ) -> Result<Vec<ProvenanceRecord>> {
+        self.query_provenance("component_id", component_id)
+    }
+
+    /// Delete provenance records downloaded before `cutoff`
+    ///
+    /// The newest record of every component is always kept, so installed
+    /// components never lose their provenance.
+    ///
+    /// # Returns
+    ///
+    /// Number of records deleted
+    pub fn prune_provenance(&self, cutoff: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM component_provenance
+                 WHERE downloaded_at < ?1
+                   AND id NOT IN (
+                       SELECT MAX(id) FROM component_provenance GROUP BY component_id
+                   )",
+                params![cutoff as i64],
+            )
+            .context("Failed to prune provenance records")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Query provenance records matching one indexed column
+    fn query_provenance(&self, column: &str, value: &str) -> Result<Vec<ProvenanceRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT data FROM component_provenance WHERE {} = ?1 ORDER BY id DESC",
+                column
+            ))
+            .context("Failed to prepare statement")?;
+
+        let records = stmt
+            .query_map(params![value], |row| {
+                let data: String = row.get(0)?;
+                let record: ProvenanceRecord = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(record)
+            })
+            .context("Failed to query provenance records")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse provenance records")?;
+
+        Ok(records)
+    }
+
+    /// Record that a component of an app version was reproduced locally,
+    /// replacing an earlier check of the same component
+    pub fn record_reproducibility_check(&self, check: &ReproducibilityCheck) -> Result<()> {
+        let data =
+            serde_json::to_string(check).context("Failed to serialize reproducibility check")?;
+
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO reproducibility_checks
+                    (app_id, app_version, component_id, data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    &check.app_id,
+                    &check.app_version,
+                    &check.component_id,
+                    &data
+                ],
+            )
+            .context("Failed to record reproducibility check")?;
+
+        Ok(())
+    }
+
+    /// Components of an app version reproduced locally, by component id
+    pub fn list_reproducibility_checks(
+        &self,
+        app_id: &str,
+        app_version: &str,
+    ) -> Result<Vec<ReproducibilityCheck>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT data FROM reproducibility_checks
+                 WHERE app_id = ?1 AND app_version = ?2 ORDER BY component_id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let checks = stmt
+            .query_map(params![app_id, app_version], |row| {
+                let data: String = row.get(0)?;
+                serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+            })
+            .context("Failed to query reproducibility checks")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse reproducibility checks")?;
+
+        Ok(checks)
+    }
+
+    /// Delete the reproducibility checks of every version of an app
+    pub fn delete_reproducibility_checks(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM reproducibility_checks WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete reproducibility checks")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Network Observations
+    // ========================================================================
+
+    /// Add connections seen by the network proxy to a backend's totals for
+    /// a host
+    ///
+    /// # Arguments
+    ///
+    /// * `owner_id` - Process owner of the backend (an app ID, or a shared
+    ///   component's registry ID)
+    /// * `observed` - Counts to add; the first and last seen times widen
+    ///   the recorded range
+    pub fn add_network_observation(&self, owner_id: &str, observed: &ObservedHost) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO network_observations
+                    (owner_id, component_id, host, allowed, denied, bytes_sent,
+                     bytes_received, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT (owner_id, component_id, host) DO UPDATE SET
+                    allowed = allowed + excluded.allowed,
+                    denied = denied + excluded.denied,
+                    bytes_sent = bytes_sent + excluded.bytes_sent,
+                    bytes_received = bytes_received + excluded.bytes_received,
+                    first_seen = MIN(first_seen, excluded.first_seen),
+                    last_seen = MAX(last_seen, excluded.last_seen)",
+                params![
+                    owner_id,
+                    &observed.component_id,
+                    &observed.host,
+                    observed.allowed as i64,
+                    observed.denied as i64,
+                    observed.bytes_sent as i64,
+                    observed.bytes_received as i64,
+                    observed.first_seen as i64,
+                    observed.last_seen as i64,
+                ],
+            )
+            .context("Failed to record network observation")?;
+
+        Ok(())
+    }
+
+    /// Hosts the backends of an owner were seen reaching, by component and
+    /// host
+    pub fn list_network_observations(&self, owner_id: &str) -> Result<Vec<ObservedHost>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT component_id, host, allowed, denied, bytes_sent, bytes_received,
+                        first_seen, last_seen
+                 FROM network_observations WHERE owner_id = ?1
+                 ORDER BY component_id, host",
+            )
+            .context("Failed to prepare statement")?;
+
+        let observations = stmt
+            .query_map(params![owner_id], |row| {
+                Ok(ObservedHost {
+                    component_id: row.get(0)?,
+                    host: row.get(1)?,
+                    allowed: row.get::<_, i64>(2)? as u64,
+                    denied: row.get::<_, i64>(3)? as u64,
+                    bytes_sent: row.get::<_, i64>(4)? as u64,
+                    bytes_received: row.get::<_, i64>(5)? as u64,
+                    first_seen: row.get::<_, i64>(6)? as u64,
+                    last_seen: row.get::<_, i64>(7)? as u64,
+                })
+            })
+            .context("Failed to query network observations")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse network observations")?;
+
+        Ok(observations)
+    }
+
+    /// Delete the network observations of an owner's backends
+    pub fn delete_network_observations(&self, owner_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM network_observations WHERE owner_id = ?1",
+                params![owner_id],
+            )
+            .context("Failed to delete network observations")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Remote Sessions
+    // ========================================================================
+
+    /// Insert or update a remote session
+    ///
+    /// # Arguments
+    ///
+    /// * `token_hash` - Hash of the session token (the token is never stored)
+    /// * `session` - Session record
+    pub fn upsert_remote_session(&self, token_hash: &str, session: &RemoteSession) -> Result<()> {
+        let session_json =
+            serde_json::to_string(session).context("Failed to serialize remote session")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO remote_sessions (token_hash, device_id, expires_at, data)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(token_hash) DO UPDATE SET
+                expires_at = excluded.expires_at,
+                data = excluded.data",
+                params![
+                    token_hash,
+                    &session.device_id,
+                    session.expires_at as i64,
+                    &session_json
+                ],
+            )
+            .context("Failed to upsert remote session")?;
+
+        Ok(())
+    }
+
+    /// Get a remote session by token hash
+    pub fn get_remote_session(&self, token_hash: &str) -> Result<Option<RemoteSession>> {
+        let result = self
+            .conn
+            .query_row(
+                "SELECT data FROM remote_sessions WHERE token_hash = ?1",
+                params![token_hash],
+                |row| {
+                    let data: String = row.get(0)?;
+                    let session: RemoteSession = serde_json::from_str(&data)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                    Ok(session)
+                },
+            )
+            .optional()
+            .context("Failed to query remote session")?;
+
+        Ok(result)
+    }
+
+    /// List all remote sessions, oldest first
+    pub fn list_remote_sessions(&self) -> Result<Vec<RemoteSession>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM remote_sessions")
+            .context("Failed to prepare statement")?;
+
+        let mut sessions = stmt
+            .query_map([], |row| {
+                let data: String = row.get(0)?;
+                let session: RemoteSession = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(session)
+            })
+            .context("Failed to query remote sessions")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse remote sessions")?;
+
+        sessions.sort_by_key(|s| s.created_at);
+        Ok(sessions)
+    }
+
+    /// Delete a remote session by token hash
+    pub fn delete_remote_session(&self, token_hash: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM remote_sessions WHERE token_hash = ?1",
+                params![token_hash],
+            )
+            .context("Failed to delete remote session")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Delete every remote session of a device, returning how many were removed
+    pub fn delete_remote_sessions_for_device(&self, device_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM remote_sessions WHERE device_id = ?1",
+                params![device_id],
+            )
+            .context("Failed to delete remote sessions")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Delete remote sessions that expired at or before `now`
+    pub fn delete_expired_remote_sessions(&self, now: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM remote_sessions WHERE expires_at <= ?1",
+                params![now as i64],
+            )
+            .context("Failed to delete expired remote sessions")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Grant a permission to a paired device
+    ///
+    /// Returns `false` if the device already had it.
+    pub fn grant_device_permission(
+        &self,
+        device_id: &str,
+        permission: &str,
+        granted_at: u64,
+    ) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO device_permissions (device_id, permission, granted_at)
+             VALUES (?1, ?2, ?3)",
+                params![device_id, permission, granted_at as i64],
+            )
+            .context("Failed to grant device permission")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Whether a paired device holds a permission
+    pub fn has_device_permission(&self, device_id: &str, permission: &str) -> Result<bool> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM device_permissions WHERE device_id = ?1 AND permission = ?2",
+                params![device_id, permission],
+                |_| Ok(()),
+            )
+            .optional()
+            .context("Failed to query device permission")?;
+
+        Ok(found.is_some())
+    }
+
+    /// Permissions held by a paired device, by name
+    pub fn list_device_permissions(&self, device_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT permission FROM device_permissions WHERE device_id = ?1
+                 ORDER BY permission",
+            )
+            .context("Failed to prepare statement")?;
+
+        let permissions = stmt
+            .query_map(params![device_id], |row| row.get(0))
+            .context("Failed to query device permissions")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse device permissions")?;
+
+        Ok(permissions)
+    }
+
+    /// Revoke a permission from a device, or all of them when `permission`
+    /// is `None`, returning how many were removed
+    pub fn revoke_device_permission(
+        &self,
+        device_id: &str,
+        permission: Option<&str>,
+    ) -> Result<usize> {
+        let rows_affected = match permission {
+            Some(permission) => self.conn.execute(
+                "DELETE FROM device_permissions WHERE device_id = ?1 AND permission = ?2",
+                params![device_id, permission],
+            ),
+            None => self.conn.execute(
+                "DELETE FROM device_permissions WHERE device_id = ?1",
+                params![device_id],
+            ),
+        }
+        .context("Failed to revoke device permission")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Launcher Changes
+    // ========================================================================
+
+    /// Number a launcher change with the next generation, returning it
+    ///
+    /// Only the last [`LAUNCHER_CHANGE_HISTORY`] changes are kept.
+    pub fn record_launcher_change(
+        &self,
+        kind: LauncherChangeKind,
+        app_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<u64> {
+        self.conn
+            .execute(
+                "INSERT INTO launcher_changes (kind, app_id, user_id) VALUES (?1, ?2, ?3)",
+                params![kind.as_str(), app_id, user_id],
+            )
+            .context("Failed to record launcher change")?;
+        let generation = self.conn.last_insert_rowid();
+
+        self.conn
+            .execute(
+                "DELETE FROM launcher_changes WHERE generation <= ?1",
+                params![generation - LAUNCHER_CHANGE_HISTORY as i64],
+            )
+            .context("Failed to prune launcher changes")?;
+
+        Ok(generation as u64)
+    }
+
+    /// Generation of the latest launcher change, 0 before the first
+    pub fn launcher_generation(&self) -> Result<u64> {
+        let generation: i64 = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(MAX(generation), 0) FROM launcher_changes",
+                [],
+                |row| row.get(0),
+            )
+            .context("Failed to query launcher generation")?;
+
+        Ok(generation as u64)
+    }
+
+    /// Generation of the oldest kept launcher change
+    pub fn oldest_launcher_change(&self) -> Result<Option<u64>> {
+        let generation: Option<i64> = self
+            .conn
+            .query_row("SELECT MIN(generation) FROM launcher_changes", [], |row| {
+                row.get(0)
+            })
+            .context("Failed to query launcher changes")?;
+
+        Ok(generation.map(|generation| generation as u64))
+    }
+
+    /// Kept launcher changes after a generation, oldest first
+    pub fn list_launcher_changes_since(&self, since: u64) -> Result<Vec<LauncherChange>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT generation, kind, app_id, user_id FROM launcher_changes
+                 WHERE generation > ?1 ORDER BY generation",
+            )
+            .context("Failed to prepare statement")?;
+
+        let changes = stmt
+            .query_map(params![since as i64], |row| {
+                let kind: String = row.get(1)?;
+                let kind = LauncherChangeKind::parse(&kind).ok_or_else(|| {
+                    rusqlite::Error::InvalidColumnType(1, kind.clone(), rusqlite::types::Type::Text)
+                })?;
+                Ok(LauncherChange {
+                    generation: row.get::<_, i64>(0)? as u64,
+                    kind,
+                    app_id: row.get(2)?,
+                    user_id: row.get(3)?,
+                })
+            })
+            .context("Failed to query launcher changes")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse launcher changes")?;
+
+        Ok(changes)
+    }
+
+    // ========================================================================
+    // Mutation Receipts
+    // ========================================================================
+
+    /// Apply a mutation at most once per mutation id
+    ///
+    /// `mutate` runs inside an immediate transaction and returns the
+    /// generation it produced; its writes on this connection are committed
+    /// together with the receipt or not at all, and mutations from other
+    /// connections wait rather than interleave. An id already recorded in
+    /// `scope` returns its first receipt without running `mutate`, for
+    /// [`MUTATION_RECEIPT_TTL_SECS`] after it was recorded.
+    pub fn apply_mutation<F>(
+        &self,
+        scope: &str,
+        mutation_id: &str,
+        mutate: F,
+    ) -> Result<MutationReceipt>
+    where
+        F: FnOnce() -> Result<u64>,
+    {
+        let tx = rusqlite::Transaction::new_unchecked(
+            &self.conn,
+            rusqlite::TransactionBehavior::Immediate,
+        )
+        .context("Failed to start mutation")?;
+
+        let recorded: Option<i64> = tx
+            .query_row(
+                "SELECT generation FROM mutation_receipts
+                 WHERE scope = ?1 AND mutation_id = ?2",
+                params![scope, mutation_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query mutation receipt")?;
+        let generation = match recorded {
+            Some(generation) => generation as u64,
+            None => {
+                let generation = mutate()?;
+                tx.execute(
+                    "DELETE FROM mutation_receipts
+                     WHERE recorded_at < CAST(strftime('%s', 'now') AS INTEGER) - ?1",
+                    params![MUTATION_RECEIPT_TTL_SECS as i64],
+                )
+                .context("Failed to prune mutation receipts")?;
+                tx.execute(
+                    "INSERT INTO mutation_receipts (scope, mutation_id, generation)
+                     VALUES (?1, ?2, ?3)",
+                    params![scope, mutation_id, generation as i64],
+                )
+                .context("Failed to record mutation receipt")?;
+                generation
+            }
+        };
+        tx.commit().context("Failed to commit mutation")?;
+
+        Ok(MutationReceipt {
+            generation,
+            mutation_id: mutation_id.to_string(),
+        })
+    }
+
+    // ========================================================================
+    // App Consents
+    // ========================================================================
+
+    /// Record a user's review decision for an app, replacing any earlier one
+    pub fn upsert_app_consent(&self, user_id: &str, consent: &AppConsent) -> Result<()> {
+        let data = serde_json::to_string(consent).context("Failed to serialize app consent")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO app_consents (user_id, app_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(user_id, app_id) DO UPDATE SET data = excluded.data",
+                params![user_id, consent.app_id, data],
+            )
+            .context("Failed to upsert app consent")?;
+
+        Ok(())
+    }
+
+    /// A user's latest review decision for an app
+    pub fn get_app_consent(&self, user_id: &str, app_id: &str) -> Result<Option<AppConsent>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM app_consents WHERE user_id = ?1 AND app_id = ?2",
+                params![user_id, app_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query app consent")?;
+
+        data.map(|data| serde_json::from_str(&data).context("Failed to parse app consent"))
+            .transpose()
+    }
+
+    /// Delete every user's review decision for an app
+    pub fn delete_app_consents(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM app_consents WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete app consents")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // URI Scheme Claims
+    // ========================================================================
+
+    /// Record that an app handles a URI scheme
+    ///
+    /// Claims keep their original order if recorded again.
+    pub fn add_uri_scheme_claim(&self, scheme: &str, app_id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO uri_scheme_claims (scheme, app_id) VALUES (?1, ?2)",
+                params![scheme, app_id],
+            )
+            .context("Failed to add URI scheme claim")?;
+
+        Ok(())
+    }
+
+    /// Remove every URI scheme claim of an app
+    pub fn remove_uri_scheme_claims(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM uri_scheme_claims WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to remove URI scheme claims")?;
+
+        Ok(rows_affected)
+    }
+
+    /// List URI scheme claims as (scheme, app_id), in the order they were made
+    pub fn list_uri_scheme_claims(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT scheme, app_id FROM uri_scheme_claims ORDER BY sequence")
+            .context("Failed to prepare statement")?;
+
+        let claims = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("Failed to query URI scheme claims")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse URI scheme claims")?;
+
+        Ok(claims)
+    }
+
+    // ========================================================================
+    // Permission Grants
+    // ========================================================================
+
+    /// Remember a user's permission decision for a component
+    ///
+    /// Replaces any earlier decision for the same capability.
+    pub fn set_permission_grant(&self, user_id: &str, grant: &PermissionGrant) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO permission_grants
+                 (user_id, app_id, component_id, capability, granted, decided_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    user_id,
+                    grant.app_id,
+                    grant.component_id,
+                    grant.capability.as_str(),
+                    grant.granted,
+                    grant.decided_at as i64
+                ],
+            )
+            .context("Failed to store permission grant")?;
+
+        Ok(())
+    }
+
+    /// Get a user's remembered decision for a component capability
+    pub fn get_permission_grant(
+        &self,
+        user_id: &str,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+    ) -> Result<Option<PermissionGrant>> {
+        self.conn
+            .query_row(
+                "SELECT app_id, component_id, capability, granted, decided_at
+                 FROM permission_grants
+                 WHERE user_id = ?1 AND app_id = ?2 AND component_id = ?3 AND capability = ?4",
+                params![user_id, app_id, component_id, capability.as_str()],
+                Self::row_to_permission_grant,
+            )
+            .optional()
+            .context("Failed to query permission grant")
+    }
+
+    /// List a user's remembered decisions, optionally for one app
+    pub fn list_permission_grants(
+        &self,
+        user_id: &str,
+        app_id: Option<&str>,
+    ) -> Result<Vec<PermissionGrant>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT app_id, component_id, capability, granted, decided_at
+                 FROM permission_grants
+                 WHERE user_id = ?1 AND (?2 IS NULL OR app_id = ?2)
+                 ORDER BY app_id, component_id, capability",
+            )
+            .context("Failed to prepare statement")?;
+
+        let grants = stmt
+            .query_map(params![user_id, app_id], Self::row_to_permission_grant)
+            .context("Failed to query permission grants")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse permission grants")?;
+
+        Ok(grants)
+    }
+
+    /// Forget a user's decision for a component capability
+    pub fn delete_permission_grant(
+        &self,
+        user_id: &str,
+        app_id: &str,
+        component_id: &str,
+        capability: Capability,
+    ) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM permission_grants
+                 WHERE user_id = ?1 AND app_id = ?2 AND component_id = ?3 AND capability = ?4",
+                params![user_id, app_id, component_id, capability.as_str()],
+            )
+            .context("Failed to delete permission grant")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Forget every user's decisions for an app
+    pub fn delete_permission_grants_for_app(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM permission_grants WHERE app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete permission grants")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Convert a `permission_grants` row into a PermissionGrant
+    fn row_to_permission_grant(row: &rusqlite::Row<'_>) -> rusqlite::Result<PermissionGrant> {
+        let capability: String = row.get(2)?;
+        let capability = capability
+            .parse::<Capability>()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let decided_at: i64 = row.get(4)?;
+
+        Ok(PermissionGrant {
+            app_id: row.get(0)?,
+            component_id: row.get(1)?,
+            capability,
+            granted: row.get(3)?,
+            decided_at: decided_at as u64,
+        })
+    }
+
+    // ========================================================================
+    // Shared Data Grants
+    // ========================================================================
+
+    /// Record that a consumer app may read a data offer
+    ///
+    /// Granting again refreshes the timestamp.
+    pub fn set_shared_data_grant(&self, user_id: &str, grant: &SharedDataGrant) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO shared_data_grants
+                 (user_id, provider_app_id, offer_id, consumer_app_id, granted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    user_id,
+                    grant.provider_app_id,
+                    grant.offer_id,
+                    grant.consumer_app_id,
+                    grant.granted_at as i64
+                ],
+            )
+            .context("Failed to store shared data grant")?;
+
+        Ok(())
+    }
+
+    /// Get a consumer app's grant for a data offer
+    pub fn get_shared_data_grant(
+        &self,
+        user_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+        consumer_app_id: &str,
+    ) -> Result<Option<SharedDataGrant>> {
+        self.conn
+            .query_row(
+                "SELECT provider_app_id, offer_id, consumer_app_id, granted_at
+                 FROM shared_data_grants
+                 WHERE user_id = ?1 AND provider_app_id = ?2 AND offer_id = ?3
+                   AND consumer_app_id = ?4",
+                params![user_id, provider_app_id, offer_id, consumer_app_id],
+                Self::row_to_shared_data_grant,
+            )
+            .optional()
+            .context("Failed to query shared data grant")
+    }
+
+    /// List a user's data sharing grants, optionally those involving one
+    /// app as provider or consumer
+    pub fn list_shared_data_grants(
+        &self,
+        user_id: &str,
+        app_id: Option<&str>,
+    ) -> Result<Vec<SharedDataGrant>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT provider_app_id, offer_id, consumer_app_id, granted_at
+                 FROM shared_data_grants
+                 WHERE user_id = ?1
+                   AND (?2 IS NULL OR provider_app_id = ?2 OR consumer_app_id = ?2)
+                 ORDER BY provider_app_id, offer_id, consumer_app_id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let grants = stmt
+            .query_map(params![user_id, app_id], Self::row_to_shared_data_grant)
+            .context("Failed to query shared data grants")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse shared data grants")?;
+
+        Ok(grants)
+    }
+
+    /// Remove a consumer app's grant for a data offer
+    pub fn delete_shared_data_grant(
+        &self,
+        user_id: &str,
+        provider_app_id: &str,
+        offer_id: &str,
+        consumer_app_id: &str,
+    ) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM shared_data_grants
+                 WHERE user_id = ?1 AND provider_app_id = ?2 AND offer_id = ?3
+                   AND consumer_app_id = ?4",
+                params![user_id, provider_app_id, offer_id, consumer_app_id],
+            )
+            .context("Failed to delete shared data grant")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Remove every user's grants where an app is provider or consumer
+    pub fn delete_shared_data_grants_for_app(&self, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM shared_data_grants
+                 WHERE provider_app_id = ?1 OR consumer_app_id = ?1",
+                params![app_id],
+            )
+            .context("Failed to delete shared data grants")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Convert a `shared_data_grants` row into a SharedDataGrant
+    fn row_to_shared_data_grant(row: &rusqlite::Row<'_>) -> rusqlite::Result<SharedDataGrant> {
+        let granted_at: i64 = row.get(3)?;
+
+        Ok(SharedDataGrant {
+            provider_app_id: row.get(0)?,
+            offer_id: row.get(1)?,
+            consumer_app_id: row.get(2)?,
+            granted_at: granted_at as u64,
+        })
+    }
+
+    // ========================================================================
+    // Notifications
+    // ========================================================================
+
+    /// Store a notification for a user, returning its id
+    pub fn insert_notification(
+        &self,
+        user_id: &str,
+        app_id: &str,
+        posted_at: u64,
+        notification: &Notification,
+    ) -> Result<i64> {
+        let data =
+            serde_json::to_string(notification).context("Failed to serialize notification")?;
+
+        self.conn
+            .execute(
+                "INSERT INTO notifications (user_id, app_id, tag, posted_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![user_id, app_id, &notification.tag, posted_at as i64, &data],
+            )
+            .context("Failed to insert notification")?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// List a user's notifications, newest first
+    pub fn list_notifications(&self, user_id: &str) -> Result<Vec<StoredNotification>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, app_id, posted_at, read, data FROM notifications
+             WHERE user_id = ?1 ORDER BY id DESC",
+            )
+            .context("Failed to prepare statement")?;
+
+        let notifications = stmt
+            .query_map(params![user_id], |row| {
+                let data: String = row.get(4)?;
+                let notification: Notification = serde_json::from_str(&data)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                Ok(StoredNotification {
+                    id: row.get(0)?,
+                    app_id: row.get(1)?,
+                    posted_at: row.get::<_, i64>(2)? as u64,
+                    read: row.get(3)?,
+                    notification,
+                })
+            })
+            .context("Failed to query notifications")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse notifications")?;
+
+        Ok(notifications)
+    }
+
+    /// Whether a user already has a notification from an app with a tag
+    pub fn has_notification_tag(&self, user_id: &str, app_id: &str, tag: &str) -> Result<bool> {
+        let count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM notifications
+             WHERE user_id = ?1 AND app_id = ?2 AND tag = ?3",
+                params![user_id, app_id, tag],
+                |row| row.get(0),
+            )
+            .context("Failed to query notification tag")?;
+
+        Ok(count > 0)
+    }
+
+    /// Mark notifications read, returning how many changed
+    pub fn mark_notifications_read(&self, user_id: &str, ids: &[i64]) -> Result<usize> {
+        let mut changed = 0;
+        for id in ids {
+            changed += self
+                .conn
+                .execute(
+                    "UPDATE notifications SET read = 1
+                 WHERE user_id = ?1 AND id = ?2 AND read = 0",
+                    params![user_id, id],
+                )
+                .context("Failed to mark notification read")?;
+        }
+
+        Ok(changed)
+    }
+
+    /// Delete a user's notifications from an app, returning how many were removed
+    pub fn delete_notifications_for_app(&self, user_id: &str, app_id: &str) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM notifications WHERE user_id = ?1 AND app_id = ?2",
+                params![user_id, app_id],
+            )
+            .context("Failed to delete notifications")?;
+
+        Ok(rows_affected)
+    }
+
+    /// Keep at most `capacity` notifications for a user
+    ///
+    /// The oldest read notifications go first; unread ones are only removed
+    /// once no read ones are left. Returns how many were removed.
+    pub fn prune_notifications(&self, user_id: &str, capacity: usize) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .context("Failed to count notifications")?;
+
+        let excess = count - capacity as i64;
+        if excess <= 0 {
+            return Ok(0);
+        }
+
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM notifications WHERE id IN (
+                SELECT id FROM notifications WHERE user_id = ?1
+                ORDER BY read DESC, id ASC LIMIT ?2
+             )",
+                params![user_id, excess],
+            )
+            .context("Failed to prune notifications")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Search Index
+    // ========================================================================
+
+    /// Insert or update a serialized search document
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Unique document identifier
+    /// * `kind` - Document kind (e.g. installed app, catalog entry, setting)
+    /// * `app_id` - Application the document belongs to
+    /// * `data` - Serialized document
+    pub fn upsert_search_document(
+        &self,
+        id: &str,
+        kind: &str,
+        app_id: &str,
+        data: &str,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO search_documents (id, kind, app_id, data)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                kind = excluded.kind,
+                app_id = excluded.app_id,
+                data = excluded.data",
+                params![id, kind, app_id, data],
+            )
+            .context("Failed to upsert search document")?;
+
+        Ok(())
+    }
+
+    /// Delete search documents of a kind, optionally only for one app
+    pub fn delete_search_documents(&self, kind: &str, app_id: Option<&str>) -> Result<usize> {
+        let rows_affected = match app_id {
+            Some(app_id) => self.conn.execute(
+                "DELETE FROM search_documents WHERE kind = ?1 AND app_id = ?2",
+                params![kind, app_id],
+            ),
+            None => self.conn.execute(
+                "DELETE FROM search_documents WHERE kind = ?1",
+                params![kind],
+            ),
+        }
+        .context("Failed to delete search documents")?;
+
+        Ok(rows_affected)
+    }
+
+    /// List all serialized search documents
+    pub fn list_search_documents(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM search_documents")
+            .context("Failed to prepare statement")?;
+
+        let documents = stmt
+            .query_map([], |row| row.get(0))
+            .context("Failed to query search documents")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse search documents")?;
+
+        Ok(documents)
+    }
+
+    /// Store a user's encrypted config value index for an app
+    pub fn upsert_search_value_index(
+        &self,
+        user_id: &str,
+        app_id: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO search_value_indexes (user_id, app_id, data)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(user_id, app_id) DO UPDATE SET data = excluded.data",
+                params![user_id, app_id, data],
+            )
+            .context("Failed to upsert search value index")?;
+
+        Ok(())
+    }
+
+    /// Delete a user's value indexes, optionally only for one app
+    pub fn delete_search_value_indexes(
+        &self,
+        user_id: &str,
+        app_id: Option<&str>,
+    ) -> Result<usize> {
+        let rows_affected = match app_id {
+            Some(app_id) => self.conn.execute(
+                "DELETE FROM search_value_indexes WHERE user_id = ?1 AND app_id = ?2",
+                params![user_id, app_id],
+            ),
+            None => self.conn.execute(
+                "DELETE FROM search_value_indexes WHERE user_id = ?1",
+                params![user_id],
+            ),
+        }
+        .context("Failed to delete search value indexes")?;
+
+        Ok(rows_affected)
+    }
+
+    /// List a user's encrypted value indexes as (app ID, data) pairs
+    pub fn list_search_value_indexes(&self, user_id: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT app_id, data FROM search_value_indexes WHERE user_id = ?1 ORDER BY app_id",
+            )
+            .context("Failed to prepare statement")?;
+
+        let indexes = stmt
+            .query_map(params![user_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("Failed to query search value indexes")?
+            .collect::<Result<Vec<(String, Vec<u8>)>, _>>()
+            .context("Failed to parse search value indexes")?;
+
+        Ok(indexes)
+    }
+
+    // ========================================================================
+    // Handoffs
+    // ========================================================================
+
+    /// Store a received handoff, returning `false` if it was already stored
+    pub fn insert_handoff(
+        &self,
+        handoff_id: &str,
+        pairing_id: &str,
+        data: &str,
+        expires_at: u64,
+    ) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO handoffs (handoff_id, pairing_id, data, expires_at)
+             VALUES (?1, ?2, ?3, ?4)",
+                params![handoff_id, pairing_id, data, expires_at],
+            )
+            .context("Failed to insert handoff")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Get a stored handoff's serialized data
+    pub fn get_handoff(&self, handoff_id: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT data FROM handoffs WHERE handoff_id = ?1",
+                params![handoff_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to get handoff")
+    }
+
+    /// List stored handoffs expiring after `now`, oldest first
+    pub fn list_handoffs(&self, now: u64) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM handoffs WHERE expires_at > ?1 ORDER BY rowid")
+            .context("Failed to prepare statement")?;
+
+        let handoffs = stmt
+            .query_map(params![now], |row| row.get(0))
+            .context("Failed to query handoffs")?
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to parse handoffs")?;
+
+        Ok(handoffs)
+    }
+
+    /// Delete a stored handoff, returning whether it existed
+    pub fn delete_handoff(&self, handoff_id: &str) -> Result<bool> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM handoffs WHERE handoff_id = ?1",
+                params![handoff_id],
+            )
+            .context("Failed to delete handoff")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Delete handoffs that expired at or before `now`
+    pub fn delete_expired_handoffs(&self, now: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM handoffs WHERE expires_at <= ?1", params![now])
+            .context("Failed to delete expired handoffs")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Audit Log
+    // ========================================================================
+
+    /// Append a serialized audit entry and advance the chain head
+    ///
+    /// Both writes happen in one transaction. Fails if `sequence` exists.
+    pub fn append_audit_entry(&self, sequence: u64, data: &str, mac: &str) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to begin transaction")?;
+
+        tx.execute(
+            "INSERT INTO audit_log (sequence, data) VALUES (?1, ?2)",
+            params![sequence as i64, data],
+        )
+        .context("Failed to append audit entry")?;
+        tx.execute(
+            "INSERT INTO audit_head (id, sequence, mac)
+             VALUES (0, ?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET
+                sequence = excluded.sequence,
+                mac = excluded.mac",
+            params![sequence as i64, mac],
+        )
+        .context("Failed to update audit head")?;
+
+        tx.commit().context("Failed to commit audit entry")?;
+        Ok(())
+    }
+
+    /// Get the sequence number and MAC of the latest audit entry
+    pub fn get_audit_head(&self) -> Result<Option<(u64, String)>> {
+        let head = self
+            .conn
+            .query_row(
+                "SELECT sequence, mac FROM audit_head WHERE id = 0",
+                [],
+                |row| {
+                    let sequence: i64 = row.get(0)?;
+                    let mac: String = row.get(1)?;
+                    Ok((sequence as u64, mac))
+                },
+            )
+            .optional()
+            .context("Failed to query audit head")?;
+
+        Ok(head)
+    }
+
+    /// List serialized audit entries `(sequence, data)` in sequence order
+    pub fn list_audit_entries(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT sequence, data FROM audit_log ORDER BY sequence")
+            .context("Failed to prepare statement")?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                let sequence: i64 = row.get(0)?;
+                let data: String = row.get(1)?;
+                Ok((sequence as u64, data))
+            })
+            .context("Failed to query audit log")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse audit log")?;
+
+        Ok(entries)
+    }
+
+    /// Delete audit entries up to and including `sequence`
+    pub fn delete_audit_entries_through(&self, sequence: u64) -> Result<usize> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "DELETE FROM audit_log WHERE sequence <= ?1",
+                params![sequence as i64],
+            )
+            .context("Failed to delete audit entries")?;
+
+        Ok(rows_affected)
+    }
+
+    // ========================================================================
+    // Retention
+    // ========================================================================
+
+    /// Number and size of the rows in a retained store
+    pub fn retained_footprint(&self, rows: RetainedRows<'_>) -> Result<Footprint> {
+        let query = rows.query();
+        let (items, bytes): (i64, i64) = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*), COALESCE(SUM({}), 0) FROM {} WHERE {}",
+                    query.bytes, query.table, query.scope
+                ),
+                params_from_iter(rows.params()),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .context("Failed to measure retained rows")?;
+
+        Ok(Footprint {
+            items: items as u64,
+            bytes: bytes as u64,
+        })
+    }
+
+    /// Rows of a retained store a policy may remove, in removal order
+    pub fn list_retained(&self, rows: RetainedRows<'_>) -> Result<Vec<RetainedItem>> {
+        let query = rows.query();
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT rowid, {}, {} FROM {} WHERE {} AND {} ORDER BY {}",
+                query.timestamp,
+                query.bytes,
+                query.table,
+                query.scope,
+                query.removable,
+                query.order
+            ))
+            .context("Failed to prepare statement")?;
+
+        let items = stmt
+            .query_map(params_from_iter(rows.params()), |row| {
+                let timestamp: i64 = row.get(1)?;
+                let bytes: i64 = row.get(2)?;
+                Ok(RetainedItem {
+                    id: row.get(0)?,
+                    timestamp: timestamp as u64,
+                    bytes: bytes as u64,
+                })
+            })
+            .context("Failed to query retained rows")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read retained rows")?;
+
+        Ok(items)
+    }
+
+    /// Delete rows of a retained store by row ID
+    pub fn delete_retained(&self, rows: RetainedRows<'_>, ids: &[i64]) -> Result<usize> {
+        let sql = format!("DELETE FROM {} WHERE rowid = ?1", rows.query().table);
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to begin transaction")?;
+
+        let mut removed = 0;
+        for id in ids {
+            removed += tx
+                .execute(&sql, params![id])
+                .context("Failed to delete retained rows")?;
+        }
+
+        tx.commit().context("Failed to commit retention")?;
+        Ok(removed)
+    }
+}
+
+/// Rows kept under a retention policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetainedRows<'a> {
+    /// A user's notifications, oldest read ones first
+    Notifications {
+        /// User the notifications belong to
+        user_id: &'a str,
+    },
+    /// Crash reports, oldest first
+    CrashReports,
+    /// Audit log entries, in chain order
+    AuditLog,
+    /// Key usage counters, earliest ending bucket first
+    KeyUsage,
+    /// Provenance records, oldest first; the newest record of each
+    /// component is never removed
+    Provenance,
+    /// Snapshots of one app configuration, oldest first
+    ConfigSnapshots {
+        /// Application the configuration belongs to
+        app_id: &'a str,
+        /// User the configuration belongs to
+        user_id: &'a str,
+    },
+}
+
+/// SQL fragments selecting a retained store's rows
+struct RetainedQuery {
+    table: &'static str,
+    scope: &'static str,
+    timestamp: &'static str,
+    bytes: &'static str,
+    order: &'static str,
+    removable: &'static str,
+}
+
+impl RetainedRows<'_> {
+    fn query(&self) -> RetainedQuery {
+        match self {
+            Self::Notifications { .. } => RetainedQuery {
+                table: "notifications",
+                scope: "user_id = ?1",
+                timestamp: "posted_at",
+                bytes: "LENGTH(data)",
+                order: "read DESC, id ASC",
+                removable: "1",
+            },
+            Self::CrashReports => RetainedQuery {
+                table: "crash_reports",
+                scope: "1",
+                timestamp: "crashed_at",
+                bytes: "LENGTH(data)",
+                order: "crashed_at, rowid",
+                removable: "1",
+            },
+            Self::AuditLog => RetainedQuery {
+                table: "audit_log",
+                scope: "1",
+                timestamp: "json_extract(data, '$.timestamp')",
+                bytes: "LENGTH(data)",
+                order: "sequence",
+                removable: "1",
+            },
+            Self::KeyUsage => RetainedQuery {
+                table: "key_usage",
+                scope: "1",
+                timestamp: "bucket",
+                // Four integers besides the text columns
+                bytes: "LENGTH(granularity) + LENGTH(component_id) + LENGTH(operation) + 32",
+                // By bucket end, so a day's counter outlives its hours
+                order: "bucket + CASE granularity WHEN 'day' THEN 86400 ELSE 3600 END, rowid",
+                removable: "1",
+            },
+            Self::Provenance => RetainedQuery {
+                table: "component_provenance",
+                scope: "1",
+                timestamp: "downloaded_at",
+                bytes: "LENGTH(data)",
+                order: "downloaded_at, id",
+                removable: "id NOT IN (
+                    SELECT MAX(id) FROM component_provenance GROUP BY component_id
+                )",
+            },
+            Self::ConfigSnapshots { .. } => RetainedQuery {
+                table: "config_snapshots",
+                scope: "app_id = ?1 AND user_id = ?2",
+                timestamp: "created_at",
+                bytes: "LENGTH(content_encrypted)",
+                order: "id",
+                removable: "1",
+            },
+        }
+    }
+
+    fn params(&self) -> Vec<&str> {
+        match self {
+            Self::Notifications { user_id } => vec![user_id],
+            Self::ConfigSnapshots { app_id, user_id } => vec![app_id, user_id],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Read the schema version stamped on a database
+fn read_schema_version(conn: &Connection) -> Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("Failed to read schema version")
+}
+
+/// Refuse a database stamped by a newer release
+fn check_schema_version(path: &Path, version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        return Err(OsnovaError::SchemaTooNew {
+            path: path.to_path_buf(),
+            found: version,
+            supported: SCHEMA_VERSION,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Encrypt the content of a configuration snapshot
+fn encrypt_snapshot(content: &SnapshotContent, encryption_key: &[u8; 32]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(content).context("Failed to serialize snapshot")?;
+    CocoonEncryption::new(encryption_key)
+        .encrypt(&json)
+        .context("Failed to encrypt snapshot")
+}
+
+/// Decrypt the encrypted value of a row, with the row's context if it
+/// does not decrypt
+fn decrypt_row(
+    location: DecryptLocation,
+    encrypted: &[u8],
+    encryption_key: &[u8; 32],
+    class: Option<DataClass>,
+    key_source: Option<KeySource>,
+    modified: Option<u64>,
+) -> Result<Vec<u8>> {
+    CocoonEncryption::new(encryption_key)
+        .try_decrypt(encrypted)
+        .map_err(|failure: DecryptFailure| {
+            DecryptError {
+                location,
+                class,
+                key_source,
+                size: encrypted.len() as u64,
+                modified,
+                failure,
+            }
+            .into()
+        })
+}
+
+/// Decrypt a stored annotation
+fn decrypt_annotation(
+    user_id: &str,
+    subject: Subject,
+    encrypted: &[u8],
+    updated_at: u64,
+    encryption_key: &[u8; 32],
+) -> Result<Annotation> {
+    let decrypted = decrypt_row(
+        DecryptLocation::row(
+            "annotations",
+            format!("{}/{}/{}", user_id, subject.kind(), subject.id()),
+        ),
+        encrypted,
+        encryption_key,
+        None,
+        None,
+        Some(updated_at),
+    )?;
+    let text = String::from_utf8(decrypted).context("Annotation is not valid UTF-8")?;
+    Ok(Annotation {
+        subject,
+        text,
+        updated_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::application::{ComponentKind, ComponentRef};
+    use crate::models::pairing::PairingStatus;
+
+    fn create_test_app() -> OsnovaApplication {
+        let component = ComponentRef::new(
+            "component-001",
+            "Test Component",
+            ComponentKind::Frontend,
+            "1.0.0",
+        )
+        .unwrap();
+
+        OsnovaApplication::new(
+            "app-001",
+            "Test App",
+            "1.0.0",
+            "https://example.com/icon.png",
+            "Test application description",
+            vec![component],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_upsert_and_get_application() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let app = create_test_app();
+
+        // Insert
+        storage.upsert_application(&app)?;
+        let retrieved = storage.get_application(app.id())?;
+        assert!(retrieved.is_some());
+        let retrieved_app = retrieved.unwrap();
+        assert_eq!(retrieved_app.name(), app.name());
+
+        // Update
+        let component = ComponentRef::new(
+            "component-001",
+            "Test Component",
+            ComponentKind::Frontend,
+            "1.0.0",
+        )?;
+        let updated_app = OsnovaApplication::new(
+            app.id(),
+            "Updated App",
+            app.version(),
+            app.icon_uri(),
+            app.description(),
+            vec![component],
+        )?;
+        storage.upsert_application(&updated_app)?;
+        let retrieved = storage.get_application(updated_app.id())?;
+        assert_eq!(retrieved.unwrap().name(), "Updated App");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_applications() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+
+        let app1 = create_test_app();
+        let component2 = ComponentRef::new(
+            "component-002",
+            "Component 2",
+            ComponentKind::Backend,
+            "1.0.0",
+        )?;
+        let app2 = OsnovaApplication::new(
+            "app-002",
+            "Second App",
+            "1.0.0",
+            "https://example.com/icon2.png",
+            "Second app description",
+            vec![component2],
+        )?;
+
+        storage.upsert_application(&app1)?;
+        storage.upsert_application(&app2)?;
+
+        let apps = storage.list_applications()?;
+        assert_eq!(apps.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_version() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("osnova.db");
+        assert_eq!(SqlStorage::check_schema(&path)?, None);
+
+        drop(SqlStorage::new_for_install(&path, "test-install")?);
+        assert_eq!(SqlStorage::check_schema(&path)?, Some(SCHEMA_VERSION));
+
+        // A newer release's database is refused, not migrated
+        Connection::open(&path)?.pragma_update(None, "user_version", SCHEMA_VERSION + 1)?;
+        for err in [
+            SqlStorage::check_schema(&path).unwrap_err(),
+            SqlStorage::new_for_install(&path, "test-install")
+                .err()
+                .unwrap(),
+        ] {
+            assert!(matches!(
+                err.downcast_ref::<OsnovaError>(),
+                Some(OsnovaError::SchemaTooNew { found, .. }) if *found == SCHEMA_VERSION + 1
+            ));
+        }
+        assert_eq!(SqlStorage::schema_version(&path)?, Some(SCHEMA_VERSION + 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_read_only() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("osnova.db");
+        let storage = SqlStorage::new_for_install(&path, "test-install")?;
+        for app in ["app-a", "app-b", "app-c"] {
+            storage.record_app_launch(app, 100)?;
+        }
+
+        let rows = SqlStorage::query_read_only(
+            &path,
+            "SELECT app_id, launch_count FROM app_launches WHERE app_id != :skip ORDER BY app_id",
+            &[(":skip", serde_json::json!("app-b"))],
+            1,
+        )?;
+        assert_eq!(rows.columns, vec!["app_id", "launch_count"]);
+        assert_eq!(
+            rows.rows,
+            vec![vec![serde_json::json!("app-a"), serde_json::json!(1)]]
+        );
+        assert!(rows.truncated);
+
+        for sql in [
+            "DELETE FROM app_launches",
+            "SELECT 1; DELETE FROM app_launches",
+            "PRAGMA user_version = 9",
+        ] {
+            assert!(
+                SqlStorage::query_read_only(&path, sql, &[], 10).is_err(),
+                "{}",
+                sql
+            );
+        }
+        assert!(SqlStorage::query_read_only(
+            &path,
+            "SELECT :x",
+            &[(":x", serde_json::json!(1.5))],
+            10
+        )
+        .is_err());
+        let count =
+            SqlStorage::query_read_only(&path, "SELECT COUNT(*) FROM app_launches", &[], 1)?;
+        assert_eq!(count.rows, vec![vec![serde_json::json!(3)]]);
+        drop(storage);
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_snapshot_and_pin() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("osnova.db");
+
+        // A database from before snapshots and pins
+        Connection::open(&path)?.execute_batch(
+            "CREATE TABLE applications (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            );",
+        )?;
+        let app = create_test_app();
+        let storage = SqlStorage::new_for_install(&path, "test-install")?;
+        assert!(storage.get_manifest_snapshot(app.id())?.is_none());
+
+        storage.upsert_application(&app)?;
+        let snapshot = storage.get_manifest_snapshot(app.id())?.unwrap();
+        assert_eq!(OsnovaApplication::try_from(&snapshot)?, app);
+
+        assert!(storage.set_pinned_version(app.id(), Some("1.0.0"))?);
+        assert!(!storage.set_pinned_version("app-missing", Some("1.0.0"))?);
+
+        // Reinstalling replaces the snapshot and keeps the pin
+        let mut manifest = snapshot.clone();
+        manifest.description = "Resolved manifest".to_string();
+        storage.upsert_application_with_manifest(&app, &manifest)?;
+        assert_eq!(storage.get_manifest_snapshot(app.id())?, Some(manifest));
+        assert_eq!(
+            storage.get_pinned_version(app.id())?.as_deref(),
+            Some("1.0.0")
+        );
+
+        storage.set_pinned_version(app.id(), None)?;
+        assert!(storage.get_pinned_version(app.id())?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_applications_and_update() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        for id in ["app-a", "app-b", "app-c"] {
+            let app = OsnovaApplication::new(id, id, "1.0.0", "icon", "desc", vec![])?;
+            storage.upsert_application(&app)?;
+        }
+        storage.set_pinned_version("app-a", Some("1.0.0"))?;
+        assert!(storage.set_last_refreshed("app-a", 300)?);
+        assert!(storage.set_last_refreshed("app-b", 100)?);
+        assert!(!storage.set_last_refreshed("app-missing", 100)?);
+        assert_eq!(storage.get_last_refreshed("app-b")?, Some(100));
+        assert_eq!(storage.get_last_refreshed("app-c")?, None);
+
+        // Never refreshed first, then oldest first
+        assert_eq!(
+            storage.list_stale_applications(1_000, 10)?,
+            vec!["app-c", "app-b", "app-a"]
+        );
+        assert_eq!(
+            storage.list_stale_applications(200, 10)?,
+            vec!["app-c", "app-b"]
+        );
+        assert_eq!(storage.list_stale_applications(1_000, 1)?, vec!["app-c"]);
+
+        // Updating the record keeps the snapshot, pin and refresh time
+        let snapshot = storage.get_manifest_snapshot("app-a")?;
+        let renamed = OsnovaApplication::new("app-a", "Renamed", "1.0.0", "icon", "desc", vec![])?;
+        assert!(storage.update_application(&renamed)?);
+        assert_eq!(storage.get_application("app-a")?.unwrap().name(), "Renamed");
+        assert_eq!(storage.get_manifest_snapshot("app-a")?, snapshot);
+        assert_eq!(
+            storage.get_pinned_version("app-a")?.as_deref(),
+            Some("1.0.0")
+        );
+        assert_eq!(storage.get_last_refreshed("app-a")?, Some(300));
+
+        let missing = OsnovaApplication::new("app-missing", "x", "1.0.0", "icon", "desc", vec![])?;
+        assert!(!storage.update_application(&missing)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialization() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        for id in ["app-a", "app-b"] {
+            let app = OsnovaApplication::new(id, id, "1.0.0", "icon", "desc", vec![])?;
+            storage.upsert_application(&app)?;
+        }
+        let partial = Materialization::PartiallyMaterialized {
+            missing: vec!["backend".to_string()],
+        };
+        assert!(storage.set_materialization("app-a", &partial)?);
+        assert!(!storage.set_materialization("app-missing", &partial)?);
+        assert_eq!(storage.get_materialization("app-a")?, Some(partial.clone()));
+        assert_eq!(storage.get_materialization("app-b")?, None);
+        assert_eq!(
+            storage.list_materialization()?,
+            HashMap::from([("app-a".to_string(), partial)])
+        );
+
+        // Reinstalling keeps the state; the install records a new one
+        let app = storage.get_application("app-a")?.unwrap();
+        storage.upsert_application(&app)?;
+        assert!(storage.get_materialization("app-a")?.is_some());
+
+        assert_eq!(storage.mark_unmaterialized()?, vec!["app-a", "app-b"]);
+        for id in ["app-a", "app-b"] {
+            assert_eq!(
+                storage.get_materialization(id)?,
+                Some(Materialization::NotMaterialized)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handoffs_expire() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        assert!(storage.insert_handoff("h-1", "pair-1", "{\"n\":1}", 100)?);
+        assert!(storage.insert_handoff("h-2", "pair-1", "{\"n\":2}", 200)?);
+        assert!(!storage.insert_handoff("h-1", "pair-1", "{}", 300)?);
+
+        assert_eq!(storage.get_handoff("h-1")?.as_deref(), Some("{\"n\":1}"));
+        assert_eq!(storage.list_handoffs(50)?.len(), 2);
+        assert_eq!(storage.list_handoffs(100)?, vec!["{\"n\":2}".to_string()]);
+
+        assert_eq!(storage.delete_expired_handoffs(100)?, 1);
+        assert!(storage.delete_handoff("h-2")?);
+        assert!(!storage.delete_handoff("h-2")?);
+        assert!(storage.get_handoff("h-2")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_application() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let app = create_test_app();
+
+        storage.upsert_application(&app)?;
+        assert!(storage.delete_application(app.id())?);
+        assert!(storage.get_application(app.id())?.is_none());
+
+        // Delete non-existent
+        assert!(!storage.delete_application("nonexistent")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_and_restore_application() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let app = create_test_app();
+
+        storage.upsert_application(&app)?;
+        assert!(storage.trash_application(app.id(), 100, 200)?);
+        assert!(storage.get_application(app.id())?.is_none());
+
+        let trashed = storage.list_trashed_applications()?;
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].application().id(), app.id());
+        assert_eq!(trashed[0].deleted_at(), 100);
+        assert_eq!(trashed[0].purge_after(), 200);
+        assert!(trashed[0].launcher_positions().is_empty());
+
+        let positions = BTreeMap::from([("user-1".to_string(), OrderKey::between(None, None))]);
+        assert!(storage.set_trashed_launcher_positions(app.id(), &positions)?);
+        let trashed = storage.get_trashed_application(app.id())?.unwrap();
+        assert_eq!(trashed.launcher_positions(), &positions);
+
+        assert!(storage.restore_application(app.id())?);
+        assert!(storage.get_application(app.id())?.is_some());
+        assert!(storage.get_trashed_application(app.id())?.is_none());
+
+        // Nothing to trash or restore
+        assert!(!storage.trash_application("nonexistent", 100, 200)?);
+        assert!(!storage.restore_application(app.id())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_trashed_application_removes_configs() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let app = create_test_app();
+        let key = [42u8; 32];
+
+        storage.upsert_application(&app)?;
+        let config = AppConfiguration::new(app.id(), "user-001");
+        storage.set_app_config(app.id(), "user-001", &config, &key)?;
+
+        storage.trash_application(app.id(), 100, 200)?;
+        assert!(storage
+            .get_app_config(app.id(), "user-001", &key)?
+            .is_some());
+
+        assert!(storage.purge_trashed_application(app.id())?);
+        assert!(storage.get_trashed_application(app.id())?.is_none());
+        assert!(storage
+            .get_app_config(app.id(), "user-001", &key)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_version_history() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let app = create_test_app();
+        storage.upsert_application(&app)?;
+
+        for replaced_at in 1..=4 {
+            storage.push_app_version(&app, replaced_at, 3)?;
+        }
+        let versions = storage.list_app_versions(app.id())?;
+        assert_eq!(
+            versions.iter().map(|(_, _, at)| *at).collect::<Vec<_>>(),
+            vec![4, 3, 2]
+        );
+
+        assert!(storage.delete_app_version(versions[0].0)?);
+        assert_eq!(storage.list_app_versions(app.id())?.len(), 2);
+
+        storage.set_pending_update(app.id(), Some(&app))?;
+        storage.set_skipped_update(app.id(), Some("2.0.0"))?;
+        assert_eq!(storage.get_pending_update(app.id())?, Some(app.clone()));
+        storage.set_pending_update(app.id(), None)?;
+        assert!(storage.get_pending_update(app.id())?.is_none());
+        assert_eq!(
+            storage.get_skipped_update(app.id())?.as_deref(),
+            Some("2.0.0")
+        );
+
+        // Trashing keeps the history; purging removes it
+        storage.trash_application(app.id(), 100, 200)?;
+        assert_eq!(storage.list_app_versions(app.id())?.len(), 2);
+        storage.purge_trashed_application(app.id())?;
+        assert!(storage.list_app_versions(app.id())?.is_empty());
+        assert!(storage.get_skipped_update(app.id())?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bandwidth_usage_accumulates() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+
+        storage.add_bandwidth_usage(10, "upload", 5)?;
+        storage.add_bandwidth_usage(10, "upload", 7)?;
+        storage.add_bandwidth_usage(11, "prefetch", 3)?;
+        storage.add_bandwidth_usage(12, "upload", 1)?;
+
+        let rows = storage.get_bandwidth_usage(10, 11)?;
+        assert_eq!(
+            rows,
+            vec![
+                (10, "upload".to_string(), 12),
+                (11, "prefetch".to_string(), 3)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_usage_accumulates_per_key() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let hour = UsageGranularity::Hour;
+
+        storage.add_key_usage(hour, 3600, "com.wallet", KeyOperation::Derive, &[0, 1])?;
+        storage.add_key_usage(hour, 3600, "com.wallet", KeyOperation::Derive, &[1])?;
+        storage.add_key_usage(hour, 7200, "com.wallet", KeyOperation::Sign, &[1])?;
+        storage.add_key_usage(hour, 3600, "com.other", KeyOperation::Derive, &[0])?;
+        storage.add_key_usage(
+            UsageGranularity::Day,
+            0,
+            "com.wallet",
+            KeyOperation::Sign,
+            &[1],
+        )?;
+
+        let rows = storage.list_key_usage(hour, "com.wallet", 0, 7200)?;
+        let counts: Vec<(u64, u64)> = rows.iter().map(|r| (r.key_index, r.count)).collect();
+        assert_eq!(counts, vec![(0, 1), (1, 2)]);
+        assert_eq!(storage.count_key_usage(hour, "com.wallet", 0, 10_800)?, 4);
+
+        assert_eq!(storage.prune_key_usage(hour, 7200)?, 3);
+        assert_eq!(storage.count_key_usage(hour, "com.wallet", 0, 10_800)?, 1);
+        assert_eq!(
+            storage.count_key_usage(UsageGranularity::Day, "com.wallet", 0, 86_400)?,
+            1
+        );
+
+        let anomaly = |component_id: &str, detected_at| KeyUsageAnomaly {
+            component_id: component_id.to_string(),
+            hour: detected_at - detected_at % 3600,
+            hourly_count: 500,
+            trailing_count: 60,
+            trailing_hours: 24,
+            detected_at,
+        };
+        storage.insert_key_usage_anomaly(&anomaly("com.wallet", 100))?;
+        storage.insert_key_usage_anomaly(&anomaly("com.other", 200))?;
+        storage.insert_key_usage_anomaly(&anomaly("com.wallet", 300))?;
+        let wallet = storage.list_key_usage_anomalies(Some("com.wallet"), 0)?;
+        assert_eq!(
+            wallet,
+            vec![anomaly("com.wallet", 300), anomaly("com.wallet", 100)]
+        );
+        assert_eq!(storage.list_key_usage_anomalies(None, 150)?.len(), 2);
+        assert_eq!(storage.prune_key_usage_anomalies(250)?, 2);
+        assert_eq!(storage.list_key_usage_anomalies(None, 0)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backend_process_registry() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+
+        let first = BackendProcess::new(
+            "com.test.app",
+            4242,
+            "100",
+            "backend --serve",
+            1_700_000_000,
+        )
+        .with_socket_path("/tmp/test.sock");
+        let second = BackendProcess::new("com.other.app", 4343, "200", "other", 1_700_000_000);
+        storage.register_backend_process(&first)?;
+        storage.register_backend_process(&second)?;
+
+        assert_eq!(
+            storage.list_backend_processes()?,
+            vec![first, second.clone()]
+        );
+
+        assert!(storage.remove_backend_process(4242)?);
+        assert!(!storage.remove_backend_process(4242)?);
+        assert_eq!(storage.list_backend_processes()?, vec![second]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crash_reports() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let report = |id: &str, app_id: &str, crashed_at: u64| CrashReport {
+            id: id.to_string(),
+            app_id: app_id.to_string(),
+            component_id: Some("ant://backend".to_string()),
+            component_version: Some("1.0.0".to_string()),
+            pid: 4242,
+            exit_code: None,
+            signal: Some(11),
+            started_at: 100,
+            crashed_at,
+            stderr_tail: vec!["segfault".to_string()],
+        };
+
+        storage.insert_crash_report(&report("a", "com.test.app", 200))?;
+        storage.insert_crash_report(&report("b", "com.test.app", 300))?;
+        storage.insert_crash_report(&report("c", "com.other.app", 250))?;
+
+        let ids = |reports: Vec<CrashReport>| -> Vec<String> {
+            reports.into_iter().map(|r| r.id).collect()
+        };
+        assert_eq!(ids(storage.list_crash_reports("com.test.app")?), ["b", "a"]);
+        assert_eq!(ids(storage.list_recent_crash_reports(2)?), ["b", "c"]);
+        assert_eq!(
+            storage.get_crash_report("a")?,
+            Some(report("a", "com.test.app", 200))
+        );
+        assert_eq!(storage.get_crash_report("missing")?, None);
+
+        // Pruning keeps the newest reports of that owner only
+        assert_eq!(storage.prune_crash_reports("com.test.app", 1)?, 1);
+        assert_eq!(ids(storage.list_crash_reports("com.test.app")?), ["b"]);
+        assert_eq!(storage.list_crash_reports("com.other.app")?.len(), 1);
+
+        assert_eq!(storage.get_acknowledged_crash("com.test.app")?, None);
+        storage.acknowledge_crash("com.test.app", "a")?;
+        storage.acknowledge_crash("com.test.app", "b")?;
+        assert_eq!(
+            storage.get_acknowledged_crash("com.test.app")?.as_deref(),
+            Some("b")
+        );
+        assert_eq!(storage.get_acknowledged_crash("com.other.app")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_health_transitions() -> Result<()> {
+        use crate::models::health::HealthStatus;
+
+        let storage = SqlStorage::new_in_memory()?;
+        let transition = |app_id: &str, at: u64| HealthTransition {
+            app_id: app_id.to_string(),
+            component_id: "ant://backend".to_string(),
+            from: HealthStatus::Healthy,
+            to: HealthStatus::Unhealthy,
+            reason: "3 health checks failed in a row".to_string(),
+            restarts: 0,
+            at,
+        };
+
+        for at in [100, 200, 300] {
+            storage.record_health_transition(&transition("com.test.app", at), 2)?;
+        }
+        storage.record_health_transition(&transition("com.other.app", 150), 2)?;
+
+        let at = |transitions: Vec<HealthTransition>| -> Vec<u64> {
+            transitions.into_iter().map(|t| t.at).collect()
+        };
+        assert_eq!(
+            at(storage.list_health_transitions("com.test.app")?),
+            [300, 200]
+        );
+        assert_eq!(at(storage.list_health_transitions("com.other.app")?), [150]);
+        assert!(storage.list_health_transitions("com.none.app")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reproducibility_checks() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let check = |version: &str, component_id: &str, verified_at: u64| ReproducibilityCheck {
+            app_id: "com.test.app".to_string(),
+            app_version: version.to_string(),
+            component_id: component_id.to_string(),
+            hash: "hash".to_string(),
+            verified_at,
+        };
+
+        storage.record_reproducibility_check(&check("1.0.0", "ant://frontend", 100))?;
+        storage.record_reproducibility_check(&check("1.0.0", "ant://backend", 100))?;
+        storage.record_reproducibility_check(&check("1.0.0", "ant://frontend", 200))?;
+        storage.record_reproducibility_check(&check("2.0.0", "ant://frontend", 300))?;
+
+        let checks = storage.list_reproducibility_checks("com.test.app", "1.0.0")?;
+        assert_eq!(
+            checks,
+            [
+                check("1.0.0", "ant://backend", 100),
+                check("1.0.0", "ant://frontend", 200)
+            ]
+        );
+        assert!(storage
+            .list_reproducibility_checks("com.test.app", "3.0.0")?
+            .is_empty());
+
+        assert_eq!(storage.delete_reproducibility_checks("com.test.app")?, 3);
+        assert!(storage
+            .list_reproducibility_checks("com.test.app", "2.0.0")?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_observations_accumulate() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let observed = |host: &str, allowed: u64, denied: u64, at: u64| ObservedHost {
+            component_id: "ant://backend".to_string(),
+            host: host.to_string(),
+            allowed,
+            denied,
+            bytes_sent: allowed * 10,
+            bytes_received: allowed * 100,
+            first_seen: at,
+            last_seen: at,
+        };
+
+        storage.add_network_observation("com.test.app", &observed("b.example.com", 1, 0, 200))?;
+        storage.add_network_observation("com.test.app", &observed("b.example.com", 2, 1, 100))?;
+        storage.add_network_observation("com.test.app", &observed("a.example.com", 0, 1, 300))?;
+        storage.add_network_observation("com.other.app", &observed("a.example.com", 1, 0, 300))?;
+
+        let observations = storage.list_network_observations("com.test.app")?;
+        assert_eq!(
+            observations,
+            [
+                observed("a.example.com", 0, 1, 300),
+                ObservedHost {
+                    first_seen: 100,
+                    last_seen: 200,
+                    ..observed("b.example.com", 3, 1, 0)
+                },
+            ]
+        );
+
+        storage.purge_trashed_application("com.test.app")?;
+        assert!(storage
+            .list_network_observations("com.test.app")?
+            .is_empty());
+        assert_eq!(storage.list_network_observations("com.other.app")?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotations() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let key = [3u8; 32];
+        let note = |subject: Subject, text: &str| Annotation {
+            subject,
+            text: text.to_string(),
+            updated_at: 100,
+        };
+        let app = Subject::App("com.test.app".to_string());
+        let device = Subject::Device("device-1".to_string());
+
+        storage.set_annotation("user-1", &note(device.clone(), "Kitchen tablet"), &key)?;
+        storage.set_annotation("user-1", &note(app.clone(), "Approved for kids"), &key)?;
+        storage.set_annotation(
+            "user-1",
+            &note(app.clone(), "Pinned: v2 breaks plugin X"),
+            &key,
+        )?;
+
+        assert_eq!(
+            storage
+                .get_annotation("user-1", &app, &key)?
+                .map(|a| a.text),
+            Some("Pinned: v2 breaks plugin X".to_string())
+        );
+        let subjects: Vec<Subject> = storage
+            .list_annotations("user-1", &key)?
+            .into_iter()
+            .map(|a| a.subject)
+            .collect();
+        assert_eq!(subjects, [app.clone(), device.clone()]);
+        assert!(storage.list_annotations("user-2", &key)?.is_empty());
+
+        // Stored encrypted, and unreadable under another key
+        let raw: Vec<u8> = storage.conn.query_row(
+            "SELECT value_encrypted FROM annotations WHERE subject_kind = 'device'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert!(!raw.windows(7).any(|w| w == b"Kitchen"));
+        assert!(storage
+            .get_annotation("user-1", &device, &[4u8; 32])
+            .is_err());
+
+        assert!(storage.delete_annotation("user-1", &device)?);
+        assert!(!storage.delete_annotation("user-1", &device)?);
+        assert!(storage.get_annotation("user-1", &device, &key)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_journal() -> Result<()> {
+        use crate::models::install_journal::InstallStep;
+
+        let storage = SqlStorage::new_in_memory()?;
+        let app = OsnovaApplication::new(
+            "com.test.app",
+            "Test App",
+            "1.0.0",
+            "https://icon.url",
+            "Test app",
+            vec![],
+        )?;
+        let mut entry = InstallJournalEntry {
+            app_id: "com.test.app".to_string(),
+            manifest: ManifestSchema::from(&app),
+            step: InstallStep::Started,
+            previously_installed: false,
+            started_at: 100,
+            updated_at: 100,
+        };
+
+        assert_eq!(storage.get_install_journal("com.test.app")?, None);
+        storage.put_install_journal(&entry)?;
+        entry.step = InstallStep::ComponentsFetched;
+        storage.put_install_journal(&entry)?;
+        assert_eq!(
+            storage.get_install_journal("com.test.app")?,
+            Some(entry.clone())
+        );
+        assert_eq!(storage.list_install_journal()?, vec![entry]);
+
+        assert!(storage.delete_install_journal("com.test.app")?);
+        assert!(!storage.delete_install_journal("com.test.app")?);
+        assert!(storage.list_install_journal()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_component_registry() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+
+        let v1 = ComponentRef::new("ant://sync-1", "Sync", ComponentKind::Backend, "1.0.0")?
+            .with_hash("h1")
+            .with_shared_id("org.test.sync");
+        let v2 = ComponentRef::new("ant://sync-2", "Sync", ComponentKind::Backend, "2.0.0")?
+            .with_hash("h2")
+            .with_shared_id("org.test.sync");
+        storage.register_shared_component(&v1)?;
+        storage.register_shared_component(&v2)?;
+        storage.register_shared_component(&v1)?;
 
-    /// Set app configuration (encrypted at rest)
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if encryption or database write fails
-    pub fn set_app_config(
-        &self,
-        app_id: &str,
-        user_id: &str,
-        config: &AppConfiguration,
-        encryption_key: &[u8; 32],
-    ) -> Result<()> {
-        let config_json = serde_json::to_vec(config).context("Failed to serialize config")?;
-        let encryption = CocoonEncryption::new(encryption_key);
-        let encrypted = encryption
-            .encrypt(&config_json)
-            .context("Failed to encrypt config")?;
+        assert_eq!(
+            storage.list_shared_components()?,
+            vec![v1.clone(), v2.clone()]
+        );
 
-        self.conn
-            .execute(
-                "INSERT INTO app_configurations (app_id, user_id, settings_encrypted, updated_at)
-             VALUES (?1, ?2, ?3, strftime('%s', 'now'))
-             ON CONFLICT(app_id, user_id) DO UPDATE SET
-                settings_encrypted = excluded.settings_encrypted,
-                updated_at = excluded.updated_at",
-                params![app_id, user_id, &encrypted],
-            )
-            .context("Failed to upsert app configuration")?;
+        assert!(storage.remove_shared_component(&v1.shared_key().unwrap())?);
+        assert!(!storage.remove_shared_component(&v1.shared_key().unwrap())?);
+        assert_eq!(storage.list_shared_components()?, vec![v2]);
+
+        // Private components are not registered
+        let private = ComponentRef::new("ant://ui", "UI", ComponentKind::Frontend, "1.0.0")?;
+        assert!(storage.register_shared_component(&private).is_err());
 
         Ok(())
     }
 
-    /// Get app configuration (decrypted)
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if decryption fails or configuration doesn't exist
-    pub fn get_app_config(
-        &self,
-        app_id: &str,
-        user_id: &str,
-        encryption_key: &[u8; 32],
-    ) -> Result<Option<AppConfiguration>> {
-        let encrypted: Option<Vec<u8>> = self
-            .conn
-            .query_row(
-                "SELECT settings_encrypted FROM app_configurations
-                 WHERE app_id = ?1 AND user_id = ?2",
-                params![app_id, user_id],
-                |row| row.get(0),
-            )
-            .optional()
-            .context("Failed to query app configuration")?;
+    #[test]
+    fn test_backup_checks_disk_space() -> Result<()> {
+        use crate::error::OsnovaError;
+        use crate::platform::disk::FixedDiskSpace;
+        use std::sync::Arc;
 
-        match encrypted {
-            Some(data) => {
-                let encryption = CocoonEncryption::new(encryption_key);
-                let decrypted = encryption
-                    .decrypt(&data)
-                    .context("Failed to decrypt config")?;
-                let config: AppConfiguration =
-                    serde_json::from_slice(&decrypted).context("Failed to deserialize config")?;
-                Ok(Some(config))
+        let temp = tempfile::TempDir::new()?;
+        let disk = Arc::new(FixedDiskSpace::new(1024));
+        let storage = SqlStorage::new(temp.path().join("osnova.db"))?
+            .with_disk_guard(DiskGuard::new(1024).with_provider(disk.clone()));
+        storage.set_encrypted_blob("k", b"value", &[7u8; 32])?;
+
+        let dest = temp.path().join("backup.db");
+        let err = storage.backup_to(&dest).unwrap_err();
+        match err.downcast_ref::<OsnovaError>() {
+            Some(OsnovaError::DiskSpaceLow {
+                available,
+                required,
+                ..
+            }) => {
+                assert_eq!(*available, 1024);
+                assert!(*required > 1024);
             }
-            None => Ok(None),
+            other => panic!("unexpected error: {:?}", other),
         }
-    }
+        assert!(!dest.exists());
 
-    /// Delete app configuration
-    pub fn delete_app_config(&self, app_id: &str, user_id: &str) -> Result<bool> {
-        let rows_affected = self
-            .conn
-            .execute(
-                "DELETE FROM app_configurations WHERE app_id = ?1 AND user_id = ?2",
-                params![app_id, user_id],
-            )
-            .context("Failed to delete app configuration")?;
+        disk.set(u64::MAX);
+        assert!(storage.backup_to(&dest)? > 0);
+        let restored = SqlStorage::new(&dest)?;
+        assert_eq!(
+            restored.get_encrypted_blob("k", &[7u8; 32])?,
+            Some(b"value".to_vec())
+        );
 
-        Ok(rows_affected > 0)
+        Ok(())
     }
 
-    // ========================================================================
-    // Encrypted Blob Storage
-    // ========================================================================
+    #[test]
+    fn test_remote_session_operations() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let session = |device_id: &str, created_at: u64| RemoteSession {
+            device_id: device_id.to_string(),
+            created_at,
+            expires_at: created_at + 100,
+            last_seen: created_at,
+        };
 
-    /// Store an encrypted blob
-    pub fn set_encrypted_blob(
-        &self,
-        key: &str,
-        value: &[u8],
-        encryption_key: &[u8; 32],
-    ) -> Result<()> {
-        let encryption = CocoonEncryption::new(encryption_key);
-        let encrypted = encryption
-            .encrypt(value)
-            .context("Failed to encrypt blob")?;
+        storage.upsert_remote_session("hash-a", &session("device-1", 10))?;
+        storage.upsert_remote_session("hash-b", &session("device-1", 20))?;
+        storage.upsert_remote_session("hash-c", &session("device-2", 5))?;
 
-        self.conn
-            .execute(
-                "INSERT INTO encrypted_blobs (key, value_encrypted, updated_at)
-             VALUES (?1, ?2, strftime('%s', 'now'))
-             ON CONFLICT(key) DO UPDATE SET
-                value_encrypted = excluded.value_encrypted,
-                updated_at = excluded.updated_at",
-                params![key, &encrypted],
-            )
-            .context("Failed to upsert encrypted blob")?;
+        let mut renewed = session("device-1", 10);
+        renewed.expires_at = 500;
+        storage.upsert_remote_session("hash-a", &renewed)?;
+        assert_eq!(storage.get_remote_session("hash-a")?, Some(renewed));
+        assert_eq!(storage.list_remote_sessions()?[0].device_id, "device-2");
+
+        assert_eq!(storage.delete_expired_remote_sessions(120)?, 2);
+        assert!(storage.get_remote_session("hash-b")?.is_none());
+        assert_eq!(storage.delete_remote_sessions_for_device("device-1")?, 1);
+        assert!(!storage.delete_remote_session("hash-a")?);
+        assert!(storage.list_remote_sessions()?.is_empty());
 
         Ok(())
     }
 
-    /// Retrieve and decrypt a blob
-    pub fn get_encrypted_blob(
-        &self,
-        key: &str,
-        encryption_key: &[u8; 32],
-    ) -> Result<Option<Vec<u8>>> {
-        let encrypted: Option<Vec<u8>> = self
-            .conn
-            .query_row(
-                "SELECT value_encrypted FROM encrypted_blobs WHERE key = ?1",
-                params![key],
-                |row| row.get(0),
-            )
-            .optional()
-            .context("Failed to query encrypted blob")?;
+    #[test]
+    fn test_device_permission_operations() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
 
-        match encrypted {
-            Some(data) => {
-                let encryption = CocoonEncryption::new(encryption_key);
-                let decrypted = encryption
-                    .decrypt(&data)
-                    .context("Failed to decrypt blob")?;
-                Ok(Some(decrypted))
-            }
-            None => Ok(None),
-        }
-    }
+        assert!(storage.grant_device_permission("device-1", "logs.read", 10)?);
+        assert!(!storage.grant_device_permission("device-1", "logs.read", 20)?);
+        storage.grant_device_permission("device-1", "apps.list", 10)?;
+        storage.grant_device_permission("device-2", "logs.read", 10)?;
 
-    /// Delete an encrypted blob
-    pub fn delete_encrypted_blob(&self, key: &str) -> Result<bool> {
-        let rows_affected = self
-            .conn
-            .execute("DELETE FROM encrypted_blobs WHERE key = ?1", params![key])
-            .context("Failed to delete encrypted blob")?;
+        assert!(storage.has_device_permission("device-1", "logs.read")?);
+        assert!(!storage.has_device_permission("device-3", "logs.read")?);
+        assert_eq!(
+            storage.list_device_permissions("device-1")?,
+            vec!["apps.list", "logs.read"]
+        );
 
-        Ok(rows_affected > 0)
+        assert_eq!(
+            storage.revoke_device_permission("device-1", Some("logs.read"))?,
+            1
+        );
+        assert_eq!(storage.revoke_device_permission("device-1", None)?, 1);
+        assert!(storage.list_device_permissions("device-1")?.is_empty());
+        assert!(storage.has_device_permission("device-2", "logs.read")?);
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::application::{ComponentKind, ComponentRef};
-    use crate::models::pairing::PairingStatus;
+    #[test]
+    fn test_launcher_change_operations() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        assert_eq!(storage.launcher_generation()?, 0);
+        assert_eq!(storage.oldest_launcher_change()?, None);
 
-    fn create_test_app() -> OsnovaApplication {
-        let component = ComponentRef::new(
-            "component-001",
-            "Test Component",
-            ComponentKind::Frontend,
-            "1.0.0",
-        )
-        .unwrap();
+        let added = storage.record_launcher_change(
+            LauncherChangeKind::Added,
+            Some("com.test.app"),
+            None,
+        )?;
+        let layout =
+            storage.record_launcher_change(LauncherChangeKind::Layout, None, Some("user-1"))?;
+        assert_eq!((added, layout), (1, 2));
+        assert_eq!(storage.launcher_generation()?, 2);
 
-        OsnovaApplication::new(
-            "app-001",
-            "Test App",
-            "1.0.0",
-            "https://example.com/icon.png",
-            "Test application description",
-            vec![component],
-        )
-        .unwrap()
+        let changes = storage.list_launcher_changes_since(1)?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, LauncherChangeKind::Layout);
+        assert_eq!(changes[0].user_id.as_deref(), Some("user-1"));
+
+        for _ in 0..LAUNCHER_CHANGE_HISTORY {
+            storage.record_launcher_change(LauncherChangeKind::Updated, Some("com.x"), None)?;
+        }
+        assert_eq!(storage.launcher_generation()?, LAUNCHER_CHANGE_HISTORY + 2);
+        assert_eq!(storage.oldest_launcher_change()?, Some(3));
+
+        Ok(())
     }
 
     #[test]
-    fn test_upsert_and_get_application() -> Result<()> {
+    fn test_app_consent_operations() -> Result<()> {
         let storage = SqlStorage::new_in_memory()?;
-        let app = create_test_app();
+        let consent = |version: &str, accepted: bool| AppConsent {
+            app_id: "com.test.app".to_string(),
+            version: version.to_string(),
+            accepted,
+            acknowledged_warnings: vec!["unsigned".to_string()],
+            permissions: vec![],
+            decided_at: 10,
+        };
 
-        // Insert
-        storage.upsert_application(&app)?;
-        let retrieved = storage.get_application(app.id())?;
-        assert!(retrieved.is_some());
-        let retrieved_app = retrieved.unwrap();
-        assert_eq!(retrieved_app.name(), app.name());
+        assert!(storage.get_app_consent("user-1", "com.test.app")?.is_none());
+        storage.upsert_app_consent("user-1", &consent("1.0.0", false))?;
+        storage.upsert_app_consent("user-1", &consent("1.1.0", true))?;
+        storage.upsert_app_consent("user-2", &consent("1.0.0", false))?;
 
-        // Update
-        let component = ComponentRef::new(
-            "component-001",
-            "Test Component",
-            ComponentKind::Frontend,
-            "1.0.0",
-        )?;
-        let updated_app = OsnovaApplication::new(
-            app.id(),
-            "Updated App",
-            app.version(),
-            app.icon_uri(),
-            app.description(),
-            vec![component],
-        )?;
-        storage.upsert_application(&updated_app)?;
-        let retrieved = storage.get_application(updated_app.id())?;
-        assert_eq!(retrieved.unwrap().name(), "Updated App");
+        assert_eq!(
+            storage.get_app_consent("user-1", "com.test.app")?,
+            Some(consent("1.1.0", true))
+        );
+        assert_eq!(storage.delete_app_consents("com.test.app")?, 2);
+        assert!(storage.get_app_consent("user-2", "com.test.app")?.is_none());
 
         Ok(())
     }
 
     #[test]
-    fn test_list_applications() -> Result<()> {
+    fn test_notification_operations() -> Result<()> {
         let storage = SqlStorage::new_in_memory()?;
+        let post = |app_id: &str, title: &str| {
+            storage.insert_notification("user-1", app_id, 10, &Notification::new(title, ""))
+        };
 
-        let app1 = create_test_app();
-        let component2 = ComponentRef::new(
-            "component-002",
-            "Component 2",
-            ComponentKind::Backend,
-            "1.0.0",
-        )?;
-        let app2 = OsnovaApplication::new(
-            "app-002",
-            "Second App",
-            "1.0.0",
-            "https://example.com/icon2.png",
-            "Second app description",
-            vec![component2],
+        let first = post("com.a", "one")?;
+        let second = post("com.a", "two")?;
+        let third = post("com.b", "three")?;
+        storage.insert_notification(
+            "user-2",
+            "com.a",
+            10,
+            &Notification::new("other user", "").with_tag("t"),
         )?;
 
-        storage.upsert_application(&app1)?;
-        storage.upsert_application(&app2)?;
+        let listed = storage.list_notifications("user-1")?;
+        assert_eq!(
+            listed.iter().map(|n| n.id).collect::<Vec<_>>(),
+            vec![third, second, first]
+        );
+        assert!(storage.has_notification_tag("user-2", "com.a", "t")?);
+        assert!(!storage.has_notification_tag("user-1", "com.a", "t")?);
 
-        let apps = storage.list_applications()?;
-        assert_eq!(apps.len(), 2);
+        assert_eq!(
+            storage.mark_notifications_read("user-1", &[second, second])?,
+            1
+        );
+        assert_eq!(storage.mark_notifications_read("user-2", &[first])?, 0);
+
+        // The read notification goes first even though it is not the oldest
+        assert_eq!(storage.prune_notifications("user-1", 2)?, 1);
+        let remaining = storage.list_notifications("user-1")?;
+        assert_eq!(
+            remaining.iter().map(|n| n.id).collect::<Vec<_>>(),
+            vec![third, first]
+        );
+
+        assert_eq!(storage.delete_notifications_for_app("user-1", "com.a")?, 1);
+        assert_eq!(storage.list_notifications("user-2")?.len(), 1);
 
         Ok(())
     }
 
     #[test]
-    fn test_delete_application() -> Result<()> {
+    fn test_retained_rows() -> Result<()> {
         let storage = SqlStorage::new_in_memory()?;
-        let app = create_test_app();
+        let rows = RetainedRows::Notifications { user_id: "user-1" };
+        let post = |user_id: &str, title: &str, posted_at: u64| {
+            storage.insert_notification(user_id, "com.a", posted_at, &Notification::new(title, ""))
+        };
 
-        storage.upsert_application(&app)?;
-        assert!(storage.delete_application(app.id())?);
-        assert!(storage.get_application(app.id())?.is_none());
+        let first = post("user-1", "one", 10)?;
+        let second = post("user-1", "two", 20)?;
+        post("user-2", "other", 30)?;
+        storage.mark_notifications_read("user-1", &[second])?;
 
-        // Delete non-existent
-        assert!(!storage.delete_application("nonexistent")?);
+        let footprint = storage.retained_footprint(rows)?;
+        assert_eq!(footprint.items, 2);
+        assert!(footprint.bytes > 0);
+
+        // Listed in removal order: read notifications first
+        let items = storage.list_retained(rows)?;
+        assert_eq!(
+            items
+                .iter()
+                .map(|i| (i.id, i.timestamp))
+                .collect::<Vec<_>>(),
+            vec![(second, 20), (first, 10)]
+        );
+        assert_eq!(items.iter().map(|i| i.bytes).sum::<u64>(), footprint.bytes);
+
+        assert_eq!(storage.delete_retained(rows, &[second])?, 1);
+        assert_eq!(storage.retained_footprint(rows)?.items, 1);
+        assert_eq!(
+            storage
+                .retained_footprint(RetainedRows::Notifications { user_id: "user-2" })?
+                .items,
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_provenance_history_and_pruning() -> Result<()> {
+        use crate::models::provenance::VerificationOutcome;
+
+        let storage = SqlStorage::new_in_memory()?;
+        let record = |id: &str, hash: &str, downloaded_at: u64| ProvenanceRecord {
+            component_id: id.to_string(),
+            component_version: "1.0.0".to_string(),
+            component_hash: hash.to_string(),
+            source_uri: id.to_string(),
+            origin: None,
+            downloaded_at,
+            verification: VerificationOutcome::NoHashDeclared,
+            downloader_version: "0.1.0".to_string(),
+            content_check: None,
+            network: None,
+        };
+
+        storage.append_provenance(&record("ant://ui", "h1", 100))?;
+        storage.append_provenance(&record("ant://ui", "h1", 200))?;
+        storage.append_provenance(&record("ant://ui", "h2", 300))?;
+        storage.append_provenance(&record("ant://backend", "h3", 50))?;
+
+        let history = storage.list_provenance_by_component("ant://ui")?;
+        assert_eq!(
+            history.iter().map(|r| r.downloaded_at).collect::<Vec<_>>(),
+            vec![300, 200, 100]
+        );
+        assert_eq!(storage.list_provenance_by_hash("h1")?.len(), 2);
+
+        // Old records go, but each component keeps its newest one
+        assert_eq!(storage.prune_provenance(250)?, 2);
+        assert_eq!(storage.list_provenance_by_component("ant://ui")?.len(), 1);
+        assert_eq!(
+            storage.list_provenance_by_component("ant://backend")?.len(),
+            1
+        );
 
         Ok(())
     }
@@ -687,6 +6177,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pairing_session_awaiting_confirmation_round_trip() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("osnova.db");
+
+        // A table from before the confirmation step, with one session in it
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "CREATE TABLE pairing_sessions (
+                session_id TEXT PRIMARY KEY,
+                server_public_key BLOB NOT NULL,
+                device_public_key BLOB NOT NULL,
+                established_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL,
+                status TEXT NOT NULL CHECK(status IN ('pending', 'established', 'failed'))
+            );",
+        )?;
+        conn.execute(
+            "INSERT INTO pairing_sessions VALUES ('old', ?1, ?2, 5000, 9000, 'established')",
+            params![[1u8; 32].to_vec(), [2u8; 32].to_vec()],
+        )?;
+        drop(conn);
+
+        let storage = SqlStorage::new(&path)?;
+        let old = storage.get_pairing_session("old")?.unwrap();
+        assert_eq!(old.established_at(), Some(5000));
+        assert_eq!(old.expires_at(), Some(9000));
+
+        let mut session = PairingSession::new("session-002", &[1u8; 32], &[2u8; 32])?;
+        session.await_confirmation("042917", 7000);
+        session.record_confirmation(true, 6000);
+        storage.upsert_pairing_session(&session)?;
+
+        let retrieved = storage.get_pairing_session("session-002")?.unwrap();
+        assert_eq!(retrieved, session);
+        assert_eq!(
+            storage.list_pairing_sessions_by_status("awaiting_confirmation")?,
+            vec![session]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_encrypted_config_operations() -> Result<()> {
         let storage = SqlStorage::new_in_memory()?;
@@ -740,6 +6272,84 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_encrypted_blob_classification() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let key = [99u8; 32];
+        storage.set_encrypted_blob_classified("wallet/seed", b"seed", &key, DataClass::Secret)?;
+        storage.set_encrypted_blob_classified("notes/today", b"milk", &key, DataClass::Personal)?;
+        storage.set_encrypted_blob_classified("prefs", b"dark", &key, DataClass::Preferences)?;
+        storage.set_encrypted_blob("legacy", b"old", &key)?;
+
+        // Rewriting without a class keeps the class
+        storage.set_encrypted_blob("wallet/seed", b"seed2", &key)?;
+        assert_eq!(
+            storage.encrypted_blob_class("wallet/seed")?,
+            Some(DataClass::Secret)
+        );
+        assert_eq!(storage.encrypted_blob_class("legacy")?, None);
+        assert_eq!(storage.list_unclassified_blobs()?, vec!["legacy"]);
+
+        assert_eq!(
+            storage.encrypted_blob_keys_for(Purpose::NetworkBackup)?,
+            vec!["notes/today", "prefs"]
+        );
+        assert_eq!(
+            storage.encrypted_blob_keys_for(Purpose::DiagnosticsBundle)?,
+            vec!["prefs"]
+        );
+        let bundle =
+            |key_name| storage.get_encrypted_blob_for(key_name, &key, Purpose::DiagnosticsBundle);
+        assert!(bundle("wallet/seed").is_err());
+        assert!(bundle("notes/today").is_err());
+        assert!(bundle("legacy").is_err());
+        assert_eq!(bundle("prefs")?, Some(b"dark".to_vec()));
+        assert_eq!(bundle("missing")?, None);
+        assert_eq!(
+            storage.get_encrypted_blob_for("notes/today", &key, Purpose::NetworkBackup)?,
+            Some(b"milk".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_blob_compression() -> Result<()> {
+        let storage = SqlStorage::new_in_memory()?;
+        let encryption_key = [99u8; 32];
+        let json: Vec<u8> = b"{\"setting\":true},"
+            .iter()
+            .copied()
+            .cycle()
+            .take(32 * 1024)
+            .collect();
+
+        storage.set_encrypted_blob("json", &json, &encryption_key)?;
+        assert_eq!(
+            storage.get_encrypted_blob("json", &encryption_key)?,
+            Some(json.clone())
+        );
+
+        let stored: Vec<u8> = storage.conn.query_row(
+            "SELECT value_encrypted FROM encrypted_blobs WHERE key = 'json'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert!(stored.len() < json.len() / 4);
+
+        // Blobs written before compression existed still read back
+        let legacy = CocoonEncryption::new(&encryption_key).encrypt(&json)?;
+        storage.conn.execute(
+            "INSERT INTO encrypted_blobs (key, value_encrypted) VALUES ('legacy', ?1)",
+            params![legacy],
+        )?;
+        assert_eq!(
+            storage.get_encrypted_blob("legacy", &encryption_key)?,
+            Some(json)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_wrong_encryption_key_fails() -> Result<()> {
         let storage = SqlStorage::new_in_memory()?;
@@ -0,0 +1,540 @@
+//! Incremental settings diffing for multi-device config sync
+//!
+//! After an initial full sync, devices exchange [`ConfigChangeset`]s: the
+//! per-key changes between two [`AppConfiguration`] snapshots, each carrying
+//! the version counter of the key's last write. [`apply_changeset`] merges
+//! them last-writer-wins per key, so concurrent edits to different keys
+//! both survive and concurrent edits to the same key resolve to the higher
+//! counter (ties go to the greater canonical JSON value, so every device
+//! picks the same winner).
+//!
+//! Every changeset records the sender's version it was computed against.
+//! The receiver remembers the highest version it applied from each origin
+//! device; a changeset based on a newer version means earlier changes were
+//! missed, and [`ApplyOutcome::NeedsFullResync`] asks for a
+//! [`SyncPayload::Full`] instead.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use crate::error::{OsnovaError, Result};
+use crate::models::config_cache::AppConfiguration;
+use crate::util::canonical_json;
+
+/// Largest serialized changeset sent or accepted (64 KiB)
+///
+/// Larger change sets are sent as a full configuration instead.
+pub const MAX_CHANGESET_BYTES: usize = 64 * 1024;
+
+/// How a key changed between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    /// Key is new
+    Added,
+    /// Key's value changed
+    Changed,
+    /// Key was removed
+    Removed,
+}
+
+/// A change to one setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyChange {
+    /// Setting key
+    pub key: String,
+    /// How the key changed
+    pub kind: ChangeKind,
+    /// New value, absent for removals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// Version counter of the write
+    pub version: u64,
+}
+
+/// Per-key changes to one app configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChangeset {
+    /// Application ID
+    pub app_id: String,
+    /// User ID
+    pub user_id: String,
+    /// Device the changes come from
+    pub origin: String,
+    /// Sender's version the changes were computed against
+    pub base_version: u64,
+    /// Sender's version after the changes
+    pub version: u64,
+    /// Changed keys, sorted by key
+    pub changes: Vec<KeyChange>,
+}
+
+impl ConfigChangeset {
+    /// Whether the changeset carries no changes
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Serialized size in bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the changeset cannot be serialized
+    pub fn encoded_len(&self) -> Result<usize> {
+        Ok(serde_json::to_vec(self)?.len())
+    }
+}
+
+/// What one device sends another to sync a configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SyncPayload {
+    /// The whole configuration, for the initial sync or a resync
+    Full {
+        /// Device the configuration comes from
+        origin: String,
+        /// Sender's configuration
+        config: AppConfiguration,
+    },
+    /// Only the keys changed since the receiver's last sync
+    Changes(ConfigChangeset),
+}
+
+/// Result of applying a sync payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// Changes were merged
+    Applied {
+        /// Keys taken from the payload
+        applied: usize,
+        /// Keys kept because the local write was newer
+        skipped: usize,
+    },
+    /// The changeset builds on changes this device never received
+    NeedsFullResync {
+        /// Highest version applied from the origin so far
+        synced_version: u64,
+        /// Version the changeset was computed against
+        base_version: u64,
+    },
+}
+
+/// Compute the per-key changes from `from` to `to`
+///
+/// `from` is the snapshot the receiver is known to have; `origin` names
+/// the device producing the changeset.
+///
+/// # Errors
+///
+/// Returns an error if the snapshots belong to different apps or users
+pub fn diff(
+    from: &AppConfiguration,
+    to: &AppConfiguration,
+    origin: &str,
+) -> Result<ConfigChangeset> {
+    if from.app_id() != to.app_id() || from.user_id() != to.user_id() {
+        return Err(OsnovaError::Other(format!(
+            "Cannot diff configurations of {}/{} and {}/{}",
+            from.app_id(),
+            from.user_id(),
+            to.app_id(),
+            to.user_id()
+        )));
+    }
+
+    let keys: BTreeSet<&String> = from
+        .settings()
+        .keys()
+        .chain(to.settings().keys())
+        .chain(to.key_versions().keys())
+        .collect();
+
+    let changes = keys
+        .into_iter()
+        .filter_map(|key| {
+            let before = from.get_setting(key);
+            let after = to.get_setting(key);
+            let version = to.key_version(key);
+            if version == from.key_version(key) && before == after {
+                return None;
+            }
+            let kind = match (before, after) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (_, None) => ChangeKind::Removed,
+            };
+            Some(KeyChange {
+                key: key.clone(),
+                kind,
+                value: after.cloned(),
+                version,
+            })
+        })
+        .collect();
+
+    Ok(ConfigChangeset {
+        app_id: to.app_id().to_string(),
+        user_id: to.user_id().to_string(),
+        origin: origin.to_string(),
+        base_version: from.version(),
+        version: to.version(),
+        changes,
+    })
+}
+
+/// Compute the changes written after `base_version`
+///
+/// Used when the sender no longer has the receiver's snapshot, only the
+/// version the receiver last acknowledged. Without the old values, keys
+/// that still have a value are reported as [`ChangeKind::Changed`].
+pub fn changes_since(
+    config: &AppConfiguration,
+    base_version: u64,
+    origin: &str,
+) -> ConfigChangeset {
+    let mut changes: Vec<KeyChange> = config
+        .key_versions()
+        .iter()
+        .filter(|(_, version)| **version > base_version)
+        .map(|(key, version)| {
+            let value = config.get_setting(key).cloned();
+            KeyChange {
+                key: key.clone(),
+                kind: if value.is_some() {
+                    ChangeKind::Changed
+                } else {
+                    ChangeKind::Removed
+                },
+                value,
+                version: *version,
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+
+    ConfigChangeset {
+        app_id: config.app_id().to_string(),
+        user_id: config.user_id().to_string(),
+        origin: origin.to_string(),
+        base_version,
+        version: config.version(),
+        changes,
+    }
+}
+
+/// Build the payload to send a peer that last acknowledged `since`
+///
+/// Sends the full configuration when the peer has never synced (`None`)
+/// or the changeset would exceed [`MAX_CHANGESET_BYTES`].
+///
+/// # Errors
+///
+/// Returns an error if the changeset cannot be serialized
+pub fn payload(config: &AppConfiguration, since: Option<u64>, origin: &str) -> Result<SyncPayload> {
+    if let Some(since) = since {
+        let changeset = changes_since(config, since, origin);
+        if changeset.encoded_len()? <= MAX_CHANGESET_BYTES {
+            return Ok(SyncPayload::Changes(changeset));
+        }
+    }
+    Ok(SyncPayload::Full {
+        origin: origin.to_string(),
+        config: config.clone(),
+    })
+}
+
+/// Merge a changeset into `config`, last writer wins per key
+///
+/// Returns [`ApplyOutcome::NeedsFullResync`] without changing anything if
+/// the changeset's base version is newer than the last version applied
+/// from its origin.
+///
+/// # Errors
+///
+/// Returns an error if the changeset is for another app or user, exceeds
+/// [`MAX_CHANGESET_BYTES`], or has an added or changed key without a value
+pub fn apply_changeset(
+    config: &mut AppConfiguration,
+    changeset: &ConfigChangeset,
+) -> Result<ApplyOutcome> {
+    check_target(config, &changeset.app_id, &changeset.user_id)?;
+    let len = changeset.encoded_len()?;
+    if len > MAX_CHANGESET_BYTES {
+        return Err(OsnovaError::Other(format!(
+            "Changeset of {} bytes exceeds the {} byte limit",
+            len, MAX_CHANGESET_BYTES
+        )));
+    }
+
+    let synced_version = config.synced_version(&changeset.origin);
+    if changeset.base_version > synced_version {
+        return Ok(ApplyOutcome::NeedsFullResync {
+            synced_version,
+            base_version: changeset.base_version,
+        });
+    }
+
+    let mut writes = Vec::with_capacity(changeset.changes.len());
+    for change in &changeset.changes {
+        let value = match (change.kind, &change.value) {
+            (ChangeKind::Removed, _) => None,
+            (_, Some(value)) => Some(value.clone()),
+            (_, None) => {
+                return Err(OsnovaError::Other(format!(
+                    "Change to {} has no value",
+                    change.key
+                )))
+            }
+        };
+        writes.push((change.key.as_str(), value, change.version));
+    }
+
+    let outcome = merge(config, writes)?;
+    config.mark_synced(&changeset.origin, changeset.version);
+    Ok(outcome)
+}
+
+/// Apply a sync payload to `config`
+///
+/// A full configuration is merged key by key with the same last-writer-wins
+/// rule as a changeset, so local edits newer than the sender's survive.
+///
+/// # Errors
+///
+/// Returns an error if the payload is for another app or user, or a
+/// changeset is rejected by [`apply_changeset`]
+pub fn apply_payload(config: &mut AppConfiguration, payload: &SyncPayload) -> Result<ApplyOutcome> {
+    match payload {
+        SyncPayload::Changes(changeset) => apply_changeset(config, changeset),
+        SyncPayload::Full {
+            origin,
+            config: remote,
+        } => {
+            check_target(config, remote.app_id(), remote.user_id())?;
+            let keys: BTreeSet<&String> = remote
+                .settings()
+                .keys()
+                .chain(remote.key_versions().keys())
+                .collect();
+            let writes = keys
+                .into_iter()
+                .map(|key| {
+                    (
+                        key.as_str(),
+                        remote.get_setting(key).cloned(),
+                        remote.key_version(key),
+                    )
+                })
+                .collect();
+            let outcome = merge(config, writes)?;
+            config.mark_synced(origin, remote.version());
+            Ok(outcome)
+        }
+    }
+}
+
+fn check_target(config: &AppConfiguration, app_id: &str, user_id: &str) -> Result<()> {
+    if config.app_id() != app_id || config.user_id() != user_id {
+        return Err(OsnovaError::Other(format!(
+            "Sync payload for {}/{} cannot be applied to {}/{}",
+            app_id,
+            user_id,
+            config.app_id(),
+            config.user_id()
+        )));
+    }
+    Ok(())
+}
+
+fn merge(
+    config: &mut AppConfiguration,
+    writes: Vec<(&str, Option<Value>, u64)>,
+) -> Result<ApplyOutcome> {
+    let mut applied = 0;
+    let mut skipped = 0;
+    for (key, value, version) in writes {
+        let local = config.get_setting(key);
+        let order = version
+            .cmp(&config.key_version(key))
+            .then(tiebreak(value.as_ref())?.cmp(&tiebreak(local)?));
+        if order == Ordering::Greater {
+            config.put_versioned(key, value, version);
+            applied += 1;
+        } else {
+            skipped += 1;
+        }
+    }
+    Ok(ApplyOutcome::Applied { applied, skipped })
+}
+
+/// Orders same-version writes identically on every device: a value beats
+/// a removal, and larger canonical JSON beats smaller
+fn tiebreak(value: Option<&Value>) -> Result<Option<Vec<u8>>> {
+    value.map(canonical_json::to_canonical_vec).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> AppConfiguration {
+        AppConfiguration::new("com.osnova.wallet", "user-123")
+    }
+
+    /// Two devices that completed an initial full sync in both directions
+    fn synced_pair() -> (AppConfiguration, AppConfiguration) {
+        let mut laptop = config();
+        laptop.set_setting("theme", json!("dark"));
+        laptop.set_setting("fontSize", json!(14));
+
+        let mut phone = config();
+        apply_payload(&mut phone, &payload(&laptop, None, "laptop").unwrap()).unwrap();
+        apply_payload(&mut laptop, &payload(&phone, None, "phone").unwrap()).unwrap();
+        (laptop, phone)
+    }
+
+    #[test]
+    fn test_diff_round_trip() {
+        let (mut laptop, mut phone) = synced_pair();
+        let base = laptop.clone();
+
+        laptop.set_setting("language", json!("en"));
+        laptop.set_setting("fontSize", json!(16));
+        laptop.remove_setting("theme");
+
+        let changeset = diff(&base, &laptop, "laptop").unwrap();
+        let kinds: Vec<_> = changeset
+            .changes
+            .iter()
+            .map(|change| (change.key.as_str(), change.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("fontSize", ChangeKind::Changed),
+                ("language", ChangeKind::Added),
+                ("theme", ChangeKind::Removed),
+            ]
+        );
+
+        // The changeset survives the wire
+        let encoded = serde_json::to_vec(&SyncPayload::Changes(changeset)).unwrap();
+        let decoded: SyncPayload = serde_json::from_slice(&encoded).unwrap();
+
+        let outcome = apply_payload(&mut phone, &decoded).unwrap();
+        assert_eq!(
+            outcome,
+            ApplyOutcome::Applied {
+                applied: 3,
+                skipped: 0
+            }
+        );
+        assert_eq!(phone.settings(), laptop.settings());
+        assert_eq!(phone.synced_version("laptop"), laptop.version());
+
+        // Nothing changed since, so nothing is sent
+        assert!(diff(&laptop, &laptop, "laptop").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_edits_to_different_keys_both_survive() {
+        let (mut laptop, mut phone) = synced_pair();
+        let laptop_acked = phone.synced_version("laptop");
+        let phone_acked = laptop.synced_version("phone");
+
+        laptop.set_setting("theme", json!("light"));
+        phone.set_setting("fontSize", json!(18));
+
+        let to_phone = changes_since(&laptop, laptop_acked, "laptop");
+        let to_laptop = changes_since(&phone, phone_acked, "phone");
+        apply_changeset(&mut phone, &to_phone).unwrap();
+        apply_changeset(&mut laptop, &to_laptop).unwrap();
+
+        for device in [&laptop, &phone] {
+            assert_eq!(device.get_setting("theme"), Some(&json!("light")));
+            assert_eq!(device.get_setting("fontSize"), Some(&json!(18)));
+        }
+    }
+
+    #[test]
+    fn test_same_key_conflict_resolved_by_counter() {
+        let (mut laptop, mut phone) = synced_pair();
+        let laptop_acked = phone.synced_version("laptop");
+        let phone_acked = laptop.synced_version("phone");
+
+        // The laptop wrote the key twice, so its write carries the higher
+        // counter even though the phone wrote last
+        laptop.set_setting("theme", json!("blue"));
+        laptop.set_setting("theme", json!("green"));
+        phone.set_setting("theme", json!("red"));
+
+        let to_phone = changes_since(&laptop, laptop_acked, "laptop");
+        let to_laptop = changes_since(&phone, phone_acked, "phone");
+        apply_changeset(&mut phone, &to_phone).unwrap();
+        let outcome = apply_changeset(&mut laptop, &to_laptop).unwrap();
+
+        assert_eq!(
+            outcome,
+            ApplyOutcome::Applied {
+                applied: 0,
+                skipped: 1
+            }
+        );
+        assert_eq!(laptop.get_setting("theme"), Some(&json!("green")));
+        assert_eq!(phone.get_setting("theme"), Some(&json!("green")));
+
+        // Later local writes still move past every counter seen
+        phone.set_setting("theme", json!("red"));
+        assert!(phone.key_version("theme") > laptop.key_version("theme"));
+    }
+
+    #[test]
+    fn test_out_of_order_changeset_needs_full_resync() {
+        let (mut laptop, mut phone) = synced_pair();
+        let laptop_acked = phone.synced_version("laptop");
+
+        laptop.set_setting("theme", json!("light"));
+        let first = laptop.clone();
+        laptop.set_setting("language", json!("de"));
+
+        // The phone never receives the first changeset
+        let _lost = changes_since(&first, laptop_acked, "laptop");
+        let second = diff(&first, &laptop, "laptop").unwrap();
+        let before = phone.clone();
+
+        let outcome = apply_changeset(&mut phone, &second).unwrap();
+        assert_eq!(
+            outcome,
+            ApplyOutcome::NeedsFullResync {
+                synced_version: laptop_acked,
+                base_version: first.version(),
+            }
+        );
+        assert_eq!(phone, before);
+
+        // A full resync catches up
+        apply_payload(&mut phone, &payload(&laptop, None, "laptop").unwrap()).unwrap();
+        assert_eq!(phone.settings(), laptop.settings());
+    }
+
+    #[test]
+    fn test_oversized_changes_fall_back_to_full_payload() {
+        let (mut laptop, phone) = synced_pair();
+        let acked = phone.synced_version("laptop");
+        laptop.set_setting("notes", json!("x".repeat(MAX_CHANGESET_BYTES)));
+
+        assert!(matches!(
+            payload(&laptop, Some(acked), "laptop").unwrap(),
+            SyncPayload::Full { .. }
+        ));
+
+        let mut other = phone.clone();
+        let oversized = changes_since(&laptop, acked, "laptop");
+        assert!(apply_changeset(&mut other, &oversized).is_err());
+    }
+}
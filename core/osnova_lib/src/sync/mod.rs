@@ -0,0 +1,31 @@
+//! # Sync Module
+//!
+//! Multi-device synchronization of per-user data.
+//!
+//! This module provides:
+//! - [`diff`], incremental per-key change sets for app configuration, so
+//!   devices exchange only what changed after an initial full sync
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::models::config_cache::AppConfiguration;
+//! use osnova_lib::sync::diff::{self, ApplyOutcome};
+//! use serde_json::json;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut laptop = AppConfiguration::new("com.osnova.wallet", "user-123");
+//! let base = laptop.clone();
+//! laptop.set_setting("theme", json!("dark"));
+//!
+//! let changeset = diff::diff(&base, &laptop, "laptop")?;
+//! let mut phone = AppConfiguration::new("com.osnova.wallet", "user-123");
+//! let outcome = diff::apply_changeset(&mut phone, &changeset)?;
+//!
+//! assert!(matches!(outcome, ApplyOutcome::Applied { applied: 1, .. }));
+//! assert_eq!(phone.get_setting("theme"), Some(&json!("dark")));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod diff;
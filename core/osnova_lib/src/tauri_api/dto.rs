@@ -0,0 +1,378 @@
+//! Request and response shapes of the shell commands
+//!
+//! These types are the wire format the frontend depends on, kept apart from
+//! the service types so a change to a service cannot change a command's
+//! JSON by accident. Field names and casing follow what each command has
+//! always sent, including the snake_case fields of app list entries and
+//! server status.
+//!
+//! Requests mirror the arguments the frontend passes to `invoke`; Tauri
+//! converts their camelCase keys to the command's parameters.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::services::apps::{AppListItem, ConsentReview};
+use crate::services::status::{ServerStatus, ServerStatusResponse};
+use crate::services::{BottomMenuTab, Theme};
+
+/// Arguments of `identity_import`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityImportRequest {
+    /// Seed phrase the user wrote down
+    pub seed_phrase: String,
+}
+
+/// Arguments of `apps_launch`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AppsLaunchRequest {
+    /// Application to launch
+    pub app_id: String,
+    /// Hash every component before launching
+    #[serde(default)]
+    pub verify: Option<bool>,
+}
+
+/// Arguments of `launcher_set_layout`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherSetLayoutRequest {
+    /// App IDs in launcher order
+    pub app_ids: Vec<String>,
+}
+
+/// Arguments of `ui_set_theme`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UiSetThemeRequest {
+    /// `light`, `dark` or `system`
+    pub theme: String,
+}
+
+/// Arguments of `navigation_set_bottom_menu`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NavigationSetBottomMenuRequest {
+    /// `launcher`, `wallet` or `config`
+    pub tab: String,
+}
+
+/// An installed app, as listed by `apps_list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AppListEntry {
+    /// Application ID
+    pub id: String,
+    /// Application name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Icon URI
+    pub icon_uri: String,
+    /// Manifest URI
+    pub manifest_uri: String,
+}
+
+impl From<AppListItem> for AppListEntry {
+    fn from(item: AppListItem) -> Self {
+        Self {
+            id: item.id,
+            name: item.name,
+            version: item.version,
+            icon_uri: item.icon_uri,
+            manifest_uri: item.manifest_uri,
+        }
+    }
+}
+
+/// Response of `apps_list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AppsListResponse {
+    /// Installed apps
+    pub apps: Vec<AppListEntry>,
+    /// Launcher generation the list is current for
+    pub generation: u64,
+}
+
+/// Response of `launcher_get_layout`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherLayoutResponse {
+    /// App IDs in launcher order
+    pub app_ids: Vec<String>,
+    /// Launcher generation the layout is current for
+    pub generation: u64,
+}
+
+/// Theme name returned by `ui_get_theme` and accepted by `ui_set_theme`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Light theme
+    Light,
+    /// Dark theme
+    Dark,
+    /// Follows the OS preference
+    System,
+}
+
+impl ThemeName {
+    /// Parse the name the frontend sends, `None` if it is not a theme
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            "system" => Some(Self::System),
+            _ => None,
+        }
+    }
+
+    /// Name sent to the frontend
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+            Self::System => "system",
+        }
+    }
+}
+
+impl From<Theme> for ThemeName {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Light => Self::Light,
+            Theme::Dark => Self::Dark,
+            Theme::System => Self::System,
+        }
+    }
+}
+
+impl From<ThemeName> for Theme {
+    fn from(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Light => Theme::Light,
+            ThemeName::Dark => Theme::Dark,
+            ThemeName::System => Theme::System,
+        }
+    }
+}
+
+/// Tab name returned by `navigation_get_bottom_menu` and accepted by
+/// `navigation_set_bottom_menu`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TabName {
+    /// App grid
+    Launcher,
+    /// Wallet
+    Wallet,
+    /// Configuration
+    Config,
+}
+
+impl TabName {
+    /// Parse the name the frontend sends, `None` if it is not a tab
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "launcher" => Some(Self::Launcher),
+            "wallet" => Some(Self::Wallet),
+            "config" => Some(Self::Config),
+            _ => None,
+        }
+    }
+
+    /// Name sent to the frontend
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Launcher => "launcher",
+            Self::Wallet => "wallet",
+            Self::Config => "config",
+        }
+    }
+}
+
+impl From<BottomMenuTab> for TabName {
+    fn from(tab: BottomMenuTab) -> Self {
+        match tab {
+            BottomMenuTab::Launcher => Self::Launcher,
+            BottomMenuTab::Wallet => Self::Wallet,
+            BottomMenuTab::Config => Self::Config,
+        }
+    }
+}
+
+impl From<TabName> for BottomMenuTab {
+    fn from(name: TabName) -> Self {
+        match name {
+            TabName::Launcher => BottomMenuTab::Launcher,
+            TabName::Wallet => BottomMenuTab::Wallet,
+            TabName::Config => BottomMenuTab::Config,
+        }
+    }
+}
+
+/// Connection state in `status_get_server`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerStatusName {
+    /// Stand-alone mode
+    Disconnected,
+    /// Connected to a server
+    Connected,
+    /// Connecting to a server
+    Connecting,
+    /// The connection failed
+    Failed,
+}
+
+impl From<ServerStatus> for ServerStatusName {
+    fn from(status: ServerStatus) -> Self {
+        match status {
+            ServerStatus::Disconnected => Self::Disconnected,
+            ServerStatus::Connected => Self::Connected,
+            ServerStatus::Connecting => Self::Connecting,
+            ServerStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+/// Response of `status_get_server`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ServerStatusDto {
+    /// Connection state
+    pub status: ServerStatusName,
+    /// Server address, when there is one
+    pub server_address: Option<String>,
+    /// Unix timestamp of the connection
+    pub connected_at: Option<u64>,
+    /// Why the connection failed
+    pub error: Option<String>,
+}
+
+impl From<ServerStatusResponse> for ServerStatusDto {
+    fn from(response: ServerStatusResponse) -> Self {
+        Self {
+            status: response.status.into(),
+            server_address: response.server_address,
+            connected_at: response.connected_at,
+            error: response.error,
+        }
+    }
+}
+
+/// Error of `apps_launch` when the user must review the app first
+///
+/// Sent as the error string, JSON-encoded, so the frontend can show the
+/// consent sheet.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentRequiredError {
+    /// What the user should review
+    pub consent_required: ConsentReview,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// JSON the way the commands send it: through `serde_json::Value`
+    fn wire<T: Serialize>(value: &T) -> String {
+        serde_json::to_value(value).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_request_snapshots() {
+        let import: IdentityImportRequest =
+            serde_json::from_value(json!({"seedPhrase": "one two"})).unwrap();
+        assert_eq!(import.seed_phrase, "one two");
+
+        let launch: AppsLaunchRequest =
+            serde_json::from_value(json!({"appId": "com.example.notes"})).unwrap();
+        assert_eq!(launch.verify, None);
+        assert_eq!(
+            wire(&AppsLaunchRequest {
+                app_id: "com.example.notes".to_string(),
+                verify: Some(true),
+            }),
+            r#"{"appId":"com.example.notes","verify":true}"#
+        );
+
+        assert_eq!(
+            wire(&LauncherSetLayoutRequest {
+                app_ids: vec!["a".to_string(), "b".to_string()],
+            }),
+            r#"{"appIds":["a","b"]}"#
+        );
+        assert_eq!(
+            wire(&UiSetThemeRequest {
+                theme: "dark".to_string(),
+            }),
+            r#"{"theme":"dark"}"#
+        );
+        assert_eq!(
+            wire(&NavigationSetBottomMenuRequest {
+                tab: "wallet".to_string(),
+            }),
+            r#"{"tab":"wallet"}"#
+        );
+    }
+
+    #[test]
+    fn test_response_snapshots() {
+        let apps = AppsListResponse {
+            apps: vec![AppListEntry {
+                id: "com.example.notes".to_string(),
+                name: "Notes".to_string(),
+                version: "1.0.0".to_string(),
+                icon_uri: "ant://icon".to_string(),
+                manifest_uri: "com.example.notes".to_string(),
+            }],
+            generation: 3,
+        };
+        assert_eq!(
+            wire(&apps),
+            r#"{"apps":[{"icon_uri":"ant://icon","id":"com.example.notes","manifest_uri":"com.example.notes","name":"Notes","version":"1.0.0"}],"generation":3}"#
+        );
+
+        let layout = LauncherLayoutResponse {
+            app_ids: vec!["com.example.notes".to_string()],
+            generation: 4,
+        };
+        assert_eq!(
+            wire(&layout),
+            r#"{"appIds":["com.example.notes"],"generation":4}"#
+        );
+
+        let status = ServerStatusDto::from(ServerStatusResponse::failed(
+            "10.0.0.2:8443".to_string(),
+            "Connection failed".to_string(),
+        ));
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"status":"failed","server_address":"10.0.0.2:8443","connected_at":null,"error":"Connection failed"}"#
+        );
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for theme in [Theme::Light, Theme::Dark, Theme::System] {
+            let name = ThemeName::from(theme);
+            assert_eq!(ThemeName::parse(name.as_str()), Some(name));
+            assert_eq!(wire(&name), format!("\"{}\"", name.as_str()));
+            assert_eq!(Theme::from(name), theme);
+        }
+        assert_eq!(ThemeName::parse("Dark"), None);
+
+        for tab in [
+            BottomMenuTab::Launcher,
+            BottomMenuTab::Wallet,
+            BottomMenuTab::Config,
+        ] {
+            let name = TabName::from(tab);
+            assert_eq!(TabName::parse(name.as_str()), Some(name));
+            assert_eq!(wire(&name), format!("\"{}\"", name.as_str()));
+            assert_eq!(BottomMenuTab::from(name), tab);
+        }
+        assert_eq!(TabName::parse("home"), None);
+    }
+}
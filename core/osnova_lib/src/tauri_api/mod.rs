@@ -0,0 +1,559 @@
+//! Stable command API of the desktop shell
+//!
+//! The shell's Tauri commands are the frontend's only way into the core, so
+//! their names, argument keys, response JSON and the error strings the UI
+//! reacts to must not change while the services behind them are
+//! restructured. [`TauriApi`] holds the services the legacy `AppState` kept
+//! behind one `Mutex<Option<_>>` each, opened from an [`OsnovaContext`],
+//! and implements the core commands on top of them so the Tauri layer only
+//! forwards arguments:
+//!
+//! - `identity_check`, `identity_create`, `identity_import`, `identity_get`
+//! - `apps_list`, `apps_launch`
+//! - `launcher_get_layout`, `launcher_set_layout`
+//! - `ui_get_theme`, `ui_set_theme`
+//! - `navigation_get_bottom_menu`, `navigation_set_bottom_menu`
+//! - `status_get_server`
+//!
+//! Each method returns exactly what the command returns to the frontend.
+//! The wire shapes are pinned by [`dto`], and golden files under
+//! `tests/golden/tauri_api` record every response byte for byte; an
+//! intentional change has to regenerate them (see `tests/tauri_api.rs`).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use osnova_lib::context::OsnovaContext;
+//! use osnova_lib::tauri_api::{TauriApi, APPS_NOT_INITIALIZED};
+//!
+//! let api = TauriApi::from_context(&OsnovaContext::new("/tmp/osnova"));
+//! assert_eq!(api.apps_list(), Err(APPS_NOT_INITIALIZED.to_string()));
+//! assert_eq!(api.ui_get_theme().unwrap_err(), "UI service not initialized");
+//! ```
+
+pub mod dto;
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::context::{GuestGate, OsnovaContext};
+use crate::network::NetworkOptions;
+use crate::services::apps::{ConsentRequired, MaterializeProgress};
+use crate::services::{
+    AppsService, IdentityService, IdentityState, LauncherService, NavigationService, ReauthService,
+    StatusService, UIService,
+};
+use dto::{
+    AppListEntry, AppsLaunchRequest, AppsListResponse, ConsentRequiredError, IdentityImportRequest,
+    LauncherLayoutResponse, LauncherSetLayoutRequest, NavigationSetBottomMenuRequest,
+    ServerStatusDto, TabName, ThemeName, UiSetThemeRequest,
+};
+
+/// Error of identity commands before the identity service is opened
+pub const IDENTITY_NOT_INITIALIZED: &str = "Identity service not initialized";
+/// Error of apps commands before the user's services are opened
+pub const APPS_NOT_INITIALIZED: &str = "Apps service not initialized";
+/// Error of launcher commands before the user's services are opened
+pub const LAUNCHER_NOT_INITIALIZED: &str = "Launcher service not initialized";
+/// Error of UI commands before the user's services are opened
+pub const UI_NOT_INITIALIZED: &str = "UI service not initialized";
+/// Error of navigation commands before the user's services are opened
+pub const NAVIGATION_NOT_INITIALIZED: &str = "Navigation service not initialized";
+/// Error of identity commands while a guest session runs
+pub const IDENTITY_IN_GUEST_SESSION: &str = "Identity service is not available in a guest session";
+/// Error of `ui_set_theme` for an unknown theme
+pub const INVALID_THEME: &str = "Invalid theme value";
+/// Error of `navigation_set_bottom_menu` for an unknown tab
+pub const INVALID_TAB: &str = "Invalid tab value";
+/// Prefix of the error when an identity was stored but the user's services
+/// did not open
+pub const INIT_FAILED_PREFIX: &str = "Failed to initialize services: ";
+
+/// A service the shell opens once it is needed
+///
+/// Commands fail with the slot's "service not initialized" error until a
+/// service is [set](Self::set).
+pub struct ServiceSlot<T> {
+    service: Mutex<Option<T>>,
+    missing: &'static str,
+}
+
+impl<T> ServiceSlot<T> {
+    fn new(missing: &'static str) -> Self {
+        Self {
+            service: Mutex::new(None),
+            missing,
+        }
+    }
+
+    /// Lock the slot
+    pub fn lock(&self) -> MutexGuard<'_, Option<T>> {
+        self.service.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Put a service in the slot, replacing any earlier one
+    pub fn set(&self, service: T) {
+        *self.lock() = Some(service);
+    }
+
+    /// Take the service out of the slot
+    pub fn clear(&self) -> Option<T> {
+        self.lock().take()
+    }
+
+    /// Whether a service is in the slot
+    pub fn is_open(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Error commands return while the slot is empty
+    pub fn missing(&self) -> &'static str {
+        self.missing
+    }
+
+    /// Run `f` on the service, failing with the slot's error if it is empty
+    ///
+    /// # Errors
+    ///
+    /// Returns the "service not initialized" error, or the error of `f`
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> Result<R, String>) -> Result<R, String> {
+        let guard = self.lock();
+        let service = guard.as_ref().ok_or(self.missing)?;
+        f(service)
+    }
+}
+
+/// Services behind the core shell commands
+pub struct TauriApi {
+    storage_path: PathBuf,
+    reauth: Option<Arc<ReauthService>>,
+    guest: GuestGate,
+    identity: ServiceSlot<IdentityService>,
+    apps: ServiceSlot<AppsService>,
+    launcher: ServiceSlot<LauncherService>,
+    ui: ServiceSlot<Arc<UIService>>,
+    navigation: ServiceSlot<NavigationService>,
+    status: Mutex<StatusService>,
+}
+
+impl TauriApi {
+    /// Empty slots for the services of a context
+    ///
+    /// Only the status service is open; the identity service opens on the
+    /// first identity command and the others are set once the user is
+    /// known.
+    pub fn from_context(context: &OsnovaContext) -> Self {
+        Self {
+            storage_path: context.storage_path().to_path_buf(),
+            reauth: None,
+            guest: context.guest_gate(),
+            identity: ServiceSlot::new(IDENTITY_NOT_INITIALIZED),
+            apps: ServiceSlot::new(APPS_NOT_INITIALIZED),
+            launcher: ServiceSlot::new(LAUNCHER_NOT_INITIALIZED),
+            ui: ServiceSlot::new(UI_NOT_INITIALIZED),
+            navigation: ServiceSlot::new(NAVIGATION_NOT_INITIALIZED),
+            status: Mutex::new(StatusService::new().with_guest_gate(context.guest_gate())),
+        }
+    }
+
+    /// Gate sensitive identity operations behind OS re-authentication
+    pub fn with_reauth(mut self, reauth: Arc<ReauthService>) -> Self {
+        self.reauth = Some(reauth);
+        self
+    }
+
+    /// The identity service
+    pub fn identity(&self) -> &ServiceSlot<IdentityService> {
+        &self.identity
+    }
+
+    /// The apps service of the current user
+    pub fn apps(&self) -> &ServiceSlot<AppsService> {
+        &self.apps
+    }
+
+    /// The launcher service of the current user
+    pub fn launcher(&self) -> &ServiceSlot<LauncherService> {
+        &self.launcher
+    }
+
+    /// The UI service of the current user
+    pub fn ui(&self) -> &ServiceSlot<Arc<UIService>> {
+        &self.ui
+    }
+
+    /// The navigation service of the current user
+    pub fn navigation(&self) -> &ServiceSlot<NavigationService> {
+        &self.navigation
+    }
+
+    /// The status service
+    pub fn status(&self) -> MutexGuard<'_, StatusService> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Open the identity service unless it is open already
+    ///
+    /// The host's identity stays closed while a guest session runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage directory cannot be opened or a
+    /// guest session runs
+    pub fn open_identity(&self) -> Result<(), String> {
+        if self.guest.is_active() {
+            return Err(IDENTITY_IN_GUEST_SESSION.to_string());
+        }
+        let mut slot = self.identity.lock();
+        if slot.is_none() {
+            let mut service = IdentityService::new(&self.storage_path)
+                .map_err(|e| e.to_string())?
+                .with_guest_gate(self.guest.clone());
+            if let Some(reauth) = &self.reauth {
+                service = service.with_reauth(reauth.clone());
+            }
+            *slot = Some(service);
+        }
+        Ok(())
+    }
+
+    /// Whether a usable identity is stored (`identity_check`)
+    ///
+    /// An identity that needs recovery is not usable yet, and a status that
+    /// cannot be read counts as no identity.
+    pub fn identity_check(&self) -> Result<bool, String> {
+        self.open_identity()?;
+        self.identity.with(|service| match service.status() {
+            Ok(status) => Ok(status.state == IdentityState::Initialized),
+            Err(_) => Ok(false),
+        })
+    }
+
+    /// Create an identity and return its seed phrase (`identity_create`)
+    ///
+    /// `init_for_user` opens the user's services for the new address.
+    pub fn identity_create<F>(&self, init_for_user: F) -> Result<String, String>
+    where
+        F: FnOnce(&str) -> Result<(), String>,
+    {
+        self.open_identity()?;
+        let (seed_phrase, address) = self
+            .identity
+            .with(|service| service.create().map_err(|e| e.to_string()))?;
+        init_for_user(&address).map_err(|e| format!("{}{}", INIT_FAILED_PREFIX, e))?;
+        Ok(seed_phrase)
+    }
+
+    /// Restore an identity from its seed phrase and return its address
+    /// (`identity_import`)
+    ///
+    /// `init_for_user` opens the user's services for the address.
+    pub fn identity_import<F>(
+        &self,
+        request: IdentityImportRequest,
+        init_for_user: F,
+    ) -> Result<String, String>
+    where
+        F: FnOnce(&str) -> Result<(), String>,
+    {
+        self.open_identity()?;
+        let address = self.identity.with(|service| {
+            service
+                .import_with_phrase(&request.seed_phrase)
+                .map_err(|e| e.to_string())
+        })?;
+        init_for_user(&address).map_err(|e| format!("{}{}", INIT_FAILED_PREFIX, e))?;
+        Ok(address)
+    }
+
+    /// Fingerprint of the stored identity, hex-encoded (`identity_get`)
+    pub fn identity_get(&self) -> Result<String, String> {
+        self.open_identity()?;
+        self.identity.with(|service| {
+            let identity = service.get_identity().map_err(|e| e.to_string())?;
+            Ok(hex::encode(identity.fingerprint()))
+        })
+    }
+
+    /// Installed apps with the launcher generation they are current for
+    /// (`apps_list`)
+    pub fn apps_list(&self) -> Result<String, String> {
+        self.apps.with(|service| {
+            let generation = service.generation().map_err(|e| e.to_string())?;
+            let apps = service.list().map_err(|e| e.to_string())?;
+            encode_object(&AppsListResponse {
+                apps: apps.into_iter().map(AppListEntry::from).collect(),
+                generation,
+            })
+        })
+    }
+
+    /// Launch an app (`apps_launch`)
+    ///
+    /// A restored app, or any app when `verify` is set, has its components
+    /// checked and downloaded first, with `on_progress` called as each
+    /// missing one finishes. When the user must review the app first, the
+    /// error is a JSON [`ConsentRequiredError`]. With a launch handshake,
+    /// the launch fails rather than return before the backends are ready.
+    // The apps service is not Sync, so it stays locked for the whole
+    // launch, as it was in the legacy command
+    #[allow(clippy::await_holding_lock)]
+    pub async fn apps_launch<F>(
+        &self,
+        request: AppsLaunchRequest,
+        options: &NetworkOptions,
+        on_progress: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(&MaterializeProgress),
+    {
+        let guard = self.apps.lock();
+        let service = guard.as_ref().ok_or(self.apps.missing())?;
+        let app_id = &request.app_id;
+        let unmaterialized = service
+            .materialization(app_id)
+            .map_err(|e| e.to_string())?
+            .is_some_and(|state| !state.is_materialized());
+        let launched = if request.verify.unwrap_or(false) || unmaterialized {
+            service
+                .launch_materialized(app_id, options, on_progress)
+                .await
+        } else {
+            service.launch(app_id)
+        };
+        launched.map_err(|e| match e.downcast_ref::<ConsentRequired>() {
+            Some(required) => encode_object(&ConsentRequiredError {
+                consent_required: required.review.clone(),
+            })
+            .unwrap_or_else(|e| e),
+            None => e.to_string(),
+        })?;
+
+        if service.has_handshake() {
+            service
+                .wait_ready(app_id)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Layout with the launcher generation it is current for
+    /// (`launcher_get_layout`)
+    pub fn launcher_get_layout(&self) -> Result<String, String> {
+        self.launcher.with(|service| {
+            let generation = service.generation().map_err(|e| e.to_string())?;
+            let layout = service.get_layout().map_err(|e| e.to_string())?;
+            encode_object(&LauncherLayoutResponse {
+                app_ids: layout.app_ids,
+                generation,
+            })
+        })
+    }
+
+    /// Replace the launcher layout (`launcher_set_layout`)
+    pub fn launcher_set_layout(&self, request: LauncherSetLayoutRequest) -> Result<(), String> {
+        self.launcher.with(|service| {
+            service
+                .set_layout(request.app_ids)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// The theme name (`ui_get_theme`)
+    pub fn ui_get_theme(&self) -> Result<String, String> {
+        self.ui.with(|service| {
+            let theme = service.get_theme().map_err(|e| e.to_string())?;
+            Ok(ThemeName::from(theme).as_str().to_string())
+        })
+    }
+
+    /// Set the theme by name (`ui_set_theme`)
+    pub fn ui_set_theme(&self, request: UiSetThemeRequest) -> Result<(), String> {
+        self.ui.with(|service| {
+            let theme = ThemeName::parse(&request.theme).ok_or(INVALID_THEME)?;
+            service.set_theme(theme.into()).map_err(|e| e.to_string())
+        })
+    }
+
+    /// The active bottom menu tab (`navigation_get_bottom_menu`)
+    pub fn navigation_get_bottom_menu(&self) -> Result<String, String> {
+        self.navigation.with(|service| {
+            let tab = service.get_bottom_menu().map_err(|e| e.to_string())?;
+            Ok(TabName::from(tab).as_str().to_string())
+        })
+    }
+
+    /// Switch the bottom menu tab by name (`navigation_set_bottom_menu`)
+    pub fn navigation_set_bottom_menu(
+        &self,
+        request: NavigationSetBottomMenuRequest,
+    ) -> Result<(), String> {
+        self.navigation.with(|service| {
+            let tab = TabName::parse(&request.tab).ok_or(INVALID_TAB)?;
+            service
+                .set_bottom_menu(tab.into())
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// Server connection status (`status_get_server`)
+    pub fn status_get_server(&self) -> Result<String, String> {
+        let status = self.status().get_server().map_err(|e| e.to_string())?;
+        serde_json::to_string(&ServerStatusDto::from(status)).map_err(|e| e.to_string())
+    }
+}
+
+/// Encode a response built with `json!` in the legacy commands
+///
+/// Going through [`serde_json::Value`] orders the keys the same way.
+fn encode_object<T: Serialize>(response: &T) -> Result<String, String> {
+    serde_json::to_value(response)
+        .map(|value| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SEED: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn api() -> (TempDir, TauriApi) {
+        let temp = TempDir::new().unwrap();
+        let api = TauriApi::from_context(&OsnovaContext::new(temp.path()));
+        (temp, api)
+    }
+
+    fn import(seed_phrase: &str) -> IdentityImportRequest {
+        IdentityImportRequest {
+            seed_phrase: seed_phrase.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_uninitialized_services_fail_as_before() {
+        let (_temp, api) = api();
+        assert_eq!(api.apps_list().unwrap_err(), "Apps service not initialized");
+        assert_eq!(
+            api.launcher_get_layout().unwrap_err(),
+            "Launcher service not initialized"
+        );
+        assert_eq!(
+            api.launcher_set_layout(LauncherSetLayoutRequest { app_ids: vec![] })
+                .unwrap_err(),
+            "Launcher service not initialized"
+        );
+        assert_eq!(
+            api.ui_get_theme().unwrap_err(),
+            "UI service not initialized"
+        );
+        // The service is missing before the theme is even looked at
+        assert_eq!(
+            api.ui_set_theme(UiSetThemeRequest {
+                theme: "sepia".to_string(),
+            })
+            .unwrap_err(),
+            "UI service not initialized"
+        );
+        assert_eq!(
+            api.navigation_get_bottom_menu().unwrap_err(),
+            "Navigation service not initialized"
+        );
+        assert_eq!(
+            api.navigation_set_bottom_menu(NavigationSetBottomMenuRequest {
+                tab: "wallet".to_string(),
+            })
+            .unwrap_err(),
+            "Navigation service not initialized"
+        );
+
+        let launch = AppsLaunchRequest {
+            app_id: "com.example.notes".to_string(),
+            verify: None,
+        };
+        let launched = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(api.apps_launch(launch, &NetworkOptions::default(), |_| {}));
+        assert_eq!(launched.unwrap_err(), "Apps service not initialized");
+
+        // Status and identity need no user
+        assert!(api.status_get_server().is_ok());
+        assert_eq!(api.identity_check(), Ok(false));
+        assert!(api.identity().is_open());
+    }
+
+    #[test]
+    fn test_identity_commands_open_the_service_and_init_the_user() {
+        let (_temp, api) = api();
+        let mut initialized = Vec::new();
+        let seed_phrase = api
+            .identity_create(|address| {
+                initialized.push(address.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seed_phrase.split_whitespace().count(), 12);
+        assert_eq!(initialized.len(), 1);
+        assert_eq!(api.identity_check(), Ok(true));
+        assert_eq!(api.identity_get().unwrap().len(), 64);
+
+        assert_eq!(
+            api.identity_create(|_| Ok(())).unwrap_err(),
+            "Identity already exists. Use importWithPhrase to restore from backup."
+        );
+        assert_eq!(
+            api.identity_import(import(SEED), |_| Ok(())).unwrap_err(),
+            "Identity already exists. Delete existing identity first."
+        );
+    }
+
+    #[test]
+    fn test_failed_user_init_is_reported_with_its_prefix() {
+        let (_temp, api) = api();
+        let error = api
+            .identity_import(import(SEED), |_| Err("disk full".to_string()))
+            .unwrap_err();
+        assert_eq!(error, "Failed to initialize services: disk full");
+        // The identity itself was stored
+        assert_eq!(api.identity_check(), Ok(true));
+    }
+
+    #[test]
+    fn test_slots_can_be_cleared_and_reopened() {
+        let (temp, api) = api();
+        let navigation = NavigationService::new(temp.path(), "user-1").unwrap();
+        api.navigation().set(navigation);
+        assert_eq!(api.navigation_get_bottom_menu().unwrap(), "launcher");
+        assert_eq!(
+            api.navigation_set_bottom_menu(NavigationSetBottomMenuRequest {
+                tab: "home".to_string(),
+            })
+            .unwrap_err(),
+            "Invalid tab value"
+        );
+
+        assert!(api.navigation().clear().is_some());
+        assert_eq!(
+            api.navigation_get_bottom_menu().unwrap_err(),
+            NAVIGATION_NOT_INITIALIZED
+        );
+    }
+
+    #[test]
+    fn test_host_identity_stays_closed_during_a_guest_session() {
+        let temp = TempDir::new().unwrap();
+        let context = OsnovaContext::new(temp.path()).with_component_cache(temp.path());
+        let api = TauriApi::from_context(&context);
+
+        let session = context.start_guest_session().unwrap();
+        assert_eq!(api.identity_check().unwrap_err(), IDENTITY_IN_GUEST_SESSION);
+        assert!(!api.identity().is_open());
+
+        session.end();
+        assert_eq!(api.identity_check(), Ok(false));
+    }
+}
@@ -0,0 +1,351 @@
+//! Clock implementations
+//!
+//! [`HybridClock`] is the default for anything that orders or expires data:
+//! it follows the wall clock forward but never hands out a value smaller
+//! than one it already returned, even across restarts when a high-water
+//! mark file is configured. [`SystemClock`] reads the raw wall clock and is
+//! only suitable for display. [`MockClock`] is driven by hand in tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{OsnovaError, Result};
+
+/// Source of Unix timestamps and monotonic elapsed time
+pub trait Clock: Send + Sync {
+    /// Current Unix time in seconds
+    fn now_unix(&self) -> u64;
+
+    /// Time elapsed since the clock was created
+    ///
+    /// Unaffected by wall-clock adjustments.
+    fn elapsed(&self) -> Duration;
+}
+
+/// Shared handle to a clock, as injected into services
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Raw operating system wall clock
+///
+/// May jump backwards on NTP corrections or manual changes; use for display
+/// only.
+#[derive(Debug)]
+pub struct SystemClock {
+    started: Instant,
+}
+
+impl SystemClock {
+    /// Create a system clock
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Last value handed out by a [`HybridClock`]
+struct HighWater {
+    /// Largest timestamp returned so far
+    value: u64,
+    /// Elapsed time of the underlying clock when `value` was reached
+    anchor: Duration,
+}
+
+/// Wall clock that never goes backwards
+///
+/// Returns the larger of the wall clock and the last returned value plus the
+/// monotonic time since then, so timestamps keep advancing at the normal rate
+/// while the wall clock is behind.
+pub struct HybridClock {
+    wall: SharedClock,
+    high_water: Mutex<HighWater>,
+    persist_path: Option<PathBuf>,
+}
+
+impl HybridClock {
+    /// Create a hybrid clock over the system wall clock
+    pub fn new() -> Self {
+        Self::with_source(Arc::new(SystemClock::new()))
+    }
+
+    /// Create a hybrid clock over an arbitrary wall clock
+    pub fn with_source(wall: SharedClock) -> Self {
+        let anchor = wall.elapsed();
+        Self {
+            wall,
+            high_water: Mutex::new(HighWater { value: 0, anchor }),
+            persist_path: None,
+        }
+    }
+
+    /// Persist the high-water mark to `path`
+    ///
+    /// A mark left by a previous run is loaded, so timestamps stay ordered
+    /// across restarts even if the wall clock moved backwards in between.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing mark file cannot be read or parsed.
+    pub fn with_persistence<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let stored = Self::load_mark(&path)?;
+
+        let mut high_water = self.high_water.into_inner().unwrap();
+        high_water.value = high_water.value.max(stored);
+
+        Ok(Self {
+            wall: self.wall,
+            high_water: Mutex::new(high_water),
+            persist_path: Some(path),
+        })
+    }
+
+    /// Read a persisted mark, treating a missing file as zero
+    fn load_mark(path: &Path) -> Result<u64> {
+        match fs::read_to_string(path) {
+            Ok(content) => content
+                .trim()
+                .parse()
+                .map_err(|e| OsnovaError::Storage(format!("Invalid clock mark: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(OsnovaError::Storage(format!(
+                "Failed to read clock high-water mark: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Write the mark via a temporary file so a crash never leaves it torn
+    ///
+    /// Failures are ignored: the in-memory mark still holds for this run.
+    fn persist_mark(&self, value: u64) {
+        let Some(path) = &self.persist_path else {
+            return;
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        if fs::write(&tmp_path, value.to_string()).is_ok() {
+            let _ = fs::rename(&tmp_path, path);
+        }
+    }
+}
+
+impl Default for HybridClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for HybridClock {
+    fn now_unix(&self) -> u64 {
+        let wall = self.wall.now_unix();
+        let elapsed = self.wall.elapsed();
+        let mut high_water = self.high_water.lock().unwrap();
+
+        let since = elapsed.saturating_sub(high_water.anchor).as_secs();
+        let carried = high_water.value + since;
+        let now = wall.max(carried);
+
+        if now > high_water.value {
+            high_water.anchor = if wall >= carried {
+                elapsed
+            } else {
+                // Keep the sub-second remainder so carried time is not lost
+                high_water.anchor + Duration::from_secs(since)
+            };
+            high_water.value = now;
+            self.persist_mark(now);
+        }
+
+        now
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.wall.elapsed()
+    }
+}
+
+/// Manually driven clock for tests and simulations
+#[derive(Debug)]
+pub struct MockClock {
+    state: Mutex<(u64, Duration)>,
+}
+
+impl MockClock {
+    /// Create a mock clock reading `unix` seconds
+    pub fn new(unix: u64) -> Self {
+        Self {
+            state: Mutex::new((unix, Duration::ZERO)),
+        }
+    }
+
+    /// Let `duration` pass on both the wall and monotonic clocks
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += duration.as_secs();
+        state.1 += duration;
+    }
+
+    /// Set the wall clock without moving the monotonic clock
+    ///
+    /// Simulates NTP corrections and manual clock changes.
+    pub fn set_unix(&self, unix: u64) {
+        self.state.lock().unwrap().0 = unix;
+    }
+}
+
+impl Clock for MockClock {
+    fn now_unix(&self) -> u64 {
+        self.state.lock().unwrap().0
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().1
+    }
+}
+
+/// Process-wide clock used by models that are not handed one explicitly
+fn default_slot() -> &'static RwLock<SharedClock> {
+    static DEFAULT: OnceLock<RwLock<SharedClock>> = OnceLock::new();
+    DEFAULT.get_or_init(|| RwLock::new(Arc::new(HybridClock::new())))
+}
+
+/// Get the process-wide default clock
+///
+/// A [`HybridClock`] without persistence unless replaced with
+/// [`set_default_clock`].
+pub fn default_clock() -> SharedClock {
+    default_slot().read().unwrap().clone()
+}
+
+/// Replace the process-wide default clock
+///
+/// Typically called once at startup with a persistent [`HybridClock`].
+pub fn set_default_clock(clock: SharedClock) {
+    *default_slot().write().unwrap() = clock;
+}
+
+/// Current Unix time in seconds from the default clock
+pub fn now_unix() -> u64 {
+    default_clock().now_unix()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new(1_000);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now_unix(), 1_005);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+
+        clock.set_unix(900);
+        assert_eq!(clock.now_unix(), 900);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_hybrid_clock_follows_wall_clock_forward() {
+        let wall = Arc::new(MockClock::new(1_000));
+        let clock = HybridClock::with_source(wall.clone());
+
+        assert_eq!(clock.now_unix(), 1_000);
+        wall.advance(Duration::from_secs(30));
+        assert_eq!(clock.now_unix(), 1_030);
+
+        // Forward jumps are taken as-is
+        wall.set_unix(5_000);
+        assert_eq!(clock.now_unix(), 5_000);
+    }
+
+    #[test]
+    fn test_hybrid_clock_survives_backwards_jump() {
+        let wall = Arc::new(MockClock::new(10_000));
+        let clock = HybridClock::with_source(wall.clone());
+        assert_eq!(clock.now_unix(), 10_000);
+
+        // NTP correction moves the wall clock an hour back
+        wall.set_unix(10_000 - 3_600);
+        assert_eq!(clock.now_unix(), 10_000);
+
+        // Time keeps advancing at the monotonic rate
+        let mut previous = clock.now_unix();
+        for _ in 0..10 {
+            wall.advance(Duration::from_millis(400));
+            let now = clock.now_unix();
+            assert!(now >= previous);
+            previous = now;
+        }
+        assert_eq!(previous, 10_004);
+
+        // Once the wall clock catches up it takes over again
+        wall.set_unix(20_000);
+        assert_eq!(clock.now_unix(), 20_000);
+    }
+
+    #[test]
+    fn test_hybrid_clock_persists_high_water_mark() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("clock.hwm");
+
+        {
+            let wall = Arc::new(MockClock::new(150_000));
+            let clock = HybridClock::with_source(wall).with_persistence(&path)?;
+            assert_eq!(clock.now_unix(), 150_000);
+        }
+
+        // Restart with the wall clock a day behind
+        let wall = Arc::new(MockClock::new(150_000 - 86_400));
+        let clock = HybridClock::with_source(wall.clone()).with_persistence(&path)?;
+        assert_eq!(clock.now_unix(), 150_000);
+
+        wall.advance(Duration::from_secs(2));
+        assert_eq!(clock.now_unix(), 150_002);
+        assert_eq!(HybridClock::load_mark(&path)?, 150_002);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hybrid_clock_rejects_corrupt_mark() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("clock.hwm");
+        fs::write(&path, "not a number").unwrap();
+
+        assert!(HybridClock::new().with_persistence(&path).is_err());
+    }
+
+    #[test]
+    fn test_system_clock_is_near_wall_clock() {
+        let clock = SystemClock::new();
+        let wall = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(clock.now_unix().abs_diff(wall) <= 1);
+    }
+}
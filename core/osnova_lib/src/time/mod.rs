@@ -0,0 +1,35 @@
+//! # Time Module
+//!
+//! Clock abstraction for timestamps that order or expire data.
+//!
+//! This module provides:
+//! - A [`Clock`] trait with Unix time and a monotonic component
+//! - [`HybridClock`], which never returns a smaller value than it already
+//!   handed out, optionally persisting its high-water mark across restarts
+//! - [`MockClock`] for deterministic tests
+//! - A process-wide default used by models (e.g. key and pairing timestamps)
+//!
+//! Display-only timestamps may keep using `SystemTime` directly.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use osnova_lib::time::{self, Clock, HybridClock};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let clock = HybridClock::new().with_persistence("/tmp/osnova/clock.hwm")?;
+//! time::set_default_clock(Arc::new(clock));
+//!
+//! let first = time::now_unix();
+//! assert!(time::default_clock().now_unix() >= first);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod clock;
+
+pub use clock::{
+    default_clock, now_unix, set_default_clock, Clock, HybridClock, MockClock, SharedClock,
+    SystemClock,
+};
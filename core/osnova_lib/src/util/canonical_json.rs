@@ -0,0 +1,528 @@
+//! Canonical JSON encoding
+//!
+//! Anything signed, MACed, or hashed must serialize to the same bytes on
+//! every version and platform. The canonical form follows RFC 8785 (JCS)
+//! closely:
+//!
+//! - UTF-8, no insignificant whitespace
+//! - Object keys sorted by Unicode code point (UTF-8 byte order) at every
+//!   level
+//! - Strings escape only `"`, `\`, and control characters: `\b`, `\f`,
+//!   `\n`, `\r`, `\t` by name and the rest as `\u00xx` (lowercase hex);
+//!   everything else, including `/` and non-ASCII, is written as-is
+//! - Integers (from integer types) are written exactly, in decimal
+//! - Floats use the ECMAScript `Number.prototype.toString` form: shortest
+//!   round-trip digits, plain notation for magnitudes in `[1e-6, 1e21)` and
+//!   `d.ddde±x` otherwise; `-0` becomes `0` and integral values have no
+//!   fraction (`1.0` becomes `1`)
+//! - NaN and infinities are rejected
+//!
+//! Unlike JCS, keys are ordered by code point rather than UTF-16 unit (the
+//! two only differ for keys containing characters outside the Basic
+//! Multilingual Plane) and integers beyond 2^53 keep full precision.
+//!
+//! The byte output is locked by the vectors in `canonical_json_vectors.json`.
+
+use serde::ser::{self, Serialize};
+use serde_json::{Number, Value};
+
+use crate::error::Result;
+
+/// Serialize `value` to canonical JSON bytes
+///
+/// # Errors
+///
+/// Returns an error if `value` contains a NaN or infinite float, or cannot
+/// be represented as JSON (e.g. a map with non-string keys).
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    value.serialize(FiniteCheck)?;
+    let value = serde_json::to_value(value)?;
+
+    let mut out = Vec::new();
+    write_value(&mut out, &value);
+    Ok(out)
+}
+
+/// BLAKE3 hash of the canonical JSON encoding of `value`
+///
+/// # Errors
+///
+/// Returns an error if `value` has no canonical encoding (see
+/// [`to_canonical_vec`]).
+pub fn hash_canonical<T: Serialize + ?Sized>(value: &T) -> Result<[u8; 32]> {
+    Ok(*blake3::hash(&to_canonical_vec(value)?).as_bytes())
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(true) => out.extend_from_slice(b"true"),
+        Value::Bool(false) => out.extend_from_slice(b"false"),
+        Value::Number(number) => write_number(out, number),
+        Value::String(string) => write_string(out, string),
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(out, item);
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            // Sorted here rather than relying on the map type, which is
+            // insertion-ordered if serde_json's preserve_order is enabled
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_string(out, key);
+                out.push(b':');
+                write_value(out, item);
+            }
+            out.push(b'}');
+        }
+    }
+}
+
+fn write_number(out: &mut Vec<u8>, number: &Number) {
+    if let Some(n) = number.as_u64() {
+        out.extend_from_slice(n.to_string().as_bytes());
+    } else if let Some(n) = number.as_i64() {
+        out.extend_from_slice(n.to_string().as_bytes());
+    } else if let Some(n) = number.as_f64() {
+        out.extend_from_slice(format_float(n).as_bytes());
+    }
+}
+
+/// Format a finite float the way ECMAScript `Number.prototype.toString` does
+fn format_float(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+
+    // `{:e}` gives the shortest round-trip digits, e.g. "1.2345e-7"
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("LowerExp output always has an exponent");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().expect("LowerExp exponent is an integer");
+
+    // The value is 0.digits * 10^point
+    let k = digits.len() as i32;
+    let point = exponent + 1;
+
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-point) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if point > 0 { '+' } else { '-' });
+        out.push_str(&(point - 1).abs().to_string());
+    }
+    out
+}
+
+fn write_string(out: &mut Vec<u8>, string: &str) {
+    out.push(b'"');
+    for c in string.chars() {
+        match c {
+            '"' => out.extend_from_slice(b"\\\""),
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\u{08}' => out.extend_from_slice(b"\\b"),
+            '\u{0c}' => out.extend_from_slice(b"\\f"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            c if c < ' ' => out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes()),
+            c => {
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    out.push(b'"');
+}
+
+/// Serializer that only walks a value, rejecting non-finite floats
+///
+/// `serde_json` silently turns NaN and infinities into `null`, which would
+/// let two different values sign the same bytes.
+struct FiniteCheck;
+
+type CheckResult<T> = std::result::Result<T, serde_json::Error>;
+
+fn check_float(value: f64) -> CheckResult<()> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(ser::Error::custom(format!(
+            "canonical JSON cannot represent {}",
+            value
+        )))
+    }
+}
+
+impl ser::Serializer for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, _: bool) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_i8(self, _: i8) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_i16(self, _: i16) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_i32(self, _: i32) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_i64(self, _: i64) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_i128(self, _: i128) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_u8(self, _: u8) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_u16(self, _: u16) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_u32(self, _: u32) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_u64(self, _: u64) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_u128(self, _: u128) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> CheckResult<()> {
+        check_float(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> CheckResult<()> {
+        check_float(v)
+    }
+    fn serialize_char(self, _: char) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_str(self, _: &str) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_bytes(self, _: &[u8]) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_none(self) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> CheckResult<()> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> CheckResult<()> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> CheckResult<()> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &T,
+    ) -> CheckResult<()> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple(self, _: usize) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_map(self, _: Option<usize>) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> CheckResult<Self> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> CheckResult<Self> {
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> CheckResult<()> {
+        key.serialize(FiniteCheck)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for FiniteCheck {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> CheckResult<()> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> CheckResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::{BTreeMap, HashMap};
+
+    /// Committed test vectors; changing any output here breaks signatures
+    const VECTORS: &str = include_str!("canonical_json_vectors.json");
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        input: Value,
+        canonical: String,
+        blake3: String,
+    }
+
+    fn vectors() -> Vec<Vector> {
+        serde_json::from_str(VECTORS).unwrap()
+    }
+
+    #[test]
+    fn test_vectors_are_stable() {
+        for vector in vectors() {
+            let canonical = to_canonical_vec(&vector.input).unwrap();
+            assert_eq!(
+                String::from_utf8(canonical).unwrap(),
+                vector.canonical,
+                "vector {}",
+                vector.name
+            );
+            assert_eq!(
+                hex::encode(hash_canonical(&vector.input).unwrap()),
+                vector.blake3,
+                "vector {}",
+                vector.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_key_order_does_not_matter() {
+        #[derive(Serialize)]
+        struct Forward {
+            alpha: u32,
+            beta: Vec<f64>,
+            gamma: BTreeMap<String, bool>,
+        }
+
+        #[derive(Serialize)]
+        struct Backward {
+            gamma: HashMap<String, bool>,
+            beta: Vec<f64>,
+            alpha: u32,
+        }
+
+        let forward = Forward {
+            alpha: 1,
+            beta: vec![0.5, -0.0],
+            gamma: BTreeMap::from([("y".to_string(), true), ("x".to_string(), false)]),
+        };
+        let backward = Backward {
+            gamma: HashMap::from([("x".to_string(), false), ("y".to_string(), true)]),
+            beta: vec![0.5, 0.0],
+            alpha: 1,
+        };
+
+        let expected = br#"{"alpha":1,"beta":[0.5,0],"gamma":{"x":false,"y":true}}"#;
+        assert_eq!(to_canonical_vec(&forward).unwrap(), expected);
+        assert_eq!(to_canonical_vec(&backward).unwrap(), expected);
+        assert_eq!(
+            hash_canonical(&forward).unwrap(),
+            hash_canonical(&backward).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_non_finite_floats_are_rejected() {
+        #[derive(Serialize)]
+        struct Nested {
+            values: Vec<Option<f32>>,
+        }
+
+        assert!(to_canonical_vec(&f64::NAN).is_err());
+        assert!(to_canonical_vec(&vec![1.0, f64::INFINITY]).is_err());
+        assert!(to_canonical_vec(&BTreeMap::from([("x", f64::NEG_INFINITY)])).is_err());
+        assert!(hash_canonical(&Nested {
+            values: vec![Some(1.0), Some(f32::NAN)],
+        })
+        .is_err());
+        assert!(to_canonical_vec(&Nested {
+            values: vec![None, Some(f32::MAX)],
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn test_output_round_trips_through_serde_json() {
+        for vector in vectors() {
+            let canonical = to_canonical_vec(&vector.input).unwrap();
+            let parsed: Value = serde_json::from_slice(&canonical).unwrap();
+
+            assert_eq!(
+                to_canonical_vec(&parsed).unwrap(),
+                canonical,
+                "vector {}",
+                vector.name
+            );
+            if !vector.name.starts_with("float") {
+                assert_eq!(parsed, vector.input, "vector {}", vector.name);
+            }
+        }
+
+        let value = json!({"n": 0.1, "s": "a\u{7f}\u{1}"});
+        let parsed: Value = serde_json::from_slice(&to_canonical_vec(&value).unwrap()).unwrap();
+        assert_eq!(parsed, value);
+    }
+}
@@ -0,0 +1,527 @@
+//! # Display Formatting
+//!
+//! Locale-aware formatting of sizes, durations, token amounts and relative
+//! times, so every screen renders them the same way.
+//!
+//! Service responses keep their raw values (bytes, seconds, AttoTokens,
+//! Unix timestamps) for programmatic use. Response types that feed UI
+//! screens implement [`Localize`], which fills in display fields next to
+//! the raw values for a [`DisplayContext`]: the user's [`Locale`], as set
+//! through `ui.setLocale`, and the current time.
+//!
+//! Conventions, whatever the locale:
+//! - Sizes use binary units (KiB, MiB, ...) with at most one decimal
+//! - Durations show their two largest units (`1 h 5 min`)
+//! - Token amounts keep every significant decimal; trailing zeros are
+//!   dropped and the whole part is grouped by thousands
+//! - Relative times count whole minutes, hours, days, months (30 days)
+//!   and years (365 days); under a minute is "just now"
+//!
+//! Unit strings and separators come from a catalog per language (English,
+//! German, French and Spanish); other languages fall back to English.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::util::format::{format_bytes, format_token_amount, Locale};
+//!
+//! let german = Locale::new("de-DE");
+//! assert_eq!(format_bytes(1_572_864, &german), "1,5 MiB");
+//! assert_eq!(format_token_amount(1_234_500, 3, "ANT", &german), "1.234,5 ANT");
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::condition::ConditionContext;
+
+/// Locale used when none is set or detected
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// A user's locale as a BCP 47 tag (`en-US`, `de`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct Locale(String);
+
+impl Locale {
+    /// Locale with the BCP 47 tag `tag`
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self(tag.into())
+    }
+
+    /// Locale of the operating system (`LC_ALL`, `LC_MESSAGES`, `LANG`)
+    pub fn system() -> Self {
+        Self(ConditionContext::current().locale)
+    }
+
+    /// The BCP 47 tag
+    pub fn tag(&self) -> &str {
+        &self.0
+    }
+
+    /// Primary language subtag, lowercased (`de` for `de-AT`)
+    pub fn language(&self) -> String {
+        self.0
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    }
+
+    fn catalog(&self) -> &'static UnitCatalog {
+        match self.language().as_str() {
+            "de" => &GERMAN,
+            "fr" => &FRENCH,
+            "es" => &SPANISH,
+            _ => &ENGLISH,
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCALE)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Unit strings and separators of a language
+struct UnitCatalog {
+    decimal: &'static str,
+    group: &'static str,
+    /// B, KiB, MiB, GiB, TiB, PiB, EiB
+    bytes: [&'static str; 7],
+    /// Days, hours, minutes, seconds, milliseconds
+    duration: [&'static str; 5],
+    just_now: &'static str,
+    /// Past time around `{}`
+    past: &'static str,
+    /// Future time around `{}`
+    future: &'static str,
+    /// Minute, hour, day, month, year: singular and plural
+    relative: [(&'static str, &'static str); 5],
+}
+
+const ENGLISH: UnitCatalog = UnitCatalog {
+    decimal: ".",
+    group: ",",
+    bytes: ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+    duration: ["d", "h", "min", "s", "ms"],
+    just_now: "just now",
+    past: "{} ago",
+    future: "in {}",
+    relative: [
+        ("minute", "minutes"),
+        ("hour", "hours"),
+        ("day", "days"),
+        ("month", "months"),
+        ("year", "years"),
+    ],
+};
+
+const GERMAN: UnitCatalog = UnitCatalog {
+    decimal: ",",
+    group: ".",
+    bytes: ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+    duration: ["T", "h", "min", "s", "ms"],
+    just_now: "gerade eben",
+    past: "vor {}",
+    future: "in {}",
+    relative: [
+        ("Minute", "Minuten"),
+        ("Stunde", "Stunden"),
+        ("Tag", "Tagen"),
+        ("Monat", "Monaten"),
+        ("Jahr", "Jahren"),
+    ],
+};
+
+const FRENCH: UnitCatalog = UnitCatalog {
+    decimal: ",",
+    group: "\u{202f}",
+    bytes: ["o", "Kio", "Mio", "Gio", "Tio", "Pio", "Eio"],
+    duration: ["j", "h", "min", "s", "ms"],
+    just_now: "à l'instant",
+    past: "il y a {}",
+    future: "dans {}",
+    relative: [
+        ("minute", "minutes"),
+        ("heure", "heures"),
+        ("jour", "jours"),
+        ("mois", "mois"),
+        ("an", "ans"),
+    ],
+};
+
+const SPANISH: UnitCatalog = UnitCatalog {
+    decimal: ",",
+    group: ".",
+    bytes: ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+    duration: ["d", "h", "min", "s", "ms"],
+    just_now: "ahora",
+    past: "hace {}",
+    future: "dentro de {}",
+    relative: [
+        ("minuto", "minutos"),
+        ("hora", "horas"),
+        ("día", "días"),
+        ("mes", "meses"),
+        ("año", "años"),
+    ],
+};
+
+/// Group the digits of a whole number by thousands
+fn group_digits(digits: &str, separator: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Format a size in bytes with binary units (`1.5 MiB`)
+///
+/// Sizes under 1 KiB are exact; larger ones are rounded to one decimal,
+/// moving to the next unit where that rounds up to 1024.
+pub fn format_bytes(bytes: u64, locale: &Locale) -> String {
+    let catalog = locale.catalog();
+    let mut unit = 0;
+    while unit + 1 < catalog.bytes.len() && bytes >= 1u64 << (10 * (unit + 1)) {
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{} {}", bytes, catalog.bytes[0]);
+    }
+
+    let tenths = |unit: usize| {
+        let divisor = 1u128 << (10 * unit);
+        (u128::from(bytes) * 10 + divisor / 2) / divisor
+    };
+    let mut rounded = tenths(unit);
+    if rounded >= 10_240 && unit + 1 < catalog.bytes.len() {
+        unit += 1;
+        rounded = tenths(unit);
+    }
+
+    let (whole, tenth) = (rounded / 10, rounded % 10);
+    if tenth == 0 {
+        format!("{} {}", whole, catalog.bytes[unit])
+    } else {
+        format!(
+            "{}{}{} {}",
+            whole, catalog.decimal, tenth, catalog.bytes[unit]
+        )
+    }
+}
+
+/// Format a duration by its two largest units (`1 h 5 min`, `45 s`)
+///
+/// Durations under a second are shown in milliseconds.
+pub fn format_duration(duration: Duration, locale: &Locale) -> String {
+    let catalog = locale.catalog();
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{} {}", duration.subsec_millis(), catalog.duration[4]);
+    }
+
+    let parts = [secs / 86_400, secs / 3_600 % 24, secs / 60 % 60, secs % 60];
+    let largest = parts.iter().position(|&part| part > 0).unwrap_or(3);
+    let mut formatted = format!("{} {}", parts[largest], catalog.duration[largest]);
+    if let Some(&next) = parts.get(largest + 1) {
+        if next > 0 {
+            formatted.push_str(&format!(" {} {}", next, catalog.duration[largest + 1]));
+        }
+    }
+    formatted
+}
+
+/// Format an amount of a token's smallest unit as a decimal amount with
+/// its symbol (`1,234.5 ANT`)
+///
+/// `decimals` is the number of decimal places of one whole token (18 for
+/// ANT, counted in AttoTokens). Every significant decimal is kept, so
+/// amounts below one whole token are never rounded to zero.
+pub fn format_token_amount(amount: u128, decimals: u8, symbol: &str, locale: &Locale) -> String {
+    let catalog = locale.catalog();
+    let digits = format!("{:0>width$}", amount, width = usize::from(decimals) + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - usize::from(decimals));
+    let fraction = fraction.trim_end_matches('0');
+
+    let mut formatted = group_digits(whole, catalog.group);
+    if !fraction.is_empty() {
+        formatted.push_str(catalog.decimal);
+        formatted.push_str(fraction);
+    }
+    if !symbol.is_empty() {
+        formatted.push(' ');
+        formatted.push_str(symbol);
+    }
+    formatted
+}
+
+/// Format a Unix timestamp relative to `now` (`5 minutes ago`, `in 2 days`)
+pub fn format_relative_time(timestamp: u64, now: u64, locale: &Locale) -> String {
+    let catalog = locale.catalog();
+    let (elapsed, template) = if timestamp <= now {
+        (now - timestamp, catalog.past)
+    } else {
+        (timestamp - now, catalog.future)
+    };
+
+    const UNITS: [u64; 5] = [60, 3_600, 86_400, 30 * 86_400, 365 * 86_400];
+    let Some(unit) = UNITS.iter().rposition(|&secs| elapsed >= secs) else {
+        return catalog.just_now.to_string();
+    };
+    let count = elapsed / UNITS[unit];
+    let (singular, plural) = catalog.relative[unit];
+    let amount = format!("{} {}", count, if count == 1 { singular } else { plural });
+    template.replace("{}", &amount)
+}
+
+/// Locale and current time for which display fields are filled in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayContext {
+    /// The user's locale
+    pub locale: Locale,
+    /// Current Unix timestamp, for relative times
+    pub now: u64,
+}
+
+impl DisplayContext {
+    /// Context for `locale` at Unix time `now`
+    pub fn new(locale: Locale, now: u64) -> Self {
+        Self { locale, now }
+    }
+
+    /// [`format_bytes`] in this context
+    pub fn bytes(&self, bytes: u64) -> String {
+        format_bytes(bytes, &self.locale)
+    }
+
+    /// [`format_duration`] in this context
+    pub fn duration(&self, duration: Duration) -> String {
+        format_duration(duration, &self.locale)
+    }
+
+    /// [`format_token_amount`] in this context
+    pub fn token_amount(&self, amount: u128, decimals: u8, symbol: &str) -> String {
+        format_token_amount(amount, decimals, symbol, &self.locale)
+    }
+
+    /// [`format_relative_time`] from this context's current time
+    pub fn relative_time(&self, timestamp: u64) -> String {
+        format_relative_time(timestamp, self.now, &self.locale)
+    }
+}
+
+/// A response type with display fields next to its raw values
+pub trait Localize {
+    /// Fill in the display fields for `context`
+    fn localize(&mut self, context: &DisplayContext);
+
+    /// This value with its display fields filled in for `context`
+    fn localized(mut self, context: &DisplayContext) -> Self
+    where
+        Self: Sized,
+    {
+        self.localize(context);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * KIB;
+    const GIB: u64 = 1024 * MIB;
+
+    fn locales() -> [Locale; 4] {
+        ["en-US", "de-DE", "fr-FR", "es"].map(Locale::new)
+    }
+
+    #[test]
+    fn test_format_bytes_vectors() {
+        let vectors: [(u64, [&str; 4]); 9] = [
+            (0, ["0 B", "0 B", "0 o", "0 B"]),
+            (1023, ["1023 B", "1023 B", "1023 o", "1023 B"]),
+            (KIB, ["1 KiB", "1 KiB", "1 Kio", "1 KiB"]),
+            (1536, ["1.5 KiB", "1,5 KiB", "1,5 Kio", "1,5 KiB"]),
+            (MIB - 1, ["1 MiB", "1 MiB", "1 Mio", "1 MiB"]),
+            (MIB, ["1 MiB", "1 MiB", "1 Mio", "1 MiB"]),
+            (
+                5 * GIB + 300 * MIB,
+                ["5.3 GiB", "5,3 GiB", "5,3 Gio", "5,3 GiB"],
+            ),
+            (1024 * GIB, ["1 TiB", "1 TiB", "1 Tio", "1 TiB"]),
+            (u64::MAX, ["16 EiB", "16 EiB", "16 Eio", "16 EiB"]),
+        ];
+        for (bytes, expected) in vectors {
+            for (locale, expected) in locales().iter().zip(expected) {
+                assert_eq!(
+                    format_bytes(bytes, locale),
+                    expected,
+                    "{} in {}",
+                    bytes,
+                    locale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_duration_vectors() {
+        let vectors: [(Duration, [&str; 4]); 7] = [
+            (Duration::ZERO, ["0 ms", "0 ms", "0 ms", "0 ms"]),
+            (
+                Duration::from_millis(250),
+                ["250 ms", "250 ms", "250 ms", "250 ms"],
+            ),
+            (Duration::from_secs(59), ["59 s", "59 s", "59 s", "59 s"]),
+            (
+                Duration::from_secs(60),
+                ["1 min", "1 min", "1 min", "1 min"],
+            ),
+            (
+                Duration::from_secs(3_900),
+                ["1 h 5 min", "1 h 5 min", "1 h 5 min", "1 h 5 min"],
+            ),
+            (
+                Duration::from_secs(86_400 + 59),
+                ["1 d", "1 T", "1 j", "1 d"],
+            ),
+            (
+                Duration::from_secs(2 * 86_400 + 3 * 3_600 + 7),
+                ["2 d 3 h", "2 T 3 h", "2 j 3 h", "2 d 3 h"],
+            ),
+        ];
+        for (duration, expected) in vectors {
+            for (locale, expected) in locales().iter().zip(expected) {
+                assert_eq!(
+                    format_duration(duration, locale),
+                    expected,
+                    "{:?}",
+                    duration
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_token_amount_vectors() {
+        const ANT: u128 = 1_000_000_000_000_000_000;
+        let vectors: [(u128, [&str; 4]); 6] = [
+            (0, ["0 ANT", "0 ANT", "0 ANT", "0 ANT"]),
+            (
+                1,
+                [
+                    "0.000000000000000001 ANT",
+                    "0,000000000000000001 ANT",
+                    "0,000000000000000001 ANT",
+                    "0,000000000000000001 ANT",
+                ],
+            ),
+            (ANT / 2, ["0.5 ANT", "0,5 ANT", "0,5 ANT", "0,5 ANT"]),
+            (ANT, ["1 ANT", "1 ANT", "1 ANT", "1 ANT"]),
+            (
+                1_000 * ANT,
+                ["1,000 ANT", "1.000 ANT", "1\u{202f}000 ANT", "1.000 ANT"],
+            ),
+            (
+                1_234_567 * ANT + ANT / 4,
+                [
+                    "1,234,567.25 ANT",
+                    "1.234.567,25 ANT",
+                    "1\u{202f}234\u{202f}567,25 ANT",
+                    "1.234.567,25 ANT",
+                ],
+            ),
+        ];
+        for (amount, expected) in vectors {
+            for (locale, expected) in locales().iter().zip(expected) {
+                assert_eq!(format_token_amount(amount, 18, "ANT", locale), expected);
+            }
+        }
+
+        let english = Locale::default();
+        assert_eq!(format_token_amount(42, 0, "", &english), "42");
+        assert_eq!(format_token_amount(5, 2, "EUR", &english), "0.05 EUR");
+    }
+
+    #[test]
+    fn test_format_relative_time_vectors() {
+        let now = 1_700_000_000;
+        let vectors: [(u64, [&str; 4]); 8] = [
+            (now, ["just now", "gerade eben", "à l'instant", "ahora"]),
+            (
+                now - 59,
+                ["just now", "gerade eben", "à l'instant", "ahora"],
+            ),
+            (
+                now - 60,
+                [
+                    "1 minute ago",
+                    "vor 1 Minute",
+                    "il y a 1 minute",
+                    "hace 1 minuto",
+                ],
+            ),
+            (
+                now - 2 * 3_600 - 59,
+                [
+                    "2 hours ago",
+                    "vor 2 Stunden",
+                    "il y a 2 heures",
+                    "hace 2 horas",
+                ],
+            ),
+            (
+                now - 86_400,
+                ["1 day ago", "vor 1 Tag", "il y a 1 jour", "hace 1 día"],
+            ),
+            (
+                now - 45 * 86_400,
+                ["1 month ago", "vor 1 Monat", "il y a 1 mois", "hace 1 mes"],
+            ),
+            (
+                now - 800 * 86_400,
+                ["2 years ago", "vor 2 Jahren", "il y a 2 ans", "hace 2 años"],
+            ),
+            (
+                now + 3 * 86_400,
+                [
+                    "in 3 days",
+                    "in 3 Tagen",
+                    "dans 3 jours",
+                    "dentro de 3 días",
+                ],
+            ),
+        ];
+        for (timestamp, expected) in vectors {
+            for (locale, expected) in locales().iter().zip(expected) {
+                assert_eq!(format_relative_time(timestamp, now, locale), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_language_falls_back_to_english() {
+        let locale = Locale::new("pt_BR");
+        assert_eq!(locale.language(), "pt");
+        assert_eq!(format_bytes(1536, &locale), "1.5 KiB");
+        assert_eq!(Locale::new("DE-at").language(), "de");
+        assert_eq!(format_bytes(1536, &Locale::new("de-AT")), "1,5 KiB");
+    }
+}
@@ -0,0 +1,25 @@
+//! # Util Module
+//!
+//! Small helpers shared across modules.
+//!
+//! This module provides:
+//! - [`canonical_json`], the single canonical JSON encoding used wherever
+//!   bytes are signed, MACed, or hashed
+//! - [`format`], locale-aware display formatting of sizes, durations, token
+//!   amounts and relative times
+//!
+//! ## Example
+//!
+//! ```rust
+//! use osnova_lib::util::canonical_json;
+//! use serde_json::json;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let bytes = canonical_json::to_canonical_vec(&json!({"b": 1, "a": [true, null]}))?;
+//! assert_eq!(bytes, br#"{"a":[true,null],"b":1}"#);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod canonical_json;
+pub mod format;
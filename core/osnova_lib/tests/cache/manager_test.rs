@@ -1,6 +1,9 @@
 //! Tests for component cache manager
 
 use osnova_lib::cache::CacheManager;
+use osnova_lib::time::MockClock;
+use std::sync::Arc;
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[tokio::test]
@@ -89,15 +92,21 @@ async fn test_cache_eviction_when_full() {
 async fn test_cache_lru_update_on_access() {
     // Test that accessing an item updates its LRU position
     let temp_dir = TempDir::new().unwrap();
-    let cache = CacheManager::new(temp_dir.path().to_path_buf(), 2500).unwrap();
+    let clock = Arc::new(MockClock::new(1_000));
+    let cache = CacheManager::new(temp_dir.path().to_path_buf(), 2500)
+        .unwrap()
+        .with_clock(clock.clone());
 
     let data = vec![0u8; 1000];
 
     cache.store("key1", &data).await.unwrap();
+    clock.advance(Duration::from_secs(1));
     cache.store("key2", &data).await.unwrap();
+    clock.advance(Duration::from_secs(1));
 
     // Access key1 to make it recently used
     let _ = cache.get("key1").await.unwrap();
+    clock.advance(Duration::from_secs(1));
 
     // Store key3, which should evict key2 (least recently used)
     cache.store("key3", &data).await.unwrap();
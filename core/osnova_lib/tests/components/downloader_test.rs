@@ -22,6 +22,10 @@ async fn test_download_from_cache() {
         version: "1.0.0".to_string(),
         hash: Some("abc123".to_string()),
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let data = b"cached component data";
@@ -52,6 +56,10 @@ async fn test_download_from_network() {
                 version: "1.0.0".to_string(),
                 hash: None,
                 config: None,
+                shared: false,
+                shared_id: None,
+                interpreter: None,
+                extra: Default::default(),
             };
 
             let downloader = ComponentDownloader::new(cache, Some(client));
@@ -88,6 +96,10 @@ async fn test_download_local_file() {
         version: "1.0.0".to_string(),
         hash: None,
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let downloader = ComponentDownloader::new(cache, None);
@@ -122,6 +134,10 @@ async fn test_hash_verification() {
         version: "1.0.0".to_string(),
         hash: Some(hash_b64),
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let downloader = ComponentDownloader::new(cache, None);
@@ -149,6 +165,10 @@ async fn test_hash_verification_fails() {
         version: "1.0.0".to_string(),
         hash: Some("invalid_hash_value".to_string()),
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let downloader = ComponentDownloader::new(cache, None);
@@ -192,6 +212,10 @@ async fn test_extract_tarball() {
         version: "1.0.0".to_string(),
         hash: None,
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let downloader = ComponentDownloader::new(cache, None);
@@ -222,6 +246,10 @@ async fn test_backend_binary() {
         version: "1.0.0".to_string(),
         hash: None,
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let downloader = ComponentDownloader::new(cache, None);
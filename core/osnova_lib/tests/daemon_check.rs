@@ -0,0 +1,58 @@
+//! `osnova-daemon check` run as a process: the JSON report on stdout and
+//! the documented exit codes
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn daemon_check(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_osnova-daemon"))
+        .arg("check")
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("OSNOVA_STORAGE_PATH", home.join("storage"))
+        .env("OSNOVA_RPC_LISTEN", "127.0.0.1:0")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check_succeeds_quickly() {
+    let home = TempDir::new().unwrap();
+    let started = Instant::now();
+    let output = daemon_check(home.path(), &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(started.elapsed() < Duration::from_secs(10));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["exitCode"], 0);
+    assert_eq!(report["checks"].as_array().unwrap().len(), 5);
+    // Nothing is created by checking
+    assert!(!home.path().join("storage").exists());
+}
+
+#[test]
+fn test_check_invalid_config() {
+    let home = TempDir::new().unwrap();
+    let config = home.path().join("config.toml");
+    std::fs::write(&config, "mode = \"sideways\"\n").unwrap();
+    let output = daemon_check(home.path(), &["--config", config.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["checks"][0]["name"], "config");
+    assert_eq!(report["checks"][0]["status"], "failed");
+    assert_eq!(report["checks"][0]["exitCode"], 2);
+}
+
+#[test]
+fn test_check_missing_config_argument() {
+    let home = TempDir::new().unwrap();
+    let output = daemon_check(home.path(), &["--config"]);
+    assert_eq!(output.status.code(), Some(2));
+}
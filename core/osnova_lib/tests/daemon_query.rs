@@ -0,0 +1,64 @@
+//! `osnova-daemon query` run as a process: listing the diagnostic queries,
+//! and refusing anything outside them
+
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn daemon_query(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_osnova-daemon"))
+        .arg("query")
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("OSNOVA_STORAGE_PATH", home.join("storage"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_query_lists_registry() {
+    let home = TempDir::new().unwrap();
+    let output = daemon_query(home.path(), &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let queries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = queries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|query| query["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"blobsPerNamespace"));
+    assert!(names.contains(&"recentCrashReports"));
+    // The SQL itself is never shown or taken from outside
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("SELECT"));
+}
+
+#[test]
+fn test_query_rejects_unknown_queries_and_params() {
+    let home = TempDir::new().unwrap();
+    for args in [
+        &["SELECT * FROM encrypted_blobs"][..],
+        &["recentCrashReports", "limit=ten"],
+        &["recentCrashReports", "sql=DROP TABLE crash_reports"],
+        &["recentCrashReports", "limit"],
+    ] {
+        let output = daemon_query(home.path(), args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    }
+}
+
+#[cfg(not(feature = "diagnostics"))]
+#[test]
+fn test_query_refused_without_diagnostics_feature() {
+    let home = TempDir::new().unwrap();
+    let output = daemon_query(home.path(), &["blobsPerNamespace"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("not available in this build"), "{}", stderr);
+    assert!(output.stdout.is_empty());
+    assert!(!home.path().join("storage").exists());
+}
@@ -42,6 +42,7 @@ async fn test_e2e_local_manifest_with_local_components() {
         description: "Test application".to_string(),
         publisher: Some("Test Publisher".to_string()),
         signature: None,
+        signature_policy: None,
         components: vec![
             ComponentSchema {
                 id: format!("file://{}", frontend_tarball.display()),
@@ -52,6 +53,10 @@ async fn test_e2e_local_manifest_with_local_components() {
                 version: "1.0.0".to_string(),
                 hash: Some(frontend_hash.clone()),
                 config: None,
+                shared: false,
+                shared_id: None,
+                interpreter: None,
+                extra: Default::default(),
             },
             ComponentSchema {
                 id: format!("file://{}", backend_binary.display()),
@@ -62,9 +67,21 @@ async fn test_e2e_local_manifest_with_local_components() {
                 version: "1.0.0".to_string(),
                 hash: Some(backend_hash.clone()),
                 config: None,
+                shared: false,
+                shared_id: None,
+                // A script, so the x86_64 target need not match the host
+                interpreter: Some("sh".to_string()),
+                extra: Default::default(),
             },
         ],
+        uri_schemes: Vec::new(),
+        data_offers: Vec::new(),
+        searchable_config_keys: Vec::new(),
+        settings_schema: None,
+        feature_flags: Vec::new(),
         metadata: None,
+        min_core_version: None,
+        extra: Default::default(),
     };
 
     // Write manifest to file
@@ -138,6 +155,10 @@ async fn test_e2e_hash_verification_failure() {
         version: "1.0.0".to_string(),
         hash: Some("INVALID_HASH_VALUE".to_string()),
         config: None,
+        shared: false,
+        shared_id: None,
+        interpreter: None,
+        extra: Default::default(),
     };
 
     let cache = CacheManager::new(&cache_dir, 100 * 1024 * 1024).unwrap();
@@ -179,6 +200,10 @@ async fn test_e2e_cache_eviction() {
             version: "1.0.0".to_string(),
             hash: None,
             config: None,
+            shared: false,
+            shared_id: None,
+            interpreter: None,
+            extra: Default::default(),
         });
     }
 
@@ -280,7 +305,7 @@ fn create_large_test_tarball(dir: &std::path::Path, name: &str) -> std::path::Pa
 
 fn create_test_binary(dir: &std::path::Path, name: &str) -> std::path::PathBuf {
     let binary_path = dir.join(name);
-    fs::write(&binary_path, b"\x7fELF mock binary").unwrap();
+    fs::write(&binary_path, b"#!/bin/sh\necho mock backend\n").unwrap();
     binary_path
 }
 
@@ -0,0 +1,102 @@
+//! The bundled scenarios, run through the library and through
+//! `osnova-harness` as a process
+
+#![cfg(feature = "harness")]
+
+use osnova_lib::harness::{self, Scenario};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn osnova_harness(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_osnova-harness"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+async fn run_bundled(name: &str) {
+    let scenario = harness::bundled()
+        .unwrap()
+        .into_iter()
+        .find(|scenario| scenario.name == name)
+        .unwrap();
+    let report = scenario.run().await.unwrap();
+    if let Err(failure) = report.ensure_passed() {
+        panic!("{}", failure);
+    }
+    assert_eq!(report.steps_run, scenario.steps.len());
+}
+
+#[tokio::test]
+async fn test_fresh_onboarding() {
+    run_bundled("fresh onboarding").await;
+}
+
+#[tokio::test]
+async fn test_backup_restore_round_trip() {
+    run_bundled("backup and restore").await;
+}
+
+#[tokio::test]
+async fn test_update_with_rollback() {
+    run_bundled("update with rollback").await;
+}
+
+#[test]
+fn test_cli_runs_scenario_files() {
+    let temp = TempDir::new().unwrap();
+    let passing = temp.path().join("passing.json");
+    let scenario = Scenario::new("install")
+        .create_identity()
+        .install_app("com.example.notes", "1.0.0");
+    std::fs::write(&passing, scenario.to_json().unwrap()).unwrap();
+
+    let output = osnova_harness(&[passing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "ok   install (2 steps)\n"
+    );
+
+    let failing = temp.path().join("failing.json");
+    let scenario = Scenario::new("launch").launch_app("com.example.missing");
+    std::fs::write(&failing, scenario.to_json().unwrap()).unwrap();
+    let output = osnova_harness(&[passing.to_str().unwrap(), failing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("FAIL launch"));
+    assert!(stdout.contains("failed at step 1 of 1"));
+
+    let output = osnova_harness(&["--json", passing.to_str().unwrap()]);
+    let reports: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(reports[0]["name"], "install");
+    assert_eq!(reports[0]["state"]["apps"][0]["appId"], "com.example.notes");
+
+    let broken = temp.path().join("broken.json");
+    std::fs::write(
+        &broken,
+        r#"{ "name": "broken", "steps": [{ "step": "fly" }] }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        osnova_harness(&[broken.to_str().unwrap()]).status.code(),
+        Some(2)
+    );
+    assert_eq!(osnova_harness(&[]).status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_runs_bundled_scenarios() {
+    let output = osnova_harness(&["--bundled"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 3);
+
+    let output = osnova_harness(&["--list"]);
+    let listing = String::from_utf8_lossy(&output.stdout);
+    assert!(listing.contains("backup and restore: "));
+}
@@ -0,0 +1,104 @@
+//! `osnova-manifest` run as a process: normalizing a build for publishing,
+//! then verifying local builds against the published manifest
+
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn osnova_manifest(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_osnova-manifest"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// A build output directory holding `index.html`
+fn build(dir: &Path, name: &str, index: &str) -> String {
+    let build = dir.join(name);
+    std::fs::create_dir_all(build.join("assets")).unwrap();
+    std::fs::write(build.join("index.html"), index).unwrap();
+    std::fs::write(build.join("assets/app.js"), "console.log('app')").unwrap();
+    build.to_str().unwrap().to_string()
+}
+
+/// Write a manifest declaring one frontend with `hash`
+fn manifest(dir: &Path, hash: &str) -> String {
+    let manifest = serde_json::json!({
+        "id": "ant://manifest",
+        "name": "Repro",
+        "version": "1.0.0",
+        "iconUri": "ant://icon",
+        "description": "Reproducible app",
+        "components": [{
+            "id": "ant://frontend",
+            "name": "Frontend",
+            "kind": "frontend",
+            "platform": "desktop",
+            "version": "1.0.0",
+            "hash": hash,
+        }],
+    });
+    let path = dir.join("manifest.json");
+    std::fs::write(&path, manifest.to_string()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_normalize_then_verify_repro() {
+    let dir = TempDir::new().unwrap();
+    let published = dir.path().join("frontend.tar.gz");
+    let source = build(dir.path(), "release", "<html></html>");
+
+    let output = osnova_manifest(&["normalize", &source, published.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let hash = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert!(published.exists());
+    let manifest = manifest(dir.path(), &hash);
+
+    // Both the published archive and a fresh build of the same source match
+    let rebuilt = build(dir.path(), "rebuilt", "<html></html>");
+    for artifact in [published.to_str().unwrap(), &rebuilt] {
+        let output = osnova_manifest(&["verify-repro", &manifest, "ant://frontend", artifact]);
+        assert_eq!(output.status.code(), Some(0));
+        let verdict: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(verdict["verdict"], "match");
+        assert_eq!(verdict["hash"], hash.as_str());
+    }
+
+    // A one-byte change does not
+    let changed = build(dir.path(), "changed", "<html> </html>");
+    let output = osnova_manifest(&["verify-repro", &manifest, "ant://frontend", &changed]);
+    assert_eq!(output.status.code(), Some(1));
+    let verdict: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(verdict["verdict"], "mismatch");
+    assert_eq!(verdict["expected"], hash.as_str());
+    assert!(verdict["normalizationNotes"].as_array().is_some());
+}
+
+#[test]
+fn test_verify_repro_not_comparable() {
+    let dir = TempDir::new().unwrap();
+    let manifest = manifest(dir.path(), "hash");
+    let source = build(dir.path(), "release", "<html></html>");
+
+    let output = osnova_manifest(&["verify-repro", &manifest, "ant://backend", &source]);
+    assert_eq!(output.status.code(), Some(2));
+    let verdict: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(verdict["verdict"], "notComparable");
+
+    let missing = dir.path().join("missing.json");
+    let output = osnova_manifest(&[
+        "verify-repro",
+        missing.to_str().unwrap(),
+        "ant://frontend",
+        &source,
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_usage() {
+    let output = osnova_manifest(&["verify-repro"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Usage"));
+}
@@ -0,0 +1,218 @@
+//! Golden responses of the shell's core commands
+//!
+//! Runs every command of `osnova_lib::tauri_api` against a fixture storage
+//! root and compares each response, byte for byte, with the one recorded
+//! in `tests/golden/tauri_api`. A mismatch means the frontend would see a
+//! different response. If the change is intended, regenerate the files
+//! with `OSNOVA_UPDATE_GOLDEN=1 cargo test --test tauri_api` and review
+//! the diff.
+
+use osnova_lib::context::OsnovaContext;
+use osnova_lib::models::application::OsnovaApplication;
+use osnova_lib::network::NetworkOptions;
+use osnova_lib::services::{AppsService, LauncherService, NavigationService, UIService};
+use osnova_lib::storage::SqlStorage;
+use osnova_lib::tauri_api::dto::{
+    AppsLaunchRequest, IdentityImportRequest, LauncherSetLayoutRequest,
+    NavigationSetBottomMenuRequest, UiSetThemeRequest,
+};
+use osnova_lib::tauri_api::TauriApi;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+/// Seed phrase of the fixture identity
+const SEED: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// App installed in the fixture
+const NOTES: &str = "com.example.notes";
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/tauri_api")
+}
+
+/// Records responses and checks them against the golden files
+struct Golden {
+    storage: String,
+    mismatches: Vec<String>,
+}
+
+impl Golden {
+    /// Compare a command's result with `<name>.json`
+    ///
+    /// The result is recorded as `{"Ok": ...}` or `{"Err": ...}`, with the
+    /// fixture's storage path replaced so the files do not depend on it.
+    fn check<T: Serialize>(&mut self, name: &str, result: Result<T, String>) {
+        let recorded = serde_json::to_string(&result)
+            .unwrap()
+            .replace(&self.storage, "<storage>");
+        let path = golden_dir().join(format!("{}.json", name));
+        if std::env::var_os("OSNOVA_UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(golden_dir()).unwrap();
+            std::fs::write(&path, format!("{}\n", recorded)).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("{} has no golden file", name));
+        if expected.trim_end_matches('\n') != recorded {
+            self.mismatches.push(format!(
+                "{}:\n  expected {}\n  actual   {}",
+                name,
+                expected.trim_end(),
+                recorded
+            ));
+        }
+    }
+}
+
+/// Open the user's services as the shell does once the identity exists
+fn init_for_user(api: &TauriApi, storage: &Path, user_id: &str) -> Result<(), String> {
+    let apps = AppsService::new(storage)
+        .map_err(|e| e.to_string())?
+        .with_user(user_id);
+    api.apps().set(apps);
+    let launcher = LauncherService::new(storage, user_id).map_err(|e| e.to_string())?;
+    api.launcher().set(launcher);
+    let ui = UIService::new(storage, user_id).map_err(|e| e.to_string())?;
+    api.ui().set(Arc::new(ui));
+    let navigation = NavigationService::new(storage, user_id).map_err(|e| e.to_string())?;
+    api.navigation().set(navigation);
+    Ok(())
+}
+
+fn install_notes(storage: &Path) {
+    let app = OsnovaApplication::new(
+        NOTES,
+        "Notes",
+        "1.2.0",
+        "ant://notes-icon",
+        "Takes notes",
+        vec![],
+    )
+    .unwrap();
+    SqlStorage::new(storage.join("osnova.db"))
+        .unwrap()
+        .upsert_application(&app)
+        .unwrap();
+}
+
+fn launch(app_id: &str) -> AppsLaunchRequest {
+    AppsLaunchRequest {
+        app_id: app_id.to_string(),
+        verify: None,
+    }
+}
+
+#[tokio::test]
+async fn test_core_commands_match_golden_responses() {
+    let temp = TempDir::new().unwrap();
+    let storage = temp.path().to_path_buf();
+    let api = TauriApi::from_context(&OsnovaContext::new(&storage));
+    let mut golden = Golden {
+        storage: storage.display().to_string(),
+        mismatches: Vec::new(),
+    };
+    let options = NetworkOptions::default();
+
+    // Before onboarding
+    golden.check("identity_check_fresh", api.identity_check());
+    golden.check("identity_get_fresh", api.identity_get());
+    golden.check("apps_list_uninitialized", api.apps_list());
+    golden.check(
+        "apps_launch_uninitialized",
+        api.apps_launch(launch(NOTES), &options, |_| {}).await,
+    );
+    golden.check(
+        "launcher_get_layout_uninitialized",
+        api.launcher_get_layout(),
+    );
+    golden.check("ui_get_theme_uninitialized", api.ui_get_theme());
+    golden.check(
+        "navigation_get_bottom_menu_uninitialized",
+        api.navigation_get_bottom_menu(),
+    );
+    golden.check("status_get_server", api.status_get_server());
+
+    // Onboarding
+    let request = IdentityImportRequest {
+        seed_phrase: SEED.to_string(),
+    };
+    golden.check(
+        "identity_import",
+        api.identity_import(request.clone(), |address| {
+            init_for_user(&api, &storage, address)
+        }),
+    );
+    golden.check("identity_check", api.identity_check());
+    golden.check("identity_get", api.identity_get());
+    golden.check(
+        "identity_import_existing",
+        api.identity_import(request, |_| Ok(())),
+    );
+    golden.check("identity_create_existing", api.identity_create(|_| Ok(())));
+
+    // Apps and launcher
+    golden.check("apps_list_empty", api.apps_list());
+    install_notes(&storage);
+    golden.check("apps_list", api.apps_list());
+    golden.check(
+        "launcher_set_layout",
+        api.launcher_set_layout(LauncherSetLayoutRequest {
+            app_ids: vec![NOTES.to_string()],
+        }),
+    );
+    golden.check("launcher_get_layout", api.launcher_get_layout());
+    golden.check(
+        "apps_launch_missing",
+        api.apps_launch(launch("com.example.missing"), &options, |_| {})
+            .await,
+    );
+    golden.check(
+        "apps_launch_consent_required",
+        api.apps_launch(launch(NOTES), &options, |_| {}).await,
+    );
+
+    // Theme and navigation
+    golden.check("ui_get_theme_default", api.ui_get_theme());
+    golden.check(
+        "ui_set_theme",
+        api.ui_set_theme(UiSetThemeRequest {
+            theme: "dark".to_string(),
+        }),
+    );
+    golden.check("ui_get_theme", api.ui_get_theme());
+    golden.check(
+        "ui_set_theme_invalid",
+        api.ui_set_theme(UiSetThemeRequest {
+            theme: "Dark".to_string(),
+        }),
+    );
+    golden.check(
+        "navigation_get_bottom_menu_default",
+        api.navigation_get_bottom_menu(),
+    );
+    golden.check(
+        "navigation_set_bottom_menu",
+        api.navigation_set_bottom_menu(NavigationSetBottomMenuRequest {
+            tab: "wallet".to_string(),
+        }),
+    );
+    golden.check(
+        "navigation_get_bottom_menu",
+        api.navigation_get_bottom_menu(),
+    );
+    golden.check(
+        "navigation_set_bottom_menu_invalid",
+        api.navigation_set_bottom_menu(NavigationSetBottomMenuRequest {
+            tab: "home".to_string(),
+        }),
+    );
+
+    assert!(
+        golden.mismatches.is_empty(),
+        "responses changed (regenerate with OSNOVA_UPDATE_GOLDEN=1 if intended):\n{}",
+        golden.mismatches.join("\n")
+    );
+}